@@ -0,0 +1,80 @@
+//! Throughput of the RGB to output layout converters on 1080p frames
+//!
+//! Run with: `cargo bench --bench output_layout`
+//!
+//! Times the conversion of one RGB888 frame to every layout a sink can ask
+//! for, and back from the 32-bit layouts. A sink fed at 1080p30 needs under
+//! 33 ms per frame, on top of the YUV to RGB conversion.
+
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+use clean_scope_lib::output_layout::{self, OutputLayout};
+use clean_scope_lib::yuv_conversion::{ConversionError, YuvMatrix};
+
+const WIDTH: u32 = 1920;
+const HEIGHT: u32 = 1080;
+
+/// Time spent on each converter after warm-up
+const MEASURE_TIME: Duration = Duration::from_secs(2);
+
+/// Frame of pseudo-random bytes, so no converter hits a fast path
+fn frame(len: usize) -> Vec<u8> {
+    let mut state = 0x2545_f491_u32;
+    (0..len)
+        .map(|_| {
+            state ^= state << 13;
+            state ^= state >> 17;
+            state ^= state << 5;
+            state as u8
+        })
+        .collect()
+}
+
+/// Run `convert` for `MEASURE_TIME` and print the mean time per frame
+fn bench(name: &str, mut convert: impl FnMut() -> Result<Vec<u8>, ConversionError>) {
+    for _ in 0..3 {
+        black_box(convert().expect("conversion failed"));
+    }
+    let start = Instant::now();
+    let mut frames = 0u32;
+    while start.elapsed() < MEASURE_TIME {
+        black_box(convert().expect("conversion failed"));
+        frames += 1;
+    }
+    let per_frame = start.elapsed() / frames;
+    println!(
+        "{:<24} {:>8.2} ms/frame {:>8.1} fps",
+        name,
+        per_frame.as_secs_f64() * 1000.0,
+        1.0 / per_frame.as_secs_f64()
+    );
+}
+
+fn main() {
+    println!("{}x{} RGB888", WIDTH, HEIGHT);
+
+    let rgb = frame((WIDTH * HEIGHT * 3) as usize);
+    let rgba = frame((WIDTH * HEIGHT * 4) as usize);
+
+    for layout in OutputLayout::ALL {
+        bench(&format!("RGB → {:?}", layout), || {
+            output_layout::convert_rgb(&rgb, WIDTH, HEIGHT, layout)
+        });
+    }
+    bench("RGB → YUY2 (BT.709)", || {
+        output_layout::convert_rgb_with_matrix(
+            &rgb,
+            WIDTH,
+            HEIGHT,
+            OutputLayout::Yuy2,
+            YuvMatrix::Bt709,
+        )
+    });
+    bench("RGBA → RGB", || {
+        output_layout::rgba_to_rgb(&rgba, WIDTH, HEIGHT)
+    });
+    bench("BGRA → RGB", || {
+        output_layout::bgra_to_rgb(&rgba, WIDTH, HEIGHT)
+    });
+}
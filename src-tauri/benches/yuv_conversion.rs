@@ -0,0 +1,84 @@
+//! Throughput of the YUV to RGB converters on 1080p frames
+//!
+//! Run with: `cargo bench --bench yuv_conversion [--features simd-yuv]`
+//!
+//! Times the scalar reference converters and the converters the app uses,
+//! which are the `yuvutils-rs` SIMD ones with the `simd-yuv` feature (and the
+//! same scalar ones without it). 1080p30 preview needs under 33 ms per frame.
+
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+use clean_scope_lib::yuv_conversion::{self, scalar, ConversionError, YuvPackedFormat};
+
+const WIDTH: u32 = 1920;
+const HEIGHT: u32 = 1080;
+
+/// Time spent on each converter after warm-up
+const MEASURE_TIME: Duration = Duration::from_secs(2);
+
+/// Frame of pseudo-random bytes, so no converter hits a fast path
+fn frame(len: usize) -> Vec<u8> {
+    let mut state = 0x2545_f491_u32;
+    (0..len)
+        .map(|_| {
+            state ^= state << 13;
+            state ^= state >> 17;
+            state ^= state << 5;
+            state as u8
+        })
+        .collect()
+}
+
+/// Run `convert` for `MEASURE_TIME` and print the mean time per frame
+fn bench(name: &str, mut convert: impl FnMut() -> Result<Vec<u8>, ConversionError>) {
+    for _ in 0..3 {
+        black_box(convert().expect("conversion failed"));
+    }
+    let start = Instant::now();
+    let mut frames = 0u32;
+    while start.elapsed() < MEASURE_TIME {
+        black_box(convert().expect("conversion failed"));
+        frames += 1;
+    }
+    let per_frame = start.elapsed() / frames;
+    println!(
+        "{:<24} {:>8.2} ms/frame {:>8.1} fps",
+        name,
+        per_frame.as_secs_f64() * 1000.0,
+        1.0 / per_frame.as_secs_f64()
+    );
+}
+
+fn main() {
+    let path = if cfg!(feature = "simd-yuv") {
+        "simd"
+    } else {
+        "scalar (simd-yuv off)"
+    };
+    println!("{}x{}, app converters: {}", WIDTH, HEIGHT, path);
+
+    let packed = frame((WIDTH * HEIGHT * 2) as usize);
+    let planar = frame((WIDTH * HEIGHT * 3 / 2) as usize);
+
+    for format in [YuvPackedFormat::Yuyv, YuvPackedFormat::Uyvy] {
+        bench(&format!("{:?} scalar", format), || {
+            scalar::convert_yuv422_to_rgb(&packed, WIDTH, HEIGHT, None, format)
+        });
+        bench(&format!("{:?} app", format), || {
+            yuv_conversion::convert_yuv422_to_rgb(&packed, WIDTH, HEIGHT, None, format)
+        });
+    }
+    bench("I420 scalar", || {
+        scalar::convert_i420_to_rgb(&planar, WIDTH, HEIGHT)
+    });
+    bench("I420 app", || {
+        yuv_conversion::convert_i420_to_rgb(&planar, WIDTH, HEIGHT)
+    });
+    bench("NV12 scalar", || {
+        scalar::convert_nv12_to_rgb(&planar, WIDTH, HEIGHT)
+    });
+    bench("NV12 app", || {
+        yuv_conversion::convert_nv12_to_rgb(&planar, WIDTH, HEIGHT)
+    });
+}
@@ -7,6 +7,11 @@ use std::process::Command;
 fn main() {
     // Generate build info
     generate_build_info();
+    // Decide which network-capable features are compiled in
+    configure_network_feature("SHELL", "shell_open");
+    configure_network_feature("PLUGINS", "plugin_loading");
+    // Decide whether a USB camera backend is compiled in
+    configure_usb_streaming();
     // Only run Android-specific logic when building for Android
     #[cfg(target_os = "android")]
     {
@@ -27,6 +32,43 @@ fn main() {
     tauri_build::build();
 }
 
+/// Set `cfg` when the network-capable `feature` (as in `CARGO_FEATURE_*`) is
+/// enabled and `no-network` is not
+///
+/// The shell plugin opens URLs and plugins are arbitrary native code that
+/// could open sockets. Cargo features are additive (`--all-features` turns on
+/// both), so `no-network` wins instead of failing the build; the feature's
+/// dependency is then compiled but never referenced.
+fn configure_network_feature(feature: &str, cfg: &str) {
+    println!("cargo:rustc-check-cfg=cfg({})", cfg);
+    if std::env::var_os(format!("CARGO_FEATURE_{}", feature)).is_none() {
+        return;
+    }
+    if std::env::var_os("CARGO_FEATURE_NO_NETWORK").is_some() {
+        println!(
+            "cargo:warning=Feature `{}` is ignored because `no-network` is enabled",
+            feature.to_lowercase()
+        );
+    } else {
+        println!("cargo:rustc-cfg={}", cfg);
+    }
+}
+
+/// Set the `usb_streaming` cfg when a USB camera backend is compiled in
+///
+/// That is always the case on Android (libusb) and on desktop with the
+/// `desktop-usb` feature (rusb). The frame pipeline shared by both backends is
+/// gated on this cfg.
+fn configure_usb_streaming() {
+    println!("cargo:rustc-check-cfg=cfg(usb_streaming)");
+
+    let android = std::env::var("CARGO_CFG_TARGET_OS").is_ok_and(|os| os == "android");
+    let desktop_usb = std::env::var_os("CARGO_FEATURE_DESKTOP_USB").is_some();
+    if android || desktop_usb {
+        println!("cargo:rustc-cfg=usb_streaming");
+    }
+}
+
 /// Generate build info environment variables for compile-time inclusion
 fn generate_build_info() {
     // Get git commit hash
@@ -1,13 +1,33 @@
 package com.cleanscope.app
 
+import android.content.BroadcastReceiver
+import android.content.Context
+import android.content.Intent
+import android.content.IntentFilter
+import android.hardware.usb.UsbDevice
+import android.hardware.usb.UsbManager
 import android.os.Bundle
 import android.view.WindowManager
 import androidx.activity.enableEdgeToEdge
+import androidx.core.content.ContextCompat
+import androidx.core.content.IntentCompat
 import androidx.core.view.WindowCompat
 import androidx.core.view.WindowInsetsCompat
 import androidx.core.view.WindowInsetsControllerCompat
 
 class MainActivity : TauriActivity() {
+  // USB_DEVICE_DETACHED is only delivered as a broadcast, not to the activity
+  private val usbDetachReceiver = object : BroadcastReceiver() {
+    override fun onReceive(context: Context, intent: Intent) {
+      if (intent.action == UsbManager.ACTION_USB_DEVICE_DETACHED) {
+        // Any USB device may go away; the Rust side only stops for its camera
+        val device =
+          IntentCompat.getParcelableExtra(intent, UsbManager.EXTRA_DEVICE, UsbDevice::class.java)
+        onUsbDeviceDetached(device?.deviceName)
+      }
+    }
+  }
+
   override fun onCreate(savedInstanceState: Bundle?) {
     enableEdgeToEdge()
     super.onCreate(savedInstanceState)
@@ -17,8 +37,43 @@ class MainActivity : TauriActivity() {
 
     // Keep screen on while app is active (useful for endoscope viewing)
     window.addFlags(WindowManager.LayoutParams.FLAG_KEEP_SCREEN_ON)
+
+    // Let the Rust side stop streaming cleanly when the camera is unplugged
+    ContextCompat.registerReceiver(
+      this,
+      usbDetachReceiver,
+      IntentFilter(UsbManager.ACTION_USB_DEVICE_DETACHED),
+      ContextCompat.RECEIVER_NOT_EXPORTED
+    )
   }
 
+  override fun onDestroy() {
+    unregisterReceiver(usbDetachReceiver)
+    super.onDestroy()
+  }
+
+  override fun onNewIntent(intent: Intent) {
+    super.onNewIntent(intent)
+    setIntent(intent)
+
+    // Forward cleanscope:// deep links to the Rust side while the app is running
+    if (intent.action == Intent.ACTION_VIEW) {
+      intent.dataString?.let { onDeepLink(it) }
+    }
+
+    // A camera plugged in while the app is running; the Rust side reopens it
+    // through UsbManager (the new intent carries the device name)
+    if (intent.action == UsbManager.ACTION_USB_DEVICE_ATTACHED) {
+      onUsbDeviceAttached(-1)
+    }
+  }
+
+  private external fun onDeepLink(url: String)
+
+  private external fun onUsbDeviceAttached(fd: Int)
+
+  private external fun onUsbDeviceDetached(deviceName: String?)
+
   private fun enableImmersiveMode() {
     // Allow content to extend under system bars
     WindowCompat.setDecorFitsSystemWindows(window, false)
@@ -0,0 +1,93 @@
+/*
+ * C API for the CleanScope frame processing core (src/ffi.rs).
+ *
+ * Link against libclean_scope_lib (cdylib or staticlib). Functions return a
+ * CS_* status; after an error, cleanscope_last_error() describes it. Buffers
+ * returned by the library are owned by the caller and released with
+ * cleanscope_buffer_free() / cleanscope_frames_free().
+ */
+#ifndef CLEANSCOPE_H
+#define CLEANSCOPE_H
+
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+/* Status codes */
+#define CS_OK 0
+#define CS_FRAME_READY 1
+#define CS_SKIPPED 2
+#define CS_ERROR_NULL_POINTER (-1)
+#define CS_ERROR_INVALID_ARGUMENT (-2)
+#define CS_ERROR_CONVERSION (-3)
+#define CS_ERROR_IO (-4)
+#define CS_ERROR_PANIC (-5)
+
+/* Pixel formats for cleanscope_convert_to_rgb */
+#define CS_PIXEL_YUYV 0u
+#define CS_PIXEL_UYVY 1u
+#define CS_PIXEL_NV12 2u
+#define CS_PIXEL_I420 3u
+#define CS_PIXEL_RGB888 4u
+#define CS_PIXEL_BGR888 5u
+#define CS_PIXEL_BAYER_RGGB 6u
+#define CS_PIXEL_NV21 7u
+#define CS_PIXEL_YV12 8u
+
+/* Levels for cleanscope_validate_yuy2 */
+#define CS_VALIDATION_STRICT 0u
+#define CS_VALIDATION_MODERATE 1u
+#define CS_VALIDATION_MINIMAL 2u
+#define CS_VALIDATION_OFF 3u
+
+typedef struct CsBuffer {
+    uint8_t *data;
+    size_t len;
+} CsBuffer;
+
+typedef struct CsValidation {
+    bool valid;
+    bool has_avg_row_diff;
+    float avg_row_diff;
+    size_t actual_size;
+    size_t expected_size;
+    float size_ratio;
+    bool stride_aligned;
+} CsValidation;
+
+typedef struct CsAssembler CsAssembler;
+
+/* Message for the last error on this thread, or NULL */
+const char *cleanscope_last_error(void);
+
+void cleanscope_buffer_free(CsBuffer buffer);
+
+/* Frame assembly from UVC payload packets */
+CsAssembler *cleanscope_assembler_new(size_t expected_frame_size, bool mjpeg);
+int32_t cleanscope_assembler_set_headerless(CsAssembler *assembler, bool enabled);
+/* Returns CS_OK (accumulating), CS_FRAME_READY or CS_SKIPPED */
+int32_t cleanscope_assembler_push(CsAssembler *assembler, const uint8_t *data, size_t len);
+int32_t cleanscope_assembler_take_frame(CsAssembler *assembler, CsBuffer *out);
+void cleanscope_assembler_free(CsAssembler *assembler);
+
+/* Conversion and validation of assembled frames (stride 0: width * 2) */
+int32_t cleanscope_convert_to_rgb(const uint8_t *data, size_t len, uint32_t width,
+                                  uint32_t height, uint32_t stride, uint32_t pixel_format,
+                                  CsBuffer *out);
+int32_t cleanscope_validate_yuy2(const uint8_t *data, size_t len, uint32_t width,
+                                 uint32_t height, size_t expected_size, uint32_t level,
+                                 CsValidation *out);
+
+/* Replay of captures written by the app */
+int32_t cleanscope_replay_capture(const char *path, CsBuffer **out_frames, size_t *out_count);
+void cleanscope_frames_free(CsBuffer *frames, size_t count);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif /* CLEANSCOPE_H */
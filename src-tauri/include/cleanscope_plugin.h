@@ -0,0 +1,59 @@
+/*
+ * Frame processor plugin interface for CleanScope (src/plugins.rs).
+ *
+ * A plugin is a shared library exporting cleanscope_plugin_v1(), installed in
+ * its own directory under plugins/ in the app data directory together with a
+ * plugin.json manifest (name, version, api_version, library, capabilities).
+ *
+ * process() is called for every converted RGB24 frame, one call at a time but
+ * not always from the same thread. Without the "modify_frame" capability the
+ * plugin gets a copy of the frame; without "annotate" its annotations are
+ * ignored. Return the number of annotations written, or a negative value on
+ * failure (a plugin failing 30 frames in a row is skipped).
+ */
+#ifndef CLEANSCOPE_PLUGIN_H
+#define CLEANSCOPE_PLUGIN_H
+
+#include <stddef.h>
+#include <stdint.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+#define CLEANSCOPE_PLUGIN_API_VERSION 1u
+#define CS_ANNOTATION_LABEL_LEN 64
+
+typedef struct CsPluginFrame {
+    uint8_t *rgb;      /* RGB24, rows of width * 3 bytes */
+    size_t len;
+    uint32_t width;
+    uint32_t height;
+    uint64_t sequence; /* frames passed to plugins since loading */
+} CsPluginFrame;
+
+typedef struct CsAnnotation {
+    uint32_t x;
+    uint32_t y;
+    uint32_t width;
+    uint32_t height;
+    float confidence;                     /* 0.0 to 1.0 */
+    char label[CS_ANNOTATION_LABEL_LEN];  /* NUL-terminated UTF-8 */
+} CsAnnotation;
+
+typedef struct CsPlugin {
+    uint32_t api_version; /* CLEANSCOPE_PLUGIN_API_VERSION */
+    void *(*create)(void);                 /* optional; NULL fails the load */
+    void (*destroy)(void *instance);       /* optional */
+    int32_t (*process)(void *instance, CsPluginFrame *frame, CsAnnotation *annotations,
+                       size_t max_annotations);
+} CsPlugin;
+
+/* Entry point every plugin exports; the table must stay valid while loaded */
+const CsPlugin *cleanscope_plugin_v1(void);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif /* CLEANSCOPE_PLUGIN_H */
@@ -0,0 +1,234 @@
+//! The `cleanscope` Python module
+//!
+//! Exposes capture replay, frame assembly, YUV conversion and YUY2 validation
+//! to Python as the `cleanscope` module, with frames returned as numpy
+//! arrays for pandas/numpy analysis of captures. Build a wheel with maturin
+//! from `src-tauri/python/`:
+//!
+//! ```bash
+//! cd src-tauri/python && maturin develop --release
+//! ```
+//!
+//! ```python
+//! import cleanscope
+//!
+//! replay = cleanscope.PacketReplay("capture_12345.bin")
+//! for timestamp_us, frame in replay.extract_frames(42_000, 43_000):
+//!     rgb = cleanscope.convert_to_rgb(frame, 640, 480, "YUYV")  # (480, 640, 3)
+//! ```
+
+use std::path::PathBuf;
+
+use numpy::{IntoPyArray, PyArray1, PyArray3, PyArrayMethods};
+use pyo3::exceptions::{PyIOError, PyValueError};
+use pyo3::prelude::*;
+use pyo3::types::PyBytes;
+
+use crate::frame_assembler::{FrameAssembler, ProcessResult};
+use crate::frame_validation::{validate_yuy2_frame, ValidationLevel};
+use crate::replay::{PacketReplay, ReplayConfig, ReplayError};
+use crate::PixelFormat;
+
+/// Python exception for a replay error
+fn replay_error(e: ReplayError) -> PyErr {
+    match e {
+        ReplayError::FileOpen(e) => PyIOError::new_err(e.to_string()),
+        e => PyValueError::new_err(e.to_string()),
+    }
+}
+
+/// Python object parsed from the JSON form of `value`
+fn to_python<'py, T: serde::Serialize>(py: Python<'py>, value: &T) -> PyResult<Bound<'py, PyAny>> {
+    let json = serde_json::to_string(value).map_err(|e| PyValueError::new_err(e.to_string()))?;
+    py.import_bound("json")?.call_method1("loads", (json,))
+}
+
+/// Assembles frames from UVC payload packets
+#[pyclass(name = "FrameAssembler", module = "cleanscope")]
+struct PyFrameAssembler {
+    inner: FrameAssembler,
+}
+
+#[pymethods]
+impl PyFrameAssembler {
+    /// Assembler for frames of `expected_frame_size` bytes (0: detect), or
+    /// EOF-delimited MJPEG frames with `mjpeg`
+    #[new]
+    #[pyo3(signature = (expected_frame_size = 0, mjpeg = false))]
+    fn new(expected_frame_size: usize, mjpeg: bool) -> Self {
+        let inner = if mjpeg {
+            FrameAssembler::new_mjpeg()
+        } else {
+            FrameAssembler::new(expected_frame_size)
+        };
+        Self { inner }
+    }
+
+    /// Treat packets as raw payload without UVC headers
+    fn set_headerless(&mut self, enabled: bool) {
+        self.inner.set_headerless(enabled);
+    }
+
+    /// Feed one packet; returns the completed frame (uint8 array) or `None`
+    fn push<'py>(&mut self, py: Python<'py>, packet: &[u8]) -> Option<Bound<'py, PyArray1<u8>>> {
+        match self.inner.process_packet(packet) {
+            ProcessResult::Frame(frame) => Some(frame.into_pyarray_bound(py)),
+            ProcessResult::Accumulating | ProcessResult::Skipped => None,
+        }
+    }
+
+    /// Drop any partial frame and wait for the next frame boundary
+    fn reset(&mut self) {
+        self.inner.reset();
+    }
+}
+
+/// A packet capture loaded for offline analysis
+#[pyclass(name = "PacketReplay", module = "cleanscope")]
+struct PyPacketReplay {
+    inner: PacketReplay,
+}
+
+#[pymethods]
+impl PyPacketReplay {
+    /// Load a capture file (and its metadata, if present)
+    ///
+    /// `mjpeg` and `expected_frame_size` override the assembler settings
+    /// derived from the metadata.
+    #[new]
+    #[pyo3(signature = (path, mjpeg = false, expected_frame_size = 0))]
+    fn new(path: PathBuf, mjpeg: bool, expected_frame_size: usize) -> PyResult<Self> {
+        let config = ReplayConfig {
+            speed: 0.0,
+            force_mjpeg: mjpeg,
+            expected_frame_size,
+            ..Default::default()
+        };
+        let inner = PacketReplay::load_with_config(&path, config).map_err(replay_error)?;
+        Ok(Self { inner })
+    }
+
+    /// Number of packets in the capture
+    #[getter]
+    fn packet_count(&self) -> usize {
+        self.inner.packet_count()
+    }
+
+    /// Capture duration in milliseconds
+    #[getter]
+    fn duration_ms(&self) -> u64 {
+        self.inner.duration_ms()
+    }
+
+    /// Capture metadata as a dict, or `None` without a metadata file
+    #[getter]
+    fn metadata<'py>(&self, py: Python<'py>) -> PyResult<Option<Bound<'py, PyAny>>> {
+        self.inner
+            .metadata()
+            .map(|metadata| to_python(py, metadata))
+            .transpose()
+    }
+
+    /// All packets as `(timestamp_us, endpoint, data)` tuples
+    fn packets<'py>(&self, py: Python<'py>) -> Vec<(u64, u8, Bound<'py, PyBytes>)> {
+        self.inner
+            .packets()
+            .iter()
+            .map(|p| (p.timestamp_us, p.endpoint, PyBytes::new_bound(py, &p.data)))
+            .collect()
+    }
+
+    /// Completed frames as `(sequence, timestamp_us, packet_index)` tuples
+    fn frame_index(&self) -> Vec<(u64, u64, usize)> {
+        self.inner
+            .frame_index()
+            .into_iter()
+            .map(|e| (e.sequence, e.timestamp_us, e.packet_index))
+            .collect()
+    }
+
+    /// Every assembled frame, as uint8 arrays
+    fn frames<'py>(&self, py: Python<'py>) -> Vec<Bound<'py, PyArray1<u8>>> {
+        self.inner
+            .extract_frames(0, u64::MAX)
+            .into_iter()
+            .map(|frame| frame.data.into_pyarray_bound(py))
+            .collect()
+    }
+
+    /// Frames completed in `[from_ms, to_ms)` as `(timestamp_us, frame)` tuples
+    fn extract_frames<'py>(
+        &self,
+        py: Python<'py>,
+        from_ms: u64,
+        to_ms: u64,
+    ) -> Vec<(u64, Bound<'py, PyArray1<u8>>)> {
+        self.inner
+            .extract_frames(from_ms, to_ms)
+            .into_iter()
+            .map(|frame| (frame.entry.timestamp_us, frame.data.into_pyarray_bound(py)))
+            .collect()
+    }
+}
+
+/// Convert a raw frame to an RGB array of shape `(height, width, 3)`
+///
+/// `pixel_format` is a name such as `"YUYV"`, `"NV12"` or `"RGB24"`;
+/// `stride` is the row stride of packed 4:2:2 frames (0: `width * 2`).
+#[pyfunction]
+#[pyo3(signature = (data, width, height, pixel_format = "YUYV", stride = 0))]
+fn convert_to_rgb<'py>(
+    py: Python<'py>,
+    data: &[u8],
+    width: u32,
+    height: u32,
+    pixel_format: &str,
+    stride: u32,
+) -> PyResult<Bound<'py, PyArray3<u8>>> {
+    let pixel_format: PixelFormat = pixel_format.parse().map_err(PyValueError::new_err)?;
+    let stride = if stride == 0 { width * 2 } else { stride };
+    let rgb = crate::yuv_conversion::convert_to_rgb(data, width, height, stride, pixel_format)
+        .map_err(|e| PyValueError::new_err(e.0))?;
+    rgb.into_pyarray_bound(py)
+        .reshape([height as usize, width as usize, 3])
+}
+
+/// Check a YUY2 frame for corruption; returns the validation result as a dict
+///
+/// `expected_size` defaults to `width * height * 2`; `level` is `"strict"`,
+/// `"moderate"`, `"minimal"` or `"off"`.
+#[pyfunction]
+#[pyo3(signature = (data, width, height, expected_size = 0, level = "strict"))]
+fn validate_yuy2<'py>(
+    py: Python<'py>,
+    data: &[u8],
+    width: usize,
+    height: usize,
+    expected_size: usize,
+    level: &str,
+) -> PyResult<Bound<'py, PyAny>> {
+    let expected_size = if expected_size == 0 {
+        width * height * 2
+    } else {
+        expected_size
+    };
+    let result = validate_yuy2_frame(
+        data,
+        width,
+        height,
+        expected_size,
+        ValidationLevel::from_env_str(level),
+    );
+    to_python(py, &result)
+}
+
+/// The `cleanscope` Python module
+#[pymodule]
+#[pyo3(name = "cleanscope")]
+fn cleanscope_module(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add_class::<PyFrameAssembler>()?;
+    m.add_class::<PyPacketReplay>()?;
+    m.add_function(wrap_pyfunction!(convert_to_rgb, m)?)?;
+    m.add_function(wrap_pyfunction!(validate_yuy2, m)?)?;
+    Ok(())
+}
@@ -0,0 +1,51 @@
+//! Python bindings for the `CleanScope` capture replay and frame processing core
+//!
+//! Compiles the Tauri-free modules of `clean_scope_lib` that replay needs
+//! (capture files, pcap import, replay, frame assembly, YUV conversion,
+//! frame validation) together with the PyO3 `cleanscope` module in
+//! [`bindings`], without building the app. The modules are included from
+//! `../src` rather than copied, so Python analyses run the same code as the
+//! app. Build a wheel with maturin from this directory (see `bindings`).
+
+#[path = "../../src/capture.rs"]
+pub mod capture;
+#[path = "../../src/frame_assembler.rs"]
+pub mod frame_assembler;
+#[path = "../../src/frame_boundary.rs"]
+pub mod frame_boundary;
+#[path = "../../src/format_registry.rs"]
+pub mod format_registry;
+#[path = "../../src/frame_validation.rs"]
+pub mod frame_validation;
+#[path = "../../src/pcap_import.rs"]
+pub mod pcap_import;
+#[path = "../../src/pixel_format.rs"]
+pub mod pixel_format;
+#[path = "../../src/replay.rs"]
+pub mod replay;
+#[path = "../../src/storage.rs"]
+pub mod storage;
+#[path = "../../src/yuv_conversion.rs"]
+pub mod yuv_conversion;
+
+#[cfg(test)]
+#[path = "../../src/test_utils/mod.rs"]
+pub mod test_utils;
+
+mod bindings;
+
+pub use frame_validation::ValidationLevel;
+pub use pixel_format::PixelFormat;
+
+use std::sync::{Mutex, MutexGuard};
+
+/// Lock a mutex, recovering the data if a panicking thread poisoned it
+///
+/// Same as the app's `lock_or_recover`, which the shared modules call.
+pub(crate) fn lock_or_recover<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(|poisoned| {
+        log::error!("Mutex poisoned, recovering");
+        mutex.clear_poison();
+        poisoned.into_inner()
+    })
+}
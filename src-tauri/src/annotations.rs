@@ -0,0 +1,311 @@
+//! Frame annotations published by plugins and filters
+//!
+//! Anything that analyses frames (native plugins, the ONNX detector) reports
+//! its results as typed [`Annotation`]s keyed by the sequence number of the
+//! frame they describe. The [`AnnotationBus`] filters them by the user's
+//! [`AnnotationConfig`], forwards them to the frontend as
+//! `frame-annotations` events, and keeps each source's latest annotations so
+//! the overlay renderer (`overlay.rs`) can burn them into recordings.
+//!
+//! Sources run at their own pace: plugins annotate the frame they were given,
+//! while a detection model may report on a frame several sequences back. An
+//! annotation stays active for [`AnnotationConfig::hold_frames`] frames after
+//! the frame it describes, so slow sources don't flicker in recordings.
+
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+use serde::{Deserialize, Serialize};
+
+/// Default number of frames an annotation stays active
+pub const DEFAULT_HOLD_FRAMES: u32 = 15;
+
+/// Geometry of an annotation, in frame pixels
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum AnnotationKind {
+    /// Labelled region
+    Box {
+        /// Left edge
+        x: u32,
+        /// Top edge
+        y: u32,
+        /// Width
+        width: u32,
+        /// Height
+        height: u32,
+    },
+    /// Text anchored at a point
+    Label {
+        /// Anchor x
+        x: u32,
+        /// Anchor y
+        y: u32,
+    },
+    /// Measured distance between two points
+    Measurement {
+        /// Start x
+        x1: u32,
+        /// Start y
+        y1: u32,
+        /// End x
+        x2: u32,
+        /// End y
+        y2: u32,
+        /// Measured value
+        value: f32,
+        /// Unit of `value` (e.g. "mm", "px")
+        unit: String,
+    },
+}
+
+/// A typed result reported for one frame
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Annotation {
+    /// Plugin or model that produced it
+    pub source: String,
+    /// Label text
+    pub label: String,
+    /// Confidence from 0.0 to 1.0 (1.0 for exact results such as measurements)
+    pub confidence: f32,
+    /// Geometry
+    #[serde(flatten)]
+    pub kind: AnnotationKind,
+}
+
+/// Payload of the `frame-annotations` event
+#[derive(Debug, Clone, Serialize)]
+pub struct FrameAnnotations {
+    /// Sequence number of the annotated frame (see `FrameInfo::sequence`)
+    pub sequence: u64,
+    /// Annotations of the frame, from one or more sources
+    pub annotations: Vec<Annotation>,
+}
+
+/// How annotations are delivered, set with `set_annotation_config`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AnnotationConfig {
+    /// Deliver annotations at all (off: sources still run, results are dropped)
+    pub enabled: bool,
+    /// Drop annotations below this confidence
+    pub min_confidence: f32,
+    /// Draw active annotations into recorded RGB frames
+    pub burn_into_recordings: bool,
+    /// Frames an annotation stays active after the frame it describes
+    pub hold_frames: u32,
+}
+
+impl Default for AnnotationConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            min_confidence: 0.0,
+            burn_into_recordings: false,
+            hold_frames: DEFAULT_HOLD_FRAMES,
+        }
+    }
+}
+
+#[derive(Default)]
+struct BusState {
+    config: AnnotationConfig,
+    /// Latest annotations per source, with the sequence they describe
+    latest: HashMap<String, (u64, Vec<Annotation>)>,
+}
+
+/// Collects annotations from all sources
+#[derive(Default)]
+pub struct AnnotationBus {
+    state: Mutex<BusState>,
+}
+
+impl AnnotationBus {
+    /// Create a bus with the default config
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Current delivery settings
+    #[must_use]
+    pub fn config(&self) -> AnnotationConfig {
+        crate::lock_or_recover(&self.state).config.clone()
+    }
+
+    /// Replace the delivery settings
+    ///
+    /// Disabling delivery also forgets the active annotations.
+    pub fn set_config(&self, config: AnnotationConfig) {
+        let mut state = crate::lock_or_recover(&self.state);
+        if !config.enabled {
+            state.latest.clear();
+        }
+        state.config = config;
+    }
+
+    /// Accept annotations for a frame, returning what to send to the frontend
+    ///
+    /// Annotations below the confidence threshold are dropped. Each source's
+    /// annotations replace its previous ones unless they describe an older
+    /// frame. Returns `None` if delivery is disabled or nothing is left.
+    pub fn publish(&self, frame: FrameAnnotations) -> Option<FrameAnnotations> {
+        let mut state = crate::lock_or_recover(&self.state);
+        if !state.config.enabled {
+            return None;
+        }
+        let min_confidence = state.config.min_confidence;
+        let annotations: Vec<Annotation> = frame
+            .annotations
+            .into_iter()
+            .filter(|a| a.confidence >= min_confidence)
+            .collect();
+
+        let mut by_source: HashMap<&str, Vec<Annotation>> = HashMap::new();
+        for annotation in &annotations {
+            by_source
+                .entry(&annotation.source)
+                .or_default()
+                .push(annotation.clone());
+        }
+        for (source, source_annotations) in by_source {
+            let older = matches!(
+                state.latest.get(source),
+                Some((sequence, _)) if *sequence > frame.sequence
+            );
+            if !older {
+                state
+                    .latest
+                    .insert(source.to_string(), (frame.sequence, source_annotations));
+            }
+        }
+
+        (!annotations.is_empty()).then_some(FrameAnnotations {
+            sequence: frame.sequence,
+            annotations,
+        })
+    }
+
+    /// Annotations to draw into recorded frame `sequence`
+    ///
+    /// `current` are the not yet published annotations of that frame; they
+    /// replace the held annotations of their sources. Empty unless burning
+    /// into recordings is enabled.
+    #[must_use]
+    pub fn active_for_recording(&self, sequence: u64, current: &[Annotation]) -> Vec<Annotation> {
+        let state = crate::lock_or_recover(&self.state);
+        if !state.config.enabled || !state.config.burn_into_recordings {
+            return Vec::new();
+        }
+        let hold = u64::from(state.config.hold_frames);
+        let mut active: Vec<Annotation> = current
+            .iter()
+            .filter(|a| a.confidence >= state.config.min_confidence)
+            .cloned()
+            .collect();
+        for (source, (annotated, annotations)) in &state.latest {
+            let held = *annotated <= sequence && sequence - annotated <= hold;
+            if held && !current.iter().any(|a| &a.source == source) {
+                active.extend(annotations.iter().cloned());
+            }
+        }
+        active
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn boxed(source: &str, confidence: f32) -> Annotation {
+        Annotation {
+            source: source.to_string(),
+            label: "crack".to_string(),
+            confidence,
+            kind: AnnotationKind::Box {
+                x: 1,
+                y: 2,
+                width: 3,
+                height: 4,
+            },
+        }
+    }
+
+    fn frame(sequence: u64, annotations: Vec<Annotation>) -> FrameAnnotations {
+        FrameAnnotations {
+            sequence,
+            annotations,
+        }
+    }
+
+    #[test]
+    fn test_annotation_serializes_kind_inline() {
+        let json = serde_json::to_value(boxed("weld", 0.5)).unwrap();
+        assert_eq!(json["type"], "box");
+        assert_eq!(json["source"], "weld");
+        assert_eq!(json["width"], 3);
+
+        let measurement = Annotation {
+            source: "calibration".to_string(),
+            label: String::new(),
+            confidence: 1.0,
+            kind: AnnotationKind::Measurement {
+                x1: 0,
+                y1: 0,
+                x2: 10,
+                y2: 0,
+                value: 2.5,
+                unit: "mm".to_string(),
+            },
+        };
+        let json = serde_json::to_value(measurement).unwrap();
+        assert_eq!(json["type"], "measurement");
+        assert_eq!(json["unit"], "mm");
+    }
+
+    #[test]
+    fn test_publish_filters_by_confidence() {
+        let bus = AnnotationBus::new();
+        bus.set_config(AnnotationConfig {
+            min_confidence: 0.5,
+            ..AnnotationConfig::default()
+        });
+
+        let delivered = bus
+            .publish(frame(1, vec![boxed("a", 0.9), boxed("a", 0.1)]))
+            .unwrap();
+        assert_eq!(delivered.annotations.len(), 1);
+        assert!(bus.publish(frame(2, vec![boxed("a", 0.2)])).is_none());
+
+        bus.set_config(AnnotationConfig {
+            enabled: false,
+            ..AnnotationConfig::default()
+        });
+        assert!(bus.publish(frame(3, vec![boxed("a", 0.9)])).is_none());
+    }
+
+    #[test]
+    fn test_active_annotations_expire_and_keep_newest_per_source() {
+        let bus = AnnotationBus::new();
+        bus.publish(frame(10, vec![boxed("model", 0.9)]));
+        // Burning is off by default
+        assert!(bus.active_for_recording(10, &[]).is_empty());
+
+        bus.set_config(AnnotationConfig {
+            burn_into_recordings: true,
+            hold_frames: 5,
+            ..AnnotationConfig::default()
+        });
+        bus.publish(frame(12, vec![boxed("plugin", 0.9), boxed("plugin", 0.8)]));
+        // A late result for an older frame doesn't replace a newer one
+        bus.publish(frame(11, vec![boxed("plugin", 0.7)]));
+
+        assert_eq!(bus.active_for_recording(12, &[]).len(), 3);
+        assert_eq!(bus.active_for_recording(16, &[]).len(), 2);
+        assert!(bus.active_for_recording(18, &[]).is_empty());
+
+        // The frame being recorded replaces what its sources published before
+        let current = [boxed("plugin", 0.9)];
+        assert_eq!(bus.active_for_recording(13, &current).len(), 2);
+    }
+}
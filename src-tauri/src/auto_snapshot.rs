@@ -0,0 +1,323 @@
+//! Time-based auto-snapshot scheduler
+//!
+//! `start_auto_snapshot(interval_secs, label)` saves a snapshot every
+//! `interval_secs` while the camera streams, for inspections that need a
+//! photo of every stretch of pipe. Snapshots go into the session's directory
+//! and are added to the session manifest as a numbered series
+//! (`<label>-0001`, `<label>-0002`, ...), independently of any recording.
+//!
+//! The scheduler only decides when a snapshot is due: the first one right
+//! away, then one per interval. A tick is held back until a frame newer than
+//! the last snapshot arrives, so nothing is saved twice while the stream is
+//! stalled, and ticks missed meanwhile are skipped rather than caught up.
+
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::image_encoder::ImageFormat;
+
+/// Shortest interval between snapshots (seconds)
+pub const MIN_INTERVAL_SECS: f64 = 1.0;
+
+/// Longest interval between snapshots (seconds)
+pub const MAX_INTERVAL_SECS: f64 = 24.0 * 60.0 * 60.0;
+
+/// Series label used when none is given
+pub const DEFAULT_LABEL: &str = "auto";
+
+/// Longest accepted series label
+pub const MAX_LABEL_LEN: usize = 64;
+
+/// How often the scheduler thread checks for a due snapshot
+pub const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Errors configuring the scheduler
+#[derive(Debug, Error)]
+pub enum AutoSnapshotError {
+    /// Interval outside MIN_INTERVAL_SECS..=MAX_INTERVAL_SECS
+    #[error("snapshot interval must be between 1 second and 24 hours, got {0} s")]
+    Interval(f64),
+    /// Label unusable in file and series names
+    #[error("invalid snapshot label {0:?}")]
+    Label(String),
+}
+
+/// Result type alias for auto-snapshot operations
+pub type Result<T> = std::result::Result<T, AutoSnapshotError>;
+
+/// What the scheduler saves and how often
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AutoSnapshotSettings {
+    /// Seconds between snapshots
+    pub interval_secs: f64,
+    /// Series label in the session manifest
+    pub label: String,
+    /// Image format (`None`: the snapshot format setting)
+    pub format: Option<ImageFormat>,
+}
+
+impl AutoSnapshotSettings {
+    /// Validated settings
+    ///
+    /// The label is trimmed and defaults to [`DEFAULT_LABEL`].
+    ///
+    /// # Errors
+    ///
+    /// Returns `AutoSnapshotError::Interval` for an interval outside
+    /// [`MIN_INTERVAL_SECS`] to [`MAX_INTERVAL_SECS`], or
+    /// `AutoSnapshotError::Label` for a label longer than [`MAX_LABEL_LEN`] or
+    /// with characters other than letters, digits, `-` and `_`.
+    pub fn new(
+        interval_secs: f64,
+        label: Option<String>,
+        format: Option<ImageFormat>,
+    ) -> Result<Self> {
+        if !(MIN_INTERVAL_SECS..=MAX_INTERVAL_SECS).contains(&interval_secs) {
+            return Err(AutoSnapshotError::Interval(interval_secs));
+        }
+        let label = match label.as_deref().map(str::trim) {
+            None | Some("") => DEFAULT_LABEL.to_string(),
+            Some(label) => {
+                let usable = label.len() <= MAX_LABEL_LEN
+                    && label
+                        .chars()
+                        .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+                if !usable {
+                    return Err(AutoSnapshotError::Label(label.to_string()));
+                }
+                label.to_string()
+            }
+        };
+        Ok(Self {
+            interval_secs,
+            label,
+            format,
+        })
+    }
+
+    fn interval(&self) -> Duration {
+        Duration::from_secs_f64(self.interval_secs)
+    }
+}
+
+/// Scheduler state, returned by the auto-snapshot commands
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct AutoSnapshotStatus {
+    /// Whether snapshots are being taken
+    pub active: bool,
+    /// Settings of the running (or last) schedule
+    pub settings: Option<AutoSnapshotSettings>,
+    /// Snapshots saved since the schedule started
+    pub taken: u32,
+    /// Snapshots that could not be saved
+    pub failed: u32,
+    /// Time until the next snapshot is due (milliseconds, 0 if overdue)
+    pub next_in_ms: Option<u64>,
+    /// Path of the last saved snapshot
+    pub last_path: Option<String>,
+    /// Why the last snapshot failed, if it did
+    pub last_error: Option<String>,
+}
+
+#[derive(Debug)]
+struct Schedule {
+    settings: AutoSnapshotSettings,
+    next_due: Instant,
+    /// Frame saved last, so a stalled stream isn't saved twice
+    last_frame: Option<u64>,
+    taken: u32,
+    failed: u32,
+    last_path: Option<String>,
+    last_error: Option<String>,
+}
+
+/// Decides when the next scheduled snapshot is due
+#[derive(Debug, Default)]
+pub struct AutoSnapshotScheduler {
+    schedule: Mutex<Option<Schedule>>,
+    /// Last schedule, kept so its counts can still be reported after `stop`
+    stopped: Mutex<AutoSnapshotStatus>,
+}
+
+impl AutoSnapshotScheduler {
+    /// Start (or restart) taking snapshots, the first one right away
+    pub fn start(&self, settings: AutoSnapshotSettings, now: Instant) -> AutoSnapshotStatus {
+        let mut schedule = crate::lock_or_recover(&self.schedule);
+        *schedule = Some(Schedule {
+            settings,
+            next_due: now,
+            last_frame: None,
+            taken: 0,
+            failed: 0,
+            last_path: None,
+            last_error: None,
+        });
+        status_of(schedule.as_ref(), now)
+    }
+
+    /// Stop taking snapshots and return the final status
+    pub fn stop(&self, now: Instant) -> AutoSnapshotStatus {
+        let schedule = crate::lock_or_recover(&self.schedule).take();
+        let mut status = status_of(schedule.as_ref(), now);
+        if schedule.is_none() {
+            status = crate::lock_or_recover(&self.stopped).clone();
+        }
+        status.active = false;
+        status.next_in_ms = None;
+        *crate::lock_or_recover(&self.stopped) = status.clone();
+        status
+    }
+
+    /// Current status
+    pub fn status(&self, now: Instant) -> AutoSnapshotStatus {
+        let schedule = crate::lock_or_recover(&self.schedule);
+        if schedule.is_none() {
+            return crate::lock_or_recover(&self.stopped).clone();
+        }
+        status_of(schedule.as_ref(), now)
+    }
+
+    /// Settings to take a snapshot with, if one is due and `frame_sequence`
+    /// is newer than the last snapshot
+    pub fn due(&self, now: Instant, frame_sequence: u64) -> Option<AutoSnapshotSettings> {
+        let schedule = crate::lock_or_recover(&self.schedule);
+        let schedule = schedule.as_ref()?;
+        let fresh = frame_sequence != 0 && schedule.last_frame != Some(frame_sequence);
+        (now >= schedule.next_due && fresh).then(|| schedule.settings.clone())
+    }
+
+    /// Record the outcome of a due snapshot and schedule the next one
+    ///
+    /// Failures count as taken ticks too, so a broken output directory
+    /// doesn't make the scheduler retry on every poll.
+    pub fn record(
+        &self,
+        now: Instant,
+        frame_sequence: u64,
+        outcome: std::result::Result<String, String>,
+    ) {
+        let mut schedule = crate::lock_or_recover(&self.schedule);
+        let Some(schedule) = schedule.as_mut() else {
+            return;
+        };
+        match outcome {
+            Ok(path) => {
+                schedule.taken += 1;
+                schedule.last_frame = Some(frame_sequence);
+                schedule.last_path = Some(path);
+                schedule.last_error = None;
+            }
+            Err(e) => {
+                schedule.failed += 1;
+                schedule.last_error = Some(e);
+            }
+        }
+        let interval = schedule.settings.interval();
+        schedule.next_due += interval;
+        if schedule.next_due <= now {
+            // Skip the ticks missed while the stream was stalled
+            schedule.next_due = now + interval;
+        }
+    }
+}
+
+fn status_of(schedule: Option<&Schedule>, now: Instant) -> AutoSnapshotStatus {
+    let Some(schedule) = schedule else {
+        return AutoSnapshotStatus::default();
+    };
+    AutoSnapshotStatus {
+        active: true,
+        settings: Some(schedule.settings.clone()),
+        taken: schedule.taken,
+        failed: schedule.failed,
+        next_in_ms: Some(schedule.next_due.saturating_duration_since(now).as_millis() as u64),
+        last_path: schedule.last_path.clone(),
+        last_error: schedule.last_error.clone(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn settings(interval_secs: f64) -> AutoSnapshotSettings {
+        AutoSnapshotSettings::new(interval_secs, None, None).unwrap()
+    }
+
+    #[test]
+    fn test_settings_validation() {
+        assert_eq!(settings(5.0).label, DEFAULT_LABEL);
+        let labelled =
+            AutoSnapshotSettings::new(60.0, Some(" joint_A-2 ".to_string()), None).unwrap();
+        assert_eq!(labelled.label, "joint_A-2");
+
+        for interval in [0.5, MAX_INTERVAL_SECS + 1.0, f64::NAN] {
+            assert!(matches!(
+                AutoSnapshotSettings::new(interval, None, None),
+                Err(AutoSnapshotError::Interval(_))
+            ));
+        }
+        assert!(matches!(
+            AutoSnapshotSettings::new(5.0, Some("../escape".to_string()), None),
+            Err(AutoSnapshotError::Label(_))
+        ));
+    }
+
+    #[test]
+    fn test_snapshots_follow_the_interval() {
+        let scheduler = AutoSnapshotScheduler::default();
+        let t0 = Instant::now();
+        assert!(scheduler.due(t0, 1).is_none());
+
+        scheduler.start(settings(10.0), t0);
+        // The first snapshot is due right away
+        assert!(scheduler.due(t0, 1).is_some());
+        scheduler.record(t0, 1, Ok("a.jpg".to_string()));
+
+        assert!(scheduler.due(t0 + Duration::from_secs(9), 5).is_none());
+        assert!(scheduler.due(t0 + Duration::from_secs(10), 6).is_some());
+
+        let status = scheduler.status(t0 + Duration::from_secs(4));
+        assert!(status.active);
+        assert_eq!(status.taken, 1);
+        assert_eq!(status.next_in_ms, Some(6000));
+        assert_eq!(status.last_path.as_deref(), Some("a.jpg"));
+    }
+
+    #[test]
+    fn test_stalled_stream_is_not_saved_twice() {
+        let scheduler = AutoSnapshotScheduler::default();
+        let t0 = Instant::now();
+        scheduler.start(settings(1.0), t0);
+        assert!(scheduler.due(t0, 0).is_none(), "no frame yet");
+        scheduler.record(t0, 7, Ok("a.jpg".to_string()));
+
+        let late = t0 + Duration::from_secs(30);
+        assert!(scheduler.due(late, 7).is_none());
+        assert!(scheduler.due(late, 8).is_some());
+
+        // Missed ticks are skipped, not caught up
+        scheduler.record(late, 8, Ok("b.jpg".to_string()));
+        assert!(scheduler.due(late, 9).is_none());
+        assert!(scheduler.due(late + Duration::from_secs(1), 9).is_some());
+    }
+
+    #[test]
+    fn test_failures_are_counted_and_stop_keeps_totals() {
+        let scheduler = AutoSnapshotScheduler::default();
+        let t0 = Instant::now();
+        scheduler.start(settings(2.0), t0);
+        scheduler.record(t0, 1, Err("disk full".to_string()));
+        assert!(scheduler.due(t0, 1).is_none());
+
+        let stopped = scheduler.stop(t0);
+        assert!(!stopped.active);
+        assert_eq!((stopped.taken, stopped.failed), (0, 1));
+        assert_eq!(stopped.last_error.as_deref(), Some("disk full"));
+        assert_eq!(scheduler.status(t0), stopped);
+        assert!(scheduler.due(t0 + Duration::from_secs(5), 2).is_none());
+    }
+}
@@ -0,0 +1,630 @@
+//! Scripted command sequences for repeatable QA runs
+//!
+//! `execute_script(json)` runs a [`Script`]: steps such as setting the
+//! resolution, waiting, saving a snapshot, setting the LED or recording for a
+//! number of seconds, in order. A failing step is retried `retries` times and
+//! then, depending on its [`ErrorPolicy`], either aborts the script or is
+//! skipped. The [`ScriptReport`] lists the outcome and output (e.g. the
+//! snapshot path) of every step that ran, so a test station can check it.
+//!
+//! ```json
+//! {
+//!   "name": "LED sweep",
+//!   "on_error": "abort",
+//!   "steps": [
+//!     { "op": "set_resolution", "width": 1280, "height": 720 },
+//!     { "op": "set_led", "level": 100 },
+//!     { "op": "wait", "ms": 500 },
+//!     { "op": "snapshot", "path": "qa", "retries": 2 },
+//!     { "op": "record", "seconds": 5, "label": "led-100", "on_error": "continue" }
+//!   ]
+//! }
+//! ```
+//!
+//! This module parses, validates and sequences steps; a [`StepRunner`]
+//! executes them (in the app, against `AppState`).
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
+
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::image_encoder::ImageFormat;
+
+/// Most steps in one script
+pub const MAX_STEPS: usize = 256;
+
+/// Longest `wait` or `wait_for_frame` step (milliseconds)
+pub const MAX_WAIT_MS: u64 = 10 * 60 * 1000;
+
+/// Longest `record` step (seconds)
+pub const MAX_RECORD_SECONDS: f64 = 60.0 * 60.0;
+
+/// Most retries of one step
+pub const MAX_RETRIES: u32 = 10;
+
+/// Pause before retrying a failed step
+pub const RETRY_DELAY: Duration = Duration::from_millis(250);
+
+/// Granularity of cancellable waits
+const POLL_INTERVAL: Duration = Duration::from_millis(20);
+
+/// Errors parsing or running a script
+#[derive(Debug, Error)]
+pub enum ScriptError {
+    /// Not valid script JSON
+    #[error("invalid script: {0}")]
+    Parse(String),
+    /// A step has unusable parameters
+    #[error("step {index}: {message}")]
+    InvalidStep {
+        /// Position of the step (0-based)
+        index: usize,
+        /// Description of the problem
+        message: String,
+    },
+    /// Another script is running
+    #[error("a script is already running")]
+    Busy,
+    /// `cancel_script` was called
+    #[error("script cancelled")]
+    Cancelled,
+    /// Something the step waits for didn't happen in time
+    #[error("timed out waiting for {0}")]
+    Timeout(String),
+}
+
+/// Result type alias for script operations
+pub type Result<T> = std::result::Result<T, ScriptError>;
+
+/// What to do when a step fails after its retries
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ErrorPolicy {
+    /// Stop the script
+    #[default]
+    Abort,
+    /// Go on with the next step
+    Continue,
+}
+
+/// An operation a step performs
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "op", rename_all = "snake_case")]
+pub enum Operation {
+    /// Renegotiate the stream at this resolution of the current format and
+    /// wait for it to restart
+    SetResolution {
+        /// Width in pixels
+        width: u16,
+        /// Height in pixels
+        height: u16,
+    },
+    /// Renegotiate the stream at the supported frame rate closest to `fps`
+    SetFramerate {
+        /// Frames per second
+        fps: f32,
+    },
+    /// Set an image control (`brightness`, `contrast`, ..., `exposure`)
+    SetControl {
+        /// Control name
+        name: String,
+        /// Raw control value
+        value: i32,
+    },
+    /// Set the LED brightness in percent (0 turns it off)
+    SetLed {
+        /// Brightness percentage
+        level: u8,
+    },
+    /// Do nothing for a while
+    Wait {
+        /// Milliseconds to wait
+        ms: u64,
+    },
+    /// Wait until a new frame arrives
+    WaitForFrame {
+        /// Milliseconds to wait at most
+        timeout_ms: u64,
+    },
+    /// Save the current frame (see `save_snapshot`)
+    Snapshot {
+        /// Directory inside the output directory
+        path: Option<String>,
+        /// Image format (default: the snapshot format setting)
+        format: Option<ImageFormat>,
+    },
+    /// Record processed frames for a number of seconds
+    Record {
+        /// Recording length
+        seconds: f64,
+        /// Label stored in the recording index
+        label: Option<String>,
+    },
+    /// Bookmark the current frame in the session
+    Bookmark {
+        /// Bookmark note
+        note: Option<String>,
+    },
+}
+
+impl Operation {
+    /// Name of the operation as written in scripts
+    #[must_use]
+    pub fn name(&self) -> &'static str {
+        match self {
+            Operation::SetResolution { .. } => "set_resolution",
+            Operation::SetFramerate { .. } => "set_framerate",
+            Operation::SetControl { .. } => "set_control",
+            Operation::SetLed { .. } => "set_led",
+            Operation::Wait { .. } => "wait",
+            Operation::WaitForFrame { .. } => "wait_for_frame",
+            Operation::Snapshot { .. } => "snapshot",
+            Operation::Record { .. } => "record",
+            Operation::Bookmark { .. } => "bookmark",
+        }
+    }
+
+    /// Check the parameters are in range
+    fn validate(&self) -> std::result::Result<(), String> {
+        match self {
+            Operation::SetResolution { width, height } if *width == 0 || *height == 0 => {
+                Err(format!("resolution {}x{} is empty", width, height))
+            }
+            Operation::SetFramerate { fps } if !(fps.is_finite() && *fps > 0.0) => {
+                Err(format!("frame rate {} must be positive", fps))
+            }
+            Operation::SetLed { level } if *level > 100 => {
+                Err(format!("LED level {} is above 100", level))
+            }
+            Operation::Wait { ms: wait } | Operation::WaitForFrame { timeout_ms: wait }
+                if *wait > MAX_WAIT_MS =>
+            {
+                Err(format!("{} ms is longer than {} ms", wait, MAX_WAIT_MS))
+            }
+            Operation::Record { seconds, .. }
+                if !(seconds.is_finite() && *seconds > 0.0 && *seconds <= MAX_RECORD_SECONDS) =>
+            {
+                Err(format!(
+                    "recording length {} must be between 0 and {} seconds",
+                    seconds, MAX_RECORD_SECONDS
+                ))
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+/// A step of a script
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Step {
+    /// What to do
+    #[serde(flatten)]
+    pub operation: Operation,
+    /// Times to retry the step after it fails
+    #[serde(default)]
+    pub retries: u32,
+    /// Overrides the script's error policy for this step
+    #[serde(default)]
+    pub on_error: Option<ErrorPolicy>,
+}
+
+/// A sequence of steps, run by `execute_script`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Script {
+    /// Name reported back in the [`ScriptReport`]
+    #[serde(default)]
+    pub name: Option<String>,
+    /// What to do when a step fails (default: abort)
+    #[serde(default)]
+    pub on_error: ErrorPolicy,
+    /// Steps in order
+    pub steps: Vec<Step>,
+}
+
+impl Script {
+    /// Parse and validate a script
+    ///
+    /// # Errors
+    ///
+    /// Returns `ScriptError::Parse` for malformed JSON, unknown operations or
+    /// a step count outside 1 to [`MAX_STEPS`], and `ScriptError::InvalidStep`
+    /// for out-of-range parameters.
+    pub fn parse(json: &str) -> Result<Self> {
+        let script: Script =
+            serde_json::from_str(json).map_err(|e| ScriptError::Parse(e.to_string()))?;
+        if script.steps.is_empty() || script.steps.len() > MAX_STEPS {
+            return Err(ScriptError::Parse(format!(
+                "a script needs 1 to {} steps, got {}",
+                MAX_STEPS,
+                script.steps.len()
+            )));
+        }
+        for (index, step) in script.steps.iter().enumerate() {
+            if step.retries > MAX_RETRIES {
+                return Err(ScriptError::InvalidStep {
+                    index,
+                    message: format!("at most {} retries", MAX_RETRIES),
+                });
+            }
+            step.operation
+                .validate()
+                .map_err(|message| ScriptError::InvalidStep { index, message })?;
+        }
+        Ok(script)
+    }
+}
+
+/// Outcome of one step
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct StepReport {
+    /// Position of the step (0-based)
+    pub index: usize,
+    /// Operation name
+    pub op: &'static str,
+    /// Whether the step succeeded
+    pub ok: bool,
+    /// Times the step was run (1 + retries used)
+    pub attempts: u32,
+    /// Time taken including retries (milliseconds)
+    pub duration_ms: u64,
+    /// What the step produced, e.g. a snapshot path or recording directory
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub output: Option<String>,
+    /// Why the last attempt failed
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// Outcome of a script, returned by `execute_script`
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct ScriptReport {
+    /// Name of the script
+    pub name: Option<String>,
+    /// Steps that succeeded
+    pub succeeded: usize,
+    /// Steps that failed
+    pub failed: usize,
+    /// Whether a failed step stopped the script
+    pub aborted: bool,
+    /// Whether `cancel_script` stopped the script
+    pub cancelled: bool,
+    /// Total run time (milliseconds)
+    pub duration_ms: u64,
+    /// Steps that ran, in order
+    pub steps: Vec<StepReport>,
+}
+
+impl ScriptReport {
+    /// Whether every step ran and succeeded
+    #[must_use]
+    pub fn passed(&self) -> bool {
+        self.failed == 0 && !self.aborted && !self.cancelled
+    }
+}
+
+/// Executes the operations of a script
+pub trait StepRunner {
+    /// Run one operation, returning what it produced (e.g. a file path)
+    ///
+    /// # Errors
+    ///
+    /// Returns a description of the failure.
+    fn run(&mut self, operation: &Operation) -> std::result::Result<Option<String>, String>;
+
+    /// Called after each step, e.g. to report progress
+    fn finished(&mut self, _report: &StepReport) {}
+}
+
+/// Whether a script is running, and its cancellation
+#[derive(Debug, Default)]
+pub struct ScriptControl {
+    running: AtomicBool,
+    cancel: AtomicBool,
+}
+
+/// Marks a script as running until dropped
+#[derive(Debug)]
+pub struct RunGuard<'a>(&'a ScriptControl);
+
+impl Drop for RunGuard<'_> {
+    fn drop(&mut self) {
+        self.0.running.store(false, Ordering::Release);
+    }
+}
+
+impl ScriptControl {
+    /// Mark a script as running
+    ///
+    /// # Errors
+    ///
+    /// Returns `ScriptError::Busy` if one already is.
+    pub fn begin(&self) -> Result<RunGuard<'_>> {
+        if self.running.swap(true, Ordering::AcqRel) {
+            return Err(ScriptError::Busy);
+        }
+        self.cancel.store(false, Ordering::Release);
+        Ok(RunGuard(self))
+    }
+
+    /// Ask the running script to stop; returns whether one was running
+    pub fn cancel(&self) -> bool {
+        let running = self.running.load(Ordering::Acquire);
+        if running {
+            self.cancel.store(true, Ordering::Release);
+        }
+        running
+    }
+
+    /// Whether the running script should stop
+    #[must_use]
+    pub fn is_cancelled(&self) -> bool {
+        self.cancel.load(Ordering::Acquire)
+    }
+
+    /// Sleep for `duration` unless cancelled
+    ///
+    /// # Errors
+    ///
+    /// Returns `ScriptError::Cancelled` as soon as the script is cancelled.
+    pub fn sleep(&self, duration: Duration) -> Result<()> {
+        let deadline = Instant::now() + duration;
+        self.wait_until(duration, || Instant::now() >= deadline)
+            .map(|_| ())
+    }
+
+    /// Poll `done` until it returns true, for at most `timeout`
+    ///
+    /// Returns whether `done` became true.
+    ///
+    /// # Errors
+    ///
+    /// Returns `ScriptError::Cancelled` as soon as the script is cancelled.
+    pub fn wait_until(&self, timeout: Duration, mut done: impl FnMut() -> bool) -> Result<bool> {
+        let deadline = Instant::now() + timeout;
+        loop {
+            if self.is_cancelled() {
+                return Err(ScriptError::Cancelled);
+            }
+            if done() {
+                return Ok(true);
+            }
+            let now = Instant::now();
+            if now >= deadline {
+                return Ok(false);
+            }
+            std::thread::sleep(POLL_INTERVAL.min(deadline - now));
+        }
+    }
+}
+
+/// Run the steps of `script` in order with `runner`
+///
+/// Stops at the first failed step with the `abort` policy, or when `control`
+/// is cancelled.
+pub fn run_script(
+    script: &Script,
+    control: &ScriptControl,
+    runner: &mut impl StepRunner,
+) -> ScriptReport {
+    let started = Instant::now();
+    let mut report = ScriptReport {
+        name: script.name.clone(),
+        ..Default::default()
+    };
+
+    for (index, step) in script.steps.iter().enumerate() {
+        if control.is_cancelled() {
+            break;
+        }
+        let step_started = Instant::now();
+        let mut attempts = 0;
+        let outcome = loop {
+            attempts += 1;
+            match runner.run(&step.operation) {
+                Err(e) if attempts <= step.retries => {
+                    log::warn!(
+                        "Script step {} ({}) failed, retrying: {}",
+                        index,
+                        step.operation.name(),
+                        e
+                    );
+                    if control.sleep(RETRY_DELAY).is_err() {
+                        break Err(e);
+                    }
+                }
+                outcome => break outcome,
+            }
+        };
+
+        let (output, error) = match outcome {
+            Ok(output) => (output, None),
+            Err(e) => (None, Some(e)),
+        };
+        let step_report = StepReport {
+            index,
+            op: step.operation.name(),
+            ok: error.is_none(),
+            attempts,
+            duration_ms: step_started.elapsed().as_millis() as u64,
+            output,
+            error,
+        };
+        runner.finished(&step_report);
+        let ok = step_report.ok;
+        report.steps.push(step_report);
+
+        if ok {
+            report.succeeded += 1;
+        } else {
+            report.failed += 1;
+            if step.on_error.unwrap_or(script.on_error) == ErrorPolicy::Abort {
+                report.aborted = !control.is_cancelled();
+                break;
+            }
+        }
+    }
+
+    report.cancelled = control.is_cancelled();
+    report.duration_ms = started.elapsed().as_millis() as u64;
+    report
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Runner that fails the given operations a number of times
+    struct FakeRunner {
+        /// Operation names to fail, and how often
+        failures: Vec<(&'static str, u32)>,
+        ran: Vec<&'static str>,
+    }
+
+    impl FakeRunner {
+        fn new(failures: Vec<(&'static str, u32)>) -> Self {
+            Self {
+                failures,
+                ran: Vec::new(),
+            }
+        }
+    }
+
+    impl StepRunner for FakeRunner {
+        fn run(&mut self, operation: &Operation) -> std::result::Result<Option<String>, String> {
+            let name = operation.name();
+            self.ran.push(name);
+            match self.failures.iter_mut().find(|(op, _)| *op == name) {
+                Some((_, left)) if *left > 0 => {
+                    *left -= 1;
+                    Err(format!("{} failed", name))
+                }
+                _ => Ok(Some(name.to_string())),
+            }
+        }
+    }
+
+    fn script(json: &str) -> Script {
+        Script::parse(json).unwrap()
+    }
+
+    #[test]
+    fn test_parse_script() {
+        let parsed = script(
+            r#"{"name": "qa", "steps": [
+                {"op": "set_resolution", "width": 640, "height": 480},
+                {"op": "wait", "ms": 100},
+                {"op": "snapshot", "format": "png", "retries": 2},
+                {"op": "record", "seconds": 1.5, "on_error": "continue"}
+            ]}"#,
+        );
+        assert_eq!(parsed.name.as_deref(), Some("qa"));
+        assert_eq!(parsed.on_error, ErrorPolicy::Abort);
+        assert_eq!(
+            parsed.steps[2].operation,
+            Operation::Snapshot {
+                path: None,
+                format: Some(ImageFormat::Png)
+            }
+        );
+        assert_eq!(parsed.steps[2].retries, 2);
+        assert_eq!(parsed.steps[3].on_error, Some(ErrorPolicy::Continue));
+    }
+
+    #[test]
+    fn test_invalid_scripts_are_rejected() {
+        assert!(matches!(
+            Script::parse(r#"{"steps": []}"#),
+            Err(ScriptError::Parse(_))
+        ));
+        assert!(matches!(
+            Script::parse(r#"{"steps": [{"op": "launch_rocket"}]}"#),
+            Err(ScriptError::Parse(_))
+        ));
+        assert!(matches!(
+            Script::parse(
+                r#"{"steps": [{"op": "wait", "ms": 1}, {"op": "set_led", "level": 150}]}"#
+            ),
+            Err(ScriptError::InvalidStep { index: 1, .. })
+        ));
+        assert!(matches!(
+            Script::parse(r#"{"steps": [{"op": "record", "seconds": 0}]}"#),
+            Err(ScriptError::InvalidStep { index: 0, .. })
+        ));
+        assert!(matches!(
+            Script::parse(r#"{"steps": [{"op": "snapshot", "retries": 99}]}"#),
+            Err(ScriptError::InvalidStep { index: 0, .. })
+        ));
+    }
+
+    #[test]
+    fn test_abort_stops_at_the_failed_step() {
+        let script = script(
+            r#"{"steps": [{"op": "set_led", "level": 50}, {"op": "snapshot"}, {"op": "bookmark"}]}"#,
+        );
+        let mut runner = FakeRunner::new(vec![("snapshot", 1)]);
+        let report = run_script(&script, &ScriptControl::default(), &mut runner);
+        assert_eq!(runner.ran, ["set_led", "snapshot"]);
+        assert_eq!((report.succeeded, report.failed), (1, 1));
+        assert!(report.aborted && !report.passed());
+        assert_eq!(report.steps[0].output.as_deref(), Some("set_led"));
+        assert_eq!(report.steps[1].error.as_deref(), Some("snapshot failed"));
+    }
+
+    #[test]
+    fn test_continue_and_retries() {
+        let script = script(
+            r#"{"on_error": "continue", "steps": [
+                {"op": "snapshot", "retries": 1},
+                {"op": "set_control", "name": "gamma", "value": 3},
+                {"op": "bookmark", "on_error": "abort"}
+            ]}"#,
+        );
+        let mut runner = FakeRunner::new(vec![("snapshot", 1), ("set_control", 5)]);
+        let report = run_script(&script, &ScriptControl::default(), &mut runner);
+        assert_eq!(
+            runner.ran,
+            ["snapshot", "snapshot", "set_control", "bookmark"]
+        );
+        assert!(report.steps[0].ok);
+        assert_eq!(report.steps[0].attempts, 2);
+        assert!(!report.steps[1].ok);
+        assert_eq!((report.succeeded, report.failed), (2, 1));
+        assert!(!report.aborted);
+    }
+
+    #[test]
+    fn test_cancel_stops_the_script() {
+        struct Cancelling<'a>(&'a ScriptControl);
+        impl StepRunner for Cancelling<'_> {
+            fn run(&mut self, _: &Operation) -> std::result::Result<Option<String>, String> {
+                self.0.cancel();
+                Ok(None)
+            }
+        }
+
+        let control = ScriptControl::default();
+        let _guard = control.begin().unwrap();
+        assert!(matches!(control.begin(), Err(ScriptError::Busy)));
+
+        let script = script(r#"{"steps": [{"op": "wait", "ms": 1}, {"op": "wait", "ms": 1}]}"#);
+        let report = run_script(&script, &control, &mut Cancelling(&control));
+        assert_eq!(report.steps.len(), 1);
+        assert!(report.cancelled && !report.aborted);
+        assert!(matches!(
+            control.sleep(Duration::from_secs(60)),
+            Err(ScriptError::Cancelled)
+        ));
+    }
+
+    #[test]
+    fn test_guard_allows_the_next_script() {
+        let control = ScriptControl::default();
+        assert!(!control.cancel());
+        drop(control.begin().unwrap());
+        let _guard = control.begin().unwrap();
+        assert!(!control.is_cancelled());
+        assert!(control.wait_until(Duration::ZERO, || true).unwrap());
+    }
+}
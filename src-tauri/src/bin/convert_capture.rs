@@ -0,0 +1,58 @@
+//! Converts packet captures between the legacy and current file layouts.
+//!
+//! Run with: `cargo run --bin convert_capture -- <to-packets|to-legacy> <input.bin> [output_dir]`
+//!
+//! `to-packets` migrates a legacy `capture_*.bin` (with its `capture_*.json`,
+//! if present) to `packets_*.bin` and `metadata_*.json`. `to-legacy` does the
+//! reverse for `packets_*.bin`, keeping its timestamps and endpoints (or
+//! synthesizing timestamps for headerless version 1 files). The output
+//! directory defaults to the directory of the input file.
+
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+
+use clean_scope_lib::capture::{convert_legacy_to_packets, convert_packets_to_legacy};
+use clean_scope_lib::storage::Storage;
+
+const USAGE: &str = "Usage: convert_capture <to-packets|to-legacy> <input.bin> [output_dir]";
+
+fn main() -> ExitCode {
+    let mut args = std::env::args_os().skip(1);
+    let (Some(verb), Some(input)) = (args.next(), args.next().map(PathBuf::from)) else {
+        eprintln!("{}", USAGE);
+        return ExitCode::FAILURE;
+    };
+    let output_dir = args.next().map_or_else(
+        || {
+            input
+                .parent()
+                .filter(|dir| !dir.as_os_str().is_empty())
+                .map_or_else(|| PathBuf::from("."), Path::to_path_buf)
+        },
+        PathBuf::from,
+    );
+    let storage = Storage::new(output_dir);
+
+    let result = match verb.to_str() {
+        Some("to-packets") => convert_legacy_to_packets(&storage, &input),
+        Some("to-legacy") => convert_packets_to_legacy(&storage, &input),
+        _ => {
+            eprintln!("{}", USAGE);
+            return ExitCode::FAILURE;
+        }
+    };
+
+    match result {
+        Ok(result) => {
+            println!(
+                "Wrote {} packets to {} ({})",
+                result.metadata.total_packets, result.packets_path, result.metadata_path
+            );
+            ExitCode::SUCCESS
+        }
+        Err(e) => {
+            eprintln!("Conversion failed: {}", e);
+            ExitCode::FAILURE
+        }
+    }
+}
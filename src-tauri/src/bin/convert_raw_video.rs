@@ -0,0 +1,43 @@
+//! Converts a raw video recording to YUV4MPEG2 (`.y4m`).
+//!
+//! Run with: `cargo run --bin convert_raw_video -- <recording_dir> [output.y4m]`
+//!
+//! The recording directory is one pulled from the device (for example with
+//! `adb pull`) that was recorded in raw video mode, so it contains
+//! `raw_frames.bin` and `raw_index.json`. The output defaults to
+//! `raw_video.y4m` inside the recording directory. Y4M plays in mpv and
+//! converts with ffmpeg, e.g. `ffmpeg -i raw_video.y4m -c:v ffv1 out.mkv`.
+
+use std::fs::File;
+use std::io::BufWriter;
+use std::path::PathBuf;
+use std::process::ExitCode;
+
+use clean_scope_lib::raw_video::{write_y4m, RawVideoReader};
+
+fn main() -> ExitCode {
+    let mut args = std::env::args_os().skip(1);
+    let Some(directory) = args.next().map(PathBuf::from) else {
+        eprintln!("Usage: convert_raw_video <recording_dir> [output.y4m]");
+        return ExitCode::FAILURE;
+    };
+    let output = args
+        .next()
+        .map_or_else(|| directory.join("raw_video.y4m"), PathBuf::from);
+
+    let result = RawVideoReader::open(&directory).and_then(|mut reader| {
+        let mut out = BufWriter::new(File::create(&output)?);
+        write_y4m(&mut reader, &mut out)
+    });
+
+    match result {
+        Ok(frames) => {
+            println!("Wrote {} frames to {}", frames, output.display());
+            ExitCode::SUCCESS
+        }
+        Err(e) => {
+            eprintln!("Conversion failed: {}", e);
+            ExitCode::FAILURE
+        }
+    }
+}
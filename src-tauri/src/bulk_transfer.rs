@@ -0,0 +1,231 @@
+//! Bulk transfer sizing, timeout and retry policy
+//!
+//! UVC cameras with a bulk streaming endpoint send one payload per transfer,
+//! terminated by a short packet. A transfer buffer smaller than the negotiated
+//! `dwMaxPayloadTransferSize` splits payloads or overflows, so the buffer is
+//! sized from the probe/commit result unless overridden.
+//!
+//! Settings are read from environment variables at startup:
+//!
+//! | Variable | Default | Meaning |
+//! |----------|---------|---------|
+//! | `CLEANSCOPE_BULK_TRANSFER_SIZE` | from `dwMaxPayloadTransferSize` | Transfer buffer size in bytes |
+//! | `CLEANSCOPE_BULK_TIMEOUT_MS` | 1000 | Timeout of a single transfer |
+//! | `CLEANSCOPE_BULK_RETRIES` | 3 | Consecutive failed transfers to retry before giving up |
+//! | `CLEANSCOPE_BULK_STALL_TIMEOUTS` | 3 | Consecutive timeouts before the stream is reported as stalled |
+//!
+//! With libusb on Android, bulk endpoints stream through asynchronous
+//! transfers (`libusb_android::BulkStream`) that never time out, so only the
+//! transfer size and retries apply there.
+
+/// Transfer size used when the camera reports no `dwMaxPayloadTransferSize`
+pub const DEFAULT_TRANSFER_SIZE: usize = 16 * 1024;
+
+/// Smallest transfer size (one high-speed bulk packet)
+pub const MIN_TRANSFER_SIZE: usize = 512;
+
+/// Largest transfer size
+pub const MAX_TRANSFER_SIZE: usize = 1024 * 1024;
+
+/// Default timeout of a single bulk transfer (milliseconds)
+pub const DEFAULT_TIMEOUT_MS: u32 = 1000;
+
+/// Default number of consecutive failed transfers to retry
+pub const DEFAULT_MAX_RETRIES: u32 = 3;
+
+/// Default number of consecutive timeouts before the stream counts as stalled
+pub const DEFAULT_STALL_TIMEOUTS: u32 = 3;
+
+/// Bulk streaming settings
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BulkTransferConfig {
+    /// Fixed transfer size in bytes (`None` = derive from `dwMaxPayloadTransferSize`)
+    pub transfer_size: Option<usize>,
+    /// Timeout of a single transfer in milliseconds
+    pub timeout_ms: u32,
+    /// Consecutive failed transfers (other than timeouts) to retry before giving up
+    pub max_retries: u32,
+    /// Consecutive timeouts before the stream is reported as stalled
+    pub stall_after_timeouts: u32,
+}
+
+impl Default for BulkTransferConfig {
+    fn default() -> Self {
+        Self {
+            transfer_size: None,
+            timeout_ms: DEFAULT_TIMEOUT_MS,
+            max_retries: DEFAULT_MAX_RETRIES,
+            stall_after_timeouts: DEFAULT_STALL_TIMEOUTS,
+        }
+    }
+}
+
+impl BulkTransferConfig {
+    /// Read settings from the `CLEANSCOPE_BULK_*` environment variables
+    pub fn from_env() -> Self {
+        Self::from_lookup(|key| std::env::var(key).ok())
+    }
+
+    /// Read settings through `lookup`, ignoring missing or unparsable values
+    pub fn from_lookup(lookup: impl Fn(&str) -> Option<String>) -> Self {
+        let parse = |key: &str| -> Option<u64> {
+            let value = lookup(key)?;
+            match value.trim().parse() {
+                Ok(n) => Some(n),
+                Err(_) => {
+                    log::warn!("Ignoring invalid {}={:?}", key, value);
+                    None
+                }
+            }
+        };
+
+        let defaults = Self::default();
+        Self {
+            transfer_size: parse("CLEANSCOPE_BULK_TRANSFER_SIZE")
+                .filter(|&n| n > 0)
+                .map(|n| n as usize),
+            timeout_ms: parse("CLEANSCOPE_BULK_TIMEOUT_MS")
+                .filter(|&n| n > 0)
+                .map_or(defaults.timeout_ms, |n| n.min(u64::from(u32::MAX)) as u32),
+            max_retries: parse("CLEANSCOPE_BULK_RETRIES")
+                .map_or(defaults.max_retries, |n| n.min(u64::from(u32::MAX)) as u32),
+            stall_after_timeouts: parse("CLEANSCOPE_BULK_STALL_TIMEOUTS")
+                .filter(|&n| n > 0)
+                .map_or(defaults.stall_after_timeouts, |n| {
+                    n.min(u64::from(u32::MAX)) as u32
+                }),
+        }
+    }
+
+    /// Transfer buffer size for a camera that negotiated `max_payload` bytes
+    ///
+    /// Rounded up to whole 512-byte packets and clamped to
+    /// [`MIN_TRANSFER_SIZE`]..=[`MAX_TRANSFER_SIZE`].
+    pub fn transfer_size(&self, max_payload: u32) -> usize {
+        let size = match self.transfer_size {
+            Some(size) => size,
+            None if max_payload == 0 => DEFAULT_TRANSFER_SIZE,
+            None => max_payload as usize,
+        };
+        size.clamp(MIN_TRANSFER_SIZE, MAX_TRANSFER_SIZE)
+            .next_multiple_of(MIN_TRANSFER_SIZE)
+    }
+}
+
+/// Change in a run of consecutive timeouts
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StreakChange {
+    /// Nothing worth reporting
+    None,
+    /// The streak just reached the stall threshold
+    Stalled(u32),
+    /// Data arrived after a stall of the given number of timeouts
+    Recovered(u32),
+}
+
+/// Tracks consecutive bulk transfer timeouts
+#[derive(Debug, Clone)]
+pub struct TimeoutStreak {
+    count: u32,
+    threshold: u32,
+}
+
+impl TimeoutStreak {
+    /// Track timeouts, reporting a stall after `threshold` in a row
+    pub fn new(threshold: u32) -> Self {
+        Self {
+            count: 0,
+            threshold: threshold.max(1),
+        }
+    }
+
+    /// Current number of consecutive timeouts
+    pub fn count(&self) -> u32 {
+        self.count
+    }
+
+    /// Whether the streak has reached the stall threshold
+    pub fn is_stalled(&self) -> bool {
+        self.count >= self.threshold
+    }
+
+    /// Record a timed-out transfer
+    pub fn timeout(&mut self) -> StreakChange {
+        self.count = self.count.saturating_add(1);
+        if self.count == self.threshold {
+            StreakChange::Stalled(self.count)
+        } else {
+            StreakChange::None
+        }
+    }
+
+    /// Record a transfer that returned data
+    pub fn success(&mut self) -> StreakChange {
+        let change = if self.is_stalled() {
+            StreakChange::Recovered(self.count)
+        } else {
+            StreakChange::None
+        };
+        self.count = 0;
+        change
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_transfer_size_from_max_payload() {
+        let config = BulkTransferConfig::default();
+        assert_eq!(config.transfer_size(0), DEFAULT_TRANSFER_SIZE);
+        assert_eq!(config.transfer_size(3072), 3072);
+        assert_eq!(config.transfer_size(3000), 3072);
+        assert_eq!(config.transfer_size(100), MIN_TRANSFER_SIZE);
+        assert_eq!(config.transfer_size(u32::MAX), MAX_TRANSFER_SIZE);
+    }
+
+    #[test]
+    fn test_transfer_size_override() {
+        let config = BulkTransferConfig {
+            transfer_size: Some(65536),
+            ..Default::default()
+        };
+        assert_eq!(config.transfer_size(3072), 65536);
+    }
+
+    #[test]
+    fn test_from_lookup() {
+        let config = BulkTransferConfig::from_lookup(|key| match key {
+            "CLEANSCOPE_BULK_TRANSFER_SIZE" => Some("32768".to_string()),
+            "CLEANSCOPE_BULK_TIMEOUT_MS" => Some(" 250 ".to_string()),
+            "CLEANSCOPE_BULK_RETRIES" => Some("0".to_string()),
+            "CLEANSCOPE_BULK_STALL_TIMEOUTS" => Some("many".to_string()),
+            _ => None,
+        });
+        assert_eq!(config.transfer_size, Some(32768));
+        assert_eq!(config.timeout_ms, 250);
+        assert_eq!(config.max_retries, 0);
+        assert_eq!(config.stall_after_timeouts, DEFAULT_STALL_TIMEOUTS);
+
+        assert_eq!(
+            BulkTransferConfig::from_lookup(|_| None),
+            BulkTransferConfig::default()
+        );
+    }
+
+    #[test]
+    fn test_timeout_streak() {
+        let mut streak = TimeoutStreak::new(3);
+        assert_eq!(streak.timeout(), StreakChange::None);
+        assert_eq!(streak.success(), StreakChange::None);
+
+        assert_eq!(streak.timeout(), StreakChange::None);
+        assert_eq!(streak.timeout(), StreakChange::None);
+        assert_eq!(streak.timeout(), StreakChange::Stalled(3));
+        assert_eq!(streak.timeout(), StreakChange::None);
+        assert!(streak.is_stalled());
+        assert_eq!(streak.success(), StreakChange::Recovered(4));
+        assert_eq!(streak.count(), 0);
+    }
+}
@@ -0,0 +1,738 @@
+//! Measurement calibration
+//!
+//! A [`Calibration`] maps frame pixels to millimetres with a scale
+//! (`pixels_per_mm`) and a single-coefficient radial distortion model, which
+//! is enough for the barrel distortion of typical endoscope lenses:
+//!
+//! ```text
+//! undistorted = center + p * (1 + k1 * (|p| / R)^2),  p = pixel - center
+//! ```
+//!
+//! where `R` is half the frame diagonal. It can be set manually from two
+//! points a known distance apart, or detected in one step from a printed
+//! target with [`detect_target`]: a grid of dark dots (spacing = centre to
+//! centre) or a checkerboard (spacing = square size) on light paper.
+//!
+//! Detection thresholds the frame (Otsu), collects the dark blobs, keeps the
+//! ones of similar size and shape, pairs each with its nearest grid
+//! neighbours and solves for the scale and `k1` that make every neighbour
+//! distance equal in undistorted space.
+
+use std::collections::HashSet;
+
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::zoom::Zoom;
+
+/// Fewest target points needed for a calibration
+pub const MIN_TARGET_POINTS: usize = 9;
+
+/// Most blobs considered before the frame is rejected as too noisy
+const MAX_BLOBS: usize = 4096;
+
+/// Smallest blob area in pixels counted as a target point
+const MIN_BLOB_AREA: usize = 12;
+
+/// Smallest luminance range a frame needs for thresholding
+const MIN_CONTRAST: u8 = 32;
+
+/// Blob areas accepted relative to the median area
+const AREA_TOLERANCE: f64 = 2.0;
+
+/// Neighbour distances accepted relative to a point's nearest neighbour
+const NEIGHBOUR_TOLERANCE: f64 = 1.25;
+
+/// Largest RMS error of the fitted neighbour distances (fraction of spacing)
+const MAX_RESIDUAL: f64 = 0.1;
+
+/// Errors calibrating measurements
+#[derive(Debug, Error)]
+pub enum CalibrationError {
+    /// Spacing, length or points are unusable
+    #[error("invalid calibration: {0}")]
+    Invalid(String),
+    /// Frame buffer does not match its dimensions
+    #[error("frame is {len} bytes, expected {expected} for {width}x{height} RGB")]
+    FrameSize {
+        /// Buffer length
+        len: usize,
+        /// Expected length
+        expected: usize,
+        /// Frame width
+        width: u32,
+        /// Frame height
+        height: u32,
+    },
+    /// No usable target in the frame
+    #[error("calibration target not found: {0}")]
+    TargetNotFound(String),
+    /// Made at another digital zoom than the frame being measured
+    #[error("calibrated at {0} zoom; restore that zoom or recalibrate")]
+    ZoomMismatch(Zoom),
+}
+
+/// Result type alias for calibration operations
+pub type Result<T> = std::result::Result<T, CalibrationError>;
+
+/// Printed target layout
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TargetKind {
+    /// Dark dots on a square grid
+    Dots,
+    /// Checkerboard of dark and light squares
+    Checker,
+}
+
+/// Target to look for, passed to `calibrate_from_target`
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct TargetSpec {
+    /// Layout
+    pub kind: TargetKind,
+    /// Dot pitch (centre to centre) or checker square size, in millimetres
+    pub spacing_mm: f32,
+}
+
+impl TargetSpec {
+    /// Distance between neighbouring dark blobs in millimetres
+    ///
+    /// Dark checker squares only touch diagonally, so their centres are a
+    /// square diagonal apart.
+    fn blob_spacing_mm(&self) -> f64 {
+        let spacing = f64::from(self.spacing_mm);
+        match self.kind {
+            TargetKind::Dots => spacing,
+            TargetKind::Checker => spacing * std::f64::consts::SQRT_2,
+        }
+    }
+}
+
+/// How a calibration was obtained
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "method", rename_all = "snake_case")]
+pub enum CalibrationSource {
+    /// Two points a known distance apart
+    Manual {
+        /// Reference length in millimetres
+        length_mm: f32,
+    },
+    /// Detected printed target
+    Target {
+        /// Target that was detected
+        target: TargetSpec,
+        /// Number of target points used
+        points: usize,
+        /// RMS error of the fitted neighbour distances, as a fraction of the spacing
+        residual: f32,
+    },
+}
+
+/// Pixel to millimetre mapping for one resolution
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Calibration {
+    /// Scale at the distortion centre
+    pub pixels_per_mm: f32,
+    /// Radial distortion coefficient (0: none, negative: pincushion)
+    pub k1: f32,
+    /// Frame width the calibration was made at
+    pub width: u32,
+    /// Frame height the calibration was made at
+    pub height: u32,
+    /// Digital zoom of the frames the calibration was made on
+    pub zoom: Zoom,
+    /// How it was obtained
+    pub source: CalibrationSource,
+}
+
+impl Calibration {
+    /// Calibrate from two points `length_mm` apart, without distortion
+    ///
+    /// # Errors
+    ///
+    /// Returns `CalibrationError::Invalid` if the points coincide or the
+    /// length isn't positive.
+    pub fn from_reference(
+        width: u32,
+        height: u32,
+        a: (f32, f32),
+        b: (f32, f32),
+        length_mm: f32,
+    ) -> Result<Self> {
+        if !(length_mm.is_finite() && length_mm > 0.0) {
+            return Err(CalibrationError::Invalid(format!(
+                "reference length {} mm must be positive",
+                length_mm
+            )));
+        }
+        let pixels = (b.0 - a.0).hypot(b.1 - a.1);
+        if !(pixels.is_finite() && pixels >= 1.0) {
+            return Err(CalibrationError::Invalid(
+                "reference points must be at least a pixel apart".to_string(),
+            ));
+        }
+        Ok(Self {
+            pixels_per_mm: pixels / length_mm,
+            k1: 0.0,
+            width,
+            height,
+            zoom: Zoom::default(),
+            source: CalibrationSource::Manual { length_mm },
+        })
+    }
+
+    /// The calibration, made on frames zoomed by `zoom`
+    #[must_use]
+    pub fn with_zoom(mut self, zoom: Zoom) -> Self {
+        self.zoom = zoom;
+        self
+    }
+
+    /// Whether the calibration was made at this resolution
+    #[must_use]
+    pub fn matches(&self, width: u32, height: u32) -> bool {
+        self.width == width && self.height == height
+    }
+
+    /// Check that frames zoomed by `zoom` can be measured with the calibration
+    ///
+    /// Zooming scales the pixels and moves the distortion centre, so only
+    /// the zoom the calibration was made at applies.
+    ///
+    /// # Errors
+    ///
+    /// Returns `CalibrationError::ZoomMismatch` for any other zoom.
+    pub fn check_zoom(&self, zoom: &Zoom) -> Result<()> {
+        if self.zoom == *zoom || (self.zoom.is_identity() && zoom.is_identity()) {
+            Ok(())
+        } else {
+            Err(CalibrationError::ZoomMismatch(self.zoom))
+        }
+    }
+
+    /// Relative standard uncertainty of the scale
+    ///
+    /// Target calibrations average the remaining spacing error over their
+    /// points; manual ones assume each reference point is placed within a
+    /// pixel.
+    #[must_use]
+    pub fn scale_uncertainty(&self) -> f32 {
+        match &self.source {
+            CalibrationSource::Manual { length_mm } => {
+                std::f32::consts::SQRT_2 / (self.pixels_per_mm * length_mm)
+            }
+            CalibrationSource::Target {
+                points, residual, ..
+            } => residual / (*points as f32).sqrt(),
+        }
+    }
+
+    /// Remove lens distortion from a pixel position
+    #[must_use]
+    pub fn undistort(&self, point: (f32, f32)) -> (f32, f32) {
+        let geometry = Geometry::new(self.width, self.height);
+        let (x, y) =
+            geometry.undistort((f64::from(point.0), f64::from(point.1)), f64::from(self.k1));
+        (x as f32, y as f32)
+    }
+
+    /// Distance between two pixel positions in millimetres
+    #[must_use]
+    pub fn distance_mm(&self, a: (f32, f32), b: (f32, f32)) -> f32 {
+        let (a, b) = (self.undistort(a), self.undistort(b));
+        (b.0 - a.0).hypot(b.1 - a.1) / self.pixels_per_mm
+    }
+}
+
+/// Distortion centre and normalization radius of a frame
+#[derive(Debug, Clone, Copy)]
+struct Geometry {
+    cx: f64,
+    cy: f64,
+    radius: f64,
+}
+
+impl Geometry {
+    fn new(width: u32, height: u32) -> Self {
+        let (w, h) = (f64::from(width), f64::from(height));
+        Self {
+            cx: w / 2.0,
+            cy: h / 2.0,
+            radius: (w.hypot(h) / 2.0).max(1.0),
+        }
+    }
+
+    /// Offset from the centre, in units of `radius`
+    fn normalized(&self, (x, y): (f64, f64)) -> (f64, f64) {
+        ((x - self.cx) / self.radius, (y - self.cy) / self.radius)
+    }
+
+    fn undistort(&self, point: (f64, f64), k1: f64) -> (f64, f64) {
+        let (nx, ny) = self.normalized(point);
+        let scale = 1.0 + k1 * (nx * nx + ny * ny);
+        (
+            self.cx + (point.0 - self.cx) * scale,
+            self.cy + (point.1 - self.cy) * scale,
+        )
+    }
+}
+
+/// Find a printed target in an RGB24 frame and calibrate from it
+///
+/// # Errors
+///
+/// Returns `CalibrationError::Invalid` for a non-positive spacing,
+/// `CalibrationError::FrameSize` for a short buffer and
+/// `CalibrationError::TargetNotFound` if too few regular points are found or
+/// they don't form an even grid.
+pub fn detect_target(
+    rgb: &[u8],
+    width: u32,
+    height: u32,
+    target: TargetSpec,
+) -> Result<Calibration> {
+    if !(target.spacing_mm.is_finite() && target.spacing_mm > 0.0) {
+        return Err(CalibrationError::Invalid(format!(
+            "target spacing {} mm must be positive",
+            target.spacing_mm
+        )));
+    }
+    let expected = width as usize * height as usize * 3;
+    if rgb.len() < expected || expected == 0 {
+        return Err(CalibrationError::FrameSize {
+            len: rgb.len(),
+            expected,
+            width,
+            height,
+        });
+    }
+
+    let gray: Vec<u8> = rgb[..expected]
+        .chunks_exact(3)
+        .map(|p| ((u32::from(p[0]) * 77 + u32::from(p[1]) * 150 + u32::from(p[2]) * 29) >> 8) as u8)
+        .collect();
+    let threshold = otsu_threshold(&gray)?;
+    let blobs = dark_blobs(&gray, width as usize, height as usize, threshold)?;
+    let points = target_points(&blobs);
+    if points.len() < MIN_TARGET_POINTS {
+        return Err(CalibrationError::TargetNotFound(format!(
+            "found {} target points, need at least {}",
+            points.len(),
+            MIN_TARGET_POINTS
+        )));
+    }
+
+    let geometry = Geometry::new(width, height);
+    let pairs = neighbour_pairs(&points);
+    let fit = fit_grid(&points, &pairs, geometry).ok_or_else(|| {
+        CalibrationError::TargetNotFound("target points don't form a grid".to_string())
+    })?;
+    if fit.residual > MAX_RESIDUAL {
+        return Err(CalibrationError::TargetNotFound(format!(
+            "grid spacing varies by {:.0}% after correction",
+            fit.residual * 100.0
+        )));
+    }
+
+    Ok(Calibration {
+        pixels_per_mm: (fit.spacing / target.blob_spacing_mm()) as f32,
+        k1: fit.k1 as f32,
+        width,
+        height,
+        zoom: Zoom::default(),
+        source: CalibrationSource::Target {
+            target,
+            points: points.len(),
+            residual: fit.residual as f32,
+        },
+    })
+}
+
+/// Threshold separating dark and light pixels (Otsu's method)
+fn otsu_threshold(gray: &[u8]) -> Result<u8> {
+    let mut histogram = [0usize; 256];
+    for &v in gray {
+        histogram[v as usize] += 1;
+    }
+    let min = histogram.iter().position(|&n| n > 0).unwrap_or(0);
+    let max = histogram.iter().rposition(|&n| n > 0).unwrap_or(0);
+    if max - min < usize::from(MIN_CONTRAST) {
+        return Err(CalibrationError::TargetNotFound(
+            "frame has too little contrast".to_string(),
+        ));
+    }
+
+    let total = gray.len() as f64;
+    let sum: f64 = histogram
+        .iter()
+        .enumerate()
+        .map(|(v, &n)| v as f64 * n as f64)
+        .sum();
+    let (mut best, mut best_variance) = (min, 0.0);
+    let (mut below, mut below_sum) = (0.0, 0.0);
+    for (v, &n) in histogram.iter().enumerate().take(max) {
+        below += n as f64;
+        below_sum += v as f64 * n as f64;
+        if below == 0.0 || below == total {
+            continue;
+        }
+        let above = total - below;
+        let difference = below_sum / below - (sum - below_sum) / above;
+        let variance = below * above * difference * difference;
+        if variance > best_variance {
+            best_variance = variance;
+            best = v;
+        }
+    }
+    Ok(best as u8)
+}
+
+/// A connected region of dark pixels
+#[derive(Debug, Clone, Copy)]
+struct Blob {
+    area: usize,
+    x: f64,
+    y: f64,
+    /// Fraction of the bounding box covered
+    fill: f64,
+    /// Bounding box width over height
+    aspect: f64,
+}
+
+/// 4-connected regions at or below `threshold`, excluding those touching the border
+fn dark_blobs(gray: &[u8], width: usize, height: usize, threshold: u8) -> Result<Vec<Blob>> {
+    let mut visited = vec![false; gray.len()];
+    let mut blobs = Vec::new();
+    let mut stack = Vec::new();
+    for start in 0..gray.len() {
+        if visited[start] || gray[start] > threshold {
+            continue;
+        }
+        visited[start] = true;
+        stack.push(start);
+        let (mut area, mut sum_x, mut sum_y) = (0usize, 0usize, 0usize);
+        let (mut min_x, mut min_y, mut max_x, mut max_y) = (width, height, 0, 0);
+        while let Some(i) = stack.pop() {
+            let (x, y) = (i % width, i / width);
+            area += 1;
+            sum_x += x;
+            sum_y += y;
+            min_x = min_x.min(x);
+            max_x = max_x.max(x);
+            min_y = min_y.min(y);
+            max_y = max_y.max(y);
+            let neighbours = [
+                (x > 0).then(|| i - 1),
+                (x + 1 < width).then(|| i + 1),
+                (y > 0).then(|| i - width),
+                (y + 1 < height).then(|| i + width),
+            ];
+            for n in neighbours.into_iter().flatten() {
+                if !visited[n] && gray[n] <= threshold {
+                    visited[n] = true;
+                    stack.push(n);
+                }
+            }
+        }
+
+        let touches_border = min_x == 0 || min_y == 0 || max_x + 1 == width || max_y + 1 == height;
+        if touches_border || area < MIN_BLOB_AREA {
+            continue;
+        }
+        if blobs.len() == MAX_BLOBS {
+            return Err(CalibrationError::TargetNotFound(
+                "frame is too noisy".to_string(),
+            ));
+        }
+        let (box_w, box_h) = ((max_x - min_x + 1) as f64, (max_y - min_y + 1) as f64);
+        blobs.push(Blob {
+            area,
+            x: sum_x as f64 / area as f64 + 0.5,
+            y: sum_y as f64 / area as f64 + 0.5,
+            fill: area as f64 / (box_w * box_h),
+            aspect: box_w / box_h,
+        });
+    }
+    Ok(blobs)
+}
+
+/// Centres of the compact blobs of similar size
+fn target_points(blobs: &[Blob]) -> Vec<(f64, f64)> {
+    let compact: Vec<&Blob> = blobs
+        .iter()
+        .filter(|b| b.fill >= 0.45 && (0.5..=2.0).contains(&b.aspect))
+        .collect();
+    let mut areas: Vec<usize> = compact.iter().map(|b| b.area).collect();
+    areas.sort_unstable();
+    let Some(&median) = areas.get(areas.len() / 2) else {
+        return Vec::new();
+    };
+    let median = median as f64;
+    compact
+        .into_iter()
+        .filter(|b| {
+            let ratio = b.area as f64 / median;
+            (1.0 / AREA_TOLERANCE..=AREA_TOLERANCE).contains(&ratio)
+        })
+        .map(|b| (b.x, b.y))
+        .collect()
+}
+
+/// Index pairs of grid neighbours: each point's nearest points within tolerance
+fn neighbour_pairs(points: &[(f64, f64)]) -> Vec<(usize, usize)> {
+    let distance = |a: (f64, f64), b: (f64, f64)| (b.0 - a.0).hypot(b.1 - a.1);
+    let mut pairs = Vec::new();
+    let mut seen = HashSet::new();
+    for (i, &a) in points.iter().enumerate() {
+        let nearest = points
+            .iter()
+            .enumerate()
+            .filter(|&(j, _)| j != i)
+            .map(|(_, &b)| distance(a, b))
+            .fold(f64::INFINITY, f64::min);
+        for (j, &b) in points.iter().enumerate() {
+            let is_neighbour = j != i && distance(a, b) <= nearest * NEIGHBOUR_TOLERANCE;
+            let pair = (i.min(j), i.max(j));
+            if is_neighbour && seen.insert(pair) {
+                pairs.push(pair);
+            }
+        }
+    }
+    pairs
+}
+
+/// Fitted grid spacing and distortion
+struct GridFit {
+    /// Undistorted neighbour distance in pixels
+    spacing: f64,
+    k1: f64,
+    /// RMS of the remaining distance errors, as a fraction of `spacing`
+    residual: f64,
+}
+
+/// Solve for the `k1` and spacing that make all neighbour distances equal
+///
+/// Undistorting scales a short segment `d` at normalized offset `p` to about
+/// `|d| + k1 * (|p|²|d|² + 2(p·d)²) / |d|`, which is linear in `k1`, so both
+/// unknowns come from one least-squares solve.
+fn fit_grid(
+    points: &[(f64, f64)],
+    pairs: &[(usize, usize)],
+    geometry: Geometry,
+) -> Option<GridFit> {
+    if pairs.len() < 2 {
+        return None;
+    }
+    let rows: Vec<(f64, f64)> = pairs
+        .iter()
+        .map(|&(i, j)| {
+            let (a, b) = (points[i], points[j]);
+            let d = (b.0 - a.0, b.1 - a.1);
+            let length = d.0.hypot(d.1);
+            let p = geometry.normalized(((a.0 + b.0) / 2.0, (a.1 + b.1) / 2.0));
+            let r2 = p.0 * p.0 + p.1 * p.1;
+            let dot = p.0 * d.0 + p.1 * d.1;
+            (length, (r2 * length * length + 2.0 * dot * dot) / length)
+        })
+        .collect();
+
+    // Minimize sum((length + k1 * slope - spacing)^2) over k1 and spacing
+    let n = rows.len() as f64;
+    let mean_length = rows.iter().map(|r| r.0).sum::<f64>() / n;
+    let mean_slope = rows.iter().map(|r| r.1).sum::<f64>() / n;
+    let covariance: f64 = rows
+        .iter()
+        .map(|r| (r.0 - mean_length) * (r.1 - mean_slope))
+        .sum();
+    let variance: f64 = rows.iter().map(|r| (r.1 - mean_slope).powi(2)).sum();
+    let k1 = if variance > f64::EPSILON * n {
+        -covariance / variance
+    } else {
+        0.0
+    };
+    let spacing = mean_length + k1 * mean_slope;
+    if !(spacing.is_finite() && spacing > 0.0) {
+        return None;
+    }
+    let squared_error: f64 = rows
+        .iter()
+        .map(|r| (r.0 + k1 * r.1 - spacing).powi(2))
+        .sum();
+    Some(GridFit {
+        spacing,
+        k1,
+        residual: (squared_error / n).sqrt() / spacing,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const WIDTH: u32 = 320;
+    const HEIGHT: u32 = 240;
+
+    fn white() -> Vec<u8> {
+        vec![235u8; (WIDTH * HEIGHT * 3) as usize]
+    }
+
+    fn fill_pixel(rgb: &mut [u8], x: i64, y: i64) {
+        if (0..i64::from(WIDTH)).contains(&x) && (0..i64::from(HEIGHT)).contains(&y) {
+            let i = (y as usize * WIDTH as usize + x as usize) * 3;
+            rgb[i..i + 3].copy_from_slice(&[20, 20, 20]);
+        }
+    }
+
+    fn draw_dot(rgb: &mut [u8], (cx, cy): (f64, f64), radius: f64) {
+        for y in (cy - radius).floor() as i64..=(cy + radius).ceil() as i64 {
+            for x in (cx - radius).floor() as i64..=(cx + radius).ceil() as i64 {
+                if (x as f64 + 0.5 - cx).hypot(y as f64 + 0.5 - cy) <= radius {
+                    fill_pixel(rgb, x, y);
+                }
+            }
+        }
+    }
+
+    /// Dot grid with `pitch` pixel spacing, distorted so that `k1` undoes it
+    fn dot_grid(pitch: f64, k1: f64) -> Vec<u8> {
+        let geometry = Geometry::new(WIDTH, HEIGHT);
+        let mut rgb = white();
+        for row in -3..=3 {
+            for col in -4..=4 {
+                let target = (
+                    geometry.cx + f64::from(col) * pitch,
+                    geometry.cy + f64::from(row) * pitch,
+                );
+                // Invert the undistortion by fixed-point iteration
+                let mut point = target;
+                for _ in 0..20 {
+                    let (nx, ny) = geometry.normalized(point);
+                    let scale = 1.0 + k1 * (nx * nx + ny * ny);
+                    point = (
+                        geometry.cx + (target.0 - geometry.cx) / scale,
+                        geometry.cy + (target.1 - geometry.cy) / scale,
+                    );
+                }
+                draw_dot(&mut rgb, point, 5.0);
+            }
+        }
+        rgb
+    }
+
+    fn dots(spacing_mm: f32) -> TargetSpec {
+        TargetSpec {
+            kind: TargetKind::Dots,
+            spacing_mm,
+        }
+    }
+
+    #[test]
+    fn test_detects_dot_grid_scale() {
+        let calibration = detect_target(&dot_grid(30.0, 0.0), WIDTH, HEIGHT, dots(2.0)).unwrap();
+        assert!((calibration.pixels_per_mm - 15.0).abs() < 0.3);
+        assert!(calibration.k1.abs() < 0.02);
+        assert!(matches!(
+            calibration.source,
+            CalibrationSource::Target { points: 63, .. }
+        ));
+        assert!((calibration.distance_mm((100.0, 100.0), (130.0, 100.0)) - 2.0).abs() < 0.1);
+    }
+
+    #[test]
+    fn test_recovers_barrel_distortion() {
+        let calibration = detect_target(&dot_grid(30.0, 0.3), WIDTH, HEIGHT, dots(1.0)).unwrap();
+        assert!(
+            (calibration.k1 - 0.3).abs() < 0.06,
+            "k1 = {}",
+            calibration.k1
+        );
+        assert!((calibration.pixels_per_mm - 30.0).abs() < 1.0);
+    }
+
+    #[test]
+    fn test_detects_checkerboard() {
+        let mut rgb = white();
+        let square = 20i64;
+        for y in 20..220i64 {
+            for x in 20..300i64 {
+                if ((x - 20) / square + (y - 20) / square) % 2 == 0 {
+                    fill_pixel(&mut rgb, x, y);
+                }
+            }
+        }
+        // Separate diagonally touching squares, as a slightly blurred print does
+        for y in (40..220i64).step_by(square as usize) {
+            for x in (40..300i64).step_by(square as usize) {
+                for (dx, dy) in [(-1, -1), (0, -1), (-1, 0), (0, 0)] {
+                    let i = ((y + dy) as usize * WIDTH as usize + (x + dx) as usize) * 3;
+                    rgb[i..i + 3].copy_from_slice(&[235, 235, 235]);
+                }
+            }
+        }
+        let target = TargetSpec {
+            kind: TargetKind::Checker,
+            spacing_mm: 4.0,
+        };
+        let calibration = detect_target(&rgb, WIDTH, HEIGHT, target).unwrap();
+        assert!((calibration.pixels_per_mm - 5.0).abs() < 0.2);
+    }
+
+    #[test]
+    fn test_rejects_frames_without_target() {
+        assert!(matches!(
+            detect_target(&white(), WIDTH, HEIGHT, dots(1.0)),
+            Err(CalibrationError::TargetNotFound(_))
+        ));
+
+        let mut rgb = white();
+        draw_dot(&mut rgb, (100.0, 100.0), 5.0);
+        draw_dot(&mut rgb, (140.0, 100.0), 5.0);
+        assert!(matches!(
+            detect_target(&rgb, WIDTH, HEIGHT, dots(1.0)),
+            Err(CalibrationError::TargetNotFound(_))
+        ));
+
+        assert!(matches!(
+            detect_target(&white(), WIDTH, HEIGHT, dots(0.0)),
+            Err(CalibrationError::Invalid(_))
+        ));
+        assert!(matches!(
+            detect_target(&[0; 10], WIDTH, HEIGHT, dots(1.0)),
+            Err(CalibrationError::FrameSize { .. })
+        ));
+    }
+
+    #[test]
+    fn test_manual_reference() {
+        let calibration =
+            Calibration::from_reference(WIDTH, HEIGHT, (10.0, 10.0), (40.0, 50.0), 5.0).unwrap();
+        assert!((calibration.pixels_per_mm - 10.0).abs() < 1e-4);
+        assert!((calibration.distance_mm((0.0, 0.0), (0.0, 100.0)) - 10.0).abs() < 1e-3);
+        assert!(Calibration::from_reference(WIDTH, HEIGHT, (1.0, 1.0), (1.0, 1.0), 5.0).is_err());
+        assert!(Calibration::from_reference(WIDTH, HEIGHT, (1.0, 1.0), (9.0, 1.0), -1.0).is_err());
+    }
+
+    #[test]
+    fn test_calibration_only_applies_at_its_zoom() {
+        let zoomed = Zoom::new(2.0, 0.25, 0.5).unwrap();
+        let calibration = Calibration::from_reference(WIDTH, HEIGHT, (0.0, 0.0), (50.0, 0.0), 5.0)
+            .unwrap()
+            .with_zoom(zoomed);
+        assert!(calibration.check_zoom(&zoomed).is_ok());
+        assert!(matches!(
+            calibration.check_zoom(&Zoom::default()),
+            Err(CalibrationError::ZoomMismatch(zoom)) if zoom == zoomed
+        ));
+        // Same factor, other crop
+        assert!(calibration
+            .check_zoom(&Zoom::new(2.0, 0.75, 0.5).unwrap())
+            .is_err());
+
+        // Without zoom the centre doesn't matter
+        let unzoomed =
+            Calibration::from_reference(WIDTH, HEIGHT, (0.0, 0.0), (50.0, 0.0), 5.0).unwrap();
+        assert!(unzoomed
+            .check_zoom(&Zoom::new(1.0, 0.1, 0.9).unwrap())
+            .is_ok());
+    }
+}
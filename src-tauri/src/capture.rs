@@ -7,8 +7,37 @@
 //! # File Format
 //!
 //! Packets are stored in a binary format:
-//! - `packets.bin`: Sequence of `[u32 LE: length][bytes: data]...`
+//! - `packets.bin`: [`PACKETS_MAGIC`] and a `u16 LE` version
+//!   ([`PACKETS_VERSION`], see [`write_packets`]), then
+//!   `[u64 LE: timestamp_us][u8: endpoint][u32 LE: length][bytes: data]...`.
+//!   Version 1 files have no header and store only `[u32 LE: length][data]`;
+//!   [`read_packets_file`] reads both.
 //! - `metadata.json`: Device and capture information
+//! - `transfers.bin` (optional): One [`IsoPacketRecord`] per isochronous packet,
+//!   including packets that errored or carried no data
+//!
+//! [`export_pcapng`] converts either layout to pcapng with Linux usbmon
+//! headers, so captures can be opened in Wireshark.
+//!
+//! # Malformed-Only Capture
+//!
+//! With [`CaptureFilter::MalformedOnly`] an active capture stages each frame's
+//! packets and keeps them only if the streaming code reports the frame as
+//! malformed through [`CaptureState::end_frame`] (failed validation, or a
+//! partial frame that had to be discarded). A device that streams cleanly
+//! most of the time then produces a capture of just its bad frames. The
+//! filter can be switched while a capture runs.
+//!
+//! # Repro Buffer
+//!
+//! [`CaptureState::enable_repro_buffer`] keeps the last few seconds of packets
+//! in memory while streaming, without a capture running. [`CaptureState::replay_last`]
+//! re-runs them through a fresh [`FrameAssembler`] with verbose logging, so a
+//! glitch that was just seen can be reproduced on-device without writing files.
+//!
+//! Older captures use the legacy `capture_*.bin` layout with per-packet
+//! timestamps (see [`write_capture_files`]). [`convert_legacy_to_packets`] and
+//! [`convert_packets_to_legacy`] migrate between the two layouts.
 //!
 //! # Example
 //!
@@ -24,17 +53,21 @@
 //! capture.record_packet(&packet_data);
 //!
 //! // When done:
-//! let result = capture.stop_capture(Path::new("/output"))?;
+//! let result = capture.stop_capture(&Storage::new("/output"))?;
 //! ```
 
 use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
 use std::io::Write;
 use std::path::Path;
-use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::atomic::{AtomicBool, AtomicU32, AtomicU64, Ordering};
 use std::sync::Mutex;
-use std::time::Instant;
+use std::time::{Duration, Instant};
 use thiserror::Error;
 
+use crate::frame_assembler::{is_jpeg_data, FrameAssembler, ProcessResult};
+use crate::storage::Storage;
+
 /// Errors that can occur during packet capture operations.
 #[derive(Error, Debug)]
 pub enum CaptureError {
@@ -61,6 +94,14 @@ pub enum CaptureError {
     /// Output directory does not exist.
     #[error("output directory does not exist: {0}")]
     DirectoryNotFound(String),
+
+    /// The in-memory repro buffer is not enabled.
+    #[error("repro buffer is not enabled")]
+    ReproBufferDisabled,
+
+    /// A packets file was written by a newer version of the app.
+    #[error("unsupported packets file version {0}")]
+    UnsupportedVersion(u16),
 }
 
 /// Result type alias for capture operations.
@@ -94,6 +135,97 @@ pub struct CaptureMetadata {
     /// Optional description or notes about the capture.
     #[serde(default)]
     pub description: String,
+    /// Whether per-packet isochronous transfer records are captured.
+    #[serde(default)]
+    pub record_transfers: bool,
+    /// Total number of isochronous packet records captured.
+    #[serde(default)]
+    pub total_transfer_records: u64,
+}
+
+/// Magic number at the start of a `packets_*.bin` file.
+pub const PACKETS_MAGIC: [u8; 4] = *b"CSPK";
+
+/// [`CaptureState`] start offset meaning no capture has started yet.
+const NOT_STARTED: u64 = u64::MAX;
+
+/// Current layout version of `packets_*.bin` files.
+///
+/// Version 1 files predate the header and carry no timestamps or endpoints.
+pub const PACKETS_VERSION: u16 = 2;
+
+/// Size of one serialized [`IsoPacketRecord`] in bytes.
+pub const ISO_PACKET_RECORD_SIZE: usize = 30;
+
+/// Marker for [`IsoPacketRecord::captured_index`] when no payload was stored.
+const NO_CAPTURED_INDEX: u64 = u64::MAX;
+
+/// Status and lengths of a single isochronous packet within a transfer.
+///
+/// Unlike the payload capture, these records are kept for every packet
+/// descriptor, including packets that failed or were empty, so corruption can
+/// be traced back to the packets that errored.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct IsoPacketRecord {
+    /// Sequence number of the transfer (URB) the packet belongs to.
+    pub transfer_sequence: u64,
+    /// Index of the packet descriptor within the transfer.
+    pub packet_index: u16,
+    /// libusb transfer status of the packet (`LIBUSB_TRANSFER_*`).
+    pub status: i32,
+    /// Requested packet length in bytes.
+    pub length: u32,
+    /// Number of bytes actually received.
+    pub actual_length: u32,
+    /// Index of the packet's payload in the packet capture, if it was stored.
+    pub captured_index: Option<u64>,
+}
+
+impl IsoPacketRecord {
+    /// Serializes the record as fixed-size little-endian bytes.
+    ///
+    /// Layout: `[u64 transfer_sequence][u16 packet_index][i32 status]
+    /// [u32 length][u32 actual_length][u64 captured_index, MAX if none]`
+    #[must_use]
+    pub fn to_bytes(self) -> [u8; ISO_PACKET_RECORD_SIZE] {
+        let mut out = [0u8; ISO_PACKET_RECORD_SIZE];
+        out[0..8].copy_from_slice(&self.transfer_sequence.to_le_bytes());
+        out[8..10].copy_from_slice(&self.packet_index.to_le_bytes());
+        out[10..14].copy_from_slice(&self.status.to_le_bytes());
+        out[14..18].copy_from_slice(&self.length.to_le_bytes());
+        out[18..22].copy_from_slice(&self.actual_length.to_le_bytes());
+        out[22..30].copy_from_slice(
+            &self
+                .captured_index
+                .unwrap_or(NO_CAPTURED_INDEX)
+                .to_le_bytes(),
+        );
+        out
+    }
+
+    /// Parses a record serialized by [`IsoPacketRecord::to_bytes`].
+    #[must_use]
+    pub fn from_bytes(bytes: &[u8; ISO_PACKET_RECORD_SIZE]) -> Self {
+        let u64_at = |i: usize| {
+            let mut b = [0u8; 8];
+            b.copy_from_slice(&bytes[i..i + 8]);
+            u64::from_le_bytes(b)
+        };
+        let u32_at = |i: usize| {
+            let mut b = [0u8; 4];
+            b.copy_from_slice(&bytes[i..i + 4]);
+            u32::from_le_bytes(b)
+        };
+        let captured = u64_at(22);
+        Self {
+            transfer_sequence: u64_at(0),
+            packet_index: u16::from_le_bytes([bytes[8], bytes[9]]),
+            status: u32_at(10) as i32,
+            length: u32_at(14),
+            actual_length: u32_at(18),
+            captured_index: (captured != NO_CAPTURED_INDEX).then_some(captured),
+        }
+    }
 }
 
 /// Result returned when capture stops successfully.
@@ -103,6 +235,9 @@ pub struct CaptureResult {
     pub packets_path: String,
     /// Path to the saved metadata.json file.
     pub metadata_path: String,
+    /// Path to the saved isochronous packet records, if any were captured.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub transfers_path: Option<String>,
     /// Summary of the capture session.
     pub metadata: CaptureMetadata,
 }
@@ -114,18 +249,120 @@ pub struct CaptureResult {
 pub struct CaptureState {
     /// Whether capture is currently active.
     is_capturing: AtomicBool,
-    /// Captured packet data (each packet is a `Vec<u8>`).
-    packets: Mutex<Vec<Vec<u8>>>,
-    /// When the capture started.
-    start_time: Mutex<Option<Instant>>,
+    /// Captured packets with their timestamps and endpoints.
+    packets: Mutex<Vec<CapturedPacket>>,
+    /// Whether per-packet transfer records are captured.
+    record_transfers: AtomicBool,
+    /// Captured isochronous packet records.
+    transfers: Mutex<Vec<IsoPacketRecord>>,
+    /// Reference point for [`Self::start_offset_us`].
+    epoch: Instant,
+    /// When the capture started, in microseconds after `epoch`, or
+    /// [`NOT_STARTED`]. Atomic so packet timestamps don't take a lock.
+    start_offset_us: AtomicU64,
     /// Metadata about the capture session.
     metadata: Mutex<CaptureMetadata>,
     /// Atomic counter for total packets (fast path for USB callback).
     packet_count: AtomicU64,
     /// Atomic counter for total bytes (fast path for USB callback).
     byte_count: AtomicU64,
+    /// Seconds of packets kept in the repro buffer (0 = disabled).
+    repro_seconds: AtomicU32,
+    /// Most recent packets, kept while the repro buffer is enabled.
+    repro: Mutex<ReproBuffer>,
+    /// Whether a packet tap is installed (fast path for USB callback).
+    tap_active: AtomicBool,
+    /// Extra consumer of raw packets (see [`CaptureState::set_packet_tap`]).
+    tap: Mutex<Option<PacketTap>>,
+    /// Packets the tap could not take because its queue was full.
+    tap_dropped: AtomicU64,
+    /// Whether packets are collected for a frame trace (fast path for USB callback).
+    trace_active: AtomicBool,
+    /// Packets collected for a frame trace (see [`CaptureState::start_packet_trace`]).
+    trace: Mutex<PacketTrace>,
+    /// Whether only the packets of malformed frames are kept (fast path for USB callback).
+    malformed_only: AtomicBool,
+    /// Packets of the frame being assembled, while only malformed frames are kept.
+    staging: Mutex<FrameStaging>,
+    /// Malformed frames whose packets were kept.
+    malformed_frames: AtomicU64,
+}
+
+/// Which packets an active capture keeps.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CaptureFilter {
+    /// Every packet.
+    #[default]
+    All,
+    /// Only the packets of frames reported malformed to [`CaptureState::end_frame`].
+    MalformedOnly,
+}
+
+/// Upper bound on packet bytes staged for one frame.
+///
+/// A frame that grows past it never completed properly; its packets are
+/// dropped and staging starts over.
+pub const MAX_STAGED_BYTES: usize = 16 * 1024 * 1024;
+
+/// A packet held back until its frame is known to be malformed.
+struct StagedPacket {
+    data: Option<CapturedPacket>,
+    record: Option<IsoPacketRecord>,
+}
+
+/// Packets of the frame being assembled.
+#[derive(Default)]
+struct FrameStaging {
+    packets: Vec<StagedPacket>,
+    bytes: usize,
+}
+
+impl FrameStaging {
+    /// Stages a packet, starting over if the frame exceeds [`MAX_STAGED_BYTES`].
+    fn push(&mut self, packet: StagedPacket) {
+        let len = packet.data.as_ref().map_or(0, |p| p.data.len());
+        if self.bytes + len > MAX_STAGED_BYTES {
+            log::debug!(
+                "Dropping {} staged packets of an oversized frame",
+                self.packets.len()
+            );
+            self.clear();
+        }
+        self.bytes += len;
+        self.packets.push(packet);
+    }
+
+    fn clear(&mut self) {
+        self.packets.clear();
+        self.bytes = 0;
+    }
+}
+
+/// Upper bound on packet bytes collected for one frame trace.
+pub const MAX_TRACE_BYTES: usize = 32 * 1024 * 1024;
+
+/// Packets collected for a frame trace.
+#[derive(Default)]
+struct PacketTrace {
+    packets: Vec<Vec<u8>>,
+    bytes: usize,
+}
+
+impl PacketTrace {
+    /// Appends a packet unless that would exceed [`MAX_TRACE_BYTES`].
+    fn push(&mut self, packet: &[u8]) {
+        if self.bytes + packet.len() > MAX_TRACE_BYTES {
+            return;
+        }
+        self.bytes += packet.len();
+        self.packets.push(packet.to_vec());
+    }
 }
 
+/// Extra consumer of raw packets, fed without blocking the USB thread.
+pub type PacketTap = std::sync::mpsc::SyncSender<Vec<u8>>;
+
 impl CaptureState {
     /// Creates a new capture state with no active capture.
     #[must_use]
@@ -133,10 +370,23 @@ impl CaptureState {
         Self {
             is_capturing: AtomicBool::new(false),
             packets: Mutex::new(Vec::new()),
-            start_time: Mutex::new(None),
+            record_transfers: AtomicBool::new(false),
+            transfers: Mutex::new(Vec::new()),
+            epoch: Instant::now(),
+            start_offset_us: AtomicU64::new(NOT_STARTED),
             metadata: Mutex::new(CaptureMetadata::default()),
             packet_count: AtomicU64::new(0),
             byte_count: AtomicU64::new(0),
+            repro_seconds: AtomicU32::new(0),
+            repro: Mutex::new(ReproBuffer::default()),
+            tap_active: AtomicBool::new(false),
+            tap: Mutex::new(None),
+            tap_dropped: AtomicU64::new(0),
+            trace_active: AtomicBool::new(false),
+            trace: Mutex::new(PacketTrace::default()),
+            malformed_only: AtomicBool::new(false),
+            staging: Mutex::new(FrameStaging::default()),
+            malformed_frames: AtomicU64::new(0),
         }
     }
 
@@ -146,6 +396,124 @@ impl CaptureState {
         self.is_capturing.load(Ordering::Acquire)
     }
 
+    /// Returns whether packets should be handed to this state at all.
+    ///
+    /// True while a capture is active, the repro buffer is enabled, a
+    /// packet tap is installed or a frame trace is collecting packets. USB
+    /// callbacks check this before copying packet data.
+    #[must_use]
+    pub fn wants_packets(&self) -> bool {
+        self.is_capturing()
+            || self.repro_seconds.load(Ordering::Acquire) > 0
+            || self.tap_active.load(Ordering::Acquire)
+            || self.trace_active.load(Ordering::Acquire)
+    }
+
+    /// Installs (or with `None`, removes) a consumer that gets a copy of every packet.
+    ///
+    /// Packets are offered with `try_send`, so a slow consumer never stalls
+    /// streaming; packets it can't take are counted instead (see
+    /// [`CaptureState::tap_dropped_packets`]). Removing the tap drops the
+    /// sender, which ends the consumer's receive loop.
+    pub fn set_packet_tap(&self, tap: Option<PacketTap>) {
+        let mut current = crate::lock_or_recover(&self.tap);
+        self.tap_active.store(tap.is_some(), Ordering::Release);
+        if tap.is_some() {
+            self.tap_dropped.store(0, Ordering::Release);
+        }
+        *current = tap;
+    }
+
+    /// Returns how many packets the current (or last) tap had to drop.
+    #[must_use]
+    pub fn tap_dropped_packets(&self) -> u64 {
+        self.tap_dropped.load(Ordering::Relaxed)
+    }
+
+    /// Starts collecting packets for a frame trace, discarding earlier ones.
+    ///
+    /// Collection stops at [`MAX_TRACE_BYTES`] or when the packets are taken
+    /// with [`CaptureState::take_packet_trace`].
+    pub fn start_packet_trace(&self) {
+        let mut trace = crate::lock_or_recover(&self.trace);
+        *trace = PacketTrace::default();
+        self.trace_active.store(true, Ordering::Release);
+    }
+
+    /// Stops collecting packets for a frame trace and returns them.
+    pub fn take_packet_trace(&self) -> Vec<Vec<u8>> {
+        let mut trace = crate::lock_or_recover(&self.trace);
+        self.trace_active.store(false, Ordering::Release);
+        std::mem::take(&mut *trace).packets
+    }
+
+    /// Sets which packets captures keep, now and for later captures.
+    ///
+    /// Switching to [`CaptureFilter::All`] drops the packets staged for the
+    /// frame in progress, since whether it is malformed is not known yet.
+    pub fn set_filter(&self, filter: CaptureFilter) {
+        let malformed_only = filter == CaptureFilter::MalformedOnly;
+        if self.malformed_only.swap(malformed_only, Ordering::AcqRel) == malformed_only {
+            return;
+        }
+        crate::lock_or_recover(&self.staging).clear();
+        log::info!("Capture filter set to {:?}", filter);
+    }
+
+    /// Returns which packets captures keep.
+    #[must_use]
+    pub fn filter(&self) -> CaptureFilter {
+        if self.malformed_only.load(Ordering::Acquire) {
+            CaptureFilter::MalformedOnly
+        } else {
+            CaptureFilter::All
+        }
+    }
+
+    /// Ends the frame whose packets were recorded since the previous call.
+    ///
+    /// Called by the streaming code for every completed or discarded frame.
+    /// With [`CaptureFilter::MalformedOnly`], the frame's staged packets are
+    /// added to the capture if `malformed` and dropped otherwise; with
+    /// [`CaptureFilter::All`] this does nothing.
+    pub fn end_frame(&self, malformed: bool) {
+        if !self.is_capturing() || !self.malformed_only.load(Ordering::Acquire) {
+            return;
+        }
+        let staged = {
+            let mut staging = crate::lock_or_recover(&self.staging);
+            let staged = std::mem::take(&mut staging.packets);
+            staging.bytes = 0;
+            staged
+        };
+        if !malformed || staged.is_empty() {
+            return;
+        }
+        self.malformed_frames.fetch_add(1, Ordering::Relaxed);
+
+        let mut packets = crate::lock_or_recover(&self.packets);
+        let mut transfers = crate::lock_or_recover(&self.transfers);
+        for StagedPacket { data, record } in staged {
+            let index = data.map(|packet| {
+                self.packet_count.fetch_add(1, Ordering::Relaxed);
+                self.byte_count
+                    .fetch_add(packet.data.len() as u64, Ordering::Relaxed);
+                packets.push(packet);
+                packets.len() as u64 - 1
+            });
+            if let Some(mut record) = record {
+                record.captured_index = index;
+                transfers.push(record);
+            }
+        }
+    }
+
+    /// Returns how many malformed frames the current (or last) capture kept.
+    #[must_use]
+    pub fn malformed_frame_count(&self) -> u64 {
+        self.malformed_frames.load(Ordering::Relaxed)
+    }
+
     /// Returns the current packet count (thread-safe, lock-free).
     #[must_use]
     pub fn packet_count(&self) -> u64 {
@@ -158,6 +526,31 @@ impl CaptureState {
         self.byte_count.load(Ordering::Relaxed)
     }
 
+    /// Returns whether per-packet transfer records are being captured.
+    #[must_use]
+    pub fn is_recording_transfers(&self) -> bool {
+        self.is_capturing() && self.record_transfers.load(Ordering::Acquire)
+    }
+
+    /// Returns when the current (or last) capture started.
+    ///
+    /// Used by the recorder so processed frames share the capture's time base.
+    #[must_use]
+    pub fn started_at(&self) -> Option<Instant> {
+        match self.start_offset_us.load(Ordering::Acquire) {
+            NOT_STARTED => None,
+            offset => Some(self.epoch + Duration::from_micros(offset)),
+        }
+    }
+
+    /// Microseconds since the current (or last) capture started, 0 if none has.
+    fn elapsed_us(&self) -> u64 {
+        match self.start_offset_us.load(Ordering::Acquire) {
+            NOT_STARTED => 0,
+            offset => (self.epoch.elapsed().as_micros() as u64).saturating_sub(offset),
+        }
+    }
+
     /// Starts a new capture session.
     ///
     /// # Arguments
@@ -186,19 +579,26 @@ impl CaptureState {
                 .map_err(|e| CaptureError::LockError(e.to_string()))?;
             packets.clear();
         }
+        {
+            let mut transfers = self
+                .transfers
+                .lock()
+                .map_err(|e| CaptureError::LockError(e.to_string()))?;
+            transfers.clear();
+        }
+        self.record_transfers
+            .store(metadata.record_transfers, Ordering::Release);
+
+        crate::lock_or_recover(&self.staging).clear();
 
         // Reset counters
         self.packet_count.store(0, Ordering::Release);
         self.byte_count.store(0, Ordering::Release);
+        self.malformed_frames.store(0, Ordering::Release);
 
         // Set start time
-        {
-            let mut start_time = self
-                .start_time
-                .lock()
-                .map_err(|e| CaptureError::LockError(e.to_string()))?;
-            *start_time = Some(Instant::now());
-        }
+        self.start_offset_us
+            .store(self.epoch.elapsed().as_micros() as u64, Ordering::Release);
 
         // Store metadata
         {
@@ -213,34 +613,113 @@ impl CaptureState {
         Ok(())
     }
 
-    /// Records a packet during capture.
+    /// Records a packet from an unknown endpoint during capture.
+    ///
+    /// Same as [`CaptureState::record_packet_from`] with endpoint 0.
+    pub fn record_packet(&self, packet: &[u8]) {
+        self.record_packet_from(packet, 0);
+    }
+
+    /// Records a packet received from `endpoint` during capture.
     ///
     /// This method is designed to be called from USB callback threads and
     /// is optimized for minimal blocking. If capture is not active, the
-    /// packet is silently ignored.
+    /// packet is silently ignored. The packet is stamped with the time
+    /// since the capture started.
     ///
     /// # Arguments
     ///
     /// * `packet` - Raw packet data to record.
-    pub fn record_packet(&self, packet: &[u8]) {
+    /// * `endpoint` - Endpoint address the packet came from (0 if unknown).
+    pub fn record_packet_from(&self, packet: &[u8], endpoint: u8) {
+        self.observe_packet(packet);
+
         // Fast path: check if capturing without locking
         if !self.is_capturing.load(Ordering::Acquire) {
             return;
         }
+        let packet = self.stamp(packet, endpoint);
+
+        if self.malformed_only.load(Ordering::Acquire) {
+            crate::lock_or_recover(&self.staging).push(StagedPacket {
+                data: Some(packet),
+                record: None,
+            });
+            return;
+        }
 
         // Update atomic counters (lock-free)
         self.packet_count.fetch_add(1, Ordering::Relaxed);
         self.byte_count
-            .fetch_add(packet.len() as u64, Ordering::Relaxed);
+            .fetch_add(packet.data.len() as u64, Ordering::Relaxed);
 
         // Store packet data (requires lock)
-        if let Ok(mut packets) = self.packets.lock() {
-            packets.push(packet.to_vec());
-        } else {
-            log::warn!("Failed to acquire lock for packet recording");
+        crate::lock_or_recover(&self.packets).push(packet);
+    }
+
+    /// Copies a packet with its time since the capture started.
+    fn stamp(&self, data: &[u8], endpoint: u8) -> CapturedPacket {
+        CapturedPacket {
+            timestamp_us: self.elapsed_us(),
+            data: data.to_vec(),
+            endpoint,
+        }
+    }
+
+    /// Records an isochronous packet descriptor, and its payload if given.
+    ///
+    /// Payloads are stored exactly like [`CaptureState::record_packet_from`], and
+    /// the record's `captured_index` is set to the payload's position in the
+    /// packet capture. Records are only kept if transfer recording was enabled
+    /// via [`CaptureMetadata::record_transfers`]; otherwise only the payload is
+    /// stored.
+    pub fn record_iso_packet(
+        &self,
+        mut record: IsoPacketRecord,
+        payload: Option<&[u8]>,
+        endpoint: u8,
+    ) {
+        if let Some(data) = payload {
+            self.observe_packet(data);
+        }
+        if !self.is_capturing.load(Ordering::Acquire) {
+            return;
+        }
+
+        record.captured_index = None;
+        if self.malformed_only.load(Ordering::Acquire) {
+            crate::lock_or_recover(&self.staging).push(StagedPacket {
+                data: payload.map(|data| self.stamp(data, endpoint)),
+                record: self
+                    .record_transfers
+                    .load(Ordering::Acquire)
+                    .then_some(record),
+            });
+            return;
+        }
+        if let Some(data) = payload {
+            self.packet_count.fetch_add(1, Ordering::Relaxed);
+            self.byte_count
+                .fetch_add(data.len() as u64, Ordering::Relaxed);
+            let packet = self.stamp(data, endpoint);
+            let mut packets = crate::lock_or_recover(&self.packets);
+            record.captured_index = Some(packets.len() as u64);
+            packets.push(packet);
+        }
+
+        if self.record_transfers.load(Ordering::Acquire) {
+            crate::lock_or_recover(&self.transfers).push(record);
         }
     }
 
+    /// Takes the captured isochronous packet records, leaving none behind.
+    pub fn take_transfer_records(&self) -> Vec<IsoPacketRecord> {
+        self.transfers
+            .lock()
+            .map(|mut t| std::mem::take(&mut *t))
+            .unwrap_or_default()
+    }
+
     /// Increments the frame counter in metadata.
     ///
     /// Call this when a complete frame has been assembled.
@@ -249,9 +728,7 @@ impl CaptureState {
             return;
         }
 
-        if let Ok(mut meta) = self.metadata.lock() {
-            meta.total_frames += 1;
-        }
+        crate::lock_or_recover(&self.metadata).total_frames += 1;
     }
 
     /// Stops the capture and saves data to disk.
@@ -266,7 +743,7 @@ impl CaptureState {
     /// Returns `CaptureError::DirectoryNotFound` if the output directory doesn't exist.
     /// Returns `CaptureError::Io` if file operations fail.
     /// Returns `CaptureError::Json` if metadata serialization fails.
-    pub fn stop_capture(&self, output_dir: &Path) -> Result<CaptureResult> {
+    pub fn stop_capture(&self, storage: &Storage) -> Result<CaptureResult> {
         // Check if capturing
         if self
             .is_capturing
@@ -275,29 +752,24 @@ impl CaptureState {
         {
             return Err(CaptureError::NotActive);
         }
+        crate::lock_or_recover(&self.staging).clear();
 
         // Verify output directory exists
-        if !output_dir.exists() {
+        if !storage.root().exists() {
             return Err(CaptureError::DirectoryNotFound(
-                output_dir.display().to_string(),
+                storage.root().display().to_string(),
             ));
         }
 
         // Calculate duration
-        let duration_ms = {
-            let start_time = self
-                .start_time
-                .lock()
-                .map_err(|e| CaptureError::LockError(e.to_string()))?;
-            start_time
-                .map(|t| t.elapsed().as_millis() as u64)
-                .unwrap_or(0)
-        };
+        let duration_ms = self.elapsed_us() / 1000;
 
         // Get final counts
         let total_packets = self.packet_count.load(Ordering::Acquire);
         let total_bytes = self.byte_count.load(Ordering::Acquire);
 
+        let transfers = self.take_transfer_records();
+
         // Update metadata with final stats
         let metadata = {
             let mut meta = self
@@ -307,6 +779,7 @@ impl CaptureState {
             meta.duration_ms = duration_ms;
             meta.total_packets = total_packets;
             meta.total_bytes = total_bytes;
+            meta.total_transfer_records = transfers.len() as u64;
             meta.clone()
         };
 
@@ -317,14 +790,23 @@ impl CaptureState {
             .unwrap_or(0);
 
         // Save packets to binary file
-        let packets_filename = format!("packets_{}.bin", timestamp);
-        let packets_path = output_dir.join(&packets_filename);
-        self.save_packets(&packets_path)?;
+        let packets_path = self.save_packets(storage, &format!("packets_{}.bin", timestamp))?;
 
         // Save metadata to JSON file
-        let metadata_filename = format!("metadata_{}.json", timestamp);
-        let metadata_path = output_dir.join(&metadata_filename);
-        self.save_metadata(&metadata_path, &metadata)?;
+        let metadata_path =
+            self.save_metadata(storage, &format!("metadata_{}.json", timestamp), &metadata)?;
+
+        // Save transfer records, if any
+        let transfers_path = if transfers.is_empty() {
+            None
+        } else {
+            let path = write_transfer_records(
+                storage,
+                format!("transfers_{}.bin", timestamp),
+                &transfers,
+            )?;
+            Some(path.display().to_string())
+        };
 
         log::info!(
             "Capture stopped: {} packets, {} bytes, {} ms",
@@ -336,6 +818,7 @@ impl CaptureState {
         Ok(CaptureResult {
             packets_path: packets_path.display().to_string(),
             metadata_path: metadata_path.display().to_string(),
+            transfers_path,
             metadata,
         })
     }
@@ -345,44 +828,45 @@ impl CaptureState {
     /// This is useful for aborting a capture due to errors.
     pub fn cancel_capture(&self) {
         self.is_capturing.store(false, Ordering::Release);
+        crate::lock_or_recover(&self.staging).clear();
         if let Ok(mut packets) = self.packets.lock() {
             packets.clear();
         }
+        if let Ok(mut transfers) = self.transfers.lock() {
+            transfers.clear();
+        }
         log::info!("Capture cancelled");
     }
 
-    /// Saves packets to a binary file.
+    /// Saves packets to a binary file, returning its path.
     ///
-    /// Format: `[u32 LE: packet_length][bytes: packet_data]...`
-    fn save_packets(&self, path: &Path) -> Result<()> {
+    /// Written in the current layout (see [`write_packets`]).
+    fn save_packets(&self, storage: &Storage, name: &str) -> Result<std::path::PathBuf> {
         let packets = self
             .packets
             .lock()
             .map_err(|e| CaptureError::LockError(e.to_string()))?;
 
-        let mut file = std::fs::File::create(path)?;
-
-        for packet in packets.iter() {
-            // Write packet length as u32 little-endian
-            let len = packet.len() as u32;
-            file.write_all(&len.to_le_bytes())?;
-
-            // Write packet data
-            file.write_all(packet)?;
-        }
-
+        let (path, file) = storage.create(name)?;
+        let mut file = std::io::BufWriter::new(file);
+        write_packets(&mut file, &packets)?;
         file.flush()?;
         log::debug!("Saved {} packets to {}", packets.len(), path.display());
 
-        Ok(())
+        Ok(path)
     }
 
-    /// Saves metadata to a JSON file.
-    fn save_metadata(&self, path: &Path, metadata: &CaptureMetadata) -> Result<()> {
+    /// Saves metadata to a JSON file, returning its path.
+    fn save_metadata(
+        &self,
+        storage: &Storage,
+        name: &str,
+        metadata: &CaptureMetadata,
+    ) -> Result<std::path::PathBuf> {
         let json = serde_json::to_string_pretty(metadata)?;
-        std::fs::write(path, json)?;
+        let path = storage.write(name, json)?;
         log::debug!("Saved metadata to {}", path.display());
-        Ok(())
+        Ok(path)
     }
 }
 
@@ -392,6 +876,225 @@ impl Default for CaptureState {
     }
 }
 
+// =============================================================================
+// Repro Buffer
+// =============================================================================
+
+/// Longest window the repro buffer can keep.
+pub const MAX_REPRO_SECONDS: u32 = 30;
+
+/// Memory cap for the repro buffer; the oldest packets are dropped beyond it.
+const MAX_REPRO_BYTES: usize = 64 * 1024 * 1024;
+
+/// Packets kept in the repro buffer, oldest first.
+#[derive(Default)]
+struct ReproBuffer {
+    packets: VecDeque<(Instant, Vec<u8>)>,
+    bytes: usize,
+}
+
+impl ReproBuffer {
+    /// Adds a packet and drops packets older than `window` or beyond the memory cap.
+    fn push(&mut self, now: Instant, packet: &[u8], window: Duration) {
+        self.bytes += packet.len();
+        self.packets.push_back((now, packet.to_vec()));
+
+        while let Some((received, data)) = self.packets.front() {
+            let expired = now.duration_since(*received) > window;
+            if !expired && self.bytes <= MAX_REPRO_BYTES {
+                break;
+            }
+            self.bytes -= data.len();
+            self.packets.pop_front();
+        }
+    }
+
+    fn clear(&mut self) {
+        self.packets.clear();
+        self.bytes = 0;
+    }
+}
+
+/// A frame assembled while replaying the repro buffer.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReproFrame {
+    /// Index of the packet that completed the frame.
+    pub packet_index: usize,
+    /// Timestamp of the completing packet, relative to the first replayed packet (microseconds).
+    pub timestamp_us: u64,
+    /// Assembled frame size in bytes.
+    pub size: usize,
+    /// Whether the frame starts with a JPEG SOI marker.
+    pub is_jpeg: bool,
+}
+
+/// Outcome of [`CaptureState::replay_last`].
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReproReport {
+    /// Number of packets replayed.
+    pub packets: usize,
+    /// Total payload bytes replayed.
+    pub bytes: u64,
+    /// Time span covered by the replayed packets (milliseconds).
+    pub duration_ms: u64,
+    /// Packets the assembler skipped (invalid or out-of-sync headers).
+    pub skipped_packets: usize,
+    /// Frames completed during the replay, in order.
+    pub frames: Vec<ReproFrame>,
+}
+
+impl CaptureState {
+    /// Keeps the last `seconds` of packets in memory (clamped to [`MAX_REPRO_SECONDS`]).
+    ///
+    /// Passing 0 disables the buffer and frees its packets.
+    pub fn enable_repro_buffer(&self, seconds: u32) {
+        let seconds = seconds.min(MAX_REPRO_SECONDS);
+        self.repro_seconds.store(seconds, Ordering::Release);
+        if seconds == 0 {
+            crate::lock_or_recover(&self.repro).clear();
+            log::info!("Repro buffer disabled");
+        } else {
+            log::info!("Repro buffer keeping the last {} s of packets", seconds);
+        }
+    }
+
+    /// Returns how many seconds of packets the repro buffer keeps (0 = disabled).
+    #[must_use]
+    pub fn repro_buffer_seconds(&self) -> u32 {
+        self.repro_seconds.load(Ordering::Acquire)
+    }
+
+    /// Returns the packets received in the last `seconds`, with timestamps
+    /// relative to the first returned packet.
+    #[must_use]
+    pub fn recent_packets(&self, seconds: u32) -> Vec<CapturedPacket> {
+        let repro = crate::lock_or_recover(&self.repro);
+        // None if the window reaches back before the clock's origin: keep everything
+        let cutoff = Instant::now().checked_sub(Duration::from_secs(u64::from(seconds)));
+        let recent = repro
+            .packets
+            .iter()
+            .filter(|(received, _)| cutoff.is_none_or(|cutoff| *received >= cutoff));
+        let mut first = None;
+        recent
+            .map(|(received, data)| {
+                let start = *first.get_or_insert(*received);
+                CapturedPacket {
+                    timestamp_us: received.duration_since(start).as_micros() as u64,
+                    data: data.clone(),
+                    endpoint: 0,
+                }
+            })
+            .collect()
+    }
+
+    /// Re-runs the last `seconds` of buffered packets through `assembler`.
+    ///
+    /// `assembler` should be freshly created for the stream's format. Every
+    /// packet and completed frame is logged, so the log shows exactly how the
+    /// assembler saw the glitch.
+    ///
+    /// # Errors
+    ///
+    /// Returns `CaptureError::ReproBufferDisabled` if the repro buffer is off.
+    pub fn replay_last(&self, seconds: u32, mut assembler: FrameAssembler) -> Result<ReproReport> {
+        if self.repro_buffer_seconds() == 0 {
+            return Err(CaptureError::ReproBufferDisabled);
+        }
+
+        let packets = self.recent_packets(seconds);
+        log::info!(
+            "[repro] Replaying {} packets from the last {} s",
+            packets.len(),
+            seconds
+        );
+
+        let mut report = ReproReport {
+            packets: packets.len(),
+            bytes: packets.iter().map(|p| p.data.len() as u64).sum(),
+            duration_ms: packets.last().map_or(0, |p| p.timestamp_us / 1000),
+            skipped_packets: 0,
+            frames: Vec::new(),
+        };
+
+        for (index, packet) in packets.iter().enumerate() {
+            let header_len = packet.data.first().copied().unwrap_or(0);
+            let header_info = packet.data.get(1).copied().unwrap_or(0);
+            let result = assembler.process_packet(&packet.data);
+            let outcome = match &result {
+                ProcessResult::Accumulating => "accumulating",
+                ProcessResult::Frame(_) => "frame",
+                ProcessResult::Skipped => "skipped",
+            };
+            log::info!(
+                "[repro] #{} t={}us len={} hdr_len={} bmHeaderInfo=0x{:02x} (fid={} eof={} err={}) -> {}",
+                index,
+                packet.timestamp_us,
+                packet.data.len(),
+                header_len,
+                header_info,
+                header_info & 0x01,
+                (header_info >> 1) & 0x01,
+                (header_info >> 6) & 0x01,
+                outcome
+            );
+
+            match result {
+                ProcessResult::Frame(frame) => {
+                    let is_jpeg = is_jpeg_data(&frame);
+                    log::info!(
+                        "[repro] Frame {} completed by packet #{}: {} bytes{}",
+                        report.frames.len(),
+                        index,
+                        frame.len(),
+                        if is_jpeg { " (JPEG)" } else { "" }
+                    );
+                    report.frames.push(ReproFrame {
+                        packet_index: index,
+                        timestamp_us: packet.timestamp_us,
+                        size: frame.len(),
+                        is_jpeg,
+                    });
+                }
+                ProcessResult::Skipped => report.skipped_packets += 1,
+                ProcessResult::Accumulating => {}
+            }
+        }
+
+        log::info!(
+            "[repro] Done: {} packets, {} frames, {} skipped",
+            report.packets,
+            report.frames.len(),
+            report.skipped_packets
+        );
+        Ok(report)
+    }
+
+    /// Hands a packet to the repro buffer and the packet tap, if enabled.
+    fn observe_packet(&self, packet: &[u8]) {
+        let seconds = self.repro_seconds.load(Ordering::Acquire);
+        if seconds > 0 {
+            crate::lock_or_recover(&self.repro).push(
+                Instant::now(),
+                packet,
+                Duration::from_secs(u64::from(seconds)),
+            );
+        }
+
+        if self.tap_active.load(Ordering::Acquire) {
+            if let Some(tap) = crate::lock_or_recover(&self.tap).as_ref() {
+                if tap.try_send(packet.to_vec()).is_err() {
+                    self.tap_dropped.fetch_add(1, Ordering::Relaxed);
+                }
+            }
+        }
+
+        if self.trace_active.load(Ordering::Acquire) {
+            crate::lock_or_recover(&self.trace).push(packet);
+        }
+    }
+}
+
 // =============================================================================
 // Legacy API Compatibility
 // =============================================================================
@@ -410,10 +1113,14 @@ pub struct CaptureStatus {
     pub duration_ms: u64,
     /// Total bytes captured.
     pub total_bytes: u64,
+    /// Which packets are kept.
+    pub filter: CaptureFilter,
+    /// Malformed frames kept while only malformed frames are captured.
+    pub malformed_frames: u64,
 }
 
-/// A single captured packet with timestamp (legacy API).
-#[derive(Debug, Clone)]
+/// A single captured packet with timestamp and endpoint.
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct CapturedPacket {
     /// Timestamp relative to capture start (microseconds).
     pub timestamp_us: u64,
@@ -437,18 +1144,25 @@ impl CaptureState {
             .map_err(|e| e.to_string())
     }
 
+    /// Get the description of the current (or last) capture session.
+    #[must_use]
+    pub fn description(&self) -> String {
+        self.metadata
+            .lock()
+            .map(|m| m.description.clone())
+            .unwrap_or_default()
+    }
+
     /// Stop capturing and return captured packets (legacy API).
     ///
     /// This returns packets directly instead of saving to disk.
     /// Use `stop_capture` for the new API that saves to files.
     pub fn stop(&self) -> Vec<CapturedPacket> {
-        // Set capturing to false
+        // Set capturing to false; a staged frame never finished
         self.is_capturing.store(false, Ordering::Release);
+        crate::lock_or_recover(&self.staging).clear();
 
-        // Get duration for timestamps
-        let start_time = self.start_time.lock().ok().and_then(|g| *g);
-
-        // Extract packets with timestamps
+        // Extract packets with their timestamps and endpoints
         let packets = if let Ok(mut p) = self.packets.lock() {
             std::mem::take(&mut *p)
         } else {
@@ -461,72 +1175,49 @@ impl CaptureState {
             self.byte_count.load(Ordering::Acquire)
         );
 
-        // Convert to CapturedPacket format
-        // Note: Since we don't store timestamps per-packet in the new format,
-        // we estimate based on packet index
-        let duration_us = start_time
-            .map(|t| t.elapsed().as_micros() as u64)
-            .unwrap_or(0);
-        let packet_count = packets.len() as u64;
-
         packets
-            .into_iter()
-            .enumerate()
-            .map(|(i, data)| {
-                let timestamp_us = if packet_count > 1 {
-                    (duration_us * i as u64) / (packet_count - 1).max(1)
-                } else {
-                    0
-                };
-                CapturedPacket {
-                    timestamp_us,
-                    data,
-                    endpoint: 0, // Endpoint info not captured in new format
-                }
-            })
-            .collect()
     }
 
     /// Get current capture status (legacy API).
     #[must_use]
     pub fn status(&self) -> CaptureStatus {
-        let duration_ms = if let Ok(start) = self.start_time.lock() {
-            start.map(|s| s.elapsed().as_millis() as u64).unwrap_or(0)
-        } else {
-            0
-        };
+        let duration_ms = self.elapsed_us() / 1000;
 
         CaptureStatus {
             is_capturing: self.is_capturing.load(Ordering::Acquire),
             packet_count: self.packet_count.load(Ordering::Relaxed),
             duration_ms,
             total_bytes: self.byte_count.load(Ordering::Relaxed),
+            filter: self.filter(),
+            malformed_frames: self.malformed_frame_count(),
         }
     }
 
     /// Add a packet to the capture buffer with endpoint info (legacy API).
     ///
-    /// Called during streaming. Use `record_packet` for the new API.
-    pub fn add_packet(&self, data: &[u8], _endpoint: u8) {
-        // Delegate to new API (endpoint info is not preserved)
-        self.record_packet(data);
+    /// Called during streaming. Use `record_packet_from` for the new API.
+    pub fn add_packet(&self, data: &[u8], endpoint: u8) {
+        self.record_packet_from(data, endpoint);
     }
 }
 
 /// Write captured packets to files (legacy API).
 ///
-/// Creates two files in the specified directory:
+/// Creates these files in the specified directory:
 /// - `capture_<timestamp>.bin` - Raw packet data with headers
-/// - `capture_<timestamp>.json` - Metadata about the capture
+/// - `capture_<timestamp>_transfers.bin` - Transfer records, if there are any
+/// - `capture_<timestamp>.json` - Metadata about the capture, written last
 ///
 /// # Errors
 ///
-/// Returns an error string if file operations fail.
+/// Returns `CaptureError::Io` or `CaptureError::Json` if the files can't be written.
 pub fn write_capture_files(
-    cache_dir: &std::path::Path,
+    storage: &Storage,
     packets: &[CapturedPacket],
+    transfers: &[IsoPacketRecord],
     duration_ms: u64,
-) -> std::result::Result<CaptureResult, String> {
+    description: &str,
+) -> Result<CaptureResult> {
     use std::io::Write as _;
 
     let timestamp = std::time::SystemTime::now()
@@ -539,38 +1230,35 @@ pub fn write_capture_files(
     let total_bytes: u64 = packets.iter().map(|p| p.data.len() as u64).sum();
 
     // Write binary packet file (legacy format with timestamps)
-    let packets_filename = format!("capture_{}.bin", timestamp);
-    let packets_path = cache_dir.join(&packets_filename);
-
-    let mut file = std::fs::File::create(&packets_path)
-        .map_err(|e| format!("Could not create file: {}", e))?;
+    let (packets_path, mut file) = storage.create(format!("capture_{}.bin", timestamp))?;
 
-    // Write packet data with simple header format:
-    // [8 bytes: timestamp_us][4 bytes: length][1 byte: endpoint][data...]
     for packet in packets {
-        file.write_all(&packet.timestamp_us.to_le_bytes())
-            .map_err(|e| format!("Write error: {}", e))?;
-        file.write_all(&(packet.data.len() as u32).to_le_bytes())
-            .map_err(|e| format!("Write error: {}", e))?;
-        file.write_all(&[packet.endpoint])
-            .map_err(|e| format!("Write error: {}", e))?;
-        file.write_all(&packet.data)
-            .map_err(|e| format!("Write error: {}", e))?;
+        write_legacy_packet(&mut file, packet)?;
     }
 
-    // Write metadata JSON
-    let metadata_filename = format!("capture_{}.json", timestamp);
-    let metadata_path = cache_dir.join(&metadata_filename);
+    let transfers_path = if transfers.is_empty() {
+        None
+    } else {
+        let path = write_transfer_records(
+            storage,
+            packets_path.with_file_name(format!("capture_{}_transfers.bin", timestamp)),
+            transfers,
+        )?;
+        Some(path.to_string_lossy().to_string())
+    };
 
+    // Write metadata JSON once everything it describes is on disk
     let metadata = CaptureMetadata {
         total_packets: packet_count,
         total_bytes,
+        total_transfer_records: transfers.len() as u64,
         duration_ms,
+        description: description.to_string(),
         ..Default::default()
     };
 
-    let json = serde_json::to_string_pretty(&metadata).map_err(|e| format!("JSON error: {}", e))?;
-    std::fs::write(&metadata_path, json).map_err(|e| format!("Could not write metadata: {}", e))?;
+    let json = serde_json::to_string_pretty(&metadata)?;
+    let metadata_path = storage.write(format!("capture_{}.json", timestamp), json)?;
 
     log::info!(
         "Capture saved: {} packets, {} bytes to {}",
@@ -582,17 +1270,148 @@ pub fn write_capture_files(
     Ok(CaptureResult {
         packets_path: packets_path.to_string_lossy().to_string(),
         metadata_path: metadata_path.to_string_lossy().to_string(),
+        transfers_path,
         metadata,
     })
 }
 
+/// Writes one packet in the legacy format.
+///
+/// Format: `[u64 LE: timestamp_us][u32 LE: length][u8: endpoint][data bytes]`
+fn write_legacy_packet(out: &mut impl Write, packet: &CapturedPacket) -> std::io::Result<()> {
+    out.write_all(&packet.timestamp_us.to_le_bytes())?;
+    out.write_all(&(packet.data.len() as u32).to_le_bytes())?;
+    out.write_all(&[packet.endpoint])?;
+    out.write_all(&packet.data)
+}
+
+/// Timestamp of packet `index` out of `count` spread evenly over `duration_us`.
+fn spread_timestamp_us(index: u64, count: u64, duration_us: u64) -> u64 {
+    if count > 1 {
+        (duration_us * index) / (count - 1)
+    } else {
+        0
+    }
+}
+
 // =============================================================================
 // File Reading Utilities
 // =============================================================================
 
-/// Reads packets from a binary capture file.
+/// Packets read from a `packets_*.bin` file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PacketsFile {
+    /// Layout version (1 for files without a header).
+    pub version: u16,
+    /// Packets in capture order; version 1 leaves timestamps and endpoints 0.
+    pub packets: Vec<CapturedPacket>,
+}
+
+impl PacketsFile {
+    /// Returns whether the file recorded per-packet timestamps and endpoints.
+    #[must_use]
+    pub fn has_timing(&self) -> bool {
+        self.version >= 2
+    }
+}
+
+/// Writes packets in the current `packets_*.bin` layout.
 ///
-/// # Arguments
+/// Format: [`PACKETS_MAGIC`], `[u16 LE: version][u16: reserved]`, then
+/// `[u64 LE: timestamp_us][u8: endpoint][u32 LE: length][data bytes]...`
+///
+/// # Errors
+///
+/// Returns the I/O error if writing fails.
+pub fn write_packets(out: &mut impl Write, packets: &[CapturedPacket]) -> std::io::Result<()> {
+    out.write_all(&PACKETS_MAGIC)?;
+    out.write_all(&PACKETS_VERSION.to_le_bytes())?;
+    out.write_all(&0u16.to_le_bytes())?;
+    for packet in packets {
+        out.write_all(&packet.timestamp_us.to_le_bytes())?;
+        out.write_all(&[packet.endpoint])?;
+        out.write_all(&(packet.data.len() as u32).to_le_bytes())?;
+        out.write_all(&packet.data)?;
+    }
+    Ok(())
+}
+
+/// Parses a `packets_*.bin` file of either layout version.
+///
+/// # Errors
+///
+/// Returns `CaptureError::Io` if the data ends mid-packet, or
+/// `CaptureError::UnsupportedVersion` for a header of an unknown version.
+pub fn parse_packets(bytes: &[u8]) -> Result<PacketsFile> {
+    let mut packets = Vec::new();
+    let Some(mut rest) = bytes.strip_prefix(PACKETS_MAGIC.as_slice()) else {
+        // Version 1: `[u32 LE: length][data bytes]...`
+        let mut rest = bytes;
+        while !rest.is_empty() {
+            let len = u32::from_le_bytes(take_array(&mut rest)?) as usize;
+            packets.push(CapturedPacket {
+                timestamp_us: 0,
+                data: take(&mut rest, len)?.to_vec(),
+                endpoint: 0,
+            });
+        }
+        return Ok(PacketsFile {
+            version: 1,
+            packets,
+        });
+    };
+
+    let version = u16::from_le_bytes(take_array(&mut rest)?);
+    let _reserved: [u8; 2] = take_array(&mut rest)?;
+    if version != PACKETS_VERSION {
+        return Err(CaptureError::UnsupportedVersion(version));
+    }
+    while !rest.is_empty() {
+        let timestamp_us = u64::from_le_bytes(take_array(&mut rest)?);
+        let [endpoint] = take_array(&mut rest)?;
+        let len = u32::from_le_bytes(take_array(&mut rest)?) as usize;
+        packets.push(CapturedPacket {
+            timestamp_us,
+            data: take(&mut rest, len)?.to_vec(),
+            endpoint,
+        });
+    }
+    Ok(PacketsFile { version, packets })
+}
+
+/// Splits `len` bytes off the front of `rest`.
+fn take<'a>(rest: &mut &'a [u8], len: usize) -> Result<&'a [u8]> {
+    if rest.len() < len {
+        return Err(CaptureError::Io(std::io::Error::new(
+            std::io::ErrorKind::UnexpectedEof,
+            "truncated packet",
+        )));
+    }
+    let (head, tail) = rest.split_at(len);
+    *rest = tail;
+    Ok(head)
+}
+
+/// Splits `N` bytes off the front of `rest`.
+fn take_array<const N: usize>(rest: &mut &[u8]) -> Result<[u8; N]> {
+    let mut array = [0u8; N];
+    array.copy_from_slice(take(rest, N)?);
+    Ok(array)
+}
+
+/// Reads a `packets_*.bin` file of either layout version.
+///
+/// # Errors
+///
+/// Returns `CaptureError::Io` if the file cannot be read or ends mid-packet,
+/// or `CaptureError::UnsupportedVersion` if it was written by a newer version.
+pub fn read_packets_file(path: &Path) -> Result<PacketsFile> {
+    parse_packets(&std::fs::read(path)?)
+}
+
+/// Reads the packet payloads of a `packets_*.bin` file.
+///
+/// # Arguments
 ///
 /// * `path` - Path to the `packets.bin` file.
 ///
@@ -604,30 +1423,137 @@ pub fn write_capture_files(
 ///
 /// Returns `CaptureError::Io` if file operations fail.
 pub fn read_packets(path: &Path) -> Result<Vec<Vec<u8>>> {
-    use std::io::Read;
+    Ok(read_packets_file(path)?
+        .packets
+        .into_iter()
+        .map(|p| p.data)
+        .collect())
+}
 
-    let mut file = std::fs::File::open(path)?;
-    let mut packets = Vec::new();
+/// Reads a `packets_*.bin` file with per-packet timestamps.
+///
+/// Version 1 files have none, so they are synthesized like
+/// [`convert_packets_to_legacy`] does: spread over the companion metadata's
+/// `duration_ms`, or [`SYNTHETIC_PACKET_INTERVAL_US`] apart.
+///
+/// # Errors
+///
+/// Returns `CaptureError::Io` if the file cannot be read, `CaptureError::Json`
+/// if the companion metadata is invalid, or `CaptureError::UnsupportedVersion`.
+pub fn read_timed_packets(path: &Path) -> Result<Vec<CapturedPacket>> {
+    let file = read_packets_file(path)?;
+    if file.has_timing() {
+        return Ok(file.packets);
+    }
+    let duration_ms = match find_companion_metadata(path) {
+        Some(path) => read_metadata(&path)?.duration_ms,
+        None => 0,
+    };
+    let count = file.packets.len() as u64;
+    let duration_us = if duration_ms > 0 {
+        duration_ms * 1000
+    } else {
+        count.saturating_sub(1) * SYNTHETIC_PACKET_INTERVAL_US
+    };
+    Ok(file
+        .packets
+        .into_iter()
+        .enumerate()
+        .map(|(i, packet)| CapturedPacket {
+            timestamp_us: spread_timestamp_us(i as u64, count, duration_us),
+            ..packet
+        })
+        .collect())
+}
 
-    loop {
-        // Read packet length (u32 little-endian)
-        let mut len_bytes = [0u8; 4];
-        match file.read_exact(&mut len_bytes) {
-            Ok(()) => {}
-            Err(e) if e.kind() == std::io::ErrorKind::UnexpectedEof => break,
-            Err(e) => return Err(CaptureError::Io(e)),
-        }
+/// Reads packets from a legacy capture file written by [`write_capture_files`].
+///
+/// Format: `[u64 LE: timestamp_us][u32 LE: length][u8: endpoint][data bytes]...`
+///
+/// # Errors
+///
+/// Returns `CaptureError::Io` if the file cannot be read or ends mid-packet.
+pub fn read_legacy_packets(path: &Path) -> Result<Vec<CapturedPacket>> {
+    let bytes = std::fs::read(path)?;
+    let truncated = || {
+        CaptureError::Io(std::io::Error::new(
+            std::io::ErrorKind::UnexpectedEof,
+            "truncated legacy packet",
+        ))
+    };
 
-        let len = u32::from_le_bytes(len_bytes) as usize;
+    let mut packets = Vec::new();
+    let mut rest = bytes.as_slice();
+    while !rest.is_empty() {
+        if rest.len() < 13 {
+            return Err(truncated());
+        }
+        let mut timestamp = [0u8; 8];
+        timestamp.copy_from_slice(&rest[0..8]);
+        let mut len = [0u8; 4];
+        len.copy_from_slice(&rest[8..12]);
+        let len = u32::from_le_bytes(len) as usize;
+        let endpoint = rest[12];
+        let data = rest.get(13..13 + len).ok_or_else(truncated)?;
+
+        packets.push(CapturedPacket {
+            timestamp_us: u64::from_le_bytes(timestamp),
+            data: data.to_vec(),
+            endpoint,
+        });
+        rest = &rest[13 + len..];
+    }
 
-        // Read packet data
-        let mut packet = vec![0u8; len];
-        file.read_exact(&mut packet)?;
+    Ok(packets)
+}
 
-        packets.push(packet);
+/// Writes isochronous packet records to a binary file in `storage`, returning its path.
+///
+/// Each record is [`ISO_PACKET_RECORD_SIZE`] bytes (see [`IsoPacketRecord::to_bytes`]).
+///
+/// # Errors
+///
+/// Returns `CaptureError::Io` if file operations fail or `path` is outside the storage.
+pub fn write_transfer_records(
+    storage: &Storage,
+    path: impl AsRef<Path>,
+    records: &[IsoPacketRecord],
+) -> Result<std::path::PathBuf> {
+    let (path, file) = storage.create(path)?;
+    let mut file = std::io::BufWriter::new(file);
+    for record in records {
+        file.write_all(&record.to_bytes())?;
     }
+    file.flush()?;
+    log::debug!(
+        "Saved {} transfer records to {}",
+        records.len(),
+        path.display()
+    );
+    Ok(path)
+}
 
-    Ok(packets)
+/// Reads isochronous packet records from a binary file.
+///
+/// # Errors
+///
+/// Returns `CaptureError::Io` if the file cannot be read or ends mid-record.
+pub fn read_transfer_records(path: &Path) -> Result<Vec<IsoPacketRecord>> {
+    let bytes = std::fs::read(path)?;
+    if bytes.len() % ISO_PACKET_RECORD_SIZE != 0 {
+        return Err(CaptureError::Io(std::io::Error::new(
+            std::io::ErrorKind::UnexpectedEof,
+            "truncated transfer record",
+        )));
+    }
+    Ok(bytes
+        .chunks_exact(ISO_PACKET_RECORD_SIZE)
+        .map(|chunk| {
+            let mut record = [0u8; ISO_PACKET_RECORD_SIZE];
+            record.copy_from_slice(chunk);
+            IsoPacketRecord::from_bytes(&record)
+        })
+        .collect())
 }
 
 /// Reads capture metadata from a JSON file.
@@ -646,6 +1572,328 @@ pub fn read_metadata(path: &Path) -> Result<CaptureMetadata> {
     Ok(metadata)
 }
 
+// =============================================================================
+// Format Conversion
+// =============================================================================
+// Captures from before the metadata-aware API (`capture_*.bin`) carry
+// per-packet timestamps and endpoints, as does `packets_*.bin` from version 2;
+// version 1 `packets_*.bin` carries neither. The converters below migrate
+// between the layouts so old captures stay usable.
+
+/// Packet spacing used when a capture records no duration (one USB 2.0 microframe).
+pub const SYNTHETIC_PACKET_INTERVAL_US: u64 = 125;
+
+/// Returns the metadata file written next to a capture file, if any.
+///
+/// Checks `<name>.json` first (legacy `capture_*.bin`), then the
+/// `metadata_*.json` companion of a `packets_*.bin` file.
+pub fn find_companion_metadata(path: &Path) -> Option<std::path::PathBuf> {
+    let json_path = path.with_extension("json");
+    if json_path.exists() {
+        return Some(json_path);
+    }
+    let suffix = path.file_name()?.to_str()?.strip_prefix("packets_")?;
+    let json_path = path
+        .with_file_name(format!("metadata_{}", suffix))
+        .with_extension("json");
+    json_path.exists().then_some(json_path)
+}
+
+/// Returns the transfer records written next to a `packets_*.bin` capture
+/// (`transfers_*.bin`), if any.
+pub fn find_companion_transfers(path: &Path) -> Option<std::path::PathBuf> {
+    let suffix = path.file_name()?.to_str()?.strip_prefix("packets_")?;
+    let transfers_path = path.with_file_name(format!("transfers_{}", suffix));
+    transfers_path.exists().then_some(transfers_path)
+}
+
+/// File name suffix shared by a capture's files (`capture_123.bin` -> `123`).
+fn capture_suffix(path: &Path) -> String {
+    let stem = path
+        .file_stem()
+        .map(|s| s.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    ["capture_", "packets_"]
+        .iter()
+        .find_map(|prefix| stem.strip_prefix(prefix).map(str::to_string))
+        .unwrap_or(stem)
+}
+
+/// Converts a legacy `capture_*.bin` file to `packets_*.bin` and `metadata_*.json` in `storage`.
+///
+/// Packets are kept in order with their timestamps and endpoints; the span
+/// of the timestamps is kept as `duration_ms` when the companion metadata
+/// has none.
+///
+/// # Errors
+///
+/// Returns `CaptureError::Io` if the input cannot be read or the output cannot be written.
+/// Returns `CaptureError::Json` if the companion metadata is invalid.
+pub fn convert_legacy_to_packets(storage: &Storage, legacy_path: &Path) -> Result<CaptureResult> {
+    let packets = read_legacy_packets(legacy_path)?;
+    let mut metadata = match find_companion_metadata(legacy_path) {
+        Some(path) => read_metadata(&path)?,
+        None => CaptureMetadata::default(),
+    };
+
+    metadata.total_packets = packets.len() as u64;
+    metadata.total_bytes = packets.iter().map(|p| p.data.len() as u64).sum();
+    if metadata.duration_ms == 0 {
+        metadata.duration_ms = packets.last().map_or(0, |p| p.timestamp_us / 1000);
+    }
+
+    let suffix = capture_suffix(legacy_path);
+    let (packets_path, file) = storage.create(format!("packets_{}.bin", suffix))?;
+    let mut file = std::io::BufWriter::new(file);
+    write_packets(&mut file, &packets)?;
+    file.flush()?;
+
+    let metadata_path = storage.write(
+        format!("metadata_{}.json", suffix),
+        serde_json::to_string_pretty(&metadata)?,
+    )?;
+
+    log::info!(
+        "Converted {} legacy packets from {} to {}",
+        packets.len(),
+        legacy_path.display(),
+        packets_path.display()
+    );
+
+    Ok(CaptureResult {
+        packets_path: packets_path.display().to_string(),
+        metadata_path: metadata_path.display().to_string(),
+        transfers_path: None,
+        metadata,
+    })
+}
+
+/// Converts a `packets_*.bin` file to a legacy `capture_*.bin` and `capture_*.json` in `storage`.
+///
+/// Timestamps and endpoints are kept. Version 1 files have neither, so
+/// timestamps are synthesized (see [`read_timed_packets`]) and endpoints are
+/// written as 0.
+///
+/// # Errors
+///
+/// Returns `CaptureError::Io` if the input cannot be read or the output cannot be written.
+/// Returns `CaptureError::Json` if the companion metadata is invalid.
+pub fn convert_packets_to_legacy(storage: &Storage, packets_path: &Path) -> Result<CaptureResult> {
+    let packets = read_timed_packets(packets_path)?;
+    let mut metadata = match find_companion_metadata(packets_path) {
+        Some(path) => read_metadata(&path)?,
+        None => CaptureMetadata::default(),
+    };
+
+    let count = packets.len() as u64;
+    metadata.total_packets = count;
+    metadata.total_bytes = packets.iter().map(|p| p.data.len() as u64).sum();
+    if metadata.duration_ms == 0 {
+        metadata.duration_ms = packets.last().map_or(0, |p| p.timestamp_us / 1000);
+    }
+
+    let suffix = capture_suffix(packets_path);
+    let (legacy_path, file) = storage.create(format!("capture_{}.bin", suffix))?;
+    let mut file = std::io::BufWriter::new(file);
+    for packet in &packets {
+        write_legacy_packet(&mut file, packet)?;
+    }
+    file.flush()?;
+
+    let metadata_path = storage.write(
+        format!("capture_{}.json", suffix),
+        serde_json::to_string_pretty(&metadata)?,
+    )?;
+
+    log::info!(
+        "Converted {} packets from {} to {}",
+        count,
+        packets_path.display(),
+        legacy_path.display()
+    );
+
+    Ok(CaptureResult {
+        packets_path: legacy_path.display().to_string(),
+        metadata_path: metadata_path.display().to_string(),
+        transfers_path: None,
+        metadata,
+    })
+}
+
+// =============================================================================
+// pcapng Export
+// =============================================================================
+// Each packet becomes one Enhanced Packet Block on a `LINKTYPE_USB_LINUX_MMAPPED`
+// interface: a 64-byte usbmon header describing a completed isochronous IN URB
+// with a single packet descriptor, followed by the payload. Captures don't
+// record the device address, so packets are attributed to device 1, and to
+// endpoint [`PCAP_DEFAULT_ENDPOINT`] unless the capture names one.
+
+/// pcapng link type for Linux usbmon with the 64-byte memory-mapped header
+pub const LINKTYPE_USB_LINUX_MMAPPED: u16 = 220;
+
+/// Endpoint address used for packets whose capture recorded none (IN 1)
+pub const PCAP_DEFAULT_ENDPOINT: u8 = 0x81;
+
+/// Size of the usbmon header before each packet
+const USBMON_HEADER_SIZE: usize = 64;
+
+/// Size of one usbmon isochronous packet descriptor
+const USBMON_ISO_DESCRIPTOR_SIZE: usize = 16;
+
+/// Result of exporting a capture as pcapng.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PcapExportResult {
+    /// Path to the written `.pcapng` file.
+    pub path: String,
+    /// Number of packets exported.
+    pub packets: u64,
+}
+
+/// Writes packets as a pcapng section with one usbmon interface.
+///
+/// `start_unix_us` is the wall-clock time of the first packet (microseconds
+/// since the Unix epoch); packet timestamps are added to it.
+///
+/// # Errors
+///
+/// Returns the I/O error if writing fails.
+pub fn write_pcapng(
+    out: &mut impl Write,
+    packets: &[CapturedPacket],
+    start_unix_us: u64,
+) -> std::io::Result<()> {
+    // Section Header Block: byte-order magic, version 1.0, unknown section length
+    let mut shb = Vec::with_capacity(16);
+    shb.extend_from_slice(&0x1A2B_3C4Du32.to_le_bytes());
+    shb.extend_from_slice(&1u16.to_le_bytes());
+    shb.extend_from_slice(&0u16.to_le_bytes());
+    shb.extend_from_slice(&(-1i64).to_le_bytes());
+    write_pcapng_block(out, 0x0A0D_0D0A, &shb)?;
+
+    // Interface Description Block: link type, reserved, no snapshot limit,
+    // microsecond timestamps (the default resolution)
+    let mut idb = Vec::with_capacity(8);
+    idb.extend_from_slice(&LINKTYPE_USB_LINUX_MMAPPED.to_le_bytes());
+    idb.extend_from_slice(&0u16.to_le_bytes());
+    idb.extend_from_slice(&0u32.to_le_bytes());
+    write_pcapng_block(out, 1, &idb)?;
+
+    for (id, packet) in packets.iter().enumerate() {
+        let timestamp_us = start_unix_us + packet.timestamp_us;
+        let record = usbmon_record(id as u64, packet, timestamp_us);
+
+        // Enhanced Packet Block: interface 0, timestamp, captured and original length
+        let mut epb = Vec::with_capacity(20 + record.len() + 3);
+        epb.extend_from_slice(&0u32.to_le_bytes());
+        epb.extend_from_slice(&((timestamp_us >> 32) as u32).to_le_bytes());
+        epb.extend_from_slice(&(timestamp_us as u32).to_le_bytes());
+        epb.extend_from_slice(&(record.len() as u32).to_le_bytes());
+        epb.extend_from_slice(&(record.len() as u32).to_le_bytes());
+        epb.extend_from_slice(&record);
+        write_pcapng_block(out, 6, &epb)?;
+    }
+    Ok(())
+}
+
+/// Writes one pcapng block, padding the body to 32 bits.
+fn write_pcapng_block(out: &mut impl Write, block_type: u32, body: &[u8]) -> std::io::Result<()> {
+    let padding = (4 - body.len() % 4) % 4;
+    let total_len = (12 + body.len() + padding) as u32;
+    out.write_all(&block_type.to_le_bytes())?;
+    out.write_all(&total_len.to_le_bytes())?;
+    out.write_all(body)?;
+    out.write_all(&[0u8; 3][..padding])?;
+    out.write_all(&total_len.to_le_bytes())
+}
+
+/// Builds the usbmon record (header, one iso descriptor, payload) of a packet.
+fn usbmon_record(id: u64, packet: &CapturedPacket, timestamp_us: u64) -> Vec<u8> {
+    let len = packet.data.len() as u32;
+    let endpoint = if packet.endpoint == 0 {
+        PCAP_DEFAULT_ENDPOINT
+    } else {
+        packet.endpoint | 0x80
+    };
+
+    let mut record =
+        Vec::with_capacity(USBMON_HEADER_SIZE + USBMON_ISO_DESCRIPTOR_SIZE + packet.data.len());
+    record.extend_from_slice(&id.to_le_bytes());
+    record.push(b'C'); // completion event
+    record.push(0); // isochronous transfer
+    record.push(endpoint);
+    record.push(1); // device address
+    record.extend_from_slice(&1u16.to_le_bytes()); // bus
+    record.push(b'-'); // no setup packet
+    record.push(0); // data present
+    record.extend_from_slice(&((timestamp_us / 1_000_000) as i64).to_le_bytes());
+    record.extend_from_slice(&((timestamp_us % 1_000_000) as i32).to_le_bytes());
+    record.extend_from_slice(&0i32.to_le_bytes()); // status
+    record.extend_from_slice(&len.to_le_bytes()); // URB length
+    record.extend_from_slice(&len.to_le_bytes()); // captured data length
+    record.extend_from_slice(&0i32.to_le_bytes()); // iso error count
+    record.extend_from_slice(&1i32.to_le_bytes()); // iso packet count
+    record.extend_from_slice(&1i32.to_le_bytes()); // interval
+    record.extend_from_slice(&0i32.to_le_bytes()); // start frame
+    record.extend_from_slice(&0u32.to_le_bytes()); // transfer flags
+    record.extend_from_slice(&1u32.to_le_bytes()); // descriptors in the data
+    debug_assert_eq!(record.len(), USBMON_HEADER_SIZE);
+
+    record.extend_from_slice(&0i32.to_le_bytes()); // packet status
+    record.extend_from_slice(&0u32.to_le_bytes()); // offset
+    record.extend_from_slice(&len.to_le_bytes());
+    record.extend_from_slice(&0u32.to_le_bytes()); // padding
+    record.extend_from_slice(&packet.data);
+    record
+}
+
+/// Exports a capture file as `<name>.pcapng` next to it in `storage`.
+///
+/// Accepts a legacy `capture_*.bin` or a `packets_*.bin` (see
+/// [`read_timed_packets`] for version 1 files, whose timestamps are
+/// synthesized). The capture's end is taken to be the file's modification
+/// time.
+///
+/// # Errors
+///
+/// Returns `CaptureError::Io` if the input cannot be read or the output cannot be written.
+/// Returns `CaptureError::Json` if the companion metadata is invalid.
+pub fn export_pcapng(storage: &Storage, capture_path: &Path) -> Result<PcapExportResult> {
+    let is_legacy = capture_path
+        .file_name()
+        .and_then(|name| name.to_str())
+        .is_some_and(|name| name.starts_with("capture_"));
+    let packets = if is_legacy {
+        read_legacy_packets(capture_path)?
+    } else {
+        read_timed_packets(capture_path)?
+    };
+
+    let end_unix_us = std::fs::metadata(capture_path)?
+        .modified()?
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_micros() as u64)
+        .unwrap_or(0);
+    let span_us = packets.last().map_or(0, |p| p.timestamp_us);
+
+    let (path, file) = storage.create(capture_path.with_extension("pcapng"))?;
+    let mut file = std::io::BufWriter::new(file);
+    write_pcapng(&mut file, &packets, end_unix_us.saturating_sub(span_us))?;
+    file.flush()?;
+
+    log::info!(
+        "Exported {} packets from {} to {}",
+        packets.len(),
+        capture_path.display(),
+        path.display()
+    );
+
+    Ok(PcapExportResult {
+        path: path.display().to_string(),
+        packets: packets.len() as u64,
+    })
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -713,6 +1961,26 @@ mod tests {
         assert_eq!(state.byte_count(), 0);
     }
 
+    #[test]
+    fn test_record_packet_survives_poisoned_lock() {
+        let state = Arc::new(CaptureState::new());
+        state.start_capture(CaptureMetadata::default()).unwrap();
+
+        let poisoner = Arc::clone(&state);
+        let _ = thread::spawn(move || {
+            let _guard = poisoner.packets.lock().unwrap();
+            panic!("consumer panicked while holding the packet buffer");
+        })
+        .join();
+        assert!(state.packets.is_poisoned());
+
+        state.record_packet(&[0x00, 0x01, 0x02]);
+        state.record_frame();
+
+        assert_eq!(state.packets.lock().unwrap().len(), 1);
+        assert_eq!(state.metadata.lock().unwrap().total_frames, 1);
+    }
+
     #[test]
     fn test_cancel_capture() {
         let state = CaptureState::new();
@@ -730,7 +1998,7 @@ mod tests {
     #[test]
     fn test_stop_capture_not_active() {
         let state = CaptureState::new();
-        let result = state.stop_capture(Path::new("/tmp"));
+        let result = state.stop_capture(&Storage::new("/tmp"));
 
         assert!(matches!(result, Err(CaptureError::NotActive)));
     }
@@ -810,6 +2078,7 @@ mod tests {
             duration_ms: 1000,
             total_bytes: 50000,
             description: "Test capture".to_string(),
+            ..Default::default()
         };
 
         // Write metadata
@@ -836,7 +2105,7 @@ mod tests {
 
     #[test]
     fn test_full_capture_workflow() {
-        let temp_dir = std::env::temp_dir();
+        let temp_dir = tempfile::tempdir().unwrap();
         let state = CaptureState::new();
 
         let metadata = CaptureMetadata {
@@ -862,7 +2131,7 @@ mod tests {
         state.record_frame();
 
         // Stop capture
-        let result = state.stop_capture(&temp_dir).unwrap();
+        let result = state.stop_capture(&Storage::new(temp_dir.path())).unwrap();
 
         // Verify result
         assert_eq!(result.metadata.vendor_id, 0xABCD);
@@ -886,4 +2155,653 @@ mod tests {
         std::fs::remove_file(&result.packets_path).ok();
         std::fs::remove_file(&result.metadata_path).ok();
     }
+
+    #[test]
+    fn test_legacy_capture_with_description() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let state = CaptureState::new();
+
+        state
+            .start_capture(CaptureMetadata {
+                description: "JobX".to_string(),
+                ..Default::default()
+            })
+            .unwrap();
+        state.add_packet(&[1, 2, 3], 0x81);
+        let status = state.status();
+        let packets = state.stop();
+        assert_eq!(state.description(), "JobX");
+
+        let result = write_capture_files(
+            &Storage::new(temp_dir.path()),
+            &packets,
+            &[],
+            status.duration_ms,
+            &state.description(),
+        )
+        .unwrap();
+
+        let read_meta = read_metadata(Path::new(&result.metadata_path)).unwrap();
+        assert_eq!(read_meta.description, "JobX");
+        assert_eq!(read_meta.total_packets, 1);
+        assert!(result.transfers_path.is_none());
+    }
+
+    #[test]
+    fn test_legacy_capture_with_transfers() {
+        // A ".bin" elsewhere in the path must not affect the transfers file name
+        let temp_dir = tempfile::tempdir().unwrap();
+        let storage = Storage::new(temp_dir.path().join("out.bin"));
+        let transfers = [iso_record(0, 0, 0, 12), iso_record(0, 1, -1, 0)];
+
+        let packets = [CapturedPacket {
+            timestamp_us: 0,
+            data: vec![1, 2, 3],
+            endpoint: 0x81,
+        }];
+
+        let result = write_capture_files(&storage, &packets, &transfers, 0, "").unwrap();
+
+        let packets_path = Path::new(&result.packets_path);
+        let transfers_path = Path::new(result.transfers_path.as_deref().unwrap());
+        assert_eq!(transfers_path.parent(), packets_path.parent());
+        let stem = packets_path.file_stem().unwrap().to_string_lossy();
+        assert_eq!(
+            transfers_path.file_name().unwrap().to_string_lossy(),
+            format!("{}_transfers.bin", stem)
+        );
+        assert_eq!(read_transfer_records(transfers_path).unwrap(), transfers);
+
+        let read_meta = read_metadata(Path::new(&result.metadata_path)).unwrap();
+        assert_eq!(read_meta.total_transfer_records, 2);
+        assert_eq!(result.metadata.total_transfer_records, 2);
+    }
+
+    fn iso_record(sequence: u64, index: u16, status: i32, actual_length: u32) -> IsoPacketRecord {
+        IsoPacketRecord {
+            transfer_sequence: sequence,
+            packet_index: index,
+            status,
+            length: 1024,
+            actual_length,
+            captured_index: None,
+        }
+    }
+
+    #[test]
+    fn test_iso_packet_record_bytes_round_trip() {
+        let mut record = iso_record(7, 31, -1, 0);
+        assert_eq!(IsoPacketRecord::from_bytes(&record.to_bytes()), record);
+
+        record.captured_index = Some(12);
+        assert_eq!(IsoPacketRecord::from_bytes(&record.to_bytes()), record);
+    }
+
+    #[test]
+    fn test_iso_packets_recorded_with_status() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let state = CaptureState::new();
+        state
+            .start_capture(CaptureMetadata {
+                record_transfers: true,
+                ..Default::default()
+            })
+            .unwrap();
+        assert!(state.is_recording_transfers());
+
+        state.record_iso_packet(iso_record(0, 0, 0, 3), Some(&[1, 2, 3]), 0x81);
+        state.record_iso_packet(iso_record(0, 1, 1, 0), None, 0x81);
+        state.record_iso_packet(iso_record(1, 0, 0, 2), Some(&[4, 5]), 0x81);
+
+        let result = state.stop_capture(&Storage::new(temp_dir.path())).unwrap();
+        assert_eq!(result.metadata.total_packets, 2);
+        assert_eq!(result.metadata.total_transfer_records, 3);
+
+        let records = read_transfer_records(Path::new(&result.transfers_path.unwrap())).unwrap();
+        assert_eq!(records.len(), 3);
+        assert_eq!(records[0].captured_index, Some(0));
+        assert_eq!(records[1].status, 1);
+        assert_eq!(records[1].captured_index, None);
+        assert_eq!(records[2].transfer_sequence, 1);
+        assert_eq!(records[2].captured_index, Some(1));
+
+        let packets = read_packets(Path::new(&result.packets_path)).unwrap();
+        assert_eq!(packets, vec![vec![1, 2, 3], vec![4, 5]]);
+    }
+
+    #[test]
+    fn test_iso_packets_without_transfer_recording() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let state = CaptureState::new();
+        state.start_capture(CaptureMetadata::default()).unwrap();
+        assert!(!state.is_recording_transfers());
+
+        state.record_iso_packet(iso_record(0, 0, 0, 1), Some(&[9]), 0x81);
+        state.record_iso_packet(iso_record(0, 1, 1, 0), None, 0x81);
+
+        let result = state.stop_capture(&Storage::new(temp_dir.path())).unwrap();
+        assert_eq!(result.metadata.total_packets, 1);
+        assert!(result.transfers_path.is_none());
+    }
+
+    #[test]
+    fn test_malformed_only_keeps_bad_frames() {
+        let state = CaptureState::new();
+        state.set_filter(CaptureFilter::MalformedOnly);
+        state
+            .start_capture(CaptureMetadata {
+                record_transfers: true,
+                ..Default::default()
+            })
+            .unwrap();
+
+        // A good frame is dropped, a bad one kept with its transfer records
+        state.record_packet(&[1]);
+        state.record_packet(&[2]);
+        state.end_frame(false);
+        state.record_iso_packet(iso_record(3, 0, 0, 1), Some(&[3]), 0x81);
+        state.record_iso_packet(iso_record(3, 1, 1, 0), None, 0x81);
+        state.record_iso_packet(iso_record(3, 2, 0, 1), Some(&[4]), 0x81);
+        state.end_frame(true);
+        // The last frame never ends
+        state.record_packet(&[5]);
+
+        let status = state.status();
+        assert_eq!(status.filter, CaptureFilter::MalformedOnly);
+        assert_eq!((status.packet_count, status.malformed_frames), (2, 1));
+
+        let records = state.take_transfer_records();
+        let indices: Vec<_> = records.iter().map(|r| r.captured_index).collect();
+        assert_eq!(indices, vec![Some(0), None, Some(1)]);
+        let packets: Vec<Vec<u8>> = state.stop().into_iter().map(|p| p.data).collect();
+        assert_eq!(packets, vec![vec![3], vec![4]]);
+    }
+
+    #[test]
+    fn test_filter_can_be_switched_during_capture() {
+        let state = CaptureState::new();
+        state.start_capture(CaptureMetadata::default()).unwrap();
+        state.record_packet(&[1]);
+        // Ending frames does nothing while every packet is kept
+        state.end_frame(false);
+
+        state.set_filter(CaptureFilter::MalformedOnly);
+        state.record_packet(&[2]);
+        state.end_frame(false);
+        state.record_packet(&[3]);
+        // The staged packet of the unfinished frame is dropped
+        state.set_filter(CaptureFilter::All);
+        state.record_packet(&[4]);
+
+        let packets: Vec<Vec<u8>> = state.stop().into_iter().map(|p| p.data).collect();
+        assert_eq!(packets, vec![vec![1], vec![4]]);
+    }
+
+    #[test]
+    fn test_read_truncated_transfer_records() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let path = temp_dir.path().join("transfers.bin");
+        std::fs::write(&path, [0u8; ISO_PACKET_RECORD_SIZE + 1]).unwrap();
+        assert!(read_transfer_records(&path).is_err());
+    }
+
+    fn legacy_packet(timestamp_us: u64, data: &[u8]) -> CapturedPacket {
+        CapturedPacket {
+            timestamp_us,
+            data: data.to_vec(),
+            endpoint: 0x81,
+        }
+    }
+
+    #[test]
+    fn test_read_truncated_legacy_packets() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let path = temp_dir.path().join("capture_1.bin");
+        let mut bytes = Vec::new();
+        write_legacy_packet(&mut bytes, &legacy_packet(0, &[1, 2, 3])).unwrap();
+        bytes.pop();
+        std::fs::write(&path, bytes).unwrap();
+        assert!(read_legacy_packets(&path).is_err());
+    }
+
+    #[test]
+    fn test_convert_legacy_to_packets() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let storage = Storage::new(temp_dir.path());
+        let path = temp_dir.path().join("capture_42.bin");
+        let mut bytes = Vec::new();
+        write_legacy_packet(&mut bytes, &legacy_packet(0, &[1, 2])).unwrap();
+        write_legacy_packet(&mut bytes, &legacy_packet(2_500_000, &[3])).unwrap();
+        std::fs::write(&path, bytes).unwrap();
+
+        let result = convert_legacy_to_packets(&storage, &path).unwrap();
+
+        assert!(result.packets_path.ends_with("packets_42.bin"));
+        assert!(result.metadata_path.ends_with("metadata_42.json"));
+        assert_eq!(
+            read_packets(Path::new(&result.packets_path)).unwrap(),
+            vec![vec![1, 2], vec![3]]
+        );
+        let metadata = read_metadata(Path::new(&result.metadata_path)).unwrap();
+        assert_eq!(metadata.total_packets, 2);
+        assert_eq!(metadata.total_bytes, 3);
+        assert_eq!(metadata.duration_ms, 2500);
+
+        let file = read_packets_file(Path::new(&result.packets_path)).unwrap();
+        assert_eq!(file.packets[1], legacy_packet(2_500_000, &[3]));
+    }
+
+    /// Writes a version 1 `packets_*.bin` file (no header, no timing)
+    fn write_v1_packets(path: &Path, packets: &[&[u8]]) {
+        let mut bytes = Vec::new();
+        for packet in packets {
+            bytes.extend_from_slice(&(packet.len() as u32).to_le_bytes());
+            bytes.extend_from_slice(packet);
+        }
+        std::fs::write(path, bytes).unwrap();
+    }
+
+    #[test]
+    fn test_packets_keep_timestamps_and_endpoints() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let state = CaptureState::new();
+        state.start_capture(CaptureMetadata::default()).unwrap();
+        state.add_packet(&[1, 2], 0x81);
+        thread::sleep(Duration::from_millis(2));
+        state.record_packet_from(&[3], 0x82);
+
+        let result = state.stop_capture(&Storage::new(temp_dir.path())).unwrap();
+        let bytes = std::fs::read(&result.packets_path).unwrap();
+        assert_eq!(&bytes[..4], &PACKETS_MAGIC);
+
+        let file = read_packets_file(Path::new(&result.packets_path)).unwrap();
+        assert_eq!(file.version, PACKETS_VERSION);
+        assert!(file.has_timing());
+        let endpoints: Vec<u8> = file.packets.iter().map(|p| p.endpoint).collect();
+        assert_eq!(endpoints, vec![0x81, 0x82]);
+        assert!(file.packets[1].timestamp_us >= file.packets[0].timestamp_us + 2_000);
+        assert_eq!(file.packets[1].data, vec![3]);
+    }
+
+    #[test]
+    fn test_parse_packets_versions() {
+        let v1 = parse_packets(&[2, 0, 0, 0, 0xAA, 0xBB]).unwrap();
+        assert_eq!(v1.version, 1);
+        assert!(!v1.has_timing());
+        assert_eq!(v1.packets[0].data, vec![0xAA, 0xBB]);
+
+        let mut future = PACKETS_MAGIC.to_vec();
+        future.extend_from_slice(&3u16.to_le_bytes());
+        future.extend_from_slice(&[0, 0]);
+        assert!(matches!(
+            parse_packets(&future),
+            Err(CaptureError::UnsupportedVersion(3))
+        ));
+
+        let mut truncated = Vec::new();
+        write_packets(&mut truncated, &[legacy_packet(5, &[1, 2, 3])]).unwrap();
+        truncated.pop();
+        assert!(parse_packets(&truncated).is_err());
+    }
+
+    #[test]
+    fn test_convert_packets_to_legacy_spreads_over_duration() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let storage = Storage::new(temp_dir.path());
+        let path = temp_dir.path().join("packets_9.bin");
+        write_v1_packets(&path, &[&[0], &[1], &[2]]);
+        let metadata = CaptureMetadata {
+            format_type: "mjpeg".to_string(),
+            duration_ms: 10,
+            ..Default::default()
+        };
+        std::fs::write(
+            temp_dir.path().join("metadata_9.json"),
+            serde_json::to_string(&metadata).unwrap(),
+        )
+        .unwrap();
+
+        let result = convert_packets_to_legacy(&storage, &path).unwrap();
+
+        assert_eq!(result.metadata.format_type, "mjpeg");
+        let packets = read_legacy_packets(Path::new(&result.packets_path)).unwrap();
+        let timestamps: Vec<u64> = packets.iter().map(|p| p.timestamp_us).collect();
+        assert_eq!(timestamps, vec![0, 5_000, 10_000]);
+        assert_eq!(packets[2].data, vec![2]);
+    }
+
+    #[test]
+    fn test_convert_packets_to_legacy_without_metadata() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let storage = Storage::new(temp_dir.path());
+        let path = temp_dir.path().join("packets_7.bin");
+        write_v1_packets(&path, &[&[0xAA], &[0xBB], &[0xCC]]);
+
+        let result = convert_packets_to_legacy(&storage, &path).unwrap();
+
+        assert!(result.packets_path.ends_with("capture_7.bin"));
+        let packets = read_legacy_packets(Path::new(&result.packets_path)).unwrap();
+        let timestamps: Vec<u64> = packets.iter().map(|p| p.timestamp_us).collect();
+        assert_eq!(
+            timestamps,
+            vec![
+                0,
+                SYNTHETIC_PACKET_INTERVAL_US,
+                2 * SYNTHETIC_PACKET_INTERVAL_US
+            ]
+        );
+    }
+
+    #[test]
+    fn test_repro_buffer_disabled_by_default() {
+        let capture = CaptureState::new();
+        assert!(!capture.wants_packets());
+
+        capture.record_packet(&[1, 2, 3]);
+        assert!(capture.recent_packets(5).is_empty());
+        assert!(matches!(
+            capture.replay_last(5, FrameAssembler::new_mjpeg()),
+            Err(CaptureError::ReproBufferDisabled)
+        ));
+    }
+
+    #[test]
+    fn test_repro_buffer_keeps_packets_without_capture() {
+        let capture = CaptureState::new();
+        capture.enable_repro_buffer(5);
+        assert!(capture.wants_packets());
+        assert!(!capture.is_capturing());
+
+        capture.record_packet(&[1, 2, 3]);
+        capture.record_iso_packet(iso_record(0, 0, 0, 2), Some(&[4, 5]), 0x81);
+        capture.record_iso_packet(iso_record(0, 1, 1, 0), None, 0x81);
+
+        let packets = capture.recent_packets(5);
+        let data: Vec<&[u8]> = packets.iter().map(|p| p.data.as_slice()).collect();
+        assert_eq!(data, vec![&[1, 2, 3][..], &[4, 5][..]]);
+        assert_eq!(packets[0].timestamp_us, 0);
+        // Nothing is captured for saving
+        assert_eq!(capture.packet_count(), 0);
+
+        capture.enable_repro_buffer(0);
+        assert!(!capture.wants_packets());
+        assert!(capture.recent_packets(5).is_empty());
+    }
+
+    #[test]
+    fn test_packet_tap_receives_copies_without_blocking() {
+        let capture = CaptureState::new();
+        let (tx, rx) = std::sync::mpsc::sync_channel(1);
+        capture.set_packet_tap(Some(tx));
+        assert!(capture.wants_packets());
+
+        capture.record_packet(&[1, 2]);
+        capture.record_packet(&[3, 4]); // queue full: dropped
+        assert_eq!(rx.try_recv().unwrap(), vec![1, 2]);
+        assert_eq!(capture.tap_dropped_packets(), 1);
+
+        capture.set_packet_tap(None);
+        assert!(!capture.wants_packets());
+        assert!(rx.recv().is_err());
+    }
+
+    #[test]
+    fn test_packet_trace_collects_until_taken() {
+        let capture = CaptureState::new();
+        capture.record_packet(&[0; 4]); // before the trace: not collected
+        capture.start_packet_trace();
+        assert!(capture.wants_packets());
+
+        capture.record_packet(&[1, 2]);
+        capture.record_packet(&[3]);
+        assert_eq!(capture.take_packet_trace(), vec![vec![1, 2], vec![3]]);
+        assert!(!capture.wants_packets());
+        assert!(capture.take_packet_trace().is_empty());
+    }
+
+    #[test]
+    fn test_repro_buffer_window_is_clamped() {
+        let capture = CaptureState::new();
+        capture.enable_repro_buffer(MAX_REPRO_SECONDS + 100);
+        assert_eq!(capture.repro_buffer_seconds(), MAX_REPRO_SECONDS);
+    }
+
+    #[test]
+    fn test_repro_buffer_drops_expired_packets() {
+        let mut buffer = ReproBuffer::default();
+        let start = Instant::now();
+        let window = Duration::from_secs(1);
+
+        buffer.push(start, &[1; 10], window);
+        buffer.push(start + Duration::from_millis(500), &[2; 10], window);
+        buffer.push(start + Duration::from_millis(1200), &[3; 10], window);
+
+        assert_eq!(buffer.packets.len(), 2);
+        assert_eq!(buffer.bytes, 20);
+        assert_eq!(buffer.packets[0].1, vec![2; 10]);
+    }
+
+    #[test]
+    fn test_replay_last_reassembles_frames() {
+        use crate::test_utils::{PacketGenerator, Rgb};
+
+        let capture = CaptureState::new();
+        capture.enable_repro_buffer(10);
+        let mut gen = PacketGenerator::new(256);
+        for _ in 0..3 {
+            for packet in gen.yuy2_solid_frame(16, 8, Rgb::GREEN) {
+                capture.record_packet(&packet);
+            }
+        }
+
+        let report = capture
+            .replay_last(10, FrameAssembler::new_yuy2(16, 8))
+            .unwrap();
+
+        assert_eq!(report.packets, capture.recent_packets(10).len());
+        assert!(!report.frames.is_empty());
+        assert!(report
+            .frames
+            .iter()
+            .all(|f| f.size == 16 * 8 * 2 && !f.is_jpeg));
+        // A fresh assembler replays the same packets the same way
+        let again = capture
+            .replay_last(10, FrameAssembler::new_yuy2(16, 8))
+            .unwrap();
+        assert_eq!(again.frames.len(), report.frames.len());
+    }
+
+    #[test]
+    fn test_write_pcapng_blocks() {
+        let packets = vec![
+            legacy_packet(0, &[0x0C, 0x8D, 1, 2, 3]),
+            legacy_packet(125, &[]),
+        ];
+        let mut out = Vec::new();
+        write_pcapng(&mut out, &packets, 1_700_000_000_000_000).unwrap();
+
+        // Walk the blocks by their length fields
+        let mut blocks = Vec::new();
+        let mut rest = out.as_slice();
+        while !rest.is_empty() {
+            let block_type = u32::from_le_bytes(rest[0..4].try_into().unwrap());
+            let len = u32::from_le_bytes(rest[4..8].try_into().unwrap()) as usize;
+            assert_eq!(len % 4, 0);
+            assert_eq!(&rest[len - 4..len], &rest[4..8], "trailing length");
+            blocks.push((block_type, rest[8..len - 4].to_vec()));
+            rest = &rest[len..];
+        }
+        let types: Vec<u32> = blocks.iter().map(|(t, _)| *t).collect();
+        assert_eq!(types, vec![0x0A0D_0D0A, 1, 6, 6]);
+        assert_eq!(
+            u16::from_le_bytes(blocks[1].1[0..2].try_into().unwrap()),
+            LINKTYPE_USB_LINUX_MMAPPED
+        );
+
+        let epb = &blocks[2].1;
+        let captured_len = u32::from_le_bytes(epb[12..16].try_into().unwrap()) as usize;
+        assert_eq!(
+            captured_len,
+            USBMON_HEADER_SIZE + USBMON_ISO_DESCRIPTOR_SIZE + 5
+        );
+        let record = &epb[20..20 + captured_len];
+        assert_eq!(record[8], b'C');
+        assert_eq!(record[10], PCAP_DEFAULT_ENDPOINT);
+        assert_eq!(&record[record.len() - 5..], &[0x0C, 0x8D, 1, 2, 3]);
+
+        // The second packet is 125 us later
+        let timestamp = |epb: &[u8]| {
+            let high = u32::from_le_bytes(epb[4..8].try_into().unwrap()) as u64;
+            let low = u32::from_le_bytes(epb[8..12].try_into().unwrap()) as u64;
+            (high << 32) | low
+        };
+        assert_eq!(timestamp(&blocks[3].1) - timestamp(epb), 125);
+    }
+
+    #[test]
+    fn test_export_pcapng_from_packets_file() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let storage = Storage::new(temp_dir.path());
+        let state = CaptureState::new();
+        state.start_capture(CaptureMetadata::default()).unwrap();
+        for i in 0..3u8 {
+            state.record_packet(&[0x02, 0x80, i]);
+        }
+        let captured = state.stop_capture(&storage).unwrap();
+
+        let result = export_pcapng(&storage, Path::new(&captured.packets_path)).unwrap();
+
+        assert_eq!(result.packets, 3);
+        assert!(result.path.ends_with(".pcapng"));
+        let bytes = std::fs::read(&result.path).unwrap();
+        assert_eq!(&bytes[0..4], &0x0A0D_0D0Au32.to_le_bytes());
+    }
+}
+
+#[cfg(test)]
+mod round_trip_tests {
+    //! Property tests: whatever is written can be read back unchanged.
+
+    use super::*;
+    use crate::replay::PacketReplay;
+    use proptest::prelude::*;
+
+    /// Packet payloads, including empty ones
+    fn payloads() -> impl Strategy<Value = Vec<Vec<u8>>> {
+        prop::collection::vec(prop::collection::vec(any::<u8>(), 0..2048), 0..64)
+    }
+
+    /// Legacy packets with increasing timestamps and arbitrary endpoints
+    fn legacy_packets() -> impl Strategy<Value = Vec<CapturedPacket>> {
+        prop::collection::vec(
+            (
+                0u64..100_000,
+                any::<u8>(),
+                prop::collection::vec(any::<u8>(), 0..2048),
+            ),
+            0..64,
+        )
+        .prop_map(|entries| {
+            let mut timestamp_us = 0;
+            entries
+                .into_iter()
+                .map(|(gap_us, endpoint, data)| {
+                    timestamp_us += gap_us;
+                    CapturedPacket {
+                        timestamp_us,
+                        data,
+                        endpoint,
+                    }
+                })
+                .collect()
+        })
+    }
+
+    fn iso_records() -> impl Strategy<Value = Vec<IsoPacketRecord>> {
+        let record = (
+            any::<u64>(),
+            any::<u16>(),
+            any::<i32>(),
+            any::<u32>(),
+            any::<u32>(),
+            // u64::MAX is the on-disk marker for "not captured"
+            prop::option::of(0..u64::MAX),
+        )
+            .prop_map(
+                |(
+                    transfer_sequence,
+                    packet_index,
+                    status,
+                    length,
+                    actual_length,
+                    captured_index,
+                )| {
+                    IsoPacketRecord {
+                        transfer_sequence,
+                        packet_index,
+                        status,
+                        length,
+                        actual_length,
+                        captured_index,
+                    }
+                },
+            );
+        prop::collection::vec(record, 0..128)
+    }
+
+    proptest! {
+        #![proptest_config(ProptestConfig::with_cases(64))]
+
+        #[test]
+        fn packets_round_trip(packets in payloads()) {
+            let dir = tempfile::tempdir().unwrap();
+            let state = CaptureState::new();
+            state.start_capture(CaptureMetadata::default()).unwrap();
+            for packet in &packets {
+                state.record_packet(packet);
+            }
+            let result = state.stop_capture(&Storage::new(dir.path())).unwrap();
+
+            prop_assert_eq!(result.metadata.total_packets, packets.len() as u64);
+            let read = read_packets(Path::new(&result.packets_path)).unwrap();
+            prop_assert_eq!(read, packets);
+        }
+
+        #[test]
+        fn legacy_packets_round_trip(packets in legacy_packets()) {
+            let dir = tempfile::tempdir().unwrap();
+            let result = write_capture_files(&Storage::new(dir.path()), &packets, &[], 0, "").unwrap();
+
+            let replay = PacketReplay::load(Path::new(&result.packets_path)).unwrap();
+            prop_assert_eq!(replay.packets().len(), packets.len());
+            for (read, written) in replay.packets().iter().zip(&packets) {
+                prop_assert_eq!(read.timestamp_us, written.timestamp_us);
+                prop_assert_eq!(read.endpoint, written.endpoint);
+                prop_assert_eq!(&read.data, &written.data);
+            }
+        }
+
+        #[test]
+        fn legacy_conversion_round_trip(packets in legacy_packets()) {
+            let dir = tempfile::tempdir().unwrap();
+            let storage = Storage::new(dir.path());
+            let legacy = write_capture_files(&storage, &packets, &[], 0, "").unwrap();
+            let converted =
+                convert_legacy_to_packets(&storage, Path::new(&legacy.packets_path)).unwrap();
+            let restored =
+                convert_packets_to_legacy(&storage, Path::new(&converted.packets_path)).unwrap();
+
+            let read = read_legacy_packets(Path::new(&restored.packets_path)).unwrap();
+            prop_assert_eq!(read, packets);
+        }
+
+        #[test]
+        fn transfer_records_round_trip(records in iso_records()) {
+            let dir = tempfile::tempdir().unwrap();
+            let path =
+                write_transfer_records(&Storage::new(dir.path()), "transfers.bin", &records)
+                    .unwrap();
+            prop_assert_eq!(read_transfer_records(&path).unwrap(), records);
+        }
+    }
 }
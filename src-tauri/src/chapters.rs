@@ -0,0 +1,191 @@
+//! Chapter metadata generated from recording markers
+//!
+//! Bookmarks taken during a recording become chapters. MKV videos embed them
+//! (see [`crate::mkv_writer`]); AVI has no chapter support. They are also
+//! written next to the recording in two standard formats:
+//! - `chapters.ffmetadata`: `FFmpeg` metadata, embedded as MP4/MKV chapters with
+//!   `ffmpeg -i video -i chapters.ffmetadata -map_metadata 1 -map_chapters 1 ...`
+//! - `chapters.vtt`: `WebVTT` chapter track, understood by browsers and VLC
+
+use crate::recording::RecordingMarker;
+
+/// A titled time range within a recording
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Chapter {
+    /// Start time (microseconds from recording start)
+    pub start_us: u64,
+    /// End time (microseconds from recording start)
+    pub end_us: u64,
+    /// Chapter title
+    pub title: String,
+}
+
+/// Build chapters from recording markers.
+///
+/// Each marker starts a chapter that runs until the next marker (or the end of
+/// the recording). If the first marker is not at the very start, an initial
+/// "Start" chapter covers the time before it. Returns no chapters if there are
+/// no markers.
+pub fn chapters_from_markers(markers: &[RecordingMarker], duration_us: u64) -> Vec<Chapter> {
+    let mut sorted: Vec<&RecordingMarker> = markers.iter().collect();
+    sorted.sort_by_key(|m| m.timestamp_us);
+
+    let mut chapters = Vec::with_capacity(sorted.len() + 1);
+    if let Some(first) = sorted.first() {
+        if first.timestamp_us > 0 {
+            chapters.push(Chapter {
+                start_us: 0,
+                end_us: first.timestamp_us.min(duration_us),
+                title: "Start".to_string(),
+            });
+        }
+    }
+
+    for (i, marker) in sorted.iter().enumerate() {
+        let start_us = marker.timestamp_us.min(duration_us);
+        let end_us = sorted
+            .get(i + 1)
+            .map_or(duration_us, |next| next.timestamp_us.min(duration_us));
+        let title = marker
+            .note
+            .clone()
+            .unwrap_or_else(|| format!("Bookmark {}", i + 1));
+        chapters.push(Chapter {
+            start_us,
+            end_us: end_us.max(start_us),
+            title,
+        });
+    }
+
+    chapters
+}
+
+/// Render chapters as an `FFmpeg` metadata file (`;FFMETADATA1`).
+pub fn to_ffmetadata(title: &str, chapters: &[Chapter]) -> String {
+    let mut out = String::from(";FFMETADATA1\n");
+    if !title.is_empty() {
+        out.push_str(&format!("title={}\n", escape_ffmetadata(title)));
+    }
+    for chapter in chapters {
+        out.push_str(&format!(
+            "\n[CHAPTER]\nTIMEBASE=1/1000000\nSTART={}\nEND={}\ntitle={}\n",
+            chapter.start_us,
+            chapter.end_us,
+            escape_ffmetadata(&chapter.title)
+        ));
+    }
+    out
+}
+
+/// Render chapters as a `WebVTT` chapter track.
+pub fn to_webvtt(chapters: &[Chapter]) -> String {
+    let mut out = String::from("WEBVTT\n");
+    for (i, chapter) in chapters.iter().enumerate() {
+        out.push_str(&format!(
+            "\n{}\n{} --> {}\n{}\n",
+            i + 1,
+            format_vtt_time(chapter.start_us),
+            format_vtt_time(chapter.end_us),
+            // Cue text ends at the first blank line; keep titles on one line
+            chapter.title.replace(['\r', '\n'], " ")
+        ));
+    }
+    out
+}
+
+/// Escape characters with special meaning in `FFmpeg` metadata values.
+fn escape_ffmetadata(value: &str) -> String {
+    let mut out = String::with_capacity(value.len());
+    for c in value.chars() {
+        if matches!(c, '=' | ';' | '#' | '\\' | '\n') {
+            out.push('\\');
+        }
+        out.push(c);
+    }
+    out
+}
+
+/// Format microseconds as a `WebVTT` timestamp (`HH:MM:SS.mmm`).
+fn format_vtt_time(us: u64) -> String {
+    let ms = us / 1000;
+    format!(
+        "{:02}:{:02}:{:02}.{:03}",
+        ms / 3_600_000,
+        (ms / 60_000) % 60,
+        (ms / 1000) % 60,
+        ms % 1000
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn marker(timestamp_us: u64, note: Option<&str>) -> RecordingMarker {
+        RecordingMarker {
+            frame: 0,
+            timestamp_us,
+            note: note.map(str::to_string),
+        }
+    }
+
+    #[test]
+    fn test_no_markers_no_chapters() {
+        assert!(chapters_from_markers(&[], 1_000_000).is_empty());
+    }
+
+    #[test]
+    fn test_chapters_cover_recording() {
+        let markers = [marker(5_000_000, Some("Joint")), marker(2_000_000, None)];
+        let chapters = chapters_from_markers(&markers, 10_000_000);
+
+        assert_eq!(
+            chapters,
+            vec![
+                Chapter {
+                    start_us: 0,
+                    end_us: 2_000_000,
+                    title: "Start".to_string()
+                },
+                Chapter {
+                    start_us: 2_000_000,
+                    end_us: 5_000_000,
+                    title: "Bookmark 1".to_string()
+                },
+                Chapter {
+                    start_us: 5_000_000,
+                    end_us: 10_000_000,
+                    title: "Joint".to_string()
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_marker_at_zero_has_no_start_chapter() {
+        let chapters = chapters_from_markers(&[marker(0, None)], 1_000);
+        assert_eq!(chapters.len(), 1);
+        assert_eq!(chapters[0].start_us, 0);
+        assert_eq!(chapters[0].end_us, 1_000);
+    }
+
+    #[test]
+    fn test_ffmetadata_output() {
+        let chapters = chapters_from_markers(&[marker(0, Some("a=b; c"))], 2_000);
+        let text = to_ffmetadata("Job#1", &chapters);
+        assert_eq!(
+            text,
+            ";FFMETADATA1\ntitle=Job\\#1\n\n[CHAPTER]\nTIMEBASE=1/1000000\nSTART=0\nEND=2000\ntitle=a\\=b\\; c\n"
+        );
+    }
+
+    #[test]
+    fn test_webvtt_output() {
+        let chapters = chapters_from_markers(&[marker(61_500_000, Some("x\ny"))], 3_723_004_000);
+        let text = to_webvtt(&chapters);
+        assert_eq!(
+            text,
+            "WEBVTT\n\n1\n00:00:00.000 --> 00:01:01.500\nStart\n\n2\n00:01:01.500 --> 01:02:03.004\nx y\n"
+        );
+    }
+}
@@ -0,0 +1,322 @@
+//! Animated clip export from recordings
+//!
+//! Exports the last N seconds of a finished recording as an animated WebP,
+//! which is far smaller than the recording itself and plays inline in
+//! messaging apps. Frames are read from `frames.bin` and encoded one at a
+//! time, so memory use does not grow with the clip length.
+//!
+//! Clips are thinned to [`ClipOptions::max_fps`] to keep them small: a frame
+//! is kept once at least `1 / max_fps` seconds have passed since the last
+//! kept frame, and is shown until the next kept frame.
+
+use serde::{Deserialize, Serialize};
+use std::fs::File;
+use std::io::{BufReader, BufWriter, Read, Seek, SeekFrom};
+use thiserror::Error;
+
+use crate::image_encoder::EncodeError;
+use crate::recording::{self, FrameIndexEntry, RecordingError, RecordingIndex};
+use crate::storage::Storage;
+use crate::webp_anim::AnimatedWebPWriter;
+
+/// Default clip length in seconds
+pub const DEFAULT_CLIP_SECONDS: f64 = 10.0;
+
+/// Default frame rate cap for clips
+pub const DEFAULT_CLIP_MAX_FPS: f64 = 10.0;
+
+/// Errors that can occur while exporting a clip
+#[derive(Error, Debug)]
+pub enum ClipError {
+    /// The recording has no frames in the requested range
+    #[error("Recording has no frames to export")]
+    NoFrames,
+
+    /// Clip length or frame rate is not a positive number
+    #[error("Invalid clip options: {0}")]
+    InvalidOptions(String),
+
+    /// The recording index could not be read
+    #[error("Recording error: {0}")]
+    Recording(#[from] RecordingError),
+
+    /// A frame could not be encoded
+    #[error("Encode error: {0}")]
+    Encode(#[from] EncodeError),
+
+    /// I/O error reading frames or writing the clip
+    #[error("I/O error: {0}")]
+    Io(#[from] std::io::Error),
+}
+
+/// Result type for clip operations
+pub type Result<T> = std::result::Result<T, ClipError>;
+
+/// Options for exporting a clip
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct ClipOptions {
+    /// Clip length, counted back from the last recorded frame (seconds)
+    pub seconds: f64,
+    /// Highest frame rate to keep (frames per second)
+    pub max_fps: f64,
+}
+
+impl Default for ClipOptions {
+    fn default() -> Self {
+        Self {
+            seconds: DEFAULT_CLIP_SECONDS,
+            max_fps: DEFAULT_CLIP_MAX_FPS,
+        }
+    }
+}
+
+/// A frame kept for the clip
+#[derive(Debug, Clone, PartialEq)]
+pub struct ClipFrame<'a> {
+    /// Frame in the recording index
+    pub entry: &'a FrameIndexEntry,
+    /// How long the frame is shown (milliseconds)
+    pub duration_ms: u32,
+}
+
+/// Exported clip information returned to the frontend
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ClipResult {
+    /// Path to the animated WebP
+    pub path: String,
+    /// Frames in the clip
+    pub frame_count: u32,
+    /// Clip duration in milliseconds
+    pub duration_ms: u64,
+    /// File size in bytes
+    pub size: u64,
+}
+
+/// Select the frames of the last `options.seconds` of a recording
+///
+/// # Errors
+///
+/// Returns `ClipError::InvalidOptions` if the length or frame rate is not
+/// positive, or `ClipError::NoFrames` if the recording is empty.
+pub fn select_frames<'a>(
+    index: &'a RecordingIndex,
+    options: &ClipOptions,
+) -> Result<Vec<ClipFrame<'a>>> {
+    if !(options.seconds > 0.0 && options.max_fps > 0.0) {
+        return Err(ClipError::InvalidOptions(format!(
+            "seconds {} and max_fps {} must be positive",
+            options.seconds, options.max_fps
+        )));
+    }
+    let last = index.frames.last().ok_or(ClipError::NoFrames)?;
+
+    let clip_us = (options.seconds * 1_000_000.0) as u64;
+    let interval_us = (1_000_000.0 / options.max_fps) as u64;
+    let start_us = last.timestamp_us.saturating_sub(clip_us);
+
+    let mut kept: Vec<&FrameIndexEntry> = Vec::new();
+    for entry in index.frames.iter().filter(|f| f.timestamp_us >= start_us) {
+        let due = kept
+            .last()
+            .is_none_or(|prev| entry.timestamp_us >= prev.timestamp_us + interval_us);
+        if due {
+            kept.push(entry);
+        }
+    }
+
+    // Each frame lasts until the next one; the last one for one interval
+    let frames = kept
+        .iter()
+        .enumerate()
+        .map(|(i, entry)| {
+            let duration_us = kept
+                .get(i + 1)
+                .map_or(interval_us, |next| next.timestamp_us - entry.timestamp_us);
+            ClipFrame {
+                entry,
+                duration_ms: (duration_us / 1000).max(1) as u32,
+            }
+        })
+        .collect();
+    Ok(frames)
+}
+
+/// Export the last `options.seconds` of a recording as `clip_<timestamp>.webp`
+///
+/// `recording` is the recording's directory storage (see
+/// [`Storage::subdir`]); the clip is written next to `frames.bin`.
+///
+/// # Errors
+///
+/// Returns `ClipError::Recording` if the index cannot be read,
+/// `ClipError::Encode` if a frame cannot be encoded (including
+/// `EncodeError::Unsupported` without the `webp` feature), or the errors of
+/// [`select_frames`].
+pub fn export_webp(recording: &Storage, options: &ClipOptions) -> Result<ClipResult> {
+    let index = recording::read_index(&recording.resolve("index.json")?)?;
+    let frames = select_frames(&index, options)?;
+    let mut source = BufReader::new(File::open(recording.resolve("frames.bin")?)?);
+
+    let timestamp = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0);
+    let (path, file) = recording.create(format!("clip_{}.webp", timestamp))?;
+
+    let result = write_frames(&mut source, BufWriter::new(file), &frames);
+    let (frame_count, size) = match result {
+        Ok(written) => written,
+        Err(e) => {
+            let _ = recording.remove_file(&path);
+            return Err(e);
+        }
+    };
+
+    let duration_ms = frames.iter().map(|f| u64::from(f.duration_ms)).sum();
+    log::info!(
+        "Exported {} frame clip ({} ms, {} bytes) to {}",
+        frame_count,
+        duration_ms,
+        size,
+        path.display()
+    );
+
+    Ok(ClipResult {
+        path: path.display().to_string(),
+        frame_count,
+        duration_ms,
+        size,
+    })
+}
+
+/// Read, encode and append each frame, returning the frame count and file size
+fn write_frames(
+    source: &mut BufReader<File>,
+    out: BufWriter<File>,
+    frames: &[ClipFrame<'_>],
+) -> Result<(u32, u64)> {
+    let mut writer = AnimatedWebPWriter::new(out);
+    let mut data = Vec::new();
+    for frame in frames {
+        let entry = frame.entry;
+        data.resize(entry.size as usize, 0);
+        source.seek(SeekFrom::Start(entry.offset))?;
+        source.read_exact(&mut data)?;
+        writer.push_frame(&data, entry.width, entry.height, frame.duration_ms)?;
+    }
+    let frame_count = writer.frame_count();
+    let file = writer
+        .finish()?
+        .into_inner()
+        .map_err(|e| ClipError::Io(e.into_error()))?;
+    Ok((frame_count, file.metadata()?.len()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::recording::FrameFormat;
+
+    fn index(timestamps_ms: &[u64]) -> RecordingIndex {
+        RecordingIndex {
+            frames: timestamps_ms
+                .iter()
+                .enumerate()
+                .map(|(i, &ms)| FrameIndexEntry {
+                    sequence: i as u64,
+                    timestamp_us: ms * 1000,
+                    offset: 0,
+                    size: 0,
+                    width: 1,
+                    height: 1,
+                    format: FrameFormat::Rgb,
+                    packet_index: None,
+                })
+                .collect(),
+            ..Default::default()
+        }
+    }
+
+    fn selected(frames: &[ClipFrame<'_>]) -> Vec<(u64, u32)> {
+        frames
+            .iter()
+            .map(|f| (f.entry.sequence, f.duration_ms))
+            .collect()
+    }
+
+    #[test]
+    fn test_select_last_seconds() {
+        // 30 fps for 20 seconds
+        let timestamps: Vec<u64> = (0..600).map(|i| i * 1000 / 30).collect();
+        let index = index(&timestamps);
+        let options = ClipOptions {
+            seconds: 5.0,
+            max_fps: 10.0,
+        };
+
+        let frames = select_frames(&index, &options).unwrap();
+        let first = frames.first().unwrap().entry.timestamp_us;
+        assert!(first >= 14_900_000, "clip starts at {} us", first);
+        assert_eq!(frames.last().unwrap().entry.sequence, 599);
+        assert!((49..=51).contains(&frames.len()), "{} frames", frames.len());
+        assert!(frames.iter().all(|f| (100..=134).contains(&f.duration_ms)));
+    }
+
+    #[test]
+    fn test_select_keeps_irregular_timing() {
+        let index = index(&[0, 40, 80, 500, 520, 2000]);
+        let options = ClipOptions {
+            seconds: 60.0,
+            max_fps: 10.0,
+        };
+        let frames = select_frames(&index, &options).unwrap();
+        assert_eq!(selected(&frames), [(0, 500), (3, 1500), (5, 100)]);
+    }
+
+    #[test]
+    fn test_select_rejects_bad_input() {
+        let options = ClipOptions::default();
+        assert!(matches!(
+            select_frames(&index(&[]), &options),
+            Err(ClipError::NoFrames)
+        ));
+        let zero = ClipOptions {
+            seconds: 0.0,
+            ..options
+        };
+        assert!(matches!(
+            select_frames(&index(&[0]), &zero),
+            Err(ClipError::InvalidOptions(_))
+        ));
+    }
+
+    #[test]
+    fn test_export_without_encoder_leaves_no_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let storage = Storage::new(dir.path());
+        let rec = storage.subdir("recording_1").unwrap();
+        rec.write("frames.bin", [0u8; 3]).unwrap();
+        let mut index = index(&[0]);
+        index.frames[0].size = 3;
+        rec.write("index.json", serde_json::to_string(&index).unwrap())
+            .unwrap();
+
+        match export_webp(&rec, &ClipOptions::default()) {
+            Ok(result) => assert!(std::path::Path::new(&result.path).exists()),
+            Err(ClipError::Encode(EncodeError::Unsupported(_))) => {
+                let clips = std::fs::read_dir(rec.root())
+                    .unwrap()
+                    .filter(|e| {
+                        e.as_ref()
+                            .unwrap()
+                            .file_name()
+                            .to_string_lossy()
+                            .starts_with("clip_")
+                    })
+                    .count();
+                assert_eq!(clips, 0);
+            }
+            Err(e) => panic!("export failed: {}", e),
+        }
+    }
+}
@@ -0,0 +1,297 @@
+//! Deep link handling for external automation
+//!
+//! Parses `cleanscope://` URLs delivered via Android intents (or the
+//! `handle_deep_link` command) into actions that map onto existing commands:
+//! - `cleanscope://snapshot` - save the current frame
+//! - `cleanscope://start-recording?label=JobX` - start a labeled recording
+//! - `cleanscope://stop-recording` - stop the recording and save it
+//!
+//! The Android intent filter is deliberately not `BROWSABLE`: links on web
+//! pages cannot reach the camera, only explicit intents from installed apps.
+
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+/// URL scheme registered for `CleanScope` deep links
+pub const DEEP_LINK_SCHEME: &str = "cleanscope";
+
+/// Maximum accepted length for a recording label
+const MAX_LABEL_LEN: usize = 128;
+
+/// Errors that can occur while parsing a deep link
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum DeepLinkError {
+    /// URL does not use the `cleanscope://` scheme
+    #[error("Unsupported scheme in deep link: {0}")]
+    InvalidScheme(String),
+
+    /// URL names an action we do not know about
+    #[error("Unknown deep link action: {0}")]
+    UnknownAction(String),
+
+    /// A query parameter is malformed or out of range
+    #[error("Invalid deep link parameter '{name}': {message}")]
+    InvalidParameter {
+        /// Parameter name
+        name: String,
+        /// Description of the problem
+        message: String,
+    },
+}
+
+/// An action requested by an external app via deep link
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "action", rename_all = "kebab-case")]
+pub enum DeepLinkAction {
+    /// Save the current frame to the cache directory
+    Snapshot,
+    /// Start a recording (with raw packets), optionally tagged with a label
+    StartRecording {
+        /// Free-form label stored in the recording index
+        label: Option<String>,
+    },
+    /// Stop the active recording and save it
+    StopRecording,
+}
+
+/// Parse a `cleanscope://` URL into a [`DeepLinkAction`].
+///
+/// The action is taken from the URL host (`cleanscope://snapshot`); a path form
+/// (`cleanscope:///snapshot`) is accepted as well. Unknown query parameters are
+/// ignored so that newer automation apps keep working with older builds.
+///
+/// # Errors
+///
+/// Returns `DeepLinkError::InvalidScheme` if the URL is not a `cleanscope://` link,
+/// `DeepLinkError::UnknownAction` for unrecognized actions, and
+/// `DeepLinkError::InvalidParameter` for malformed query parameters.
+pub fn parse_deep_link(url: &str) -> Result<DeepLinkAction, DeepLinkError> {
+    let url = url.trim();
+    let rest = url
+        .split_once("://")
+        .filter(|(scheme, _)| scheme.eq_ignore_ascii_case(DEEP_LINK_SCHEME))
+        .map(|(_, rest)| rest)
+        .ok_or_else(|| DeepLinkError::InvalidScheme(url.to_string()))?;
+
+    // Drop any fragment, then split off the query string
+    let rest = rest.split('#').next().unwrap_or_default();
+    let (target, query) = rest.split_once('?').unwrap_or((rest, ""));
+    let action = target.trim_matches('/').to_ascii_lowercase();
+
+    match action.as_str() {
+        "snapshot" => Ok(DeepLinkAction::Snapshot),
+        "start-recording" => {
+            let label = match query_param(query, "label")? {
+                Some(label) if label.chars().any(char::is_control) => {
+                    return Err(invalid_param("label", "contains control characters"));
+                }
+                Some(label) if label.len() > MAX_LABEL_LEN => {
+                    return Err(invalid_param(
+                        "label",
+                        &format!("longer than {} bytes", MAX_LABEL_LEN),
+                    ));
+                }
+                Some(label) if label.is_empty() => None,
+                other => other,
+            };
+            Ok(DeepLinkAction::StartRecording { label })
+        }
+        "stop-recording" => Ok(DeepLinkAction::StopRecording),
+        _ => Err(DeepLinkError::UnknownAction(action)),
+    }
+}
+
+fn invalid_param(name: &str, message: &str) -> DeepLinkError {
+    DeepLinkError::InvalidParameter {
+        name: name.to_string(),
+        message: message.to_string(),
+    }
+}
+
+/// Look up a query parameter by name, percent-decoding its value.
+fn query_param(query: &str, name: &str) -> Result<Option<String>, DeepLinkError> {
+    for pair in query.split('&').filter(|p| !p.is_empty()) {
+        let (key, value) = pair.split_once('=').unwrap_or((pair, ""));
+        if key == name {
+            return percent_decode(value)
+                .map(Some)
+                .ok_or_else(|| invalid_param(name, "invalid percent-encoding"));
+        }
+    }
+    Ok(None)
+}
+
+/// Decode `%XX` escapes and `+` (space) in a query value.
+///
+/// Returns `None` for truncated escapes or if the result is not valid UTF-8.
+fn percent_decode(value: &str) -> Option<String> {
+    let bytes = value.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'%' => {
+                let hex = value.get(i + 1..i + 3)?;
+                out.push(u8::from_str_radix(hex, 16).ok()?);
+                i += 3;
+            }
+            b'+' => {
+                out.push(b' ');
+                i += 1;
+            }
+            b => {
+                out.push(b);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8(out).ok()
+}
+
+/// Get the deep link URL from the intent that launched the activity, if any.
+///
+/// Returns `None` when the app was started normally or via `USB_DEVICE_ATTACHED`.
+#[cfg(target_os = "android")]
+pub fn get_launch_deep_link() -> Option<String> {
+    use crate::jni_helpers::{self, JniError};
+
+    let url = jni_helpers::with_activity(|env, activity| {
+        let intent = jni_helpers::call_object(
+            env,
+            activity,
+            "getIntent",
+            "()Landroid/content/Intent;",
+            &[],
+        )?;
+        if intent.is_null() {
+            return Ok(None);
+        }
+        jni_helpers::call_string(env, &intent, "getDataString")
+    })
+    .map_err(|e: JniError| log::warn!("Could not read launch intent: {}", e))
+    .ok()??;
+
+    log::info!("Launch intent has deep link: {}", url);
+    Some(url)
+}
+
+/// App handle used by the JNI callback to route deep links delivered while running
+#[cfg(target_os = "android")]
+static APP_HANDLE: std::sync::OnceLock<tauri::AppHandle> = std::sync::OnceLock::new();
+
+/// Register the app handle so `onNewIntent` deep links can be dispatched.
+#[cfg(target_os = "android")]
+pub fn register_app_handle(app: tauri::AppHandle) {
+    let _ = APP_HANDLE.set(app);
+}
+
+/// JNI callback for deep links delivered to an already running activity
+/// (`MainActivity.onNewIntent`).
+#[cfg(target_os = "android")]
+#[no_mangle]
+pub extern "system" fn Java_com_cleanscope_app_MainActivity_onDeepLink(
+    mut env: jni::JNIEnv,
+    _this: jni::objects::JObject,
+    url: jni::objects::JString,
+) {
+    let url: String = match env.get_string(&url) {
+        Ok(url) => url.into(),
+        Err(e) => {
+            log::error!("Failed to read deep link URL from JNI: {}", e);
+            return;
+        }
+    };
+
+    match APP_HANDLE.get() {
+        Some(app) => crate::process_deep_link(app, &url),
+        None => log::warn!("Deep link received before app setup, ignoring: {}", url),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_snapshot() {
+        assert_eq!(
+            parse_deep_link("cleanscope://snapshot"),
+            Ok(DeepLinkAction::Snapshot)
+        );
+    }
+
+    #[test]
+    fn test_parse_path_form_and_case() {
+        assert_eq!(
+            parse_deep_link("CleanScope:///Snapshot/"),
+            Ok(DeepLinkAction::Snapshot)
+        );
+    }
+
+    #[test]
+    fn test_parse_start_recording_with_label() {
+        assert_eq!(
+            parse_deep_link("cleanscope://start-recording?label=Job%20X+42"),
+            Ok(DeepLinkAction::StartRecording {
+                label: Some("Job X 42".to_string())
+            })
+        );
+    }
+
+    #[test]
+    fn test_parse_start_recording_without_label() {
+        assert_eq!(
+            parse_deep_link("cleanscope://start-recording"),
+            Ok(DeepLinkAction::StartRecording { label: None })
+        );
+        assert_eq!(
+            parse_deep_link("cleanscope://start-recording?label=&other=1"),
+            Ok(DeepLinkAction::StartRecording { label: None })
+        );
+    }
+
+    #[test]
+    fn test_parse_stop_recording_ignores_fragment() {
+        assert_eq!(
+            parse_deep_link("cleanscope://stop-recording#ignored"),
+            Ok(DeepLinkAction::StopRecording)
+        );
+    }
+
+    #[test]
+    fn test_rejects_other_schemes() {
+        assert!(matches!(
+            parse_deep_link("https://snapshot"),
+            Err(DeepLinkError::InvalidScheme(_))
+        ));
+        assert!(matches!(
+            parse_deep_link("snapshot"),
+            Err(DeepLinkError::InvalidScheme(_))
+        ));
+    }
+
+    #[test]
+    fn test_rejects_unknown_action() {
+        assert_eq!(
+            parse_deep_link("cleanscope://format-disk"),
+            Err(DeepLinkError::UnknownAction("format-disk".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_rejects_bad_labels() {
+        assert!(matches!(
+            parse_deep_link("cleanscope://start-recording?label=%ZZ"),
+            Err(DeepLinkError::InvalidParameter { .. })
+        ));
+        assert!(matches!(
+            parse_deep_link("cleanscope://start-recording?label=a%0Ab"),
+            Err(DeepLinkError::InvalidParameter { .. })
+        ));
+        let long = format!("cleanscope://start-recording?label={}", "x".repeat(200));
+        assert!(matches!(
+            parse_deep_link(&long),
+            Err(DeepLinkError::InvalidParameter { .. })
+        ));
+    }
+}
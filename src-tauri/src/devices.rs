@@ -0,0 +1,306 @@
+//! Connected UVC cameras and the one to stream from
+//!
+//! The USB backends list the attached cameras into a [`DeviceRegistry`]
+//! whenever they look for a camera to open, and `list_usb_devices` refreshes
+//! it on demand. A camera is identified by its platform device name
+//! ([`CameraDevice::id`]), or by vendor and product ID (`vvvv:pppp`), which
+//! stays the same across replugs while the device name does not.
+//!
+//! `select_device` records the chosen camera; the backends open it in
+//! preference to the others ([`DeviceRegistry::pick`]) and restart the stream
+//! when it is not the open one ([`DeviceRegistry::wants_switch`]). Without a
+//! selection the first camera found is used, as before. A selected camera
+//! that can't be opened is dropped ([`DeviceRegistry::clear_selection`]) so
+//! the backends fall back to the others instead of retrying it.
+
+use serde::Serialize;
+use thiserror::Error;
+
+use crate::usb_permission::DeviceKey;
+
+/// Errors listing or selecting cameras
+#[derive(Debug, Error)]
+pub enum DeviceError {
+    /// No connected camera has the id or vendor and product ID
+    #[error("no connected camera matches '{0}'")]
+    UnknownDevice(String),
+    /// The platform could not list USB devices
+    #[error("could not list USB devices: {0}")]
+    Enumeration(String),
+}
+
+/// Result type alias for device operations
+pub type Result<T> = std::result::Result<T, DeviceError>;
+
+/// A connected UVC camera
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct CameraDevice {
+    /// Platform device name: `/dev/bus/usb/001/004` on Android,
+    /// `usb/<bus>/<address>` on desktop
+    pub id: String,
+    /// Vendor and product ID
+    pub key: DeviceKey,
+    /// Product name, if the camera reports one
+    pub name: Option<String>,
+    /// Whether this is the camera being streamed from
+    pub active: bool,
+}
+
+impl CameraDevice {
+    /// Camera that is not open
+    pub fn new(id: String, key: DeviceKey, name: Option<String>) -> Self {
+        Self {
+            id,
+            key,
+            name,
+            active: false,
+        }
+    }
+
+    /// Whether `id` is this camera's device name or `vvvv:pppp` ID
+    pub fn matches(&self, id: &str) -> bool {
+        self.id == id || self.key.to_string().eq_ignore_ascii_case(id)
+    }
+}
+
+/// The chosen camera, by name and by ID so it is found again after a replug
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Selection {
+    id: String,
+    key: DeviceKey,
+}
+
+/// Cameras found by the last enumeration, the selected one and the open one
+#[derive(Debug, Default)]
+pub struct DeviceRegistry {
+    devices: Vec<CameraDevice>,
+    selected: Option<Selection>,
+    open: Option<String>,
+}
+
+impl DeviceRegistry {
+    /// Create an empty registry
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Replace the known cameras with a new enumeration
+    ///
+    /// Names from earlier enumerations are kept for cameras that could not
+    /// report one this time (e.g. because they are open).
+    pub fn update(&mut self, mut devices: Vec<CameraDevice>) {
+        for device in &mut devices {
+            if device.name.is_none() {
+                device.name = self
+                    .devices
+                    .iter()
+                    .find(|known| known.id == device.id)
+                    .and_then(|known| known.name.clone());
+            }
+        }
+        devices.sort_by(|a, b| a.id.cmp(&b.id));
+        self.devices = devices;
+    }
+
+    /// Known cameras, with the open one marked active
+    pub fn list(&self) -> Vec<CameraDevice> {
+        self.devices
+            .iter()
+            .map(|device| CameraDevice {
+                active: self.open.as_deref() == Some(device.id.as_str()),
+                ..device.clone()
+            })
+            .collect()
+    }
+
+    /// Choose the camera to stream from by device name or `vvvv:pppp` ID
+    ///
+    /// # Errors
+    ///
+    /// Returns `DeviceError::UnknownDevice` if no known camera matches.
+    pub fn select(&mut self, id: &str) -> Result<CameraDevice> {
+        // An exact device name wins over a camera of the same model
+        let device = self
+            .devices
+            .iter()
+            .find(|device| device.id == id)
+            .or_else(|| self.devices.iter().find(|device| device.matches(id)))
+            .ok_or_else(|| DeviceError::UnknownDevice(id.to_string()))?
+            .clone();
+        self.selected = Some(Selection {
+            id: device.id.clone(),
+            key: device.key,
+        });
+        Ok(CameraDevice {
+            active: self.open.as_deref() == Some(device.id.as_str()),
+            ..device
+        })
+    }
+
+    /// Index of the selected camera among `candidates`, if one was selected
+    /// and is connected
+    ///
+    /// Matches the device name first, then (after a replug) the ID.
+    pub fn pick(&self, candidates: &[CameraDevice]) -> Option<usize> {
+        let selected = self.selected.as_ref()?;
+        candidates
+            .iter()
+            .position(|device| device.id == selected.id)
+            .or_else(|| {
+                candidates
+                    .iter()
+                    .position(|device| device.key == selected.key)
+            })
+    }
+
+    /// Record the camera the backend opened (None when it closed it)
+    pub fn set_open(&mut self, id: Option<String>) {
+        // Follow the selected camera to its new device name after a replug
+        if let (Some(selected), Some(id)) = (self.selected.as_mut(), id.as_deref()) {
+            let key = self.devices.iter().find(|d| d.id == id).map(|d| d.key);
+            if key == Some(selected.key) {
+                selected.id = id.to_string();
+            }
+        }
+        self.open = id;
+    }
+
+    /// Device name of the open camera
+    pub fn open(&self) -> Option<&str> {
+        self.open.as_deref()
+    }
+
+    /// Forget the selected camera after it could not be opened
+    ///
+    /// The backends go back to their default choice and
+    /// [`Self::wants_switch`] stops asking for a restart.
+    pub fn clear_selection(&mut self) {
+        self.selected = None;
+    }
+
+    /// Whether a camera other than the open one was selected
+    pub fn wants_switch(&self) -> bool {
+        self.selected
+            .as_ref()
+            .is_some_and(|selected| self.open.as_deref() != Some(selected.id.as_str()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn camera(id: &str, vendor_id: u16, product_id: u16) -> CameraDevice {
+        CameraDevice::new(
+            id.to_string(),
+            DeviceKey {
+                vendor_id,
+                product_id,
+            },
+            Some(format!("Scope {}", id)),
+        )
+    }
+
+    fn two_cameras() -> DeviceRegistry {
+        let mut registry = DeviceRegistry::new();
+        registry.update(vec![
+            camera("/dev/bus/usb/001/005", 0x1234, 0x0002),
+            camera("/dev/bus/usb/001/004", 0x1234, 0x0001),
+        ]);
+        registry
+    }
+
+    #[test]
+    fn test_list_is_sorted_and_marks_the_open_camera() {
+        let mut registry = two_cameras();
+        assert_eq!(registry.open(), None);
+        registry.set_open(Some("/dev/bus/usb/001/005".to_string()));
+        assert_eq!(registry.open(), Some("/dev/bus/usb/001/005"));
+        let list = registry.list();
+        assert_eq!(list[0].id, "/dev/bus/usb/001/004");
+        assert_eq!(
+            list.iter().map(|d| d.active).collect::<Vec<_>>(),
+            vec![false, true]
+        );
+    }
+
+    #[test]
+    fn test_select_by_name_or_id() {
+        let mut registry = two_cameras();
+        let device = registry.select("1234:0002").unwrap();
+        assert_eq!(device.id, "/dev/bus/usb/001/005");
+        let device = registry.select("/dev/bus/usb/001/004").unwrap();
+        assert_eq!(device.key.product_id, 1);
+        assert!(matches!(
+            registry.select("ffff:0001"),
+            Err(DeviceError::UnknownDevice(_))
+        ));
+        // A failed selection keeps the previous one
+        assert_eq!(registry.pick(&registry.list()), Some(0));
+    }
+
+    #[test]
+    fn test_switch_until_the_selected_camera_is_open() {
+        let mut registry = two_cameras();
+        registry.set_open(Some("/dev/bus/usb/001/004".to_string()));
+        assert!(!registry.wants_switch());
+
+        registry.select("/dev/bus/usb/001/005").unwrap();
+        assert!(registry.wants_switch());
+        let candidates = registry.list();
+        assert_eq!(registry.pick(&candidates), Some(1));
+
+        registry.set_open(Some("/dev/bus/usb/001/005".to_string()));
+        assert!(!registry.wants_switch());
+    }
+
+    #[test]
+    fn test_cleared_selection_stops_the_switch() {
+        let mut registry = two_cameras();
+        registry.set_open(Some("/dev/bus/usb/001/004".to_string()));
+        registry.select("/dev/bus/usb/001/005").unwrap();
+        assert!(registry.wants_switch());
+
+        registry.clear_selection();
+        assert!(!registry.wants_switch());
+        assert_eq!(registry.pick(&registry.list()), None);
+    }
+
+    #[test]
+    fn test_selection_follows_a_replugged_camera() {
+        let mut registry = two_cameras();
+        registry.select("/dev/bus/usb/001/005").unwrap();
+
+        // Replugged: same ID, new device name, name unknown until opened
+        let mut replugged = camera("/dev/bus/usb/001/009", 0x1234, 0x0002);
+        replugged.name = None;
+        registry.update(vec![
+            camera("/dev/bus/usb/001/004", 0x1234, 0x0001),
+            replugged,
+        ]);
+        let candidates = registry.list();
+        assert_eq!(registry.pick(&candidates), Some(1));
+
+        registry.set_open(Some("/dev/bus/usb/001/009".to_string()));
+        assert!(!registry.wants_switch());
+    }
+
+    #[test]
+    fn test_update_keeps_known_names() {
+        let mut registry = two_cameras();
+        let mut open = camera("/dev/bus/usb/001/004", 0x1234, 0x0001);
+        open.name = None;
+        registry.update(vec![open]);
+        assert_eq!(
+            registry.list()[0].name.as_deref(),
+            Some("Scope /dev/bus/usb/001/004")
+        );
+    }
+
+    #[test]
+    fn test_no_selection_picks_nothing() {
+        let registry = two_cameras();
+        assert_eq!(registry.pick(&registry.list()), None);
+        assert!(!registry.wants_switch());
+    }
+}
@@ -0,0 +1,257 @@
+//! Diagnostics bundle for bug reports
+//!
+//! Collects what differs between installs and matters when a camera
+//! misbehaves - build, linked libusb, enabled quirks, stream health - into one
+//! serializable report. libusb behavior in particular varies across Android
+//! NDK builds (hotplug support, `libusb_wrap_sys_device`, logging), so the
+//! linked version and its capabilities are reported rather than assumed.
+//!
+//! libusb's own log output is forwarded to the app log (target `libusb`) at
+//! a level that can be changed while streaming, so transfer-level errors show
+//! up without a custom libusb build (see [`set_libusb_log_level`]).
+
+use std::sync::atomic::{AtomicU8, Ordering};
+
+use serde::{Deserialize, Serialize};
+
+use crate::quirks::DeviceQuirks;
+use crate::stream_health::HealthStats;
+use crate::{BuildCapabilities, BuildInfo};
+
+/// Environment variable libusb reads its log level from at init
+pub const LIBUSB_DEBUG_ENV: &str = "LIBUSB_DEBUG";
+
+/// Current libusb log level (a [`LibusbLogLevel`] discriminant)
+static LIBUSB_LOG_LEVEL: AtomicU8 = AtomicU8::new(LibusbLogLevel::None as u8);
+
+/// First libusb release with `libusb_wrap_sys_device`
+const WRAP_SYS_DEVICE_SINCE: (u16, u16, u16) = (1, 0, 23);
+
+/// Version of the linked libusb
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LibusbVersion {
+    /// Major version
+    pub major: u16,
+    /// Minor version
+    pub minor: u16,
+    /// Micro version
+    pub micro: u16,
+    /// Nano version (build number)
+    pub nano: u16,
+    /// Release candidate suffix, e.g. `-rc1` (empty for releases)
+    pub rc: String,
+}
+
+impl LibusbVersion {
+    /// Whether this version has `libusb_wrap_sys_device`, which Android needs
+    /// to open a file descriptor from `UsbManager`
+    pub fn has_wrap_sys_device(&self) -> bool {
+        (self.major, self.minor, self.micro) >= WRAP_SYS_DEVICE_SINCE
+    }
+}
+
+impl std::fmt::Display for LibusbVersion {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}.{}.{}.{}{}",
+            self.major, self.minor, self.micro, self.nano, self.rc
+        )
+    }
+}
+
+/// libusb's own log verbosity
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LibusbLogLevel {
+    /// No messages
+    #[default]
+    None,
+    /// Errors only
+    Error,
+    /// Warnings and errors
+    Warning,
+    /// Informational messages, warnings and errors
+    Info,
+    /// Everything, including transfer-level debug output
+    Debug,
+}
+
+impl LibusbLogLevel {
+    /// Parse a libusb log level number (0 = none to 4 = debug)
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.trim() {
+            "0" => Some(Self::None),
+            "1" => Some(Self::Error),
+            "2" => Some(Self::Warning),
+            "3" => Some(Self::Info),
+            "4" => Some(Self::Debug),
+            _ => None,
+        }
+    }
+
+    /// Level libusb picks up from [`LIBUSB_DEBUG_ENV`] at init
+    pub fn from_env() -> Self {
+        std::env::var(LIBUSB_DEBUG_ENV)
+            .ok()
+            .and_then(|value| Self::parse(&value))
+            .unwrap_or_default()
+    }
+
+    /// libusb's number for this level (`LIBUSB_LOG_LEVEL_*`)
+    pub fn as_raw(self) -> i32 {
+        self as i32
+    }
+
+    /// Level set by the last [`set_libusb_log_level`] call
+    pub fn current() -> Self {
+        match LIBUSB_LOG_LEVEL.load(Ordering::Relaxed) {
+            1 => Self::Error,
+            2 => Self::Warning,
+            3 => Self::Info,
+            4 => Self::Debug,
+            _ => Self::None,
+        }
+    }
+}
+
+/// Set libusb's log level for the open context and every later one
+///
+/// libusb messages at or above `level` are forwarded to the app log. If
+/// [`LIBUSB_DEBUG_ENV`] is set, libusb ignores this and keeps that level.
+pub fn set_libusb_log_level(level: LibusbLogLevel) {
+    LIBUSB_LOG_LEVEL.store(level as u8, Ordering::Relaxed);
+    #[cfg(target_os = "android")]
+    crate::libusb_android::apply_log_level(level);
+    log::info!("libusb log level: {:?}", level);
+}
+
+/// Linked libusb version and capabilities
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LibusbReport {
+    /// Linked libusb version (`None` where libusb isn't linked, i.e. off Android)
+    pub version: Option<String>,
+    /// `LIBUSB_CAP_HAS_HOTPLUG` is reported
+    pub has_hotplug: bool,
+    /// `libusb_wrap_sys_device` is available
+    pub has_wrap_sys_device: bool,
+    /// Active libusb log level
+    pub log_level: LibusbLogLevel,
+}
+
+impl LibusbReport {
+    /// Build a report from a queried version and capability
+    pub fn new(
+        version: Option<&LibusbVersion>,
+        has_hotplug: bool,
+        log_level: LibusbLogLevel,
+    ) -> Self {
+        Self {
+            version: version.map(ToString::to_string),
+            has_hotplug,
+            has_wrap_sys_device: version.is_some_and(LibusbVersion::has_wrap_sys_device),
+            log_level,
+        }
+    }
+
+    /// Report for the libusb linked into this binary
+    #[cfg(target_os = "android")]
+    pub fn current() -> Self {
+        Self::new(
+            Some(&crate::libusb_android::libusb_version()),
+            crate::libusb_android::has_hotplug(),
+            LibusbLogLevel::current(),
+        )
+    }
+
+    /// Report for the libusb linked into this binary (none off Android)
+    #[cfg(not(target_os = "android"))]
+    pub fn current() -> Self {
+        Self::new(None, false, LibusbLogLevel::current())
+    }
+}
+
+/// Everything a bug report needs about this install
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DiagnosticsBundle {
+    /// Version and build of the app
+    pub build: BuildInfo,
+    /// Networking subsystems compiled in
+    pub build_capabilities: BuildCapabilities,
+    /// Linked libusb
+    pub libusb: LibusbReport,
+    /// Device quirks enabled for this session
+    pub quirks: DeviceQuirks,
+    /// Current stream health
+    pub stream_health: HealthStats,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn version(major: u16, minor: u16, micro: u16) -> LibusbVersion {
+        LibusbVersion {
+            major,
+            minor,
+            micro,
+            nano: 11882,
+            rc: String::new(),
+        }
+    }
+
+    #[test]
+    fn test_version_display() {
+        assert_eq!(version(1, 0, 27).to_string(), "1.0.27.11882");
+        let rc = LibusbVersion {
+            rc: "-rc1".to_string(),
+            ..version(1, 0, 28)
+        };
+        assert_eq!(rc.to_string(), "1.0.28.11882-rc1");
+    }
+
+    #[test]
+    fn test_wrap_sys_device_by_version() {
+        assert!(!version(1, 0, 22).has_wrap_sys_device());
+        assert!(version(1, 0, 23).has_wrap_sys_device());
+        assert!(version(1, 1, 0).has_wrap_sys_device());
+    }
+
+    #[test]
+    fn test_report_from_version() {
+        let report = LibusbReport::new(None, false, LibusbLogLevel::None);
+        assert_eq!(report.version, None);
+        assert!(!report.has_wrap_sys_device);
+
+        let report = LibusbReport::new(Some(&version(1, 0, 27)), true, LibusbLogLevel::Debug);
+        assert_eq!(report.version.as_deref(), Some("1.0.27.11882"));
+        assert!(report.has_hotplug);
+        assert!(report.has_wrap_sys_device);
+    }
+
+    #[test]
+    fn test_parse_log_level() {
+        assert_eq!(LibusbLogLevel::parse("0"), Some(LibusbLogLevel::None));
+        assert_eq!(LibusbLogLevel::parse(" 4 "), Some(LibusbLogLevel::Debug));
+        assert_eq!(LibusbLogLevel::parse("debug"), None);
+        assert_eq!(LibusbLogLevel::parse("5"), None);
+    }
+
+    #[test]
+    fn test_log_level_round_trip() {
+        let before = LibusbLogLevel::current();
+        for level in [
+            LibusbLogLevel::Debug,
+            LibusbLogLevel::Warning,
+            LibusbLogLevel::None,
+        ] {
+            set_libusb_log_level(level);
+            assert_eq!(LibusbLogLevel::current(), level);
+            assert_eq!(
+                LibusbLogLevel::parse(&level.as_raw().to_string()),
+                Some(level)
+            );
+        }
+        set_libusb_log_level(before);
+    }
+}
@@ -0,0 +1,196 @@
+//! Adaptive JPEG quality for frames encoded on the streaming thread
+//!
+//! Video recordings of YUY2 cameras encode every RGB frame to JPEG on the
+//! streaming thread. When that takes longer than the time between frames,
+//! the thread falls behind and the live view stutters. [`AdaptiveQuality`]
+//! compares each encode time with the frame interval and lowers the quality
+//! by [`QUALITY_STEP`] after [`LOWER_AFTER`] frames in a row over budget,
+//! down to [`MIN_QUALITY`]. It raises it again only after [`RAISE_AFTER`]
+//! frames in a row that took less than [`HEADROOM_PERCENT`] of the interval,
+//! so the quality doesn't flip between two steps on every other frame.
+//!
+//! Every change is returned as a [`QualityChange`], emitted as the
+//! `encode-quality` event.
+
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+use crate::image_encoder::DEFAULT_JPEG_QUALITY;
+
+/// Lowest quality the controller goes down to
+pub const MIN_QUALITY: u8 = 50;
+
+/// Quality change per adjustment
+pub const QUALITY_STEP: u8 = 10;
+
+/// Frames in a row over budget before the quality is lowered
+pub const LOWER_AFTER: u32 = 5;
+
+/// Frames in a row with headroom before the quality is raised
+pub const RAISE_AFTER: u32 = 60;
+
+/// Share of the frame interval (percent) an encode must stay under to count
+/// as headroom
+pub const HEADROOM_PERCENT: u32 = 50;
+
+/// Payload of the `encode-quality` event
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct QualityChange {
+    /// Quality before the change
+    pub from: u8,
+    /// Quality from now on
+    pub to: u8,
+    /// Encode time of the frame that triggered the change, in microseconds
+    pub encode_us: u64,
+    /// Frame interval the encode time was compared with, in microseconds
+    pub interval_us: u64,
+}
+
+/// JPEG quality controller with hysteresis
+#[derive(Debug, Clone)]
+pub struct AdaptiveQuality {
+    quality: u8,
+    max: u8,
+    /// Frames in a row over budget
+    over: u32,
+    /// Frames in a row with headroom
+    under: u32,
+}
+
+impl Default for AdaptiveQuality {
+    fn default() -> Self {
+        Self::new(DEFAULT_JPEG_QUALITY)
+    }
+}
+
+impl AdaptiveQuality {
+    /// Controller starting at, and never going above, `max`
+    pub fn new(max: u8) -> Self {
+        Self {
+            quality: max,
+            max,
+            over: 0,
+            under: 0,
+        }
+    }
+
+    /// Quality to encode the next frame with
+    pub fn quality(&self) -> u8 {
+        self.quality
+    }
+
+    /// Record how long a frame took to encode against the frame interval
+    ///
+    /// Returns the change if the quality was adjusted.
+    pub fn observe(&mut self, encode: Duration, interval: Duration) -> Option<QualityChange> {
+        if interval.is_zero() {
+            return None;
+        }
+        if encode > interval {
+            self.under = 0;
+            self.over += 1;
+            if self.over < LOWER_AFTER {
+                return None;
+            }
+            self.over = 0;
+            let to = self.quality.saturating_sub(QUALITY_STEP).max(MIN_QUALITY);
+            self.change_to(to, encode, interval)
+        } else if encode * 100 < interval * HEADROOM_PERCENT {
+            self.over = 0;
+            self.under += 1;
+            if self.under < RAISE_AFTER {
+                return None;
+            }
+            self.under = 0;
+            let to = self.quality.saturating_add(QUALITY_STEP).min(self.max);
+            self.change_to(to, encode, interval)
+        } else {
+            self.over = 0;
+            self.under = 0;
+            None
+        }
+    }
+
+    fn change_to(&mut self, to: u8, encode: Duration, interval: Duration) -> Option<QualityChange> {
+        if to == self.quality {
+            return None;
+        }
+        let from = std::mem::replace(&mut self.quality, to);
+        Some(QualityChange {
+            from,
+            to,
+            encode_us: encode.as_micros() as u64,
+            interval_us: interval.as_micros() as u64,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const INTERVAL: Duration = Duration::from_millis(33);
+    const SLOW: Duration = Duration::from_millis(40);
+    const FAST: Duration = Duration::from_millis(10);
+    /// Within budget but without headroom
+    const TIGHT: Duration = Duration::from_millis(25);
+
+    fn feed(quality: &mut AdaptiveQuality, encode: Duration, frames: u32) -> Vec<u8> {
+        (0..frames)
+            .filter_map(|_| quality.observe(encode, INTERVAL))
+            .map(|change| change.to)
+            .collect()
+    }
+
+    #[test]
+    fn test_lowers_quality_when_over_budget() {
+        let mut quality = AdaptiveQuality::new(90);
+        assert!(feed(&mut quality, SLOW, LOWER_AFTER - 1).is_empty());
+        assert_eq!(feed(&mut quality, SLOW, 1), vec![80]);
+        assert_eq!(quality.quality(), 80);
+        // Down to the floor, then no further changes
+        assert_eq!(
+            feed(&mut quality, SLOW, LOWER_AFTER * 10),
+            vec![70, 60, MIN_QUALITY]
+        );
+    }
+
+    #[test]
+    fn test_restores_quality_with_headroom() {
+        let mut quality = AdaptiveQuality::new(90);
+        feed(&mut quality, SLOW, LOWER_AFTER * 2);
+        assert_eq!(quality.quality(), 70);
+        // Within budget but without headroom: hold
+        assert!(feed(&mut quality, TIGHT, RAISE_AFTER * 2).is_empty());
+        assert_eq!(feed(&mut quality, FAST, RAISE_AFTER * 3), vec![80, 90]);
+        assert_eq!(quality.quality(), 90);
+    }
+
+    #[test]
+    fn test_streaks_need_consecutive_frames() {
+        let mut quality = AdaptiveQuality::new(90);
+        for _ in 0..LOWER_AFTER * 4 {
+            feed(&mut quality, SLOW, LOWER_AFTER - 1);
+            feed(&mut quality, TIGHT, 1);
+        }
+        assert_eq!(quality.quality(), 90);
+    }
+
+    #[test]
+    fn test_change_reports_timings() {
+        let mut quality = AdaptiveQuality::new(90);
+        let change = (0..LOWER_AFTER)
+            .find_map(|_| quality.observe(SLOW, INTERVAL))
+            .unwrap();
+        assert_eq!(
+            change,
+            QualityChange {
+                from: 90,
+                to: 80,
+                encode_us: 40_000,
+                interval_us: 33_000,
+            }
+        );
+    }
+}
@@ -0,0 +1,500 @@
+//! Histogram-based exposure suggestions
+//!
+//! The `exposure-advisor` thread takes the current frame every
+//! [`ANALYSIS_INTERVAL`] and builds a luma histogram of it ([`LumaStats`]).
+//! A frame whose mean is very low or high, or with a large share of crushed
+//! shadows or clipped highlights, counts as under- or overexposed. Only when
+//! [`CHRONIC_SAMPLES`] analyses in a row agree is the exposure considered
+//! chronically wrong: a single dark frame while the probe passes a hole is
+//! not a reason to touch the camera.
+//!
+//! A chronic condition produces an [`ExposureSuggestion`], emitted as
+//! `exposure-suggestion`. [`ExposureSuggestion::plan`] prefers the exposure
+//! control, then brightness, stepping by a sixteenth of the control's range;
+//! once both are at their limit (or the camera has neither) it falls back
+//! to the LED, which may be a physical dial. With `auto_apply` the control
+//! change is made through `uvc_controls`, and the next one waits for another
+//! run of [`CHRONIC_SAMPLES`] analyses at the new setting. Otherwise the
+//! suggestion is repeated at most every [`REPEAT_INTERVAL`].
+
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use serde::{Deserialize, Serialize};
+
+use crate::uvc_controls::{CameraControl, ControlInfo};
+
+/// How often the current frame is analysed
+pub const ANALYSIS_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Consecutive analyses with the same condition before it is chronic
+pub const CHRONIC_SAMPLES: u32 = 5;
+
+/// Shortest time between repeated suggestions for the same condition
+pub const REPEAT_INTERVAL: Duration = Duration::from_secs(30);
+
+/// Only every `SAMPLE_STRIDE`th pixel goes into the histogram
+pub const SAMPLE_STRIDE: usize = 4;
+
+/// Mean luma below which a frame is underexposed
+pub const UNDER_MEAN: f32 = 50.0;
+
+/// Mean luma above which a frame is overexposed
+pub const OVER_MEAN: f32 = 200.0;
+
+/// Share of crushed or clipped pixels that makes a frame under- or
+/// overexposed whatever its mean
+pub const CLIPPED_FRACTION: f32 = 0.3;
+
+/// Luma at or below which a pixel counts as crushed
+const DARK_LEVEL: usize = 16;
+
+/// Luma at or above which a pixel counts as clipped
+const BRIGHT_LEVEL: usize = 240;
+
+/// Each control step is this fraction of the control's range
+const STEPS_PER_RANGE: i32 = 16;
+
+/// Luma histogram summary of one frame
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct LumaStats {
+    /// Mean luma (0-255)
+    pub mean: f32,
+    /// Share of pixels at or below the crushed-shadow level
+    pub dark_fraction: f32,
+    /// Share of pixels at or above the clipped-highlight level
+    pub bright_fraction: f32,
+}
+
+impl LumaStats {
+    /// Histogram statistics of an RGB24 frame, `None` if it has no pixels
+    #[must_use]
+    pub fn from_rgb(rgb: &[u8]) -> Option<Self> {
+        let mut histogram = [0u32; 256];
+        for p in rgb.chunks_exact(3).step_by(SAMPLE_STRIDE) {
+            let y = (u32::from(p[0]) * 77 + u32::from(p[1]) * 150 + u32::from(p[2]) * 29) >> 8;
+            histogram[y as usize] += 1;
+        }
+        let total: u64 = histogram.iter().map(|&n| u64::from(n)).sum();
+        if total == 0 {
+            return None;
+        }
+        let count = |range: &[u32]| range.iter().map(|&n| u64::from(n)).sum::<u64>() as f32;
+        let sum: u64 = histogram
+            .iter()
+            .enumerate()
+            .map(|(v, &n)| v as u64 * u64::from(n))
+            .sum();
+        Some(Self {
+            mean: sum as f32 / total as f32,
+            dark_fraction: count(&histogram[..=DARK_LEVEL]) / total as f32,
+            bright_fraction: count(&histogram[BRIGHT_LEVEL..]) / total as f32,
+        })
+    }
+
+    /// Whether the frame is under- or overexposed
+    #[must_use]
+    pub fn condition(&self) -> ExposureCondition {
+        if self.mean < UNDER_MEAN || self.dark_fraction > CLIPPED_FRACTION {
+            ExposureCondition::Underexposed
+        } else if self.mean > OVER_MEAN || self.bright_fraction > CLIPPED_FRACTION {
+            ExposureCondition::Overexposed
+        } else {
+            ExposureCondition::Normal
+        }
+    }
+}
+
+/// Exposure of a frame
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ExposureCondition {
+    /// Neither too dark nor too bright
+    #[default]
+    Normal,
+    /// Too dark
+    Underexposed,
+    /// Too bright
+    Overexposed,
+}
+
+/// What the user (or auto-apply) should change
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SuggestedAction {
+    /// Raise the exposure time
+    IncreaseExposure,
+    /// Lower the exposure time
+    ReduceExposure,
+    /// Raise the brightness control
+    IncreaseBrightness,
+    /// Lower the brightness control
+    ReduceBrightness,
+    /// Turn the LED up (controls exhausted or unavailable)
+    IncreaseLed,
+    /// Turn the LED down (controls exhausted or unavailable)
+    ReduceLed,
+}
+
+/// A camera control change that would correct the exposure
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ControlChange {
+    /// Control to change
+    pub control: CameraControl,
+    /// Current value
+    pub from: i32,
+    /// Proposed value
+    pub to: i32,
+}
+
+/// Payload of the `exposure-suggestion` event
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ExposureSuggestion {
+    /// Chronic condition the suggestion corrects
+    pub condition: ExposureCondition,
+    /// Suggested correction
+    pub action: SuggestedAction,
+    /// Histogram of the last analysed frame
+    pub stats: LumaStats,
+    /// Control change for exposure and brightness actions
+    pub change: Option<ControlChange>,
+    /// Whether the change was made (auto-apply)
+    pub applied: bool,
+}
+
+impl ExposureSuggestion {
+    /// Pick a correction for `condition` given the camera's controls
+    ///
+    /// Exposure is tried before brightness; a control already at its limit
+    /// in the needed direction is skipped. Without a usable control the LED
+    /// is suggested, with no change to apply.
+    #[must_use]
+    pub fn plan(condition: ExposureCondition, stats: LumaStats, controls: &[ControlInfo]) -> Self {
+        let up = condition != ExposureCondition::Overexposed;
+        let change = [CameraControl::Exposure, CameraControl::Brightness]
+            .into_iter()
+            .filter_map(|control| controls.iter().find(|info| info.control == control))
+            .find_map(|info| {
+                // abs_diff: the full i32 range would overflow a subtraction
+                let range_step = info.max.abs_diff(info.min) / STEPS_PER_RANGE.unsigned_abs();
+                let step = i32::try_from(range_step)
+                    .unwrap_or(i32::MAX)
+                    .max(info.step)
+                    .max(1);
+                let to = if up {
+                    info.current.saturating_add(step).min(info.max)
+                } else {
+                    info.current.saturating_sub(step).max(info.min)
+                };
+                (to != info.current).then_some(ControlChange {
+                    control: info.control,
+                    from: info.current,
+                    to,
+                })
+            });
+        let action = match (change.map(|c| c.control), up) {
+            (Some(CameraControl::Exposure), true) => SuggestedAction::IncreaseExposure,
+            (Some(CameraControl::Exposure), false) => SuggestedAction::ReduceExposure,
+            (Some(_), true) => SuggestedAction::IncreaseBrightness,
+            (Some(_), false) => SuggestedAction::ReduceBrightness,
+            (None, true) => SuggestedAction::IncreaseLed,
+            (None, false) => SuggestedAction::ReduceLed,
+        };
+        Self {
+            condition,
+            action,
+            stats,
+            change,
+            applied: false,
+        }
+    }
+}
+
+/// Whether the advisor runs and may change controls itself
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ExposureSettings {
+    /// Analyse frames and emit suggestions
+    pub enabled: bool,
+    /// Apply exposure and brightness changes through the camera controls
+    pub auto_apply: bool,
+}
+
+impl Default for ExposureSettings {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            auto_apply: false,
+        }
+    }
+}
+
+/// Advisor state, returned by the exposure advisor commands
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct ExposureStatus {
+    /// Current settings
+    pub settings: ExposureSettings,
+    /// Condition of the last analysed frame
+    pub condition: ExposureCondition,
+    /// Consecutive analyses with that condition
+    pub streak: u32,
+    /// Histogram of the last analysed frame
+    pub stats: Option<LumaStats>,
+    /// Suggestions emitted since the settings last changed
+    pub suggestions: u32,
+    /// Suggestions applied to the camera
+    pub applied: u32,
+    /// Most recent suggestion
+    pub last_suggestion: Option<ExposureSuggestion>,
+}
+
+#[derive(Debug, Default)]
+struct Tracker {
+    last_frame: Option<u64>,
+    condition: ExposureCondition,
+    streak: u32,
+    stats: Option<LumaStats>,
+    suggested_at: Option<Instant>,
+    suggestions: u32,
+    applied: u32,
+    last_suggestion: Option<ExposureSuggestion>,
+}
+
+/// Tracks how long the exposure has been off and when to suggest a fix
+#[derive(Debug, Default)]
+pub struct ExposureAdvisor {
+    settings: Mutex<ExposureSettings>,
+    tracker: Mutex<Tracker>,
+}
+
+impl ExposureAdvisor {
+    /// Current settings
+    pub fn settings(&self) -> ExposureSettings {
+        *crate::lock_or_recover(&self.settings)
+    }
+
+    /// Change the settings, starting the analysis over
+    pub fn set_settings(&self, settings: ExposureSettings) -> ExposureStatus {
+        *crate::lock_or_recover(&self.settings) = settings;
+        *crate::lock_or_recover(&self.tracker) = Tracker::default();
+        self.status()
+    }
+
+    /// Current status
+    pub fn status(&self) -> ExposureStatus {
+        let tracker = crate::lock_or_recover(&self.tracker);
+        ExposureStatus {
+            settings: self.settings(),
+            condition: tracker.condition,
+            streak: tracker.streak,
+            stats: tracker.stats,
+            suggestions: tracker.suggestions,
+            applied: tracker.applied,
+            last_suggestion: tracker.last_suggestion.clone(),
+        }
+    }
+
+    /// Whether `frame_sequence` should be analysed: the advisor is enabled
+    /// and the frame is newer than the last one analysed
+    pub fn wants_frame(&self, frame_sequence: u64) -> bool {
+        frame_sequence != 0
+            && self.settings().enabled
+            && crate::lock_or_recover(&self.tracker).last_frame != Some(frame_sequence)
+    }
+
+    /// Record the analysis of a frame, returning the condition to suggest a
+    /// correction for if it has become chronic and wasn't suggested recently
+    pub fn observe(
+        &self,
+        frame_sequence: u64,
+        stats: LumaStats,
+        now: Instant,
+    ) -> Option<ExposureCondition> {
+        let mut tracker = crate::lock_or_recover(&self.tracker);
+        let condition = stats.condition();
+        if condition == tracker.condition {
+            tracker.streak = tracker.streak.saturating_add(1);
+        } else {
+            tracker.condition = condition;
+            tracker.streak = 1;
+            tracker.suggested_at = None;
+        }
+        tracker.last_frame = Some(frame_sequence);
+        tracker.stats = Some(stats);
+
+        let recent = tracker
+            .suggested_at
+            .is_some_and(|at| now.duration_since(at) < REPEAT_INTERVAL);
+        (condition != ExposureCondition::Normal && tracker.streak >= CHRONIC_SAMPLES && !recent)
+            .then_some(condition)
+    }
+
+    /// Record an emitted suggestion
+    ///
+    /// An applied change restarts the streak, so the next correction waits
+    /// for [`CHRONIC_SAMPLES`] analyses at the new setting.
+    pub fn record(&self, suggestion: &ExposureSuggestion, now: Instant) {
+        let mut tracker = crate::lock_or_recover(&self.tracker);
+        tracker.suggestions += 1;
+        if suggestion.applied {
+            tracker.applied += 1;
+            tracker.streak = 0;
+        } else {
+            tracker.suggested_at = Some(now);
+        }
+        tracker.last_suggestion = Some(suggestion.clone());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn gray_frame(levels: &[u8]) -> Vec<u8> {
+        levels.iter().flat_map(|&v| [v; 3]).collect()
+    }
+
+    fn stats(mean: f32) -> LumaStats {
+        LumaStats {
+            mean,
+            dark_fraction: 0.0,
+            bright_fraction: 0.0,
+        }
+    }
+
+    fn info(control: CameraControl, min: i32, max: i32, current: i32) -> ControlInfo {
+        ControlInfo {
+            control,
+            min,
+            max,
+            step: 1,
+            default: current,
+            current,
+        }
+    }
+
+    #[test]
+    fn test_luma_stats() {
+        assert!(LumaStats::from_rgb(&[]).is_none());
+
+        let dark = LumaStats::from_rgb(&gray_frame(&[0; 64])).unwrap();
+        assert_eq!(dark.mean, 0.0);
+        assert_eq!(dark.dark_fraction, 1.0);
+        assert_eq!(dark.condition(), ExposureCondition::Underexposed);
+
+        let bright = LumaStats::from_rgb(&gray_frame(&[255; 64])).unwrap();
+        assert_eq!(bright.bright_fraction, 1.0);
+        assert_eq!(bright.condition(), ExposureCondition::Overexposed);
+
+        let mid = LumaStats::from_rgb(&gray_frame(&[128; 64])).unwrap();
+        assert!((mid.mean - 127.0).abs() < 1.5);
+        assert_eq!(mid.condition(), ExposureCondition::Normal);
+
+        // Half crushed shadows: underexposed although the mean is fine
+        let mut levels = vec![0u8; 32];
+        levels.extend([255u8; 32]);
+        let split = LumaStats::from_rgb(&gray_frame(&levels)).unwrap();
+        assert_eq!(split.condition(), ExposureCondition::Underexposed);
+    }
+
+    #[test]
+    fn test_plan_prefers_exposure_then_brightness_then_led() {
+        let controls = [
+            info(CameraControl::Brightness, -64, 64, 0),
+            info(CameraControl::Exposure, 1, 1601, 100),
+        ];
+        let plan =
+            ExposureSuggestion::plan(ExposureCondition::Underexposed, stats(20.0), &controls);
+        assert_eq!(plan.action, SuggestedAction::IncreaseExposure);
+        assert_eq!(
+            plan.change,
+            Some(ControlChange {
+                control: CameraControl::Exposure,
+                from: 100,
+                to: 200,
+            })
+        );
+
+        // Exposure at its maximum: brightness is next
+        let controls = [
+            info(CameraControl::Brightness, -64, 64, 0),
+            info(CameraControl::Exposure, 1, 1601, 1601),
+        ];
+        let plan =
+            ExposureSuggestion::plan(ExposureCondition::Underexposed, stats(20.0), &controls);
+        assert_eq!(plan.action, SuggestedAction::IncreaseBrightness);
+        assert_eq!(plan.change.unwrap().to, 8);
+
+        let plan = ExposureSuggestion::plan(ExposureCondition::Overexposed, stats(230.0), &[]);
+        assert_eq!(plan.action, SuggestedAction::ReduceLed);
+        assert!(plan.change.is_none());
+    }
+
+    #[test]
+    fn test_plan_handles_full_i32_range() {
+        let controls = [info(CameraControl::Exposure, i32::MIN, i32::MAX, 0)];
+        let plan =
+            ExposureSuggestion::plan(ExposureCondition::Overexposed, stats(230.0), &controls);
+        // A sixteenth of the 2^32 - 1 wide range
+        assert_eq!(plan.change.unwrap().to, -268_435_455);
+    }
+
+    #[test]
+    fn test_suggestion_needs_chronic_condition() {
+        let advisor = ExposureAdvisor::default();
+        let now = Instant::now();
+        let dark = stats(10.0);
+
+        for frame in 1..CHRONIC_SAMPLES as u64 {
+            assert!(advisor.wants_frame(frame));
+            assert_eq!(advisor.observe(frame, dark, now), None);
+        }
+        assert!(!advisor.wants_frame(u64::from(CHRONIC_SAMPLES) - 1));
+
+        // A normal frame breaks the streak
+        assert_eq!(advisor.observe(100, stats(128.0), now), None);
+        for frame in 0..CHRONIC_SAMPLES as u64 - 1 {
+            assert_eq!(advisor.observe(200 + frame, dark, now), None);
+        }
+        let condition = advisor.observe(300, dark, now);
+        assert_eq!(condition, Some(ExposureCondition::Underexposed));
+
+        let suggestion = ExposureSuggestion::plan(ExposureCondition::Underexposed, dark, &[]);
+        advisor.record(&suggestion, now);
+        assert_eq!(advisor.observe(301, dark, now), None);
+        assert_eq!(
+            advisor.observe(302, dark, now + REPEAT_INTERVAL),
+            Some(ExposureCondition::Underexposed)
+        );
+        assert_eq!(advisor.status().suggestions, 1);
+    }
+
+    #[test]
+    fn test_applied_change_restarts_streak() {
+        let advisor = ExposureAdvisor::default();
+        let now = Instant::now();
+        let bright = stats(240.0);
+        for frame in 1..=CHRONIC_SAMPLES as u64 {
+            advisor.observe(frame, bright, now);
+        }
+        let mut suggestion = ExposureSuggestion::plan(
+            ExposureCondition::Overexposed,
+            bright,
+            &[info(CameraControl::Exposure, 1, 1601, 800)],
+        );
+        suggestion.applied = true;
+        advisor.record(&suggestion, now);
+
+        for frame in 10..10 + CHRONIC_SAMPLES as u64 - 1 {
+            assert_eq!(advisor.observe(frame, bright, now), None);
+        }
+        assert!(advisor.observe(20, bright, now).is_some());
+        assert_eq!(advisor.status().applied, 1);
+
+        let status = advisor.set_settings(ExposureSettings {
+            enabled: false,
+            auto_apply: true,
+        });
+        assert_eq!(status.streak, 0);
+        assert!(!advisor.wants_frame(21));
+    }
+}
@@ -0,0 +1,535 @@
+//! C ABI for the frame processing core
+//!
+//! Lets native Android (Kotlin through JNI glue), iOS and third-party tools
+//! reuse frame assembly, YUV conversion, frame validation and capture replay
+//! without the Tauri shell. The library already builds as `cdylib` and
+//! `staticlib`; `include/cleanscope.h` declares everything exported here.
+//!
+//! Conventions:
+//! - Functions return a status code (`CS_OK`, or a negative error). After an
+//!   error, `cleanscope_last_error` describes it (per thread).
+//! - Buffers handed out as [`CsBuffer`] belong to the caller and must be
+//!   released with `cleanscope_buffer_free`.
+//! - Panics are caught at the boundary and reported as `CS_ERROR_PANIC`.
+
+use std::cell::RefCell;
+use std::ffi::{c_char, CStr, CString};
+use std::panic::{catch_unwind, AssertUnwindSafe};
+use std::path::Path;
+
+use crate::frame_assembler::{FrameAssembler, ProcessResult};
+use crate::frame_validation::{validate_yuy2_frame, ValidationLevel};
+use crate::replay::replay_all_frames;
+use crate::yuv_conversion::convert_to_rgb;
+use crate::PixelFormat;
+
+/// Success
+pub const CS_OK: i32 = 0;
+/// `cleanscope_assembler_push`: a complete frame is ready to take
+pub const CS_FRAME_READY: i32 = 1;
+/// `cleanscope_assembler_push`: the packet was skipped (not synced, error bit)
+pub const CS_SKIPPED: i32 = 2;
+/// A required pointer was null
+pub const CS_ERROR_NULL_POINTER: i32 = -1;
+/// An argument was out of range (unknown pixel format, bad UTF-8 path, ...)
+pub const CS_ERROR_INVALID_ARGUMENT: i32 = -2;
+/// YUV to RGB conversion failed
+pub const CS_ERROR_CONVERSION: i32 = -3;
+/// A capture file couldn't be read
+pub const CS_ERROR_IO: i32 = -4;
+/// Rust code panicked
+pub const CS_ERROR_PANIC: i32 = -5;
+
+thread_local! {
+    static LAST_ERROR: RefCell<Option<CString>> = const { RefCell::new(None) };
+}
+
+/// An error crossing the boundary: status code plus message
+struct FfiError {
+    status: i32,
+    message: String,
+}
+
+impl FfiError {
+    fn new(status: i32, message: impl Into<String>) -> Self {
+        Self {
+            status,
+            message: message.into(),
+        }
+    }
+
+    fn null(name: &str) -> Self {
+        Self::new(CS_ERROR_NULL_POINTER, format!("`{}` is null", name))
+    }
+}
+
+type FfiResult = Result<i32, FfiError>;
+
+/// Run `body`, turning errors and panics into status codes
+fn guard(body: impl FnOnce() -> FfiResult) -> i32 {
+    let result = catch_unwind(AssertUnwindSafe(body))
+        .unwrap_or_else(|_| Err(FfiError::new(CS_ERROR_PANIC, "panic in frame processing")));
+    match result {
+        Ok(status) => status,
+        Err(error) => {
+            let message = CString::new(error.message.replace('\0', " ")).unwrap_or_default();
+            LAST_ERROR.with(|last| *last.borrow_mut() = Some(message));
+            error.status
+        }
+    }
+}
+
+/// Borrow `len` bytes at `data` (`data` may be null when `len` is 0)
+///
+/// # Safety
+///
+/// `data` must point to `len` readable bytes that outlive the returned slice.
+unsafe fn input<'a>(data: *const u8, len: usize, name: &str) -> Result<&'a [u8], FfiError> {
+    if len == 0 {
+        return Ok(&[]);
+    }
+    if data.is_null() {
+        return Err(FfiError::null(name));
+    }
+    Ok(std::slice::from_raw_parts(data, len))
+}
+
+/// Pixel format from its C value (`CS_PIXEL_*`)
+fn pixel_format_from_raw(value: u32) -> Result<PixelFormat, FfiError> {
+    Ok(match value {
+        0 => PixelFormat::Yuyv,
+        1 => PixelFormat::Uyvy,
+        2 => PixelFormat::Nv12,
+        3 => PixelFormat::I420,
+        4 => PixelFormat::Rgb888,
+        5 => PixelFormat::Bgr888,
+        6 => PixelFormat::BayerRggb,
+        7 => PixelFormat::Nv21,
+        8 => PixelFormat::Yv12,
+        _ => {
+            return Err(FfiError::new(
+                CS_ERROR_INVALID_ARGUMENT,
+                format!("unknown pixel format {}", value),
+            ))
+        }
+    })
+}
+
+/// Validation level from its C value (`CS_VALIDATION_*`)
+fn validation_level_from_raw(value: u32) -> Result<ValidationLevel, FfiError> {
+    Ok(match value {
+        0 => ValidationLevel::Strict,
+        1 => ValidationLevel::Moderate,
+        2 => ValidationLevel::Minimal,
+        3 => ValidationLevel::Off,
+        _ => {
+            return Err(FfiError::new(
+                CS_ERROR_INVALID_ARGUMENT,
+                format!("unknown validation level {}", value),
+            ))
+        }
+    })
+}
+
+/// Byte buffer owned by the caller; release with `cleanscope_buffer_free`
+#[repr(C)]
+#[derive(Debug)]
+pub struct CsBuffer {
+    /// First byte (never null, even when `len` is 0)
+    pub data: *mut u8,
+    /// Length in bytes
+    pub len: usize,
+}
+
+impl CsBuffer {
+    fn from_vec(data: Vec<u8>) -> Self {
+        let data = Box::into_raw(data.into_boxed_slice());
+        Self {
+            data: data.cast::<u8>(),
+            len: data.len(),
+        }
+    }
+}
+
+/// Frame validation verdict (see `frame_validation::ValidationResult`)
+#[repr(C)]
+#[derive(Debug, Default)]
+pub struct CsValidation {
+    /// Whether the frame passed validation
+    pub valid: bool,
+    /// Whether `avg_row_diff` was measured (Strict level only)
+    pub has_avg_row_diff: bool,
+    /// Average Y-channel difference between adjacent rows
+    pub avg_row_diff: f32,
+    /// Actual frame size in bytes
+    pub actual_size: usize,
+    /// Expected frame size in bytes
+    pub expected_size: usize,
+    /// Size ratio (actual / expected)
+    pub size_ratio: f32,
+    /// Whether stride alignment is correct
+    pub stride_aligned: bool,
+}
+
+/// Frame assembler handle with the last completed frame
+pub struct CsAssembler {
+    assembler: FrameAssembler,
+    frame: Option<Vec<u8>>,
+}
+
+/// Message describing the last error on this thread, or null
+///
+/// The string stays valid until the next failing call on the same thread.
+#[no_mangle]
+pub extern "C" fn cleanscope_last_error() -> *const c_char {
+    LAST_ERROR.with(|last| {
+        last.borrow()
+            .as_ref()
+            .map_or(std::ptr::null(), |message| message.as_ptr())
+    })
+}
+
+/// Release a buffer returned by this library
+///
+/// # Safety
+///
+/// `buffer` must come from this library and must not be freed twice.
+#[no_mangle]
+pub unsafe extern "C" fn cleanscope_buffer_free(buffer: CsBuffer) {
+    if !buffer.data.is_null() {
+        drop(Box::from_raw(std::ptr::slice_from_raw_parts_mut(
+            buffer.data,
+            buffer.len,
+        )));
+    }
+}
+
+/// Create a frame assembler
+///
+/// `mjpeg` selects end-of-frame detection for MJPEG; otherwise frames are
+/// cut at `expected_frame_size` bytes (0: detect the format from the data).
+/// Returns null only if Rust panicked.
+#[no_mangle]
+pub extern "C" fn cleanscope_assembler_new(
+    expected_frame_size: usize,
+    mjpeg: bool,
+) -> *mut CsAssembler {
+    catch_unwind(|| {
+        let assembler = if mjpeg {
+            FrameAssembler::new_mjpeg()
+        } else {
+            FrameAssembler::new(expected_frame_size)
+        };
+        Box::into_raw(Box::new(CsAssembler {
+            assembler,
+            frame: None,
+        }))
+    })
+    .unwrap_or(std::ptr::null_mut())
+}
+
+/// Treat packets as headerless payload (cameras with the `headerless` quirk)
+///
+/// # Safety
+///
+/// `assembler` must come from `cleanscope_assembler_new` and not be freed.
+#[no_mangle]
+pub unsafe extern "C" fn cleanscope_assembler_set_headerless(
+    assembler: *mut CsAssembler,
+    enabled: bool,
+) -> i32 {
+    guard(|| {
+        let assembler = assembler
+            .as_mut()
+            .ok_or_else(|| FfiError::null("assembler"))?;
+        assembler.assembler.set_headerless(enabled);
+        Ok(CS_OK)
+    })
+}
+
+/// Feed one USB payload packet (UVC header included)
+///
+/// Returns `CS_OK` while a frame is accumulating, `CS_FRAME_READY` when one
+/// completed (fetch it with `cleanscope_assembler_take_frame`) or `CS_SKIPPED`.
+///
+/// # Safety
+///
+/// `assembler` must come from `cleanscope_assembler_new` and not be freed;
+/// `data` must point to `len` readable bytes.
+#[no_mangle]
+pub unsafe extern "C" fn cleanscope_assembler_push(
+    assembler: *mut CsAssembler,
+    data: *const u8,
+    len: usize,
+) -> i32 {
+    guard(|| {
+        let assembler = assembler
+            .as_mut()
+            .ok_or_else(|| FfiError::null("assembler"))?;
+        let packet = input(data, len, "data")?;
+        Ok(match assembler.assembler.process_packet(packet) {
+            ProcessResult::Accumulating => CS_OK,
+            ProcessResult::Frame(frame) => {
+                assembler.frame = Some(frame);
+                CS_FRAME_READY
+            }
+            ProcessResult::Skipped => CS_SKIPPED,
+        })
+    })
+}
+
+/// Move the last completed frame into `out`
+///
+/// Fails with `CS_ERROR_INVALID_ARGUMENT` if no frame is ready.
+///
+/// # Safety
+///
+/// `assembler` must come from `cleanscope_assembler_new` and not be freed;
+/// `out` must be writable.
+#[no_mangle]
+pub unsafe extern "C" fn cleanscope_assembler_take_frame(
+    assembler: *mut CsAssembler,
+    out: *mut CsBuffer,
+) -> i32 {
+    guard(|| {
+        let assembler = assembler
+            .as_mut()
+            .ok_or_else(|| FfiError::null("assembler"))?;
+        let out = out.as_mut().ok_or_else(|| FfiError::null("out"))?;
+        let frame = assembler
+            .frame
+            .take()
+            .ok_or_else(|| FfiError::new(CS_ERROR_INVALID_ARGUMENT, "no frame is ready"))?;
+        *out = CsBuffer::from_vec(frame);
+        Ok(CS_OK)
+    })
+}
+
+/// Destroy an assembler (null is ignored)
+///
+/// # Safety
+///
+/// `assembler` must come from `cleanscope_assembler_new` and not be freed twice.
+#[no_mangle]
+pub unsafe extern "C" fn cleanscope_assembler_free(assembler: *mut CsAssembler) {
+    if !assembler.is_null() {
+        drop(Box::from_raw(assembler));
+    }
+}
+
+/// Convert an assembled frame to RGB24
+///
+/// `pixel_format` is a `CS_PIXEL_*` value; `stride` is the row stride in bytes
+/// for packed 4:2:2 formats (0: `width * 2`).
+///
+/// # Safety
+///
+/// `data` must point to `len` readable bytes; `out` must be writable.
+#[no_mangle]
+pub unsafe extern "C" fn cleanscope_convert_to_rgb(
+    data: *const u8,
+    len: usize,
+    width: u32,
+    height: u32,
+    stride: u32,
+    pixel_format: u32,
+    out: *mut CsBuffer,
+) -> i32 {
+    guard(|| {
+        let frame = input(data, len, "data")?;
+        let out = out.as_mut().ok_or_else(|| FfiError::null("out"))?;
+        let pixel_format = pixel_format_from_raw(pixel_format)?;
+        let stride = if stride == 0 { width * 2 } else { stride };
+        let rgb = convert_to_rgb(frame, width, height, stride, pixel_format)
+            .map_err(|e| FfiError::new(CS_ERROR_CONVERSION, e.to_string()))?;
+        *out = CsBuffer::from_vec(rgb);
+        Ok(CS_OK)
+    })
+}
+
+/// Check a YUY2 frame for banding and size artifacts
+///
+/// `level` is a `CS_VALIDATION_*` value.
+///
+/// # Safety
+///
+/// `data` must point to `len` readable bytes; `out` must be writable.
+#[no_mangle]
+pub unsafe extern "C" fn cleanscope_validate_yuy2(
+    data: *const u8,
+    len: usize,
+    width: u32,
+    height: u32,
+    expected_size: usize,
+    level: u32,
+    out: *mut CsValidation,
+) -> i32 {
+    guard(|| {
+        let frame = input(data, len, "data")?;
+        let out = out.as_mut().ok_or_else(|| FfiError::null("out"))?;
+        let level = validation_level_from_raw(level)?;
+        let result =
+            validate_yuy2_frame(frame, width as usize, height as usize, expected_size, level);
+        *out = CsValidation {
+            valid: result.valid,
+            has_avg_row_diff: result.avg_row_diff.is_some(),
+            avg_row_diff: result.avg_row_diff.unwrap_or(0.0),
+            actual_size: result.actual_size,
+            expected_size: result.expected_size,
+            size_ratio: result.size_ratio,
+            stride_aligned: result.stride_aligned,
+        };
+        Ok(CS_OK)
+    })
+}
+
+/// Replay a packet capture and return every frame it assembles
+///
+/// `path` is a capture written by the app (`packets.bin` or legacy layout).
+/// On success `*out_frames` holds `*out_count` buffers; release them with
+/// `cleanscope_frames_free`.
+///
+/// # Safety
+///
+/// `path` must be a NUL-terminated string; `out_frames` and `out_count` must
+/// be writable.
+#[no_mangle]
+pub unsafe extern "C" fn cleanscope_replay_capture(
+    path: *const c_char,
+    out_frames: *mut *mut CsBuffer,
+    out_count: *mut usize,
+) -> i32 {
+    guard(|| {
+        if path.is_null() {
+            return Err(FfiError::null("path"));
+        }
+        let out_frames = out_frames
+            .as_mut()
+            .ok_or_else(|| FfiError::null("out_frames"))?;
+        let out_count = out_count
+            .as_mut()
+            .ok_or_else(|| FfiError::null("out_count"))?;
+        let path = CStr::from_ptr(path)
+            .to_str()
+            .map_err(|_| FfiError::new(CS_ERROR_INVALID_ARGUMENT, "path is not UTF-8"))?;
+
+        let frames = replay_all_frames(Path::new(path))
+            .map_err(|e| FfiError::new(CS_ERROR_IO, e.to_string()))?;
+        let frames: Box<[CsBuffer]> = frames.into_iter().map(CsBuffer::from_vec).collect();
+        *out_count = frames.len();
+        *out_frames = Box::into_raw(frames).cast::<CsBuffer>();
+        Ok(CS_OK)
+    })
+}
+
+/// Release the frames returned by `cleanscope_replay_capture`
+///
+/// # Safety
+///
+/// `frames` and `count` must be exactly what `cleanscope_replay_capture`
+/// returned, and must not be freed twice.
+#[no_mangle]
+pub unsafe extern "C" fn cleanscope_frames_free(frames: *mut CsBuffer, count: usize) {
+    if frames.is_null() {
+        return;
+    }
+    let frames = Box::from_raw(std::ptr::slice_from_raw_parts_mut(frames, count));
+    for frame in frames.into_vec() {
+        cleanscope_buffer_free(frame);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::{PacketGenerator, Rgb};
+
+    fn empty_buffer() -> CsBuffer {
+        CsBuffer {
+            data: std::ptr::null_mut(),
+            len: 0,
+        }
+    }
+
+    #[test]
+    fn test_assemble_and_convert_yuy2_frame() {
+        let (width, height) = (16, 8);
+        // The assembler syncs on the first frame boundary, so send two frames
+        let mut gen = PacketGenerator::new(64);
+        let mut packets = gen.yuy2_solid_frame(width, height, Rgb::RED);
+        packets.extend(gen.yuy2_solid_frame(width, height, Rgb::RED));
+
+        unsafe {
+            let assembler = cleanscope_assembler_new((width * height * 2) as usize, false);
+            let mut ready = false;
+            for packet in &packets {
+                let status = cleanscope_assembler_push(assembler, packet.as_ptr(), packet.len());
+                assert!(status >= CS_OK);
+                ready |= status == CS_FRAME_READY;
+            }
+            assert!(ready, "no frame assembled");
+
+            let mut frame = empty_buffer();
+            assert_eq!(
+                cleanscope_assembler_take_frame(assembler, &mut frame),
+                CS_OK
+            );
+            assert_eq!(frame.len, (width * height * 2) as usize);
+
+            let mut validation = CsValidation::default();
+            let status = cleanscope_validate_yuy2(
+                frame.data,
+                frame.len,
+                width,
+                height,
+                frame.len,
+                1, // Moderate
+                &mut validation,
+            );
+            assert_eq!(status, CS_OK);
+            assert!(validation.valid);
+
+            let mut rgb = empty_buffer();
+            let status =
+                cleanscope_convert_to_rgb(frame.data, frame.len, width, height, 0, 0, &mut rgb);
+            assert_eq!(status, CS_OK);
+            assert_eq!(rgb.len, (width * height * 3) as usize);
+
+            cleanscope_buffer_free(rgb);
+            cleanscope_buffer_free(frame);
+            cleanscope_assembler_free(assembler);
+        }
+    }
+
+    #[test]
+    fn test_errors_set_last_error() {
+        unsafe {
+            let mut rgb = empty_buffer();
+            let status = cleanscope_convert_to_rgb(std::ptr::null(), 4, 2, 1, 0, 0, &mut rgb);
+            assert_eq!(status, CS_ERROR_NULL_POINTER);
+
+            let data = [0u8; 4];
+            let status = cleanscope_convert_to_rgb(data.as_ptr(), 4, 2, 1, 0, 99, &mut rgb);
+            assert_eq!(status, CS_ERROR_INVALID_ARGUMENT);
+            let message = CStr::from_ptr(cleanscope_last_error()).to_str().unwrap();
+            assert!(message.contains("pixel format"), "{}", message);
+
+            let assembler = cleanscope_assembler_new(0, true);
+            assert_eq!(
+                cleanscope_assembler_take_frame(assembler, &mut rgb),
+                CS_ERROR_INVALID_ARGUMENT
+            );
+            cleanscope_assembler_free(assembler);
+        }
+    }
+
+    #[test]
+    fn test_replay_missing_capture_is_io_error() {
+        let path = CString::new("/nonexistent/capture.bin").unwrap();
+        let mut frames = std::ptr::null_mut();
+        let mut count = 0;
+        let status = unsafe { cleanscope_replay_capture(path.as_ptr(), &mut frames, &mut count) };
+        assert_eq!(status, CS_ERROR_IO);
+        assert!(frames.is_null());
+    }
+}
@@ -0,0 +1,253 @@
+//! Registry of uncompressed pixel formats
+//!
+//! One [`FormatEntry`] per [`PixelFormat`]: its names, the UVC format GUIDs
+//! cameras report for it, its frame size and its converter to RGB888. Name
+//! parsing, [`PixelFormat::frame_size`], GUID lookup and
+//! [`convert_to_rgb`](crate::yuv_conversion::convert_to_rgb) all read from
+//! [`FORMATS`], so adding a format (e.g. RGB565 or Bayer `BA81`) means adding
+//! the `PixelFormat` variant, its converter and one entry here.
+//!
+//! Kept free of Tauri and platform dependencies like the rest of the
+//! conversion pipeline, so it is also built for `wasm32` (see `wasm/`).
+
+use crate::yuv_conversion::{
+    convert_bayer_rggb_to_rgb, convert_bgr888_to_rgb, convert_i420_to_rgb, convert_nv12_to_rgb,
+    convert_nv21_to_rgb, convert_yuv422_to_rgb, convert_yv12_to_rgb, pass_through_rgb888,
+    ConversionError, DemosaicMethod, YuvPackedFormat,
+};
+use crate::PixelFormat;
+
+/// Trailing 12 bytes shared by the FourCC-based format GUIDs
+/// (`XXXXXXXX-0000-0010-8000-00AA00389B71`)
+pub const FOURCC_GUID_SUFFIX: [u8; 12] = [
+    0x00, 0x00, 0x10, 0x00, 0x80, 0x00, 0x00, 0xAA, 0x00, 0x38, 0x9B, 0x71,
+];
+
+/// Format GUID for RGB24 (RGB888, R-G-B order)
+pub const RGB24_GUID: [u8; 16] = [
+    0x7d, 0xeb, 0x36, 0xe4, 0x4f, 0x52, 0xce, 0x11, 0x9f, 0x53, 0x00, 0x20, 0xaf, 0x0b, 0xa7, 0x70,
+];
+
+/// Format GUID some cameras report for BGR24 (BGR888, B-G-R order)
+pub const BGR24_GUID: [u8; 16] = [
+    0xe4, 0x36, 0xeb, 0x7d, 0x52, 0x4f, 0x11, 0xce, 0x9f, 0x53, 0x00, 0x20, 0xaf, 0x0b, 0xa7, 0x70,
+];
+
+/// Build the GUID of a FourCC-based format (e.g. `b"YUY2"`)
+pub const fn fourcc_guid(fourcc: [u8; 4]) -> [u8; 16] {
+    let mut guid = [0u8; 16];
+    let mut i = 0;
+    while i < 16 {
+        guid[i] = if i < 4 {
+            fourcc[i]
+        } else {
+            FOURCC_GUID_SUFFIX[i - 4]
+        };
+        i += 1;
+    }
+    guid
+}
+
+/// Converts a frame to RGB888 given `(data, width, height, stride)`
+///
+/// `stride` is the row length in bytes; converters of formats without row
+/// padding ignore it.
+pub type Converter = fn(&[u8], u32, u32, u32) -> Result<Vec<u8>, ConversionError>;
+
+/// Everything the pipeline needs to know about one pixel format
+#[derive(Debug)]
+pub struct FormatEntry {
+    /// The format
+    pub format: PixelFormat,
+    /// Display name (e.g. `"YUYV"`), also accepted when parsing
+    pub name: &'static str,
+    /// Other names accepted when parsing (e.g. `"YUY2"`)
+    pub aliases: &'static [&'static str],
+    /// UVC format GUIDs cameras report for the format
+    pub guids: &'static [[u8; 16]],
+    /// Average bits per pixel of an unpadded frame
+    pub bits_per_pixel: u8,
+    /// Conversion to RGB888
+    pub convert: Converter,
+}
+
+/// Registered formats, in `PixelFormat` declaration order
+///
+/// The order is also the order `cycle_pixel_format` steps through.
+pub static FORMATS: &[FormatEntry] = &[
+    FormatEntry {
+        format: PixelFormat::Yuyv,
+        name: "YUYV",
+        aliases: &["YUY2"],
+        guids: &[fourcc_guid(*b"YUY2")],
+        bits_per_pixel: 16,
+        convert: |data, width, height, stride| {
+            convert_yuv422_to_rgb(data, width, height, Some(stride), YuvPackedFormat::Yuyv)
+        },
+    },
+    FormatEntry {
+        format: PixelFormat::Uyvy,
+        name: "UYVY",
+        aliases: &[],
+        guids: &[fourcc_guid(*b"UYVY")],
+        bits_per_pixel: 16,
+        convert: |data, width, height, stride| {
+            convert_yuv422_to_rgb(data, width, height, Some(stride), YuvPackedFormat::Uyvy)
+        },
+    },
+    FormatEntry {
+        format: PixelFormat::Nv12,
+        name: "NV12",
+        aliases: &[],
+        guids: &[fourcc_guid(*b"NV12")],
+        bits_per_pixel: 12,
+        convert: |data, width, height, _| convert_nv12_to_rgb(data, width, height),
+    },
+    FormatEntry {
+        format: PixelFormat::I420,
+        name: "I420",
+        aliases: &[],
+        guids: &[fourcc_guid(*b"I420")],
+        bits_per_pixel: 12,
+        convert: |data, width, height, _| convert_i420_to_rgb(data, width, height),
+    },
+    FormatEntry {
+        format: PixelFormat::Rgb888,
+        name: "RGB24",
+        aliases: &["RGB888"],
+        guids: &[RGB24_GUID],
+        bits_per_pixel: 24,
+        convert: |data, width, height, _| pass_through_rgb888(data, width, height),
+    },
+    FormatEntry {
+        format: PixelFormat::Bgr888,
+        name: "BGR24",
+        aliases: &["BGR888"],
+        guids: &[BGR24_GUID],
+        bits_per_pixel: 24,
+        convert: |data, width, height, _| convert_bgr888_to_rgb(data, width, height),
+    },
+    FormatEntry {
+        format: PixelFormat::BayerRggb,
+        name: "RGGB",
+        aliases: &["BAYER", "BAYER_RGGB"],
+        guids: &[fourcc_guid(*b"RGGB")],
+        bits_per_pixel: 8,
+        convert: |data, width, height, _| {
+            convert_bayer_rggb_to_rgb(data, width, height, DemosaicMethod::Bilinear)
+        },
+    },
+    FormatEntry {
+        format: PixelFormat::Nv21,
+        name: "NV21",
+        aliases: &[],
+        guids: &[fourcc_guid(*b"NV21")],
+        bits_per_pixel: 12,
+        convert: |data, width, height, _| convert_nv21_to_rgb(data, width, height),
+    },
+    FormatEntry {
+        format: PixelFormat::Yv12,
+        name: "YV12",
+        aliases: &[],
+        guids: &[fourcc_guid(*b"YV12")],
+        bits_per_pixel: 12,
+        convert: |data, width, height, _| convert_yv12_to_rgb(data, width, height),
+    },
+];
+
+/// Registry entry of `format`
+pub fn entry(format: PixelFormat) -> &'static FormatEntry {
+    &FORMATS[format as usize]
+}
+
+/// Entry whose name or alias is `name`, case-insensitively
+pub fn by_name(name: &str) -> Option<&'static FormatEntry> {
+    FORMATS.iter().find(|e| {
+        e.name.eq_ignore_ascii_case(name) || e.aliases.iter().any(|a| a.eq_ignore_ascii_case(name))
+    })
+}
+
+/// Entry of the format a UVC format GUID identifies
+pub fn by_guid(guid: &[u8; 16]) -> Option<&'static FormatEntry> {
+    FORMATS.iter().find(|e| e.guids.contains(guid))
+}
+
+/// Entry of the format whose FourCC-based GUID is built from `fourcc`
+/// (e.g. `b"YUY2"`)
+pub fn by_fourcc(fourcc: [u8; 4]) -> Option<&'static FormatEntry> {
+    by_guid(&fourcc_guid(fourcc))
+}
+
+/// The format after `format` in registry order, wrapping around
+pub fn next_format(format: PixelFormat) -> PixelFormat {
+    FORMATS[(format as usize + 1) % FORMATS.len()].format
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_entries_are_in_declaration_order() {
+        for (i, entry) in FORMATS.iter().enumerate() {
+            assert_eq!(entry.format as usize, i, "{} is out of order", entry.name);
+        }
+    }
+
+    #[test]
+    fn test_lookups() {
+        assert_eq!(by_name("yuy2").map(|e| e.format), Some(PixelFormat::Yuyv));
+        assert_eq!(
+            by_name("rgb24").map(|e| e.format),
+            Some(PixelFormat::Rgb888)
+        );
+        assert_eq!(
+            by_name("bayer").map(|e| e.format),
+            Some(PixelFormat::BayerRggb)
+        );
+        assert!(by_name("H264").is_none());
+
+        assert_eq!(
+            by_fourcc(*b"NV12").map(|e| e.format),
+            Some(PixelFormat::Nv12)
+        );
+        assert_eq!(
+            by_fourcc(*b"RGGB").map(|e| e.format),
+            Some(PixelFormat::BayerRggb)
+        );
+        assert_eq!(
+            by_fourcc(*b"NV21").map(|e| e.format),
+            Some(PixelFormat::Nv21)
+        );
+        assert_eq!(by_name("yv12").map(|e| e.format), Some(PixelFormat::Yv12));
+        assert_eq!(
+            by_guid(&BGR24_GUID).map(|e| e.format),
+            Some(PixelFormat::Bgr888)
+        );
+        assert!(by_fourcc(*b"H264").is_none());
+    }
+
+    #[test]
+    fn test_next_format_visits_every_format() {
+        let mut format = PixelFormat::default();
+        let mut seen = Vec::new();
+        for _ in 0..FORMATS.len() {
+            seen.push(format);
+            format = next_format(format);
+        }
+        assert_eq!(format, PixelFormat::default());
+        assert!(FORMATS.iter().all(|e| seen.contains(&e.format)));
+    }
+
+    #[test]
+    fn test_converters_produce_rgb888() {
+        let (width, height) = (4u32, 2u32);
+        for entry in FORMATS {
+            let frame = vec![128u8; entry.format.frame_size(width, height)];
+            let stride = width * u32::from(entry.bits_per_pixel) / 8;
+            let rgb = (entry.convert)(&frame, width, height, stride)
+                .unwrap_or_else(|e| panic!("{} failed: {}", entry.name, e));
+            assert_eq!(rgb.len(), (width * height * 3) as usize, "{}", entry.name);
+        }
+    }
+}
@@ -18,6 +18,10 @@
 //! }
 //! ```
 
+use std::sync::Arc;
+
+use crate::frame_boundary::ContentSplitter;
+
 /// Common YUY2 frame sizes for auto-detection
 const FRAME_SIZES: &[(usize, &str)] = &[
     (320 * 240 * 2, "320x240"),
@@ -57,6 +61,36 @@ pub struct FrameAssembler {
     is_mjpeg: Option<bool>,
     /// Expected frame size for uncompressed video
     expected_frame_size: usize,
+    /// Join a lone header length byte with the next packet (see `set_stitch_split_headers`)
+    stitch_split_headers: bool,
+    /// Header length byte waiting for the rest of its header
+    pending_header_byte: Option<u8>,
+    /// Payloads carry no UVC headers (see `set_headerless`)
+    headerless: bool,
+    /// Content-based frame splitting (see `set_content_boundaries`)
+    content: Option<ContentSplitter>,
+    /// Where assembled and dropped frames are reported (see `set_observer`)
+    observer: Option<Arc<dyn AssemblyObserver>>,
+    /// Whether the observer was told about the frame in the buffer
+    frame_started: bool,
+    /// A payload of the frame in the buffer carried the still image bit
+    buffer_still: bool,
+    /// The last assembled frame carried the still image bit (see `last_frame_still`)
+    last_still: bool,
+}
+
+/// Receives assembly events from a [`FrameAssembler`] (see `set_observer`)
+///
+/// Implemented by the app's `stats::StreamStats`. A trait rather than the
+/// type itself keeps this module free of app modules and `std::time`, which
+/// the wasm build can't use.
+pub trait AssemblyObserver: Send + Sync + std::fmt::Debug {
+    /// The first payload of a new frame arrived
+    fn frame_started(&self);
+    /// A frame of `size` bytes was completed
+    fn frame_assembled(&self, size: usize);
+    /// A partial frame was discarded
+    fn frame_dropped(&self);
 }
 
 impl FrameAssembler {
@@ -72,6 +106,14 @@ impl FrameAssembler {
             synced: false,
             is_mjpeg: None,
             expected_frame_size,
+            stitch_split_headers: false,
+            pending_header_byte: None,
+            headerless: false,
+            content: None,
+            observer: None,
+            frame_started: false,
+            buffer_still: false,
+            last_still: false,
         }
     }
 
@@ -95,6 +137,63 @@ impl FrameAssembler {
         self.frame_buffer.clear();
         self.last_frame_id = None;
         self.synced = false;
+        self.pending_header_byte = None;
+        if let Some(content) = self.content.as_mut() {
+            content.reset();
+        }
+        self.frame_started = false;
+        self.buffer_still = false;
+        self.last_still = false;
+    }
+
+    /// Report started, assembled and dropped frames to `observer`
+    pub fn set_observer(&mut self, observer: Option<Arc<dyn AssemblyObserver>>) {
+        self.observer = observer;
+        self.frame_started = false;
+    }
+
+    /// Treat every payload as raw uncompressed data with no UVC header
+    ///
+    /// For cameras that stream raw YUY2 without per-payload headers. With no
+    /// FID or EOF flags to go on, frames are split purely by the expected
+    /// frame size, so the stream must start on a frame boundary; a lost
+    /// payload shifts every later frame.
+    pub fn set_headerless(&mut self, enabled: bool) {
+        self.headerless = enabled;
+        if enabled {
+            self.is_mjpeg = Some(false);
+        }
+    }
+
+    /// Split uncompressed frames where the image content jumps
+    ///
+    /// Last resort for cameras that neither toggle FID reliably nor send
+    /// frames of a consistent size (see [`crate::frame_boundary`]). FID
+    /// toggles are ignored and frames end where the row starting at an
+    /// offset differs sharply from the row above it. `row_stride` is the
+    /// length of one image row in bytes; `None` turns detection off. Data
+    /// before the first boundary found is discarded, and the expected frame
+    /// size still bounds how far to look: with no boundary in twice that,
+    /// one expected-size frame is split off instead.
+    pub fn set_content_boundaries(&mut self, row_stride: Option<usize>) {
+        self.content = row_stride.and_then(ContentSplitter::new);
+    }
+
+    /// Stitch UVC headers split across two payloads
+    ///
+    /// Some bulk-mode cameras occasionally end a transfer right after the
+    /// header length byte, so the header arrives as a 1-byte payload followed
+    /// by a payload starting with the flags byte. Without stitching, both
+    /// halves are misread as pixel data. When enabled, a 1-byte payload that
+    /// could be a header length is held back and joined with the next packet
+    /// if that forms a valid header. Off by default: a real 1-byte payload
+    /// followed by bytes that happen to look like header flags would then be
+    /// stripped as a header.
+    pub fn set_stitch_split_headers(&mut self, enabled: bool) {
+        self.stitch_split_headers = enabled;
+        if !enabled {
+            self.pending_header_byte = None;
+        }
     }
 
     /// Force sync state (for testing with known-good packet streams)
@@ -121,37 +220,96 @@ impl FrameAssembler {
         self.is_mjpeg
     }
 
+    /// Whether the payloads of the last assembled frame carried the still
+    /// image bit, i.e. it is a method 2 still rather than a video frame
+    pub fn last_frame_still(&self) -> bool {
+        self.last_still
+    }
+
     /// Process a single UVC payload packet
     ///
     /// Returns `ProcessResult::Frame(data)` when a complete frame is assembled.
     pub fn process_packet(&mut self, packet_data: &[u8]) -> ProcessResult {
+        let result = self.assemble_packet(packet_data);
+        if let Some(observer) = &self.observer {
+            if let ProcessResult::Frame(frame) = &result {
+                observer.frame_assembled(frame.len());
+                self.frame_started = false;
+            }
+            // Overflow bytes after a frame start the next one
+            if !self.frame_started && !self.frame_buffer.is_empty() {
+                observer.frame_started();
+                self.frame_started = true;
+            }
+        }
+        result
+    }
+
+    /// Process a packet without notifying the observer
+    fn assemble_packet(&mut self, packet_data: &[u8]) -> ProcessResult {
         if packet_data.is_empty() {
             return ProcessResult::Skipped;
         }
 
-        // Parse UVC header
-        let validated_header = validate_uvc_header(packet_data);
-        let header_len = validated_header.unwrap_or(0);
+        if self.headerless {
+            self.frame_buffer.extend_from_slice(packet_data);
+            return match self.check_yuy2_frame_complete() {
+                Some(frame) => {
+                    // Without headers there is no still image bit
+                    self.last_still = false;
+                    ProcessResult::Frame(frame)
+                }
+                None => ProcessResult::Accumulating,
+            };
+        }
+
+        if self.stitch_split_headers {
+            if let Some(prefix) = self.pending_header_byte.take() {
+                let mut stitched = Vec::with_capacity(packet_data.len() + 1);
+                stitched.push(prefix);
+                stitched.extend_from_slice(packet_data);
+                if validate_uvc_header(&stitched).is_some() {
+                    log::debug!(
+                        "Stitched UVC header split across packets ({} bytes)",
+                        prefix
+                    );
+                    return self.process_payload(&stitched);
+                }
+                // The byte was payload after all. A lone byte can only finish
+                // a frame that the next packet then can't also finish, unless a
+                // whole frame fits in one packet, so one result is enough.
+                let prefix_result = self.process_payload(&[prefix]);
+                let result = self.assemble_packet(packet_data);
+                return match prefix_result {
+                    ProcessResult::Frame(_) => prefix_result,
+                    _ => result,
+                };
+            }
+            if is_split_header_prefix(packet_data) {
+                self.pending_header_byte = Some(packet_data[0]);
+                return ProcessResult::Accumulating;
+            }
+        }
+
+        self.process_payload(packet_data)
+    }
 
-        // Extract flags from header (if present)
-        let (end_of_frame, frame_id, error) = if validated_header.is_some() {
-            let header_flags = packet_data[1];
-            (
-                (header_flags & 0x02) != 0, // EOF
-                (header_flags & 0x01) != 0, // FID
-                (header_flags & 0x40) != 0, // Error
-            )
+    /// Process a packet once any split header has been stitched back together
+    fn process_payload(&mut self, packet_data: &[u8]) -> ProcessResult {
+        let info = parse_uvc_payload(packet_data);
+        // No header - use last known FID
+        let frame_id = if info.has_header {
+            info.frame_id
         } else {
-            // No header - use last known FID
-            (false, self.last_frame_id.unwrap_or(false), false)
+            self.last_frame_id.unwrap_or(false)
         };
 
         // Handle UVC error flag
-        if error {
+        if info.error {
             let is_mjpeg = self.is_mjpeg.unwrap_or(false);
             if is_mjpeg {
                 log::warn!("UVC error in MJPEG packet - clearing buffer");
-                self.frame_buffer.clear();
+                self.discard_frame();
                 self.synced = false;
                 return ProcessResult::Skipped;
             }
@@ -173,41 +331,51 @@ impl FrameAssembler {
         }
 
         let is_mjpeg = self.is_mjpeg.unwrap_or(false);
+        // Content boundaries replace FID toggles, which these devices get wrong
+        let by_content = !is_mjpeg && self.content.is_some();
         let mut result = ProcessResult::Accumulating;
 
         // Handle FID toggle (frame boundary detection)
         if let Some(last_fid) = self.last_frame_id {
-            if frame_id != last_fid {
+            if frame_id != last_fid && !by_content {
                 // FID toggled - new frame is starting
                 if is_mjpeg {
                     result = self.handle_mjpeg_fid_toggle();
                 } else {
                     result = self.handle_yuy2_fid_toggle();
                 }
+                if matches!(result, ProcessResult::Frame(_)) {
+                    self.last_still = self.buffer_still;
+                }
+                // The toggling payload belongs to the next frame
+                self.buffer_still = false;
                 self.synced = true;
             }
         }
         self.last_frame_id = Some(frame_id);
 
         // Skip accumulation if not synced
-        if !self.synced {
+        if !self.synced && !by_content {
             return ProcessResult::Skipped;
         }
 
-        // Extract and accumulate payload
-        self.accumulate_payload(packet_data, header_len, validated_header.is_some());
+        // Accumulate payload
+        self.frame_buffer.extend_from_slice(info.payload);
+        self.buffer_still |= info.still_image;
 
         // Check for complete frame (format-specific)
-        if !is_mjpeg {
+        let frame = if !is_mjpeg {
             // YUY2: Size-based frame detection
-            if let Some(frame) = self.check_yuy2_frame_complete() {
-                return ProcessResult::Frame(frame);
-            }
-        } else if end_of_frame && !self.frame_buffer.is_empty() {
+            self.check_yuy2_frame_complete()
+        } else if info.end_of_frame && !self.frame_buffer.is_empty() {
             // MJPEG: EOF-based frame detection
-            if let Some(frame) = self.extract_mjpeg_frame() {
-                return ProcessResult::Frame(frame);
-            }
+            self.extract_mjpeg_frame()
+        } else {
+            None
+        };
+        if let Some(frame) = frame {
+            self.last_still = std::mem::take(&mut self.buffer_still);
+            return ProcessResult::Frame(frame);
         }
 
         result
@@ -227,7 +395,7 @@ impl FrameAssembler {
                 return ProcessResult::Frame(frame);
             }
         }
-        self.frame_buffer.clear();
+        self.discard_frame();
         ProcessResult::Accumulating
     }
 
@@ -261,26 +429,16 @@ impl FrameAssembler {
         ProcessResult::Accumulating
     }
 
-    /// Accumulate payload data into frame buffer
-    fn accumulate_payload(&mut self, packet_data: &[u8], header_len: usize, has_header: bool) {
-        if has_header {
-            if header_len <= packet_data.len() {
-                let payload = &packet_data[header_len..];
-                // Skip zero-filled payloads
-                if !(payload.len() > 8 && payload[0..8].iter().all(|&b| b == 0)) {
-                    self.frame_buffer.extend_from_slice(payload);
-                }
-            }
-        } else {
-            // Pure payload data - skip zero-filled packets
-            if !(packet_data.len() > 8 && packet_data[0..8].iter().all(|&b| b == 0)) {
-                self.frame_buffer.extend_from_slice(packet_data);
-            }
+    /// Check if YUY2 frame is complete based on size
+    fn check_yuy2_frame_complete(&mut self) -> Option<Vec<u8>> {
+        if let Some(content) = self.content.as_mut() {
+            return content.split(&mut self.frame_buffer, self.expected_frame_size);
         }
+        self.split_expected_size()
     }
 
-    /// Check if YUY2 frame is complete based on size
-    fn check_yuy2_frame_complete(&mut self) -> Option<Vec<u8>> {
+    /// Split off one frame of the expected size once the buffer holds it
+    fn split_expected_size(&mut self) -> Option<Vec<u8>> {
         let buffer_size = self.frame_buffer.len();
         let expected_size = self.expected_frame_size;
 
@@ -324,9 +482,82 @@ impl FrameAssembler {
             }
         }
 
-        self.frame_buffer.clear();
+        self.discard_frame();
         None
     }
+
+    /// Throw away the partial frame in the buffer
+    fn discard_frame(&mut self) {
+        if !self.frame_buffer.is_empty() {
+            if let Some(observer) = &self.observer {
+                observer.frame_dropped();
+            }
+        }
+        self.frame_buffer.clear();
+        self.frame_started = false;
+        self.buffer_still = false;
+    }
+}
+
+/// A single UVC payload packet split into header flags and payload bytes
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PayloadInfo<'a> {
+    /// Whether the packet started with a valid UVC payload header
+    pub has_header: bool,
+    /// Header length in bytes (0 if no header)
+    pub header_len: usize,
+    /// End of Frame flag (false if no header)
+    pub end_of_frame: bool,
+    /// Frame ID flag, toggles each frame (false if no header)
+    pub frame_id: bool,
+    /// Error flag (false if no header)
+    pub error: bool,
+    /// Still image flag: the payload belongs to a method 2 still (false if no header)
+    pub still_image: bool,
+    /// Payload bytes after the header; empty for zero-filled padding packets
+    pub payload: &'a [u8],
+}
+
+/// Parse a single UVC payload packet
+///
+/// Per USB Video Class 1.5 Section 2.4.3.3, every payload transfer starts with
+/// a header, regardless of format. The header is always stripped so header
+/// bytes never end up in pixel data. Packets without a recognizable header are
+/// treated as pure payload. Zero-filled payloads (padding some cameras send
+/// between frames) are returned as empty.
+pub fn parse_uvc_payload(packet_data: &[u8]) -> PayloadInfo<'_> {
+    let validated = validate_uvc_header(packet_data);
+    let header_len = validated.unwrap_or(0);
+
+    // Extract flags from header (if present)
+    let (end_of_frame, frame_id, error, still_image) = if validated.is_some() {
+        let header_flags = packet_data[1];
+        (
+            (header_flags & 0x02) != 0, // EOF
+            (header_flags & 0x01) != 0, // FID
+            (header_flags & 0x40) != 0, // Error
+            (header_flags & 0x20) != 0, // STI
+        )
+    } else {
+        (false, false, false, false)
+    };
+
+    let payload = &packet_data[header_len..];
+    let payload = if payload.len() > 8 && payload[0..8].iter().all(|&b| b == 0) {
+        &[]
+    } else {
+        payload
+    };
+
+    PayloadInfo {
+        has_header: validated.is_some(),
+        header_len,
+        end_of_frame,
+        frame_id,
+        error,
+        still_image,
+        payload,
+    }
 }
 
 /// Validate UVC header and return header length if valid
@@ -360,6 +591,15 @@ pub fn validate_uvc_header(data: &[u8]) -> Option<usize> {
     Some(header_len)
 }
 
+/// Check if a payload could be the first byte of a header split across packets
+///
+/// A lone byte within the valid header length range (2-12) may be a header
+/// length whose flags and remaining bytes arrive in the next payload.
+#[inline]
+pub fn is_split_header_prefix(data: &[u8]) -> bool {
+    data.len() == 1 && (2..=12).contains(&data[0])
+}
+
 /// Check if data starts with JPEG SOI marker (0xFFD8)
 ///
 /// JPEG images always begin with the Start Of Image marker: 0xFF 0xD8.
@@ -369,6 +609,68 @@ pub fn is_jpeg_data(data: &[u8]) -> bool {
     data.len() >= 2 && data[0] == 0xFF && data[1] == 0xD8
 }
 
+/// Read the image dimensions from a JPEG's SOF (Start Of Frame) header
+///
+/// Walks the marker segments up to the first `SOFn` marker without decoding
+/// anything, so it is cheap enough to run on every MJPEG frame. Returns
+/// `(width, height)`, or `None` if the data is not a JPEG, the headers are
+/// truncated, or the scan starts before a frame header was found.
+pub fn jpeg_dimensions(data: &[u8]) -> Option<(u32, u32)> {
+    if !is_jpeg_data(data) {
+        return None;
+    }
+
+    let mut pos = 2;
+    loop {
+        if *data.get(pos)? != 0xFF {
+            return None;
+        }
+        let marker = *data.get(pos + 1)?;
+        match marker {
+            // Fill byte before a marker
+            0xFF => {
+                pos += 1;
+                continue;
+            }
+            // Standalone markers without a length field (TEM, RSTn)
+            0x01 | 0xD0..=0xD7 => {
+                pos += 2;
+                continue;
+            }
+            // Start of scan or end of image: no frame header before the image data
+            0xDA | 0xD9 => return None,
+            _ => {}
+        }
+
+        let segment = data.get(pos + 2..)?;
+        let length = usize::from(u16::from_be_bytes([*segment.first()?, *segment.get(1)?]));
+        if length < 2 {
+            return None;
+        }
+
+        // SOF0..SOF15, except DHT (C4), JPG (C8) and DAC (CC)
+        if matches!(marker, 0xC0..=0xCF) && !matches!(marker, 0xC4 | 0xC8 | 0xCC) {
+            // length(2) precision(1) height(2) width(2)
+            let header = segment.get(..7)?;
+            let height = u32::from(u16::from_be_bytes([header[3], header[4]]));
+            let width = u32::from(u16::from_be_bytes([header[5], header[6]]));
+            return (width > 0 && height > 0).then_some((width, height));
+        }
+
+        pos += 2 + length;
+    }
+}
+
+/// Check if a JPEG frame is complete: its headers parse and it ends with EOI
+///
+/// Trailing zero bytes after the End Of Image marker (0xFFD9) are allowed,
+/// since some cameras pad frames to a fixed transfer size. A frame that lost
+/// its last payloads has parseable headers but no EOI.
+pub fn is_complete_jpeg(data: &[u8]) -> bool {
+    let end = data.iter().rposition(|&b| b != 0x00).map_or(0, |i| i + 1);
+    jpeg_dimensions(data).is_some() && data[..end].ends_with(&[0xFF, 0xD9])
+}
+
 /// Round a byte count to the nearest standard YUY2 frame size
 pub fn round_to_yuy2_frame_size(actual_size: usize) -> usize {
     let mut best_match = actual_size;
@@ -488,6 +790,30 @@ mod tests {
         assert_eq!(validate_uvc_header(&data), None);
     }
 
+    #[test]
+    fn test_reject_truncated_pts_header() {
+        // Header split after the PTS flag: claims 6 bytes, only 3 arrived
+        let data = [0x06, 0x84, 0x11];
+        assert_eq!(validate_uvc_header(&data), None);
+    }
+
+    #[test]
+    fn test_reject_header_missing_length_byte() {
+        // Second half of a split header: starts with the flags byte
+        let data = [0x83, 0xAB, 0xCD, 0xEF];
+        assert_eq!(validate_uvc_header(&data), None);
+    }
+
+    #[test]
+    fn test_split_header_prefix() {
+        assert!(is_split_header_prefix(&[0x02]));
+        assert!(is_split_header_prefix(&[0x0C]));
+        assert!(!is_split_header_prefix(&[0x01]));
+        assert!(!is_split_header_prefix(&[0x0D]));
+        assert!(!is_split_header_prefix(&[0x02, 0x80]));
+        assert!(!is_split_header_prefix(&[]));
+    }
+
     #[test]
     fn test_yuy2_false_positive_protection() {
         // YUY2 data that might look like a header
@@ -498,6 +824,75 @@ mod tests {
         assert_eq!(validate_uvc_header(&data), Some(8));
     }
 
+    // =========================================================================
+    // Payload Parsing Tests
+    // =========================================================================
+
+    #[test]
+    fn test_parse_payload_with_header() {
+        let data = [0x02, 0x83, 0xFF, 0xD8, 0x01];
+        let info = parse_uvc_payload(&data);
+        assert!(info.has_header);
+        assert_eq!(info.header_len, 2);
+        assert!(info.end_of_frame);
+        assert!(info.frame_id);
+        assert!(!info.error);
+        assert_eq!(info.payload, &[0xFF, 0xD8, 0x01]);
+    }
+
+    #[test]
+    fn test_parse_payload_strips_pts_scr_header() {
+        let mut data = vec![0x0C, 0x8C, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10];
+        data.extend_from_slice(&[0xAA, 0xBB]);
+        let info = parse_uvc_payload(&data);
+        assert_eq!(info.header_len, 12);
+        assert!(!info.end_of_frame);
+        assert!(!info.frame_id);
+        assert_eq!(info.payload, &[0xAA, 0xBB]);
+    }
+
+    #[test]
+    fn test_parse_payload_error_flag() {
+        let info = parse_uvc_payload(&[0x02, 0xC0, 0x11]);
+        assert!(info.error);
+        assert_eq!(info.payload, &[0x11]);
+    }
+
+    #[test]
+    fn test_parse_payload_still_image_flag() {
+        let info = parse_uvc_payload(&[0x02, 0xA2, 0x11]);
+        assert!(info.still_image && info.end_of_frame);
+        assert!(!parse_uvc_payload(&[0x02, 0x82, 0x11]).still_image);
+    }
+
+    #[test]
+    fn test_parse_payload_without_header() {
+        let data = [0x10, 0x20, 0x30];
+        let info = parse_uvc_payload(&data);
+        assert!(!info.has_header);
+        assert_eq!(info.header_len, 0);
+        assert!(!info.end_of_frame && !info.frame_id && !info.error);
+        assert_eq!(info.payload, &data);
+    }
+
+    #[test]
+    fn test_parse_payload_header_only() {
+        let info = parse_uvc_payload(&[0x02, 0x82]);
+        assert!(info.has_header && info.end_of_frame);
+        assert!(info.payload.is_empty());
+    }
+
+    #[test]
+    fn test_parse_payload_skips_zero_fill() {
+        let mut data = vec![0x02, 0x80];
+        data.extend_from_slice(&[0u8; 16]);
+        assert!(parse_uvc_payload(&data).payload.is_empty());
+        assert!(parse_uvc_payload(&[0u8; 16]).payload.is_empty());
+
+        // Short zero runs are kept (could be real pixel data)
+        assert_eq!(parse_uvc_payload(&[0x02, 0x80, 0, 0]).payload, &[0, 0]);
+    }
+
     // =========================================================================
     // JPEG Detection Tests
     // =========================================================================
@@ -519,6 +914,72 @@ mod tests {
         assert!(!is_jpeg_data(&[0x80, 0x80])); // Random data
     }
 
+    /// SOI followed by an APP0 segment, then `sof` and an SOS marker
+    fn jpeg_with_sof(sof: &[u8]) -> Vec<u8> {
+        let mut jpeg = vec![0xFF, 0xD8];
+        jpeg.extend_from_slice(&[0xFF, 0xE0, 0x00, 0x04, 0x00, 0x00]);
+        jpeg.extend_from_slice(sof);
+        jpeg.extend_from_slice(&[0xFF, 0xDA, 0x00, 0x02]);
+        jpeg
+    }
+
+    #[test]
+    fn test_jpeg_dimensions_baseline() {
+        let sof0 = [
+            0xFF, 0xC0, 0x00, 0x0B, 0x08, 0x02, 0xD0, 0x05, 0x00, 0x01, 0x01,
+        ];
+        assert_eq!(jpeg_dimensions(&jpeg_with_sof(&sof0)), Some((1280, 720)));
+    }
+
+    #[test]
+    fn test_jpeg_dimensions_progressive_after_fill_bytes() {
+        let sof2 = [
+            0xFF, 0xFF, 0xC2, 0x00, 0x0B, 0x08, 0x01, 0xE0, 0x02, 0x80, 0x01,
+        ];
+        assert_eq!(jpeg_dimensions(&jpeg_with_sof(&sof2)), Some((640, 480)));
+    }
+
+    #[test]
+    fn test_jpeg_dimensions_skips_dht() {
+        // DHT (C4) shares the SOF marker range but is not a frame header
+        let mut segments = vec![0xFF, 0xC4, 0x00, 0x04, 0x00, 0x00];
+        segments.extend_from_slice(&[0xFF, 0xC0, 0x00, 0x0B, 0x08, 0x00, 0x08, 0x00, 0x10, 0x01]);
+        assert_eq!(jpeg_dimensions(&jpeg_with_sof(&segments)), Some((16, 8)));
+    }
+
+    #[test]
+    fn test_jpeg_dimensions_invalid() {
+        assert_eq!(jpeg_dimensions(&[]), None);
+        assert_eq!(jpeg_dimensions(&[0x00, 0x00, 0x00, 0x00]), None);
+        // No SOF before the scan
+        assert_eq!(jpeg_dimensions(&jpeg_with_sof(&[])), None);
+        // Truncated inside the SOF header
+        assert_eq!(
+            jpeg_dimensions(&[0xFF, 0xD8, 0xFF, 0xC0, 0x00, 0x11, 0x08]),
+            None
+        );
+        // Segment length pointing past the end of the data
+        assert_eq!(jpeg_dimensions(&[0xFF, 0xD8, 0xFF, 0xE0, 0x10, 0x00]), None);
+    }
+
+    #[test]
+    fn test_is_complete_jpeg_needs_eoi() {
+        let sof0 = [
+            0xFF, 0xC0, 0x00, 0x0B, 0x08, 0x02, 0xD0, 0x05, 0x00, 0x01, 0x01,
+        ];
+        let truncated = jpeg_with_sof(&sof0);
+        assert!(!is_complete_jpeg(&truncated));
+
+        let mut complete = truncated.clone();
+        complete.extend_from_slice(&[0x12, 0x34, 0xFF, 0xD9]);
+        assert!(is_complete_jpeg(&complete));
+        // Zero padding after EOI
+        complete.extend_from_slice(&[0x00; 6]);
+        assert!(is_complete_jpeg(&complete));
+        // EOI without a frame header
+        assert!(!is_complete_jpeg(&[0xFF, 0xD8, 0xFF, 0xD9]));
+    }
+
     // =========================================================================
     // FrameAssembler Tests
     // =========================================================================
@@ -564,6 +1025,17 @@ mod tests {
         assert_eq!(assembler.last_frame_id, None);
     }
 
+    #[test]
+    fn test_reset_clears_pending_header() {
+        let mut assembler = FrameAssembler::new(1024);
+        assembler.set_stitch_split_headers(true);
+        assembler.process_packet(&[0x02]);
+        assert_eq!(assembler.pending_header_byte, Some(0x02));
+
+        assembler.reset();
+        assert_eq!(assembler.pending_header_byte, None);
+    }
+
     #[test]
     fn test_round_to_yuy2_frame_size_exact() {
         assert_eq!(round_to_yuy2_frame_size(640 * 480 * 2), 640 * 480 * 2);
@@ -700,6 +1172,28 @@ mod integration_tests {
         assert_eq!(frames[0][len - 1], 0xD9, "Missing JPEG EOI marker (D9)");
     }
 
+    #[test]
+    fn test_still_image_bit_marks_the_frame() {
+        let mut assembler = FrameAssembler::new_mjpeg();
+        assembler.force_sync();
+
+        // Video frame, still (STI on its payloads), video frame
+        let frames = [
+            [[0x02, 0x80, 0xFF, 0xD8], [0x02, 0x82, 0xFF, 0xD9]],
+            [[0x02, 0xA1, 0xFF, 0xD8], [0x02, 0xA3, 0xFF, 0xD9]],
+            [[0x02, 0x80, 0xFF, 0xD8], [0x02, 0x82, 0xFF, 0xD9]],
+        ];
+        let mut stills = Vec::new();
+        for packets in &frames {
+            for packet in packets {
+                if let ProcessResult::Frame(_) = assembler.process_packet(packet) {
+                    stills.push(assembler.last_frame_still());
+                }
+            }
+        }
+        assert_eq!(stills, vec![false, true, false]);
+    }
+
     #[test]
     fn test_gradient_frame_pixel_verification() {
         let mut gen = PacketGenerator::new(2048);
@@ -809,4 +1303,207 @@ mod integration_tests {
         let result = assembler.process_packet(&error_packet);
         assert_eq!(result, ProcessResult::Skipped);
     }
+
+    /// Split the header of every `nth` packet after its length byte
+    fn split_headers(packets: &[Vec<u8>], nth: usize) -> Vec<Vec<u8>> {
+        let mut split = Vec::new();
+        for (i, packet) in packets.iter().enumerate() {
+            if i % nth == 0 {
+                split.push(packet[..1].to_vec());
+                split.push(packet[1..].to_vec());
+            } else {
+                split.push(packet.clone());
+            }
+        }
+        split
+    }
+
+    fn assemble(assembler: &mut FrameAssembler, packets: &[Vec<u8>]) -> Vec<Vec<u8>> {
+        packets
+            .iter()
+            .filter_map(|p| match assembler.process_packet(p) {
+                ProcessResult::Frame(frame) => Some(frame),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_split_headers_corrupt_frames_without_stitching() {
+        let mut gen = PacketGenerator::new(1024);
+        let frame = gen.generate_yuy2_vertical_gradient(64, 64);
+        let packets = split_headers(&gen.yuy2_vertical_gradient_frame(64, 64), 3);
+
+        let mut assembler = FrameAssembler::new_yuy2(64, 64);
+        assembler.force_sync();
+        let frames = assemble(&mut assembler, &packets);
+
+        assert!(
+            !frames.contains(&frame),
+            "split header bytes should leak into pixel data"
+        );
+    }
+
+    #[test]
+    fn test_split_headers_stitched() {
+        let mut gen = PacketGenerator::new(1024);
+        let frame = gen.generate_yuy2_vertical_gradient(64, 64);
+        let mut packets = Vec::new();
+        for _ in 0..3 {
+            packets.extend(split_headers(&gen.yuy2_vertical_gradient_frame(64, 64), 3));
+        }
+
+        let mut assembler = FrameAssembler::new_yuy2(64, 64);
+        assembler.set_stitch_split_headers(true);
+        let frames = assemble(&mut assembler, &packets);
+
+        // First frame is lost while syncing on the FID toggle
+        assert_eq!(frames.len(), 2);
+        assert!(frames.iter().all(|f| *f == frame));
+    }
+
+    #[test]
+    fn test_stitching_keeps_real_one_byte_payloads() {
+        let mut assembler = FrameAssembler::new_yuy2(64, 64);
+        assembler.set_stitch_split_headers(true);
+        assembler.force_sync();
+
+        // A lone byte followed by a packet with its own valid header
+        assert_eq!(
+            assembler.process_packet(&[0x05]),
+            ProcessResult::Accumulating
+        );
+        assert_eq!(assembler.buffer_len(), 0);
+        assembler.process_packet(&[0x02, 0x80, 0xAA, 0xBB]);
+
+        assert_eq!(assembler.frame_buffer, [0x05, 0xAA, 0xBB]);
+    }
+
+    #[test]
+    fn test_headerless_frames_split_by_size() {
+        let gen = PacketGenerator::new(1000);
+        let frames: Vec<Vec<u8>> = [Rgb::RED, Rgb::GREEN, Rgb::BLUE]
+            .iter()
+            .map(|&c| gen.generate_yuy2_solid(64, 64, c))
+            .collect();
+        // Raw bytes cut into transfers that ignore frame boundaries
+        let stream = frames.concat();
+
+        let mut assembler = FrameAssembler::new_yuy2(64, 64);
+        assembler.set_headerless(true);
+        let packets: Vec<Vec<u8>> = stream.chunks(1000).map(<[u8]>::to_vec).collect();
+        let assembled = assemble(&mut assembler, &packets);
+
+        assert_eq!(assembled, frames);
+    }
+
+    #[test]
+    fn test_headerless_keeps_header_like_bytes() {
+        let mut assembler = FrameAssembler::new(8);
+        assembler.set_headerless(true);
+
+        // Would parse as a 2-byte header in normal mode
+        assembler.process_packet(&[0x02, 0x80, 0x10, 0x20]);
+        let result = assembler.process_packet(&[0x02, 0x83, 0x30, 0x40]);
+
+        assert_eq!(
+            result,
+            ProcessResult::Frame(vec![0x02, 0x80, 0x10, 0x20, 0x02, 0x83, 0x30, 0x40])
+        );
+    }
+    #[test]
+    fn test_content_boundaries_split_varying_sizes() {
+        use crate::test_utils::{FidBehavior, HeaderStyle, QuirkProfile, SimulatedCamera};
+
+        let gen = PacketGenerator::default();
+        let frames: Vec<Vec<u8>> = [48, 44, 40, 48, 46, 42]
+            .iter()
+            .map(|&h| gen.generate_yuy2_vertical_gradient(64, h))
+            .collect();
+        // FID never toggles and no frame has the expected size twice in a row
+        let mut camera = SimulatedCamera::new(
+            QuirkProfile {
+                max_payload_size: 1000,
+                header_style: HeaderStyle::Minimal,
+                fid_behavior: FidBehavior::Constant,
+                ..Default::default()
+            },
+            0,
+        );
+        let packets: Vec<Vec<u8>> = frames
+            .iter()
+            .flat_map(|f| camera.packetize(f).packets)
+            .collect();
+
+        let mut assembler = FrameAssembler::new_yuy2(64, 48);
+        assembler.set_content_boundaries(Some(64 * 2));
+        let assembled = assemble(&mut assembler, &packets);
+
+        // The first frame syncs the detector; the last one has no successor
+        assert_eq!(assembled, frames[1..5]);
+    }
+
+    #[test]
+    fn test_content_boundaries_fall_back_to_size() {
+        let gen = PacketGenerator::default();
+        let frame = gen.generate_yuy2_solid(64, 48, Rgb::GRAY);
+        let stream = frame.repeat(3);
+
+        let mut assembler = FrameAssembler::new_yuy2(64, 48);
+        assembler.set_headerless(true);
+        assembler.set_content_boundaries(Some(64 * 2));
+        let packets: Vec<Vec<u8>> = stream.chunks(1000).map(<[u8]>::to_vec).collect();
+        let assembled = assemble(&mut assembler, &packets);
+
+        // Flat frames hide every boundary, so size decides
+        assert_eq!(assembled.first(), Some(&frame));
+    }
+
+    #[test]
+    fn test_observer_sees_frames_and_drops() {
+        #[derive(Debug, Default)]
+        struct Events(std::sync::Mutex<Vec<String>>);
+        impl AssemblyObserver for Events {
+            fn frame_started(&self) {
+                self.0.lock().unwrap().push("start".to_string());
+            }
+            fn frame_assembled(&self, size: usize) {
+                self.0.lock().unwrap().push(format!("frame {}", size));
+            }
+            fn frame_dropped(&self) {
+                self.0.lock().unwrap().push("drop".to_string());
+            }
+        }
+
+        let events = Arc::new(Events::default());
+        let mut gen = PacketGenerator::new(512);
+        let mut assembler = FrameAssembler::new_mjpeg();
+        assembler.set_observer(Some(Arc::clone(&events) as Arc<dyn AssemblyObserver>));
+        assembler.force_sync();
+
+        let frames = assemble(&mut assembler, &gen.mjpeg_solid_frame(8, 8, Rgb::BLUE));
+        // A frame without a JPEG SOI marker is dropped at EOF
+        assemble(
+            &mut assembler,
+            &[vec![0x02, 0x80, 0x12, 0x34], vec![0x02, 0x82, 0x56]],
+        );
+
+        // The small frame fits in one packet, so it was never in progress
+        let expected = vec![
+            format!("frame {}", frames[0].len()),
+            "start".to_string(),
+            "drop".to_string(),
+        ];
+        assert_eq!(*events.0.lock().unwrap(), expected);
+    }
+
+    #[test]
+    fn test_zero_row_stride_disables_content_boundaries() {
+        let mut assembler = FrameAssembler::new_yuy2(64, 48);
+        assert!(assembler.content.is_none());
+        assembler.set_content_boundaries(Some(64 * 2));
+        assert!(assembler.content.is_some());
+        assembler.set_content_boundaries(Some(0));
+        assert!(assembler.content.is_none());
+    }
 }
@@ -0,0 +1,269 @@
+//! Content-based frame boundary detection for uncompressed streams
+//!
+//! Last resort for cameras whose frame ID never toggles reliably and whose
+//! frame sizes drift (lost payloads, variable blanking), so neither FID nor
+//! size can split the byte stream. Camera images are vertically smooth: each
+//! row is close to the row above it. Where one frame ends and the next
+//! begins, the bottom row of one image sits directly above the top row of the
+//! next, and the two usually look nothing alike - the stream's equivalent of
+//! vertical blanking.
+//!
+//! [`find_frame_boundary`] scores every candidate offset by the mean
+//! difference between the row starting there and the row before it, and
+//! reports a boundary where that score stands far above the stream's typical
+//! row-to-row difference. A lost payload inside a frame doesn't upset the
+//! score: bytes one stride apart are still vertical neighbors.
+//!
+//! This needs the top and bottom of the image to differ, which holds for
+//! endoscope footage (dark vignetted edges around a lit center on most
+//! scopes) but not for a flat scene, where no boundary is found. Enabled per
+//! device with the `content-boundaries` quirk (see [`crate::quirks`]).
+//! [`ContentSplitter`] applies it to a growing frame buffer; the frame
+//! assembler and the isochronous stream both split frames with it.
+
+/// Candidate boundaries are multiples of this many bytes (one YUY2 pixel)
+const BOUNDARY_ALIGN: usize = 2;
+
+/// How many times the typical row difference a boundary must reach
+const MIN_CONTRAST: u64 = 4;
+
+/// Minimum mean byte difference across a boundary, so near-flat images never split
+const MIN_DISCONTINUITY: u64 = 24;
+
+/// Difference between the byte at `p` and the byte one row above it
+fn row_diff(data: &[u8], stride: usize, p: usize) -> u64 {
+    u64::from(data[p].abs_diff(data[p - stride]))
+}
+
+/// Find the first frame boundary in `data` at or after offset `from`
+///
+/// `stride` is the length of one image row in bytes. Returns the offset of
+/// the first byte of the new frame, or `None` if no offset stands out (the
+/// data holds no boundary, or the image content hides it).
+pub fn find_frame_boundary(data: &[u8], stride: usize, from: usize) -> Option<usize> {
+    if stride == 0 || data.len() < 2 * stride {
+        return None;
+    }
+
+    // Typical row-to-row difference over the whole buffer
+    let total: u64 = (stride..data.len())
+        .map(|p| row_diff(data, stride, p))
+        .sum();
+    let baseline = total / (data.len() - stride) as u64;
+    let threshold = (baseline * MIN_CONTRAST).max(MIN_DISCONTINUITY) * stride as u64;
+
+    // A boundary at b compares the row [b, b + stride) with the row above it
+    let first = from.max(stride).next_multiple_of(BOUNDARY_ALIGN);
+    let last = data.len() - stride;
+    if first > last {
+        return None;
+    }
+
+    let mut window: u64 = (first..first + stride)
+        .map(|p| row_diff(data, stride, p))
+        .sum();
+    let mut candidate: Option<(usize, u64)> = None;
+    let mut b = first;
+    loop {
+        match candidate {
+            // Windows overlapping a boundary score high too; the boundary
+            // itself is the peak within one row of the first hit
+            Some((start, _)) if b > start + stride => break,
+            Some((_, best)) if window > best => candidate = Some((b, window)),
+            None if window >= threshold => candidate = Some((b, window)),
+            _ => {}
+        }
+
+        let next = b + BOUNDARY_ALIGN;
+        if next > last {
+            break;
+        }
+        for p in b..next {
+            window -= row_diff(data, stride, p);
+            window += row_diff(data, stride, p + stride);
+        }
+        b = next;
+    }
+
+    candidate.map(|(offset, _)| offset)
+}
+
+/// Splits frames off the front of a growing buffer at content boundaries
+///
+/// Keeps what has to survive between payloads: whether the buffer starts at
+/// a boundary yet, and how long it has to grow before the next scan.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ContentSplitter {
+    /// Length of one image row in bytes
+    stride: usize,
+    /// Whether the buffer starts at a frame boundary found by content
+    synced: bool,
+    /// Buffer length to reach before the next scan
+    next_scan: usize,
+}
+
+impl ContentSplitter {
+    /// Splitter for rows of `stride` bytes (`None` if `stride` is 0)
+    pub fn new(stride: usize) -> Option<Self> {
+        (stride > 0).then_some(Self {
+            stride,
+            synced: false,
+            next_scan: 0,
+        })
+    }
+
+    /// Look for a boundary again from the start of the next data
+    pub fn reset(&mut self) {
+        self.synced = false;
+        self.next_scan = 0;
+    }
+
+    /// Split one frame off the front of `buffer`
+    ///
+    /// Data before the first boundary found is discarded. The expected frame
+    /// size bounds how far to look: with no boundary in twice that, one
+    /// expected-size frame is split off instead.
+    pub fn split(&mut self, buffer: &mut Vec<u8>, expected_size: usize) -> Option<Vec<u8>> {
+        if expected_size == 0 {
+            return None;
+        }
+
+        loop {
+            // Scan once a buffer can hold a long frame plus the next one's top
+            let scan_at = self.next_scan.max(expected_size + expected_size / 4);
+            let buffer_size = buffer.len();
+            if buffer_size < scan_at {
+                return None;
+            }
+
+            // Frames vary in size, but not by half
+            let from = if self.synced { expected_size / 2 } else { 0 };
+            match find_frame_boundary(buffer, self.stride, from) {
+                Some(boundary) if self.synced => {
+                    log::debug!(
+                        "Complete YUY2 frame: {} bytes (trigger: content boundary)",
+                        boundary
+                    );
+                    self.next_scan = 0;
+                    return Some(buffer.drain(..boundary).collect());
+                }
+                Some(boundary) => {
+                    log::info!(
+                        "Synced to content frame boundary, discarding {} bytes",
+                        boundary
+                    );
+                    buffer.drain(..boundary);
+                    self.synced = true;
+                    self.next_scan = 0;
+                }
+                None if buffer_size >= 2 * expected_size => {
+                    log::debug!(
+                        "No content frame boundary found, splitting {} bytes by size",
+                        expected_size
+                    );
+                    self.next_scan = 0;
+                    return Some(buffer.drain(..expected_size).collect());
+                }
+                None => {
+                    self.next_scan = buffer_size + expected_size / 4;
+                    return None;
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::PacketGenerator;
+
+    const WIDTH: u32 = 64;
+    const STRIDE: usize = WIDTH as usize * 2;
+
+    /// Gradient frames (dark top, bright bottom) with the given heights, back to back
+    fn stream(heights: &[u32]) -> Vec<u8> {
+        let gen = PacketGenerator::default();
+        heights
+            .iter()
+            .flat_map(|&h| gen.generate_yuy2_vertical_gradient(WIDTH, h))
+            .collect()
+    }
+
+    #[test]
+    fn test_finds_boundary_between_frames() {
+        let data = stream(&[48, 48]);
+        assert_eq!(find_frame_boundary(&data, STRIDE, 0), Some(48 * STRIDE));
+    }
+
+    #[test]
+    fn test_boundary_of_short_frame() {
+        let data = stream(&[37, 48]);
+        assert_eq!(find_frame_boundary(&data, STRIDE, 0), Some(37 * STRIDE));
+    }
+
+    #[test]
+    fn test_search_starts_at_offset() {
+        let data = stream(&[40, 40, 40]);
+        assert_eq!(find_frame_boundary(&data, STRIDE, 0), Some(40 * STRIDE));
+        // Past the first row of a frame, the next boundary is the next frame's
+        assert_eq!(
+            find_frame_boundary(&data, STRIDE, 41 * STRIDE),
+            Some(80 * STRIDE)
+        );
+        assert_eq!(find_frame_boundary(&data, STRIDE, 81 * STRIDE), None);
+    }
+
+    #[test]
+    fn test_boundary_after_lost_bytes() {
+        // A payload lost mid-frame shifts pixels but keeps rows comparable
+        let mut data = stream(&[48, 48]);
+        data.drain(10 * STRIDE + 6..10 * STRIDE + 500);
+        let lost = 494;
+        assert_eq!(
+            find_frame_boundary(&data, STRIDE, 0),
+            Some(48 * STRIDE - lost)
+        );
+    }
+
+    #[test]
+    fn test_flat_content_has_no_boundary() {
+        let gen = PacketGenerator::default();
+        let frame = gen.generate_yuy2_solid(WIDTH, 48, crate::test_utils::Rgb::GRAY);
+        let data = frame.repeat(3);
+        assert_eq!(find_frame_boundary(&data, STRIDE, 0), None);
+    }
+
+    #[test]
+    fn test_splitter_syncs_then_splits() {
+        let mut buffer = stream(&[30, 48, 40, 48]);
+        let mut splitter = ContentSplitter::new(STRIDE).unwrap();
+        let expected_size = 48 * STRIDE;
+
+        // The partial first frame is discarded
+        let frame = splitter.split(&mut buffer, expected_size).unwrap();
+        assert_eq!(frame.len(), 48 * STRIDE);
+        let frame = splitter.split(&mut buffer, expected_size).unwrap();
+        assert_eq!(frame.len(), 40 * STRIDE);
+        // The last frame has no boundary after it yet
+        assert_eq!(splitter.split(&mut buffer, expected_size), None);
+        assert_eq!(buffer.len(), 48 * STRIDE);
+    }
+
+    #[test]
+    fn test_splitter_falls_back_to_size() {
+        let gen = PacketGenerator::default();
+        let frame = gen.generate_yuy2_solid(WIDTH, 48, crate::test_utils::Rgb::GRAY);
+        let mut buffer = frame.repeat(2);
+        let mut splitter = ContentSplitter::new(STRIDE).unwrap();
+        assert_eq!(splitter.split(&mut buffer, frame.len()), Some(frame));
+        assert_eq!(ContentSplitter::new(0), None);
+    }
+
+    #[test]
+    fn test_short_input() {
+        assert_eq!(find_frame_boundary(&[], STRIDE, 0), None);
+        assert_eq!(find_frame_boundary(&[0; 100], 0, 0), None);
+        assert_eq!(find_frame_boundary(&[0; STRIDE], STRIDE, 0), None);
+    }
+}
@@ -0,0 +1,508 @@
+//! Multi-consumer frame broadcast
+//!
+//! Assembled frames are published once into a fixed-size ring, and every
+//! consumer (display, recorder, stats, a future frame server) reads them
+//! through its own [`FrameCursor`] at its own pace. Frames are shared as
+//! `Arc<Vec<u8>>`: the producer's buffer moves in without a copy, consumers
+//! (and the `FrameBuffer` they store into) share it, and the ring bounds
+//! memory: a consumer that falls more than `capacity` frames behind skips to
+//! the oldest frame still in the ring (counted in [`FrameCursor::lagged`])
+//! instead of holding up the producer or the other consumers.
+//!
+//! The ring is bounded but [`FrameSender::send`] never blocks: the USB
+//! callbacks can't wait for a slow consumer, so when a consumer's backlog is
+//! full the oldest frame is dropped for it. [`ChannelMetrics`] records how
+//! full consumers let the ring get and how many frames they lost this way
+//! (each one a send a blocking bounded channel would have stalled on); the
+//! USB streams share theirs with `get_stream_stats`.
+//!
+//! Producers publish what they found out assembling a frame with it
+//! ([`FrameSender::send_with_notes`]): the validation verdict, so consumers
+//! reading with [`FrameCursor::recv_with_notes_timeout`] don't check the frame
+//! again, and whether it is a UVC method 2 still.
+//!
+//! Receiving mirrors `std::sync::mpsc`: [`FrameCursor::recv_timeout`] returns
+//! the same [`RecvTimeoutError`], and reports `Disconnected` once every
+//! [`FrameSender`] is gone and the cursor has read everything left.
+//!
+//! # Example
+//!
+//! ```rust,ignore
+//! use clean_scope_lib::frame_broadcast;
+//! use std::time::Duration;
+//!
+//! let (sender, mut display) = frame_broadcast::channel(4);
+//! let mut recorder = display.clone();
+//!
+//! sender.send(vec![0xFF, 0xD8, 0xFF, 0xD9]);
+//! let a = display.recv_timeout(Duration::from_millis(10)).unwrap();
+//! let b = recorder.recv_timeout(Duration::from_millis(10)).unwrap();
+//! assert!(std::sync::Arc::ptr_eq(&a, &b));
+//! ```
+
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::mpsc::{RecvTimeoutError, TryRecvError};
+use std::sync::{Arc, Condvar, Mutex};
+use std::time::{Duration, Instant};
+
+use crate::frame_validation::ValidationResult;
+
+/// A published frame, shared by every consumer
+pub type Frame = Arc<Vec<u8>>;
+
+/// What the producer found out about a frame while assembling it
+#[derive(Debug, Clone, Default)]
+pub struct FrameNotes {
+    /// Validation done before publishing (`None` if the frame wasn't checked)
+    pub validation: Option<Arc<ValidationResult>>,
+    /// The frame's payloads carried the still image bit (a method 2 still)
+    pub still_image: bool,
+}
+
+/// A published frame with the producer's notes on it
+#[derive(Debug, Clone)]
+pub struct PublishedFrame {
+    /// The frame
+    pub data: Frame,
+    /// What the producer found out about it
+    pub notes: FrameNotes,
+}
+
+/// Frames kept for consumers by default (about a quarter second at 30 fps)
+pub const DEFAULT_CAPACITY: usize = 8;
+
+/// Largest configurable capacity (about two seconds at 30 fps)
+pub const MAX_CAPACITY: usize = 64;
+
+/// Environment variable overriding [`DEFAULT_CAPACITY`] for the USB streams
+pub const CAPACITY_ENV: &str = "CLEANSCOPE_FRAME_CHANNEL_CAPACITY";
+
+/// Limit a requested capacity to `1..=MAX_CAPACITY`
+pub fn clamp_capacity(capacity: usize) -> usize {
+    capacity.clamp(1, MAX_CAPACITY)
+}
+
+/// Capacity from [`CAPACITY_ENV`], if set to a number
+pub fn capacity_from_env() -> Option<usize> {
+    let value = std::env::var(CAPACITY_ENV).ok()?;
+    match value.trim().parse() {
+        Ok(n) => Some(clamp_capacity(n)),
+        Err(_) => {
+            log::warn!("Ignoring invalid {}={:?}", CAPACITY_ENV, value);
+            None
+        }
+    }
+}
+
+/// Backlog and overflow counters of a broadcast, shared with its creator
+///
+/// Counters are updated by the cursors as they read, so frames a consumer
+/// lost show up once it gets to them.
+#[derive(Debug, Default)]
+pub struct ChannelMetrics {
+    capacity: AtomicUsize,
+    high_water_mark: AtomicUsize,
+    overflows: AtomicU64,
+}
+
+impl ChannelMetrics {
+    /// Clear the counters (the capacity is kept)
+    pub fn reset(&self) {
+        self.high_water_mark.store(0, Ordering::Relaxed);
+        self.overflows.store(0, Ordering::Relaxed);
+    }
+
+    /// Frames the ring holds (0 before a broadcast was created)
+    pub fn capacity(&self) -> usize {
+        self.capacity.load(Ordering::Relaxed)
+    }
+
+    /// Most frames any consumer had waiting when it read one
+    ///
+    /// Reaching the capacity means that consumer's backlog was full.
+    pub fn high_water_mark(&self) -> usize {
+        self.high_water_mark.load(Ordering::Relaxed)
+    }
+
+    /// Frames consumers lost because the ring was full when they were published
+    pub fn overflows(&self) -> u64 {
+        self.overflows.load(Ordering::Relaxed)
+    }
+}
+
+/// Published frames still available to consumers
+struct Ring {
+    /// Frames in publish order, tagged with their sequence number
+    frames: VecDeque<(u64, PublishedFrame)>,
+    /// Sequence number the next published frame gets
+    next_seq: u64,
+    /// All senders dropped
+    closed: bool,
+}
+
+struct Shared {
+    ring: Mutex<Ring>,
+    /// Signalled on every publish and on close
+    published: Condvar,
+    capacity: usize,
+    senders: AtomicUsize,
+    metrics: Arc<ChannelMetrics>,
+}
+
+/// Create a broadcast keeping the last `capacity` frames, with one cursor
+///
+/// More cursors come from [`FrameSender::subscribe`] or cloning a cursor.
+pub fn channel(capacity: usize) -> (FrameSender, FrameCursor) {
+    channel_with_metrics(capacity, Arc::new(ChannelMetrics::default()))
+}
+
+/// Like [`channel`], recording backlog and overflows in `metrics`
+///
+/// The metrics are reset and take the new broadcast's capacity.
+pub fn channel_with_metrics(
+    capacity: usize,
+    metrics: Arc<ChannelMetrics>,
+) -> (FrameSender, FrameCursor) {
+    metrics.reset();
+    metrics.capacity.store(capacity.max(1), Ordering::Relaxed);
+    let shared = Arc::new(Shared {
+        ring: Mutex::new(Ring {
+            frames: VecDeque::with_capacity(capacity.max(1)),
+            next_seq: 0,
+            closed: false,
+        }),
+        published: Condvar::new(),
+        capacity: capacity.max(1),
+        senders: AtomicUsize::new(1),
+        metrics,
+    });
+    let cursor = FrameCursor {
+        shared: Arc::clone(&shared),
+        next_seq: 0,
+        lagged: 0,
+    };
+    (FrameSender { shared }, cursor)
+}
+
+/// Publishing end of a frame broadcast
+///
+/// Cloneable, like an mpsc sender; the broadcast closes when the last clone
+/// is dropped.
+pub struct FrameSender {
+    shared: Arc<Shared>,
+}
+
+impl FrameSender {
+    /// Publish a frame to every cursor, evicting the oldest if the ring is full
+    ///
+    /// Never blocks on consumers.
+    pub fn send(&self, frame: impl Into<Frame>) {
+        self.send_with_notes(frame, FrameNotes::default());
+    }
+
+    /// Publish a frame together with what the producer found out about it
+    pub fn send_with_notes(&self, frame: impl Into<Frame>, notes: FrameNotes) {
+        let frame = PublishedFrame {
+            data: frame.into(),
+            notes,
+        };
+        let mut ring = crate::lock_or_recover(&self.shared.ring);
+        if ring.frames.len() == self.shared.capacity {
+            ring.frames.pop_front();
+        }
+        let seq = ring.next_seq;
+        ring.frames.push_back((seq, frame));
+        ring.next_seq += 1;
+        drop(ring);
+        self.shared.published.notify_all();
+    }
+
+    /// New cursor starting at the next published frame
+    pub fn subscribe(&self) -> FrameCursor {
+        let next_seq = crate::lock_or_recover(&self.shared.ring).next_seq;
+        FrameCursor {
+            shared: Arc::clone(&self.shared),
+            next_seq,
+            lagged: 0,
+        }
+    }
+}
+
+impl Clone for FrameSender {
+    fn clone(&self) -> Self {
+        self.shared.senders.fetch_add(1, Ordering::Relaxed);
+        Self {
+            shared: Arc::clone(&self.shared),
+        }
+    }
+}
+
+impl Drop for FrameSender {
+    fn drop(&mut self) {
+        if self.shared.senders.fetch_sub(1, Ordering::AcqRel) == 1 {
+            crate::lock_or_recover(&self.shared.ring).closed = true;
+            self.shared.published.notify_all();
+        }
+    }
+}
+
+/// One consumer's read position in a frame broadcast
+///
+/// Cloning gives an independent cursor at the same position.
+#[derive(Clone)]
+pub struct FrameCursor {
+    shared: Arc<Shared>,
+    /// Sequence number of the next frame to return
+    next_seq: u64,
+    lagged: u64,
+}
+
+impl FrameCursor {
+    /// Next frame, if one has been published since the last call
+    ///
+    /// # Errors
+    ///
+    /// Returns `Empty` if there is no new frame yet, or `Disconnected` once
+    /// all senders are gone and every remaining frame has been read.
+    pub fn try_recv(&mut self) -> Result<Frame, TryRecvError> {
+        let shared = Arc::clone(&self.shared);
+        let ring = crate::lock_or_recover(&shared.ring);
+        match self.take(&ring) {
+            Some(frame) => Ok(frame.data),
+            None if ring.closed => Err(TryRecvError::Disconnected),
+            None => Err(TryRecvError::Empty),
+        }
+    }
+
+    /// Next frame, waiting up to `timeout` for one to be published
+    ///
+    /// # Errors
+    ///
+    /// Returns `Timeout` if no frame arrives in time, or `Disconnected` once
+    /// all senders are gone and every remaining frame has been read.
+    pub fn recv_timeout(&mut self, timeout: Duration) -> Result<Frame, RecvTimeoutError> {
+        self.recv_with_notes_timeout(timeout)
+            .map(|frame| frame.data)
+    }
+
+    /// Like [`recv_timeout`](Self::recv_timeout), with the producer's notes
+    ///
+    /// # Errors
+    ///
+    /// Same as [`recv_timeout`](Self::recv_timeout).
+    pub fn recv_with_notes_timeout(
+        &mut self,
+        timeout: Duration,
+    ) -> Result<PublishedFrame, RecvTimeoutError> {
+        let deadline = Instant::now() + timeout;
+        let shared = Arc::clone(&self.shared);
+        let mut ring = crate::lock_or_recover(&shared.ring);
+        loop {
+            if let Some(frame) = self.take(&ring) {
+                return Ok(frame);
+            }
+            if ring.closed {
+                return Err(RecvTimeoutError::Disconnected);
+            }
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            if remaining.is_zero() {
+                return Err(RecvTimeoutError::Timeout);
+            }
+            ring = shared
+                .published
+                .wait_timeout(ring, remaining)
+                .unwrap_or_else(std::sync::PoisonError::into_inner)
+                .0;
+        }
+    }
+
+    /// Frames this cursor skipped because it fell behind the ring
+    pub fn lagged(&self) -> u64 {
+        self.lagged
+    }
+
+    /// Frames published but not yet read by this cursor (at most the capacity)
+    pub fn pending(&self) -> usize {
+        let ring = crate::lock_or_recover(&self.shared.ring);
+        (ring.next_seq.saturating_sub(self.next_seq) as usize).min(ring.frames.len())
+    }
+
+    /// Take the next frame for this cursor, skipping ahead if it was evicted
+    fn take(&mut self, ring: &Ring) -> Option<PublishedFrame> {
+        let (oldest, _) = ring.frames.front()?;
+        let metrics = &self.shared.metrics;
+        let backlog = ring.next_seq.saturating_sub(self.next_seq) as usize;
+        metrics
+            .high_water_mark
+            .fetch_max(backlog.min(ring.frames.len()), Ordering::Relaxed);
+        if self.next_seq < *oldest {
+            let skipped = oldest - self.next_seq;
+            self.lagged += skipped;
+            metrics.overflows.fetch_add(skipped, Ordering::Relaxed);
+            self.next_seq = *oldest;
+        }
+        let index = (self.next_seq - oldest) as usize;
+        let (_, frame) = ring.frames.get(index)?;
+        self.next_seq += 1;
+        Some(frame.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const WAIT: Duration = Duration::from_millis(20);
+
+    #[test]
+    fn test_every_cursor_sees_every_frame() {
+        let (sender, mut a) = channel(4);
+        let mut b = sender.subscribe();
+        for i in 0..3u8 {
+            sender.send(vec![i]);
+        }
+        for i in 0..3u8 {
+            let fa = a.recv_timeout(WAIT).unwrap();
+            let fb = b.recv_timeout(WAIT).unwrap();
+            assert_eq!(&*fa, &[i]);
+            // Shared, not copied per consumer
+            assert!(Arc::ptr_eq(&fa, &fb));
+        }
+        assert_eq!(a.recv_timeout(WAIT), Err(RecvTimeoutError::Timeout));
+        assert_eq!(b.try_recv(), Err(TryRecvError::Empty));
+    }
+
+    #[test]
+    fn test_slow_cursor_skips_to_oldest() {
+        let (sender, mut slow) = channel(3);
+        let mut fast = sender.subscribe();
+        for i in 0..5u8 {
+            sender.send(vec![i]);
+            assert_eq!(&*fast.try_recv().unwrap(), &[i]);
+        }
+
+        // Frames 0 and 1 were evicted before the slow cursor got to them
+        assert_eq!(slow.pending(), 3);
+        assert_eq!(&*slow.try_recv().unwrap(), &[2]);
+        assert_eq!(slow.lagged(), 2);
+        assert_eq!(fast.lagged(), 0);
+    }
+
+    #[test]
+    fn test_metrics_track_backlog_and_overflows() {
+        let metrics = Arc::new(ChannelMetrics::default());
+        let (sender, mut slow) = channel_with_metrics(3, Arc::clone(&metrics));
+        let mut fast = sender.subscribe();
+        assert_eq!(metrics.capacity(), 3);
+
+        sender.send(vec![0]);
+        sender.send(vec![1]);
+        fast.try_recv().unwrap();
+        fast.try_recv().unwrap();
+        assert_eq!(metrics.high_water_mark(), 2);
+        assert_eq!(metrics.overflows(), 0);
+
+        for i in 2..6u8 {
+            sender.send(vec![i]);
+            fast.try_recv().unwrap();
+        }
+        // The slow cursor's backlog filled the ring and it lost frames 0-2
+        assert_eq!(&*slow.try_recv().unwrap(), &[3]);
+        assert_eq!(metrics.high_water_mark(), 3);
+        assert_eq!(metrics.overflows(), 3);
+
+        // A new broadcast starts from zero
+        let _ = channel_with_metrics(5, Arc::clone(&metrics));
+        assert_eq!(
+            (
+                metrics.capacity(),
+                metrics.high_water_mark(),
+                metrics.overflows()
+            ),
+            (5, 0, 0)
+        );
+    }
+
+    #[test]
+    fn test_clamp_capacity() {
+        assert_eq!(clamp_capacity(0), 1);
+        assert_eq!(clamp_capacity(16), 16);
+        assert_eq!(clamp_capacity(1000), MAX_CAPACITY);
+    }
+
+    #[test]
+    fn test_subscribe_starts_at_next_frame() {
+        let (sender, mut first) = channel(4);
+        sender.send(vec![1]);
+        let mut late = sender.subscribe();
+        sender.send(vec![2]);
+
+        assert_eq!(&*first.try_recv().unwrap(), &[1]);
+        assert_eq!(&*late.try_recv().unwrap(), &[2]);
+
+        // A clone continues from the original's position
+        let mut copy = first.clone();
+        assert_eq!(&*copy.try_recv().unwrap(), &[2]);
+        assert_eq!(&*first.try_recv().unwrap(), &[2]);
+    }
+
+    #[test]
+    fn test_notes_travel_with_the_frame() {
+        use crate::frame_validation::{validate_yuy2_frame, ValidationLevel};
+
+        let (sender, mut cursor) = channel(4);
+        let verdict = validate_yuy2_frame(&[0; 8], 2, 2, 8, ValidationLevel::Minimal);
+        sender.send_with_notes(
+            vec![0; 8],
+            FrameNotes {
+                validation: Some(Arc::new(verdict)),
+                still_image: true,
+            },
+        );
+        sender.send(vec![1]);
+
+        let noted = cursor.recv_with_notes_timeout(WAIT).unwrap();
+        assert!(noted.notes.validation.unwrap().valid);
+        assert!(noted.notes.still_image);
+        let plain = cursor.recv_with_notes_timeout(WAIT).unwrap();
+        assert_eq!(&*plain.data, &[1]);
+        assert!(plain.notes.validation.is_none());
+        assert!(!plain.notes.still_image);
+    }
+
+    #[test]
+    fn test_disconnect_after_draining() {
+        let (sender, mut cursor) = channel(4);
+        let second = sender.clone();
+        sender.send(vec![1]);
+        drop(sender);
+        // One sender left: still connected
+        assert_eq!(cursor.try_recv().as_deref(), Ok(&vec![1u8]));
+        assert_eq!(cursor.try_recv(), Err(TryRecvError::Empty));
+
+        second.send(vec![2]);
+        drop(second);
+        assert_eq!(cursor.recv_timeout(WAIT).as_deref(), Ok(&vec![2u8]));
+        assert_eq!(
+            cursor.recv_timeout(WAIT),
+            Err(RecvTimeoutError::Disconnected)
+        );
+    }
+
+    #[test]
+    fn test_recv_wakes_on_publish() {
+        let (sender, mut cursor) = channel(2);
+        let producer = std::thread::spawn(move || {
+            std::thread::sleep(Duration::from_millis(10));
+            sender.send(vec![9]);
+        });
+        let frame = cursor.recv_timeout(Duration::from_secs(5)).unwrap();
+        assert_eq!(&*frame, &[9]);
+        producer.join().unwrap();
+        assert_eq!(
+            cursor.recv_timeout(WAIT),
+            Err(RecvTimeoutError::Disconnected)
+        );
+    }
+}
@@ -0,0 +1,185 @@
+//! Cache of converted frames, keyed by frame sequence
+//!
+//! The frontend may request the same frame several times (an eager render
+//! loop, a snapshot right after a preview), and converting a frame (JPEG
+//! decode, RGB to PNG) costs far more than copying it. The cache keeps the
+//! converted outputs of the current frame so repeated requests reuse them.
+//!
+//! Only the latest frame is ever requested, so inserting a conversion for a
+//! new sequence number drops everything cached for older frames. Memory use
+//! is bounded by the number of distinct conversions of one frame.
+
+use std::sync::Arc;
+
+use crate::image_encoder::ImageFormat;
+
+/// Most conversions kept for one frame
+pub const FRAME_CACHE_CAPACITY: usize = 4;
+
+/// Output a frame was converted to
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Conversion {
+    /// Decoded to RGB24
+    Rgb,
+    /// Encoded as a still image
+    Image(ImageFormat),
+}
+
+/// A cached conversion
+#[derive(Debug)]
+struct Entry {
+    conversion: Conversion,
+    data: Arc<Vec<u8>>,
+}
+
+/// Converted outputs of the current frame
+#[derive(Debug, Default)]
+pub struct FrameCache {
+    /// Sequence number the entries belong to
+    sequence: u64,
+    /// Conversions, least recently used first
+    entries: Vec<Entry>,
+    /// Requests answered from the cache
+    hits: u64,
+    /// Requests that needed a conversion
+    misses: u64,
+}
+
+impl FrameCache {
+    /// Empty cache
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Cached output of `conversion` for frame `sequence`
+    pub fn get(&mut self, sequence: u64, conversion: Conversion) -> Option<Arc<Vec<u8>>> {
+        if sequence != self.sequence {
+            return None;
+        }
+        let pos = self
+            .entries
+            .iter()
+            .position(|e| e.conversion == conversion)?;
+        let entry = self.entries.remove(pos);
+        let data = Arc::clone(&entry.data);
+        self.entries.push(entry);
+        self.hits += 1;
+        Some(data)
+    }
+
+    /// Cache the output of `conversion` for frame `sequence`
+    ///
+    /// Drops entries for other frames, and the least recently used entry if
+    /// the cache is full.
+    pub fn insert(&mut self, sequence: u64, conversion: Conversion, data: Vec<u8>) -> Arc<Vec<u8>> {
+        if sequence != self.sequence {
+            self.entries.clear();
+            self.sequence = sequence;
+        }
+        self.entries.retain(|e| e.conversion != conversion);
+        if self.entries.len() >= FRAME_CACHE_CAPACITY {
+            self.entries.remove(0);
+        }
+        let data = Arc::new(data);
+        self.entries.push(Entry {
+            conversion,
+            data: Arc::clone(&data),
+        });
+        data
+    }
+
+    /// Cached output, or the result of `convert` (cached on success)
+    ///
+    /// # Errors
+    ///
+    /// Returns the error from `convert`; nothing is cached then.
+    pub fn get_or_try_insert_with<E>(
+        &mut self,
+        sequence: u64,
+        conversion: Conversion,
+        convert: impl FnOnce() -> Result<Vec<u8>, E>,
+    ) -> Result<Arc<Vec<u8>>, E> {
+        if let Some(data) = self.get(sequence, conversion) {
+            return Ok(data);
+        }
+        self.misses += 1;
+        Ok(self.insert(sequence, conversion, convert()?))
+    }
+
+    /// Requests answered from the cache and requests that needed a conversion
+    pub fn stats(&self) -> (u64, u64) {
+        (self.hits, self.misses)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn convert(calls: &mut u32, value: u8) -> Result<Vec<u8>, ()> {
+        *calls += 1;
+        Ok(vec![value])
+    }
+
+    #[test]
+    fn test_repeated_requests_convert_once() {
+        let mut cache = FrameCache::new();
+        let mut calls = 0;
+
+        for _ in 0..3 {
+            let data = cache
+                .get_or_try_insert_with(1, Conversion::Rgb, || convert(&mut calls, 7))
+                .unwrap();
+            assert_eq!(*data, [7]);
+        }
+        assert_eq!(calls, 1);
+        assert_eq!(cache.stats(), (2, 1));
+    }
+
+    #[test]
+    fn test_new_frame_invalidates() {
+        let mut cache = FrameCache::new();
+        cache.insert(1, Conversion::Rgb, vec![1]);
+        cache.insert(1, Conversion::Image(ImageFormat::Png), vec![2]);
+
+        cache.insert(2, Conversion::Rgb, vec![3]);
+        assert!(cache.get(1, Conversion::Rgb).is_none());
+        assert!(cache.get(2, Conversion::Image(ImageFormat::Png)).is_none());
+        assert_eq!(*cache.get(2, Conversion::Rgb).unwrap(), [3]);
+    }
+
+    #[test]
+    fn test_capacity_evicts_least_recently_used() {
+        let mut cache = FrameCache::new();
+        let formats = [
+            ImageFormat::Jpeg,
+            ImageFormat::Png,
+            ImageFormat::WebP,
+            ImageFormat::Avif,
+        ];
+        for (i, &format) in formats.iter().enumerate() {
+            cache.insert(5, Conversion::Image(format), vec![i as u8]);
+        }
+        // Touch JPEG so PNG becomes the oldest
+        assert!(cache.get(5, Conversion::Image(ImageFormat::Jpeg)).is_some());
+        cache.insert(5, Conversion::Rgb, vec![9]);
+
+        assert!(cache.get(5, Conversion::Image(ImageFormat::Png)).is_none());
+        assert!(cache.get(5, Conversion::Image(ImageFormat::Jpeg)).is_some());
+        assert!(cache.get(5, Conversion::Rgb).is_some());
+    }
+
+    #[test]
+    fn test_failed_conversion_is_not_cached() {
+        let mut cache = FrameCache::new();
+        let result: Result<_, &str> =
+            cache.get_or_try_insert_with(1, Conversion::Rgb, || Err("decode failed"));
+        assert!(result.is_err());
+
+        let mut calls = 0;
+        cache
+            .get_or_try_insert_with(1, Conversion::Rgb, || convert(&mut calls, 1))
+            .unwrap();
+        assert_eq!(calls, 1);
+    }
+}
@@ -0,0 +1,258 @@
+//! Push-based frame delivery over Tauri IPC channels
+//!
+//! Polling `get_frame` after every `frame-ready` event costs a round trip per
+//! frame. Instead the frontend can pass a `Channel` to `subscribe_frames`:
+//! every stored frame is then pushed to it as one binary message, arriving in
+//! JavaScript as an `ArrayBuffer` without Base64 encoding.
+//!
+//! Delivery is credit based: a channel gets one frame, then no more until the
+//! frontend acknowledges it with `ack_frame` ([`FrameStream::ack`]). Frames
+//! stored in between are not queued, so a slow webview only ever sees the
+//! latest frame instead of a backlog.
+//!
+//! Message layout (little-endian): a [`FRAME_HEADER_LEN`]-byte header
+//! `[u64 sequence][u32 width][u32 height][u8 format][3 bytes reserved]`
+//! followed by the frame data, where format is [`FORMAT_RGB`] (RGB24) or
+//! [`FORMAT_JPEG`].
+
+use std::sync::Mutex;
+
+use tauri::ipc::{Channel, InvokeResponseBody};
+
+use crate::FrameInfo;
+
+/// Size of the header in front of every frame message
+pub const FRAME_HEADER_LEN: usize = 20;
+
+/// Header format byte: raw RGB24 pixels
+pub const FORMAT_RGB: u8 = 0;
+
+/// Header format byte: JPEG data
+pub const FORMAT_JPEG: u8 = 1;
+
+/// Encode a frame as a channel message
+#[must_use]
+pub fn encode_frame(info: &FrameInfo, data: &[u8]) -> Vec<u8> {
+    let format = if info.format == "jpeg" {
+        FORMAT_JPEG
+    } else {
+        FORMAT_RGB
+    };
+    let mut message = Vec::with_capacity(FRAME_HEADER_LEN + data.len());
+    message.extend_from_slice(&info.sequence.to_le_bytes());
+    message.extend_from_slice(&info.width.to_le_bytes());
+    message.extend_from_slice(&info.height.to_le_bytes());
+    message.extend_from_slice(&[format, 0, 0, 0]);
+    message.extend_from_slice(data);
+    message
+}
+
+/// A subscribed channel
+struct Subscriber {
+    channel: Channel<InvokeResponseBody>,
+    /// The frontend is done with the last frame sent and can take another
+    ready: bool,
+}
+
+#[derive(Default)]
+struct Subscribers {
+    channels: Vec<Subscriber>,
+    /// Sequence of the last frame sent, so a frame is never sent twice
+    last_sequence: u64,
+}
+
+/// Frontend channels that receive every new frame
+#[derive(Default)]
+pub struct FrameStream {
+    subscribers: Mutex<Subscribers>,
+}
+
+impl FrameStream {
+    /// Create a stream without subscribers
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Start sending frames to `channel`; returns its id for [`Self::unsubscribe`]
+    pub fn subscribe(&self, channel: Channel<InvokeResponseBody>) -> u32 {
+        let id = channel.id();
+        crate::lock_or_recover(&self.subscribers)
+            .channels
+            .push(Subscriber {
+                channel,
+                ready: true,
+            });
+        log::info!("Frame channel {} subscribed", id);
+        id
+    }
+
+    /// Stop sending frames to the channel with `id`; returns whether it was subscribed
+    pub fn unsubscribe(&self, id: u32) -> bool {
+        let mut subscribers = crate::lock_or_recover(&self.subscribers);
+        let before = subscribers.channels.len();
+        subscribers.channels.retain(|s| s.channel.id() != id);
+        subscribers.channels.len() != before
+    }
+
+    /// Let the channel with `id` receive the next frame
+    ///
+    /// Called by the frontend once it has displayed the last frame sent.
+    /// Returns whether the channel is subscribed.
+    pub fn ack(&self, id: u32) -> bool {
+        let mut subscribers = crate::lock_or_recover(&self.subscribers);
+        match subscribers
+            .channels
+            .iter_mut()
+            .find(|s| s.channel.id() == id)
+        {
+            Some(subscriber) => {
+                subscriber.ready = true;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Whether any channel is subscribed (checked before reading the frame)
+    pub fn has_subscribers(&self) -> bool {
+        !crate::lock_or_recover(&self.subscribers)
+            .channels
+            .is_empty()
+    }
+
+    /// Send a frame to every subscriber that acknowledged the last one
+    ///
+    /// Frames with a sequence number that was already sent are skipped.
+    /// Channels whose webview is gone are dropped. The message is encoded
+    /// once and moved into the last channel, so one subscriber costs no
+    /// copy beyond the encoding.
+    pub fn publish(&self, info: &FrameInfo, data: &[u8]) {
+        let mut subscribers = crate::lock_or_recover(&self.subscribers);
+        if info.sequence <= subscribers.last_sequence {
+            return;
+        }
+        let ready: Vec<usize> = (0..subscribers.channels.len())
+            .filter(|&i| subscribers.channels[i].ready)
+            .collect();
+        let Some(&last) = ready.last() else {
+            return;
+        };
+        subscribers.last_sequence = info.sequence;
+
+        let mut message = encode_frame(info, data);
+        let mut closed = Vec::new();
+        for &i in &ready {
+            let body = if i == last {
+                std::mem::take(&mut message)
+            } else {
+                message.clone()
+            };
+            let subscriber = &mut subscribers.channels[i];
+            subscriber.ready = false;
+            if let Err(e) = subscriber.channel.send(InvokeResponseBody::Raw(body)) {
+                log::info!("Dropping frame channel {}: {}", subscriber.channel.id(), e);
+                closed.push(subscriber.channel.id());
+            }
+        }
+        subscribers
+            .channels
+            .retain(|s| !closed.contains(&s.channel.id()));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::mpsc;
+
+    fn info(sequence: u64, format: &str) -> FrameInfo {
+        FrameInfo {
+            sequence,
+            width: 4,
+            height: 2,
+            format: format.to_string(),
+            size: 3,
+        }
+    }
+
+    /// Channel that forwards raw messages to the returned receiver
+    fn test_channel() -> (Channel<InvokeResponseBody>, mpsc::Receiver<Vec<u8>>) {
+        let (tx, rx) = mpsc::channel();
+        let channel = Channel::new(move |body| {
+            if let InvokeResponseBody::Raw(bytes) = body {
+                tx.send(bytes)
+                    .map_err(|e| tauri::Error::Io(std::io::Error::other(e)))?;
+            }
+            Ok(())
+        });
+        (channel, rx)
+    }
+
+    #[test]
+    fn test_encode_frame_header() {
+        let message = encode_frame(&info(7, "jpeg"), &[0xFF, 0xD8, 0xD9]);
+        assert_eq!(message.len(), FRAME_HEADER_LEN + 3);
+        assert_eq!(&message[0..8], &7u64.to_le_bytes());
+        assert_eq!(&message[8..12], &4u32.to_le_bytes());
+        assert_eq!(&message[12..16], &2u32.to_le_bytes());
+        assert_eq!(message[16], FORMAT_JPEG);
+        assert_eq!(&message[FRAME_HEADER_LEN..], &[0xFF, 0xD8, 0xD9]);
+
+        assert_eq!(encode_frame(&info(1, "rgb"), &[])[16], FORMAT_RGB);
+    }
+
+    #[test]
+    fn test_publish_skips_repeated_frames() {
+        let stream = FrameStream::new();
+        let (channel, rx) = test_channel();
+        let id = stream.subscribe(channel);
+        assert!(stream.has_subscribers());
+
+        stream.publish(&info(1, "rgb"), &[1, 2, 3]);
+        stream.ack(id);
+        stream.publish(&info(1, "rgb"), &[1, 2, 3]);
+        stream.publish(&info(2, "rgb"), &[4, 5, 6]);
+        let sequences: Vec<_> = rx.try_iter().map(|m| m[0]).collect();
+        assert_eq!(sequences, [1, 2]);
+
+        assert!(stream.unsubscribe(id));
+        assert!(!stream.unsubscribe(id));
+        assert!(!stream.has_subscribers());
+    }
+
+    #[test]
+    fn test_frames_wait_for_ack() {
+        let stream = FrameStream::new();
+        let (slow, slow_rx) = test_channel();
+        let (fast, fast_rx) = test_channel();
+        let slow_id = stream.subscribe(slow);
+        let fast_id = stream.subscribe(fast);
+
+        stream.publish(&info(1, "rgb"), &[1]);
+        assert!(stream.ack(fast_id));
+        stream.publish(&info(2, "rgb"), &[2]);
+        // Frame 3 finds neither channel ready and is not queued
+        stream.publish(&info(3, "rgb"), &[3]);
+        assert!(stream.ack(slow_id));
+        assert!(stream.ack(fast_id));
+        stream.publish(&info(4, "rgb"), &[4]);
+
+        let slow: Vec<_> = slow_rx.try_iter().map(|m| m[0]).collect();
+        let fast: Vec<_> = fast_rx.try_iter().map(|m| m[0]).collect();
+        assert_eq!(slow, [1, 4]);
+        assert_eq!(fast, [1, 2, 4]);
+        assert!(!stream.ack(99));
+    }
+
+    #[test]
+    fn test_closed_channel_is_dropped() {
+        let stream = FrameStream::new();
+        let (channel, rx) = test_channel();
+        stream.subscribe(channel);
+        drop(rx);
+
+        stream.publish(&info(1, "rgb"), &[0]);
+        assert!(!stream.has_subscribers());
+    }
+}
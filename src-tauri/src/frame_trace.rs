@@ -0,0 +1,433 @@
+//! One-frame pipeline trace
+//!
+//! [`FrameTracer::arm`] makes the streaming thread write every intermediate
+//! buffer of the next complete frame into `traces/trace_<unix millis>/`:
+//!
+//! - `raw_payload.bin`: USB payloads received for the frame, headers included,
+//!   each written as `[u32 LE: length][bytes: data]` with no file header or
+//!   timestamps
+//! - `assembled.bin`: the frame as assembled from the payloads (YUV or JPEG)
+//! - `rgb.bin`: RGB24 after conversion (YUV) or decoding (MJPEG)
+//! - `encoded.jpg`: the RGB frame encoded as JPEG
+//! - `manifest.json`: a [`TraceManifest`] with geometry, packet sizes, the
+//!   validation verdict and any stage that failed
+//!
+//! Packets are collected from the frame boundary before the traced frame, so
+//! the payload covers one frame's worth of transfers. Frames are processed a
+//! little after their packets arrive, so the first or last packets may belong
+//! to a neighbouring frame.
+
+use std::io;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver, SyncSender};
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde::Serialize;
+use thiserror::Error;
+
+use crate::capture::CaptureState;
+use crate::frame_validation::ValidationResult;
+use crate::image_encoder::{self, ImageFormat};
+use crate::storage::Storage;
+use crate::PixelFormat;
+
+/// Subdirectory of the output directory holding frame traces
+pub const TRACE_DIR: &str = "traces";
+
+const RAW_PAYLOAD_FILE: &str = "raw_payload.bin";
+const ASSEMBLED_FILE: &str = "assembled.bin";
+const RGB_FILE: &str = "rgb.bin";
+const ENCODED_FILE: &str = "encoded.jpg";
+const MANIFEST_FILE: &str = "manifest.json";
+
+/// Errors from frame tracing
+#[derive(Debug, Error)]
+pub enum TraceError {
+    /// A trace is already waiting for a frame
+    #[error("a frame trace is already pending")]
+    AlreadyArmed,
+
+    /// The trace directory or manifest couldn't be written
+    #[error("I/O error: {0}")]
+    Io(#[from] io::Error),
+
+    /// The manifest couldn't be serialized
+    #[error("JSON serialization error: {0}")]
+    Json(#[from] serde_json::Error),
+}
+
+/// Result type for frame tracing
+pub type Result<T> = std::result::Result<T, TraceError>;
+
+/// Pipeline stage of a trace artifact or failure
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TraceStage {
+    /// USB payloads as received
+    RawPayload,
+    /// Frame assembled from the payloads
+    Assembled,
+    /// RGB24 after conversion or decoding
+    Rgb,
+    /// RGB encoded as JPEG
+    Encoded,
+}
+
+/// A file written by a trace
+#[derive(Debug, Clone, Serialize)]
+pub struct TraceArtifact {
+    /// Stage the buffer was taken from
+    pub stage: TraceStage,
+    /// File name within the trace directory
+    pub file: String,
+    /// Size in bytes
+    pub bytes: usize,
+}
+
+/// A stage that produced no artifact
+#[derive(Debug, Clone, Serialize)]
+pub struct TraceFailure {
+    /// Stage that failed
+    pub stage: TraceStage,
+    /// What went wrong
+    pub message: String,
+}
+
+/// Everything known about a traced frame, written as `manifest.json`
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct TraceManifest {
+    /// Directory holding the artifacts
+    pub directory: String,
+    /// Unix time the frame was traced (milliseconds)
+    pub created_at_ms: u64,
+    /// Pixel format of the assembled frame (`None` for MJPEG)
+    pub pixel_format: Option<PixelFormat>,
+    /// Frame width in pixels (0 if unknown)
+    pub width: u32,
+    /// Frame height in pixels (0 if unknown)
+    pub height: u32,
+    /// Row stride in bytes (uncompressed frames only)
+    pub stride: Option<u32>,
+    /// Size of each raw payload packet, in arrival order
+    pub packet_sizes: Vec<usize>,
+    /// Post-validation verdict (uncompressed frames only)
+    pub validation: Option<ValidationResult>,
+    /// Files written, in pipeline order
+    pub artifacts: Vec<TraceArtifact>,
+    /// Stages that failed
+    pub failures: Vec<TraceFailure>,
+}
+
+/// A trace waiting for its frame
+struct PendingTrace {
+    storage: Storage,
+    directory: String,
+    sender: SyncSender<Result<TraceManifest>>,
+    /// Whether packets are being collected (a frame boundary has been seen)
+    collecting: bool,
+}
+
+/// Arms and hands out one-frame traces for the streaming thread
+#[derive(Default)]
+pub struct FrameTracer {
+    /// Whether a trace is pending (fast path for the streaming thread)
+    armed: AtomicBool,
+    pending: Mutex<Option<PendingTrace>>,
+}
+
+impl FrameTracer {
+    /// Create a tracer with nothing armed
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Trace the next complete frame into a new directory under [`TRACE_DIR`]
+    ///
+    /// The returned receiver gets the manifest once the frame has been
+    /// written.
+    ///
+    /// # Errors
+    ///
+    /// Returns `TraceError::AlreadyArmed` if a trace is already pending, or an
+    /// I/O error if the trace directory can't be created.
+    pub fn arm(&self, storage: &Storage) -> Result<Receiver<Result<TraceManifest>>> {
+        let mut pending = crate::lock_or_recover(&self.pending);
+        if pending.is_some() {
+            return Err(TraceError::AlreadyArmed);
+        }
+
+        let directory = format!("{}/trace_{}", TRACE_DIR, unix_millis());
+        let path = storage.create_dir_all(&directory)?;
+        let storage = storage.subdir(&directory)?;
+        let (sender, receiver) = mpsc::sync_channel(1);
+        *pending = Some(PendingTrace {
+            storage,
+            directory: path.display().to_string(),
+            sender,
+            collecting: false,
+        });
+        self.armed.store(true, Ordering::Release);
+        log::info!("Frame trace armed: {}", path.display());
+        Ok(receiver)
+    }
+
+    /// Whether a trace is waiting for a frame
+    #[must_use]
+    pub fn is_armed(&self) -> bool {
+        self.armed.load(Ordering::Acquire)
+    }
+
+    /// Drop the pending trace, if any (e.g. no frame arrived in time)
+    pub fn cancel(&self, capture: &CaptureState) {
+        let mut pending = crate::lock_or_recover(&self.pending);
+        if pending.take().is_some_and(|trace| trace.collecting) {
+            capture.take_packet_trace();
+        }
+        self.armed.store(false, Ordering::Release);
+    }
+
+    /// Called by the streaming thread for every complete frame
+    ///
+    /// The first frame after arming only starts collecting packets; the next
+    /// one is returned as the [`FrameTrace`] to fill in. Costs one atomic load
+    /// when nothing is armed.
+    pub fn begin_frame(&self, capture: &CaptureState) -> Option<FrameTrace> {
+        if !self.is_armed() {
+            return None;
+        }
+        let mut pending = crate::lock_or_recover(&self.pending);
+        let trace = pending.as_mut()?;
+        if !trace.collecting {
+            trace.collecting = true;
+            capture.start_packet_trace();
+            return None;
+        }
+
+        let trace = pending.take()?;
+        self.armed.store(false, Ordering::Release);
+        Some(FrameTrace::new(trace, capture.take_packet_trace()))
+    }
+}
+
+/// Artifacts of the frame being traced, written as the pipeline produces them
+///
+/// Write failures are recorded in the manifest rather than returned, so a
+/// trace never interrupts streaming. [`FrameTrace::finish`] must be called to
+/// deliver the manifest.
+pub struct FrameTrace {
+    storage: Storage,
+    sender: SyncSender<Result<TraceManifest>>,
+    manifest: TraceManifest,
+}
+
+impl FrameTrace {
+    fn new(pending: PendingTrace, packets: Vec<Vec<u8>>) -> Self {
+        let mut trace = Self {
+            storage: pending.storage,
+            sender: pending.sender,
+            manifest: TraceManifest {
+                directory: pending.directory,
+                created_at_ms: unix_millis(),
+                packet_sizes: packets.iter().map(Vec::len).collect(),
+                ..TraceManifest::default()
+            },
+        };
+
+        let total: usize = packets.iter().map(|packet| packet.len() + 4).sum();
+        let mut payload = Vec::with_capacity(total);
+        for packet in &packets {
+            payload.extend_from_slice(&(packet.len() as u32).to_le_bytes());
+            payload.extend_from_slice(packet);
+        }
+        trace.write(TraceStage::RawPayload, RAW_PAYLOAD_FILE, &payload);
+        trace
+    }
+
+    /// Record an assembled uncompressed frame and its geometry
+    pub fn assembled(
+        &mut self,
+        data: &[u8],
+        pixel_format: PixelFormat,
+        width: u32,
+        height: u32,
+        stride: u32,
+    ) {
+        self.manifest.pixel_format = Some(pixel_format);
+        self.manifest.width = width;
+        self.manifest.height = height;
+        self.manifest.stride = Some(stride);
+        self.write(TraceStage::Assembled, ASSEMBLED_FILE, data);
+    }
+
+    /// Record the validation verdict for the assembled frame
+    pub fn validation(&mut self, result: ValidationResult) {
+        self.manifest.validation = Some(result);
+    }
+
+    /// Record the RGB24 frame and its JPEG encoding
+    pub fn rgb(&mut self, rgb: &[u8], width: u32, height: u32) {
+        self.write(TraceStage::Rgb, RGB_FILE, rgb);
+        match image_encoder::encode_frame(rgb, width, height, ImageFormat::Jpeg) {
+            Ok(jpeg) => self.write(TraceStage::Encoded, ENCODED_FILE, &jpeg),
+            Err(e) => self.fail(TraceStage::Encoded, e),
+        }
+    }
+
+    /// Record an MJPEG frame: the assembled JPEG, its decoded RGB and re-encoding
+    ///
+    /// `width`/`height` may be 0; the JPEG header has the real dimensions.
+    pub fn jpeg(&mut self, jpeg: &[u8], width: u32, height: u32) {
+        self.manifest.width = width;
+        self.manifest.height = height;
+        self.write(TraceStage::Assembled, ASSEMBLED_FILE, jpeg);
+        match image_encoder::decode_frame(jpeg, width, height) {
+            Ok((rgb, width, height)) => {
+                self.manifest.width = width;
+                self.manifest.height = height;
+                self.rgb(&rgb, width, height);
+            }
+            Err(e) => self.fail(TraceStage::Rgb, e),
+        }
+    }
+
+    /// Record that `stage` produced nothing
+    pub fn fail(&mut self, stage: TraceStage, message: impl std::fmt::Display) {
+        let message = message.to_string();
+        log::warn!("Frame trace: {:?} failed: {}", stage, message);
+        self.manifest.failures.push(TraceFailure { stage, message });
+    }
+
+    /// Write the manifest and hand it to whoever armed the trace
+    pub fn finish(self) {
+        let result = serde_json::to_vec_pretty(&self.manifest)
+            .map_err(TraceError::from)
+            .and_then(|json| Ok(self.storage.write(MANIFEST_FILE, json)?))
+            .map(|_| self.manifest);
+        match &result {
+            Ok(manifest) => log::info!("Frame trace written: {}", manifest.directory),
+            Err(e) => log::error!("Frame trace manifest failed: {}", e),
+        }
+        // The caller may have stopped waiting
+        let _ = self.sender.try_send(result);
+    }
+
+    fn write(&mut self, stage: TraceStage, file: &str, data: &[u8]) {
+        match self.storage.write(file, data) {
+            Ok(_) => self.manifest.artifacts.push(TraceArtifact {
+                stage,
+                file: file.to_string(),
+                bytes: data.len(),
+            }),
+            Err(e) => self.fail(stage, e),
+        }
+    }
+}
+
+fn unix_millis() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::frame_validation::{validate_yuy2_frame, ValidationLevel};
+
+    #[test]
+    fn test_trace_skips_partial_frame_and_writes_next() {
+        let dir = tempfile::tempdir().unwrap();
+        let storage = Storage::new(dir.path());
+        let capture = CaptureState::new();
+        let tracer = FrameTracer::new();
+        let receiver = tracer.arm(&storage).unwrap();
+        assert!(tracer.is_armed());
+
+        // Packets before the first boundary belong to a partial frame
+        capture.record_packet(&[0xAA; 8]);
+        assert!(tracer.begin_frame(&capture).is_none());
+        capture.record_packet(&[1, 2, 3]);
+        capture.record_packet(&[4]);
+
+        let mut trace = tracer.begin_frame(&capture).unwrap();
+        assert!(!tracer.is_armed());
+        assert!(!capture.wants_packets());
+
+        let frame = vec![128u8; 4 * 2 * 2];
+        trace.assembled(&frame, PixelFormat::Yuyv, 4, 2, 8);
+        trace.validation(validate_yuy2_frame(
+            &frame,
+            4,
+            2,
+            frame.len(),
+            ValidationLevel::Moderate,
+        ));
+        trace.rgb(&[0u8; 4 * 2 * 3], 4, 2);
+        trace.finish();
+
+        let manifest = receiver.recv().unwrap().unwrap();
+        assert_eq!(manifest.packet_sizes, vec![3, 1]);
+        assert_eq!(manifest.stride, Some(8));
+        assert!(manifest.validation.unwrap().valid);
+
+        let trace_dir = std::path::Path::new(&manifest.directory);
+        let payload = std::fs::read(trace_dir.join(RAW_PAYLOAD_FILE)).unwrap();
+        assert_eq!(payload, [3, 0, 0, 0, 1, 2, 3, 1, 0, 0, 0, 4]);
+        assert!(trace_dir.join(MANIFEST_FILE).exists());
+        assert_eq!(
+            std::fs::read(trace_dir.join(ASSEMBLED_FILE)).unwrap(),
+            frame
+        );
+        let stages: Vec<_> = manifest.artifacts.iter().map(|a| a.stage).collect();
+        assert_eq!(
+            stages[..3],
+            [
+                TraceStage::RawPayload,
+                TraceStage::Assembled,
+                TraceStage::Rgb
+            ]
+        );
+    }
+
+    #[test]
+    fn test_trace_records_undecodable_jpeg_as_failure() {
+        let dir = tempfile::tempdir().unwrap();
+        let tracer = FrameTracer::new();
+        let capture = CaptureState::new();
+        let receiver = tracer.arm(&Storage::new(dir.path())).unwrap();
+        assert!(tracer.begin_frame(&capture).is_none());
+
+        let mut trace = tracer.begin_frame(&capture).unwrap();
+        trace.jpeg(&[0xFF, 0xD8, 0x00, 0x01], 0, 0);
+        trace.finish();
+
+        let manifest = receiver.recv().unwrap().unwrap();
+        assert_eq!(manifest.pixel_format, None);
+        assert_eq!(manifest.failures.len(), 1);
+        assert_eq!(manifest.failures[0].stage, TraceStage::Rgb);
+    }
+
+    #[test]
+    fn test_arm_twice_and_cancel() {
+        let dir = tempfile::tempdir().unwrap();
+        let storage = Storage::new(dir.path());
+        let capture = CaptureState::new();
+        let tracer = FrameTracer::new();
+        let _receiver = tracer.arm(&storage).unwrap();
+        assert!(matches!(
+            tracer.arm(&storage),
+            Err(TraceError::AlreadyArmed)
+        ));
+
+        assert!(tracer.begin_frame(&capture).is_none());
+        assert!(capture.wants_packets());
+        tracer.cancel(&capture);
+        assert!(!tracer.is_armed());
+        assert!(!capture.wants_packets());
+        assert!(tracer.begin_frame(&capture).is_none());
+        assert!(tracer.arm(&storage).is_ok());
+    }
+}
@@ -1,13 +1,19 @@
-//! Frame corruption detection for YUY2 video streams
+//! Frame corruption detection for uncompressed video streams
 //!
 //! Detects common artifacts from cheap USB endoscopes:
 //! - Horizontal banding (rows shifted or repeated)
 //! - Diagonal shearing (stride misalignment)
 //!
+//! Row checks follow each format's layout: the luma of packed 4:2:2, the
+//! Y plane of planar and semi-planar 4:2:0, the green channel of RGB and
+//! same-colour rows of a Bayer mosaic.
+//!
 //! Configurable via `CLEANSCOPE_FRAME_VALIDATION` environment variable.
 
 use serde::{Deserialize, Serialize};
 
+use crate::PixelFormat;
+
 /// Configuration for frame validation thresholds
 ///
 /// These values control how strictly frames are validated for corruption.
@@ -74,7 +80,7 @@ impl ValidationLevel {
 }
 
 /// Frame validation result with diagnostic metrics
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize)]
 pub struct ValidationResult {
     /// Whether the frame passed validation
     pub valid: bool,
@@ -94,6 +100,8 @@ pub struct ValidationResult {
 
 /// Validate a YUY2 frame for corruption artifacts
 ///
+/// Shorthand for [`validate_frame`] with [`PixelFormat::Yuyv`].
+///
 /// # Arguments
 /// * `data` - Raw YUY2 frame data
 /// * `width` - Expected frame width in pixels
@@ -109,6 +117,65 @@ pub fn validate_yuy2_frame(
     height: usize,
     expected_size: usize,
     level: ValidationLevel,
+) -> ValidationResult {
+    validate_frame(data, width, height, PixelFormat::Yuyv, expected_size, level)
+}
+
+/// Where the row checks sample a frame of one pixel format
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct RowLayout {
+    /// Bytes per row of the first (or only) plane
+    stride: usize,
+    /// Byte offset of the sampled channel within a pixel group
+    offset: usize,
+    /// Bytes between samples (16 pixels)
+    step: usize,
+    /// Rows between the compared rows (2 for Bayer, whose adjacent rows
+    /// carry different colours)
+    pitch: usize,
+}
+
+impl RowLayout {
+    fn of(pixel_format: PixelFormat, width: usize) -> Self {
+        let (bytes_per_pixel, offset, pitch) = match pixel_format {
+            PixelFormat::Yuyv => (2, 0, 1),
+            PixelFormat::Uyvy => (2, 1, 1),
+            PixelFormat::Nv12 | PixelFormat::Nv21 | PixelFormat::I420 | PixelFormat::Yv12 => {
+                (1, 0, 1)
+            }
+            PixelFormat::Rgb888 | PixelFormat::Bgr888 => (3, 1, 1),
+            PixelFormat::BayerRggb => (1, 0, 2),
+        };
+        Self {
+            stride: width * bytes_per_pixel,
+            offset,
+            step: 16 * bytes_per_pixel,
+            pitch,
+        }
+    }
+}
+
+/// Validate an uncompressed frame for corruption artifacts
+///
+/// Stride alignment and the row checks use `pixel_format`'s layout.
+///
+/// # Arguments
+/// * `data` - Raw frame data
+/// * `width` - Expected frame width in pixels
+/// * `height` - Expected frame height in pixels
+/// * `pixel_format` - Layout of `data`
+/// * `expected_size` - Expected frame size in bytes
+/// * `level` - Validation strictness level
+///
+/// # Returns
+/// `ValidationResult` with metrics and pass/fail status
+pub fn validate_frame(
+    data: &[u8],
+    width: usize,
+    height: usize,
+    pixel_format: PixelFormat,
+    expected_size: usize,
+    level: ValidationLevel,
 ) -> ValidationResult {
     let actual_size = data.len();
     let size_ratio = actual_size as f32 / expected_size.max(1) as f32;
@@ -147,7 +214,8 @@ pub fn validate_yuy2_frame(
     }
 
     // Stride alignment check (Moderate and Strict)
-    let stride = width * 2; // YUY2 = 2 bytes per pixel
+    let layout = RowLayout::of(pixel_format, width);
+    let stride = layout.stride.max(1);
     let stride_aligned = if level == ValidationLevel::Strict || level == ValidationLevel::Moderate {
         // Allow small deviations (within one stride) from expected size
         actual_size.is_multiple_of(stride)
@@ -164,12 +232,15 @@ pub fn validate_yuy2_frame(
     }
 
     // Row similarity check (Strict only)
-    let avg_row_diff =
-        if level == ValidationLevel::Strict && height >= 4 && data.len() >= stride * 4 {
-            Some(compute_row_similarity(data, stride, height))
-        } else {
-            None
-        };
+    let rows_needed = 4 * layout.pitch;
+    let avg_row_diff = if level == ValidationLevel::Strict
+        && height >= rows_needed
+        && data.len() >= stride * rows_needed
+    {
+        Some(compute_row_similarity(data, layout, height))
+    } else {
+        None
+    };
 
     let row_diff_valid = match (level, avg_row_diff) {
         (ValidationLevel::Strict, Some(diff)) => {
@@ -204,22 +275,20 @@ pub fn validate_yuy2_frame(
     }
 }
 
-/// Compute average Y-channel difference between adjacent rows
+/// Compute average difference of the sampled channel between nearby rows
 ///
-/// Samples the first 3-4 rows, checking every 16th pixel for performance.
+/// Samples the first 3-4 row pairs, checking every 16th pixel for performance.
 /// High values (>40-80) indicate banding/corruption.
-fn compute_row_similarity(data: &[u8], stride: usize, height: usize) -> f32 {
-    let rows_to_check = 3.min(height - 1);
+fn compute_row_similarity(data: &[u8], layout: RowLayout, height: usize) -> f32 {
+    let rows_to_check = 3.min(height.saturating_sub(layout.pitch));
     let mut total_diff: u64 = 0;
     let mut samples: u64 = 0;
 
     for row in 0..rows_to_check {
-        let row0_start = row * stride;
-        let row1_start = (row + 1) * stride;
+        let row0_start = row * layout.stride + layout.offset;
+        let row1_start = (row + layout.pitch) * layout.stride + layout.offset;
 
-        // Sample every 16th pixel (every 32nd byte since YUY2 = 2 bytes/pixel)
-        // Y values are at even indices (0, 2, 4, ...) in YUYV
-        for x in (0..stride).step_by(32) {
+        for x in (0..layout.stride.saturating_sub(layout.offset)).step_by(layout.step) {
             if row1_start + x >= data.len() {
                 break;
             }
@@ -363,6 +432,66 @@ mod tests {
         assert!(result.avg_row_diff.is_none()); // No row diff computed for Moderate
     }
 
+    #[test]
+    fn test_rgb_frame_uses_three_byte_stride() {
+        // Solid red: a 2-byte stride would compare different channels
+        let (width, height) = (64, 48);
+        let data: Vec<u8> = [255u8, 0, 0].repeat(width * height);
+
+        let result = validate_frame(
+            &data,
+            width,
+            height,
+            PixelFormat::Rgb888,
+            data.len(),
+            ValidationLevel::Strict,
+        );
+        assert!(result.valid, "{:?}", result.failure_reason);
+        assert_eq!(result.avg_row_diff, Some(0.0));
+    }
+
+    #[test]
+    fn test_bayer_rows_are_compared_by_colour() {
+        // Uniform scene: R G / G B rows differ, same-colour rows don't
+        let (width, height) = (64, 48);
+        let mut data = Vec::with_capacity(width * height);
+        for row in 0..height {
+            let pair: [u8; 2] = if row % 2 == 0 { [200, 100] } else { [100, 30] };
+            data.extend(pair.repeat(width / 2));
+        }
+
+        let result = validate_frame(
+            &data,
+            width,
+            height,
+            PixelFormat::BayerRggb,
+            data.len(),
+            ValidationLevel::Strict,
+        );
+        assert!(result.valid, "{:?}", result.failure_reason);
+    }
+
+    #[test]
+    fn test_banding_in_planar_luma_is_detected() {
+        let (width, height) = (64, 48);
+        let mut data = vec![128u8; width * height * 3 / 2];
+        for row in 0..height {
+            let val = if row % 2 == 0 { 16u8 } else { 235u8 };
+            data[row * width..(row + 1) * width].fill(val);
+        }
+
+        let result = validate_frame(
+            &data,
+            width,
+            height,
+            PixelFormat::Nv12,
+            data.len(),
+            ValidationLevel::Strict,
+        );
+        assert!(!result.valid);
+        assert!(result.stride_aligned);
+    }
+
     #[test]
     fn test_from_env_str() {
         assert_eq!(
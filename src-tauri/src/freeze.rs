@@ -0,0 +1,375 @@
+//! Frozen frames with user-drawn vector annotations
+//!
+//! `freeze_frame` keeps a decoded copy of the frame on screen while the
+//! stream continues. Arrows, circles and text positions drawn on it are kept
+//! here as [`VectorAnnotation`]s in frame pixels rather than baked into the
+//! image by the frontend, so they can be edited, saved as JSON next to a
+//! snapshot and rendered into a composite with [`FrozenFrame::render`].
+
+use std::sync::Arc;
+
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+/// Most annotations kept on one frozen frame
+pub const MAX_ANNOTATIONS: usize = 256;
+
+/// Longest accepted text of a text annotation
+pub const MAX_TEXT_LEN: usize = 256;
+
+/// Errors annotating a frozen frame
+#[derive(Debug, Error)]
+pub enum FreezeError {
+    /// No frame is frozen
+    #[error("no frame is frozen")]
+    NotFrozen,
+    /// No annotation with this id
+    #[error("unknown annotation {0}")]
+    UnknownAnnotation(u32),
+    /// Annotation geometry or text is unusable
+    #[error("invalid annotation: {0}")]
+    Invalid(String),
+}
+
+/// Result type alias for freeze operations
+pub type Result<T> = std::result::Result<T, FreezeError>;
+
+/// Geometry of a vector annotation, in frame pixels
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Shape {
+    /// Arrow pointing from the start to the end
+    Arrow {
+        /// Start x
+        x1: f32,
+        /// Start y
+        y1: f32,
+        /// Tip x
+        x2: f32,
+        /// Tip y
+        y2: f32,
+    },
+    /// Circle outline
+    Circle {
+        /// Centre x
+        x: f32,
+        /// Centre y
+        y: f32,
+        /// Radius
+        radius: f32,
+    },
+    /// Text anchored at a point (the frontend draws the text)
+    Text {
+        /// Anchor x
+        x: f32,
+        /// Anchor y
+        y: f32,
+        /// Text to show
+        text: String,
+    },
+}
+
+impl Shape {
+    /// Check the shape is finite, inside the frame and has usable text
+    ///
+    /// Circles may extend past the frame edges, but not by more than the
+    /// frame diagonal, beyond which nothing more of them could be seen.
+    fn validate(&self, width: u32, height: u32) -> Result<()> {
+        let (w, h) = (width as f32, height as f32);
+        let inside = |x: f32, y: f32| (0.0..=w).contains(&x) && (0.0..=h).contains(&y);
+        match self {
+            Shape::Arrow { x1, y1, x2, y2 } => {
+                if !inside(*x1, *y1) || !inside(*x2, *y2) {
+                    return Err(FreezeError::Invalid("arrow outside the frame".into()));
+                }
+            }
+            Shape::Circle { x, y, radius } => {
+                if !inside(*x, *y) {
+                    return Err(FreezeError::Invalid(
+                        "circle centre outside the frame".into(),
+                    ));
+                }
+                if !(radius.is_finite() && *radius > 0.0 && *radius <= w.hypot(h)) {
+                    return Err(FreezeError::Invalid(format!("radius {}", radius)));
+                }
+            }
+            Shape::Text { x, y, text } => {
+                if !inside(*x, *y) {
+                    return Err(FreezeError::Invalid("text outside the frame".into()));
+                }
+                if text.trim().is_empty() || text.len() > MAX_TEXT_LEN {
+                    return Err(FreezeError::Invalid(format!(
+                        "text must be 1 to {} bytes",
+                        MAX_TEXT_LEN
+                    )));
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// An annotation drawn on a frozen frame
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct VectorAnnotation {
+    /// Annotation number on this frame (starting at 1)
+    pub id: u32,
+    /// Geometry
+    #[serde(flatten)]
+    pub shape: Shape,
+    /// Colour as RGB (`None`: the renderer's default)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub color: Option<[u8; 3]>,
+}
+
+/// A frozen frame and its annotations
+#[derive(Debug, Clone, Serialize)]
+pub struct FrozenFrame {
+    /// Sequence number of the frozen frame
+    pub sequence: u64,
+    /// Frame width in pixels
+    pub width: u32,
+    /// Frame height in pixels
+    pub height: u32,
+    /// Annotations in drawing order
+    pub annotations: Vec<VectorAnnotation>,
+    /// Decoded frame (RGB24)
+    #[serde(skip)]
+    rgb: Arc<Vec<u8>>,
+    #[serde(skip)]
+    next_id: u32,
+}
+
+impl FrozenFrame {
+    /// Freeze a decoded RGB24 frame
+    ///
+    /// # Errors
+    ///
+    /// Returns `FreezeError::Invalid` if `rgb` does not match the dimensions.
+    pub fn new(sequence: u64, rgb: Arc<Vec<u8>>, width: u32, height: u32) -> Result<Self> {
+        if width == 0 || height == 0 || rgb.len() != width as usize * height as usize * 3 {
+            return Err(FreezeError::Invalid(format!(
+                "{} bytes for a {}x{} RGB frame",
+                rgb.len(),
+                width,
+                height
+            )));
+        }
+        Ok(Self {
+            sequence,
+            width,
+            height,
+            annotations: Vec::new(),
+            rgb,
+            next_id: 1,
+        })
+    }
+
+    /// Add an annotation and return it
+    ///
+    /// # Errors
+    ///
+    /// Returns `FreezeError::Invalid` if the shape is unusable or the frame
+    /// already has [`MAX_ANNOTATIONS`] annotations.
+    pub fn add(&mut self, shape: Shape, color: Option<[u8; 3]>) -> Result<VectorAnnotation> {
+        shape.validate(self.width, self.height)?;
+        if self.annotations.len() >= MAX_ANNOTATIONS {
+            return Err(FreezeError::Invalid(format!(
+                "at most {} annotations per frame",
+                MAX_ANNOTATIONS
+            )));
+        }
+        let annotation = VectorAnnotation {
+            id: self.next_id,
+            shape,
+            color,
+        };
+        self.next_id += 1;
+        self.annotations.push(annotation.clone());
+        Ok(annotation)
+    }
+
+    /// Replace the geometry and colour of an annotation
+    ///
+    /// # Errors
+    ///
+    /// Returns `FreezeError::UnknownAnnotation` if there is no annotation
+    /// `id`, or `FreezeError::Invalid` if the shape is unusable.
+    pub fn update(
+        &mut self,
+        id: u32,
+        shape: Shape,
+        color: Option<[u8; 3]>,
+    ) -> Result<VectorAnnotation> {
+        shape.validate(self.width, self.height)?;
+        let annotation = self
+            .annotations
+            .iter_mut()
+            .find(|a| a.id == id)
+            .ok_or(FreezeError::UnknownAnnotation(id))?;
+        annotation.shape = shape;
+        annotation.color = color;
+        Ok(annotation.clone())
+    }
+
+    /// Remove an annotation
+    ///
+    /// # Errors
+    ///
+    /// Returns `FreezeError::UnknownAnnotation` if there is no annotation `id`.
+    pub fn remove(&mut self, id: u32) -> Result<()> {
+        let index = self
+            .annotations
+            .iter()
+            .position(|a| a.id == id)
+            .ok_or(FreezeError::UnknownAnnotation(id))?;
+        self.annotations.remove(index);
+        Ok(())
+    }
+
+    /// Replace all annotations, e.g. to undo an edit
+    ///
+    /// New annotations keep getting ids above any given out before.
+    pub fn set_annotations(&mut self, annotations: Vec<VectorAnnotation>) {
+        let next = annotations.iter().map(|a| a.id + 1).max().unwrap_or(1);
+        self.next_id = self.next_id.max(next);
+        self.annotations = annotations;
+    }
+
+    /// The frame with its annotations drawn in (RGB24)
+    #[must_use]
+    pub fn render(&self) -> Vec<u8> {
+        let mut rgb = self.rgb.as_ref().clone();
+        crate::overlay::draw_vector_annotations(
+            &mut rgb,
+            self.width,
+            self.height,
+            &self.annotations,
+        );
+        rgb
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn frozen() -> FrozenFrame {
+        FrozenFrame::new(5, Arc::new(vec![0; 32 * 16 * 3]), 32, 16).unwrap()
+    }
+
+    fn arrow() -> Shape {
+        Shape::Arrow {
+            x1: 2.0,
+            y1: 2.0,
+            x2: 20.0,
+            y2: 10.0,
+        }
+    }
+
+    #[test]
+    fn test_annotation_lifecycle() {
+        let mut frame = frozen();
+        let first = frame.add(arrow(), None).unwrap();
+        let circle = Shape::Circle {
+            x: 16.0,
+            y: 8.0,
+            radius: 4.0,
+        };
+        let second = frame.add(circle.clone(), Some([255, 0, 0])).unwrap();
+        assert_eq!((first.id, second.id), (1, 2));
+
+        frame.remove(1).unwrap();
+        // Ids are not reused after a removal
+        assert_eq!(frame.add(arrow(), None).unwrap().id, 3);
+
+        let moved = Shape::Circle {
+            x: 10.0,
+            y: 8.0,
+            radius: 2.0,
+        };
+        assert_eq!(frame.update(2, moved.clone(), None).unwrap().shape, moved);
+        assert!(matches!(
+            frame.update(1, circle, None),
+            Err(FreezeError::UnknownAnnotation(1))
+        ));
+        assert!(matches!(
+            frame.remove(9),
+            Err(FreezeError::UnknownAnnotation(9))
+        ));
+    }
+
+    #[test]
+    fn test_restored_annotations_keep_ids_unique() {
+        let mut frame = frozen();
+        frame.add(arrow(), None).unwrap();
+        let before = frame.annotations.clone();
+        frame.add(arrow(), None).unwrap();
+
+        frame.set_annotations(before.clone());
+        assert_eq!(frame.annotations, before);
+        assert_eq!(frame.add(arrow(), None).unwrap().id, 3);
+    }
+
+    #[test]
+    fn test_invalid_shapes_are_rejected() {
+        let mut frame = frozen();
+        let outside = Shape::Arrow {
+            x1: 0.0,
+            y1: 0.0,
+            x2: 40.0,
+            y2: 0.0,
+        };
+        let flat = Shape::Circle {
+            x: 4.0,
+            y: 4.0,
+            radius: 0.0,
+        };
+        let huge = Shape::Circle {
+            x: 4.0,
+            y: 4.0,
+            radius: 1e30,
+        };
+        let blank = Shape::Text {
+            x: 4.0,
+            y: 4.0,
+            text: "  ".to_string(),
+        };
+        for shape in [outside, flat, huge, blank] {
+            assert!(matches!(
+                frame.add(shape, None),
+                Err(FreezeError::Invalid(_))
+            ));
+        }
+        assert!(frame.annotations.is_empty());
+    }
+
+    #[test]
+    fn test_render_leaves_the_frozen_frame_untouched() {
+        let mut frame = frozen();
+        frame.add(arrow(), None).unwrap();
+        let composite = frame.render();
+        assert!(composite.iter().any(|&b| b != 0));
+        assert!(frame.rgb.iter().all(|&b| b == 0));
+    }
+
+    #[test]
+    fn test_serializes_without_pixels() {
+        let mut frame = frozen();
+        frame
+            .add(
+                Shape::Text {
+                    x: 1.0,
+                    y: 2.0,
+                    text: "crack".to_string(),
+                },
+                None,
+            )
+            .unwrap();
+        let json = serde_json::to_value(&frame).unwrap();
+        assert_eq!(json["sequence"], 5);
+        assert_eq!(json["annotations"][0]["type"], "text");
+        assert_eq!(json["annotations"][0]["text"], "crack");
+        assert!(json.get("rgb").is_none());
+    }
+}
@@ -0,0 +1,265 @@
+//! Undo and redo of annotation and settings edits
+//!
+//! Commands that change frozen-frame annotations, the measurement calibration
+//! or display settings record the state before and after the change as a
+//! [`Snapshot`] pair. `undo` restores the earlier snapshot and `redo` the
+//! later one, so a mis-tap on a small screen costs one more tap rather than
+//! redrawing or recalibrating. [`History`] itself knows nothing about the
+//! snapshots; the caller applies them.
+//!
+//! An edit that can no longer be applied (e.g. annotations of a frame that is
+//! no longer frozen) fails with [`HistoryError::Stale`] and is dropped.
+
+use std::collections::VecDeque;
+
+use serde::Serialize;
+use thiserror::Error;
+
+use crate::calibration::Calibration;
+use crate::freeze::VectorAnnotation;
+use crate::measurement::LengthUnit;
+use crate::transform::Transform;
+use crate::zoom::Zoom;
+
+/// Most edits kept for undo
+pub const MAX_HISTORY: usize = 100;
+
+/// Errors undoing or redoing an edit
+#[derive(Debug, Error)]
+pub enum HistoryError {
+    /// What the edit changed is gone
+    #[error("the {0} being restored no longer exists")]
+    Stale(&'static str),
+}
+
+/// Result type alias for history operations
+pub type Result<T> = std::result::Result<T, HistoryError>;
+
+/// State restored by undo or redo
+#[derive(Debug, Clone, PartialEq)]
+pub enum Snapshot {
+    /// Annotations of the frozen frame with this sequence number
+    Annotations {
+        /// Sequence number of the frozen frame
+        sequence: u64,
+        /// Annotations in drawing order
+        annotations: Vec<VectorAnnotation>,
+    },
+    /// Measurement calibration
+    Calibration(Option<Calibration>),
+    /// Frame rotation and flips
+    Transform(Transform),
+    /// Unit measurements are reported in
+    MeasurementUnit(LengthUnit),
+    /// Digital zoom and pan
+    Zoom(Zoom),
+}
+
+/// A recorded change
+#[derive(Debug, Clone)]
+struct Edit<S> {
+    label: &'static str,
+    before: S,
+    after: S,
+}
+
+/// What can be undone and redone, returned by `undo`, `redo` and `get_history`
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct HistoryStatus {
+    /// Edits that can be undone
+    pub undo_count: usize,
+    /// Edits that can be redone
+    pub redo_count: usize,
+    /// What `undo` would revert (e.g. "annotation", "calibration")
+    pub undo_label: Option<&'static str>,
+    /// What `redo` would reapply
+    pub redo_label: Option<&'static str>,
+}
+
+/// Undo and redo stacks of before/after snapshots
+#[derive(Debug)]
+pub struct History<S> {
+    undo: VecDeque<Edit<S>>,
+    redo: Vec<Edit<S>>,
+    limit: usize,
+}
+
+impl<S> Default for History<S> {
+    fn default() -> Self {
+        Self::new(MAX_HISTORY)
+    }
+}
+
+impl<S> History<S> {
+    /// Empty history keeping at most `limit` edits
+    #[must_use]
+    pub fn new(limit: usize) -> Self {
+        Self {
+            undo: VecDeque::new(),
+            redo: Vec::new(),
+            limit: limit.max(1),
+        }
+    }
+
+    /// Record a change from `before` to `after`
+    ///
+    /// Clears the redo stack. Changes that leave the state as it was are not
+    /// recorded, and the oldest edit is dropped once the limit is reached.
+    pub fn record(&mut self, label: &'static str, before: S, after: S)
+    where
+        S: PartialEq,
+    {
+        if before == after {
+            return;
+        }
+        self.redo.clear();
+        if self.undo.len() == self.limit {
+            self.undo.pop_front();
+        }
+        self.undo.push_back(Edit {
+            label,
+            before,
+            after,
+        });
+    }
+
+    /// Restore the state before the latest edit with `apply`
+    ///
+    /// Returns the edit's label, or `None` if there is nothing to undo.
+    ///
+    /// # Errors
+    ///
+    /// Returns the error of `apply`; the edit is then dropped.
+    pub fn undo<E>(
+        &mut self,
+        apply: impl FnOnce(&S) -> std::result::Result<(), E>,
+    ) -> std::result::Result<Option<&'static str>, E> {
+        let Some(edit) = self.undo.pop_back() else {
+            return Ok(None);
+        };
+        apply(&edit.before)?;
+        let label = edit.label;
+        self.redo.push(edit);
+        Ok(Some(label))
+    }
+
+    /// Reapply the latest undone edit with `apply`
+    ///
+    /// Returns the edit's label, or `None` if there is nothing to redo.
+    ///
+    /// # Errors
+    ///
+    /// Returns the error of `apply`; the edit is then dropped.
+    pub fn redo<E>(
+        &mut self,
+        apply: impl FnOnce(&S) -> std::result::Result<(), E>,
+    ) -> std::result::Result<Option<&'static str>, E> {
+        let Some(edit) = self.redo.pop() else {
+            return Ok(None);
+        };
+        apply(&edit.after)?;
+        let label = edit.label;
+        self.undo.push_back(edit);
+        Ok(Some(label))
+    }
+
+    /// Forget all edits
+    pub fn clear(&mut self) {
+        self.undo.clear();
+        self.redo.clear();
+    }
+
+    /// What can be undone and redone
+    #[must_use]
+    pub fn status(&self) -> HistoryStatus {
+        HistoryStatus {
+            undo_count: self.undo.len(),
+            redo_count: self.redo.len(),
+            undo_label: self.undo.back().map(|edit| edit.label),
+            redo_label: self.redo.last().map(|edit| edit.label),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Apply snapshots to a plain value
+    fn set(value: &mut i32) -> impl FnOnce(&i32) -> std::result::Result<(), ()> + '_ {
+        move |snapshot| {
+            *value = *snapshot;
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_undo_and_redo() {
+        let mut history = History::default();
+        let mut value = 3;
+        history.record("a", 1, 2);
+        history.record("b", 2, 3);
+
+        assert_eq!(history.undo(set(&mut value)), Ok(Some("b")));
+        assert_eq!(value, 2);
+        assert_eq!(history.undo(set(&mut value)), Ok(Some("a")));
+        assert_eq!(value, 1);
+        assert_eq!(history.undo(set(&mut value)), Ok(None));
+
+        assert_eq!(history.redo(set(&mut value)), Ok(Some("a")));
+        assert_eq!(value, 2);
+        assert_eq!(
+            history.status(),
+            HistoryStatus {
+                undo_count: 1,
+                redo_count: 1,
+                undo_label: Some("a"),
+                redo_label: Some("b"),
+            }
+        );
+    }
+
+    #[test]
+    fn test_new_edit_clears_redo() {
+        let mut history = History::default();
+        let mut value = 0;
+        history.record("a", 0, 1);
+        history.undo(set(&mut value)).unwrap();
+        history.record("b", 0, 5);
+        assert_eq!(history.redo(set(&mut value)), Ok(None));
+        assert_eq!(history.status().undo_label, Some("b"));
+    }
+
+    #[test]
+    fn test_no_op_edits_and_limit() {
+        let mut history = History::new(2);
+        history.record("same", 4, 4);
+        assert_eq!(history.status().undo_count, 0);
+
+        for i in 0..5 {
+            history.record("step", i, i + 1);
+        }
+        let mut value = 5;
+        history.undo(set(&mut value)).unwrap();
+        history.undo(set(&mut value)).unwrap();
+        assert_eq!(value, 3);
+        assert_eq!(history.undo(set(&mut value)), Ok(None));
+    }
+
+    #[test]
+    fn test_failed_apply_drops_the_edit() {
+        let mut history = History::default();
+        history.record("a", 0, 1);
+        history.record("b", 1, 2);
+        assert_eq!(history.undo(|_| Err("gone")), Err("gone"));
+        assert_eq!(
+            history.status(),
+            HistoryStatus {
+                undo_count: 1,
+                redo_count: 0,
+                undo_label: Some("a"),
+                redo_label: None,
+            }
+        );
+    }
+}
@@ -0,0 +1,500 @@
+//! Still image encoders for snapshots and exports
+//!
+//! Frames in the frame buffer are either JPEG (MJPEG cameras) or RGB24
+//! (converted YUY2). [`encode_frame`] turns either into the requested
+//! [`ImageFormat`]: JPEG frames are passed through untouched when JPEG is
+//! requested, and are decoded to RGB for every other format.
+//!
+//! Each encoder sits behind a Cargo feature so builds only carry the codecs
+//! they need:
+//!
+//! | Format | Feature | Default | Notes |
+//! |--------|---------|---------|-------|
+//! | JPEG | `jpeg` | yes | Passthrough for MJPEG frames works without it |
+//! | PNG | `png` | yes | Lossless |
+//! | WebP | `webp` | no | Lossless |
+//! | AVIF | `avif` | no | Lossy, slow to encode |
+
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
+use thiserror::Error;
+
+use crate::frame_assembler::is_jpeg_data;
+
+/// JPEG quality used when re-encoding RGB frames
+pub const DEFAULT_JPEG_QUALITY: u8 = 90;
+
+/// AVIF quality (0-100)
+pub const DEFAULT_AVIF_QUALITY: f32 = 80.0;
+
+/// AVIF encoder speed (1 = slowest/best, 10 = fastest)
+pub const DEFAULT_AVIF_SPEED: u8 = 6;
+
+/// Errors that can occur while encoding an image
+#[derive(Error, Debug)]
+pub enum EncodeError {
+    /// The format's encoder was not compiled in
+    #[error("{0} encoding is not available in this build")]
+    Unsupported(ImageFormat),
+
+    /// Unknown format name
+    #[error("unknown image format: {0}")]
+    UnknownFormat(String),
+
+    /// Pixel data does not match the dimensions
+    #[error("invalid {width}x{height} RGB frame of {len} bytes")]
+    InvalidFrame {
+        /// Frame width in pixels
+        width: u32,
+        /// Frame height in pixels
+        height: u32,
+        /// Length of the pixel data
+        len: usize,
+    },
+
+    /// The JPEG frame could not be decoded
+    #[error("could not decode JPEG frame: {0}")]
+    Decode(String),
+
+    /// Writing the encoded image failed
+    #[error("I/O error: {0}")]
+    Io(#[from] std::io::Error),
+
+    /// The encoder failed
+    #[error("{format} encoding failed: {message}")]
+    Encode {
+        /// Format being encoded
+        format: ImageFormat,
+        /// Encoder error message
+        message: String,
+    },
+}
+
+/// Result type for encoding operations
+pub type Result<T> = std::result::Result<T, EncodeError>;
+
+/// Output image format
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ImageFormat {
+    /// JPEG (lossy)
+    #[default]
+    Jpeg,
+    /// PNG (lossless)
+    Png,
+    /// WebP (lossless)
+    WebP,
+    /// AVIF (lossy)
+    Avif,
+}
+
+impl ImageFormat {
+    /// Every format, compiled in or not
+    pub const ALL: &'static [ImageFormat] = &[
+        ImageFormat::Jpeg,
+        ImageFormat::Png,
+        ImageFormat::WebP,
+        ImageFormat::Avif,
+    ];
+
+    /// File extension (without the dot)
+    pub fn extension(self) -> &'static str {
+        match self {
+            ImageFormat::Jpeg => "jpg",
+            ImageFormat::Png => "png",
+            ImageFormat::WebP => "webp",
+            ImageFormat::Avif => "avif",
+        }
+    }
+
+    /// MIME type
+    pub fn mime_type(self) -> &'static str {
+        match self {
+            ImageFormat::Jpeg => "image/jpeg",
+            ImageFormat::Png => "image/png",
+            ImageFormat::WebP => "image/webp",
+            ImageFormat::Avif => "image/avif",
+        }
+    }
+
+    /// Whether RGB frames can be encoded to this format in this build
+    pub fn is_available(self) -> bool {
+        encoder_for(self).is_ok()
+    }
+
+    /// Formats whose encoder is compiled in
+    pub fn available() -> Vec<ImageFormat> {
+        Self::ALL
+            .iter()
+            .copied()
+            .filter(|f| f.is_available())
+            .collect()
+    }
+}
+
+impl fmt::Display for ImageFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            ImageFormat::Jpeg => "JPEG",
+            ImageFormat::Png => "PNG",
+            ImageFormat::WebP => "WebP",
+            ImageFormat::Avif => "AVIF",
+        })
+    }
+}
+
+impl FromStr for ImageFormat {
+    type Err = EncodeError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "jpeg" | "jpg" => Ok(ImageFormat::Jpeg),
+            "png" => Ok(ImageFormat::Png),
+            "webp" => Ok(ImageFormat::WebP),
+            "avif" => Ok(ImageFormat::Avif),
+            _ => Err(EncodeError::UnknownFormat(s.to_string())),
+        }
+    }
+}
+
+/// Encodes RGB24 frames into a still image format
+pub trait ImageEncoder: Send + Sync {
+    /// Format produced by this encoder
+    fn format(&self) -> ImageFormat;
+
+    /// Encode `rgb` (3 bytes per pixel, row-major) into an image file
+    ///
+    /// # Errors
+    ///
+    /// Returns `EncodeError::Encode` if the encoder fails. Callers are expected
+    /// to have validated the frame size (see [`encode_frame`]).
+    fn encode(&self, rgb: &[u8], width: u32, height: u32) -> Result<Vec<u8>>;
+}
+
+/// Baseline JPEG encoder
+#[cfg(feature = "jpeg")]
+#[derive(Debug, Clone, Copy)]
+pub struct JpegEncoder {
+    /// Quality (1-100)
+    pub quality: u8,
+}
+
+#[cfg(feature = "jpeg")]
+impl Default for JpegEncoder {
+    fn default() -> Self {
+        Self {
+            quality: DEFAULT_JPEG_QUALITY,
+        }
+    }
+}
+
+#[cfg(feature = "jpeg")]
+impl ImageEncoder for JpegEncoder {
+    fn format(&self) -> ImageFormat {
+        ImageFormat::Jpeg
+    }
+
+    fn encode(&self, rgb: &[u8], width: u32, height: u32) -> Result<Vec<u8>> {
+        let (width, height) = jpeg_dimensions(width, height)?;
+        let mut out = Vec::new();
+        jpeg_encoder::Encoder::new(&mut out, self.quality)
+            .encode(rgb, width, height, jpeg_encoder::ColorType::Rgb)
+            .map_err(|e| encode_error(ImageFormat::Jpeg, e))?;
+        Ok(out)
+    }
+}
+
+/// JPEG dimensions are limited to 16 bits
+#[cfg(feature = "jpeg")]
+fn jpeg_dimensions(width: u32, height: u32) -> Result<(u16, u16)> {
+    match (u16::try_from(width), u16::try_from(height)) {
+        (Ok(w), Ok(h)) => Ok((w, h)),
+        _ => Err(EncodeError::Encode {
+            format: ImageFormat::Jpeg,
+            message: format!("{}x{} exceeds the JPEG size limit", width, height),
+        }),
+    }
+}
+
+/// Lossless PNG encoder
+#[cfg(feature = "png")]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct PngEncoder;
+
+#[cfg(feature = "png")]
+impl ImageEncoder for PngEncoder {
+    fn format(&self) -> ImageFormat {
+        ImageFormat::Png
+    }
+
+    fn encode(&self, rgb: &[u8], width: u32, height: u32) -> Result<Vec<u8>> {
+        let mut out = Vec::new();
+        let mut encoder = png::Encoder::new(&mut out, width, height);
+        encoder.set_color(png::ColorType::Rgb);
+        encoder.set_depth(png::BitDepth::Eight);
+        let mut writer = encoder
+            .write_header()
+            .map_err(|e| encode_error(ImageFormat::Png, e))?;
+        writer
+            .write_image_data(rgb)
+            .map_err(|e| encode_error(ImageFormat::Png, e))?;
+        writer
+            .finish()
+            .map_err(|e| encode_error(ImageFormat::Png, e))?;
+        Ok(out)
+    }
+}
+
+/// Lossless WebP encoder
+#[cfg(feature = "webp")]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct WebPEncoder;
+
+#[cfg(feature = "webp")]
+impl ImageEncoder for WebPEncoder {
+    fn format(&self) -> ImageFormat {
+        ImageFormat::WebP
+    }
+
+    fn encode(&self, rgb: &[u8], width: u32, height: u32) -> Result<Vec<u8>> {
+        let mut out = Vec::new();
+        image_webp::WebPEncoder::new(&mut out)
+            .encode(rgb, width, height, image_webp::ColorType::Rgb8)
+            .map_err(|e| encode_error(ImageFormat::WebP, e))?;
+        Ok(out)
+    }
+}
+
+/// AVIF encoder
+#[cfg(feature = "avif")]
+#[derive(Debug, Clone, Copy)]
+pub struct AvifEncoder {
+    /// Quality (0-100)
+    pub quality: f32,
+    /// Speed (1 = slowest/best, 10 = fastest)
+    pub speed: u8,
+}
+
+#[cfg(feature = "avif")]
+impl Default for AvifEncoder {
+    fn default() -> Self {
+        Self {
+            quality: DEFAULT_AVIF_QUALITY,
+            speed: DEFAULT_AVIF_SPEED,
+        }
+    }
+}
+
+#[cfg(feature = "avif")]
+impl ImageEncoder for AvifEncoder {
+    fn format(&self) -> ImageFormat {
+        ImageFormat::Avif
+    }
+
+    fn encode(&self, rgb: &[u8], width: u32, height: u32) -> Result<Vec<u8>> {
+        let pixels: Vec<rgb::RGB8> = rgb
+            .chunks_exact(3)
+            .map(|p| rgb::RGB8::new(p[0], p[1], p[2]))
+            .collect();
+        let image = imgref::Img::new(pixels.as_slice(), width as usize, height as usize);
+        let encoded = ravif::Encoder::new()
+            .with_quality(self.quality)
+            .with_speed(self.speed)
+            .encode_rgb(image)
+            .map_err(|e| encode_error(ImageFormat::Avif, e))?;
+        Ok(encoded.avif_file)
+    }
+}
+
+/// Encoder for `format`, if it is compiled in
+///
+/// # Errors
+///
+/// Returns `EncodeError::Unsupported` if the format's feature is disabled.
+pub fn encoder_for(format: ImageFormat) -> Result<Box<dyn ImageEncoder>> {
+    match format {
+        #[cfg(feature = "jpeg")]
+        ImageFormat::Jpeg => Ok(Box::new(JpegEncoder::default())),
+        #[cfg(feature = "png")]
+        ImageFormat::Png => Ok(Box::new(PngEncoder)),
+        #[cfg(feature = "webp")]
+        ImageFormat::WebP => Ok(Box::new(WebPEncoder)),
+        #[cfg(feature = "avif")]
+        ImageFormat::Avif => Ok(Box::new(AvifEncoder::default())),
+        #[allow(unreachable_patterns)]
+        _ => Err(EncodeError::Unsupported(format)),
+    }
+}
+
+/// Encode a frame-buffer frame (JPEG or RGB24) as `format`
+///
+/// JPEG frames requested as JPEG are returned unchanged, without re-encoding.
+///
+/// # Errors
+///
+/// Returns `EncodeError::Unsupported` if the encoder is not compiled in,
+/// `EncodeError::InvalidFrame` if an RGB frame does not match its dimensions,
+/// `EncodeError::Decode` if a JPEG frame cannot be decoded, or
+/// `EncodeError::Encode` if encoding fails.
+pub fn encode_frame(frame: &[u8], width: u32, height: u32, format: ImageFormat) -> Result<Vec<u8>> {
+    if is_jpeg_data(frame) {
+        if format == ImageFormat::Jpeg {
+            return Ok(frame.to_vec());
+        }
+        let encoder = encoder_for(format)?;
+        let (rgb, width, height) = decode_jpeg(frame)?;
+        return encoder.encode(&rgb, width, height);
+    }
+
+    let encoder = encoder_for(format)?;
+    check_rgb_frame(frame, width, height)?;
+    encoder.encode(frame, width, height)
+}
+
+/// Encode a frame-buffer frame (JPEG or RGB24) as JPEG at `quality` (1-100)
+///
+/// JPEG frames are returned unchanged, like [`encode_frame`] does.
+///
+/// # Errors
+///
+/// Returns `EncodeError::Unsupported` without the `jpeg` feature,
+/// `EncodeError::InvalidFrame` if an RGB frame does not match its dimensions,
+/// or `EncodeError::Encode` if encoding fails.
+pub fn encode_jpeg(frame: &[u8], width: u32, height: u32, quality: u8) -> Result<Vec<u8>> {
+    if is_jpeg_data(frame) {
+        return Ok(frame.to_vec());
+    }
+
+    #[cfg(feature = "jpeg")]
+    {
+        check_rgb_frame(frame, width, height)?;
+        JpegEncoder { quality }.encode(frame, width, height)
+    }
+    #[cfg(not(feature = "jpeg"))]
+    {
+        let _ = (width, height, quality);
+        Err(EncodeError::Unsupported(ImageFormat::Jpeg))
+    }
+}
+
+/// Decode a frame-buffer frame (JPEG or RGB24) to RGB24
+///
+/// Returns the pixels with their dimensions, which for JPEG frames come from
+/// the JPEG header rather than `width`/`height`.
+///
+/// # Errors
+///
+/// Returns `EncodeError::Decode` if a JPEG frame cannot be decoded, or
+/// `EncodeError::InvalidFrame` if an RGB frame does not match its dimensions.
+pub fn decode_frame(frame: &[u8], width: u32, height: u32) -> Result<(Vec<u8>, u32, u32)> {
+    if is_jpeg_data(frame) {
+        return decode_jpeg(frame);
+    }
+    check_rgb_frame(frame, width, height)?;
+    Ok((frame.to_vec(), width, height))
+}
+
+/// Check that an RGB24 frame has exactly `width * height` pixels
+fn check_rgb_frame(frame: &[u8], width: u32, height: u32) -> Result<()> {
+    let expected = u64::from(width) * u64::from(height) * 3;
+    if width == 0 || height == 0 || frame.len() as u64 != expected {
+        return Err(EncodeError::InvalidFrame {
+            width,
+            height,
+            len: frame.len(),
+        });
+    }
+    Ok(())
+}
+
+/// Decode a JPEG frame to RGB24, returning the pixels and dimensions
+fn decode_jpeg(data: &[u8]) -> Result<(Vec<u8>, u32, u32)> {
+    let frame = crate::jpeg_decode::decode(data).map_err(|e| EncodeError::Decode(e.to_string()))?;
+    Ok((frame.rgb, frame.width, frame.height))
+}
+
+/// Wrap an encoder error
+fn encode_error(format: ImageFormat, err: impl fmt::Display) -> EncodeError {
+    EncodeError::Encode {
+        format,
+        message: err.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_names() {
+        for &format in ImageFormat::ALL {
+            assert_eq!(format.extension().parse::<ImageFormat>().unwrap(), format);
+            let json = serde_json::to_value(format).unwrap();
+            assert_eq!(json, format.extension().replace("jpg", "jpeg"));
+        }
+        assert!(matches!(
+            "gif".parse::<ImageFormat>(),
+            Err(EncodeError::UnknownFormat(_))
+        ));
+    }
+
+    #[test]
+    fn test_jpeg_passthrough() {
+        let jpeg = [0xFF, 0xD8, 0xFF, 0xDB, 0x00, 0x02, 0xFF, 0xD9];
+        assert_eq!(encode_frame(&jpeg, 0, 0, ImageFormat::Jpeg).unwrap(), jpeg);
+    }
+
+    #[cfg(feature = "jpeg")]
+    #[test]
+    fn test_jpeg_quality_is_applied() {
+        let rgb: Vec<u8> = (0..64 * 64 * 3).map(|i| (i * 7 % 251) as u8).collect();
+        let high = encode_jpeg(&rgb, 64, 64, 95).unwrap();
+        let low = encode_jpeg(&rgb, 64, 64, 30).unwrap();
+        assert!(is_jpeg_data(&low));
+        assert!(low.len() < high.len());
+        assert_eq!(
+            encode_jpeg(&rgb, 64, 64, DEFAULT_JPEG_QUALITY).unwrap(),
+            encode_frame(&rgb, 64, 64, ImageFormat::Jpeg).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_rgb_frame_size_is_checked() {
+        let result = encode_frame(&[0u8; 10], 2, 2, ImageFormat::Png);
+        assert!(matches!(
+            result,
+            Err(EncodeError::InvalidFrame { len: 10, .. }) | Err(EncodeError::Unsupported(_))
+        ));
+    }
+
+    #[cfg(feature = "png")]
+    #[test]
+    fn test_png_round_trip() {
+        let rgb: Vec<u8> = (0..4 * 3 * 3).map(|i| i as u8).collect();
+        let encoded = encode_frame(&rgb, 4, 3, ImageFormat::Png).unwrap();
+
+        let decoder = png::Decoder::new(encoded.as_slice());
+        let mut reader = decoder.read_info().unwrap();
+        let mut decoded = vec![0u8; reader.output_buffer_size()];
+        let info = reader.next_frame(&mut decoded).unwrap();
+        assert_eq!((info.width, info.height), (4, 3));
+        assert_eq!(info.color_type, png::ColorType::Rgb);
+        assert_eq!(&decoded[..info.buffer_size()], rgb.as_slice());
+    }
+
+    #[test]
+    fn test_unavailable_formats_are_reported() {
+        for &format in ImageFormat::ALL {
+            let rgb = [0u8; 3];
+            match encode_frame(&rgb, 1, 1, format) {
+                Ok(_) => assert!(format.is_available()),
+                Err(EncodeError::Unsupported(f)) => {
+                    assert_eq!(f, format);
+                    assert!(!ImageFormat::available().contains(&format));
+                }
+                Err(e) => panic!("{} failed: {}", format, e),
+            }
+        }
+    }
+}
@@ -0,0 +1,514 @@
+//! On-device defect detection with a user-supplied ONNX model
+//!
+//! A background worker runs the model on downscaled copies of stored frames
+//! and publishes detections as boxes on the annotation bus, like plugins do.
+//! Models are read from a local file and run with `tract`, a pure Rust
+//! inference engine: nothing is downloaded and no frame leaves the device.
+//!
+//! The model must take one `[1, 3, S, S]` float input (RGB, 0.0 to 1.0, where
+//! `S` is [`InferenceConfig::input_size`]) and return detections as rows of
+//! six floats, `[x1, y1, x2, y2, score, class]` in input pixels, the layout of
+//! YOLO exports with NMS included. Frames arriving while the model is busy are
+//! skipped, so a slow model lowers the detection rate, not the frame rate.
+//!
+//! Running a model needs the `inference` feature. Without it the types are
+//! still available but [`Detector::start`] fails with
+//! [`InferenceError::Disabled`].
+
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::mpsc::{self, SyncSender, TrySendError};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::annotations::{Annotation, AnnotationKind, FrameAnnotations};
+use crate::Frame;
+
+/// Default model input width and height in pixels
+pub const DEFAULT_INPUT_SIZE: u32 = 320;
+
+/// Default minimum score for a detection to be reported
+pub const DEFAULT_CONFIDENCE_THRESHOLD: f32 = 0.5;
+
+/// Values per detection row (`x1, y1, x2, y2, score, class`)
+const DETECTION_LEN: usize = 6;
+
+/// Errors starting or running a model
+#[derive(Debug, Error)]
+pub enum InferenceError {
+    /// The model file could not be read
+    #[error("model I/O error: {0}")]
+    Io(#[from] std::io::Error),
+    /// The model could not be loaded or run
+    #[error("model error: {0}")]
+    Model(String),
+    /// The configuration is unusable
+    #[error("invalid inference config: {0}")]
+    InvalidConfig(String),
+    /// A model is already running
+    #[error("inference is already running")]
+    AlreadyRunning,
+    /// No model is running
+    #[error("inference is not running")]
+    NotRunning,
+    /// Inference support is not compiled in
+    #[error("inference support is not compiled in (enable the `inference` feature)")]
+    Disabled,
+}
+
+/// Result type alias for inference operations
+pub type Result<T> = std::result::Result<T, InferenceError>;
+
+/// Model settings passed to `start_inference`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct InferenceConfig {
+    /// ONNX model file, resolved inside the output directory
+    pub model_path: String,
+    /// Model input width and height; frames are scaled to this square
+    pub input_size: u32,
+    /// Minimum score for a detection to be reported
+    pub confidence_threshold: f32,
+    /// Class names by class index (detections of other classes are labelled
+    /// with their index)
+    pub labels: Vec<String>,
+}
+
+impl Default for InferenceConfig {
+    fn default() -> Self {
+        Self {
+            model_path: String::new(),
+            input_size: DEFAULT_INPUT_SIZE,
+            confidence_threshold: DEFAULT_CONFIDENCE_THRESHOLD,
+            labels: Vec::new(),
+        }
+    }
+}
+
+impl InferenceConfig {
+    /// Check the settings that don't need the model
+    ///
+    /// # Errors
+    ///
+    /// Returns `InferenceError::InvalidConfig` for a missing model path, an
+    /// input size outside 16..=2048, or a threshold outside 0.0..=1.0.
+    pub fn validate(&self) -> Result<()> {
+        if self.model_path.is_empty() {
+            return Err(InferenceError::InvalidConfig("no model path".to_string()));
+        }
+        if !(16..=2048).contains(&self.input_size) {
+            return Err(InferenceError::InvalidConfig(format!(
+                "input size {} is outside 16..=2048",
+                self.input_size
+            )));
+        }
+        if !(0.0..=1.0).contains(&self.confidence_threshold) {
+            return Err(InferenceError::InvalidConfig(format!(
+                "confidence threshold {} is outside 0.0..=1.0",
+                self.confidence_threshold
+            )));
+        }
+        Ok(())
+    }
+
+    fn label(&self, class: usize) -> String {
+        self.labels
+            .get(class)
+            .cloned()
+            .unwrap_or_else(|| class.to_string())
+    }
+}
+
+/// Detector state returned by the inference commands
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct InferenceStatus {
+    /// Whether a model is running
+    pub running: bool,
+    /// Model file of the running (or last) model
+    pub model: Option<String>,
+    /// Frames the model was run on
+    pub frames_processed: u64,
+    /// Frames skipped because the model was busy
+    pub frames_skipped: u64,
+    /// Detections reported
+    pub detections: u64,
+    /// Last error running the model
+    pub last_error: Option<String>,
+    /// Whether this build can run models (the `inference` feature)
+    pub available: bool,
+}
+
+/// Scale an RGB24 frame to a `size` x `size` NCHW float tensor (0.0 to 1.0)
+///
+/// Uses nearest-neighbour sampling, which is plenty for detection and keeps
+/// the per-frame cost negligible. Returns `None` if `rgb` is smaller than
+/// `width * height * 3`.
+#[must_use]
+pub fn frame_to_tensor(rgb: &[u8], width: u32, height: u32, size: u32) -> Option<Vec<f32>> {
+    let (width, height, size) = (width as usize, height as usize, size as usize);
+    if width == 0 || height == 0 || rgb.len() < width * height * 3 {
+        return None;
+    }
+    let plane = size * size;
+    let mut tensor = vec![0.0; plane * 3];
+    for y in 0..size {
+        let row = (y * height / size) * width;
+        for x in 0..size {
+            let pixel = (row + x * width / size) * 3;
+            for channel in 0..3 {
+                tensor[channel * plane + y * size + x] = f32::from(rgb[pixel + channel]) / 255.0;
+            }
+        }
+    }
+    Some(tensor)
+}
+
+/// Turn model output rows into annotations in frame pixels
+///
+/// Rows scoring below the threshold are dropped; boxes are scaled from the
+/// `input_size` square to the frame and clamped to it.
+#[must_use]
+pub fn decode_detections(
+    output: &[f32],
+    config: &InferenceConfig,
+    model: &str,
+    width: u32,
+    height: u32,
+) -> Vec<Annotation> {
+    let scale_x = width as f32 / config.input_size as f32;
+    let scale_y = height as f32 / config.input_size as f32;
+    let clamp = |value: f32, max: u32| (value.max(0.0) as u32).min(max);
+
+    output
+        .chunks_exact(DETECTION_LEN)
+        .filter(|row| row[4] >= config.confidence_threshold)
+        .map(|row| {
+            let x1 = clamp(row[0] * scale_x, width);
+            let y1 = clamp(row[1] * scale_y, height);
+            let x2 = clamp(row[2] * scale_x, width);
+            let y2 = clamp(row[3] * scale_y, height);
+            Annotation {
+                source: model.to_string(),
+                label: config.label(row[5].max(0.0) as usize),
+                confidence: row[4].clamp(0.0, 1.0),
+                kind: AnnotationKind::Box {
+                    x: x1,
+                    y: y1,
+                    width: x2.saturating_sub(x1),
+                    height: y2.saturating_sub(y1),
+                },
+            }
+        })
+        .collect()
+}
+
+/// A loaded model, ready to run
+#[cfg(feature = "inference")]
+struct Model {
+    plan: tract_onnx::prelude::TypedRunnableModel<tract_onnx::prelude::TypedModel>,
+    size: usize,
+}
+
+#[cfg(feature = "inference")]
+impl Model {
+    fn load(path: &Path, size: u32) -> Result<Self> {
+        use tract_onnx::prelude::*;
+
+        let size = size as usize;
+        let model_error = |e: TractError| InferenceError::Model(e.to_string());
+        let plan = tract_onnx::onnx()
+            .model_for_read(&mut std::fs::File::open(path)?)
+            .map_err(model_error)?
+            .with_input_fact(0, f32::fact([1, 3, size, size]).into())
+            .map_err(model_error)?
+            .into_optimized()
+            .map_err(model_error)?
+            .into_runnable()
+            .map_err(model_error)?;
+        Ok(Self { plan, size })
+    }
+
+    fn run(&self, tensor: Vec<f32>) -> Result<Vec<f32>> {
+        use tract_onnx::prelude::*;
+
+        let model_error = |e: TractError| InferenceError::Model(e.to_string());
+        let input =
+            Tensor::from_shape(&[1, 3, self.size, self.size], &tensor).map_err(model_error)?;
+        let outputs = self.plan.run(tvec!(input.into())).map_err(model_error)?;
+        let output = outputs[0].to_array_view::<f32>().map_err(model_error)?;
+        Ok(output.iter().copied().collect())
+    }
+}
+
+#[cfg(not(feature = "inference"))]
+struct Model;
+
+#[cfg(not(feature = "inference"))]
+impl Model {
+    fn load(_path: &Path, _size: u32) -> Result<Self> {
+        Err(InferenceError::Disabled)
+    }
+
+    fn run(&self, _tensor: Vec<f32>) -> Result<Vec<f32>> {
+        Err(InferenceError::Disabled)
+    }
+}
+
+/// Counters shared with the worker thread
+#[derive(Default)]
+struct Counters {
+    frames_processed: AtomicU64,
+    frames_skipped: AtomicU64,
+    detections: AtomicU64,
+    last_error: Mutex<Option<String>>,
+}
+
+struct Worker {
+    sender: SyncSender<Arc<Frame>>,
+    handle: JoinHandle<()>,
+}
+
+#[derive(Default)]
+struct DetectorState {
+    worker: Option<Worker>,
+    model: Option<PathBuf>,
+}
+
+/// Runs a model on stored frames in the background
+#[derive(Default)]
+pub struct Detector {
+    state: Mutex<DetectorState>,
+    counters: Arc<Counters>,
+}
+
+impl Detector {
+    /// Create a detector without a model
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Load the model at `path` and start running it on offered frames
+    ///
+    /// `on_detections` is called on the worker thread for every frame with at
+    /// least one detection.
+    ///
+    /// # Errors
+    ///
+    /// Returns `InferenceError` if the config is invalid, a model is already
+    /// running, or the model can't be loaded (`Disabled` without the
+    /// `inference` feature).
+    pub fn start<F>(&self, path: &Path, config: InferenceConfig, on_detections: F) -> Result<()>
+    where
+        F: Fn(FrameAnnotations) + Send + 'static,
+    {
+        config.validate()?;
+        if crate::lock_or_recover(&self.state).worker.is_some() {
+            return Err(InferenceError::AlreadyRunning);
+        }
+        // Loading can take seconds; frames keep being offered meanwhile
+        let model = Model::load(path, config.input_size)?;
+        let mut state = crate::lock_or_recover(&self.state);
+        if state.worker.is_some() {
+            return Err(InferenceError::AlreadyRunning);
+        }
+        let name = path
+            .file_stem()
+            .map(|s| s.to_string_lossy().to_string())
+            .unwrap_or_default();
+        log::info!("Loaded inference model {}", path.display());
+
+        // Capacity 1: one frame waits while the model runs, newer ones are skipped
+        let (sender, frames) = mpsc::sync_channel::<Arc<Frame>>(1);
+        let counters = Arc::clone(&self.counters);
+        counters.frames_processed.store(0, Ordering::Relaxed);
+        counters.frames_skipped.store(0, Ordering::Relaxed);
+        counters.detections.store(0, Ordering::Relaxed);
+        *crate::lock_or_recover(&counters.last_error) = None;
+
+        let handle = thread::spawn(move || {
+            for frame in frames {
+                match detect(&model, &config, &name, &frame) {
+                    Ok(annotations) => {
+                        counters.frames_processed.fetch_add(1, Ordering::Relaxed);
+                        if !annotations.is_empty() {
+                            counters
+                                .detections
+                                .fetch_add(annotations.len() as u64, Ordering::Relaxed);
+                            on_detections(FrameAnnotations {
+                                sequence: frame.sequence,
+                                annotations,
+                            });
+                        }
+                    }
+                    Err(e) => {
+                        log::debug!("Inference failed on frame {}: {}", frame.sequence, e);
+                        *crate::lock_or_recover(&counters.last_error) = Some(e.to_string());
+                    }
+                }
+            }
+        });
+        state.worker = Some(Worker { sender, handle });
+        state.model = Some(path.to_path_buf());
+        Ok(())
+    }
+
+    /// Stop the worker, waiting for the frame it is running on
+    ///
+    /// # Errors
+    ///
+    /// Returns `InferenceError::NotRunning` if no model is running.
+    pub fn stop(&self) -> Result<()> {
+        let worker = crate::lock_or_recover(&self.state)
+            .worker
+            .take()
+            .ok_or(InferenceError::NotRunning)?;
+        drop(worker.sender);
+        let _ = worker.handle.join();
+        Ok(())
+    }
+
+    /// Whether a model is running
+    #[must_use]
+    pub fn is_running(&self) -> bool {
+        crate::lock_or_recover(&self.state).worker.is_some()
+    }
+
+    /// Queue a frame for the model, skipping it if the model is busy
+    pub fn offer(&self, frame: Arc<Frame>) {
+        let state = crate::lock_or_recover(&self.state);
+        let Some(worker) = &state.worker else {
+            return;
+        };
+        if let Err(TrySendError::Full(_)) = worker.sender.try_send(frame) {
+            self.counters.frames_skipped.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    /// Current detector state
+    #[must_use]
+    pub fn status(&self) -> InferenceStatus {
+        let state = crate::lock_or_recover(&self.state);
+        InferenceStatus {
+            running: state.worker.is_some(),
+            model: state
+                .model
+                .as_ref()
+                .map(|p| p.to_string_lossy().to_string()),
+            frames_processed: self.counters.frames_processed.load(Ordering::Relaxed),
+            frames_skipped: self.counters.frames_skipped.load(Ordering::Relaxed),
+            detections: self.counters.detections.load(Ordering::Relaxed),
+            last_error: crate::lock_or_recover(&self.counters.last_error).clone(),
+            available: cfg!(feature = "inference"),
+        }
+    }
+}
+
+/// Run the model on one stored frame (decoding MJPEG frames first)
+fn detect(
+    model: &Model,
+    config: &InferenceConfig,
+    name: &str,
+    frame: &Frame,
+) -> Result<Vec<Annotation>> {
+    let (rgb, width, height) =
+        crate::image_encoder::decode_frame(&frame.data, frame.width, frame.height)
+            .map_err(|e| InferenceError::Model(e.to_string()))?;
+    let tensor = frame_to_tensor(&rgb, width, height, config.input_size)
+        .ok_or_else(|| InferenceError::Model("frame is smaller than its dimensions".to_string()))?;
+    let output = model.run(tensor)?;
+    Ok(decode_detections(&output, config, name, width, height))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(size: u32) -> InferenceConfig {
+        InferenceConfig {
+            model_path: "model.onnx".to_string(),
+            input_size: size,
+            labels: vec!["crack".to_string(), "pore".to_string()],
+            ..InferenceConfig::default()
+        }
+    }
+
+    #[test]
+    fn test_frame_to_tensor_is_planar_and_normalized() {
+        // 2x1 frame: red, blue
+        let rgb = [255, 0, 0, 0, 0, 255];
+        let tensor = frame_to_tensor(&rgb, 2, 1, 2).unwrap();
+        // R plane, G plane, B plane; each row repeats the single source row
+        assert_eq!(&tensor[0..4], &[1.0, 0.0, 1.0, 0.0]);
+        assert_eq!(&tensor[4..8], &[0.0; 4]);
+        assert_eq!(&tensor[8..12], &[0.0, 1.0, 0.0, 1.0]);
+
+        assert!(frame_to_tensor(&rgb, 4, 4, 2).is_none());
+        assert!(frame_to_tensor(&[], 0, 0, 2).is_none());
+    }
+
+    #[test]
+    fn test_decode_detections_scales_and_filters() {
+        let output = [
+            10.0, 20.0, 50.0, 60.0, 0.9, 1.0, // kept
+            0.0, 0.0, 10.0, 10.0, 0.2, 0.0, // below threshold
+            90.0, 90.0, 150.0, 150.0, 0.7, 7.0, // clamped, unknown class
+        ];
+        let annotations = decode_detections(&output, &config(100), "weld", 200, 100);
+        assert_eq!(annotations.len(), 2);
+        assert_eq!(annotations[0].label, "pore");
+        assert_eq!(annotations[0].source, "weld");
+        assert_eq!(
+            annotations[0].kind,
+            AnnotationKind::Box {
+                x: 20,
+                y: 20,
+                width: 80,
+                height: 40
+            }
+        );
+        assert_eq!(annotations[1].label, "7");
+        assert_eq!(
+            annotations[1].kind,
+            AnnotationKind::Box {
+                x: 180,
+                y: 90,
+                width: 20,
+                height: 10
+            }
+        );
+    }
+
+    #[test]
+    fn test_config_validation() {
+        assert!(config(320).validate().is_ok());
+        assert!(InferenceConfig::default().validate().is_err());
+        assert!(config(4).validate().is_err());
+        let mut threshold = config(320);
+        threshold.confidence_threshold = 1.5;
+        assert!(threshold.validate().is_err());
+
+        let parsed: InferenceConfig = serde_json::from_str(r#"{"model_path": "m.onnx"}"#).unwrap();
+        assert_eq!(parsed.input_size, DEFAULT_INPUT_SIZE);
+    }
+
+    #[test]
+    fn test_detector_ignores_frames_when_stopped() {
+        let detector = Detector::new();
+        assert!(matches!(detector.stop(), Err(InferenceError::NotRunning)));
+        detector.offer(Arc::new(Frame {
+            data: Arc::new(vec![0; 12]),
+            raw: Vec::new(),
+            timestamp: std::time::Instant::now(),
+            width: 2,
+            height: 2,
+            sequence: 1,
+        }));
+        let status = detector.status();
+        assert!(!status.running);
+        assert_eq!(status.frames_skipped, 0);
+    }
+}
@@ -0,0 +1,227 @@
+//! JNI call helpers with Java exception handling
+//!
+//! A JNI call that throws leaves a pending Java exception on the thread, and
+//! every later call fails until it is cleared. Chaining calls with `.ok()?`
+//! therefore loses both the call that failed and the reason. The helpers here
+//! wrap the common call patterns, log and clear pending exceptions, and return
+//! a [`JniError`] naming the call and the Java exception.
+
+use jni::objects::{JObject, JString, JValue};
+use jni::{JNIEnv, JavaVM};
+use ndk_context::android_context;
+use thiserror::Error;
+
+/// Java exception thrown when an Android permission is missing
+const SECURITY_EXCEPTION: &str = "java.lang.SecurityException";
+
+/// Failed JNI call
+#[derive(Error, Debug)]
+pub enum JniError {
+    /// The Java method threw an exception
+    #[error("{call} threw {class}: {message}")]
+    Exception {
+        /// Java method or JNI function that failed
+        call: String,
+        /// Fully qualified exception class name
+        class: String,
+        /// `Throwable.getMessage()`, empty if there is none
+        message: String,
+    },
+
+    /// The Java method returned null where an object was required
+    #[error("{call} returned null")]
+    NullResult {
+        /// Java method that returned null
+        call: String,
+    },
+
+    /// The JNI call failed without a Java exception (bad signature, VM error, ...)
+    #[error("{call} failed: {source}")]
+    Jni {
+        /// Java method or JNI function that failed
+        call: String,
+        /// Underlying `jni` crate error
+        #[source]
+        source: jni::errors::Error,
+    },
+}
+
+impl JniError {
+    /// Whether the call failed because an Android permission is missing
+    pub fn is_permission_denied(&self) -> bool {
+        matches!(self, JniError::Exception { class, .. } if class == SECURITY_EXCEPTION)
+    }
+}
+
+/// Convert a failed JNI call into a [`JniError`], logging and clearing any
+/// pending Java exception
+pub fn take_error(env: &mut JNIEnv, call: &str, err: jni::errors::Error) -> JniError {
+    if !env.exception_check().unwrap_or(false) {
+        log::error!("JNI {} failed: {}", call, err);
+        return JniError::Jni {
+            call: call.to_string(),
+            source: err,
+        };
+    }
+
+    let throwable = env.exception_occurred();
+    // The exception must be cleared before calling back into Java to describe it
+    let _ = env.exception_clear();
+    let (class, message) = match throwable {
+        Ok(throwable) if !throwable.is_null() => describe_throwable(env, &throwable),
+        _ => (String::from("<unknown>"), String::new()),
+    };
+
+    log::error!("JNI {} threw {}: {}", call, class, message);
+    JniError::Exception {
+        call: call.to_string(),
+        class,
+        message,
+    }
+}
+
+/// Class name and message of a caught `Throwable`
+fn describe_throwable(env: &mut JNIEnv, throwable: &JObject) -> (String, String) {
+    let class = throwable_class_name(env, throwable).unwrap_or_else(|_| String::from("<unknown>"));
+    let message = env
+        .call_method(throwable, "getMessage", "()Ljava/lang/String;", &[])
+        .and_then(|v| v.l())
+        .and_then(|s| read_java_string(env, &s))
+        .unwrap_or_default();
+
+    // Describing the exception must not leave another one pending
+    if env.exception_check().unwrap_or(false) {
+        let _ = env.exception_clear();
+    }
+    (class, message)
+}
+
+/// `throwable.getClass().getName()`
+fn throwable_class_name(env: &mut JNIEnv, throwable: &JObject) -> jni::errors::Result<String> {
+    let class = env
+        .call_method(throwable, "getClass", "()Ljava/lang/Class;", &[])?
+        .l()?;
+    let name = env
+        .call_method(&class, "getName", "()Ljava/lang/String;", &[])?
+        .l()?;
+    read_java_string(env, &name)
+}
+
+/// Read a `java.lang.String`, mapping null to an empty string
+fn read_java_string(env: &mut JNIEnv, obj: &JObject) -> jni::errors::Result<String> {
+    if obj.is_null() {
+        return Ok(String::new());
+    }
+    let jstring: &JString = obj.into();
+    Ok(env.get_string(jstring)?.into())
+}
+
+/// Run a closure with a JNI env attached to the current thread and the current activity
+pub fn with_activity<T, E: From<JniError>>(
+    f: impl FnOnce(&mut JNIEnv, &JObject) -> Result<T, E>,
+) -> Result<T, E> {
+    let ctx = android_context();
+    // SAFETY: ctx.vm() returns a valid JNI JavaVM pointer from the Android runtime.
+    let vm = unsafe { JavaVM::from_raw(ctx.vm().cast()) }.map_err(|e| JniError::Jni {
+        call: String::from("JavaVM::from_raw"),
+        source: e,
+    })?;
+    // SAFETY: ctx.context() returns a valid Android Activity jobject reference.
+    let activity = unsafe { JObject::from_raw(ctx.context().cast()) };
+
+    let mut env = vm.attach_current_thread().map_err(|e| JniError::Jni {
+        call: String::from("AttachCurrentThread"),
+        source: e,
+    })?;
+    f(&mut env, &activity)
+}
+
+/// Call a Java method returning an object, which may be null
+pub fn call_object<'a>(
+    env: &mut JNIEnv<'a>,
+    obj: &JObject,
+    name: &str,
+    sig: &str,
+    args: &[JValue],
+) -> Result<JObject<'a>, JniError> {
+    env.call_method(obj, name, sig, args)
+        .and_then(|v| v.l())
+        .map_err(|e| take_error(env, name, e))
+}
+
+/// Call a Java method returning an object, treating null as an error
+pub fn call_non_null<'a>(
+    env: &mut JNIEnv<'a>,
+    obj: &JObject,
+    name: &str,
+    sig: &str,
+    args: &[JValue],
+) -> Result<JObject<'a>, JniError> {
+    let result = call_object(env, obj, name, sig, args)?;
+    if result.is_null() {
+        log::error!("JNI {} returned null", name);
+        return Err(JniError::NullResult {
+            call: name.to_string(),
+        });
+    }
+    Ok(result)
+}
+
+/// Call a Java method returning `int`
+pub fn call_int(
+    env: &mut JNIEnv,
+    obj: &JObject,
+    name: &str,
+    sig: &str,
+    args: &[JValue],
+) -> Result<i32, JniError> {
+    env.call_method(obj, name, sig, args)
+        .and_then(|v| v.i())
+        .map_err(|e| take_error(env, name, e))
+}
+
+/// Call a Java method returning `boolean`
+pub fn call_bool(
+    env: &mut JNIEnv,
+    obj: &JObject,
+    name: &str,
+    sig: &str,
+    args: &[JValue],
+) -> Result<bool, JniError> {
+    env.call_method(obj, name, sig, args)
+        .and_then(|v| v.z())
+        .map_err(|e| take_error(env, name, e))
+}
+
+/// Call a no-argument Java method returning `String`, mapping null to `None`
+pub fn call_string(
+    env: &mut JNIEnv,
+    obj: &JObject,
+    name: &str,
+) -> Result<Option<String>, JniError> {
+    let result = call_object(env, obj, name, "()Ljava/lang/String;", &[])?;
+    if result.is_null() {
+        return Ok(None);
+    }
+    read_java_string(env, &result)
+        .map(Some)
+        .map_err(|e| take_error(env, name, e))
+}
+
+/// Read a static `String` field such as `Context.USB_SERVICE`
+pub fn static_string_field<'a>(
+    env: &mut JNIEnv<'a>,
+    class: &str,
+    name: &str,
+) -> Result<JObject<'a>, JniError> {
+    env.get_static_field(class, name, "Ljava/lang/String;")
+        .and_then(|v| v.l())
+        .map_err(|e| take_error(env, name, e))
+}
+
+/// Create a `java.lang.String`
+pub fn new_string<'a>(env: &mut JNIEnv<'a>, value: &str) -> Result<JObject<'a>, JniError> {
+    env.new_string(value)
+        .map(JObject::from)
+        .map_err(|e| take_error(env, "NewStringUTF", e))
+}
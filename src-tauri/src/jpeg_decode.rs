@@ -0,0 +1,125 @@
+//! MJPEG frame decoding to RGB24
+//!
+//! MJPEG frames are normally stored and sent to the frontend as JPEG, while
+//! every YUV path produces RGB. With decoding enabled (`set_mjpeg_decode` or
+//! `CLEANSCOPE_DECODE_MJPEG=1`) the streaming backends decode each MJPEG
+//! frame here and hand it to the RGB path, so plugins, overlays and RGB-only
+//! processing apply to every format. Decoding costs CPU time per frame, so it
+//! is off by default.
+
+use thiserror::Error;
+
+/// Environment variable enabling MJPEG decoding at startup
+pub const DECODE_MJPEG_ENV: &str = "CLEANSCOPE_DECODE_MJPEG";
+
+/// Errors decoding a JPEG frame
+#[derive(Debug, Error)]
+pub enum JpegDecodeError {
+    /// The JPEG data is malformed or unsupported
+    #[error("{0}")]
+    Decode(String),
+    /// The decoder produced no frame header
+    #[error("missing frame header")]
+    MissingHeader,
+}
+
+/// Result type alias for JPEG decoding
+pub type Result<T> = std::result::Result<T, JpegDecodeError>;
+
+/// A decoded frame
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DecodedFrame {
+    /// RGB24 pixels, row by row
+    pub rgb: Vec<u8>,
+    /// Width from the JPEG header
+    pub width: u32,
+    /// Height from the JPEG header
+    pub height: u32,
+}
+
+/// Whether `CLEANSCOPE_DECODE_MJPEG` enables decoding (`1`, `true`, `yes`, `on`)
+pub fn enabled_from_env() -> bool {
+    std::env::var(DECODE_MJPEG_ENV).is_ok_and(|value| parse_flag(&value))
+}
+
+fn parse_flag(value: &str) -> bool {
+    matches!(
+        value.trim().to_ascii_lowercase().as_str(),
+        "1" | "true" | "yes" | "on"
+    )
+}
+
+/// Decode a JPEG frame to RGB24
+///
+/// Grayscale and CMYK JPEGs are converted to RGB as well.
+///
+/// # Errors
+///
+/// Returns an error if the data is not a decodable JPEG.
+pub fn decode(data: &[u8]) -> Result<DecodedFrame> {
+    let mut decoder = jpeg_decoder::Decoder::new(data);
+    let pixels = decoder
+        .decode()
+        .map_err(|e| JpegDecodeError::Decode(e.to_string()))?;
+    let info = decoder.info().ok_or(JpegDecodeError::MissingHeader)?;
+
+    let rgb = match info.pixel_format {
+        jpeg_decoder::PixelFormat::RGB24 => pixels,
+        jpeg_decoder::PixelFormat::L8 => pixels.iter().flat_map(|&l| [l, l, l]).collect(),
+        jpeg_decoder::PixelFormat::L16 => pixels
+            .chunks_exact(2)
+            .flat_map(|l| [l[0], l[0], l[0]])
+            .collect(),
+        jpeg_decoder::PixelFormat::CMYK32 => pixels
+            .chunks_exact(4)
+            .flat_map(|p| {
+                // Adobe CMYK JPEGs store inverted values
+                let k = u16::from(p[3]);
+                [p[0], p[1], p[2]].map(|c| ((u16::from(c) * k) / 255) as u8)
+            })
+            .collect(),
+    };
+    Ok(DecodedFrame {
+        rgb,
+        width: u32::from(info.width),
+        height: u32::from(info.height),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_flag() {
+        assert!(parse_flag("1"));
+        assert!(parse_flag(" On "));
+        assert!(!parse_flag("0"));
+        assert!(!parse_flag(""));
+    }
+
+    #[test]
+    fn test_truncated_jpeg_is_rejected() {
+        let jpeg = [0xFF, 0xD8, 0xFF, 0xDB, 0x00, 0x02, 0xFF, 0xD9];
+        assert!(decode(&jpeg).is_err());
+    }
+
+    #[cfg(feature = "jpeg")]
+    #[test]
+    fn test_decode_round_trip() {
+        use crate::image_encoder::{encode_frame, ImageFormat};
+
+        // Flat colour survives JPEG compression within a few levels
+        let rgb: Vec<u8> = [200u8, 100, 50].repeat(16 * 8);
+        let jpeg = encode_frame(&rgb, 16, 8, ImageFormat::Jpeg).unwrap();
+        let decoded = decode(&jpeg).unwrap();
+
+        assert_eq!((decoded.width, decoded.height), (16, 8));
+        assert_eq!(decoded.rgb.len(), rgb.len());
+        assert!(decoded
+            .rgb
+            .iter()
+            .zip(&rgb)
+            .all(|(&a, &b)| a.abs_diff(b) <= 4));
+    }
+}
@@ -2,23 +2,90 @@
 //!
 //! This module contains the core Tauri application logic and USB camera handling.
 
-mod capture;
+pub mod annotations;
+pub mod auto_snapshot;
+pub mod automation;
+pub mod bulk_transfer;
+pub mod calibration;
+pub mod capture;
+pub mod chapters;
+pub mod clip;
+pub mod deep_link;
+pub mod devices;
+pub mod diagnostics;
+pub mod encode_quality;
+pub mod exposure;
+pub mod ffi;
+pub mod format_registry;
+pub mod frame_broadcast;
+pub mod frame_cache;
+pub mod frame_stream;
+pub mod frame_trace;
 pub mod frame_validation;
+pub mod freeze;
+pub mod history;
+pub mod image_encoder;
+pub mod inference;
+pub mod jpeg_decode;
+pub mod lifecycle;
+pub mod measurement;
+pub mod messages;
+pub mod mkv_writer;
+pub mod output_layout;
+pub mod overlay;
+pub mod pcap_import;
+pub mod pipeline_compare;
+pub mod pixel_format;
+pub mod plugins;
+pub mod preflight;
+pub mod quirks;
+pub mod raw_video;
+pub mod recording;
 pub mod replay;
+pub mod resources;
+pub mod resume;
+pub mod session;
+pub mod settings;
+pub mod spool;
+pub mod stats;
+pub mod still_capture;
+pub mod storage;
+pub mod stream_health;
+pub mod stream_supervisor;
+pub mod submission;
+pub mod transform;
 mod usb;
+pub mod usb_permission;
+pub mod uvc_controls;
+pub mod uvc_descriptors;
+pub mod video_recorder;
+pub mod warmup;
+pub mod webp_anim;
 pub mod yuv_conversion;
+pub mod zoom;
 
 pub mod frame_assembler;
+pub mod frame_boundary;
 pub mod test_utils;
 
+#[cfg(target_os = "android")]
+mod jni_helpers;
 #[cfg(target_os = "android")]
 mod libusb_android;
+#[cfg(target_os = "android")]
+mod usb_connection;
+#[cfg(all(feature = "desktop-usb", not(target_os = "android")))]
+mod usb_desktop;
 
 pub use frame_validation::ValidationLevel;
+pub use image_encoder::ImageFormat;
+pub use pixel_format::PixelFormat;
 
-use frame_assembler::is_jpeg_data;
+use arc_swap::ArcSwap;
+use frame_assembler::{is_jpeg_data, jpeg_dimensions};
 use serde::{Deserialize, Serialize};
-use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard};
 use std::time::Instant;
 use tauri::{AppHandle, Emitter, Manager, State};
 
@@ -53,15 +120,183 @@ pub enum AppError {
     /// Resource not found (e.g., no formats discovered)
     #[error("Not found: {0}")]
     NotFound(String),
+
+    /// Recording error
+    #[error("Recording error: {0}")]
+    Recording(#[from] recording::RecordingError),
+
+    /// Malformed or unsupported deep link
+    #[error("Deep link error: {0}")]
+    DeepLink(#[from] deep_link::DeepLinkError),
+
+    /// Android permission was not granted (e.g. USB device access)
+    #[error("Permission denied: {0}")]
+    PermissionDenied(String),
+
+    /// Android platform API call failed
+    #[error("Android API error: {0}")]
+    Android(String),
+
+    /// Image encoding error (e.g. format not compiled in)
+    #[error("Encode error: {0}")]
+    Encode(#[from] image_encoder::EncodeError),
+
+    /// Clip export error
+    #[error("Clip error: {0}")]
+    Clip(#[from] clip::ClipError),
+
+    /// Capture submission could not be packaged
+    #[error("Submission error: {0}")]
+    Submission(#[from] submission::SubmissionError),
+
+    /// Pipeline comparison could not be started or stopped
+    #[error("Comparison error: {0}")]
+    Comparison(#[from] pipeline_compare::ComparisonError),
+
+    /// Frame trace could not be armed or written
+    #[error("Trace error: {0}")]
+    Trace(#[from] frame_trace::TraceError),
+
+    /// Packet capture could not be replayed
+    #[error("Replay error: {0}")]
+    Replay(#[from] replay::ReplayError),
+
+    /// Frame could not be converted to RGB
+    #[error("Conversion error: {0}")]
+    Conversion(#[from] yuv_conversion::ConversionError),
+
+    /// Detection model could not be loaded or run
+    #[error("Inference error: {0}")]
+    Inference(#[from] inference::InferenceError),
+
+    /// Camera control could not be read or changed
+    #[error("Camera control error: {0}")]
+    CameraControl(#[from] uvc_controls::UvcControlError),
+
+    /// Measurement calibration failed
+    #[error("Calibration error: {0}")]
+    Calibration(#[from] calibration::CalibrationError),
+
+    /// Measurement points are unusable
+    #[error("Measurement error: {0}")]
+    Measurement(#[from] measurement::MeasurementError),
+
+    /// No frozen frame or unusable annotation
+    #[error("Freeze error: {0}")]
+    Freeze(#[from] freeze::FreezeError),
+
+    /// Unsupported rotation
+    #[error("Transform error: {0}")]
+    Transform(#[from] transform::TransformError),
+
+    /// Edit to undo or redo no longer applies
+    #[error("History error: {0}")]
+    History(#[from] history::HistoryError),
+
+    /// Invalid zoom factor or centre
+    #[error("Zoom error: {0}")]
+    Zoom(#[from] zoom::ZoomError),
+
+    /// Invalid script, or another script is running
+    #[error("Script error: {0}")]
+    Script(#[from] automation::ScriptError),
+
+    /// Invalid auto-snapshot interval or label
+    #[error("Auto-snapshot error: {0}")]
+    AutoSnapshot(#[from] auto_snapshot::AutoSnapshotError),
+
+    /// Storage or memory too low to start recording
+    #[error("Low resources: {0}")]
+    Resources(#[from] resources::ResourceError),
+
+    /// Still image could not be negotiated, triggered or received
+    #[error("Still capture error: {0}")]
+    StillCapture(#[from] still_capture::StillError),
+
+    /// Cameras could not be listed, or the selected one is not connected
+    #[error("Device error: {0}")]
+    Device(#[from] devices::DeviceError),
+
+    /// Invalid preference value
+    #[error("Settings error: {0}")]
+    Settings(#[from] settings::SettingsError),
+
+    /// libusb call failed
+    #[cfg(target_os = "android")]
+    #[error("USB error: {0}")]
+    Usb(#[from] libusb_android::LibusbError),
+}
+
+impl AppError {
+    /// Stable code identifying the kind of error (for localization and log grepping)
+    pub fn code(&self) -> messages::MessageCode {
+        use messages::MessageCode;
+
+        match self {
+            AppError::LockPoisoned(_) => MessageCode::LockPoisoned,
+            AppError::Io(_) => MessageCode::IoError,
+            AppError::Capture(_) => MessageCode::CaptureError,
+            AppError::NoFrame => MessageCode::NoFrame,
+            AppError::PathError(_) => MessageCode::PathError,
+            AppError::NotFound(_) => MessageCode::NotFound,
+            AppError::Recording(_) => MessageCode::RecordingError,
+            AppError::DeepLink(_) => MessageCode::DeepLinkError,
+            AppError::PermissionDenied(_) => MessageCode::PermissionDenied,
+            AppError::Android(_) => MessageCode::AndroidError,
+            AppError::Encode(_) => MessageCode::EncodeError,
+            AppError::Clip(_) => MessageCode::ClipError,
+            AppError::Submission(submission::SubmissionError::ConsentRequired) => {
+                MessageCode::ConsentRequired
+            }
+            AppError::Submission(_) => MessageCode::SubmissionError,
+            AppError::Comparison(_) => MessageCode::ComparisonError,
+            AppError::Trace(_) => MessageCode::TraceError,
+            AppError::Replay(_) => MessageCode::ReplayError,
+            AppError::Conversion(_) => MessageCode::ConversionError,
+            AppError::Inference(_) => MessageCode::InferenceError,
+            AppError::CameraControl(_) => MessageCode::CameraControlError,
+            AppError::Calibration(_) => MessageCode::CalibrationError,
+            AppError::Measurement(_) => MessageCode::MeasurementError,
+            AppError::Freeze(_) => MessageCode::FreezeError,
+            AppError::Transform(_) => MessageCode::TransformError,
+            AppError::History(_) => MessageCode::HistoryError,
+            AppError::Zoom(_) => MessageCode::ZoomError,
+            AppError::Script(_) => MessageCode::ScriptError,
+            AppError::AutoSnapshot(_) => MessageCode::AutoSnapshotError,
+            AppError::Resources(_) => MessageCode::LowResources,
+            AppError::StillCapture(_) => MessageCode::StillCaptureError,
+            AppError::Device(_) => MessageCode::DeviceError,
+            AppError::Settings(_) => MessageCode::SettingsError,
+            #[cfg(target_os = "android")]
+            AppError::Usb(_) => MessageCode::UsbCameraError,
+        }
+    }
+}
+
+#[cfg(target_os = "android")]
+impl From<jni_helpers::JniError> for AppError {
+    fn from(err: jni_helpers::JniError) -> Self {
+        if err.is_permission_denied() {
+            AppError::PermissionDenied(err.to_string())
+        } else {
+            AppError::Android(err.to_string())
+        }
+    }
 }
 
-// Tauri requires errors to be serializable for IPC
+// Tauri requires errors to be serializable for IPC.
+// Serialized as `{ code, message }` so the frontend can localize by code.
 impl serde::Serialize for AppError {
     fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
     where
         S: serde::Serializer,
     {
-        serializer.serialize_str(&self.to_string())
+        use serde::ser::SerializeStruct;
+
+        let mut s = serializer.serialize_struct("AppError", 2)?;
+        s.serialize_field("code", &self.code())?;
+        s.serialize_field("message", &self.to_string())?;
+        s.end()
     }
 }
 
@@ -74,35 +309,191 @@ macro_rules! lock_or_err {
     };
 }
 
-/// Shared frame buffer for storing the latest camera frame
-pub struct FrameBuffer {
-    /// Processed frame data (JPEG or RGB)
-    pub frame: Vec<u8>,
-    /// Raw frame data before conversion (for debugging)
-    pub raw_frame: Vec<u8>,
+/// Lock a mutex, recovering it if a thread panicked while holding it.
+///
+/// Used on the streaming path, where a panic in one frame consumer must not
+/// stop frame delivery. The poison flag is cleared so that later
+/// [`lock_or_err!`] callers (Tauri commands) see the recovered value instead
+/// of failing with [`AppError::LockPoisoned`] for the rest of the session.
+pub(crate) fn lock_or_recover<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(|poisoned| {
+        log::error!("Mutex poisoned, recovering");
+        mutex.clear_poison();
+        poisoned.into_inner()
+    })
+}
+
+/// Storage for everything the app writes, rooted at the output directory
+///
+/// Uses `CLEANSCOPE_OUTPUT_DIR` if it was set at startup, otherwise the
+/// capture directory from the settings, otherwise the app cache directory
+/// (app-specific storage on Android).
+fn app_storage(app: &AppHandle, state: &AppState) -> Result<storage::Storage, AppError> {
+    let capture_dir = lock_or_err!(state.settings)?.capture_dir.clone();
+    let root = match state.output_dir.clone().or(capture_dir) {
+        Some(dir) => dir,
+        None => app
+            .path()
+            .app_cache_dir()
+            .map_err(|e| AppError::PathError(e.to_string()))?,
+    };
+    Ok(storage::Storage::new(root))
+}
+
+/// Storage for the settings file, rooted at the app config directory
+fn settings_storage(app: &AppHandle) -> Result<storage::Storage, AppError> {
+    let root = app
+        .path()
+        .app_config_dir()
+        .map_err(|e| AppError::PathError(e.to_string()))?;
+    Ok(storage::Storage::new(root))
+}
+
+/// A stored camera frame
+///
+/// Frames are immutable once published; readers hold an `Arc<Frame>` while
+/// the streaming thread publishes the next one.
+pub struct Frame {
+    /// Processed frame data (JPEG or RGB), shared with the frame broadcast
+    pub data: Arc<Vec<u8>>,
+    /// Raw frame data before conversion (empty unless raw capture is enabled)
+    pub raw: Vec<u8>,
     /// Timestamp when frame was captured
     pub timestamp: Instant,
     /// Frame width in pixels
     pub width: u32,
     /// Frame height in pixels
     pub height: u32,
-    /// Whether to capture raw frame data (disabled by default to save ~54MB/s at 30fps 720p)
-    pub capture_raw_frames: bool,
+    /// Number of frames stored since startup (0 before the first frame)
+    pub sequence: u64,
 }
 
-impl Default for FrameBuffer {
-    fn default() -> Self {
+impl Frame {
+    fn empty() -> Self {
         Self {
-            frame: Vec::new(),
-            raw_frame: Vec::new(),
+            data: Arc::new(Vec::new()),
+            raw: Vec::new(),
             timestamp: Instant::now(),
             width: 0,
             height: 0,
-            capture_raw_frames: false,
+            sequence: 0,
+        }
+    }
+
+    /// Whether no frame has been stored yet
+    pub fn is_empty(&self) -> bool {
+        self.data.is_empty()
+    }
+
+    /// Metadata of this frame
+    pub fn info(&self) -> FrameInfo {
+        let format = if is_jpeg_data(&self.data) {
+            "jpeg"
+        } else {
+            "rgb"
+        };
+        FrameInfo {
+            sequence: self.sequence,
+            width: self.width,
+            height: self.height,
+            format: format.to_string(),
+            size: self.data.len(),
+        }
+    }
+}
+
+/// Shared frame buffer for storing the latest camera frame
+///
+/// The current frame is swapped atomically, so the streaming thread never
+/// waits for a command reading a frame and readers never wait for it.
+pub struct FrameBuffer {
+    current: ArcSwap<Frame>,
+    /// Sequence number of the last stored frame
+    sequence: AtomicU64,
+    /// Whether to capture raw frame data (disabled by default to save ~54MB/s at 30fps 720p)
+    capture_raw_frames: AtomicBool,
+}
+
+impl Default for FrameBuffer {
+    fn default() -> Self {
+        Self {
+            current: ArcSwap::from_pointee(Frame::empty()),
+            sequence: AtomicU64::new(0),
+            capture_raw_frames: AtomicBool::new(false),
         }
     }
 }
 
+impl FrameBuffer {
+    /// The current frame (cheap: clones an `Arc`)
+    pub fn load(&self) -> Arc<Frame> {
+        self.current.load_full()
+    }
+
+    /// Store a new frame, keeping the sequence, timestamp and dimensions in sync
+    ///
+    /// `width` and `height` are 0 when the stream does not know them. JPEG
+    /// frames use the dimensions from their SOF header instead, since MJPEG
+    /// cameras do not always send the negotiated resolution.
+    /// Returns the metadata sent with `frame-ready`.
+    ///
+    /// Takes a `Vec` or a frame shared with the frame broadcast; neither is
+    /// copied.
+    pub fn store(&self, frame: impl Into<Arc<Vec<u8>>>, width: u32, height: u32) -> FrameInfo {
+        self.store_with_raw(frame, &[], width, height)
+    }
+
+    /// Store a new frame along with the data it was converted from
+    ///
+    /// `raw` is only kept while raw capture is enabled.
+    pub fn store_with_raw(
+        &self,
+        frame: impl Into<Arc<Vec<u8>>>,
+        raw: &[u8],
+        width: u32,
+        height: u32,
+    ) -> FrameInfo {
+        let frame = frame.into();
+        let (width, height) = jpeg_dimensions(&frame).unwrap_or((width, height));
+        let raw = if self.capture_raw_frames() {
+            raw.to_vec()
+        } else {
+            Vec::new()
+        };
+        let frame = Frame {
+            data: frame,
+            raw,
+            timestamp: Instant::now(),
+            width,
+            height,
+            sequence: self.sequence.fetch_add(1, Ordering::Relaxed) + 1,
+        };
+        let info = frame.info();
+        self.current.store(Arc::new(frame));
+        info
+    }
+
+    /// Metadata of the current frame
+    pub fn info(&self) -> FrameInfo {
+        self.current.load().info()
+    }
+
+    /// Sequence number of the current frame
+    pub fn sequence(&self) -> u64 {
+        self.current.load().sequence
+    }
+
+    /// Whether raw frames are kept alongside processed ones
+    pub fn capture_raw_frames(&self) -> bool {
+        self.capture_raw_frames.load(Ordering::Relaxed)
+    }
+
+    /// Enable or disable keeping raw frames
+    pub fn set_capture_raw_frames(&self, enabled: bool) {
+        self.capture_raw_frames.store(enabled, Ordering::Relaxed);
+    }
+}
+
 /// Display settings that can be adjusted independently
 #[derive(Debug, Clone, Copy, Default)]
 pub struct DisplaySettings {
@@ -130,42 +521,39 @@ pub struct DisplayConfig {
     pub height_index: Option<usize>,
     /// Current stride option index (None = auto)
     pub stride_index: Option<usize>,
+    /// Rotation and flips applied to RGB frames
+    pub transform: transform::Transform,
+    /// Digital zoom applied to RGB frames after the transform
+    pub zoom: zoom::Zoom,
 }
 
-/// Pixel format variants for video frames
-/// Includes both YUV and RGB formats
-#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
-pub enum PixelFormat {
-    /// YUYV format: Y0-U-Y1-V byte order (packed YUV422, luminance first)
-    #[default]
-    Yuyv,
-    /// UYVY format: U-Y0-V-Y1 byte order (packed YUV422, chrominance first)
-    /// This is what macOS reports for many USB endoscopes
-    Uyvy,
-    /// NV12 format: Y plane followed by interleaved UV plane (semi-planar YUV420)
-    /// Uses 1.5 bytes per pixel (12 bits)
-    Nv12,
-    /// I420 format: Y plane, then U plane, then V plane (planar YUV420)
-    /// Uses 1.5 bytes per pixel (12 bits)
-    I420,
-    /// RGB888 format: R-G-B byte order (3 bytes per pixel)
-    /// Direct pass-through, no conversion needed
-    Rgb888,
-    /// BGR888 format: B-G-R byte order (3 bytes per pixel)
-    /// Requires R↔B swap for display
-    Bgr888,
-}
-
-impl std::fmt::Display for PixelFormat {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        match self {
-            PixelFormat::Yuyv => write!(f, "YUYV"),
-            PixelFormat::Uyvy => write!(f, "UYVY"),
-            PixelFormat::Nv12 => write!(f, "NV12"),
-            PixelFormat::I420 => write!(f, "I420"),
-            PixelFormat::Rgb888 => write!(f, "RGB24"),
-            PixelFormat::Bgr888 => write!(f, "BGR24"),
+impl DisplayConfig {
+    /// Whether `apply_view` leaves frames unchanged
+    pub fn is_default_view(&self) -> bool {
+        self.transform.is_identity() && self.zoom.is_identity()
+    }
+
+    /// Rotate, flip and zoom an RGB frame, returning it with its new dimensions
+    ///
+    /// Frames that don't match their dimensions are passed through unchanged.
+    pub(crate) fn apply_view(&self, rgb: Vec<u8>, width: u32, height: u32) -> (Vec<u8>, u32, u32) {
+        let mut frame = (rgb, width, height);
+        if !self.transform.is_identity() {
+            match self.transform.apply_rgb(&frame.0, width, height) {
+                Ok(transformed) => frame = transformed,
+                Err(e) => {
+                    log::debug!("Skipping frame transform: {}", e);
+                    return frame;
+                }
+            }
+        }
+        if !self.zoom.is_identity() {
+            match self.zoom.apply_rgb(&frame.0, frame.1, frame.2) {
+                Ok(zoomed) => frame.0 = zoomed,
+                Err(e) => log::debug!("Skipping frame zoom: {}", e),
+            }
         }
+        frame
     }
 }
 
@@ -174,16 +562,291 @@ impl std::fmt::Display for PixelFormat {
 pub struct StreamingConfig {
     /// Skip MJPEG format detection and go straight to YUV
     pub skip_mjpeg_detection: bool,
+    /// Decode MJPEG frames to RGB in the backend (see `set_mjpeg_decode`)
+    pub decode_mjpeg: bool,
     /// Pixel format for frame conversion (YUV variants or RGB)
     pub pixel_format: PixelFormat,
     /// Selected format index (None = auto-detect, Some(n) = use format n)
     pub selected_format_index: Option<u8>,
     /// Selected frame index for resolution (None = use first available, Some(n) = use frame n)
     pub selected_frame_index: Option<u8>,
-    /// Available formats discovered from camera
-    pub available_formats: Vec<DiscoveredFormat>,
+    /// Selected frame interval in 100 ns units (None = the frame's default)
+    ///
+    /// Kept across resolution changes; each resolution uses its closest supported interval.
+    pub selected_frame_interval: Option<u32>,
+    /// Formats and resolutions parsed from the camera's UVC descriptors
+    pub format_catalog: uvc_descriptors::FormatCatalog,
     /// Flag to signal streaming should restart with new settings
     pub restart_requested: bool,
+    /// Bulk endpoint transfer size, timeout and retry settings
+    pub bulk_transfer: bulk_transfer::BulkTransferConfig,
+    /// Workarounds for the connected camera
+    pub quirks: quirks::DeviceQuirks,
+    /// Format and resolution negotiated for the running stream (None = not streaming)
+    pub active_stream: Option<ActiveStream>,
+    /// Frames the frame broadcast keeps for slow consumers
+    /// (None = [`frame_broadcast::DEFAULT_CAPACITY`])
+    pub frame_capacity: Option<usize>,
+    /// Resolution negotiated when no frame index is selected (from the settings)
+    pub preferred_resolution: Option<settings::PreferredResolution>,
+    /// Format names tried first when no format index is selected, most
+    /// preferred first (from the settings)
+    pub format_preference: Vec<String>,
+}
+
+impl StreamingConfig {
+    /// Capacity of the frame broadcast for the next stream
+    pub fn frame_channel_capacity(&self) -> usize {
+        self.frame_capacity.map_or(
+            frame_broadcast::DEFAULT_CAPACITY,
+            frame_broadcast::clamp_capacity,
+        )
+    }
+
+    /// Record the negotiated stream and adopt the pixel format of its GUID
+    ///
+    /// The format GUID decides the frame layout: if the configured pixel
+    /// format has a different size per pixel than the format registered for
+    /// the GUID (e.g. YUYV for a Bayer stream), the GUID's format is used.
+    /// Formats of the same size (YUYV / UYVY, RGB24 / BGR24) keep the
+    /// configured one, since cameras misreport their byte order.
+    pub fn set_active_stream(&mut self, active: ActiveStream) {
+        let detected = self
+            .format_catalog
+            .format(active.format_index)
+            .and_then(|format| format.pixel_format());
+        if let Some(detected) = detected {
+            let bits = |format| format_registry::entry(format).bits_per_pixel;
+            if bits(detected) != bits(self.pixel_format) {
+                log::info!(
+                    "Using {} pixel format from the descriptor (was {})",
+                    detected,
+                    self.pixel_format
+                );
+                self.pixel_format = detected;
+            }
+        }
+        self.active_stream = Some(active);
+    }
+
+    /// Format whose resolutions are listed and cycled
+    ///
+    /// The streaming format if known, otherwise the selected format, otherwise
+    /// the first discovered one.
+    pub fn current_format(&self) -> Option<&uvc_descriptors::FormatDescriptor> {
+        let index = self
+            .active_stream
+            .map(|s| s.format_index)
+            .or(self.selected_format_index)
+            .or_else(|| self.format_catalog.formats.first().map(|f| f.index))?;
+        self.format_catalog.format(index)
+    }
+
+    /// Parameters of the running stream, as negotiated with probe/commit
+    pub fn stream_info(&self) -> Option<StreamInfo> {
+        let active = self.active_stream?;
+        let format = self
+            .format_catalog
+            .format(active.format_index)
+            .map_or_else(|| "UNKNOWN".to_string(), |f| f.name());
+        Some(StreamInfo {
+            format,
+            format_index: active.format_index,
+            frame_index: active.frame_index,
+            width: active.width,
+            height: active.height,
+            fps: uvc_descriptors::interval_to_fps(active.frame_interval),
+            frame_interval: active.frame_interval,
+            max_frame_size: active.max_frame_size,
+            max_payload: active.max_payload,
+        })
+    }
+
+    /// Frame index (resolution) of the current format
+    ///
+    /// The streaming frame if known, otherwise the selected frame, otherwise
+    /// the format's default frame.
+    pub fn current_frame_index(&self) -> Option<u8> {
+        self.active_stream
+            .map(|s| s.frame_index)
+            .or(self.selected_frame_index)
+            .or_else(|| {
+                self.current_format()
+                    .and_then(|f| f.default_frame())
+                    .map(|f| f.index)
+            })
+    }
+
+    /// Select the next resolution of the current format and request a restart
+    ///
+    /// The stream is renegotiated (UVC probe/commit) with the new frame index
+    /// in the same format. Returns the selected resolution, or `None` if no
+    /// resolutions have been discovered.
+    pub fn cycle_resolution(&mut self) -> Option<ResolutionInfo> {
+        let current = self.current_frame_index();
+        let format = self.current_format()?;
+        if format.frames.is_empty() {
+            return None;
+        }
+
+        let current_pos = format
+            .frames
+            .iter()
+            .position(|f| Some(f.index) == current)
+            .unwrap_or(0);
+        let next_pos = (current_pos + 1) % format.frames.len();
+        let next_frame = &format.frames[next_pos];
+        let result = ResolutionInfo {
+            width: next_frame.width,
+            height: next_frame.height,
+            frame_index: next_frame.index,
+            available_count: format.frames.len(),
+        };
+
+        let format_index = format.index;
+
+        // Pin the format so the restart renegotiates it instead of auto-detecting
+        self.selected_format_index = Some(format_index);
+        self.selected_frame_index = Some(result.frame_index);
+        self.restart_requested = true;
+        Some(result)
+    }
+
+    /// Select a resolution of the current format and request a restart
+    ///
+    /// No restart is requested if the stream already runs at this resolution.
+    /// Returns `None` if the current format doesn't offer it.
+    pub fn select_resolution(&mut self, width: u16, height: u16) -> Option<ResolutionInfo> {
+        let format = self.current_format()?;
+        let frame = format
+            .frames
+            .iter()
+            .find(|f| f.width == width && f.height == height)?;
+        let result = ResolutionInfo {
+            width,
+            height,
+            frame_index: frame.index,
+            available_count: format.frames.len(),
+        };
+        let format_index = format.index;
+
+        let active = self
+            .active_stream
+            .is_some_and(|a| a.format_index == format_index && a.frame_index == result.frame_index);
+        self.selected_format_index = Some(format_index);
+        self.selected_frame_index = Some(result.frame_index);
+        if !active {
+            self.restart_requested = true;
+        }
+        Some(result)
+    }
+
+    /// Frame rates of a resolution of the current format, fastest first
+    ///
+    /// `frame_index` defaults to the current resolution. Returns `None` if the
+    /// resolution is unknown.
+    pub fn frame_rates(&self, frame_index: Option<u8>) -> Option<Vec<FrameRate>> {
+        let format = self.current_format()?;
+        let frame = format.frame(frame_index.or_else(|| self.current_frame_index())?)?;
+        let active_interval = self
+            .active_stream
+            .filter(|a| a.format_index == format.index && a.frame_index == frame.index)
+            .map(|a| a.frame_interval);
+        Some(
+            frame
+                .intervals
+                .selectable()
+                .into_iter()
+                .map(|interval| FrameRate::new(interval, active_interval == Some(interval)))
+                .collect(),
+        )
+    }
+
+    /// Select the supported frame rate closest to `fps` and request a restart
+    ///
+    /// The stream is renegotiated with the matching `dwFrameInterval` at the
+    /// current resolution. Returns `None` if `fps` isn't positive or no
+    /// resolution is known.
+    pub fn set_frame_rate(&mut self, fps: f32) -> Option<FrameRate> {
+        let interval = uvc_descriptors::fps_to_interval(fps);
+        if interval == 0 {
+            return None;
+        }
+        let format = self.current_format()?;
+        let frame = format.frame(self.current_frame_index()?)?;
+        let interval = frame.intervals.nearest(interval)?;
+        let (format_index, frame_index) = (format.index, frame.index);
+
+        // Pin format and resolution so the restart only changes the interval
+        self.selected_format_index = Some(format_index);
+        self.selected_frame_index = Some(frame_index);
+        self.selected_frame_interval = Some(interval);
+        self.restart_requested = true;
+        Some(FrameRate::new(interval, false))
+    }
+}
+
+/// A frame rate a resolution supports, returned by `get_framerates`
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct FrameRate {
+    /// Frames per second
+    pub fps: f32,
+    /// `dwFrameInterval` in 100 ns units
+    pub frame_interval: u32,
+    /// Whether the camera is currently streaming at this rate
+    pub active: bool,
+}
+
+impl FrameRate {
+    fn new(frame_interval: u32, active: bool) -> Self {
+        Self {
+            fps: uvc_descriptors::interval_to_fps(frame_interval),
+            frame_interval,
+            active,
+        }
+    }
+}
+
+/// Format and resolution the camera is currently streaming
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ActiveStream {
+    /// UVC format index (1-based)
+    pub format_index: u8,
+    /// UVC frame index (1-based)
+    pub frame_index: u8,
+    /// Frame width in pixels
+    pub width: u16,
+    /// Frame height in pixels
+    pub height: u16,
+    /// Negotiated frame interval in 100 ns units (0 if the camera chose none)
+    pub frame_interval: u32,
+    /// Negotiated `dwMaxVideoFrameSize` in bytes
+    pub max_frame_size: u32,
+    /// Negotiated `dwMaxPayloadTransferSize` in bytes
+    pub max_payload: u32,
+}
+
+/// Stream parameters the camera agreed to, returned by `get_stream_info`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct StreamInfo {
+    /// Format name (e.g. `"MJPEG"`, `"YUY2"`)
+    pub format: String,
+    /// UVC format index (1-based)
+    pub format_index: u8,
+    /// UVC frame index (1-based)
+    pub frame_index: u8,
+    /// Frame width in pixels
+    pub width: u16,
+    /// Frame height in pixels
+    pub height: u16,
+    /// Frames per second (0 if the camera chose no interval)
+    pub fps: f32,
+    /// `dwFrameInterval` in 100 ns units
+    pub frame_interval: u32,
+    /// Largest frame the camera will send, in bytes
+    pub max_frame_size: u32,
+    /// Largest payload per transfer, in bytes
+    pub max_payload: u32,
 }
 
 /// A discovered frame descriptor (resolution info) from UVC
@@ -208,6 +871,24 @@ pub struct DiscoveredFormat {
     pub frames: Vec<DiscoveredFrame>,
 }
 
+impl From<&uvc_descriptors::FormatDescriptor> for DiscoveredFormat {
+    fn from(format: &uvc_descriptors::FormatDescriptor) -> Self {
+        Self {
+            index: format.index,
+            format_type: format.name(),
+            frames: format
+                .frames
+                .iter()
+                .map(|f| DiscoveredFrame {
+                    frame_index: f.index,
+                    width: f.width,
+                    height: f.height,
+                })
+                .collect(),
+        }
+    }
+}
+
 /// Available width options for cycling
 pub const WIDTH_OPTIONS: &[u32] = &[1280, 1920, 640, 800, 1024, 960, 720, 1440];
 
@@ -229,17 +910,75 @@ pub const STRIDE_OPTIONS: &[f32] = &[
 /// Application state managed by Tauri
 pub struct AppState {
     /// Shared frame buffer protected by mutex
-    pub frame_buffer: Arc<Mutex<FrameBuffer>>,
+    pub frame_buffer: Arc<FrameBuffer>,
     /// Consolidated display configuration (settings + cycling indexes)
     pub display: Arc<Mutex<DisplayConfig>>,
     /// Streaming configuration (MJPEG skip, YUV format)
     pub streaming_config: Arc<Mutex<StreamingConfig>>,
     /// Packet capture state for debugging
     pub capture_state: Arc<capture::CaptureState>,
+    /// Recorder for processed frames (and raw packets via `capture_state`)
+    pub recording: Arc<recording::RecordingState>,
+    /// Manifest of the current live session (bookmarks)
+    pub session: Mutex<session::SessionManifest>,
     /// Flag to signal USB streaming should stop (for graceful shutdown)
     pub usb_stop_flag: Arc<std::sync::atomic::AtomicBool>,
-    /// Frame validation level (cached from env var at startup, immutable)
-    pub validation_level: ValidationLevel,
+    /// USB permission state per device, kept across replugs
+    pub usb_permissions: Arc<Mutex<usb_permission::PermissionCache>>,
+    /// Frame delivery statistics for the connection health indicator
+    pub stream_health: Arc<stream_health::StreamHealth>,
+    /// Frame assembly statistics for the diagnostics overlay
+    pub stream_stats: Arc<stats::StreamStats>,
+    /// Frame validation level applied before frames are published (see `set_validation_level`)
+    pub validation_level: Arc<Mutex<ValidationLevel>>,
+    /// Output directory override from `CLEANSCOPE_OUTPUT_DIR` (default: app cache directory)
+    pub output_dir: Option<std::path::PathBuf>,
+    /// Format for saved frames (`None`: keep as captured), from `CLEANSCOPE_SNAPSHOT_FORMAT`
+    pub snapshot_format: Mutex<Option<ImageFormat>>,
+    /// Converted outputs of the current frame
+    pub frame_cache: Mutex<frame_cache::FrameCache>,
+    /// Background writer for every Nth frame (kiosk mode)
+    pub spooler: Arc<spool::FrameSpooler>,
+    /// Dual-pipeline debug comparison fed from `capture_state`
+    pub pipeline_comparison: pipeline_compare::PipelineComparison,
+    /// One-frame pipeline trace (see `trace_next_frame`)
+    pub frame_tracer: Arc<frame_trace::FrameTracer>,
+    /// Playback of a packet capture in place of a camera (see `load_replay`)
+    pub replay: replay::ReplayPlayer,
+    /// Frontend channels frames are pushed to (see `subscribe_frames`)
+    pub frame_stream: frame_stream::FrameStream,
+    /// Third-party frame processors (see `list_plugins`)
+    pub plugins: Arc<plugins::PluginHost>,
+    /// ONNX defect detection on stored frames (see `start_inference`)
+    pub inference: inference::Detector,
+    /// Annotations from plugins and models (see `set_annotation_config`)
+    pub annotations: Arc<annotations::AnnotationBus>,
+    /// Image controls of the connected camera (see `get_camera_controls`)
+    pub camera_controls: Arc<uvc_controls::CameraControls>,
+    /// Still image capture of the connected camera (see `capture_still`)
+    pub still_capture: Arc<still_capture::StillCapture>,
+    /// Connected cameras and the one to stream from (see `list_usb_devices`)
+    pub devices: Arc<Mutex<devices::DeviceRegistry>>,
+    /// Pixel to millimetre mapping for measurements (see `calibrate_from_target`)
+    pub calibration: Mutex<Option<calibration::Calibration>>,
+    /// Unit calibrated measurements are reported in (see `set_measurement_unit`)
+    pub measurement_unit: Mutex<measurement::LengthUnit>,
+    /// Frame held for annotation (see `freeze_frame`)
+    pub frozen: Mutex<Option<freeze::FrozenFrame>>,
+    /// Annotation and settings edits for `undo` / `redo`
+    pub history: Mutex<history::History<history::Snapshot>>,
+    /// Saved user preferences (see `get_settings`)
+    pub settings: Mutex<settings::Settings>,
+    /// Session the previous run left behind when it crashed (see `get_resume_offer`)
+    pub pending_resume: Mutex<Option<resume::Checkpoint>>,
+    /// Running `execute_script` script and its cancellation
+    pub scripts: automation::ScriptControl,
+    /// Time-based snapshot schedule (see `start_auto_snapshot`)
+    pub auto_snapshot: auto_snapshot::AutoSnapshotScheduler,
+    /// Histogram exposure analysis (see `set_exposure_advisor`)
+    pub exposure: exposure::ExposureAdvisor,
+    /// Background threads and subsystems stopped in order on exit
+    pub lifecycle: lifecycle::Lifecycle,
 }
 
 /// USB device connection status
@@ -249,6 +988,9 @@ pub struct UsbStatus {
     pub connected: bool,
     /// Optional information about the connected device
     pub info: Option<String>,
+    /// Frame delivery statistics (frame age, fps, error counts)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub health: Option<stream_health::HealthStats>,
 }
 
 /// Reason for USB device disconnection
@@ -270,6 +1012,8 @@ pub enum DisconnectReason {
 /// USB error event for detailed error reporting to frontend
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct UsbError {
+    /// Stable message code for localization
+    pub code: messages::MessageCode,
     /// Type of error that occurred
     pub error_type: DisconnectReason,
     /// Human-readable error message
@@ -285,6 +1029,12 @@ pub struct Resolution {
     pub width: u32,
     /// Height in pixels
     pub height: u32,
+    /// UVC frame index (1-based)
+    #[serde(default)]
+    pub frame_index: u8,
+    /// Whether the camera is currently streaming this resolution
+    #[serde(default)]
+    pub active: bool,
 }
 
 /// Resolution info with frame index and available count
@@ -311,118 +1061,299 @@ pub struct BuildInfo {
     pub build_time: String,
 }
 
+impl BuildInfo {
+    /// Build information of the running binary
+    pub fn current() -> Self {
+        Self {
+            version: env!("CARGO_PKG_VERSION").to_string(),
+            git_hash: env!("BUILD_GIT_HASH").to_string(),
+            build_time: env!("BUILD_TIMESTAMP").to_string(),
+        }
+    }
+}
+
 /// Get build information (version, git hash, build time)
 #[tauri::command]
 fn get_build_info() -> BuildInfo {
-    BuildInfo {
-        version: env!("CARGO_PKG_VERSION").to_string(),
-        git_hash: env!("BUILD_GIT_HASH").to_string(),
-        build_time: env!("BUILD_TIMESTAMP").to_string(),
+    BuildInfo::current()
+}
+
+/// The app's own network-capable features compiled into this binary
+///
+/// The webview's content security policy limits `connect-src` to IPC in every
+/// build; the `no-network` feature also leaves out the features listed here.
+/// Tauri and the system webview are not covered.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BuildCapabilities {
+    /// Built with the `no-network` feature
+    pub no_network: bool,
+    /// The shell plugin is registered, so the frontend can open URLs in the browser
+    pub shell_open: bool,
+    /// Native frame processor plugins can be loaded (see [`plugins`])
+    pub native_plugins: bool,
+    /// The Android app requests `android.permission.INTERNET`: debug builds
+    /// only, to load the frontend from the dev server
+    pub internet_permission: bool,
+}
+
+impl BuildCapabilities {
+    /// Capabilities of the running binary
+    pub const fn current() -> Self {
+        Self {
+            no_network: cfg!(feature = "no-network"),
+            shell_open: cfg!(shell_open),
+            native_plugins: cfg!(plugin_loading),
+            internet_permission: cfg!(all(target_os = "android", debug_assertions)),
+        }
+    }
+
+    /// Whether none of the app's own network-capable features are compiled in
+    pub const fn is_network_free(&self) -> bool {
+        !(self.shell_open || self.native_plugins || self.internet_permission)
     }
 }
 
-/// Check the current USB device status
+/// Report which network-capable features are compiled in
+///
+/// With the default `no-network` feature the shell plugin and native plugins
+/// are reported as absent.
 #[tauri::command]
-fn check_usb_status() -> Result<UsbStatus, AppError> {
-    // TODO: Implement actual USB status check via JNI on Android
-    log::info!("Checking USB status");
-    Ok(UsbStatus {
-        connected: false,
-        info: None,
-    })
+fn get_build_capabilities() -> BuildCapabilities {
+    BuildCapabilities::current()
 }
 
-/// Cycle through available camera resolutions within the current format
-/// Returns the new resolution info including dimensions and available count
+/// Collect a diagnostics bundle for bug reports
+///
+/// Includes the linked libusb version and capabilities, which differ across
+/// Android NDK libusb builds.
 #[tauri::command]
-fn cycle_resolution(state: State<'_, AppState>) -> Result<ResolutionInfo, AppError> {
-    let mut config = lock_or_err!(&state.streaming_config)?;
+fn get_diagnostics(state: State<'_, AppState>) -> Result<diagnostics::DiagnosticsBundle, AppError> {
+    collect_diagnostics(&state)
+}
 
-    // Get current format (use first available if none selected)
-    let current_format_idx = config
-        .selected_format_index
-        .or_else(|| config.available_formats.first().map(|f| f.index));
+/// Diagnostics bundle for the running app
+fn collect_diagnostics(state: &AppState) -> Result<diagnostics::DiagnosticsBundle, AppError> {
+    let quirks = lock_or_err!(&state.streaming_config)?.quirks;
+    Ok(diagnostics::DiagnosticsBundle {
+        build: BuildInfo::current(),
+        build_capabilities: BuildCapabilities::current(),
+        libusb: diagnostics::LibusbReport::current(),
+        quirks,
+        stream_health: state.stream_health.stats(),
+    })
+}
 
-    let Some(format_idx) = current_format_idx else {
-        return Err(AppError::NotFound(
-            "No video formats discovered".to_string(),
-        ));
-    };
+/// Get frame assembly statistics: frame rate, frame sizes, dropped, corrupt
+/// and rejected frames, USB errors and assembly latency
+///
+/// The same report is emitted as `stream-stats` while a camera is connected.
+#[tauri::command]
+fn get_stream_stats(state: State<'_, AppState>) -> stats::StreamStatsReport {
+    state.stream_stats.report()
+}
 
-    // Find the current format's frames
-    let format = config
-        .available_formats
-        .iter()
-        .find(|f| f.index == format_idx)
-        .ok_or_else(|| AppError::NotFound(format!("Format {} not found", format_idx)))?;
+/// Set how strictly uncompressed frames are checked for corruption
+///
+/// Takes effect on the next frame; frames that fail are not displayed and
+/// are counted in `rejected_frames` of the stream stats. `Off` displays
+/// every frame. The level is saved in the settings.
+#[tauri::command]
+fn set_validation_level(
+    app: tauri::AppHandle,
+    state: State<'_, AppState>,
+    level: ValidationLevel,
+) -> Result<(), AppError> {
+    *lock_or_err!(state.validation_level)? = level;
+    log::info!("Frame validation level: {:?}", level);
+    change_settings(&app, &state, |settings| {
+        settings.validation_level = Some(level);
+    })
+}
 
-    if format.frames.is_empty() {
-        return Err(AppError::NotFound(
-            "No resolutions available for this format".to_string(),
-        ));
+/// Set how many frames the frame broadcast keeps for slow consumers
+///
+/// `None` restores the default. The value is limited to
+/// `1..=frame_broadcast::MAX_CAPACITY`; a running stream restarts to apply
+/// it. Returns the capacity that will be used.
+#[tauri::command]
+fn set_frame_channel_capacity(
+    state: State<'_, AppState>,
+    capacity: Option<usize>,
+) -> Result<usize, AppError> {
+    let mut config = lock_or_err!(&state.streaming_config)?;
+    config.frame_capacity = capacity.map(frame_broadcast::clamp_capacity);
+    let capacity = config.frame_channel_capacity();
+    if config.active_stream.is_some() {
+        config.restart_requested = true;
     }
+    log::info!("Frame channel capacity set to {} frames", capacity);
+    Ok(capacity)
+}
 
-    // Get current frame index or default to first frame
-    let current_frame_idx = config
-        .selected_frame_index
-        .unwrap_or(format.frames[0].frame_index);
+/// Get the frame broadcast capacity the next stream will use
+#[tauri::command]
+fn get_frame_channel_capacity(state: State<'_, AppState>) -> Result<usize, AppError> {
+    Ok(lock_or_err!(&state.streaming_config)?.frame_channel_capacity())
+}
 
-    // Find current position and cycle to next
-    let current_pos = format
-        .frames
-        .iter()
-        .position(|f| f.frame_index == current_frame_idx)
-        .unwrap_or(0);
-    let next_pos = (current_pos + 1) % format.frames.len();
-    let next_frame = &format.frames[next_pos];
-
-    // Clone data we need before releasing the lock
-    let result = ResolutionInfo {
-        width: next_frame.width,
-        height: next_frame.height,
-        frame_index: next_frame.frame_index,
-        available_count: format.frames.len(),
+/// Package a packet capture for sending to the developers
+///
+/// Nothing is packaged unless `consent` is `true`, which the frontend must only
+/// set after the user agreed to share the capture. The sanitized capture (and,
+/// with `include_diagnostics`, the diagnostics bundle) is written to
+/// `submission_<timestamp>.tar` in the output directory. Nothing is sent: the
+/// returned path is for the platform share sheet.
+#[tauri::command]
+async fn prepare_capture_submission(
+    app: tauri::AppHandle,
+    state: State<'_, AppState>,
+    capture: String,
+    consent: bool,
+    include_diagnostics: Option<bool>,
+    note: Option<String>,
+) -> Result<submission::SubmissionResult, AppError> {
+    let options = submission::SubmissionOptions {
+        consent,
+        include_diagnostics: include_diagnostics.unwrap_or(false),
+        note,
+    };
+    let storage = app_storage(&app, &state)?;
+    let capture_path = storage.resolve(&capture)?;
+    let diagnostics = if options.include_diagnostics {
+        Some(collect_diagnostics(&state)?)
+    } else {
+        None
     };
 
-    // Update state
-    config.selected_frame_index = Some(next_frame.frame_index);
-    config.restart_requested = true;
-
-    log::info!(
-        "Cycling resolution to {}x{} (frame_index={}, {}/{} available)",
-        result.width,
-        result.height,
-        result.frame_index,
-        next_pos + 1,
-        result.available_count
-    );
+    // Large captures take a while to copy; keep it off the async runtime
+    tauri::async_runtime::spawn_blocking(move || {
+        submission::package_capture(&storage, &capture_path, diagnostics.as_ref(), &options)
+    })
+    .await
+    .map_err(|e| AppError::Io(std::io::Error::other(e.to_string())))?
+    .map_err(AppError::from)
+}
 
-    Ok(result)
+/// Change libusb's own log level at runtime
+///
+/// libusb messages are forwarded to the app log under the `libusb` target.
+#[tauri::command]
+fn set_libusb_log_level(level: diagnostics::LibusbLogLevel) {
+    diagnostics::set_libusb_log_level(level);
 }
 
-/// Get the list of available resolutions for the current format
+/// Check the current USB device status
 #[tauri::command]
-fn get_resolutions(state: State<'_, AppState>) -> Result<Vec<Resolution>, AppError> {
-    let config = lock_or_err!(&state.streaming_config)?;
+fn check_usb_status(state: State<'_, AppState>) -> Result<UsbStatus, AppError> {
+    log::info!("Checking USB status");
+    let (connected, info) = state.stream_health.connection();
+    Ok(UsbStatus {
+        connected,
+        info,
+        health: Some(state.stream_health.stats()),
+    })
+}
 
-    // Get current format (use first available if none selected)
-    let current_format_idx = config
-        .selected_format_index
-        .or_else(|| config.available_formats.first().map(|f| f.index));
+/// List the connected UVC cameras
+///
+/// The camera being streamed from is marked `active`.
+#[tauri::command]
+fn list_usb_devices(state: State<'_, AppState>) -> Result<Vec<devices::CameraDevice>, AppError> {
+    let cameras = usb::list_cameras()?;
+    let mut registry = lock_or_err!(&state.devices)?;
+    registry.update(cameras);
+    Ok(registry.list())
+}
 
-    let Some(format_idx) = current_format_idx else {
-        return Ok(vec![]); // No formats discovered yet
+/// Stream from another connected camera
+///
+/// `id` is a device name from `list_usb_devices` or a `vvvv:pppp` vendor and
+/// product ID. The current stream is stopped and the streaming thread opens
+/// the chosen camera; the choice is kept if it is unplugged and plugged back
+/// in, and dropped if the camera can't be opened.
+#[tauri::command]
+fn select_device(
+    state: State<'_, AppState>,
+    id: String,
+) -> Result<devices::CameraDevice, AppError> {
+    let cameras = usb::list_cameras()?;
+    let mut registry = lock_or_err!(&state.devices)?;
+    registry.update(cameras);
+    let device = registry.select(&id)?;
+    if registry.wants_switch() {
+        log::info!("Switching to camera {} ({})", device.id, device.key);
+        lock_or_err!(&state.streaming_config)?.restart_requested = true;
+    }
+    Ok(device)
+}
+
+/// Run startup health checks
+///
+/// Checks USB host support, camera permission, storage access and available
+/// memory, returning a checklist the UI can use to show setup guidance.
+#[tauri::command]
+fn preflight(app: tauri::AppHandle) -> preflight::PreflightReport {
+    let cache_dir = app.path().app_cache_dir().ok();
+    preflight::run_preflight(cache_dir.as_deref())
+}
+
+/// Cycle through available camera resolutions within the current format
+///
+/// The streaming thread stops the stream, renegotiates UVC probe/commit with the
+/// new frame index and restarts streaming. The resolution is saved in the
+/// settings as the preferred one.
+/// Returns the new resolution info including dimensions and available count
+#[tauri::command]
+fn cycle_resolution(
+    app: tauri::AppHandle,
+    state: State<'_, AppState>,
+) -> Result<ResolutionInfo, AppError> {
+    let result = {
+        let mut config = lock_or_err!(&state.streaming_config)?;
+        if config.current_format().is_none() {
+            return Err(AppError::NotFound(
+                "No video formats discovered".to_string(),
+            ));
+        }
+        let result = config.cycle_resolution().ok_or_else(|| {
+            AppError::NotFound("No resolutions available for this format".to_string())
+        })?;
+        config.preferred_resolution = Some(settings::PreferredResolution {
+            width: result.width,
+            height: result.height,
+        });
+        result
     };
+    change_settings(&app, &state, |settings| {
+        settings.resolution = Some(settings::PreferredResolution {
+            width: result.width,
+            height: result.height,
+        });
+    })?;
 
-    // Find the current format's frames
-    let format = config
-        .available_formats
-        .iter()
-        .find(|f| f.index == format_idx);
+    log::info!(
+        "Cycling resolution to {}x{} (frame_index={}, {} available)",
+        result.width,
+        result.height,
+        result.frame_index,
+        result.available_count
+    );
+
+    Ok(result)
+}
+
+/// Get the list of available resolutions for the current format
+///
+/// The current format is the one being streamed, once negotiated; the entry
+/// the camera is streaming is marked `active`.
+#[tauri::command]
+fn get_resolutions(state: State<'_, AppState>) -> Result<Vec<Resolution>, AppError> {
+    let config = lock_or_err!(&state.streaming_config)?;
 
-    let Some(format) = format else {
-        return Ok(vec![]);
+    let Some(format) = config.current_format() else {
+        return Ok(vec![]); // No formats discovered yet
     };
+    let active = config.active_stream;
 
     Ok(format
         .frames
@@ -430,63 +1361,103 @@ fn get_resolutions(state: State<'_, AppState>) -> Result<Vec<Resolution>, AppErr
         .map(|f| Resolution {
             width: f.width as u32,
             height: f.height as u32,
+            frame_index: f.index,
+            active: active
+                .is_some_and(|a| a.format_index == format.index && a.frame_index == f.index),
         })
         .collect())
 }
 
 /// Get the current resolution info
+///
+/// While streaming, this is the resolution negotiated with the camera.
 #[tauri::command]
 fn get_current_resolution(state: State<'_, AppState>) -> Result<ResolutionInfo, AppError> {
     let config = lock_or_err!(&state.streaming_config)?;
 
-    // Get current format (use first available if none selected)
-    let current_format_idx = config
-        .selected_format_index
-        .or_else(|| config.available_formats.first().map(|f| f.index));
-
-    let Some(format_idx) = current_format_idx else {
+    let Some(format) = config.current_format() else {
         return Err(AppError::NotFound(
             "No video formats discovered".to_string(),
         ));
     };
-
-    // Find the current format's frames
-    let format = config
-        .available_formats
-        .iter()
-        .find(|f| f.index == format_idx)
-        .ok_or_else(|| AppError::NotFound(format!("Format {} not found", format_idx)))?;
-
-    if format.frames.is_empty() {
-        return Err(AppError::NotFound("No resolutions available".to_string()));
+    let available_count = format.frames.len();
+
+    if let Some(active) = config.active_stream {
+        return Ok(ResolutionInfo {
+            width: active.width,
+            height: active.height,
+            frame_index: active.frame_index,
+            available_count,
+        });
     }
 
-    // Get current frame index or default to first frame
-    let current_frame_idx = config
-        .selected_frame_index
-        .unwrap_or(format.frames[0].frame_index);
-    let current_frame = format
-        .frames
-        .iter()
-        .find(|f| f.frame_index == current_frame_idx)
-        .or_else(|| format.frames.first())
-        .ok_or_else(|| AppError::NotFound("No frames available".to_string()))?;
+    let current_frame = config
+        .current_frame_index()
+        .and_then(|index| format.frame(index))
+        .or_else(|| format.default_frame())
+        .ok_or_else(|| AppError::NotFound("No resolutions available".to_string()))?;
 
     Ok(ResolutionInfo {
         width: current_frame.width,
         height: current_frame.height,
-        frame_index: current_frame.frame_index,
-        available_count: format.frames.len(),
+        frame_index: current_frame.index,
+        available_count,
     })
 }
 
-/// Frame information returned to frontend
-#[derive(Debug, Clone, serde::Serialize)]
-struct FrameInfo {
-    width: u32,
-    height: u32,
+/// Get the format, resolution, frame rate and payload size the camera agreed
+/// to in the last probe/commit
+#[tauri::command]
+fn get_stream_info(state: State<'_, AppState>) -> Result<StreamInfo, AppError> {
+    lock_or_err!(&state.streaming_config)?
+        .stream_info()
+        .ok_or_else(|| AppError::NotFound("No stream negotiated".to_string()))
+}
+
+/// Get the frame rates a resolution of the current format supports
+///
+/// `frame_index` is a resolution from `get_resolutions` (default: the
+/// current one). Cameras with a continuous interval range list its ends and
+/// the common rates within it.
+#[tauri::command]
+fn get_framerates(
+    state: State<'_, AppState>,
+    frame_index: Option<u8>,
+) -> Result<Vec<FrameRate>, AppError> {
+    let config = lock_or_err!(&state.streaming_config)?;
+    config
+        .frame_rates(frame_index)
+        .ok_or_else(|| AppError::NotFound("Resolution not found".to_string()))
+}
+
+/// Renegotiate the stream at the supported frame rate closest to `fps`
+#[tauri::command]
+fn set_framerate(state: State<'_, AppState>, fps: f32) -> Result<FrameRate, AppError> {
+    let mut config = lock_or_err!(&state.streaming_config)?;
+    let rate = config
+        .set_frame_rate(fps)
+        .ok_or_else(|| AppError::NotFound(format!("No frame rate near {} fps", fps)))?;
+    log::info!(
+        "Setting frame rate to {:.2} fps (interval {})",
+        rate.fps,
+        rate.frame_interval
+    );
+    Ok(rate)
+}
+
+/// Frame metadata sent with `frame-ready` and returned by `get_frame_info`
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize)]
+pub struct FrameInfo {
+    /// Sequence number of the frame (see [`Frame::sequence`])
+    pub sequence: u64,
+    /// Frame width in pixels (0 if unknown)
+    pub width: u32,
+    /// Frame height in pixels (0 if unknown)
+    pub height: u32,
     /// "jpeg" or "rgb"
-    format: String,
+    pub format: String,
+    /// Frame size in bytes
+    pub size: usize,
 }
 
 /// Get the latest camera frame as raw bytes
@@ -498,13 +1469,48 @@ struct FrameInfo {
 /// - YUY2 cameras: Raw RGB24 data (3 bytes per pixel)
 #[tauri::command]
 fn get_frame(state: State<'_, AppState>) -> Result<tauri::ipc::Response, AppError> {
-    let buffer = lock_or_err!(state.frame_buffer)?;
+    let frame = state.frame_buffer.load();
 
-    if buffer.frame.is_empty() {
+    if frame.is_empty() {
         return Err(AppError::NoFrame);
     }
 
-    Ok(tauri::ipc::Response::new(buffer.frame.clone()))
+    Ok(tauri::ipc::Response::new(frame.data.to_vec()))
+}
+
+/// Get the latest camera frame as RGB24 (3 bytes per pixel)
+///
+/// MJPEG frames are decoded; the result is cached per frame, so repeated
+/// calls for the same frame decode it once. Dimensions are those reported in
+/// the `frame-ready` event (see [`FrameInfo`]).
+#[tauri::command]
+fn get_frame_rgb(state: State<'_, AppState>) -> Result<tauri::ipc::Response, AppError> {
+    let rgb = current_frame_rgb(&state)?;
+    Ok(tauri::ipc::Response::new(rgb.as_ref().clone()))
+}
+
+/// Decode the current frame to RGB24, reusing the cached result if it is unchanged
+///
+/// Decodes the frame it loaded, so streaming continues while it decodes.
+fn current_frame_rgb(state: &AppState) -> Result<Arc<Vec<u8>>, AppError> {
+    frame_rgb(state, &state.frame_buffer.load())
+}
+
+/// Decode a loaded frame to RGB24, reusing the cached result if it is unchanged
+///
+/// For callers that also need the frame's sequence or dimensions, which
+/// must come from the frame that was decoded.
+fn frame_rgb(state: &AppState, frame: &Frame) -> Result<Arc<Vec<u8>>, AppError> {
+    if frame.is_empty() {
+        return Err(AppError::NoFrame);
+    }
+    let mut cache = lock_or_err!(state.frame_cache)?;
+    Ok(
+        cache.get_or_try_insert_with(frame.sequence, frame_cache::Conversion::Rgb, || {
+            image_encoder::decode_frame(&frame.data, frame.width, frame.height)
+                .map(|(rgb, _, _)| rgb)
+        })?,
+    )
 }
 
 /// Captured frame information returned to frontend
@@ -522,6 +1528,8 @@ struct CapturedFrame {
     header_hex: String,
     /// Detected format hint
     format_hint: String,
+    /// Format the processed frame was encoded to (`None`: saved as captured)
+    image_format: Option<ImageFormat>,
     /// Frame dimensions if known
     width: u32,
     height: u32,
@@ -530,29 +1538,61 @@ struct CapturedFrame {
 /// Dump the current frame to files for analysis
 ///
 /// Saves both the processed frame (RGB/JPEG) and the raw frame (YUY2) if available.
+/// The processed frame is encoded as `format` if given, otherwise as the
+/// snapshot format setting (see `set_snapshot_format`).
 /// Returns information about the captured frames including file paths.
 /// Automatically disables raw frame capture after dumping to save memory bandwidth.
 #[tauri::command]
 fn dump_frame(
     app: tauri::AppHandle,
     state: State<'_, AppState>,
+    format: Option<ImageFormat>,
 ) -> Result<CapturedFrame, AppError> {
-    use std::io::Write;
+    save_frame_dump(&app, &state, format)
+}
+
+/// Encode the processed frame for saving, returning the bytes and file extension
+///
+/// With no format the frame is kept as captured: JPEG, or raw RGB24 (`.rgb`).
+/// Encoded frames are cached, so saving the same frame twice encodes it once.
+fn encode_processed_frame(
+    frame: &Frame,
+    format: Option<ImageFormat>,
+    cache: &mut frame_cache::FrameCache,
+) -> Result<(Arc<Vec<u8>>, &'static str), AppError> {
+    match format {
+        Some(format) => {
+            let encoded = cache.get_or_try_insert_with(
+                frame.sequence,
+                frame_cache::Conversion::Image(format),
+                || image_encoder::encode_frame(&frame.data, frame.width, frame.height, format),
+            )?;
+            Ok((encoded, format.extension()))
+        }
+        None if is_jpeg_data(&frame.data) => Ok((Arc::clone(&frame.data), "jpg")),
+        None => Ok((Arc::clone(&frame.data), "rgb")),
+    }
+}
 
-    let mut buffer = lock_or_err!(&state.frame_buffer)?;
+/// Write the current frame (and raw frame, if captured) to the app cache directory
+///
+/// `format` overrides the snapshot format setting for this call.
+fn save_frame_dump(
+    app: &AppHandle,
+    state: &AppState,
+    format: Option<ImageFormat>,
+) -> Result<CapturedFrame, AppError> {
+    let format = match format {
+        Some(format) => Some(format),
+        None => *lock_or_err!(&state.snapshot_format)?,
+    };
+    let frame = state.frame_buffer.load();
 
-    if buffer.frame.is_empty() {
+    if frame.is_empty() {
         return Err(AppError::NoFrame);
     }
 
-    // Get app cache directory (works on Android)
-    let cache_dir = app
-        .path()
-        .app_cache_dir()
-        .map_err(|e| AppError::PathError(e.to_string()))?;
-
-    // Create directory if it doesn't exist
-    std::fs::create_dir_all(&cache_dir)?;
+    let storage = app_storage(app, state)?;
 
     // Generate filename with timestamp
     let timestamp = std::time::SystemTime::now()
@@ -561,11 +1601,11 @@ fn dump_frame(
         .unwrap_or(0);
 
     // Detect format from raw frame first bytes (if available), otherwise from processed frame
-    let raw_available = !buffer.raw_frame.is_empty();
-    let analysis_data = if raw_available {
-        &buffer.raw_frame
+    let raw_available = !frame.raw.is_empty();
+    let analysis_data: &[u8] = if raw_available {
+        &frame.raw
     } else {
-        &buffer.frame
+        &frame.data
     };
 
     let (format_hint, raw_extension) = if is_jpeg_data(analysis_data) {
@@ -587,42 +1627,35 @@ fn dump_frame(
         }
     };
 
-    // Save processed frame (RGB or JPEG)
-    let processed_ext = if is_jpeg_data(&buffer.frame) {
-        "jpg"
-    } else {
-        "rgb"
+    // Save processed frame (as captured, or encoded to the requested format)
+    let (processed, processed_ext) = {
+        let mut cache = lock_or_err!(&state.frame_cache)?;
+        encode_processed_frame(&frame, format, &mut cache)?
     };
     let processed_filename = format!(
         "frame_{}_{}x{}.{}",
-        timestamp, buffer.width, buffer.height, processed_ext
+        timestamp, frame.width, frame.height, processed_ext
     );
-    let processed_filepath = cache_dir.join(&processed_filename);
-
-    let mut file = std::fs::File::create(&processed_filepath)?;
-    file.write_all(&buffer.frame)?;
+    let processed_filepath = storage.write(&processed_filename, processed.as_slice())?;
 
     log::info!(
         "Dumped processed frame to {}: {} bytes",
         processed_filepath.display(),
-        buffer.frame.len()
+        processed.len()
     );
 
     // Save raw frame if available
     let raw_path = if raw_available {
         let raw_filename = format!(
             "frame_{}_{}x{}_raw.{}",
-            timestamp, buffer.width, buffer.height, raw_extension
+            timestamp, frame.width, frame.height, raw_extension
         );
-        let raw_filepath = cache_dir.join(&raw_filename);
-
-        let mut file = std::fs::File::create(&raw_filepath)?;
-        file.write_all(&buffer.raw_frame)?;
+        let raw_filepath = storage.write(&raw_filename, &frame.raw)?;
 
         log::info!(
             "Dumped raw frame to {}: {} bytes, format: {}",
             raw_filepath.display(),
-            buffer.raw_frame.len(),
+            frame.raw.len(),
             format_hint
         );
 
@@ -642,15 +1675,13 @@ fn dump_frame(
 
     log::info!("Header: {}", header_hex);
 
-    // Capture values before clearing
-    let frame_size = buffer.frame.len();
-    let raw_size = buffer.raw_frame.len();
-    let width = buffer.width;
-    let height = buffer.height;
+    let frame_size = processed.len();
+    let raw_size = frame.raw.len();
+    let width = frame.width;
+    let height = frame.height;
 
-    // Disable raw capture and clear raw frame buffer to save memory
-    buffer.capture_raw_frames = false;
-    buffer.raw_frame.clear();
+    // Disable raw capture; the next stored frame drops the raw data
+    state.frame_buffer.set_capture_raw_frames(false);
     log::info!("Raw frame capture disabled after dump");
 
     Ok(CapturedFrame {
@@ -660,340 +1691,2749 @@ fn dump_frame(
         raw_size,
         header_hex,
         format_hint: format_hint.to_string(),
+        image_format: format,
         width,
         height,
     })
 }
 
-/// Get frame metadata (dimensions and format)
+/// Save the current frame as an image with a timestamped filename
+///
+/// `path` is a directory inside the output directory (default: the output
+/// directory itself). The frame is encoded as `format` if given, otherwise as
+/// the snapshot format setting (see `set_snapshot_format`). Emits
+/// `snapshot-saved` with the saved file.
 #[tauri::command]
-fn get_frame_info(state: State<'_, AppState>) -> Result<FrameInfo, AppError> {
-    let buffer = lock_or_err!(state.frame_buffer)?;
+fn save_snapshot(
+    app: tauri::AppHandle,
+    state: State<'_, AppState>,
+    path: Option<String>,
+    format: Option<ImageFormat>,
+) -> Result<recording::Snapshot, AppError> {
+    snapshot_current_frame(&app, &state, path, format)
+}
 
-    if buffer.frame.is_empty() {
-        return Err(AppError::NoFrame);
-    }
+/// Encode the current frame into the output directory and emit `snapshot-saved`
+fn snapshot_current_frame(
+    app: &AppHandle,
+    state: &AppState,
+    path: Option<String>,
+    format: Option<ImageFormat>,
+) -> Result<recording::Snapshot, AppError> {
+    let format = match format {
+        Some(format) => Some(format),
+        None => *lock_or_err!(&state.snapshot_format)?,
+    };
+    let storage = app_storage(app, state)?;
+
+    let (data, extension, width, height) = {
+        let frame = state.frame_buffer.load();
+        if frame.is_empty() {
+            return Err(AppError::NoFrame);
+        }
+        let mut cache = lock_or_err!(&state.frame_cache)?;
+        let (data, extension) = encode_processed_frame(&frame, format, &mut cache)?;
+        (data, extension, frame.width, frame.height)
+    };
+
+    let snapshot = recording::write_snapshot(
+        &storage,
+        path.as_deref().unwrap_or_default(),
+        &data,
+        width,
+        height,
+        extension,
+        format,
+    )?;
+    log::info!(
+        "Saved snapshot to {}: {} bytes",
+        snapshot.path,
+        snapshot.size
+    );
 
-    // Detect format based on JPEG signature
-    let format = if is_jpeg_data(&buffer.frame) {
-        "jpeg".to_string()
+    let _ = app.emit("snapshot-saved", &snapshot);
+    Ok(snapshot)
+}
+
+/// Take a still image with the camera's still capture method and save it
+///
+/// Negotiates a `width` x `height` still (default: the largest still size of
+/// the streaming format; give both or neither), triggers it and waits for it; with method 2 the
+/// still takes the place of one video frame. The still is saved like
+/// `save_snapshot`: into `path` inside the output directory, encoded as
+/// `format` or the snapshot format setting. Emits `snapshot-saved`.
+#[tauri::command]
+async fn capture_still(
+    app: tauri::AppHandle,
+    state: State<'_, AppState>,
+    width: Option<u16>,
+    height: Option<u16>,
+    path: Option<String>,
+    format: Option<ImageFormat>,
+) -> Result<recording::Snapshot, AppError> {
+    let format = match format {
+        Some(format) => Some(format),
+        None => *lock_or_err!(&state.snapshot_format)?,
+    };
+    let size = still_capture::requested_size(width, height)?;
+    let storage = app_storage(&app, &state)?;
+    let (request, pixel_format) = still_request(&lock_or_err!(&state.streaming_config)?, size)?;
+
+    let still_capture = Arc::clone(&state.still_capture);
+    let still = tauri::async_runtime::spawn_blocking(move || still_capture.capture(&request))
+        .await
+        .map_err(|e| AppError::Io(std::io::Error::other(e.to_string())))??;
+
+    let (width, height) = (u32::from(still.width), u32::from(still.height));
+    let image = if is_jpeg_data(&still.data) {
+        still.data
     } else {
-        "rgb".to_string()
+        let stride = width * u32::from(format_registry::entry(pixel_format).bits_per_pixel) / 8;
+        yuv_conversion::convert_to_rgb(&still.data, width, height, stride, pixel_format)?
+    };
+    let (data, extension) = match format {
+        Some(format) => (
+            image_encoder::encode_frame(&image, width, height, format)?,
+            format.extension(),
+        ),
+        None if is_jpeg_data(&image) => (image, "jpg"),
+        None => (image, "rgb"),
     };
 
-    Ok(FrameInfo {
-        width: buffer.width,
-        height: buffer.height,
+    let snapshot = recording::write_snapshot(
+        &storage,
+        path.as_deref().unwrap_or_default(),
+        &data,
+        width,
+        height,
+        extension,
         format,
-    })
+    )?;
+    log::info!(
+        "Saved {}x{} still to {}: {} bytes",
+        width,
+        height,
+        snapshot.path,
+        snapshot.size
+    );
+
+    let _ = app.emit("snapshot-saved", &snapshot);
+    Ok(snapshot)
 }
 
-/// Cycle through options: None -> 0 -> 1 -> ... -> N-1 -> None
-fn cycle_index(current: &mut Option<usize>, max_len: usize) -> Option<usize> {
-    let new_index = match *current {
-        None => Some(0),
-        Some(i) if i + 1 < max_len => Some(i + 1),
-        Some(_) => None,
+/// Still request for the running stream, with the pixel format of its frames
+fn still_request(
+    config: &StreamingConfig,
+    size: Option<(u16, u16)>,
+) -> Result<(still_capture::StillRequest, PixelFormat), still_capture::StillError> {
+    let active = config
+        .active_stream
+        .ok_or(still_capture::StillError::NotStreaming)?;
+    let format = config
+        .format_catalog
+        .format(active.format_index)
+        .ok_or(still_capture::StillError::NoStillSizes(active.format_index))?;
+    let pixel_format = format.pixel_format().unwrap_or(config.pixel_format);
+    let request = still_capture::StillRequest {
+        method: still_capture::StillMethod::from_descriptor(
+            config.format_catalog.still_capture_method,
+        ),
+        format: format.clone(),
+        video_size: (active.width, active.height),
+        size,
     };
-    *current = new_index;
-    new_index
+    Ok((request, pixel_format))
 }
 
-/// Cycle through width options
+/// Save a snapshot every `interval_secs` seconds while the camera streams
+///
+/// Snapshots go into the session's directory and are listed in the session
+/// manifest as `<label>-0001`, `<label>-0002`, ... (default label `auto`).
+/// Replaces a running schedule; the first snapshot is taken right away.
+/// Emits `auto-snapshot` with each saved [`session::SessionSnapshot`].
 #[tauri::command]
-fn cycle_width(state: State<'_, AppState>) -> Result<String, AppError> {
-    let mut display = lock_or_err!(state.display)?;
-
-    let new_index = cycle_index(&mut display.width_index, WIDTH_OPTIONS.len());
-    display.settings.width = new_index.map(|i| WIDTH_OPTIONS[i]);
-
-    Ok(match new_index {
-        None => "W:Auto".to_string(),
-        Some(i) => format!("W:{}", WIDTH_OPTIONS[i]),
-    })
+fn start_auto_snapshot(
+    state: State<'_, AppState>,
+    interval_secs: f64,
+    label: Option<String>,
+    format: Option<ImageFormat>,
+) -> Result<auto_snapshot::AutoSnapshotStatus, AppError> {
+    let settings = auto_snapshot::AutoSnapshotSettings::new(interval_secs, label, format)?;
+    log::info!(
+        "Auto-snapshot every {} s as {}",
+        settings.interval_secs,
+        settings.label
+    );
+    Ok(state.auto_snapshot.start(settings, Instant::now()))
 }
 
-/// Cycle through height options
+/// Stop scheduled snapshots and return how many were taken
 #[tauri::command]
-fn cycle_height(state: State<'_, AppState>) -> Result<String, AppError> {
-    let mut display = lock_or_err!(state.display)?;
+fn stop_auto_snapshot(state: State<'_, AppState>) -> auto_snapshot::AutoSnapshotStatus {
+    state.auto_snapshot.stop(Instant::now())
+}
 
-    let new_index = cycle_index(&mut display.height_index, HEIGHT_OPTIONS.len());
-    display.settings.height = new_index.map(|i| HEIGHT_OPTIONS[i]);
+/// Get the state of the snapshot schedule
+#[tauri::command]
+fn get_auto_snapshot_status(state: State<'_, AppState>) -> auto_snapshot::AutoSnapshotStatus {
+    state.auto_snapshot.status(Instant::now())
+}
 
-    Ok(match new_index {
-        None => "H:Auto".to_string(),
-        Some(i) => format!("H:{}", HEIGHT_OPTIONS[i]),
-    })
+/// Save a due scheduled snapshot into the session
+fn take_auto_snapshot(
+    app: &AppHandle,
+    state: &AppState,
+    settings: &auto_snapshot::AutoSnapshotSettings,
+    frame_sequence: u64,
+) -> Result<session::SessionSnapshot, AppError> {
+    let dir = lock_or_err!(state.session)?.snapshot_dir();
+    let snapshot = snapshot_current_frame(app, state, Some(dir), settings.format)?;
+
+    let mut manifest = lock_or_err!(state.session)?;
+    let entry = manifest.add_snapshot(&settings.label, frame_sequence, snapshot.path);
+    manifest.save(&app_storage(app, state)?)?;
+    Ok(entry)
 }
 
-/// Cycle through stride options
+/// Freeze the frame on screen for annotation
+///
+/// Keeps a decoded copy while the stream continues, replacing any frozen
+/// frame and its annotations.
 #[tauri::command]
-fn cycle_stride(state: State<'_, AppState>) -> Result<String, AppError> {
-    let mut display = lock_or_err!(state.display)?;
-
-    let new_index = cycle_index(&mut display.stride_index, STRIDE_OPTIONS.len());
+fn freeze_frame(state: State<'_, AppState>) -> Result<freeze::FrozenFrame, AppError> {
+    let frame = state.frame_buffer.load();
+    let rgb = frame_rgb(&state, &frame)?;
+    let frozen = freeze::FrozenFrame::new(frame.sequence, rgb, frame.width, frame.height)?;
+    log::info!("Froze frame {}", frozen.sequence);
+    *lock_or_err!(state.frozen)? = Some(frozen.clone());
+    Ok(frozen)
+}
 
-    Ok(match new_index {
-        None => "S:Auto".to_string(),
-        Some(i) => format!("S:x{:.3}", STRIDE_OPTIONS[i]),
-    })
+/// Discard the frozen frame and its annotations
+#[tauri::command]
+fn unfreeze_frame(state: State<'_, AppState>) -> Result<(), AppError> {
+    *lock_or_err!(state.frozen)? = None;
+    Ok(())
 }
 
-/// Get current display settings as a summary string
+/// Get the frozen frame's sequence, dimensions and annotations, if frozen
 #[tauri::command]
-fn get_display_settings(state: State<'_, AppState>) -> Result<String, AppError> {
-    let display = lock_or_err!(state.display)?;
-    let w = display
-        .settings
-        .width
-        .map(|v| v.to_string())
-        .unwrap_or_else(|| "Auto".to_string());
-    let h = display
-        .settings
-        .height
-        .map(|v| v.to_string())
-        .unwrap_or_else(|| "Auto".to_string());
-    let s = display
-        .settings
-        .stride
-        .map(|v| v.to_string())
-        .unwrap_or_else(|| "Auto".to_string());
-    Ok(format!("{}x{} stride:{}", w, h, s))
+fn get_frozen_frame(state: State<'_, AppState>) -> Result<Option<freeze::FrozenFrame>, AppError> {
+    Ok(lock_or_err!(state.frozen)?.clone())
 }
 
-/// Toggle MJPEG detection skip
-/// When enabled, skips MJPEG format probing and goes straight to YUV streaming
+/// Run `f` on the frozen frame and record the annotation change for undo
+fn with_frozen<T>(
+    state: &AppState,
+    f: impl FnOnce(&mut freeze::FrozenFrame) -> freeze::Result<T>,
+) -> Result<T, AppError> {
+    let (result, before, after) = {
+        let mut frozen = lock_or_err!(state.frozen)?;
+        let frozen = frozen.as_mut().ok_or(freeze::FreezeError::NotFrozen)?;
+        let before = frozen.annotations.clone();
+        let result = f(frozen)?;
+        let snapshot = |annotations| history::Snapshot::Annotations {
+            sequence: frozen.sequence,
+            annotations,
+        };
+        (
+            result,
+            snapshot(before),
+            snapshot(frozen.annotations.clone()),
+        )
+    };
+    record_edit(state, "annotation", before, after)?;
+    Ok(result)
+}
+
+/// Add an arrow, circle or text position to the frozen frame
 #[tauri::command]
-fn toggle_skip_mjpeg(state: State<'_, AppState>) -> Result<String, AppError> {
-    let mut config = lock_or_err!(&state.streaming_config)?;
-    config.skip_mjpeg_detection = !config.skip_mjpeg_detection;
-    log::info!("MJPEG skip: {}", config.skip_mjpeg_detection);
-    Ok(if config.skip_mjpeg_detection {
-        "MJPEG:Skip".to_string()
-    } else {
-        "MJPEG:Try".to_string()
-    })
+fn add_frozen_annotation(
+    state: State<'_, AppState>,
+    shape: freeze::Shape,
+    color: Option<[u8; 3]>,
+) -> Result<freeze::VectorAnnotation, AppError> {
+    with_frozen(&state, |frozen| frozen.add(shape, color))
 }
 
-/// Enable raw frame capture for one frame
-/// This enables capturing the next raw frame data for debugging/analysis.
-/// After the frame is captured, call `dump_frame` to save it.
-/// Automatically disables after `dump_frame` is called.
+/// Replace an annotation of the frozen frame
 #[tauri::command]
-fn enable_raw_capture(state: State<'_, AppState>) -> Result<String, AppError> {
-    let mut buffer = lock_or_err!(&state.frame_buffer)?;
-    buffer.capture_raw_frames = true;
-    log::info!("Raw frame capture enabled");
-    Ok("Raw capture enabled".to_string())
+fn update_frozen_annotation(
+    state: State<'_, AppState>,
+    id: u32,
+    shape: freeze::Shape,
+    color: Option<[u8; 3]>,
+) -> Result<freeze::VectorAnnotation, AppError> {
+    with_frozen(&state, |frozen| frozen.update(id, shape, color))
 }
 
-/// Check if raw frame capture is enabled
+/// Remove an annotation from the frozen frame
 #[tauri::command]
-fn is_raw_capture_enabled(state: State<'_, AppState>) -> Result<bool, AppError> {
-    let buffer = lock_or_err!(&state.frame_buffer)?;
-    Ok(buffer.capture_raw_frames)
+fn remove_frozen_annotation(state: State<'_, AppState>, id: u32) -> Result<(), AppError> {
+    with_frozen(&state, |frozen| frozen.remove(id))
 }
 
-/// Cycle through pixel format options (YUYV / UYVY / NV12 / I420 / RGB888 / BGR888)
+/// Save the frozen frame with its annotations drawn in
+///
+/// Written like `save_snapshot` (RGB24 if no format is given or set), with the
+/// annotations as JSON in a file of the same name ending in `.json`.
 #[tauri::command]
-fn cycle_pixel_format(state: State<'_, AppState>) -> Result<String, AppError> {
-    let mut config = lock_or_err!(&state.streaming_config)?;
-    config.pixel_format = match config.pixel_format {
-        PixelFormat::Yuyv => PixelFormat::Uyvy,
-        PixelFormat::Uyvy => PixelFormat::Nv12,
-        PixelFormat::Nv12 => PixelFormat::I420,
-        PixelFormat::I420 => PixelFormat::Rgb888,
-        PixelFormat::Rgb888 => PixelFormat::Bgr888,
-        PixelFormat::Bgr888 => PixelFormat::Yuyv,
+fn save_frozen_snapshot(
+    app: tauri::AppHandle,
+    state: State<'_, AppState>,
+    path: Option<String>,
+    format: Option<ImageFormat>,
+) -> Result<recording::Snapshot, AppError> {
+    let format = match format {
+        Some(format) => Some(format),
+        None => *lock_or_err!(&state.snapshot_format)?,
+    };
+    let frozen = lock_or_err!(state.frozen)?
+        .clone()
+        .ok_or(freeze::FreezeError::NotFrozen)?;
+    let composite = frozen.render();
+    let (data, extension) = match format {
+        Some(format) => (
+            image_encoder::encode_frame(&composite, frozen.width, frozen.height, format)?,
+            format.extension(),
+        ),
+        None => (composite, "rgb"),
     };
-    log::info!("Pixel format: {:?}", config.pixel_format);
-    Ok(format_pixel_display(&config.pixel_format))
-}
 
-/// Format pixel format for display
-fn format_pixel_display(format: &PixelFormat) -> String {
-    match format {
-        PixelFormat::Yuyv => "FMT:YUYV".to_string(),
-        PixelFormat::Uyvy => "FMT:UYVY".to_string(),
-        PixelFormat::Nv12 => "FMT:NV12".to_string(),
-        PixelFormat::I420 => "FMT:I420".to_string(),
-        PixelFormat::Rgb888 => "FMT:RGB24".to_string(),
-        PixelFormat::Bgr888 => "FMT:BGR24".to_string(),
+    let storage = app_storage(&app, &state)?;
+    let dir = path.as_deref().unwrap_or_default();
+    let snapshot = recording::write_snapshot(
+        &storage,
+        dir,
+        &data,
+        frozen.width,
+        frozen.height,
+        extension,
+        format,
+    )?;
+    if let Some(name) = std::path::Path::new(&snapshot.path).file_name() {
+        let sidecar = std::path::Path::new(dir).join(name).with_extension("json");
+        let json = serde_json::to_string_pretty(&frozen).map_err(std::io::Error::from)?;
+        storage.write(sidecar, json)?;
     }
+    log::info!(
+        "Saved annotated frame {} to {}",
+        frozen.sequence,
+        snapshot.path
+    );
+
+    let _ = app.emit("snapshot-saved", &snapshot);
+    Ok(snapshot)
 }
 
-/// Get current streaming configuration
-#[tauri::command]
-fn get_streaming_config(state: State<'_, AppState>) -> Result<(String, String), AppError> {
-    let config = lock_or_err!(&state.streaming_config)?;
-    let mjpeg = if config.skip_mjpeg_detection {
-        "MJPEG:Skip".to_string()
-    } else {
-        "MJPEG:Try".to_string()
-    };
-    let pixel = format_pixel_display(&config.pixel_format);
-    Ok((mjpeg, pixel))
+/// Snapshot format setting and the formats this build can encode
+#[derive(Debug, Clone, Serialize)]
+struct SnapshotFormats {
+    /// Current setting (`None`: frames are saved as captured)
+    current: Option<ImageFormat>,
+    /// Formats whose encoder is compiled in
+    available: Vec<ImageFormat>,
 }
 
-/// Cycle through available video formats
-/// Returns the new format setting as a display string
+/// Get the snapshot format setting and the formats available in this build
 #[tauri::command]
-fn cycle_video_format(state: State<'_, AppState>) -> Result<String, AppError> {
-    let mut config = lock_or_err!(&state.streaming_config)?;
+fn get_snapshot_formats(state: State<'_, AppState>) -> Result<SnapshotFormats, AppError> {
+    Ok(SnapshotFormats {
+        current: *lock_or_err!(&state.snapshot_format)?,
+        available: ImageFormat::available(),
+    })
+}
 
-    if config.available_formats.is_empty() {
-        // No formats discovered yet
-        return Ok("FMT:Auto".to_string());
-    }
+/// Set the format frames are saved in (`None`: keep as captured)
+#[tauri::command]
+fn set_snapshot_format(
+    state: State<'_, AppState>,
+    format: Option<ImageFormat>,
+) -> Result<(), AppError> {
+    if let Some(format) = format {
+        if !format.is_available() {
+            return Err(image_encoder::EncodeError::Unsupported(format).into());
+        }
+    }
+    *lock_or_err!(&state.snapshot_format)? = format;
+    log::info!("Snapshot format: {:?}", format);
+    Ok(())
+}
+
+/// Parse `CLEANSCOPE_SNAPSHOT_FORMAT`, ignoring unknown or unavailable formats
+///
+/// `native` (or an empty value) keeps frames as captured.
+fn parse_snapshot_format(value: &str) -> Option<ImageFormat> {
+    if value.trim().is_empty() || value.trim().eq_ignore_ascii_case("native") {
+        return None;
+    }
+    match value.parse::<ImageFormat>() {
+        Ok(format) if format.is_available() => Some(format),
+        Ok(format) => {
+            log::warn!(
+                "{} encoding is not available in this build, ignoring",
+                format
+            );
+            None
+        }
+        Err(e) => {
+            log::warn!("Invalid CLEANSCOPE_SNAPSHOT_FORMAT: {}", e);
+            None
+        }
+    }
+}
+
+/// Get the frame spooler's settings, directory and counters
+#[tauri::command]
+fn get_spool_status(state: State<'_, AppState>) -> spool::SpoolStatus {
+    state.spooler.status()
+}
+
+/// Change the frame spooler's settings
+///
+/// The writer thread starts the first time spooling is enabled. Returns the
+/// updated status.
+#[tauri::command]
+fn set_spool_config(
+    app: tauri::AppHandle,
+    state: State<'_, AppState>,
+    config: spool::SpoolConfig,
+) -> Result<spool::SpoolStatus, AppError> {
+    if let Some(format) = config.format {
+        if !format.is_available() {
+            return Err(image_encoder::EncodeError::Unsupported(format).into());
+        }
+    }
+    if config.is_enabled() && state.spooler.status().directory.is_none() {
+        state.spooler.start(&app_storage(&app, &state)?)?;
+    }
+    state.spooler.set_config(config);
+    Ok(state.spooler.status())
+}
+
+/// Get frame metadata (dimensions and format)
+#[tauri::command]
+fn get_frame_info(state: State<'_, AppState>) -> Result<FrameInfo, AppError> {
+    let frame = state.frame_buffer.load();
+
+    if frame.is_empty() {
+        return Err(AppError::NoFrame);
+    }
+
+    Ok(frame.info())
+}
+
+/// Push every new frame to `channel` instead of waiting for `get_frame` polls
+///
+/// Each message is a binary frame with a small header (see `frame_stream`).
+/// Returns the subscription id for `unsubscribe_frames`.
+#[tauri::command]
+fn subscribe_frames(
+    state: State<'_, AppState>,
+    channel: tauri::ipc::Channel<tauri::ipc::InvokeResponseBody>,
+) -> u32 {
+    state.frame_stream.subscribe(channel)
+}
+
+/// Stop pushing frames to a channel registered with `subscribe_frames`
+#[tauri::command]
+fn unsubscribe_frames(state: State<'_, AppState>, id: u32) -> bool {
+    state.frame_stream.unsubscribe(id)
+}
+
+/// Let a channel registered with `subscribe_frames` receive its next frame
+///
+/// Called by the frontend once it has displayed the last frame pushed.
+#[tauri::command]
+fn ack_frame(state: State<'_, AppState>, id: u32) -> bool {
+    state.frame_stream.ack(id)
+}
+
+/// Directory plugins are loaded from (`plugins/` in the app data directory)
+fn plugin_dir(app: &AppHandle) -> Result<std::path::PathBuf, AppError> {
+    app.path()
+        .app_data_dir()
+        .map(|dir| dir.join(plugins::PLUGIN_DIR))
+        .map_err(|e| AppError::PathError(e.to_string()))
+}
+
+/// List plugin directories and whether each plugin loaded
+#[tauri::command]
+fn list_plugins(state: State<'_, AppState>) -> Vec<plugins::PluginInfo> {
+    state.plugins.list()
+}
+
+/// Unload all plugins and load them again from the plugins directory
+#[tauri::command]
+fn reload_plugins(
+    app: tauri::AppHandle,
+    state: State<'_, AppState>,
+) -> Result<Vec<plugins::PluginInfo>, AppError> {
+    Ok(state.plugins.load_dir(&plugin_dir(&app)?))
+}
+
+/// Get how annotations are delivered and recorded
+#[tauri::command]
+fn get_annotation_config(state: State<'_, AppState>) -> annotations::AnnotationConfig {
+    state.annotations.config()
+}
+
+/// Set how annotations are delivered and recorded
+///
+/// With `burn_into_recordings`, active annotations are drawn into recorded
+/// RGB frames (not MJPEG); the live view always gets them as events.
+#[tauri::command]
+fn set_annotation_config(
+    state: State<'_, AppState>,
+    config: annotations::AnnotationConfig,
+) -> annotations::AnnotationConfig {
+    state.annotations.set_config(config);
+    state.annotations.config()
+}
+
+/// Start running an ONNX detection model on stored frames
+///
+/// `config.model_path` is resolved inside the output directory. Detections
+/// are emitted as `frame-annotations` events, keyed by frame sequence.
+#[tauri::command]
+fn start_inference(
+    app: tauri::AppHandle,
+    state: State<'_, AppState>,
+    config: inference::InferenceConfig,
+) -> Result<inference::InferenceStatus, AppError> {
+    let path = app_storage(&app, &state)?.resolve(&config.model_path)?;
+    state.inference.start(&path, config, move |annotations| {
+        emit_frame_annotations(&app, annotations);
+    })?;
+    Ok(state.inference.status())
+}
+
+/// Stop the detection model
+#[tauri::command]
+fn stop_inference(state: State<'_, AppState>) -> Result<inference::InferenceStatus, AppError> {
+    state.inference.stop()?;
+    Ok(state.inference.status())
+}
+
+/// Get the running model and detection counters
+#[tauri::command]
+fn inference_status(state: State<'_, AppState>) -> inference::InferenceStatus {
+    state.inference.status()
+}
+
+/// Get the ranges and values of the connected camera's image controls
+///
+/// Only controls the camera advertises and answers for are listed.
+#[tauri::command]
+fn get_camera_controls(
+    state: State<'_, AppState>,
+) -> Result<Vec<uvc_controls::ControlInfo>, AppError> {
+    Ok(state.camera_controls.list()?)
+}
+
+/// Set an image control (`brightness`, `contrast`, `saturation`, `sharpness`,
+/// `gamma` or `exposure`)
+///
+/// Setting the exposure switches the camera to manual exposure.
+#[tauri::command]
+fn set_camera_control(
+    state: State<'_, AppState>,
+    name: String,
+    value: i32,
+) -> Result<uvc_controls::ControlInfo, AppError> {
+    let control: uvc_controls::CameraControl = name.parse()?;
+    Ok(state.camera_controls.set(control, value)?)
+}
+
+/// List the vendor extension units of the connected camera
+///
+/// Used to find the unit and selector of an LED control for `set_led_control`.
+#[tauri::command]
+fn get_extension_units(
+    state: State<'_, AppState>,
+) -> Result<Vec<uvc_controls::ExtensionUnit>, AppError> {
+    Ok(state.camera_controls.extension_units()?)
+}
+
+/// Name the extension unit control of the LED as `<unit id or GUID>:<selector>`
+///
+/// `None` forgets it. Overrides `CLEANSCOPE_LED_CONTROL`.
+#[tauri::command]
+fn set_led_control(state: State<'_, AppState>, control: Option<String>) -> Result<(), AppError> {
+    let led = control
+        .map(|c| c.parse::<uvc_controls::LedControl>())
+        .transpose()?;
+    state.camera_controls.set_led_control(led);
+    Ok(())
+}
+
+/// Set the LED brightness in percent (0 turns it off)
+///
+/// Returns the raw value written to the extension unit control.
+#[tauri::command]
+fn set_led_brightness(state: State<'_, AppState>, level: u8) -> Result<u32, AppError> {
+    Ok(state.camera_controls.set_led_brightness(level)?)
+}
+
+/// Enable or disable exposure suggestions and whether they are applied
+///
+/// With `auto_apply`, exposure and brightness corrections are made through
+/// the camera controls (switching to manual exposure); LED suggestions are
+/// only emitted.
+#[tauri::command]
+fn set_exposure_advisor(
+    state: State<'_, AppState>,
+    enabled: bool,
+    auto_apply: bool,
+) -> exposure::ExposureStatus {
+    state.exposure.set_settings(exposure::ExposureSettings {
+        enabled,
+        auto_apply,
+    })
+}
+
+/// Get the exposure advisor's settings, last analysis and suggestion counts
+#[tauri::command]
+fn get_exposure_advisor_status(state: State<'_, AppState>) -> exposure::ExposureStatus {
+    state.exposure.status()
+}
+
+/// Calibrate measurements from a printed target in the current frame
+///
+/// Detects a dot grid or checkerboard with the given spacing and derives
+/// pixels-per-mm and lens distortion in one step. Replaces any previous
+/// calibration.
+#[tauri::command]
+fn calibrate_from_target(
+    state: State<'_, AppState>,
+    target: calibration::TargetSpec,
+) -> Result<calibration::Calibration, AppError> {
+    let frame = state.frame_buffer.load();
+    let rgb = frame_rgb(&state, &frame)?;
+    let zoom = lock_or_err!(state.display)?.zoom;
+    let result =
+        calibration::detect_target(&rgb, frame.width, frame.height, target)?.with_zoom(zoom);
+    log::info!(
+        "Calibrated from {:?} target: {:.2} px/mm, k1={:.3}",
+        target.kind,
+        result.pixels_per_mm,
+        result.k1
+    );
+    replace_calibration(&state, Some(result.clone()))?;
+    Ok(result)
+}
+
+/// Calibrate measurements from two points `length_mm` apart in the current frame
+#[tauri::command]
+fn set_reference_calibration(
+    state: State<'_, AppState>,
+    x1: f32,
+    y1: f32,
+    x2: f32,
+    y2: f32,
+    length_mm: f32,
+) -> Result<calibration::Calibration, AppError> {
+    let frame = state.frame_buffer.load();
+    if frame.is_empty() {
+        return Err(AppError::NoFrame);
+    }
+    let zoom = lock_or_err!(state.display)?.zoom;
+    let result = calibration::Calibration::from_reference(
+        frame.width,
+        frame.height,
+        (x1, y1),
+        (x2, y2),
+        length_mm,
+    )?
+    .with_zoom(zoom);
+    replace_calibration(&state, Some(result.clone()))?;
+    Ok(result)
+}
+
+/// Get the current measurement calibration, if any
+#[tauri::command]
+fn get_calibration(
+    state: State<'_, AppState>,
+) -> Result<Option<calibration::Calibration>, AppError> {
+    Ok(lock_or_err!(state.calibration)?.clone())
+}
+
+/// Forget the measurement calibration
+#[tauri::command]
+fn clear_calibration(state: State<'_, AppState>) -> Result<(), AppError> {
+    replace_calibration(&state, None)
+}
+
+/// Set the measurement calibration, recording the change for undo
+fn replace_calibration(
+    state: &AppState,
+    calibration: Option<calibration::Calibration>,
+) -> Result<(), AppError> {
+    let before = std::mem::replace(&mut *lock_or_err!(state.calibration)?, calibration.clone());
+    record_edit(
+        state,
+        "calibration",
+        history::Snapshot::Calibration(before),
+        history::Snapshot::Calibration(calibration),
+    )
+}
+
+/// The current frame and the calibration to measure it with
+///
+/// A calibration made at another resolution does not apply; measurements fall
+/// back to pixels. One made at another zoom is refused, since the frame's
+/// pixels no longer have the calibrated scale.
+fn frame_calibration(
+    state: &AppState,
+) -> Result<(Arc<Frame>, Option<calibration::Calibration>), AppError> {
+    let frame = state.frame_buffer.load();
+    if frame.is_empty() {
+        return Err(AppError::NoFrame);
+    }
+    let zoom = lock_or_err!(state.display)?.zoom;
+    let calibration = lock_or_err!(state.calibration)?
+        .as_ref()
+        .filter(|c| c.matches(frame.width, frame.height))
+        .cloned();
+    if let Some(calibration) = &calibration {
+        calibration.check_zoom(&zoom)?;
+    }
+    Ok((frame, calibration))
+}
+
+/// Record a measurement of `frame` in the session manifest
+///
+/// `frame` is the one [`frame_calibration`] returned, so the measurement
+/// names the frame it was made on even if a newer one has been stored since.
+fn record_measurement(
+    app: &AppHandle,
+    state: &AppState,
+    frame: &Frame,
+    kind: measurement::MeasurementKind,
+    points: Vec<[f32; 2]>,
+    value: measurement::Quantity,
+) -> Result<measurement::Measurement, AppError> {
+    let mut manifest = lock_or_err!(state.session)?;
+    let result = manifest.add_measurement(kind, points, value, frame.sequence);
+    manifest.save(&app_storage(app, state)?)?;
+    Ok(result)
+}
+
+/// Measure the distance between two points in the current frame
+///
+/// Reported in the measurement unit with its uncertainty when calibrated,
+/// otherwise in pixels. The result is recorded in the session manifest.
+#[tauri::command]
+fn measure_line(
+    app: tauri::AppHandle,
+    state: State<'_, AppState>,
+    x1: f32,
+    y1: f32,
+    x2: f32,
+    y2: f32,
+) -> Result<measurement::Measurement, AppError> {
+    let (frame, calibration) = frame_calibration(&state)?;
+    let unit = *lock_or_err!(state.measurement_unit)?;
+    let points = vec![[x1, y1], [x2, y2]];
+    let value = measurement::line_length(calibration.as_ref(), unit, points[0], points[1]);
+    record_measurement(
+        &app,
+        &state,
+        &frame,
+        measurement::MeasurementKind::Line,
+        points,
+        value,
+    )
+}
+
+/// Measure the angle at `p2` between the lines to `p1` and `p3`, in degrees
+///
+/// Points are corrected for lens distortion when calibrated. The result is
+/// recorded in the session manifest.
+#[tauri::command]
+fn measure_angle(
+    app: tauri::AppHandle,
+    state: State<'_, AppState>,
+    p1: [f32; 2],
+    p2: [f32; 2],
+    p3: [f32; 2],
+) -> Result<measurement::Measurement, AppError> {
+    let (frame, calibration) = frame_calibration(&state)?;
+    let value = measurement::angle(calibration.as_ref(), p1, p2, p3)?;
+    record_measurement(
+        &app,
+        &state,
+        &frame,
+        measurement::MeasurementKind::Angle,
+        vec![p1, p2, p3],
+        value,
+    )
+}
+
+/// Measure the area of the polygon through `points` in the current frame
+///
+/// Reported in the square of the measurement unit when calibrated, otherwise
+/// in square pixels. The result is recorded in the session manifest.
+#[tauri::command]
+fn measure_polygon_area(
+    app: tauri::AppHandle,
+    state: State<'_, AppState>,
+    points: Vec<[f32; 2]>,
+) -> Result<measurement::Measurement, AppError> {
+    let (frame, calibration) = frame_calibration(&state)?;
+    let unit = *lock_or_err!(state.measurement_unit)?;
+    let value = measurement::polygon_area(calibration.as_ref(), unit, &points)?;
+    record_measurement(
+        &app,
+        &state,
+        &frame,
+        measurement::MeasurementKind::Area,
+        points,
+        value,
+    )
+}
+
+/// Get all measurements of the current session
+#[tauri::command]
+fn get_measurements(state: State<'_, AppState>) -> Result<Vec<measurement::Measurement>, AppError> {
+    Ok(lock_or_err!(state.session)?.measurements.clone())
+}
+
+/// Set the unit calibrated measurements are reported in
+///
+/// Applies to new measurements; recorded ones keep their unit.
+#[tauri::command]
+fn set_measurement_unit(
+    state: State<'_, AppState>,
+    unit: measurement::LengthUnit,
+) -> Result<(), AppError> {
+    let before = std::mem::replace(&mut *lock_or_err!(state.measurement_unit)?, unit);
+    record_edit(
+        &state,
+        "measurement unit",
+        history::Snapshot::MeasurementUnit(before),
+        history::Snapshot::MeasurementUnit(unit),
+    )
+}
+
+/// Get the unit calibrated measurements are reported in
+#[tauri::command]
+fn get_measurement_unit(state: State<'_, AppState>) -> Result<measurement::LengthUnit, AppError> {
+    Ok(*lock_or_err!(state.measurement_unit)?)
+}
+
+/// Export the session's measurements as CSV and return the file path
+#[tauri::command]
+fn export_measurements(
+    app: tauri::AppHandle,
+    state: State<'_, AppState>,
+) -> Result<String, AppError> {
+    let (file_name, csv) = {
+        let manifest = lock_or_err!(state.session)?;
+        (
+            format!("measurements_{}.csv", manifest.started_at_ms / 1000),
+            measurement::to_csv(&manifest.measurements),
+        )
+    };
+    let path = app_storage(&app, &state)?.write(file_name, csv)?;
+    log::info!("Exported measurements to {}", path.display());
+    Ok(path.to_string_lossy().to_string())
+}
+
+/// Cycle through options: None -> 0 -> 1 -> ... -> N-1 -> None
+fn cycle_index(current: &mut Option<usize>, max_len: usize) -> Option<usize> {
+    let new_index = match *current {
+        None => Some(0),
+        Some(i) if i + 1 < max_len => Some(i + 1),
+        Some(_) => None,
+    };
+    *current = new_index;
+    new_index
+}
+
+/// Cycle through width options
+#[tauri::command]
+fn cycle_width(state: State<'_, AppState>) -> Result<String, AppError> {
+    let mut display = lock_or_err!(state.display)?;
+
+    let new_index = cycle_index(&mut display.width_index, WIDTH_OPTIONS.len());
+    display.settings.width = new_index.map(|i| WIDTH_OPTIONS[i]);
+
+    Ok(match new_index {
+        None => "W:Auto".to_string(),
+        Some(i) => format!("W:{}", WIDTH_OPTIONS[i]),
+    })
+}
+
+/// Cycle through height options
+#[tauri::command]
+fn cycle_height(state: State<'_, AppState>) -> Result<String, AppError> {
+    let mut display = lock_or_err!(state.display)?;
+
+    let new_index = cycle_index(&mut display.height_index, HEIGHT_OPTIONS.len());
+    display.settings.height = new_index.map(|i| HEIGHT_OPTIONS[i]);
+
+    Ok(match new_index {
+        None => "H:Auto".to_string(),
+        Some(i) => format!("H:{}", HEIGHT_OPTIONS[i]),
+    })
+}
+
+/// Cycle through stride options
+#[tauri::command]
+fn cycle_stride(state: State<'_, AppState>) -> Result<String, AppError> {
+    let mut display = lock_or_err!(state.display)?;
+
+    let new_index = cycle_index(&mut display.stride_index, STRIDE_OPTIONS.len());
+
+    Ok(match new_index {
+        None => "S:Auto".to_string(),
+        Some(i) => format!("S:x{:.3}", STRIDE_OPTIONS[i]),
+    })
+}
+
+/// Get current display settings as a summary string
+#[tauri::command]
+fn get_display_settings(state: State<'_, AppState>) -> Result<String, AppError> {
+    let display = lock_or_err!(state.display)?;
+    let w = display
+        .settings
+        .width
+        .map(|v| v.to_string())
+        .unwrap_or_else(|| "Auto".to_string());
+    let h = display
+        .settings
+        .height
+        .map(|v| v.to_string())
+        .unwrap_or_else(|| "Auto".to_string());
+    let s = display
+        .settings
+        .stride
+        .map(|v| v.to_string())
+        .unwrap_or_else(|| "Auto".to_string());
+    Ok(format!("{}x{} stride:{}", w, h, s))
+}
+
+/// Toggle MJPEG detection skip
+/// When enabled, skips MJPEG format probing and goes straight to YUV streaming
+#[tauri::command]
+fn toggle_skip_mjpeg(state: State<'_, AppState>) -> Result<String, AppError> {
+    let mut config = lock_or_err!(&state.streaming_config)?;
+    config.skip_mjpeg_detection = !config.skip_mjpeg_detection;
+    log::info!("MJPEG skip: {}", config.skip_mjpeg_detection);
+    Ok(if config.skip_mjpeg_detection {
+        "MJPEG:Skip".to_string()
+    } else {
+        "MJPEG:Try".to_string()
+    })
+}
+
+/// Decode MJPEG frames to RGB before they are stored
+///
+/// Takes effect on the next frame. Frames then go through the same RGB path as
+/// YUV formats (plugins, overlays); the original JPEG is kept as the raw frame.
+#[tauri::command]
+fn set_mjpeg_decode(state: State<'_, AppState>, enabled: bool) -> Result<(), AppError> {
+    lock_or_err!(&state.streaming_config)?.decode_mjpeg = enabled;
+    log::info!("MJPEG decoding: {}", enabled);
+    Ok(())
+}
+
+/// Check if MJPEG frames are decoded to RGB
+#[tauri::command]
+fn is_mjpeg_decode_enabled(state: State<'_, AppState>) -> Result<bool, AppError> {
+    Ok(lock_or_err!(&state.streaming_config)?.decode_mjpeg)
+}
+
+/// Rotate frames clockwise by `deg` (0, 90, 180 or 270)
+///
+/// Takes effect on the next frame and is saved in the settings. MJPEG frames
+/// are decoded to RGB while a rotation or flip is set.
+#[tauri::command]
+fn set_rotation(
+    app: tauri::AppHandle,
+    state: State<'_, AppState>,
+    deg: u32,
+) -> Result<transform::Transform, AppError> {
+    let rotation = transform::Rotation::from_degrees(deg)?;
+    log::info!("Frame rotation: {} degrees", deg);
+    update_transform(&app, &state, "rotation", |t| t.rotation = rotation)
+}
+
+/// Mirror frames horizontally and/or vertically (after rotating)
+///
+/// Saved in the settings.
+#[tauri::command]
+fn set_flip(
+    app: tauri::AppHandle,
+    state: State<'_, AppState>,
+    horizontal: bool,
+    vertical: bool,
+) -> Result<transform::Transform, AppError> {
+    log::info!(
+        "Frame flip: horizontal={}, vertical={}",
+        horizontal,
+        vertical
+    );
+    update_transform(&app, &state, "flip", |t| {
+        t.flip_horizontal = horizontal;
+        t.flip_vertical = vertical;
+    })
+}
+
+/// Change the frame transform, recording the change for undo and saving it
+/// in the settings
+fn update_transform(
+    app: &AppHandle,
+    state: &AppState,
+    label: &'static str,
+    f: impl FnOnce(&mut transform::Transform),
+) -> Result<transform::Transform, AppError> {
+    let (before, after) = {
+        let mut display = lock_or_err!(state.display)?;
+        let before = display.transform;
+        f(&mut display.transform);
+        (before, display.transform)
+    };
+    save_transform(app, state, after)?;
+    record_edit(
+        state,
+        label,
+        history::Snapshot::Transform(before),
+        history::Snapshot::Transform(after),
+    )?;
+    Ok(after)
+}
+
+/// Get the rotation and flips applied to frames
+#[tauri::command]
+fn get_transform(state: State<'_, AppState>) -> Result<transform::Transform, AppError> {
+    Ok(lock_or_err!(state.display)?.transform)
+}
+
+/// Zoom into frames by `factor` (1.0-8.0) around a centre given as fractions of the frame
+///
+/// Every following frame is cropped to the region and scaled back to its
+/// full size; `factor` 1.0 shows the whole frame again. MJPEG frames are
+/// decoded to RGB while zoomed. Measurements need a calibration made at the
+/// same zoom. The change is recorded for undo.
+#[tauri::command]
+fn set_zoom(
+    state: State<'_, AppState>,
+    factor: f32,
+    center_x: f32,
+    center_y: f32,
+) -> Result<zoom::Zoom, AppError> {
+    let zoom = zoom::Zoom::new(factor, center_x, center_y)?;
+    let before = std::mem::replace(&mut lock_or_err!(state.display)?.zoom, zoom);
+    log::debug!("Zoom: {:.2}x at ({:.2}, {:.2})", factor, center_x, center_y);
+    record_edit(
+        &state,
+        "zoom",
+        history::Snapshot::Zoom(before),
+        history::Snapshot::Zoom(zoom),
+    )?;
+    Ok(zoom)
+}
+
+/// Get the digital zoom applied to frames
+#[tauri::command]
+fn get_zoom(state: State<'_, AppState>) -> Result<zoom::Zoom, AppError> {
+    Ok(lock_or_err!(state.display)?.zoom)
+}
+
+/// Record an edit for `undo`
+fn record_edit(
+    state: &AppState,
+    label: &'static str,
+    before: history::Snapshot,
+    after: history::Snapshot,
+) -> Result<(), AppError> {
+    lock_or_err!(state.history)?.record(label, before, after);
+    Ok(())
+}
+
+/// Save the rotation and flips of `transform` in the settings
+fn save_transform(
+    app: &AppHandle,
+    state: &AppState,
+    transform: transform::Transform,
+) -> Result<(), AppError> {
+    change_settings(app, state, |settings| {
+        settings.rotation = transform.rotation;
+        settings.flip_horizontal = transform.flip_horizontal;
+        settings.flip_vertical = transform.flip_vertical;
+    })
+}
+
+/// Restore a recorded state
+fn apply_snapshot(
+    app: &AppHandle,
+    state: &AppState,
+    snapshot: &history::Snapshot,
+) -> Result<(), AppError> {
+    match snapshot {
+        history::Snapshot::Annotations {
+            sequence,
+            annotations,
+        } => {
+            let mut frozen = lock_or_err!(state.frozen)?;
+            let frozen = frozen
+                .as_mut()
+                .filter(|frozen| frozen.sequence == *sequence)
+                .ok_or(history::HistoryError::Stale("frozen frame"))?;
+            frozen.set_annotations(annotations.clone());
+        }
+        history::Snapshot::Calibration(calibration) => {
+            *lock_or_err!(state.calibration)? = calibration.clone();
+        }
+        history::Snapshot::Transform(transform) => {
+            lock_or_err!(state.display)?.transform = *transform;
+            save_transform(app, state, *transform)?;
+        }
+        history::Snapshot::MeasurementUnit(unit) => {
+            *lock_or_err!(state.measurement_unit)? = *unit;
+        }
+        history::Snapshot::Zoom(zoom) => {
+            lock_or_err!(state.display)?.zoom = *zoom;
+        }
+    }
+    Ok(())
+}
+
+/// Undo the latest annotation, calibration or display settings edit
+///
+/// Does nothing if there is nothing to undo. The frontend should refetch
+/// whatever `redo_label` of the returned status names.
+#[tauri::command]
+fn undo(
+    app: tauri::AppHandle,
+    state: State<'_, AppState>,
+) -> Result<history::HistoryStatus, AppError> {
+    let mut history = lock_or_err!(state.history)?;
+    if let Some(label) = history.undo(|snapshot| apply_snapshot(&app, &state, snapshot))? {
+        log::info!("Undid {} edit", label);
+    }
+    Ok(history.status())
+}
+
+/// Reapply the latest undone edit
+#[tauri::command]
+fn redo(
+    app: tauri::AppHandle,
+    state: State<'_, AppState>,
+) -> Result<history::HistoryStatus, AppError> {
+    let mut history = lock_or_err!(state.history)?;
+    if let Some(label) = history.redo(|snapshot| apply_snapshot(&app, &state, snapshot))? {
+        log::info!("Redid {} edit", label);
+    }
+    Ok(history.status())
+}
+
+/// Get what can be undone and redone
+#[tauri::command]
+fn get_history(state: State<'_, AppState>) -> Result<history::HistoryStatus, AppError> {
+    Ok(lock_or_err!(state.history)?.status())
+}
+
+/// Get the session the previous run left behind when it crashed, if any
+#[tauri::command]
+fn get_resume_offer(state: State<'_, AppState>) -> Result<Option<resume::ResumeOffer>, AppError> {
+    let pending = lock_or_err!(state.pending_resume)?.clone();
+    Ok(pending.map(|checkpoint| resume::ResumeOffer {
+        device_connected: device_reconnected(&state, &checkpoint),
+        checkpoint,
+    }))
+}
+
+/// Resume the session the previous run left behind
+///
+/// Replaces the current session with the saved manifest, finishes the
+/// interrupted recording, restores the display, stream and measurement
+/// settings and, if the same camera is connected, renegotiates the saved
+/// format and resolution. A recording that cannot be finished is reported in
+/// `recording_error` rather than failing the resume.
+#[tauri::command]
+fn resume_session(
+    app: tauri::AppHandle,
+    state: State<'_, AppState>,
+) -> Result<resume::ResumedSession, AppError> {
+    let checkpoint = lock_or_err!(state.pending_resume)?
+        .clone()
+        .ok_or_else(|| AppError::NotFound("No session to resume".to_string()))?;
+    let storage = app_storage(&app, &state)?;
+
+    if let Some(file) = &checkpoint.session_file {
+        *lock_or_err!(state.session)? = session::SessionManifest::load(&storage, file)?;
+    }
+    let (recording, recording_error) = match finish_interrupted_recording(&storage, &checkpoint) {
+        Ok(recording) => (recording, None),
+        Err(e) => {
+            log::warn!("Interrupted recording could not be recovered: {}", e);
+            (None, Some(e.to_string()))
+        }
+    };
+    let stream_restored = restore_settings(&settings_storage(&app)?, &state, &checkpoint)?;
+
+    resume::Checkpoint::remove(&storage)?;
+    *lock_or_err!(state.pending_resume)? = None;
+    log::info!(
+        "Resumed session with {} bookmarks and {} measurements",
+        checkpoint.bookmarks,
+        checkpoint.measurements
+    );
+    Ok(resume::ResumedSession {
+        session: lock_or_err!(state.session)?.clone(),
+        recording,
+        recording_error,
+        stream_restored,
+    })
+}
+
+/// Drop the session the previous run left behind
+///
+/// Its interrupted recording is still finished so the frames are playable,
+/// unless `finalize_recording` is false.
+#[tauri::command]
+fn dismiss_resume(
+    app: tauri::AppHandle,
+    state: State<'_, AppState>,
+    finalize_recording: Option<bool>,
+) -> Result<Option<recording::RecordingResult>, AppError> {
+    let checkpoint = lock_or_err!(state.pending_resume)?
+        .take()
+        .ok_or_else(|| AppError::NotFound("No session to resume".to_string()))?;
+    let storage = app_storage(&app, &state)?;
+    let recording = if finalize_recording.unwrap_or(true) {
+        finish_interrupted_recording(&storage, &checkpoint)?
+    } else {
+        None
+    };
+    resume::Checkpoint::remove(&storage)?;
+    Ok(recording)
+}
+
+/// Whether the camera of a checkpoint is connected again
+fn device_reconnected(state: &AppState, checkpoint: &resume::Checkpoint) -> bool {
+    let (connected, info) = state.stream_health.connection();
+    connected && checkpoint.device.is_some() && info == checkpoint.device
+}
+
+/// Write the index of the recording a checkpoint was taken during
+fn finish_interrupted_recording(
+    storage: &storage::Storage,
+    checkpoint: &resume::Checkpoint,
+) -> Result<Option<recording::RecordingResult>, AppError> {
+    let Some(dir) = &checkpoint.recording_dir else {
+        return Ok(None);
+    };
+    Ok(Some(recording::recover(&storage.subdir(dir)?)?))
+}
+
+/// Apply the settings of a checkpoint
+///
+/// The transform and pixel format are saved in the settings (in
+/// `settings_storage`) too, as if they had been set again. Returns whether
+/// the stream is being restarted with the saved format and resolution, which
+/// only happens for the camera the checkpoint was taken with.
+fn restore_settings(
+    settings_storage: &storage::Storage,
+    state: &AppState,
+    checkpoint: &resume::Checkpoint,
+) -> Result<bool, AppError> {
+    let settings = &checkpoint.settings;
+    {
+        let mut display = lock_or_err!(state.display)?;
+        display.transform = settings.transform;
+        display.zoom = settings.zoom;
+    }
+    save_changed_settings(settings_storage, &state.settings, |saved| {
+        saved.rotation = settings.transform.rotation;
+        saved.flip_horizontal = settings.transform.flip_horizontal;
+        saved.flip_vertical = settings.transform.flip_vertical;
+        saved.pixel_format = Some(settings.pixel_format);
+    })?;
+    *lock_or_err!(state.measurement_unit)? = settings.measurement_unit;
+
+    // Format and frame indices only mean something to the camera they came from
+    let reconnected = device_reconnected(state, checkpoint);
+    let mut config = lock_or_err!(state.streaming_config)?;
+    config.pixel_format = settings.pixel_format;
+    config.decode_mjpeg = settings.decode_mjpeg;
+    let (Some(format_index), Some(frame_index)) = (settings.format_index, settings.frame_index)
+    else {
+        return Ok(false);
+    };
+    let streaming = config.active_stream.is_some_and(|active| {
+        active.format_index == format_index
+            && active.frame_index == frame_index
+            && settings
+                .frame_interval
+                .is_none_or(|interval| interval == active.frame_interval)
+    });
+    if !reconnected || streaming {
+        return Ok(false);
+    }
+    config.selected_format_index = Some(format_index);
+    config.selected_frame_index = Some(frame_index);
+    config.selected_frame_interval = settings.frame_interval;
+    config.restart_requested = true;
+    Ok(true)
+}
+
+/// Get the saved user preferences
+#[tauri::command]
+fn get_settings(state: State<'_, AppState>) -> Result<settings::Settings, AppError> {
+    Ok(lock_or_err!(state.settings)?.clone())
+}
+
+/// Save user preferences for the next start and apply them
+///
+/// The settings take effect right away; while streaming, a preferred
+/// resolution the current format offers restarts the stream in it. Returns
+/// the saved settings.
+#[tauri::command]
+fn update_settings(
+    app: tauri::AppHandle,
+    state: State<'_, AppState>,
+    settings: settings::Settings,
+) -> Result<settings::Settings, AppError> {
+    settings.validate()?;
+    let mut current = lock_or_err!(state.settings)?;
+    settings.save(&settings_storage(&app)?)?;
+    apply_settings(&state, &settings)?;
+    log::info!("Saved settings: {:?}", settings);
+    *current = settings.clone();
+    Ok(settings)
+}
+
+/// Change and save one of the user preferences
+///
+/// `AppState.settings` is the single source of truth for preferences, so
+/// commands that change one on its own go through here rather than leaving
+/// the saved settings stale.
+fn change_settings(
+    app: &AppHandle,
+    state: &AppState,
+    change: impl FnOnce(&mut settings::Settings),
+) -> Result<(), AppError> {
+    save_changed_settings(&settings_storage(app)?, &state.settings, change)
+}
+
+/// Apply `change` to `settings` and save them to `storage` if it changed
+///
+/// Holding the lock while saving keeps concurrent changes from overwriting
+/// each other's file.
+fn save_changed_settings(
+    storage: &storage::Storage,
+    settings: &Mutex<settings::Settings>,
+    change: impl FnOnce(&mut settings::Settings),
+) -> Result<(), AppError> {
+    let mut settings = lock_or_err!(settings)?;
+    let mut changed = settings.clone();
+    change(&mut changed);
+    if changed != *settings {
+        changed.save(storage)?;
+        *settings = changed;
+    }
+    Ok(())
+}
+
+/// Apply user preferences to the display and streaming configuration
+///
+/// An unset validation level keeps the current one; an unset pixel format
+/// is YUYV.
+fn apply_settings(state: &AppState, settings: &settings::Settings) -> Result<(), AppError> {
+    {
+        let mut display = lock_or_err!(state.display)?;
+        display.transform.rotation = settings.rotation;
+        display.transform.flip_horizontal = settings.flip_horizontal;
+        display.transform.flip_vertical = settings.flip_vertical;
+    }
+    if let Some(level) = settings.validation_level {
+        *lock_or_err!(state.validation_level)? = level;
+    }
+    let mut config = lock_or_err!(state.streaming_config)?;
+    config.pixel_format = settings.pixel_format.unwrap_or_default();
+    config.preferred_resolution = settings.resolution;
+    config.format_preference = settings.format_preference.clone();
+    if let (Some(resolution), Some(_)) = (settings.resolution, config.active_stream) {
+        config.select_resolution(resolution.width, resolution.height);
+    }
+    Ok(())
+}
+
+/// Enable raw frame capture for one frame
+/// This enables capturing the next raw frame data for debugging/analysis.
+/// After the frame is captured, call `dump_frame` to save it.
+/// Automatically disables after `dump_frame` is called.
+#[tauri::command]
+fn enable_raw_capture(state: State<'_, AppState>) -> Result<String, AppError> {
+    state.frame_buffer.set_capture_raw_frames(true);
+    log::info!("Raw frame capture enabled");
+    Ok("Raw capture enabled".to_string())
+}
+
+/// Check if raw frame capture is enabled
+#[tauri::command]
+fn is_raw_capture_enabled(state: State<'_, AppState>) -> Result<bool, AppError> {
+    Ok(state.frame_buffer.capture_raw_frames())
+}
+
+/// Cycle through pixel format options in `format_registry` order (YUYV / UYVY / NV12 / I420 / RGB24 / BGR24 / RGGB / NV21 / YV12)
+///
+/// The new format is saved in the settings.
+#[tauri::command]
+fn cycle_pixel_format(
+    app: tauri::AppHandle,
+    state: State<'_, AppState>,
+) -> Result<String, AppError> {
+    let format = {
+        let mut config = lock_or_err!(&state.streaming_config)?;
+        config.pixel_format = format_registry::next_format(config.pixel_format);
+        config.pixel_format
+    };
+    log::info!("Pixel format: {:?}", format);
+    change_settings(&app, &state, |settings| {
+        settings.pixel_format = Some(format)
+    })?;
+    Ok(format_pixel_display(&format))
+}
+
+/// Format pixel format for display
+fn format_pixel_display(format: &PixelFormat) -> String {
+    format!("FMT:{format}")
+}
+
+/// Get current streaming configuration
+#[tauri::command]
+fn get_streaming_config(state: State<'_, AppState>) -> Result<(String, String), AppError> {
+    let config = lock_or_err!(&state.streaming_config)?;
+    let mjpeg = if config.skip_mjpeg_detection {
+        "MJPEG:Skip".to_string()
+    } else {
+        "MJPEG:Try".to_string()
+    };
+    let pixel = format_pixel_display(&config.pixel_format);
+    Ok((mjpeg, pixel))
+}
+
+/// Cycle through available video formats
+/// Returns the new format setting as a display string
+#[tauri::command]
+fn cycle_video_format(state: State<'_, AppState>) -> Result<String, AppError> {
+    let mut config = lock_or_err!(&state.streaming_config)?;
+
+    if config.format_catalog.is_empty() {
+        // No formats discovered yet
+        return Ok("FMT:Auto".to_string());
+    }
+
+    // Cycle: None (Auto) -> format 0 -> format 1 -> ... -> None (Auto)
+    let new_index = match config.selected_format_index {
+        None => Some(0usize),
+        Some(current) => {
+            // Find current position in the catalog
+            let formats = &config.format_catalog.formats;
+            let current_pos = formats.iter().position(|f| f.index == current);
+            match current_pos {
+                Some(pos) if pos + 1 < formats.len() => Some(pos + 1),
+                _ => None, // Wrap back to Auto
+            }
+        }
+    };
+
+    config.selected_format_index = new_index.map(|i| config.format_catalog.formats[i].index);
+
+    // Signal streaming to restart with new format
+    config.restart_requested = true;
+
+    let result = match new_index {
+        None => "FMT:Auto".to_string(),
+        Some(i) => {
+            let fmt = &config.format_catalog.formats[i];
+            format!("FMT:{}:{}", fmt.index, fmt.name())
+        }
+    };
+
+    log::info!(
+        "Video format changed: {:?} -> {} (restart requested)",
+        config.selected_format_index,
+        result
+    );
+    Ok(result)
+}
+
+/// Get available video formats discovered from camera
+#[tauri::command]
+fn get_available_formats(state: State<'_, AppState>) -> Result<Vec<DiscoveredFormat>, AppError> {
+    let config = lock_or_err!(&state.streaming_config)?;
+    Ok(config
+        .format_catalog
+        .formats
+        .iter()
+        .map(DiscoveredFormat::from)
+        .collect())
+}
+
+/// Get current video format setting
+#[tauri::command]
+fn get_video_format(state: State<'_, AppState>) -> Result<String, AppError> {
+    let config = lock_or_err!(&state.streaming_config)?;
+
+    Ok(match config.selected_format_index {
+        None => "FMT:Auto".to_string(),
+        Some(idx) => {
+            // Find format info
+            if let Some(fmt) = config.format_catalog.format(idx) {
+                format!("FMT:{}:{}", fmt.index, fmt.name())
+            } else {
+                format!("FMT:{}", idx)
+            }
+        }
+    })
+}
+
+/// Start capturing USB packets for debugging
+///
+/// Begins capturing raw USB packets during streaming. The packets are stored
+/// in memory until `stop_packet_capture` is called. With `transfers`, the
+/// status and actual length of every isochronous packet is recorded too.
+/// Refuses with `LOW_RESOURCES` when storage or memory is nearly exhausted.
+#[tauri::command]
+fn start_packet_capture(
+    app: tauri::AppHandle,
+    state: State<'_, AppState>,
+    transfers: Option<bool>,
+) -> Result<String, AppError> {
+    guard_resources(&app, &app_storage(&app, &state)?)?;
+    state
+        .capture_state
+        .start_capture(capture::CaptureMetadata {
+            record_transfers: transfers.unwrap_or(false),
+            ..Default::default()
+        })?;
+    Ok("Packet capture started".to_string())
+}
+
+/// Stop capturing USB packets and save to files
+///
+/// Stops the capture, writes the captured packets to the output directory,
+/// and returns information about the captured data.
+#[tauri::command]
+fn stop_packet_capture(
+    app: tauri::AppHandle,
+    state: State<'_, AppState>,
+) -> Result<capture::CaptureResult, AppError> {
+    save_packet_capture(&app, &state)
+}
+
+/// Stop packet capture and write the captured packets to the output directory
+fn save_packet_capture(
+    app: &AppHandle,
+    state: &AppState,
+) -> Result<capture::CaptureResult, AppError> {
+    // Get status before stopping (for duration)
+    let status = state.capture_state.status();
+
+    // Stop capture and get packets
+    let packets = state.capture_state.stop();
+
+    if packets.is_empty() {
+        return Err(AppError::NotFound("No packets captured".to_string()));
+    }
+
+    let storage = app_storage(app, state)?;
+
+    // Write capture files
+    Ok(capture::write_capture_files(
+        &storage,
+        &packets,
+        &state.capture_state.take_transfer_records(),
+        status.duration_ms,
+        &state.capture_state.description(),
+    )?)
+}
+
+/// Get the current packet capture status
+///
+/// Returns information about whether capture is active and how many packets
+/// have been captured so far.
+#[tauri::command]
+fn get_capture_status(state: State<'_, AppState>) -> capture::CaptureStatus {
+    state.capture_state.status()
+}
+
+/// Choose which packets packet capture keeps
+///
+/// `malformed_only` keeps just the packets of frames that fail validation or
+/// are discarded incomplete, for chasing intermittent corruption without
+/// recording everything. Takes effect immediately, also during a capture.
+#[tauri::command]
+fn set_capture_filter(
+    state: State<'_, AppState>,
+    filter: capture::CaptureFilter,
+) -> capture::CaptureStatus {
+    state.capture_state.set_filter(filter);
+    state.capture_state.status()
+}
+
+/// Export a packet capture as pcapng for Wireshark
+///
+/// `path` is a `capture_*.bin` or `packets_*.bin` file resolved inside the
+/// output directory; the `.pcapng` file is written next to it with Linux
+/// usbmon headers.
+#[tauri::command]
+fn export_capture_pcap(
+    app: tauri::AppHandle,
+    state: State<'_, AppState>,
+    path: String,
+) -> Result<capture::PcapExportResult, AppError> {
+    let storage = app_storage(&app, &state)?;
+    let path = storage.resolve(&path)?;
+    Ok(capture::export_pcapng(&storage, &path)?)
+}
+
+/// Keep the last `seconds` of USB packets in memory (0 disables)
+///
+/// Runs independently of packet capture and writes nothing to disk. Use
+/// `replay_last` to re-run the buffered packets after a glitch.
+#[tauri::command]
+fn set_repro_buffer(state: State<'_, AppState>, seconds: u32) -> u32 {
+    state.capture_state.enable_repro_buffer(seconds);
+    state.capture_state.repro_buffer_seconds()
+}
+
+/// Re-run the last `seconds` of buffered packets through a fresh frame assembler
+///
+/// Every packet and assembled frame is logged; the returned report summarizes
+/// what the assembler produced.
+#[tauri::command]
+fn replay_last(state: State<'_, AppState>, seconds: u32) -> Result<capture::ReproReport, AppError> {
+    let assembler = repro_assembler(&lock_or_err!(state.streaming_config)?);
+    Ok(state.capture_state.replay_last(seconds, assembler)?)
+}
+
+/// Start comparing the live pipeline with an experimental configuration
+///
+/// The baseline is the current stream's format, resolution, pixel format and
+/// stride; `experimental` overrides parts of it. Both run on a copy of the
+/// packet stream in the background, so the displayed video is unaffected.
+#[tauri::command]
+fn start_pipeline_comparison(
+    state: State<'_, AppState>,
+    experimental: pipeline_compare::PipelineOverrides,
+) -> Result<pipeline_compare::ComparisonReport, AppError> {
+    let baseline = {
+        let config = lock_or_err!(state.streaming_config)?;
+        let display = lock_or_err!(state.display)?;
+        current_pipeline_variant(&config, &display)?
+    };
+    let experimental = experimental.apply(&baseline);
+    state
+        .pipeline_comparison
+        .start(&state.capture_state, baseline, experimental)?;
+    Ok(state.pipeline_comparison.report(&state.capture_state))
+}
+
+/// Stop the pipeline comparison and return its final report
+#[tauri::command]
+fn stop_pipeline_comparison(
+    state: State<'_, AppState>,
+) -> Result<pipeline_compare::ComparisonReport, AppError> {
+    Ok(state.pipeline_comparison.stop(&state.capture_state)?)
+}
+
+/// Progress of the running pipeline comparison (or the last one)
+#[tauri::command]
+fn get_pipeline_comparison(state: State<'_, AppState>) -> pipeline_compare::ComparisonReport {
+    state.pipeline_comparison.report(&state.capture_state)
+}
 
-    // Cycle: None (Auto) -> format 0 -> format 1 -> ... -> None (Auto)
-    let new_index = match config.selected_format_index {
-        None => Some(0usize),
-        Some(current) => {
-            // Find current position in available_formats
-            let current_pos = config
-                .available_formats
-                .iter()
-                .position(|f| f.index == current);
-            match current_pos {
-                Some(pos) if pos + 1 < config.available_formats.len() => Some(pos + 1),
-                _ => None, // Wrap back to Auto
-            }
-        }
+/// Load a packet capture for playback in place of a camera
+///
+/// `path` is resolved inside the output directory. Wireshark pcap/pcapng
+/// captures of usbmon are accepted too. `endpoint` keeps only that
+/// endpoint's packets, in any format (pcap default: the busiest IN
+/// endpoint). Stops any running playback.
+#[tauri::command]
+fn load_replay(
+    app: tauri::AppHandle,
+    state: State<'_, AppState>,
+    path: String,
+    endpoint: Option<u8>,
+) -> Result<replay::ReplayStatus, AppError> {
+    let path = app_storage(&app, &state)?.resolve(&path)?;
+    Ok(state.replay.load_endpoint(&path, endpoint)?)
+}
+
+/// Start playing back the loaded capture
+///
+/// Replayed frames are converted like live ones, stored in the shared
+/// `FrameBuffer` and announced with `frame-ready`, so the frontend can be
+/// developed without a camera. Raw frames are converted with the capture's
+/// format if it names one, else with the configured pixel format, and need
+/// the capture's resolution. Refused while a camera is streaming into the
+/// same buffer.
+#[tauri::command]
+fn start_replay(
+    app: tauri::AppHandle,
+    state: State<'_, AppState>,
+    config: Option<replay::ReplayOptions>,
+) -> Result<replay::ReplayStatus, AppError> {
+    if state.stream_health.connection().0 {
+        return Err(replay::ReplayError::CameraStreaming.into());
+    }
+    let config = config.unwrap_or_default();
+    let metadata = state.replay.status().metadata.unwrap_or_default();
+    let configured = lock_or_err!(state.streaming_config)?.pixel_format;
+    let pixel_format = metadata.format_type.parse().unwrap_or(configured);
+    let (width, height) = (metadata.width, metadata.height);
+    if (width == 0 || height == 0) && !state.replay.is_mjpeg(&config)? {
+        return Err(replay::ReplayError::UnknownResolution.into());
+    }
+    let frame_buffer = Arc::clone(&state.frame_buffer);
+    let plugins = Arc::clone(&state.plugins);
+    let display = Arc::clone(&state.display);
+
+    state.replay.start(config, move |frame| {
+        let display = lock_or_recover(&display).clone();
+        show_replayed_frame(
+            &app,
+            &frame_buffer,
+            &plugins,
+            &display,
+            frame,
+            width,
+            height,
+            pixel_format,
+        );
+    })?;
+    Ok(state.replay.status())
+}
+
+/// Stop capture playback
+#[tauri::command]
+fn stop_replay(state: State<'_, AppState>) -> Result<replay::ReplayStatus, AppError> {
+    state.replay.stop()?;
+    Ok(state.replay.status())
+}
+
+/// Get the loaded capture and playback progress
+#[tauri::command]
+fn replay_status(state: State<'_, AppState>) -> replay::ReplayStatus {
+    state.replay.status()
+}
+
+/// Convert a replayed frame and hand it to the frontend like a live frame
+fn show_replayed_frame(
+    app: &AppHandle,
+    frame_buffer: &FrameBuffer,
+    plugins: &plugins::PluginHost,
+    display: &DisplayConfig,
+    frame: Vec<u8>,
+    width: u32,
+    height: u32,
+    pixel_format: PixelFormat,
+) {
+    let mut annotations = Vec::new();
+    let (mut width, mut height) = (width, height);
+    let data = if is_jpeg_data(&frame) {
+        frame
+    } else {
+        let (mut rgb, w, h) =
+            match yuv_conversion::convert_to_rgb(&frame, width, height, width * 2, pixel_format) {
+                Ok(rgb) => display.apply_view(rgb, width, height),
+                Err(e) => {
+                    log::debug!("Skipping replayed frame: {}", e);
+                    return;
+                }
+            };
+        (width, height) = (w, h);
+        annotations = plugins.process_frame(&mut rgb, width, height);
+        rgb
     };
+    let info = frame_buffer.store(data, width, height);
+    emit_frame_ready(app, &info);
+    if !annotations.is_empty() {
+        emit_frame_annotations(
+            app,
+            annotations::FrameAnnotations {
+                sequence: info.sequence,
+                annotations,
+            },
+        );
+    }
+}
 
-    config.selected_format_index = new_index.map(|i| config.available_formats[i].index);
+/// How long `trace_next_frame` waits for a frame
+const TRACE_TIMEOUT_SECS: u64 = 10;
 
-    // Signal streaming to restart with new format
-    config.restart_requested = true;
+/// Write every intermediate buffer of the next complete frame to disk
+///
+/// Persists the raw USB payload, assembled frame, validation verdict, converted
+/// RGB and JPEG encoding with a manifest under `traces/` in the output
+/// directory, and returns the manifest. Fails with `NOT_FOUND` if no frame
+/// arrives within `TRACE_TIMEOUT_SECS`.
+#[tauri::command]
+async fn trace_next_frame(
+    app: tauri::AppHandle,
+    state: State<'_, AppState>,
+) -> Result<frame_trace::TraceManifest, AppError> {
+    let storage = app_storage(&app, &state)?;
+    let receiver = state.frame_tracer.arm(&storage)?;
 
-    let result = match new_index {
-        None => "FMT:Auto".to_string(),
-        Some(i) => {
-            let fmt = &config.available_formats[i];
-            format!("FMT:{}:{}", fmt.index, fmt.format_type)
+    let outcome = tauri::async_runtime::spawn_blocking(move || {
+        receiver.recv_timeout(std::time::Duration::from_secs(TRACE_TIMEOUT_SECS))
+    })
+    .await
+    .map_err(|e| AppError::Io(std::io::Error::other(e.to_string())))?;
+
+    match outcome {
+        Ok(manifest) => Ok(manifest?),
+        Err(_) => {
+            state.frame_tracer.cancel(&state.capture_state);
+            Err(AppError::NotFound(format!(
+                "no frame arrived within {} seconds",
+                TRACE_TIMEOUT_SECS
+            )))
+        }
+    }
+}
+
+/// Pipeline settings the live stream is processed with
+fn current_pipeline_variant(
+    config: &StreamingConfig,
+    display: &DisplayConfig,
+) -> Result<pipeline_compare::PipelineVariant, AppError> {
+    let active = config
+        .active_stream
+        .ok_or_else(|| AppError::NotFound("No active stream to compare".to_string()))?;
+    let width = display.settings.width.unwrap_or(u32::from(active.width));
+    let height = display.settings.height.unwrap_or(u32::from(active.height));
+    let stride = match display.stride_index {
+        Some(index) => Some(((width as f32 * STRIDE_OPTIONS[index]) as u32 / 2) * 2),
+        None => display.settings.stride,
+    };
+
+    Ok(pipeline_compare::PipelineVariant {
+        label: "current".to_string(),
+        mjpeg: config
+            .current_format()
+            .is_some_and(|f| f.kind == uvc_descriptors::FormatKind::Mjpeg),
+        pixel_format: config.pixel_format,
+        width,
+        height,
+        stride,
+        headerless: config.quirks.headerless_payloads,
+    })
+}
+
+/// A fresh frame assembler configured like the one of the running stream
+fn repro_assembler(config: &StreamingConfig) -> frame_assembler::FrameAssembler {
+    let mut assembler = match (config.current_format(), config.active_stream) {
+        (Some(format), _) if format.kind == uvc_descriptors::FormatKind::Mjpeg => {
+            frame_assembler::FrameAssembler::new_mjpeg()
         }
+        (_, Some(active)) => frame_assembler::FrameAssembler::new(
+            config
+                .pixel_format
+                .frame_size(u32::from(active.width), u32::from(active.height)),
+        ),
+        // Unknown format: let the assembler detect frame boundaries
+        _ => frame_assembler::FrameAssembler::new(0),
+    };
+    assembler.set_headerless(config.quirks.headerless_payloads);
+    assembler
+}
+
+/// Start recording processed frames
+///
+/// Frames are written to a new `recording_<timestamp>` directory in the
+/// output directory. With `include_raw`, the raw USB payload stream is captured alongside
+/// so the session can be reprocessed later. With `raw_video`, assembled YUV frames are
+/// also written before conversion (optionally zstd-compressed with `raw_compression`).
+/// With `video` (`"avi"` or `"mkv"`), the processed frames are also muxed into an MJPEG
+/// video; AVI uses a `video_fps` time base (default 30), MKV keeps frame timestamps and
+/// embeds bookmarks as chapters.
+/// Returns the recording directory.
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+fn start_recording(
+    app: tauri::AppHandle,
+    state: State<'_, AppState>,
+    label: Option<String>,
+    include_raw: Option<bool>,
+    raw_video: Option<bool>,
+    raw_compression: Option<raw_video::RawCompression>,
+    video: Option<video_recorder::VideoContainer>,
+    video_fps: Option<u32>,
+) -> Result<String, AppError> {
+    let options = recording::RecordingOptions {
+        label: label.unwrap_or_default(),
+        include_raw: include_raw.unwrap_or(false),
+        raw_video: raw_video.unwrap_or(false),
+        raw_compression: raw_compression.unwrap_or_default(),
+        video,
+        video_fps,
+    };
+    begin_recording(&app, &state, options)
+}
+
+/// Start a recording in the output directory
+fn begin_recording(
+    app: &AppHandle,
+    state: &AppState,
+    mut options: recording::RecordingOptions,
+) -> Result<String, AppError> {
+    options.frame_interval = lock_or_err!(&state.streaming_config)?
+        .active_stream
+        .map(|active| std::time::Duration::from_nanos(u64::from(active.frame_interval) * 100));
+    let storage = app_storage(app, state)?;
+    guard_resources(app, &storage)?;
+    let dir = state.recording.start(&storage, options)?;
+    Ok(dir.to_string_lossy().to_string())
+}
+
+/// Refuse to start writing when storage or memory is nearly exhausted, and
+/// emit `resource-warning` when either is low
+fn guard_resources(app: &AppHandle, storage: &storage::Storage) -> Result<(), AppError> {
+    let status = resources::ensure_available(storage.root())?;
+    if status.level != resources::ResourceLevel::Ok {
+        log::warn!(
+            "Starting with low resources: storage {:?}, memory {:?}",
+            status.storage,
+            status.memory
+        );
+        let _ = app.emit(
+            "resource-warning",
+            resources::ResourceWarning {
+                status,
+                stopped: Vec::new(),
+            },
+        );
+    }
+    Ok(())
+}
+
+/// Stop the active recording and write its index
+#[tauri::command]
+fn stop_recording(state: State<'_, AppState>) -> Result<recording::RecordingResult, AppError> {
+    Ok(state.recording.stop()?)
+}
+
+/// Export the end of a finished recording as an animated WebP clip
+///
+/// `recording` is the directory returned by `stop_recording`. The clip covers
+/// the last `seconds` (default 10) at up to `max_fps` (default 10) frames per
+/// second and is written into the recording directory.
+#[tauri::command]
+fn export_clip(
+    app: tauri::AppHandle,
+    state: State<'_, AppState>,
+    recording: String,
+    seconds: Option<f64>,
+    max_fps: Option<f64>,
+) -> Result<clip::ClipResult, AppError> {
+    let defaults = clip::ClipOptions::default();
+    let options = clip::ClipOptions {
+        seconds: seconds.unwrap_or(defaults.seconds),
+        max_fps: max_fps.unwrap_or(defaults.max_fps),
     };
+    let storage = app_storage(&app, &state)?.subdir(recording)?;
+    Ok(clip::export_webp(&storage, &options)?)
+}
+
+/// Get the current recording status
+#[tauri::command]
+fn get_recording_status(state: State<'_, AppState>) -> recording::RecordingStatus {
+    state.recording.status()
+}
+
+/// Bookmark the frame currently on screen
+///
+/// Records the frame sequence number, timestamp and optional note in the
+/// session manifest, and as a marker in the active recording if there is one.
+#[tauri::command]
+fn bookmark(
+    app: tauri::AppHandle,
+    state: State<'_, AppState>,
+    note: Option<String>,
+) -> Result<session::Bookmark, AppError> {
+    add_bookmark(&app, &state, note)
+}
+
+/// Bookmark the current frame in the session manifest and active recording
+fn add_bookmark(
+    app: &AppHandle,
+    state: &AppState,
+    note: Option<String>,
+) -> Result<session::Bookmark, AppError> {
+    let frame_sequence = state.frame_buffer.sequence();
+    let note = note.as_deref().and_then(session::normalize_note);
+    let marker = state.recording.add_marker(note.clone());
+
+    let mut manifest = lock_or_err!(state.session)?;
+    let bookmark = manifest.add_bookmark(frame_sequence, note, marker);
+
+    manifest.save(&app_storage(app, state)?)?;
 
     log::info!(
-        "Video format changed: {:?} -> {} (restart requested)",
-        config.selected_format_index,
-        result
+        "Bookmark {} at frame {}",
+        bookmark.id,
+        bookmark.frame_sequence
     );
-    Ok(result)
+    Ok(bookmark)
 }
 
-/// Get available video formats discovered from camera
+/// Get all bookmarks of the current session
 #[tauri::command]
-fn get_available_formats(state: State<'_, AppState>) -> Result<Vec<DiscoveredFormat>, AppError> {
-    let config = lock_or_err!(&state.streaming_config)?;
-    Ok(config.available_formats.clone())
+fn get_bookmarks(state: State<'_, AppState>) -> Result<Vec<session::Bookmark>, AppError> {
+    Ok(lock_or_err!(state.session)?.bookmarks.clone())
 }
 
-/// Get current video format setting
+/// How long a `set_resolution` or `set_framerate` script step waits for the
+/// stream to restart
+const SCRIPT_RESTART_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(15);
+
+/// Run a QA script (see [`automation`]) and report the outcome of each step
+///
+/// Steps run in order on a worker thread; `script-step` is emitted with the
+/// report of each step as it finishes. Only one script runs at a time.
 #[tauri::command]
-fn get_video_format(state: State<'_, AppState>) -> Result<String, AppError> {
-    let config = lock_or_err!(&state.streaming_config)?;
+async fn execute_script(
+    app: tauri::AppHandle,
+    json: String,
+) -> Result<automation::ScriptReport, AppError> {
+    let script = automation::Script::parse(&json)?;
 
-    Ok(match config.selected_format_index {
-        None => "FMT:Auto".to_string(),
-        Some(idx) => {
-            // Find format info
-            if let Some(fmt) = config.available_formats.iter().find(|f| f.index == idx) {
-                format!("FMT:{}:{}", fmt.index, fmt.format_type)
-            } else {
-                format!("FMT:{}", idx)
-            }
-        }
+    tauri::async_runtime::spawn_blocking(move || {
+        let state = app.state::<AppState>();
+        let _running = state.scripts.begin()?;
+        log::info!(
+            "Running script {} ({} steps)",
+            script.name.as_deref().unwrap_or("(unnamed)"),
+            script.steps.len()
+        );
+        let mut runner = AppStepRunner {
+            app: &app,
+            state: state.inner(),
+        };
+        let report = automation::run_script(&script, &state.scripts, &mut runner);
+        log::info!(
+            "Script finished: {} succeeded, {} failed{}",
+            report.succeeded,
+            report.failed,
+            if report.cancelled { ", cancelled" } else { "" }
+        );
+        Ok::<_, AppError>(report)
     })
+    .await
+    .map_err(|e| AppError::Io(std::io::Error::other(e.to_string())))?
 }
 
-/// Start capturing USB packets for debugging
+/// Stop the running script after its current step
 ///
-/// Begins capturing raw USB packets during streaming. The packets are stored
-/// in memory until `stop_packet_capture` is called.
+/// Waits and recordings in progress are cut short. Returns whether a script
+/// was running.
 #[tauri::command]
-fn start_packet_capture(state: State<'_, AppState>) -> Result<String, String> {
-    state.capture_state.start()?;
-    Ok("Packet capture started".to_string())
+fn cancel_script(state: State<'_, AppState>) -> bool {
+    state.scripts.cancel()
 }
 
-/// Stop capturing USB packets and save to files
+/// Runs script steps against the app state
+struct AppStepRunner<'a> {
+    app: &'a AppHandle,
+    state: &'a AppState,
+}
+
+impl automation::StepRunner for AppStepRunner<'_> {
+    fn run(&mut self, operation: &automation::Operation) -> Result<Option<String>, String> {
+        run_operation(self.app, self.state, operation).map_err(|e| e.to_string())
+    }
+
+    fn finished(&mut self, report: &automation::StepReport) {
+        let _ = self.app.emit("script-step", report);
+    }
+}
+
+/// Perform one script operation with the same code paths as the commands
+fn run_operation(
+    app: &AppHandle,
+    state: &AppState,
+    operation: &automation::Operation,
+) -> Result<Option<String>, AppError> {
+    use automation::Operation;
+    use std::time::Duration;
+
+    match operation {
+        Operation::SetResolution { width, height } => {
+            lock_or_err!(state.streaming_config)?
+                .select_resolution(*width, *height)
+                .ok_or_else(|| {
+                    AppError::NotFound(format!(
+                        "No {}x{} resolution in the current format",
+                        width, height
+                    ))
+                })?;
+            let active = wait_for_stream(state)?;
+            if (active.width, active.height) != (*width, *height) {
+                return Err(AppError::NotFound(format!(
+                    "Camera is streaming {}x{} instead of {}x{}",
+                    active.width, active.height, width, height
+                )));
+            }
+            Ok(Some(format!("{}x{}", active.width, active.height)))
+        }
+        Operation::SetFramerate { fps } => {
+            let rate = lock_or_err!(state.streaming_config)?
+                .set_frame_rate(*fps)
+                .ok_or_else(|| AppError::NotFound(format!("No frame rate near {} fps", fps)))?;
+            wait_for_stream(state)?;
+            Ok(Some(format!("{:.2} fps", rate.fps)))
+        }
+        Operation::SetControl { name, value } => {
+            let control: uvc_controls::CameraControl = name.parse()?;
+            let info = state.camera_controls.set(control, *value)?;
+            Ok(Some(info.current.to_string()))
+        }
+        Operation::SetLed { level } => {
+            let raw = state.camera_controls.set_led_brightness(*level)?;
+            Ok(Some(raw.to_string()))
+        }
+        Operation::Wait { ms } => {
+            state.scripts.sleep(Duration::from_millis(*ms))?;
+            Ok(None)
+        }
+        Operation::WaitForFrame { timeout_ms } => {
+            let sequence = state.frame_buffer.sequence();
+            let arrived = state
+                .scripts
+                .wait_until(Duration::from_millis(*timeout_ms), || {
+                    state.frame_buffer.sequence() != sequence
+                })?;
+            if !arrived {
+                return Err(automation::ScriptError::Timeout("a frame".to_string()).into());
+            }
+            Ok(Some(state.frame_buffer.sequence().to_string()))
+        }
+        Operation::Snapshot { path, format } => {
+            let snapshot = snapshot_current_frame(app, state, path.clone(), *format)?;
+            Ok(Some(snapshot.path))
+        }
+        Operation::Record { seconds, label } => {
+            let options = recording::RecordingOptions {
+                label: label.clone().unwrap_or_default(),
+                ..Default::default()
+            };
+            begin_recording(app, state, options)?;
+            // Stop the recording even if the script is cancelled meanwhile
+            let waited = state.scripts.sleep(Duration::from_secs_f64(*seconds));
+            let result = state.recording.stop()?;
+            waited?;
+            Ok(Some(result.directory))
+        }
+        Operation::Bookmark { note } => {
+            let bookmark = add_bookmark(app, state, note.clone())?;
+            Ok(Some(bookmark.id.to_string()))
+        }
+    }
+}
+
+/// Wait for a requested stream restart to finish and return the new stream
+fn wait_for_stream(state: &AppState) -> Result<ActiveStream, AppError> {
+    let mut active = None;
+    state.scripts.wait_until(SCRIPT_RESTART_TIMEOUT, || {
+        let config = lock_or_recover(&state.streaming_config);
+        active = config.active_stream.filter(|_| !config.restart_requested);
+        active.is_some()
+    })?;
+    active
+        .ok_or_else(|| automation::ScriptError::Timeout("the stream to restart".to_string()).into())
+}
+
+/// Get the USB permission state of every device seen this session
+#[tauri::command]
+fn get_usb_permissions(
+    state: State<'_, AppState>,
+) -> Result<Vec<usb_permission::DevicePermission>, AppError> {
+    Ok(lock_or_err!(state.usb_permissions)?.devices())
+}
+
+/// Outcome of a deep link action, returned to the caller and emitted to the frontend
+#[derive(Debug, Clone, Serialize)]
+struct DeepLinkResult {
+    /// The action that was performed
+    action: deep_link::DeepLinkAction,
+    /// Human-readable summary of what happened
+    message: String,
+    /// Path of the file written by the action, if any
+    path: Option<String>,
+}
+
+/// Event payload emitted to the frontend after handling a deep link
+#[derive(Debug, Clone, Serialize)]
+struct DeepLinkEvent {
+    /// The URL that was received
+    url: String,
+    /// Result on success
+    result: Option<DeepLinkResult>,
+    /// Error message on failure
+    error: Option<String>,
+}
+
+/// Run a parsed deep link action against the application state
+fn dispatch_deep_link(
+    app: &AppHandle,
+    state: &AppState,
+    action: deep_link::DeepLinkAction,
+) -> Result<DeepLinkResult, AppError> {
+    use deep_link::DeepLinkAction;
+
+    let (message, path) = match &action {
+        DeepLinkAction::Snapshot => {
+            let frame = save_frame_dump(app, state, None)?;
+            ("Snapshot saved".to_string(), Some(frame.path))
+        }
+        DeepLinkAction::StartRecording { label } => {
+            let options = recording::RecordingOptions {
+                label: label.clone().unwrap_or_default(),
+                include_raw: true,
+                ..Default::default()
+            };
+            let dir = begin_recording(app, state, options)?;
+            let message = match label {
+                Some(label) => format!("Recording started: {}", label),
+                None => "Recording started".to_string(),
+            };
+            (message, Some(dir))
+        }
+        DeepLinkAction::StopRecording => {
+            let result = state.recording.stop()?;
+            ("Recording saved".to_string(), Some(result.directory))
+        }
+    };
+
+    log::info!("Deep link {:?}: {}", action, message);
+    Ok(DeepLinkResult {
+        action,
+        message,
+        path,
+    })
+}
+
+/// Handle a `cleanscope://` deep link
 ///
-/// Stops the capture, writes the captured packets to the app cache directory,
-/// and returns information about the captured data.
+/// Lets external workflow apps drive captures, e.g. `cleanscope://snapshot` or
+/// `cleanscope://start-recording?label=JobX`. The frontend is notified via a
+/// `deep-link` event in addition to the returned result.
 #[tauri::command]
-fn stop_packet_capture(
+fn handle_deep_link(
     app: tauri::AppHandle,
     state: State<'_, AppState>,
-) -> Result<capture::CaptureResult, String> {
-    // Get status before stopping (for duration)
-    let status = state.capture_state.status();
+    url: String,
+) -> Result<DeepLinkResult, AppError> {
+    let result = deep_link::parse_deep_link(&url)
+        .map_err(AppError::from)
+        .and_then(|action| dispatch_deep_link(&app, &state, action));
+    emit_deep_link(&app, &url, &result);
+    result
+}
+
+/// Parse and dispatch a deep link received from the platform (e.g. an Android intent)
+pub(crate) fn process_deep_link(app: &AppHandle, url: &str) {
+    let state = app.state::<AppState>();
+    let result = deep_link::parse_deep_link(url)
+        .map_err(AppError::from)
+        .and_then(|action| dispatch_deep_link(app, &state, action));
+    if let Err(e) = &result {
+        log::warn!("Deep link {} failed: {}", url, e);
+    }
+    emit_deep_link(app, url, &result);
+}
+
+/// Emit the outcome of a deep link to the frontend
+fn emit_deep_link(app: &AppHandle, url: &str, result: &Result<DeepLinkResult, AppError>) {
+    let event = match result {
+        Ok(result) => DeepLinkEvent {
+            url: url.to_string(),
+            result: Some(result.clone()),
+            error: None,
+        },
+        Err(e) => DeepLinkEvent {
+            url: url.to_string(),
+            result: None,
+            error: Some(e.to_string()),
+        },
+    };
+    let _ = app.emit("deep-link", event);
+}
+
+/// Get the English message catalog keyed by message code
+///
+/// The frontend uses this as the fallback when it has no translation for a code.
+#[tauri::command]
+fn get_message_catalog() -> Vec<messages::CatalogEntry> {
+    messages::message_catalog()
+}
+
+/// Get the current display settings for use in streaming
+///
+/// Computes the effective `DisplaySettings` from the consolidated `DisplayConfig`,
+/// applying stride multiplier if a stride index is set.
+///
+/// # Errors
+///
+/// Returns `AppError::LockPoisoned` if the mutex lock is poisoned.
+pub fn get_current_display_settings(state: &AppState) -> Result<DisplaySettings, AppError> {
+    let display = lock_or_err!(&state.display)?;
+
+    // Calculate stride if stride multiplier is set
+    let stride = if let Some(si) = display.stride_index {
+        let multiplier = STRIDE_OPTIONS[si];
+        let width = display.settings.width.unwrap_or(1280);
+        Some((width as f32 * multiplier) as u32)
+    } else {
+        display.settings.stride
+    };
+
+    Ok(DisplaySettings {
+        width: display.settings.width,
+        height: display.settings.height,
+        stride,
+    })
+}
+
+/// Emit a USB device event to the frontend
+pub fn emit_usb_event(app: &AppHandle, connected: bool, info: Option<String>) {
+    let health = app.try_state::<AppState>().map(|state| {
+        state.stream_health.set_connection(connected, info.clone());
+        state.stream_health.stats()
+    });
+    let _ = app.emit(
+        "usb-device-event",
+        UsbStatus {
+            connected,
+            info,
+            health,
+        },
+    );
+}
+
+/// Emit the connection status with frame statistics as a `usb-health` event
+pub fn emit_stream_health(app: &AppHandle) {
+    let Some(state) = app.try_state::<AppState>() else {
+        return;
+    };
+    let (connected, info) = state.stream_health.connection();
+    let _ = app.emit(
+        "usb-health",
+        UsbStatus {
+            connected,
+            info,
+            health: Some(state.stream_health.stats()),
+        },
+    );
+}
+
+/// Emit the connection status with frame statistics every
+/// [`stream_health::HEALTH_EVENT_INTERVAL`] while a camera is connected,
+/// followed by the frame assembly statistics as `stream-stats`
+///
+/// Runs until the app shuts down.
+fn spawn_health_reporter(app: AppHandle) {
+    let handle = app.clone();
+    handle
+        .state::<AppState>()
+        .lifecycle
+        .spawn("usb-health", lifecycle::Stage::Reporting, move |stop| {
+            let state = app.state::<AppState>();
+            while stop.sleep(stream_health::HEALTH_EVENT_INTERVAL) {
+                if state.stream_health.connection().0 {
+                    emit_stream_health(&app);
+                    let _ = app.emit("stream-stats", state.stream_stats.report());
+                }
+            }
+        })
+        .expect("Failed to spawn health reporter thread");
+}
+
+/// Checkpoint of the running session, or `None` if there is nothing worth
+/// resuming (no bookmarks, measurements or recording)
+fn session_checkpoint(state: &AppState) -> Result<Option<resume::Checkpoint>, AppError> {
+    let (session_file, bookmarks, measurements) = {
+        let manifest = lock_or_err!(state.session)?;
+        let has_content = !manifest.bookmarks.is_empty()
+            || !manifest.measurements.is_empty()
+            || !manifest.snapshots.is_empty();
+        (
+            has_content.then(|| manifest.file_name()),
+            manifest.bookmarks.len(),
+            manifest.measurements.len(),
+        )
+    };
+    let recording_dir = state.recording.directory().and_then(|dir| {
+        dir.file_name()
+            .map(|name| name.to_string_lossy().to_string())
+    });
+    if session_file.is_none() && recording_dir.is_none() {
+        return Ok(None);
+    }
+
+    let (pixel_format, decode_mjpeg, active) = {
+        let config = lock_or_err!(state.streaming_config)?;
+        (
+            config.pixel_format,
+            config.decode_mjpeg,
+            config.active_stream,
+        )
+    };
+    let (transform, zoom) = {
+        let display = lock_or_err!(state.display)?;
+        (display.transform, display.zoom)
+    };
+    let (connected, device) = state.stream_health.connection();
+    Ok(Some(resume::Checkpoint {
+        session_file,
+        bookmarks,
+        measurements,
+        recording_dir,
+        device: device.filter(|_| connected),
+        settings: resume::ResumeSettings {
+            pixel_format,
+            decode_mjpeg,
+            format_index: active.map(|a| a.format_index),
+            frame_index: active.map(|a| a.frame_index),
+            // 0: the camera chose the interval
+            frame_interval: active.map(|a| a.frame_interval).filter(|&i| i != 0),
+            transform,
+            zoom,
+            measurement_unit: *lock_or_err!(state.measurement_unit)?,
+        },
+    }))
+}
+
+/// Save or delete `resume.json` if the checkpoint differs from `saved`
+fn update_checkpoint(
+    app: &AppHandle,
+    state: &AppState,
+    saved: &mut Option<resume::Checkpoint>,
+) -> Result<(), AppError> {
+    let checkpoint = session_checkpoint(state)?;
+    if checkpoint == *saved {
+        return Ok(());
+    }
+    let storage = app_storage(app, state)?;
+    match &checkpoint {
+        Some(checkpoint) => checkpoint.save(&storage)?,
+        None => resume::Checkpoint::remove(&storage)?,
+    }
+    *saved = checkpoint;
+    Ok(())
+}
+
+/// Keep the session checkpoint and the recording journal on disk every
+/// [`resume::CHECKPOINT_INTERVAL`] so a crash loses little
+///
+/// Runs until the app shuts down. A checkpoint left by the previous run
+/// is kept until it is resumed or dismissed.
+fn spawn_session_checkpointer(app: AppHandle) {
+    let handle = app.clone();
+    handle
+        .state::<AppState>()
+        .lifecycle
+        .spawn(
+            "session-checkpoint",
+            lifecycle::Stage::Persistence,
+            move |stop| {
+                let state = app.state::<AppState>();
+                let mut saved = None;
+                while stop.sleep(resume::CHECKPOINT_INTERVAL) {
+                    state.recording.flush();
+                    if lock_or_recover(&state.pending_resume).is_some() {
+                        continue;
+                    }
+                    if let Err(e) = update_checkpoint(&app, &state, &mut saved) {
+                        log::warn!("Failed to save session checkpoint: {}", e);
+                    }
+                }
+            },
+        )
+        .expect("Failed to spawn session checkpoint thread");
+}
+
+/// Spawn the thread that takes scheduled snapshots
+///
+/// Runs until the app shuts down.
+fn spawn_auto_snapshotter(app: AppHandle) {
+    let handle = app.clone();
+    handle
+        .state::<AppState>()
+        .lifecycle
+        .spawn("auto-snapshot", lifecycle::Stage::Processing, move |stop| {
+            let state = app.state::<AppState>();
+            while stop.sleep(auto_snapshot::POLL_INTERVAL) {
+                let frame_sequence = state.frame_buffer.sequence();
+                let Some(settings) = state.auto_snapshot.due(Instant::now(), frame_sequence) else {
+                    continue;
+                };
+                let outcome = match take_auto_snapshot(&app, &state, &settings, frame_sequence) {
+                    Ok(entry) => {
+                        log::info!("Auto-snapshot {} saved to {}", entry.label, entry.path);
+                        let _ = app.emit("auto-snapshot", &entry);
+                        Ok(entry.path)
+                    }
+                    Err(e) => {
+                        log::warn!("Auto-snapshot failed: {}", e);
+                        Err(e.to_string())
+                    }
+                };
+                state
+                    .auto_snapshot
+                    .record(Instant::now(), frame_sequence, outcome);
+            }
+        })
+        .expect("Failed to spawn auto-snapshot thread");
+}
 
-    // Stop capture and get packets
-    let packets = state.capture_state.stop();
+/// Analyse the current frame's histogram every
+/// [`exposure::ANALYSIS_INTERVAL`] and emit `exposure-suggestion` when the
+/// exposure is chronically off, applying the change if auto-apply is on
+///
+/// Runs until the app shuts down.
+fn spawn_exposure_advisor(app: AppHandle) {
+    let handle = app.clone();
+    handle
+        .state::<AppState>()
+        .lifecycle
+        .spawn(
+            "exposure-advisor",
+            lifecycle::Stage::Processing,
+            move |stop| {
+                let state = app.state::<AppState>();
+                while stop.sleep(exposure::ANALYSIS_INTERVAL) {
+                    let frame = state.frame_buffer.load();
+                    let frame_sequence = frame.sequence;
+                    if !state.exposure.wants_frame(frame_sequence) {
+                        continue;
+                    }
+                    let stats = match frame_rgb(&state, &frame) {
+                        Ok(rgb) => exposure::LumaStats::from_rgb(&rgb),
+                        Err(e) => {
+                            log::debug!("Exposure analysis skipped: {}", e);
+                            None
+                        }
+                    };
+                    let Some(stats) = stats else {
+                        continue;
+                    };
+                    let Some(condition) =
+                        state
+                            .exposure
+                            .observe(frame_sequence, stats, Instant::now())
+                    else {
+                        continue;
+                    };
+
+                    let controls = state.camera_controls.list().unwrap_or_default();
+                    let mut suggestion =
+                        exposure::ExposureSuggestion::plan(condition, stats, &controls);
+                    if let Some(change) = suggestion.change {
+                        if state.exposure.settings().auto_apply {
+                            match state.camera_controls.set(change.control, change.to) {
+                                Ok(_) => suggestion.applied = true,
+                                Err(e) => log::warn!(
+                                    "Failed to apply {} {}: {}",
+                                    change.control.name(),
+                                    change.to,
+                                    e
+                                ),
+                            }
+                        }
+                    }
+                    log::info!(
+                        "Exposure {:?} (mean luma {:.0}): {:?}{}",
+                        suggestion.condition,
+                        stats.mean,
+                        suggestion.action,
+                        if suggestion.applied { ", applied" } else { "" }
+                    );
+                    state.exposure.record(&suggestion, Instant::now());
+                    let _ = app.emit("exposure-suggestion", &suggestion);
+                }
+            },
+        )
+        .expect("Failed to spawn exposure advisor thread");
+}
 
-    if packets.is_empty() {
-        return Err("No packets captured".to_string());
+/// Stop the recording and packet capture because a resource is critical
+fn stop_for_resources(app: &AppHandle, state: &AppState) -> Vec<resources::StoppedActivity> {
+    let mut stopped = Vec::new();
+    if state.recording.is_recording() {
+        match state.recording.stop() {
+            Ok(result) => log::warn!("Stopped recording {} on low resources", result.directory),
+            Err(e) => log::error!("Failed to stop recording on low resources: {}", e),
+        }
+        stopped.push(resources::StoppedActivity::Recording);
     }
+    if state.capture_state.is_capturing() {
+        match save_packet_capture(app, state) {
+            Ok(result) => log::warn!(
+                "Stopped packet capture {} on low resources",
+                result.packets_path
+            ),
+            Err(e) => log::error!("Failed to save packet capture on low resources: {}", e),
+        }
+        stopped.push(resources::StoppedActivity::PacketCapture);
+    }
+    stopped
+}
 
-    // Get app cache directory
-    let cache_dir = app
-        .path()
-        .app_cache_dir()
-        .map_err(|e| format!("Could not get cache dir: {}", e))?;
+/// Check free storage and memory every [`resources::CHECK_INTERVAL`] while a
+/// recording or packet capture runs
+///
+/// Emits `resource-warning` when the level changes, and stops both once a
+/// resource is critical so they end cleanly instead of failing mid-write.
+/// Runs until the app shuts down.
+fn spawn_resource_monitor(app: AppHandle) {
+    let handle = app.clone();
+    handle
+        .state::<AppState>()
+        .lifecycle
+        .spawn(
+            "resource-monitor",
+            lifecycle::Stage::Processing,
+            move |stop| {
+                let state = app.state::<AppState>();
+                let mut reported = resources::ResourceLevel::Ok;
+                while stop.sleep(resources::CHECK_INTERVAL) {
+                    if !state.recording.is_recording() && !state.capture_state.is_capturing() {
+                        reported = resources::ResourceLevel::Ok;
+                        continue;
+                    }
+                    let Ok(storage) = app_storage(&app, &state) else {
+                        continue;
+                    };
+                    let status = resources::check(storage.root());
+                    let stopped = if status.level == resources::ResourceLevel::Critical {
+                        stop_for_resources(&app, &state)
+                    } else {
+                        Vec::new()
+                    };
+                    if status.level != reported || !stopped.is_empty() {
+                        if status.level != resources::ResourceLevel::Ok {
+                            log::warn!(
+                                "Resources {:?}: storage {:?}, memory {:?}",
+                                status.level,
+                                status.storage,
+                                status.memory
+                            );
+                        }
+                        reported = status.level;
+                        let _ = app.emit(
+                            "resource-warning",
+                            resources::ResourceWarning { status, stopped },
+                        );
+                    }
+                }
+            },
+        )
+        .expect("Failed to spawn resource monitor thread");
+}
 
-    // Create directory if it doesn't exist
-    std::fs::create_dir_all(&cache_dir)
-        .map_err(|e| format!("Could not create cache dir: {}", e))?;
+/// Read the saved user preferences into `AppState` and apply them
+fn load_settings(app: &AppHandle) -> Result<(), AppError> {
+    let state = app.state::<AppState>();
+    let settings = settings::Settings::load(&settings_storage(app)?)?;
+    settings.validate()?;
+    apply_settings(&state, &settings)?;
+    log::info!("Loaded settings: {:?}", settings);
+    *lock_or_err!(state.settings)? = settings;
+    Ok(())
+}
 
-    // Write capture files
-    capture::write_capture_files(&cache_dir, &packets, status.duration_ms)
+/// Pick up the checkpoint of a previous run that did not exit cleanly
+fn load_pending_resume(app: &AppHandle) -> Result<(), AppError> {
+    let state = app.state::<AppState>();
+    let Some(checkpoint) = resume::Checkpoint::load(&app_storage(app, &state)?)? else {
+        return Ok(());
+    };
+    log::info!(
+        "Previous session ended unexpectedly ({} bookmarks, recording: {})",
+        checkpoint.bookmarks,
+        checkpoint.recording_dir.as_deref().unwrap_or("none")
+    );
+    *lock_or_err!(state.pending_resume)? = Some(checkpoint);
+    Ok(())
 }
 
-/// Get the current packet capture status
-///
-/// Returns information about whether capture is active and how many packets
-/// have been captured so far.
-#[tauri::command]
-fn get_capture_status(state: State<'_, AppState>) -> capture::CaptureStatus {
-    state.capture_state.status()
+/// Stop the subsystems that manage their own threads when the app shuts down
+fn register_shutdown_hooks(app: &AppHandle) {
+    let state = app.state::<AppState>();
+    let handle = app.clone();
+    state
+        .lifecycle
+        .on_shutdown("replay", lifecycle::Stage::Capture, move || {
+            let _ = handle.state::<AppState>().replay.stop();
+        });
+    let handle = app.clone();
+    state.lifecycle.on_shutdown(
+        "pipeline-comparison",
+        lifecycle::Stage::Processing,
+        move || {
+            let state = handle.state::<AppState>();
+            let _ = state.pipeline_comparison.stop(&state.capture_state);
+        },
+    );
+    let handle = app.clone();
+    state
+        .lifecycle
+        .on_shutdown("inference", lifecycle::Stage::Processing, move || {
+            let _ = handle.state::<AppState>().inference.stop();
+        });
+    let handle = app.clone();
+    state
+        .lifecycle
+        .on_shutdown("frame-spool", lifecycle::Stage::Processing, move || {
+            handle.state::<AppState>().spooler.stop();
+        });
 }
 
-/// Get the current display settings for use in streaming
-///
-/// Computes the effective `DisplaySettings` from the consolidated `DisplayConfig`,
-/// applying stride multiplier if a stride index is set.
+/// Stop background threads and subsystems in [`lifecycle::Stage`] order
 ///
-/// # Errors
-///
-/// Returns an error if the mutex lock is poisoned.
-pub fn get_current_display_settings(state: &AppState) -> Result<DisplaySettings, String> {
-    let display = state
-        .display
-        .lock()
-        .map_err(|e| format!("Lock poisoned: {}", e))?;
-
-    // Calculate stride if stride multiplier is set
-    let stride = if let Some(si) = display.stride_index {
-        let multiplier = STRIDE_OPTIONS[si];
-        let width = display.settings.width.unwrap_or(1280);
-        Some((width as f32 * multiplier) as u32)
+/// Runs before `finish_session`, so the camera has stopped delivering frames
+/// before the recording is closed and the checkpointer can't rewrite the
+/// checkpoint after it is removed.
+fn shutdown_services(app: &AppHandle) {
+    let report = app
+        .state::<AppState>()
+        .lifecycle
+        .shutdown(lifecycle::SHUTDOWN_TIMEOUT);
+    if report.timed_out.is_empty() {
+        log::info!("Stopped {} services", report.stopped.len());
     } else {
-        display.settings.stride
-    };
-
-    Ok(DisplaySettings {
-        width: display.settings.width,
-        height: display.settings.height,
-        stride,
-    })
+        log::warn!("Still running at exit: {}", report.timed_out.join(", "));
+    }
 }
 
-/// Emit a USB device event to the frontend
-pub fn emit_usb_event(app: &AppHandle, connected: bool, info: Option<String>) {
-    let _ = app.emit("usb-device-event", UsbStatus { connected, info });
+/// Close the session on a clean exit: finish a running recording and delete
+/// the checkpoint, unless a resume offer is still unanswered
+fn finish_session(app: &AppHandle) {
+    let state = app.state::<AppState>();
+    if state.recording.is_recording() {
+        match state.recording.stop() {
+            Ok(result) => log::info!("Stopped recording on exit: {}", result.directory),
+            Err(e) => log::error!("Failed to stop recording on exit: {}", e),
+        }
+    }
+    if lock_or_recover(&state.pending_resume).is_some() {
+        return;
+    }
+    let removed =
+        app_storage(app, &state).and_then(|storage| Ok(resume::Checkpoint::remove(&storage)?));
+    if let Err(e) = removed {
+        log::warn!("Failed to remove session checkpoint: {}", e);
+    }
 }
 
 /// Extended USB status with disconnect reason
@@ -1010,6 +4450,9 @@ pub struct UsbStatusExtended {
 
 /// Emit a USB disconnect event with reason to the frontend
 pub fn emit_usb_disconnect(app: &AppHandle, reason: DisconnectReason, info: Option<String>) {
+    if let Some(state) = app.try_state::<AppState>() {
+        state.stream_health.set_connection(false, info.clone());
+    }
     let _ = app.emit(
         "usb-device-event",
         UsbStatusExtended {
@@ -1067,28 +4510,41 @@ pub fn emit_usb_reconnect_stopped(app: &AppHandle, message: Option<String>) {
 
 /// Emit a USB error event to the frontend
 pub fn emit_usb_error(app: &AppHandle, error: UsbError) {
+    log::warn!("[{}] {}", error.code, error.message);
     let _ = app.emit("usb-error", error);
 }
 
-/// Emit a camera frame event to the frontend
-pub fn emit_camera_frame(app: &AppHandle, width: u32, height: u32) {
-    let _ = app.emit("camera-frame", Resolution { width, height });
-}
-
 /// Emit frame-ready event with frame metadata
 ///
 /// This allows the frontend to skip the `get_frame_info` IPC call
-/// and only fetch the raw frame data.
-pub fn emit_frame_ready(app: &AppHandle, width: u32, height: u32, is_jpeg: bool) {
-    let format = if is_jpeg { "jpeg" } else { "rgb" };
-    let info = FrameInfo {
-        width,
-        height,
-        format: format.to_string(),
-    };
+/// and only fetch the raw frame data. Channels registered with
+/// `subscribe_frames` get the frame itself, and a running detection model
+/// is offered it.
+pub fn emit_frame_ready(app: &AppHandle, info: &FrameInfo) {
+    if let Some(state) = app.try_state::<AppState>() {
+        let frame = state.frame_buffer.load();
+        if state.frame_stream.has_subscribers() {
+            state.frame_stream.publish(&frame.info(), &frame.data);
+        }
+        state.inference.offer(frame);
+    }
     let _ = app.emit("frame-ready", info);
 }
 
+/// Publish annotations of a stored frame on the annotation bus
+///
+/// Emits `frame-annotations` with what is left after the bus applied the
+/// annotation config.
+pub fn emit_frame_annotations(app: &AppHandle, annotations: annotations::FrameAnnotations) {
+    let delivered = match app.try_state::<AppState>() {
+        Some(state) => state.annotations.publish(annotations),
+        None => Some(annotations),
+    };
+    if let Some(delivered) = delivered {
+        let _ = app.emit("frame-annotations", delivered);
+    }
+}
+
 /// Run the `CleanScope` application
 ///
 /// Initializes logging, sets up the Tauri builder with commands and plugins,
@@ -1115,19 +4571,60 @@ pub fn run() {
     }
 
     log::info!("CleanScope starting up");
+    diagnostics::set_libusb_log_level(diagnostics::LibusbLogLevel::from_env());
 
     // Create shared state for camera frames and display settings
-    let frame_buffer = Arc::new(Mutex::new(FrameBuffer::default()));
+    let frame_buffer = Arc::new(FrameBuffer::default());
     let display = Arc::new(Mutex::new(DisplayConfig::default()));
-    let streaming_config = Arc::new(Mutex::new(StreamingConfig::default()));
+    let streaming_config = Arc::new(Mutex::new(StreamingConfig {
+        bulk_transfer: bulk_transfer::BulkTransferConfig::from_env(),
+        quirks: quirks::DeviceQuirks::from_env(),
+        decode_mjpeg: jpeg_decode::enabled_from_env(),
+        frame_capacity: frame_broadcast::capacity_from_env(),
+        ..Default::default()
+    }));
     let capture_state = Arc::new(capture::CaptureState::new());
+    let recording = Arc::new(recording::RecordingState::new(Arc::clone(&capture_state)));
     let usb_stop_flag = Arc::new(std::sync::atomic::AtomicBool::new(false));
+    let usb_permissions = Arc::new(Mutex::new(usb_permission::PermissionCache::new()));
+    let stream_health = Arc::new(stream_health::StreamHealth::new());
+    let stream_stats = Arc::new(stats::StreamStats::new());
+
+    // Frame validation level, overridden from the environment (default: saved setting, else strict)
+    let validation_override = std::env::var("CLEANSCOPE_FRAME_VALIDATION")
+        .ok()
+        .map(|s| ValidationLevel::from_env_str(&s));
+    let validation_level = Arc::new(Mutex::new(validation_override.unwrap_or_default()));
+
+    // Output directory for frame dumps, captures and recordings (default: app cache)
+    let output_dir = std::env::var_os("CLEANSCOPE_OUTPUT_DIR")
+        .filter(|dir| !dir.is_empty())
+        .map(std::path::PathBuf::from);
+    if let Some(dir) = &output_dir {
+        log::info!("Output directory: {}", dir.display());
+    }
+
+    // Snapshot format (default: keep frames as captured)
+    let snapshot_format = std::env::var("CLEANSCOPE_SNAPSHOT_FORMAT")
+        .ok()
+        .and_then(|s| parse_snapshot_format(&s));
+    log::info!("Snapshot format: {:?}", snapshot_format);
+
+    // Background frame spool (default: off)
+    let spooler = Arc::new(spool::FrameSpooler::new(spool::SpoolConfig::from_env()));
+
+    let frame_tracer = Arc::new(frame_trace::FrameTracer::new());
 
-    // Read frame validation level from environment (default: strict)
-    let validation_level = std::env::var("CLEANSCOPE_FRAME_VALIDATION")
-        .map(|s| ValidationLevel::from_env_str(&s))
-        .unwrap_or_default();
-    log::info!("Frame validation level: {:?}", validation_level);
+    // Frame processor plugins, loaded in setup once the data directory is known
+    let plugins = Arc::new(plugins::PluginHost::new());
+
+    // Annotations from plugins and models, sent to the frontend and recordings
+    let annotations = Arc::new(annotations::AnnotationBus::new());
+
+    // Image controls, attached by the streaming backend while a camera is open
+    let camera_controls = Arc::new(uvc_controls::CameraControls::from_env());
+    let still_capture = Arc::new(still_capture::StillCapture::new());
+    let devices = Arc::new(Mutex::new(devices::DeviceRegistry::new()));
 
     // Clone Arcs for the setup closure (used in Android USB handler)
     #[allow(unused_variables)]
@@ -1136,26 +4633,132 @@ pub fn run() {
     let streaming_config_clone = Arc::clone(&streaming_config);
     #[allow(unused_variables)]
     let usb_stop_flag_clone = Arc::clone(&usb_stop_flag);
+    #[allow(unused_variables)]
+    let capture_state_clone = Arc::clone(&capture_state);
+    #[allow(unused_variables)]
+    let recording_clone = Arc::clone(&recording);
+    #[allow(unused_variables)]
+    let usb_permissions_clone = Arc::clone(&usb_permissions);
+    #[allow(unused_variables)]
+    let stream_health_clone = Arc::clone(&stream_health);
+    #[allow(unused_variables)]
+    let stream_stats_clone = Arc::clone(&stream_stats);
+    #[allow(unused_variables)]
+    let spooler_clone = Arc::clone(&spooler);
+    #[allow(unused_variables)]
+    let frame_tracer_clone = Arc::clone(&frame_tracer);
+    let plugins_clone = Arc::clone(&plugins);
+    #[allow(unused_variables)]
+    let annotations_clone = Arc::clone(&annotations);
+    #[allow(unused_variables)]
+    let camera_controls_clone = Arc::clone(&camera_controls);
+    #[allow(unused_variables)]
+    let still_capture_clone = Arc::clone(&still_capture);
+    #[allow(unused_variables)]
+    let devices_clone = Arc::clone(&devices);
+    let validation_level_clone = Arc::clone(&validation_level);
+
+    let builder = tauri::Builder::default();
+    // Opening URLs is the frontend's only way out to the network
+    #[cfg(shell_open)]
+    let builder = builder.plugin(tauri_plugin_shell::init());
 
-    tauri::Builder::default()
-        .plugin(tauri_plugin_shell::init())
+    builder
         .manage(AppState {
             frame_buffer: Arc::clone(&frame_buffer),
             display,
             streaming_config,
             capture_state,
+            recording,
+            session: Mutex::new(session::SessionManifest::new()),
             usb_stop_flag,
+            usb_permissions,
+            stream_health,
+            stream_stats,
             validation_level,
+            output_dir,
+            snapshot_format: Mutex::new(snapshot_format),
+            frame_cache: Mutex::new(frame_cache::FrameCache::new()),
+            spooler,
+            pipeline_comparison: pipeline_compare::PipelineComparison::new(),
+            frame_tracer,
+            replay: replay::ReplayPlayer::new(),
+            frame_stream: frame_stream::FrameStream::new(),
+            plugins,
+            inference: inference::Detector::new(),
+            annotations,
+            camera_controls,
+            still_capture,
+            devices,
+            calibration: Mutex::new(None),
+            measurement_unit: Mutex::new(measurement::LengthUnit::default()),
+            frozen: Mutex::new(None),
+            history: Mutex::new(history::History::default()),
+            settings: Mutex::new(settings::Settings::default()),
+            pending_resume: Mutex::new(None),
+            scripts: automation::ScriptControl::default(),
+            auto_snapshot: auto_snapshot::AutoSnapshotScheduler::default(),
+            exposure: exposure::ExposureAdvisor::default(),
+            lifecycle: lifecycle::Lifecycle::new(),
         })
         .invoke_handler(tauri::generate_handler![
             get_build_info,
+            get_build_capabilities,
+            get_diagnostics,
+            prepare_capture_submission,
+            set_libusb_log_level,
             check_usb_status,
+            list_usb_devices,
+            select_device,
             cycle_resolution,
             get_resolutions,
             get_current_resolution,
+            get_stream_info,
+            get_framerates,
+            set_framerate,
             get_frame,
+            get_frame_rgb,
             get_frame_info,
+            subscribe_frames,
+            unsubscribe_frames,
+            ack_frame,
+            list_plugins,
+            reload_plugins,
+            get_annotation_config,
+            set_annotation_config,
+            start_inference,
+            stop_inference,
+            inference_status,
+            get_camera_controls,
+            set_camera_control,
+            get_extension_units,
+            set_led_control,
+            set_led_brightness,
+            set_exposure_advisor,
+            get_exposure_advisor_status,
+            calibrate_from_target,
+            set_reference_calibration,
+            get_calibration,
+            clear_calibration,
+            measure_line,
+            measure_angle,
+            measure_polygon_area,
+            get_measurements,
+            set_measurement_unit,
+            get_measurement_unit,
+            export_measurements,
+            freeze_frame,
+            unfreeze_frame,
+            get_frozen_frame,
+            add_frozen_annotation,
+            update_frozen_annotation,
+            remove_frozen_annotation,
+            save_frozen_snapshot,
             dump_frame,
+            save_snapshot,
+            capture_still,
+            get_snapshot_formats,
+            set_snapshot_format,
             cycle_width,
             cycle_height,
             cycle_stride,
@@ -1163,7 +4766,41 @@ pub fn run() {
             start_packet_capture,
             stop_packet_capture,
             get_capture_status,
+            set_capture_filter,
+            export_capture_pcap,
+            set_repro_buffer,
+            replay_last,
+            load_replay,
+            start_replay,
+            stop_replay,
+            replay_status,
+            start_pipeline_comparison,
+            stop_pipeline_comparison,
+            get_pipeline_comparison,
+            trace_next_frame,
             toggle_skip_mjpeg,
+            set_mjpeg_decode,
+            is_mjpeg_decode_enabled,
+            set_rotation,
+            set_flip,
+            get_transform,
+            set_zoom,
+            get_zoom,
+            undo,
+            redo,
+            get_history,
+            get_resume_offer,
+            resume_session,
+            dismiss_resume,
+            execute_script,
+            cancel_script,
+            get_stream_stats,
+            set_validation_level,
+            set_frame_channel_capacity,
+            get_frame_channel_capacity,
+            start_auto_snapshot,
+            stop_auto_snapshot,
+            get_auto_snapshot_status,
             enable_raw_capture,
             is_raw_capture_enabled,
             cycle_pixel_format,
@@ -1171,30 +4808,134 @@ pub fn run() {
             cycle_video_format,
             get_available_formats,
             get_video_format,
+            handle_deep_link,
+            get_message_catalog,
+            preflight,
+            start_recording,
+            stop_recording,
+            export_clip,
+            get_recording_status,
+            bookmark,
+            get_bookmarks,
+            get_usb_permissions,
+            get_spool_status,
+            set_spool_config,
+            get_settings,
+            update_settings,
         ])
-        .setup(move |_app| {
+        .setup(move |app| {
             log::info!("Tauri app setup complete");
 
-            // On Android, we'll initialize the USB handling here
-            #[cfg(target_os = "android")]
+            // Granted here rather than in capabilities/, which every build
+            // compiles in, so builds without the plugin don't reference it
+            #[cfg(shell_open)]
+            app.add_capability(
+                tauri::ipc::CapabilityBuilder::new("shell-open")
+                    .window("main")
+                    .permission("shell:allow-open"),
+            )?;
+
+            // Apply saved preferences before anything streams or writes files
+            if let Err(e) = load_settings(app.handle()) {
+                log::warn!("Failed to load settings, using defaults: {}", e);
+            }
+            if let Some(level) = validation_override {
+                *lock_or_recover(&validation_level_clone) = level;
+            }
+            log::info!(
+                "Frame validation level: {:?}",
+                *lock_or_recover(&validation_level_clone)
+            );
+
+            spawn_health_reporter(app.handle().clone());
+            spawn_auto_snapshotter(app.handle().clone());
+            spawn_exposure_advisor(app.handle().clone());
+            spawn_resource_monitor(app.handle().clone());
+            register_shutdown_hooks(app.handle());
+
+            // Offer to resume the session a crashed run left behind
+            if let Err(e) = load_pending_resume(app.handle()) {
+                log::warn!("Failed to read session checkpoint: {}", e);
+            }
+            spawn_session_checkpointer(app.handle().clone());
+
+            // Load frame processor plugins before the first frame arrives
+            match plugin_dir(app.handle()) {
+                Ok(dir) => {
+                    plugins_clone.load_dir(&dir);
+                }
+                Err(e) => log::warn!("Plugins not loaded: {}", e),
+            }
+
+            // Start spooling right away so it doesn't depend on the frontend
+            if spooler_clone.config().is_enabled() {
+                let state = app.state::<AppState>();
+                match app_storage(app.handle(), &state) {
+                    Ok(storage) => {
+                        if let Err(e) = spooler_clone.start(&storage) {
+                            log::error!("Failed to start frame spool: {}", e);
+                        }
+                    }
+                    Err(e) => log::error!("Failed to start frame spool: {}", e),
+                }
+            }
+
+            // Start the USB camera backend (Android, or desktop with `desktop-usb`)
+            #[cfg(usb_streaming)]
             {
                 let ctx = usb::StreamingContext {
-                    app_handle: _app.handle().clone(),
+                    app_handle: app.handle().clone(),
                     frame_buffer: Arc::clone(&frame_buffer),
                     display: Arc::clone(&display_clone),
                     streaming_config: Arc::clone(&streaming_config_clone),
                     stop_flag: Arc::clone(&usb_stop_flag_clone),
-                    validation_level,
+                    validation_level: Arc::clone(&validation_level_clone),
+                    capture_state: Arc::clone(&capture_state_clone),
+                    recording: Arc::clone(&recording_clone),
+                    usb_permissions: Arc::clone(&usb_permissions_clone),
+                    stream_health: Arc::clone(&stream_health_clone),
+                    stream_stats: Arc::clone(&stream_stats_clone),
+                    spooler: Arc::clone(&spooler_clone),
+                    frame_tracer: Arc::clone(&frame_tracer_clone),
+                    plugins: Arc::clone(&plugins_clone),
+                    annotations: Arc::clone(&annotations_clone),
+                    camera_controls: Arc::clone(&camera_controls_clone),
+                    still_capture: Arc::clone(&still_capture_clone),
+                    devices: Arc::clone(&devices_clone),
+                    stream_supervisor: Arc::new(stream_supervisor::StreamSupervisor::from_env()),
+                    conversion_buffer: Arc::new(Mutex::new(Vec::new())),
                 };
-                std::thread::spawn(move || {
-                    usb::init_usb_handler(ctx);
-                });
+                app.state::<AppState>()
+                    .lifecycle
+                    .spawn_with_signal(
+                        "usb-camera",
+                        lifecycle::Stage::Capture,
+                        lifecycle::StopSignal::from_flag(Arc::clone(&usb_stop_flag_clone)),
+                        move |stop| usb::init_usb_handler(ctx, &stop),
+                    )
+                    .expect("Failed to spawn USB camera thread");
+            }
+
+            #[cfg(target_os = "android")]
+            {
+                // Route deep links from the launch intent and later onNewIntent calls
+                let app_handle = app.handle().clone();
+                deep_link::register_app_handle(app_handle.clone());
+                if let Some(url) = deep_link::get_launch_deep_link() {
+                    process_deep_link(&app_handle, &url);
+                }
             }
 
             Ok(())
         })
-        .run(tauri::generate_context!())
-        .expect("error while running tauri application");
+        .build(tauri::generate_context!())
+        .expect("error while building tauri application")
+        .run(|app, event| {
+            if let tauri::RunEvent::Exit = event {
+                shutdown_services(app);
+                finish_session(app);
+            }
+        });
 }
 
 #[cfg(test)]
@@ -1203,13 +4944,43 @@ mod command_tests {
 
     /// Create a test `AppState` for unit testing
     fn create_test_state() -> AppState {
+        let capture_state = Arc::new(capture::CaptureState::new());
         AppState {
-            frame_buffer: Arc::new(Mutex::new(FrameBuffer::default())),
+            frame_buffer: Arc::new(FrameBuffer::default()),
             display: Arc::new(Mutex::new(DisplayConfig::default())),
             streaming_config: Arc::new(Mutex::new(StreamingConfig::default())),
-            capture_state: Arc::new(capture::CaptureState::new()),
+            recording: Arc::new(recording::RecordingState::new(Arc::clone(&capture_state))),
+            capture_state,
+            session: Mutex::new(session::SessionManifest::new()),
             usb_stop_flag: Arc::new(std::sync::atomic::AtomicBool::new(false)),
-            validation_level: ValidationLevel::default(),
+            usb_permissions: Arc::new(Mutex::new(usb_permission::PermissionCache::new())),
+            stream_health: Arc::new(stream_health::StreamHealth::new()),
+            stream_stats: Arc::new(stats::StreamStats::new()),
+            validation_level: Arc::new(Mutex::new(ValidationLevel::default())),
+            output_dir: None,
+            snapshot_format: Mutex::new(None),
+            frame_cache: Mutex::new(frame_cache::FrameCache::new()),
+            spooler: Arc::new(spool::FrameSpooler::new(spool::SpoolConfig::default())),
+            pipeline_comparison: pipeline_compare::PipelineComparison::new(),
+            frame_tracer: Arc::new(frame_trace::FrameTracer::new()),
+            replay: replay::ReplayPlayer::new(),
+            frame_stream: frame_stream::FrameStream::new(),
+            plugins: Arc::new(plugins::PluginHost::new()),
+            inference: inference::Detector::new(),
+            annotations: Arc::new(annotations::AnnotationBus::new()),
+            camera_controls: Arc::new(uvc_controls::CameraControls::new()),
+            still_capture: Arc::new(still_capture::StillCapture::new()),
+            devices: Arc::new(Mutex::new(devices::DeviceRegistry::new())),
+            calibration: Mutex::new(None),
+            measurement_unit: Mutex::new(measurement::LengthUnit::default()),
+            frozen: Mutex::new(None),
+            history: Mutex::new(history::History::default()),
+            settings: Mutex::new(settings::Settings::default()),
+            pending_resume: Mutex::new(None),
+            scripts: automation::ScriptControl::default(),
+            auto_snapshot: auto_snapshot::AutoSnapshotScheduler::default(),
+            exposure: exposure::ExposureAdvisor::default(),
+            lifecycle: lifecycle::Lifecycle::new(),
         }
     }
 
@@ -1263,6 +5034,9 @@ mod command_tests {
         assert_eq!(format_pixel_display(&PixelFormat::I420), "FMT:I420");
         assert_eq!(format_pixel_display(&PixelFormat::Rgb888), "FMT:RGB24");
         assert_eq!(format_pixel_display(&PixelFormat::Bgr888), "FMT:BGR24");
+        assert_eq!(format_pixel_display(&PixelFormat::BayerRggb), "FMT:RGGB");
+        assert_eq!(format_pixel_display(&PixelFormat::Nv21), "FMT:NV21");
+        assert_eq!(format_pixel_display(&PixelFormat::Yv12), "FMT:YV12");
     }
 
     // ========================================================================
@@ -1433,14 +5207,7 @@ mod command_tests {
             .streaming_config
             .lock()
             .map_err(|e| format!("Lock poisoned: {}", e))?;
-        config.pixel_format = match config.pixel_format {
-            PixelFormat::Yuyv => PixelFormat::Uyvy,
-            PixelFormat::Uyvy => PixelFormat::Nv12,
-            PixelFormat::Nv12 => PixelFormat::I420,
-            PixelFormat::I420 => PixelFormat::Rgb888,
-            PixelFormat::Rgb888 => PixelFormat::Bgr888,
-            PixelFormat::Bgr888 => PixelFormat::Yuyv,
-        };
+        config.pixel_format = format_registry::next_format(config.pixel_format);
         Ok(format_pixel_display(&config.pixel_format))
     }
 
@@ -1484,30 +5251,33 @@ mod command_tests {
 
         // Default is YUYV, so first cycle goes to UYVY
         let mut results = Vec::new();
-        for _ in 0..6 {
+        for _ in 0..9 {
             results.push(test_cycle_pixel_format(&state).unwrap());
         }
 
-        // Should cycle through all 6 formats
+        // Should cycle through all 9 formats
         assert_eq!(results[0], "FMT:UYVY"); // YUYV -> UYVY
         assert_eq!(results[1], "FMT:NV12"); // UYVY -> NV12
         assert_eq!(results[2], "FMT:I420"); // NV12 -> I420
         assert_eq!(results[3], "FMT:RGB24"); // I420 -> RGB888
         assert_eq!(results[4], "FMT:BGR24"); // RGB888 -> BGR888
-        assert_eq!(results[5], "FMT:YUYV"); // BGR888 -> YUYV (wraps)
+        assert_eq!(results[5], "FMT:RGGB"); // BGR888 -> RGGB
+        assert_eq!(results[6], "FMT:NV21"); // RGGB -> NV21
+        assert_eq!(results[7], "FMT:YV12"); // NV21 -> YV12
+        assert_eq!(results[8], "FMT:YUYV"); // YV12 -> YUYV (wraps)
     }
 
     #[test]
     fn test_cycle_pixel_format_all_unique_in_cycle() {
         let state = create_test_state();
 
-        let formats: Vec<String> = (0..6)
+        let formats: Vec<String> = (0..9)
             .map(|_| test_cycle_pixel_format(&state).unwrap())
             .collect();
 
-        // All 6 should be different (cycling through 6 formats)
+        // All 9 should be different (cycling through 9 formats)
         let unique: std::collections::HashSet<_> = formats.iter().collect();
-        assert_eq!(unique.len(), 6);
+        assert_eq!(unique.len(), 9);
     }
 
     // ========================================================================
@@ -1516,26 +5286,13 @@ mod command_tests {
 
     /// Helper to simulate `get_frame_info` command logic on test state
     fn test_get_frame_info(state: &AppState) -> Result<FrameInfo, String> {
-        let buffer = state
-            .frame_buffer
-            .lock()
-            .map_err(|e| format!("Lock error: {}", e))?;
+        let frame = state.frame_buffer.load();
 
-        if buffer.frame.is_empty() {
+        if frame.is_empty() {
             return Err("No frame available".to_string());
         }
 
-        let format = if is_jpeg_data(&buffer.frame) {
-            "jpeg".to_string()
-        } else {
-            "rgb".to_string()
-        };
-
-        Ok(FrameInfo {
-            width: buffer.width,
-            height: buffer.height,
-            format,
-        })
+        Ok(frame.info())
     }
 
     #[test]
@@ -1551,12 +5308,7 @@ mod command_tests {
         let state = create_test_state();
 
         // Set up RGB frame data (not JPEG - no 0xFFD8 marker)
-        {
-            let mut buffer = state.frame_buffer.lock().unwrap();
-            buffer.width = 640;
-            buffer.height = 480;
-            buffer.frame = vec![0u8; 640 * 480 * 3]; // RGB24 data
-        }
+        state.frame_buffer.store(vec![0u8; 640 * 480 * 3], 640, 480); // RGB24 data
 
         let info = test_get_frame_info(&state).unwrap();
         assert_eq!(info.width, 640);
@@ -1569,13 +5321,10 @@ mod command_tests {
         let state = create_test_state();
 
         // Set up JPEG frame data with SOI marker (0xFFD8)
-        {
-            let mut buffer = state.frame_buffer.lock().unwrap();
-            buffer.width = 1280;
-            buffer.height = 720;
-            // Minimal JPEG header: SOI marker + some data
-            buffer.frame = vec![0xFF, 0xD8, 0xFF, 0xE0, 0x00, 0x10];
-        }
+        // Minimal JPEG header: SOI marker + some data
+        state
+            .frame_buffer
+            .store(vec![0xFF, 0xD8, 0xFF, 0xE0, 0x00, 0x10], 1280, 720);
 
         let info = test_get_frame_info(&state).unwrap();
         assert_eq!(info.width, 1280);
@@ -1583,6 +5332,137 @@ mod command_tests {
         assert_eq!(info.format, "jpeg");
     }
 
+    #[test]
+    fn test_frame_buffer_readers_keep_their_frame() {
+        let state = create_test_state();
+        state.frame_buffer.store(vec![1u8; 12], 2, 2);
+        let held = state.frame_buffer.load();
+
+        // The streaming thread stores the next frame while a command holds one
+        let writer = {
+            let frame_buffer = Arc::clone(&state.frame_buffer);
+            std::thread::spawn(move || frame_buffer.store(vec![2u8; 27], 3, 3))
+        };
+        let info = writer.join().unwrap();
+
+        assert_eq!((held.sequence, held.data[0], held.width), (1, 1, 2));
+        assert_eq!(info.sequence, 2);
+        assert_eq!(test_get_frame_info(&state).unwrap().width, 3);
+    }
+
+    #[test]
+    fn test_frame_buffer_keeps_raw_only_while_capturing() {
+        let buffer = FrameBuffer::default();
+        buffer.store_with_raw(vec![0u8; 12], &[7u8; 8], 2, 2);
+        assert!(buffer.load().raw.is_empty());
+
+        buffer.set_capture_raw_frames(true);
+        buffer.store_with_raw(vec![0u8; 12], &[7u8; 8], 2, 2);
+        assert_eq!(buffer.load().raw, [7u8; 8]);
+    }
+
+    #[test]
+    fn test_frame_buffer_store_keeps_metadata_in_sync() {
+        let buffer = FrameBuffer::default();
+
+        let info = buffer.store(vec![0xFF, 0xD8, 0xFF, 0xD9], 1280, 720);
+        assert_eq!(
+            info,
+            FrameInfo {
+                sequence: 1,
+                width: 1280,
+                height: 720,
+                format: "jpeg".to_string(),
+                size: 4,
+            }
+        );
+
+        let info = buffer.store(vec![0u8; 2 * 2 * 3], 2, 2);
+        assert_eq!(info.sequence, 2);
+        assert_eq!(info.format, "rgb");
+        assert_eq!(info.size, 12);
+        assert_eq!((buffer.load().width, buffer.load().height), (2, 2));
+        assert_eq!(buffer.info(), info);
+
+        let json = serde_json::to_value(&info).unwrap();
+        assert_eq!(json["sequence"], 2);
+        assert_eq!(json["size"], 12);
+    }
+
+    #[test]
+    fn test_frame_buffer_store_reads_jpeg_dimensions() {
+        let buffer = FrameBuffer::default();
+        let jpeg = [
+            0xFF, 0xD8, // SOI
+            0xFF, 0xC0, 0x00, 0x0B, 0x08, 0x01, 0xE0, 0x02, 0x80, 0x01, // SOF0 640x480
+            0xFF, 0xD9, // EOI
+        ];
+
+        // Bulk MJPEG streams pass no dimensions
+        let info = buffer.store(jpeg.to_vec(), 0, 0);
+        assert_eq!((info.width, info.height), (640, 480));
+        assert_eq!((buffer.load().width, buffer.load().height), (640, 480));
+
+        // The SOF header wins over a stale negotiated resolution
+        let info = buffer.store(jpeg.to_vec(), 1280, 720);
+        assert_eq!((info.width, info.height), (640, 480));
+    }
+
+    #[test]
+    fn test_encode_processed_frame_keeps_native_format() {
+        let buffer = FrameBuffer::default();
+        buffer.store(vec![0u8; 2 * 2 * 3], 2, 2);
+        let frame = buffer.load();
+        let mut cache = frame_cache::FrameCache::new();
+        let (data, ext) = encode_processed_frame(&frame, None, &mut cache).unwrap();
+        assert_eq!((data.len(), ext), (12, "rgb"));
+
+        let result = encode_processed_frame(&frame, Some(ImageFormat::Png), &mut cache);
+        if ImageFormat::Png.is_available() {
+            let (data, ext) = result.unwrap();
+            assert_eq!(ext, "png");
+            assert_eq!(&data[1..4], b"PNG");
+        } else {
+            assert_eq!(
+                result.unwrap_err().code(),
+                messages::MessageCode::EncodeError
+            );
+        }
+    }
+
+    #[test]
+    fn test_frame_rgb_is_converted_once_per_frame() {
+        let state = create_test_state();
+        assert!(matches!(current_frame_rgb(&state), Err(AppError::NoFrame)));
+
+        lock_or_err!(state.frame_buffer)
+            .unwrap()
+            .store(vec![1u8; 2 * 2 * 3], 2, 2);
+        let first = current_frame_rgb(&state).unwrap();
+        let second = current_frame_rgb(&state).unwrap();
+        assert!(Arc::ptr_eq(&first, &second));
+        assert_eq!(state.frame_cache.lock().unwrap().stats(), (1, 1));
+
+        // A new frame is converted again
+        lock_or_err!(state.frame_buffer)
+            .unwrap()
+            .store(vec![2u8; 2 * 2 * 3], 2, 2);
+        assert_eq!(*current_frame_rgb(&state).unwrap(), vec![2u8; 12]);
+        assert_eq!(state.frame_cache.lock().unwrap().stats(), (1, 2));
+    }
+
+    #[test]
+    fn test_parse_snapshot_format() {
+        assert_eq!(parse_snapshot_format(""), None);
+        assert_eq!(parse_snapshot_format("native"), None);
+        assert_eq!(parse_snapshot_format("bmp"), None);
+        // Formats not compiled in are ignored
+        let expected = ImageFormat::Jpeg
+            .is_available()
+            .then_some(ImageFormat::Jpeg);
+        assert_eq!(parse_snapshot_format("JPG"), expected);
+    }
+
     // ========================================================================
     // Tests for get_current_display_settings (public helper function)
     // ========================================================================
@@ -1731,21 +5611,13 @@ mod command_tests {
 
     /// Helper to simulate `enable_raw_capture` command logic on test state
     fn test_enable_raw_capture(state: &AppState) -> Result<String, String> {
-        let mut buffer = state
-            .frame_buffer
-            .lock()
-            .map_err(|e| format!("Lock error: {}", e))?;
-        buffer.capture_raw_frames = true;
+        state.frame_buffer.set_capture_raw_frames(true);
         Ok("Raw capture enabled".to_string())
     }
 
     /// Helper to simulate `is_raw_capture_enabled` command logic on test state
     fn test_is_raw_capture_enabled(state: &AppState) -> Result<bool, String> {
-        let buffer = state
-            .frame_buffer
-            .lock()
-            .map_err(|e| format!("Lock error: {}", e))?;
-        Ok(buffer.capture_raw_frames)
+        Ok(state.frame_buffer.capture_raw_frames())
     }
 
     #[test]
@@ -1765,4 +5637,529 @@ mod command_tests {
         let enabled = test_is_raw_capture_enabled(&state).unwrap();
         assert!(enabled);
     }
+
+    // ========================================================================
+    // Tests for error serialization
+    // ========================================================================
+
+    #[test]
+    fn test_app_error_serializes_code_and_message() {
+        let json = serde_json::to_value(AppError::NoFrame).unwrap();
+        assert_eq!(json["code"], "NO_FRAME");
+        assert_eq!(json["message"], "No frame available");
+    }
+
+    #[test]
+    fn test_app_error_codes_are_stable_for_wrapped_errors() {
+        let err = AppError::from(capture::CaptureError::NotActive);
+        assert_eq!(err.code(), messages::MessageCode::CaptureError);
+
+        let err = AppError::from(deep_link::DeepLinkError::UnknownAction("x".to_string()));
+        let json = serde_json::to_value(&err).unwrap();
+        assert_eq!(json["code"], "DEEP_LINK_ERROR");
+
+        let err = AppError::from(yuv_conversion::ConversionError("bad stride".to_string()));
+        let json = serde_json::to_value(&err).unwrap();
+        assert_eq!(json["code"], "CONVERSION_ERROR");
+        assert_eq!(json["message"], "Conversion error: bad stride");
+    }
+
+    #[test]
+    fn test_no_network_build_has_no_networking() {
+        let caps = BuildCapabilities::current();
+        if caps.no_network && !cfg!(debug_assertions) {
+            assert!(caps.is_network_free());
+        }
+        assert_eq!(caps.shell_open, cfg!(shell_open));
+        assert_eq!(caps.native_plugins, cfg!(plugin_loading));
+    }
+
+    #[test]
+    fn test_static_permissions_have_no_network_access() {
+        // The shell permission is only added at runtime when the plugin is in
+        let capability = include_str!("../capabilities/default.json");
+        assert!(!capability.contains("shell:"));
+        // INTERNET is only requested by the debug manifest (dev server)
+        let manifest = include_str!("../gen/android/app/src/main/AndroidManifest.xml");
+        assert!(!manifest.contains("android.permission.INTERNET"));
+    }
+
+    #[test]
+    fn test_csp_only_connects_to_ipc() {
+        let conf: serde_json::Value =
+            serde_json::from_str(include_str!("../tauri.conf.json")).unwrap();
+        let csp = conf["app"]["security"]["csp"].as_str().unwrap();
+        let connect_src = csp
+            .split(';')
+            .find_map(|directive| directive.trim().strip_prefix("connect-src "))
+            .expect("CSP must restrict connect-src");
+        for source in connect_src.split_whitespace() {
+            assert!(
+                matches!(source, "ipc:" | "http://ipc.localhost"),
+                "connect-src allows {}",
+                source
+            );
+        }
+        // default-src is the fallback for every other fetch directive
+        assert!(csp.contains("default-src 'self'"));
+    }
+
+    fn config_with_formats() -> StreamingConfig {
+        use uvc_descriptors::{FormatCatalog, FormatDescriptor, FormatKind, FrameDescriptor};
+
+        let frame = |index, width, height| FrameDescriptor {
+            index,
+            width,
+            height,
+            min_bit_rate: 0,
+            max_bit_rate: 0,
+            max_frame_size: None,
+            default_interval: 333_333,
+            intervals: uvc_descriptors::FrameIntervals::Discrete(vec![333_333]),
+        };
+        let format = |index, kind, default_frame_index, frames| FormatDescriptor {
+            index,
+            kind,
+            guid: (kind == FormatKind::Uncompressed)
+                .then(|| uvc_descriptors::fourcc_guid(*b"YUY2")),
+            bits_per_pixel: (kind == FormatKind::Uncompressed).then_some(16),
+            default_frame_index,
+            frames,
+            still_frame: None,
+        };
+        StreamingConfig {
+            format_catalog: FormatCatalog {
+                formats: vec![
+                    format(1, FormatKind::Uncompressed, 1, vec![frame(1, 640, 480)]),
+                    format(
+                        2,
+                        FormatKind::Mjpeg,
+                        2,
+                        vec![frame(1, 1280, 720), frame(2, 640, 480), frame(3, 320, 240)],
+                    ),
+                ],
+                ..Default::default()
+            },
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_cycle_resolution_without_formats() {
+        let mut config = StreamingConfig::default();
+        assert!(config.cycle_resolution().is_none());
+        assert!(!config.restart_requested);
+    }
+
+    #[test]
+    fn test_cycle_resolution_follows_active_stream() {
+        let mut config = config_with_formats();
+        config.active_stream = Some(ActiveStream {
+            format_index: 2,
+            frame_index: 1,
+            width: 1280,
+            height: 720,
+            frame_interval: 333_333,
+            max_frame_size: 0,
+            max_payload: 0,
+        });
+        assert_eq!(config.current_format().unwrap().index, 2);
+
+        let next = config.cycle_resolution().unwrap();
+        assert_eq!((next.width, next.height, next.frame_index), (640, 480, 2));
+        assert_eq!(next.available_count, 3);
+        // Pinned to the streaming format, not the first discovered one
+        assert_eq!(config.selected_format_index, Some(2));
+        assert_eq!(config.selected_frame_index, Some(2));
+        assert!(config.restart_requested);
+    }
+
+    #[test]
+    fn test_stream_info_reports_negotiated_params() {
+        let mut config = config_with_formats();
+        assert_eq!(config.stream_info(), None);
+
+        config.active_stream = Some(ActiveStream {
+            format_index: 2,
+            frame_index: 1,
+            width: 1280,
+            height: 720,
+            frame_interval: 333_333,
+            max_frame_size: 1_843_200,
+            max_payload: 3072,
+        });
+        let info = config.stream_info().unwrap();
+        assert_eq!(info.format, "MJPEG");
+        assert_eq!((info.width, info.height), (1280, 720));
+        assert!((info.fps - 30.0).abs() < 0.01);
+        assert_eq!((info.max_frame_size, info.max_payload), (1_843_200, 3072));
+    }
+
+    #[test]
+    fn test_cycle_resolution_wraps_before_streaming() {
+        let mut config = config_with_formats();
+        config.selected_format_index = Some(2);
+        config.selected_frame_index = Some(3);
+
+        let next = config.cycle_resolution().unwrap();
+        assert_eq!(next.frame_index, 1);
+        assert_eq!(config.current_frame_index(), Some(1));
+    }
+
+    #[test]
+    fn test_still_request_follows_active_stream() {
+        let mut config = config_with_formats();
+        assert!(matches!(
+            still_request(&config, None),
+            Err(still_capture::StillError::NotStreaming)
+        ));
+
+        config.format_catalog.still_capture_method = 2;
+        config.active_stream = Some(ActiveStream {
+            format_index: 1,
+            frame_index: 1,
+            width: 640,
+            height: 480,
+            frame_interval: 333_333,
+            max_frame_size: 0,
+            max_payload: 0,
+        });
+        let (request, pixel_format) = still_request(&config, Some((640, 480))).unwrap();
+        assert_eq!(request.method, still_capture::StillMethod::Stream);
+        assert_eq!(request.format.index, 1);
+        assert_eq!(request.video_size, (640, 480));
+        assert_eq!(request.size, Some((640, 480)));
+        assert_eq!(pixel_format, PixelFormat::Yuyv);
+    }
+
+    #[test]
+    fn test_select_resolution_skips_restart_when_active() {
+        let mut config = config_with_formats();
+        config.active_stream = Some(ActiveStream {
+            format_index: 2,
+            frame_index: 2,
+            width: 640,
+            height: 480,
+            frame_interval: 333_333,
+            max_frame_size: 0,
+            max_payload: 0,
+        });
+
+        let same = config.select_resolution(640, 480).unwrap();
+        assert_eq!(same.frame_index, 2);
+        assert!(!config.restart_requested);
+
+        let smaller = config.select_resolution(320, 240).unwrap();
+        assert_eq!((smaller.frame_index, smaller.available_count), (3, 3));
+        assert_eq!(config.selected_format_index, Some(2));
+        assert!(config.restart_requested);
+        assert!(config.select_resolution(800, 600).is_none());
+    }
+
+    #[test]
+    fn test_apply_settings_restarts_at_preferred_resolution() {
+        let state = create_test_state();
+        let mut config = config_with_formats();
+        config.active_stream = Some(ActiveStream {
+            format_index: 2,
+            frame_index: 2,
+            width: 640,
+            height: 480,
+            frame_interval: 333_333,
+            max_frame_size: 0,
+            max_payload: 0,
+        });
+        *state.streaming_config.lock().unwrap() = config;
+
+        let settings = settings::Settings {
+            resolution: Some(settings::PreferredResolution {
+                width: 320,
+                height: 240,
+            }),
+            pixel_format: Some(PixelFormat::Uyvy),
+            rotation: transform::Rotation::Cw90,
+            flip_vertical: true,
+            validation_level: Some(ValidationLevel::Off),
+            format_preference: vec!["YUY2".to_string()],
+            ..Default::default()
+        };
+        apply_settings(&state, &settings).unwrap();
+        assert_eq!(
+            *state.validation_level.lock().unwrap(),
+            ValidationLevel::Off
+        );
+
+        let config = state.streaming_config.lock().unwrap();
+        assert_eq!(config.pixel_format, PixelFormat::Uyvy);
+        assert_eq!(config.preferred_resolution, settings.resolution);
+        assert_eq!(config.format_preference, settings.format_preference);
+        assert_eq!(config.selected_frame_index, Some(3));
+        assert!(config.restart_requested);
+        let transform = state.display.lock().unwrap().transform;
+        assert_eq!(transform.rotation, transform::Rotation::Cw90);
+        assert!(!transform.flip_horizontal);
+        assert!(transform.flip_vertical);
+    }
+
+    #[test]
+    fn test_apply_settings_without_pixel_format_uses_yuyv() {
+        let state = create_test_state();
+        lock_or_recover(&state.streaming_config).pixel_format = PixelFormat::Nv12;
+        apply_settings(&state, &settings::Settings::default()).unwrap();
+        assert_eq!(
+            lock_or_recover(&state.streaming_config).pixel_format,
+            PixelFormat::Yuyv
+        );
+    }
+
+    #[test]
+    fn test_changed_settings_are_saved() {
+        let dir = tempfile::tempdir().unwrap();
+        let storage = storage::Storage::new(dir.path());
+        let settings = Mutex::new(settings::Settings::default());
+
+        save_changed_settings(&storage, &settings, |s| {
+            s.rotation = transform::Rotation::Cw270;
+        })
+        .unwrap();
+        save_changed_settings(&storage, &settings, |s| {
+            s.pixel_format = Some(PixelFormat::Nv12);
+        })
+        .unwrap();
+        let saved = settings::Settings::load(&storage).unwrap();
+        assert_eq!(saved, *settings.lock().unwrap());
+        assert_eq!(saved.rotation, transform::Rotation::Cw270);
+        assert_eq!(saved.pixel_format, Some(PixelFormat::Nv12));
+
+        // Unchanged settings are not written again
+        save_changed_settings(&storage, &settings, |s| {
+            s.rotation = transform::Rotation::Cw270;
+        })
+        .unwrap();
+        assert_eq!(storage.audit_entries().unwrap().len(), 2);
+    }
+
+    #[test]
+    fn test_set_frame_rate_pins_current_resolution() {
+        let mut config = config_with_formats();
+        config.format_catalog.formats[1].frames[1].intervals =
+            uvc_descriptors::FrameIntervals::Discrete(vec![333_333, 666_666]);
+        config.active_stream = Some(ActiveStream {
+            format_index: 2,
+            frame_index: 2,
+            width: 640,
+            height: 480,
+            frame_interval: 333_333,
+            max_frame_size: 0,
+            max_payload: 0,
+        });
+
+        let rates = config.frame_rates(None).unwrap();
+        assert_eq!(rates.len(), 2);
+        assert!(rates[0].active && !rates[1].active);
+        assert_eq!(config.frame_rates(Some(1)).unwrap().len(), 1);
+        assert!(config.frame_rates(Some(9)).is_none());
+
+        let rate = config.set_frame_rate(14.0).unwrap();
+        assert_eq!(rate.frame_interval, 666_666);
+        assert_eq!(config.selected_format_index, Some(2));
+        assert_eq!(config.selected_frame_index, Some(2));
+        assert_eq!(config.selected_frame_interval, Some(666_666));
+        assert!(config.restart_requested);
+        assert!(config.set_frame_rate(0.0).is_none());
+    }
+
+    #[test]
+    fn test_active_stream_adopts_descriptor_pixel_layout() {
+        let active = ActiveStream {
+            format_index: 1,
+            frame_index: 1,
+            width: 640,
+            height: 480,
+            frame_interval: 333_333,
+            max_frame_size: 0,
+            max_payload: 0,
+        };
+
+        // Same size per pixel: the configured byte order is kept
+        let mut config = config_with_formats();
+        config.pixel_format = PixelFormat::Uyvy;
+        config.set_active_stream(active);
+        assert_eq!(config.pixel_format, PixelFormat::Uyvy);
+        assert_eq!(config.active_stream, Some(active));
+
+        // A Bayer stream can't be read as YUYV
+        let mut config = config_with_formats();
+        config.format_catalog.formats[0].guid = Some(uvc_descriptors::fourcc_guid(*b"RGGB"));
+        config.set_active_stream(active);
+        assert_eq!(config.pixel_format, PixelFormat::BayerRggb);
+
+        // MJPEG streams leave the pixel format alone
+        let mut config = config_with_formats();
+        config.set_active_stream(ActiveStream {
+            format_index: 2,
+            ..active
+        });
+        assert_eq!(config.pixel_format, PixelFormat::Yuyv);
+    }
+
+    #[test]
+    fn test_current_frame_defaults_to_descriptor_default() {
+        let mut config = config_with_formats();
+        config.selected_format_index = Some(2);
+        // bDefaultFrameIndex of the MJPEG format, not its first frame
+        assert_eq!(config.current_frame_index(), Some(2));
+
+        let formats: Vec<DiscoveredFormat> = config
+            .format_catalog
+            .formats
+            .iter()
+            .map(DiscoveredFormat::from)
+            .collect();
+        assert_eq!(formats[0].format_type, "YUY2");
+        assert_eq!(formats[1].format_type, "MJPEG");
+        assert_eq!(formats[1].frames[2].width, 320);
+    }
+
+    #[test]
+    fn test_frame_channel_capacity_defaults_and_clamps() {
+        let mut config = StreamingConfig::default();
+        assert_eq!(
+            config.frame_channel_capacity(),
+            frame_broadcast::DEFAULT_CAPACITY
+        );
+        config.frame_capacity = Some(0);
+        assert_eq!(config.frame_channel_capacity(), 1);
+        config.frame_capacity = Some(10_000);
+        assert_eq!(
+            config.frame_channel_capacity(),
+            frame_broadcast::MAX_CAPACITY
+        );
+    }
+
+    #[test]
+    fn test_current_pipeline_variant_follows_stream_and_display() {
+        let mut config = config_with_formats();
+        let mut display = DisplayConfig::default();
+        assert!(matches!(
+            current_pipeline_variant(&config, &display),
+            Err(AppError::NotFound(_))
+        ));
+
+        config.active_stream = Some(ActiveStream {
+            format_index: 1,
+            frame_index: 1,
+            width: 640,
+            height: 480,
+            frame_interval: 333_333,
+            max_frame_size: 0,
+            max_payload: 0,
+        });
+        display.settings.height = Some(400);
+        display.settings.stride = Some(1300);
+        let variant = current_pipeline_variant(&config, &display).unwrap();
+        assert!(!variant.mjpeg);
+        assert_eq!((variant.width, variant.height), (640, 400));
+        assert_eq!(variant.stride, Some(1300));
+
+        config.active_stream = Some(ActiveStream {
+            format_index: 2,
+            frame_index: 1,
+            width: 1280,
+            height: 720,
+            frame_interval: 333_333,
+            max_frame_size: 0,
+            max_payload: 0,
+        });
+        assert!(current_pipeline_variant(&config, &display).unwrap().mjpeg);
+    }
+
+    #[test]
+    fn test_display_view_rotates_then_zooms() {
+        let mut display = DisplayConfig::default();
+        let rgb: Vec<u8> = (0..4 * 2 * 3).map(|i| i as u8).collect();
+        assert!(display.is_default_view());
+        assert_eq!(display.apply_view(rgb.clone(), 4, 2), (rgb.clone(), 4, 2));
+
+        display.transform.rotation = transform::Rotation::Cw90;
+        display.zoom = zoom::Zoom::new(2.0, 0.5, 0.5).unwrap();
+        assert!(!display.is_default_view());
+        let (out, width, height) = display.apply_view(rgb.clone(), 4, 2);
+        assert_eq!((width, height), (2, 4));
+        assert_eq!(out.len(), rgb.len());
+
+        // Mismatched frames pass through
+        assert_eq!(
+            display.apply_view(vec![1, 2, 3], 4, 2),
+            (vec![1, 2, 3], 4, 2)
+        );
+    }
+
+    #[test]
+    fn test_session_checkpoint_restores_settings() {
+        let state = create_test_state();
+        assert_eq!(session_checkpoint(&state).unwrap(), None);
+
+        lock_or_recover(&state.session).add_bookmark(7, None, None);
+        state
+            .stream_health
+            .set_connection(true, Some("Camera".to_string()));
+        lock_or_recover(&state.streaming_config).active_stream = Some(ActiveStream {
+            format_index: 2,
+            frame_index: 3,
+            width: 640,
+            height: 480,
+            frame_interval: 333_333,
+            max_frame_size: 0,
+            max_payload: 0,
+        });
+        lock_or_recover(&state.display).transform.flip_horizontal = true;
+        let checkpoint = session_checkpoint(&state).unwrap().unwrap();
+        assert_eq!(checkpoint.bookmarks, 1);
+        assert_eq!(checkpoint.recording_dir, None);
+        assert_eq!(checkpoint.device.as_deref(), Some("Camera"));
+        assert_eq!(checkpoint.settings.frame_interval, Some(333_333));
+
+        // Next run, same camera: the saved mode is renegotiated
+        let dir = tempfile::tempdir().unwrap();
+        let storage = storage::Storage::new(dir.path());
+        let state = create_test_state();
+        state
+            .stream_health
+            .set_connection(true, Some("Camera".to_string()));
+        assert!(restore_settings(&storage, &state, &checkpoint).unwrap());
+        assert!(lock_or_recover(&state.display).transform.flip_horizontal);
+        // The restored preferences are saved, not just applied
+        assert!(lock_or_recover(&state.settings).flip_horizontal);
+        assert_eq!(
+            settings::Settings::load(&storage).unwrap(),
+            *lock_or_recover(&state.settings)
+        );
+        {
+            let config = lock_or_recover(&state.streaming_config);
+            assert_eq!(config.selected_format_index, Some(2));
+            assert_eq!(config.selected_frame_index, Some(3));
+            assert!(config.restart_requested);
+        }
+
+        // Another camera keeps its own mode but gets the display settings
+        let state = create_test_state();
+        state
+            .stream_health
+            .set_connection(true, Some("Other camera".to_string()));
+        assert!(!restore_settings(&storage, &state, &checkpoint).unwrap());
+        assert!(lock_or_recover(&state.display).transform.flip_horizontal);
+        assert!(!lock_or_recover(&state.streaming_config).restart_requested);
+    }
+
+    #[test]
+    fn test_app_error_permission_denied_code() {
+        let err = AppError::PermissionDenied("USB permission not granted".to_string());
+        let json = serde_json::to_value(&err).unwrap();
+        assert_eq!(json["code"], "PERMISSION_DENIED");
+        assert_eq!(
+            json["message"],
+            "Permission denied: USB permission not granted"
+        );
+    }
 }
@@ -11,14 +11,21 @@
 //! 3. Perform USB transfers on that handle
 //!
 //! For video streaming from UVC cameras, we use asynchronous isochronous
-//! transfers which provide guaranteed bandwidth for real-time video data.
+//! transfers which provide guaranteed bandwidth for real-time video data, or
+//! asynchronous bulk transfers for cameras with a bulk streaming endpoint
+//! ([`VideoStream`] picks one from the endpoint's transfer type).
 
 use std::collections::BTreeMap;
+use std::ffi::CStr;
 use std::ptr;
-use std::sync::atomic::{AtomicBool, AtomicU64, AtomicU8, Ordering};
+use std::sync::atomic::{AtomicBool, AtomicU32, AtomicU64, AtomicU8, Ordering};
 use std::sync::Arc;
 
-use crate::frame_assembler::{is_jpeg_data, validate_uvc_header};
+use crate::diagnostics::{LibusbLogLevel, LibusbVersion};
+use crate::frame_assembler::{is_jpeg_data, parse_uvc_payload};
+use crate::still_capture::StillTransport;
+use crate::uvc_controls::{self, ControlTransport, ControlUnits, UvcControlError};
+use crate::uvc_descriptors::FormatCatalog;
 
 /// libusb error codes
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -87,6 +94,104 @@ impl std::error::Error for LibusbError {}
 /// libusb option for disabling device discovery (needed for Android)
 const LIBUSB_OPTION_NO_DEVICE_DISCOVERY: u32 = 2;
 
+/// libusb capability flag: hotplug notifications are supported
+const LIBUSB_CAP_HAS_HOTPLUG: u32 = 0x0001;
+
+/// Version of the linked libusb
+pub fn libusb_version() -> LibusbVersion {
+    // SAFETY: libusb_get_version needs no context and returns a pointer to a
+    // static struct whose rc field is a static NUL-terminated string.
+    unsafe {
+        let version = &*libusb1_sys::libusb_get_version();
+        let rc = if version.rc.is_null() {
+            String::new()
+        } else {
+            CStr::from_ptr(version.rc).to_string_lossy().into_owned()
+        };
+        LibusbVersion {
+            major: version.major,
+            minor: version.minor,
+            micro: version.micro,
+            nano: version.nano,
+            rc,
+        }
+    }
+}
+
+/// libusb option for the log level
+const LIBUSB_OPTION_LOG_LEVEL: u32 = 0;
+
+/// libusb log callback mode: receive messages from every context
+const LIBUSB_LOG_CB_GLOBAL: libc::c_int = 1;
+
+/// Open context, kept so log level changes reach it (0 when none is open)
+///
+/// Locked while a context is torn down, so a level change never touches a
+/// freed context.
+static ACTIVE_CONTEXT: std::sync::Mutex<usize> = std::sync::Mutex::new(0);
+
+/// Forward a libusb log message to the `log` crate
+///
+/// libusb1-sys declares the message as `*mut c_void`; libusb passes a
+/// NUL-terminated `const char *`.
+extern "system" fn forward_libusb_log(
+    _ctx: *mut libusb1_sys::libusb_context,
+    level: libc::c_int,
+    message: *mut libc::c_void,
+) {
+    if message.is_null() {
+        return;
+    }
+    // SAFETY: libusb passes a NUL-terminated string valid for the call
+    let message = unsafe { CStr::from_ptr(message as *const libc::c_char) }.to_string_lossy();
+    let level = match level {
+        1 => log::Level::Error,
+        2 => log::Level::Warn,
+        3 => log::Level::Info,
+        _ => log::Level::Debug,
+    };
+    log::log!(target: "libusb", level, "{}", message.trim_end());
+}
+
+/// Route libusb's log output through the `log` crate (once per process)
+fn install_log_callback() {
+    static INSTALL: std::sync::Once = std::sync::Once::new();
+    INSTALL.call_once(|| {
+        // SAFETY: a global callback with a null context is valid before any
+        // context exists; forward_libusb_log lives for the whole process.
+        unsafe {
+            libusb1_sys::libusb_set_log_cb(
+                ptr::null_mut(),
+                Some(forward_libusb_log),
+                LIBUSB_LOG_CB_GLOBAL,
+            );
+        }
+    });
+}
+
+/// Apply a log level to new contexts and the open one
+pub fn apply_log_level(level: LibusbLogLevel) {
+    let active = crate::lock_or_recover(&ACTIVE_CONTEXT);
+    // SAFETY: a null context sets the default for new contexts; the active
+    // context stays valid while its registration lock is held.
+    unsafe {
+        libusb1_sys::libusb_set_option(ptr::null_mut(), LIBUSB_OPTION_LOG_LEVEL, level.as_raw());
+        if *active != 0 {
+            libusb1_sys::libusb_set_option(
+                *active as *mut libusb1_sys::libusb_context,
+                LIBUSB_OPTION_LOG_LEVEL,
+                level.as_raw(),
+            );
+        }
+    }
+}
+
+/// Whether the linked libusb reports hotplug support
+pub fn has_hotplug() -> bool {
+    // SAFETY: libusb_has_capability needs no context and only reads build flags
+    unsafe { libusb1_sys::libusb_has_capability(LIBUSB_CAP_HAS_HOTPLUG) != 0 }
+}
+
 /// Wrapper to allow sending a libusb context pointer across threads
 ///
 /// # Safety
@@ -155,6 +260,18 @@ impl Default for StopReason {
     }
 }
 
+impl From<u8> for StopReason {
+    fn from(value: u8) -> Self {
+        match value {
+            1 => StopReason::Normal,
+            2 => StopReason::DeviceUnplugged,
+            3 => StopReason::TransferError,
+            4 => StopReason::Timeout,
+            _ => StopReason::NotStopped,
+        }
+    }
+}
+
 impl From<i32> for TransferStatus {
     fn from(status: i32) -> Self {
         match status {
@@ -212,12 +329,86 @@ pub struct EndpointInfo {
     pub max_packet_size: u16,
     /// Transactions per microframe (1-3 for high-speed isochronous)
     pub transactions_per_microframe: u16,
+    /// SuperSpeed endpoint companion descriptor, if the device provided one
+    pub ss_companion: Option<SsEndpointCompanion>,
     /// Interface number this endpoint belongs to
     pub interface_number: u8,
     /// Alternate setting that enables this endpoint
     pub alt_setting: u8,
 }
 
+impl EndpointInfo {
+    /// Bytes the endpoint can deliver per service interval (one iso packet)
+    ///
+    /// High-bandwidth high-speed endpoints send up to 3 transactions per
+    /// microframe (e.g. 1024 x3 = 3072 bytes). SuperSpeed endpoints declare
+    /// their burst and multiplier in the companion descriptor instead, with the
+    /// total in `wBytesPerInterval`. Sizing packets by `max_packet_size` alone
+    /// truncates data and corrupts frames at higher resolutions.
+    pub fn effective_packet_size(&self) -> u16 {
+        effective_packet_size(
+            self.max_packet_size,
+            self.transactions_per_microframe,
+            self.ss_companion.as_ref(),
+        )
+    }
+}
+
+/// SuperSpeed endpoint companion descriptor (USB 3.x spec, Section 9.6.7)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SsEndpointCompanion {
+    /// Packets per burst minus one (0-15)
+    pub max_burst: u8,
+    /// Bursts per service interval minus one (0-2, isochronous only)
+    pub mult: u8,
+    /// Total bytes per service interval
+    pub bytes_per_interval: u16,
+}
+
+/// Descriptor type of the SuperSpeed endpoint companion descriptor
+const SS_ENDPOINT_COMPANION_TYPE: u8 = 0x30;
+
+/// Find and parse a SuperSpeed endpoint companion in an endpoint's extra bytes
+pub fn parse_ss_endpoint_companion(extra: &[u8]) -> Option<SsEndpointCompanion> {
+    let mut offset = 0;
+    while offset + 2 <= extra.len() {
+        let len = extra[offset] as usize;
+        if len < 2 || offset + len > extra.len() {
+            return None;
+        }
+        if extra[offset + 1] == SS_ENDPOINT_COMPANION_TYPE && len >= 6 {
+            let desc = &extra[offset..offset + len];
+            return Some(SsEndpointCompanion {
+                max_burst: desc[2],
+                mult: desc[3] & 0x03,
+                bytes_per_interval: u16::from_le_bytes([desc[4], desc[5]]),
+            });
+        }
+        offset += len;
+    }
+    None
+}
+
+/// Compute the per-packet buffer size for an isochronous endpoint
+///
+/// Uses `wBytesPerInterval` from the SuperSpeed companion if present, falling
+/// back to `(bMaxBurst + 1) * (Mult + 1) * max_packet_size` if it is zero.
+/// Otherwise applies the high-speed transactions-per-microframe multiplier.
+pub fn effective_packet_size(
+    max_packet_size: u16,
+    transactions_per_microframe: u16,
+    ss_companion: Option<&SsEndpointCompanion>,
+) -> u16 {
+    match ss_companion {
+        Some(c) if c.bytes_per_interval > 0 => c.bytes_per_interval,
+        Some(c) => {
+            let packets = (u32::from(c.max_burst) + 1) * (u32::from(c.mult) + 1);
+            (u32::from(max_packet_size) * packets).min(u32::from(u16::MAX)) as u16
+        }
+        None => max_packet_size.saturating_mul(transactions_per_microframe.max(1)),
+    }
+}
+
 /// Wrapper around libusb context
 pub struct LibusbContext {
     ctx: *mut libusb1_sys::libusb_context,
@@ -232,6 +423,8 @@ impl LibusbContext {
     /// Create a new libusb context configured for Android (no device discovery)
     pub fn new_android() -> Result<Self, LibusbError> {
         unsafe {
+            install_log_callback();
+
             // Set the no-discovery option before init
             // This is required on Android where we can't enumerate devices
             let ret =
@@ -248,7 +441,18 @@ impl LibusbContext {
                 return Err(LibusbError::from(ret));
             }
 
-            log::info!("libusb context initialized for Android");
+            libusb1_sys::libusb_set_option(
+                ctx,
+                LIBUSB_OPTION_LOG_LEVEL,
+                LibusbLogLevel::current().as_raw(),
+            );
+            *crate::lock_or_recover(&ACTIVE_CONTEXT) = ctx as usize;
+
+            log::info!(
+                "libusb {} context initialized for Android (hotplug: {})",
+                libusb_version(),
+                has_hotplug()
+            );
             Ok(LibusbContext { ctx })
         }
     }
@@ -308,6 +512,10 @@ impl LibusbContext {
 
 impl Drop for LibusbContext {
     fn drop(&mut self) {
+        let mut active = crate::lock_or_recover(&ACTIVE_CONTEXT);
+        if *active == self.ctx as usize {
+            *active = 0;
+        }
         unsafe {
             if !self.ctx.is_null() {
                 libusb1_sys::libusb_exit(self.ctx);
@@ -500,6 +708,18 @@ impl LibusbDeviceHandle {
         unsafe { libusb1_sys::libusb_get_device(self.handle) }
     }
 
+    /// Isochronous service interval of the bus in microseconds
+    ///
+    /// Low/full-speed devices are serviced once per 1 ms frame; high-speed and
+    /// SuperSpeed devices once per 125 us microframe.
+    pub fn service_interval_us(&self) -> u32 {
+        // LIBUSB_SPEED_LOW = 1, LIBUSB_SPEED_FULL = 2
+        match unsafe { libusb1_sys::libusb_get_device_speed(self.get_device()) } {
+            1 | 2 => 1000,
+            _ => 125,
+        }
+    }
+
     /// Get the device descriptor
     pub fn get_device_descriptor(&self) -> Result<DeviceDescriptor, LibusbError> {
         unsafe {
@@ -564,16 +784,13 @@ impl LibusbDeviceHandle {
                         if is_video_class && is_streaming { " [VIDEO STREAMING]" } else { "" }
                     );
 
-                    // Parse UVC format descriptors from class-specific extra bytes
+                    // Log UVC formats from the class-specific extra bytes
                     if is_video_class && is_streaming && altsetting.extra_length > 0 {
                         let extra_bytes = std::slice::from_raw_parts(
                             altsetting.extra,
                             altsetting.extra_length as usize,
                         );
-                        let formats = uvc::parse_format_descriptors(extra_bytes);
-                        for fmt in &formats {
-                            log::info!("  Available format: {:?}", fmt);
-                        }
+                        FormatCatalog::parse(extra_bytes);
                     }
 
                     // Iterate through endpoints
@@ -617,8 +834,19 @@ impl LibusbDeviceHandle {
                         let max_packet_size = ep.wMaxPacketSize & 0x7FF;
                         let transactions = ((ep.wMaxPacketSize >> 11) & 0x03) + 1;
 
+                        // SuperSpeed devices describe burst/mult in a companion
+                        // descriptor that libusb leaves in the endpoint's extra bytes
+                        let ss_companion = if !ep.extra.is_null() && ep.extra_length > 0 {
+                            parse_ss_endpoint_companion(std::slice::from_raw_parts(
+                                ep.extra,
+                                ep.extra_length as usize,
+                            ))
+                        } else {
+                            None
+                        };
+
                         log::info!(
-                            "  Endpoint 0x{:02x}: {} maxPacket={} x{} interval={}{}",
+                            "  Endpoint 0x{:02x}: {} maxPacket={} x{} interval={}{}{}",
                             ep_addr,
                             transfer_type_str,
                             max_packet_size,
@@ -628,7 +856,13 @@ impl LibusbDeviceHandle {
                                 format!(" sync={} usage={}", sync_type, usage_type)
                             } else {
                                 String::new()
-                            }
+                            },
+                            ss_companion.map_or(String::new(), |c| format!(
+                                " ss_burst={} ss_mult={} bytes_per_interval={}",
+                                c.max_burst + 1,
+                                c.mult + 1,
+                                c.bytes_per_interval
+                            ))
                         );
 
                         // Look for video streaming IN endpoint
@@ -643,6 +877,7 @@ impl LibusbDeviceHandle {
                                 transfer_type: TransferType::from_u8(transfer_type),
                                 max_packet_size,
                                 transactions_per_microframe: transactions,
+                                ss_companion,
                                 interface_number: altsetting.bInterfaceNumber,
                                 alt_setting: altsetting.bAlternateSetting,
                             };
@@ -674,9 +909,9 @@ impl LibusbDeviceHandle {
         }
     }
 
-    /// Get UVC format descriptors from the device.
-    /// Returns a list of all formats and their frame descriptors (resolutions).
-    pub fn get_format_descriptors(&self) -> Result<Vec<uvc::UvcFormatInfo>, LibusbError> {
+    /// Get the UVC format catalog of the device.
+    /// Lists all formats with their frame descriptors (resolutions and frame intervals).
+    pub fn get_format_catalog(&self) -> Result<FormatCatalog, LibusbError> {
         unsafe {
             let device = self.get_device();
             let mut cfg_desc: *const libusb1_sys::libusb_config_descriptor = std::ptr::null();
@@ -687,7 +922,7 @@ impl LibusbDeviceHandle {
             }
 
             let cfg = &*cfg_desc;
-            let mut all_formats = Vec::new();
+            let mut catalog = FormatCatalog::default();
 
             // Iterate through interfaces looking for video streaming interface
             for i in 0..cfg.bNumInterfaces as usize {
@@ -704,25 +939,100 @@ impl LibusbDeviceHandle {
                             altsetting.extra,
                             altsetting.extra_length as usize,
                         );
-                        let formats = uvc::parse_format_descriptors(extra_bytes);
-                        all_formats.extend(formats);
+                        catalog = FormatCatalog::parse(extra_bytes);
                         // Only parse from first matching interface
-                        if !all_formats.is_empty() {
+                        if !catalog.is_empty() {
                             break;
                         }
                     }
                 }
-                if !all_formats.is_empty() {
+                if !catalog.is_empty() {
                     break;
                 }
             }
 
             libusb1_sys::libusb_free_config_descriptor(cfg_desc as *mut _);
-            Ok(all_formats)
+            Ok(catalog)
+        }
+    }
+
+    /// Find the camera terminal and processing unit of the video control interface
+    ///
+    /// Returns empty units if the device has no video control interface.
+    pub fn get_control_units(&self) -> Result<ControlUnits, LibusbError> {
+        unsafe {
+            let device = self.get_device();
+            let mut cfg_desc: *const libusb1_sys::libusb_config_descriptor = std::ptr::null();
+
+            let ret = libusb1_sys::libusb_get_active_config_descriptor(device, &mut cfg_desc);
+            if ret < 0 {
+                return Err(LibusbError::from(ret));
+            }
+
+            let cfg = &*cfg_desc;
+            let mut units = ControlUnits::default();
+
+            'interfaces: for i in 0..cfg.bNumInterfaces as usize {
+                let interface = &*cfg.interface.add(i);
+
+                for j in 0..interface.num_altsetting as usize {
+                    let altsetting = &*interface.altsetting.add(j);
+
+                    let is_video_class = altsetting.bInterfaceClass == 0x0E;
+                    let is_control = altsetting.bInterfaceSubClass == 0x01;
+
+                    if is_video_class && is_control && altsetting.extra_length > 0 {
+                        let extra_bytes = std::slice::from_raw_parts(
+                            altsetting.extra,
+                            altsetting.extra_length as usize,
+                        );
+                        units = ControlUnits::parse(altsetting.bInterfaceNumber, extra_bytes);
+                        break 'interfaces;
+                    }
+                }
+            }
+
+            libusb1_sys::libusb_free_config_descriptor(cfg_desc as *mut _);
+            Ok(units)
         }
     }
 }
 
+impl ControlTransport for LibusbDeviceHandle {
+    fn control_transfer(
+        &self,
+        request_type: u8,
+        request: u8,
+        value: u16,
+        index: u16,
+        data: &mut [u8],
+        timeout_ms: u32,
+    ) -> uvc_controls::Result<usize> {
+        LibusbDeviceHandle::control_transfer(
+            self,
+            request_type,
+            request,
+            value,
+            index,
+            data,
+            timeout_ms,
+        )
+        .map_err(|e| UvcControlError::Transfer(e.to_string()))
+    }
+}
+
+impl StillTransport for LibusbDeviceHandle {
+    fn read_bulk(
+        &self,
+        endpoint: u8,
+        data: &mut [u8],
+        timeout_ms: u32,
+    ) -> uvc_controls::Result<usize> {
+        self.bulk_transfer(endpoint, data, timeout_ms)
+            .map_err(|e| UvcControlError::Transfer(e.to_string()))
+    }
+}
+
 impl Drop for LibusbDeviceHandle {
     fn drop(&mut self) {
         unsafe {
@@ -780,268 +1090,6 @@ pub mod uvc {
     /// Endpoint direction
     pub const USB_ENDPOINT_IN: u8 = 0x80;
     pub const USB_ENDPOINT_OUT: u8 = 0x00;
-
-    /// UVC Video Streaming Interface Descriptor Subtypes
-    pub const VS_UNDEFINED: u8 = 0x00;
-    pub const VS_INPUT_HEADER: u8 = 0x01;
-    pub const VS_OUTPUT_HEADER: u8 = 0x02;
-    pub const VS_STILL_IMAGE_FRAME: u8 = 0x03;
-    pub const VS_FORMAT_UNCOMPRESSED: u8 = 0x04;
-    pub const VS_FRAME_UNCOMPRESSED: u8 = 0x05;
-    pub const VS_FORMAT_MJPEG: u8 = 0x06;
-    pub const VS_FRAME_MJPEG: u8 = 0x07;
-    pub const VS_FORMAT_MPEG2TS: u8 = 0x0A;
-    pub const VS_FORMAT_DV: u8 = 0x0C;
-    pub const VS_COLORFORMAT: u8 = 0x0D;
-    pub const VS_FORMAT_FRAME_BASED: u8 = 0x10;
-    pub const VS_FRAME_FRAME_BASED: u8 = 0x11;
-    pub const VS_FORMAT_STREAM_BASED: u8 = 0x12;
-
-    /// UVC format GUID for YUY2
-    pub const YUY2_GUID: [u8; 16] = [
-        0x59, 0x55, 0x59, 0x32, // "YUY2"
-        0x00, 0x00, 0x10, 0x00, 0x80, 0x00, 0x00, 0xAA, 0x00, 0x38, 0x9B, 0x71,
-    ];
-
-    /// UVC format GUID for NV12 (semi-planar YUV420)
-    pub const NV12_GUID: [u8; 16] = [
-        0x4E, 0x56, 0x31, 0x32, // "NV12"
-        0x00, 0x00, 0x10, 0x00, 0x80, 0x00, 0x00, 0xAA, 0x00, 0x38, 0x9B, 0x71,
-    ];
-
-    /// UVC format GUID for I420 (planar YUV420, also known as IYUV)
-    pub const I420_GUID: [u8; 16] = [
-        0x49, 0x34, 0x32, 0x30, // "I420"
-        0x00, 0x00, 0x10, 0x00, 0x80, 0x00, 0x00, 0xAA, 0x00, 0x38, 0x9B, 0x71,
-    ];
-
-    /// UVC format GUID for YV12 (planar YUV420 with V before U)
-    pub const YV12_GUID: [u8; 16] = [
-        0x59, 0x56, 0x31, 0x32, // "YV12"
-        0x00, 0x00, 0x10, 0x00, 0x80, 0x00, 0x00, 0xAA, 0x00, 0x38, 0x9B, 0x71,
-    ];
-
-    /// UVC format GUID for UYVY (packed YUV422)
-    pub const UYVY_GUID: [u8; 16] = [
-        0x55, 0x59, 0x56, 0x59, // "UYVY"
-        0x00, 0x00, 0x10, 0x00, 0x80, 0x00, 0x00, 0xAA, 0x00, 0x38, 0x9B, 0x71,
-    ];
-
-    /// UVC format GUID for RGB24 (RGB888, 3 bytes per pixel, R-G-B order)
-    /// FourCC: "RGBT" or similar - this is the standard RGB24 GUID
-    pub const RGB24_GUID: [u8; 16] = [
-        0x7d, 0xeb, 0x36, 0xe4, 0x4f, 0x52, 0xce, 0x11, 0x9f, 0x53, 0x00, 0x20, 0xaf, 0x0b, 0xa7,
-        0x70,
-    ];
-
-    /// UVC format GUID for BGR24 (BGR888, 3 bytes per pixel, B-G-R order)
-    /// Some cameras report this GUID for BGR format
-    pub const BGR24_GUID: [u8; 16] = [
-        0xe4, 0x36, 0xeb, 0x7d, 0x52, 0x4f, 0x11, 0xce, 0x9f, 0x53, 0x00, 0x20, 0xaf, 0x0b, 0xa7,
-        0x70,
-    ];
-
-    /// Parsed UVC frame descriptor (resolution info)
-    #[derive(Debug, Clone, Copy)]
-    pub struct UvcFrameInfo {
-        pub frame_index: u8,
-        pub width: u16,
-        pub height: u16,
-        pub max_frame_size: u32,
-    }
-
-    /// Parsed UVC format information
-    #[derive(Debug, Clone)]
-    pub struct UvcFormatInfo {
-        pub format_index: u8,
-        pub format_type: UvcFormatType,
-        pub num_frame_descriptors: u8,
-        pub guid: Option<[u8; 16]>,
-        pub bits_per_pixel: Option<u8>,
-        pub frames: Vec<UvcFrameInfo>,
-    }
-
-    /// UVC format types
-    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
-    pub enum UvcFormatType {
-        Mjpeg,
-        Uncompressed,
-        UncompressedRgb, // RGB24/BGR24 - detected via GUID
-        FrameBased,
-        Unknown(u8),
-    }
-
-    /// Parse UVC class-specific descriptors from interface extra bytes
-    pub fn parse_format_descriptors(extra: &[u8]) -> Vec<UvcFormatInfo> {
-        let mut formats = Vec::new();
-        let mut offset = 0;
-
-        while offset + 2 < extra.len() {
-            let desc_len = extra[offset] as usize;
-            if desc_len < 3 || offset + desc_len > extra.len() {
-                break;
-            }
-
-            let desc_type = extra[offset + 1];
-            let desc_subtype = extra[offset + 2];
-
-            // Check if this is a class-specific Video Streaming descriptor (0x24 = CS_INTERFACE)
-            if desc_type == 0x24 {
-                match desc_subtype {
-                    VS_FORMAT_MJPEG if desc_len >= 11 => {
-                        let format_index = extra[offset + 3];
-                        let num_frame_descs = extra[offset + 4];
-                        log::info!(
-                            "Found MJPEG format: index={}, frame_descriptors={}",
-                            format_index,
-                            num_frame_descs
-                        );
-                        formats.push(UvcFormatInfo {
-                            format_index,
-                            format_type: UvcFormatType::Mjpeg,
-                            num_frame_descriptors: num_frame_descs,
-                            guid: None,
-                            bits_per_pixel: None,
-                            frames: Vec::new(),
-                        });
-                    }
-                    VS_FORMAT_UNCOMPRESSED if desc_len >= 27 => {
-                        let format_index = extra[offset + 3];
-                        let num_frame_descs = extra[offset + 4];
-                        let mut guid = [0u8; 16];
-                        guid.copy_from_slice(&extra[offset + 5..offset + 21]);
-                        let bits_per_pixel = extra[offset + 21];
-
-                        // Try to identify the format from GUID
-                        let format_name = if guid == YUY2_GUID {
-                            "YUY2"
-                        } else if guid == UYVY_GUID {
-                            "UYVY"
-                        } else if guid == NV12_GUID {
-                            "NV12"
-                        } else if guid == I420_GUID {
-                            "I420"
-                        } else if guid == YV12_GUID {
-                            "YV12"
-                        } else if guid == RGB24_GUID {
-                            "RGB24"
-                        } else if guid == BGR24_GUID {
-                            "BGR24"
-                        } else {
-                            "Unknown"
-                        };
-
-                        // Determine if this is an RGB format
-                        let is_rgb = guid == RGB24_GUID || guid == BGR24_GUID;
-                        let format_type = if is_rgb {
-                            UvcFormatType::UncompressedRgb
-                        } else {
-                            UvcFormatType::Uncompressed
-                        };
-
-                        log::info!(
-                            "Found Uncompressed format: index={}, type={}, bpp={}, frame_descriptors={}",
-                            format_index, format_name, bits_per_pixel, num_frame_descs
-                        );
-                        log::debug!("  GUID: {:02x?}", &guid);
-
-                        formats.push(UvcFormatInfo {
-                            format_index,
-                            format_type,
-                            num_frame_descriptors: num_frame_descs,
-                            guid: Some(guid),
-                            bits_per_pixel: Some(bits_per_pixel),
-                            frames: Vec::new(),
-                        });
-                    }
-                    VS_FORMAT_FRAME_BASED if desc_len >= 28 => {
-                        let format_index = extra[offset + 3];
-                        let num_frame_descs = extra[offset + 4];
-                        let mut guid = [0u8; 16];
-                        guid.copy_from_slice(&extra[offset + 5..offset + 21]);
-                        let bits_per_pixel = extra[offset + 21];
-
-                        log::info!(
-                            "Found Frame-Based format: index={}, bpp={}, frame_descriptors={}",
-                            format_index,
-                            bits_per_pixel,
-                            num_frame_descs
-                        );
-                        log::debug!("  GUID: {:02x?}", &guid);
-
-                        formats.push(UvcFormatInfo {
-                            format_index,
-                            format_type: UvcFormatType::FrameBased,
-                            num_frame_descriptors: num_frame_descs,
-                            guid: Some(guid),
-                            bits_per_pixel: Some(bits_per_pixel),
-                            frames: Vec::new(),
-                        });
-                    }
-                    VS_INPUT_HEADER => {
-                        if desc_len >= 13 {
-                            let num_formats = extra[offset + 3];
-                            log::info!("VS Input Header: {} format(s) available", num_formats);
-                        }
-                    }
-                    VS_FRAME_UNCOMPRESSED | VS_FRAME_MJPEG => {
-                        // Parse frame descriptor to get resolution info
-                        // Offset 3: frame index
-                        // Offset 5-6: wWidth (little-endian)
-                        // Offset 7-8: wHeight (little-endian)
-                        // Offset 9-12: dwMinBitRate
-                        // Offset 13-16: dwMaxBitRate
-                        // Offset 17-20: dwMaxVideoFrameBufferSize
-                        if desc_len >= 21 {
-                            let frame_index = extra[offset + 3];
-                            let width = u16::from_le_bytes([extra[offset + 5], extra[offset + 6]]);
-                            let height = u16::from_le_bytes([extra[offset + 7], extra[offset + 8]]);
-                            let max_frame_size = u32::from_le_bytes([
-                                extra[offset + 17],
-                                extra[offset + 18],
-                                extra[offset + 19],
-                                extra[offset + 20],
-                            ]);
-                            let format_type_name = if desc_subtype == VS_FRAME_UNCOMPRESSED {
-                                "Uncompressed"
-                            } else {
-                                "MJPEG"
-                            };
-                            log::info!(
-                                "  Frame {}: {}x{} ({}) max_size={}",
-                                frame_index,
-                                width,
-                                height,
-                                format_type_name,
-                                max_frame_size
-                            );
-
-                            // Add this frame to the most recently added format
-                            if let Some(format) = formats.last_mut() {
-                                format.frames.push(UvcFrameInfo {
-                                    frame_index,
-                                    width,
-                                    height,
-                                    max_frame_size,
-                                });
-                            }
-                        }
-                    }
-                    _ => {
-                        log::debug!(
-                            "UVC VS descriptor subtype {:02x}, len={}",
-                            desc_subtype,
-                            desc_len
-                        );
-                    }
-                }
-            }
-
-            offset += desc_len;
-        }
-
-        formats
-    }
 }
 
 // ============================================================================
@@ -1056,7 +1104,8 @@ pub mod uvc {
 pub struct IsoTransferConfig {
     /// Number of isochronous packets per transfer.
     /// Higher values = better throughput, lower values = lower latency.
-    /// Default: 32 (balanced for USB endoscopes)
+    /// Default: 32 (balanced for USB endoscopes), used when the frame interval
+    /// is unknown; see [`packets_per_transfer`].
     pub packets_per_transfer: i32,
 
     /// Number of transfers to keep in flight simultaneously.
@@ -1087,6 +1136,49 @@ const ISO_CONFIG: IsoTransferConfig = IsoTransferConfig {
     event_timeout_ms: 100,
 };
 
+/// Fewest packets per transfer (keeps callback overhead bounded)
+const MIN_PACKETS_PER_TRANSFER: i32 = 8;
+
+/// Most packets per transfer (Linux usbfs rejects URBs with more than 128)
+const MAX_PACKETS_PER_TRANSFER: i32 = 128;
+
+/// Choose the number of iso packets per transfer so a transfer spans about one frame
+///
+/// The time-based count is the number of service intervals in one frame
+/// interval. The size-based count is the number of full packets needed to carry
+/// the largest frame. The smaller of the two is used, so small or fast frames
+/// are delivered with low latency, while large frames at high resolutions need
+/// fewer callbacks. Falls back to the fixed default if the frame interval is
+/// unknown.
+///
+/// # Arguments
+/// * `frame_interval` - Negotiated `dwFrameInterval` in 100 ns units
+/// * `service_interval_us` - Bus service interval (125 for high speed)
+/// * `packet_size` - Effective bytes per iso packet
+/// * `max_frame_size` - Largest expected frame in bytes (0 if unknown)
+pub fn packets_per_transfer(
+    frame_interval: u32,
+    service_interval_us: u32,
+    packet_size: u16,
+    max_frame_size: usize,
+) -> i32 {
+    if frame_interval == 0 || service_interval_us == 0 {
+        return ISO_CONFIG.packets_per_transfer;
+    }
+
+    let by_time = u64::from(frame_interval) / (u64::from(service_interval_us) * 10);
+    let by_size = if packet_size > 0 && max_frame_size > 0 {
+        (max_frame_size as u64).div_ceil(u64::from(packet_size))
+    } else {
+        by_time
+    };
+
+    by_time.min(by_size).clamp(
+        MIN_PACKETS_PER_TRANSFER as u64,
+        MAX_PACKETS_PER_TRANSFER as u64,
+    ) as i32
+}
+
 /// Known YUY2 frame sizes for common resolutions
 ///
 /// Format: (frame_size_bytes, width, height)
@@ -1139,15 +1231,43 @@ struct SharedFrameState {
     pending_urbs: BTreeMap<u64, UrbPayload>,
     /// Next expected URB sequence number for in-order processing
     next_expected_sequence: u64,
+    /// When the first payload of the frame in the buffer arrived
+    frame_started: Option<std::time::Instant>,
+    /// A payload of the frame in the buffer carried the still image bit
+    frame_still: bool,
+    /// Uncompressed frames are split by content (`content-boundaries` quirk)
+    content: Option<ContentSplitter>,
+}
+
+impl SharedFrameState {
+    /// Empty state for frames of `expected_frame_size` bytes
+    fn new(expected_frame_size: usize) -> Self {
+        Self {
+            frame_buffer: Vec::with_capacity(expected_frame_size + 1024), // Frame size + margin
+            last_frame_id: None,
+            synced: false,
+            is_mjpeg: None, // Will be detected from first frame data
+            expected_frame_size,
+            validation_warning_count: 0,
+            pending_urbs: BTreeMap::new(),
+            next_expected_sequence: 0,
+            frame_started: None,
+            frame_still: false,
+            content: None,
+        }
+    }
 }
 
 // Forward declaration for capture module
-use crate::capture::CaptureState;
+use crate::capture::{CaptureState, IsoPacketRecord};
+use crate::frame_boundary::ContentSplitter;
+use crate::frame_broadcast::{self, FrameCursor, FrameNotes, FrameSender};
+use crate::stats::StreamStats;
 
-/// Context passed to the isochronous transfer callback
+/// Context passed to the isochronous transfer callback (and wrapped by the bulk one)
 struct IsoCallbackContext {
-    /// Channel to send received frame data
-    frame_sender: std::sync::mpsc::Sender<Vec<u8>>,
+    /// Broadcast to publish received frame data
+    frame_sender: FrameSender,
     /// Flag to signal when streaming should stop
     stop_flag: Arc<AtomicBool>,
     /// Reason why streaming stopped
@@ -1160,12 +1280,16 @@ struct IsoCallbackContext {
     expected_frame_size: usize,
     /// Optional capture state for recording raw packets (E2E testing)
     capture_state: Option<Arc<CaptureState>>,
-    /// Frame validation level
-    validation_level: crate::ValidationLevel,
+    /// Frame assembly statistics (`get_stream_stats`)
+    stats: Arc<StreamStats>,
+    /// Frame validation level, shared with `set_validation_level`
+    validation_level: Arc<std::sync::Mutex<crate::ValidationLevel>>,
     /// Frame width in pixels (for validation)
     frame_width: usize,
     /// Frame height in pixels (for validation)
     frame_height: usize,
+    /// Pixel format of uncompressed frames (for validation)
+    pixel_format: crate::PixelFormat,
     /// Transfer index (0 to ISO_CONFIG.num_transfers-1) for this transfer
     transfer_index: usize,
     /// Global sequence counter shared across all transfers for ordering
@@ -1193,7 +1317,7 @@ impl std::fmt::Display for FrameTrigger {
     }
 }
 
-/// Emits a complete MJPEG frame to the frame receiver.
+/// Emits a complete MJPEG frame to the frame consumers.
 ///
 /// Takes the entire frame buffer and sends it if non-empty.
 /// The buffer is cleared after emission regardless of success.
@@ -1203,26 +1327,57 @@ fn emit_mjpeg_frame(
     trigger: FrameTrigger,
 ) {
     let frame = std::mem::take(&mut state.frame_buffer);
+    let assembly = state.frame_started.take().map(|t| t.elapsed());
+    let still_image = std::mem::take(&mut state.frame_still);
+    if let Some(capture_state) = &context.capture_state {
+        // A JPEG whose headers don't parse or that lacks EOI is malformed
+        capture_state
+            .end_frame(!frame.is_empty() && !crate::frame_assembler::is_complete_jpeg(&frame));
+    }
     if !frame.is_empty() {
         log::info!(
             "Complete MJPEG frame: {} bytes (trigger: {})",
             frame.len(),
             trigger
         );
-        let _ = context.frame_sender.send(frame);
+        context.stats.record_frame(frame.len(), assembly);
+        context.frame_sender.send_with_notes(
+            frame,
+            FrameNotes {
+                still_image,
+                ..Default::default()
+            },
+        );
+    }
+}
+
+/// Throws away the partial frame in the buffer, counting it as dropped.
+fn discard_frame(state: &mut SharedFrameState, context: &IsoCallbackContext) {
+    if !state.frame_buffer.is_empty() {
+        context.stats.record_dropped();
+    }
+    if let Some(capture_state) = &context.capture_state {
+        capture_state.end_frame(!state.frame_buffer.is_empty());
     }
+    state.frame_buffer.clear();
+    state.frame_started = None;
+    state.frame_still = false;
 }
 
-/// Emits a complete YUY2 frame to the frame receiver with validation.
+/// Takes the next complete YUY2 frame off the front of the buffer.
 ///
-/// Drains exactly `expected_size` bytes from the buffer, validates the frame,
-/// and sends it. Overflow bytes are preserved in the buffer.
-fn emit_yuy2_frame(state: &mut SharedFrameState, context: &IsoCallbackContext) {
+/// Frames are `expected_size` bytes, or end at a content boundary on devices
+/// with the `content-boundaries` quirk. Overflow bytes are preserved in the
+/// buffer.
+fn take_yuy2_frame(state: &mut SharedFrameState) -> Option<Vec<u8>> {
     let expected_size = state.expected_frame_size;
-    let buffer_size = state.frame_buffer.len();
+    if let Some(content) = state.content.as_mut() {
+        return content.split(&mut state.frame_buffer, expected_size);
+    }
 
+    let buffer_size = state.frame_buffer.len();
     if buffer_size < expected_size {
-        return;
+        return None;
     }
 
     let overflow = buffer_size - expected_size;
@@ -1234,18 +1389,35 @@ fn emit_yuy2_frame(state: &mut SharedFrameState, context: &IsoCallbackContext) {
         );
     }
 
-    let frame: Vec<u8> = state.frame_buffer.drain(..expected_size).collect();
+    Some(state.frame_buffer.drain(..expected_size).collect())
+}
+
+/// Emits a complete YUY2 frame to the frame consumers with validation.
+///
+/// Validates the frame taken by `take_yuy2_frame` and sends it.
+fn emit_yuy2_frame(state: &mut SharedFrameState, context: &IsoCallbackContext, frame: Vec<u8>) {
+    let assembly = state.frame_started.map(|t| t.elapsed());
+    // Overflow bytes are attributed to the next frame without the bit
+    let still_image = std::mem::take(&mut state.frame_still);
+    // Overflow bytes start the next frame
+    state.frame_started = (!state.frame_buffer.is_empty()).then(std::time::Instant::now);
 
     // Validate frame for corruption
-    let validation = crate::frame_validation::validate_yuy2_frame(
+    let validation = crate::frame_validation::validate_frame(
         &frame,
         context.frame_width,
         context.frame_height,
+        context.pixel_format,
         context.expected_frame_size,
-        context.validation_level,
+        *crate::lock_or_recover(&context.validation_level),
     );
 
+    context.stats.record_frame(frame.len(), assembly);
+    if let Some(capture_state) = &context.capture_state {
+        capture_state.end_frame(!validation.valid);
+    }
     if !validation.valid {
+        context.stats.record_corrupt();
         state.validation_warning_count += 1;
         if state.validation_warning_count <= 10 || state.validation_warning_count % 100 == 0 {
             log::warn!(
@@ -1259,7 +1431,14 @@ fn emit_yuy2_frame(state: &mut SharedFrameState, context: &IsoCallbackContext) {
         }
     }
 
-    let _ = context.frame_sender.send(frame);
+    // Consumers reuse the verdict instead of validating again
+    context.frame_sender.send_with_notes(
+        frame,
+        FrameNotes {
+            validation: Some(Arc::new(validation)),
+            still_image,
+        },
+    );
 }
 
 /// Manages isochronous USB transfers for video streaming
@@ -1272,6 +1451,8 @@ pub struct IsochronousStream {
     endpoint: u8,
     /// Maximum packet size
     max_packet_size: u16,
+    /// Number of iso packets in each transfer
+    packets_per_transfer: i32,
     /// Pre-allocated transfer structures
     transfers: Vec<*mut libusb1_sys::libusb_transfer>,
     /// Buffers for each transfer
@@ -1282,8 +1463,8 @@ pub struct IsochronousStream {
     pub stop_flag: Arc<AtomicBool>,
     /// Reason why streaming stopped (public for checking after stop)
     pub stop_reason: Arc<AtomicU8>,
-    /// Receiver for completed frames
-    frame_receiver: Option<std::sync::mpsc::Receiver<Vec<u8>>>,
+    /// Broadcast of completed frames (consumers get cursors via `subscribe`)
+    frame_sender: FrameSender,
 }
 
 impl IsochronousStream {
@@ -1298,23 +1479,32 @@ impl IsochronousStream {
     /// * `handle` - libusb device handle pointer
     /// * `endpoint` - Endpoint address
     /// * `max_packet_size` - Maximum packet size for the endpoint
+    /// * `packets_per_transfer` - Iso packets per transfer (see [`packets_per_transfer`])
     /// * `expected_frame_size` - Expected frame size from descriptor (e.g., 614400 for 640x480 YUY2)
     /// * `capture_state` - Optional capture state for recording raw packets (E2E testing)
-    /// * `validation_level` - Frame corruption validation strictness
+    /// * `stats` - Frame assembly statistics to update
+    /// * `frame_capacity` - Frames the broadcast keeps for slow consumers
+    /// * `validation_level` - Frame corruption validation strictness (read per frame)
     /// * `frame_width` - Frame width in pixels (for validation)
     /// * `frame_height` - Frame height in pixels (for validation)
+    /// * `pixel_format` - Pixel format of uncompressed frames (for validation)
     pub unsafe fn new(
         ctx: *mut libusb1_sys::libusb_context,
         handle: *mut libusb1_sys::libusb_device_handle,
         endpoint: u8,
         max_packet_size: u16,
+        packets_per_transfer: i32,
         expected_frame_size: usize,
         capture_state: Option<Arc<CaptureState>>,
-        validation_level: crate::ValidationLevel,
+        stats: Arc<StreamStats>,
+        frame_capacity: usize,
+        validation_level: Arc<std::sync::Mutex<crate::ValidationLevel>>,
         frame_width: usize,
         frame_height: usize,
+        pixel_format: crate::PixelFormat,
     ) -> Result<Self, LibusbError> {
-        let (frame_sender, frame_receiver) = std::sync::mpsc::channel();
+        let (frame_sender, _) =
+            frame_broadcast::channel_with_metrics(frame_capacity, stats.frame_channel());
         let stop_flag = Arc::new(AtomicBool::new(false));
         let stop_reason = Arc::new(AtomicU8::new(StopReason::NotStopped as u8));
 
@@ -1331,21 +1521,12 @@ impl IsochronousStream {
         );
 
         // Create shared state for frame accumulation (shared across all transfers)
-        let shared_state = Arc::new(std::sync::Mutex::new(SharedFrameState {
-            frame_buffer: Vec::with_capacity(frame_size + 1024), // Frame size + margin
-            last_frame_id: None,
-            synced: false,
-            is_mjpeg: None, // Will be detected from first frame data
-            expected_frame_size: frame_size,
-            validation_warning_count: 0,
-            pending_urbs: BTreeMap::new(),
-            next_expected_sequence: 0,
-        }));
+        let shared_state = Arc::new(std::sync::Mutex::new(SharedFrameState::new(frame_size)));
 
         // Global sequence counter for URB ordering (shared across all transfers)
         let sequence_counter = Arc::new(AtomicU64::new(0));
 
-        let buffer_size = (max_packet_size as usize) * (ISO_CONFIG.packets_per_transfer as usize);
+        let buffer_size = (max_packet_size as usize) * (packets_per_transfer as usize);
 
         let mut transfers = Vec::with_capacity(ISO_CONFIG.num_transfers);
         let mut buffers = Vec::with_capacity(ISO_CONFIG.num_transfers);
@@ -1353,7 +1534,7 @@ impl IsochronousStream {
 
         for i in 0..ISO_CONFIG.num_transfers {
             // Allocate transfer with space for ISO packet descriptors
-            let transfer = libusb1_sys::libusb_alloc_transfer(ISO_CONFIG.packets_per_transfer);
+            let transfer = libusb1_sys::libusb_alloc_transfer(packets_per_transfer);
             if transfer.is_null() {
                 // Clean up already allocated transfers
                 for t in &transfers {
@@ -1375,9 +1556,11 @@ impl IsochronousStream {
                 max_packet_size,
                 expected_frame_size: frame_size,
                 capture_state: capture_state.clone(),
-                validation_level,
+                stats: Arc::clone(&stats),
+                validation_level: Arc::clone(&validation_level),
                 frame_width,
                 frame_height,
+                pixel_format,
                 transfer_index: i,
                 sequence_counter: Arc::clone(&sequence_counter),
             });
@@ -1390,7 +1573,7 @@ impl IsochronousStream {
         log::info!(
             "Allocated {} isochronous transfers, {} packets each, {} bytes per packet (buffer {})",
             ISO_CONFIG.num_transfers,
-            ISO_CONFIG.packets_per_transfer,
+            packets_per_transfer,
             max_packet_size,
             buffer_size
         );
@@ -1400,15 +1583,28 @@ impl IsochronousStream {
             handle,
             endpoint,
             max_packet_size,
+            packets_per_transfer,
             transfers,
             buffers,
             contexts,
             stop_flag,
             stop_reason,
-            frame_receiver: Some(frame_receiver),
+            frame_sender,
         })
     }
 
+    /// Split uncompressed frames where the image content jumps
+    ///
+    /// For devices with the `content-boundaries` quirk (see
+    /// [`crate::frame_boundary`]); `row_stride` is the length of one image
+    /// row in bytes, `None` turns it off. Call before `start`.
+    pub fn set_content_boundaries(&self, row_stride: Option<usize>) {
+        if let Some(context) = self.contexts.first() {
+            crate::lock_or_recover(&context.shared_state).content =
+                row_stride.and_then(ContentSplitter::new);
+        }
+    }
+
     /// Start streaming by submitting all transfers
     pub fn start(&mut self) -> Result<(), LibusbError> {
         log::info!(
@@ -1439,7 +1635,7 @@ impl IsochronousStream {
             (*transfer).timeout = 0; // No timeout for isochronous
             (*transfer).length = buffer_len;
             (*transfer).buffer = buffer;
-            (*transfer).num_iso_packets = ISO_CONFIG.packets_per_transfer;
+            (*transfer).num_iso_packets = self.packets_per_transfer;
             (*transfer).callback = iso_transfer_callback;
             (*transfer).user_data = context_ptr as *mut libc::c_void;
 
@@ -1458,9 +1654,12 @@ impl IsochronousStream {
         }
     }
 
-    /// Take the frame receiver (can only be called once)
-    pub fn take_frame_receiver(&mut self) -> Option<std::sync::mpsc::Receiver<Vec<u8>>> {
-        self.frame_receiver.take()
+    /// New frame consumer, reading from the next completed frame on
+    ///
+    /// Each consumer reads at its own pace; one that falls behind skips
+    /// frames rather than holding up the others.
+    pub fn subscribe(&self) -> FrameCursor {
+        self.frame_sender.subscribe()
     }
 
     /// Run the event loop to process USB transfers
@@ -1503,14 +1702,7 @@ impl IsochronousStream {
 
     /// Get the reason why streaming stopped
     pub fn get_stop_reason(&self) -> StopReason {
-        let reason_u8 = self.stop_reason.load(Ordering::Relaxed);
-        match reason_u8 {
-            1 => StopReason::Normal,
-            2 => StopReason::DeviceUnplugged,
-            3 => StopReason::TransferError,
-            4 => StopReason::Timeout,
-            _ => StopReason::NotStopped,
-        }
+        StopReason::from(self.stop_reason.load(Ordering::Relaxed))
     }
 
     /// Set the stop reason (for use by streaming loops)
@@ -1604,16 +1796,10 @@ unsafe fn iso_transfer_callback_inner(transfer: *mut libusb1_sys::libusb_transfe
             // Lock shared state FIRST to check format before extracting payloads
             // This allows us to skip header validation for YUY2 format, avoiding
             // false positives where pixel data matches header patterns.
-            let mut state = match context.shared_state.lock() {
-                Ok(guard) => guard,
-                Err(poisoned) => {
-                    log::error!("Shared state mutex poisoned, recovering");
-                    poisoned.into_inner()
-                }
-            };
+            let mut state = crate::lock_or_recover(&context.shared_state);
 
             // Extract payload from this URB (always parse UVC headers per spec)
-            let payload = extract_urb_payloads(xfr, context.max_packet_size, context);
+            let payload = extract_urb_payloads(xfr, context.max_packet_size, context, sequence);
 
             log::trace!(
                 "URB completed: transfer_index={}, sequence={}, payload_bytes={}",
@@ -1660,6 +1846,7 @@ unsafe fn iso_transfer_callback_inner(transfer: *mut libusb1_sys::libusb_transfe
         }
         TransferStatus::Error | TransferStatus::Stall | TransferStatus::Overflow => {
             log::warn!("Transfer error: {:?}", status);
+            context.stats.record_usb_errors(1);
             context
                 .stop_reason
                 .store(StopReason::TransferError as u8, Ordering::Relaxed);
@@ -1692,6 +1879,8 @@ struct PacketMeta {
     frame_id: bool,
     /// Whether this packet had an error flag
     error: bool,
+    /// Still image flag from UVC header (the packet belongs to a method 2 still)
+    still_image: bool,
     /// Whether this packet had a valid UVC header
     had_header: bool,
     /// Number of payload bytes from this packet
@@ -1715,6 +1904,7 @@ struct PacketMeta {
 /// * `xfr` - The completed USB transfer
 /// * `max_packet_size` - Maximum packet size for this endpoint
 /// * `context` - Callback context with capture state
+/// * `sequence` - Sequence number of this URB, stored in capture transfer records
 ///
 /// # Safety
 /// The transfer pointer must be valid.
@@ -1722,10 +1912,12 @@ unsafe fn extract_urb_payloads(
     xfr: &mut libusb1_sys::libusb_transfer,
     max_packet_size: u16,
     context: &IsoCallbackContext,
+    sequence: u64,
 ) -> UrbPayload {
     let num_packets = xfr.num_iso_packets as usize;
     let mut data = Vec::with_capacity(num_packets * max_packet_size as usize);
     let mut packets = Vec::with_capacity(num_packets);
+    let mut errors = 0;
 
     for i in 0..num_packets {
         let pkt_desc_ptr = xfr.iso_packet_desc.as_ptr().add(i);
@@ -1733,69 +1925,58 @@ unsafe fn extract_urb_payloads(
 
         let pkt_status = TransferStatus::from(pkt_desc.status);
         let actual_length = pkt_desc.actual_length as usize;
-
-        if pkt_status != TransferStatus::Completed || actual_length == 0 {
-            continue;
-        }
+        let usable = pkt_status == TransferStatus::Completed && actual_length > 0;
 
         // Get packet data
         let offset = i * (max_packet_size as usize);
-        let pkt_data = std::slice::from_raw_parts(xfr.buffer.add(offset), actual_length);
+        let pkt_data = if usable {
+            std::slice::from_raw_parts(xfr.buffer.add(offset), actual_length)
+        } else {
+            &[]
+        };
 
-        // Record raw packet for E2E testing (before any parsing)
+        // Record raw packet for E2E testing (before any parsing), along with
+        // the descriptor's status and lengths so errored packets are visible too.
         // Fast path: atomic check avoids allocation when not capturing
         if let Some(capture_state) = &context.capture_state {
-            if capture_state.is_capturing() {
-                // Endpoint is stored in the transfer's endpoint field
-                capture_state.add_packet(pkt_data, xfr.endpoint);
+            if capture_state.wants_packets() {
+                let record = IsoPacketRecord {
+                    transfer_sequence: sequence,
+                    packet_index: i as u16,
+                    status: pkt_desc.status,
+                    length: pkt_desc.length,
+                    actual_length: pkt_desc.actual_length,
+                    captured_index: None,
+                };
+                capture_state.record_iso_packet(record, usable.then_some(pkt_data), xfr.endpoint);
             }
         }
 
-        // Per UVC 1.5 spec Section 2.4.3.3: Every payload transfer starts with a header.
-        // Headers are 2-12 bytes depending on PTS/SCR flags. The header length is in byte 0.
-        // We MUST always parse and strip headers regardless of video format (MJPEG, YUY2, etc.)
-        // because header bytes included as pixel data cause row misalignment and visual artifacts.
-        let validated = validate_uvc_header(pkt_data);
-        let (is_uvc_header, header_len) = (validated.is_some(), validated.unwrap_or(0));
-
-        // Extract flags from header (if present)
-        let (end_of_frame, frame_id, error) = if is_uvc_header {
-            let header_flags = pkt_data[1];
-            (
-                (header_flags & 0x02) != 0, // EOF
-                (header_flags & 0x01) != 0, // FID
-                (header_flags & 0x40) != 0, // Error
-            )
-        } else {
-            (false, false, false)
-        };
-
-        // Extract payload (skip header if present)
-        let payload = if is_uvc_header && header_len <= actual_length {
-            &pkt_data[header_len..]
-        } else {
-            pkt_data
-        };
-
-        // Skip zero-filled payloads
-        let payload_to_add = if payload.len() > 8 && payload[0..8].iter().all(|&b| b == 0) {
-            &[]
-        } else {
-            payload
-        };
+        if !usable {
+            if pkt_status != TransferStatus::Completed {
+                errors += 1;
+            }
+            continue;
+        }
 
-        let payload_len = payload_to_add.len();
-        data.extend_from_slice(payload_to_add);
+        // Headers are always stripped regardless of video format (see parse_uvc_payload)
+        let info = parse_uvc_payload(pkt_data);
+        let payload_len = info.payload.len();
+        data.extend_from_slice(info.payload);
 
         packets.push(PacketMeta {
-            end_of_frame,
-            frame_id,
-            error,
-            had_header: is_uvc_header,
+            end_of_frame: info.end_of_frame,
+            frame_id: info.frame_id,
+            error: info.error,
+            still_image: info.still_image,
+            had_header: info.has_header,
             payload_len,
         });
     }
 
+    if errors > 0 {
+        context.stats.record_usb_errors(errors);
+    }
     UrbPayload { data, packets }
 }
 
@@ -1815,7 +1996,7 @@ fn process_urb_payload_in_order(
             let is_mjpeg = state.is_mjpeg.unwrap_or(false);
             if is_mjpeg {
                 log::warn!("UVC error in MJPEG packet - clearing buffer");
-                state.frame_buffer.clear();
+                discard_frame(state, context);
                 state.synced = false;
             }
             data_offset += pkt.payload_len;
@@ -1845,8 +2026,9 @@ fn process_urb_payload_in_order(
                     if is_mjpeg {
                         if state.synced && is_jpeg_data(&state.frame_buffer) {
                             emit_mjpeg_frame(state, context, FrameTrigger::FidToggle);
+                        } else {
+                            discard_frame(state, context);
                         }
-                        state.frame_buffer.clear();
                     }
                     // For YUY2: FID toggle is unreliable, don't use for frame boundaries
                     state.synced = true;
@@ -1855,8 +2037,10 @@ fn process_urb_payload_in_order(
             state.last_frame_id = Some(pkt.frame_id);
         }
 
-        // Only accumulate data if we're synced
-        if !state.synced {
+        // Only accumulate data if we're synced (content boundaries sync
+        // without FID, which these devices get wrong)
+        let by_content = !is_mjpeg && state.content.is_some();
+        if !state.synced && !by_content {
             data_offset += pkt.payload_len;
             continue;
         }
@@ -1864,21 +2048,28 @@ fn process_urb_payload_in_order(
         // Add payload data to frame buffer
         if pkt.payload_len > 0 {
             let payload_slice = &payload.data[data_offset..data_offset + pkt.payload_len];
+            if state.frame_buffer.is_empty() {
+                state.frame_started = Some(std::time::Instant::now());
+            }
             state.frame_buffer.extend_from_slice(payload_slice);
+            state.frame_still |= pkt.still_image;
         }
         data_offset += pkt.payload_len;
 
-        // For YUY2: Check if buffer has reached expected frame size
-        if !is_mjpeg && state.frame_buffer.len() >= state.expected_frame_size {
-            emit_yuy2_frame(state, context);
+        // For YUY2: Check if buffer holds a complete frame
+        if !is_mjpeg {
+            while let Some(frame) = take_yuy2_frame(state) {
+                emit_yuy2_frame(state, context, frame);
+            }
         }
 
         // For MJPEG: EOF is reliable
         if is_mjpeg && pkt.end_of_frame && !state.frame_buffer.is_empty() {
             if is_jpeg_data(&state.frame_buffer) {
                 emit_mjpeg_frame(state, context, FrameTrigger::EofMarker);
+            } else {
+                discard_frame(state, context);
             }
-            state.frame_buffer.clear();
         }
     }
 }
@@ -1898,9 +2089,536 @@ fn process_pending_urbs_in_order(state: &mut SharedFrameState, context: &IsoCall
     }
 }
 
-#[cfg(test)]
-mod tests {
-    use super::validate_uvc_header;
+// ============================================================================
+// Bulk Transfer Support
+// ============================================================================
+
+/// Context passed to the bulk transfer callback
+struct BulkCallbackContext {
+    /// Frame assembly state, shared with the isochronous path
+    frame: IsoCallbackContext,
+    /// Consecutive failed transfers across the whole stream
+    failures: Arc<AtomicU32>,
+    /// Consecutive failed transfers to resubmit before stopping
+    max_retries: u32,
+}
+
+/// Manages asynchronous bulk USB transfers for video streaming
+///
+/// Cameras with a bulk streaming endpoint send one UVC payload per transfer.
+/// Several transfers are kept in flight, as with [`IsochronousStream`], so
+/// the camera never waits for the host to queue the next read; completed
+/// payloads go through the same in-order frame assembly and frame channel.
+pub struct BulkStream {
+    /// libusb context (needed for event handling)
+    ctx: *mut libusb1_sys::libusb_context,
+    /// Device handle
+    handle: *mut libusb1_sys::libusb_device_handle,
+    /// Endpoint address
+    endpoint: u8,
+    /// Pre-allocated transfer structures
+    transfers: Vec<*mut libusb1_sys::libusb_transfer>,
+    /// Buffers for each transfer
+    buffers: Vec<Vec<u8>>,
+    /// Callback contexts (boxed to ensure stable addresses)
+    contexts: Vec<Box<BulkCallbackContext>>,
+    /// Flag to signal stop (public for external access)
+    pub stop_flag: Arc<AtomicBool>,
+    /// Reason why streaming stopped (public for checking after stop)
+    pub stop_reason: Arc<AtomicU8>,
+    /// Broadcast of completed frames (consumers get cursors via `subscribe`)
+    frame_sender: FrameSender,
+}
+
+impl BulkStream {
+    /// Create a new bulk stream for the given endpoint
+    ///
+    /// # Safety
+    /// The caller must ensure the device handle and context remain valid
+    /// for the lifetime of this stream.
+    ///
+    /// # Arguments
+    /// * `ctx` - libusb context pointer
+    /// * `handle` - libusb device handle pointer
+    /// * `endpoint` - Endpoint address
+    /// * `transfer_size` - Bytes per transfer (see [`crate::bulk_transfer::BulkTransferConfig::transfer_size`])
+    /// * `max_retries` - Consecutive failed transfers to resubmit before stopping
+    /// * `expected_frame_size` - Expected frame size from descriptor (e.g., 614400 for 640x480 YUY2)
+    /// * `capture_state` - Optional capture state for recording raw packets (E2E testing)
+    /// * `stats` - Frame assembly statistics to update
+    /// * `frame_capacity` - Frames the broadcast keeps for slow consumers
+    /// * `validation_level` - Frame corruption validation strictness (read per frame)
+    /// * `frame_width` - Frame width in pixels (for validation)
+    /// * `frame_height` - Frame height in pixels (for validation)
+    /// * `pixel_format` - Pixel format of uncompressed frames (for validation)
+    pub unsafe fn new(
+        ctx: *mut libusb1_sys::libusb_context,
+        handle: *mut libusb1_sys::libusb_device_handle,
+        endpoint: u8,
+        transfer_size: usize,
+        max_retries: u32,
+        expected_frame_size: usize,
+        capture_state: Option<Arc<CaptureState>>,
+        stats: Arc<StreamStats>,
+        frame_capacity: usize,
+        validation_level: Arc<std::sync::Mutex<crate::ValidationLevel>>,
+        frame_width: usize,
+        frame_height: usize,
+        pixel_format: crate::PixelFormat,
+    ) -> Result<Self, LibusbError> {
+        let (frame_sender, _) =
+            frame_broadcast::channel_with_metrics(frame_capacity, stats.frame_channel());
+        let stop_flag = Arc::new(AtomicBool::new(false));
+        let stop_reason = Arc::new(AtomicU8::new(StopReason::NotStopped as u8));
+        let frame_size = if expected_frame_size > 0 {
+            expected_frame_size
+        } else {
+            EXPECTED_YUY2_720P_SIZE
+        };
+
+        log::info!(
+            "Creating bulk stream with expected frame size: {} bytes",
+            frame_size
+        );
+
+        let shared_state = Arc::new(std::sync::Mutex::new(SharedFrameState::new(frame_size)));
+        let sequence_counter = Arc::new(AtomicU64::new(0));
+        let failures = Arc::new(AtomicU32::new(0));
+
+        let mut transfers = Vec::with_capacity(ISO_CONFIG.num_transfers);
+        let mut buffers = Vec::with_capacity(ISO_CONFIG.num_transfers);
+        let mut contexts = Vec::with_capacity(ISO_CONFIG.num_transfers);
+
+        for i in 0..ISO_CONFIG.num_transfers {
+            // Bulk transfers need no packet descriptors
+            let transfer = libusb1_sys::libusb_alloc_transfer(0);
+            if transfer.is_null() {
+                for t in &transfers {
+                    libusb1_sys::libusb_free_transfer(*t);
+                }
+                log::error!("Failed to allocate bulk transfer {}", i);
+                return Err(LibusbError::NoMem);
+            }
+
+            let context = Box::new(BulkCallbackContext {
+                frame: IsoCallbackContext {
+                    frame_sender: frame_sender.clone(),
+                    stop_flag: Arc::clone(&stop_flag),
+                    stop_reason: Arc::clone(&stop_reason),
+                    shared_state: Arc::clone(&shared_state),
+                    // Only used to split isochronous packets
+                    max_packet_size: 0,
+                    expected_frame_size: frame_size,
+                    capture_state: capture_state.clone(),
+                    stats: Arc::clone(&stats),
+                    validation_level: Arc::clone(&validation_level),
+                    frame_width,
+                    frame_height,
+                    pixel_format,
+                    transfer_index: i,
+                    sequence_counter: Arc::clone(&sequence_counter),
+                },
+                failures: Arc::clone(&failures),
+                max_retries,
+            });
+
+            transfers.push(transfer);
+            buffers.push(vec![0u8; transfer_size]);
+            contexts.push(context);
+        }
+
+        log::info!(
+            "Allocated {} bulk transfers of {} bytes",
+            ISO_CONFIG.num_transfers,
+            transfer_size
+        );
+
+        Ok(Self {
+            ctx,
+            handle,
+            endpoint,
+            transfers,
+            buffers,
+            contexts,
+            stop_flag,
+            stop_reason,
+            frame_sender,
+        })
+    }
+
+    /// Start streaming by submitting all transfers
+    pub fn start(&mut self) -> Result<(), LibusbError> {
+        log::info!(
+            "Starting bulk streaming on endpoint 0x{:02x}",
+            self.endpoint
+        );
+
+        for i in 0..self.transfers.len() {
+            self.setup_and_submit_transfer(i)?;
+        }
+
+        log::info!("All {} bulk transfers submitted", self.transfers.len());
+        Ok(())
+    }
+
+    /// Set up a transfer and submit it
+    fn setup_and_submit_transfer(&mut self, index: usize) -> Result<(), LibusbError> {
+        unsafe {
+            let transfer = self.transfers[index];
+            let context_ptr = self.contexts[index].as_mut() as *mut BulkCallbackContext;
+
+            (*transfer).dev_handle = self.handle;
+            (*transfer).endpoint = self.endpoint;
+            (*transfer).transfer_type = transfer_type::BULK;
+            // No timeout: stalls are detected by the frame consumers
+            (*transfer).timeout = 0;
+            (*transfer).length = self.buffers[index].len() as i32;
+            (*transfer).buffer = self.buffers[index].as_mut_ptr();
+            (*transfer).num_iso_packets = 0;
+            (*transfer).callback = bulk_transfer_callback;
+            (*transfer).user_data = context_ptr as *mut libc::c_void;
+
+            let ret = libusb1_sys::libusb_submit_transfer(transfer);
+            if ret < 0 {
+                log::error!("Failed to submit bulk transfer {}: {}", index, ret);
+                return Err(LibusbError::from(ret));
+            }
+
+            log::debug!("Submitted bulk transfer {}", index);
+            Ok(())
+        }
+    }
+
+    /// New frame consumer, reading from the next completed frame on
+    pub fn subscribe(&self) -> FrameCursor {
+        self.frame_sender.subscribe()
+    }
+
+    /// Signal the stream to stop
+    pub fn stop(&self) {
+        log::info!("Stopping bulk stream");
+        self.stop_flag.store(true, Ordering::Relaxed);
+    }
+
+    /// Check if streaming is stopped
+    pub fn is_stopped(&self) -> bool {
+        self.stop_flag.load(Ordering::Relaxed)
+    }
+
+    /// Get the reason why streaming stopped
+    pub fn get_stop_reason(&self) -> StopReason {
+        StopReason::from(self.stop_reason.load(Ordering::Relaxed))
+    }
+
+    /// Set the stop reason (for use by streaming loops)
+    pub fn set_stop_reason(&self, reason: StopReason) {
+        self.stop_reason.store(reason as u8, Ordering::Relaxed);
+    }
+}
+
+impl Drop for BulkStream {
+    fn drop(&mut self) {
+        log::info!("Cleaning up bulk stream");
+        self.stop_flag.store(true, Ordering::Relaxed);
+
+        for (i, transfer) in self.transfers.iter().enumerate() {
+            unsafe {
+                let ret = libusb1_sys::libusb_cancel_transfer(*transfer);
+                if ret < 0 && ret != -5 {
+                    // -5 is LIBUSB_ERROR_NOT_FOUND (transfer not pending)
+                    log::warn!("Failed to cancel bulk transfer {}: {}", i, ret);
+                }
+            }
+        }
+
+        // Handle remaining events to complete cancellations
+        unsafe {
+            let mut timeval = libc::timeval {
+                tv_sec: 0,
+                tv_usec: 100_000 as libc::suseconds_t, // 100ms
+            };
+            let _ = libusb1_sys::libusb_handle_events_timeout(self.ctx, &mut timeval);
+        }
+
+        for transfer in &self.transfers {
+            unsafe {
+                libusb1_sys::libusb_free_transfer(*transfer);
+            }
+        }
+
+        log::info!("Bulk stream cleanup complete");
+    }
+}
+
+/// Callback function invoked when a bulk transfer completes
+///
+/// # Safety
+/// This is called from libusb's event handling thread. The transfer pointer
+/// and user_data must be valid.
+extern "system" fn bulk_transfer_callback(transfer: *mut libusb1_sys::libusb_transfer) {
+    // SAFETY: libusb guarantees transfer is valid in callback
+    unsafe { bulk_transfer_callback_inner(transfer) }
+}
+
+/// Inner implementation of the bulk transfer callback
+unsafe fn bulk_transfer_callback_inner(transfer: *mut libusb1_sys::libusb_transfer) {
+    if transfer.is_null() || (*transfer).user_data.is_null() {
+        log::error!("bulk_transfer_callback: null transfer or user_data");
+        return;
+    }
+    let xfr = &mut *transfer;
+    let context = &*(xfr.user_data as *const BulkCallbackContext);
+    let frame_ctx = &context.frame;
+
+    if frame_ctx.stop_flag.load(Ordering::Relaxed) {
+        log::debug!("Bulk callback: stop flag set, not resubmitting");
+        return;
+    }
+
+    match TransferStatus::from(xfr.status) {
+        TransferStatus::Completed => {
+            context.failures.store(0, Ordering::Relaxed);
+            let sequence = frame_ctx.sequence_counter.fetch_add(1, Ordering::SeqCst);
+            let data = if xfr.actual_length > 0 {
+                std::slice::from_raw_parts(xfr.buffer, xfr.actual_length as usize)
+            } else {
+                &[]
+            };
+
+            if let Some(capture_state) = &frame_ctx.capture_state {
+                if capture_state.wants_packets() {
+                    capture_state.add_packet(data, xfr.endpoint);
+                }
+            }
+
+            // Empty payloads are queued too, keeping the sequence gap-free.
+            // Frames are assembled, and ended in the capture, exactly as for
+            // isochronous transfers
+            let mut state = crate::lock_or_recover(&frame_ctx.shared_state);
+            state.pending_urbs.insert(sequence, bulk_payload(data));
+            process_pending_urbs_in_order(&mut state, frame_ctx);
+        }
+        TransferStatus::TimedOut => {
+            log::trace!("Bulk transfer timeout");
+        }
+        TransferStatus::Cancelled => {
+            log::debug!("Bulk transfer cancelled");
+            return;
+        }
+        TransferStatus::NoDevice => {
+            log::error!("Device disconnected");
+            frame_ctx
+                .stop_reason
+                .store(StopReason::DeviceUnplugged as u8, Ordering::Relaxed);
+            frame_ctx.stop_flag.store(true, Ordering::Relaxed);
+            return;
+        }
+        status @ (TransferStatus::Error | TransferStatus::Overflow) => {
+            frame_ctx.stats.record_usb_errors(1);
+            let failures = context.failures.fetch_add(1, Ordering::Relaxed) + 1;
+            if failures > context.max_retries {
+                log::warn!(
+                    "Bulk transfer error: {:?} after {} retries",
+                    status,
+                    context.max_retries
+                );
+                frame_ctx
+                    .stop_reason
+                    .store(StopReason::TransferError as u8, Ordering::Relaxed);
+                frame_ctx.stop_flag.store(true, Ordering::Relaxed);
+                return;
+            }
+            log::warn!(
+                "Bulk transfer error: {:?}, retrying ({}/{})",
+                status,
+                failures,
+                context.max_retries
+            );
+        }
+        TransferStatus::Stall => {
+            // Clearing the halt is a synchronous request, which can't be made
+            // from the event thread; the camera loop restarts the stream
+            log::warn!("Bulk endpoint stalled");
+            frame_ctx.stats.record_usb_errors(1);
+            frame_ctx
+                .stop_reason
+                .store(StopReason::TransferError as u8, Ordering::Relaxed);
+            frame_ctx.stop_flag.store(true, Ordering::Relaxed);
+            return;
+        }
+    }
+
+    let ret = libusb1_sys::libusb_submit_transfer(transfer);
+    if ret < 0 {
+        log::error!("Failed to resubmit bulk transfer: {}", ret);
+        frame_ctx.stop_flag.store(true, Ordering::Relaxed);
+    }
+}
+
+/// Payload of one bulk transfer, which carries a single UVC payload
+fn bulk_payload(data: &[u8]) -> UrbPayload {
+    if data.is_empty() {
+        return UrbPayload {
+            data: Vec::new(),
+            packets: Vec::new(),
+        };
+    }
+    let info = parse_uvc_payload(data);
+    UrbPayload {
+        data: info.payload.to_vec(),
+        packets: vec![PacketMeta {
+            end_of_frame: info.end_of_frame,
+            frame_id: info.frame_id,
+            error: info.error,
+            still_image: info.still_image,
+            had_header: info.has_header,
+            payload_len: info.payload.len(),
+        }],
+    }
+}
+
+/// An isochronous or bulk video stream, chosen by the endpoint's transfer type
+pub enum VideoStream {
+    /// Isochronous endpoint
+    Isochronous(IsochronousStream),
+    /// Bulk endpoint
+    Bulk(BulkStream),
+}
+
+impl VideoStream {
+    /// Start streaming by submitting all transfers
+    pub fn start(&mut self) -> Result<(), LibusbError> {
+        match self {
+            VideoStream::Isochronous(stream) => stream.start(),
+            VideoStream::Bulk(stream) => stream.start(),
+        }
+    }
+
+    /// New frame consumer, reading from the next completed frame on
+    pub fn subscribe(&self) -> FrameCursor {
+        match self {
+            VideoStream::Isochronous(stream) => stream.subscribe(),
+            VideoStream::Bulk(stream) => stream.subscribe(),
+        }
+    }
+
+    /// Flag that stops the stream when set
+    pub fn stop_flag(&self) -> &Arc<AtomicBool> {
+        match self {
+            VideoStream::Isochronous(stream) => &stream.stop_flag,
+            VideoStream::Bulk(stream) => &stream.stop_flag,
+        }
+    }
+
+    /// Signal the stream to stop
+    pub fn stop(&self) {
+        match self {
+            VideoStream::Isochronous(stream) => stream.stop(),
+            VideoStream::Bulk(stream) => stream.stop(),
+        }
+    }
+
+    /// Check if streaming is stopped
+    pub fn is_stopped(&self) -> bool {
+        self.stop_flag().load(Ordering::Relaxed)
+    }
+
+    /// Get the reason why streaming stopped
+    pub fn get_stop_reason(&self) -> StopReason {
+        match self {
+            VideoStream::Isochronous(stream) => stream.get_stop_reason(),
+            VideoStream::Bulk(stream) => stream.get_stop_reason(),
+        }
+    }
+
+    /// Set the stop reason (for use by streaming loops)
+    pub fn set_stop_reason(&self, reason: StopReason) {
+        match self {
+            VideoStream::Isochronous(stream) => stream.set_stop_reason(reason),
+            VideoStream::Bulk(stream) => stream.set_stop_reason(reason),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        bulk_payload, effective_packet_size, packets_per_transfer, parse_ss_endpoint_companion,
+        SsEndpointCompanion,
+    };
+    use crate::frame_assembler::validate_uvc_header;
+
+    #[test]
+    fn test_effective_packet_size_high_bandwidth() {
+        assert_eq!(effective_packet_size(1024, 1, None), 1024);
+        assert_eq!(effective_packet_size(1024, 3, None), 3072);
+        assert_eq!(effective_packet_size(512, 0, None), 512);
+    }
+
+    #[test]
+    fn test_effective_packet_size_superspeed() {
+        let companion = SsEndpointCompanion {
+            max_burst: 15,
+            mult: 2,
+            bytes_per_interval: 0,
+        };
+        assert_eq!(effective_packet_size(1024, 1, Some(&companion)), 49152);
+
+        let companion = SsEndpointCompanion {
+            bytes_per_interval: 24576,
+            ..companion
+        };
+        assert_eq!(effective_packet_size(1024, 1, Some(&companion)), 24576);
+    }
+
+    #[test]
+    fn test_packets_per_transfer_from_frame_interval() {
+        // Unknown interval falls back to the fixed default
+        assert_eq!(packets_per_transfer(0, 125, 3072, 1_843_200), 32);
+
+        // 30 fps high speed: 266 microframes per frame, capped at usbfs limit
+        assert_eq!(packets_per_transfer(333_333, 125, 3072, 1_843_200), 128);
+
+        // Small frames need fewer packets than the frame interval allows
+        assert_eq!(packets_per_transfer(333_333, 125, 3072, 153_600), 50);
+
+        // 30 fps full speed: 33 frames of 1 ms
+        assert_eq!(packets_per_transfer(333_333, 1000, 1023, 0), 33);
+
+        // Very fast streams are clamped to the minimum
+        assert_eq!(packets_per_transfer(10_000, 125, 1024, 4096), 8);
+    }
+
+    #[test]
+    fn test_bulk_payload_strips_the_header() {
+        // 2-byte header with FID and EOF set, then 3 payload bytes
+        let payload = bulk_payload(&[0x02, 0x83, 0xFF, 0xD8, 0xFF]);
+        assert_eq!(payload.data, vec![0xFF, 0xD8, 0xFF]);
+        assert_eq!(payload.packets.len(), 1);
+        let meta = &payload.packets[0];
+        assert!(meta.had_header && meta.end_of_frame && meta.frame_id && !meta.error);
+        assert_eq!(meta.payload_len, 3);
+
+        // Zero-length transfers keep their place in the sequence with no packets
+        let empty = bulk_payload(&[]);
+        assert!(empty.data.is_empty() && empty.packets.is_empty());
+    }
+
+    #[test]
+    fn test_parse_ss_endpoint_companion() {
+        // Unrelated class-specific descriptor, then the companion
+        let extra = [0x03, 0x25, 0x01, 0x06, 0x30, 0x07, 0x01, 0x00, 0x60];
+        assert_eq!(
+            parse_ss_endpoint_companion(&extra),
+            Some(SsEndpointCompanion {
+                max_burst: 7,
+                mult: 1,
+                bytes_per_interval: 0x6000,
+            })
+        );
+        assert_eq!(parse_ss_endpoint_companion(&[]), None);
+        assert_eq!(parse_ss_endpoint_companion(&[0x06, 0x30, 0x00]), None);
+    }
 
     // Tests for UVC header validation
     // Per libuvc/Linux kernel approach: we trust HLE (byte 0) if in range 2-12,
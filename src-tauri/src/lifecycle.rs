@@ -0,0 +1,446 @@
+//! Ordered shutdown of background threads and subsystems
+//!
+//! Long-running threads (the USB camera loop, the health reporter, the
+//! auto-snapshot and resource monitors, the session checkpointer) are spawned
+//! through [`Lifecycle::spawn`] instead of `std::thread::spawn`, and
+//! subsystems that own their own threads (spool writer, model worker,
+//! replay) register a stop function with [`Lifecycle::on_shutdown`].
+//!
+//! [`Lifecycle::shutdown`] stops them one [`Stage`] at a time in
+//! [`Stage::SHUTDOWN_ORDER`]: frame producers first, so nothing downstream
+//! sees a half-stopped pipeline, and persistence last, so the final
+//! checkpoint isn't rewritten behind the exit handler's back. Each thread
+//! gets a [`StopSignal`] whose [`StopSignal::sleep`] wakes as soon as it is
+//! stopped, so a stage finishes in milliseconds rather than a poll interval;
+//! threads serving a channel wait with [`StopSignal::recv`], which returns
+//! on stop even if a sender is kept alive forever.
+//! Threads still running at the deadline are detached and reported.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{mpsc, Arc, Condvar, Mutex};
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+
+use serde::Serialize;
+
+/// How long `shutdown` waits for all threads on exit
+pub const SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(3);
+
+/// Interval at which `shutdown` checks whether a stopped thread has exited
+const JOIN_POLL_INTERVAL: Duration = Duration::from_millis(5);
+
+/// Interval at which [`StopSignal::recv`] checks whether it was stopped
+const RECV_POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+/// Shutdown group of a service
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Stage {
+    /// Frame sources: the USB camera loop and replay
+    Capture,
+    /// Frame consumers: spool writer, model worker, scheduled snapshots
+    Processing,
+    /// Status events for the frontend
+    Reporting,
+    /// Session checkpoints and journals
+    Persistence,
+}
+
+impl Stage {
+    /// Order in which stages are stopped
+    pub const SHUTDOWN_ORDER: [Stage; 4] = [
+        Stage::Capture,
+        Stage::Processing,
+        Stage::Reporting,
+        Stage::Persistence,
+    ];
+}
+
+/// Tells a service thread to exit
+///
+/// Wraps an `AtomicBool` so code that already polls a stop flag (the USB
+/// backends) can share it via [`StopSignal::from_flag`].
+#[derive(Debug, Clone, Default)]
+pub struct StopSignal {
+    flag: Arc<AtomicBool>,
+    wake: Arc<(Mutex<()>, Condvar)>,
+}
+
+impl StopSignal {
+    /// A signal that is not stopped
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// A signal that sets (and reads) an existing stop flag
+    pub fn from_flag(flag: Arc<AtomicBool>) -> Self {
+        Self {
+            flag,
+            wake: Arc::default(),
+        }
+    }
+
+    /// Whether the thread should exit
+    pub fn is_stopped(&self) -> bool {
+        self.flag.load(Ordering::Relaxed)
+    }
+
+    /// Ask the thread to exit and wake it if it is sleeping
+    pub fn stop(&self) {
+        let _guard = crate::lock_or_recover(&self.wake.0);
+        self.flag.store(true, Ordering::Relaxed);
+        self.wake.1.notify_all();
+    }
+
+    /// Sleep for `duration` unless stopped first
+    ///
+    /// Returns `true` if the thread should keep running, so service loops
+    /// read `while stop.sleep(INTERVAL) { ... }`.
+    pub fn sleep(&self, duration: Duration) -> bool {
+        let deadline = Instant::now() + duration;
+        let mut guard = crate::lock_or_recover(&self.wake.0);
+        while !self.is_stopped() {
+            let now = Instant::now();
+            if now >= deadline {
+                return true;
+            }
+            guard = match self.wake.1.wait_timeout(guard, deadline - now) {
+                Ok((guard, _)) => guard,
+                Err(poisoned) => poisoned.into_inner().0,
+            };
+        }
+        false
+    }
+
+    /// Wait for the next message on `receiver` unless stopped first
+    ///
+    /// Returns `None` once the thread should exit or every sender is gone,
+    /// so event loops read `while let Some(event) = stop.recv(&events) { ... }`.
+    pub fn recv<T>(&self, receiver: &mpsc::Receiver<T>) -> Option<T> {
+        while !self.is_stopped() {
+            match receiver.recv_timeout(RECV_POLL_INTERVAL) {
+                Ok(message) => return Some(message),
+                Err(mpsc::RecvTimeoutError::Timeout) => {}
+                Err(mpsc::RecvTimeoutError::Disconnected) => return None,
+            }
+        }
+        None
+    }
+}
+
+/// Outcome of [`Lifecycle::shutdown`], in stop order
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct ShutdownReport {
+    /// Services that stopped in time
+    pub stopped: Vec<String>,
+    /// Threads still running at the deadline (detached)
+    pub timed_out: Vec<String>,
+}
+
+enum Stopper {
+    Thread {
+        signal: StopSignal,
+        handle: JoinHandle<()>,
+    },
+    Hook(Box<dyn FnOnce() + Send>),
+}
+
+struct Service {
+    name: String,
+    stage: Stage,
+    stopper: Stopper,
+}
+
+/// Registry of the services `shutdown` stops
+#[derive(Default)]
+pub struct Lifecycle {
+    services: Mutex<Vec<Service>>,
+    shut_down: AtomicBool,
+}
+
+impl std::fmt::Debug for Lifecycle {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let services = crate::lock_or_recover(&self.services);
+        f.debug_struct("Lifecycle")
+            .field(
+                "services",
+                &services.iter().map(|s| s.name.as_str()).collect::<Vec<_>>(),
+            )
+            .field("shut_down", &self.shut_down.load(Ordering::Relaxed))
+            .finish()
+    }
+}
+
+impl Lifecycle {
+    /// Create an empty registry
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Spawn a named service thread with its own stop signal
+    ///
+    /// # Errors
+    ///
+    /// Returns the I/O error if the thread can't be spawned.
+    pub fn spawn<F>(&self, name: &str, stage: Stage, run: F) -> std::io::Result<()>
+    where
+        F: FnOnce(StopSignal) + Send + 'static,
+    {
+        self.spawn_with_signal(name, stage, StopSignal::new(), run)
+    }
+
+    /// Spawn a named service thread that stops through `signal`
+    ///
+    /// After shutdown the signal is stopped before the thread starts, so a
+    /// late service exits right away instead of outliving the app.
+    ///
+    /// # Errors
+    ///
+    /// Returns the I/O error if the thread can't be spawned.
+    pub fn spawn_with_signal<F>(
+        &self,
+        name: &str,
+        stage: Stage,
+        signal: StopSignal,
+        run: F,
+    ) -> std::io::Result<()>
+    where
+        F: FnOnce(StopSignal) + Send + 'static,
+    {
+        if self.shut_down.load(Ordering::Relaxed) {
+            signal.stop();
+        }
+        let thread_signal = signal.clone();
+        let handle = std::thread::Builder::new()
+            .name(name.to_string())
+            .spawn(move || run(thread_signal))?;
+        crate::lock_or_recover(&self.services).push(Service {
+            name: name.to_string(),
+            stage,
+            stopper: Stopper::Thread { signal, handle },
+        });
+        Ok(())
+    }
+
+    /// Run `stop` when `stage` is shut down
+    ///
+    /// For subsystems that manage their own threads; `stop` should return
+    /// once they have exited.
+    pub fn on_shutdown(&self, name: &str, stage: Stage, stop: impl FnOnce() + Send + 'static) {
+        crate::lock_or_recover(&self.services).push(Service {
+            name: name.to_string(),
+            stage,
+            stopper: Stopper::Hook(Box::new(stop)),
+        });
+    }
+
+    /// Stop every service, stage by stage, within `timeout`
+    ///
+    /// Within a stage all threads are signalled first, then each thread is
+    /// waited for (or hook run) in registration order. Calling it again only
+    /// stops services registered since.
+    pub fn shutdown(&self, timeout: Duration) -> ShutdownReport {
+        self.shut_down.store(true, Ordering::Relaxed);
+        let deadline = Instant::now() + timeout;
+        let mut services = std::mem::take(&mut *crate::lock_or_recover(&self.services));
+        let mut report = ShutdownReport::default();
+
+        for stage in Stage::SHUTDOWN_ORDER {
+            let (current, rest): (Vec<Service>, Vec<Service>) =
+                services.into_iter().partition(|s| s.stage == stage);
+            services = rest;
+
+            for service in &current {
+                if let Stopper::Thread { signal, .. } = &service.stopper {
+                    signal.stop();
+                }
+            }
+            for service in current {
+                let stopped = match service.stopper {
+                    Stopper::Thread { handle, .. } => join_until(handle, deadline),
+                    Stopper::Hook(stop) => {
+                        stop();
+                        true
+                    }
+                };
+                if stopped {
+                    log::debug!("Stopped {}", service.name);
+                    report.stopped.push(service.name);
+                } else {
+                    log::warn!("{} did not stop within {:?}", service.name, timeout);
+                    report.timed_out.push(service.name);
+                }
+            }
+        }
+        report
+    }
+}
+
+impl Drop for Lifecycle {
+    /// Signal threads that were never shut down so they don't run forever
+    fn drop(&mut self) {
+        for service in crate::lock_or_recover(&self.services).iter() {
+            if let Stopper::Thread { signal, .. } = &service.stopper {
+                signal.stop();
+            }
+        }
+    }
+}
+
+/// Join `handle` if it exits before `deadline`
+fn join_until(handle: JoinHandle<()>, deadline: Instant) -> bool {
+    while !handle.is_finished() {
+        if Instant::now() >= deadline {
+            return false;
+        }
+        std::thread::sleep(JOIN_POLL_INTERVAL);
+    }
+    if handle.join().is_err() {
+        log::error!("Service thread panicked");
+    }
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::AtomicUsize;
+
+    #[test]
+    fn test_sleep_wakes_on_stop() {
+        let signal = StopSignal::new();
+        assert!(signal.sleep(Duration::from_millis(1)));
+
+        let waker = signal.clone();
+        let started = Instant::now();
+        let thread = std::thread::spawn(move || {
+            std::thread::sleep(Duration::from_millis(20));
+            waker.stop();
+        });
+        assert!(!signal.sleep(Duration::from_secs(60)));
+        assert!(started.elapsed() < Duration::from_secs(5));
+        thread.join().unwrap();
+    }
+
+    #[test]
+    fn test_no_thread_outlives_shutdown() {
+        let lifecycle = Lifecycle::new();
+        let running = Arc::new(AtomicUsize::new(0));
+        for (name, stage) in [
+            ("camera", Stage::Capture),
+            ("snapshots", Stage::Processing),
+            ("health", Stage::Reporting),
+            ("checkpoint", Stage::Persistence),
+        ] {
+            let running = Arc::clone(&running);
+            running.fetch_add(1, Ordering::SeqCst);
+            lifecycle
+                .spawn(name, stage, move |stop| {
+                    while stop.sleep(Duration::from_secs(3600)) {}
+                    running.fetch_sub(1, Ordering::SeqCst);
+                })
+                .unwrap();
+        }
+
+        let report = lifecycle.shutdown(Duration::from_secs(5));
+        assert!(report.timed_out.is_empty());
+        assert_eq!(report.stopped.len(), 4);
+        assert_eq!(running.load(Ordering::SeqCst), 0);
+    }
+
+    #[test]
+    fn test_event_loop_stops_while_sender_lives() {
+        let lifecycle = Lifecycle::new();
+        // Kept alive like a sender in a static, so the channel never closes
+        let (sender, events) = mpsc::channel();
+        let handled = Arc::new(AtomicUsize::new(0));
+        let counter = Arc::clone(&handled);
+        lifecycle
+            .spawn("hotplug", Stage::Capture, move |stop| {
+                while let Some(()) = stop.recv(&events) {
+                    counter.fetch_add(1, Ordering::SeqCst);
+                }
+            })
+            .unwrap();
+        sender.send(()).unwrap();
+        while handled.load(Ordering::SeqCst) == 0 {
+            std::thread::sleep(Duration::from_millis(1));
+        }
+
+        let report = lifecycle.shutdown(Duration::from_secs(5));
+        assert!(report.timed_out.is_empty());
+        assert_eq!(report.stopped, vec!["hotplug".to_string()]);
+        // The receiver is gone with the thread
+        assert!(sender.send(()).is_err());
+    }
+
+    #[test]
+    fn test_recv_returns_when_senders_are_gone() {
+        let (sender, events) = mpsc::channel();
+        sender.send(1).unwrap();
+        drop(sender);
+        let stop = StopSignal::new();
+        assert_eq!(stop.recv(&events), Some(1));
+        assert_eq!(stop.recv(&events), None);
+    }
+
+    #[test]
+    fn test_stages_stop_in_order() {
+        let lifecycle = Lifecycle::new();
+        let order = Arc::new(Mutex::new(Vec::new()));
+        // Registered in reverse of the shutdown order
+        for (name, stage) in [
+            ("checkpoint", Stage::Persistence),
+            ("health", Stage::Reporting),
+            ("spool", Stage::Processing),
+            ("camera", Stage::Capture),
+        ] {
+            let order = Arc::clone(&order);
+            if stage == Stage::Processing {
+                lifecycle.on_shutdown(name, stage, move || order.lock().unwrap().push(name));
+                continue;
+            }
+            lifecycle
+                .spawn(name, stage, move |stop| {
+                    while stop.sleep(Duration::from_secs(3600)) {}
+                    order.lock().unwrap().push(name);
+                })
+                .unwrap();
+        }
+
+        let report = lifecycle.shutdown(Duration::from_secs(5));
+        let expected = vec!["camera", "spool", "health", "checkpoint"];
+        assert_eq!(*order.lock().unwrap(), expected);
+        assert_eq!(report.stopped, expected);
+    }
+
+    #[test]
+    fn test_stuck_thread_times_out() {
+        let lifecycle = Lifecycle::new();
+        let release = StopSignal::new();
+        let stuck = release.clone();
+        lifecycle
+            .spawn("stuck", Stage::Processing, move |_| {
+                // Ignores its own signal
+                stuck.sleep(Duration::from_secs(60));
+            })
+            .unwrap();
+
+        let report = lifecycle.shutdown(Duration::from_millis(50));
+        assert_eq!(report.timed_out, vec!["stuck".to_string()]);
+        release.stop();
+    }
+
+    #[test]
+    fn test_spawn_after_shutdown_exits() {
+        let lifecycle = Lifecycle::new();
+        lifecycle.shutdown(Duration::from_millis(10));
+        lifecycle
+            .spawn("late", Stage::Reporting, |stop| {
+                while stop.sleep(Duration::from_secs(3600)) {}
+            })
+            .unwrap();
+        let report = lifecycle.shutdown(Duration::from_secs(5));
+        assert_eq!(report.stopped, vec!["late".to_string()]);
+    }
+}
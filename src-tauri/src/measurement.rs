@@ -0,0 +1,396 @@
+//! Measurements in calibrated units
+//!
+//! Points are given in frame pixels. With a [`Calibration`] made at the
+//! frame's resolution, points are undistorted and results converted to the
+//! selected [`LengthUnit`] (areas to its square); without one they stay in
+//! pixels. Angles are in degrees either way. Every [`Quantity`] carries a
+//! standard uncertainty combining the calibration's scale uncertainty with a
+//! one-pixel placement error per point.
+//!
+//! Measurements are kept in the session manifest and can be exported as CSV
+//! with [`to_csv`].
+
+use std::fmt::Write as _;
+
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::calibration::Calibration;
+
+/// Millimetres per inch
+pub const MM_PER_INCH: f32 = 25.4;
+
+/// Standard placement error of a measured point in pixels
+const POINT_UNCERTAINTY_PX: f32 = 1.0;
+
+/// Fewest points of a polygon
+pub const MIN_POLYGON_POINTS: usize = 3;
+
+/// Errors measuring points
+#[derive(Debug, Error)]
+pub enum MeasurementError {
+    /// Polygon has too few points
+    #[error("polygon needs at least 3 points, got {0}")]
+    TooFewPoints(usize),
+    /// An angle arm has zero length
+    #[error("angle arms must not have zero length")]
+    Degenerate,
+}
+
+/// Result type alias for measurement operations
+pub type Result<T> = std::result::Result<T, MeasurementError>;
+
+/// Unit calibrated lengths are reported in, set with `set_measurement_unit`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LengthUnit {
+    /// Millimetres
+    #[default]
+    Mm,
+    /// Inches
+    Inch,
+}
+
+impl LengthUnit {
+    /// Unit symbol used in results
+    #[must_use]
+    pub fn symbol(self) -> &'static str {
+        match self {
+            LengthUnit::Mm => "mm",
+            LengthUnit::Inch => "in",
+        }
+    }
+
+    /// Symbol of the square of this unit
+    #[must_use]
+    pub fn area_symbol(self) -> &'static str {
+        match self {
+            LengthUnit::Mm => "mm²",
+            LengthUnit::Inch => "in²",
+        }
+    }
+
+    /// Convert a length in millimetres to this unit
+    #[must_use]
+    pub fn convert_mm(self, mm: f32) -> f32 {
+        match self {
+            LengthUnit::Mm => mm,
+            LengthUnit::Inch => mm / MM_PER_INCH,
+        }
+    }
+}
+
+/// A measured value with its standard uncertainty
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Quantity {
+    /// Measured value
+    pub value: f32,
+    /// Standard uncertainty, in the same unit
+    pub uncertainty: f32,
+    /// Unit symbol ("mm", "in", "px" when uncalibrated, squared for areas,
+    /// "deg" for angles)
+    pub unit: String,
+}
+
+/// What was measured
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MeasurementKind {
+    /// Distance between two points
+    Line,
+    /// Angle at the middle of three points
+    Angle,
+    /// Area enclosed by a polygon
+    Area,
+}
+
+impl MeasurementKind {
+    /// Name used in exports
+    #[must_use]
+    pub fn name(self) -> &'static str {
+        match self {
+            MeasurementKind::Line => "line",
+            MeasurementKind::Angle => "angle",
+            MeasurementKind::Area => "area",
+        }
+    }
+}
+
+/// A measurement recorded in the session manifest
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Measurement {
+    /// Measurement number within the session (starting at 1)
+    pub id: u32,
+    /// What was measured
+    pub kind: MeasurementKind,
+    /// Measured points in frame pixels
+    pub points: Vec<[f32; 2]>,
+    /// Result
+    pub value: Quantity,
+    /// Sequence number of the measured frame
+    pub frame_sequence: u64,
+    /// Wall-clock time of the measurement (milliseconds since the Unix epoch)
+    pub timestamp_ms: u64,
+}
+
+/// Length of the line from `a` to `b`
+///
+/// In `unit` if `calibration` is given, otherwise in pixels.
+#[must_use]
+pub fn line_length(
+    calibration: Option<&Calibration>,
+    unit: LengthUnit,
+    a: [f32; 2],
+    b: [f32; 2],
+) -> Quantity {
+    let placement_px = std::f32::consts::SQRT_2 * POINT_UNCERTAINTY_PX;
+    let Some(calibration) = calibration else {
+        return Quantity {
+            value: (b[0] - a[0]).hypot(b[1] - a[1]),
+            uncertainty: placement_px,
+            unit: "px".to_string(),
+        };
+    };
+    let mm = calibration.distance_mm((a[0], a[1]), (b[0], b[1]));
+    let placement = placement_px / calibration.pixels_per_mm;
+    let scale = mm * calibration.scale_uncertainty();
+    Quantity {
+        value: unit.convert_mm(mm),
+        uncertainty: unit.convert_mm(placement.hypot(scale)),
+        unit: unit.symbol().to_string(),
+    }
+}
+
+/// Angle at `vertex` between the arms to `a` and `b`, in degrees (0 to 180)
+///
+/// Points are undistorted first if `calibration` is given.
+///
+/// # Errors
+///
+/// Returns [`MeasurementError::Degenerate`] if either arm has zero length.
+pub fn angle(
+    calibration: Option<&Calibration>,
+    a: [f32; 2],
+    vertex: [f32; 2],
+    b: [f32; 2],
+) -> Result<Quantity> {
+    let [a, vertex, b] = [a, vertex, b].map(|p| undistorted(calibration, p));
+    let u = [a[0] - vertex[0], a[1] - vertex[1]];
+    let v = [b[0] - vertex[0], b[1] - vertex[1]];
+    let (uu, vv) = (u[0] * u[0] + u[1] * u[1], v[0] * v[0] + v[1] * v[1]);
+    if uu == 0.0 || vv == 0.0 {
+        return Err(MeasurementError::Degenerate);
+    }
+    let cross = u[0] * v[1] - u[1] * v[0];
+    let dot = u[0] * v[0] + u[1] * v[1];
+
+    // Gradients of the angle with respect to each arm end; the vertex moves
+    // both arms, so its gradient is minus their sum
+    let ga = [u[1] / uu, -u[0] / uu];
+    let gb = [-v[1] / vv, v[0] / vv];
+    let gv = [ga[0] + gb[0], ga[1] + gb[1]];
+    let spread = (1.0 / uu + 1.0 / vv + gv[0] * gv[0] + gv[1] * gv[1]).sqrt();
+
+    Ok(Quantity {
+        value: cross.atan2(dot).abs().to_degrees(),
+        uncertainty: (POINT_UNCERTAINTY_PX * spread).to_degrees(),
+        unit: "deg".to_string(),
+    })
+}
+
+/// Area enclosed by the polygon through `points`
+///
+/// In the square of `unit` if `calibration` is given, otherwise in square
+/// pixels. The polygon is closed implicitly and must not self-intersect.
+///
+/// # Errors
+///
+/// Returns [`MeasurementError::TooFewPoints`] for fewer than
+/// [`MIN_POLYGON_POINTS`] points.
+pub fn polygon_area(
+    calibration: Option<&Calibration>,
+    unit: LengthUnit,
+    points: &[[f32; 2]],
+) -> Result<Quantity> {
+    if points.len() < MIN_POLYGON_POINTS {
+        return Err(MeasurementError::TooFewPoints(points.len()));
+    }
+    let points: Vec<[f32; 2]> = points
+        .iter()
+        .map(|&p| undistorted(calibration, p))
+        .collect();
+    let n = points.len();
+
+    // Shoelace formula; each coordinate's gradient is half the difference of
+    // its neighbours' opposite coordinates
+    let mut twice_area = 0.0;
+    let mut gradient_sq = 0.0;
+    for i in 0..n {
+        let (prev, p, next) = (points[(i + n - 1) % n], points[i], points[(i + 1) % n]);
+        twice_area += p[0] * next[1] - next[0] * p[1];
+        gradient_sq += (next[1] - prev[1]).powi(2) + (prev[0] - next[0]).powi(2);
+    }
+    let area_px = twice_area.abs() / 2.0;
+    let placement_px = POINT_UNCERTAINTY_PX * gradient_sq.sqrt() / 2.0;
+
+    let Some(calibration) = calibration else {
+        return Ok(Quantity {
+            value: area_px,
+            uncertainty: placement_px,
+            unit: "px²".to_string(),
+        });
+    };
+    let px_per_unit = calibration.pixels_per_mm / unit.convert_mm(1.0);
+    let area = area_px / (px_per_unit * px_per_unit);
+    let placement = placement_px / (px_per_unit * px_per_unit);
+    // The area scales with the square of the scale
+    let scale = 2.0 * area * calibration.scale_uncertainty();
+    Ok(Quantity {
+        value: area,
+        uncertainty: placement.hypot(scale),
+        unit: unit.area_symbol().to_string(),
+    })
+}
+
+/// Undistort a point if calibrated
+fn undistorted(calibration: Option<&Calibration>, point: [f32; 2]) -> [f32; 2] {
+    match calibration {
+        Some(calibration) => {
+            let (x, y) = calibration.undistort((point[0], point[1]));
+            [x, y]
+        }
+        None => point,
+    }
+}
+
+/// Render measurements as CSV, one row per measurement
+///
+/// Points are written as `x y` pairs separated by `;`.
+#[must_use]
+pub fn to_csv(measurements: &[Measurement]) -> String {
+    let mut csv =
+        String::from("id,type,value,uncertainty,unit,frame_sequence,timestamp_ms,points\n");
+    for m in measurements {
+        let points: Vec<String> = m
+            .points
+            .iter()
+            .map(|[x, y]| format!("{} {}", x, y))
+            .collect();
+        let _ = writeln!(
+            csv,
+            "{},{},{},{},{},{},{},{}",
+            m.id,
+            m.kind.name(),
+            m.value.value,
+            m.value.uncertainty,
+            m.value.unit,
+            m.frame_sequence,
+            m.timestamp_ms,
+            points.join(";")
+        );
+    }
+    csv
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn calibration() -> Calibration {
+        // 10 px/mm from a 5 mm reference
+        Calibration::from_reference(640, 480, (0.0, 0.0), (50.0, 0.0), 5.0).unwrap()
+    }
+
+    #[test]
+    fn test_line_length_units() {
+        let calibration = calibration();
+        let mm = line_length(Some(&calibration), LengthUnit::Mm, [0.0, 0.0], [0.0, 254.0]);
+        assert!((mm.value - 25.4).abs() < 1e-3);
+        assert_eq!(mm.unit, "mm");
+
+        let inch = line_length(
+            Some(&calibration),
+            LengthUnit::Inch,
+            [0.0, 0.0],
+            [0.0, 254.0],
+        );
+        assert!((inch.value - 1.0).abs() < 1e-4);
+        assert!((inch.uncertainty - mm.uncertainty / MM_PER_INCH).abs() < 1e-6);
+
+        let px = line_length(None, LengthUnit::Mm, [0.0, 0.0], [3.0, 4.0]);
+        assert_eq!((px.value, px.unit.as_str()), (5.0, "px"));
+    }
+
+    #[test]
+    fn test_uncertainty_grows_with_length() {
+        let calibration = calibration();
+        let short = line_length(Some(&calibration), LengthUnit::Mm, [0.0, 0.0], [10.0, 0.0]);
+        let long = line_length(Some(&calibration), LengthUnit::Mm, [0.0, 0.0], [400.0, 0.0]);
+        // Placement error alone: sqrt(2) px at 10 px/mm
+        assert!(short.uncertainty > 0.14);
+        assert!(long.uncertainty > short.uncertainty);
+    }
+
+    #[test]
+    fn test_angle() {
+        let right = angle(None, [10.0, 0.0], [0.0, 0.0], [0.0, 10.0]).unwrap();
+        assert!((right.value - 90.0).abs() < 1e-4);
+        assert_eq!(right.unit, "deg");
+
+        // Order of the arms doesn't matter
+        let obtuse = angle(None, [0.0, 20.0], [0.0, 0.0], [-20.0, -20.0]).unwrap();
+        assert!((obtuse.value - 135.0).abs() < 1e-3);
+
+        // Longer arms pin the angle down better
+        let long = angle(None, [100.0, 0.0], [0.0, 0.0], [0.0, 100.0]).unwrap();
+        assert!(long.uncertainty < right.uncertainty);
+
+        assert!(matches!(
+            angle(None, [0.0, 0.0], [0.0, 0.0], [5.0, 5.0]),
+            Err(MeasurementError::Degenerate)
+        ));
+    }
+
+    #[test]
+    fn test_polygon_area() {
+        let square = [[0.0, 0.0], [100.0, 0.0], [100.0, 100.0], [0.0, 100.0]];
+        let px = polygon_area(None, LengthUnit::Mm, &square).unwrap();
+        assert_eq!((px.value, px.unit.as_str()), (10_000.0, "px²"));
+
+        // Winding order doesn't matter
+        let mut reversed = square;
+        reversed.reverse();
+        let mm = polygon_area(Some(&calibration()), LengthUnit::Mm, &reversed).unwrap();
+        assert!((mm.value - 100.0).abs() < 1e-3);
+        assert_eq!(mm.unit, "mm²");
+        assert!(mm.uncertainty > 0.0);
+
+        let inch = polygon_area(Some(&calibration()), LengthUnit::Inch, &square).unwrap();
+        assert!((inch.value - 100.0 / (MM_PER_INCH * MM_PER_INCH)).abs() < 1e-5);
+
+        assert!(matches!(
+            polygon_area(None, LengthUnit::Mm, &square[..2]),
+            Err(MeasurementError::TooFewPoints(2))
+        ));
+    }
+
+    #[test]
+    fn test_csv_export() {
+        let measurement = Measurement {
+            id: 1,
+            kind: MeasurementKind::Line,
+            points: vec![[1.0, 2.0], [3.5, 4.0]],
+            value: Quantity {
+                value: 2.5,
+                uncertainty: 0.1,
+                unit: "mm".to_string(),
+            },
+            frame_sequence: 7,
+            timestamp_ms: 1000,
+        };
+        let csv = to_csv(&[measurement]);
+        let rows: Vec<&str> = csv.lines().collect();
+        assert_eq!(rows.len(), 2);
+        assert_eq!(rows[1], "1,line,2.5,0.1,mm,7,1000,1 2;3.5 4");
+    }
+}
@@ -0,0 +1,303 @@
+//! Stable message codes and the English message catalog
+//!
+//! Errors and status events carry a [`MessageCode`] alongside their human-readable
+//! text. The frontend can look codes up in its own translations (falling back to
+//! the English text), and logs stay greppable by code regardless of wording.
+
+use serde::{Deserialize, Serialize};
+
+/// Stable identifier for an error or status message
+///
+/// Serialized as `SCREAMING_SNAKE_CASE` (e.g. `"NO_FRAME"`). Codes must never be
+/// renamed once released; add new variants instead.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum MessageCode {
+    // Command errors
+    /// Internal lock was poisoned by a panicking thread
+    LockPoisoned,
+    /// File system operation failed
+    IoError,
+    /// Packet capture failed
+    CaptureError,
+    /// No frame has been received yet
+    NoFrame,
+    /// App directory could not be resolved
+    PathError,
+    /// Requested resource does not exist
+    NotFound,
+    /// Recording could not be started or saved
+    RecordingError,
+    /// Deep link URL was malformed or unsupported
+    DeepLinkError,
+    /// Android permission was not granted
+    PermissionDenied,
+    /// Android platform API call failed
+    AndroidError,
+    /// Image could not be encoded in the requested format
+    EncodeError,
+    /// Clip could not be exported
+    ClipError,
+    /// Capture submission could not be packaged
+    SubmissionError,
+    /// The user has not agreed to share the capture
+    ConsentRequired,
+    /// Pipeline comparison could not be started or stopped
+    ComparisonError,
+    /// Frame trace could not be armed or written
+    TraceError,
+    /// Packet capture could not be replayed
+    ReplayError,
+    /// Frame could not be converted to RGB
+    ConversionError,
+    /// Detection model could not be loaded or run
+    InferenceError,
+    /// Camera control could not be read or changed
+    CameraControlError,
+    /// Measurement calibration failed or target not found
+    CalibrationError,
+    /// Measurement points are unusable
+    MeasurementError,
+    /// No frozen frame or unusable annotation
+    FreezeError,
+    /// Unsupported rotation
+    TransformError,
+    /// Edit to undo or redo no longer applies
+    HistoryError,
+    /// Invalid zoom factor or centre
+    ZoomError,
+    /// Invalid script, or another script is running
+    ScriptError,
+    /// Invalid auto-snapshot interval or label
+    AutoSnapshotError,
+    /// Too little storage or memory left to record
+    LowResources,
+    /// Still image could not be taken
+    StillCaptureError,
+    /// Camera could not be listed or selected
+    DeviceError,
+    /// Settings were invalid or could not be saved
+    SettingsError,
+    /// Uncategorized error
+    Unknown,
+
+    // USB errors
+    /// Camera was physically disconnected
+    UsbDeviceUnplugged,
+    /// No frames received within the timeout
+    UsbTimeout,
+    /// USB transfer failed (STALL, pipe error, etc.)
+    UsbTransferError,
+    /// Camera setup or streaming failed
+    UsbCameraError,
+
+    // Streaming status
+    /// Probing the camera for a working video format
+    StatusDetectingFormat,
+    /// Streaming MJPEG frames
+    StatusStreamingMjpeg,
+    /// Streaming YUY2 frames converted to RGB
+    StatusStreamingYuy2,
+    /// Re-probing the camera after a run of corrupt frames
+    StatusResynchronizing,
+}
+
+impl MessageCode {
+    /// Every code, in catalog order
+    pub const ALL: &'static [MessageCode] = &[
+        MessageCode::LockPoisoned,
+        MessageCode::IoError,
+        MessageCode::CaptureError,
+        MessageCode::NoFrame,
+        MessageCode::PathError,
+        MessageCode::NotFound,
+        MessageCode::RecordingError,
+        MessageCode::DeepLinkError,
+        MessageCode::PermissionDenied,
+        MessageCode::AndroidError,
+        MessageCode::EncodeError,
+        MessageCode::ClipError,
+        MessageCode::SubmissionError,
+        MessageCode::ConsentRequired,
+        MessageCode::ComparisonError,
+        MessageCode::TraceError,
+        MessageCode::ReplayError,
+        MessageCode::ConversionError,
+        MessageCode::InferenceError,
+        MessageCode::CameraControlError,
+        MessageCode::CalibrationError,
+        MessageCode::MeasurementError,
+        MessageCode::FreezeError,
+        MessageCode::TransformError,
+        MessageCode::HistoryError,
+        MessageCode::ZoomError,
+        MessageCode::ScriptError,
+        MessageCode::AutoSnapshotError,
+        MessageCode::LowResources,
+        MessageCode::StillCaptureError,
+        MessageCode::DeviceError,
+        MessageCode::SettingsError,
+        MessageCode::Unknown,
+        MessageCode::UsbDeviceUnplugged,
+        MessageCode::UsbTimeout,
+        MessageCode::UsbTransferError,
+        MessageCode::UsbCameraError,
+        MessageCode::StatusDetectingFormat,
+        MessageCode::StatusStreamingMjpeg,
+        MessageCode::StatusStreamingYuy2,
+        MessageCode::StatusResynchronizing,
+    ];
+
+    /// The code as it appears on the wire and in logs
+    pub fn as_str(self) -> &'static str {
+        match self {
+            MessageCode::LockPoisoned => "LOCK_POISONED",
+            MessageCode::IoError => "IO_ERROR",
+            MessageCode::CaptureError => "CAPTURE_ERROR",
+            MessageCode::NoFrame => "NO_FRAME",
+            MessageCode::PathError => "PATH_ERROR",
+            MessageCode::NotFound => "NOT_FOUND",
+            MessageCode::RecordingError => "RECORDING_ERROR",
+            MessageCode::DeepLinkError => "DEEP_LINK_ERROR",
+            MessageCode::PermissionDenied => "PERMISSION_DENIED",
+            MessageCode::AndroidError => "ANDROID_ERROR",
+            MessageCode::EncodeError => "ENCODE_ERROR",
+            MessageCode::ClipError => "CLIP_ERROR",
+            MessageCode::SubmissionError => "SUBMISSION_ERROR",
+            MessageCode::ConsentRequired => "CONSENT_REQUIRED",
+            MessageCode::ComparisonError => "COMPARISON_ERROR",
+            MessageCode::TraceError => "TRACE_ERROR",
+            MessageCode::ReplayError => "REPLAY_ERROR",
+            MessageCode::ConversionError => "CONVERSION_ERROR",
+            MessageCode::InferenceError => "INFERENCE_ERROR",
+            MessageCode::CameraControlError => "CAMERA_CONTROL_ERROR",
+            MessageCode::CalibrationError => "CALIBRATION_ERROR",
+            MessageCode::MeasurementError => "MEASUREMENT_ERROR",
+            MessageCode::FreezeError => "FREEZE_ERROR",
+            MessageCode::TransformError => "TRANSFORM_ERROR",
+            MessageCode::HistoryError => "HISTORY_ERROR",
+            MessageCode::ZoomError => "ZOOM_ERROR",
+            MessageCode::ScriptError => "SCRIPT_ERROR",
+            MessageCode::AutoSnapshotError => "AUTO_SNAPSHOT_ERROR",
+            MessageCode::LowResources => "LOW_RESOURCES",
+            MessageCode::StillCaptureError => "STILL_CAPTURE_ERROR",
+            MessageCode::DeviceError => "DEVICE_ERROR",
+            MessageCode::SettingsError => "SETTINGS_ERROR",
+            MessageCode::Unknown => "UNKNOWN",
+            MessageCode::UsbDeviceUnplugged => "USB_DEVICE_UNPLUGGED",
+            MessageCode::UsbTimeout => "USB_TIMEOUT",
+            MessageCode::UsbTransferError => "USB_TRANSFER_ERROR",
+            MessageCode::UsbCameraError => "USB_CAMERA_ERROR",
+            MessageCode::StatusDetectingFormat => "STATUS_DETECTING_FORMAT",
+            MessageCode::StatusStreamingMjpeg => "STATUS_STREAMING_MJPEG",
+            MessageCode::StatusStreamingYuy2 => "STATUS_STREAMING_YUY2",
+            MessageCode::StatusResynchronizing => "STATUS_RESYNCHRONIZING",
+        }
+    }
+
+    /// Default English text for the code, used when no translation is available
+    pub fn default_message(self) -> &'static str {
+        match self {
+            MessageCode::LockPoisoned => "Internal state is unavailable",
+            MessageCode::IoError => "Could not read or write a file",
+            MessageCode::CaptureError => "Packet capture failed",
+            MessageCode::NoFrame => "No frame available",
+            MessageCode::PathError => "Could not access the app directory",
+            MessageCode::NotFound => "Not found",
+            MessageCode::RecordingError => "Recording failed",
+            MessageCode::DeepLinkError => "Invalid deep link",
+            MessageCode::PermissionDenied => "Permission denied",
+            MessageCode::AndroidError => "Android system call failed",
+            MessageCode::EncodeError => "Could not encode the image",
+            MessageCode::ClipError => "Could not export the clip",
+            MessageCode::SubmissionError => "Could not package the capture",
+            MessageCode::ConsentRequired => "Sharing the capture needs your consent",
+            MessageCode::ComparisonError => "Pipeline comparison failed",
+            MessageCode::TraceError => "Could not trace the frame",
+            MessageCode::ReplayError => "Could not replay the capture",
+            MessageCode::ConversionError => "Could not convert the frame",
+            MessageCode::InferenceError => "Could not run the detection model",
+            MessageCode::CameraControlError => "Could not change the camera setting",
+            MessageCode::CalibrationError => "Could not calibrate measurements",
+            MessageCode::MeasurementError => "Could not measure the selected points",
+            MessageCode::FreezeError => "Could not annotate the frozen frame",
+            MessageCode::TransformError => "Could not rotate the frame",
+            MessageCode::HistoryError => "Could not undo the change",
+            MessageCode::ZoomError => "Could not zoom the frame",
+            MessageCode::ScriptError => "Could not run the script",
+            MessageCode::AutoSnapshotError => "Could not schedule snapshots",
+            MessageCode::LowResources => "Not enough storage or memory left to record",
+            MessageCode::StillCaptureError => "Could not take a still image",
+            MessageCode::DeviceError => "Could not select the camera",
+            MessageCode::SettingsError => "Could not save the settings",
+            MessageCode::Unknown => "An unexpected error occurred",
+            MessageCode::UsbDeviceUnplugged => "USB camera was disconnected",
+            MessageCode::UsbTimeout => "No video frames received - camera may be disconnected",
+            MessageCode::UsbTransferError => "USB transfer error",
+            MessageCode::UsbCameraError => "Camera error",
+            MessageCode::StatusDetectingFormat => "Detecting video format...",
+            MessageCode::StatusStreamingMjpeg => "Streaming MJPEG",
+            MessageCode::StatusStreamingYuy2 => "Streaming YUY2 (converting to RGB)",
+            MessageCode::StatusResynchronizing => "Corrupt frames, resynchronizing with the camera",
+        }
+    }
+}
+
+impl std::fmt::Display for MessageCode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// A single entry in the message catalog
+#[derive(Debug, Clone, Serialize)]
+pub struct CatalogEntry {
+    /// Stable message code
+    pub code: MessageCode,
+    /// Default English text
+    pub message: &'static str,
+}
+
+/// Build the English message catalog for the frontend
+pub fn message_catalog() -> Vec<CatalogEntry> {
+    MessageCode::ALL
+        .iter()
+        .map(|&code| CatalogEntry {
+            code,
+            message: code.default_message(),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashSet;
+
+    #[test]
+    fn test_as_str_matches_serde() {
+        for &code in MessageCode::ALL {
+            let json = serde_json::to_string(&code).unwrap();
+            assert_eq!(json, format!("\"{}\"", code.as_str()));
+        }
+    }
+
+    #[test]
+    fn test_codes_are_unique() {
+        let codes: HashSet<_> = MessageCode::ALL.iter().map(|c| c.as_str()).collect();
+        assert_eq!(codes.len(), MessageCode::ALL.len());
+    }
+
+    #[test]
+    fn test_catalog_covers_all_codes() {
+        let catalog = message_catalog();
+        assert_eq!(catalog.len(), MessageCode::ALL.len());
+        assert!(catalog.iter().all(|e| !e.message.is_empty()));
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let code: MessageCode = serde_json::from_str("\"USB_TIMEOUT\"").unwrap();
+        assert_eq!(code, MessageCode::UsbTimeout);
+    }
+}
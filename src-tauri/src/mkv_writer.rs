@@ -0,0 +1,590 @@
+//! Incremental Matroska (MKV) writer for video recordings with chapters
+//!
+//! Stores the same MJPEG frames as the AVI writer, but with their own
+//! timestamps (millisecond precision, no fixed frame rate) and with the
+//! recording's bookmarks as embedded chapters, which VLC, mpv and `FFmpeg`
+//! based players list in their chapter menu. AVI has no chapter support, so
+//! this is the container to pick when the chapters should travel with the
+//! video file.
+//!
+//! # Layout
+//!
+//! ```text
+//! EBML       header (DocType matroska)
+//! Segment
+//!   SeekHead   positions of Info, Tracks and Chapters
+//!   Info       timestamp scale (1 ms), duration
+//!   Tracks     one V_MJPEG video track
+//!   Void       padding up to the first cluster
+//!   Cluster    timestamp, then one SimpleBlock per frame (up to 1 s)
+//!   ...
+//!   Chapters   one edition with one atom per chapter
+//! ```
+//!
+//! Frames are written as they arrive; the header is rewritten and the
+//! chapters appended when the file is finished.
+
+use std::io::{Seek, SeekFrom, Write};
+
+use crate::chapters::Chapter;
+use crate::image_encoder::{encode_jpeg, DEFAULT_JPEG_QUALITY};
+use crate::video_recorder::{Result, VideoError};
+
+/// Space reserved for everything before the first cluster
+const HEADER_LEN: usize = 256;
+
+/// Nanoseconds per timestamp tick (1 ms)
+const TIMESTAMP_SCALE_NS: u64 = 1_000_000;
+
+/// Longest time span of one cluster (ms)
+const CLUSTER_MS: u64 = 1_000;
+
+/// Track number of the video track
+const TRACK: u8 = 1;
+
+/// Name written as muxing and writing application
+const APP_NAME: &str = "CleanScope";
+
+/// Matroska element ids
+mod id {
+    pub const EBML: u32 = 0x1A45_DFA3;
+    pub const EBML_VERSION: u32 = 0x4286;
+    pub const EBML_READ_VERSION: u32 = 0x42F7;
+    pub const EBML_MAX_ID_LENGTH: u32 = 0x42F2;
+    pub const EBML_MAX_SIZE_LENGTH: u32 = 0x42F3;
+    pub const DOC_TYPE: u32 = 0x4282;
+    pub const DOC_TYPE_VERSION: u32 = 0x4287;
+    pub const DOC_TYPE_READ_VERSION: u32 = 0x4285;
+    pub const SEGMENT: u32 = 0x1853_8067;
+    pub const SEEK_HEAD: u32 = 0x114D_9B74;
+    pub const SEEK: u32 = 0x4DBB;
+    pub const SEEK_ID: u32 = 0x53AB;
+    pub const SEEK_POSITION: u32 = 0x53AC;
+    pub const INFO: u32 = 0x1549_A966;
+    pub const TIMESTAMP_SCALE: u32 = 0x2A_D7B1;
+    pub const MUXING_APP: u32 = 0x4D80;
+    pub const WRITING_APP: u32 = 0x5741;
+    pub const DURATION: u32 = 0x4489;
+    pub const TRACKS: u32 = 0x1654_AE6B;
+    pub const TRACK_ENTRY: u32 = 0xAE;
+    pub const TRACK_NUMBER: u32 = 0xD7;
+    pub const TRACK_UID: u32 = 0x73C5;
+    pub const TRACK_TYPE: u32 = 0x83;
+    pub const FLAG_LACING: u32 = 0x9C;
+    pub const CODEC_ID: u32 = 0x86;
+    pub const VIDEO: u32 = 0xE0;
+    pub const PIXEL_WIDTH: u32 = 0xB0;
+    pub const PIXEL_HEIGHT: u32 = 0xBA;
+    pub const VOID: u32 = 0xEC;
+    pub const CLUSTER: u32 = 0x1F43_B675;
+    pub const TIMESTAMP: u32 = 0xE7;
+    pub const SIMPLE_BLOCK: u32 = 0xA3;
+    pub const CHAPTERS: u32 = 0x1043_A770;
+    pub const EDITION_ENTRY: u32 = 0x45B9;
+    pub const EDITION_UID: u32 = 0x45BC;
+    pub const CHAPTER_ATOM: u32 = 0xB6;
+    pub const CHAPTER_UID: u32 = 0x73C4;
+    pub const CHAPTER_TIME_START: u32 = 0x91;
+    pub const CHAPTER_TIME_END: u32 = 0x92;
+    pub const CHAPTER_DISPLAY: u32 = 0x80;
+    pub const CHAP_STRING: u32 = 0x85;
+    pub const CHAP_LANGUAGE: u32 = 0x437C;
+}
+
+/// Writes an MJPEG Matroska file frame by frame
+pub struct MkvWriter<W: Write + Seek> {
+    /// Output file
+    out: W,
+    /// Stream position of the EBML header
+    start: u64,
+    /// Frame size, set by the first frame
+    size: Option<(u32, u32)>,
+    /// Timestamp of the first frame (microseconds)
+    first_timestamp_us: u64,
+    /// Blocks of the open cluster
+    cluster: Vec<u8>,
+    /// Timestamp of the open cluster (ms)
+    cluster_ms: u64,
+    /// Timestamp of the last frame (ms)
+    last_ms: u64,
+    /// Time between the last two frames (ms)
+    last_interval_ms: u64,
+    /// Frames written
+    frames: u32,
+    /// JPEG quality for RGB frames
+    quality: u8,
+}
+
+impl<W: Write + Seek> MkvWriter<W> {
+    /// Writer that places frames by their own timestamps
+    ///
+    /// Nothing is written until the first frame is added.
+    pub fn new(out: W) -> Self {
+        Self {
+            out,
+            start: 0,
+            size: None,
+            first_timestamp_us: 0,
+            cluster: Vec::new(),
+            cluster_ms: 0,
+            last_ms: 0,
+            last_interval_ms: 0,
+            frames: 0,
+            quality: DEFAULT_JPEG_QUALITY,
+        }
+    }
+
+    /// JPEG quality (1-100) for the RGB frames added from now on
+    pub fn set_quality(&mut self, quality: u8) {
+        self.quality = quality.clamp(1, 100);
+    }
+
+    /// Frames written so far
+    pub fn frame_count(&self) -> u32 {
+        self.frames
+    }
+
+    /// Encode a frame-buffer frame (JPEG or RGB24) and add it
+    ///
+    /// # Errors
+    ///
+    /// Returns the encoding error (`EncodeError::Unsupported` for RGB frames
+    /// without the `jpeg` feature), or any error from [`MkvWriter::add_frame`].
+    pub fn push_frame(
+        &mut self,
+        frame: &[u8],
+        width: u32,
+        height: u32,
+        timestamp_us: u64,
+    ) -> Result<()> {
+        let jpeg = encode_jpeg(frame, width, height, self.quality)?;
+        self.add_frame(&jpeg, width, height, timestamp_us)
+    }
+
+    /// Add a JPEG image captured at `timestamp_us`
+    ///
+    /// The first frame sets the video size and the start of the timeline;
+    /// later frames must match the size.
+    ///
+    /// # Errors
+    ///
+    /// Returns `VideoError::InvalidFrame` if the size differs from the first
+    /// frame, or `VideoError::Io` if writing fails.
+    pub fn add_frame(
+        &mut self,
+        jpeg: &[u8],
+        width: u32,
+        height: u32,
+        timestamp_us: u64,
+    ) -> Result<()> {
+        match self.size {
+            Some(size) if size != (width, height) => {
+                return Err(VideoError::InvalidFrame(format!(
+                    "{}x{} frame in a {}x{} video",
+                    width, height, size.0, size.1
+                )));
+            }
+            Some(_) => {}
+            None => {
+                self.start = self.out.stream_position()?;
+                self.out.write_all(&[0; HEADER_LEN])?;
+                self.size = Some((width, height));
+                self.first_timestamp_us = timestamp_us;
+            }
+        }
+
+        // Block timestamps may not go backwards
+        let ms = (timestamp_us.saturating_sub(self.first_timestamp_us) / 1000).max(self.last_ms);
+        if !self.cluster.is_empty() && ms - self.cluster_ms >= CLUSTER_MS {
+            self.flush_cluster()?;
+        }
+        if self.cluster.is_empty() {
+            self.cluster_ms = ms;
+        }
+
+        put_id(&mut self.cluster, id::SIMPLE_BLOCK);
+        put_size(&mut self.cluster, 4 + jpeg.len() as u64);
+        self.cluster.push(0x80 | TRACK);
+        self.cluster
+            .extend_from_slice(&((ms - self.cluster_ms) as i16).to_be_bytes());
+        self.cluster.push(0x80); // Key frame
+        self.cluster.extend_from_slice(jpeg);
+
+        if self.frames > 0 {
+            self.last_interval_ms = ms - self.last_ms;
+        }
+        self.last_ms = ms;
+        self.frames += 1;
+        Ok(())
+    }
+
+    /// Append `chapters`, rewrite the header and return the output
+    ///
+    /// Chapter times use the same time base as the frame timestamps.
+    ///
+    /// # Errors
+    ///
+    /// Returns `VideoError::InvalidFrame` if no frames were added, or
+    /// `VideoError::Io` if writing fails.
+    pub fn finish(mut self, chapters: &[Chapter]) -> Result<W> {
+        let Some((width, height)) = self.size else {
+            return Err(VideoError::InvalidFrame("video has no frames".to_string()));
+        };
+        self.flush_cluster()?;
+
+        let chapters_pos = self.out.stream_position()?;
+        if !chapters.is_empty() {
+            let element = self.chapters(chapters);
+            self.out.write_all(&element)?;
+        }
+        let end = self.out.stream_position()?;
+
+        let segment_data = self.start + ebml_header().len() as u64 + 12;
+        let chapters_pos = (!chapters.is_empty()).then_some(chapters_pos - segment_data);
+        let header = self.header(width, height, end - segment_data, chapters_pos);
+        self.out.seek(SeekFrom::Start(self.start))?;
+        self.out.write_all(&header)?;
+        self.out.seek(SeekFrom::Start(end))?;
+        self.out.flush()?;
+        Ok(self.out)
+    }
+
+    /// Write the open cluster, if it has any blocks
+    fn flush_cluster(&mut self) -> Result<()> {
+        if self.cluster.is_empty() {
+            return Ok(());
+        }
+        let mut timestamp = Vec::new();
+        put_uint(&mut timestamp, id::TIMESTAMP, self.cluster_ms);
+
+        let mut head = Vec::new();
+        put_id(&mut head, id::CLUSTER);
+        put_size(&mut head, (timestamp.len() + self.cluster.len()) as u64);
+        head.extend_from_slice(&timestamp);
+        self.out.write_all(&head)?;
+        self.out.write_all(&self.cluster)?;
+        self.cluster.clear();
+        Ok(())
+    }
+
+    /// `Chapters` element, with times relative to the first frame
+    fn chapters(&self, chapters: &[Chapter]) -> Vec<u8> {
+        let ns = |us: u64| us.saturating_sub(self.first_timestamp_us) * 1000;
+        let mut out = Vec::new();
+        put_master(&mut out, id::CHAPTERS, |chapters_el| {
+            put_master(chapters_el, id::EDITION_ENTRY, |edition| {
+                put_uint(edition, id::EDITION_UID, 1);
+                for (i, chapter) in chapters.iter().enumerate() {
+                    put_master(edition, id::CHAPTER_ATOM, |atom| {
+                        put_uint(atom, id::CHAPTER_UID, i as u64 + 1);
+                        put_uint(atom, id::CHAPTER_TIME_START, ns(chapter.start_us));
+                        put_uint(atom, id::CHAPTER_TIME_END, ns(chapter.end_us));
+                        put_master(atom, id::CHAPTER_DISPLAY, |display| {
+                            put_element(display, id::CHAP_STRING, chapter.title.as_bytes());
+                            put_element(display, id::CHAP_LANGUAGE, b"eng");
+                        });
+                    });
+                }
+            });
+        });
+        out
+    }
+
+    /// Everything before the first cluster, for a segment of `segment_len`
+    /// bytes with the chapters at `chapters_pos` (relative to the segment data)
+    fn header(
+        &self,
+        width: u32,
+        height: u32,
+        segment_len: u64,
+        chapters_pos: Option<u64>,
+    ) -> Vec<u8> {
+        let duration_ms = self.last_ms + self.last_interval_ms.max(1);
+
+        let mut info = Vec::new();
+        put_master(&mut info, id::INFO, |info| {
+            put_uint(info, id::TIMESTAMP_SCALE, TIMESTAMP_SCALE_NS);
+            put_element(info, id::MUXING_APP, APP_NAME.as_bytes());
+            put_element(info, id::WRITING_APP, APP_NAME.as_bytes());
+            put_element(info, id::DURATION, &(duration_ms as f64).to_be_bytes());
+        });
+
+        let mut tracks = Vec::new();
+        put_master(&mut tracks, id::TRACKS, |tracks| {
+            put_master(tracks, id::TRACK_ENTRY, |entry| {
+                put_uint(entry, id::TRACK_NUMBER, u64::from(TRACK));
+                put_uint(entry, id::TRACK_UID, u64::from(TRACK));
+                put_uint(entry, id::TRACK_TYPE, 1); // Video
+                put_uint(entry, id::FLAG_LACING, 0);
+                put_element(entry, id::CODEC_ID, b"V_MJPEG");
+                put_master(entry, id::VIDEO, |video| {
+                    put_uint(video, id::PIXEL_WIDTH, u64::from(width));
+                    put_uint(video, id::PIXEL_HEIGHT, u64::from(height));
+                });
+            });
+        });
+
+        // Fixed-width positions keep the SeekHead size independent of them
+        let seek_len = if chapters_pos.is_some() { 3 } else { 2 } * 21 + 5;
+        let mut seek_head = Vec::new();
+        put_master(&mut seek_head, id::SEEK_HEAD, |seek_head| {
+            let info_pos = seek_len as u64;
+            let tracks_pos = info_pos + info.len() as u64;
+            let entries = [(id::INFO, info_pos), (id::TRACKS, tracks_pos)]
+                .into_iter()
+                .chain(chapters_pos.map(|pos| (id::CHAPTERS, pos)));
+            for (element, pos) in entries {
+                put_master(seek_head, id::SEEK, |seek| {
+                    put_element(seek, id::SEEK_ID, &element.to_be_bytes());
+                    put_element(seek, id::SEEK_POSITION, &pos.to_be_bytes());
+                });
+            }
+        });
+        debug_assert_eq!(seek_head.len(), seek_len);
+
+        let mut h = ebml_header();
+        put_id(&mut h, id::SEGMENT);
+        h.extend_from_slice(&(segment_len | 1 << 56).to_be_bytes());
+        h.extend_from_slice(&seek_head);
+        h.extend_from_slice(&info);
+        h.extend_from_slice(&tracks);
+
+        // Pad to the first cluster with a Void element (8-byte size)
+        let gap = HEADER_LEN - h.len();
+        debug_assert!(gap >= 9);
+        put_id(&mut h, id::VOID);
+        h.extend_from_slice(&((gap - 9) as u64 | 1 << 56).to_be_bytes());
+        h.resize(HEADER_LEN, 0);
+        h
+    }
+}
+
+/// The `EBML` header element declaring a Matroska file
+fn ebml_header() -> Vec<u8> {
+    let mut out = Vec::new();
+    put_master(&mut out, id::EBML, |ebml| {
+        put_uint(ebml, id::EBML_VERSION, 1);
+        put_uint(ebml, id::EBML_READ_VERSION, 1);
+        put_uint(ebml, id::EBML_MAX_ID_LENGTH, 4);
+        put_uint(ebml, id::EBML_MAX_SIZE_LENGTH, 8);
+        put_element(ebml, id::DOC_TYPE, b"matroska");
+        put_uint(ebml, id::DOC_TYPE_VERSION, 4);
+        put_uint(ebml, id::DOC_TYPE_READ_VERSION, 2);
+    });
+    out
+}
+
+/// Append an element id (its leading bits encode its own length)
+fn put_id(out: &mut Vec<u8>, id: u32) {
+    let bytes = id.to_be_bytes();
+    let skip = bytes.iter().position(|&b| b != 0).unwrap_or(3);
+    out.extend_from_slice(&bytes[skip..]);
+}
+
+/// Append an element data size as a variable-length integer
+fn put_size(out: &mut Vec<u8>, size: u64) {
+    // All ones is reserved for "unknown size", hence `- 1`
+    let len = (1..8).find(|&len| size < (1 << (7 * len)) - 1).unwrap_or(8);
+    let marked = size | 1 << (7 * len);
+    out.extend_from_slice(&marked.to_be_bytes()[8 - len..]);
+}
+
+/// Append an element with binary or string data
+fn put_element(out: &mut Vec<u8>, id: u32, data: &[u8]) {
+    put_id(out, id);
+    put_size(out, data.len() as u64);
+    out.extend_from_slice(data);
+}
+
+/// Append an unsigned integer element in as few bytes as possible
+fn put_uint(out: &mut Vec<u8>, id: u32, value: u64) {
+    let bytes = value.to_be_bytes();
+    let skip = bytes.iter().position(|&b| b != 0).unwrap_or(7);
+    put_element(out, id, &bytes[skip..]);
+}
+
+/// Append a master element whose children `build` writes
+fn put_master(out: &mut Vec<u8>, id: u32, build: impl FnOnce(&mut Vec<u8>)) {
+    let mut children = Vec::new();
+    build(&mut children);
+    put_element(out, id, &children);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    const JPEG: &[u8] = &[0xFF, 0xD8, 0xAA, 0xFF, 0xD9];
+
+    /// Read a variable-length integer, with or without its length marker
+    fn vint(data: &[u8], pos: &mut usize, keep_marker: bool) -> u64 {
+        let len = data[*pos].leading_zeros() as usize + 1;
+        let mut value = u64::from(data[*pos]);
+        if !keep_marker {
+            value &= 0xFF >> len;
+        }
+        for &b in &data[*pos + 1..*pos + len] {
+            value = value << 8 | u64::from(b);
+        }
+        *pos += len;
+        value
+    }
+
+    /// `(id, data)` of the elements in `data`
+    fn elements(data: &[u8]) -> Vec<(u32, &[u8])> {
+        let mut pos = 0;
+        let mut out = Vec::new();
+        while pos < data.len() {
+            let id = vint(data, &mut pos, true) as u32;
+            let size = vint(data, &mut pos, false) as usize;
+            out.push((id, &data[pos..pos + size]));
+            pos += size;
+        }
+        out
+    }
+
+    /// Data of the first child `id` of a master element
+    fn child(data: &[u8], id: u32) -> &[u8] {
+        elements(data)
+            .into_iter()
+            .find(|(i, _)| *i == id)
+            .map(|(_, d)| d)
+            .unwrap_or_else(|| panic!("no element {:X}", id))
+    }
+
+    fn uint(data: &[u8]) -> u64 {
+        data.iter().fold(0, |v, &b| v << 8 | u64::from(b))
+    }
+
+    fn chapter(start_us: u64, end_us: u64, title: &str) -> Chapter {
+        Chapter {
+            start_us,
+            end_us,
+            title: title.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_vint_sizes() {
+        for (size, expected) in [
+            (0u64, vec![0x80]),
+            (126, vec![0xFE]),
+            (127, vec![0x40, 0x7F]),
+            (300, vec![0x41, 0x2C]),
+        ] {
+            let mut out = Vec::new();
+            put_size(&mut out, size);
+            assert_eq!(out, expected, "{}", size);
+        }
+    }
+
+    #[test]
+    fn test_mkv_layout() {
+        let mut writer = MkvWriter::new(Cursor::new(Vec::new()));
+        // Frames 1.0 s, 1.4 s and 2.5 s into the recording: two clusters
+        for timestamp_us in [1_000_000, 1_400_000, 2_500_000] {
+            writer.add_frame(JPEG, 640, 480, timestamp_us).unwrap();
+        }
+        assert_eq!(writer.frame_count(), 3);
+        let data = writer
+            .finish(&[
+                chapter(1_000_000, 2_000_000, "Start"),
+                chapter(2_000_000, 2_600_000, "Joint"),
+            ])
+            .unwrap()
+            .into_inner();
+
+        let top = elements(&data);
+        assert_eq!(top.len(), 2);
+        assert_eq!(top[0].0, id::EBML);
+        assert_eq!(child(top[0].1, id::DOC_TYPE), b"matroska");
+        assert_eq!(top[1].0, id::SEGMENT);
+        let segment = top[1].1;
+        assert_eq!(
+            segment.as_ptr() as usize + segment.len(),
+            data.as_ptr() as usize + data.len()
+        );
+
+        // The SeekHead points at the elements it names
+        for (_, seek) in elements(child(segment, id::SEEK_HEAD)) {
+            let target = uint(child(seek, id::SEEK_ID)) as u32;
+            let pos = uint(child(seek, id::SEEK_POSITION)) as usize;
+            let mut at = pos;
+            assert_eq!(vint(segment, &mut at, true) as u32, target);
+        }
+
+        let info = child(segment, id::INFO);
+        assert_eq!(uint(child(info, id::TIMESTAMP_SCALE)), 1_000_000);
+        let duration = f64::from_be_bytes(child(info, id::DURATION).try_into().unwrap());
+        assert_eq!(duration, 2600.0);
+        let track = child(child(segment, id::TRACKS), id::TRACK_ENTRY);
+        assert_eq!(child(track, id::CODEC_ID), b"V_MJPEG");
+        assert_eq!(uint(child(child(track, id::VIDEO), id::PIXEL_WIDTH)), 640);
+
+        let clusters: Vec<&[u8]> = elements(segment)
+            .into_iter()
+            .filter(|(id, _)| *id == id::CLUSTER)
+            .map(|(_, d)| d)
+            .collect();
+        assert_eq!(clusters.len(), 2);
+        let blocks: Vec<(u64, i16, &[u8])> = clusters
+            .iter()
+            .flat_map(|cluster| {
+                let timestamp = uint(child(cluster, id::TIMESTAMP));
+                elements(cluster)
+                    .into_iter()
+                    .filter(|(id, _)| *id == id::SIMPLE_BLOCK)
+                    .map(move |(_, b)| (timestamp, i16::from_be_bytes([b[1], b[2]]), &b[4..]))
+            })
+            .collect();
+        assert_eq!(blocks, vec![(0, 0, JPEG), (0, 400, JPEG), (1500, 0, JPEG)]);
+
+        let edition = child(child(segment, id::CHAPTERS), id::EDITION_ENTRY);
+        let atoms: Vec<(u64, u64, &[u8])> = elements(edition)
+            .into_iter()
+            .filter(|(id, _)| *id == id::CHAPTER_ATOM)
+            .map(|(_, atom)| {
+                (
+                    uint(child(atom, id::CHAPTER_TIME_START)),
+                    uint(child(atom, id::CHAPTER_TIME_END)),
+                    child(child(atom, id::CHAPTER_DISPLAY), id::CHAP_STRING),
+                )
+            })
+            .collect();
+        // Chapter times are relative to the first frame, in nanoseconds
+        assert_eq!(
+            atoms,
+            vec![
+                (0, 1_000_000_000, b"Start".as_slice()),
+                (1_000_000_000, 1_600_000_000, b"Joint".as_slice()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_without_chapters_has_no_chapters_element() {
+        let mut writer = MkvWriter::new(Cursor::new(Vec::new()));
+        writer.add_frame(JPEG, 4, 4, 0).unwrap();
+        let data = writer.finish(&[]).unwrap().into_inner();
+        let segment = elements(&data)[1].1;
+        assert!(elements(segment).iter().all(|(id, _)| *id != id::CHAPTERS));
+        assert_eq!(elements(child(segment, id::SEEK_HEAD)).len(), 2);
+    }
+
+    #[test]
+    fn test_rejects_invalid_frames() {
+        let writer = MkvWriter::new(Cursor::new(Vec::new()));
+        assert!(matches!(
+            writer.finish(&[]),
+            Err(VideoError::InvalidFrame(_))
+        ));
+
+        let mut writer = MkvWriter::new(Cursor::new(Vec::new()));
+        writer.add_frame(JPEG, 640, 480, 0).unwrap();
+        assert!(matches!(
+            writer.add_frame(JPEG, 320, 240, 40_000),
+            Err(VideoError::InvalidFrame(_))
+        ));
+    }
+}
@@ -0,0 +1,467 @@
+//! Conversion of RGB frames to the layouts output sinks consume
+//!
+//! The pipeline converts every frame to RGB24 ([`crate::yuv_conversion`]).
+//! Sinks that need another layout convert from there: a canvas takes RGBA,
+//! native surfaces BGRA, a virtual camera YUY2 and video encoders I420 or
+//! NV12. The 32-bit layouts convert both ways; the YUV ones are re-encoded
+//! with limited range BT.601 (the matrix the pipeline decodes with) or, with
+//! the `*_with_matrix` variants, BT.709, and decode back with the
+//! `yuv_conversion` converters.
+//!
+//! Chroma is subsampled by averaging the RGB of the pixels that share a
+//! sample, so YUV layouts need an even width (and, for 4:2:0, an even
+//! height). Compare the converters with `cargo bench --bench output_layout`.
+
+use crate::yuv_conversion::{ConversionError, YuvMatrix, YuvPackedFormat};
+
+/// Frame layout an output sink consumes
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OutputLayout {
+    /// RGB888, the pipeline's own layout
+    Rgb24,
+    /// RGB888 plus an opaque alpha byte (canvas `ImageData`)
+    Rgba,
+    /// B-G-R-A byte order (Windows and Android surfaces)
+    Bgra,
+    /// Packed YUV 4:2:2, Y0-U-Y1-V
+    Yuy2,
+    /// Packed YUV 4:2:2, U-Y0-V-Y1
+    Uyvy,
+    /// Planar YUV 4:2:0: Y, U and V planes
+    I420,
+    /// Semi-planar YUV 4:2:0: Y plane, interleaved UV plane
+    Nv12,
+}
+
+impl OutputLayout {
+    /// Every layout
+    pub const ALL: [OutputLayout; 7] = [
+        OutputLayout::Rgb24,
+        OutputLayout::Rgba,
+        OutputLayout::Bgra,
+        OutputLayout::Yuy2,
+        OutputLayout::Uyvy,
+        OutputLayout::I420,
+        OutputLayout::Nv12,
+    ];
+
+    /// Size in bytes of a `width` x `height` frame
+    pub fn frame_size(self, width: u32, height: u32) -> usize {
+        let pixels = width as usize * height as usize;
+        match self {
+            OutputLayout::Rgb24 => pixels * 3,
+            OutputLayout::Rgba | OutputLayout::Bgra => pixels * 4,
+            OutputLayout::Yuy2 | OutputLayout::Uyvy => pixels * 2,
+            OutputLayout::I420 | OutputLayout::Nv12 => pixels * 3 / 2,
+        }
+    }
+}
+
+/// Forward matrix rows `[R, G, B]` for Y, Cb and Cr, scaled by 65536
+///
+/// Luma is scaled to 219 codes and chroma to 224, so the results only need
+/// the 16 / 128 offsets. The chroma rows sum to zero so grays get 128.
+const fn forward(matrix: YuvMatrix) -> [[i32; 3]; 3] {
+    match matrix {
+        YuvMatrix::Bt601 => [
+            [16829, 33039, 6416],
+            [-9714, -19070, 28784],
+            [28784, -24103, -4681],
+        ],
+        YuvMatrix::Bt709 => [
+            [11966, 40254, 4064],
+            [-6596, -22188, 28784],
+            [28784, -26145, -2639],
+        ],
+    }
+}
+
+/// Apply one matrix row to an RGB triple, rounding, and add `offset`
+fn encode(row: [i32; 3], [r, g, b]: [i32; 3], offset: i32) -> u8 {
+    (((row[0] * r + row[1] * g + row[2] * b + 0x8000) >> 16) + offset).clamp(0, 255) as u8
+}
+
+/// RGB of pixel `index`
+fn pixel(rgb: &[u8], index: usize) -> [i32; 3] {
+    let p = &rgb[index * 3..index * 3 + 3];
+    [i32::from(p[0]), i32::from(p[1]), i32::from(p[2])]
+}
+
+/// (Cb, Cr) of the rounded mean of `pixels`
+fn chroma(matrix: &[[i32; 3]; 3], pixels: &[[i32; 3]]) -> (u8, u8) {
+    let n = pixels.len() as i32;
+    let mut mean = [0; 3];
+    for (c, sum) in mean.iter_mut().enumerate() {
+        *sum = (pixels.iter().map(|p| p[c]).sum::<i32>() + n / 2) / n;
+    }
+    (encode(matrix[1], mean, 128), encode(matrix[2], mean, 128))
+}
+
+/// Check that `data` holds a `width` x `height` frame of `bytes_per_pixel`
+fn check_size(
+    data: &[u8],
+    bytes_per_pixel: usize,
+    width: u32,
+    height: u32,
+    name: &str,
+) -> Result<(), ConversionError> {
+    let expected = width as usize * height as usize * bytes_per_pixel;
+    if data.len() < expected {
+        return Err(ConversionError(format!(
+            "{} data too small: {} bytes, expected {} for {}x{}",
+            name,
+            data.len(),
+            expected,
+            width,
+            height
+        )));
+    }
+    Ok(())
+}
+
+/// Check that chroma samples pair up: an even width, and an even height for 4:2:0
+fn check_subsampling(
+    width: u32,
+    height: u32,
+    vertical: bool,
+    name: &str,
+) -> Result<(), ConversionError> {
+    if !width.is_multiple_of(2) || (vertical && !height.is_multiple_of(2)) {
+        return Err(ConversionError(format!(
+            "{} needs even dimensions, got {}x{}",
+            name, width, height
+        )));
+    }
+    Ok(())
+}
+
+/// Convert an RGB888 frame to `layout`, encoding YUV layouts as BT.601
+///
+/// # Errors
+///
+/// Returns `ConversionError` if `rgb` is smaller than the frame or the
+/// dimensions don't suit the layout's chroma subsampling.
+pub fn convert_rgb(
+    rgb: &[u8],
+    width: u32,
+    height: u32,
+    layout: OutputLayout,
+) -> Result<Vec<u8>, ConversionError> {
+    convert_rgb_with_matrix(rgb, width, height, layout, YuvMatrix::Bt601)
+}
+
+/// Convert an RGB888 frame to `layout`, encoding YUV layouts with `matrix`
+///
+/// # Errors
+///
+/// Returns `ConversionError` if `rgb` is smaller than the frame or the
+/// dimensions don't suit the layout's chroma subsampling.
+pub fn convert_rgb_with_matrix(
+    rgb: &[u8],
+    width: u32,
+    height: u32,
+    layout: OutputLayout,
+    matrix: YuvMatrix,
+) -> Result<Vec<u8>, ConversionError> {
+    match layout {
+        OutputLayout::Rgb24 => {
+            check_size(rgb, 3, width, height, "RGB888")?;
+            Ok(rgb[..layout.frame_size(width, height)].to_vec())
+        }
+        OutputLayout::Rgba => rgb_to_rgba(rgb, width, height),
+        OutputLayout::Bgra => rgb_to_bgra(rgb, width, height),
+        OutputLayout::Yuy2 => rgb_to_yuv422(rgb, width, height, YuvPackedFormat::Yuyv, matrix),
+        OutputLayout::Uyvy => rgb_to_yuv422(rgb, width, height, YuvPackedFormat::Uyvy, matrix),
+        OutputLayout::I420 => rgb_to_i420(rgb, width, height, matrix),
+        OutputLayout::Nv12 => rgb_to_nv12(rgb, width, height, matrix),
+    }
+}
+
+/// Convert RGB888 to RGBA8888 with opaque alpha
+///
+/// # Errors
+///
+/// Returns `ConversionError` if `rgb` is smaller than the frame.
+pub fn rgb_to_rgba(rgb: &[u8], width: u32, height: u32) -> Result<Vec<u8>, ConversionError> {
+    check_size(rgb, 3, width, height, "RGB888")?;
+    let pixels = width as usize * height as usize;
+    let mut out = Vec::with_capacity(pixels * 4);
+    for p in rgb[..pixels * 3].chunks_exact(3) {
+        out.extend_from_slice(&[p[0], p[1], p[2], 255]);
+    }
+    Ok(out)
+}
+
+/// Convert RGB888 to BGRA8888 with opaque alpha
+///
+/// # Errors
+///
+/// Returns `ConversionError` if `rgb` is smaller than the frame.
+pub fn rgb_to_bgra(rgb: &[u8], width: u32, height: u32) -> Result<Vec<u8>, ConversionError> {
+    check_size(rgb, 3, width, height, "RGB888")?;
+    let pixels = width as usize * height as usize;
+    let mut out = Vec::with_capacity(pixels * 4);
+    for p in rgb[..pixels * 3].chunks_exact(3) {
+        out.extend_from_slice(&[p[2], p[1], p[0], 255]);
+    }
+    Ok(out)
+}
+
+/// Convert RGBA8888 to RGB888, dropping alpha
+///
+/// # Errors
+///
+/// Returns `ConversionError` if `rgba` is smaller than the frame.
+pub fn rgba_to_rgb(rgba: &[u8], width: u32, height: u32) -> Result<Vec<u8>, ConversionError> {
+    check_size(rgba, 4, width, height, "RGBA8888")?;
+    let pixels = width as usize * height as usize;
+    let mut out = Vec::with_capacity(pixels * 3);
+    for p in rgba[..pixels * 4].chunks_exact(4) {
+        out.extend_from_slice(&p[..3]);
+    }
+    Ok(out)
+}
+
+/// Convert BGRA8888 to RGB888, dropping alpha
+///
+/// # Errors
+///
+/// Returns `ConversionError` if `bgra` is smaller than the frame.
+pub fn bgra_to_rgb(bgra: &[u8], width: u32, height: u32) -> Result<Vec<u8>, ConversionError> {
+    check_size(bgra, 4, width, height, "BGRA8888")?;
+    let pixels = width as usize * height as usize;
+    let mut out = Vec::with_capacity(pixels * 3);
+    for p in bgra[..pixels * 4].chunks_exact(4) {
+        out.extend_from_slice(&[p[2], p[1], p[0]]);
+    }
+    Ok(out)
+}
+
+/// Encode RGB888 as packed YUV 4:2:2 (YUY2 or UYVY)
+///
+/// Each horizontal pixel pair shares the chroma of its mean color.
+///
+/// # Errors
+///
+/// Returns `ConversionError` if `rgb` is smaller than the frame or `width`
+/// is odd.
+pub fn rgb_to_yuv422(
+    rgb: &[u8],
+    width: u32,
+    height: u32,
+    format: YuvPackedFormat,
+    matrix: YuvMatrix,
+) -> Result<Vec<u8>, ConversionError> {
+    check_size(rgb, 3, width, height, "RGB888")?;
+    check_subsampling(width, height, false, "YUV 4:2:2")?;
+    let m = forward(matrix);
+    let pixels = width as usize * height as usize;
+    let mut out = Vec::with_capacity(pixels * 2);
+    // Rows have an even width, so pairs never straddle two rows
+    for i in (0..pixels).step_by(2) {
+        let (p0, p1) = (pixel(rgb, i), pixel(rgb, i + 1));
+        let (y0, y1) = (encode(m[0], p0, 16), encode(m[0], p1, 16));
+        let (u, v) = chroma(&m, &[p0, p1]);
+        match format {
+            YuvPackedFormat::Yuyv => out.extend_from_slice(&[y0, u, y1, v]),
+            YuvPackedFormat::Uyvy => out.extend_from_slice(&[u, y0, v, y1]),
+        }
+    }
+    Ok(out)
+}
+
+/// Encode YUV 4:2:0: the Y plane, then the (Cb, Cr) of every 2x2 block as
+/// U and V planes, or interleaved for `semi_planar`
+fn encode_420(
+    rgb: &[u8],
+    width: u32,
+    height: u32,
+    matrix: YuvMatrix,
+    semi_planar: bool,
+) -> Result<Vec<u8>, ConversionError> {
+    check_size(rgb, 3, width, height, "RGB888")?;
+    check_subsampling(width, height, true, "YUV 4:2:0")?;
+    let m = forward(matrix);
+    let (width, height) = (width as usize, height as usize);
+    let mut out = Vec::with_capacity(width * height * 3 / 2);
+    out.extend((0..width * height).map(|i| encode(m[0], pixel(rgb, i), 16)));
+    let mut chroma_samples = Vec::with_capacity(width * height / 4);
+    for y in (0..height).step_by(2) {
+        for x in (0..width).step_by(2) {
+            let top = y * width + x;
+            let bottom = top + width;
+            chroma_samples.push(chroma(
+                &m,
+                &[
+                    pixel(rgb, top),
+                    pixel(rgb, top + 1),
+                    pixel(rgb, bottom),
+                    pixel(rgb, bottom + 1),
+                ],
+            ));
+        }
+    }
+    if semi_planar {
+        out.extend(chroma_samples.iter().flat_map(|&(u, v)| [u, v]));
+    } else {
+        out.extend(chroma_samples.iter().map(|&(u, _)| u));
+        out.extend(chroma_samples.iter().map(|&(_, v)| v));
+    }
+    Ok(out)
+}
+
+/// Encode RGB888 as planar I420 (Y plane, U plane, V plane)
+///
+/// Each 2x2 block shares the chroma of its mean color.
+///
+/// # Errors
+///
+/// Returns `ConversionError` if `rgb` is smaller than the frame or a
+/// dimension is odd.
+pub fn rgb_to_i420(
+    rgb: &[u8],
+    width: u32,
+    height: u32,
+    matrix: YuvMatrix,
+) -> Result<Vec<u8>, ConversionError> {
+    encode_420(rgb, width, height, matrix, false)
+}
+
+/// Encode RGB888 as semi-planar NV12 (Y plane, interleaved UV plane)
+///
+/// Each 2x2 block shares the chroma of its mean color.
+///
+/// # Errors
+///
+/// Returns `ConversionError` if `rgb` is smaller than the frame or a
+/// dimension is odd.
+pub fn rgb_to_nv12(
+    rgb: &[u8],
+    width: u32,
+    height: u32,
+    matrix: YuvMatrix,
+) -> Result<Vec<u8>, ConversionError> {
+    encode_420(rgb, width, height, matrix, true)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::{Rgb, SMPTE_BARS};
+    use crate::yuv_conversion::scalar;
+
+    /// One row of `colors`, each two pixels wide, repeated for `rows` rows
+    fn bars(colors: &[Rgb], rows: u32) -> Vec<u8> {
+        let row: Vec<u8> = colors
+            .iter()
+            .flat_map(|c| [c.r, c.g, c.b, c.r, c.g, c.b])
+            .collect();
+        row.repeat(rows as usize)
+    }
+
+    #[test]
+    fn test_frame_sizes() {
+        let sizes: Vec<usize> = OutputLayout::ALL
+            .iter()
+            .map(|layout| layout.frame_size(4, 2))
+            .collect();
+        assert_eq!(sizes, vec![24, 32, 32, 16, 16, 12, 12]);
+    }
+
+    #[test]
+    fn test_rgba_and_bgra_round_trip() {
+        let rgb = [1, 2, 3, 4, 5, 6];
+        let rgba = rgb_to_rgba(&rgb, 2, 1).unwrap();
+        assert_eq!(rgba, [1, 2, 3, 255, 4, 5, 6, 255]);
+        let bgra = rgb_to_bgra(&rgb, 2, 1).unwrap();
+        assert_eq!(bgra, [3, 2, 1, 255, 6, 5, 4, 255]);
+        assert_eq!(rgba_to_rgb(&rgba, 2, 1).unwrap(), rgb);
+        assert_eq!(bgra_to_rgb(&bgra, 2, 1).unwrap(), rgb);
+    }
+
+    #[test]
+    fn test_yuy2_encodes_the_reference_codes() {
+        let colors: Vec<Rgb> = SMPTE_BARS.iter().map(|bar| bar.rgb).collect();
+        let rgb = bars(&colors, 1);
+        let width = colors.len() as u32 * 2;
+        for matrix in [YuvMatrix::Bt601, YuvMatrix::Bt709] {
+            let yuy2 = rgb_to_yuv422(&rgb, width, 1, YuvPackedFormat::Yuyv, matrix).unwrap();
+            for (bar, chunk) in SMPTE_BARS.iter().zip(yuy2.chunks_exact(4)) {
+                let expected = bar.ycbcr(matrix);
+                let actual = (chunk[0], chunk[1], chunk[3]);
+                assert_eq!(chunk[0], chunk[2], "{}", bar.name);
+                assert!(
+                    expected.0.abs_diff(actual.0) <= 1
+                        && expected.1.abs_diff(actual.1) <= 1
+                        && expected.2.abs_diff(actual.2) <= 1,
+                    "{} {:?}: expected {:?}, got {:?}",
+                    bar.name,
+                    matrix,
+                    expected,
+                    actual
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_yuv_layouts_decode_back_to_rgb() {
+        let colors: Vec<Rgb> = SMPTE_BARS.iter().map(|bar| bar.rgb).collect();
+        let rgb = bars(&colors, 2);
+        let width = colors.len() as u32 * 2;
+        let decoded = [
+            scalar::convert_yuv422_to_rgb(
+                &convert_rgb(&rgb, width, 2, OutputLayout::Yuy2).unwrap(),
+                width,
+                2,
+                None,
+                YuvPackedFormat::Yuyv,
+            ),
+            scalar::convert_yuv422_to_rgb(
+                &convert_rgb(&rgb, width, 2, OutputLayout::Uyvy).unwrap(),
+                width,
+                2,
+                None,
+                YuvPackedFormat::Uyvy,
+            ),
+            scalar::convert_i420_to_rgb(
+                &convert_rgb(&rgb, width, 2, OutputLayout::I420).unwrap(),
+                width,
+                2,
+            ),
+            scalar::convert_nv12_to_rgb(
+                &convert_rgb(&rgb, width, 2, OutputLayout::Nv12).unwrap(),
+                width,
+                2,
+            ),
+        ];
+        for round_trip in decoded {
+            let round_trip = round_trip.unwrap();
+            for (i, (&a, &b)) in rgb.iter().zip(&round_trip).enumerate() {
+                assert!(a.abs_diff(b) <= 3, "byte {}: {} became {}", i, a, b);
+            }
+        }
+    }
+
+    #[test]
+    fn test_chroma_is_averaged() {
+        // Red and blue share one chroma sample, between the two
+        let rgb = [255, 0, 0, 0, 0, 255];
+        let yuy2 = rgb_to_yuv422(&rgb, 2, 1, YuvPackedFormat::Yuyv, YuvMatrix::Bt601).unwrap();
+        let (u, v) = (yuy2[1], yuy2[3]);
+        let (red_u, red_v) = chroma(&forward(YuvMatrix::Bt601), &[[255, 0, 0]]);
+        let (blue_u, blue_v) = chroma(&forward(YuvMatrix::Bt601), &[[0, 0, 255]]);
+        assert!(u > red_u && u < blue_u);
+        assert!(v < red_v && v > blue_v);
+    }
+
+    #[test]
+    fn test_rejects_short_data_and_odd_sizes() {
+        assert!(rgb_to_rgba(&[0; 5], 2, 1).is_err());
+        assert!(bgra_to_rgb(&[0; 7], 2, 1).is_err());
+        assert!(convert_rgb(&[0; 9], 3, 1, OutputLayout::Yuy2).is_err());
+        assert!(convert_rgb(&[0; 18], 3, 2, OutputLayout::Rgb24).is_ok());
+        assert!(convert_rgb(&[0; 18], 2, 3, OutputLayout::Nv12).is_err());
+        assert!(convert_rgb(&[0; 18], 2, 3, OutputLayout::Uyvy).is_ok());
+    }
+}
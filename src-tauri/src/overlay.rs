@@ -0,0 +1,319 @@
+//! Drawing annotations into RGB24 frames
+//!
+//! Used to burn annotations into recordings and frozen-frame composites,
+//! where the frontend's canvas overlay isn't available. Boxes and circles are
+//! drawn as outlines, measurements as lines with end markers, arrows as lines
+//! with a head and labels and text positions as markers; text is left to the
+//! frontend. Everything is clipped to the frame.
+
+use crate::annotations::{Annotation, AnnotationKind};
+use crate::freeze::{Shape, VectorAnnotation};
+
+/// Outline thickness in pixels
+const LINE_WIDTH: u32 = 2;
+
+/// Half the size of point and end markers in pixels
+const MARKER_RADIUS: u32 = 3;
+
+/// Colour of boxes
+const BOX_COLOR: [u8; 3] = [255, 220, 0];
+
+/// Colour of measurements
+const MEASUREMENT_COLOR: [u8; 3] = [0, 220, 255];
+
+/// Colour of labels
+const LABEL_COLOR: [u8; 3] = [255, 255, 255];
+
+/// Default colour of arrows and circles on frozen frames
+const VECTOR_COLOR: [u8; 3] = [255, 64, 64];
+
+/// Length of an arrow head's sides in pixels
+const ARROW_HEAD_LENGTH: f32 = 12.0;
+
+/// Angle between an arrow's shaft and each side of its head
+const ARROW_HEAD_ANGLE: f32 = std::f32::consts::PI / 6.0;
+
+/// Draw `annotations` into an RGB24 frame
+///
+/// Does nothing if `rgb` is smaller than `width * height * 3`.
+pub fn draw_annotations(rgb: &mut [u8], width: u32, height: u32, annotations: &[Annotation]) {
+    if rgb.len() < width as usize * height as usize * 3 {
+        return;
+    }
+    let mut canvas = Canvas { rgb, width, height };
+    for annotation in annotations {
+        match annotation.kind {
+            AnnotationKind::Box {
+                x,
+                y,
+                width,
+                height,
+            } => canvas.outline(x, y, width, height, BOX_COLOR),
+            AnnotationKind::Label { x, y } => canvas.marker(x, y, LABEL_COLOR),
+            AnnotationKind::Measurement { x1, y1, x2, y2, .. } => {
+                canvas.line(
+                    i64::from(x1),
+                    i64::from(y1),
+                    i64::from(x2),
+                    i64::from(y2),
+                    MEASUREMENT_COLOR,
+                );
+                canvas.marker(x1, y1, MEASUREMENT_COLOR);
+                canvas.marker(x2, y2, MEASUREMENT_COLOR);
+            }
+        }
+    }
+}
+
+/// Draw frozen-frame annotations into an RGB24 frame
+///
+/// Does nothing if `rgb` is smaller than `width * height * 3`.
+pub fn draw_vector_annotations(
+    rgb: &mut [u8],
+    width: u32,
+    height: u32,
+    annotations: &[VectorAnnotation],
+) {
+    if rgb.len() < width as usize * height as usize * 3 {
+        return;
+    }
+    let mut canvas = Canvas { rgb, width, height };
+    for annotation in annotations {
+        match &annotation.shape {
+            Shape::Arrow { x1, y1, x2, y2 } => {
+                let color = annotation.color.unwrap_or(VECTOR_COLOR);
+                canvas.arrow((*x1, *y1), (*x2, *y2), color);
+            }
+            Shape::Circle { x, y, radius } => {
+                let color = annotation.color.unwrap_or(VECTOR_COLOR);
+                canvas.circle(*x, *y, *radius, color);
+            }
+            Shape::Text { x, y, .. } => {
+                let color = annotation.color.unwrap_or(LABEL_COLOR);
+                canvas.marker(*x as u32, *y as u32, color);
+            }
+        }
+    }
+}
+
+struct Canvas<'a> {
+    rgb: &'a mut [u8],
+    width: u32,
+    height: u32,
+}
+
+impl Canvas<'_> {
+    /// Fill a rectangle, clipped to the frame
+    fn fill(&mut self, x: u32, y: u32, width: u32, height: u32, color: [u8; 3]) {
+        let x_end = x.saturating_add(width).min(self.width);
+        let y_end = y.saturating_add(height).min(self.height);
+        for row in y.min(y_end)..y_end {
+            let start = (row as usize * self.width as usize + x.min(x_end) as usize) * 3;
+            let end = (row as usize * self.width as usize + x_end as usize) * 3;
+            for pixel in self.rgb[start..end].chunks_exact_mut(3) {
+                pixel.copy_from_slice(&color);
+            }
+        }
+    }
+
+    fn outline(&mut self, x: u32, y: u32, width: u32, height: u32, color: [u8; 3]) {
+        let line = LINE_WIDTH.min(width).min(height);
+        self.fill(x, y, width, line, color);
+        self.fill(x, y.saturating_add(height - line), width, line, color);
+        self.fill(x, y, line, height, color);
+        self.fill(x.saturating_add(width - line), y, line, height, color);
+    }
+
+    fn marker(&mut self, x: u32, y: u32, color: [u8; 3]) {
+        let size = MARKER_RADIUS * 2 + 1;
+        self.fill(
+            x.saturating_sub(MARKER_RADIUS),
+            y.saturating_sub(MARKER_RADIUS),
+            size,
+            size,
+            color,
+        );
+    }
+
+    /// Bresenham line of `LINE_WIDTH` square dots
+    ///
+    /// Ends may lie outside the frame; dots left or above it are skipped.
+    fn line(&mut self, x1: i64, y1: i64, x2: i64, y2: i64, color: [u8; 3]) {
+        let (mut x, mut y) = (x1, y1);
+        let dx = (x2 - x).abs();
+        let dy = -(y2 - y).abs();
+        let step_x = if x < x2 { 1 } else { -1 };
+        let step_y = if y < y2 { 1 } else { -1 };
+        let mut error = dx + dy;
+        loop {
+            if x >= 0 && y >= 0 {
+                self.fill(x as u32, y as u32, LINE_WIDTH, LINE_WIDTH, color);
+            }
+            if x == x2 && y == y2 {
+                break;
+            }
+            let doubled = 2 * error;
+            if doubled >= dy {
+                error += dy;
+                x += step_x;
+            }
+            if doubled <= dx {
+                error += dx;
+                y += step_y;
+            }
+        }
+    }
+
+    /// Line from `from` to `to` with a head at `to`
+    fn arrow(&mut self, from: (f32, f32), to: (f32, f32), color: [u8; 3]) {
+        let round = |p: (f32, f32)| (p.0.round() as i64, p.1.round() as i64);
+        let (x1, y1) = round(from);
+        let (x2, y2) = round(to);
+        self.line(x1, y1, x2, y2, color);
+
+        if (to.0 - from.0).hypot(to.1 - from.1) == 0.0 {
+            return;
+        }
+        let shaft = (to.1 - from.1).atan2(to.0 - from.0);
+        for side in [-ARROW_HEAD_ANGLE, ARROW_HEAD_ANGLE] {
+            let angle = shaft + std::f32::consts::PI + side;
+            let end = round((
+                to.0 + ARROW_HEAD_LENGTH * angle.cos(),
+                to.1 + ARROW_HEAD_LENGTH * angle.sin(),
+            ));
+            self.line(x2, y2, end.0, end.1, color);
+        }
+    }
+
+    /// Circle outline of `LINE_WIDTH` square dots (midpoint algorithm)
+    fn circle(&mut self, cx: f32, cy: f32, radius: f32, color: [u8; 3]) {
+        let (cx, cy) = (cx.round() as i64, cy.round() as i64);
+        let r = radius.round().max(1.0) as i64;
+        let (mut x, mut y) = (r, 0i64);
+        let mut error = 1 - r;
+        while x >= y {
+            for (dx, dy) in [
+                (x, y),
+                (y, x),
+                (-y, x),
+                (-x, y),
+                (-x, -y),
+                (-y, -x),
+                (y, -x),
+                (x, -y),
+            ] {
+                let (px, py) = (cx + dx, cy + dy);
+                if px >= 0 && py >= 0 {
+                    self.fill(px as u32, py as u32, LINE_WIDTH, LINE_WIDTH, color);
+                }
+            }
+            y += 1;
+            if error < 0 {
+                error += 2 * y + 1;
+            } else {
+                x -= 1;
+                error += 2 * (y - x) + 1;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pixel(rgb: &[u8], width: u32, x: u32, y: u32) -> [u8; 3] {
+        let i = (y * width + x) as usize * 3;
+        [rgb[i], rgb[i + 1], rgb[i + 2]]
+    }
+
+    fn annotation(kind: AnnotationKind) -> Annotation {
+        Annotation {
+            source: "test".to_string(),
+            label: String::new(),
+            confidence: 1.0,
+            kind,
+        }
+    }
+
+    #[test]
+    fn test_box_is_drawn_as_outline() {
+        let mut rgb = vec![0u8; 20 * 20 * 3];
+        let boxed = annotation(AnnotationKind::Box {
+            x: 2,
+            y: 2,
+            width: 10,
+            height: 10,
+        });
+        draw_annotations(&mut rgb, 20, 20, &[boxed]);
+        assert_eq!(pixel(&rgb, 20, 2, 2), BOX_COLOR);
+        assert_eq!(pixel(&rgb, 20, 11, 11), BOX_COLOR);
+        assert_eq!(pixel(&rgb, 20, 6, 6), [0, 0, 0]);
+        assert_eq!(pixel(&rgb, 20, 12, 12), [0, 0, 0]);
+    }
+
+    #[test]
+    fn test_drawing_is_clipped_to_the_frame() {
+        let mut rgb = vec![0u8; 8 * 8 * 3];
+        let annotations = [
+            annotation(AnnotationKind::Box {
+                x: 6,
+                y: 6,
+                width: 100,
+                height: 100,
+            }),
+            annotation(AnnotationKind::Measurement {
+                x1: 0,
+                y1: 7,
+                x2: 50,
+                y2: 7,
+                value: 1.0,
+                unit: "mm".to_string(),
+            }),
+            annotation(AnnotationKind::Label { x: 0, y: 0 }),
+        ];
+        draw_annotations(&mut rgb, 8, 8, &annotations);
+        assert_eq!(pixel(&rgb, 8, 6, 6), BOX_COLOR);
+        assert_eq!(pixel(&rgb, 8, 3, 7), MEASUREMENT_COLOR);
+        assert_eq!(pixel(&rgb, 8, 0, 0), LABEL_COLOR);
+
+        // Too-small buffers are left alone
+        let mut short = vec![0u8; 10];
+        draw_annotations(&mut short, 8, 8, &annotations);
+        assert!(short.iter().all(|&b| b == 0));
+    }
+
+    #[test]
+    fn test_vector_annotations() {
+        let mut rgb = vec![0u8; 40 * 40 * 3];
+        let annotations = [
+            VectorAnnotation {
+                id: 1,
+                shape: Shape::Circle {
+                    x: 20.0,
+                    y: 20.0,
+                    radius: 10.0,
+                },
+                color: None,
+            },
+            VectorAnnotation {
+                id: 2,
+                // Head reaches past the left edge
+                shape: Shape::Arrow {
+                    x1: 20.0,
+                    y1: 2.0,
+                    x2: 2.0,
+                    y2: 2.0,
+                },
+                color: Some([0, 255, 0]),
+            },
+        ];
+        draw_vector_annotations(&mut rgb, 40, 40, &annotations);
+        assert_eq!(pixel(&rgb, 40, 30, 20), VECTOR_COLOR);
+        assert_eq!(pixel(&rgb, 40, 20, 30), VECTOR_COLOR);
+        assert_eq!(pixel(&rgb, 40, 20, 20), [0, 0, 0]);
+        assert_eq!(pixel(&rgb, 40, 10, 2), [0, 255, 0]);
+        // Head sides run back towards the tail from the tip
+        assert_eq!(pixel(&rgb, 40, 12, 8), [0, 255, 0]);
+    }
+}
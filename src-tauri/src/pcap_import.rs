@@ -0,0 +1,607 @@
+//! Wireshark USB capture import for replay
+//!
+//! Reads pcap and pcapng files of Linux usbmon traffic (Wireshark on a
+//! `usbmonN` interface, `tcpdump -i usbmonN`, or the app's own
+//! [`crate::capture::export_pcapng`]) and turns the completed isochronous and
+//! bulk IN transfers of one endpoint into [`ReplayPacket`]s, so a camera
+//! captured on a Linux desktop can be replayed through
+//! [`crate::frame_assembler::FrameAssembler`] to debug stride and format
+//! problems.
+//!
+//! Both usbmon link types are understood: `LINKTYPE_USB_LINUX` (189, 48-byte
+//! header) and `LINKTYPE_USB_LINUX_MMAPPED` (220, 64-byte header). With the
+//! 64-byte header every isochronous packet descriptor of a completed URB
+//! becomes one replay packet, as the app's own captures record them; the
+//! 48-byte header carries no descriptors, so each URB becomes one packet. A
+//! bulk completion is one packet. Submissions, failed transfers, OUT
+//! endpoints and control and interrupt traffic are skipped, as are pcapng
+//! blocks other than Enhanced Packet Blocks. Timestamps are made relative to
+//! the first imported packet.
+//!
+//! The usbmon header is in the capturing host's byte order, which is assumed
+//! to be the file's.
+
+use std::collections::HashMap;
+use std::path::Path;
+
+use crate::capture::LINKTYPE_USB_LINUX_MMAPPED;
+use crate::replay::{ReplayError, ReplayPacket, Result};
+
+/// Link type for Linux usbmon with the 48-byte header
+pub const LINKTYPE_USB_LINUX: u16 = 189;
+
+/// pcap magic number with microsecond timestamps
+const PCAP_MAGIC_US: u32 = 0xA1B2_C3D4;
+
+/// pcap magic number with nanosecond timestamps
+const PCAP_MAGIC_NS: u32 = 0xA1B2_3C4D;
+
+/// pcapng Section Header Block type (the same in both byte orders)
+const PCAPNG_SECTION_HEADER: u32 = 0x0A0D_0D0A;
+
+/// pcapng byte-order magic in the Section Header Block
+const PCAPNG_BYTE_ORDER_MAGIC: u32 = 0x1A2B_3C4D;
+
+/// pcapng Interface Description Block type
+const PCAPNG_INTERFACE: u32 = 1;
+
+/// pcapng Enhanced Packet Block type
+const PCAPNG_ENHANCED_PACKET: u32 = 6;
+
+/// Interface option giving the timestamp resolution
+const PCAPNG_OPTION_TSRESOL: u16 = 9;
+
+/// usbmon transfer types
+const XFER_ISOCHRONOUS: u8 = 0;
+const XFER_BULK: u8 = 3;
+
+/// Size of one usbmon isochronous packet descriptor
+const ISO_DESCRIPTOR_SIZE: usize = 16;
+
+/// Whether `header` (the start of a file) is a pcap or pcapng capture
+pub fn is_pcap(header: &[u8]) -> bool {
+    let Some(magic) = header.get(..4) else {
+        return false;
+    };
+    let le = u32::from_le_bytes([magic[0], magic[1], magic[2], magic[3]]);
+    let be = u32::from_be_bytes([magic[0], magic[1], magic[2], magic[3]]);
+    le == PCAPNG_SECTION_HEADER
+        || [PCAP_MAGIC_US, PCAP_MAGIC_NS].contains(&le)
+        || [PCAP_MAGIC_US, PCAP_MAGIC_NS].contains(&be)
+}
+
+/// Read the IN packets of `endpoint` from a pcap or pcapng file
+///
+/// See [`parse`].
+///
+/// # Errors
+///
+/// Returns `ReplayError::FileOpen` if the file cannot be read, or any error
+/// of [`parse`].
+pub fn load(path: &Path, endpoint: Option<u8>) -> Result<Vec<ReplayPacket>> {
+    let data = std::fs::read(path)?;
+    parse(&data, endpoint)
+}
+
+/// Extract the IN packets of `endpoint` from pcap or pcapng data
+///
+/// `endpoint` is an endpoint address or number (`0x81` and `1` both mean
+/// IN 1). If several devices used it, or no endpoint is given, the
+/// device/endpoint pair that carried the most payload is taken: with a whole
+/// bus captured, that is the camera.
+///
+/// # Errors
+///
+/// Returns `ReplayError::InvalidPacket` if the data is not pcap or pcapng
+/// or its structure is broken, `ReplayError::UnsupportedLinkType` if it holds
+/// no usbmon traffic, and `ReplayError::NoPackets` if no isochronous or bulk
+/// IN transfer matches.
+pub fn parse(data: &[u8], endpoint: Option<u8>) -> Result<Vec<ReplayPacket>> {
+    let mut payloads = Vec::new();
+    if data.len() >= 4
+        && u32::from_le_bytes([data[0], data[1], data[2], data[3]]) == PCAPNG_SECTION_HEADER
+    {
+        read_pcapng(data, &mut payloads)?;
+    } else {
+        read_pcap(data, &mut payloads)?;
+    }
+    select_stream(payloads, endpoint)
+}
+
+/// A completed IN transfer (or isochronous packet) read from a capture
+struct UsbPayload<'a> {
+    /// Capture timestamp (microseconds since the Unix epoch)
+    timestamp_us: u64,
+    /// Bus number, device address and endpoint address
+    stream: (u16, u8, u8),
+    data: &'a [u8],
+}
+
+/// Fixed-endianness reads from a byte slice
+#[derive(Clone, Copy)]
+struct Reader<'a> {
+    data: &'a [u8],
+    big_endian: bool,
+}
+
+impl Reader<'_> {
+    fn bytes<const N: usize>(&self, offset: usize) -> Option<[u8; N]> {
+        self.data
+            .get(offset..offset.checked_add(N)?)?
+            .try_into()
+            .ok()
+    }
+
+    fn u16(&self, offset: usize) -> Option<u16> {
+        let b = self.bytes(offset)?;
+        Some(if self.big_endian {
+            u16::from_be_bytes(b)
+        } else {
+            u16::from_le_bytes(b)
+        })
+    }
+
+    fn u32(&self, offset: usize) -> Option<u32> {
+        let b = self.bytes(offset)?;
+        Some(if self.big_endian {
+            u32::from_be_bytes(b)
+        } else {
+            u32::from_le_bytes(b)
+        })
+    }
+
+    fn i32(&self, offset: usize) -> Option<i32> {
+        self.u32(offset).map(|v| v as i32)
+    }
+}
+
+fn invalid(offset: usize, message: impl Into<String>) -> ReplayError {
+    ReplayError::InvalidPacket {
+        offset: offset as u64,
+        message: message.into(),
+    }
+}
+
+/// usbmon header size of a link type, or `None` if it isn't usbmon
+fn usbmon_header_len(link_type: u32) -> Option<usize> {
+    match link_type {
+        t if t == u32::from(LINKTYPE_USB_LINUX) => Some(48),
+        t if t == u32::from(LINKTYPE_USB_LINUX_MMAPPED) => Some(64),
+        _ => None,
+    }
+}
+
+/// Read a classic pcap file
+fn read_pcap<'a>(data: &'a [u8], out: &mut Vec<UsbPayload<'a>>) -> Result<()> {
+    let magic = data
+        .get(..4)
+        .map(|m| u32::from_le_bytes([m[0], m[1], m[2], m[3]]))
+        .ok_or_else(|| invalid(0, "file too short for a pcap header"))?;
+    let (big_endian, nanoseconds) = match magic {
+        PCAP_MAGIC_US => (false, false),
+        PCAP_MAGIC_NS => (false, true),
+        m if m.swap_bytes() == PCAP_MAGIC_US => (true, false),
+        m if m.swap_bytes() == PCAP_MAGIC_NS => (true, true),
+        _ => return Err(invalid(0, "not a pcap or pcapng file")),
+    };
+    let file = Reader { data, big_endian };
+    // The upper bits of the link type field hold FCS information
+    let link_type = file
+        .u32(20)
+        .ok_or_else(|| invalid(0, "file too short for a pcap header"))?
+        & 0x0FFF_FFFF;
+    let header_len =
+        usbmon_header_len(link_type).ok_or(ReplayError::UnsupportedLinkType(link_type))?;
+
+    let mut offset = 24;
+    while offset < data.len() {
+        let (Some(seconds), Some(fraction), Some(captured)) =
+            (file.u32(offset), file.u32(offset + 4), file.u32(offset + 8))
+        else {
+            log::warn!("Ignoring truncated pcap record at offset {}", offset);
+            break;
+        };
+        let start = offset + 16;
+        let Some(record) = data.get(start..start + captured as usize) else {
+            log::warn!("Ignoring truncated pcap record at offset {}", offset);
+            break;
+        };
+        let fraction_us = if nanoseconds {
+            u64::from(fraction) / 1000
+        } else {
+            u64::from(fraction)
+        };
+        let timestamp_us = u64::from(seconds) * 1_000_000 + fraction_us;
+        read_usbmon(record, header_len, big_endian, timestamp_us, out);
+        offset = start + captured as usize;
+    }
+    Ok(())
+}
+
+/// A pcapng interface's link type and timestamp resolution
+struct Interface {
+    header_len: Option<usize>,
+    tsresol: u8,
+}
+
+/// Read a pcapng file
+fn read_pcapng<'a>(data: &'a [u8], out: &mut Vec<UsbPayload<'a>>) -> Result<()> {
+    let mut file = Reader {
+        data,
+        big_endian: false,
+    };
+    let mut interfaces: Vec<Interface> = Vec::new();
+    let mut link_types = Vec::new();
+
+    let mut offset = 0;
+    while offset < data.len() {
+        let Some(block_type) = file.u32(offset) else {
+            log::warn!("Ignoring truncated pcapng block at offset {}", offset);
+            break;
+        };
+        if block_type == PCAPNG_SECTION_HEADER {
+            // Each section sets its own byte order and interfaces
+            file.big_endian = match file.u32(offset + 8) {
+                Some(PCAPNG_BYTE_ORDER_MAGIC) => file.big_endian,
+                Some(m) if m.swap_bytes() == PCAPNG_BYTE_ORDER_MAGIC => !file.big_endian,
+                _ => return Err(invalid(offset, "bad pcapng byte-order magic")),
+            };
+            interfaces.clear();
+        }
+        let Some(total_len) = file.u32(offset + 4).map(|n| n as usize) else {
+            log::warn!("Ignoring truncated pcapng block at offset {}", offset);
+            break;
+        };
+        if total_len < 12 || !total_len.is_multiple_of(4) {
+            return Err(invalid(
+                offset,
+                format!("bad pcapng block length {}", total_len),
+            ));
+        }
+        let Some(block) = data.get(offset..offset + total_len) else {
+            log::warn!("Ignoring truncated pcapng block at offset {}", offset);
+            break;
+        };
+        let body = Reader {
+            data: &block[8..total_len - 4],
+            big_endian: file.big_endian,
+        };
+
+        match block_type {
+            PCAPNG_INTERFACE => {
+                let link_type = u32::from(body.u16(0).unwrap_or(0));
+                link_types.push(link_type);
+                interfaces.push(Interface {
+                    header_len: usbmon_header_len(link_type),
+                    tsresol: interface_tsresol(body),
+                });
+            }
+            PCAPNG_ENHANCED_PACKET => {
+                let (Some(id), Some(high), Some(low), Some(captured)) =
+                    (body.u32(0), body.u32(4), body.u32(8), body.u32(12))
+                else {
+                    return Err(invalid(offset, "truncated enhanced packet block"));
+                };
+                let interface = interfaces
+                    .get(id as usize)
+                    .ok_or_else(|| invalid(offset, format!("unknown interface {}", id)))?;
+                let record = body
+                    .data
+                    .get(20..20 + captured as usize)
+                    .ok_or_else(|| invalid(offset, "packet data exceeds its block"))?;
+                if let Some(header_len) = interface.header_len {
+                    let timestamp = (u64::from(high) << 32) | u64::from(low);
+                    let timestamp_us = timestamp_to_us(timestamp, interface.tsresol);
+                    read_usbmon(record, header_len, file.big_endian, timestamp_us, out);
+                }
+            }
+            _ => {}
+        }
+        offset += total_len;
+    }
+
+    if !link_types.is_empty() && link_types.iter().all(|&t| usbmon_header_len(t).is_none()) {
+        return Err(ReplayError::UnsupportedLinkType(link_types[0]));
+    }
+    Ok(())
+}
+
+/// The `if_tsresol` option of an Interface Description Block body
+/// (default: microseconds)
+fn interface_tsresol(body: Reader<'_>) -> u8 {
+    let mut offset = 8;
+    while let (Some(code), Some(len)) = (body.u16(offset), body.u16(offset + 2)) {
+        if code == 0 {
+            break;
+        }
+        if code == PCAPNG_OPTION_TSRESOL && len >= 1 {
+            if let Some(&resolution) = body.data.get(offset + 4) {
+                return resolution;
+            }
+        }
+        offset += 4 + usize::from(len).next_multiple_of(4);
+    }
+    6
+}
+
+/// Convert a pcapng timestamp in units of `tsresol` to microseconds
+///
+/// A clear top bit means units of 10^-n seconds, a set one 2^-n seconds.
+fn timestamp_to_us(timestamp: u64, tsresol: u8) -> u64 {
+    let exponent = u32::from(tsresol & 0x7F);
+    let us = if tsresol & 0x80 != 0 {
+        (u128::from(timestamp) * 1_000_000) >> exponent.min(127)
+    } else if exponent <= 6 {
+        u128::from(timestamp) * 10u128.pow(6 - exponent)
+    } else {
+        10u128
+            .checked_pow(exponent - 6)
+            .map_or(0, |unit| u128::from(timestamp) / unit)
+    };
+    us.min(u128::from(u64::MAX)) as u64
+}
+
+/// Add the IN payloads of one usbmon record to `out`
+fn read_usbmon<'a>(
+    record: &'a [u8],
+    header_len: usize,
+    big_endian: bool,
+    timestamp_us: u64,
+    out: &mut Vec<UsbPayload<'a>>,
+) {
+    if record.len() < header_len {
+        return;
+    }
+    let header = Reader {
+        data: record,
+        big_endian,
+    };
+    let (event, transfer_type, endpoint, device) = (record[8], record[9], record[10], record[11]);
+    let flag_data = record[15];
+    // Completions of IN transfers whose data was captured
+    if event != b'C' || endpoint & 0x80 == 0 || flag_data != 0 {
+        return;
+    }
+    let stream = (header.u16(12).unwrap_or(0), device, endpoint);
+    let status = header.i32(28).unwrap_or(-1);
+    let captured = header.u32(36).unwrap_or(0) as usize;
+    let data = &record[header_len..];
+
+    match transfer_type {
+        XFER_BULK if status == 0 => {
+            let data = &data[..captured.min(data.len())];
+            if !data.is_empty() {
+                out.push(UsbPayload {
+                    timestamp_us,
+                    stream,
+                    data,
+                });
+            }
+        }
+        XFER_ISOCHRONOUS if header_len == 64 => {
+            let count = header.u32(60).unwrap_or(0) as usize;
+            let Some(descriptors) = data.get(..count.saturating_mul(ISO_DESCRIPTOR_SIZE)) else {
+                return;
+            };
+            let buffer = &data[descriptors.len()..];
+            let descriptors = Reader {
+                data: descriptors,
+                big_endian,
+            };
+            for i in 0..count {
+                let at = i * ISO_DESCRIPTOR_SIZE;
+                let (Some(status), Some(offset), Some(len)) = (
+                    descriptors.i32(at),
+                    descriptors.u32(at + 4),
+                    descriptors.u32(at + 8),
+                ) else {
+                    break;
+                };
+                let (offset, len) = (offset as usize, len as usize);
+                if status != 0 || len == 0 {
+                    continue;
+                }
+                // Packets beyond the captured length were truncated
+                if let Some(packet) = buffer.get(offset..offset + len) {
+                    out.push(UsbPayload {
+                        timestamp_us,
+                        stream,
+                        data: packet,
+                    });
+                }
+            }
+        }
+        XFER_ISOCHRONOUS => {
+            let data = &data[..captured.min(data.len())];
+            if status == 0 && !data.is_empty() {
+                out.push(UsbPayload {
+                    timestamp_us,
+                    stream,
+                    data,
+                });
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Keep the payloads of the busiest device/endpoint matching `endpoint`
+fn select_stream(payloads: Vec<UsbPayload<'_>>, endpoint: Option<u8>) -> Result<Vec<ReplayPacket>> {
+    let endpoint = endpoint.map(|e| e | 0x80);
+    let mut bytes: HashMap<(u16, u8, u8), usize> = HashMap::new();
+    for payload in &payloads {
+        if endpoint.is_none_or(|e| payload.stream.2 == e) {
+            *bytes.entry(payload.stream).or_default() += payload.data.len();
+        }
+    }
+    let (stream, _) = bytes
+        .into_iter()
+        .max_by_key(|&(stream, total)| (total, std::cmp::Reverse(stream)))
+        .ok_or(ReplayError::NoPackets)?;
+
+    let mut selected: Vec<&UsbPayload<'_>> =
+        payloads.iter().filter(|p| p.stream == stream).collect();
+    // Isochronous packets of one URB share its timestamp; keep their order
+    selected.sort_by_key(|p| p.timestamp_us);
+    let start = selected.first().map_or(0, |p| p.timestamp_us);
+    log::info!(
+        "Imported {} packets from bus {} device {} endpoint {:#04x}",
+        selected.len(),
+        stream.0,
+        stream.1,
+        stream.2
+    );
+    Ok(selected
+        .into_iter()
+        .map(|p| ReplayPacket {
+            timestamp_us: p.timestamp_us.saturating_sub(start),
+            endpoint: stream.2,
+            data: p.data.to_vec(),
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::capture::{write_pcapng, CapturedPacket};
+
+    fn captured(timestamp_us: u64, data: &[u8]) -> CapturedPacket {
+        CapturedPacket {
+            timestamp_us,
+            endpoint: 0x81,
+            data: data.to_vec(),
+        }
+    }
+
+    /// A 64-byte usbmon record with the given event, type, endpoint and
+    /// iso descriptors `(status, offset, len)`, followed by `data`
+    fn usbmon(
+        event: u8,
+        transfer_type: u8,
+        endpoint: u8,
+        device: u8,
+        descriptors: &[(i32, u32, u32)],
+        data: &[u8],
+    ) -> Vec<u8> {
+        let mut record = vec![0u8; 64];
+        record[8] = event;
+        record[9] = transfer_type;
+        record[10] = endpoint;
+        record[11] = device;
+        record[12..14].copy_from_slice(&1u16.to_le_bytes());
+        record[36..40].copy_from_slice(&(data.len() as u32).to_le_bytes());
+        record[60..64].copy_from_slice(&(descriptors.len() as u32).to_le_bytes());
+        for &(status, offset, len) in descriptors {
+            record.extend_from_slice(&status.to_le_bytes());
+            record.extend_from_slice(&offset.to_le_bytes());
+            record.extend_from_slice(&len.to_le_bytes());
+            record.extend_from_slice(&0u32.to_le_bytes());
+        }
+        record.extend_from_slice(data);
+        record
+    }
+
+    /// A little-endian microsecond pcap file of usbmon records
+    fn pcap(link_type: u32, records: &[(u64, Vec<u8>)]) -> Vec<u8> {
+        let mut out = Vec::new();
+        out.extend_from_slice(&PCAP_MAGIC_US.to_le_bytes());
+        out.extend_from_slice(&2u16.to_le_bytes());
+        out.extend_from_slice(&4u16.to_le_bytes());
+        out.extend_from_slice(&[0u8; 8]);
+        out.extend_from_slice(&65535u32.to_le_bytes());
+        out.extend_from_slice(&link_type.to_le_bytes());
+        for (timestamp_us, record) in records {
+            out.extend_from_slice(&((timestamp_us / 1_000_000) as u32).to_le_bytes());
+            out.extend_from_slice(&((timestamp_us % 1_000_000) as u32).to_le_bytes());
+            out.extend_from_slice(&(record.len() as u32).to_le_bytes());
+            out.extend_from_slice(&(record.len() as u32).to_le_bytes());
+            out.extend_from_slice(record);
+        }
+        out
+    }
+
+    #[test]
+    fn test_exported_pcapng_round_trips() {
+        let packets = vec![
+            captured(0, &[0x02, 0x80, 1, 2, 3]),
+            captured(125, &[0x02, 0x80, 4]),
+            captured(2_000_250, &[0x02, 0x82, 5, 6]),
+        ];
+        let mut file = Vec::new();
+        write_pcapng(&mut file, &packets, 1_700_000_000_000_000).unwrap();
+        assert!(is_pcap(&file));
+
+        let imported = parse(&file, Some(1)).unwrap();
+        assert_eq!(imported.len(), 3);
+        for (read, written) in imported.iter().zip(&packets) {
+            assert_eq!(read.timestamp_us, written.timestamp_us);
+            assert_eq!(read.endpoint, 0x81);
+            assert_eq!(read.data, written.data);
+        }
+    }
+
+    #[test]
+    fn test_pcap_splits_iso_descriptors_and_skips_other_traffic() {
+        let records = vec![
+            // Submission, OUT transfer and interrupt traffic are skipped
+            (1_000, usbmon(b'S', XFER_ISOCHRONOUS, 0x81, 5, &[], &[])),
+            (1_000, usbmon(b'C', XFER_BULK, 0x02, 5, &[], &[9; 8])),
+            (1_000, usbmon(b'C', 1, 0x83, 5, &[], &[9; 8])),
+            // Two good packets and one failed one in a single URB
+            (
+                2_000,
+                usbmon(
+                    b'C',
+                    XFER_ISOCHRONOUS,
+                    0x81,
+                    5,
+                    &[(0, 0, 3), (-18, 3, 2), (0, 8, 2)],
+                    &[1, 2, 3, 7, 7, 0, 0, 0, 4, 5],
+                ),
+            ),
+            // A busier bulk endpoint on another device
+            (3_000, usbmon(b'C', XFER_BULK, 0x82, 6, &[], &[8; 64])),
+        ];
+        let file = pcap(u32::from(LINKTYPE_USB_LINUX_MMAPPED), &records);
+
+        let iso = parse(&file, Some(0x81)).unwrap();
+        let data: Vec<&[u8]> = iso.iter().map(|p| p.data.as_slice()).collect();
+        assert_eq!(data, vec![&[1u8, 2, 3][..], &[4, 5][..]]);
+        assert_eq!(iso[0].timestamp_us, 0);
+
+        // Without an endpoint, the stream with the most payload wins
+        let bulk = parse(&file, None).unwrap();
+        assert_eq!(bulk.len(), 1);
+        assert_eq!((bulk[0].endpoint, bulk[0].data.len()), (0x82, 64));
+
+        assert!(matches!(
+            parse(&file, Some(0x85)),
+            Err(ReplayError::NoPackets)
+        ));
+    }
+
+    #[test]
+    fn test_rejects_other_link_types() {
+        let ethernet = pcap(1, &[]);
+        assert!(is_pcap(&ethernet));
+        assert!(matches!(
+            parse(&ethernet, None),
+            Err(ReplayError::UnsupportedLinkType(1))
+        ));
+        assert!(!is_pcap(b"\x00\x01\x02\x03"));
+        assert!(matches!(
+            parse(b"\x00\x01\x02\x03", None),
+            Err(ReplayError::InvalidPacket { .. })
+        ));
+    }
+
+    #[test]
+    fn test_timestamp_resolution() {
+        assert_eq!(timestamp_to_us(1_500, 6), 1_500);
+        assert_eq!(timestamp_to_us(1_500_000, 9), 1_500);
+        assert_eq!(timestamp_to_us(3, 3), 3_000);
+        // 2^-10 s units
+        assert_eq!(timestamp_to_us(1024, 0x80 | 10), 1_000_000);
+    }
+}
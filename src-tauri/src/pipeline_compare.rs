@@ -0,0 +1,584 @@
+//! Comparative dual-pipeline debug mode
+//!
+//! Runs the live packet stream through two assembler/conversion
+//! configurations side by side — usually the current settings and an
+//! experimental change such as a different stride — and reports, per frame,
+//! whether and how much their output diverges. Risky pipeline changes can be
+//! checked against real hardware without touching what is displayed.
+//!
+//! Packets reach the comparison through the capture packet tap (see
+//! [`CaptureState::set_packet_tap`]) and are processed on a thread of their
+//! own. If the comparison falls behind, packets are dropped and counted rather
+//! than slowing the stream down.
+
+use std::collections::VecDeque;
+use std::sync::mpsc::{self, Receiver};
+use std::sync::{Arc, Mutex};
+use std::thread::JoinHandle;
+
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::capture::CaptureState;
+use crate::frame_assembler::{FrameAssembler, ProcessResult};
+use crate::yuv_conversion::convert_to_rgb;
+use crate::PixelFormat;
+
+/// Packets queued for the comparison thread before new ones are dropped
+const PACKET_QUEUE_DEPTH: usize = 1024;
+
+/// Per-frame comparisons kept for [`PipelineComparison::report`]
+const RECENT_COMPARISONS: usize = 32;
+
+/// Frames one side may complete ahead of the other before they are given up on
+const MAX_PENDING_FRAMES: usize = 4;
+
+/// Errors starting or stopping a comparison
+#[derive(Debug, Error)]
+pub enum ComparisonError {
+    /// A comparison is already running
+    #[error("a pipeline comparison is already running")]
+    AlreadyRunning,
+    /// No comparison is running
+    #[error("no pipeline comparison is running")]
+    NotRunning,
+    /// The comparison thread could not be started
+    #[error("could not start comparison thread: {0}")]
+    Thread(#[from] std::io::Error),
+}
+
+/// Result type alias for comparison operations
+pub type Result<T> = std::result::Result<T, ComparisonError>;
+
+/// One assembler/conversion configuration
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PipelineVariant {
+    /// Name shown in logs and reports (e.g. "current", "stride x1.5")
+    pub label: String,
+    /// Assemble MJPEG frames; their bytes are compared without decoding
+    #[serde(default)]
+    pub mjpeg: bool,
+    /// Pixel format used for RGB conversion
+    pub pixel_format: PixelFormat,
+    /// Frame width in pixels
+    pub width: u32,
+    /// Frame height in pixels
+    pub height: u32,
+    /// Row stride in bytes for packed 4:2:2 formats (None = width * 2)
+    #[serde(default)]
+    pub stride: Option<u32>,
+    /// Payloads carry no UVC headers
+    #[serde(default)]
+    pub headerless: bool,
+}
+
+impl PipelineVariant {
+    fn assembler(&self) -> FrameAssembler {
+        let mut assembler = if self.mjpeg {
+            FrameAssembler::new_mjpeg()
+        } else {
+            FrameAssembler::new(self.pixel_format.frame_size(self.width, self.height))
+        };
+        assembler.set_headerless(self.headerless);
+        assembler
+    }
+
+    /// Turn an assembled frame into the bytes that are compared
+    fn convert(&self, frame: Vec<u8>) -> std::result::Result<Vec<u8>, String> {
+        if self.mjpeg {
+            return Ok(frame);
+        }
+        let stride = self.stride.unwrap_or(self.width * 2);
+        convert_to_rgb(&frame, self.width, self.height, stride, self.pixel_format)
+            .map_err(|e| e.to_string())
+    }
+}
+
+/// Changes to apply to the baseline to get the experimental pipeline
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct PipelineOverrides {
+    /// Label for the experimental pipeline (default "experimental")
+    #[serde(default)]
+    pub label: Option<String>,
+    /// Pixel format to convert with
+    #[serde(default)]
+    pub pixel_format: Option<PixelFormat>,
+    /// Frame width in pixels
+    #[serde(default)]
+    pub width: Option<u32>,
+    /// Frame height in pixels
+    #[serde(default)]
+    pub height: Option<u32>,
+    /// Row stride in bytes
+    #[serde(default)]
+    pub stride: Option<u32>,
+    /// Whether payloads carry no UVC headers
+    #[serde(default)]
+    pub headerless: Option<bool>,
+}
+
+impl PipelineOverrides {
+    /// The experimental pipeline: `baseline` with these overrides applied
+    #[must_use]
+    pub fn apply(&self, baseline: &PipelineVariant) -> PipelineVariant {
+        PipelineVariant {
+            label: self
+                .label
+                .clone()
+                .unwrap_or_else(|| "experimental".to_string()),
+            mjpeg: baseline.mjpeg,
+            pixel_format: self.pixel_format.unwrap_or(baseline.pixel_format),
+            width: self.width.unwrap_or(baseline.width),
+            height: self.height.unwrap_or(baseline.height),
+            stride: self.stride.or(baseline.stride),
+            headerless: self.headerless.unwrap_or(baseline.headerless),
+        }
+    }
+}
+
+/// How one pair of frames compared
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FrameComparison {
+    /// Pair number within the comparison (starting at 0)
+    pub sequence: u64,
+    /// Output size of the baseline pipeline in bytes
+    pub baseline_bytes: usize,
+    /// Output size of the experimental pipeline in bytes
+    pub experimental_bytes: usize,
+    /// Bytes that differ, counting the length difference
+    pub differing_bytes: usize,
+    /// Mean absolute difference per byte (0-255)
+    pub mean_abs_diff: f64,
+    /// Largest absolute difference of a single byte
+    pub max_abs_diff: u8,
+    /// Whether the outputs differ (or either pipeline failed)
+    pub diverged: bool,
+    /// Conversion error of either pipeline
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+impl FrameComparison {
+    fn new(
+        sequence: u64,
+        baseline: &std::result::Result<Vec<u8>, String>,
+        experimental: &std::result::Result<Vec<u8>, String>,
+    ) -> Self {
+        let (a, b) = match (baseline, experimental) {
+            (Ok(a), Ok(b)) => (a, b),
+            (a, b) => {
+                let error = [("baseline", a), ("experimental", b)]
+                    .iter()
+                    .filter_map(|(side, r)| r.as_ref().err().map(|e| format!("{side}: {e}")))
+                    .collect::<Vec<_>>()
+                    .join("; ");
+                return Self {
+                    sequence,
+                    baseline_bytes: a.as_ref().map_or(0, Vec::len),
+                    experimental_bytes: b.as_ref().map_or(0, Vec::len),
+                    differing_bytes: 0,
+                    mean_abs_diff: 0.0,
+                    max_abs_diff: 0,
+                    diverged: true,
+                    error: Some(error),
+                };
+            }
+        };
+
+        let longest = a.len().max(b.len());
+        let mut differing = longest - a.len().min(b.len());
+        let mut total_diff = 0u64;
+        let mut max_abs_diff = 0u8;
+        for (&x, &y) in a.iter().zip(b) {
+            let diff = x.abs_diff(y);
+            if diff > 0 {
+                differing += 1;
+                total_diff += u64::from(diff);
+                max_abs_diff = max_abs_diff.max(diff);
+            }
+        }
+
+        Self {
+            sequence,
+            baseline_bytes: a.len(),
+            experimental_bytes: b.len(),
+            differing_bytes: differing,
+            mean_abs_diff: if longest == 0 {
+                0.0
+            } else {
+                total_diff as f64 / longest as f64
+            },
+            max_abs_diff,
+            diverged: differing > 0,
+            error: None,
+        }
+    }
+}
+
+/// Running totals of a comparison
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ComparisonStats {
+    /// Frame pairs compared
+    pub compared: u64,
+    /// Pairs whose outputs differed
+    pub diverged: u64,
+    /// Baseline frames with no experimental counterpart
+    pub baseline_only: u64,
+    /// Experimental frames with no baseline counterpart
+    pub experimental_only: u64,
+    /// Packets dropped because the comparison fell behind
+    pub dropped_packets: u64,
+    /// Largest mean absolute difference seen
+    pub worst_mean_abs_diff: f64,
+}
+
+/// Snapshot of a running or finished comparison
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ComparisonReport {
+    /// Whether the comparison is still running
+    pub running: bool,
+    /// Configuration frames are compared against
+    pub baseline: Option<PipelineVariant>,
+    /// Configuration under test
+    pub experimental: Option<PipelineVariant>,
+    /// Totals since the comparison started
+    pub stats: ComparisonStats,
+    /// Most recent frame comparisons, oldest first
+    pub recent: Vec<FrameComparison>,
+}
+
+/// Feeds packets to two pipelines and pairs up the frames they produce
+///
+/// Frames are paired in completion order. If one pipeline gets more than
+/// [`MAX_PENDING_FRAMES`] frames ahead (it splits frames differently), its
+/// oldest frames are counted as unmatched.
+pub struct PipelineComparator {
+    sides: [Side; 2],
+    sequence: u64,
+    stats: ComparisonStats,
+}
+
+struct Side {
+    variant: PipelineVariant,
+    assembler: FrameAssembler,
+    pending: VecDeque<std::result::Result<Vec<u8>, String>>,
+}
+
+impl PipelineComparator {
+    /// Create a comparator for `baseline` and `experimental`
+    #[must_use]
+    pub fn new(baseline: PipelineVariant, experimental: PipelineVariant) -> Self {
+        let side = |variant: PipelineVariant| Side {
+            assembler: variant.assembler(),
+            variant,
+            pending: VecDeque::new(),
+        };
+        Self {
+            sides: [side(baseline), side(experimental)],
+            sequence: 0,
+            stats: ComparisonStats::default(),
+        }
+    }
+
+    /// Totals so far
+    #[must_use]
+    pub fn stats(&self) -> &ComparisonStats {
+        &self.stats
+    }
+
+    /// Feed one packet to both pipelines, returning any frame pairs it completed
+    pub fn process_packet(&mut self, packet: &[u8]) -> Vec<FrameComparison> {
+        for (index, side) in self.sides.iter_mut().enumerate() {
+            if let ProcessResult::Frame(frame) = side.assembler.process_packet(packet) {
+                side.pending.push_back(side.variant.convert(frame));
+                if side.pending.len() > MAX_PENDING_FRAMES {
+                    side.pending.pop_front();
+                    if index == 0 {
+                        self.stats.baseline_only += 1;
+                    } else {
+                        self.stats.experimental_only += 1;
+                    }
+                }
+            }
+        }
+
+        let mut comparisons = Vec::new();
+        while !self.sides[0].pending.is_empty() && !self.sides[1].pending.is_empty() {
+            let (Some(baseline), Some(experimental)) = (
+                self.sides[0].pending.pop_front(),
+                self.sides[1].pending.pop_front(),
+            ) else {
+                break;
+            };
+            let comparison = FrameComparison::new(self.sequence, &baseline, &experimental);
+            self.sequence += 1;
+
+            self.stats.compared += 1;
+            if comparison.diverged {
+                self.stats.diverged += 1;
+                log::info!(
+                    "Pipeline divergence at frame {}: {} vs {} bytes, {} differ (mean {:.2}, max {}){}",
+                    comparison.sequence,
+                    comparison.baseline_bytes,
+                    comparison.experimental_bytes,
+                    comparison.differing_bytes,
+                    comparison.mean_abs_diff,
+                    comparison.max_abs_diff,
+                    comparison
+                        .error
+                        .as_ref()
+                        .map_or(String::new(), |e| format!(" [{e}]"))
+                );
+            }
+            self.stats.worst_mean_abs_diff =
+                self.stats.worst_mean_abs_diff.max(comparison.mean_abs_diff);
+            comparisons.push(comparison);
+        }
+        comparisons
+    }
+}
+
+/// A running comparison thread
+struct Running {
+    thread: JoinHandle<()>,
+}
+
+/// Dual-pipeline comparison driven by the capture packet tap
+#[derive(Default)]
+pub struct PipelineComparison {
+    running: Mutex<Option<Running>>,
+    report: Arc<Mutex<ComparisonReport>>,
+}
+
+impl PipelineComparison {
+    /// Create an idle comparison
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Start comparing `baseline` and `experimental` on live packets from `capture`
+    ///
+    /// # Errors
+    ///
+    /// Returns `ComparisonError::AlreadyRunning` if a comparison is running,
+    /// or `ComparisonError::Thread` if the thread can't be spawned.
+    pub fn start(
+        &self,
+        capture: &CaptureState,
+        baseline: PipelineVariant,
+        experimental: PipelineVariant,
+    ) -> Result<()> {
+        let mut running = crate::lock_or_recover(&self.running);
+        if running.is_some() {
+            return Err(ComparisonError::AlreadyRunning);
+        }
+
+        *crate::lock_or_recover(&self.report) = ComparisonReport {
+            running: true,
+            baseline: Some(baseline.clone()),
+            experimental: Some(experimental.clone()),
+            ..Default::default()
+        };
+        log::info!(
+            "Comparing pipelines \"{}\" and \"{}\"",
+            baseline.label,
+            experimental.label
+        );
+
+        let (sender, receiver) = mpsc::sync_channel(PACKET_QUEUE_DEPTH);
+        let comparator = PipelineComparator::new(baseline, experimental);
+        let report = Arc::clone(&self.report);
+        let thread = std::thread::Builder::new()
+            .name("pipeline-compare".to_string())
+            .spawn(move || run_comparator(comparator, receiver, &report))?;
+
+        capture.set_packet_tap(Some(sender));
+        *running = Some(Running { thread });
+        Ok(())
+    }
+
+    /// Stop the comparison and return its final report
+    ///
+    /// # Errors
+    ///
+    /// Returns `ComparisonError::NotRunning` if no comparison is running.
+    pub fn stop(&self, capture: &CaptureState) -> Result<ComparisonReport> {
+        let running = crate::lock_or_recover(&self.running)
+            .take()
+            .ok_or(ComparisonError::NotRunning)?;
+
+        // Dropping the tap's sender ends the thread once its queue drains
+        capture.set_packet_tap(None);
+        if running.thread.join().is_err() {
+            log::error!("Pipeline comparison thread panicked");
+        }
+
+        let mut report = crate::lock_or_recover(&self.report);
+        report.running = false;
+        report.stats.dropped_packets = capture.tap_dropped_packets();
+        log::info!(
+            "Pipeline comparison finished: {} frames compared, {} diverged",
+            report.stats.compared,
+            report.stats.diverged
+        );
+        Ok(report.clone())
+    }
+
+    /// Current report (of the running comparison, or the last one)
+    pub fn report(&self, capture: &CaptureState) -> ComparisonReport {
+        let mut report = crate::lock_or_recover(&self.report).clone();
+        if report.running {
+            report.stats.dropped_packets = capture.tap_dropped_packets();
+        }
+        report
+    }
+}
+
+/// Comparison thread: process packets until the tap is removed
+fn run_comparator(
+    mut comparator: PipelineComparator,
+    packets: Receiver<Vec<u8>>,
+    report: &Mutex<ComparisonReport>,
+) {
+    for packet in packets {
+        let comparisons = comparator.process_packet(&packet);
+        if comparisons.is_empty() {
+            continue;
+        }
+
+        let mut report = crate::lock_or_recover(report);
+        let dropped = report.stats.dropped_packets;
+        report.stats = ComparisonStats {
+            dropped_packets: dropped,
+            ..comparator.stats().clone()
+        };
+        report.recent.extend(comparisons);
+        let excess = report.recent.len().saturating_sub(RECENT_COMPARISONS);
+        report.recent.drain(..excess);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::{PacketGenerator, Rgb};
+
+    fn variant(label: &str, pixel_format: PixelFormat) -> PipelineVariant {
+        PipelineVariant {
+            label: label.to_string(),
+            mjpeg: false,
+            pixel_format,
+            width: 16,
+            height: 8,
+            stride: None,
+            headerless: false,
+        }
+    }
+
+    fn packets(frames: usize) -> Vec<Vec<u8>> {
+        let mut gen = PacketGenerator::new(128);
+        (0..frames)
+            .flat_map(|_| gen.yuy2_gradient_frame(16, 8))
+            .collect()
+    }
+
+    #[test]
+    fn test_identical_pipelines_never_diverge() {
+        let mut comparator = PipelineComparator::new(
+            variant("a", PixelFormat::Yuyv),
+            variant("b", PixelFormat::Yuyv),
+        );
+        let comparisons: Vec<_> = packets(4)
+            .iter()
+            .flat_map(|p| comparator.process_packet(p))
+            .collect();
+
+        assert!(!comparisons.is_empty());
+        assert!(comparisons.iter().all(|c| !c.diverged));
+        assert_eq!(comparator.stats().diverged, 0);
+        assert_eq!(comparator.stats().compared, comparisons.len() as u64);
+    }
+
+    #[test]
+    fn test_different_conversion_diverges() {
+        let mut comparator = PipelineComparator::new(
+            variant("yuyv", PixelFormat::Yuyv),
+            variant("uyvy", PixelFormat::Uyvy),
+        );
+        let comparisons: Vec<_> = packets(4)
+            .iter()
+            .flat_map(|p| comparator.process_packet(p))
+            .collect();
+
+        assert!(!comparisons.is_empty());
+        assert!(comparisons
+            .iter()
+            .all(|c| c.diverged && c.differing_bytes > 0));
+        assert_eq!(comparisons[0].baseline_bytes, 16 * 8 * 3);
+        assert!(comparator.stats().worst_mean_abs_diff > 0.0);
+    }
+
+    #[test]
+    fn test_overrides_apply_to_baseline() {
+        let baseline = variant("current", PixelFormat::Yuyv);
+        let overrides = PipelineOverrides {
+            stride: Some(48),
+            ..Default::default()
+        };
+        let experimental = overrides.apply(&baseline);
+
+        assert_eq!(experimental.label, "experimental");
+        assert_eq!(experimental.stride, Some(48));
+        assert_eq!(experimental.pixel_format, PixelFormat::Yuyv);
+        assert_eq!(experimental.width, baseline.width);
+    }
+
+    #[test]
+    fn test_frame_comparison_counts_length_difference() {
+        let comparison = FrameComparison::new(0, &Ok(vec![10, 20, 30]), &Ok(vec![10, 25]));
+        assert_eq!(comparison.differing_bytes, 2);
+        assert_eq!(comparison.max_abs_diff, 5);
+        assert!(comparison.diverged);
+
+        let failed = FrameComparison::new(1, &Ok(vec![1]), &Err("bad stride".to_string()));
+        assert!(failed.diverged);
+        assert_eq!(failed.error.as_deref(), Some("experimental: bad stride"));
+    }
+
+    #[test]
+    fn test_comparison_runs_on_packet_tap() {
+        let capture = CaptureState::new();
+        let comparison = PipelineComparison::new();
+        comparison
+            .start(
+                &capture,
+                variant("a", PixelFormat::Yuyv),
+                variant("b", PixelFormat::Uyvy),
+            )
+            .unwrap();
+        assert!(matches!(
+            comparison.start(
+                &capture,
+                variant("a", PixelFormat::Yuyv),
+                variant("b", PixelFormat::Yuyv)
+            ),
+            Err(ComparisonError::AlreadyRunning)
+        ));
+
+        for packet in packets(4) {
+            capture.record_packet(&packet);
+        }
+        let report = comparison.stop(&capture).unwrap();
+
+        assert!(!report.running);
+        assert!(report.stats.compared > 0);
+        assert_eq!(report.stats.diverged, report.stats.compared);
+        assert_eq!(report.recent.len() as u64, report.stats.compared);
+        assert!(!capture.wants_packets());
+        assert!(matches!(
+            comparison.stop(&capture),
+            Err(ComparisonError::NotRunning)
+        ));
+    }
+}
@@ -0,0 +1,98 @@
+//! Pixel formats of raw video frames
+//!
+//! Kept free of Tauri and platform dependencies so the conversion pipeline can
+//! also be built for `wasm32` (see `wasm/`). Re-exported as
+//! `clean_scope_lib::PixelFormat`. Names, sizes and converters of the formats
+//! live in [`format_registry`](crate::format_registry).
+
+use crate::format_registry;
+use serde::{Deserialize, Serialize};
+
+/// Pixel format variants for video frames
+/// Includes both YUV and RGB formats. Every variant needs an entry in
+/// [`format_registry::FORMATS`], in declaration order.
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+pub enum PixelFormat {
+    /// YUYV format: Y0-U-Y1-V byte order (packed YUV422, luminance first)
+    #[default]
+    Yuyv,
+    /// UYVY format: U-Y0-V-Y1 byte order (packed YUV422, chrominance first)
+    /// This is what macOS reports for many USB endoscopes
+    Uyvy,
+    /// NV12 format: Y plane followed by interleaved UV plane (semi-planar YUV420)
+    /// Uses 1.5 bytes per pixel (12 bits)
+    Nv12,
+    /// I420 format: Y plane, then U plane, then V plane (planar YUV420)
+    /// Uses 1.5 bytes per pixel (12 bits)
+    I420,
+    /// RGB888 format: R-G-B byte order (3 bytes per pixel)
+    /// Direct pass-through, no conversion needed
+    Rgb888,
+    /// BGR888 format: B-G-R byte order (3 bytes per pixel)
+    /// Requires R↔B swap for display
+    Bgr888,
+    /// 8-bit RGGB Bayer mosaic (RAW sensor data, 1 byte per pixel)
+    /// Demosaiced to RGB for display
+    BayerRggb,
+    /// NV21 format: Y plane followed by interleaved VU plane (semi-planar YUV420)
+    /// Android camera default; uses 1.5 bytes per pixel (12 bits)
+    Nv21,
+    /// YV12 format: Y plane, then V plane, then U plane (planar YUV420)
+    /// Uses 1.5 bytes per pixel (12 bits)
+    Yv12,
+}
+
+impl PixelFormat {
+    /// Size in bytes of an unpadded frame
+    ///
+    /// YUV422 (YUYV/UYVY): 2 bytes per pixel
+    /// YUV420 (I420/YV12/NV12/NV21): 1.5 bytes per pixel
+    /// RGB (RGB888/BGR888): 3 bytes per pixel
+    /// Bayer (RGGB): 1 byte per pixel
+    pub fn frame_size(self, width: u32, height: u32) -> usize {
+        let pixels = width as usize * height as usize;
+        pixels * usize::from(format_registry::entry(self).bits_per_pixel) / 8
+    }
+}
+
+impl std::fmt::Display for PixelFormat {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(format_registry::entry(*self).name)
+    }
+}
+
+impl std::str::FromStr for PixelFormat {
+    type Err = String;
+
+    /// Parses a format name, case-insensitively: the `Display` names plus
+    /// the registered aliases (`YUY2`, `RGB888`, `BGR888`)
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        format_registry::by_name(s)
+            .map(|entry| entry.format)
+            .ok_or_else(|| format!("Unknown pixel format '{s}'"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_display_names_round_trip() {
+        for format in [
+            PixelFormat::Yuyv,
+            PixelFormat::Uyvy,
+            PixelFormat::Nv12,
+            PixelFormat::I420,
+            PixelFormat::Rgb888,
+            PixelFormat::Bgr888,
+            PixelFormat::BayerRggb,
+            PixelFormat::Nv21,
+            PixelFormat::Yv12,
+        ] {
+            assert_eq!(format.to_string().parse::<PixelFormat>(), Ok(format));
+        }
+        assert_eq!("yuy2".parse::<PixelFormat>(), Ok(PixelFormat::Yuyv));
+        assert!("mjpeg".parse::<PixelFormat>().is_err());
+    }
+}
@@ -0,0 +1,641 @@
+//! Third-party frame processor plugins
+//!
+//! Plugins are native libraries that see every converted RGB frame and may
+//! modify it in place or return annotations (labelled boxes, published on the
+//! annotation bus), so specialized
+//! processing such as weld-defect detection can be added without forking.
+//! Each plugin lives in its own directory under `plugins/` in the app data
+//! directory, next to a capability manifest:
+//!
+//! ```text
+//! plugins/weld-inspect/plugin.json
+//! plugins/weld-inspect/libweld_inspect.so
+//! ```
+//!
+//! ```json
+//! {
+//!   "name": "weld-inspect",
+//!   "version": "1.0.0",
+//!   "api_version": 1,
+//!   "library": "libweld_inspect.so",
+//!   "capabilities": ["annotate"]
+//! }
+//! ```
+//!
+//! Capabilities are enforced by the host: a plugin without `modify_frame`
+//! works on a copy of the frame, and annotations from a plugin without
+//! `annotate` are dropped. The C interface is declared in
+//! `include/cleanscope_plugin.h`; [`PLUGIN_API_VERSION`] is bumped on every
+//! incompatible change and plugins built for another version are refused.
+//! MJPEG frames are not passed to plugins.
+//!
+//! Loading native code needs the `plugins` feature, and is left out of
+//! `no-network` builds since a plugin could open network connections. Without
+//! it, manifests are still listed but every plugin fails with
+//! [`PluginError::Disabled`].
+
+use std::ffi::{c_char, c_void, CStr};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::annotations::{Annotation, AnnotationKind};
+
+/// Version of the plugin interface implemented by this host
+pub const PLUGIN_API_VERSION: u32 = 1;
+
+/// Directory (inside the app data directory) plugins are loaded from
+pub const PLUGIN_DIR: &str = "plugins";
+
+/// Manifest file in every plugin directory
+pub const MANIFEST_FILE: &str = "plugin.json";
+
+/// Symbol every plugin library exports, returning its [`CsPlugin`]
+pub const ENTRY_POINT: &str = "cleanscope_plugin_v1";
+
+/// Annotations a plugin can return per frame
+pub const MAX_ANNOTATIONS: usize = 64;
+
+/// Size of [`CsAnnotation::label`], including the terminating NUL
+pub const LABEL_LEN: usize = 64;
+
+/// Consecutive failed frames after which a plugin is skipped
+const MAX_CONSECUTIVE_FAILURES: u32 = 30;
+
+/// Errors loading a plugin
+#[derive(Debug, Error)]
+pub enum PluginError {
+    /// The plugin directory or manifest could not be read
+    #[error("plugin I/O error: {0}")]
+    Io(#[from] std::io::Error),
+    /// The manifest is not valid JSON or misses fields
+    #[error("invalid plugin manifest: {0}")]
+    Manifest(#[from] serde_json::Error),
+    /// The plugin targets another interface version
+    #[error(
+        "plugin '{name}' targets plugin API {found}, this build supports {}",
+        PLUGIN_API_VERSION
+    )]
+    ApiVersion {
+        /// Plugin name
+        name: String,
+        /// API version the plugin was built for
+        found: u32,
+    },
+    /// The manifest's library is not a file name inside the plugin directory
+    #[error("plugin library '{0}' must be a file name inside the plugin directory")]
+    LibraryPath(String),
+    /// The library could not be loaded or lacks the entry point
+    #[error("could not load plugin library: {0}")]
+    Load(String),
+    /// Plugin support is not compiled in
+    #[error(
+        "plugin support is not compiled in (enable the `plugins` feature and disable `no-network`)"
+    )]
+    Disabled,
+}
+
+/// Result type alias for plugin operations
+pub type Result<T> = std::result::Result<T, PluginError>;
+
+/// What a plugin is allowed to do with frames
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PluginCapability {
+    /// Change the displayed (and recorded) frame in place
+    ModifyFrame,
+    /// Return annotations, forwarded with `frame-annotations` events
+    Annotate,
+}
+
+/// Contents of a plugin's `plugin.json`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PluginManifest {
+    /// Unique plugin name
+    pub name: String,
+    /// Plugin version (informational)
+    pub version: String,
+    /// Plugin interface version the library was built for
+    pub api_version: u32,
+    /// File name of the library, relative to the plugin directory
+    pub library: String,
+    /// Granted capabilities
+    #[serde(default)]
+    pub capabilities: Vec<PluginCapability>,
+    /// Human-readable description
+    #[serde(default)]
+    pub description: String,
+}
+
+impl PluginManifest {
+    /// Read and validate the manifest in `dir`
+    ///
+    /// # Errors
+    ///
+    /// Returns `PluginError` if the manifest can't be read or parsed, targets
+    /// another API version, or points outside the plugin directory.
+    pub fn read(dir: &Path) -> Result<Self> {
+        let manifest: Self = serde_json::from_slice(&std::fs::read(dir.join(MANIFEST_FILE))?)?;
+        manifest.validate()?;
+        Ok(manifest)
+    }
+
+    /// Check the API version and library path
+    ///
+    /// # Errors
+    ///
+    /// Returns `PluginError::ApiVersion` or `PluginError::LibraryPath`.
+    pub fn validate(&self) -> Result<()> {
+        if self.api_version != PLUGIN_API_VERSION {
+            return Err(PluginError::ApiVersion {
+                name: self.name.clone(),
+                found: self.api_version,
+            });
+        }
+        let library = Path::new(&self.library);
+        if self.library.is_empty() || library.file_name() != Some(library.as_os_str()) {
+            return Err(PluginError::LibraryPath(self.library.clone()));
+        }
+        Ok(())
+    }
+
+    /// Whether the manifest grants `capability`
+    #[must_use]
+    pub fn allows(&self, capability: PluginCapability) -> bool {
+        self.capabilities.contains(&capability)
+    }
+}
+
+/// A plugin directory and its load outcome, for `list_plugins`
+#[derive(Debug, Clone, Serialize)]
+pub struct PluginInfo {
+    /// Plugin directory
+    pub directory: String,
+    /// Parsed manifest, if it could be read
+    pub manifest: Option<PluginManifest>,
+    /// Whether the plugin is loaded and receives frames
+    pub loaded: bool,
+    /// Why the plugin isn't loaded
+    pub error: Option<String>,
+}
+
+/// Frame handed to a plugin (`CsPluginFrame` in the C header)
+#[repr(C)]
+pub struct CsPluginFrame {
+    /// RGB24 pixels, `len` bytes, rows of `width * 3` bytes
+    pub rgb: *mut u8,
+    /// Length of `rgb` in bytes
+    pub len: usize,
+    /// Frame width in pixels
+    pub width: u32,
+    /// Frame height in pixels
+    pub height: u32,
+    /// Frames passed to plugins since they were loaded
+    pub sequence: u64,
+}
+
+/// Annotation written by a plugin (`CsAnnotation` in the C header)
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct CsAnnotation {
+    /// Left edge in pixels
+    pub x: u32,
+    /// Top edge in pixels
+    pub y: u32,
+    /// Width in pixels
+    pub width: u32,
+    /// Height in pixels
+    pub height: u32,
+    /// Confidence from 0.0 to 1.0
+    pub confidence: f32,
+    /// NUL-terminated UTF-8 label
+    pub label: [c_char; LABEL_LEN],
+}
+
+impl CsAnnotation {
+    const EMPTY: Self = Self {
+        x: 0,
+        y: 0,
+        width: 0,
+        height: 0,
+        confidence: 0.0,
+        label: [0; LABEL_LEN],
+    };
+
+    fn label(&self) -> String {
+        // Force termination so a plugin can't make us read past the array
+        let mut label = self.label;
+        label[LABEL_LEN - 1] = 0;
+        // SAFETY: the array is NUL-terminated
+        unsafe { CStr::from_ptr(label.as_ptr()) }
+            .to_string_lossy()
+            .into_owned()
+    }
+}
+
+/// Function table returned by a plugin's entry point (`CsPlugin` in the C header)
+#[repr(C)]
+pub struct CsPlugin {
+    /// Must equal [`PLUGIN_API_VERSION`]
+    pub api_version: u32,
+    /// Create a plugin instance (optional); null fails the load
+    pub create: Option<unsafe extern "C" fn() -> *mut c_void>,
+    /// Destroy an instance returned by `create` (optional)
+    pub destroy: Option<unsafe extern "C" fn(instance: *mut c_void)>,
+    /// Process one frame; returns the number of annotations written, or a
+    /// negative value on failure
+    pub process: Option<
+        unsafe extern "C" fn(
+            instance: *mut c_void,
+            frame: *mut CsPluginFrame,
+            annotations: *mut CsAnnotation,
+            max_annotations: usize,
+        ) -> i32,
+    >,
+}
+
+/// A loaded plugin library and its instance
+struct LoadedPlugin {
+    manifest: PluginManifest,
+    /// Plugin directory, matching its [`PluginInfo::directory`]
+    directory: String,
+    /// Points into the library, valid while `library` is loaded
+    vtable: *const CsPlugin,
+    instance: *mut c_void,
+    consecutive_failures: u32,
+    /// Dropped last, after the instance is destroyed
+    #[cfg(plugin_loading)]
+    library: libloading::Library,
+}
+
+// SAFETY: plugin instances are only used behind the host's mutex, one call at
+// a time; the interface requires plugins to tolerate calls from any thread.
+unsafe impl Send for LoadedPlugin {}
+
+impl LoadedPlugin {
+    #[cfg(plugin_loading)]
+    fn load(dir: &Path, manifest: PluginManifest) -> Result<Self> {
+        let path = dir.join(&manifest.library);
+        // SAFETY: loading a library runs its initializers; plugins are trusted
+        // code the user installed into the app data directory.
+        unsafe {
+            let library =
+                libloading::Library::new(&path).map_err(|e| PluginError::Load(e.to_string()))?;
+            let entry: libloading::Symbol<unsafe extern "C" fn() -> *const CsPlugin> = library
+                .get(ENTRY_POINT.as_bytes())
+                .map_err(|e| PluginError::Load(e.to_string()))?;
+            let vtable = entry();
+            if vtable.is_null() {
+                return Err(PluginError::Load(format!("{} returned null", ENTRY_POINT)));
+            }
+            if (*vtable).api_version != PLUGIN_API_VERSION {
+                return Err(PluginError::ApiVersion {
+                    name: manifest.name,
+                    found: (*vtable).api_version,
+                });
+            }
+            let instance = match (*vtable).create {
+                Some(create) => create(),
+                None => std::ptr::null_mut(),
+            };
+            if instance.is_null() && (*vtable).create.is_some() {
+                return Err(PluginError::Load("create returned null".to_string()));
+            }
+            Ok(Self {
+                manifest,
+                directory: dir.to_string_lossy().to_string(),
+                vtable,
+                instance,
+                consecutive_failures: 0,
+                library,
+            })
+        }
+    }
+
+    #[cfg(not(plugin_loading))]
+    fn load(_dir: &Path, _manifest: PluginManifest) -> Result<Self> {
+        Err(PluginError::Disabled)
+    }
+
+    fn is_healthy(&self) -> bool {
+        self.consecutive_failures < MAX_CONSECUTIVE_FAILURES
+    }
+
+    /// Run the plugin on one frame, appending its annotations to `out`
+    fn process(
+        &mut self,
+        rgb: &mut [u8],
+        width: u32,
+        height: u32,
+        sequence: u64,
+        out: &mut Vec<Annotation>,
+    ) {
+        // SAFETY: the vtable stays valid while the library is loaded
+        let Some(process) = (unsafe { (*self.vtable).process }) else {
+            return;
+        };
+        let mut copy;
+        let pixels: &mut [u8] = if self.manifest.allows(PluginCapability::ModifyFrame) {
+            rgb
+        } else {
+            copy = rgb.to_vec();
+            &mut copy
+        };
+        let mut frame = CsPluginFrame {
+            rgb: pixels.as_mut_ptr(),
+            len: pixels.len(),
+            width,
+            height,
+            sequence,
+        };
+        let mut annotations = [CsAnnotation::EMPTY; MAX_ANNOTATIONS];
+
+        // SAFETY: all pointers are valid for the duration of the call
+        let count = unsafe {
+            process(
+                self.instance,
+                &mut frame,
+                annotations.as_mut_ptr(),
+                MAX_ANNOTATIONS,
+            )
+        };
+        if count < 0 {
+            self.consecutive_failures += 1;
+            if !self.is_healthy() {
+                log::error!(
+                    "Plugin '{}' failed {} frames in a row, skipping it",
+                    self.manifest.name,
+                    self.consecutive_failures
+                );
+            }
+            return;
+        }
+        self.consecutive_failures = 0;
+
+        if self.manifest.allows(PluginCapability::Annotate) {
+            let count = (count as usize).min(MAX_ANNOTATIONS);
+            out.extend(annotations[..count].iter().map(|a| Annotation {
+                source: self.manifest.name.clone(),
+                label: a.label(),
+                confidence: a.confidence,
+                kind: AnnotationKind::Box {
+                    x: a.x,
+                    y: a.y,
+                    width: a.width,
+                    height: a.height,
+                },
+            }));
+        }
+    }
+}
+
+impl Drop for LoadedPlugin {
+    fn drop(&mut self) {
+        // SAFETY: the instance came from this vtable's `create`
+        unsafe {
+            if let Some(destroy) = (*self.vtable).destroy {
+                destroy(self.instance);
+            }
+        }
+    }
+}
+
+#[derive(Default)]
+struct HostState {
+    plugins: Vec<LoadedPlugin>,
+    infos: Vec<PluginInfo>,
+    frames_processed: u64,
+}
+
+/// The loaded plugins, run on every converted RGB frame
+#[derive(Default)]
+pub struct PluginHost {
+    state: Mutex<HostState>,
+}
+
+impl PluginHost {
+    /// Create a host without plugins
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Unload all plugins and load every plugin directory under `dir`
+    ///
+    /// A missing `dir` just means no plugins. Plugins that fail to load are
+    /// reported in the returned list (and logged) rather than failing the
+    /// whole load.
+    pub fn load_dir(&self, dir: &Path) -> Vec<PluginInfo> {
+        let mut plugins = Vec::new();
+        let mut infos = Vec::new();
+        let mut directories: Vec<PathBuf> = match std::fs::read_dir(dir) {
+            Ok(entries) => entries
+                .filter_map(|e| e.ok().map(|e| e.path()))
+                .filter(|p| p.is_dir())
+                .collect(),
+            Err(_) => Vec::new(),
+        };
+        directories.sort();
+
+        for directory in directories {
+            let mut info = PluginInfo {
+                directory: directory.to_string_lossy().to_string(),
+                manifest: None,
+                loaded: false,
+                error: None,
+            };
+            let loaded = PluginManifest::read(&directory).and_then(|manifest| {
+                info.manifest = Some(manifest.clone());
+                LoadedPlugin::load(&directory, manifest)
+            });
+            match loaded {
+                Ok(plugin) => {
+                    log::info!(
+                        "Loaded plugin '{}' {} ({:?})",
+                        plugin.manifest.name,
+                        plugin.manifest.version,
+                        plugin.manifest.capabilities
+                    );
+                    info.loaded = true;
+                    plugins.push(plugin);
+                }
+                Err(e) => {
+                    log::warn!("Plugin in {} not loaded: {}", info.directory, e);
+                    info.error = Some(e.to_string());
+                }
+            }
+            infos.push(info);
+        }
+
+        let mut state = crate::lock_or_recover(&self.state);
+        // Old instances are destroyed before their replacements start working
+        state.plugins = plugins;
+        state.infos = infos.clone();
+        state.frames_processed = 0;
+        infos
+    }
+
+    /// Plugin directories found by the last [`Self::load_dir`]
+    #[must_use]
+    pub fn list(&self) -> Vec<PluginInfo> {
+        crate::lock_or_recover(&self.state).infos.clone()
+    }
+
+    /// Run every loaded plugin on an RGB24 frame
+    ///
+    /// Plugins with `modify_frame` change `rgb` in place. Returns the
+    /// annotations of plugins with `annotate`.
+    pub fn process_frame(&self, rgb: &mut [u8], width: u32, height: u32) -> Vec<Annotation> {
+        let mut state = crate::lock_or_recover(&self.state);
+        let mut annotations = Vec::new();
+        if state.plugins.is_empty() {
+            return annotations;
+        }
+        let sequence = state.frames_processed;
+        state.frames_processed += 1;
+        let HostState { plugins, infos, .. } = &mut *state;
+        for plugin in plugins.iter_mut().filter(|p| p.is_healthy()) {
+            plugin.process(rgb, width, height, sequence, &mut annotations);
+            if !plugin.is_healthy() {
+                // Skipped from now on, so no longer reported as loaded
+                if let Some(info) = infos.iter_mut().find(|i| i.directory == plugin.directory) {
+                    info.loaded = false;
+                    info.error = Some(format!(
+                        "failed {} frames in a row",
+                        plugin.consecutive_failures
+                    ));
+                }
+            }
+        }
+        annotations
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn manifest(json: &str) -> Result<PluginManifest> {
+        let manifest: PluginManifest = serde_json::from_str(json)?;
+        manifest.validate()?;
+        Ok(manifest)
+    }
+
+    #[test]
+    fn test_manifest_capabilities() {
+        let manifest = manifest(
+            r#"{"name": "weld", "version": "1.0.0", "api_version": 1,
+                "library": "libweld.so", "capabilities": ["annotate"]}"#,
+        )
+        .unwrap();
+        assert!(manifest.allows(PluginCapability::Annotate));
+        assert!(!manifest.allows(PluginCapability::ModifyFrame));
+    }
+
+    #[test]
+    fn test_manifest_rejects_other_api_and_paths() {
+        let base = |api: u32, library: &str| {
+            format!(
+                r#"{{"name": "p", "version": "1", "api_version": {}, "library": "{}"}}"#,
+                api, library
+            )
+        };
+        assert!(matches!(
+            manifest(&base(2, "libp.so")),
+            Err(PluginError::ApiVersion { found: 2, .. })
+        ));
+        for library in ["../libp.so", "/tmp/libp.so", "sub/libp.so", ""] {
+            assert!(
+                matches!(
+                    manifest(&base(1, library)),
+                    Err(PluginError::LibraryPath(_))
+                ),
+                "{library} accepted"
+            );
+        }
+    }
+
+    #[test]
+    fn test_load_dir_reports_bad_plugins() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir(dir.path().join("broken")).unwrap();
+        std::fs::write(dir.path().join("broken").join(MANIFEST_FILE), "{").unwrap();
+        std::fs::create_dir(dir.path().join("missing-lib")).unwrap();
+        std::fs::write(
+            dir.path().join("missing-lib").join(MANIFEST_FILE),
+            r#"{"name": "m", "version": "1", "api_version": 1, "library": "libm_missing.so"}"#,
+        )
+        .unwrap();
+
+        let host = PluginHost::new();
+        let infos = host.load_dir(dir.path());
+        assert_eq!(infos.len(), 2);
+        assert!(infos.iter().all(|i| !i.loaded && i.error.is_some()));
+        assert!(infos[0].manifest.is_none());
+        assert_eq!(infos[1].manifest.as_ref().unwrap().name, "m");
+
+        let mut frame = vec![1u8; 12];
+        assert!(host.process_frame(&mut frame, 2, 2).is_empty());
+        assert!(host.load_dir(&dir.path().join("nonexistent")).is_empty());
+    }
+
+    unsafe extern "C" fn failing_process(
+        _instance: *mut c_void,
+        _frame: *mut CsPluginFrame,
+        _annotations: *mut CsAnnotation,
+        _max_annotations: usize,
+    ) -> i32 {
+        -1
+    }
+
+    static FAILING_PLUGIN: CsPlugin = CsPlugin {
+        api_version: PLUGIN_API_VERSION,
+        create: None,
+        destroy: None,
+        process: Some(failing_process),
+    };
+
+    #[test]
+    #[cfg(not(plugin_loading))]
+    fn test_failing_plugin_is_reported_unloaded() {
+        let manifest =
+            manifest(r#"{"name": "f", "version": "1", "api_version": 1, "library": "libf.so"}"#)
+                .unwrap();
+        let host = PluginHost::new();
+        {
+            let mut state = crate::lock_or_recover(&host.state);
+            state.infos.push(PluginInfo {
+                directory: "plugins/f".to_string(),
+                manifest: Some(manifest.clone()),
+                loaded: true,
+                error: None,
+            });
+            state.plugins.push(LoadedPlugin {
+                manifest,
+                directory: "plugins/f".to_string(),
+                vtable: &FAILING_PLUGIN,
+                instance: std::ptr::null_mut(),
+                consecutive_failures: 0,
+            });
+        }
+
+        let mut frame = vec![0u8; 12];
+        for _ in 1..MAX_CONSECUTIVE_FAILURES {
+            host.process_frame(&mut frame, 2, 2);
+        }
+        assert!(host.list()[0].loaded);
+        host.process_frame(&mut frame, 2, 2);
+        let info = &host.list()[0];
+        assert!(!info.loaded);
+        assert_eq!(info.error.as_deref(), Some("failed 30 frames in a row"));
+    }
+
+    #[test]
+    fn test_annotation_label_is_bounded() {
+        let mut annotation = CsAnnotation::EMPTY;
+        annotation.label = [b'a' as c_char; LABEL_LEN];
+        assert_eq!(annotation.label().len(), LABEL_LEN - 1);
+    }
+}
@@ -0,0 +1,329 @@
+//! Startup health check and permission preflight
+//!
+//! Runs a set of environment checks (USB host support, camera permission,
+//! storage access, available memory) and returns a structured checklist so the
+//! UI can show actionable setup guidance instead of failing later.
+
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+/// Available memory below which streaming is likely to fail (bytes)
+pub const MEMORY_FAIL_BYTES: u64 = 64 * 1024 * 1024;
+
+/// Available memory below which a warning is shown (bytes)
+pub const MEMORY_WARN_BYTES: u64 = 256 * 1024 * 1024;
+
+/// Outcome of a single preflight check
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CheckStatus {
+    /// Check passed
+    Ok,
+    /// Check passed with a potential problem
+    Warning,
+    /// Check failed; the app will not work until it is fixed
+    Failed,
+    /// Check could not be performed on this platform
+    Unknown,
+}
+
+/// A single entry in the preflight checklist
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PreflightCheck {
+    /// Stable identifier (e.g. `usb_host`, `storage`)
+    pub id: String,
+    /// Check outcome
+    pub status: CheckStatus,
+    /// What was found
+    pub detail: String,
+    /// What the user can do about it (set for warnings and failures)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub guidance: Option<String>,
+}
+
+impl PreflightCheck {
+    fn new(id: &str, status: CheckStatus, detail: impl Into<String>) -> Self {
+        Self {
+            id: id.to_string(),
+            status,
+            detail: detail.into(),
+            guidance: None,
+        }
+    }
+
+    fn with_guidance(mut self, guidance: impl Into<String>) -> Self {
+        self.guidance = Some(guidance.into());
+        self
+    }
+}
+
+/// Result of running all preflight checks
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PreflightReport {
+    /// True if no check failed
+    pub ready: bool,
+    /// Individual check results, in display order
+    pub checks: Vec<PreflightCheck>,
+}
+
+impl PreflightReport {
+    /// Build a report from individual checks
+    pub fn from_checks(checks: Vec<PreflightCheck>) -> Self {
+        let ready = checks.iter().all(|c| c.status != CheckStatus::Failed);
+        Self { ready, checks }
+    }
+}
+
+/// Run all preflight checks.
+///
+/// `storage_dir` is the directory where snapshots and captures are written
+/// (the app cache directory), or `None` if it could not be resolved.
+pub fn run_preflight(storage_dir: Option<&Path>) -> PreflightReport {
+    let storage = match storage_dir {
+        Some(dir) => check_storage(dir),
+        None => PreflightCheck::new("storage", CheckStatus::Failed, "App directory unavailable")
+            .with_guidance("Reinstall the app or free up device storage"),
+    };
+
+    let mem_available = std::fs::read_to_string("/proc/meminfo")
+        .ok()
+        .and_then(|s| parse_mem_available(&s));
+
+    let report = PreflightReport::from_checks(vec![
+        check_usb_host(),
+        check_camera_permission(),
+        storage,
+        check_memory(mem_available),
+    ]);
+
+    for check in &report.checks {
+        log::info!(
+            "Preflight {}: {:?} ({})",
+            check.id,
+            check.status,
+            check.detail
+        );
+    }
+    report
+}
+
+/// Check that the app directory is writable by creating and removing a probe file
+pub fn check_storage(dir: &Path) -> PreflightCheck {
+    let probe = dir.join(".preflight_probe");
+    let result = std::fs::create_dir_all(dir)
+        .and_then(|()| std::fs::write(&probe, b"ok"))
+        .and_then(|()| std::fs::remove_file(&probe));
+
+    match result {
+        Ok(()) => PreflightCheck::new(
+            "storage",
+            CheckStatus::Ok,
+            format!("Writable: {}", dir.display()),
+        ),
+        Err(e) => PreflightCheck::new(
+            "storage",
+            CheckStatus::Failed,
+            format!("Cannot write to {}: {}", dir.display(), e),
+        )
+        .with_guidance("Free up device storage; snapshots and captures cannot be saved"),
+    }
+}
+
+/// Classify available memory (in bytes) against the warning/failure thresholds
+pub fn check_memory(available_bytes: Option<u64>) -> PreflightCheck {
+    let Some(bytes) = available_bytes else {
+        return PreflightCheck::new("memory", CheckStatus::Unknown, "Available memory unknown");
+    };
+
+    let detail = format!("{} MB available", bytes / (1024 * 1024));
+    if bytes < MEMORY_FAIL_BYTES {
+        PreflightCheck::new("memory", CheckStatus::Failed, detail)
+            .with_guidance("Close other apps to free memory before streaming")
+    } else if bytes < MEMORY_WARN_BYTES {
+        PreflightCheck::new("memory", CheckStatus::Warning, detail)
+            .with_guidance("Memory is low; high resolutions or recording may stutter")
+    } else {
+        PreflightCheck::new("memory", CheckStatus::Ok, detail)
+    }
+}
+
+/// Parse the `MemAvailable` line of `/proc/meminfo` into bytes
+pub fn parse_mem_available(meminfo: &str) -> Option<u64> {
+    meminfo
+        .lines()
+        .find_map(|line| line.strip_prefix("MemAvailable:"))
+        .and_then(|rest| rest.split_whitespace().next())
+        .and_then(|kb| kb.parse::<u64>().ok())
+        .map(|kb| kb * 1024)
+}
+
+/// Check whether the device supports USB host mode
+#[cfg(target_os = "android")]
+fn check_usb_host() -> PreflightCheck {
+    match android::has_system_feature("android.hardware.usb.host") {
+        Some(true) => PreflightCheck::new("usb_host", CheckStatus::Ok, "USB host supported"),
+        Some(false) => PreflightCheck::new(
+            "usb_host",
+            CheckStatus::Failed,
+            "This device does not support USB host (OTG)",
+        )
+        .with_guidance("USB cameras require a device with USB OTG support"),
+        None => PreflightCheck::new("usb_host", CheckStatus::Unknown, "Could not query USB host"),
+    }
+}
+
+/// Check whether the device supports USB host mode
+#[cfg(not(target_os = "android"))]
+fn check_usb_host() -> PreflightCheck {
+    let detail = if std::env::var("CLEANSCOPE_REPLAY_PATH").is_ok() {
+        "Desktop build: replaying capture file"
+    } else {
+        "Desktop build: live USB streaming not available"
+    };
+    PreflightCheck::new("usb_host", CheckStatus::Unknown, detail)
+}
+
+/// Check whether the camera permission has been granted
+#[cfg(target_os = "android")]
+fn check_camera_permission() -> PreflightCheck {
+    match android::has_permission("android.permission.CAMERA") {
+        Some(true) => PreflightCheck::new("camera_permission", CheckStatus::Ok, "Granted"),
+        Some(false) => PreflightCheck::new(
+            "camera_permission",
+            CheckStatus::Warning,
+            "Camera permission not granted",
+        )
+        .with_guidance(
+            "Grant the camera permission in system settings if the camera is not detected",
+        ),
+        None => PreflightCheck::new(
+            "camera_permission",
+            CheckStatus::Unknown,
+            "Could not query permission",
+        ),
+    }
+}
+
+/// Check whether the camera permission has been granted
+#[cfg(not(target_os = "android"))]
+fn check_camera_permission() -> PreflightCheck {
+    PreflightCheck::new(
+        "camera_permission",
+        CheckStatus::Ok,
+        "Not required on desktop",
+    )
+}
+
+#[cfg(target_os = "android")]
+mod android {
+    use jni::objects::JValue;
+
+    use crate::jni_helpers::{self, JniError};
+
+    /// `PackageManager.PERMISSION_GRANTED`
+    const PERMISSION_GRANTED: i32 = 0;
+
+    /// `PackageManager.hasSystemFeature(feature)`
+    pub fn has_system_feature(feature: &str) -> Option<bool> {
+        jni_helpers::with_activity(|env, activity| {
+            let pm = jni_helpers::call_non_null(
+                env,
+                activity,
+                "getPackageManager",
+                "()Landroid/content/pm/PackageManager;",
+                &[],
+            )?;
+            let name = jni_helpers::new_string(env, feature)?;
+            jni_helpers::call_bool(
+                env,
+                &pm,
+                "hasSystemFeature",
+                "(Ljava/lang/String;)Z",
+                &[JValue::Object(&name)],
+            )
+        })
+        .map_err(|e: JniError| log::warn!("Could not query system feature {}: {}", feature, e))
+        .ok()
+    }
+
+    /// `Context.checkSelfPermission(permission) == PERMISSION_GRANTED`
+    pub fn has_permission(permission: &str) -> Option<bool> {
+        jni_helpers::with_activity(|env, activity| {
+            let name = jni_helpers::new_string(env, permission)?;
+            let result = jni_helpers::call_int(
+                env,
+                activity,
+                "checkSelfPermission",
+                "(Ljava/lang/String;)I",
+                &[JValue::Object(&name)],
+            )?;
+            Ok(result == PERMISSION_GRANTED)
+        })
+        .map_err(|e: JniError| log::warn!("Could not check permission {}: {}", permission, e))
+        .ok()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_mem_available() {
+        let meminfo = "MemTotal:        8000000 kB\nMemFree:          100000 kB\nMemAvailable:    2048000 kB\n";
+        assert_eq!(parse_mem_available(meminfo), Some(2_048_000 * 1024));
+        assert_eq!(parse_mem_available("MemTotal: 1 kB\n"), None);
+    }
+
+    #[test]
+    fn test_check_memory_thresholds() {
+        assert_eq!(check_memory(None).status, CheckStatus::Unknown);
+        assert_eq!(check_memory(Some(32 << 20)).status, CheckStatus::Failed);
+        assert_eq!(check_memory(Some(128 << 20)).status, CheckStatus::Warning);
+        assert_eq!(check_memory(Some(1 << 30)).status, CheckStatus::Ok);
+        assert!(check_memory(Some(128 << 20)).guidance.is_some());
+    }
+
+    #[test]
+    fn test_check_storage_writable() {
+        let dir = tempfile::tempdir().unwrap();
+        let check = check_storage(&dir.path().join("cache"));
+        assert_eq!(check.status, CheckStatus::Ok);
+        assert!(!dir.path().join("cache/.preflight_probe").exists());
+    }
+
+    #[test]
+    fn test_check_storage_unwritable() {
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join("not_a_dir");
+        std::fs::write(&file, b"x").unwrap();
+        let check = check_storage(&file);
+        assert_eq!(check.status, CheckStatus::Failed);
+        assert!(check.guidance.is_some());
+    }
+
+    #[test]
+    fn test_report_ready_ignores_warnings() {
+        let report = PreflightReport::from_checks(vec![
+            PreflightCheck::new("a", CheckStatus::Ok, ""),
+            PreflightCheck::new("b", CheckStatus::Warning, ""),
+            PreflightCheck::new("c", CheckStatus::Unknown, ""),
+        ]);
+        assert!(report.ready);
+
+        let report =
+            PreflightReport::from_checks(vec![PreflightCheck::new("a", CheckStatus::Failed, "")]);
+        assert!(!report.ready);
+    }
+
+    #[test]
+    fn test_run_preflight_without_storage_dir() {
+        let report = run_preflight(None);
+        assert!(!report.ready);
+        assert!(report
+            .checks
+            .iter()
+            .any(|c| c.id == "storage" && c.status == CheckStatus::Failed));
+    }
+}
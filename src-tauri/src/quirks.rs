@@ -0,0 +1,84 @@
+//! Per-device workarounds for cameras that break the UVC spec
+//!
+//! Quirks are opt-in: each one relaxes an assumption the streaming path
+//! otherwise relies on, which would hurt compliant cameras. They are read
+//! from a comma-separated list in an environment variable at startup:
+//!
+//! ```text
+//! CLEANSCOPE_QUIRKS=headerless
+//! ```
+//!
+//! | Quirk | Meaning |
+//! |-------|---------|
+//! | `headerless` | Payloads carry raw YUY2 with no UVC payload headers; frames are split by size only |
+//! | `content-boundaries` | FID and frame size are both unreliable; frames are split where the image content jumps (see [`crate::frame_boundary`]) |
+
+use serde::{Deserialize, Serialize};
+
+/// Environment variable listing the enabled quirks
+pub const QUIRKS_ENV: &str = "CLEANSCOPE_QUIRKS";
+
+/// Enabled workarounds
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DeviceQuirks {
+    /// Payloads have no UVC headers (`headerless`)
+    pub headerless_payloads: bool,
+    /// Split uncompressed frames by content (`content-boundaries`)
+    pub content_boundaries: bool,
+}
+
+impl DeviceQuirks {
+    /// Read quirks from [`QUIRKS_ENV`]
+    pub fn from_env() -> Self {
+        std::env::var(QUIRKS_ENV)
+            .map(|spec| Self::parse(&spec))
+            .unwrap_or_default()
+    }
+
+    /// Parse a comma-separated quirk list, ignoring unknown names
+    pub fn parse(spec: &str) -> Self {
+        let mut quirks = Self::default();
+        for name in spec.split(',').map(str::trim).filter(|s| !s.is_empty()) {
+            match name.to_ascii_lowercase().as_str() {
+                "headerless" => quirks.headerless_payloads = true,
+                "content-boundaries" => quirks.content_boundaries = true,
+                _ => log::warn!("Ignoring unknown quirk {:?} in {}", name, QUIRKS_ENV),
+            }
+        }
+        if quirks != Self::default() {
+            log::info!("Device quirks enabled: {:?}", quirks);
+        }
+        quirks
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_empty() {
+        assert_eq!(DeviceQuirks::parse(""), DeviceQuirks::default());
+        assert_eq!(DeviceQuirks::parse(" , "), DeviceQuirks::default());
+    }
+
+    #[test]
+    fn test_parse_headerless() {
+        let quirks = DeviceQuirks::parse(" Headerless ");
+        assert!(quirks.headerless_payloads);
+    }
+
+    #[test]
+    fn test_parse_several() {
+        let quirks = DeviceQuirks::parse("headerless, content-boundaries");
+        assert!(quirks.headerless_payloads);
+        assert!(quirks.content_boundaries);
+    }
+
+    #[test]
+    fn test_parse_ignores_unknown() {
+        let quirks = DeviceQuirks::parse("bogus,headerless");
+        assert!(quirks.headerless_payloads);
+        assert_eq!(DeviceQuirks::parse("bogus"), DeviceQuirks::default());
+    }
+}
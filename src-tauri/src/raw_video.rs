@@ -0,0 +1,629 @@
+//! Lossless raw video recording (assembled YUV frames plus an index)
+//!
+//! Processed recordings store what was displayed: JPEG, or RGB converted
+//! from YUV with whatever stride and pixel format settings were active. Raw
+//! video mode additionally stores every assembled frame exactly as the camera
+//! sent it, so it can be re-converted later with different settings or
+//! analysed without conversion artifacts.
+//!
+//! # Layout
+//!
+//! Inside the recording directory:
+//! - `raw_frames.bin`: assembled frames, concatenated, each compressed on its
+//!   own when zstd compression is enabled (so frames stay randomly accessible)
+//! - `raw_index.json`: [`RawVideoIndex`] with per-frame offsets, timestamps and
+//!   layout (dimensions, stride, pixel format)
+//!
+//! [`write_y4m`] converts a raw recording to YUV4MPEG2 (`.y4m`), which ffmpeg,
+//! mpv and most analysis tools read directly. The `convert_raw_video` binary
+//! wraps it for desktop use.
+
+use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
+use std::fs::File;
+use std::io::{BufWriter, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use thiserror::Error;
+
+use crate::storage::Storage;
+use crate::PixelFormat;
+
+/// Raw frame data file in the recording directory
+pub const RAW_FRAMES_FILE: &str = "raw_frames.bin";
+
+/// Raw frame index file in the recording directory
+pub const RAW_INDEX_FILE: &str = "raw_index.json";
+
+/// zstd level for raw frames (fast enough to keep up with streaming)
+#[cfg(feature = "zstd")]
+const ZSTD_LEVEL: i32 = 3;
+
+/// Errors that can occur while writing, reading or converting raw video
+#[derive(Error, Debug)]
+pub enum RawVideoError {
+    /// The compression or conversion is not available
+    #[error("Unsupported: {0}")]
+    Unsupported(String),
+
+    /// The frame data does not match its index entry
+    #[error("Corrupt raw frame {sequence}: {reason}")]
+    Corrupt {
+        /// Frame sequence number
+        sequence: u64,
+        /// What is wrong with the frame
+        reason: String,
+    },
+
+    /// I/O error
+    #[error("I/O error: {0}")]
+    Io(#[from] std::io::Error),
+
+    /// Index serialization error
+    #[error("JSON error: {0}")]
+    Json(#[from] serde_json::Error),
+}
+
+/// Result type for raw video operations
+pub type Result<T> = std::result::Result<T, RawVideoError>;
+
+/// Compression applied to each raw frame
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RawCompression {
+    /// Frames stored as-is
+    #[default]
+    None,
+    /// Each frame compressed with zstd (requires the `zstd` feature)
+    Zstd,
+}
+
+impl RawCompression {
+    /// Whether this compression is compiled in
+    pub fn is_available(self) -> bool {
+        match self {
+            RawCompression::None => true,
+            RawCompression::Zstd => cfg!(feature = "zstd"),
+        }
+    }
+
+    fn compress(self, data: &[u8]) -> Result<Cow<'_, [u8]>> {
+        match self {
+            RawCompression::None => Ok(Cow::Borrowed(data)),
+            #[cfg(feature = "zstd")]
+            RawCompression::Zstd => Ok(Cow::Owned(zstd::bulk::compress(data, ZSTD_LEVEL)?)),
+            #[cfg(not(feature = "zstd"))]
+            RawCompression::Zstd => Err(zstd_unavailable()),
+        }
+    }
+
+    fn decompress(self, data: Vec<u8>, raw_size: usize) -> Result<Vec<u8>> {
+        match self {
+            RawCompression::None => Ok(data),
+            #[cfg(feature = "zstd")]
+            RawCompression::Zstd => Ok(zstd::bulk::decompress(&data, raw_size)?),
+            #[cfg(not(feature = "zstd"))]
+            RawCompression::Zstd => {
+                let _ = raw_size;
+                Err(zstd_unavailable())
+            }
+        }
+    }
+}
+
+#[cfg(not(feature = "zstd"))]
+fn zstd_unavailable() -> RawVideoError {
+    RawVideoError::Unsupported("zstd compression is not available in this build".to_string())
+}
+
+/// How the pixels of a raw frame are laid out
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct FrameLayout {
+    /// Frame width in pixels
+    pub width: u32,
+    /// Frame height in pixels
+    pub height: u32,
+    /// Bytes per row of the first plane (packed formats may be padded)
+    pub stride: u32,
+    /// Pixel format
+    pub pixel_format: PixelFormat,
+}
+
+/// Location, timing and layout of one raw frame in `raw_frames.bin`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RawFrameEntry {
+    /// Frame sequence number within the recording (starting at 0)
+    pub sequence: u64,
+    /// Time since recording start (microseconds)
+    pub timestamp_us: u64,
+    /// Byte offset of the stored frame in `raw_frames.bin`
+    pub offset: u64,
+    /// Stored (possibly compressed) size in bytes
+    pub size: u32,
+    /// Uncompressed size in bytes
+    pub raw_size: u32,
+    /// Pixel layout
+    #[serde(flatten)]
+    pub layout: FrameLayout,
+}
+
+/// Index written as `raw_index.json` when a recording stops
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct RawVideoIndex {
+    /// Compression applied to each frame
+    pub compression: RawCompression,
+    /// Per-frame entries, in recording order
+    pub frames: Vec<RawFrameEntry>,
+}
+
+/// Streams raw frames to `raw_frames.bin`
+pub struct RawVideoWriter {
+    writer: BufWriter<File>,
+    path: PathBuf,
+    offset: u64,
+    index: RawVideoIndex,
+}
+
+impl RawVideoWriter {
+    /// Create `raw_frames.bin` in the recording directory
+    ///
+    /// # Errors
+    ///
+    /// Returns `RawVideoError::Unsupported` if the compression is not compiled
+    /// in, or `RawVideoError::Io` if the file cannot be created.
+    pub fn create(storage: &Storage, compression: RawCompression) -> Result<Self> {
+        if !compression.is_available() {
+            return Err(RawVideoError::Unsupported(format!(
+                "{:?} compression is not available in this build",
+                compression
+            )));
+        }
+        let (path, file) = storage.create(RAW_FRAMES_FILE)?;
+        Ok(Self {
+            writer: BufWriter::new(file),
+            path,
+            offset: 0,
+            index: RawVideoIndex {
+                compression,
+                frames: Vec::new(),
+            },
+        })
+    }
+
+    /// Path of `raw_frames.bin`
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Frames written so far
+    pub fn frame_count(&self) -> u64 {
+        self.index.frames.len() as u64
+    }
+
+    /// Append a frame
+    ///
+    /// # Errors
+    ///
+    /// Returns the compression or I/O error; the frame is not indexed then.
+    pub fn write_frame(
+        &mut self,
+        data: &[u8],
+        timestamp_us: u64,
+        layout: FrameLayout,
+    ) -> Result<()> {
+        let stored = self.index.compression.compress(data)?;
+        self.writer.write_all(&stored)?;
+
+        self.index.frames.push(RawFrameEntry {
+            sequence: self.frame_count(),
+            timestamp_us,
+            offset: self.offset,
+            size: stored.len() as u32,
+            raw_size: data.len() as u32,
+            layout,
+        });
+        self.offset += stored.len() as u64;
+        Ok(())
+    }
+
+    /// Flush the frames and write `raw_index.json`, returning its path
+    ///
+    /// # Errors
+    ///
+    /// Returns the I/O or JSON error.
+    pub fn finish(mut self, storage: &Storage) -> Result<PathBuf> {
+        self.writer.flush()?;
+        Ok(storage.write(RAW_INDEX_FILE, serde_json::to_string_pretty(&self.index)?)?)
+    }
+}
+
+/// Reads frames back from a raw recording
+pub struct RawVideoReader {
+    file: File,
+    index: RawVideoIndex,
+}
+
+impl RawVideoReader {
+    /// Open the raw video in a recording directory
+    ///
+    /// # Errors
+    ///
+    /// Returns `RawVideoError::Io` if the files cannot be opened, or
+    /// `RawVideoError::Json` if the index is invalid.
+    pub fn open(directory: &Path) -> Result<Self> {
+        let json = std::fs::read_to_string(directory.join(RAW_INDEX_FILE))?;
+        Ok(Self {
+            index: serde_json::from_str(&json)?,
+            file: File::open(directory.join(RAW_FRAMES_FILE))?,
+        })
+    }
+
+    /// The raw video index
+    pub fn index(&self) -> &RawVideoIndex {
+        &self.index
+    }
+
+    /// Read and decompress the frame at `position` in the index
+    ///
+    /// # Errors
+    ///
+    /// Returns `RawVideoError::Corrupt` if the position is out of range or the
+    /// frame does not have its recorded size, or the I/O/decompression error.
+    pub fn read_frame(&mut self, position: usize) -> Result<Vec<u8>> {
+        let entry = self
+            .index
+            .frames
+            .get(position)
+            .ok_or_else(|| RawVideoError::Corrupt {
+                sequence: position as u64,
+                reason: "not in the index".to_string(),
+            })?;
+
+        let mut stored = vec![0u8; entry.size as usize];
+        self.file.seek(SeekFrom::Start(entry.offset))?;
+        self.file.read_exact(&mut stored)?;
+        let data = self
+            .index
+            .compression
+            .decompress(stored, entry.raw_size as usize)?;
+
+        if data.len() != entry.raw_size as usize {
+            return Err(RawVideoError::Corrupt {
+                sequence: entry.sequence,
+                reason: format!("{} bytes, expected {}", data.len(), entry.raw_size),
+            });
+        }
+        Ok(data)
+    }
+}
+
+/// Y4M colorspace tag for a pixel format
+fn y4m_colorspace(format: PixelFormat) -> Result<&'static str> {
+    match format {
+        PixelFormat::Yuyv | PixelFormat::Uyvy => Ok("422"),
+        PixelFormat::Nv12 | PixelFormat::Nv21 | PixelFormat::I420 | PixelFormat::Yv12 => {
+            Ok("420jpeg")
+        }
+        PixelFormat::Rgb888 | PixelFormat::Bgr888 | PixelFormat::BayerRggb => Err(not_yuv(format)),
+    }
+}
+
+fn not_yuv(format: PixelFormat) -> RawVideoError {
+    RawVideoError::Unsupported(format!("{} frames cannot be written as Y4M", format))
+}
+
+/// Convert a raw frame to the planar Y, U, V layout Y4M expects
+///
+/// The width must be even: every supported format shares a chroma sample
+/// between two horizontal pixels.
+fn to_planar(data: &[u8], layout: &FrameLayout, sequence: u64) -> Result<Vec<u8>> {
+    let width = layout.width as usize;
+    let height = layout.height as usize;
+    if width == 0 || height == 0 || !width.is_multiple_of(2) {
+        return Err(RawVideoError::Unsupported(format!(
+            "{}x{} frames cannot be written as Y4M",
+            width, height
+        )));
+    }
+    let too_short = |needed: usize| RawVideoError::Corrupt {
+        sequence,
+        reason: format!(
+            "{} bytes is too short for {}x{} {} ({} needed)",
+            data.len(),
+            width,
+            height,
+            layout.pixel_format,
+            needed
+        ),
+    };
+
+    match layout.pixel_format {
+        PixelFormat::Yuyv | PixelFormat::Uyvy => {
+            let stride = (layout.stride as usize).max(width * 2);
+            let needed = stride * (height - 1) + width * 2;
+            if data.len() < needed {
+                return Err(too_short(needed));
+            }
+            // Byte offsets of Y0, U, Y1, V within each 4-byte macropixel
+            let (y0, u, y1, v) = if layout.pixel_format == PixelFormat::Yuyv {
+                (0, 1, 2, 3)
+            } else {
+                (1, 0, 3, 2)
+            };
+
+            let chroma_width = width / 2;
+            let mut y_plane = Vec::with_capacity(width * height);
+            let mut u_plane = Vec::with_capacity(chroma_width * height);
+            let mut v_plane = Vec::with_capacity(chroma_width * height);
+            for row in data.chunks(stride).take(height) {
+                for px in row[..chroma_width * 4].chunks_exact(4) {
+                    y_plane.extend_from_slice(&[px[y0], px[y1]]);
+                    u_plane.push(px[u]);
+                    v_plane.push(px[v]);
+                }
+            }
+            y_plane.append(&mut u_plane);
+            y_plane.append(&mut v_plane);
+            Ok(y_plane)
+        }
+        PixelFormat::I420 => {
+            let needed = width * height * 3 / 2;
+            if data.len() < needed {
+                return Err(too_short(needed));
+            }
+            Ok(data[..needed].to_vec())
+        }
+        PixelFormat::Yv12 => {
+            let luma = width * height;
+            let chroma = luma / 4;
+            let needed = luma + chroma * 2;
+            if data.len() < needed {
+                return Err(too_short(needed));
+            }
+            // Swap the V and U planes
+            let mut planar = Vec::with_capacity(needed);
+            planar.extend_from_slice(&data[..luma]);
+            planar.extend_from_slice(&data[luma + chroma..needed]);
+            planar.extend_from_slice(&data[luma..luma + chroma]);
+            Ok(planar)
+        }
+        PixelFormat::Nv12 | PixelFormat::Nv21 => {
+            let luma = width * height;
+            let needed = luma * 3 / 2;
+            if data.len() < needed {
+                return Err(too_short(needed));
+            }
+            let mut planar = Vec::with_capacity(needed);
+            planar.extend_from_slice(&data[..luma]);
+            let uv = &data[luma..needed];
+            let (u, v) = if layout.pixel_format == PixelFormat::Nv21 {
+                (1, 0)
+            } else {
+                (0, 1)
+            };
+            planar.extend(uv.iter().skip(u).step_by(2));
+            planar.extend(uv.iter().skip(v).step_by(2));
+            Ok(planar)
+        }
+        PixelFormat::Rgb888 | PixelFormat::Bgr888 | PixelFormat::BayerRggb => {
+            Err(not_yuv(layout.pixel_format))
+        }
+    }
+}
+
+/// Convert a raw recording to YUV4MPEG2, returning the number of frames written
+///
+/// The frame rate is the average over the recording. Y4M needs one size and
+/// pixel format for the whole stream, so conversion stops at the first frame
+/// whose layout differs from the first frame's.
+///
+/// # Errors
+///
+/// Returns `RawVideoError::Unsupported` for RGB recordings or an empty index,
+/// `RawVideoError::Corrupt` for truncated frames, or the I/O error.
+pub fn write_y4m(reader: &mut RawVideoReader, out: &mut impl Write) -> Result<u64> {
+    let frames = reader.index().frames.clone();
+    let Some(first) = frames.first() else {
+        return Err(RawVideoError::Unsupported(
+            "recording has no raw frames".to_string(),
+        ));
+    };
+    let layout = first.layout;
+    let colorspace = y4m_colorspace(layout.pixel_format)?;
+
+    let (fps_num, fps_den) = match frames.last() {
+        Some(last) if frames.len() > 1 && last.timestamp_us > first.timestamp_us => {
+            let span_us = (last.timestamp_us - first.timestamp_us) as f64;
+            let fps = (frames.len() - 1) as f64 * 1_000_000.0 / span_us;
+            ((fps * 1000.0).round().max(1.0) as u64, 1000)
+        }
+        _ => (30, 1),
+    };
+
+    writeln!(
+        out,
+        "YUV4MPEG2 W{} H{} F{}:{} Ip A1:1 C{}",
+        layout.width, layout.height, fps_num, fps_den, colorspace
+    )?;
+
+    let mut written = 0;
+    for (position, entry) in frames.iter().enumerate() {
+        let same_size = entry.layout.width == layout.width
+            && entry.layout.height == layout.height
+            && entry.layout.pixel_format == layout.pixel_format;
+        if !same_size {
+            log::warn!(
+                "Raw frame {} is {}x{} {}, stopping Y4M output at {} frames",
+                entry.sequence,
+                entry.layout.width,
+                entry.layout.height,
+                entry.layout.pixel_format,
+                written
+            );
+            break;
+        }
+        let data = reader.read_frame(position)?;
+        out.write_all(b"FRAME\n")?;
+        out.write_all(&to_planar(&data, &entry.layout, entry.sequence)?)?;
+        written += 1;
+    }
+    out.flush()?;
+    Ok(written)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn layout(width: u32, height: u32, stride: u32, pixel_format: PixelFormat) -> FrameLayout {
+        FrameLayout {
+            width,
+            height,
+            stride,
+            pixel_format,
+        }
+    }
+
+    #[test]
+    fn test_write_and_read_back() {
+        let dir = tempfile::tempdir().unwrap();
+        let storage = Storage::new(dir.path());
+        let yuyv = layout(2, 1, 4, PixelFormat::Yuyv);
+
+        let mut writer = RawVideoWriter::create(&storage, RawCompression::None).unwrap();
+        writer.write_frame(&[1, 2, 3, 4], 0, yuyv).unwrap();
+        writer
+            .write_frame(&[5, 6, 7, 8, 9, 9], 33_000, yuyv)
+            .unwrap();
+        writer.finish(&storage).unwrap();
+
+        let mut reader = RawVideoReader::open(dir.path()).unwrap();
+        let index = reader.index().clone();
+        assert_eq!(index.frames.len(), 2);
+        assert_eq!(index.frames[1].offset, 4);
+        assert_eq!(index.frames[1].timestamp_us, 33_000);
+        assert_eq!(reader.read_frame(1).unwrap(), [5, 6, 7, 8, 9, 9]);
+        assert_eq!(reader.read_frame(0).unwrap(), [1, 2, 3, 4]);
+        assert!(reader.read_frame(2).is_err());
+    }
+
+    #[test]
+    fn test_zstd_availability() {
+        let dir = tempfile::tempdir().unwrap();
+        let storage = Storage::new(dir.path());
+        let result = RawVideoWriter::create(&storage, RawCompression::Zstd);
+        assert_eq!(result.is_ok(), RawCompression::Zstd.is_available());
+    }
+
+    #[cfg(feature = "zstd")]
+    #[test]
+    fn test_zstd_round_trip() {
+        let dir = tempfile::tempdir().unwrap();
+        let storage = Storage::new(dir.path());
+        let frame = vec![128u8; 64 * 48 * 2];
+
+        let mut writer = RawVideoWriter::create(&storage, RawCompression::Zstd).unwrap();
+        writer
+            .write_frame(&frame, 0, layout(64, 48, 128, PixelFormat::Yuyv))
+            .unwrap();
+        writer.finish(&storage).unwrap();
+
+        let mut reader = RawVideoReader::open(dir.path()).unwrap();
+        assert!((reader.index().frames[0].size as usize) < frame.len());
+        assert_eq!(reader.read_frame(0).unwrap(), frame);
+    }
+
+    #[test]
+    fn test_packed_422_to_planar() {
+        // 2x2 YUYV with 2 bytes of row padding
+        let data = [
+            10, 20, 11, 30, 0, 0, //
+            12, 21, 13, 31, 0, 0,
+        ];
+        let planar = to_planar(&data, &layout(2, 2, 6, PixelFormat::Yuyv), 0).unwrap();
+        assert_eq!(planar, [10, 11, 12, 13, 20, 21, 30, 31]);
+
+        let uyvy = [20, 10, 30, 11];
+        let planar = to_planar(&uyvy, &layout(2, 1, 4, PixelFormat::Uyvy), 0).unwrap();
+        assert_eq!(planar, [10, 11, 20, 30]);
+
+        assert!(to_planar(&data[..8], &layout(2, 2, 6, PixelFormat::Yuyv), 0).is_err());
+        // Odd widths have half a macropixel; empty frames have no rows
+        for (width, height) in [(3, 2), (2, 0), (0, 2)] {
+            assert!(matches!(
+                to_planar(&data, &layout(width, height, 6, PixelFormat::Yuyv), 0),
+                Err(RawVideoError::Unsupported(_))
+            ));
+        }
+    }
+
+    #[test]
+    fn test_nv12_to_planar() {
+        // 2x2 luma, one interleaved UV pair
+        let data = [1, 2, 3, 4, 50, 60];
+        let planar = to_planar(&data, &layout(2, 2, 2, PixelFormat::Nv12), 0).unwrap();
+        assert_eq!(planar, [1, 2, 3, 4, 50, 60]);
+
+        let data = [1, 2, 3, 4, 5, 6, 7, 8, 50, 60, 51, 61];
+        let planar = to_planar(&data, &layout(4, 2, 4, PixelFormat::Nv12), 0).unwrap();
+        assert_eq!(&planar[8..], [50, 51, 60, 61]);
+
+        let planar = to_planar(&data, &layout(4, 2, 4, PixelFormat::Nv21), 0).unwrap();
+        assert_eq!(&planar[8..], [60, 61, 50, 51]);
+    }
+
+    #[test]
+    fn test_yv12_to_planar() {
+        // 4x2 luma, one row of V then one row of U
+        let data = [1, 2, 3, 4, 5, 6, 7, 8, 60, 61, 50, 51];
+        let planar = to_planar(&data, &layout(4, 2, 4, PixelFormat::Yv12), 0).unwrap();
+        assert_eq!(&planar[8..], [50, 51, 60, 61]);
+    }
+
+    #[test]
+    fn test_y4m_output() {
+        let dir = tempfile::tempdir().unwrap();
+        let storage = Storage::new(dir.path());
+        let yuyv = layout(2, 1, 4, PixelFormat::Yuyv);
+
+        let mut writer = RawVideoWriter::create(&storage, RawCompression::None).unwrap();
+        for i in 0..3u8 {
+            writer
+                .write_frame(&[i, 128, i, 128], u64::from(i) * 40_000, yuyv)
+                .unwrap();
+        }
+        // Resolution change ends the stream
+        writer
+            .write_frame(&[0; 8], 120_000, layout(4, 1, 8, PixelFormat::Yuyv))
+            .unwrap();
+        writer.finish(&storage).unwrap();
+
+        let mut reader = RawVideoReader::open(dir.path()).unwrap();
+        let mut out = Vec::new();
+        assert_eq!(write_y4m(&mut reader, &mut out).unwrap(), 3);
+
+        let header = "YUV4MPEG2 W2 H1 F25000:1000 Ip A1:1 C422\n";
+        assert!(out.starts_with(header.as_bytes()));
+        assert_eq!(out.len(), header.len() + 3 * (6 + 4));
+        assert_eq!(
+            &out[header.len()..header.len() + 10],
+            b"FRAME\n\x00\x00\x80\x80"
+        );
+    }
+
+    #[test]
+    fn test_y4m_rejects_rgb() {
+        let dir = tempfile::tempdir().unwrap();
+        let storage = Storage::new(dir.path());
+        let mut writer = RawVideoWriter::create(&storage, RawCompression::None).unwrap();
+        writer
+            .write_frame(&[0; 3], 0, layout(1, 1, 3, PixelFormat::Rgb888))
+            .unwrap();
+        writer.finish(&storage).unwrap();
+
+        let mut reader = RawVideoReader::open(dir.path()).unwrap();
+        assert!(matches!(
+            write_y4m(&mut reader, &mut Vec::new()),
+            Err(RawVideoError::Unsupported(_))
+        ));
+    }
+}
@@ -0,0 +1,1568 @@
+//! Session recording of processed frames, optionally alongside raw packets.
+//!
+//! A recording writes every processed frame (JPEG or RGB, as stored in the
+//! frame buffer) to disk as it arrives. With `include_raw` enabled, the raw
+//! device payload stream is captured at the same time via [`CaptureState`],
+//! so the session can later be reprocessed with a different pipeline.
+//!
+//! # Layout
+//!
+//! Each recording gets its own directory `recording_<timestamp>/` (with a
+//! `_1`, `_2`, ... suffix if one was already started in that second):
+//! - `frames.bin`: processed frames, concatenated
+//! - `index.json`: [`RecordingIndex`] with per-frame offsets and timestamps
+//! - `index.journal`: the index entries as JSON lines, appended while
+//!   recording and removed once `index.json` is written; [`recover`] rebuilds
+//!   the index from it after a crash
+//! - `packets_<ts>.bin` / `metadata_<ts>.json` / `transfers_<ts>.bin`: raw capture
+//!   and per-packet transfer records (if enabled)
+//! - `chapters.ffmetadata` / `chapters.vtt`: chapters from bookmarks (if any)
+//! - `raw_frames.bin` / `raw_index.json`: assembled frames before conversion,
+//!   in raw video mode (see [`crate::raw_video`])
+//! - `video.avi` / `video.mkv`: the processed frames as MJPEG video, if
+//!   requested (see [`crate::video_recorder`]); MKV videos also carry the
+//!   chapters
+//!
+//! Frame timestamps are relative to the capture start time, and each frame
+//! records how many raw packets had been captured when it was stored, so the
+//! two outputs can be correlated exactly.
+//!
+//! Processed frames are streamed straight to disk rather than buffered; the
+//! raw packets are buffered once, by the capture itself. The video is written
+//! by its own thread, so JPEG-encoding RGB frames never holds up streaming.
+//!
+//! # Snapshots
+//!
+//! Single frames are saved with [`write_snapshot`] as
+//! `snapshot_<unix ms>_<width>x<height>.<ext>`, independent of any recording.
+
+use serde::{Deserialize, Serialize};
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver, SyncSender, TrySendError};
+use std::sync::{Arc, Mutex};
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+use thiserror::Error;
+
+use crate::capture::{CaptureError, CaptureMetadata, CaptureResult, CaptureState};
+use crate::chapters;
+use crate::encode_quality::{AdaptiveQuality, QualityChange};
+use crate::frame_assembler::jpeg_dimensions;
+use crate::image_encoder::ImageFormat;
+use crate::raw_video::{FrameLayout, RawCompression, RawVideoError, RawVideoWriter};
+use crate::storage::Storage;
+use crate::video_recorder::{VideoContainer, VideoError, VideoWriter, DEFAULT_VIDEO_FPS};
+
+/// Errors that can occur during recording operations.
+#[derive(Error, Debug)]
+pub enum RecordingError {
+    /// Attempted to stop a recording that wasn't started.
+    #[error("No recording is currently active")]
+    NotActive,
+
+    /// Attempted to start a recording while one is already active.
+    #[error("Recording is already active")]
+    AlreadyActive,
+
+    /// Failed to acquire a lock on shared state.
+    #[error("Failed to acquire lock: {0}")]
+    LockError(String),
+
+    /// I/O error during file operations.
+    #[error("I/O error: {0}")]
+    Io(#[from] std::io::Error),
+
+    /// JSON serialization error for the index.
+    #[error("JSON error: {0}")]
+    Json(#[from] serde_json::Error),
+
+    /// The raw packet capture failed.
+    #[error("Raw capture error: {0}")]
+    Capture(#[from] CaptureError),
+
+    /// The raw video output failed.
+    #[error("Raw video error: {0}")]
+    RawVideo(#[from] RawVideoError),
+
+    /// The video output failed.
+    #[error("Video error: {0}")]
+    Video(#[from] VideoError),
+
+    /// An interrupted recording has nothing to recover.
+    #[error("Recording cannot be recovered: {0}")]
+    Unrecoverable(String),
+}
+
+/// Result type for recording operations.
+pub type Result<T> = std::result::Result<T, RecordingError>;
+
+/// Processed frames file in a recording directory.
+const FRAMES_FILE: &str = "frames.bin";
+
+/// Index file written when a recording stops.
+const INDEX_FILE: &str = "index.json";
+
+/// Index entries appended while recording, for [`recover`].
+const JOURNAL_FILE: &str = "index.journal";
+
+/// Frames waiting for the video thread before new ones are left out.
+const VIDEO_QUEUE_DEPTH: usize = 4;
+
+/// Encoding of a recorded frame.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FrameFormat {
+    /// JPEG (MJPEG stream)
+    Jpeg,
+    /// Packed RGB888 (converted from YUV)
+    Rgb,
+}
+
+/// Options for starting a recording.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct RecordingOptions {
+    /// Free-form label stored in the index and raw capture metadata.
+    #[serde(default)]
+    pub label: String,
+    /// Also capture the raw device payload stream.
+    #[serde(default)]
+    pub include_raw: bool,
+    /// Also write assembled YUV frames before conversion (raw video mode).
+    #[serde(default)]
+    pub raw_video: bool,
+    /// Compression for raw video frames.
+    #[serde(default)]
+    pub raw_compression: RawCompression,
+    /// Also mux the processed frames into a video file.
+    #[serde(default)]
+    pub video: Option<VideoContainer>,
+    /// Frame rate of the video time base (default [`DEFAULT_VIDEO_FPS`]).
+    #[serde(default)]
+    pub video_fps: Option<u32>,
+    /// Frame interval negotiated with the camera, which encoding RGB frames
+    /// into the video has to keep up with. The video time base is used if
+    /// it is unknown.
+    #[serde(skip)]
+    pub frame_interval: Option<Duration>,
+}
+
+/// Location and timing of a single frame in `frames.bin`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FrameIndexEntry {
+    /// Frame sequence number within the recording (starting at 0).
+    pub sequence: u64,
+    /// Time since recording start (microseconds).
+    pub timestamp_us: u64,
+    /// Byte offset of the frame in `frames.bin`.
+    pub offset: u64,
+    /// Frame size in bytes.
+    pub size: u32,
+    /// Frame width in pixels.
+    pub width: u32,
+    /// Frame height in pixels.
+    pub height: u32,
+    /// Frame encoding.
+    pub format: FrameFormat,
+    /// Number of raw packets captured before this frame (if raw capture is enabled).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub packet_index: Option<u64>,
+}
+
+/// A bookmarked position within a recording.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RecordingMarker {
+    /// Sequence number of the most recently recorded frame.
+    pub frame: u64,
+    /// Time since recording start (microseconds).
+    pub timestamp_us: u64,
+    /// Optional note.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub note: Option<String>,
+}
+
+/// Index written next to `frames.bin` when a recording stops.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct RecordingIndex {
+    /// Label given when the recording started.
+    #[serde(default)]
+    pub label: String,
+    /// Wall-clock start time (milliseconds since the Unix epoch).
+    pub started_at_ms: u64,
+    /// Recording duration in milliseconds.
+    pub duration_ms: u64,
+    /// Total bytes written to `frames.bin`.
+    pub total_bytes: u64,
+    /// File name of the raw packet capture, if recorded.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub raw_packets_file: Option<String>,
+    /// Bookmarks added during the recording.
+    #[serde(default)]
+    pub markers: Vec<RecordingMarker>,
+    /// Per-frame entries, in recording order.
+    pub frames: Vec<FrameIndexEntry>,
+    /// Frames the stream delivered but the recorder fell too far behind to
+    /// write.
+    #[serde(default)]
+    pub skipped_frames: u64,
+}
+
+/// A line of `index.journal`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum JournalEntry {
+    /// First line: what the index header needs.
+    Start { label: String, started_at_ms: u64 },
+    /// A recorded frame.
+    Frame(FrameIndexEntry),
+    /// A bookmark.
+    Marker(RecordingMarker),
+    /// Frames the recorder fell too far behind to write.
+    Skipped { frames: u64 },
+}
+
+/// Result of a completed recording.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RecordingResult {
+    /// Directory containing all recording files.
+    pub directory: String,
+    /// Path to `frames.bin`.
+    pub frames_path: String,
+    /// Path to `index.json`.
+    pub index_path: String,
+    /// Number of frames recorded.
+    pub frame_count: u64,
+    /// Recording duration in milliseconds.
+    pub duration_ms: u64,
+    /// Raw capture output, if raw recording was enabled.
+    pub raw: Option<CaptureResult>,
+    /// Path to `chapters.ffmetadata`, if any bookmarks were added.
+    /// A `WebVTT` version is written next to it as `chapters.vtt`.
+    pub chapters_path: Option<String>,
+    /// Path to `raw_index.json`, in raw video mode.
+    #[serde(default)]
+    pub raw_video_path: Option<String>,
+    /// Path to the video file, if video output was requested.
+    #[serde(default)]
+    pub video_path: Option<String>,
+    /// Why the video could not be finished; the frames and index are kept.
+    #[serde(default)]
+    pub video_error: Option<String>,
+    /// Frames the recorder fell too far behind to write.
+    #[serde(default)]
+    pub skipped_frames: u64,
+}
+
+/// Current recording status for the frontend.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RecordingStatus {
+    /// Whether a recording is active.
+    pub is_recording: bool,
+    /// Frames recorded so far.
+    pub frame_count: u64,
+    /// Time since recording started (milliseconds).
+    pub duration_ms: u64,
+    /// Whether raw packets are being captured too.
+    pub include_raw: bool,
+    /// Recording label.
+    pub label: String,
+    /// Frames skipped so far because the recorder fell behind.
+    pub skipped_frames: u64,
+}
+
+/// State of the recording in progress.
+struct ActiveRecording {
+    storage: Storage,
+    frames_path: PathBuf,
+    writer: BufWriter<File>,
+    journal: BufWriter<File>,
+    epoch: Instant,
+    started_at_ms: u64,
+    offset: u64,
+    options: RecordingOptions,
+    frames: Vec<FrameIndexEntry>,
+    markers: Vec<RecordingMarker>,
+    skipped_frames: u64,
+    raw_video: Option<RawVideoWriter>,
+    video: Option<VideoThread>,
+}
+
+/// Video output of the recording in progress.
+struct ActiveVideo {
+    path: PathBuf,
+    writer: VideoWriter<BufWriter<File>>,
+    /// JPEG quality for RGB frames, lowered while encoding can't keep up.
+    quality: AdaptiveQuality,
+    /// Time the camera takes per frame, the budget for encoding one.
+    frame_interval: Duration,
+}
+
+impl ActiveVideo {
+    /// Adds a frame to the video, adapting the JPEG quality of RGB frames to
+    /// the camera's frame interval.
+    ///
+    /// The time between recorded frames can't be the budget: frames arrive
+    /// late while encoding is slow, so the gap grows with the encode time.
+    ///
+    /// Returns the quality change, if any.
+    fn push_frame(
+        &mut self,
+        data: &[u8],
+        width: u32,
+        height: u32,
+        format: FrameFormat,
+        timestamp_us: u64,
+    ) -> Option<QualityChange> {
+        let started = Instant::now();
+        if let Err(e) = self.writer.push_frame(data, width, height, timestamp_us) {
+            log::error!("Failed to write video frame: {}", e);
+            return None;
+        }
+        // JPEG frames are stored without re-encoding
+        if format != FrameFormat::Rgb {
+            return None;
+        }
+
+        self.adapt_quality(started.elapsed())
+    }
+
+    /// Adjusts the JPEG quality for an RGB frame that took `encode` to add.
+    fn adapt_quality(&mut self, encode: Duration) -> Option<QualityChange> {
+        let change = self.quality.observe(encode, self.frame_interval)?;
+        self.writer.set_quality(change.to);
+        log::info!(
+            "Video JPEG quality {} -> {} (encode {} us, frame interval {} us)",
+            change.from,
+            change.to,
+            change.encode_us,
+            change.interval_us
+        );
+        Some(change)
+    }
+}
+
+/// A frame handed to the video thread.
+struct VideoFrame {
+    data: Vec<u8>,
+    width: u32,
+    height: u32,
+    format: FrameFormat,
+    timestamp_us: u64,
+}
+
+/// Thread writing the video of the recording in progress.
+///
+/// The streaming thread only queues frames. If the video thread falls
+/// [`VIDEO_QUEUE_DEPTH`] frames behind, new frames are left out of the video
+/// (the frames file still has them) and the adaptive quality drops.
+struct VideoThread {
+    sender: SyncSender<VideoFrame>,
+    /// Quality changes made on the video thread, not yet reported.
+    quality_changes: Receiver<QualityChange>,
+    handle: JoinHandle<ActiveVideo>,
+    /// Frames left out because the queue was full.
+    skipped: u64,
+}
+
+impl VideoThread {
+    fn spawn(mut video: ActiveVideo) -> std::io::Result<Self> {
+        let (sender, frames) = mpsc::sync_channel::<VideoFrame>(VIDEO_QUEUE_DEPTH);
+        let (changes, quality_changes) = mpsc::channel();
+        let handle = std::thread::Builder::new()
+            .name("recording-video".to_string())
+            .spawn(move || {
+                for frame in frames {
+                    let change = video.push_frame(
+                        &frame.data,
+                        frame.width,
+                        frame.height,
+                        frame.format,
+                        frame.timestamp_us,
+                    );
+                    if let Some(change) = change {
+                        let _ = changes.send(change);
+                    }
+                }
+                video
+            })?;
+        Ok(Self {
+            sender,
+            quality_changes,
+            handle,
+            skipped: 0,
+        })
+    }
+
+    /// Queues a frame; returns the latest quality change since the last call.
+    fn push(&mut self, frame: VideoFrame) -> Option<QualityChange> {
+        match self.sender.try_send(frame) {
+            Ok(()) => {}
+            Err(TrySendError::Full(_)) => self.skipped += 1,
+            Err(TrySendError::Disconnected(_)) => {
+                log::error!("Video thread stopped; frames are no longer added to the video");
+            }
+        }
+        self.quality_changes.try_iter().last()
+    }
+
+    /// Waits for the queued frames to be written and returns the video.
+    fn finish(self) -> Option<ActiveVideo> {
+        drop(self.sender);
+        if self.skipped > 0 {
+            log::warn!(
+                "Video left out {} frames while encoding fell behind",
+                self.skipped
+            );
+        }
+        self.handle.join().ok()
+    }
+}
+
+/// Thread-safe recorder shared between commands and the streaming thread.
+pub struct RecordingState {
+    /// Whether a recording is active (fast path for the streaming thread).
+    is_recording: AtomicBool,
+    /// The recording in progress.
+    active: Mutex<Option<ActiveRecording>>,
+    /// Packet capture used for the raw output.
+    capture: Arc<CaptureState>,
+}
+
+impl RecordingState {
+    /// Creates a recorder that uses `capture` for raw packet recording.
+    #[must_use]
+    pub fn new(capture: Arc<CaptureState>) -> Self {
+        Self {
+            is_recording: AtomicBool::new(false),
+            active: Mutex::new(None),
+            capture,
+        }
+    }
+
+    /// Returns whether a recording is active.
+    #[must_use]
+    pub fn is_recording(&self) -> bool {
+        self.is_recording.load(Ordering::Acquire)
+    }
+
+    /// Starts a new recording in a fresh directory in `storage`.
+    ///
+    /// Returns the recording directory.
+    ///
+    /// # Errors
+    ///
+    /// Returns `RecordingError::AlreadyActive` if a recording is in progress,
+    /// `RecordingError::Capture` if raw capture is requested but a packet capture
+    /// is already running, and `RecordingError::Io` if the files cannot be created.
+    pub fn start(&self, storage: &Storage, options: RecordingOptions) -> Result<PathBuf> {
+        let mut active = self
+            .active
+            .lock()
+            .map_err(|e| RecordingError::LockError(e.to_string()))?;
+        if active.is_some() {
+            return Err(RecordingError::AlreadyActive);
+        }
+
+        let now = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap_or_default();
+        let name = create_recording_dir(storage, now.as_secs())?;
+        // Removes the directory again if any of the steps below fails
+        let created = CreatedDir {
+            storage,
+            name: Some(name.clone()),
+        };
+        let rec_storage = storage.subdir(&name)?;
+        let (frames_path, file) = rec_storage.create(FRAMES_FILE)?;
+        let writer = BufWriter::new(file);
+        let directory = rec_storage.root().to_path_buf();
+        let started_at_ms = now.as_millis() as u64;
+        let mut journal = BufWriter::new(rec_storage.create(JOURNAL_FILE)?.1);
+        append_journal(
+            &mut journal,
+            &JournalEntry::Start {
+                label: options.label.clone(),
+                started_at_ms,
+            },
+        )?;
+
+        let raw_video = if options.raw_video {
+            Some(RawVideoWriter::create(
+                &rec_storage,
+                options.raw_compression,
+            )?)
+        } else {
+            None
+        };
+
+        let video = match options.video {
+            Some(container) => {
+                let (path, file) = rec_storage.create(container.file_name())?;
+                let fps = options.video_fps.unwrap_or(DEFAULT_VIDEO_FPS);
+                Some(VideoThread::spawn(ActiveVideo {
+                    path,
+                    writer: VideoWriter::new(container, BufWriter::new(file), fps),
+                    quality: AdaptiveQuality::default(),
+                    frame_interval: options
+                        .frame_interval
+                        .filter(|interval| !interval.is_zero())
+                        .unwrap_or_else(|| Duration::from_secs(1) / fps.max(1)),
+                })?)
+            }
+            None => None,
+        };
+
+        // Start raw capture last so its time base is shared with the frames
+        let epoch = if options.include_raw {
+            self.capture.start_capture(CaptureMetadata {
+                description: options.label.clone(),
+                record_transfers: true,
+                ..Default::default()
+            })?;
+            self.capture.started_at().unwrap_or_else(Instant::now)
+        } else {
+            Instant::now()
+        };
+        created.keep();
+
+        log::info!(
+            "Recording started in {} (raw: {}, raw video: {}, video: {:?})",
+            directory.display(),
+            options.include_raw,
+            options.raw_video,
+            options.video
+        );
+
+        *active = Some(ActiveRecording {
+            storage: rec_storage,
+            frames_path,
+            writer,
+            journal,
+            epoch,
+            started_at_ms,
+            offset: 0,
+            options,
+            frames: Vec::new(),
+            markers: Vec::new(),
+            skipped_frames: 0,
+            raw_video,
+            video,
+        });
+        self.is_recording.store(true, Ordering::Release);
+        Ok(directory)
+    }
+
+    /// Records a processed frame.
+    ///
+    /// Called from the streaming thread; does nothing when no recording is
+    /// active. Write errors are logged rather than interrupting streaming.
+    /// JPEG frames are recorded at the size in their header, since bulk
+    /// MJPEG streams pass 0x0.
+    ///
+    /// Returns the change if the video's JPEG quality was adapted to the
+    /// encode time (see [`crate::encode_quality`]) since the last frame.
+    pub fn record_frame(
+        &self,
+        data: &[u8],
+        width: u32,
+        height: u32,
+        format: FrameFormat,
+    ) -> Option<QualityChange> {
+        if !self.is_recording.load(Ordering::Acquire) || data.is_empty() {
+            return None;
+        }
+
+        let (width, height) = match format {
+            FrameFormat::Jpeg => jpeg_dimensions(data).unwrap_or((width, height)),
+            FrameFormat::Rgb => (width, height),
+        };
+
+        let mut guard = crate::lock_or_recover(&self.active);
+        let rec = guard.as_mut()?;
+
+        if let Err(e) = rec.writer.write_all(data) {
+            log::error!("Failed to write recorded frame: {}", e);
+            return None;
+        }
+
+        let packet_index = if rec.options.include_raw {
+            self.capture.record_frame();
+            Some(self.capture.packet_count())
+        } else {
+            None
+        };
+
+        let timestamp_us = rec.epoch.elapsed().as_micros() as u64;
+        let quality_change = rec.video.as_mut().and_then(|video| {
+            video.push(VideoFrame {
+                data: data.to_vec(),
+                width,
+                height,
+                format,
+                timestamp_us,
+            })
+        });
+
+        let entry = FrameIndexEntry {
+            sequence: rec.frames.len() as u64,
+            timestamp_us,
+            offset: rec.offset,
+            size: data.len() as u32,
+            width,
+            height,
+            format,
+            packet_index,
+        };
+        if let Err(e) = append_journal(&mut rec.journal, &JournalEntry::Frame(entry.clone())) {
+            log::error!("Failed to write recording journal: {}", e);
+        }
+        rec.frames.push(entry);
+        rec.offset += data.len() as u64;
+        quality_change
+    }
+
+    /// Records an assembled frame before conversion, in raw video mode.
+    ///
+    /// Called from the streaming thread; does nothing unless a raw video
+    /// recording is active. Write errors are logged rather than interrupting
+    /// streaming.
+    pub fn record_raw_frame(&self, data: &[u8], layout: FrameLayout) {
+        if !self.is_recording.load(Ordering::Acquire) || data.is_empty() {
+            return;
+        }
+
+        let mut guard = crate::lock_or_recover(&self.active);
+        let Some(rec) = guard.as_mut() else {
+            return;
+        };
+        let timestamp_us = rec.epoch.elapsed().as_micros() as u64;
+        if let Some(raw_video) = rec.raw_video.as_mut() {
+            if let Err(e) = raw_video.write_frame(data, timestamp_us, layout) {
+                log::error!("Failed to write raw video frame: {}", e);
+            }
+        }
+    }
+
+    /// Counts frames the recorder missed because it fell behind the stream.
+    ///
+    /// The count is kept in the index, so a recording with gaps says so.
+    /// Does nothing when no recording is active.
+    pub fn record_skipped(&self, frames: u64) {
+        if frames == 0 || !self.is_recording.load(Ordering::Acquire) {
+            return;
+        }
+        let mut guard = crate::lock_or_recover(&self.active);
+        let Some(rec) = guard.as_mut() else {
+            return;
+        };
+        log::warn!(
+            "Recording skipped {} frames while the disk was busy",
+            frames
+        );
+        rec.skipped_frames += frames;
+        if let Err(e) = append_journal(&mut rec.journal, &JournalEntry::Skipped { frames }) {
+            log::error!("Failed to write recording journal: {}", e);
+        }
+    }
+
+    /// Adds a marker at the most recently recorded frame.
+    ///
+    /// Returns `None` if no recording is active.
+    pub fn add_marker(&self, note: Option<String>) -> Option<RecordingMarker> {
+        let mut guard = self.active.lock().ok()?;
+        let rec = guard.as_mut()?;
+        let marker = RecordingMarker {
+            frame: (rec.frames.len() as u64).saturating_sub(1),
+            timestamp_us: rec.epoch.elapsed().as_micros() as u64,
+            note,
+        };
+        if let Err(e) = append_journal(&mut rec.journal, &JournalEntry::Marker(marker.clone())) {
+            log::error!("Failed to write recording journal: {}", e);
+        }
+        rec.markers.push(marker.clone());
+        Some(marker)
+    }
+
+    /// Flushes the recorded frames and journal to disk.
+    ///
+    /// Called periodically so that [`recover`] loses at most the frames since
+    /// the last flush. Does nothing when no recording is active.
+    pub fn flush(&self) {
+        let mut guard = crate::lock_or_recover(&self.active);
+        let Some(rec) = guard.as_mut() else {
+            return;
+        };
+        if let Err(e) = rec.writer.flush().and_then(|()| rec.journal.flush()) {
+            log::error!("Failed to flush recording: {}", e);
+        }
+    }
+
+    /// Returns the directory of the active recording.
+    #[must_use]
+    pub fn directory(&self) -> Option<PathBuf> {
+        let guard = self.active.lock().ok()?;
+        guard.as_ref().map(|rec| rec.storage.root().to_path_buf())
+    }
+
+    /// Stops the recording and writes the index (and raw capture, if enabled).
+    ///
+    /// # Errors
+    ///
+    /// Returns `RecordingError::NotActive` if no recording is in progress, or an
+    /// I/O, JSON or capture error if the output files cannot be written.
+    pub fn stop(&self) -> Result<RecordingResult> {
+        let mut rec = self
+            .active
+            .lock()
+            .map_err(|e| RecordingError::LockError(e.to_string()))?
+            .take()
+            .ok_or(RecordingError::NotActive)?;
+        self.is_recording.store(false, Ordering::Release);
+
+        rec.writer.flush()?;
+        let duration_us = rec.epoch.elapsed().as_micros() as u64;
+        let duration_ms = duration_us / 1000;
+
+        let raw = if rec.options.include_raw {
+            Some(self.capture.stop_capture(&rec.storage)?)
+        } else {
+            None
+        };
+
+        let raw_video_path = match rec.raw_video.take() {
+            Some(raw_video) => Some(raw_video.finish(&rec.storage)?.display().to_string()),
+            None => None,
+        };
+
+        // Bookmarks become chapters, embedded in the video where the container allows
+        let markers = std::mem::take(&mut rec.markers);
+        let chapters = chapters::chapters_from_markers(&markers, duration_us);
+
+        // A video that can't be finished is reported, but doesn't cost the
+        // recording its index
+        let mut video_error = None;
+        let video_path = match rec.video.take().map(VideoThread::finish) {
+            Some(Some(video)) if video.writer.frame_count() > 0 => {
+                match video.writer.finish(&chapters) {
+                    Ok(_) => Some(video.path.display().to_string()),
+                    Err(e) => {
+                        log::error!("Failed to finish {}: {}", video.path.display(), e);
+                        video_error = Some(e.to_string());
+                        None
+                    }
+                }
+            }
+            Some(Some(video)) => {
+                // No frames: a video without a stream header would not play
+                drop(video.writer);
+                if let Err(e) = rec.storage.remove_file(&video.path) {
+                    log::warn!("Could not remove empty video: {}", e);
+                }
+                None
+            }
+            Some(None) => {
+                video_error = Some("the video thread panicked".to_string());
+                None
+            }
+            None => None,
+        };
+
+        let index = RecordingIndex {
+            label: rec.options.label.clone(),
+            started_at_ms: rec.started_at_ms,
+            duration_ms,
+            total_bytes: rec.offset,
+            raw_packets_file: raw.as_ref().and_then(|r| {
+                Path::new(&r.packets_path)
+                    .file_name()
+                    .map(|n| n.to_string_lossy().to_string())
+            }),
+            markers,
+            frames: std::mem::take(&mut rec.frames),
+            skipped_frames: rec.skipped_frames,
+        };
+        let (index_path, chapters_path) = write_index(&rec.storage, &index, &chapters)?;
+        drop(rec.journal);
+        rec.storage.remove_file(JOURNAL_FILE)?;
+
+        log::info!(
+            "Recording stopped: {} frames ({} skipped), {} bytes, {} ms",
+            index.frames.len(),
+            index.skipped_frames,
+            index.total_bytes,
+            duration_ms
+        );
+
+        Ok(RecordingResult {
+            directory: rec.storage.root().display().to_string(),
+            frames_path: rec.frames_path.display().to_string(),
+            index_path: index_path.display().to_string(),
+            frame_count: index.frames.len() as u64,
+            duration_ms,
+            raw,
+            chapters_path,
+            raw_video_path,
+            video_path,
+            video_error,
+            skipped_frames: index.skipped_frames,
+        })
+    }
+
+    /// Returns the current recording status.
+    #[must_use]
+    pub fn status(&self) -> RecordingStatus {
+        let guard = self.active.lock().ok();
+        match guard.as_deref().and_then(Option::as_ref) {
+            Some(rec) => RecordingStatus {
+                is_recording: true,
+                frame_count: rec.frames.len() as u64,
+                duration_ms: rec.epoch.elapsed().as_millis() as u64,
+                include_raw: rec.options.include_raw,
+                label: rec.options.label.clone(),
+                skipped_frames: rec.skipped_frames,
+            },
+            None => RecordingStatus {
+                is_recording: false,
+                frame_count: 0,
+                duration_ms: 0,
+                include_raw: false,
+                label: String::new(),
+                skipped_frames: 0,
+            },
+        }
+    }
+}
+
+/// Create a fresh `recording_<secs>` directory in `storage`
+///
+/// Recordings started within the same second get a `_1`, `_2`, ... suffix;
+/// an existing directory is never reused.
+fn create_recording_dir(storage: &Storage, secs: u64) -> std::io::Result<String> {
+    let stem = format!("recording_{}", secs);
+    let mut name = stem.clone();
+    let mut suffix = 1;
+    loop {
+        match storage.create_dir(&name) {
+            Ok(_) => return Ok(name),
+            Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => {
+                name = format!("{}_{}", stem, suffix);
+                suffix += 1;
+            }
+            Err(e) => return Err(e),
+        }
+    }
+}
+
+/// Directory created by [`RecordingState::start`], removed on drop unless kept
+///
+/// Declared before the files written into it, so they are closed first.
+struct CreatedDir<'a> {
+    storage: &'a Storage,
+    name: Option<String>,
+}
+
+impl CreatedDir<'_> {
+    /// Keep the directory: the recording started
+    fn keep(mut self) {
+        self.name = None;
+    }
+}
+
+impl Drop for CreatedDir<'_> {
+    fn drop(&mut self) {
+        if let Some(name) = self.name.take() {
+            if let Err(e) = self.storage.remove_dir_all(&name) {
+                log::warn!("Could not remove incomplete recording {}: {}", name, e);
+            }
+        }
+    }
+}
+
+/// Appends an entry to `index.journal`.
+fn append_journal(journal: &mut BufWriter<File>, entry: &JournalEntry) -> std::io::Result<()> {
+    serde_json::to_writer(&mut *journal, entry)?;
+    journal.write_all(b"\n")
+}
+
+/// Writes `index.json`, plus the chapter sidecars if there are any chapters.
+///
+/// Returns the index path and the `chapters.ffmetadata` path.
+fn write_index(
+    storage: &Storage,
+    index: &RecordingIndex,
+    chapters: &[chapters::Chapter],
+) -> Result<(PathBuf, Option<String>)> {
+    let index_path = storage.write(INDEX_FILE, serde_json::to_string_pretty(index)?)?;
+
+    // Sidecars for AVI videos, frame-only recordings and external tools
+    let chapters_path = if chapters.is_empty() {
+        None
+    } else {
+        let ffmetadata_path = storage.write(
+            "chapters.ffmetadata",
+            chapters::to_ffmetadata(&index.label, chapters),
+        )?;
+        storage.write("chapters.vtt", chapters::to_webvtt(chapters))?;
+        Some(ffmetadata_path.display().to_string())
+    };
+    Ok((index_path, chapters_path))
+}
+
+/// Finishes a recording that was interrupted before [`RecordingState::stop`].
+///
+/// `storage` is the recording directory. The index is rebuilt from
+/// `index.journal`, keeping the frames whose data reached `frames.bin`, and a
+/// partly written frame at the end of `frames.bin` is cut off. Raw packets
+/// and video output of the interrupted recording are not recovered.
+///
+/// # Errors
+///
+/// Returns `RecordingError::Unrecoverable` if the recording was finished or
+/// has no journal, or an I/O or JSON error if the files cannot be read or
+/// written.
+pub fn recover(storage: &Storage) -> Result<RecordingResult> {
+    if storage.resolve(INDEX_FILE)?.exists() {
+        return Err(RecordingError::Unrecoverable(
+            "it was already finished".to_string(),
+        ));
+    }
+    let journal_path = storage.resolve(JOURNAL_FILE)?;
+    if !journal_path.exists() {
+        return Err(RecordingError::Unrecoverable(
+            "it has no journal".to_string(),
+        ));
+    }
+    let journal = std::fs::read_to_string(&journal_path)?;
+    let frames_path = storage.resolve(FRAMES_FILE)?;
+    let data_len = std::fs::metadata(&frames_path)?.len();
+
+    let mut index = RecordingIndex::default();
+    for line in journal.lines() {
+        // The last line may have been cut off mid-write
+        let Ok(entry) = serde_json::from_str::<JournalEntry>(line) else {
+            break;
+        };
+        match entry {
+            JournalEntry::Start {
+                label,
+                started_at_ms,
+            } => {
+                index.label = label;
+                index.started_at_ms = started_at_ms;
+            }
+            JournalEntry::Frame(frame) => {
+                if frame.offset + u64::from(frame.size) > data_len {
+                    break;
+                }
+                index.frames.push(frame);
+            }
+            JournalEntry::Marker(marker) => index.markers.push(marker),
+            JournalEntry::Skipped { frames } => index.skipped_frames += frames,
+        }
+    }
+    let frame_count = index.frames.len() as u64;
+    index.markers.retain(|m| m.frame < frame_count);
+    index.total_bytes = index
+        .frames
+        .last()
+        .map_or(0, |f| f.offset + u64::from(f.size));
+    let duration_us = index.frames.last().map_or(0, |f| f.timestamp_us);
+    index.duration_ms = duration_us / 1000;
+
+    storage.truncate(FRAMES_FILE, index.total_bytes)?;
+    let chapters = chapters::chapters_from_markers(&index.markers, duration_us);
+    let (index_path, chapters_path) = write_index(storage, &index, &chapters)?;
+    storage.remove_file(JOURNAL_FILE)?;
+
+    log::info!(
+        "Recovered recording {}: {} frames, {} bytes",
+        storage.root().display(),
+        frame_count,
+        index.total_bytes
+    );
+
+    Ok(RecordingResult {
+        directory: storage.root().display().to_string(),
+        frames_path: frames_path.display().to_string(),
+        index_path: index_path.display().to_string(),
+        frame_count,
+        duration_ms: index.duration_ms,
+        raw: None,
+        chapters_path,
+        raw_video_path: None,
+        video_path: None,
+        video_error: None,
+        skipped_frames: index.skipped_frames,
+    })
+}
+
+/// Reads a recording index from `index.json`.
+///
+/// # Errors
+///
+/// Returns `RecordingError::Io` if the file cannot be read and
+/// `RecordingError::Json` if it is not a valid index.
+pub fn read_index(path: &Path) -> Result<RecordingIndex> {
+    let json = std::fs::read_to_string(path)?;
+    Ok(serde_json::from_str(&json)?)
+}
+
+/// A single frame saved with [`write_snapshot`].
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Snapshot {
+    /// Path of the saved file.
+    pub path: String,
+    /// Format the frame was encoded to (`None`: saved as captured).
+    pub format: Option<ImageFormat>,
+    /// Frame width in pixels.
+    pub width: u32,
+    /// Frame height in pixels.
+    pub height: u32,
+    /// File size in bytes.
+    pub size: usize,
+}
+
+/// Writes an encoded frame to a timestamped file in `dir`.
+///
+/// `dir` is relative to `storage` (empty for its root) and is created if
+/// missing. `extension` is the file extension of `data` (see
+/// [`ImageFormat::extension`]). Snapshots taken within the same millisecond
+/// get a numeric suffix instead of overwriting each other.
+///
+/// # Errors
+///
+/// Returns `RecordingError::Io` if `dir` lies outside `storage` or the file
+/// cannot be written.
+pub fn write_snapshot(
+    storage: &Storage,
+    dir: &str,
+    data: &[u8],
+    width: u32,
+    height: u32,
+    extension: &str,
+    format: Option<ImageFormat>,
+) -> Result<Snapshot> {
+    let storage = if dir.is_empty() {
+        storage.clone()
+    } else {
+        storage.create_dir_all(dir)?;
+        storage.subdir(dir)?
+    };
+
+    let now_ms = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis();
+    let stem = format!("snapshot_{}_{}x{}", now_ms, width, height);
+    let mut name = format!("{}.{}", stem, extension);
+    let mut suffix = 1;
+    while storage.resolve(&name)?.exists() {
+        name = format!("{}_{}.{}", stem, suffix, extension);
+        suffix += 1;
+    }
+
+    let path = storage.write(&name, data)?;
+    Ok(Snapshot {
+        path: path.to_string_lossy().to_string(),
+        format,
+        width,
+        height,
+        size: data.len(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::capture::read_packets;
+
+    fn recorder() -> (RecordingState, Arc<CaptureState>) {
+        let capture = Arc::new(CaptureState::new());
+        (RecordingState::new(Arc::clone(&capture)), capture)
+    }
+
+    #[test]
+    fn test_record_processed_only() {
+        let dir = tempfile::tempdir().unwrap();
+        let (recorder, capture) = recorder();
+
+        recorder
+            .start(&Storage::new(dir.path()), RecordingOptions::default())
+            .unwrap();
+        assert!(recorder.is_recording());
+        assert!(!capture.is_capturing());
+
+        recorder.record_frame(&[1, 2, 3], 1, 1, FrameFormat::Rgb);
+        recorder.record_frame(&[0xFF, 0xD8, 0xFF, 0xD9], 2, 2, FrameFormat::Jpeg);
+        let result = recorder.stop().unwrap();
+
+        assert!(!recorder.is_recording());
+        assert_eq!(result.frame_count, 2);
+        assert!(result.raw.is_none());
+        assert!(result.chapters_path.is_none());
+        assert!(result.raw_video_path.is_none());
+
+        let frames = std::fs::read(&result.frames_path).unwrap();
+        assert_eq!(frames, vec![1, 2, 3, 0xFF, 0xD8, 0xFF, 0xD9]);
+        assert!(!Path::new(&result.directory).join(JOURNAL_FILE).exists());
+
+        let index = read_index(Path::new(&result.index_path)).unwrap();
+        assert_eq!(index.frames.len(), 2);
+        assert_eq!(index.frames[1].offset, 3);
+        assert_eq!(index.frames[1].size, 4);
+        assert_eq!(index.frames[1].format, FrameFormat::Jpeg);
+        assert_eq!(index.frames[1].packet_index, None);
+        assert!(index.frames[0].timestamp_us <= index.frames[1].timestamp_us);
+    }
+
+    #[test]
+    fn test_record_raw_video() {
+        let dir = tempfile::tempdir().unwrap();
+        let (recorder, _) = recorder();
+        let layout = FrameLayout {
+            width: 2,
+            height: 1,
+            stride: 4,
+            pixel_format: crate::PixelFormat::Yuyv,
+        };
+
+        // Ignored while not recording
+        recorder.record_raw_frame(&[0; 4], layout);
+
+        recorder
+            .start(
+                &Storage::new(dir.path()),
+                RecordingOptions {
+                    raw_video: true,
+                    ..Default::default()
+                },
+            )
+            .unwrap();
+        recorder.record_raw_frame(&[16, 128, 235, 128], layout);
+        recorder.record_frame(&[1, 2, 3, 4, 5, 6], 2, 1, FrameFormat::Rgb);
+        let result = recorder.stop().unwrap();
+
+        let raw_index = Path::new(result.raw_video_path.as_deref().unwrap());
+        let mut reader =
+            crate::raw_video::RawVideoReader::open(raw_index.parent().unwrap()).unwrap();
+        assert_eq!(reader.index().frames.len(), 1);
+        assert_eq!(reader.index().frames[0].layout, layout);
+        assert_eq!(reader.read_frame(0).unwrap(), [16, 128, 235, 128]);
+    }
+
+    #[test]
+    fn test_record_video() {
+        let dir = tempfile::tempdir().unwrap();
+        let (recorder, _) = recorder();
+
+        recorder
+            .start(
+                &Storage::new(dir.path()),
+                RecordingOptions {
+                    video: Some(VideoContainer::Avi),
+                    ..Default::default()
+                },
+            )
+            .unwrap();
+        recorder.record_frame(&[0xFF, 0xD8, 0xFF, 0xD9], 2, 2, FrameFormat::Jpeg);
+        let result = recorder.stop().unwrap();
+
+        let video = std::fs::read(result.video_path.unwrap()).unwrap();
+        assert_eq!(&video[0..4], b"RIFF");
+        assert_eq!(&video[8..12], b"AVI ");
+    }
+
+    #[test]
+    fn test_bulk_jpeg_frames_take_their_header_size() {
+        let dir = tempfile::tempdir().unwrap();
+        let (recorder, _) = recorder();
+        recorder
+            .start(
+                &Storage::new(dir.path()),
+                RecordingOptions {
+                    video: Some(VideoContainer::Avi),
+                    ..Default::default()
+                },
+            )
+            .unwrap();
+        // SOF0 for a 4x2 image; the bulk path doesn't know the size
+        let jpeg = [
+            0xFF, 0xD8, 0xFF, 0xC0, 0x00, 0x11, 0x08, 0x00, 0x02, 0x00, 0x04, 0x03, 0xFF, 0xD9,
+        ];
+        recorder.record_frame(&jpeg, 0, 0, FrameFormat::Jpeg);
+        let result = recorder.stop().unwrap();
+
+        let index = read_index(Path::new(&result.index_path)).unwrap();
+        assert_eq!((index.frames[0].width, index.frames[0].height), (4, 2));
+        assert!(result.video_path.is_some());
+        assert!(result.video_error.is_none());
+    }
+
+    #[test]
+    fn test_mkv_video_embeds_chapters() {
+        let dir = tempfile::tempdir().unwrap();
+        let (recorder, _) = recorder();
+
+        recorder
+            .start(
+                &Storage::new(dir.path()),
+                RecordingOptions {
+                    video: Some(VideoContainer::Mkv),
+                    ..Default::default()
+                },
+            )
+            .unwrap();
+        recorder.record_frame(&[0xFF, 0xD8, 0xFF, 0xD9], 2, 2, FrameFormat::Jpeg);
+        recorder.add_marker(Some("joint".to_string()));
+        let result = recorder.stop().unwrap();
+
+        let video_path = result.video_path.unwrap();
+        assert!(video_path.ends_with("video.mkv"));
+        let video = std::fs::read(video_path).unwrap();
+        assert_eq!(&video[0..4], &[0x1A, 0x45, 0xDF, 0xA3]);
+        assert!(video.windows(5).any(|w| w == b"joint"));
+        // The sidecars are still written
+        assert!(result.chapters_path.is_some());
+    }
+
+    #[test]
+    fn test_video_quality_follows_the_camera_interval() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join(VideoContainer::Mkv.file_name());
+        let file = File::create(&path).unwrap();
+        let mut video = ActiveVideo {
+            path,
+            writer: VideoWriter::new(VideoContainer::Mkv, BufWriter::new(file), DEFAULT_VIDEO_FPS),
+            quality: AdaptiveQuality::default(),
+            frame_interval: Duration::from_millis(33),
+        };
+
+        // Encoding slower than the camera lowers the quality, however far
+        // apart the slow encodes made the recorded frames
+        let change = (0..10)
+            .find_map(|_| video.adapt_quality(Duration::from_millis(40)))
+            .unwrap();
+        assert!(change.to < change.from);
+        assert_eq!(change.interval_us, 33_000);
+    }
+
+    #[test]
+    fn test_video_without_frames_is_removed() {
+        let dir = tempfile::tempdir().unwrap();
+        let (recorder, _) = recorder();
+
+        let directory = recorder
+            .start(
+                &Storage::new(dir.path()),
+                RecordingOptions {
+                    video: Some(VideoContainer::Avi),
+                    ..Default::default()
+                },
+            )
+            .unwrap();
+        let result = recorder.stop().unwrap();
+
+        assert!(result.video_path.is_none());
+        assert!(!directory.join(VideoContainer::Avi.file_name()).exists());
+    }
+
+    #[test]
+    fn test_unavailable_raw_compression_fails_start() {
+        if RawCompression::Zstd.is_available() {
+            return;
+        }
+        let dir = tempfile::tempdir().unwrap();
+        let (recorder, _) = recorder();
+        let result = recorder.start(
+            &Storage::new(dir.path()),
+            RecordingOptions {
+                raw_video: true,
+                raw_compression: RawCompression::Zstd,
+                ..Default::default()
+            },
+        );
+        assert!(matches!(result, Err(RecordingError::RawVideo(_))));
+        assert!(!recorder.is_recording());
+    }
+
+    #[test]
+    fn test_record_with_raw_correlates_packets() {
+        let dir = tempfile::tempdir().unwrap();
+        let (recorder, capture) = recorder();
+
+        recorder
+            .start(
+                &Storage::new(dir.path()),
+                RecordingOptions {
+                    label: "JobX".to_string(),
+                    include_raw: true,
+                    ..Default::default()
+                },
+            )
+            .unwrap();
+        assert!(capture.is_capturing());
+
+        capture.record_packet(&[0x0C, 0x8C, 1]);
+        capture.record_packet(&[0x0C, 0x8E, 2]);
+        recorder.record_frame(&[9; 6], 1, 2, FrameFormat::Rgb);
+        capture.record_packet(&[0x0C, 0x8D, 3]);
+        recorder.record_frame(&[8; 6], 1, 2, FrameFormat::Rgb);
+
+        let result = recorder.stop().unwrap();
+        assert!(!capture.is_capturing());
+
+        let raw = result.raw.expect("raw capture result");
+        assert_eq!(raw.metadata.total_packets, 3);
+        assert_eq!(raw.metadata.total_frames, 2);
+        assert_eq!(raw.metadata.description, "JobX");
+        assert_eq!(read_packets(Path::new(&raw.packets_path)).unwrap().len(), 3);
+
+        let index = read_index(Path::new(&result.index_path)).unwrap();
+        assert_eq!(index.label, "JobX");
+        assert_eq!(index.frames[0].packet_index, Some(2));
+        assert_eq!(index.frames[1].packet_index, Some(3));
+        assert!(index.raw_packets_file.unwrap().starts_with("packets_"));
+    }
+
+    #[test]
+    fn test_markers_written_to_index() {
+        let dir = tempfile::tempdir().unwrap();
+        let (recorder, _) = recorder();
+        assert!(recorder.add_marker(None).is_none());
+
+        recorder
+            .start(&Storage::new(dir.path()), RecordingOptions::default())
+            .unwrap();
+        recorder.record_frame(&[1], 1, 1, FrameFormat::Rgb);
+        recorder.record_frame(&[2], 1, 1, FrameFormat::Rgb);
+        let marker = recorder.add_marker(Some("weld".to_string())).unwrap();
+        assert_eq!(marker.frame, 1);
+
+        let result = recorder.stop().unwrap();
+        let index = read_index(Path::new(&result.index_path)).unwrap();
+        assert_eq!(index.markers, vec![marker]);
+
+        let chapters_path = result.chapters_path.expect("chapters written");
+        let ffmetadata = std::fs::read_to_string(&chapters_path).unwrap();
+        assert!(ffmetadata.contains("title=weld"));
+        assert!(Path::new(&result.directory).join("chapters.vtt").exists());
+    }
+
+    #[test]
+    fn test_start_twice_fails() {
+        let dir = tempfile::tempdir().unwrap();
+        let (recorder, _) = recorder();
+        recorder
+            .start(&Storage::new(dir.path()), RecordingOptions::default())
+            .unwrap();
+        assert!(matches!(
+            recorder.start(&Storage::new(dir.path()), RecordingOptions::default()),
+            Err(RecordingError::AlreadyActive)
+        ));
+    }
+
+    #[test]
+    fn test_raw_recording_fails_if_capture_active() {
+        let dir = tempfile::tempdir().unwrap();
+        let (recorder, capture) = recorder();
+        capture.start().unwrap();
+
+        let result = recorder.start(
+            &Storage::new(dir.path()),
+            RecordingOptions {
+                include_raw: true,
+                ..Default::default()
+            },
+        );
+        assert!(matches!(
+            result,
+            Err(RecordingError::Capture(CaptureError::AlreadyActive))
+        ));
+        assert!(!recorder.is_recording());
+        // Only the audit log of the rolled-back directory is left
+        let entries: Vec<_> = std::fs::read_dir(dir.path())
+            .unwrap()
+            .map(|e| e.unwrap().file_name())
+            .collect();
+        assert_eq!(entries, vec![crate::storage::AUDIT_LOG_NAME]);
+    }
+
+    #[test]
+    fn test_recording_directories_are_never_reused() {
+        let dir = tempfile::tempdir().unwrap();
+        let storage = Storage::new(dir.path());
+
+        assert_eq!(create_recording_dir(&storage, 42).unwrap(), "recording_42");
+        storage
+            .write("recording_42/frames.bin", [1u8, 2, 3])
+            .unwrap();
+        assert_eq!(
+            create_recording_dir(&storage, 42).unwrap(),
+            "recording_42_1"
+        );
+        assert_eq!(
+            create_recording_dir(&storage, 42).unwrap(),
+            "recording_42_2"
+        );
+        assert_eq!(
+            std::fs::read(dir.path().join("recording_42/frames.bin")).unwrap(),
+            [1, 2, 3]
+        );
+    }
+
+    #[test]
+    fn test_failed_start_keeps_existing_recordings() {
+        let dir = tempfile::tempdir().unwrap();
+        let storage = Storage::new(dir.path());
+        let (recorder, capture) = recorder();
+
+        recorder
+            .start(&storage, RecordingOptions::default())
+            .unwrap();
+        let first = recorder.stop().unwrap();
+
+        capture.start().unwrap();
+        let raw = RecordingOptions {
+            include_raw: true,
+            ..Default::default()
+        };
+        assert!(recorder.start(&storage, raw).is_err());
+        assert!(Path::new(&first.index_path).exists());
+        let recordings = std::fs::read_dir(dir.path())
+            .unwrap()
+            .filter(|e| e.as_ref().unwrap().path().is_dir())
+            .count();
+        assert_eq!(recordings, 1);
+    }
+
+    #[test]
+    fn test_recover_interrupted_recording() {
+        let dir = tempfile::tempdir().unwrap();
+        let (recorder, _) = recorder();
+        let options = RecordingOptions {
+            label: "pipe".to_string(),
+            ..Default::default()
+        };
+        let directory = recorder.start(&Storage::new(dir.path()), options).unwrap();
+        assert_eq!(recorder.directory(), Some(directory.clone()));
+        recorder.record_frame(&[1, 2, 3], 1, 1, FrameFormat::Rgb);
+        recorder.record_skipped(2);
+        recorder.add_marker(Some("joint".to_string()));
+        recorder.record_frame(&[4, 5, 6], 1, 1, FrameFormat::Rgb);
+        recorder.flush();
+
+        // Crash with the last frame only partly on disk
+        drop(recorder);
+        std::fs::OpenOptions::new()
+            .write(true)
+            .open(directory.join(FRAMES_FILE))
+            .unwrap()
+            .set_len(4)
+            .unwrap();
+
+        let storage = Storage::new(&directory);
+        let result = recover(&storage).unwrap();
+        assert_eq!(result.frame_count, 1);
+        assert_eq!(std::fs::read(&result.frames_path).unwrap(), vec![1, 2, 3]);
+        assert!(!directory.join(JOURNAL_FILE).exists());
+
+        let index = read_index(Path::new(&result.index_path)).unwrap();
+        assert_eq!(index.label, "pipe");
+        assert_eq!(index.total_bytes, 3);
+        assert_eq!(index.markers.len(), 1);
+        assert_eq!(index.skipped_frames, 2);
+
+        assert!(matches!(
+            recover(&storage),
+            Err(RecordingError::Unrecoverable(_))
+        ));
+    }
+
+    #[test]
+    fn test_skipped_frames_are_kept_in_the_index() {
+        let dir = tempfile::tempdir().unwrap();
+        let (recorder, _) = recorder();
+        recorder.record_skipped(5);
+        recorder
+            .start(&Storage::new(dir.path()), RecordingOptions::default())
+            .unwrap();
+        recorder.record_frame(&[1, 2, 3], 1, 1, FrameFormat::Rgb);
+        recorder.record_skipped(2);
+        recorder.record_skipped(1);
+        assert_eq!(recorder.status().skipped_frames, 3);
+
+        let result = recorder.stop().unwrap();
+        assert_eq!((result.frame_count, result.skipped_frames), (1, 3));
+        let index = read_index(Path::new(&result.index_path)).unwrap();
+        assert_eq!(index.skipped_frames, 3);
+    }
+
+    #[test]
+    fn test_stop_without_start_fails() {
+        let (recorder, _) = recorder();
+        assert!(matches!(recorder.stop(), Err(RecordingError::NotActive)));
+    }
+
+    #[test]
+    fn test_record_frame_survives_poisoned_lock() {
+        let dir = tempfile::tempdir().unwrap();
+        let (recorder, _) = recorder();
+        recorder
+            .start(&Storage::new(dir.path()), RecordingOptions::default())
+            .unwrap();
+
+        std::thread::scope(|s| {
+            let _ = s
+                .spawn(|| {
+                    let _guard = recorder.active.lock().unwrap();
+                    panic!("consumer panicked while holding the recording");
+                })
+                .join();
+        });
+        assert!(recorder.active.is_poisoned());
+
+        recorder.record_frame(&[1, 2, 3], 1, 1, FrameFormat::Rgb);
+        let result = recorder.stop().unwrap();
+        assert_eq!(result.frame_count, 1);
+    }
+
+    #[test]
+    fn test_frames_ignored_when_not_recording() {
+        let (recorder, _) = recorder();
+        recorder.record_frame(&[1, 2, 3], 1, 1, FrameFormat::Rgb);
+        assert_eq!(recorder.status().frame_count, 0);
+        assert!(!recorder.status().is_recording);
+    }
+
+    #[test]
+    fn test_write_snapshot() {
+        let dir = tempfile::tempdir().unwrap();
+        let storage = Storage::new(dir.path());
+        let jpeg = [0xFF, 0xD8, 0xFF, 0xD9];
+
+        let first = write_snapshot(&storage, "snapshots", &jpeg, 4, 3, "jpg", None).unwrap();
+        let second = write_snapshot(&storage, "snapshots", &jpeg, 4, 3, "jpg", None).unwrap();
+
+        assert_ne!(first.path, second.path);
+        for snapshot in [&first, &second] {
+            let path = Path::new(&snapshot.path);
+            assert!(path.starts_with(dir.path().join("snapshots")));
+            let name = path.file_name().unwrap().to_str().unwrap();
+            assert!(name.starts_with("snapshot_"));
+            assert!(name.contains("_4x3"));
+            assert!(name.ends_with(".jpg"));
+            assert_eq!(std::fs::read(path).unwrap(), jpeg);
+        }
+        assert_eq!(first.size, 4);
+    }
+
+    #[test]
+    fn test_write_snapshot_rejects_outside_dir() {
+        let dir = tempfile::tempdir().unwrap();
+        let storage = Storage::new(dir.path().join("out"));
+        let result = write_snapshot(&storage, "../elsewhere", &[1, 2, 3], 1, 1, "rgb", None);
+        assert!(matches!(result, Err(RecordingError::Io(_))));
+    }
+}
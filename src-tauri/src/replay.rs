@@ -10,6 +10,12 @@
 //! [u64 LE: timestamp_us][u32 LE: length][u8: endpoint][data bytes]...
 //! ```
 //!
+//! the `packets_*.bin` files of `CaptureState::stop_capture`, both versioned
+//! (per-packet timestamps and endpoints) and the older headerless layout
+//! (timestamps synthesized, see [`crate::capture::read_timed_packets`]),
+//! and Wireshark pcap/pcapng captures of Linux usbmon, read by
+//! [`crate::pcap_import`] (see [`PacketReplay::load_pcap`]).
+//!
 //! # Example
 //!
 //! ```rust,ignore
@@ -26,16 +32,46 @@
 //!     process_frame(&frame);
 //! }
 //! ```
+//!
+//! # Frame Extraction
+//!
+//! [`extract_frames`] returns only the frames completed within a time range,
+//! located through the capture's frame index (see
+//! [`PacketReplay::frame_index`]).
+//!
+//! # Deterministic Replay
+//!
+//! Paced replay normally sleeps in real time, which makes timing assertions
+//! flaky on loaded CI machines. With [`ReplayClock::Virtual`], the replay
+//! thread waits on a [`VirtualClock`] that only moves when the test advances
+//! it:
+//!
+//! ```rust,ignore
+//! let clock = VirtualClock::new();
+//! let config = ReplayConfig {
+//!     clock: ReplayClock::Virtual(clock.clone()),
+//!     ..Default::default()
+//! };
+//! let mut replay = PacketReplay::load_with_config(path, config)?;
+//! let receiver = replay.start()?;
+//!
+//! clock.advance(Duration::from_millis(40));
+//! clock.settle(); // replay has emitted everything due by 40 ms
+//! let frames: Vec<_> = receiver.try_iter().collect();
+//! ```
 
+use serde::{Deserialize, Serialize};
 use std::io::Read;
-use std::path::Path;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::{Arc, Condvar, Mutex};
 use std::thread::{self, JoinHandle};
 use std::time::{Duration, Instant};
 use thiserror::Error;
 
-use crate::capture::{read_metadata, CaptureMetadata};
-use crate::frame_assembler::{FrameAssembler, ProcessResult};
+use crate::capture::{find_companion_metadata, read_metadata, CaptureError, CaptureMetadata};
+use crate::frame_assembler::{is_jpeg_data, FrameAssembler, ProcessResult};
 
 /// Errors that can occur during packet replay operations.
 #[derive(Error, Debug)]
@@ -65,6 +101,27 @@ pub enum ReplayError {
     #[error("replay is not running")]
     NotRunning,
 
+    /// No capture has been loaded for playback.
+    #[error("no capture is loaded")]
+    NotLoaded,
+
+    /// Raw frames can't be converted without the capture's resolution.
+    #[error("the capture doesn't record its resolution, so its raw frames can't be shown")]
+    UnknownResolution,
+
+    /// A camera is streaming into the frame buffer playback would use.
+    #[error("a camera is streaming; disconnect it before playing a capture")]
+    CameraStreaming,
+
+    /// pcap/pcapng capture of something other than Linux usbmon.
+    #[error("unsupported pcap link type {0} (expected Linux usbmon, 189 or 220)")]
+    UnsupportedLinkType(u32),
+
+    /// Capture without isochronous or bulk IN transfers (of the requested
+    /// endpoint) to replay.
+    #[error("no isochronous or bulk IN packets for the endpoint in the capture")]
+    NoPackets,
+
     /// Channel send error.
     #[error("channel closed")]
     ChannelClosed,
@@ -84,6 +141,29 @@ pub struct ReplayPacket {
     pub data: Vec<u8>,
 }
 
+/// Position of an assembled frame within a capture.
+///
+/// A frame's timestamp is that of the packet that completed it, relative to
+/// the capture start.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ReplayFrameEntry {
+    /// Frame number within the capture (starting at 0).
+    pub sequence: u64,
+    /// Timestamp of the completing packet (microseconds).
+    pub timestamp_us: u64,
+    /// Index of the packet that completed the frame.
+    pub packet_index: usize,
+}
+
+/// A frame extracted by timestamp with [`extract_frames`].
+#[derive(Debug, Clone)]
+pub struct ExtractedFrame {
+    /// Position of the frame within the capture.
+    pub entry: ReplayFrameEntry,
+    /// Assembled frame data.
+    pub data: Vec<u8>,
+}
+
 /// Configuration for packet replay.
 #[derive(Debug, Clone)]
 pub struct ReplayConfig {
@@ -95,6 +175,8 @@ pub struct ReplayConfig {
     pub expected_frame_size: usize,
     /// Force MJPEG mode (overrides auto-detection).
     pub force_mjpeg: bool,
+    /// Time source that paces playback.
+    pub clock: ReplayClock,
 }
 
 impl Default for ReplayConfig {
@@ -104,6 +186,170 @@ impl Default for ReplayConfig {
             loop_playback: false,
             expected_frame_size: 0,
             force_mjpeg: false,
+            clock: ReplayClock::Real,
+        }
+    }
+}
+
+/// Time source for paced replay.
+#[derive(Debug, Clone, Default)]
+pub enum ReplayClock {
+    /// Wall-clock time: the replay thread sleeps between packets.
+    #[default]
+    Real,
+    /// Test-controlled time: the replay thread waits until the clock is advanced.
+    Virtual(VirtualClock),
+}
+
+/// Virtual clock state shared with the replay thread.
+#[derive(Debug, Default)]
+struct VirtualClockState {
+    /// Current virtual time.
+    now: Duration,
+    /// Virtual time the replay thread is waiting for, if it is waiting.
+    waiting_for: Option<Duration>,
+    /// Replay threads currently running on this clock.
+    running: usize,
+}
+
+/// A clock that only moves when advanced, for deterministic replay tests.
+///
+/// Clones share the same time, so a test keeps one handle and passes a clone
+/// in [`ReplayConfig::clock`].
+#[derive(Debug, Clone, Default)]
+pub struct VirtualClock {
+    inner: Arc<(Mutex<VirtualClockState>, Condvar)>,
+}
+
+impl VirtualClock {
+    /// Create a clock at virtual time zero.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Current virtual time.
+    #[must_use]
+    pub fn now(&self) -> Duration {
+        self.state().now
+    }
+
+    /// Move the clock forward, releasing a replay waiting for that time.
+    pub fn advance(&self, by: Duration) {
+        self.state().now += by;
+        self.inner.1.notify_all();
+    }
+
+    /// Block until the replay has caught up with the clock.
+    ///
+    /// Returns the virtual time the replay is waiting for next, or `None` once
+    /// no replay is running (finished, stopped, or not started).
+    pub fn settle(&self) -> Option<Duration> {
+        let (lock, condvar) = &*self.inner;
+        let state = condvar
+            .wait_while(crate::lock_or_recover(lock), |state| {
+                state.running > 0 && state.waiting_for.is_none_or(|t| t <= state.now)
+            })
+            .unwrap_or_else(std::sync::PoisonError::into_inner);
+        if state.running > 0 {
+            state.waiting_for
+        } else {
+            None
+        }
+    }
+
+    fn state(&self) -> std::sync::MutexGuard<'_, VirtualClockState> {
+        crate::lock_or_recover(&self.inner.0)
+    }
+
+    /// Wake waiting replay threads so they notice a stop request.
+    fn wake(&self) {
+        let _state = self.state();
+        self.inner.1.notify_all();
+    }
+
+    /// Wait until virtual time `deadline`; returns `false` if stopped first.
+    fn wait_until(&self, deadline: Duration, stop_rx: &Receiver<()>) -> bool {
+        let (lock, condvar) = &*self.inner;
+        let mut state = crate::lock_or_recover(lock);
+        while state.now < deadline {
+            // Checked under the lock, so a stop followed by `wake` is never missed
+            if stop_rx.try_recv().is_ok() {
+                state.waiting_for = None;
+                condvar.notify_all();
+                return false;
+            }
+            state.waiting_for = Some(deadline);
+            condvar.notify_all();
+            state = condvar
+                .wait(state)
+                .unwrap_or_else(std::sync::PoisonError::into_inner);
+        }
+        state.waiting_for = None;
+        true
+    }
+}
+
+/// Registers a replay thread on a virtual clock for as long as it runs.
+struct VirtualClockRun(VirtualClock);
+
+impl VirtualClockRun {
+    fn new(clock: &VirtualClock) -> Self {
+        clock.state().running += 1;
+        Self(clock.clone())
+    }
+}
+
+impl Drop for VirtualClockRun {
+    fn drop(&mut self) {
+        let mut state = self.0.state();
+        state.running -= 1;
+        state.waiting_for = None;
+        drop(state);
+        self.0.inner.1.notify_all();
+    }
+}
+
+/// Paces one pass over the packets against the configured clock.
+enum Pacer {
+    Real(Instant),
+    Virtual(VirtualClock, Duration),
+}
+
+impl Pacer {
+    fn start(clock: &ReplayClock) -> Self {
+        match clock {
+            ReplayClock::Real => Self::Real(Instant::now()),
+            ReplayClock::Virtual(clock) => Self::Virtual(clock.clone(), clock.now()),
+        }
+    }
+
+    /// Time since the pass started.
+    fn elapsed(&self) -> Duration {
+        match self {
+            Self::Real(start) => start.elapsed(),
+            Self::Virtual(clock, start) => clock.now().saturating_sub(*start),
+        }
+    }
+
+    /// Wait until `target` after the pass started; returns `false` if stopped first.
+    fn wait_until(&self, target: Duration, stop_rx: &Receiver<()>) -> bool {
+        match self {
+            Self::Real(_) => {
+                // Sleep in small chunks to check for stop signals
+                let chunk = Duration::from_millis(10);
+                let mut remaining = target.saturating_sub(self.elapsed());
+                while remaining > Duration::ZERO {
+                    if stop_rx.try_recv().is_ok() {
+                        return false;
+                    }
+                    let sleep = remaining.min(chunk);
+                    thread::sleep(sleep);
+                    remaining = remaining.saturating_sub(sleep);
+                }
+                true
+            }
+            Self::Virtual(clock, start) => clock.wait_until(*start + target, stop_rx),
         }
     }
 }
@@ -123,6 +369,8 @@ pub struct PacketReplay {
     thread_handle: Option<JoinHandle<()>>,
     /// Sender to stop the replay.
     stop_sender: Option<Sender<()>>,
+    /// Virtual clock the running replay waits on (to wake it for stopping).
+    running_clock: Option<VirtualClock>,
 }
 
 impl PacketReplay {
@@ -131,6 +379,12 @@ impl PacketReplay {
     /// Expects the legacy capture format:
     /// `[u64 LE: timestamp_us][u32 LE: length][u8: endpoint][data bytes]...`
     ///
+    /// `packets_*.bin` files are read with
+    /// [`crate::capture::read_timed_packets`]: versioned ones are recognized
+    /// by [`crate::capture::PACKETS_MAGIC`], headerless ones by their name.
+    /// pcap and pcapng files are recognized by their magic number and loaded
+    /// with [`PacketReplay::load_pcap`] from the busiest IN endpoint.
+    ///
     /// # Arguments
     ///
     /// * `path` - Path to the binary capture file (e.g., `capture_12345.bin`).
@@ -140,8 +394,87 @@ impl PacketReplay {
     /// Returns `ReplayError::FileOpen` if the file cannot be opened.
     /// Returns `ReplayError::InvalidPacket` if the file contains corrupted data.
     pub fn load(path: &Path) -> Result<Self> {
-        let packets = Self::read_packets_with_timestamps(path)?;
+        Self::load_endpoint(path, None)
+    }
 
+    /// Load a capture like [`PacketReplay::load`], keeping only the packets
+    /// of `endpoint` if one is given.
+    ///
+    /// pcap and pcapng files are imported from `endpoint` (see
+    /// [`PacketReplay::load_pcap`]); the app's own captures are filtered to
+    /// the packets recorded from it. Headerless `packets_*.bin` files don't
+    /// record endpoints, so only `None` loads them.
+    ///
+    /// # Errors
+    ///
+    /// As [`PacketReplay::load`], and `ReplayError::NoPackets` if no packet
+    /// is from `endpoint`.
+    pub fn load_endpoint(path: &Path, endpoint: Option<u8>) -> Result<Self> {
+        let mut magic = [0u8; 4];
+        let has_magic = std::fs::File::open(path)?.read_exact(&mut magic).is_ok();
+        if has_magic && crate::pcap_import::is_pcap(&magic) {
+            return Self::load_pcap(path, endpoint);
+        }
+        let is_packets_file = (has_magic && magic == crate::capture::PACKETS_MAGIC)
+            || path
+                .file_name()
+                .and_then(|name| name.to_str())
+                .is_some_and(|name| name.starts_with("packets_"));
+        let mut packets = if is_packets_file {
+            Self::read_packets_file(path)?
+        } else {
+            Self::read_packets_with_timestamps(path)?
+        };
+        if let Some(endpoint) = endpoint {
+            packets.retain(|packet| packet.endpoint == endpoint);
+            if packets.is_empty() {
+                return Err(ReplayError::NoPackets);
+            }
+        }
+        Ok(Self::from_packets(path, packets))
+    }
+
+    /// Read a `packets_*.bin` file of either layout version.
+    fn read_packets_file(path: &Path) -> Result<Vec<ReplayPacket>> {
+        let packets = crate::capture::read_timed_packets(path).map_err(|e| match e {
+            CaptureError::Io(e) => ReplayError::FileOpen(e),
+            CaptureError::UnsupportedVersion(version) => ReplayError::InvalidPacket {
+                offset: 4,
+                message: format!("unsupported packets file version {}", version),
+            },
+            e => ReplayError::Metadata(e.to_string()),
+        })?;
+        Ok(packets
+            .into_iter()
+            .map(|p| ReplayPacket {
+                timestamp_us: p.timestamp_us,
+                endpoint: p.endpoint,
+                data: p.data,
+            })
+            .collect())
+    }
+
+    /// Load the IN packets of `endpoint` from a Wireshark pcap or pcapng
+    /// capture of Linux usbmon.
+    ///
+    /// See [`crate::pcap_import::parse`] for what is imported and how the
+    /// endpoint is chosen when `endpoint` is `None`. Pass the frame size or
+    /// MJPEG mode in [`ReplayConfig`] unless a companion `.json` metadata
+    /// file exists, since pcap files don't describe the video format.
+    ///
+    /// # Errors
+    ///
+    /// Returns `ReplayError::FileOpen` if the file cannot be read,
+    /// `ReplayError::InvalidPacket` or `ReplayError::UnsupportedLinkType` if
+    /// it isn't a usbmon capture, and `ReplayError::NoPackets` if nothing
+    /// matches the endpoint.
+    pub fn load_pcap(path: &Path, endpoint: Option<u8>) -> Result<Self> {
+        let packets = crate::pcap_import::load(path, endpoint)?;
+        Ok(Self::from_packets(path, packets))
+    }
+
+    /// Wrap packets loaded from `path`, with its companion metadata if any.
+    fn from_packets(path: &Path, packets: Vec<ReplayPacket>) -> Self {
         // Try to load metadata from a companion .json file
         let metadata = Self::try_load_metadata(path);
 
@@ -158,13 +491,14 @@ impl PacketReplay {
             );
         }
 
-        Ok(Self {
+        Self {
             packets,
             metadata,
             config: ReplayConfig::default(),
             thread_handle: None,
             stop_sender: None,
-        })
+            running_clock: None,
+        }
     }
 
     /// Load packets with a custom configuration.
@@ -256,11 +590,11 @@ impl PacketReplay {
 
     /// Try to load metadata from a companion JSON file.
     ///
-    /// Looks for a file with the same base name but `.json` extension.
+    /// Looks for a file with the same base name but `.json` extension, or
+    /// the `metadata_*.json` of a `packets_*.bin` file.
     fn try_load_metadata(path: &Path) -> Option<CaptureMetadata> {
-        // Try same directory with .json extension
-        let json_path = path.with_extension("json");
-        if json_path.exists() {
+        // Try same directory with .json extension, or metadata_<suffix>.json
+        if let Some(json_path) = find_companion_metadata(path) {
             if let Ok(meta) = read_metadata(&json_path) {
                 return Some(meta);
             }
@@ -290,6 +624,12 @@ impl PacketReplay {
         self.metadata.as_ref()
     }
 
+    /// Get the loaded packets, in capture order.
+    #[must_use]
+    pub fn packets(&self) -> &[ReplayPacket] {
+        &self.packets
+    }
+
     /// Get the number of loaded packets.
     #[must_use]
     pub fn packet_count(&self) -> usize {
@@ -305,6 +645,99 @@ impl PacketReplay {
             .unwrap_or(0)
     }
 
+    /// Build the frame index: where each frame is completed and when.
+    ///
+    /// Assembles the whole capture once (with the current configuration)
+    /// without keeping any frame data.
+    #[must_use]
+    pub fn frame_index(&self) -> Vec<ReplayFrameEntry> {
+        let mut assembler = Self::create_assembler(&self.config, &self.metadata);
+        let mut index = Vec::new();
+        for (packet_index, packet) in self.packets.iter().enumerate() {
+            if let ProcessResult::Frame(_) = assembler.process_packet(&packet.data) {
+                index.push(ReplayFrameEntry {
+                    sequence: index.len() as u64,
+                    timestamp_us: packet.timestamp_us,
+                    packet_index,
+                });
+            }
+        }
+        index
+    }
+
+    /// Whether the capture holds MJPEG frames.
+    ///
+    /// Taken from the configuration or metadata if they say so, else from
+    /// the first frame the capture assembles into.
+    #[must_use]
+    pub fn is_mjpeg(&self) -> bool {
+        if self.config.force_mjpeg
+            || self
+                .metadata
+                .as_ref()
+                .is_some_and(|meta| meta.format_type.to_lowercase().contains("jpeg"))
+        {
+            return true;
+        }
+        let mut assembler = Self::create_assembler(&self.config, &self.metadata);
+        self.packets
+            .iter()
+            .find_map(|packet| match assembler.process_packet(&packet.data) {
+                ProcessResult::Frame(frame) => Some(is_jpeg_data(&frame)),
+                _ => None,
+            })
+            .unwrap_or(false)
+    }
+
+    /// Extract the frames whose timestamps fall in `[from_ms, to_ms)`.
+    ///
+    /// The frame index locates the packets needed: assembly starts at the
+    /// packet that completed the frame before the first wanted one (a fresh
+    /// assembler only syncs to the frame boundary there, as it does at the
+    /// start of a capture) and stops at the last wanted frame.
+    #[must_use]
+    pub fn extract_frames(&self, from_ms: u64, to_ms: u64) -> Vec<ExtractedFrame> {
+        let from_us = from_ms.saturating_mul(1000);
+        let to_us = to_ms.saturating_mul(1000);
+        let index = self.frame_index();
+        let Some(first) = index.iter().position(|entry| entry.timestamp_us >= from_us) else {
+            return Vec::new();
+        };
+        let wanted: Vec<_> = index[first..]
+            .iter()
+            .take_while(|entry| entry.timestamp_us < to_us)
+            .copied()
+            .collect();
+        let Some(last) = wanted.last() else {
+            return Vec::new();
+        };
+        let start = first
+            .checked_sub(1)
+            .map_or(0, |previous| index[previous].packet_index);
+
+        let mut assembler = Self::create_assembler(&self.config, &self.metadata);
+        let mut wanted = wanted.iter().peekable();
+        let mut frames = Vec::new();
+        for (packet_index, packet) in self
+            .packets
+            .iter()
+            .enumerate()
+            .take(last.packet_index + 1)
+            .skip(start)
+        {
+            let ProcessResult::Frame(data) = assembler.process_packet(&packet.data) else {
+                continue;
+            };
+            if let Some(entry) = wanted.next_if(|e| e.packet_index == packet_index) {
+                frames.push(ExtractedFrame {
+                    entry: *entry,
+                    data,
+                });
+            }
+        }
+        frames
+    }
+
     /// Set the replay configuration.
     pub fn set_config(&mut self, config: ReplayConfig) {
         self.config = config;
@@ -336,7 +769,17 @@ impl PacketReplay {
         let config = self.config.clone();
         let metadata = self.metadata.clone();
 
+        // Registered before the thread starts, so `settle` can't miss it
+        let clock_run = match &config.clock {
+            ReplayClock::Virtual(clock) => {
+                self.running_clock = Some(clock.clone());
+                Some(VirtualClockRun::new(clock))
+            }
+            ReplayClock::Real => None,
+        };
+
         let handle = thread::spawn(move || {
+            let _clock_run = clock_run;
             Self::replay_thread(packets, config, metadata, frame_tx, stop_rx);
         });
 
@@ -360,6 +803,9 @@ impl PacketReplay {
 
         // Signal the thread to stop
         let _ = stop_tx.send(());
+        if let Some(clock) = self.running_clock.take() {
+            clock.wake();
+        }
 
         // Wait for the thread to finish
         handle.join().map_err(|_| ReplayError::NotRunning)?;
@@ -380,7 +826,7 @@ impl PacketReplay {
         let mut assembler = Self::create_assembler(&config, &metadata);
 
         loop {
-            let replay_start = Instant::now();
+            let pacer = Pacer::start(&config.clock);
             let mut last_timestamp_us = 0u64;
 
             for packet in &packets {
@@ -400,21 +846,12 @@ impl PacketReplay {
                         let expected_elapsed = Duration::from_micros(
                             (packet.timestamp_us as f64 / config.speed) as u64,
                         );
-                        let actual_elapsed = replay_start.elapsed();
-
-                        if expected_elapsed > actual_elapsed {
-                            let sleep_time = expected_elapsed - actual_elapsed;
-                            // Sleep in small chunks to check for stop signals
-                            let chunk = Duration::from_millis(10);
-                            let mut remaining = sleep_time;
-                            while remaining > Duration::ZERO {
-                                if stop_rx.try_recv().is_ok() {
-                                    return;
-                                }
-                                let sleep = remaining.min(chunk);
-                                thread::sleep(sleep);
-                                remaining = remaining.saturating_sub(sleep);
-                            }
+                        let actual_elapsed = pacer.elapsed();
+
+                        if expected_elapsed > actual_elapsed
+                            && !pacer.wait_until(expected_elapsed, &stop_rx)
+                        {
+                            return;
                         }
                     }
                 }
@@ -520,6 +957,19 @@ pub fn replay_all_frames(path: &Path) -> Result<Vec<Vec<u8>>> {
     Ok(frames)
 }
 
+/// Extract the frames of a capture file whose timestamps fall in `[from_ms, to_ms)`.
+///
+/// Frame timestamps are derived from the packet that completed each frame,
+/// relative to the capture start, so "corruption at 0:42" can be inspected
+/// with `extract_frames(path, 42_000, 43_000)`.
+///
+/// # Errors
+///
+/// Returns `ReplayError` if the file cannot be loaded or contains invalid data.
+pub fn extract_frames(path: &Path, from_ms: u64, to_ms: u64) -> Result<Vec<ExtractedFrame>> {
+    Ok(PacketReplay::load(path)?.extract_frames(from_ms, to_ms))
+}
+
 /// Replay packets and return frames via an iterator.
 ///
 /// This is a lazy iterator that processes packets on-demand.
@@ -568,16 +1018,213 @@ impl Iterator for FrameIterator {
     }
 }
 
+/// Playback options chosen in the UI (see the `start_replay` command).
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct ReplayOptions {
+    /// Playback speed multiplier (`None` = realtime, 0.0 = as fast as possible).
+    pub speed: Option<f64>,
+    /// Restart from the beginning when the capture ends.
+    pub loop_playback: bool,
+    /// Force MJPEG assembly (overrides the capture metadata).
+    pub force_mjpeg: bool,
+    /// Expected YUY2 frame size in bytes (0 = from the capture metadata).
+    pub expected_frame_size: usize,
+}
+
+impl From<ReplayOptions> for ReplayConfig {
+    fn from(options: ReplayOptions) -> Self {
+        Self {
+            speed: options.speed.unwrap_or(1.0),
+            loop_playback: options.loop_playback,
+            expected_frame_size: options.expected_frame_size,
+            force_mjpeg: options.force_mjpeg,
+            clock: ReplayClock::Real,
+        }
+    }
+}
+
+/// State of the [`ReplayPlayer`], as reported to the frontend.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ReplayStatus {
+    /// Path of the loaded capture, if any.
+    pub path: Option<String>,
+    /// Whether frames are currently being played back.
+    pub running: bool,
+    /// Number of packets in the loaded capture.
+    pub packet_count: usize,
+    /// Duration of the loaded capture in milliseconds.
+    pub duration_ms: u64,
+    /// Frames delivered since playback was last started.
+    pub frames_played: u64,
+    /// Metadata of the loaded capture, if it has a metadata file.
+    pub metadata: Option<CaptureMetadata>,
+}
+
+/// Plays a loaded capture back into the app instead of a camera.
+///
+/// Wraps a [`PacketReplay`] with a forwarding thread that hands every
+/// assembled frame to a callback, so the caller can display replayed frames
+/// exactly like live ones.
+#[derive(Default)]
+pub struct ReplayPlayer {
+    state: Mutex<PlayerState>,
+    frames_played: Arc<AtomicU64>,
+}
+
+/// Loaded capture and forwarding thread of a [`ReplayPlayer`].
+#[derive(Default)]
+struct PlayerState {
+    loaded: Option<(PathBuf, PacketReplay)>,
+    forwarder: Option<JoinHandle<()>>,
+}
+
+impl PlayerState {
+    fn is_running(&self) -> bool {
+        self.forwarder.as_ref().is_some_and(|h| !h.is_finished())
+    }
+
+    /// Stop playback (if any) and reap finished threads.
+    fn stop(&mut self) {
+        if let Some((_, replay)) = self.loaded.as_mut() {
+            if replay.is_running() {
+                let _ = replay.stop();
+            }
+        }
+        if let Some(handle) = self.forwarder.take() {
+            let _ = handle.join();
+        }
+    }
+}
+
+impl ReplayPlayer {
+    /// Create a player with nothing loaded.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Load a capture file for playback, stopping any running playback.
+    ///
+    /// # Errors
+    ///
+    /// Returns `ReplayError` if the file cannot be loaded; the previously
+    /// loaded capture is kept in that case.
+    pub fn load(&self, path: &Path) -> Result<ReplayStatus> {
+        self.set_loaded(path, PacketReplay::load(path)?)
+    }
+
+    /// Load the packets of `endpoint` from a capture file of any format (see
+    /// [`PacketReplay::load_endpoint`]), stopping any running playback.
+    ///
+    /// # Errors
+    ///
+    /// Returns `ReplayError` if the capture cannot be loaded or has no
+    /// packets from `endpoint`; the previously loaded capture is kept in
+    /// that case.
+    pub fn load_endpoint(&self, path: &Path, endpoint: Option<u8>) -> Result<ReplayStatus> {
+        self.set_loaded(path, PacketReplay::load_endpoint(path, endpoint)?)
+    }
+
+    fn set_loaded(&self, path: &Path, replay: PacketReplay) -> Result<ReplayStatus> {
+        let mut state = crate::lock_or_recover(&self.state);
+        state.stop();
+        state.loaded = Some((path.to_path_buf(), replay));
+        self.frames_played.store(0, Ordering::Relaxed);
+        Ok(self.status_of(&state))
+    }
+
+    /// Start playback, calling `on_frame` on a background thread for every
+    /// assembled frame.
+    ///
+    /// # Errors
+    ///
+    /// Returns `ReplayError::NotLoaded` if no capture is loaded, or
+    /// `ReplayError::AlreadyRunning` if playback is in progress.
+    pub fn start<F>(&self, options: ReplayOptions, mut on_frame: F) -> Result<()>
+    where
+        F: FnMut(Vec<u8>) + Send + 'static,
+    {
+        let mut state = crate::lock_or_recover(&self.state);
+        if state.is_running() {
+            return Err(ReplayError::AlreadyRunning);
+        }
+        state.stop();
+        let (_, replay) = state.loaded.as_mut().ok_or(ReplayError::NotLoaded)?;
+        replay.set_config(options.into());
+        let frames = replay.start()?;
+
+        self.frames_played.store(0, Ordering::Relaxed);
+        let frames_played = Arc::clone(&self.frames_played);
+        state.forwarder = Some(thread::spawn(move || {
+            for frame in frames {
+                frames_played.fetch_add(1, Ordering::Relaxed);
+                on_frame(frame);
+            }
+        }));
+        Ok(())
+    }
+
+    /// Whether the loaded capture plays back as MJPEG with `options` (see
+    /// [`PacketReplay::is_mjpeg`]).
+    ///
+    /// # Errors
+    ///
+    /// Returns `ReplayError::NotLoaded` if no capture is loaded.
+    pub fn is_mjpeg(&self, options: &ReplayOptions) -> Result<bool> {
+        let state = crate::lock_or_recover(&self.state);
+        let (_, replay) = state.loaded.as_ref().ok_or(ReplayError::NotLoaded)?;
+        Ok(options.force_mjpeg || replay.is_mjpeg())
+    }
+
+    /// Stop playback, waiting for the last frame to be delivered.
+    ///
+    /// # Errors
+    ///
+    /// Returns `ReplayError::NotRunning` if playback was never started.
+    pub fn stop(&self) -> Result<()> {
+        let mut state = crate::lock_or_recover(&self.state);
+        if state.forwarder.is_none() {
+            return Err(ReplayError::NotRunning);
+        }
+        state.stop();
+        Ok(())
+    }
+
+    /// Current player state.
+    #[must_use]
+    pub fn status(&self) -> ReplayStatus {
+        self.status_of(&crate::lock_or_recover(&self.state))
+    }
+
+    fn status_of(&self, state: &PlayerState) -> ReplayStatus {
+        let frames_played = self.frames_played.load(Ordering::Relaxed);
+        match &state.loaded {
+            Some((path, replay)) => ReplayStatus {
+                path: Some(path.to_string_lossy().to_string()),
+                running: state.is_running(),
+                packet_count: replay.packet_count(),
+                duration_ms: replay.duration_ms(),
+                frames_played,
+                metadata: replay.metadata().cloned(),
+            },
+            None => ReplayStatus::default(),
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     use std::io::Write;
-    use tempfile::tempdir;
+    use tempfile::{tempdir, TempDir};
 
     /// Create a test capture file with synthetic packets.
-    fn create_test_capture(packets: &[ReplayPacket]) -> std::path::PathBuf {
+    ///
+    /// The file is removed when the returned directory is dropped.
+    fn create_test_capture(packets: &[ReplayPacket]) -> (TempDir, std::path::PathBuf) {
         let dir = tempdir().unwrap();
-        let path = dir.keep().join("test_capture.bin");
+        let path = dir.path().join("test_capture.bin");
 
         let mut file = std::fs::File::create(&path).unwrap();
         for packet in packets {
@@ -588,7 +1235,7 @@ mod tests {
             file.write_all(&packet.data).unwrap();
         }
 
-        path
+        (dir, path)
     }
 
     /// Create a minimal UVC packet with header.
@@ -609,7 +1256,7 @@ mod tests {
 
     #[test]
     fn test_load_empty_capture() {
-        let path = create_test_capture(&[]);
+        let (_dir, path) = create_test_capture(&[]);
         let replay = PacketReplay::load(&path).unwrap();
         assert_eq!(replay.packet_count(), 0);
         assert_eq!(replay.duration_ms(), 0);
@@ -623,7 +1270,7 @@ mod tests {
             data: vec![0x02, 0x80, 0xAB, 0xCD],
         }];
 
-        let path = create_test_capture(&packets);
+        let (_dir, path) = create_test_capture(&packets);
         let replay = PacketReplay::load(&path).unwrap();
 
         assert_eq!(replay.packet_count(), 1);
@@ -632,6 +1279,79 @@ mod tests {
         assert_eq!(replay.packets[0].endpoint, 0x81);
     }
 
+    #[test]
+    fn test_load_detects_pcapng() {
+        let packets: Vec<crate::capture::CapturedPacket> = (0..3u8)
+            .map(|i| crate::capture::CapturedPacket {
+                timestamp_us: u64::from(i) * 500,
+                data: create_uvc_packet(false, i == 2, &[i; 4]),
+                endpoint: 0x81,
+            })
+            .collect();
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("usbmon.pcapng");
+        let mut file = std::fs::File::create(&path).unwrap();
+        crate::capture::write_pcapng(&mut file, &packets, 1_000_000).unwrap();
+        drop(file);
+
+        let replay = PacketReplay::load(&path).unwrap();
+        assert_eq!(replay.packet_count(), 3);
+        assert_eq!(replay.packets[2].timestamp_us, 1000);
+        assert_eq!(replay.packets[2].data, packets[2].data);
+        assert!(matches!(
+            PacketReplay::load_pcap(&path, Some(0x82)),
+            Err(ReplayError::NoPackets)
+        ));
+    }
+
+    #[test]
+    fn test_load_packets_files_of_both_versions() {
+        let dir = tempdir().unwrap();
+        let packets: Vec<crate::capture::CapturedPacket> = (0..3u8)
+            .map(|i| crate::capture::CapturedPacket {
+                timestamp_us: u64::from(i) * 700,
+                data: vec![0x02, 0x80, i],
+                endpoint: 0x83,
+            })
+            .collect();
+
+        // Versioned: recognized by its magic whatever the name
+        let path = dir.path().join("recorded.bin");
+        let mut bytes = Vec::new();
+        crate::capture::write_packets(&mut bytes, &packets).unwrap();
+        std::fs::write(&path, bytes).unwrap();
+        let replay = PacketReplay::load(&path).unwrap();
+        assert_eq!(replay.packets[2].timestamp_us, 1400);
+        assert_eq!(replay.packets[2].endpoint, 0x83);
+        assert_eq!(replay.packets[2].data, vec![0x02, 0x80, 2]);
+        assert_eq!(
+            PacketReplay::load_endpoint(&path, Some(0x83))
+                .unwrap()
+                .packet_count(),
+            3
+        );
+        assert!(matches!(
+            PacketReplay::load_endpoint(&path, Some(0x81)),
+            Err(ReplayError::NoPackets)
+        ));
+
+        // Headerless: timestamps synthesized one microframe apart
+        let path = dir.path().join("packets_1.bin");
+        let mut bytes = Vec::new();
+        for packet in &packets {
+            bytes.extend_from_slice(&(packet.data.len() as u32).to_le_bytes());
+            bytes.extend_from_slice(&packet.data);
+        }
+        std::fs::write(&path, bytes).unwrap();
+        let replay = PacketReplay::load(&path).unwrap();
+        assert_eq!(replay.packet_count(), 3);
+        assert_eq!(
+            replay.packets[2].timestamp_us,
+            2 * crate::capture::SYNTHETIC_PACKET_INTERVAL_US
+        );
+        assert_eq!(replay.packets[1].data, packets[1].data);
+    }
+
     #[test]
     fn test_load_multiple_packets() {
         let packets = vec![
@@ -652,7 +1372,7 @@ mod tests {
             },
         ];
 
-        let path = create_test_capture(&packets);
+        let (_dir, path) = create_test_capture(&packets);
         let replay = PacketReplay::load(&path).unwrap();
 
         assert_eq!(replay.packet_count(), 3);
@@ -670,7 +1390,7 @@ mod tests {
 
     #[test]
     fn test_replay_all_frames_empty() {
-        let path = create_test_capture(&[]);
+        let (_dir, path) = create_test_capture(&[]);
         let frames = replay_all_frames(&path).unwrap();
         assert!(frames.is_empty());
     }
@@ -715,7 +1435,7 @@ mod tests {
             },
         ];
 
-        let path = create_test_capture(&packets);
+        let (_dir, path) = create_test_capture(&packets);
 
         // Use config with expected frame size
         let config = ReplayConfig {
@@ -755,7 +1475,7 @@ mod tests {
             },
         ];
 
-        let path = create_test_capture(&packets);
+        let (_dir, path) = create_test_capture(&packets);
         let iterator = FrameIterator::new(&path).unwrap();
 
         // Iterator should process packets (may not produce frames without proper data)
@@ -784,6 +1504,7 @@ mod tests {
             duration_ms: 1000,
             total_bytes: 50000,
             description: "Test capture".to_string(),
+            ..Default::default()
         };
         let json = serde_json::to_string(&metadata).unwrap();
         std::fs::write(&json_path, json).unwrap();
@@ -799,7 +1520,7 @@ mod tests {
 
     #[test]
     fn test_already_running_error() {
-        let path = create_test_capture(&[]);
+        let (_dir, path) = create_test_capture(&[]);
         let mut replay = PacketReplay::load(&path).unwrap();
 
         let _rx = replay.start().unwrap();
@@ -812,7 +1533,7 @@ mod tests {
 
     #[test]
     fn test_not_running_error() {
-        let path = create_test_capture(&[]);
+        let (_dir, path) = create_test_capture(&[]);
         let mut replay = PacketReplay::load(&path).unwrap();
 
         let result = replay.stop();
@@ -821,7 +1542,7 @@ mod tests {
 
     #[test]
     fn test_drop_stops_replay() {
-        let path = create_test_capture(&[]);
+        let (_dir, path) = create_test_capture(&[]);
         let mut replay = PacketReplay::load(&path).unwrap();
 
         let _rx = replay.start().unwrap();
@@ -832,6 +1553,235 @@ mod tests {
         // If this doesn't hang, the thread was properly stopped
     }
 
+    /// One-packet MJPEG frames at the given timestamps (ms), numbered from 0.
+    ///
+    /// Frame 0 of each pass only syncs the assembler and is never delivered.
+    fn create_timed_mjpeg_capture(timestamps_ms: &[u64]) -> (TempDir, std::path::PathBuf) {
+        let packets: Vec<_> = timestamps_ms
+            .iter()
+            .enumerate()
+            .map(|(i, &ms)| ReplayPacket {
+                timestamp_us: ms * 1000,
+                endpoint: 0x81,
+                data: create_uvc_packet(i % 2 == 1, true, &[0xFF, 0xD8, i as u8, 0xFF, 0xD9]),
+            })
+            .collect();
+        create_test_capture(&packets)
+    }
+
+    /// Start a replay paced by a new virtual clock.
+    fn start_virtual(
+        path: &Path,
+        speed: f64,
+        loop_playback: bool,
+    ) -> (PacketReplay, Receiver<Vec<u8>>, VirtualClock) {
+        let clock = VirtualClock::new();
+        let config = ReplayConfig {
+            speed,
+            loop_playback,
+            force_mjpeg: true,
+            clock: ReplayClock::Virtual(clock.clone()),
+            ..Default::default()
+        };
+        let mut replay = PacketReplay::load_with_config(path, config).unwrap();
+        let receiver = replay.start().unwrap();
+        (replay, receiver, clock)
+    }
+
+    /// Frame numbers delivered so far.
+    fn delivered(receiver: &Receiver<Vec<u8>>) -> Vec<u8> {
+        receiver.try_iter().map(|frame| frame[2]).collect()
+    }
+
+    #[test]
+    fn test_frame_index_timestamps() {
+        let (_dir, path) = create_timed_mjpeg_capture(&[0, 10, 20, 30]);
+        let config = ReplayConfig {
+            force_mjpeg: true,
+            ..Default::default()
+        };
+        let replay = PacketReplay::load_with_config(&path, config).unwrap();
+
+        let index = replay.frame_index();
+        let timestamps: Vec<_> = index.iter().map(|e| e.timestamp_us).collect();
+        assert_eq!(timestamps, [10_000, 20_000, 30_000]);
+        assert_eq!(index[0].sequence, 0);
+        assert_eq!(index[0].packet_index, 1);
+    }
+
+    #[test]
+    fn test_extract_frames_by_range() {
+        let (_dir, path) = create_timed_mjpeg_capture(&[0, 10, 20, 30, 40]);
+        let config = ReplayConfig {
+            force_mjpeg: true,
+            ..Default::default()
+        };
+        let replay = PacketReplay::load_with_config(&path, config).unwrap();
+
+        let frames = replay.extract_frames(20, 40);
+        let numbers: Vec<_> = frames.iter().map(|f| f.data[2]).collect();
+        assert_eq!(numbers, [2, 3]);
+        assert_eq!(frames[0].entry.sequence, 1);
+        assert_eq!(frames[1].entry.timestamp_us, 30_000);
+
+        assert!(replay.extract_frames(41, 100).is_empty());
+        assert!(replay.extract_frames(30, 30).is_empty());
+    }
+
+    #[test]
+    fn test_extract_frames_matches_full_replay() {
+        let (_dir, path) = create_timed_mjpeg_capture(&[0, 10, 20, 30, 40, 50]);
+        let config = ReplayConfig {
+            force_mjpeg: true,
+            ..Default::default()
+        };
+        let replay = PacketReplay::load_with_config(&path, config).unwrap();
+        let all = replay.extract_frames(0, u64::MAX);
+        assert_eq!(all.len(), 5);
+
+        // Every seek point yields the same frames as assembling from the start
+        for (i, expected) in all.iter().enumerate() {
+            let from_ms = expected.entry.timestamp_us / 1000;
+            let frames = replay.extract_frames(from_ms, u64::MAX);
+            assert_eq!(frames.len(), all.len() - i);
+            assert_eq!(frames[0].data, expected.data);
+            assert_eq!(frames[0].entry, expected.entry);
+        }
+    }
+
+    #[test]
+    fn test_player_delivers_frames() {
+        let (_dir, path) = create_timed_mjpeg_capture(&[0, 10, 20, 30]);
+        let player = ReplayPlayer::new();
+        assert!(matches!(
+            player.start(ReplayOptions::default(), |_| {}),
+            Err(ReplayError::NotLoaded)
+        ));
+
+        let status = player.load(&path).unwrap();
+        assert_eq!(status.packet_count, 4);
+        assert_eq!(status.duration_ms, 30);
+        assert!(!status.running);
+
+        let (tx, rx) = mpsc::channel();
+        let options = ReplayOptions {
+            speed: Some(0.0),
+            force_mjpeg: true,
+            ..Default::default()
+        };
+        player
+            .start(options, move |frame| tx.send(frame[2]).unwrap())
+            .unwrap();
+        let numbers: Vec<_> = rx.iter().collect();
+        assert_eq!(numbers, [1, 2, 3]);
+
+        player.stop().unwrap();
+        let status = player.status();
+        assert!(!status.running);
+        assert_eq!(status.frames_played, 3);
+    }
+
+    #[test]
+    fn test_player_detects_mjpeg_captures() {
+        let player = ReplayPlayer::new();
+        let options = ReplayOptions::default();
+        assert!(matches!(
+            player.is_mjpeg(&options),
+            Err(ReplayError::NotLoaded)
+        ));
+
+        let (_dir, path) = create_timed_mjpeg_capture(&[0, 10, 20]);
+        player.load(&path).unwrap();
+        assert!(player.is_mjpeg(&options).unwrap());
+
+        let frame: Vec<u8> = (0..16).collect();
+        let packets: Vec<_> = [false, true, false]
+            .into_iter()
+            .enumerate()
+            .map(|(i, fid)| ReplayPacket {
+                timestamp_us: i as u64 * 1000,
+                endpoint: 0x81,
+                data: create_uvc_packet(fid, true, &frame),
+            })
+            .collect();
+        let (_yuv_dir, path) = create_test_capture(&packets);
+        player.load(&path).unwrap();
+        assert!(!player.is_mjpeg(&options).unwrap());
+        let forced = ReplayOptions {
+            force_mjpeg: true,
+            ..Default::default()
+        };
+        assert!(player.is_mjpeg(&forced).unwrap());
+    }
+
+    #[test]
+    fn test_player_stop_and_reload() {
+        let (_dir, path) = create_timed_mjpeg_capture(&[0, 10_000, 20_000]);
+        let player = ReplayPlayer::new();
+        assert!(matches!(player.stop(), Err(ReplayError::NotRunning)));
+
+        player.load(&path).unwrap();
+        player.start(ReplayOptions::default(), |_| {}).unwrap();
+        assert!(player.status().running);
+        assert!(matches!(
+            player.start(ReplayOptions::default(), |_| {}),
+            Err(ReplayError::AlreadyRunning)
+        ));
+
+        // Reloading stops the running playback instead of hanging
+        let status = player.load(&path).unwrap();
+        assert!(!status.running);
+        assert!(player.load(Path::new("/nonexistent/capture.bin")).is_err());
+        assert_eq!(player.status().path, status.path);
+    }
+
+    #[test]
+    fn test_virtual_clock_paces_packets() {
+        let (_dir, path) = create_timed_mjpeg_capture(&[0, 10, 20, 30]);
+        let (_replay, receiver, clock) = start_virtual(&path, 1.0, false);
+
+        assert_eq!(clock.settle(), Some(Duration::from_millis(10)));
+        assert!(delivered(&receiver).is_empty());
+
+        clock.advance(Duration::from_millis(15));
+        assert_eq!(clock.settle(), Some(Duration::from_millis(20)));
+        assert_eq!(delivered(&receiver), vec![1]);
+
+        clock.advance(Duration::from_millis(100));
+        assert_eq!(clock.settle(), None);
+        assert_eq!(delivered(&receiver), vec![2, 3]);
+    }
+
+    #[test]
+    fn test_virtual_clock_speed() {
+        let (_dir, path) = create_timed_mjpeg_capture(&[0, 40, 80]);
+        let (_replay, receiver, clock) = start_virtual(&path, 4.0, false);
+
+        // At 4x, capture time 40 ms plays at 10 ms
+        assert_eq!(clock.settle(), Some(Duration::from_millis(10)));
+        clock.advance(Duration::from_millis(10));
+        assert_eq!(clock.settle(), Some(Duration::from_millis(20)));
+        assert_eq!(delivered(&receiver), vec![1]);
+    }
+
+    #[test]
+    fn test_virtual_clock_loop() {
+        let (_dir, path) = create_timed_mjpeg_capture(&[0, 10]);
+        let (mut replay, receiver, clock) = start_virtual(&path, 1.0, true);
+
+        assert_eq!(clock.settle(), Some(Duration::from_millis(10)));
+        clock.advance(Duration::from_millis(10));
+        // The second pass starts at 10 ms, so its second packet is due at 20 ms
+        assert_eq!(clock.settle(), Some(Duration::from_millis(20)));
+        clock.advance(Duration::from_millis(10));
+        assert_eq!(clock.settle(), Some(Duration::from_millis(30)));
+        assert_eq!(delivered(&receiver), vec![1, 1]);
+
+        // Stopping wakes the thread waiting on the clock
+        replay.stop().unwrap();
+        assert_eq!(clock.settle(), None);
+    }
+
     #[test]
     fn test_create_assembler_mjpeg() {
         let config = ReplayConfig {
@@ -0,0 +1,225 @@
+//! Free storage and memory guards for recordings and packet captures
+//!
+//! Recordings stream to disk and packet captures buffer every packet in
+//! memory, so either can run the device out of space halfway through and
+//! fail with an I/O error that loses the end of the session. Before one
+//! starts, [`check`] measures the free space in the output directory and the
+//! available memory: below the critical thresholds it refuses with
+//! [`ResourceError`], below the warning thresholds it starts but the UI is
+//! warned. The `resource-monitor` thread repeats the check every
+//! [`CHECK_INTERVAL`] while either runs and stops them cleanly, saving what
+//! was recorded, once a critical threshold is crossed.
+
+use std::path::Path;
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::preflight;
+
+/// Free storage below which recordings and captures are stopped (bytes)
+pub const STORAGE_CRITICAL_BYTES: u64 = 100 * 1024 * 1024;
+
+/// Free storage below which a warning is emitted (bytes)
+pub const STORAGE_WARN_BYTES: u64 = 500 * 1024 * 1024;
+
+/// How often the monitor thread checks while a recording or capture runs
+pub const CHECK_INTERVAL: Duration = Duration::from_secs(5);
+
+/// A resource is too low to start or continue
+#[derive(Debug, Error)]
+pub enum ResourceError {
+    /// Free storage in the output directory below [`STORAGE_CRITICAL_BYTES`]
+    #[error("only {} MB of storage free", .0 / (1024 * 1024))]
+    Storage(u64),
+    /// Available memory below [`preflight::MEMORY_FAIL_BYTES`]
+    #[error("only {} MB of memory available", .0 / (1024 * 1024))]
+    Memory(u64),
+}
+
+/// Result type alias for resource checks
+pub type Result<T> = std::result::Result<T, ResourceError>;
+
+/// How close a resource is to running out
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ResourceLevel {
+    /// Enough left (or unknown)
+    #[default]
+    Ok,
+    /// Below the warning threshold
+    Low,
+    /// Below the critical threshold
+    Critical,
+}
+
+/// Measured free storage and memory
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ResourceStatus {
+    /// Worse of the storage and memory levels
+    pub level: ResourceLevel,
+    /// Free bytes in the output directory (`None` if unknown)
+    pub free_storage_bytes: Option<u64>,
+    /// Available memory in bytes (`None` if unknown)
+    pub available_memory_bytes: Option<u64>,
+    /// Level of the free storage
+    pub storage: ResourceLevel,
+    /// Level of the available memory
+    pub memory: ResourceLevel,
+}
+
+impl ResourceStatus {
+    /// Classify measured free storage and memory against the thresholds
+    pub fn classify(free_storage_bytes: Option<u64>, available_memory_bytes: Option<u64>) -> Self {
+        let storage = level(
+            free_storage_bytes,
+            STORAGE_CRITICAL_BYTES,
+            STORAGE_WARN_BYTES,
+        );
+        let memory = level(
+            available_memory_bytes,
+            preflight::MEMORY_FAIL_BYTES,
+            preflight::MEMORY_WARN_BYTES,
+        );
+        Self {
+            level: storage.max(memory),
+            free_storage_bytes,
+            available_memory_bytes,
+            storage,
+            memory,
+        }
+    }
+
+    /// The error for a critical resource, storage first
+    pub fn error(&self) -> Option<ResourceError> {
+        match (self.storage, self.memory) {
+            (ResourceLevel::Critical, _) => {
+                Some(ResourceError::Storage(self.free_storage_bytes.unwrap_or(0)))
+            }
+            (_, ResourceLevel::Critical) => Some(ResourceError::Memory(
+                self.available_memory_bytes.unwrap_or(0),
+            )),
+            _ => None,
+        }
+    }
+}
+
+/// What the resource monitor stopped
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StoppedActivity {
+    /// The active recording (finished and indexed)
+    Recording,
+    /// Packet capture (saved to the output directory)
+    PacketCapture,
+}
+
+/// Payload of the `resource-warning` event
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ResourceWarning {
+    /// Measured resources
+    #[serde(flatten)]
+    pub status: ResourceStatus,
+    /// What was stopped because a resource is critical
+    pub stopped: Vec<StoppedActivity>,
+}
+
+fn level(value: Option<u64>, critical: u64, warn: u64) -> ResourceLevel {
+    match value {
+        Some(v) if v < critical => ResourceLevel::Critical,
+        Some(v) if v < warn => ResourceLevel::Low,
+        _ => ResourceLevel::Ok,
+    }
+}
+
+/// Measure free storage in `dir` (or its nearest existing parent, before
+/// the first write creates it) and the available memory
+pub fn check(dir: &Path) -> ResourceStatus {
+    let existing = dir.ancestors().find(|p| p.exists()).unwrap_or(dir);
+    let memory = std::fs::read_to_string("/proc/meminfo")
+        .ok()
+        .and_then(|s| preflight::parse_mem_available(&s));
+    ResourceStatus::classify(free_space(existing), memory)
+}
+
+/// Measure resources and fail if one is critical
+///
+/// # Errors
+///
+/// Returns `ResourceError::Storage` or `ResourceError::Memory` if free
+/// storage in `dir` or the available memory is below its critical threshold.
+pub fn ensure_available(dir: &Path) -> Result<ResourceStatus> {
+    let status = check(dir);
+    match status.error() {
+        Some(e) => Err(e),
+        None => Ok(status),
+    }
+}
+
+/// Bytes available to the app on the file system holding `dir`
+#[cfg(unix)]
+pub fn free_space(dir: &Path) -> Option<u64> {
+    use std::os::unix::ffi::OsStrExt;
+
+    let path = std::ffi::CString::new(dir.as_os_str().as_bytes()).ok()?;
+    let mut stat: libc::statvfs = unsafe { std::mem::zeroed() };
+    // SAFETY: `path` is NUL-terminated and `stat` is a valid out pointer
+    if unsafe { libc::statvfs(path.as_ptr(), &mut stat) } != 0 {
+        return None;
+    }
+    // The field types are 32-bit on some targets
+    #[allow(clippy::unnecessary_cast)]
+    let bytes = stat.f_bavail as u64 * stat.f_frsize as u64;
+    Some(bytes)
+}
+
+/// Bytes available to the app on the file system holding `dir`
+#[cfg(not(unix))]
+pub fn free_space(_dir: &Path) -> Option<u64> {
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MB: u64 = 1024 * 1024;
+
+    #[test]
+    fn test_classify_thresholds() {
+        let status = ResourceStatus::classify(Some(10 * 1024 * MB), Some(1024 * MB));
+        assert_eq!(status.level, ResourceLevel::Ok);
+        assert!(status.error().is_none());
+
+        let status = ResourceStatus::classify(Some(200 * MB), Some(1024 * MB));
+        assert_eq!(status.storage, ResourceLevel::Low);
+        assert_eq!(status.level, ResourceLevel::Low);
+        assert!(status.error().is_none());
+
+        let status = ResourceStatus::classify(Some(1024 * MB), Some(32 * MB));
+        assert_eq!(status.memory, ResourceLevel::Critical);
+        assert!(matches!(status.error(), Some(ResourceError::Memory(_))));
+
+        let status = ResourceStatus::classify(Some(50 * MB), Some(32 * MB));
+        assert!(matches!(status.error(), Some(ResourceError::Storage(_))));
+    }
+
+    #[test]
+    fn test_unknown_resources_are_not_limits() {
+        let status = ResourceStatus::classify(None, None);
+        assert_eq!(status.level, ResourceLevel::Ok);
+        assert!(status.error().is_none());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_free_space_of_temp_dir() {
+        let dir = tempfile::tempdir().unwrap();
+        assert!(free_space(dir.path()).is_some());
+        assert!(free_space(&dir.path().join("missing")).is_none());
+        assert!(check(&dir.path().join("missing/output"))
+            .free_storage_bytes
+            .is_some());
+    }
+}
@@ -0,0 +1,193 @@
+//! Session resume after a crash
+//!
+//! While a session has bookmarks or measurements, or a recording is running,
+//! the app keeps a [`Checkpoint`] in `resume.json` in the output directory:
+//! the session manifest file, the recording directory, the camera that was
+//! streaming and the display, stream and measurement settings. It is
+//! rewritten whenever it changes (checked every [`CHECKPOINT_INTERVAL`]) and
+//! deleted on a clean exit, so finding one at startup means the previous run
+//! crashed or was killed.
+//!
+//! The frontend then offers to resume (`get_resume_offer`). `resume_session`
+//! reloads the manifest so bookmarks and measurements continue, finishes the
+//! interrupted recording with [`crate::recording::recover`], restores the
+//! settings and, if the same camera is still connected, renegotiates the
+//! saved format and resolution. `dismiss_resume` drops the offer.
+
+use std::io;
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+use crate::measurement::LengthUnit;
+use crate::pixel_format::PixelFormat;
+use crate::storage::Storage;
+use crate::transform::Transform;
+use crate::zoom::Zoom;
+
+/// Checkpoint file in the output directory
+pub const RESUME_FILE: &str = "resume.json";
+
+/// How often the checkpoint is refreshed
+pub const CHECKPOINT_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Settings restored on resume
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ResumeSettings {
+    /// Pixel format for frame conversion
+    pub pixel_format: PixelFormat,
+    /// Whether MJPEG frames were decoded to RGB
+    pub decode_mjpeg: bool,
+    /// UVC format index of the running stream
+    pub format_index: Option<u8>,
+    /// UVC frame (resolution) index of the running stream
+    pub frame_index: Option<u8>,
+    /// Frame interval of the running stream (100 ns units)
+    pub frame_interval: Option<u32>,
+    /// Rotation and flips
+    pub transform: Transform,
+    /// Digital zoom
+    pub zoom: Zoom,
+    /// Unit measurements are reported in
+    pub measurement_unit: LengthUnit,
+}
+
+/// State of the running session, saved so it can be resumed after a crash
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Checkpoint {
+    /// Session manifest file in the output directory, if it has been saved
+    pub session_file: Option<String>,
+    /// Bookmarks in the session
+    pub bookmarks: usize,
+    /// Measurements in the session
+    pub measurements: usize,
+    /// Directory of the recording in progress, relative to the output directory
+    pub recording_dir: Option<String>,
+    /// Camera that was streaming (as reported in `usb-status`)
+    pub device: Option<String>,
+    /// Settings in effect
+    pub settings: ResumeSettings,
+}
+
+impl Checkpoint {
+    /// Write the checkpoint to `resume.json`
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file cannot be written.
+    pub fn save(&self, storage: &Storage) -> io::Result<()> {
+        storage.write(RESUME_FILE, serde_json::to_string_pretty(self)?)?;
+        Ok(())
+    }
+
+    /// Read the checkpoint left by the previous run, if any
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file exists but cannot be read or parsed.
+    pub fn load(storage: &Storage) -> io::Result<Option<Self>> {
+        match std::fs::read_to_string(storage.resolve(RESUME_FILE)?) {
+            Ok(json) => Ok(Some(serde_json::from_str(&json)?)),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Delete `resume.json`, if present
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file exists but cannot be removed.
+    pub fn remove(storage: &Storage) -> io::Result<()> {
+        match storage.remove_file(RESUME_FILE) {
+            Err(e) if e.kind() != io::ErrorKind::NotFound => Err(e),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// A session that can be resumed, returned by `get_resume_offer`
+#[derive(Debug, Clone, Serialize)]
+pub struct ResumeOffer {
+    /// What was saved
+    #[serde(flatten)]
+    pub checkpoint: Checkpoint,
+    /// Whether the camera that was streaming is connected again
+    pub device_connected: bool,
+}
+
+/// Outcome of `resume_session`
+#[derive(Debug, Clone, Serialize)]
+pub struct ResumedSession {
+    /// The restored session manifest
+    pub session: crate::session::SessionManifest,
+    /// The interrupted recording, finished
+    pub recording: Option<crate::recording::RecordingResult>,
+    /// Why the interrupted recording could not be finished
+    pub recording_error: Option<String>,
+    /// Whether the saved format and resolution are being renegotiated
+    pub stream_restored: bool,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn checkpoint() -> Checkpoint {
+        Checkpoint {
+            session_file: Some("session_1700000000.json".to_string()),
+            bookmarks: 2,
+            measurements: 0,
+            recording_dir: Some("recording_1700000010".to_string()),
+            device: Some("Camera 1234:5678".to_string()),
+            settings: ResumeSettings {
+                pixel_format: PixelFormat::default(),
+                decode_mjpeg: true,
+                format_index: Some(1),
+                frame_index: Some(2),
+                frame_interval: None,
+                transform: Transform::default(),
+                zoom: Zoom::new(2.0, 0.25, 0.5).unwrap(),
+                measurement_unit: LengthUnit::Inch,
+            },
+        }
+    }
+
+    #[test]
+    fn test_checkpoint_round_trip() {
+        let dir = tempfile::tempdir().unwrap();
+        let storage = Storage::new(dir.path());
+        assert_eq!(Checkpoint::load(&storage).unwrap(), None);
+
+        checkpoint().save(&storage).unwrap();
+        assert_eq!(Checkpoint::load(&storage).unwrap(), Some(checkpoint()));
+
+        Checkpoint::remove(&storage).unwrap();
+        assert_eq!(Checkpoint::load(&storage).unwrap(), None);
+        // Removing twice is fine
+        Checkpoint::remove(&storage).unwrap();
+    }
+
+    #[test]
+    fn test_corrupt_checkpoint_is_an_error() {
+        let dir = tempfile::tempdir().unwrap();
+        let storage = Storage::new(dir.path());
+        storage.write(RESUME_FILE, "not json").unwrap();
+        assert_eq!(
+            Checkpoint::load(&storage).unwrap_err().kind(),
+            io::ErrorKind::InvalidData
+        );
+    }
+
+    #[test]
+    fn test_offer_flattens_the_checkpoint() {
+        let offer = ResumeOffer {
+            checkpoint: checkpoint(),
+            device_connected: true,
+        };
+        let json = serde_json::to_value(&offer).unwrap();
+        assert_eq!(json["bookmarks"], 2);
+        assert_eq!(json["settings"]["frame_index"], 2);
+        assert_eq!(json["device_connected"], true);
+    }
+}
@@ -0,0 +1,253 @@
+//! Live session manifest with frame-exact bookmarks
+//!
+//! A session spans one app run, unless it is resumed after a crash (see
+//! [`crate::resume`]). Bookmarks mark interesting moments by frame sequence
+//! number so they can be jumped to during review. The manifest is saved as
+//! `session_<timestamp>.json` in the output directory each time it changes,
+//! so bookmarks survive a crash. Measurements taken during the session are
+//! recorded alongside the bookmarks, as are snapshots taken by the
+//! auto-snapshot scheduler (see [`crate::auto_snapshot`]).
+
+use serde::{Deserialize, Serialize};
+
+use crate::measurement::{Measurement, MeasurementKind, Quantity};
+use crate::storage::Storage;
+
+/// Maximum accepted length for a bookmark note
+pub const MAX_NOTE_LEN: usize = 512;
+
+/// A bookmarked moment in a live session
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Bookmark {
+    /// Bookmark number within the session (starting at 1)
+    pub id: u32,
+    /// Sequence number of the frame that was on screen
+    pub frame_sequence: u64,
+    /// Wall-clock time of the bookmark (milliseconds since the Unix epoch)
+    pub timestamp_ms: u64,
+    /// Optional user note
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub note: Option<String>,
+    /// Position in the active recording, if one was running
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub recording: Option<crate::recording::RecordingMarker>,
+}
+
+/// A snapshot saved into the session, labelled with its place in a series
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SessionSnapshot {
+    /// Series and number, e.g. `auto-0003`
+    pub label: String,
+    /// Label of the series the snapshot belongs to
+    pub series: String,
+    /// Number within the series (starting at 1)
+    pub sequence: u32,
+    /// Sequence number of the frame that was saved
+    pub frame_sequence: u64,
+    /// Wall-clock time of the snapshot (milliseconds since the Unix epoch)
+    pub timestamp_ms: u64,
+    /// Path of the saved image
+    pub path: String,
+}
+
+/// Manifest of the current live session
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SessionManifest {
+    /// Session start time (milliseconds since the Unix epoch)
+    pub started_at_ms: u64,
+    /// Bookmarks in creation order
+    pub bookmarks: Vec<Bookmark>,
+    /// Measurements in creation order
+    #[serde(default)]
+    pub measurements: Vec<Measurement>,
+    /// Scheduled snapshots in the order they were taken
+    #[serde(default)]
+    pub snapshots: Vec<SessionSnapshot>,
+}
+
+impl SessionManifest {
+    /// Start a new session at the current time
+    pub fn new() -> Self {
+        Self {
+            started_at_ms: now_ms(),
+            bookmarks: Vec::new(),
+            measurements: Vec::new(),
+            snapshots: Vec::new(),
+        }
+    }
+
+    /// File name used when saving the manifest
+    pub fn file_name(&self) -> String {
+        format!("session_{}.json", self.started_at_ms / 1000)
+    }
+
+    /// Directory for the session's snapshots, relative to the output directory
+    pub fn snapshot_dir(&self) -> String {
+        format!("session_{}", self.started_at_ms / 1000)
+    }
+
+    /// Add a bookmark and return it
+    ///
+    /// Notes are trimmed; empty notes are dropped and long notes truncated to
+    /// [`MAX_NOTE_LEN`] bytes.
+    pub fn add_bookmark(
+        &mut self,
+        frame_sequence: u64,
+        note: Option<String>,
+        recording: Option<crate::recording::RecordingMarker>,
+    ) -> Bookmark {
+        let bookmark = Bookmark {
+            id: self.bookmarks.len() as u32 + 1,
+            frame_sequence,
+            timestamp_ms: now_ms(),
+            note: note.and_then(|n| normalize_note(&n)),
+            recording,
+        };
+        self.bookmarks.push(bookmark.clone());
+        bookmark
+    }
+
+    /// Add a measurement and return it
+    pub fn add_measurement(
+        &mut self,
+        kind: MeasurementKind,
+        points: Vec<[f32; 2]>,
+        value: Quantity,
+        frame_sequence: u64,
+    ) -> Measurement {
+        let measurement = Measurement {
+            id: self.measurements.len() as u32 + 1,
+            kind,
+            points,
+            value,
+            frame_sequence,
+            timestamp_ms: now_ms(),
+        };
+        self.measurements.push(measurement.clone());
+        measurement
+    }
+
+    /// Add a snapshot numbered after the earlier ones in `series`
+    pub fn add_snapshot(
+        &mut self,
+        series: &str,
+        frame_sequence: u64,
+        path: String,
+    ) -> SessionSnapshot {
+        let sequence = self.snapshots.iter().filter(|s| s.series == series).count() as u32 + 1;
+        let snapshot = SessionSnapshot {
+            label: format!("{}-{:04}", series, sequence),
+            series: series.to_string(),
+            sequence,
+            frame_sequence,
+            timestamp_ms: now_ms(),
+            path,
+        };
+        self.snapshots.push(snapshot.clone());
+        snapshot
+    }
+
+    /// Write the manifest as JSON into `storage`
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the directory or file cannot be written.
+    pub fn save(&self, storage: &Storage) -> std::io::Result<()> {
+        let json = serde_json::to_string_pretty(self)?;
+        storage.write(self.file_name(), json)?;
+        Ok(())
+    }
+
+    /// Read a manifest saved as `file_name` in `storage`
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file cannot be read or is not a manifest.
+    pub fn load(storage: &Storage, file_name: &str) -> std::io::Result<Self> {
+        let json = std::fs::read_to_string(storage.resolve(file_name)?)?;
+        Ok(serde_json::from_str(&json)?)
+    }
+}
+
+/// Trim a note, dropping it if empty and truncating it on a char boundary
+pub(crate) fn normalize_note(note: &str) -> Option<String> {
+    let note = note.trim();
+    if note.is_empty() {
+        return None;
+    }
+    let mut end = note.len().min(MAX_NOTE_LEN);
+    while !note.is_char_boundary(end) {
+        end -= 1;
+    }
+    Some(note[..end].to_string())
+}
+
+fn now_ms() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_add_bookmark_numbers_sequentially() {
+        let mut session = SessionManifest::new();
+        let first = session.add_bookmark(10, Some("  crack  ".to_string()), None);
+        let second = session.add_bookmark(42, None, None);
+
+        assert_eq!(first.id, 1);
+        assert_eq!(first.frame_sequence, 10);
+        assert_eq!(first.note.as_deref(), Some("crack"));
+        assert_eq!(second.id, 2);
+        assert_eq!(session.bookmarks.len(), 2);
+    }
+
+    #[test]
+    fn test_snapshot_labels_count_per_label() {
+        let mut session = SessionManifest::new();
+        let first = session.add_snapshot("auto", 3, "a.jpg".to_string());
+        session.add_snapshot("pipe-joint", 5, "b.jpg".to_string());
+        let second = session.add_snapshot("auto", 9, "c.jpg".to_string());
+
+        assert_eq!(first.label, "auto-0001");
+        assert_eq!((second.label.as_str(), second.sequence), ("auto-0002", 2));
+        assert_eq!(session.snapshots[1].label, "pipe-joint-0001");
+    }
+
+    #[test]
+    fn test_normalize_note() {
+        assert_eq!(normalize_note("   "), None);
+        let long = "é".repeat(MAX_NOTE_LEN);
+        let truncated = normalize_note(&long).unwrap();
+        assert!(truncated.len() <= MAX_NOTE_LEN);
+        assert!(truncated.chars().all(|c| c == 'é'));
+    }
+
+    #[test]
+    fn test_save_round_trip() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut session = SessionManifest::new();
+        session.add_bookmark(7, Some("joint".to_string()), None);
+        session.add_measurement(
+            MeasurementKind::Line,
+            vec![[0.0, 0.0], [3.0, 4.0]],
+            Quantity {
+                value: 5.0,
+                uncertainty: 1.4,
+                unit: "px".to_string(),
+            },
+            7,
+        );
+        let storage = Storage::new(dir.path());
+        session.save(&storage).unwrap();
+
+        let loaded = SessionManifest::load(&storage, &session.file_name()).unwrap();
+        assert_eq!(loaded.bookmarks, session.bookmarks);
+        assert_eq!(loaded.measurements, session.measurements);
+        assert_eq!(loaded.started_at_ms, session.started_at_ms);
+    }
+}
@@ -0,0 +1,232 @@
+//! User preferences kept across restarts
+//!
+//! [`Settings`] live in `settings.json` in the app config directory. They are
+//! read at startup (see `load_settings` in `lib.rs`) into `AppState.settings`
+//! and applied: the pixel format, preferred resolution and format preference
+//! go to the streaming configuration, the rotation and flips to the display
+//! transform, the validation level to frame validation, the capture
+//! directory to `app_storage`. `update_settings` validates, applies and saves
+//! a new set; `get_settings` returns the current one. `AppState.settings` is
+//! the single source of truth: the commands that change one of these
+//! preferences on its own (`set_rotation`, `set_flip`,
+//! `set_validation_level`, `cycle_resolution`, `cycle_pixel_format`) update
+//! and save it too.
+//!
+//! Environment overrides (`CLEANSCOPE_OUTPUT_DIR`,
+//! `CLEANSCOPE_FRAME_VALIDATION`) take precedence over the saved values.
+
+use std::io;
+use std::path::PathBuf;
+
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::frame_validation::ValidationLevel;
+use crate::pixel_format::PixelFormat;
+use crate::storage::Storage;
+use crate::transform::Rotation;
+
+/// Settings file in the app config directory
+pub const SETTINGS_FILE: &str = "settings.json";
+
+/// Errors validating settings
+#[derive(Debug, Error)]
+pub enum SettingsError {
+    /// Preferred resolution with a zero dimension
+    #[error("invalid preferred resolution {width}x{height}")]
+    Resolution {
+        /// Requested width
+        width: u16,
+        /// Requested height
+        height: u16,
+    },
+    /// Capture directory that is not an absolute path
+    #[error("capture directory must be an absolute path, got {0:?}")]
+    CaptureDir(PathBuf),
+    /// Empty entry in the format preference
+    #[error("format preference contains an empty name")]
+    FormatName,
+}
+
+/// Result type alias for settings operations
+pub type Result<T> = std::result::Result<T, SettingsError>;
+
+/// Resolution to stream at when the camera offers it
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PreferredResolution {
+    /// Width in pixels
+    pub width: u16,
+    /// Height in pixels
+    pub height: u16,
+}
+
+/// Saved user preferences
+///
+/// Missing fields load as their defaults, so older files stay readable.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Settings {
+    /// Resolution to negotiate (None = the camera's default frame)
+    pub resolution: Option<PreferredResolution>,
+    /// Pixel format of uncompressed frames, e.g. UYVY for cameras that
+    /// report YUYV (None = YUYV)
+    pub pixel_format: Option<PixelFormat>,
+    /// Rotation applied to frames
+    pub rotation: Rotation,
+    /// Mirror frames left and right (after rotating)
+    pub flip_horizontal: bool,
+    /// Mirror frames top and bottom (after rotating)
+    pub flip_vertical: bool,
+    /// Frame validation level (None = keep the current one, strict at startup)
+    pub validation_level: Option<ValidationLevel>,
+    /// Directory for snapshots, recordings and captures
+    /// (None = the app cache directory)
+    pub capture_dir: Option<PathBuf>,
+    /// Format names to negotiate, most preferred first (e.g. `["MJPEG",
+    /// "YUY2", "NV12"]`; empty = MJPEG if the camera has it)
+    pub format_preference: Vec<String>,
+}
+
+impl Settings {
+    /// Check values the types don't rule out
+    ///
+    /// # Errors
+    ///
+    /// Returns `SettingsError` for a zero-sized resolution, a relative
+    /// capture directory or an empty format name.
+    pub fn validate(&self) -> Result<()> {
+        if let Some(PreferredResolution { width, height }) = self.resolution {
+            if width == 0 || height == 0 {
+                return Err(SettingsError::Resolution { width, height });
+            }
+        }
+        if let Some(dir) = &self.capture_dir {
+            if !dir.is_absolute() {
+                return Err(SettingsError::CaptureDir(dir.clone()));
+            }
+        }
+        if self
+            .format_preference
+            .iter()
+            .any(|name| name.trim().is_empty())
+        {
+            return Err(SettingsError::FormatName);
+        }
+        Ok(())
+    }
+
+    /// Write the settings to `settings.json`, replacing it atomically
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file cannot be written.
+    pub fn save(&self, storage: &Storage) -> io::Result<()> {
+        storage.replace(SETTINGS_FILE, serde_json::to_string_pretty(self)?)?;
+        Ok(())
+    }
+
+    /// Read `settings.json`, or the defaults if it doesn't exist
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file exists but cannot be read or parsed.
+    pub fn load(storage: &Storage) -> io::Result<Self> {
+        match std::fs::read_to_string(storage.resolve(SETTINGS_FILE)?) {
+            Ok(json) => Ok(serde_json::from_str(&json)?),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(e) => Err(e),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn settings() -> Settings {
+        Settings {
+            resolution: Some(PreferredResolution {
+                width: 1280,
+                height: 720,
+            }),
+            pixel_format: Some(PixelFormat::Uyvy),
+            rotation: Rotation::Cw180,
+            flip_horizontal: true,
+            flip_vertical: false,
+            validation_level: Some(ValidationLevel::Moderate),
+            capture_dir: Some(std::env::temp_dir().join("captures")),
+            format_preference: vec!["MJPEG".to_string(), "YUY2".to_string()],
+        }
+    }
+
+    #[test]
+    fn test_settings_round_trip() {
+        let dir = tempfile::tempdir().unwrap();
+        let storage = Storage::new(dir.path());
+        assert_eq!(Settings::load(&storage).unwrap(), Settings::default());
+
+        settings().save(&storage).unwrap();
+        assert_eq!(Settings::load(&storage).unwrap(), settings());
+    }
+
+    #[test]
+    fn test_save_replaces_the_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let storage = Storage::new(dir.path());
+        Settings::default().save(&storage).unwrap();
+        settings().save(&storage).unwrap();
+
+        assert_eq!(Settings::load(&storage).unwrap(), settings());
+        let files: Vec<_> = std::fs::read_dir(dir.path())
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name())
+            .filter(|name| name != crate::storage::AUDIT_LOG_NAME)
+            .collect();
+        assert_eq!(files, vec![SETTINGS_FILE]);
+    }
+
+    #[test]
+    fn test_missing_fields_use_defaults() {
+        let dir = tempfile::tempdir().unwrap();
+        let storage = Storage::new(dir.path());
+        storage.write(SETTINGS_FILE, r#"{"rotation": 90}"#).unwrap();
+        let loaded = Settings::load(&storage).unwrap();
+        assert_eq!(loaded.rotation, Rotation::Cw90);
+        assert_eq!(loaded.pixel_format, None);
+
+        storage.write(SETTINGS_FILE, "not json").unwrap();
+        assert_eq!(
+            Settings::load(&storage).unwrap_err().kind(),
+            io::ErrorKind::InvalidData
+        );
+    }
+
+    #[test]
+    fn test_validate() {
+        assert!(settings().validate().is_ok());
+        let zero = Settings {
+            resolution: Some(PreferredResolution {
+                width: 0,
+                height: 480,
+            }),
+            ..settings()
+        };
+        assert!(matches!(
+            zero.validate(),
+            Err(SettingsError::Resolution { width: 0, .. })
+        ));
+        let relative = Settings {
+            capture_dir: Some(PathBuf::from("captures")),
+            ..settings()
+        };
+        assert!(matches!(
+            relative.validate(),
+            Err(SettingsError::CaptureDir(_))
+        ));
+        let blank = Settings {
+            format_preference: vec!["MJPEG".to_string(), " ".to_string()],
+            ..settings()
+        };
+        assert!(matches!(blank.validate(), Err(SettingsError::FormatName)));
+    }
+}
@@ -0,0 +1,535 @@
+//! Background frame spooler for unattended (kiosk) deployments
+//!
+//! Writes every Nth processed frame to disk from its own thread. Frames are
+//! handed over by the streaming thread, not the frontend, so the spool keeps
+//! growing while the `WebView` is crashed, reloading or never opened. The spool
+//! directory is a ring: once `max_files` frames are on disk, the oldest is
+//! removed for each new one, including frames left by an earlier run.
+//!
+//! Settings are read from environment variables at startup and can be
+//! changed at runtime with the `set_spool_config` command:
+//!
+//! | Variable | Default | Meaning |
+//! |----------|---------|---------|
+//! | `CLEANSCOPE_SPOOL_EVERY` | 0 (off) | Write every Nth frame |
+//! | `CLEANSCOPE_SPOOL_MAX_FILES` | 1000 | Frames kept before the oldest is removed |
+//! | `CLEANSCOPE_SPOOL_FORMAT` | `native` | Image format, as for `CLEANSCOPE_SNAPSHOT_FORMAT` |
+//!
+//! The streaming thread never waits on disk: if the writer falls behind, new
+//! frames are dropped (and counted) instead.
+
+use std::collections::VecDeque;
+use std::io;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::mpsc::{self, Receiver, SyncSender, TrySendError};
+use std::sync::{Arc, Mutex};
+use std::thread::JoinHandle;
+
+use serde::{Deserialize, Serialize};
+
+use crate::image_encoder::{self, ImageFormat};
+use crate::recording::FrameFormat;
+use crate::storage::Storage;
+
+/// Subdirectory of the output directory holding spooled frames
+pub const SPOOL_DIR: &str = "spool";
+
+/// Default number of spooled frames kept on disk
+pub const DEFAULT_MAX_FILES: u32 = 1000;
+
+/// Frames waiting for the writer before new ones are dropped
+const QUEUE_DEPTH: usize = 4;
+
+/// File name prefix of spooled frames (names sort in write order)
+const FILE_PREFIX: &str = "spool_";
+
+/// Spooler settings
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SpoolConfig {
+    /// Write every Nth frame (0 = spooling off)
+    pub every_nth: u32,
+    /// Frames kept on disk before the oldest is removed
+    pub max_files: u32,
+    /// Image format (`None`: keep frames as captured, JPEG or raw RGB24)
+    pub format: Option<ImageFormat>,
+}
+
+impl Default for SpoolConfig {
+    fn default() -> Self {
+        Self {
+            every_nth: 0,
+            max_files: DEFAULT_MAX_FILES,
+            format: None,
+        }
+    }
+}
+
+impl SpoolConfig {
+    /// Read settings from the `CLEANSCOPE_SPOOL_*` environment variables
+    pub fn from_env() -> Self {
+        Self::from_lookup(|key| std::env::var(key).ok())
+    }
+
+    /// Read settings through `lookup`, ignoring missing or invalid values
+    pub fn from_lookup(lookup: impl Fn(&str) -> Option<String>) -> Self {
+        let parse = |key: &str| -> Option<u32> {
+            let value = lookup(key)?;
+            match value.trim().parse() {
+                Ok(n) => Some(n),
+                Err(_) => {
+                    log::warn!("Ignoring invalid {}={:?}", key, value);
+                    None
+                }
+            }
+        };
+
+        let defaults = Self::default();
+        let format = lookup("CLEANSCOPE_SPOOL_FORMAT").and_then(|value| {
+            let value = value.trim();
+            if value.is_empty() || value.eq_ignore_ascii_case("native") {
+                return None;
+            }
+            match value.parse::<ImageFormat>() {
+                Ok(format) if format.is_available() => Some(format),
+                _ => {
+                    log::warn!("Ignoring unavailable CLEANSCOPE_SPOOL_FORMAT={:?}", value);
+                    None
+                }
+            }
+        });
+        Self {
+            every_nth: parse("CLEANSCOPE_SPOOL_EVERY").unwrap_or(defaults.every_nth),
+            max_files: parse("CLEANSCOPE_SPOOL_MAX_FILES")
+                .filter(|&n| n > 0)
+                .unwrap_or(defaults.max_files),
+            format,
+        }
+    }
+
+    /// Whether frames are being spooled
+    pub fn is_enabled(&self) -> bool {
+        self.every_nth > 0
+    }
+}
+
+/// Spooler state reported to the frontend
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SpoolStatus {
+    /// Current settings
+    pub config: SpoolConfig,
+    /// Spool directory (`None` until the writer has started)
+    pub directory: Option<String>,
+    /// Frames written since startup
+    pub frames_written: u64,
+    /// Frames dropped because the writer fell behind or a write failed
+    pub frames_dropped: u64,
+    /// Most recent write error
+    pub last_error: Option<String>,
+}
+
+/// A frame on its way to the writer thread
+struct SpoolFrame {
+    data: Vec<u8>,
+    width: u32,
+    height: u32,
+    format: FrameFormat,
+}
+
+/// State shared between the spooler handle and its writer thread
+struct Shared {
+    config: Mutex<SpoolConfig>,
+    frames_written: AtomicU64,
+    frames_dropped: AtomicU64,
+    last_error: Mutex<Option<String>>,
+}
+
+impl Shared {
+    fn record_error(&self, message: String) {
+        log::error!("Frame spool: {}", message);
+        self.frames_dropped.fetch_add(1, Ordering::Relaxed);
+        *crate::lock_or_recover(&self.last_error) = Some(message);
+    }
+}
+
+/// Hands every Nth frame to a background writer thread
+pub struct FrameSpooler {
+    shared: Arc<Shared>,
+    /// Frames offered since startup, for picking every Nth
+    offered: AtomicU64,
+    sender: Mutex<Option<SyncSender<SpoolFrame>>>,
+    writer: Mutex<Option<JoinHandle<()>>>,
+    directory: Mutex<Option<PathBuf>>,
+}
+
+impl FrameSpooler {
+    /// Create a spooler; nothing is written until [`FrameSpooler::start`]
+    #[must_use]
+    pub fn new(config: SpoolConfig) -> Self {
+        Self {
+            shared: Arc::new(Shared {
+                config: Mutex::new(config),
+                frames_written: AtomicU64::new(0),
+                frames_dropped: AtomicU64::new(0),
+                last_error: Mutex::new(None),
+            }),
+            offered: AtomicU64::new(0),
+            sender: Mutex::new(None),
+            writer: Mutex::new(None),
+            directory: Mutex::new(None),
+        }
+    }
+
+    /// Start the writer thread, spooling into [`SPOOL_DIR`] in `storage`
+    ///
+    /// Frames already in the spool directory count towards `max_files`, so
+    /// rotation carries on across restarts. Calling it again restarts the
+    /// writer in the new location.
+    ///
+    /// # Errors
+    ///
+    /// Returns an I/O error if the spool directory can't be created or read.
+    pub fn start(&self, storage: &Storage) -> io::Result<PathBuf> {
+        storage.create_dir_all(SPOOL_DIR)?;
+        let storage = storage.subdir(SPOOL_DIR)?;
+        let mut files: Vec<String> = std::fs::read_dir(storage.root())?
+            .filter_map(|entry| entry.ok()?.file_name().into_string().ok())
+            .filter(|name| name.starts_with(FILE_PREFIX))
+            .collect();
+        files.sort();
+        let directory = storage.root().to_path_buf();
+
+        let (sender, receiver) = mpsc::sync_channel(QUEUE_DEPTH);
+        let writer = SpoolWriter {
+            storage,
+            files: files.into(),
+            shared: Arc::clone(&self.shared),
+        };
+        let handle = std::thread::Builder::new()
+            .name("frame-spool".to_string())
+            .spawn(move || writer.run(receiver))?;
+
+        // Replacing the sender ends the previous writer once its queue drains
+        *crate::lock_or_recover(&self.sender) = Some(sender);
+        *crate::lock_or_recover(&self.writer) = Some(handle);
+        *crate::lock_or_recover(&self.directory) = Some(directory.clone());
+        log::info!("Frame spool directory: {}", directory.display());
+        Ok(directory)
+    }
+
+    /// Stop the writer thread after it has written the queued frames
+    ///
+    /// Frames offered afterwards are ignored until the next `start`.
+    pub fn stop(&self) {
+        crate::lock_or_recover(&self.sender).take();
+        let writer = crate::lock_or_recover(&self.writer).take();
+        if let Some(writer) = writer {
+            let _ = writer.join();
+        }
+    }
+
+    /// Offer a processed frame; every Nth one is queued for writing
+    ///
+    /// Called from the streaming thread. Never blocks: when spooling is off
+    /// this is two atomic loads, and a full queue drops the frame.
+    pub fn offer(&self, data: &[u8], width: u32, height: u32, format: FrameFormat) {
+        let every_nth = crate::lock_or_recover(&self.shared.config).every_nth;
+        if every_nth == 0 || data.is_empty() {
+            return;
+        }
+        let index = self.offered.fetch_add(1, Ordering::Relaxed);
+        if !index.is_multiple_of(u64::from(every_nth)) {
+            return;
+        }
+
+        let sender = crate::lock_or_recover(&self.sender);
+        let Some(sender) = sender.as_ref() else {
+            return;
+        };
+        let frame = SpoolFrame {
+            data: data.to_vec(),
+            width,
+            height,
+            format,
+        };
+        match sender.try_send(frame) {
+            Ok(()) => {}
+            Err(TrySendError::Full(_)) => {
+                self.shared.frames_dropped.fetch_add(1, Ordering::Relaxed);
+                log::debug!("Frame spool queue full, dropping frame");
+            }
+            Err(TrySendError::Disconnected(_)) => {
+                self.shared
+                    .record_error("writer thread stopped".to_string());
+            }
+        }
+    }
+
+    /// Current settings
+    pub fn config(&self) -> SpoolConfig {
+        *crate::lock_or_recover(&self.shared.config)
+    }
+
+    /// Change settings; takes effect from the next frame
+    pub fn set_config(&self, config: SpoolConfig) {
+        *crate::lock_or_recover(&self.shared.config) = config;
+        self.offered.store(0, Ordering::Relaxed);
+        log::info!("Frame spool settings: {:?}", config);
+    }
+
+    /// Settings, location and counters
+    pub fn status(&self) -> SpoolStatus {
+        SpoolStatus {
+            config: self.config(),
+            directory: crate::lock_or_recover(&self.directory)
+                .as_ref()
+                .map(|dir| dir.display().to_string()),
+            frames_written: self.shared.frames_written.load(Ordering::Relaxed),
+            frames_dropped: self.shared.frames_dropped.load(Ordering::Relaxed),
+            last_error: crate::lock_or_recover(&self.shared.last_error).clone(),
+        }
+    }
+}
+
+/// Writer thread state: the spool directory and the files in it, oldest first
+struct SpoolWriter {
+    storage: Storage,
+    files: VecDeque<String>,
+    shared: Arc<Shared>,
+}
+
+impl SpoolWriter {
+    fn run(mut self, receiver: Receiver<SpoolFrame>) {
+        while let Ok(frame) = receiver.recv() {
+            let config = *crate::lock_or_recover(&self.shared.config);
+            match self.write(&frame, config) {
+                Ok(()) => {
+                    self.shared.frames_written.fetch_add(1, Ordering::Relaxed);
+                }
+                Err(message) => self.shared.record_error(message),
+            }
+            self.rotate(config.max_files);
+        }
+    }
+
+    /// Encode and write one frame
+    fn write(&mut self, frame: &SpoolFrame, config: SpoolConfig) -> Result<(), String> {
+        let (data, extension) = match config.format {
+            Some(format) => (
+                image_encoder::encode_frame(&frame.data, frame.width, frame.height, format)
+                    .map_err(|e| e.to_string())?,
+                format.extension(),
+            ),
+            None => (
+                frame.data.clone(),
+                match frame.format {
+                    FrameFormat::Jpeg => "jpg",
+                    FrameFormat::Rgb => "rgb",
+                },
+            ),
+        };
+
+        let timestamp_ms = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_millis();
+        let name = format!(
+            "{}{:015}_{}x{}.{}",
+            FILE_PREFIX, timestamp_ms, frame.width, frame.height, extension
+        );
+        self.storage
+            .write(&name, &data)
+            .map_err(|e| format!("failed to write {}: {}", name, e))?;
+        self.files.push_back(name);
+        Ok(())
+    }
+
+    /// Remove the oldest frames beyond `max_files`
+    fn rotate(&mut self, max_files: u32) {
+        while self.files.len() > max_files.max(1) as usize {
+            let Some(oldest) = self.files.pop_front() else {
+                break;
+            };
+            if let Err(e) = self.storage.remove_file(&oldest) {
+                log::warn!("Frame spool: failed to remove {}: {}", oldest, e);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::{Duration, Instant};
+
+    /// Wait for the writer thread to catch up
+    fn wait_for(spooler: &FrameSpooler, written: u64) {
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while spooler.status().frames_written < written {
+            assert!(Instant::now() < deadline, "writer stalled");
+            std::thread::sleep(Duration::from_millis(5));
+        }
+    }
+
+    fn spool_files(dir: &std::path::Path) -> Vec<String> {
+        let mut names: Vec<String> = std::fs::read_dir(dir)
+            .unwrap()
+            .map(|e| e.unwrap().file_name().into_string().unwrap())
+            .collect();
+        names.sort();
+        names
+    }
+
+    fn config(every_nth: u32, max_files: u32) -> SpoolConfig {
+        SpoolConfig {
+            every_nth,
+            max_files,
+            format: None,
+        }
+    }
+
+    #[test]
+    fn test_config_from_lookup() {
+        let config = SpoolConfig::from_lookup(|key| match key {
+            "CLEANSCOPE_SPOOL_EVERY" => Some("30".to_string()),
+            "CLEANSCOPE_SPOOL_MAX_FILES" => Some("0".to_string()),
+            "CLEANSCOPE_SPOOL_FORMAT" => Some("native".to_string()),
+            _ => None,
+        });
+        assert_eq!(config.every_nth, 30);
+        assert_eq!(config.max_files, DEFAULT_MAX_FILES);
+        assert_eq!(config.format, None);
+        assert!(config.is_enabled());
+
+        let config = SpoolConfig::from_lookup(|key| {
+            (key == "CLEANSCOPE_SPOOL_EVERY").then(|| "often".to_string())
+        });
+        assert_eq!(config, SpoolConfig::default());
+        assert!(!config.is_enabled());
+    }
+
+    #[test]
+    fn test_spools_every_nth_frame() {
+        let dir = tempfile::tempdir().unwrap();
+        let spooler = FrameSpooler::new(config(3, 100));
+        let spool_dir = spooler.start(&Storage::new(dir.path())).unwrap();
+
+        for i in 0..9u8 {
+            spooler.offer(&[0xFF, 0xD8, i, 0xFF, 0xD9], 2, 2, FrameFormat::Jpeg);
+            // Keep the queue short so nothing is dropped
+            wait_for(&spooler, u64::from(i / 3) + 1);
+        }
+
+        let files = spool_files(&spool_dir);
+        assert_eq!(files.len(), 3);
+        assert!(files.iter().all(|f| f.ends_with("_2x2.jpg")));
+        let first = std::fs::read(spool_dir.join(&files[0])).unwrap();
+        assert_eq!(first, vec![0xFF, 0xD8, 0, 0xFF, 0xD9]);
+        assert_eq!(spooler.status().frames_dropped, 0);
+    }
+
+    #[test]
+    fn test_rotation_keeps_newest() {
+        let dir = tempfile::tempdir().unwrap();
+        let spooler = FrameSpooler::new(config(1, 2));
+        let spool_dir = spooler.start(&Storage::new(dir.path())).unwrap();
+
+        for i in 0..5u8 {
+            spooler.offer(&[i; 3], 1, 1, FrameFormat::Rgb);
+            wait_for(&spooler, u64::from(i) + 1);
+        }
+
+        let files = spool_files(&spool_dir);
+        assert_eq!(files.len(), 2);
+        assert_eq!(
+            std::fs::read(spool_dir.join(&files[1])).unwrap(),
+            vec![4; 3]
+        );
+    }
+
+    #[test]
+    fn test_rotation_includes_earlier_runs() {
+        let dir = tempfile::tempdir().unwrap();
+        let storage = Storage::new(dir.path());
+        storage.create_dir_all(SPOOL_DIR).unwrap();
+        for name in [
+            "spool_000000000000001_1x1.rgb",
+            "spool_000000000000002_1x1.rgb",
+        ] {
+            storage
+                .write(format!("{}/{}", SPOOL_DIR, name), [0; 3])
+                .unwrap();
+        }
+        // Other files in the directory are left alone
+        storage
+            .write(format!("{}/notes.txt", SPOOL_DIR), "keep")
+            .unwrap();
+
+        let spooler = FrameSpooler::new(config(1, 2));
+        let spool_dir = spooler.start(&storage).unwrap();
+        spooler.offer(&[7; 3], 1, 1, FrameFormat::Rgb);
+        wait_for(&spooler, 1);
+
+        let files = spool_files(&spool_dir);
+        assert_eq!(files.len(), 3);
+        assert_eq!(files[0], "notes.txt");
+        assert_eq!(files[1], "spool_000000000000002_1x1.rgb");
+    }
+
+    #[test]
+    fn test_stop_drains_queue_and_joins_writer() {
+        let dir = tempfile::tempdir().unwrap();
+        let spooler = FrameSpooler::new(config(1, 100));
+        let spool_dir = spooler.start(&Storage::new(dir.path())).unwrap();
+        for i in 0..3u8 {
+            spooler.offer(&[i; 3], 1, 1, FrameFormat::Rgb);
+        }
+        spooler.stop();
+
+        // Everything queued is on disk once stop returns
+        let status = spooler.status();
+        assert_eq!(status.frames_written + status.frames_dropped, 3);
+        assert_eq!(spool_files(&spool_dir).len() as u64, status.frames_written);
+
+        spooler.offer(&[9; 3], 1, 1, FrameFormat::Rgb);
+        assert_eq!(spooler.status().frames_written, status.frames_written);
+    }
+
+    #[test]
+    fn test_disabled_or_unstarted_writes_nothing() {
+        let dir = tempfile::tempdir().unwrap();
+        let spooler = FrameSpooler::new(config(1, 10));
+        // Not started yet: frames are ignored
+        spooler.offer(&[1; 3], 1, 1, FrameFormat::Rgb);
+
+        let spool_dir = spooler.start(&Storage::new(dir.path())).unwrap();
+        spooler.set_config(config(0, 10));
+        spooler.offer(&[1; 3], 1, 1, FrameFormat::Rgb);
+        std::thread::sleep(Duration::from_millis(20));
+
+        assert!(spool_files(&spool_dir).is_empty());
+        let status = spooler.status();
+        assert_eq!(status.frames_written, 0);
+        assert_eq!(status.directory, Some(spool_dir.display().to_string()));
+    }
+
+    #[test]
+    fn test_encodes_to_configured_format() {
+        if !ImageFormat::Png.is_available() {
+            return;
+        }
+        let dir = tempfile::tempdir().unwrap();
+        let spooler = FrameSpooler::new(SpoolConfig {
+            format: Some(ImageFormat::Png),
+            ..config(1, 10)
+        });
+        let spool_dir = spooler.start(&Storage::new(dir.path())).unwrap();
+        spooler.offer(&[200; 12], 2, 2, FrameFormat::Rgb);
+        wait_for(&spooler, 1);
+
+        let files = spool_files(&spool_dir);
+        assert!(files[0].ends_with("_2x2.png"));
+        let png = std::fs::read(spool_dir.join(&files[0])).unwrap();
+        assert_eq!(&png[..4], b"\x89PNG");
+    }
+}
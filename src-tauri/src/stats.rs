@@ -0,0 +1,353 @@
+//! Frame assembly statistics for the diagnostics overlay
+//!
+//! Where [`crate::stream_health`] tracks whether frames reach the screen, this
+//! module looks one level down, at frame assembly: the isochronous and bulk
+//! callbacks and [`crate::frame_assembler::FrameAssembler`] report every
+//! assembled frame with its size and how long it took from its first payload
+//! to completion, every partial frame they had to discard, every frame that
+//! failed validation and every USB error, the frame processor reports every
+//! frame it withheld from display at the current validation level (see
+//! `set_validation_level`), and the streams' frame broadcast
+//! reports how far behind its consumers fell (see
+//! [`crate::frame_broadcast::ChannelMetrics`]). [`StreamStats::report`]
+//! summarizes the last [`SAMPLE_WINDOW`] frames; it is returned by
+//! `get_stream_stats` and emitted as `stream-stats` alongside every
+//! `usb-health` event.
+//!
+//! Counters are atomics so the USB callbacks can update them cheaply; only
+//! the per-frame samples sit behind a mutex.
+
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use serde::{Deserialize, Serialize};
+
+use crate::frame_assembler::AssemblyObserver;
+use crate::frame_broadcast::ChannelMetrics;
+use crate::stream_health::FPS_WINDOW;
+
+/// Frames the size distribution and assembly latency are computed over
+pub const SAMPLE_WINDOW: usize = 120;
+
+/// Distribution of recent frame sizes (bytes)
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct FrameSizeStats {
+    /// Smallest frame
+    pub min: usize,
+    /// Largest frame
+    pub max: usize,
+    /// Mean frame size
+    pub mean: usize,
+    /// Median frame size
+    pub p50: usize,
+    /// 95th percentile frame size
+    pub p95: usize,
+}
+
+/// Backlog of the frame broadcast between the USB callbacks and consumers
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct FrameChannelStats {
+    /// Frames the broadcast holds (0 before the first stream)
+    pub capacity: usize,
+    /// Most frames a consumer had waiting; equal to `capacity` when one fell
+    /// a full ring behind
+    pub high_water_mark: usize,
+    /// Frames consumers lost because the ring was full (oldest dropped
+    /// instead of blocking the USB callback)
+    pub overflows: u64,
+}
+
+/// Summary of frame assembly, returned by `get_stream_stats`
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct StreamStatsReport {
+    /// Assembled frames per second over the last [`FPS_WINDOW`]
+    pub fps: f32,
+    /// Frames assembled since the stream started
+    pub frames: u64,
+    /// Partial frames discarded since the stream started
+    pub dropped_frames: u64,
+    /// Frames that failed validation since the stream started
+    pub corrupt_frames: u64,
+    /// Frames not displayed because they failed validation at the current
+    /// level, since the stream started
+    pub rejected_frames: u64,
+    /// USB transfer and packet errors since startup
+    pub usb_errors: u64,
+    /// Sizes of the last [`SAMPLE_WINDOW`] frames (`None` before the first frame)
+    pub frame_size: Option<FrameSizeStats>,
+    /// Mean time from a frame's first payload to its completion (milliseconds)
+    pub avg_assembly_latency_ms: Option<f32>,
+    /// Frame broadcast backlog since the stream started
+    pub frame_channel: FrameChannelStats,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct FrameSample {
+    at: Instant,
+    size: usize,
+    assembly: Option<Duration>,
+}
+
+/// Thread-safe frame assembly statistics shared by the USB code and commands
+#[derive(Debug, Default)]
+pub struct StreamStats {
+    frames: AtomicU64,
+    dropped: AtomicU64,
+    corrupt: AtomicU64,
+    rejected: AtomicU64,
+    usb_errors: AtomicU64,
+    samples: Mutex<VecDeque<FrameSample>>,
+    /// When the frame a `FrameAssembler` is assembling started
+    assembly_started: Mutex<Option<Instant>>,
+    channel: Arc<ChannelMetrics>,
+}
+
+impl StreamStats {
+    /// Create an empty tracker
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Metrics for the stream's frame broadcast (see
+    /// `frame_broadcast::channel_with_metrics`)
+    pub fn frame_channel(&self) -> Arc<ChannelMetrics> {
+        Arc::clone(&self.channel)
+    }
+
+    /// Start counting a new stream; USB error counts are kept
+    pub fn reset(&self) {
+        self.frames.store(0, Ordering::Relaxed);
+        self.dropped.store(0, Ordering::Relaxed);
+        self.corrupt.store(0, Ordering::Relaxed);
+        self.rejected.store(0, Ordering::Relaxed);
+        crate::lock_or_recover(&self.samples).clear();
+        *crate::lock_or_recover(&self.assembly_started) = None;
+        self.channel.reset();
+    }
+
+    /// Record an assembled frame of `size` bytes that took `assembly` to
+    /// assemble (`None` if unknown)
+    pub fn record_frame(&self, size: usize, assembly: Option<Duration>) {
+        self.record_frame_at(Instant::now(), size, assembly);
+    }
+
+    /// Record a frame assembled at `now`
+    pub fn record_frame_at(&self, now: Instant, size: usize, assembly: Option<Duration>) {
+        self.frames.fetch_add(1, Ordering::Relaxed);
+        let mut samples = crate::lock_or_recover(&self.samples);
+        if samples.len() == SAMPLE_WINDOW {
+            samples.pop_front();
+        }
+        samples.push_back(FrameSample {
+            at: now,
+            size,
+            assembly,
+        });
+    }
+
+    /// Record a partial frame that had to be discarded
+    pub fn record_dropped(&self) {
+        self.dropped.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Record a frame that failed validation
+    pub fn record_corrupt(&self) {
+        self.corrupt.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Record a frame withheld from display because it failed validation
+    pub fn record_rejected(&self) {
+        self.rejected.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Record `count` USB transfer or packet errors
+    pub fn record_usb_errors(&self, count: u64) {
+        self.usb_errors.fetch_add(count, Ordering::Relaxed);
+    }
+
+    /// Current statistics
+    pub fn report(&self) -> StreamStatsReport {
+        self.report_at(Instant::now())
+    }
+
+    /// Statistics as of `now`
+    pub fn report_at(&self, now: Instant) -> StreamStatsReport {
+        let samples = crate::lock_or_recover(&self.samples);
+
+        let recent: Vec<Instant> = samples
+            .iter()
+            .map(|s| s.at)
+            .filter(|&at| now.saturating_duration_since(at) <= FPS_WINDOW)
+            .collect();
+        let fps = match (recent.first(), recent.last()) {
+            (Some(&first), Some(&last)) if recent.len() >= 2 && last > first => {
+                (recent.len() - 1) as f32 / (last - first).as_secs_f32()
+            }
+            _ => 0.0,
+        };
+
+        let frame_size = (!samples.is_empty()).then(|| {
+            let mut sizes: Vec<usize> = samples.iter().map(|s| s.size).collect();
+            sizes.sort_unstable();
+            let percentile = |p: usize| sizes[(sizes.len() - 1) * p / 100];
+            FrameSizeStats {
+                min: sizes[0],
+                max: sizes[sizes.len() - 1],
+                mean: sizes.iter().sum::<usize>() / sizes.len(),
+                p50: percentile(50),
+                p95: percentile(95),
+            }
+        });
+
+        let latencies: Vec<Duration> = samples.iter().filter_map(|s| s.assembly).collect();
+        let avg_assembly_latency_ms = (!latencies.is_empty()).then(|| {
+            latencies.iter().sum::<Duration>().as_secs_f32() * 1000.0 / latencies.len() as f32
+        });
+
+        StreamStatsReport {
+            fps,
+            frames: self.frames.load(Ordering::Relaxed),
+            dropped_frames: self.dropped.load(Ordering::Relaxed),
+            corrupt_frames: self.corrupt.load(Ordering::Relaxed),
+            rejected_frames: self.rejected.load(Ordering::Relaxed),
+            usb_errors: self.usb_errors.load(Ordering::Relaxed),
+            frame_size,
+            avg_assembly_latency_ms,
+            frame_channel: FrameChannelStats {
+                capacity: self.channel.capacity(),
+                high_water_mark: self.channel.high_water_mark(),
+                overflows: self.channel.overflows(),
+            },
+        }
+    }
+}
+
+impl AssemblyObserver for StreamStats {
+    fn frame_started(&self) {
+        *crate::lock_or_recover(&self.assembly_started) = Some(Instant::now());
+    }
+
+    fn frame_assembled(&self, size: usize) {
+        let started = crate::lock_or_recover(&self.assembly_started).take();
+        self.record_frame(size, started.map(|t| t.elapsed()));
+    }
+
+    fn frame_dropped(&self) {
+        *crate::lock_or_recover(&self.assembly_started) = None;
+        self.record_dropped();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_empty_report() {
+        let report = StreamStats::new().report();
+        assert_eq!(report, StreamStatsReport::default());
+    }
+
+    #[test]
+    fn test_fps_sizes_and_latency() {
+        let stats = StreamStats::new();
+        let start = Instant::now();
+        for i in 0..31u64 {
+            let latency = Duration::from_millis(if i % 2 == 0 { 10 } else { 20 });
+            stats.record_frame_at(
+                start + Duration::from_millis(i * 33),
+                1000 + i as usize * 10,
+                Some(latency),
+            );
+        }
+        stats.record_frame_at(start + Duration::from_millis(31 * 33), 5000, None);
+
+        let report = stats.report_at(start + Duration::from_millis(31 * 33));
+        assert_eq!(report.frames, 32);
+        assert!((report.fps - 30.3).abs() < 0.1, "fps = {}", report.fps);
+
+        let sizes = report.frame_size.unwrap();
+        assert_eq!((sizes.min, sizes.max), (1000, 5000));
+        assert_eq!(sizes.p50, 1150);
+        assert_eq!(sizes.p95, 1290);
+
+        // 16 frames at 10 ms and 15 at 20 ms; the last frame has no latency
+        let latency = report.avg_assembly_latency_ms.unwrap();
+        assert!((latency - 14.84).abs() < 0.01, "latency = {}", latency);
+    }
+
+    #[test]
+    fn test_window_is_bounded() {
+        let stats = StreamStats::new();
+        let start = Instant::now();
+        for i in 0..(SAMPLE_WINDOW as u64 + 10) {
+            stats.record_frame_at(start + Duration::from_millis(i), i as usize, None);
+        }
+        let report = stats.report_at(start);
+        assert_eq!(report.frames, SAMPLE_WINDOW as u64 + 10);
+        assert_eq!(report.frame_size.unwrap().min, 10);
+    }
+
+    #[test]
+    fn test_reset_keeps_usb_errors() {
+        let stats = StreamStats::new();
+        stats.record_frame(100, None);
+        stats.record_dropped();
+        stats.record_corrupt();
+        stats.record_rejected();
+        stats.record_usb_errors(3);
+        assert_eq!(stats.report().rejected_frames, 1);
+        stats.reset();
+
+        let report = stats.report();
+        assert_eq!(
+            (
+                report.frames,
+                report.dropped_frames,
+                report.corrupt_frames,
+                report.rejected_frames
+            ),
+            (0, 0, 0, 0)
+        );
+        assert_eq!(report.usb_errors, 3);
+        assert_eq!(report.frame_size, None);
+    }
+
+    #[test]
+    fn test_frame_channel_metrics() {
+        let stats = StreamStats::new();
+        let (sender, mut cursor) =
+            crate::frame_broadcast::channel_with_metrics(2, stats.frame_channel());
+        for i in 0..4u8 {
+            sender.send(vec![i]);
+        }
+        cursor.try_recv().unwrap();
+
+        let channel = stats.report().frame_channel;
+        assert_eq!(
+            (channel.capacity, channel.high_water_mark, channel.overflows),
+            (2, 2, 2)
+        );
+        stats.reset();
+        assert_eq!(stats.report().frame_channel.overflows, 0);
+    }
+
+    #[test]
+    fn test_assembly_observer() {
+        let stats = StreamStats::new();
+        stats.frame_assembled(100);
+        stats.frame_started();
+        stats.frame_assembled(200);
+        stats.frame_started();
+        stats.frame_dropped();
+
+        let report = stats.report();
+        assert_eq!((report.frames, report.dropped_frames), (2, 1));
+        // Only the frame that spanned packets has an assembly time
+        let samples = crate::lock_or_recover(&stats.samples);
+        assert!(samples[0].assembly.is_none());
+        assert!(samples[1].assembly.is_some());
+    }
+}
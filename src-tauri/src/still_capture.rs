@@ -0,0 +1,852 @@
+//! UVC still image capture (still capture methods 2 and 3)
+//!
+//! Many endoscopes take stills at a higher resolution than the video stream.
+//! A camera announces how with the `bStillCaptureMethod` of its video
+//! streaming input header and a `VS_STILL_IMAGE_FRAME` descriptor per format
+//! listing the still sizes, both parsed into the
+//! [`FormatCatalog`](crate::uvc_descriptors::FormatCatalog):
+//!
+//! - Method 1: stills are ordinary video frames, so there is nothing to
+//!   negotiate; take a snapshot instead
+//! - Method 2: after `VS_STILL_IMAGE_TRIGGER_CONTROL`, the camera sends one
+//!   still in the video stream, then resumes video
+//! - Method 3: the still is sent on a dedicated bulk endpoint
+//!
+//! [`StillCapture::capture`] negotiates the still size with
+//! `VS_STILL_PROBE_CONTROL` / `VS_STILL_COMMIT_CONTROL`, triggers the still
+//! and waits for it. Method 2 stills arrive through the streaming pipeline,
+//! which hands each assembled frame to [`StillCapture::offer`]: while a
+//! capture is pending, the frame whose payloads carry the still image bit
+//! (STI) and that has the still's size (its JPEG dimensions for MJPEG) is
+//! taken and kept out of the preview. Uncompressed frames are split at the
+//! video frame size, so uncompressed method 2 stills must have the video
+//! resolution. Method 3 stills are read from the still endpoint.
+//!
+//! Like [`CameraControls`](crate::uvc_controls::CameraControls), the
+//! streaming backend attaches the open device with [`StillCapture::attach`];
+//! the returned guard detaches it when the device is closed.
+
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Condvar, Mutex};
+use std::time::{Duration, Instant};
+
+use serde::Serialize;
+use thiserror::Error;
+
+use crate::frame_assembler::{jpeg_dimensions, parse_uvc_payload};
+use crate::uvc_controls::{
+    self, ControlTransport, UvcControlError, GET_CUR, REQUEST_TYPE_IN, REQUEST_TYPE_OUT, SET_CUR,
+};
+use crate::uvc_descriptors::{FormatDescriptor, FormatKind};
+
+/// Still probe control selector of the video streaming interface
+pub const VS_STILL_PROBE_CONTROL: u8 = 0x03;
+/// Still commit control selector
+pub const VS_STILL_COMMIT_CONTROL: u8 = 0x04;
+/// Still image trigger control selector
+pub const VS_STILL_IMAGE_TRIGGER_CONTROL: u8 = 0x05;
+
+/// Length of the still probe and commit controls
+pub const STILL_PROBE_LEN: usize = 11;
+
+/// How long to wait for the still after triggering it
+pub const STILL_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Size of each bulk read from a method 3 still endpoint
+const BULK_READ_SIZE: usize = 64 * 1024;
+
+/// Errors taking a still image
+#[derive(Debug, Error)]
+pub enum StillError {
+    /// No device is attached
+    #[error("no camera is connected")]
+    NoCamera,
+    /// No stream is running to take the still from
+    #[error("the camera is not streaming")]
+    NotStreaming,
+    /// The camera has no still capture method
+    #[error("camera does not support still image capture")]
+    Unsupported,
+    /// Method 1 stills are video frames
+    #[error("camera takes stills as video frames (method 1), take a snapshot instead")]
+    VideoFrameStills,
+    /// The streaming format has no still image frame descriptor
+    #[error("format {0} has no still image sizes")]
+    NoStillSizes(u8),
+    /// The requested size is not one of the format's still sizes
+    #[error("format {format_index} has no {width}x{height} still size")]
+    UnknownSize {
+        /// UVC format index
+        format_index: u8,
+        /// Requested width
+        width: u16,
+        /// Requested height
+        height: u16,
+    },
+    /// Only one of the still's width and height was given
+    #[error("a still size needs both a width and a height")]
+    PartialSize,
+    /// An uncompressed method 2 still is split from the stream at the video
+    /// frame size, so it must have the video resolution
+    #[error(
+        "uncompressed {}x{} stills can't be taken from a {}x{} stream",
+        still.0, still.1, video.0, video.1
+    )]
+    SizeMismatch {
+        /// Still size
+        still: (u16, u16),
+        /// Video size
+        video: (u16, u16),
+    },
+    /// Another still is being taken
+    #[error("a still capture is already in progress")]
+    Busy,
+    /// The still did not arrive in time
+    #[error("no still image arrived within {} seconds", STILL_TIMEOUT.as_secs())]
+    Timeout,
+    /// A control request or bulk read failed
+    #[error("still transfer failed: {0}")]
+    Transfer(String),
+}
+
+impl From<UvcControlError> for StillError {
+    fn from(e: UvcControlError) -> Self {
+        StillError::Transfer(e.to_string())
+    }
+}
+
+/// Result type alias for still capture operations
+pub type Result<T> = std::result::Result<T, StillError>;
+
+/// How the camera delivers stills (`bStillCaptureMethod`)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StillMethod {
+    /// No still capture
+    None,
+    /// Method 1: stills are video frames
+    VideoFrame,
+    /// Method 2: a triggered still replaces one frame of the video stream
+    Stream,
+    /// Method 3: a triggered still is sent on a bulk endpoint
+    BulkEndpoint,
+}
+
+impl StillMethod {
+    /// Method from the input header's `bStillCaptureMethod`
+    pub fn from_descriptor(value: u8) -> Self {
+        match value {
+            1 => StillMethod::VideoFrame,
+            2 => StillMethod::Stream,
+            3 => StillMethod::BulkEndpoint,
+            _ => StillMethod::None,
+        }
+    }
+}
+
+/// `bTrigger` values of the still image trigger control
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+pub enum StillTrigger {
+    /// Normal operation
+    Normal = 0,
+    /// Transmit a still image (method 2)
+    Transmit = 1,
+    /// Transmit a still image on the bulk still endpoint (method 3)
+    TransmitBulk = 2,
+    /// Abort the still transmission
+    Abort = 3,
+}
+
+/// Still probe/commit control (`VS_STILL_PROBE_CONTROL`)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StillProbe {
+    /// UVC format index (1-based)
+    pub format_index: u8,
+    /// Still size index in the still image frame descriptor (1-based)
+    pub frame_index: u8,
+    /// Compression index (1-based, 0 if the still has no compression patterns)
+    pub compression_index: u8,
+    /// Maximum still size in bytes (set by the camera)
+    pub max_frame_size: u32,
+    /// Maximum payload transfer size in bytes (set by the camera)
+    pub max_payload_size: u32,
+}
+
+impl StillProbe {
+    /// Control bytes to send
+    pub fn to_bytes(&self) -> [u8; STILL_PROBE_LEN] {
+        let mut bytes = [0u8; STILL_PROBE_LEN];
+        bytes[0] = self.format_index;
+        bytes[1] = self.frame_index;
+        bytes[2] = self.compression_index;
+        bytes[3..7].copy_from_slice(&self.max_frame_size.to_le_bytes());
+        bytes[7..11].copy_from_slice(&self.max_payload_size.to_le_bytes());
+        bytes
+    }
+
+    /// Parse the control the camera returned
+    pub fn parse(bytes: &[u8]) -> Option<Self> {
+        let bytes: &[u8; STILL_PROBE_LEN] = bytes.get(..STILL_PROBE_LEN)?.try_into().ok()?;
+        let u32_at = |at: usize| {
+            u32::from_le_bytes([bytes[at], bytes[at + 1], bytes[at + 2], bytes[at + 3]])
+        };
+        Some(Self {
+            format_index: bytes[0],
+            frame_index: bytes[1],
+            compression_index: bytes[2],
+            max_frame_size: u32_at(3),
+            max_payload_size: u32_at(7),
+        })
+    }
+}
+
+/// Control requests plus reads from a method 3 still endpoint
+///
+/// Implemented by the libusb handle on Android and the rusb handle on desktop.
+pub trait StillTransport: ControlTransport {
+    /// Read from a bulk endpoint, returning the number of bytes read
+    ///
+    /// # Errors
+    ///
+    /// Returns `UvcControlError::Transfer` if the read fails or times out.
+    fn read_bulk(
+        &self,
+        endpoint: u8,
+        data: &mut [u8],
+        timeout_ms: u32,
+    ) -> uvc_controls::Result<usize>;
+}
+
+/// What to take a still of, from the running stream
+#[derive(Debug, Clone)]
+pub struct StillRequest {
+    /// Still method of the camera (`FormatCatalog::still_capture_method`)
+    pub method: StillMethod,
+    /// Format being streamed
+    pub format: FormatDescriptor,
+    /// Video resolution being streamed
+    pub video_size: (u16, u16),
+    /// Still size to take (None = the largest)
+    pub size: Option<(u16, u16)>,
+}
+
+/// A still image as the camera sent it
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StillImage {
+    /// Width in pixels
+    pub width: u16,
+    /// Height in pixels
+    pub height: u16,
+    /// JPEG (MJPEG formats) or a frame in the streaming pixel format
+    pub data: Vec<u8>,
+}
+
+/// How a pending method 2 still is recognized among video frames
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum StillMatch {
+    /// A JPEG of these dimensions
+    Jpeg(u16, u16),
+    /// An uncompressed frame of this many bytes
+    Raw(usize),
+}
+
+impl StillMatch {
+    fn matches(self, frame: &[u8]) -> bool {
+        match self {
+            StillMatch::Jpeg(width, height) => {
+                jpeg_dimensions(frame) == Some((u32::from(width), u32::from(height)))
+            }
+            StillMatch::Raw(size) => frame.len() == size,
+        }
+    }
+}
+
+/// A capture in progress
+struct PendingStill {
+    /// Frames to take from the stream (None for method 3)
+    wanted: Option<StillMatch>,
+    /// The still, once it arrived
+    frame: Option<Vec<u8>>,
+}
+
+struct AttachedStill {
+    transport: Arc<dyn StillTransport>,
+    interface: u8,
+    generation: u64,
+}
+
+/// Still capture for the connected camera, shared between streaming and commands
+#[derive(Default)]
+pub struct StillCapture {
+    camera: Mutex<Option<AttachedStill>>,
+    generation: AtomicU64,
+    pending: Mutex<Option<PendingStill>>,
+    arrived: Condvar,
+}
+
+/// Detaches the camera from [`StillCapture`] when dropped
+pub struct StillAttachment {
+    still: Arc<StillCapture>,
+    generation: u64,
+}
+
+impl Drop for StillAttachment {
+    fn drop(&mut self) {
+        let mut camera = crate::lock_or_recover(&self.still.camera);
+        if camera
+            .as_ref()
+            .is_some_and(|c| c.generation == self.generation)
+        {
+            *camera = None;
+        }
+    }
+}
+
+impl StillCapture {
+    /// Create still capture with no camera attached
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Route still requests to the streaming interface of an opened camera
+    /// until the guard is dropped
+    ///
+    /// Drop the guard before closing the device.
+    #[must_use = "the camera is detached when the guard is dropped"]
+    pub fn attach(
+        self: &Arc<Self>,
+        transport: Arc<dyn StillTransport>,
+        interface: u8,
+    ) -> StillAttachment {
+        let generation = self.generation.fetch_add(1, Ordering::Relaxed) + 1;
+        *crate::lock_or_recover(&self.camera) = Some(AttachedStill {
+            transport,
+            interface,
+            generation,
+        });
+        StillAttachment {
+            still: Arc::clone(self),
+            generation,
+        }
+    }
+
+    /// Take an assembled frame if it is the pending method 2 still
+    ///
+    /// `still_image` tells whether the frame's payloads carried the still
+    /// image bit; only such a frame is taken. Returns `true` if the frame was
+    /// taken; it should then be kept out of the preview.
+    pub fn offer(&self, frame: &[u8], still_image: bool) -> bool {
+        if !still_image {
+            return false;
+        }
+        let mut pending = crate::lock_or_recover(&self.pending);
+        let Some(still) = pending.as_mut() else {
+            return false;
+        };
+        if still.frame.is_some() || !still.wanted.is_some_and(|wanted| wanted.matches(frame)) {
+            return false;
+        }
+        still.frame = Some(frame.to_vec());
+        self.arrived.notify_all();
+        true
+    }
+
+    /// Negotiate, trigger and receive a still image
+    ///
+    /// Blocks for up to [`STILL_TIMEOUT`] while waiting for the still.
+    ///
+    /// # Errors
+    ///
+    /// Returns `StillError` if the camera can't take the requested still, no
+    /// camera is attached, another capture is running, a transfer fails or
+    /// the still does not arrive in time.
+    pub fn capture(&self, request: &StillRequest) -> Result<StillImage> {
+        match request.method {
+            StillMethod::None => return Err(StillError::Unsupported),
+            StillMethod::VideoFrame => return Err(StillError::VideoFrameStills),
+            StillMethod::Stream | StillMethod::BulkEndpoint => {}
+        }
+        let format = &request.format;
+        let still = format
+            .still_frame
+            .as_ref()
+            .filter(|still| !still.sizes.is_empty())
+            .ok_or(StillError::NoStillSizes(format.index))?;
+        let (frame_index, (width, height)) = select_size(format.index, &still.sizes, request.size)?;
+
+        let mjpeg = format.kind == FormatKind::Mjpeg;
+        let wanted = if mjpeg {
+            StillMatch::Jpeg(width, height)
+        } else {
+            if (width, height) != request.video_size {
+                return Err(StillError::SizeMismatch {
+                    still: (width, height),
+                    video: request.video_size,
+                });
+            }
+            let bits = u32::from(format.bits_per_pixel.unwrap_or(16));
+            StillMatch::Raw((u32::from(width) * u32::from(height) * bits / 8) as usize)
+        };
+
+        let (transport, interface) = {
+            let camera = crate::lock_or_recover(&self.camera);
+            let camera = camera.as_ref().ok_or(StillError::NoCamera)?;
+            (Arc::clone(&camera.transport), camera.interface)
+        };
+        let requests = StillRequests {
+            transport: transport.as_ref(),
+            interface,
+        };
+
+        let probe = StillProbe {
+            format_index: format.index,
+            frame_index,
+            compression_index: u8::from(!still.compressions.is_empty()),
+            max_frame_size: 0,
+            max_payload_size: 0,
+        };
+        let committed = requests.negotiate(probe)?;
+        log::info!(
+            "Still {}x{} negotiated (format {}, size {}, up to {} bytes)",
+            width,
+            height,
+            committed.format_index,
+            committed.frame_index,
+            committed.max_frame_size
+        );
+
+        let bulk = request.method == StillMethod::BulkEndpoint;
+        self.begin((!bulk).then_some(wanted))?;
+        let frame = if bulk {
+            let trigger = requests.trigger(StillTrigger::TransmitBulk);
+            trigger.and_then(|()| read_bulk_still(transport.as_ref(), still.endpoint))
+        } else {
+            requests
+                .trigger(StillTrigger::Transmit)
+                .and_then(|()| self.wait())
+        };
+        *crate::lock_or_recover(&self.pending) = None;
+
+        if frame.is_err() {
+            // Don't leave the camera waiting to send a still nobody reads
+            let _ = requests.trigger(StillTrigger::Abort);
+        }
+        Ok(StillImage {
+            width,
+            height,
+            data: frame?,
+        })
+    }
+
+    /// Mark a capture as in progress
+    fn begin(&self, wanted: Option<StillMatch>) -> Result<()> {
+        let mut pending = crate::lock_or_recover(&self.pending);
+        if pending.is_some() {
+            return Err(StillError::Busy);
+        }
+        *pending = Some(PendingStill {
+            wanted,
+            frame: None,
+        });
+        Ok(())
+    }
+
+    /// Wait for `offer` to take the still
+    fn wait(&self) -> Result<Vec<u8>> {
+        let deadline = Instant::now() + STILL_TIMEOUT;
+        let mut pending = crate::lock_or_recover(&self.pending);
+        loop {
+            if let Some(frame) = pending.as_mut().and_then(|p| p.frame.take()) {
+                return Ok(frame);
+            }
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            if remaining.is_zero() {
+                return Err(StillError::Timeout);
+            }
+            pending = self
+                .arrived
+                .wait_timeout(pending, remaining)
+                .unwrap_or_else(std::sync::PoisonError::into_inner)
+                .0;
+        }
+    }
+}
+
+/// Still size requested as a separate width and height
+///
+/// # Errors
+///
+/// Returns `StillError::PartialSize` if only one of them is given.
+pub fn requested_size(width: Option<u16>, height: Option<u16>) -> Result<Option<(u16, u16)>> {
+    match (width, height) {
+        (Some(width), Some(height)) => Ok(Some((width, height))),
+        (None, None) => Ok(None),
+        _ => Err(StillError::PartialSize),
+    }
+}
+
+/// Still size to take: the requested one, else the largest
+///
+/// Returns the size with its 1-based index.
+fn select_size(
+    format_index: u8,
+    sizes: &[(u16, u16)],
+    requested: Option<(u16, u16)>,
+) -> Result<(u8, (u16, u16))> {
+    let found = match requested {
+        Some(size) => sizes.iter().position(|&s| s == size).ok_or({
+            StillError::UnknownSize {
+                format_index,
+                width: size.0,
+                height: size.1,
+            }
+        })?,
+        None => (0..sizes.len())
+            .max_by_key(|&i| u32::from(sizes[i].0) * u32::from(sizes[i].1))
+            .ok_or(StillError::NoStillSizes(format_index))?,
+    };
+    Ok(((found + 1) as u8, sizes[found]))
+}
+
+/// Still control requests to one streaming interface
+struct StillRequests<'a> {
+    transport: &'a dyn StillTransport,
+    interface: u8,
+}
+
+impl StillRequests<'_> {
+    /// Probe `probe`, then commit what the camera answered
+    fn negotiate(&self, probe: StillProbe) -> Result<StillProbe> {
+        self.set(VS_STILL_PROBE_CONTROL, &mut probe.to_bytes())?;
+        let mut answer = [0u8; STILL_PROBE_LEN];
+        self.transport.control_transfer(
+            REQUEST_TYPE_IN,
+            GET_CUR,
+            u16::from(VS_STILL_PROBE_CONTROL) << 8,
+            u16::from(self.interface),
+            &mut answer,
+            uvc_controls::CONTROL_TIMEOUT_MS,
+        )?;
+        let committed = StillProbe::parse(&answer).unwrap_or(probe);
+        self.set(VS_STILL_COMMIT_CONTROL, &mut committed.to_bytes())?;
+        Ok(committed)
+    }
+
+    /// Set the still image trigger
+    fn trigger(&self, trigger: StillTrigger) -> Result<()> {
+        self.set(VS_STILL_IMAGE_TRIGGER_CONTROL, &mut [trigger as u8])
+    }
+
+    fn set(&self, selector: u8, data: &mut [u8]) -> Result<()> {
+        self.transport.control_transfer(
+            REQUEST_TYPE_OUT,
+            SET_CUR,
+            u16::from(selector) << 8,
+            u16::from(self.interface),
+            data,
+            uvc_controls::CONTROL_TIMEOUT_MS,
+        )?;
+        Ok(())
+    }
+}
+
+/// Read one still from a method 3 bulk endpoint
+///
+/// Still payloads carry UVC payload headers; the still ends at the payload
+/// with the end-of-frame bit.
+fn read_bulk_still(transport: &dyn StillTransport, endpoint: u8) -> Result<Vec<u8>> {
+    let deadline = Instant::now() + STILL_TIMEOUT;
+    let mut buffer = vec![0u8; BULK_READ_SIZE];
+    let mut still = Vec::new();
+    loop {
+        let remaining = deadline.saturating_duration_since(Instant::now());
+        if remaining.is_zero() {
+            return Err(StillError::Timeout);
+        }
+        let timeout_ms = u32::try_from(remaining.as_millis()).unwrap_or(u32::MAX);
+        let read = transport.read_bulk(endpoint, &mut buffer, timeout_ms)?;
+        let payload = parse_uvc_payload(&buffer[..read]);
+        if payload.error {
+            log::warn!(
+                "Still payload with error bit, discarding {} bytes",
+                still.len()
+            );
+            still.clear();
+            continue;
+        }
+        still.extend_from_slice(payload.payload);
+        if payload.end_of_frame && !still.is_empty() {
+            return Ok(still);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::uvc_descriptors::StillImageFrame;
+    use std::collections::VecDeque;
+
+    /// Records control requests and serves bulk reads from a queue
+    #[derive(Default)]
+    struct MockTransport {
+        requests: Mutex<Vec<(u8, u8, Vec<u8>)>>,
+        bulk: Mutex<VecDeque<Vec<u8>>>,
+    }
+
+    impl ControlTransport for MockTransport {
+        fn control_transfer(
+            &self,
+            _request_type: u8,
+            request: u8,
+            value: u16,
+            _index: u16,
+            data: &mut [u8],
+            _timeout_ms: u32,
+        ) -> uvc_controls::Result<usize> {
+            let selector = (value >> 8) as u8;
+            if request == GET_CUR {
+                // The camera fills in the maximum sizes
+                let mut probe = StillProbe::parse(&self.last_probe()).unwrap();
+                probe.max_frame_size = 1_000_000;
+                data.copy_from_slice(&probe.to_bytes());
+            }
+            self.requests
+                .lock()
+                .unwrap()
+                .push((request, selector, data.to_vec()));
+            Ok(data.len())
+        }
+    }
+
+    impl StillTransport for MockTransport {
+        fn read_bulk(
+            &self,
+            _endpoint: u8,
+            data: &mut [u8],
+            _timeout_ms: u32,
+        ) -> uvc_controls::Result<usize> {
+            let packet = self
+                .bulk
+                .lock()
+                .unwrap()
+                .pop_front()
+                .ok_or_else(|| UvcControlError::Transfer("timeout".to_string()))?;
+            data[..packet.len()].copy_from_slice(&packet);
+            Ok(packet.len())
+        }
+    }
+
+    impl MockTransport {
+        fn last_probe(&self) -> Vec<u8> {
+            let requests = self.requests.lock().unwrap();
+            let (_, _, data) = requests
+                .iter()
+                .rev()
+                .find(|(request, selector, _)| {
+                    *request == SET_CUR && *selector == VS_STILL_PROBE_CONTROL
+                })
+                .unwrap();
+            data.clone()
+        }
+
+        fn triggers(&self) -> Vec<u8> {
+            let requests = self.requests.lock().unwrap();
+            requests
+                .iter()
+                .filter(|(_, selector, _)| *selector == VS_STILL_IMAGE_TRIGGER_CONTROL)
+                .map(|(_, _, data)| data[0])
+                .collect()
+        }
+    }
+
+    fn request(method: StillMethod, kind: FormatKind, endpoint: u8) -> StillRequest {
+        StillRequest {
+            method,
+            format: FormatDescriptor {
+                index: 2,
+                kind,
+                guid: None,
+                bits_per_pixel: (kind == FormatKind::Uncompressed).then_some(16),
+                default_frame_index: 1,
+                frames: Vec::new(),
+                still_frame: Some(StillImageFrame {
+                    endpoint,
+                    sizes: vec![(640, 480), (2592, 1944), (1280, 720)],
+                    compressions: vec![1],
+                }),
+            },
+            video_size: (640, 480),
+            size: None,
+        }
+    }
+
+    /// Smallest JPEG header `jpeg_dimensions` reads: SOI and an SOF0 segment
+    fn jpeg(width: u16, height: u16) -> Vec<u8> {
+        let mut jpeg = vec![0xFF, 0xD8, 0xFF, 0xC0, 0x00, 0x11, 0x08];
+        jpeg.extend_from_slice(&height.to_be_bytes());
+        jpeg.extend_from_slice(&width.to_be_bytes());
+        jpeg.extend_from_slice(&[3, 1, 0x22, 0, 2, 0x11, 1, 3, 0x11, 1, 0xFF, 0xD9]);
+        jpeg
+    }
+
+    #[test]
+    fn test_probe_round_trip() {
+        let probe = StillProbe {
+            format_index: 2,
+            frame_index: 3,
+            compression_index: 1,
+            max_frame_size: 5_038_848,
+            max_payload_size: 3072,
+        };
+        assert_eq!(StillProbe::parse(&probe.to_bytes()), Some(probe));
+        assert_eq!(StillProbe::parse(&[0; 4]), None);
+    }
+
+    #[test]
+    fn test_select_size() {
+        let sizes = [(640, 480), (2592, 1944), (1280, 720)];
+        assert_eq!(select_size(1, &sizes, None).unwrap(), (2, (2592, 1944)));
+        assert_eq!(
+            select_size(1, &sizes, Some((1280, 720))).unwrap(),
+            (3, (1280, 720))
+        );
+        assert!(matches!(
+            select_size(1, &sizes, Some((800, 600))),
+            Err(StillError::UnknownSize { .. })
+        ));
+    }
+
+    #[test]
+    fn test_requested_size_needs_both_dimensions() {
+        assert_eq!(requested_size(None, None).unwrap(), None);
+        assert_eq!(
+            requested_size(Some(1280), Some(720)).unwrap(),
+            Some((1280, 720))
+        );
+        assert!(matches!(
+            requested_size(Some(1280), None),
+            Err(StillError::PartialSize)
+        ));
+        assert!(matches!(
+            requested_size(None, Some(720)),
+            Err(StillError::PartialSize)
+        ));
+    }
+
+    #[test]
+    fn test_stream_still_is_taken_from_the_stream() {
+        let transport = Arc::new(MockTransport::default());
+        let still = Arc::new(StillCapture::new());
+        let _guard = still.attach(Arc::clone(&transport) as Arc<dyn StillTransport>, 1);
+
+        let taker = {
+            let still = Arc::clone(&still);
+            std::thread::spawn(move || {
+                still.capture(&request(StillMethod::Stream, FormatKind::Mjpeg, 0))
+            })
+        };
+        // Video frames pass through until the still arrives, even one of
+        // the still's size without the still image bit
+        let still_jpeg = jpeg(2592, 1944);
+        let mut taken = false;
+        while !taken {
+            assert!(!still.offer(&jpeg(640, 480), false));
+            assert!(!still.offer(&still_jpeg, false));
+            taken = still.offer(&still_jpeg, true);
+            std::thread::yield_now();
+        }
+        let image = taker.join().unwrap().unwrap();
+        assert_eq!((image.width, image.height), (2592, 1944));
+        assert_eq!(image.data, still_jpeg);
+
+        // Largest size, first compression, committed as the camera answered
+        let probe = StillProbe::parse(&transport.last_probe()).unwrap();
+        assert_eq!((probe.format_index, probe.frame_index), (2, 2));
+        assert_eq!(probe.compression_index, 1);
+        assert_eq!(transport.triggers(), vec![StillTrigger::Transmit as u8]);
+        // Nothing is pending any more
+        assert!(!still.offer(&still_jpeg, true));
+    }
+
+    #[test]
+    fn test_bulk_still_is_read_from_its_endpoint() {
+        let transport = Arc::new(MockTransport::default());
+        transport
+            .bulk
+            .lock()
+            .unwrap()
+            .extend([vec![0x02, 0x80, 1, 2, 3], vec![0x02, 0x82, 4, 5]]);
+        let still = Arc::new(StillCapture::new());
+        let _guard = still.attach(Arc::clone(&transport) as Arc<dyn StillTransport>, 1);
+
+        let image = still
+            .capture(&request(StillMethod::BulkEndpoint, FormatKind::Mjpeg, 0x83))
+            .unwrap();
+        assert_eq!(image.data, vec![1, 2, 3, 4, 5]);
+        assert_eq!(transport.triggers(), vec![StillTrigger::TransmitBulk as u8]);
+    }
+
+    #[test]
+    fn test_failed_still_aborts_the_trigger() {
+        let transport = Arc::new(MockTransport::default());
+        let still = Arc::new(StillCapture::new());
+        let _guard = still.attach(Arc::clone(&transport) as Arc<dyn StillTransport>, 1);
+
+        // The bulk endpoint never delivers
+        let result = still.capture(&request(StillMethod::BulkEndpoint, FormatKind::Mjpeg, 0x83));
+        assert!(matches!(result, Err(StillError::Transfer(_))));
+        assert_eq!(
+            transport.triggers(),
+            vec![StillTrigger::TransmitBulk as u8, StillTrigger::Abort as u8]
+        );
+    }
+
+    #[test]
+    fn test_unsupported_requests() {
+        let still = Arc::new(StillCapture::new());
+        let mjpeg = |method| request(method, FormatKind::Mjpeg, 0);
+        assert!(matches!(
+            still.capture(&mjpeg(StillMethod::None)),
+            Err(StillError::Unsupported)
+        ));
+        assert!(matches!(
+            still.capture(&mjpeg(StillMethod::VideoFrame)),
+            Err(StillError::VideoFrameStills)
+        ));
+        assert!(matches!(
+            still.capture(&mjpeg(StillMethod::Stream)),
+            Err(StillError::NoCamera)
+        ));
+
+        let mut no_sizes = mjpeg(StillMethod::Stream);
+        no_sizes.format.still_frame = None;
+        assert!(matches!(
+            still.capture(&no_sizes),
+            Err(StillError::NoStillSizes(2))
+        ));
+
+        // Uncompressed stills bigger than the video can't be picked out
+        let raw = request(StillMethod::Stream, FormatKind::Uncompressed, 0);
+        assert!(matches!(
+            still.capture(&raw),
+            Err(StillError::SizeMismatch {
+                still: (2592, 1944),
+                video: (640, 480)
+            })
+        ));
+    }
+
+    #[test]
+    fn test_detached_camera_is_not_used() {
+        let still = Arc::new(StillCapture::new());
+        let guard = still.attach(Arc::new(MockTransport::default()), 1);
+        drop(guard);
+        assert!(matches!(
+            still.capture(&request(StillMethod::Stream, FormatKind::Mjpeg, 0)),
+            Err(StillError::NoCamera)
+        ));
+    }
+}
@@ -0,0 +1,423 @@
+//! Sandboxed file access with an audit log
+//!
+//! Everything the app writes (frame dumps, packet captures, recordings,
+//! session manifests) goes through a [`Storage`] rooted at the output
+//! directory: the app cache directory, or `CLEANSCOPE_OUTPUT_DIR` if set.
+//! Paths are checked lexically and may not leave the root, which keeps
+//! writes inside app-specific storage as required by Android scoped storage.
+//!
+//! Every file created, written or removed is appended to [`AUDIT_LOG_NAME`]
+//! in the output root as one JSON object per line, so users can see exactly
+//! which files the app produced. The audit log cannot be written through
+//! the facade itself.
+//!
+//! The preflight storage probe is the one deliberate exception: it writes and
+//! removes a marker file on every start and is not worth auditing.
+
+use serde::{Deserialize, Serialize};
+use std::fs::{File, OpenOptions};
+use std::io::{self, BufRead, Write};
+use std::path::{Component, Path, PathBuf};
+
+/// Name of the audit log in the output root
+pub const AUDIT_LOG_NAME: &str = "file_audit.log";
+
+/// Kind of audited file operation
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AuditOp {
+    /// File created for streaming writes (size unknown when logged)
+    Create,
+    /// File written in one go
+    Write,
+    /// File cut down (or extended) to a length
+    Truncate,
+    /// File or directory removed
+    Remove,
+}
+
+/// One line of the audit log
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AuditEntry {
+    /// Milliseconds since the Unix epoch
+    pub timestamp_ms: u64,
+    /// Operation performed
+    pub op: AuditOp,
+    /// Path relative to the output root
+    pub path: String,
+    /// Bytes written, if known
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub bytes: Option<u64>,
+}
+
+/// File access confined to a directory inside the output root
+#[derive(Debug, Clone)]
+pub struct Storage {
+    /// Output root, holding the audit log
+    base: PathBuf,
+    /// Directory this storage writes to (`base` or a directory inside it)
+    root: PathBuf,
+}
+
+impl Storage {
+    /// Storage rooted at the output directory `base`
+    pub fn new(base: impl Into<PathBuf>) -> Self {
+        let base = base.into();
+        Self {
+            root: base.clone(),
+            base,
+        }
+    }
+
+    /// Directory this storage writes to
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// Storage confined to the directory `path` inside this one
+    ///
+    /// Audit entries still go to the output root's log.
+    ///
+    /// # Errors
+    ///
+    /// Returns `PermissionDenied` if `path` leaves this storage.
+    pub fn subdir(&self, path: impl AsRef<Path>) -> io::Result<Self> {
+        Ok(Self {
+            base: self.base.clone(),
+            root: self.resolve(path)?,
+        })
+    }
+
+    /// Absolute path of `path`, which is relative to [`Storage::root`] or
+    /// absolute and inside it
+    ///
+    /// # Errors
+    ///
+    /// Returns `PermissionDenied` if the path contains `..`, lies outside the
+    /// root, or names the audit log.
+    pub fn resolve(&self, path: impl AsRef<Path>) -> io::Result<PathBuf> {
+        let path = path.as_ref();
+        let relative = if path.is_absolute() {
+            path.strip_prefix(&self.root)
+                .map_err(|_| outside_root(path))?
+        } else {
+            path
+        };
+
+        if relative
+            .components()
+            .any(|c| !matches!(c, Component::Normal(_) | Component::CurDir))
+        {
+            return Err(outside_root(path));
+        }
+
+        let resolved = self.root.join(relative);
+        if resolved == self.base.join(AUDIT_LOG_NAME) {
+            return Err(io::Error::new(
+                io::ErrorKind::PermissionDenied,
+                "the audit log is append-only",
+            ));
+        }
+        Ok(resolved)
+    }
+
+    /// Create a directory (and its parents) inside the root
+    ///
+    /// # Errors
+    ///
+    /// Returns `PermissionDenied` for paths outside the root, or the I/O error.
+    pub fn create_dir_all(&self, path: impl AsRef<Path>) -> io::Result<PathBuf> {
+        let path = self.resolve(path)?;
+        std::fs::create_dir_all(&path)?;
+        Ok(path)
+    }
+
+    /// Create a new directory inside the root, creating parent directories
+    ///
+    /// # Errors
+    ///
+    /// Returns `AlreadyExists` if the directory exists, `PermissionDenied` for
+    /// paths outside the root, or the I/O error.
+    pub fn create_dir(&self, path: impl AsRef<Path>) -> io::Result<PathBuf> {
+        let path = self.resolve(path)?;
+        create_parent(&path)?;
+        std::fs::create_dir(&path)?;
+        Ok(path)
+    }
+
+    /// Create (or truncate) a file for writing, creating parent directories
+    ///
+    /// # Errors
+    ///
+    /// Returns `PermissionDenied` for paths outside the root, or the I/O error.
+    pub fn create(&self, path: impl AsRef<Path>) -> io::Result<(PathBuf, File)> {
+        let path = self.resolve(path)?;
+        create_parent(&path)?;
+        let file = File::create(&path)?;
+        self.audit(AuditOp::Create, &path, None);
+        Ok((path, file))
+    }
+
+    /// Write `contents` to a file, creating parent directories
+    ///
+    /// # Errors
+    ///
+    /// Returns `PermissionDenied` for paths outside the root, or the I/O error.
+    pub fn write(&self, path: impl AsRef<Path>, contents: impl AsRef<[u8]>) -> io::Result<PathBuf> {
+        let path = self.resolve(path)?;
+        let contents = contents.as_ref();
+        create_parent(&path)?;
+        std::fs::write(&path, contents)?;
+        self.audit(AuditOp::Write, &path, Some(contents.len() as u64));
+        Ok(path)
+    }
+
+    /// Replace a file's contents atomically, creating parent directories
+    ///
+    /// The contents are written to a temporary file next to it, which is
+    /// then renamed over it, so a crash leaves either the old or the new
+    /// contents, never a truncated file.
+    ///
+    /// # Errors
+    ///
+    /// Returns `PermissionDenied` for paths outside the root, or the I/O error.
+    pub fn replace(
+        &self,
+        path: impl AsRef<Path>,
+        contents: impl AsRef<[u8]>,
+    ) -> io::Result<PathBuf> {
+        let path = self.resolve(path)?;
+        let contents = contents.as_ref();
+        let Some(name) = path.file_name() else {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("{} is not a file path", path.display()),
+            ));
+        };
+        let mut temp_name = name.to_os_string();
+        temp_name.push(".tmp");
+        let temp = path.with_file_name(temp_name);
+
+        create_parent(&path)?;
+        let written = File::create(&temp).and_then(|mut file| {
+            file.write_all(contents)?;
+            file.sync_all()
+        });
+        if let Err(e) = written.and_then(|()| std::fs::rename(&temp, &path)) {
+            let _ = std::fs::remove_file(&temp);
+            return Err(e);
+        }
+        self.audit(AuditOp::Write, &path, Some(contents.len() as u64));
+        Ok(path)
+    }
+
+    /// Set the length of an existing file, cutting off anything past `len`
+    ///
+    /// # Errors
+    ///
+    /// Returns `PermissionDenied` for paths outside the root, or the I/O error.
+    pub fn truncate(&self, path: impl AsRef<Path>, len: u64) -> io::Result<PathBuf> {
+        let path = self.resolve(path)?;
+        OpenOptions::new().write(true).open(&path)?.set_len(len)?;
+        self.audit(AuditOp::Truncate, &path, Some(len));
+        Ok(path)
+    }
+
+    /// Remove a file
+    ///
+    /// # Errors
+    ///
+    /// Returns `PermissionDenied` for paths outside the root, or the I/O error.
+    pub fn remove_file(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        let path = self.resolve(path)?;
+        std::fs::remove_file(&path)?;
+        self.audit(AuditOp::Remove, &path, None);
+        Ok(())
+    }
+
+    /// Remove a directory and everything in it
+    ///
+    /// # Errors
+    ///
+    /// Returns `PermissionDenied` for paths outside the root or containing the
+    /// output root itself, or the I/O error.
+    pub fn remove_dir_all(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        let path = self.resolve(path)?;
+        if self.base.starts_with(&path) {
+            return Err(io::Error::new(
+                io::ErrorKind::PermissionDenied,
+                "cannot remove the output directory",
+            ));
+        }
+        std::fs::remove_dir_all(&path)?;
+        self.audit(AuditOp::Remove, &path, None);
+        Ok(())
+    }
+
+    /// Read the audit log, skipping lines that cannot be parsed
+    ///
+    /// # Errors
+    ///
+    /// Returns the I/O error if the log exists but cannot be read.
+    pub fn audit_entries(&self) -> io::Result<Vec<AuditEntry>> {
+        let file = match File::open(self.base.join(AUDIT_LOG_NAME)) {
+            Ok(file) => file,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(e),
+        };
+        let mut entries = Vec::new();
+        for line in io::BufReader::new(file).lines() {
+            match serde_json::from_str(&line?) {
+                Ok(entry) => entries.push(entry),
+                Err(e) => log::warn!("Skipping malformed audit log line: {}", e),
+            }
+        }
+        Ok(entries)
+    }
+
+    /// Append an entry to the audit log
+    ///
+    /// Failures are logged rather than failing the operation, which has
+    /// already happened.
+    fn audit(&self, op: AuditOp, path: &Path, bytes: Option<u64>) {
+        let entry = AuditEntry {
+            timestamp_ms: std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .map(|d| d.as_millis() as u64)
+                .unwrap_or(0),
+            op,
+            path: path
+                .strip_prefix(&self.base)
+                .unwrap_or(path)
+                .to_string_lossy()
+                .to_string(),
+            bytes,
+        };
+
+        let result = serde_json::to_string(&entry)
+            .map_err(io::Error::from)
+            .and_then(|line| {
+                std::fs::create_dir_all(&self.base)?;
+                let mut log = OpenOptions::new()
+                    .create(true)
+                    .append(true)
+                    .open(self.base.join(AUDIT_LOG_NAME))?;
+                // One write per line so concurrent appends do not interleave
+                log.write_all(format!("{}\n", line).as_bytes())
+            });
+        if let Err(e) = result {
+            log::error!("Could not append to audit log: {}", e);
+        }
+    }
+}
+
+/// Create the parent directory of `path`
+fn create_parent(path: &Path) -> io::Result<()> {
+    match path.parent() {
+        Some(parent) => std::fs::create_dir_all(parent),
+        None => Ok(()),
+    }
+}
+
+/// Error for a path that leaves the storage root
+fn outside_root(path: &Path) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::PermissionDenied,
+        format!("{} is outside the output directory", path.display()),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_resolve_rejects_paths_outside_root() {
+        let storage = Storage::new("/data/cache");
+        assert_eq!(
+            storage.resolve("frame.jpg").unwrap(),
+            Path::new("/data/cache/frame.jpg")
+        );
+        assert_eq!(
+            storage.resolve("/data/cache/rec/index.json").unwrap(),
+            Path::new("/data/cache/rec/index.json")
+        );
+
+        for path in [
+            "../escape",
+            "rec/../../escape",
+            "/data/other/file",
+            "/etc/passwd",
+        ] {
+            let err = storage.resolve(path).unwrap_err();
+            assert_eq!(err.kind(), io::ErrorKind::PermissionDenied, "{}", path);
+        }
+        assert!(storage.subdir("..").is_err());
+    }
+
+    #[test]
+    fn test_audit_log_and_root_are_protected() {
+        let dir = tempfile::tempdir().unwrap();
+        let storage = Storage::new(dir.path());
+        assert!(storage.write(AUDIT_LOG_NAME, "forged").is_err());
+        assert!(storage.remove_dir_all(".").is_err());
+    }
+
+    #[test]
+    fn test_operations_are_audited() {
+        let dir = tempfile::tempdir().unwrap();
+        let storage = Storage::new(dir.path());
+
+        storage.write("frame.jpg", [0xFF, 0xD8]).unwrap();
+        let rec = storage.subdir("recording_1").unwrap();
+        let (path, mut file) = rec.create("frames.bin").unwrap();
+        file.write_all(b"data").unwrap();
+        assert_eq!(path, dir.path().join("recording_1/frames.bin"));
+        rec.truncate("frames.bin", 2).unwrap();
+        assert_eq!(std::fs::read(&path).unwrap(), b"da");
+        storage.remove_dir_all("recording_1").unwrap();
+
+        let entries = storage.audit_entries().unwrap();
+        let ops: Vec<_> = entries
+            .iter()
+            .map(|e| (e.op, e.path.as_str(), e.bytes))
+            .collect();
+        assert_eq!(
+            ops,
+            vec![
+                (AuditOp::Write, "frame.jpg", Some(2)),
+                (AuditOp::Create, "recording_1/frames.bin", None),
+                (AuditOp::Truncate, "recording_1/frames.bin", Some(2)),
+                (AuditOp::Remove, "recording_1", None),
+            ]
+        );
+        assert!(!dir.path().join("recording_1").exists());
+    }
+
+    #[test]
+    fn test_replace_swaps_in_the_new_contents() {
+        let dir = tempfile::tempdir().unwrap();
+        let storage = Storage::new(dir.path());
+        storage.replace("config/settings.json", "{}").unwrap();
+        storage
+            .replace("config/settings.json", r#"{"rotation":90}"#)
+            .unwrap();
+
+        let config = dir.path().join("config");
+        assert_eq!(
+            std::fs::read_to_string(config.join("settings.json")).unwrap(),
+            r#"{"rotation":90}"#
+        );
+        // Only the file itself is left behind
+        assert_eq!(std::fs::read_dir(&config).unwrap().count(), 1);
+        assert!(storage.replace(AUDIT_LOG_NAME, "forged").is_err());
+    }
+
+    #[test]
+    fn test_audit_log_is_appended_across_instances() {
+        let dir = tempfile::tempdir().unwrap();
+        Storage::new(dir.path()).write("a.json", "{}").unwrap();
+        Storage::new(dir.path()).write("b.json", "{}").unwrap();
+        assert_eq!(Storage::new(dir.path()).audit_entries().unwrap().len(), 2);
+    }
+}
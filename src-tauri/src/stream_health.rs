@@ -0,0 +1,234 @@
+//! Frame delivery statistics for the connection health indicator
+//!
+//! The USB handler reports every stored frame and every streaming error here.
+//! [`StreamHealth::stats`] turns that into a small snapshot (frame age, frame
+//! rate, error counts) that is attached to `usb-device-event` and emitted
+//! periodically as `usb-health`, so the UI can tell a healthy stream from one
+//! that is connected but stalled.
+
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// Window over which the current frame rate is measured
+pub const FPS_WINDOW: Duration = Duration::from_secs(2);
+
+/// Interval between periodic `usb-health` events
+pub const HEALTH_EVENT_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Snapshot of stream health, sent to the frontend
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct HealthStats {
+    /// Milliseconds since the last frame was delivered (`None` before the first frame)
+    pub last_frame_age_ms: Option<u64>,
+    /// Frames per second over the last [`FPS_WINDOW`]
+    pub fps: f32,
+    /// Frames delivered since the camera (re)connected
+    pub frames: u64,
+    /// USB transfer errors since startup
+    pub transfer_errors: u32,
+    /// Times no frame arrived within the receive timeout since startup
+    pub stalls: u32,
+    /// Successful reconnections since startup
+    pub reconnects: u32,
+    /// Current run of consecutive bulk transfer timeouts
+    pub timeout_streak: u32,
+}
+
+/// Connection state and counters behind [`StreamHealth`]
+#[derive(Debug, Default)]
+struct HealthState {
+    connected: bool,
+    info: Option<String>,
+    last_frame: Option<Instant>,
+    recent_frames: VecDeque<Instant>,
+    frames: u64,
+    transfer_errors: u32,
+    stalls: u32,
+    reconnects: u32,
+    timeout_streak: u32,
+}
+
+impl HealthState {
+    /// Reset the per-connection frame statistics
+    fn reset_frames(&mut self) {
+        self.last_frame = None;
+        self.recent_frames.clear();
+        self.frames = 0;
+        self.timeout_streak = 0;
+    }
+
+    /// Drop frame times that fell out of the FPS window
+    fn prune(&mut self, now: Instant) {
+        while let Some(&oldest) = self.recent_frames.front() {
+            if now.saturating_duration_since(oldest) <= FPS_WINDOW {
+                break;
+            }
+            self.recent_frames.pop_front();
+        }
+    }
+}
+
+/// Thread-safe frame delivery tracker shared by the USB handler and commands
+#[derive(Debug, Default)]
+pub struct StreamHealth {
+    state: Mutex<HealthState>,
+}
+
+impl StreamHealth {
+    /// Create a tracker for a disconnected camera
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, HealthState> {
+        self.state
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    /// Update the connection state; connecting resets the per-connection frame statistics
+    pub fn set_connection(&self, connected: bool, info: Option<String>) {
+        let mut state = self.lock();
+        if connected && !state.connected {
+            state.reset_frames();
+        }
+        state.connected = connected;
+        state.info = info;
+    }
+
+    /// Whether a camera is connected, and its description
+    pub fn connection(&self) -> (bool, Option<String>) {
+        let state = self.lock();
+        (state.connected, state.info.clone())
+    }
+
+    /// Record a delivered frame
+    pub fn record_frame(&self) {
+        self.record_frame_at(Instant::now());
+    }
+
+    /// Record a frame delivered at `now`
+    pub fn record_frame_at(&self, now: Instant) {
+        let mut state = self.lock();
+        state.last_frame = Some(now);
+        state.frames += 1;
+        state.recent_frames.push_back(now);
+        state.prune(now);
+    }
+
+    /// Record a USB transfer error
+    pub fn record_transfer_error(&self) {
+        self.lock().transfer_errors += 1;
+    }
+
+    /// Record that no frame arrived within the receive timeout
+    pub fn record_stall(&self) {
+        self.lock().stalls += 1;
+    }
+
+    /// Update the current run of consecutive transfer timeouts
+    pub fn set_timeout_streak(&self, streak: u32) {
+        self.lock().timeout_streak = streak;
+    }
+
+    /// Record a successful reconnection, which starts a new connection
+    pub fn record_reconnect(&self) {
+        let mut state = self.lock();
+        state.reconnects += 1;
+        state.reset_frames();
+    }
+
+    /// Current statistics
+    pub fn stats(&self) -> HealthStats {
+        self.stats_at(Instant::now())
+    }
+
+    /// Statistics as of `now`
+    pub fn stats_at(&self, now: Instant) -> HealthStats {
+        let mut state = self.lock();
+        state.prune(now);
+
+        let fps = match (state.recent_frames.front(), state.recent_frames.back()) {
+            (Some(&first), Some(&last)) if state.recent_frames.len() >= 2 && last > first => {
+                (state.recent_frames.len() - 1) as f32 / (last - first).as_secs_f32()
+            }
+            _ => 0.0,
+        };
+
+        HealthStats {
+            last_frame_age_ms: state
+                .last_frame
+                .map(|t| now.saturating_duration_since(t).as_millis() as u64),
+            fps,
+            frames: state.frames,
+            transfer_errors: state.transfer_errors,
+            stalls: state.stalls,
+            reconnects: state.reconnects,
+            timeout_streak: state.timeout_streak,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_no_frames() {
+        let health = StreamHealth::new();
+        let stats = health.stats();
+        assert_eq!(stats.last_frame_age_ms, None);
+        assert_eq!(stats.fps, 0.0);
+        assert_eq!(stats.frames, 0);
+    }
+
+    #[test]
+    fn test_fps_and_frame_age() {
+        let health = StreamHealth::new();
+        let start = Instant::now();
+        for i in 0..31 {
+            health.record_frame_at(start + Duration::from_millis(i * 33));
+        }
+        let last = start + Duration::from_millis(30 * 33);
+
+        let stats = health.stats_at(last + Duration::from_millis(100));
+        assert_eq!(stats.frames, 31);
+        assert_eq!(stats.last_frame_age_ms, Some(100));
+        assert!((stats.fps - 30.3).abs() < 0.1, "fps = {}", stats.fps);
+    }
+
+    #[test]
+    fn test_fps_drops_to_zero_when_frames_stop() {
+        let health = StreamHealth::new();
+        let start = Instant::now();
+        health.record_frame_at(start);
+        health.record_frame_at(start + Duration::from_millis(33));
+
+        let stats = health.stats_at(start + FPS_WINDOW + Duration::from_secs(1));
+        assert_eq!(stats.fps, 0.0);
+        assert_eq!(stats.frames, 2);
+        assert_eq!(stats.last_frame_age_ms, Some(2967));
+    }
+
+    #[test]
+    fn test_connect_resets_frames_but_keeps_error_counts() {
+        let health = StreamHealth::new();
+        health.set_connection(true, Some("Camera".to_string()));
+        health.record_frame();
+        health.record_transfer_error();
+        health.record_stall();
+        health.set_connection(false, None);
+        health.set_connection(true, Some("Camera".to_string()));
+        health.record_reconnect();
+
+        let stats = health.stats();
+        assert_eq!(stats.frames, 0);
+        assert_eq!(stats.last_frame_age_ms, None);
+        assert_eq!(stats.transfer_errors, 1);
+        assert_eq!(stats.stalls, 1);
+        assert_eq!(stats.reconnects, 1);
+        assert_eq!(health.connection(), (true, Some("Camera".to_string())));
+    }
+}
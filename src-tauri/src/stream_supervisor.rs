@@ -0,0 +1,215 @@
+//! Automatic re-probe after a run of corrupt frames
+//!
+//! A stream that lost sync with the camera (a dropped payload shifting every
+//! following frame, a stride the camera changed) keeps producing frames that
+//! fail validation: banding, shearing, sizes off by a row. Dropping them
+//! (see `set_validation_level`) only blanks the preview. The
+//! [`StreamSupervisor`] watches the validation result of every uncompressed
+//! frame and, after [`StreamSupervisor::threshold`] rejected frames in a row,
+//! asks the streaming loop to restart. The restart tears down the transfers
+//! and the frame assembler and renegotiates the format with UVC probe/commit,
+//! so the next frame starts from a clean payload boundary.
+//!
+//! A camera that keeps failing would re-probe forever, so after
+//! [`MAX_REPROBES`] re-probes without a valid frame in between the supervisor
+//! gives up until a frame passes validation again, or until the user streams
+//! another camera, format or resolution ([`StreamSupervisor::stream_started`]).
+//!
+//! `CLEANSCOPE_REPROBE_AFTER` sets the threshold (`0` disables re-probing).
+
+use std::sync::Mutex;
+
+/// Environment variable overriding the re-probe threshold
+pub const THRESHOLD_ENV: &str = "CLEANSCOPE_REPROBE_AFTER";
+
+/// Rejected frames in a row that trigger a re-probe (one second at 30 fps)
+pub const DEFAULT_THRESHOLD: u32 = 30;
+
+/// Re-probes without a valid frame in between before giving up
+pub const MAX_REPROBES: u32 = 3;
+
+/// What the streaming loop should do after a frame was validated
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SupervisorAction {
+    /// Keep streaming
+    Continue,
+    /// Restart the stream with a fresh probe/commit (attempt number, from 1)
+    Reprobe(u32),
+    /// Re-probing didn't help; keep streaming without further attempts
+    GiveUp,
+}
+
+/// Camera, format and resolution a stream was negotiated for
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StreamIdentity {
+    /// Device name of the open camera, if known
+    pub device: Option<String>,
+    /// UVC format index
+    pub format_index: u8,
+    /// UVC frame index
+    pub frame_index: u8,
+}
+
+#[derive(Debug, Default)]
+struct SupervisorState {
+    /// Rejected frames in a row
+    streak: u32,
+    /// Re-probes since the last valid frame
+    reprobes: u32,
+    /// Stream the re-probes were counted for
+    stream: Option<StreamIdentity>,
+}
+
+/// Counts consecutive rejected frames and decides when to re-probe
+///
+/// Shared by the streaming sessions of a camera, so re-probes are counted
+/// across the restarts they cause.
+#[derive(Debug)]
+pub struct StreamSupervisor {
+    threshold: u32,
+    state: Mutex<SupervisorState>,
+}
+
+impl Default for StreamSupervisor {
+    fn default() -> Self {
+        Self::new(DEFAULT_THRESHOLD)
+    }
+}
+
+impl StreamSupervisor {
+    /// Re-probe after `threshold` rejected frames in a row (0: never)
+    pub fn new(threshold: u32) -> Self {
+        Self {
+            threshold,
+            state: Mutex::new(SupervisorState::default()),
+        }
+    }
+
+    /// Supervisor with the threshold from [`THRESHOLD_ENV`], if set to a number
+    pub fn from_env() -> Self {
+        let threshold = match std::env::var(THRESHOLD_ENV) {
+            Ok(value) => value.trim().parse().unwrap_or_else(|_| {
+                log::warn!("Ignoring invalid {}={:?}", THRESHOLD_ENV, value);
+                DEFAULT_THRESHOLD
+            }),
+            Err(_) => DEFAULT_THRESHOLD,
+        };
+        Self::new(threshold)
+    }
+
+    /// Rejected frames in a row that trigger a re-probe (0: never)
+    pub fn threshold(&self) -> u32 {
+        self.threshold
+    }
+
+    /// Record that a stream was negotiated
+    ///
+    /// A re-probe renegotiates the same stream and keeps counting; a stream
+    /// of another camera, format or resolution was asked for explicitly and
+    /// gets a fresh set of re-probes.
+    pub fn stream_started(&self, stream: StreamIdentity) {
+        let mut state = crate::lock_or_recover(&self.state);
+        if state.stream.as_ref() != Some(&stream) {
+            state.streak = 0;
+            state.reprobes = 0;
+            state.stream = Some(stream);
+        }
+    }
+
+    /// Record whether a frame passed validation
+    pub fn frame_validated(&self, valid: bool) -> SupervisorAction {
+        let mut state = crate::lock_or_recover(&self.state);
+        if valid {
+            state.streak = 0;
+            state.reprobes = 0;
+            return SupervisorAction::Continue;
+        }
+        if self.threshold == 0 {
+            return SupervisorAction::Continue;
+        }
+        state.streak += 1;
+        if state.streak < self.threshold {
+            return SupervisorAction::Continue;
+        }
+        state.streak = 0;
+        match state.reprobes {
+            n if n < MAX_REPROBES => {
+                state.reprobes += 1;
+                SupervisorAction::Reprobe(state.reprobes)
+            }
+            MAX_REPROBES => {
+                state.reprobes += 1;
+                SupervisorAction::GiveUp
+            }
+            _ => SupervisorAction::Continue,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn reject(supervisor: &StreamSupervisor, frames: u32) -> Vec<SupervisorAction> {
+        (0..frames)
+            .map(|_| supervisor.frame_validated(false))
+            .filter(|action| *action != SupervisorAction::Continue)
+            .collect()
+    }
+
+    #[test]
+    fn test_reprobe_after_threshold_in_a_row() {
+        let supervisor = StreamSupervisor::new(3);
+        assert!(reject(&supervisor, 2).is_empty());
+        // A valid frame breaks the streak
+        assert_eq!(supervisor.frame_validated(true), SupervisorAction::Continue);
+        assert!(reject(&supervisor, 2).is_empty());
+        assert_eq!(reject(&supervisor, 1), vec![SupervisorAction::Reprobe(1)]);
+        assert_eq!(reject(&supervisor, 3), vec![SupervisorAction::Reprobe(2)]);
+    }
+
+    #[test]
+    fn test_gives_up_until_a_frame_is_valid() {
+        let supervisor = StreamSupervisor::new(2);
+        assert_eq!(
+            reject(&supervisor, 2 * (MAX_REPROBES + 3)),
+            vec![
+                SupervisorAction::Reprobe(1),
+                SupervisorAction::Reprobe(2),
+                SupervisorAction::Reprobe(3),
+                SupervisorAction::GiveUp,
+            ]
+        );
+        supervisor.frame_validated(true);
+        assert_eq!(reject(&supervisor, 2), vec![SupervisorAction::Reprobe(1)]);
+    }
+
+    #[test]
+    fn test_zero_threshold_never_reprobes() {
+        let supervisor = StreamSupervisor::new(0);
+        assert!(reject(&supervisor, 1000).is_empty());
+    }
+
+    #[test]
+    fn test_stream_change_resets_reprobes() {
+        let stream = |frame_index| StreamIdentity {
+            device: Some("/dev/bus/usb/001/005".to_string()),
+            format_index: 1,
+            frame_index,
+        };
+        let supervisor = StreamSupervisor::new(1);
+        supervisor.stream_started(stream(1));
+        assert_eq!(
+            reject(&supervisor, MAX_REPROBES + 1).last(),
+            Some(&SupervisorAction::GiveUp)
+        );
+
+        // The re-probed stream comes back the same and stays given up
+        supervisor.stream_started(stream(1));
+        assert!(reject(&supervisor, 1).is_empty());
+
+        // Another resolution starts over
+        supervisor.stream_started(stream(2));
+        assert_eq!(reject(&supervisor, 1), vec![SupervisorAction::Reprobe(1)]);
+    }
+}
@@ -0,0 +1,487 @@
+//! Capture submission bundles for bug reports
+//!
+//! Packages a packet capture and the diagnostics bundle into a single
+//! `submission_<timestamp>.tar` that the user can send to the developers. The
+//! crate never transmits it: the frontend hands the returned path to the
+//! platform share sheet, so the user picks the channel and sees what leaves the
+//! device.
+//!
+//! Nothing is packaged without consent. [`SubmissionOptions::consent`] must be
+//! set by an explicit user action, and the capture is sanitized first: its
+//! free-text description and the source file paths are left out, and legacy
+//! captures are converted to the packets layout so every bundle reads the same.
+//! The packets themselves still contain the camera image.
+//!
+//! # Bundle Layout
+//!
+//! - `manifest.json`: [`SubmissionManifest`]
+//! - `metadata.json`: sanitized [`CaptureMetadata`]
+//! - `packets.bin`: the `packets_*.bin` layout (see [`capture::write_packets`];
+//!   copied captures may be the headerless version 1)
+//! - `transfers.bin` (optional): isochronous packet records
+//! - `diagnostics.json` (optional): [`DiagnosticsBundle`]
+
+use serde::{Deserialize, Serialize};
+use std::fs::File;
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::path::Path;
+use thiserror::Error;
+
+use crate::capture::{self, CaptureError, CaptureMetadata};
+use crate::diagnostics::DiagnosticsBundle;
+use crate::storage::Storage;
+
+/// Version of the bundle layout, bumped when files change meaning
+pub const SUBMISSION_FORMAT_VERSION: u32 = 1;
+
+/// Longest note kept in the manifest, in characters
+pub const MAX_NOTE_CHARS: usize = 2000;
+
+/// Size of a tar header and the unit entries are padded to
+const TAR_BLOCK: usize = 512;
+
+/// Errors that can occur while packaging a submission
+#[derive(Error, Debug)]
+pub enum SubmissionError {
+    /// The user has not agreed to share the capture
+    #[error("Consent is required to package a capture")]
+    ConsentRequired,
+
+    /// The capture could not be read
+    #[error("Capture error: {0}")]
+    Capture(#[from] CaptureError),
+
+    /// A bundle file name does not fit in a tar header
+    #[error("Name too long for bundle: {0}")]
+    NameTooLong(String),
+
+    /// JSON serialization error
+    #[error("JSON error: {0}")]
+    Json(#[from] serde_json::Error),
+
+    /// I/O error reading the capture or writing the bundle
+    #[error("I/O error: {0}")]
+    Io(#[from] io::Error),
+}
+
+/// Result type for submission operations
+pub type Result<T> = std::result::Result<T, SubmissionError>;
+
+/// What the user agreed to share
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SubmissionOptions {
+    /// The user explicitly agreed to package the capture for sharing
+    pub consent: bool,
+    /// Include the diagnostics bundle (build, libusb, quirks, stream health)
+    pub include_diagnostics: bool,
+    /// Note written by the user for the developers
+    pub note: Option<String>,
+}
+
+/// Describes the contents of a bundle
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SubmissionManifest {
+    /// Bundle layout version ([`SUBMISSION_FORMAT_VERSION`])
+    pub format_version: u32,
+    /// Version of the app that packaged the bundle
+    pub app_version: String,
+    /// When the bundle was packaged (Unix seconds)
+    pub created_at: u64,
+    /// Files in the bundle, in order
+    pub files: Vec<String>,
+    /// Note written by the user
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub note: Option<String>,
+}
+
+/// Packaged bundle returned to the frontend
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SubmissionResult {
+    /// Path to the bundle, to hand to the share sheet
+    pub path: String,
+    /// Bundle size in bytes
+    pub size: u64,
+    /// Packets in the capture
+    pub packets: u64,
+    /// Files in the bundle
+    pub files: Vec<String>,
+}
+
+/// Package `capture_path` (a `packets_*.bin` or legacy `capture_*.bin` file) into `storage`
+///
+/// The bundle is written as `submission_<timestamp>.tar`. `diagnostics` is
+/// only included when [`SubmissionOptions::include_diagnostics`] is set.
+///
+/// # Errors
+///
+/// Returns `SubmissionError::ConsentRequired` unless `options.consent` is set,
+/// `SubmissionError::Capture` if the capture or its metadata cannot be read,
+/// or `SubmissionError::Io` if the bundle cannot be written.
+pub fn package_capture(
+    storage: &Storage,
+    capture_path: &Path,
+    diagnostics: Option<&DiagnosticsBundle>,
+    options: &SubmissionOptions,
+) -> Result<SubmissionResult> {
+    if !options.consent {
+        return Err(SubmissionError::ConsentRequired);
+    }
+
+    let created_at = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0);
+
+    let mut metadata = match capture::find_companion_metadata(capture_path) {
+        Some(path) => capture::read_metadata(&path)?,
+        None => CaptureMetadata::default(),
+    };
+    metadata.description.clear();
+
+    let (path, file) = storage.create(format!("submission_{}.tar", created_at))?;
+    let mut tar = TarWriter::new(BufWriter::new(file), created_at);
+
+    // Legacy captures are converted; packets captures are copied as they are
+    if is_legacy_capture(capture_path) {
+        let packets = capture::read_legacy_packets(capture_path)?;
+        let mut data = Vec::new();
+        capture::write_packets(&mut data, &packets)?;
+        metadata.total_packets = packets.len() as u64;
+        metadata.total_bytes = packets.iter().map(|p| p.data.len() as u64).sum();
+        if metadata.duration_ms == 0 {
+            metadata.duration_ms = packets.last().map_or(0, |p| p.timestamp_us / 1000);
+        }
+        tar.append_bytes("packets.bin", &data)?;
+    } else {
+        let file = File::open(capture_path)?;
+        let size = file.metadata()?.len();
+        tar.append("packets.bin", size, BufReader::new(file))?;
+    }
+
+    if let Some(transfers_path) = capture::find_companion_transfers(capture_path) {
+        let file = File::open(&transfers_path)?;
+        let size = file.metadata()?.len();
+        tar.append("transfers.bin", size, BufReader::new(file))?;
+    }
+
+    tar.append_bytes(
+        "metadata.json",
+        serde_json::to_string_pretty(&metadata)?.as_bytes(),
+    )?;
+
+    if let Some(diagnostics) = diagnostics.filter(|_| options.include_diagnostics) {
+        tar.append_bytes(
+            "diagnostics.json",
+            serde_json::to_string_pretty(diagnostics)?.as_bytes(),
+        )?;
+    }
+
+    let mut files = tar.names.clone();
+    files.push("manifest.json".to_string());
+    let manifest = SubmissionManifest {
+        format_version: SUBMISSION_FORMAT_VERSION,
+        app_version: env!("CARGO_PKG_VERSION").to_string(),
+        created_at,
+        files: files.clone(),
+        note: sanitize_note(options.note.as_deref()),
+    };
+    tar.append_bytes(
+        "manifest.json",
+        serde_json::to_string_pretty(&manifest)?.as_bytes(),
+    )?;
+    tar.finish()?.flush()?;
+
+    let size = std::fs::metadata(&path)?.len();
+    log::info!(
+        "Packaged capture submission: {} packets, {} bytes to {}",
+        metadata.total_packets,
+        size,
+        path.display()
+    );
+
+    Ok(SubmissionResult {
+        path: path.to_string_lossy().to_string(),
+        size,
+        packets: metadata.total_packets,
+        files,
+    })
+}
+
+/// Whether `path` is a legacy `capture_*.bin` file
+fn is_legacy_capture(path: &Path) -> bool {
+    path.file_name()
+        .and_then(|name| name.to_str())
+        .is_some_and(|name| name.starts_with("capture_"))
+}
+
+/// Trim the user's note, dropping it when empty and capping its length
+fn sanitize_note(note: Option<&str>) -> Option<String> {
+    let note = note?.trim();
+    (!note.is_empty()).then(|| note.chars().take(MAX_NOTE_CHARS).collect())
+}
+
+/// Minimal ustar writer for flat bundles of regular files
+struct TarWriter<W: Write> {
+    out: W,
+    mtime: u64,
+    names: Vec<String>,
+}
+
+impl<W: Write> TarWriter<W> {
+    fn new(out: W, mtime: u64) -> Self {
+        Self {
+            out,
+            mtime,
+            names: Vec::new(),
+        }
+    }
+
+    /// Append a file of `size` bytes read from `data`
+    fn append(&mut self, name: &str, size: u64, mut data: impl Read) -> Result<()> {
+        self.out.write_all(&tar_header(name, size, self.mtime)?)?;
+        let copied = io::copy(&mut data.by_ref().take(size), &mut self.out)?;
+        if copied != size {
+            return Err(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                format!("{} changed while packaging", name),
+            )
+            .into());
+        }
+        let padding = (TAR_BLOCK - (size as usize % TAR_BLOCK)) % TAR_BLOCK;
+        self.out.write_all(&[0u8; TAR_BLOCK][..padding])?;
+        self.names.push(name.to_string());
+        Ok(())
+    }
+
+    /// Append a file held in memory
+    fn append_bytes(&mut self, name: &str, data: &[u8]) -> Result<()> {
+        self.append(name, data.len() as u64, data)
+    }
+
+    /// Write the end-of-archive marker and return the writer
+    fn finish(mut self) -> Result<W> {
+        self.out.write_all(&[0u8; TAR_BLOCK * 2])?;
+        Ok(self.out)
+    }
+}
+
+/// Build the ustar header of a regular file
+fn tar_header(name: &str, size: u64, mtime: u64) -> Result<[u8; TAR_BLOCK]> {
+    if name.len() > 100 {
+        return Err(SubmissionError::NameTooLong(name.to_string()));
+    }
+
+    let mut header = [0u8; TAR_BLOCK];
+    header[..name.len()].copy_from_slice(name.as_bytes());
+    write_octal(&mut header[100..108], 0o644);
+    write_octal(&mut header[108..116], 0);
+    write_octal(&mut header[116..124], 0);
+    write_octal(&mut header[124..136], size);
+    write_octal(&mut header[136..148], mtime);
+    header[156] = b'0';
+    header[257..263].copy_from_slice(b"ustar\0");
+    header[263..265].copy_from_slice(b"00");
+
+    // The checksum is computed with its own field filled with spaces
+    header[148..156].fill(b' ');
+    let checksum: u32 = header.iter().map(|&b| u32::from(b)).sum();
+    header[148..155].copy_from_slice(format!("{:06o}\0", checksum).as_bytes());
+    Ok(header)
+}
+
+/// Write `value` as zero-padded octal followed by a NUL
+fn write_octal(field: &mut [u8], value: u64) {
+    let digits = format!("{:0width$o}\0", value, width = field.len() - 1);
+    field.copy_from_slice(digits.as_bytes());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::capture::CapturedPacket;
+    use std::path::PathBuf;
+    use tempfile::TempDir;
+
+    /// Parse a tar archive into `(name, contents)` pairs
+    fn read_tar(bytes: &[u8]) -> Vec<(String, Vec<u8>)> {
+        let mut entries = Vec::new();
+        let mut offset = 0;
+        while offset + TAR_BLOCK <= bytes.len() {
+            let header = &bytes[offset..offset + TAR_BLOCK];
+            if header.iter().all(|&b| b == 0) {
+                break;
+            }
+            let name_len = header[..100].iter().position(|&b| b == 0).unwrap_or(100);
+            let name = String::from_utf8(header[..name_len].to_vec()).unwrap();
+            let size_field = std::str::from_utf8(&header[124..135]).unwrap();
+            let size = usize::from_str_radix(size_field, 8).unwrap();
+
+            let mut stored = header.to_vec();
+            stored[148..156].fill(b' ');
+            let checksum: u32 = stored.iter().map(|&b| u32::from(b)).sum();
+            let checksum_field = std::str::from_utf8(&header[148..154]).unwrap();
+            assert_eq!(u32::from_str_radix(checksum_field, 8).unwrap(), checksum);
+
+            offset += TAR_BLOCK;
+            entries.push((name, bytes[offset..offset + size].to_vec()));
+            offset += size.div_ceil(TAR_BLOCK) * TAR_BLOCK;
+        }
+        entries
+    }
+
+    fn entry<'a>(entries: &'a [(String, Vec<u8>)], name: &str) -> Option<&'a [u8]> {
+        entries
+            .iter()
+            .find(|(n, _)| n == name)
+            .map(|(_, data)| data.as_slice())
+    }
+
+    fn consent() -> SubmissionOptions {
+        SubmissionOptions {
+            consent: true,
+            ..Default::default()
+        }
+    }
+
+    /// Write a packets capture with a description into `dir`
+    fn write_packets_capture(dir: &Path) -> PathBuf {
+        let path = dir.join("packets_100.bin");
+        let mut data = Vec::new();
+        for packet in [&b"\x0c\x8cabc"[..], &b"\x0c\x8ddefg"[..]] {
+            data.extend_from_slice(&(packet.len() as u32).to_le_bytes());
+            data.extend_from_slice(packet);
+        }
+        std::fs::write(&path, data).unwrap();
+        let metadata = CaptureMetadata {
+            vendor_id: 0x1234,
+            total_packets: 2,
+            description: "bathroom pipe, 12 Example Street".to_string(),
+            ..Default::default()
+        };
+        std::fs::write(
+            dir.join("metadata_100.json"),
+            serde_json::to_string(&metadata).unwrap(),
+        )
+        .unwrap();
+        path
+    }
+
+    #[test]
+    fn test_requires_consent() {
+        let dir = TempDir::new().unwrap();
+        let capture = write_packets_capture(dir.path());
+        let result = package_capture(
+            &Storage::new(dir.path()),
+            &capture,
+            None,
+            &SubmissionOptions::default(),
+        );
+        assert!(matches!(result, Err(SubmissionError::ConsentRequired)));
+        let written = std::fs::read_dir(dir.path())
+            .unwrap()
+            .filter_map(|e| e.ok())
+            .any(|e| e.file_name().to_string_lossy().starts_with("submission_"));
+        assert!(!written);
+    }
+
+    #[test]
+    fn test_packages_sanitized_capture() {
+        let dir = TempDir::new().unwrap();
+        let capture = write_packets_capture(dir.path());
+        let options = SubmissionOptions {
+            note: Some("  stream freezes after a minute  ".to_string()),
+            ..consent()
+        };
+
+        let result = package_capture(&Storage::new(dir.path()), &capture, None, &options).unwrap();
+        assert_eq!(result.packets, 2);
+        assert_eq!(
+            result.files,
+            ["packets.bin", "metadata.json", "manifest.json"]
+        );
+
+        let bytes = std::fs::read(&result.path).unwrap();
+        assert_eq!(bytes.len() as u64, result.size);
+        assert_eq!(bytes.len() % TAR_BLOCK, 0);
+        let entries = read_tar(&bytes);
+
+        assert_eq!(
+            entry(&entries, "packets.bin").unwrap(),
+            std::fs::read(&capture).unwrap()
+        );
+        let metadata: CaptureMetadata =
+            serde_json::from_slice(entry(&entries, "metadata.json").unwrap()).unwrap();
+        assert_eq!(metadata.vendor_id, 0x1234);
+        assert!(metadata.description.is_empty());
+
+        let manifest: SubmissionManifest =
+            serde_json::from_slice(entry(&entries, "manifest.json").unwrap()).unwrap();
+        assert_eq!(manifest.format_version, SUBMISSION_FORMAT_VERSION);
+        assert_eq!(manifest.files, result.files);
+        assert_eq!(
+            manifest.note.as_deref(),
+            Some("stream freezes after a minute")
+        );
+
+        // Absolute paths of the source files stay out of the bundle
+        let root = dir.path().to_string_lossy().to_string();
+        assert!(!String::from_utf8_lossy(&bytes).contains(&root));
+    }
+
+    #[test]
+    fn test_converts_legacy_capture() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("capture_7.bin");
+        let mut data = Vec::new();
+        for (i, payload) in [b"one".to_vec(), b"three".to_vec()].into_iter().enumerate() {
+            let packet = CapturedPacket {
+                timestamp_us: i as u64 * 4000,
+                endpoint: 0x81,
+                data: payload,
+            };
+            data.extend_from_slice(&packet.timestamp_us.to_le_bytes());
+            data.extend_from_slice(&(packet.data.len() as u32).to_le_bytes());
+            data.push(packet.endpoint);
+            data.extend_from_slice(&packet.data);
+        }
+        std::fs::write(&path, data).unwrap();
+
+        let result = package_capture(&Storage::new(dir.path()), &path, None, &consent()).unwrap();
+        let entries = read_tar(&std::fs::read(&result.path).unwrap());
+
+        let packets_path = dir.path().join("converted.bin");
+        std::fs::write(&packets_path, entry(&entries, "packets.bin").unwrap()).unwrap();
+        let converted = capture::read_packets_file(&packets_path).unwrap();
+        assert!(converted.has_timing());
+        let payloads: Vec<&[u8]> = converted.packets.iter().map(|p| &p.data[..]).collect();
+        assert_eq!(payloads, [&b"one"[..], &b"three"[..]]);
+        assert_eq!(converted.packets[1].timestamp_us, 4000);
+        let metadata: CaptureMetadata =
+            serde_json::from_slice(entry(&entries, "metadata.json").unwrap()).unwrap();
+        assert_eq!(metadata.total_packets, 2);
+        assert_eq!(metadata.duration_ms, 4);
+    }
+
+    #[test]
+    fn test_includes_transfers() {
+        let dir = TempDir::new().unwrap();
+        let capture = write_packets_capture(dir.path());
+        std::fs::write(dir.path().join("transfers_100.bin"), [7u8; 30]).unwrap();
+
+        let result =
+            package_capture(&Storage::new(dir.path()), &capture, None, &consent()).unwrap();
+        let entries = read_tar(&std::fs::read(&result.path).unwrap());
+        assert_eq!(entry(&entries, "transfers.bin"), Some(&[7u8; 30][..]));
+    }
+
+    #[test]
+    fn test_sanitize_note() {
+        assert_eq!(sanitize_note(None), None);
+        assert_eq!(sanitize_note(Some("   ")), None);
+        let long = "x".repeat(MAX_NOTE_CHARS + 10);
+        assert_eq!(
+            sanitize_note(Some(&long)).map(|n| n.chars().count()),
+            Some(MAX_NOTE_CHARS)
+        );
+    }
+}
@@ -0,0 +1,216 @@
+//! Reference colors for conversion accuracy tests
+//!
+//! The 75% SMPTE color bars with their 8-bit limited range YCbCr codes in
+//! BT.601 and BT.709, and per-channel tolerances for comparing converted
+//! pixels against the expected RGB.
+
+use super::packet_generator::Rgb;
+use crate::yuv_conversion::YuvMatrix;
+
+/// 75% intensity (191 of 255)
+const LEVEL: u8 = 191;
+
+/// One SMPTE color bar
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ColorBar {
+    /// Color name, for failure messages
+    pub name: &'static str,
+    /// Expected RGB after conversion
+    pub rgb: Rgb,
+    /// (Y, Cb, Cr) in BT.601
+    pub bt601: (u8, u8, u8),
+    /// (Y, Cb, Cr) in BT.709
+    pub bt709: (u8, u8, u8),
+}
+
+impl ColorBar {
+    /// (Y, Cb, Cr) of the bar in `matrix`
+    pub fn ycbcr(&self, matrix: YuvMatrix) -> (u8, u8, u8) {
+        match matrix {
+            YuvMatrix::Bt601 => self.bt601,
+            YuvMatrix::Bt709 => self.bt709,
+        }
+    }
+}
+
+/// 75% SMPTE color bars, left to right
+pub const SMPTE_BARS: [ColorBar; 8] = [
+    ColorBar {
+        name: "white",
+        rgb: Rgb {
+            r: LEVEL,
+            g: LEVEL,
+            b: LEVEL,
+        },
+        bt601: (180, 128, 128),
+        bt709: (180, 128, 128),
+    },
+    ColorBar {
+        name: "yellow",
+        rgb: Rgb {
+            r: LEVEL,
+            g: LEVEL,
+            b: 0,
+        },
+        bt601: (162, 44, 142),
+        bt709: (168, 44, 136),
+    },
+    ColorBar {
+        name: "cyan",
+        rgb: Rgb {
+            r: 0,
+            g: LEVEL,
+            b: LEVEL,
+        },
+        bt601: (131, 156, 44),
+        bt709: (145, 147, 44),
+    },
+    ColorBar {
+        name: "green",
+        rgb: Rgb {
+            r: 0,
+            g: LEVEL,
+            b: 0,
+        },
+        bt601: (112, 72, 58),
+        bt709: (133, 63, 52),
+    },
+    ColorBar {
+        name: "magenta",
+        rgb: Rgb {
+            r: LEVEL,
+            g: 0,
+            b: LEVEL,
+        },
+        bt601: (84, 184, 198),
+        bt709: (63, 193, 204),
+    },
+    ColorBar {
+        name: "red",
+        rgb: Rgb {
+            r: LEVEL,
+            g: 0,
+            b: 0,
+        },
+        bt601: (65, 100, 212),
+        bt709: (51, 109, 212),
+    },
+    ColorBar {
+        name: "blue",
+        rgb: Rgb {
+            r: 0,
+            g: 0,
+            b: LEVEL,
+        },
+        bt601: (35, 212, 114),
+        bt709: (28, 212, 120),
+    },
+    ColorBar {
+        name: "black",
+        rgb: Rgb { r: 0, g: 0, b: 0 },
+        bt601: (16, 128, 128),
+        bt709: (16, 128, 128),
+    },
+];
+
+/// Largest accepted difference per channel
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Tolerance {
+    /// Red channel
+    pub r: u8,
+    /// Green channel
+    pub g: u8,
+    /// Blue channel
+    pub b: u8,
+}
+
+impl Tolerance {
+    /// The same tolerance for every channel
+    pub const fn uniform(max: u8) -> Self {
+        Self {
+            r: max,
+            g: max,
+            b: max,
+        }
+    }
+
+    /// Widen every channel by `slack`
+    pub const fn widen(self, slack: u8) -> Self {
+        Self {
+            r: self.r.saturating_add(slack),
+            g: self.g.saturating_add(slack),
+            b: self.b.saturating_add(slack),
+        }
+    }
+
+    /// Whether every channel of `actual` is within the tolerance of `expected`
+    pub fn accepts(&self, expected: Rgb, actual: Rgb) -> bool {
+        expected.r.abs_diff(actual.r) <= self.r
+            && expected.g.abs_diff(actual.g) <= self.g
+            && expected.b.abs_diff(actual.b) <= self.b
+    }
+}
+
+/// Assert that an RGB888 `pixel` is within `tolerance` of `expected`
+///
+/// # Panics
+///
+/// If a channel is off by more than its tolerance.
+pub fn assert_color_near(pixel: &[u8], expected: Rgb, tolerance: Tolerance) {
+    let actual = Rgb {
+        r: pixel[0],
+        g: pixel[1],
+        b: pixel[2],
+    };
+    assert!(
+        tolerance.accepts(expected, actual),
+        "expected {:?} within {:?}, got {:?}",
+        expected,
+        tolerance,
+        actual
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// (Y, Cb, Cr) of 75% `rgb` from the matrix's luma weights
+    fn encode(rgb: Rgb, kr: f64, kb: f64) -> (u8, u8, u8) {
+        let [r, g, b] = [rgb.r, rgb.g, rgb.b].map(|c| if c > 0 { 0.75 } else { 0.0 });
+        let y = kr * r + (1.0 - kr - kb) * g + kb * b;
+        let cb = (b - y) / (2.0 * (1.0 - kb));
+        let cr = (r - y) / (2.0 * (1.0 - kr));
+        (
+            (16.0 + 219.0 * y).round() as u8,
+            (128.0 + 224.0 * cb).round() as u8,
+            (128.0 + 224.0 * cr).round() as u8,
+        )
+    }
+
+    #[test]
+    fn test_codes_match_the_matrix_definitions() {
+        for bar in &SMPTE_BARS {
+            assert_eq!(bar.bt601, encode(bar.rgb, 0.299, 0.114), "{}", bar.name);
+            assert_eq!(bar.bt709, encode(bar.rgb, 0.2126, 0.0722), "{}", bar.name);
+        }
+    }
+
+    #[test]
+    fn test_tolerance_is_per_channel() {
+        let tolerance = Tolerance { r: 1, g: 4, b: 0 };
+        let expected = Rgb {
+            r: 100,
+            g: 100,
+            b: 100,
+        };
+        let near = Rgb {
+            r: 101,
+            g: 96,
+            b: 100,
+        };
+        assert!(tolerance.accepts(expected, near));
+        assert!(!tolerance.accepts(expected, Rgb { b: 101, ..near }));
+        assert!(tolerance.widen(1).accepts(expected, Rgb { b: 101, ..near }));
+    }
+}
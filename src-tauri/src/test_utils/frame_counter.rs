@@ -0,0 +1,272 @@
+//! Frame counter OCR for test sequences
+//!
+//! Frames from [`PacketGenerator::sequence`](super::PacketGenerator::sequence)
+//! carry their index as white 3x5 digits on a black plate. Reading the
+//! counter back at the end of the pipeline tells a test exactly which frames
+//! made it through, so it can assert on dropped, duplicated and reordered
+//! frames instead of only counting them.
+//!
+//! # Example
+//!
+//! ```rust,ignore
+//! use clean_scope_lib::test_utils::{check_frame_counters, read_frame_counter_rgb};
+//!
+//! let counters = rgb_frames.iter().map(|rgb| read_frame_counter_rgb(rgb, width));
+//! let check = check_frame_counters(counters, 0..30);
+//! assert_eq!(check.dropped, vec![12]);
+//! ```
+
+use std::collections::BTreeMap;
+use std::ops::Range;
+
+/// Digits in the frame counter (the index is shown modulo 10^digits)
+pub const COUNTER_DIGITS: u32 = 4;
+/// Pixels per font pixel in the frame counter
+pub(crate) const COUNTER_SCALE: u32 = 2;
+/// Top-left corner of the frame counter
+pub(crate) const COUNTER_ORIGIN: (u32, u32) = (4, 4);
+/// Width of a font glyph in font pixels
+const FONT_WIDTH: u32 = 3;
+/// 3x5 digit glyphs, one row per byte, most significant of the low 3 bits on the left
+const DIGIT_FONT: [[u8; 5]; 10] = [
+    [0b111, 0b101, 0b101, 0b101, 0b111], // 0
+    [0b010, 0b110, 0b010, 0b010, 0b111], // 1
+    [0b111, 0b001, 0b111, 0b100, 0b111], // 2
+    [0b111, 0b001, 0b111, 0b001, 0b111], // 3
+    [0b101, 0b101, 0b111, 0b001, 0b001], // 4
+    [0b111, 0b100, 0b111, 0b001, 0b111], // 5
+    [0b111, 0b100, 0b111, 0b101, 0b111], // 6
+    [0b111, 0b001, 0b001, 0b001, 0b001], // 7
+    [0b111, 0b101, 0b111, 0b101, 0b111], // 8
+    [0b111, 0b101, 0b111, 0b001, 0b111], // 9
+];
+
+/// Width of one digit cell, including the gap to the next digit
+const CELL_WIDTH: u32 = (FONT_WIDTH + 1) * COUNTER_SCALE;
+
+/// Size of the frame counter plate (width, height)
+pub(crate) fn counter_plate_size() -> (u32, u32) {
+    (
+        COUNTER_DIGITS * CELL_WIDTH,
+        DIGIT_FONT[0].len() as u32 * COUNTER_SCALE,
+    )
+}
+
+/// Whether the counter for `index` lights pixel (x, y), or `None` outside the plate
+pub(crate) fn counter_pixel(x: u32, y: u32, index: u32) -> Option<bool> {
+    let (plate_width, plate_height) = counter_plate_size();
+    let dx = x.checked_sub(COUNTER_ORIGIN.0)?;
+    let dy = y.checked_sub(COUNTER_ORIGIN.1)?;
+    if dx >= plate_width || dy >= plate_height {
+        return None;
+    }
+    let position = dx / CELL_WIDTH;
+    let col = (dx % CELL_WIDTH) / COUNTER_SCALE;
+    if col >= FONT_WIDTH {
+        return Some(false);
+    }
+    let row = (dy / COUNTER_SCALE) as usize;
+    let digit = (index / 10u32.pow(COUNTER_DIGITS - 1 - position)) % 10;
+    Some(DIGIT_FONT[digit as usize][row] >> (FONT_WIDTH - 1 - col) & 1 == 1)
+}
+
+/// Read the frame counter from YUY2 frame data
+///
+/// Returns `None` if a digit doesn't match any glyph, e.g. because the frame
+/// was corrupted or isn't a sequence frame.
+pub fn read_frame_counter(yuy2: &[u8], width: u32) -> Option<u32> {
+    read_counter(|x, y| yuy2.get(((y * width + x) * 2) as usize).copied())
+}
+
+/// Read the frame counter from RGB24 frame data (pipeline output)
+///
+/// Conversion shifts levels slightly (limited-range YUV expands to full
+/// range), so each font pixel is averaged over its block and compared to
+/// the midpoint between the plate's darkest and brightest blocks.
+pub fn read_frame_counter_rgb(rgb: &[u8], width: u32) -> Option<u32> {
+    read_counter(|x, y| {
+        let i = ((y * width + x) * 3) as usize;
+        let px = rgb.get(i..i + 3)?;
+        // BT.601 luma weights, integer approximation
+        let luma = (77 * u32::from(px[0]) + 150 * u32::from(px[1]) + 29 * u32::from(px[2])) >> 8;
+        Some(luma as u8)
+    })
+}
+
+/// Match the counter glyphs against per-pixel luma from `luma_at`
+fn read_counter(luma_at: impl Fn(u32, u32) -> Option<u8>) -> Option<u32> {
+    let rows = DIGIT_FONT[0].len();
+    let mut blocks = Vec::with_capacity((COUNTER_DIGITS * FONT_WIDTH) as usize * rows);
+    for position in 0..COUNTER_DIGITS {
+        for row in 0..rows as u32 {
+            for col in 0..FONT_WIDTH {
+                let x0 = COUNTER_ORIGIN.0 + position * CELL_WIDTH + col * COUNTER_SCALE;
+                let y0 = COUNTER_ORIGIN.1 + row * COUNTER_SCALE;
+                let mut sum = 0u32;
+                for dy in 0..COUNTER_SCALE {
+                    for dx in 0..COUNTER_SCALE {
+                        sum += u32::from(luma_at(x0 + dx, y0 + dy)?);
+                    }
+                }
+                blocks.push(sum / (COUNTER_SCALE * COUNTER_SCALE));
+            }
+        }
+    }
+
+    let darkest = *blocks.iter().min()?;
+    let brightest = *blocks.iter().max()?;
+    // Every index lights some pixels and leaves others dark, so a flat
+    // plate means there is no counter at all
+    if brightest - darkest < 64 {
+        return None;
+    }
+    let threshold = (darkest + brightest) / 2;
+
+    let mut value = 0;
+    for digit_blocks in blocks.chunks((FONT_WIDTH as usize) * rows) {
+        let mut glyph = [0u8; 5];
+        for (bits, row_blocks) in glyph
+            .iter_mut()
+            .zip(digit_blocks.chunks(FONT_WIDTH as usize))
+        {
+            for &block in row_blocks {
+                *bits = (*bits << 1) | u8::from(block > threshold);
+            }
+        }
+        let digit = DIGIT_FONT.iter().position(|g| *g == glyph)?;
+        value = value * 10 + digit as u32;
+    }
+    Some(value)
+}
+
+/// Which frames of a sequence came out of a pipeline
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CounterCheck {
+    /// Frames read, including duplicates and unreadable ones
+    pub received: usize,
+    /// Expected indices that never came out
+    pub dropped: Vec<u32>,
+    /// Indices that came out more than once (listed once per extra copy)
+    pub duplicated: Vec<u32>,
+    /// Indices that came out after a higher index
+    pub reordered: Vec<u32>,
+    /// Indices outside the expected range
+    pub unexpected: Vec<u32>,
+    /// Frames whose counter couldn't be read
+    pub unreadable: usize,
+}
+
+impl CounterCheck {
+    /// Whether every expected frame came out exactly once, in order
+    pub fn is_exact(&self) -> bool {
+        self.dropped.is_empty()
+            && self.duplicated.is_empty()
+            && self.reordered.is_empty()
+            && self.unexpected.is_empty()
+            && self.unreadable == 0
+    }
+}
+
+/// Compare the counters read from pipeline output with the expected indices
+///
+/// `counters` holds the result of [`read_frame_counter`] or
+/// [`read_frame_counter_rgb`] for each output frame, in output order.
+pub fn check_frame_counters(
+    counters: impl IntoIterator<Item = Option<u32>>,
+    expected: Range<u32>,
+) -> CounterCheck {
+    let mut check = CounterCheck::default();
+    let mut seen: BTreeMap<u32, usize> = BTreeMap::new();
+    let mut highest: Option<u32> = None;
+
+    for counter in counters {
+        check.received += 1;
+        let Some(index) = counter else {
+            check.unreadable += 1;
+            continue;
+        };
+        if !expected.contains(&index) {
+            check.unexpected.push(index);
+            continue;
+        }
+        let count = seen.entry(index).or_default();
+        *count += 1;
+        if *count > 1 {
+            check.duplicated.push(index);
+        } else if highest.is_some_and(|h| index < h) {
+            check.reordered.push(index);
+        }
+        highest = highest.max(Some(index));
+    }
+
+    check.dropped = expected.filter(|i| !seen.contains_key(i)).collect();
+    check
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::{PacketGenerator, Rgb, SEQUENCE_WIDTH};
+    use crate::yuv_conversion::{convert_yuv422_to_rgb, YuvPackedFormat};
+
+    #[test]
+    fn test_read_counter_from_yuy2() {
+        let frames = PacketGenerator::default().sequence(12, 5);
+        for frame in &frames {
+            assert_eq!(
+                read_frame_counter(&frame.data, SEQUENCE_WIDTH),
+                Some(frame.index)
+            );
+        }
+    }
+
+    #[test]
+    fn test_read_counter_from_converted_rgb() {
+        let frames = PacketGenerator::default().sequence(12, 11);
+        for frame in &frames {
+            let rgb = convert_yuv422_to_rgb(
+                &frame.data,
+                SEQUENCE_WIDTH,
+                crate::test_utils::SEQUENCE_HEIGHT,
+                None,
+                YuvPackedFormat::Yuyv,
+            )
+            .unwrap();
+            assert_eq!(
+                read_frame_counter_rgb(&rgb, SEQUENCE_WIDTH),
+                Some(frame.index)
+            );
+        }
+    }
+
+    #[test]
+    fn test_frames_without_counter_are_unreadable() {
+        let gen = PacketGenerator::default();
+        for color in [Rgb::WHITE, Rgb::BLACK, Rgb::GRAY] {
+            let frame = gen.generate_yuy2_solid(SEQUENCE_WIDTH, 48, color);
+            assert_eq!(read_frame_counter(&frame, SEQUENCE_WIDTH), None);
+        }
+        assert_eq!(read_frame_counter(&[], SEQUENCE_WIDTH), None);
+    }
+
+    #[test]
+    fn test_check_exact_sequence() {
+        let check = check_frame_counters((0..10).map(Some), 0..10);
+        assert!(check.is_exact());
+        assert_eq!(check.received, 10);
+    }
+
+    #[test]
+    fn test_check_reports_drops_duplicates_and_reordering() {
+        let counters = [0, 1, 1, 3, 2, 5, 5, 5, 42].map(Some);
+        let check = check_frame_counters(counters.into_iter().chain([None]), 0..6);
+
+        assert_eq!(check.received, 10);
+        assert_eq!(check.dropped, vec![4]);
+        assert_eq!(check.duplicated, vec![1, 5, 5]);
+        assert_eq!(check.reordered, vec![2]);
+        assert_eq!(check.unexpected, vec![42]);
+        assert_eq!(check.unreadable, 1);
+        assert!(!check.is_exact());
+    }
+}
@@ -1,8 +1,15 @@
 //! Test utilities for `CleanScope`
 //!
 //! Provides synthetic packet generation and test helpers for validating
-//! the frame assembly pipeline without physical USB hardware.
+//! the frame assembly pipeline without physical USB hardware, and reference
+//! colors for checking conversion accuracy.
 
+pub mod color_reference;
+pub mod frame_counter;
 pub mod packet_generator;
+pub mod simulated_camera;
 
+pub use color_reference::*;
+pub use frame_counter::*;
 pub use packet_generator::*;
+pub use simulated_camera::*;
@@ -16,6 +16,8 @@
 //! let packets = gen.yuy2_gradient_frame(640, 480);
 //! ```
 
+use super::frame_counter::{counter_pixel, counter_plate_size, COUNTER_ORIGIN};
+
 /// RGB color for test patterns
 #[derive(Debug, Clone, Copy, PartialEq)]
 pub struct Rgb {
@@ -95,6 +97,8 @@ pub struct UvcHeader {
     pub fid: bool,
     /// End of Frame (EOF) bit
     pub eof: bool,
+    /// Error (ERR) bit - set when the camera flags the payload as bad
+    pub error: bool,
     /// Presentation Time Stamp (optional)
     pub pts: Option<u32>,
     /// Source Clock Reference (optional)
@@ -108,6 +112,7 @@ impl UvcHeader {
             length: 2,
             fid,
             eof,
+            error: false,
             pts: None,
             scr: None,
         }
@@ -119,6 +124,7 @@ impl UvcHeader {
             length: 12,
             fid,
             eof,
+            error: false,
             pts: Some(pts),
             scr: Some([0; 6]),
         }
@@ -145,6 +151,9 @@ impl UvcHeader {
         if self.scr.is_some() {
             flags |= 0x08;
         }
+        if self.error {
+            flags |= 0x40;
+        }
         bytes.push(flags);
 
         // Optional PTS (4 bytes, little-endian)
@@ -161,12 +170,130 @@ impl UvcHeader {
     }
 }
 
+/// Width of frames produced by [`PacketGenerator::sequence`]
+pub const SEQUENCE_WIDTH: u32 = 320;
+/// Height of frames produced by [`PacketGenerator::sequence`]
+pub const SEQUENCE_HEIGHT: u32 = 240;
+/// Side of the bouncing box in sequence frames (pixels)
+pub const SEQUENCE_BOX_SIZE: u32 = 32;
+
+/// Box colors a seed picks from (none is white, so the counter stays readable)
+const BOX_COLORS: [Rgb; 6] = [
+    Rgb::RED,
+    Rgb::GREEN,
+    Rgb::BLUE,
+    Rgb::YELLOW,
+    Rgb::CYAN,
+    Rgb::MAGENTA,
+];
+
+/// One frame of a test-pattern sequence, with its ground truth
+///
+/// Frames show a solid box bouncing over a black background, with the frame
+/// index drawn as white digits on a black plate in the top-left corner. The
+/// plate is drawn over the box.
+#[derive(Debug, Clone)]
+pub struct SequenceFrame {
+    /// Position of the frame in the sequence (also shown by the counter)
+    pub index: u32,
+    /// Left edge of the box (always even, so the box never splits a macropixel)
+    pub box_x: u32,
+    /// Top edge of the box
+    pub box_y: u32,
+    /// Color of the box (the same for the whole sequence)
+    pub box_color: Rgb,
+    /// Raw YUY2 frame bytes
+    pub data: Vec<u8>,
+    /// The frame packetized into UVC packets
+    pub packets: Vec<Vec<u8>>,
+}
+
+impl SequenceFrame {
+    /// Whether pixel (x, y) lies inside the box
+    pub fn box_contains(&self, x: u32, y: u32) -> bool {
+        (self.box_x..self.box_x + SEQUENCE_BOX_SIZE).contains(&x)
+            && (self.box_y..self.box_y + SEQUENCE_BOX_SIZE).contains(&y)
+    }
+}
+
+/// Deterministic pseudo-random numbers (`SplitMix64`)
+#[derive(Debug)]
+pub(crate) struct SplitMix64(pub(crate) u64);
+
+impl SplitMix64 {
+    pub(crate) fn next(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    /// Uniform value in `0..bound`
+    pub(crate) fn below(&mut self, bound: u64) -> u64 {
+        self.next() % bound
+    }
+
+    /// True with probability `p`
+    pub(crate) fn chance(&mut self, p: f64) -> bool {
+        ((self.next() >> 11) as f64 / (1u64 << 53) as f64) < p
+    }
+}
+
+/// Advance `pos` by `velocity`, reflecting off 0 and `max`
+fn bounce(pos: i64, velocity: &mut i64, max: i64) -> i64 {
+    let next = pos + *velocity;
+    if next < 0 {
+        *velocity = -*velocity;
+        -next
+    } else if next > max {
+        *velocity = -*velocity;
+        2 * max - next
+    } else {
+        next
+    }
+}
+
+/// Payload size distribution for packets from a real isochronous endpoint
+///
+/// Real devices don't fill every packet: payloads are bounded by
+/// `wMaxPacketSize` but often short, and many packets carry no payload at
+/// all, either arriving empty (zero `actual_length`) or as a bare header.
+/// Sizes are drawn from a seeded generator, so a failing layout reproduces.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PayloadDistribution {
+    /// Seed for the size draws
+    pub seed: u64,
+    /// Fraction of data packets shorter than the maximum (0.0 to 1.0)
+    pub short_packet_rate: f64,
+    /// Smallest payload of a short packet (at least 1)
+    pub min_payload_size: usize,
+    /// Chance of an empty packet before each data packet
+    pub empty_packet_rate: f64,
+    /// Chance of a header-only packet before each data packet
+    pub header_only_rate: f64,
+}
+
+impl Default for PayloadDistribution {
+    fn default() -> Self {
+        Self {
+            seed: 0,
+            short_packet_rate: 0.3,
+            min_payload_size: 1,
+            empty_packet_rate: 0.1,
+            header_only_rate: 0.1,
+        }
+    }
+}
+
 /// Generates synthetic UVC packets for testing
 pub struct PacketGenerator {
     /// Maximum payload size per packet (excluding header)
     pub max_payload_size: usize,
     /// Current frame ID (toggles each frame)
     current_fid: bool,
+    /// Variable payload sizing (`None` fills every packet)
+    payload_sizing: Option<(PayloadDistribution, SplitMix64)>,
 }
 
 impl Default for PacketGenerator {
@@ -181,9 +308,30 @@ impl PacketGenerator {
         Self {
             max_payload_size,
             current_fid: false,
+            payload_sizing: None,
         }
     }
 
+    /// Draw payload sizes from `distribution` instead of filling every packet
+    ///
+    /// # Panics
+    ///
+    /// Panics if the empty and header-only rates leave no room for data
+    /// packets or `min_payload_size` is 0.
+    pub fn with_payload_distribution(mut self, distribution: PayloadDistribution) -> Self {
+        assert!(
+            distribution.empty_packet_rate + distribution.header_only_rate < 1.0,
+            "empty and header-only packets would crowd out data"
+        );
+        assert!(
+            distribution.min_payload_size > 0,
+            "short packets need a payload"
+        );
+        let rng = SplitMix64(distribution.seed);
+        self.payload_sizing = Some((distribution, rng));
+        self
+    }
+
     /// Generate YUY2 packets for a solid color frame
     ///
     /// Returns a vector of packets, each with UVC header + payload
@@ -417,6 +565,102 @@ impl PacketGenerator {
         frame
     }
 
+    /// Generate a deterministic bouncing-box sequence at the default size
+    ///
+    /// See [`PacketGenerator::sequence_with_size`].
+    pub fn sequence(&mut self, frames: u32, seed: u64) -> Vec<SequenceFrame> {
+        self.sequence_with_size(SEQUENCE_WIDTH, SEQUENCE_HEIGHT, frames, seed)
+    }
+
+    /// Generate a deterministic bouncing-box sequence of YUY2 frames
+    ///
+    /// The seed picks the box color, start position and velocity, so the same
+    /// seed always yields byte-identical frames. Every frame moves the box,
+    /// and each frame's counter digits encode its index, giving multi-frame
+    /// features (motion detection, recording, dedup) a known ground truth.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `width` is odd or either dimension can't fit the box and
+    /// the counter.
+    pub fn sequence_with_size(
+        &mut self,
+        width: u32,
+        height: u32,
+        frames: u32,
+        seed: u64,
+    ) -> Vec<SequenceFrame> {
+        let (plate_width, plate_height) = counter_plate_size();
+        assert!(width.is_multiple_of(2), "YUY2 width must be even");
+        assert!(
+            width >= (COUNTER_ORIGIN.0 + plate_width).max(SEQUENCE_BOX_SIZE * 2)
+                && height >= (COUNTER_ORIGIN.1 + plate_height).max(SEQUENCE_BOX_SIZE * 2),
+            "{}x{} is too small for a test sequence",
+            width,
+            height
+        );
+
+        let mut rng = SplitMix64(seed);
+        let box_color = BOX_COLORS[rng.below(BOX_COLORS.len() as u64) as usize];
+        let max_x = i64::from(width - SEQUENCE_BOX_SIZE);
+        let max_y = i64::from(height - SEQUENCE_BOX_SIZE);
+        // Even x and even horizontal speed keep the box aligned to macropixels
+        let mut x = rng.below(max_x as u64 / 2 + 1) as i64 * 2;
+        let mut y = rng.below(max_y as u64 + 1) as i64;
+        let sign = |rng: &mut SplitMix64| if rng.below(2) == 0 { 1 } else { -1 };
+        let mut vx = sign(&mut rng) * (rng.below(2) as i64 + 1) * 2;
+        let mut vy = sign(&mut rng) * (rng.below(3) as i64 + 1);
+
+        let mut sequence = Vec::with_capacity(frames as usize);
+        for index in 0..frames {
+            let mut frame = SequenceFrame {
+                index,
+                box_x: x as u32,
+                box_y: y as u32,
+                box_color,
+                data: Vec::new(),
+                packets: Vec::new(),
+            };
+            frame.data = self.generate_yuy2_sequence_frame(width, height, &frame);
+            frame.packets = self.packetize_frame(&frame.data, frame.data.len());
+            sequence.push(frame);
+
+            x = bounce(x, &mut vx, max_x);
+            y = bounce(y, &mut vy, max_y);
+        }
+        sequence
+    }
+
+    /// Render one sequence frame as YUY2
+    fn generate_yuy2_sequence_frame(
+        &self,
+        width: u32,
+        height: u32,
+        frame: &SequenceFrame,
+    ) -> Vec<u8> {
+        let mut data = Vec::with_capacity((width * height * 2) as usize);
+
+        for row in 0..height {
+            // Box and counter edges are even, so both pixels of a
+            // macropixel always share a color
+            for x in (0..width).step_by(2) {
+                let color = match counter_pixel(x, row, frame.index) {
+                    Some(true) => Rgb::WHITE,
+                    Some(false) => Rgb::BLACK,
+                    None if frame.box_contains(x, row) => frame.box_color,
+                    None => Rgb::BLACK,
+                };
+                let (y_val, u_val, v_val) = color.to_yuv();
+                data.push(y_val); // Y0
+                data.push(u_val); // U
+                data.push(y_val); // Y1
+                data.push(v_val); // V
+            }
+        }
+
+        data
+    }
+
     /// Generate a minimal valid JPEG for testing
     fn generate_minimal_jpeg(&self, color: Rgb) -> Vec<u8> {
         // This creates a minimal 1x1 JPEG with the specified color
@@ -507,50 +751,52 @@ impl PacketGenerator {
 
     /// Packetize frame data into UVC packets (for uncompressed/YUY2)
     fn packetize_frame(&mut self, frame_data: &[u8], _expected_size: usize) -> Vec<Vec<u8>> {
-        let mut packets = Vec::new();
-        let mut offset = 0;
-
-        // Toggle FID for new frame
-        self.current_fid = !self.current_fid;
-
-        while offset < frame_data.len() {
-            let remaining = frame_data.len() - offset;
-            let payload_size = remaining.min(self.max_payload_size);
-            let is_last = offset + payload_size >= frame_data.len();
-
-            // Create header
-            let header = UvcHeader::minimal(self.current_fid, is_last);
-            let mut packet = header.to_bytes();
-
-            // Add payload
-            packet.extend_from_slice(&frame_data[offset..offset + payload_size]);
-
-            packets.push(packet);
-            offset += payload_size;
-        }
-
-        packets
+        self.packetize(frame_data)
     }
 
     /// Packetize MJPEG frame data into UVC packets
     fn packetize_frame_mjpeg(&mut self, jpeg_data: &[u8]) -> Vec<Vec<u8>> {
+        self.packetize(jpeg_data)
+    }
+
+    /// Split frame data into UVC packets, setting EOF on the last data packet
+    fn packetize(&mut self, frame_data: &[u8]) -> Vec<Vec<u8>> {
         let mut packets = Vec::new();
         let mut offset = 0;
 
         // Toggle FID for new frame
         self.current_fid = !self.current_fid;
 
-        while offset < jpeg_data.len() {
-            let remaining = jpeg_data.len() - offset;
-            let payload_size = remaining.min(self.max_payload_size);
-            let is_last = offset + payload_size >= jpeg_data.len();
+        while offset < frame_data.len() {
+            let remaining = frame_data.len() - offset;
+            let payload_size = match &mut self.payload_sizing {
+                None => self.max_payload_size,
+                Some((dist, rng)) => {
+                    if rng.chance(dist.empty_packet_rate) {
+                        packets.push(Vec::new());
+                        continue;
+                    }
+                    if rng.chance(dist.header_only_rate) {
+                        packets.push(UvcHeader::minimal(self.current_fid, false).to_bytes());
+                        continue;
+                    }
+                    let min = dist.min_payload_size.min(self.max_payload_size);
+                    if rng.chance(dist.short_packet_rate) && min < self.max_payload_size {
+                        min + rng.below((self.max_payload_size - min) as u64) as usize
+                    } else {
+                        self.max_payload_size
+                    }
+                }
+            };
+            let payload_size = remaining.min(payload_size);
+            let is_last = offset + payload_size >= frame_data.len();
 
             // Create header with EOF on last packet
             let header = UvcHeader::minimal(self.current_fid, is_last);
             let mut packet = header.to_bytes();
 
             // Add payload
-            packet.extend_from_slice(&jpeg_data[offset..offset + payload_size]);
+            packet.extend_from_slice(&frame_data[offset..offset + payload_size]);
 
             packets.push(packet);
             offset += payload_size;
@@ -563,6 +809,7 @@ impl PacketGenerator {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::test_utils::read_frame_counter;
 
     #[test]
     fn test_rgb_to_yuv_black() {
@@ -613,6 +860,15 @@ mod tests {
         assert_eq!(bytes[1] & 0x08, 0x08); // SCR present
     }
 
+    #[test]
+    fn test_uvc_header_error_flag() {
+        let mut header = UvcHeader::minimal(false, false);
+        header.error = true;
+        let bytes = header.to_bytes();
+        assert_eq!(bytes[1] & 0x40, 0x40); // ERR set
+        assert_eq!(bytes[1] & 0x80, 0x80); // EOH still set
+    }
+
     #[test]
     fn test_generate_yuy2_solid_size() {
         let gen = PacketGenerator::default();
@@ -881,4 +1137,181 @@ mod tests {
         assert!(u > 128, "Magenta should have U above neutral");
         assert!(v > 128, "Magenta should have V above neutral");
     }
+
+    #[test]
+    fn test_sequence_is_deterministic() {
+        let a = PacketGenerator::default().sequence(20, 42);
+        let b = PacketGenerator::default().sequence(20, 42);
+        assert_eq!(a.len(), 20);
+        for (fa, fb) in a.iter().zip(&b) {
+            assert_eq!((fa.box_x, fa.box_y), (fb.box_x, fb.box_y));
+            assert_eq!(fa.data, fb.data);
+            assert_eq!(fa.packets, fb.packets);
+        }
+    }
+
+    #[test]
+    fn test_sequence_seeds_differ() {
+        let a = PacketGenerator::default().sequence(5, 1);
+        let b = PacketGenerator::default().sequence(5, 2);
+        assert!(a.iter().zip(&b).any(|(fa, fb)| fa.data != fb.data));
+    }
+
+    #[test]
+    fn test_sequence_box_moves_within_bounds() {
+        for seed in 0..16 {
+            let frames = PacketGenerator::default().sequence(200, seed);
+            for pair in frames.windows(2) {
+                assert_ne!(
+                    (pair[0].box_x, pair[0].box_y),
+                    (pair[1].box_x, pair[1].box_y)
+                );
+                assert_ne!(pair[0].data, pair[1].data);
+            }
+            for frame in &frames {
+                assert_eq!(frame.box_x % 2, 0);
+                assert!(frame.box_x + SEQUENCE_BOX_SIZE <= SEQUENCE_WIDTH);
+                assert!(frame.box_y + SEQUENCE_BOX_SIZE <= SEQUENCE_HEIGHT);
+                assert_eq!(
+                    frame.data.len(),
+                    (SEQUENCE_WIDTH * SEQUENCE_HEIGHT * 2) as usize
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_sequence_pixels_match_ground_truth() {
+        let frames = PacketGenerator::default().sequence(30, 7);
+        let luma = |frame: &SequenceFrame, x: u32, y: u32| {
+            frame.data[((y * SEQUENCE_WIDTH + x) * 2) as usize]
+        };
+        let (plate_width, plate_height) = counter_plate_size();
+        let outside_plate = |x: u32, y: u32| {
+            x >= COUNTER_ORIGIN.0 + plate_width || y >= COUNTER_ORIGIN.1 + plate_height
+        };
+
+        for frame in &frames {
+            let (box_y, _, _) = frame.box_color.to_yuv();
+            let (black_y, _, _) = Rgb::BLACK.to_yuv();
+            for y in (0..SEQUENCE_HEIGHT).step_by(7) {
+                for x in (0..SEQUENCE_WIDTH).step_by(6) {
+                    if !outside_plate(x, y) {
+                        continue;
+                    }
+                    let expected = if frame.box_contains(x, y) {
+                        box_y
+                    } else {
+                        black_y
+                    };
+                    assert_eq!(
+                        luma(frame, x, y),
+                        expected,
+                        "frame {} ({}, {})",
+                        frame.index,
+                        x,
+                        y
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_sequence_counter_reads_back() {
+        let frames = PacketGenerator::default().sequence(25, 3);
+        for frame in &frames {
+            assert_eq!(
+                read_frame_counter(&frame.data, SEQUENCE_WIDTH),
+                Some(frame.index)
+            );
+        }
+
+        let gen = PacketGenerator::default();
+        let frame = SequenceFrame {
+            index: 1234,
+            box_x: 0,
+            box_y: 0,
+            box_color: Rgb::YELLOW,
+            data: Vec::new(),
+            packets: Vec::new(),
+        };
+        let data = gen.generate_yuy2_sequence_frame(SEQUENCE_WIDTH, SEQUENCE_HEIGHT, &frame);
+        assert_eq!(read_frame_counter(&data, SEQUENCE_WIDTH), Some(1234));
+    }
+
+    #[test]
+    fn test_sequence_packets_carry_frame() {
+        let frames = PacketGenerator::new(1024).sequence(3, 9);
+        for (i, frame) in frames.iter().enumerate() {
+            let payload: Vec<u8> = frame.packets.iter().flat_map(|p| p[2..].to_vec()).collect();
+            assert_eq!(payload, frame.data);
+
+            let last = frame.packets.last().unwrap();
+            assert_eq!(last[1] & 0x02, 0x02); // EOF on last packet
+            let fid = frame.packets[0][1] & 0x01;
+            assert_eq!(fid, u8::from(i % 2 == 0)); // FID toggles per frame
+        }
+    }
+
+    fn variable_generator(seed: u64) -> PacketGenerator {
+        PacketGenerator::new(512).with_payload_distribution(PayloadDistribution {
+            seed,
+            ..Default::default()
+        })
+    }
+
+    #[test]
+    fn test_variable_payloads_carry_frame() {
+        let mut gen = variable_generator(4);
+        let frame = gen.generate_yuy2_gradient(64, 48);
+        let packets = gen.yuy2_gradient_frame(64, 48);
+
+        let mut payload = Vec::new();
+        for packet in &packets {
+            if packet.is_empty() {
+                continue;
+            }
+            assert!(packet.len() - 2 <= 512, "payload exceeds max size");
+            payload.extend_from_slice(&packet[2..]);
+        }
+        assert_eq!(payload, frame);
+        // Only the last packet ends the frame
+        let eof_packets = packets
+            .iter()
+            .filter(|p| p.len() >= 2 && p[1] & 0x02 != 0)
+            .count();
+        assert_eq!(eof_packets, 1);
+        assert_eq!(packets.last().unwrap()[1] & 0x02, 0x02);
+    }
+
+    #[test]
+    fn test_variable_payloads_include_short_and_empty_packets() {
+        let packets = variable_generator(8).yuy2_gradient_frame(320, 240);
+        let empty = packets.iter().filter(|p| p.is_empty()).count();
+        let header_only = packets.iter().filter(|p| p.len() == 2).count();
+        let short = packets
+            .iter()
+            .filter(|p| p.len() > 2 && p.len() < 514)
+            .count();
+        let full = packets.iter().filter(|p| p.len() == 514).count();
+
+        assert!(empty > 0 && header_only > 0 && short > 1 && full > 0);
+    }
+
+    #[test]
+    fn test_variable_payloads_are_seeded() {
+        let a = variable_generator(1).yuy2_gradient_frame(64, 48);
+        let b = variable_generator(1).yuy2_gradient_frame(64, 48);
+        let c = variable_generator(2).yuy2_gradient_frame(64, 48);
+        assert_eq!(a, b);
+        assert_ne!(a, c);
+    }
+
+    #[test]
+    fn test_fixed_payloads_unchanged() {
+        let packets = PacketGenerator::new(512).yuy2_gradient_frame(64, 48);
+        assert_eq!(packets.len(), 12);
+        assert!(packets.iter().all(|p| p.len() == 514));
+    }
 }
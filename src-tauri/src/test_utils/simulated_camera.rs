@@ -0,0 +1,351 @@
+//! Simulated UVC camera with per-device quirk profiles
+//!
+//! [`PacketGenerator`](super::PacketGenerator) produces spec-compliant
+//! packets. Real endoscopes bend the spec in device-specific ways: short
+//! headers, zero-filled padding between frames, a frame ID that never
+//! toggles, payloads flagged with the error bit. A [`QuirkProfile`] captures
+//! one device's behavior, and [`SimulatedCamera`] replays any frame data
+//! through it, so a fix for a particular scope model gets a permanent
+//! emulated regression test.
+//!
+//! Profiles are plain JSON, so a new device is added by recording its
+//! behavior in a profile (see [`QuirkProfile::from_json`]) rather than code.
+//!
+//! # Example
+//!
+//! ```rust,ignore
+//! use clean_scope_lib::test_utils::{PacketGenerator, QuirkProfile, SimulatedCamera};
+//!
+//! let profile = QuirkProfile::named("zero-padding").unwrap();
+//! let mut camera = SimulatedCamera::new(profile, 42);
+//! for frame in PacketGenerator::default().sequence(30, 42) {
+//!     let simulated = camera.packetize(&frame.data);
+//!     // feed simulated.packets to the assembler
+//! }
+//! ```
+
+use serde::{Deserialize, Serialize};
+
+use super::packet_generator::{SplitMix64, UvcHeader};
+
+/// PTS increment per frame (90 kHz clock at 30 fps)
+const PTS_PER_FRAME: u32 = 3000;
+
+/// Payload header layout a device sends
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum HeaderStyle {
+    /// 2-byte headers without PTS or SCR
+    Minimal,
+    /// 12-byte headers with PTS and SCR
+    #[default]
+    Full,
+    /// No headers: raw frame bytes only (needs `FrameAssembler::set_headerless`)
+    None,
+}
+
+/// How a device drives the frame ID (FID) bit
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FidBehavior {
+    /// Toggles at every frame, per the spec
+    #[default]
+    Toggle,
+    /// Never toggles; frame boundaries are only visible from size or EOF
+    Constant,
+}
+
+/// Packet-level behavior of one camera model
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct QuirkProfile {
+    /// Profile name (a device model or the quirk it demonstrates)
+    pub name: String,
+    /// USB vendor ID of the device the profile was recorded from
+    pub vendor_id: Option<u16>,
+    /// USB product ID of the device the profile was recorded from
+    pub product_id: Option<u16>,
+    /// Maximum payload bytes per packet, excluding the header
+    pub max_payload_size: usize,
+    /// Payload header layout
+    pub header_style: HeaderStyle,
+    /// Frame ID behavior
+    pub fid_behavior: FidBehavior,
+    /// Zero-filled packets sent after each frame
+    pub padding_packets: u32,
+    /// Fraction of data packets flagged with the error bit (0.0 to 1.0)
+    pub error_packet_rate: f64,
+}
+
+impl Default for QuirkProfile {
+    /// A spec-compliant camera
+    fn default() -> Self {
+        Self {
+            name: "reference".to_string(),
+            vendor_id: None,
+            product_id: None,
+            max_payload_size: 3072,
+            header_style: HeaderStyle::Full,
+            fid_behavior: FidBehavior::Toggle,
+            padding_packets: 0,
+            error_packet_rate: 0.0,
+        }
+    }
+}
+
+impl QuirkProfile {
+    /// Built-in profiles, one per quirk the assembler is known to handle
+    pub fn builtin() -> Vec<QuirkProfile> {
+        vec![
+            QuirkProfile::default(),
+            // The "HD camera" endoscope in `device_filter.xml`, a YUY2-only
+            // scope that pads frames with zero-filled packets. Fields not
+            // yet recorded from it keep their reference values.
+            QuirkProfile {
+                name: "hd-camera-endoscope".to_string(),
+                vendor_id: Some(0x349c),
+                product_id: Some(0x0411),
+                padding_packets: 1,
+                ..Default::default()
+            },
+            QuirkProfile {
+                name: "minimal-headers".to_string(),
+                header_style: HeaderStyle::Minimal,
+                ..Default::default()
+            },
+            QuirkProfile {
+                name: "zero-padding".to_string(),
+                header_style: HeaderStyle::Minimal,
+                padding_packets: 2,
+                ..Default::default()
+            },
+            QuirkProfile {
+                name: "headerless".to_string(),
+                header_style: HeaderStyle::None,
+                ..Default::default()
+            },
+            QuirkProfile {
+                name: "error-packets".to_string(),
+                error_packet_rate: 0.02,
+                ..Default::default()
+            },
+        ]
+    }
+
+    /// Built-in profile by name
+    pub fn named(name: &str) -> Option<QuirkProfile> {
+        Self::builtin().into_iter().find(|p| p.name == name)
+    }
+
+    /// Profile recorded from the device with the given IDs
+    pub fn for_device(
+        profiles: &[QuirkProfile],
+        vendor_id: u16,
+        product_id: u16,
+    ) -> Option<&QuirkProfile> {
+        profiles
+            .iter()
+            .find(|p| p.vendor_id == Some(vendor_id) && p.product_id == Some(product_id))
+    }
+
+    /// Parse a profile from JSON; omitted fields keep their reference values
+    ///
+    /// # Errors
+    ///
+    /// Returns the parse error if the JSON doesn't describe a profile.
+    pub fn from_json(json: &str) -> Result<Self, serde_json::Error> {
+        serde_json::from_str(json)
+    }
+}
+
+/// Packets for one frame, as a simulated camera sent them
+#[derive(Debug, Clone)]
+pub struct SimulatedFrame {
+    /// Packets in transmission order, including padding
+    pub packets: Vec<Vec<u8>>,
+    /// Data packets flagged with the error bit
+    pub error_packets: usize,
+}
+
+/// A camera that packetizes frames according to a quirk profile
+#[derive(Debug)]
+pub struct SimulatedCamera {
+    profile: QuirkProfile,
+    rng: SplitMix64,
+    fid: bool,
+    pts: u32,
+}
+
+impl SimulatedCamera {
+    /// Create a camera; `seed` decides which packets get the error bit
+    pub fn new(profile: QuirkProfile, seed: u64) -> Self {
+        Self {
+            profile,
+            rng: SplitMix64(seed),
+            fid: false,
+            pts: 0,
+        }
+    }
+
+    /// Profile the camera emulates
+    pub fn profile(&self) -> &QuirkProfile {
+        &self.profile
+    }
+
+    /// Packetize one frame of raw data the way the profiled device would
+    pub fn packetize(&mut self, frame_data: &[u8]) -> SimulatedFrame {
+        if self.profile.fid_behavior == FidBehavior::Toggle {
+            self.fid = !self.fid;
+        }
+        self.pts = self.pts.wrapping_add(PTS_PER_FRAME);
+
+        let chunk_size = self.profile.max_payload_size.max(1);
+        let chunk_count = frame_data.len().div_ceil(chunk_size);
+        let mut packets = Vec::with_capacity(chunk_count + self.profile.padding_packets as usize);
+        let mut error_packets = 0;
+
+        for (i, chunk) in frame_data.chunks(chunk_size).enumerate() {
+            let eof = i + 1 == chunk_count;
+            let header = match self.profile.header_style {
+                HeaderStyle::Minimal => Some(UvcHeader::minimal(self.fid, eof)),
+                HeaderStyle::Full => Some(UvcHeader::full(self.fid, eof, self.pts)),
+                HeaderStyle::None => None,
+            };
+            let mut packet = match header {
+                // Without a header there is no error bit to set
+                Some(mut header) => {
+                    if self.rng.chance(self.profile.error_packet_rate) {
+                        header.error = true;
+                        error_packets += 1;
+                    }
+                    header.to_bytes()
+                }
+                None => Vec::with_capacity(chunk.len()),
+            };
+            packet.extend_from_slice(chunk);
+            packets.push(packet);
+        }
+
+        for _ in 0..self.profile.padding_packets {
+            packets.push(vec![0; chunk_size]);
+        }
+
+        SimulatedFrame {
+            packets,
+            error_packets,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn flags(packet: &[u8]) -> u8 {
+        packet[1]
+    }
+
+    #[test]
+    fn test_header_styles() {
+        for (style, len) in [(HeaderStyle::Minimal, 2), (HeaderStyle::Full, 12)] {
+            let profile = QuirkProfile {
+                header_style: style,
+                ..Default::default()
+            };
+            let frame = SimulatedCamera::new(profile, 0).packetize(&[7; 100]);
+            assert_eq!(frame.packets[0][0], len);
+            assert_eq!(frame.packets[0].len(), len as usize + 100);
+        }
+
+        let profile = QuirkProfile::named("headerless").unwrap();
+        let frame = SimulatedCamera::new(profile, 0).packetize(&[7; 100]);
+        assert_eq!(frame.packets, vec![vec![7; 100]]);
+    }
+
+    #[test]
+    fn test_fid_behavior() {
+        let toggling = QuirkProfile::default();
+        let mut camera = SimulatedCamera::new(toggling, 0);
+        let a = camera.packetize(&[1; 10]);
+        let b = camera.packetize(&[1; 10]);
+        assert_ne!(flags(&a.packets[0]) & 0x01, flags(&b.packets[0]) & 0x01);
+
+        let constant = QuirkProfile {
+            fid_behavior: FidBehavior::Constant,
+            ..Default::default()
+        };
+        let mut camera = SimulatedCamera::new(constant, 0);
+        let a = camera.packetize(&[1; 10]);
+        let b = camera.packetize(&[1; 10]);
+        assert_eq!(flags(&a.packets[0]) & 0x01, flags(&b.packets[0]) & 0x01);
+    }
+
+    #[test]
+    fn test_padding_follows_frame() {
+        let profile = QuirkProfile::named("zero-padding").unwrap();
+        let payload = profile.max_payload_size;
+        let frame = SimulatedCamera::new(profile, 0).packetize(&vec![9; payload * 3]);
+
+        assert_eq!(frame.packets.len(), 5);
+        assert_eq!(flags(&frame.packets[2]) & 0x02, 0x02); // EOF on last data packet
+        assert!(frame.packets[3..].iter().all(|p| p.iter().all(|&b| b == 0)));
+    }
+
+    #[test]
+    fn test_error_rate_is_seeded() {
+        let profile = QuirkProfile {
+            max_payload_size: 10,
+            error_packet_rate: 0.25,
+            ..Default::default()
+        };
+        let data = vec![3; 10_000];
+        let a = SimulatedCamera::new(profile.clone(), 1).packetize(&data);
+        let b = SimulatedCamera::new(profile, 1).packetize(&data);
+
+        assert_eq!(a.packets, b.packets);
+        // 1000 packets at 25%: well within these bounds for any seed
+        assert!((150..350).contains(&a.error_packets), "{}", a.error_packets);
+        let flagged = a.packets.iter().filter(|p| flags(p) & 0x40 != 0).count();
+        assert_eq!(flagged, a.error_packets);
+    }
+
+    #[test]
+    fn test_profile_lookup() {
+        let profiles = vec![QuirkProfile {
+            name: "scope".to_string(),
+            vendor_id: Some(0x1234),
+            product_id: Some(0x5678),
+            ..Default::default()
+        }];
+        assert_eq!(
+            QuirkProfile::for_device(&profiles, 0x1234, 0x5678).map(|p| p.name.as_str()),
+            Some("scope")
+        );
+        assert!(QuirkProfile::for_device(&profiles, 0x1234, 0x0001).is_none());
+        assert!(QuirkProfile::named("reference").is_some());
+        assert_eq!(
+            QuirkProfile::for_device(&QuirkProfile::builtin(), 0x349c, 0x0411)
+                .map(|p| p.name.as_str()),
+            Some("hd-camera-endoscope")
+        );
+        assert!(QuirkProfile::named("missing").is_none());
+    }
+
+    #[test]
+    fn test_profile_from_json_defaults() {
+        let profile = QuirkProfile::from_json(
+            r#"{"name": "scope", "vendor_id": 4660, "header_style": "minimal", "padding_packets": 1}"#,
+        )
+        .unwrap();
+        assert_eq!(profile.name, "scope");
+        assert_eq!(profile.vendor_id, Some(0x1234));
+        assert_eq!(profile.header_style, HeaderStyle::Minimal);
+        assert_eq!(profile.padding_packets, 1);
+        assert_eq!(profile.fid_behavior, FidBehavior::Toggle);
+        assert_eq!(
+            profile.max_payload_size,
+            QuirkProfile::default().max_payload_size
+        );
+        assert!(QuirkProfile::from_json(r#"{"header_style": "huge"}"#).is_err());
+    }
+}
@@ -0,0 +1,350 @@
+//! Frame rotation and flips
+//!
+//! Endoscopes are held at arbitrary orientations, so frames can be rotated
+//! clockwise in 90° steps and flipped, set with `set_rotation` and
+//! `set_flip`. The flips apply after the rotation, i.e. along the axes of the
+//! displayed frame. The streaming pipeline applies the [`Transform`] to RGB
+//! frames before plugins, recording and the frame buffer see them (MJPEG
+//! frames are decoded first).
+//!
+//! YUY2 frames can be transformed before conversion with
+//! [`Transform::apply_yuy2`]. Each pixel pair shares one U/V sample, so after
+//! a 90° rotation the pairs' chroma is the average of the two source pixels'.
+
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+/// Errors transforming a frame
+#[derive(Debug, Error)]
+pub enum TransformError {
+    /// Rotation is not a multiple of 90°
+    #[error("rotation must be 0, 90, 180 or 270 degrees, got {0}")]
+    InvalidRotation(u32),
+    /// Buffer is smaller than the dimensions need
+    #[error("{len} bytes is too small for a {width}x{height} frame")]
+    FrameSize {
+        /// Buffer length
+        len: usize,
+        /// Frame width
+        width: u32,
+        /// Frame height
+        height: u32,
+    },
+    /// YUY2 output would have an odd width
+    #[error("YUY2 frames need an even width, the result would be {0} pixels wide")]
+    OddWidth(u32),
+}
+
+/// Result type alias for transform operations
+pub type Result<T> = std::result::Result<T, TransformError>;
+
+/// Clockwise rotation, serialized as degrees
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(into = "u32", try_from = "u32")]
+pub enum Rotation {
+    /// Camera orientation
+    #[default]
+    None,
+    /// 90° clockwise
+    Cw90,
+    /// Upside down
+    Cw180,
+    /// 270° clockwise (90° counter-clockwise)
+    Cw270,
+}
+
+impl Rotation {
+    /// Rotation by `degrees` (0, 90, 180 or 270)
+    ///
+    /// # Errors
+    ///
+    /// Returns `TransformError::InvalidRotation` for any other angle.
+    pub fn from_degrees(degrees: u32) -> Result<Self> {
+        match degrees {
+            0 => Ok(Rotation::None),
+            90 => Ok(Rotation::Cw90),
+            180 => Ok(Rotation::Cw180),
+            270 => Ok(Rotation::Cw270),
+            other => Err(TransformError::InvalidRotation(other)),
+        }
+    }
+
+    /// Clockwise angle in degrees
+    #[must_use]
+    pub fn degrees(self) -> u32 {
+        match self {
+            Rotation::None => 0,
+            Rotation::Cw90 => 90,
+            Rotation::Cw180 => 180,
+            Rotation::Cw270 => 270,
+        }
+    }
+
+    /// Whether width and height swap
+    #[must_use]
+    pub fn is_quarter_turn(self) -> bool {
+        matches!(self, Rotation::Cw90 | Rotation::Cw270)
+    }
+}
+
+impl From<Rotation> for u32 {
+    fn from(rotation: Rotation) -> Self {
+        rotation.degrees()
+    }
+}
+
+impl TryFrom<u32> for Rotation {
+    type Error = TransformError;
+
+    fn try_from(degrees: u32) -> Result<Self> {
+        Self::from_degrees(degrees)
+    }
+}
+
+/// Orientation applied to every frame
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Transform {
+    /// Clockwise rotation
+    pub rotation: Rotation,
+    /// Mirror left and right (after rotating)
+    pub flip_horizontal: bool,
+    /// Mirror top and bottom (after rotating)
+    pub flip_vertical: bool,
+}
+
+impl Transform {
+    /// Whether frames pass through unchanged
+    #[must_use]
+    pub fn is_identity(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// Dimensions of a transformed `width` x `height` frame
+    #[must_use]
+    pub fn output_size(&self, width: u32, height: u32) -> (u32, u32) {
+        if self.rotation.is_quarter_turn() {
+            (height, width)
+        } else {
+            (width, height)
+        }
+    }
+
+    /// Source pixel of output pixel (`x`, `y`) for a `width` x `height` input
+    fn source(&self, x: usize, y: usize, width: usize, height: usize) -> (usize, usize) {
+        let (out_w, out_h) = if self.rotation.is_quarter_turn() {
+            (height, width)
+        } else {
+            (width, height)
+        };
+        let x = if self.flip_horizontal {
+            out_w - 1 - x
+        } else {
+            x
+        };
+        let y = if self.flip_vertical { out_h - 1 - y } else { y };
+        match self.rotation {
+            Rotation::None => (x, y),
+            Rotation::Cw90 => (y, height - 1 - x),
+            Rotation::Cw180 => (width - 1 - x, height - 1 - y),
+            Rotation::Cw270 => (width - 1 - y, x),
+        }
+    }
+
+    /// Transform an RGB24 frame, returning it with its new dimensions
+    ///
+    /// # Errors
+    ///
+    /// Returns `TransformError::FrameSize` if `rgb` is smaller than
+    /// `width * height * 3`.
+    pub fn apply_rgb(&self, rgb: &[u8], width: u32, height: u32) -> Result<(Vec<u8>, u32, u32)> {
+        let (w, h) = (width as usize, height as usize);
+        check_size(rgb.len(), w * h * 3, width, height)?;
+
+        let (out_w, out_h) = self.output_size(width, height);
+        let mut out = Vec::with_capacity(w * h * 3);
+        for y in 0..out_h as usize {
+            for x in 0..out_w as usize {
+                let (sx, sy) = self.source(x, y, w, h);
+                let i = (sy * w + sx) * 3;
+                out.extend_from_slice(&rgb[i..i + 3]);
+            }
+        }
+        Ok((out, out_w, out_h))
+    }
+
+    /// Transform a YUY2 (YUYV) frame with rows `stride` bytes apart
+    ///
+    /// The result is tightly packed (stride `2 * width`).
+    ///
+    /// # Errors
+    ///
+    /// Returns `TransformError::FrameSize` if `data` is smaller than the
+    /// stride and height need, or `TransformError::OddWidth` if the
+    /// transformed width is odd.
+    pub fn apply_yuy2(
+        &self,
+        data: &[u8],
+        width: u32,
+        height: u32,
+        stride: u32,
+    ) -> Result<(Vec<u8>, u32, u32)> {
+        let (w, h, stride) = (width as usize, height as usize, stride as usize);
+        let needed = if h == 0 { 0 } else { stride * (h - 1) + w * 2 };
+        if stride < w * 2 {
+            return Err(TransformError::FrameSize {
+                len: stride,
+                width,
+                height: 1,
+            });
+        }
+        check_size(data.len(), needed, width, height)?;
+        let (out_w, out_h) = self.output_size(width, height);
+        if out_w % 2 != 0 {
+            return Err(TransformError::OddWidth(out_w));
+        }
+
+        // Y, U and V of a source pixel
+        let sample = |x: usize, y: usize| -> [u8; 3] {
+            let pair = y * stride + (x / 2) * 4;
+            [data[pair + (x % 2) * 2], data[pair + 1], data[pair + 3]]
+        };
+        let mut out = Vec::with_capacity(out_w as usize * out_h as usize * 2);
+        for y in 0..out_h as usize {
+            for x in (0..out_w as usize).step_by(2) {
+                let (lx, ly) = self.source(x, y, w, h);
+                let (rx, ry) = self.source(x + 1, y, w, h);
+                let [y0, u0, v0] = sample(lx, ly);
+                let [y1, u1, v1] = sample(rx, ry);
+                out.extend_from_slice(&[y0, average(u0, u1), y1, average(v0, v1)]);
+            }
+        }
+        Ok((out, out_w, out_h))
+    }
+}
+
+fn check_size(len: usize, needed: usize, width: u32, height: u32) -> Result<()> {
+    if len < needed {
+        return Err(TransformError::FrameSize { len, width, height });
+    }
+    Ok(())
+}
+
+fn average(a: u8, b: u8) -> u8 {
+    (u16::from(a) + u16::from(b)).div_ceil(2) as u8
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 3x2 RGB frame whose pixels are numbered 0-5 in the red channel
+    fn numbered() -> Vec<u8> {
+        (0..6).flat_map(|i| [i, 0, 0]).collect()
+    }
+
+    fn reds(rgb: &[u8]) -> Vec<u8> {
+        rgb.chunks_exact(3).map(|p| p[0]).collect()
+    }
+
+    fn transform(degrees: u32, flip_horizontal: bool, flip_vertical: bool) -> Transform {
+        Transform {
+            rotation: Rotation::from_degrees(degrees).unwrap(),
+            flip_horizontal,
+            flip_vertical,
+        }
+    }
+
+    #[test]
+    fn test_rgb_rotations() {
+        // 0 1 2
+        // 3 4 5
+        let cases = [
+            (0, (3, 2), vec![0, 1, 2, 3, 4, 5]),
+            (90, (2, 3), vec![3, 0, 4, 1, 5, 2]),
+            (180, (3, 2), vec![5, 4, 3, 2, 1, 0]),
+            (270, (2, 3), vec![2, 5, 1, 4, 0, 3]),
+        ];
+        for (degrees, size, expected) in cases {
+            let (rgb, w, h) = transform(degrees, false, false)
+                .apply_rgb(&numbered(), 3, 2)
+                .unwrap();
+            assert_eq!((w, h), size, "{} degrees", degrees);
+            assert_eq!(reds(&rgb), expected, "{} degrees", degrees);
+        }
+    }
+
+    #[test]
+    fn test_rgb_flips() {
+        let (rgb, _, _) = transform(0, true, false)
+            .apply_rgb(&numbered(), 3, 2)
+            .unwrap();
+        assert_eq!(reds(&rgb), [2, 1, 0, 5, 4, 3]);
+
+        let (rgb, _, _) = transform(0, false, true)
+            .apply_rgb(&numbered(), 3, 2)
+            .unwrap();
+        assert_eq!(reds(&rgb), [3, 4, 5, 0, 1, 2]);
+
+        // Flips apply to the rotated frame
+        let (rgb, w, h) = transform(90, true, false)
+            .apply_rgb(&numbered(), 3, 2)
+            .unwrap();
+        assert_eq!((w, h), (2, 3));
+        assert_eq!(reds(&rgb), [0, 3, 1, 4, 2, 5]);
+
+        // Both flips are a half turn
+        let both = transform(0, true, true)
+            .apply_rgb(&numbered(), 3, 2)
+            .unwrap();
+        let half = transform(180, false, false)
+            .apply_rgb(&numbered(), 3, 2)
+            .unwrap();
+        assert_eq!(both, half);
+    }
+
+    #[test]
+    fn test_yuy2_rotation() {
+        // 2x2 frame with 2 bytes of row padding
+        let data = [
+            10, 100, 20, 200, 0, 0, //
+            30, 110, 40, 210, 0, 0,
+        ];
+        let (out, w, h) = transform(90, false, false)
+            .apply_yuy2(&data, 2, 2, 6)
+            .unwrap();
+        assert_eq!((w, h), (2, 2));
+        // Rows become columns; chroma averages the two source rows
+        assert_eq!(out, [30, 105, 10, 205, 40, 105, 20, 205]);
+
+        let (out, _, _) = transform(0, true, false)
+            .apply_yuy2(&data, 2, 2, 6)
+            .unwrap();
+        assert_eq!(&out[..4], &[20, 100, 10, 200]);
+    }
+
+    #[test]
+    fn test_invalid_input() {
+        assert!(matches!(
+            Rotation::from_degrees(45),
+            Err(TransformError::InvalidRotation(45))
+        ));
+        assert!(matches!(
+            transform(90, false, false).apply_rgb(&[0; 5], 3, 2),
+            Err(TransformError::FrameSize { len: 5, .. })
+        ));
+        // 4x3 rotated is 3 pixels wide, which YUY2 can't pair up
+        assert!(matches!(
+            transform(90, false, false).apply_yuy2(&[0; 24], 4, 3, 8),
+            Err(TransformError::OddWidth(3))
+        ));
+    }
+
+    #[test]
+    fn test_serializes_rotation_as_degrees() {
+        let json = serde_json::to_value(transform(270, true, false)).unwrap();
+        assert_eq!(json["rotation"], 270);
+        let parsed: Transform = serde_json::from_value(json).unwrap();
+        assert_eq!(parsed, transform(270, true, false));
+        assert!(serde_json::from_str::<Rotation>("45").is_err());
+    }
+}
@@ -1,35 +1,46 @@
 //! USB device handling for `CleanScope`
 //!
 //! This module handles USB device detection, permission management,
-//! and UVC camera streaming on Android.
+//! and UVC camera streaming on Android. The frame processing helpers are
+//! shared with the desktop backend in `usb_desktop` (`desktop-usb` feature).
 
 use std::sync::{Arc, Mutex};
-use tauri::AppHandle;
 
 #[cfg(target_os = "android")]
-use tauri::Emitter;
+use std::sync::atomic::{AtomicBool, Ordering};
+#[cfg(target_os = "android")]
+use std::sync::{mpsc, Condvar, OnceLock};
+use tauri::AppHandle;
 
 #[cfg(target_os = "android")]
-use crate::frame_assembler::is_jpeg_data;
+use crate::bulk_transfer::{StreakChange, TimeoutStreak};
+use crate::capture::CaptureState;
+#[cfg(target_os = "android")]
+use crate::frame_assembler::{is_jpeg_data, FrameAssembler, ProcessResult};
+#[cfg(target_os = "android")]
+use crate::frame_broadcast::FrameCursor;
+use crate::frame_trace::FrameTracer;
+use crate::lifecycle::StopSignal;
+#[cfg(usb_streaming)]
+use crate::messages::MessageCode;
+#[cfg(usb_streaming)]
+use crate::raw_video::FrameLayout;
+#[cfg(usb_streaming)]
+use crate::recording::FrameFormat;
+use crate::recording::RecordingState;
+use crate::spool::FrameSpooler;
+use crate::stream_health::StreamHealth;
+use crate::usb_permission::PermissionCache;
 use crate::{DisplayConfig, FrameBuffer, StreamingConfig, ValidationLevel};
 
-/// Lock a mutex with poison recovery.
-///
-/// If the mutex is poisoned (a thread panicked while holding it), recovers the
-/// inner value and logs a warning instead of panicking.
+/// Lock a mutex with poison recovery (see [`crate::lock_or_recover`]).
 macro_rules! lock_or_recover {
     ($mutex:expr) => {
-        match $mutex.lock() {
-            Ok(guard) => guard,
-            Err(poisoned) => {
-                log::error!("Mutex poisoned, recovering");
-                poisoned.into_inner()
-            }
-        }
+        crate::lock_or_recover(&$mutex)
     };
 }
 
-#[cfg(target_os = "android")]
+#[cfg(usb_streaming)]
 use crate::{DisplaySettings, PixelFormat};
 
 /// Context for USB streaming operations
@@ -41,36 +52,82 @@ pub struct StreamingContext {
     /// Tauri app handle for emitting events
     pub app_handle: AppHandle,
     /// Shared buffer for storing processed frames
-    pub frame_buffer: Arc<Mutex<FrameBuffer>>,
+    pub frame_buffer: Arc<FrameBuffer>,
     /// Consolidated display configuration (settings + cycling indexes)
     pub display: Arc<Mutex<DisplayConfig>>,
     /// Streaming configuration (format selection, pixel format)
     pub streaming_config: Arc<Mutex<StreamingConfig>>,
     /// Flag to signal USB streaming should stop
     pub stop_flag: Arc<std::sync::atomic::AtomicBool>,
-    /// Frame validation level
-    pub validation_level: ValidationLevel,
+    /// Frame validation level, read for every frame (see `set_validation_level`)
+    pub validation_level: Arc<Mutex<ValidationLevel>>,
+    /// Packet capture state (raw payload recording)
+    pub capture_state: Arc<CaptureState>,
+    /// Recorder for processed frames
+    pub recording: Arc<RecordingState>,
+    /// USB permission state per device
+    pub usb_permissions: Arc<Mutex<PermissionCache>>,
+    /// Frame delivery statistics
+    pub stream_health: Arc<StreamHealth>,
+    /// Frame assembly statistics
+    pub stream_stats: Arc<crate::stats::StreamStats>,
+    /// Background writer for every Nth frame
+    pub spooler: Arc<FrameSpooler>,
+    /// One-frame pipeline trace, armed by `trace_next_frame`
+    pub frame_tracer: Arc<FrameTracer>,
+    /// Third-party frame processors run on every RGB frame
+    pub plugins: Arc<crate::plugins::PluginHost>,
+    /// Annotations drawn into recordings when enabled
+    pub annotations: Arc<crate::annotations::AnnotationBus>,
+    /// Image controls, attached while a camera is open
+    pub camera_controls: Arc<crate::uvc_controls::CameraControls>,
+    /// Still image capture, attached while a camera is open
+    pub still_capture: Arc<crate::still_capture::StillCapture>,
+    /// Connected cameras and the one chosen with `select_device`
+    pub devices: Arc<Mutex<crate::devices::DeviceRegistry>>,
+    /// Re-probes the camera after a run of rejected frames
+    pub stream_supervisor: Arc<crate::stream_supervisor::StreamSupervisor>,
+    /// RGB buffer left by the pipeline warm-up, converted into by the first
+    /// frame of the stream
+    pub conversion_buffer: Arc<Mutex<Vec<u8>>>,
 }
 
 #[cfg(target_os = "android")]
 use jni::{
-    objects::{JClass, JObject, JValue},
+    objects::{GlobalRef, JClass, JObject, JString, JValue},
     sys::jint,
-    JNIEnv,
+    JNIEnv, JavaVM,
 };
 
+#[cfg(target_os = "android")]
+use crate::jni_helpers::{self, JniError};
+
+#[cfg(target_os = "android")]
+use crate::usb_permission::{DeviceKey, PermissionAction};
+#[cfg(target_os = "android")]
+use crate::AppError;
+
+#[cfg(target_os = "android")]
+use crate::usb_connection::JniUsbConnection;
+
 #[cfg(target_os = "android")]
 use crate::libusb_android::{
-    uvc, EndpointInfo, IsochronousStream, LibusbContext, LibusbDeviceHandle, LibusbError,
-    SendableContextPtr, TransferType,
+    packets_per_transfer, uvc, BulkStream, EndpointInfo, IsochronousStream, LibusbContext,
+    LibusbDeviceHandle, LibusbError, SendableContextPtr, TransferType, VideoStream,
 };
+#[cfg(usb_streaming)]
+use crate::settings::PreferredResolution;
+#[cfg(usb_streaming)]
+use crate::uvc_descriptors::FormatCatalog;
+#[cfg(target_os = "android")]
+use crate::uvc_descriptors::FormatKind;
 
 // YUV conversion functions are in the yuv_conversion module (platform-independent)
+#[cfg(usb_streaming)]
+use crate::yuv_conversion::convert_to_rgb_into;
+
 #[cfg(target_os = "android")]
-use crate::yuv_conversion::{
-    convert_bgr888_to_rgb, convert_i420_to_rgb, convert_nv12_to_rgb, convert_yuv422_to_rgb,
-    pass_through_rgb888, YuvPackedFormat,
-};
+use crate::warmup::WarmupPlan;
 
 /// Event loop timeout for libusb event handling (100ms)
 #[cfg(target_os = "android")]
@@ -86,21 +143,25 @@ const FRAME_RECV_TIMEOUT_SECS: u64 = 5;
 const FORMAT_DETECTION_TIMEOUT_SECS: u64 = 2;
 
 /// Log frame count progress every N frames
-#[cfg(target_os = "android")]
+#[cfg(usb_streaming)]
 const LOG_INTERVAL_FRAMES: u32 = 30;
 
 /// Number of initial frames to log detailed analysis for
-#[cfg(target_os = "android")]
+#[cfg(usb_streaming)]
 const INITIAL_FRAMES_TO_LOG: u32 = 5;
 
 /// Number of initial frames to log conversion errors for
-#[cfg(target_os = "android")]
+#[cfg(usb_streaming)]
 const INITIAL_FRAMES_TO_LOG_ERRORS: u32 = 5;
 
 /// Settle time after restart or reconnect (milliseconds)
 #[cfg(target_os = "android")]
 const SETTLE_MS: u64 = 100;
 
+/// How often background frame consumers check the stop flag (milliseconds)
+#[cfg(target_os = "android")]
+const CONSUMER_POLL_MS: u64 = 250;
+
 /// UVC streaming interface index
 #[cfg(target_os = "android")]
 const UVC_STREAMING_INTERFACE: u16 = 1;
@@ -125,18 +186,10 @@ const DEFAULT_HEIGHT: u16 = 480;
 #[cfg(target_os = "android")]
 const DEFAULT_ENDPOINT_ADDR: u8 = 0x81;
 
-/// Bulk transfer buffer size (16KB per transfer)
-#[cfg(target_os = "android")]
-const BULK_TRANSFER_BUFFER_SIZE: usize = 16384;
-
 /// Initial capacity for frame accumulation buffer (1MB)
 #[cfg(target_os = "android")]
 const FRAME_ACCUMULATION_CAPACITY: usize = 1024 * 1024;
 
-/// Bulk transfer timeout (milliseconds)
-#[cfg(target_os = "android")]
-const BULK_TRANSFER_TIMEOUT_MS: u32 = 1000;
-
 /// Minimum UVC header size for payload parsing
 #[cfg(target_os = "android")]
 const UVC_HEADER_MIN_SIZE: usize = 12;
@@ -212,93 +265,123 @@ fn spawn_libusb_event_loop(
         .expect("Failed to spawn event loop thread")
 }
 
-/// Initialize the USB handler
-/// This is called from the main thread during app setup
-pub fn init_usb_handler(ctx: StreamingContext) {
+/// Run the USB handler until `stop` is signalled
+///
+/// Runs on the `usb-camera` lifecycle thread and returns only once the
+/// camera loop it started has exited, so no USB thread outlives shutdown.
+pub fn init_usb_handler(ctx: StreamingContext, stop: &StopSignal) {
     log::info!("Initializing USB handler");
 
     #[cfg(target_os = "android")]
     {
-        // On Android, we need to get the USB file descriptor via JNI
-        if let Some(fd) = get_usb_file_descriptor() {
-            log::info!("USB device found with fd: {}", fd);
-            crate::emit_usb_event(
-                &ctx.app_handle,
-                true,
-                Some(format!("USB Camera (fd: {})", fd)),
-            );
-
-            // Start the camera streaming loop in a new thread
-            std::thread::spawn(move || {
-                run_camera_loop(fd, ctx);
-            });
-        } else {
-            log::info!("No USB device found on startup");
+        // The supervisor keeps the context so hot-plug events can start
+        // streaming later, even when no camera is present at startup
+        let supervisor = SUPERVISOR.get_or_init(|| UsbSupervisor::new(ctx));
+        let (sender, events) = mpsc::channel();
+        if HOTPLUG_EVENTS.set(sender).is_err() {
+            log::warn!("USB handler already initialized");
+            return;
         }
+        supervisor.start_camera_loop();
+
+        // This thread now serves the JNI hot-plug callbacks, one event at a
+        // time. HOTPLUG_EVENTS keeps the sender alive, so the channel never
+        // closes; recv returns once the app is shutting down.
+        while let Some(event) = stop.recv(&events) {
+            log::info!("USB hot-plug event: {:?}", event);
+            match event {
+                HotplugEvent::Attached => supervisor.start_camera_loop(),
+                HotplugEvent::Detached(device_name) => {
+                    supervisor.device_detached(device_name.as_deref())
+                }
+            }
+        }
+        supervisor.join_camera_loop();
+    }
+
+    #[cfg(all(feature = "desktop-usb", not(target_os = "android")))]
+    {
+        // On desktop, rusb finds the camera itself and waits for one to be plugged in
+        crate::usb_desktop::run_camera_loop(ctx, stop);
     }
 
-    #[cfg(not(target_os = "android"))]
+    #[cfg(not(usb_streaming))]
     {
-        let _ = ctx; // Suppress unused warning
+        let _ = (ctx, stop); // Suppress unused warning
         log::info!("USB handling not available on this platform");
     }
 }
 
+/// List the connected UVC cameras
+///
+/// # Errors
+///
+/// Returns `AppError` if the platform's USB device list can't be read.
+pub fn list_cameras() -> Result<Vec<crate::devices::CameraDevice>, crate::AppError> {
+    #[cfg(target_os = "android")]
+    {
+        jni_helpers::with_activity(|env, activity| {
+            let usb_manager = get_usb_manager(env, activity)?;
+            Ok(list_cameras_from_manager(env, &usb_manager)?)
+        })
+    }
+
+    #[cfg(all(feature = "desktop-usb", not(target_os = "android")))]
+    {
+        Ok(crate::usb_desktop::list_cameras()?)
+    }
+
+    #[cfg(not(usb_streaming))]
+    {
+        Ok(Vec::new())
+    }
+}
+
 /// Get the device name from the USB device in the intent that launched this activity.
 /// Returns the device name (e.g., "/dev/bus/usb/001/002") if launched via USB_DEVICE_ATTACHED.
 #[cfg(target_os = "android")]
-fn get_device_name_from_intent(env: &mut JNIEnv, activity: &JObject) -> Option<String> {
+fn get_device_name_from_intent(
+    env: &mut JNIEnv,
+    activity: &JObject,
+) -> Result<Option<String>, JniError> {
     // Get the launching intent
-    let intent = env
-        .call_method(activity, "getIntent", "()Landroid/content/Intent;", &[])
-        .ok()?
-        .l()
-        .ok()?;
+    let intent = jni_helpers::call_object(
+        env,
+        activity,
+        "getIntent",
+        "()Landroid/content/Intent;",
+        &[],
+    )?;
 
     if intent.is_null() {
         log::info!("No intent available");
-        return None;
+        return Ok(None);
     }
 
     // Get UsbManager.EXTRA_DEVICE constant ("device")
-    let extra_device_key = env
-        .get_static_field(
-            "android/hardware/usb/UsbManager",
-            "EXTRA_DEVICE",
-            "Ljava/lang/String;",
-        )
-        .ok()?
-        .l()
-        .ok()?;
+    let extra_device_key =
+        jni_helpers::static_string_field(env, "android/hardware/usb/UsbManager", "EXTRA_DEVICE")?;
 
     // Get the UsbDevice from intent extras
-    let intent_device = env
-        .call_method(
-            &intent,
-            "getParcelableExtra",
-            "(Ljava/lang/String;)Landroid/os/Parcelable;",
-            &[JValue::Object(&extra_device_key)],
-        )
-        .ok()?
-        .l()
-        .ok()?;
+    let intent_device = jni_helpers::call_object(
+        env,
+        &intent,
+        "getParcelableExtra",
+        "(Ljava/lang/String;)Landroid/os/Parcelable;",
+        &[JValue::Object(&extra_device_key)],
+    )?;
 
     if intent_device.is_null() {
         log::info!("No USB device in intent extras");
-        return None;
+        return Ok(None);
     }
 
     // Get the device name from the intent's UsbDevice
-    let device_name_jstring = env
-        .call_method(&intent_device, "getDeviceName", "()Ljava/lang/String;", &[])
-        .ok()?
-        .l()
-        .ok()?;
-
-    let device_name: String = env.get_string((&device_name_jstring).into()).ok()?.into();
-
-    log::info!("Intent has USB device: {}", device_name);
-    Some(device_name)
+    let device_name = jni_helpers::call_string(env, &intent_device, "getDeviceName")?;
+    if let Some(name) = &device_name {
+        log::info!("Intent has USB device: {}", name);
+    }
+    Ok(device_name)
 }
 
 /// Get USB device from UsbManager.getDeviceList(), optionally matching a specific device name.
@@ -308,140 +391,417 @@ fn get_device_from_manager<'a>(
     env: &mut JNIEnv<'a>,
     usb_manager: &JObject,
     target_device_name: Option<&str>,
-) -> Option<JObject<'a>> {
+) -> Result<Option<JObject<'a>>, JniError> {
     // Get the device list as a HashMap<String, UsbDevice>
-    let device_map = env
-        .call_method(usb_manager, "getDeviceList", "()Ljava/util/HashMap;", &[])
-        .ok()?
-        .l()
-        .ok()?;
+    let device_map = jni_helpers::call_non_null(
+        env,
+        usb_manager,
+        "getDeviceList",
+        "()Ljava/util/HashMap;",
+        &[],
+    )?;
 
     // If we have a target device name, try to get it directly from the map
     if let Some(target_name) = target_device_name {
-        let key = env.new_string(target_name).ok()?;
-        let device = env
-            .call_method(
-                &device_map,
-                "get",
-                "(Ljava/lang/Object;)Ljava/lang/Object;",
-                &[JValue::Object(&key)],
-            )
-            .ok()?
-            .l()
-            .ok()?;
+        let key = jni_helpers::new_string(env, target_name)?;
+        let device = jni_helpers::call_object(
+            env,
+            &device_map,
+            "get",
+            "(Ljava/lang/Object;)Ljava/lang/Object;",
+            &[JValue::Object(&key)],
+        )?;
 
         if !device.is_null() {
             log::info!("Found target device in device list: {}", target_name);
-            return Some(device);
+            return Ok(Some(device));
         }
         log::warn!("Target device {} not found in device list", target_name);
     }
 
     // Fallback: get first available device
-    let values = env
-        .call_method(&device_map, "values", "()Ljava/util/Collection;", &[])
-        .ok()?
-        .l()
-        .ok()?;
-
-    let iterator = env
-        .call_method(&values, "iterator", "()Ljava/util/Iterator;", &[])
-        .ok()?
-        .l()
-        .ok()?;
-
-    let has_next = env
-        .call_method(&iterator, "hasNext", "()Z", &[])
-        .ok()?
-        .z()
-        .ok()?;
-
-    if !has_next {
+    let values =
+        jni_helpers::call_non_null(env, &device_map, "values", "()Ljava/util/Collection;", &[])?;
+    let iterator =
+        jni_helpers::call_non_null(env, &values, "iterator", "()Ljava/util/Iterator;", &[])?;
+
+    if !jni_helpers::call_bool(env, &iterator, "hasNext", "()Z", &[])? {
         log::info!("No USB devices in device list");
-        return None;
+        return Ok(None);
     }
 
-    let device = env
-        .call_method(&iterator, "next", "()Ljava/lang/Object;", &[])
-        .ok()?
-        .l()
-        .ok()?;
+    let device = jni_helpers::call_non_null(env, &iterator, "next", "()Ljava/lang/Object;", &[])?;
 
     log::info!("Got first USB device from device list");
-    Some(device)
+    Ok(Some(device))
+}
+
+/// USB interface class of video devices
+#[cfg(target_os = "android")]
+const USB_CLASS_VIDEO: i32 = 0x0E;
+
+/// The UVC cameras in `UsbManager.getDeviceList()`
+#[cfg(target_os = "android")]
+fn list_cameras_from_manager(
+    env: &mut JNIEnv,
+    usb_manager: &JObject,
+) -> Result<Vec<crate::devices::CameraDevice>, JniError> {
+    let device_map = jni_helpers::call_non_null(
+        env,
+        usb_manager,
+        "getDeviceList",
+        "()Ljava/util/HashMap;",
+        &[],
+    )?;
+    let values =
+        jni_helpers::call_non_null(env, &device_map, "values", "()Ljava/util/Collection;", &[])?;
+    let iterator =
+        jni_helpers::call_non_null(env, &values, "iterator", "()Ljava/util/Iterator;", &[])?;
+
+    let mut cameras = Vec::new();
+    while jni_helpers::call_bool(env, &iterator, "hasNext", "()Z", &[])? {
+        let device =
+            jni_helpers::call_non_null(env, &iterator, "next", "()Ljava/lang/Object;", &[])?;
+        if !has_video_interface(env, &device)? {
+            continue;
+        }
+        let Some(name) = jni_helpers::call_string(env, &device, "getDeviceName")? else {
+            continue;
+        };
+        let key = DeviceKey {
+            vendor_id: jni_helpers::call_int(env, &device, "getVendorId", "()I", &[])? as u16,
+            product_id: jni_helpers::call_int(env, &device, "getProductId", "()I", &[])? as u16,
+        };
+        let product = jni_helpers::call_string(env, &device, "getProductName")?;
+        cameras.push(crate::devices::CameraDevice::new(name, key, product));
+    }
+    Ok(cameras)
+}
+
+/// Whether a `UsbDevice` has a video class interface
+#[cfg(target_os = "android")]
+fn has_video_interface(env: &mut JNIEnv, device: &JObject) -> Result<bool, JniError> {
+    let count = jni_helpers::call_int(env, device, "getInterfaceCount", "()I", &[])?;
+    for index in 0..count {
+        let interface = jni_helpers::call_non_null(
+            env,
+            device,
+            "getInterface",
+            "(I)Landroid/hardware/usb/UsbInterface;",
+            &[JValue::Int(index)],
+        )?;
+        if jni_helpers::call_int(env, &interface, "getInterfaceClass", "()I", &[])?
+            == USB_CLASS_VIDEO
+        {
+            return Ok(true);
+        }
+    }
+    Ok(false)
+}
+
+/// Get the `UsbManager` system service
+#[cfg(target_os = "android")]
+fn get_usb_manager<'a>(env: &mut JNIEnv<'a>, activity: &JObject) -> Result<JObject<'a>, JniError> {
+    let usb_service =
+        jni_helpers::static_string_field(env, "android/content/Context", "USB_SERVICE")?;
+
+    jni_helpers::call_non_null(
+        env,
+        activity,
+        "getSystemService",
+        "(Ljava/lang/String;)Ljava/lang/Object;",
+        &[JValue::Object(&usb_service)],
+    )
 }
 
+/// How long to wait for the user to answer the USB permission dialog
+#[cfg(target_os = "android")]
+const PERMISSION_DIALOG_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(30);
+
+/// Interval for polling `UsbManager.hasPermission()` while the dialog is shown
+#[cfg(target_os = "android")]
+const PERMISSION_POLL_INTERVAL: std::time::Duration = std::time::Duration::from_millis(250);
+
+/// Broadcast action for the permission request `PendingIntent`
+///
+/// Nothing listens for it; the result is picked up by polling `hasPermission()`.
+#[cfg(target_os = "android")]
+const ACTION_USB_PERMISSION: &str = "com.cleanscope.app.USB_PERMISSION";
+
+/// `PendingIntent.FLAG_IMMUTABLE`
+#[cfg(target_os = "android")]
+const FLAG_IMMUTABLE: i32 = 0x0400_0000;
+
 /// Get the USB file descriptor from Android via JNI
+///
+/// Returns `Ok(None)` if no USB device is attached. If the device cannot be
+/// opened for lack of permission (including a grant lost on replug), the
+/// system permission dialog is shown and the call waits for the answer,
+/// unless the user has already declined [`MAX_PERMISSION_REQUESTS`] times.
+/// A missing permission is reported as [`AppError::PermissionDenied`]; other
+/// Java exceptions as [`AppError::Android`].
+///
+/// [`MAX_PERMISSION_REQUESTS`]: crate::usb_permission::MAX_PERMISSION_REQUESTS
 #[cfg(target_os = "android")]
-fn get_usb_file_descriptor() -> Option<i32> {
-    use ndk_context::android_context;
+fn open_usb_camera(ctx: &StreamingContext) -> Result<Option<OpenedDevice>, AppError> {
+    let mut from_selection = false;
+    let result = jni_helpers::with_activity(|env, activity| {
+        let usb_manager = get_usb_manager(env, activity)?;
+
+        // Open the camera chosen with select_device, if it is connected
+        let cameras = list_cameras_from_manager(env, &usb_manager)?;
+        let selected = {
+            let mut registry = lock_or_recover!(ctx.devices);
+            registry.update(cameras.clone());
+            registry.pick(&cameras).map(|i| cameras[i].id.clone())
+        };
+        from_selection = selected.is_some();
 
-    // Get the Android context
-    let ctx = android_context();
-    // SAFETY: ctx.vm() returns a valid JNI JavaVM pointer from the Android runtime.
-    let vm = unsafe { jni::JavaVM::from_raw(ctx.vm().cast()) }.ok()?;
-    // SAFETY: ctx.context() returns a valid Android Activity jobject reference.
-    let activity = unsafe { JObject::from_raw(ctx.context().cast()) };
+        // Otherwise the device from the intent (if launched via USB_DEVICE_ATTACHED)
+        // Then look up the device in getDeviceList() - that object has proper permission context
+        let target_device_name = match selected {
+            Some(name) => Some(name),
+            None => get_device_name_from_intent(env, activity)?,
+        };
 
-    let mut env = vm.attach_current_thread().ok()?;
+        // Get the device from the device list (using the intent's device name if available)
+        let Some(device) =
+            get_device_from_manager(env, &usb_manager, target_device_name.as_deref())?
+        else {
+            return Ok(None);
+        };
 
-    // Get the UsbManager service
-    let usb_service = env
-        .get_static_field(
-            "android/content/Context",
-            "USB_SERVICE",
-            "Ljava/lang/String;",
-        )
-        .ok()?
-        .l()
-        .ok()?;
-
-    let usb_manager = env
-        .call_method(
-            &activity,
-            "getSystemService",
-            "(Ljava/lang/String;)Ljava/lang/Object;",
-            &[JValue::Object(&usb_service)],
-        )
-        .ok()?
-        .l()
-        .ok()?;
+        let device_name = jni_helpers::call_string(env, &device, "getDeviceName")?
+            .unwrap_or_else(|| String::from("<unknown>"));
+        let key = DeviceKey {
+            vendor_id: jni_helpers::call_int(env, &device, "getVendorId", "()I", &[])? as u16,
+            product_id: jni_helpers::call_int(env, &device, "getProductId", "()I", &[])? as u16,
+        };
+        log::info!("Attempting to open USB device {} ({})", device_name, key);
 
-    // Get device name from intent (if launched via USB_DEVICE_ATTACHED)
-    // Then look up the device in getDeviceList() - that object has proper permission context
-    let target_device_name = get_device_name_from_intent(&mut env, &activity);
+        if has_usb_permission(env, &usb_manager, &device)? {
+            if let Some(opened) = open_usb_device(env, &usb_manager, &device)? {
+                lock_or_recover!(ctx.usb_permissions).record_granted(key);
+                lock_or_recover!(ctx.devices).set_open(Some(device_name));
+                return Ok(Some(opened));
+            }
+            // openDevice() returns null rather than throwing when permission is missing
+            log::warn!("UsbManager.openDevice returned null for {}", device_name);
+        }
 
-    // Get the device from the device list (using the intent's device name if available)
-    let device = get_device_from_manager(&mut env, &usb_manager, target_device_name.as_deref())?;
+        let action = lock_or_recover!(ctx.usb_permissions).record_missing(key);
+        if action == PermissionAction::GiveUp {
+            return Err(AppError::PermissionDenied(format!(
+                "USB permission for {} was declined; reconnect the camera and allow access",
+                key
+            )));
+        }
 
-    log::info!("Attempting to open USB device");
+        log::info!("Requesting USB permission for {}", key);
+        crate::emit_usb_event(
+            &ctx.app_handle,
+            false,
+            Some("Waiting for USB permission...".to_string()),
+        );
+        if !request_usb_permission(env, activity, &usb_manager, &device)? {
+            lock_or_recover!(ctx.usb_permissions).record_denied(key);
+            return Err(AppError::PermissionDenied(format!(
+                "USB permission for {} was not granted",
+                key
+            )));
+        }
 
-    // Open the device and get the file descriptor
-    let connection = env
-        .call_method(
-            &usb_manager,
-            "openDevice",
-            "(Landroid/hardware/usb/UsbDevice;)Landroid/hardware/usb/UsbDeviceConnection;",
-            &[JValue::Object(&device)],
-        )
-        .ok()?
-        .l()
-        .ok()?;
+        let opened = open_usb_device(env, &usb_manager, &device)?.ok_or_else(|| {
+            AppError::PermissionDenied(format!(
+                "UsbManager.openDevice returned null for {} after permission was granted",
+                device_name
+            ))
+        })?;
+        lock_or_recover!(ctx.usb_permissions).record_granted(key);
+        lock_or_recover!(ctx.devices).set_open(Some(device_name));
+        Ok(Some(opened))
+    });
+
+    // Don't retry a selected camera that can't be opened; the next attempt
+    // falls back to the intent's device
+    if from_selection && result.is_err() {
+        log::warn!("Could not open the selected camera, dropping the selection");
+        lock_or_recover!(ctx.devices).clear_selection();
+    }
+    result
+}
 
-    if connection.is_null() {
-        log::error!("Failed to open USB device");
-        return None;
+/// `UsbManager.hasPermission(device)`
+#[cfg(target_os = "android")]
+fn has_usb_permission(
+    env: &mut JNIEnv,
+    usb_manager: &JObject,
+    device: &JObject,
+) -> Result<bool, JniError> {
+    jni_helpers::call_bool(
+        env,
+        usb_manager,
+        "hasPermission",
+        "(Landroid/hardware/usb/UsbDevice;)Z",
+        &[JValue::Object(device)],
+    )
+}
+
+/// A camera opened with `UsbManager.openDevice`
+///
+/// Owns the `UsbDeviceConnection`: libusb wraps its file descriptor, and
+/// compatibility mode ([`JniUsbConnection`]) streams through the connection
+/// itself. Dropping it closes the connection, and with it the descriptor.
+#[cfg(target_os = "android")]
+pub(crate) struct OpenedDevice {
+    /// Java VM used to close the connection
+    vm: JavaVM,
+    /// `android.hardware.usb.UsbDevice`
+    device: GlobalRef,
+    /// `android.hardware.usb.UsbDeviceConnection`
+    connection: GlobalRef,
+    /// File descriptor of the connection
+    fd: i32,
+}
+
+#[cfg(target_os = "android")]
+impl OpenedDevice {
+    /// File descriptor of the connection
+    pub(crate) fn fd(&self) -> i32 {
+        self.fd
+    }
+
+    /// `android.hardware.usb.UsbDevice`
+    pub(crate) fn device(&self) -> &GlobalRef {
+        &self.device
+    }
+
+    /// `android.hardware.usb.UsbDeviceConnection`
+    pub(crate) fn connection(&self) -> &GlobalRef {
+        &self.connection
+    }
+}
+
+#[cfg(target_os = "android")]
+impl Drop for OpenedDevice {
+    fn drop(&mut self) {
+        let Ok(mut env) = self.vm.attach_current_thread() else {
+            return;
+        };
+        let _ = env.call_method(self.connection.as_obj(), "close", "()V", &[]);
+        if env.exception_check().unwrap_or(false) {
+            let _ = env.exception_clear();
+        }
+        log::info!("Closed USB device connection (fd: {})", self.fd);
     }
+}
 
-    // Get the file descriptor
-    let fd = env
-        .call_method(&connection, "getFileDescriptor", "()I", &[])
-        .ok()?
-        .i()
-        .ok()?;
+/// Open the device, or return `None` if `openDevice` returned null
+#[cfg(target_os = "android")]
+fn open_usb_device(
+    env: &mut JNIEnv,
+    usb_manager: &JObject,
+    device: &JObject,
+) -> Result<Option<OpenedDevice>, JniError> {
+    let connection = jni_helpers::call_object(
+        env,
+        usb_manager,
+        "openDevice",
+        "(Landroid/hardware/usb/UsbDevice;)Landroid/hardware/usb/UsbDeviceConnection;",
+        &[JValue::Object(device)],
+    )?;
+    if connection.is_null() {
+        return Ok(None);
+    }
 
+    let fd = jni_helpers::call_int(env, &connection, "getFileDescriptor", "()I", &[])?;
     log::info!("fd: {}", fd);
-    Some(fd)
+
+    let vm = env
+        .get_java_vm()
+        .map_err(|e| jni_helpers::take_error(env, "GetJavaVM", e))?;
+    let device = env
+        .new_global_ref(device)
+        .map_err(|e| jni_helpers::take_error(env, "NewGlobalRef", e))?;
+    let connection = env
+        .new_global_ref(&connection)
+        .map_err(|e| jni_helpers::take_error(env, "NewGlobalRef", e))?;
+    Ok(Some(OpenedDevice {
+        vm,
+        device,
+        connection,
+        fd,
+    }))
+}
+
+/// Show the system USB permission dialog and wait for the user's answer
+///
+/// Returns whether permission was granted. A dismissed dialog counts as declined
+/// once [`PERMISSION_DIALOG_TIMEOUT`] has passed.
+#[cfg(target_os = "android")]
+fn request_usb_permission(
+    env: &mut JNIEnv,
+    activity: &JObject,
+    usb_manager: &JObject,
+    device: &JObject,
+) -> Result<bool, JniError> {
+    let action = jni_helpers::new_string(env, ACTION_USB_PERMISSION)?;
+    let intent = env
+        .new_object(
+            "android/content/Intent",
+            "(Ljava/lang/String;)V",
+            &[JValue::Object(&action)],
+        )
+        .map_err(|e| jni_helpers::take_error(env, "Intent.<init>", e))?;
+
+    // Android 14 rejects implicit intents in PendingIntents
+    let package =
+        jni_helpers::call_non_null(env, activity, "getPackageName", "()Ljava/lang/String;", &[])?;
+    jni_helpers::call_object(
+        env,
+        &intent,
+        "setPackage",
+        "(Ljava/lang/String;)Landroid/content/Intent;",
+        &[JValue::Object(&package)],
+    )?;
+
+    let pending_intent = env
+        .call_static_method(
+            "android/app/PendingIntent",
+            "getBroadcast",
+            "(Landroid/content/Context;ILandroid/content/Intent;I)Landroid/app/PendingIntent;",
+            &[
+                JValue::Object(activity),
+                JValue::Int(0),
+                JValue::Object(&intent),
+                JValue::Int(FLAG_IMMUTABLE),
+            ],
+        )
+        .and_then(|v| v.l())
+        .map_err(|e| jni_helpers::take_error(env, "PendingIntent.getBroadcast", e))?;
+
+    env.call_method(
+        usb_manager,
+        "requestPermission",
+        "(Landroid/hardware/usb/UsbDevice;Landroid/app/PendingIntent;)V",
+        &[JValue::Object(device), JValue::Object(&pending_intent)],
+    )
+    .map_err(|e| jni_helpers::take_error(env, "requestPermission", e))?;
+
+    let started = std::time::Instant::now();
+    while started.elapsed() < PERMISSION_DIALOG_TIMEOUT {
+        std::thread::sleep(PERMISSION_POLL_INTERVAL);
+        if has_usb_permission(env, usb_manager, device)? {
+            log::info!("USB permission granted");
+            return Ok(true);
+        }
+    }
+
+    log::warn!(
+        "USB permission not granted within {:?}",
+        PERMISSION_DIALOG_TIMEOUT
+    );
+    Ok(false)
 }
 
 /// UVC Probe/Commit control structure (26 bytes for UVC 1.1)
@@ -476,6 +836,10 @@ struct UvcNegotiatedParams {
     width: u16,
     height: u16,
     max_frame_size: u32,
+    /// Negotiated `dwMaxPayloadTransferSize` (bytes per payload)
+    max_payload: u32,
+    /// Negotiated frame interval in 100 ns units
+    frame_interval: u32,
 }
 
 /// Configuration for UVC format detection
@@ -485,8 +849,9 @@ struct UvcNegotiatedParams {
 #[cfg(target_os = "android")]
 #[derive(Debug, Clone)]
 pub struct UvcConfig {
-    /// Maximum format index to try when searching for MJPEG.
-    /// USB cameras may have multiple format indices (1-based).
+    /// Maximum format index to try when searching for MJPEG without descriptors.
+    /// USB cameras may have multiple format indices (1-based); when the format
+    /// catalog could be read, its formats are tried instead.
     /// Default: 4 (covers most consumer cameras)
     pub max_format_index: u8,
 
@@ -522,48 +887,83 @@ const UVC_CONFIG: UvcConfig = UvcConfig {
 
 /// Discover available video formats from UVC descriptors and store them in streaming config.
 ///
-/// Returns the discovered format descriptors for further processing.
+/// Returns the format catalog for further processing.
 #[cfg(target_os = "android")]
 fn discover_and_store_formats(
     dev: &LibusbDeviceHandle,
     streaming_config: &Arc<Mutex<StreamingConfig>>,
-) -> Vec<uvc::UvcFormatInfo> {
-    let formats = dev.get_format_descriptors().unwrap_or_default();
-    {
-        let mut config = lock_or_recover!(streaming_config);
-        config.available_formats = formats
+) -> FormatCatalog {
+    let catalog = dev.get_format_catalog().unwrap_or_default();
+    log::info!(
+        "Discovered {} video formats: {:?}",
+        catalog.formats.len(),
+        catalog
+            .formats
             .iter()
-            .map(|f| {
-                let format_type = match f.format_type {
-                    uvc::UvcFormatType::Mjpeg => "MJPEG".to_string(),
-                    uvc::UvcFormatType::Uncompressed => "YUY2".to_string(),
-                    uvc::UvcFormatType::UncompressedRgb => "RGB24".to_string(),
-                    uvc::UvcFormatType::FrameBased => "H264".to_string(),
-                    uvc::UvcFormatType::Unknown(n) => format!("UNK:{}", n),
-                };
-                let frames: Vec<crate::DiscoveredFrame> = f
-                    .frames
-                    .iter()
-                    .map(|fr| crate::DiscoveredFrame {
-                        frame_index: fr.frame_index,
-                        width: fr.width,
-                        height: fr.height,
-                    })
-                    .collect();
-                crate::DiscoveredFormat {
-                    index: f.format_index,
-                    format_type,
-                    frames,
-                }
-            })
-            .collect();
-        log::info!(
-            "Discovered {} video formats: {:?}",
-            config.available_formats.len(),
-            config.available_formats
-        );
-    }
-    formats
+            .map(|f| format!("{}:{}", f.index, f.name()))
+            .collect::<Vec<_>>()
+    );
+    lock_or_recover!(streaming_config).format_catalog = catalog.clone();
+    catalog
+}
+
+/// Frame to negotiate for `format_index`: the selected one, else the one at
+/// the preferred resolution (see `settings`), else the descriptor's default
+#[cfg(usb_streaming)]
+pub(crate) fn frame_for_format(
+    catalog: &FormatCatalog,
+    format_index: u8,
+    selected: Option<u8>,
+    preferred: Option<PreferredResolution>,
+) -> u8 {
+    let format = catalog.format(format_index);
+    selected
+        .filter(|&index| format.is_none_or(|f| f.frame(index).is_some()))
+        .or_else(|| {
+            let preferred = preferred?;
+            format?
+                .frames
+                .iter()
+                .find(|f| f.width == preferred.width && f.height == preferred.height)
+                .map(|f| f.index)
+        })
+        .or_else(|| format.and_then(|f| f.default_frame()).map(|f| f.index))
+        .unwrap_or(1)
+}
+
+/// Negotiate a format and resolution, and record it as the active stream.
+///
+/// `get_current_resolution`, `get_resolutions` and `get_stream_info` report
+/// what is recorded here.
+#[cfg(target_os = "android")]
+fn negotiate_stream(
+    dev: &LibusbDeviceHandle,
+    ep_info: &EndpointInfo,
+    stream_ctx: &StreamingContext,
+    format_index: u8,
+    frame_index: u8,
+) -> Result<UvcNegotiatedParams, LibusbError> {
+    let selected_interval = lock_or_recover!(stream_ctx.streaming_config).selected_frame_interval;
+    let params = start_uvc_streaming_with_resolution(
+        dev,
+        Some(ep_info),
+        format_index,
+        frame_index,
+        selected_interval,
+    )?;
+    record_active_stream(
+        stream_ctx,
+        crate::ActiveStream {
+            format_index: params.format_index,
+            frame_index: params.frame_index,
+            width: params.width,
+            height: params.height,
+            frame_interval: params.frame_interval,
+            max_frame_size: params.max_frame_size,
+            max_payload: params.max_payload,
+        },
+    );
+    Ok(params)
 }
 
 /// Result of MJPEG streaming attempt
@@ -588,11 +988,12 @@ fn try_mjpeg_streaming(
     ep_info: &EndpointInfo,
     stream_ctx: &StreamingContext,
     format_index: u8,
+    frame_index: u8,
     streaming_interface: i32,
 ) -> MjpegStreamingResult {
-    // Start UVC streaming with this format index and frame index 1 (highest resolution)
+    // Start UVC streaming with this format index and frame index
     // Use _with_resolution to get width/height for correct frame size detection
-    let params = match start_uvc_streaming_with_resolution(dev, Some(ep_info), format_index, 1) {
+    let params = match negotiate_stream(dev, ep_info, stream_ctx, format_index, frame_index) {
         Ok(p) => p,
         Err(e) => {
             log::warn!(
@@ -611,38 +1012,18 @@ fn try_mjpeg_streaming(
         params.height
     );
 
-    // Choose streaming method based on endpoint type
-    let result = match ep_info.transfer_type {
-        TransferType::Isochronous => {
-            log::info!("Using ISOCHRONOUS transfers for video streaming");
-            stream_frames_isochronous_with_format_detection(
-                usb_ctx,
-                dev,
-                ep_info,
-                stream_ctx.app_handle.clone(),
-                stream_ctx.frame_buffer.clone(),
-                format_index,
-                params.width,
-                params.height,
-            )
-        }
-        TransferType::Bulk => {
-            log::info!("Using BULK transfers for video streaming");
-            stream_frames(
-                dev,
-                ep_info.address,
-                stream_ctx.app_handle.clone(),
-                stream_ctx.frame_buffer.clone(),
-            )
-        }
-        _ => {
-            log::error!(
-                "Unsupported endpoint transfer type: {:?}",
-                ep_info.transfer_type
-            );
-            Err(LibusbError::NotSupported)
-        }
-    };
+    // The stream uses isochronous or bulk transfers to match the endpoint
+    let result = stream_frames_with_format_detection(
+        usb_ctx,
+        dev,
+        ep_info,
+        stream_ctx,
+        format_index,
+        params.width,
+        params.height,
+        params.frame_interval,
+        params.max_payload,
+    );
 
     match result {
         Ok(FormatDetectionResult::MjpegFound) => {
@@ -652,6 +1033,9 @@ fn try_mjpeg_streaming(
             );
             MjpegStreamingResult::Success(StreamResult::Normal)
         }
+        Ok(FormatDetectionResult::RestartRequested) => {
+            MjpegStreamingResult::Success(StreamResult::RestartRequested)
+        }
         Ok(FormatDetectionResult::NotMjpeg) => {
             log::info!("Format {} is not MJPEG, trying next format", format_index);
             // Reset interface before trying next format
@@ -669,21 +1053,29 @@ fn try_mjpeg_streaming(
 
 /// Start YUV fallback streaming when MJPEG is not available.
 ///
-/// Uses format index 1 by default and selected frame index from config.
+/// Uses the uncompressed format named first in `format_preference`, else the
+/// first one in the catalog (format index 1 if there is none), with the
+/// selected frame, the preferred resolution or the format's default frame.
 #[cfg(target_os = "android")]
+#[allow(clippy::too_many_arguments)]
 fn start_yuy2_fallback(
     usb_ctx: &LibusbContext,
     dev: &LibusbDeviceHandle,
     ep_info: &EndpointInfo,
     stream_ctx: &StreamingContext,
+    catalog: &FormatCatalog,
+    format_preference: &[String],
+    selected_frame: Option<u8>,
+    preferred: Option<PreferredResolution>,
 ) -> Result<StreamResult, LibusbError> {
-    // Get selected frame index from config, default to 1
-    let frame_idx = lock_or_recover!(stream_ctx.streaming_config)
-        .selected_frame_index
-        .unwrap_or(1);
-
-    // Start streaming with format 1 and selected frame index
-    let params = start_uvc_streaming_with_resolution(dev, Some(ep_info), 1, frame_idx)?;
+    let format_idx = catalog
+        .by_preference(format_preference)
+        .into_iter()
+        .find(|f| f.kind == FormatKind::Uncompressed)
+        .map_or(1, |f| f.index);
+    let frame_idx = frame_for_format(catalog, format_idx, selected_frame, preferred);
+
+    let params = negotiate_stream(dev, ep_info, stream_ctx, format_idx, frame_idx)?;
     log::info!(
         "Starting YUV streaming on endpoint 0x{:02x}, resolution {}x{}",
         params.endpoint,
@@ -698,6 +1090,8 @@ fn start_yuy2_fallback(
         stream_ctx,
         params.width as u32,
         params.height as u32,
+        params.frame_interval,
+        params.max_payload,
     )
 }
 
@@ -714,25 +1108,201 @@ mod reconnect_config {
     pub const BACKOFF_MULTIPLIER: f64 = 1.5;
 }
 
+/// Hot-plug supervision for the camera loop
+///
+/// Owns the streaming context after startup so the JNI attach/detach callbacks
+/// can tear a stream down and bring it back without restarting the app. At most
+/// one camera loop runs at a time; attach events that arrive while it is
+/// waiting for the camera wake it up instead of starting another.
+#[cfg(target_os = "android")]
+struct UsbSupervisor {
+    ctx: StreamingContext,
+    /// Whether a camera loop thread is running
+    loop_running: AtomicBool,
+    /// Set by the detach callback until the camera loop has handled it
+    detached: AtomicBool,
+    /// Number of attach events seen so far
+    attach_events: Mutex<u64>,
+    /// Signalled on every attach event
+    attached: Condvar,
+    /// Thread running the latest camera loop, joined on shutdown
+    camera_thread: Mutex<Option<std::thread::JoinHandle<()>>>,
+}
+
+#[cfg(target_os = "android")]
+static SUPERVISOR: OnceLock<UsbSupervisor> = OnceLock::new();
+
+/// USB hot-plug notification from `MainActivity`
+#[cfg(target_os = "android")]
+#[derive(Debug, Clone)]
+enum HotplugEvent {
+    /// A USB device was attached
+    Attached,
+    /// A USB device was detached, with its device name if the intent had one
+    Detached(Option<String>),
+}
+
+/// Queue of hot-plug events, consumed by the thread running `init_usb_handler`
+///
+/// The JNI callbacks run on the Android UI thread, so they only enqueue; the
+/// USB handler thread opens devices (which may wait on the permission dialog)
+/// and stops streams.
+#[cfg(target_os = "android")]
+static HOTPLUG_EVENTS: OnceLock<mpsc::Sender<HotplugEvent>> = OnceLock::new();
+
+/// Hand a hot-plug event to the USB handler thread
+#[cfg(target_os = "android")]
+fn send_hotplug_event(event: HotplugEvent) {
+    let Some(events) = HOTPLUG_EVENTS.get() else {
+        log::warn!("USB handler not initialized yet, ignoring {:?}", event);
+        return;
+    };
+    if events.send(event).is_err() {
+        log::warn!("USB handler stopped, ignoring {:?}", event);
+    }
+}
+
+#[cfg(target_os = "android")]
+impl UsbSupervisor {
+    fn new(ctx: StreamingContext) -> Self {
+        Self {
+            ctx,
+            loop_running: AtomicBool::new(false),
+            detached: AtomicBool::new(false),
+            attach_events: Mutex::new(0),
+            attached: Condvar::new(),
+            camera_thread: Mutex::new(None),
+        }
+    }
+
+    /// Open the camera and start the camera loop, unless one is already running
+    fn start_camera_loop(&'static self) {
+        if self.loop_running.swap(true, Ordering::SeqCst) {
+            // The running loop reopens the device itself
+            self.notify_attached();
+            return;
+        }
+
+        match open_usb_camera(&self.ctx) {
+            Ok(Some(device)) => {
+                log::info!("USB device found with fd: {}", device.fd());
+                crate::emit_usb_event(
+                    &self.ctx.app_handle,
+                    true,
+                    Some(format!("USB Camera (fd: {})", device.fd())),
+                );
+
+                // Start the camera streaming loop in a new thread; any
+                // previous one has already exited
+                let thread = std::thread::spawn(move || {
+                    run_camera_loop(device, self);
+                    self.loop_running.store(false, Ordering::SeqCst);
+                });
+                *lock_or_recover!(self.camera_thread) = Some(thread);
+                return;
+            }
+            Ok(None) => log::info!("No USB device found"),
+            Err(e) => {
+                log::error!("Could not open USB device: {}", e);
+                crate::emit_usb_error(
+                    &self.ctx.app_handle,
+                    crate::UsbError {
+                        code: e.code(),
+                        error_type: crate::DisconnectReason::Unknown,
+                        message: e.to_string(),
+                        recoverable: true,
+                    },
+                );
+            }
+        }
+        self.loop_running.store(false, Ordering::SeqCst);
+    }
+
+    /// Wait for the camera loop to see the stop flag and exit
+    ///
+    /// Its waits for the camera are bounded and check the flag in between.
+    fn join_camera_loop(&self) {
+        let Some(thread) = lock_or_recover!(self.camera_thread).take() else {
+            return;
+        };
+        if thread.join().is_err() {
+            log::error!("Camera loop thread panicked");
+        }
+    }
+
+    /// Record an attach event and wake a camera loop waiting for the device
+    fn notify_attached(&self) {
+        *lock_or_recover!(self.attach_events) += 1;
+        self.attached.notify_all();
+    }
+
+    /// Number of attach events seen so far
+    fn attach_events(&self) -> u64 {
+        *lock_or_recover!(self.attach_events)
+    }
+
+    /// Wait up to `timeout` for an attach event newer than `seen`
+    ///
+    /// Returns `true` if the camera was attached in the meantime.
+    fn wait_for_attach(&self, seen: u64, timeout: std::time::Duration) -> bool {
+        let events = lock_or_recover!(self.attach_events);
+        let (events, _) = self
+            .attached
+            .wait_timeout_while(events, timeout, |events| *events == seen)
+            .unwrap_or_else(std::sync::PoisonError::into_inner);
+        *events != seen
+    }
+
+    /// Stop the current stream because the camera was unplugged
+    ///
+    /// Detaching another USB device (`device_name` naming a device other
+    /// than the open camera) leaves the stream alone.
+    fn device_detached(&self, device_name: Option<&str>) {
+        if let Some(name) = device_name {
+            let devices = lock_or_recover!(self.ctx.devices);
+            if devices.open() != Some(name) {
+                log::info!(
+                    "Ignoring detach of {}, streaming from {:?}",
+                    name,
+                    devices.open()
+                );
+                return;
+            }
+        }
+        if self.loop_running.load(Ordering::SeqCst) {
+            self.detached.store(true, Ordering::SeqCst);
+            // Make the streaming loop return so the device is released cleanly
+            lock_or_recover!(self.ctx.streaming_config).restart_requested = true;
+        }
+        crate::emit_usb_disconnect(
+            &self.ctx.app_handle,
+            crate::DisconnectReason::DeviceUnplugged,
+            Some("USB camera detached".to_string()),
+        );
+    }
+}
+
 /// Run the camera streaming loop with restart and reconnection support
 /// This outer loop handles:
 /// - Restart requests (e.g., when user changes video format)
 /// - Automatic reconnection after device disconnection
+/// - Waiting for the attach callback after the camera was unplugged
 #[cfg(target_os = "android")]
-fn run_camera_loop(initial_fd: i32, ctx: StreamingContext) {
+fn run_camera_loop(initial_device: OpenedDevice, supervisor: &UsbSupervisor) {
     use crate::DisconnectReason;
     use reconnect_config::*;
 
-    log::info!("Starting camera loop with fd: {}", initial_fd);
+    let ctx = &supervisor.ctx;
+    log::info!("Starting camera loop with fd: {}", initial_device.fd());
 
-    let mut current_fd = initial_fd;
+    let mut current_device = initial_device;
     let mut disconnect_reason: Option<DisconnectReason> = None;
     let mut reconnect_attempt: u32 = 0;
     let mut current_delay_ms = INITIAL_DELAY_MS;
 
-    loop {
+    'camera: loop {
         // Check if we should stop (app is closing)
-        if ctx.stop_flag.load(std::sync::atomic::Ordering::Relaxed) {
+        if ctx.stop_flag.load(Ordering::Relaxed) {
             log::info!("Stop flag set, exiting camera loop");
             break;
         }
@@ -741,9 +1311,21 @@ fn run_camera_loop(initial_fd: i32, ctx: StreamingContext) {
         {
             let mut config = lock_or_recover!(ctx.streaming_config);
             config.restart_requested = false;
+            config.active_stream = None;
         }
+        supervisor.detached.store(false, Ordering::SeqCst);
+        let mut attach_seen = supervisor.attach_events();
+
+        let result = match run_camera_loop_inner(&current_device, ctx) {
+            // The detach callback stopped the stream
+            _ if supervisor.detached.swap(false, Ordering::SeqCst) => {
+                Ok(StreamResult::DeviceUnplugged)
+            }
+            Err(LibusbError::NoDevice) => Ok(StreamResult::DeviceUnplugged),
+            result => result,
+        };
 
-        match run_camera_loop_inner(current_fd, &ctx) {
+        match result {
             Ok(StreamResult::Normal) => {
                 log::info!("Camera loop ended normally");
                 disconnect_reason = Some(DisconnectReason::Normal);
@@ -756,6 +1338,24 @@ fn run_camera_loop(initial_fd: i32, ctx: StreamingContext) {
                 current_delay_ms = INITIAL_DELAY_MS;
                 // Small delay to let things settle
                 std::thread::sleep(std::time::Duration::from_millis(SETTLE_MS));
+                // A different camera was chosen with select_device
+                if lock_or_recover!(ctx.devices).wants_switch() {
+                    match open_usb_camera(ctx) {
+                        Ok(Some(new_device)) => {
+                            log::info!("Switched to the selected camera (fd: {})", new_device.fd());
+                            // Closes the previous camera's connection
+                            current_device = new_device;
+                        }
+                        Ok(None) => {
+                            log::warn!("Selected camera not found, keeping current one");
+                            lock_or_recover!(ctx.devices).clear_selection();
+                        }
+                        Err(e) => {
+                            log::warn!("Could not open the selected camera: {}", e);
+                            lock_or_recover!(ctx.devices).clear_selection();
+                        }
+                    }
+                }
                 continue;
             }
             Ok(StreamResult::DeviceUnplugged) => {
@@ -764,6 +1364,7 @@ fn run_camera_loop(initial_fd: i32, ctx: StreamingContext) {
                 crate::emit_usb_error(
                     &ctx.app_handle,
                     crate::UsbError {
+                        code: MessageCode::UsbDeviceUnplugged,
                         error_type: DisconnectReason::DeviceUnplugged,
                         message: "USB camera was disconnected".to_string(),
                         recoverable: true,
@@ -777,6 +1378,7 @@ fn run_camera_loop(initial_fd: i32, ctx: StreamingContext) {
                 crate::emit_usb_error(
                     &ctx.app_handle,
                     crate::UsbError {
+                        code: MessageCode::UsbTimeout,
                         error_type: DisconnectReason::Timeout,
                         message: "No video frames received - camera may be disconnected"
                             .to_string(),
@@ -787,10 +1389,12 @@ fn run_camera_loop(initial_fd: i32, ctx: StreamingContext) {
             }
             Ok(StreamResult::TransferError(msg)) => {
                 log::error!("USB transfer error: {}", msg);
+                ctx.stream_health.record_transfer_error();
                 disconnect_reason = Some(DisconnectReason::TransferError);
                 crate::emit_usb_error(
                     &ctx.app_handle,
                     crate::UsbError {
+                        code: MessageCode::UsbTransferError,
                         error_type: DisconnectReason::TransferError,
                         message: format!("USB transfer error: {}", msg),
                         recoverable: true,
@@ -800,10 +1404,12 @@ fn run_camera_loop(initial_fd: i32, ctx: StreamingContext) {
             }
             Err(e) => {
                 log::error!("Camera loop error: {}", e);
+                ctx.stream_health.record_transfer_error();
                 disconnect_reason = Some(DisconnectReason::Unknown);
                 crate::emit_usb_error(
                     &ctx.app_handle,
                     crate::UsbError {
+                        code: MessageCode::UsbCameraError,
                         error_type: DisconnectReason::Unknown,
                         message: format!("Camera error: {}", e),
                         recoverable: true,
@@ -813,115 +1419,163 @@ fn run_camera_loop(initial_fd: i32, ctx: StreamingContext) {
             }
         }
 
+        // An unplugged camera can't be found by polling; wait for the
+        // attach callback instead of backing off
+        let unplugged = matches!(disconnect_reason, Some(DisconnectReason::DeviceUnplugged));
+
         // If we reach here, we need to attempt reconnection
         // (either from disconnect or error)
-        reconnect_attempt += 1;
+        let new_device = loop {
+            reconnect_attempt += 1;
 
-        // Check if we've exceeded max attempts (if limit is set)
-        if MAX_ATTEMPTS > 0 && reconnect_attempt > MAX_ATTEMPTS {
-            log::warn!(
-                "Max reconnection attempts ({}) exceeded, giving up",
-                MAX_ATTEMPTS
-            );
-            crate::emit_usb_reconnect_stopped(
-                &ctx.app_handle,
-                Some(format!(
-                    "Gave up after {} reconnection attempts",
+            // Check if we've exceeded max attempts (if limit is set)
+            if MAX_ATTEMPTS > 0 && reconnect_attempt > MAX_ATTEMPTS {
+                log::warn!(
+                    "Max reconnection attempts ({}) exceeded, giving up",
                     MAX_ATTEMPTS
-                )),
-            );
-            break;
-        }
-
-        // Emit disconnect event (first attempt only)
-        if reconnect_attempt == 1 {
-            if let Some(ref reason) = disconnect_reason {
-                crate::emit_usb_disconnect(&ctx.app_handle, reason.clone(), None);
-            }
-        }
-
-        // Emit reconnecting status
-        log::info!(
-            "Reconnection attempt {} (delay: {}ms)",
-            reconnect_attempt,
-            current_delay_ms
-        );
-        crate::emit_usb_reconnecting(
-            &ctx.app_handle,
-            reconnect_attempt,
-            MAX_ATTEMPTS,
-            Some(format!(
-                "Waiting {}s before retry...",
-                current_delay_ms / 1000
-            )),
-        );
-
-        // Wait with exponential backoff
-        let delay = std::time::Duration::from_millis(current_delay_ms);
-        let start = std::time::Instant::now();
-        while start.elapsed() < delay {
-            // Check stop flag during wait
-            if ctx.stop_flag.load(std::sync::atomic::Ordering::Relaxed) {
-                log::info!("Stop flag set during reconnection wait");
+                );
                 crate::emit_usb_reconnect_stopped(
                     &ctx.app_handle,
-                    Some("Stopped by user".to_string()),
+                    Some(format!(
+                        "Gave up after {} reconnection attempts",
+                        MAX_ATTEMPTS
+                    )),
                 );
-                return;
+                break 'camera;
             }
-            std::thread::sleep(std::time::Duration::from_millis(SETTLE_MS));
-        }
-
-        // Increase delay for next attempt (exponential backoff)
-        current_delay_ms = ((current_delay_ms as f64) * BACKOFF_MULTIPLIER) as u64;
-        if current_delay_ms > MAX_DELAY_MS {
-            current_delay_ms = MAX_DELAY_MS;
-        }
 
-        // Try to get a new file descriptor
-        log::info!("Attempting to acquire new USB file descriptor...");
-        crate::emit_usb_reconnecting(
-            &ctx.app_handle,
-            reconnect_attempt,
-            MAX_ATTEMPTS,
-            Some("Looking for USB device...".to_string()),
-        );
+            // Emit disconnect event (first attempt only)
+            if reconnect_attempt == 1 {
+                if let Some(ref reason) = disconnect_reason {
+                    crate::emit_usb_disconnect(&ctx.app_handle, reason.clone(), None);
+                }
+            }
 
-        match get_usb_file_descriptor() {
-            Some(new_fd) => {
+            if unplugged {
+                log::info!("Waiting for USB camera to be attached again");
+                crate::emit_usb_reconnecting(
+                    &ctx.app_handle,
+                    reconnect_attempt,
+                    MAX_ATTEMPTS,
+                    Some("Waiting for the camera to be plugged back in...".to_string()),
+                );
+                while !supervisor
+                    .wait_for_attach(attach_seen, std::time::Duration::from_millis(SETTLE_MS))
+                {
+                    if ctx.stop_flag.load(Ordering::Relaxed) {
+                        log::info!("Stop flag set while waiting for USB camera");
+                        crate::emit_usb_reconnect_stopped(
+                            &ctx.app_handle,
+                            Some("Stopped by user".to_string()),
+                        );
+                        return;
+                    }
+                }
+                attach_seen = supervisor.attach_events();
+            } else {
+                // Emit reconnecting status
                 log::info!(
-                    "Successfully acquired new USB fd: {} (attempt {})",
-                    new_fd,
-                    reconnect_attempt
+                    "Reconnection attempt {} (delay: {}ms)",
+                    reconnect_attempt,
+                    current_delay_ms
                 );
-
-                // Emit connected event
-                crate::emit_usb_event(
+                crate::emit_usb_reconnecting(
                     &ctx.app_handle,
-                    true,
-                    Some(format!("USB Camera reconnected (fd: {})", new_fd)),
+                    reconnect_attempt,
+                    MAX_ATTEMPTS,
+                    Some(format!(
+                        "Waiting {}s before retry...",
+                        current_delay_ms / 1000
+                    )),
                 );
 
-                // Reset reconnection state
-                current_fd = new_fd;
-                reconnect_attempt = 0;
-                current_delay_ms = INITIAL_DELAY_MS;
-                // Note: disconnect_reason will be set by the next disconnection event
+                // Wait with exponential backoff, cut short by an attach event
+                let delay = std::time::Duration::from_millis(current_delay_ms);
+                let start = std::time::Instant::now();
+                while start.elapsed() < delay {
+                    // Check stop flag during wait
+                    if ctx.stop_flag.load(Ordering::Relaxed) {
+                        log::info!("Stop flag set during reconnection wait");
+                        crate::emit_usb_reconnect_stopped(
+                            &ctx.app_handle,
+                            Some("Stopped by user".to_string()),
+                        );
+                        return;
+                    }
+                    if supervisor
+                        .wait_for_attach(attach_seen, std::time::Duration::from_millis(SETTLE_MS))
+                    {
+                        log::info!("USB camera attached, retrying immediately");
+                        attach_seen = supervisor.attach_events();
+                        break;
+                    }
+                }
 
-                // Continue the loop to start streaming with new fd
-                continue;
+                // Increase delay for next attempt (exponential backoff)
+                current_delay_ms = ((current_delay_ms as f64) * BACKOFF_MULTIPLIER) as u64;
+                if current_delay_ms > MAX_DELAY_MS {
+                    current_delay_ms = MAX_DELAY_MS;
+                }
             }
-            None => {
-                log::info!(
-                    "No USB device available yet (attempt {})",
-                    reconnect_attempt
-                );
-                // Loop back to wait and try again
-                continue;
+
+            // Try to get a new file descriptor
+            log::info!("Attempting to acquire new USB file descriptor...");
+            crate::emit_usb_reconnecting(
+                &ctx.app_handle,
+                reconnect_attempt,
+                MAX_ATTEMPTS,
+                Some("Looking for USB device...".to_string()),
+            );
+
+            match open_usb_camera(ctx) {
+                Ok(Some(new_device)) => break new_device,
+                Ok(None) => {
+                    log::info!(
+                        "No USB device available yet (attempt {})",
+                        reconnect_attempt
+                    );
+                    // Loop back to wait and try again
+                }
+                Err(e) => {
+                    log::warn!(
+                        "Could not open USB device (attempt {}): {}",
+                        reconnect_attempt,
+                        e
+                    );
+                    crate::emit_usb_reconnecting(
+                        &ctx.app_handle,
+                        reconnect_attempt,
+                        MAX_ATTEMPTS,
+                        Some(e.to_string()),
+                    );
+                }
             }
-        }
+        };
+
+        log::info!(
+            "Successfully acquired new USB fd: {} (attempt {})",
+            new_device.fd(),
+            reconnect_attempt
+        );
+
+        // Emit connected event
+        crate::emit_usb_event(
+            &ctx.app_handle,
+            true,
+            Some(format!("USB Camera reconnected (fd: {})", new_device.fd())),
+        );
+        ctx.stream_health.record_reconnect();
+
+        // Reset reconnection state
+        current_device = new_device;
+        reconnect_attempt = 0;
+        current_delay_ms = INITIAL_DELAY_MS;
+        // Note: disconnect_reason will be set by the next disconnection event
     }
 
+    lock_or_recover!(ctx.streaming_config).active_stream = None;
+    lock_or_recover!(ctx.devices).set_open(None);
+
     // Emit final disconnected event with reason when camera loop exits
     let final_reason = disconnect_reason.unwrap_or(DisconnectReason::Normal);
     log::info!(
@@ -933,8 +1587,8 @@ fn run_camera_loop(initial_fd: i32, ctx: StreamingContext) {
 }
 
 /// Result of a streaming session
-#[cfg(target_os = "android")]
-enum StreamResult {
+#[cfg(usb_streaming)]
+pub(crate) enum StreamResult {
     /// Streaming ended normally (e.g., user stopped, app closing)
     Normal,
     /// Restart was requested (e.g., format change)
@@ -949,15 +1603,27 @@ enum StreamResult {
 
 #[cfg(target_os = "android")]
 fn run_camera_loop_inner(
-    fd: i32,
+    device: &OpenedDevice,
     stream_ctx: &StreamingContext,
 ) -> Result<StreamResult, LibusbError> {
     // Initialize libusb context for Android (no device discovery)
-    let usb_ctx = LibusbContext::new_android()?;
+    let usb_ctx = match LibusbContext::new_android() {
+        Ok(ctx) => ctx,
+        Err(LibusbError::NotSupported) => return stream_via_usb_connection(device, stream_ctx),
+        Err(e) => return Err(e),
+    };
     log::info!("libusb context created");
 
     // Wrap the Android file descriptor as a libusb device handle
-    let dev = usb_ctx.wrap_fd(fd)?;
+    // (shared with the camera controls while streaming)
+    let dev = match usb_ctx.wrap_fd(device.fd()) {
+        Ok(dev) => Arc::new(dev),
+        Err(LibusbError::NotSupported) => {
+            drop(usb_ctx);
+            return stream_via_usb_connection(device, stream_ctx);
+        }
+        Err(e) => return Err(e),
+    };
     log::info!("Android FD wrapped successfully");
 
     // Get device descriptor to verify we have a video device
@@ -984,7 +1650,7 @@ fn run_camera_loop_inner(
                 info.alt_setting,
                 info.max_packet_size,
                 info.transactions_per_microframe,
-                info.max_packet_size * info.transactions_per_microframe
+                info.effective_packet_size()
             );
             info
         }
@@ -1004,37 +1670,60 @@ fn run_camera_loop_inner(
     }
 
     // Discover available formats from UVC descriptors and store in streaming config
-    let formats = discover_and_store_formats(&dev, &stream_ctx.streaming_config);
+    let catalog = discover_and_store_formats(&dev, &stream_ctx.streaming_config);
+
+    // Expose image controls until this function returns (before the handle closes)
+    let control_units = dev.get_control_units().unwrap_or_else(|e| {
+        log::warn!("Could not read video control descriptors: {}", e);
+        Default::default()
+    });
+    let transport: Arc<dyn crate::uvc_controls::ControlTransport> = Arc::clone(&dev);
+    let _controls = stream_ctx.camera_controls.attach(transport, control_units);
+    let still_transport: Arc<dyn crate::still_capture::StillTransport> = Arc::clone(&dev);
+    let _still = stream_ctx
+        .still_capture
+        .attach(still_transport, ep_info.interface_number as u8);
 
     // Get user's format selection and MJPEG skip preference
-    let (selected_format, selected_frame, skip_mjpeg) = {
+    let (selected_format, selected_frame, preferred, format_preference, skip_mjpeg) = {
         let config = lock_or_recover!(stream_ctx.streaming_config);
         (
             config.selected_format_index,
             config.selected_frame_index,
-            config.skip_mjpeg_detection,
+            config.preferred_resolution,
+            config.format_preference.clone(),
+            // Headerless payloads can't carry MJPEG (no EOF to end a frame)
+            config.skip_mjpeg_detection || config.quirks.headerless_payloads,
         )
     };
-    // Default to frame index 1 if not specified
-    let frame_idx = selected_frame.unwrap_or(1);
+
+    // A preferred uncompressed format is streamed like a selected one; a
+    // preferred MJPEG format is tried first by MJPEG detection
+    let selected_format = selected_format.or_else(|| {
+        catalog
+            .preferred_format(&format_preference)
+            .filter(|f| f.kind == FormatKind::Uncompressed)
+            .map(|f| {
+                log::info!("Using preferred format {} (index {})", f.name(), f.index);
+                f.index
+            })
+    });
 
     // Determine which format(s) to try based on user selection
     if let Some(format_idx) = selected_format {
         // User explicitly selected a format - use it directly
         log::info!("Using user-selected format index: {}", format_idx);
+        let frame_idx = frame_for_format(&catalog, format_idx, selected_frame, preferred);
 
         // Check if this is an MJPEG format
-        let is_mjpeg = formats
-            .iter()
-            .find(|f| f.format_index == format_idx)
-            .map(|f| f.format_type == uvc::UvcFormatType::Mjpeg)
-            .unwrap_or(false);
+        let is_mjpeg = catalog
+            .format(format_idx)
+            .is_some_and(|f| f.kind == FormatKind::Mjpeg);
 
         if is_mjpeg {
             // Start MJPEG streaming with selected format
             // Use _with_resolution to get width/height for correct frame size detection
-            let params =
-                start_uvc_streaming_with_resolution(&dev, Some(&ep_info), format_idx, frame_idx)?;
+            let params = negotiate_stream(&dev, &ep_info, stream_ctx, format_idx, frame_idx)?;
             log::info!(
                 "MJPEG streaming started on endpoint 0x{:02x} with format {}, resolution {}x{}",
                 params.endpoint,
@@ -1043,38 +1732,26 @@ fn run_camera_loop_inner(
                 params.height
             );
 
-            match ep_info.transfer_type {
-                TransferType::Isochronous => {
-                    stream_frames_isochronous_with_format_detection(
-                        &usb_ctx,
-                        &dev,
-                        &ep_info,
-                        stream_ctx.app_handle.clone(),
-                        stream_ctx.frame_buffer.clone(),
-                        format_idx,
-                        params.width,
-                        params.height,
-                    )?;
-                }
-                TransferType::Bulk => {
-                    stream_frames(
-                        &dev,
-                        ep_info.address,
-                        stream_ctx.app_handle.clone(),
-                        stream_ctx.frame_buffer.clone(),
-                    )?;
-                }
-                _ => {
-                    log::error!("Unsupported transfer type: {:?}", ep_info.transfer_type);
-                    return Err(LibusbError::NotSupported);
+            let result = stream_frames_with_format_detection(
+                &usb_ctx,
+                &dev,
+                &ep_info,
+                stream_ctx,
+                format_idx,
+                params.width,
+                params.height,
+                params.frame_interval,
+                params.max_payload,
+            )?;
+            return Ok(match result {
+                FormatDetectionResult::RestartRequested => StreamResult::RestartRequested,
+                FormatDetectionResult::MjpegFound | FormatDetectionResult::NotMjpeg => {
+                    StreamResult::Normal
                 }
-            }
-            // MJPEG streaming doesn't support restart yet - return Normal
-            return Ok(StreamResult::Normal);
+            });
         } else {
             // Start YUV streaming with selected format
-            let params =
-                start_uvc_streaming_with_resolution(&dev, Some(&ep_info), format_idx, frame_idx)?;
+            let params = negotiate_stream(&dev, &ep_info, stream_ctx, format_idx, frame_idx)?;
             log::info!(
                 "YUV streaming started on endpoint 0x{:02x}, resolution {}x{} with format {}",
                 params.endpoint,
@@ -1090,6 +1767,8 @@ fn run_camera_loop_inner(
                 stream_ctx,
                 params.width as u32,
                 params.height as u32,
+                params.frame_interval,
+                params.max_payload,
             );
         }
     } else if skip_mjpeg {
@@ -1097,11 +1776,13 @@ fn run_camera_loop_inner(
     } else {
         // Auto-detect: Try different format indices to find MJPEG format
         // Format index 1 is not guaranteed to be MJPEG - varies by device
-        for format_index in 1..=UVC_CONFIG.max_format_index {
+        let candidates = mjpeg_candidates(&catalog, &format_preference);
+        for (attempt, &format_index) in candidates.iter().enumerate() {
             log::info!(
-                "=== Trying format index {} of {} ===",
+                "=== Trying format index {} ({} of {}) ===",
                 format_index,
-                UVC_CONFIG.max_format_index
+                attempt + 1,
+                candidates.len()
             );
 
             match try_mjpeg_streaming(
@@ -1110,6 +1791,7 @@ fn run_camera_loop_inner(
                 &ep_info,
                 stream_ctx,
                 format_index,
+                frame_for_format(&catalog, format_index, selected_frame, preferred),
                 streaming_interface,
             ) {
                 MjpegStreamingResult::Success(result) => {
@@ -1124,8 +1806,36 @@ fn run_camera_loop_inner(
         log::info!("No MJPEG format found, falling back to YUV streaming");
     }
 
-    // YUV streaming with format index 1
-    start_yuy2_fallback(&usb_ctx, &dev, &ep_info, stream_ctx)
+    // YUV streaming with the most preferred uncompressed format
+    start_yuy2_fallback(
+        &usb_ctx,
+        &dev,
+        &ep_info,
+        stream_ctx,
+        &catalog,
+        &format_preference,
+        selected_frame,
+        preferred,
+    )
+}
+
+/// Format indices to probe for MJPEG, in order
+///
+/// Formats the descriptors declare as MJPEG come first, then the rest of the
+/// catalog, since some cameras send MJPEG on a format declared otherwise.
+/// Within each group, formats follow `format_preference`. Without
+/// descriptors, indices 1 to [`UvcConfig::max_format_index`] are tried.
+#[cfg(target_os = "android")]
+fn mjpeg_candidates(catalog: &FormatCatalog, format_preference: &[String]) -> Vec<u8> {
+    if catalog.is_empty() {
+        return (1..=UVC_CONFIG.max_format_index).collect();
+    }
+    let (mjpeg, other): (Vec<_>, Vec<_>) = catalog
+        .by_preference(format_preference)
+        .into_iter()
+        .filter(|f| f.kind != FormatKind::FrameBased)
+        .partition(|f| f.kind == FormatKind::Mjpeg);
+    mjpeg.iter().chain(&other).map(|f| f.index).collect()
 }
 
 /// Result of format detection during streaming
@@ -1136,6 +1846,8 @@ enum FormatDetectionResult {
     MjpegFound,
     /// Not MJPEG format, try next format index
     NotMjpeg,
+    /// Streaming stopped so it can be renegotiated (e.g., resolution change)
+    RestartRequested,
 }
 
 /// Known YUY2 frame sizes for common resolutions
@@ -1169,7 +1881,98 @@ fn detect_yuy2_resolution(frame_size: usize) -> Option<(u32, u32)> {
     }
 }
 
-/// Stream frames using isochronous transfers with format detection
+/// Open an isochronous or bulk stream, whichever the endpoint uses
+///
+/// Isochronous transfers are sized to span about one frame (see
+/// [`packets_per_transfer`]); a bulk transfer holds one payload of up to the
+/// negotiated `dwMaxPayloadTransferSize` (see
+/// [`crate::bulk_transfer::BulkTransferConfig::transfer_size`]).
+#[cfg(target_os = "android")]
+#[allow(clippy::too_many_arguments)]
+fn open_video_stream(
+    usb_ctx: &LibusbContext,
+    dev: &LibusbDeviceHandle,
+    ep_info: &EndpointInfo,
+    stream_ctx: &StreamingContext,
+    expected_frame_size: usize,
+    validation_level: Arc<Mutex<crate::ValidationLevel>>,
+    pixel_format: PixelFormat,
+    (width, height): (u32, u32),
+    frame_interval: u32,
+    max_payload: u32,
+) -> Result<VideoStream, LibusbError> {
+    // Packets are only recorded while a capture/recording is active
+    let capture_state = Some(Arc::clone(&stream_ctx.capture_state));
+    let stats = Arc::clone(&stream_ctx.stream_stats);
+    stats.reset();
+    let frame_capacity = lock_or_recover!(stream_ctx.streaming_config).frame_channel_capacity();
+    match ep_info.transfer_type {
+        TransferType::Isochronous => {
+            // For high-bandwidth (and SuperSpeed) endpoints, the effective packet
+            // size includes the burst multiplier (e.g., 1024 x3 = 3072 bytes).
+            // Using only the base max_packet_size causes buffer overlap and frame
+            // corruption at higher resolutions.
+            let packet_size = ep_info.effective_packet_size();
+            let packets = packets_per_transfer(
+                frame_interval,
+                dev.service_interval_us(),
+                packet_size,
+                expected_frame_size,
+            );
+            log::info!("Using ISOCHRONOUS transfers for video streaming");
+            // SAFETY: ctx/dev pointers are valid libusb handles from LibusbContext/LibusbDeviceHandle.
+            let stream = unsafe {
+                IsochronousStream::new(
+                    usb_ctx.get_context_ptr(),
+                    dev.get_handle_ptr(),
+                    ep_info.address,
+                    packet_size,
+                    packets,
+                    expected_frame_size,
+                    capture_state,
+                    stats,
+                    frame_capacity,
+                    validation_level,
+                    width as usize,
+                    height as usize,
+                    pixel_format,
+                )?
+            };
+            Ok(VideoStream::Isochronous(stream))
+        }
+        TransferType::Bulk => {
+            let config = lock_or_recover!(stream_ctx.streaming_config)
+                .bulk_transfer
+                .clone();
+            log::info!("Using BULK transfers for video streaming");
+            // SAFETY: ctx/dev pointers are valid libusb handles from LibusbContext/LibusbDeviceHandle.
+            let stream = unsafe {
+                BulkStream::new(
+                    usb_ctx.get_context_ptr(),
+                    dev.get_handle_ptr(),
+                    ep_info.address,
+                    config.transfer_size(max_payload),
+                    config.max_retries,
+                    expected_frame_size,
+                    capture_state,
+                    stats,
+                    frame_capacity,
+                    validation_level,
+                    width as usize,
+                    height as usize,
+                    pixel_format,
+                )?
+            };
+            Ok(VideoStream::Bulk(stream))
+        }
+        other => {
+            log::error!("Unsupported endpoint transfer type: {:?}", other);
+            Err(LibusbError::NotSupported)
+        }
+    }
+}
+
+/// Stream frames using isochronous or bulk transfers with format detection
 /// Returns MjpegFound if JPEG frames are detected and continues streaming,
 /// or NotMjpeg if the format doesn't appear to be MJPEG
 ///
@@ -1177,37 +1980,35 @@ fn detect_yuy2_resolution(frame_size: usize) -> Option<(u32, u32)> {
 /// to calculate the correct expected frame size for YUY2 format detection.
 /// MJPEG uses EOF markers and doesn't rely on frame size.
 #[cfg(target_os = "android")]
-fn stream_frames_isochronous_with_format_detection(
+#[allow(clippy::too_many_arguments)]
+fn stream_frames_with_format_detection(
     ctx: &LibusbContext,
     dev: &LibusbDeviceHandle,
     ep_info: &EndpointInfo,
-    app_handle: AppHandle,
-    shared_frame_buffer: Arc<Mutex<FrameBuffer>>,
+    stream_ctx: &StreamingContext,
     format_index: u8,
     width: u16,
     height: u16,
+    frame_interval: u32,
+    max_payload: u32,
 ) -> Result<FormatDetectionResult, LibusbError> {
     use std::time::{Duration, Instant};
     use tauri::Emitter;
 
     log::info!(
-        "Starting isochronous streaming with format detection (format_index={}, resolution={}x{})",
+        "Starting {:?} streaming with format detection (format_index={}, resolution={}x{})",
+        ep_info.transfer_type,
         format_index,
         width,
         height
     );
 
-    // For high-bandwidth isochronous endpoints, the effective packet size includes
-    // the transactions-per-microframe multiplier (e.g., 1024 x3 = 3072 bytes).
-    // Using only the base max_packet_size causes buffer overlap and frame corruption
-    // at higher resolutions where the camera needs full bandwidth.
-    let effective_packet_size = ep_info.max_packet_size * ep_info.transactions_per_microframe;
-
     // Emit connecting status to update frontend UI during format detection
-    let _ = app_handle.emit(
+    let _ = stream_ctx.app_handle.emit(
         "usb-status",
         serde_json::json!({
             "status": "connecting",
+            "code": MessageCode::StatusDetectingFormat,
             "detail": format!("Detecting format (index {})...", format_index)
         }),
     );
@@ -1217,31 +2018,31 @@ fn stream_frames_isochronous_with_format_detection(
     // MJPEG uses EOF markers and doesn't rely on this size.
     let expected_yuy2_frame_size = (width as usize) * (height as usize) * 2;
 
-    // Create the isochronous stream
     // Use calculated frame size so YUY2 detection works correctly
     // Validation is Off since we're still detecting the format
-    // SAFETY: ctx/dev pointers are valid libusb handles obtained from LibusbContext/LibusbDeviceHandle.
-    let mut iso_stream = unsafe {
-        IsochronousStream::new(
-            ctx.get_context_ptr(),
-            dev.get_handle_ptr(),
-            ep_info.address,
-            effective_packet_size,
-            expected_yuy2_frame_size, // Use descriptor-based size for YUY2 detection
-            None,                     // No packet capture for format detection
-            crate::ValidationLevel::Off, // No validation during format detection
-            width as usize,
-            height as usize,
-        )?
-    };
+    let mut stream = open_video_stream(
+        ctx,
+        dev,
+        ep_info,
+        stream_ctx,
+        expected_yuy2_frame_size,
+        Arc::new(Mutex::new(crate::ValidationLevel::Off)),
+        PixelFormat::Yuyv,
+        (u32::from(width), u32::from(height)),
+        frame_interval,
+        max_payload,
+    )?;
 
-    let frame_receiver = iso_stream.take_frame_receiver().ok_or(LibusbError::Other)?;
-    iso_stream.start()?;
+    let mut frames = stream.subscribe();
+    // Most cameras that get here stream MJPEG; a YUY2 result restarts the
+    // stream through stream_frames_yuy2, which warms up the conversion
+    warm_up_pipeline(stream_ctx, WarmupPlan::Mjpeg);
+    stream.start()?;
 
     // Spawn event loop thread
     let event_loop_handle = spawn_libusb_event_loop(
         SendableContextPtr::new(ctx.get_context_ptr()),
-        iso_stream.stop_flag.clone(),
+        Arc::clone(stream.stop_flag()),
         "format-detection",
         false,
     );
@@ -1269,7 +2070,7 @@ fn stream_frames_isochronous_with_format_detection(
             break;
         }
 
-        match frame_receiver.recv_timeout(Duration::from_secs(FORMAT_DETECTION_TIMEOUT_SECS)) {
+        match frames.recv_timeout(Duration::from_secs(FORMAT_DETECTION_TIMEOUT_SECS)) {
             Ok(frame_data) => {
                 frames_checked += 1;
 
@@ -1300,7 +2101,7 @@ fn stream_frames_isochronous_with_format_detection(
             }
             Err(std::sync::mpsc::RecvTimeoutError::Disconnected) => {
                 log::error!("Frame channel disconnected during format detection");
-                iso_stream.stop();
+                stream.stop();
                 let _ = event_loop_handle.join();
                 return Err(LibusbError::Pipe);
             }
@@ -1324,7 +2125,7 @@ fn stream_frames_isochronous_with_format_detection(
 
     if !is_mjpeg_format {
         // Not MJPEG, stop streaming and return
-        iso_stream.stop();
+        stream.stop();
         let _ = event_loop_handle.join();
         return Ok(FormatDetectionResult::NotMjpeg);
     }
@@ -1337,44 +2138,77 @@ fn stream_frames_isochronous_with_format_detection(
 
     // Emit connected event to update frontend UI
     crate::emit_usb_event(
-        &app_handle,
+        &stream_ctx.app_handle,
         true,
         Some(format!("MJPEG Camera (format {})", format_index)),
     );
 
     // Emit status update to frontend
-    let _ = app_handle.emit(
+    let _ = stream_ctx.app_handle.emit(
         "usb-status",
         serde_json::json!({
             "status": "streaming",
+            "code": MessageCode::StatusStreamingMjpeg,
             "detail": format!("MJPEG format (index {})", format_index)
         }),
     );
 
     let mut frame_count = frames_checked;
 
+    // Recording and spooling read the stream on their own cursor, so disk
+    // writes never delay the display
+    let recorder_handle = spawn_recording_consumer(
+        stream.subscribe(),
+        stream_ctx,
+        Arc::clone(stream.stop_flag()),
+        width as u32,
+        height as u32,
+    );
+
+    let mut result = FormatDetectionResult::MjpegFound;
     loop {
-        match frame_receiver.recv_timeout(Duration::from_secs(FRAME_RECV_TIMEOUT_SECS)) {
-            Ok(frame_data) => {
-                frame_count += 1;
+        if lock_or_recover!(stream_ctx.streaming_config).restart_requested {
+            log::info!("Restart requested, stopping MJPEG streaming");
+            result = FormatDetectionResult::RestartRequested;
+            break;
+        }
 
-                // Store frame in shared buffer
+        match frames.recv_with_notes_timeout(Duration::from_secs(FRAME_RECV_TIMEOUT_SECS)) {
+            Ok(frame) => {
+                let frame_data = frame.data;
+                if stream_ctx
+                    .still_capture
+                    .offer(&frame_data, frame.notes.still_image)
                 {
-                    let mut buffer = lock_or_recover!(shared_frame_buffer);
-                    buffer.frame = frame_data;
-                    buffer.timestamp = Instant::now();
+                    continue;
+                }
+                frame_count += 1;
+                trace_jpeg_frame(stream_ctx, &frame_data, width as u32, height as u32);
+
+                if !publish_decoded_mjpeg(stream_ctx, &frame_data) {
+                    // Store frame in shared buffer (the broadcast's, not a copy)
+                    let info =
+                        stream_ctx
+                            .frame_buffer
+                            .store(frame_data, width as u32, height as u32);
+                    stream_ctx.stream_health.record_frame();
+
+                    // Emit notification to trigger frontend fetch
+                    crate::emit_frame_ready(&stream_ctx.app_handle, &info);
                 }
-
-                // Emit notification to trigger frontend fetch
-                let _ = app_handle.emit("frame-ready", ());
 
                 if frame_count % LOG_INTERVAL_FRAMES == 0 {
-                    log::info!("Received {} frames via isochronous transfer", frame_count);
+                    log::info!(
+                        "Received {} frames via {:?} transfers",
+                        frame_count,
+                        ep_info.transfer_type
+                    );
                 }
             }
             Err(std::sync::mpsc::RecvTimeoutError::Timeout) => {
                 log::warn!("No frames received in {} seconds", FRAME_RECV_TIMEOUT_SECS);
-                if iso_stream.is_stopped() {
+                stream_ctx.stream_health.record_stall();
+                if stream.is_stopped() {
                     break;
                 }
             }
@@ -1385,15 +2219,61 @@ fn stream_frames_isochronous_with_format_detection(
         }
     }
 
-    iso_stream.stop();
+    stream.stop();
     let _ = event_loop_handle.join();
+    let _ = recorder_handle.join();
 
+    if frames.lagged() > 0 {
+        log::warn!("Display skipped {} frames while busy", frames.lagged());
+    }
     log::info!("Streaming ended after {} total frames", frame_count);
-    Ok(FormatDetectionResult::MjpegFound)
+    Ok(result)
 }
 
-/// Calculated frame dimensions from raw frame data
+/// Spawn a consumer that hands every MJPEG frame to the recorder and spooler
+///
+/// Reads its own cursor, so a slow disk makes it skip frames instead of
+/// holding up the display; the skipped frames are counted in the recording
+/// (`skipped_frames`). Ends when the stream stops or closes.
 #[cfg(target_os = "android")]
+fn spawn_recording_consumer(
+    mut frames: FrameCursor,
+    stream_ctx: &StreamingContext,
+    stop_flag: Arc<std::sync::atomic::AtomicBool>,
+    width: u32,
+    height: u32,
+) -> std::thread::JoinHandle<()> {
+    let recording = Arc::clone(&stream_ctx.recording);
+    let spooler = Arc::clone(&stream_ctx.spooler);
+    std::thread::Builder::new()
+        .name("frame-recorder".to_string())
+        .spawn(move || {
+            let mut reported = 0;
+            loop {
+                match frames.recv_timeout(Duration::from_millis(CONSUMER_POLL_MS)) {
+                    Ok(frame_data) => {
+                        recording.record_skipped(frames.lagged() - reported);
+                        reported = frames.lagged();
+                        recording.record_frame(&frame_data, width, height, FrameFormat::Jpeg);
+                        spooler.offer(&frame_data, width, height, FrameFormat::Jpeg);
+                    }
+                    Err(std::sync::mpsc::RecvTimeoutError::Timeout) => {
+                        if stop_flag.load(std::sync::atomic::Ordering::Relaxed) {
+                            break;
+                        }
+                    }
+                    Err(std::sync::mpsc::RecvTimeoutError::Disconnected) => break,
+                }
+            }
+            if frames.lagged() > 0 {
+                log::warn!("Recorder skipped {} frames while busy", frames.lagged());
+            }
+        })
+        .expect("Failed to spawn frame recorder thread")
+}
+
+/// Calculated frame dimensions from raw frame data
+#[cfg(usb_streaming)]
 struct FrameDimensions {
     width: u32,
     height: u32,
@@ -1411,14 +2291,17 @@ struct FrameDimensions {
 /// Some cameras send more data than their descriptor claims (e.g., 1920px wide
 /// despite advertising 640px). This function uses the actual frame size to
 /// determine the real dimensions while respecting user overrides.
-#[cfg(target_os = "android")]
+#[cfg(usb_streaming)]
 fn calculate_frame_dimensions(
     frame_size: usize,
     base_width: u32,
     base_height: u32,
+    pixel_format: PixelFormat,
     settings: &DisplaySettings,
     stride_idx: &Option<usize>,
 ) -> FrameDimensions {
+    let bits_per_pixel = u32::from(crate::format_registry::entry(pixel_format).bits_per_pixel);
+
     // Use height from settings override or descriptor
     let height = settings.height.unwrap_or(base_height);
 
@@ -1428,11 +2311,11 @@ fn calculate_frame_dimensions(
         let raw_stride = (frame_size as u32) / height;
         (raw_stride / 2) * 2 // Round down to even
     } else {
-        base_width * 2
+        base_width * bits_per_pixel / 8
     };
 
-    // Derive actual width from stride (YUY2 = 2 bytes per pixel)
-    let actual_width = actual_stride / 2;
+    // Derive actual width from stride (YUY2 = 2 bytes per pixel, Bayer = 1)
+    let actual_width = actual_stride * 8 / bits_per_pixel;
 
     // Use settings override or calculated values
     let width = settings.width.unwrap_or(actual_width);
@@ -1455,47 +2338,27 @@ fn calculate_frame_dimensions(
     }
 }
 
-/// Convert frame data to RGB based on pixel format
+/// Warm up the frame pipeline for the negotiated stream before it starts
 ///
-/// Dispatches to the appropriate conversion function based on the pixel format.
-/// Supports YUV422 packed (YUYV/UYVY), YUV420 planar (I420/NV12), and RGB formats.
+/// Runs after probe/commit so the first real frame doesn't pay for
+/// first-use allocation and encoder setup (see `crate::warmup`).
 #[cfg(target_os = "android")]
-fn convert_frame_to_rgb(
-    frame_data: &[u8],
-    width: u32,
-    height: u32,
-    stride: u32,
-    pixel_format: PixelFormat,
-) -> Result<Vec<u8>, String> {
-    let stride_override = Some(stride);
-
-    let result = match pixel_format {
-        PixelFormat::Yuyv => convert_yuv422_to_rgb(
-            frame_data,
-            width,
-            height,
-            stride_override,
-            YuvPackedFormat::Yuyv,
-        ),
-        PixelFormat::Uyvy => convert_yuv422_to_rgb(
-            frame_data,
-            width,
-            height,
-            stride_override,
-            YuvPackedFormat::Uyvy,
-        ),
-        PixelFormat::I420 => convert_i420_to_rgb(frame_data, width, height),
-        PixelFormat::Nv12 => convert_nv12_to_rgb(frame_data, width, height),
-        PixelFormat::Rgb888 => pass_through_rgb888(frame_data, width, height),
-        PixelFormat::Bgr888 => convert_bgr888_to_rgb(frame_data, width, height),
-    };
-
-    // Convert ConversionError to String for backward compatibility
-    result.map_err(|e| e.0)
+fn warm_up_pipeline(stream_ctx: &StreamingContext, plan: WarmupPlan) {
+    use tauri::Manager;
+
+    let snapshot_format = stream_ctx
+        .app_handle
+        .try_state::<crate::AppState>()
+        .and_then(|state| *lock_or_recover!(state.snapshot_format));
+    crate::warmup::warm_up(
+        plan,
+        snapshot_format,
+        &mut lock_or_recover!(stream_ctx.conversion_buffer),
+    );
 }
 
 /// Log detailed frame analysis for the first few frames to aid debugging.
-#[cfg(target_os = "android")]
+#[cfg(usb_streaming)]
 fn log_frame_analysis(frame_count: u32, frame_data: &[u8], base_width: u32, base_height: u32) {
     let frame_size = frame_data.len();
     let expected_size = (base_width * base_height * 2) as usize;
@@ -1536,11 +2399,30 @@ fn log_frame_analysis(frame_count: u32, frame_data: &[u8], base_width: u32, base
     }
 }
 
+/// Record the negotiated stream for `get_stream_info` and the stream supervisor
+///
+/// A stream of another camera, format or resolution gives the supervisor a
+/// fresh set of re-probes.
+#[cfg(usb_streaming)]
+pub(crate) fn record_active_stream(stream_ctx: &StreamingContext, active: crate::ActiveStream) {
+    let device = lock_or_recover!(stream_ctx.devices)
+        .open()
+        .map(str::to_string);
+    stream_ctx
+        .stream_supervisor
+        .stream_started(crate::stream_supervisor::StreamIdentity {
+            device,
+            format_index: active.format_index,
+            frame_index: active.frame_index,
+        });
+    lock_or_recover!(stream_ctx.streaming_config).set_active_stream(active);
+}
+
 /// Store a converted RGB frame in the shared buffer and notify the frontend.
-#[cfg(target_os = "android")]
-fn store_frame_and_emit(
+#[cfg(usb_streaming)]
+pub(crate) fn store_frame_and_emit(
     stream_ctx: &StreamingContext,
-    rgb_data: Vec<u8>,
+    mut rgb_data: Vec<u8>,
     raw_frame_data: &[u8],
     width: u32,
     height: u32,
@@ -1560,23 +2442,442 @@ fn store_frame_and_emit(
         );
     }
 
+    let mut annotations = Vec::new();
+    let (mut width, mut height) = (width, height);
+    let format = if is_jpeg {
+        trace_jpeg_frame(stream_ctx, &rgb_data, width, height);
+        FrameFormat::Jpeg
+    } else {
+        (rgb_data, width, height) = transform_rgb(stream_ctx, rgb_data, width, height);
+        // Plugins run first, so recordings and the spool see what is displayed
+        annotations = stream_ctx
+            .plugins
+            .process_frame(&mut rgb_data, width, height);
+        FrameFormat::Rgb
+    };
+    record_with_overlay(stream_ctx, &rgb_data, width, height, format, &annotations);
+    stream_ctx.spooler.offer(&rgb_data, width, height, format);
+
+    if is_jpeg && publish_decoded_mjpeg(stream_ctx, &rgb_data) {
+        return;
+    }
+    publish_frame(
+        stream_ctx,
+        rgb_data,
+        raw_frame_data,
+        width,
+        height,
+        annotations,
+    );
+}
+
+/// Apply the display rotation, flips and zoom to an RGB frame
+#[cfg(usb_streaming)]
+fn transform_rgb(
+    stream_ctx: &StreamingContext,
+    rgb: Vec<u8>,
+    width: u32,
+    height: u32,
+) -> (Vec<u8>, u32, u32) {
+    let display = lock_or_recover!(stream_ctx.display).clone();
+    display.apply_view(rgb, width, height)
+}
+
+/// Decode an MJPEG frame and publish it as RGB if MJPEG decoding is enabled
+///
+/// Frames are also decoded while a rotation, flip or zoom is set, since those
+/// only apply to RGB. Plugins run on the decoded frame and the JPEG is kept as the
+/// raw frame. Tracing, recording and spooling stay with the caller, which
+/// handles MJPEG frames as JPEG. Returns `false` if decoding is off or the
+/// frame does not decode; the caller then stores the JPEG as it is.
+#[cfg(usb_streaming)]
+pub(crate) fn publish_decoded_mjpeg(stream_ctx: &StreamingContext, jpeg: &[u8]) -> bool {
+    let decode = lock_or_recover!(stream_ctx.streaming_config).decode_mjpeg
+        || !lock_or_recover!(stream_ctx.display).is_default_view();
+    if !decode {
+        return false;
+    }
+    let frame = match crate::jpeg_decode::decode(jpeg) {
+        Ok(frame) => frame,
+        Err(e) => {
+            log::debug!("Passing MJPEG frame through undecoded: {}", e);
+            return false;
+        }
+    };
+    let (mut rgb, width, height) = transform_rgb(stream_ctx, frame.rgb, frame.width, frame.height);
+    let annotations = stream_ctx.plugins.process_frame(&mut rgb, width, height);
+    publish_frame(stream_ctx, rgb, jpeg, width, height, annotations);
+    true
+}
+
+/// Store a frame in the shared buffer and notify the frontend
+#[cfg(usb_streaming)]
+fn publish_frame(
+    stream_ctx: &StreamingContext,
+    data: Vec<u8>,
+    raw_frame_data: &[u8],
+    width: u32,
+    height: u32,
+    annotations: Vec<crate::annotations::Annotation>,
+) {
+    let info = stream_ctx
+        .frame_buffer
+        .store_with_raw(data, raw_frame_data, width, height);
+    stream_ctx.stream_health.record_frame();
+
+    crate::emit_frame_ready(&stream_ctx.app_handle, &info);
+    if !annotations.is_empty() {
+        crate::emit_frame_annotations(
+            &stream_ctx.app_handle,
+            crate::annotations::FrameAnnotations {
+                sequence: info.sequence,
+                annotations,
+            },
+        );
+    }
+}
+
+/// Record a frame, drawing the active annotations into a copy if enabled
+///
+/// `annotations` are this frame's plugin annotations (published after the
+/// frame is stored). MJPEG frames are recorded as they are. Emits
+/// `encode-quality` when the video's JPEG quality is adapted.
+#[cfg(usb_streaming)]
+fn record_with_overlay(
+    stream_ctx: &StreamingContext,
+    rgb_data: &[u8],
+    width: u32,
+    height: u32,
+    format: FrameFormat,
+    annotations: &[crate::annotations::Annotation],
+) {
+    let mut burned = None;
+    if format == FrameFormat::Rgb && stream_ctx.recording.is_recording() {
+        let sequence = stream_ctx.frame_buffer.sequence() + 1;
+        let overlay = stream_ctx
+            .annotations
+            .active_for_recording(sequence, annotations);
+        if !overlay.is_empty() {
+            let mut frame = rgb_data.to_vec();
+            crate::overlay::draw_annotations(&mut frame, width, height, &overlay);
+            burned = Some(frame);
+        }
+    }
+    let frame = burned.as_deref().unwrap_or(rgb_data);
+    if let Some(change) = stream_ctx
+        .recording
+        .record_frame(frame, width, height, format)
+    {
+        let _ = stream_ctx.app_handle.emit("encode-quality", change);
+    }
+}
+
+/// Write an MJPEG frame to the pending frame trace, if one was armed
+#[cfg(usb_streaming)]
+fn trace_jpeg_frame(stream_ctx: &StreamingContext, jpeg: &[u8], width: u32, height: u32) {
+    if let Some(mut trace) = stream_ctx
+        .frame_tracer
+        .begin_frame(&stream_ctx.capture_state)
     {
-        let mut buffer = lock_or_recover!(stream_ctx.frame_buffer);
-        buffer.frame = rgb_data;
-        if buffer.capture_raw_frames {
-            buffer.raw_frame = raw_frame_data.to_vec();
+        trace.jpeg(jpeg, width, height);
+        trace.finish();
+    }
+}
+
+/// Turns assembled YUV frames into displayed RGB frames
+///
+/// Holds the per-session state shared by the isochronous and bulk YUV paths.
+#[cfg(usb_streaming)]
+pub(crate) struct YuvFrameProcessor {
+    /// Descriptor resolution - this is the authoritative source
+    base_width: u32,
+    base_height: u32,
+    /// Pixel format negotiated when the stream started
+    stream_format: PixelFormat,
+    /// Minimum acceptable frame size for the negotiated pixel format
+    min_expected_size: usize,
+    frame_count: u32,
+    /// Frames withheld because they failed validation
+    rejected_count: u32,
+    // Session-scoped one-shot flags (reset each streaming session)
+    rgb_logged: bool,
+    resolution_logged: bool,
+    last_settings_hash: u64,
+}
+
+#[cfg(usb_streaming)]
+impl YuvFrameProcessor {
+    pub(crate) fn new(base_width: u32, base_height: u32, pixel_format: PixelFormat) -> Self {
+        Self {
+            base_width,
+            base_height,
+            stream_format: pixel_format,
+            min_expected_size: pixel_format.frame_size(base_width, base_height),
+            frame_count: 0,
+            rejected_count: 0,
+            rgb_logged: false,
+            resolution_logged: false,
+            last_settings_hash: 0,
+        }
+    }
+
+    /// Validate a frame assembled on the streaming thread and end its frame
+    /// in the capture
+    ///
+    /// The isochronous callback validates frames as it completes them; paths
+    /// that assemble frames themselves call this instead. The frame is
+    /// validated at the negotiated format and resolution, so `process` reuses
+    /// the verdict carried in the returned notes.
+    pub(crate) fn assembled(
+        &self,
+        stream_ctx: &StreamingContext,
+        frame_data: &[u8],
+        still_image: bool,
+    ) -> crate::frame_broadcast::FrameNotes {
+        let validation = crate::frame_validation::validate_frame(
+            frame_data,
+            self.base_width as usize,
+            self.base_height as usize,
+            self.stream_format,
+            self.min_expected_size,
+            *lock_or_recover!(stream_ctx.validation_level),
+        );
+        stream_ctx.capture_state.end_frame(!validation.valid);
+        if !validation.valid {
+            stream_ctx.stream_stats.record_corrupt();
+        }
+        crate::frame_broadcast::FrameNotes {
+            validation: Some(Arc::new(validation)),
+            still_image,
+        }
+    }
+
+    /// Restart the stream with a fresh probe/commit once too many frames in a
+    /// row failed validation (see `stream_supervisor`)
+    fn supervise(&self, stream_ctx: &StreamingContext, valid: bool) {
+        use crate::stream_supervisor::SupervisorAction;
+
+        match stream_ctx.stream_supervisor.frame_validated(valid) {
+            SupervisorAction::Continue => {}
+            SupervisorAction::Reprobe(attempt) => {
+                log::warn!(
+                    "{} frames in a row failed validation, re-probing the camera (attempt {}/{})",
+                    stream_ctx.stream_supervisor.threshold(),
+                    attempt,
+                    crate::stream_supervisor::MAX_REPROBES
+                );
+                lock_or_recover!(stream_ctx.streaming_config).restart_requested = true;
+                let _ = stream_ctx.app_handle.emit(
+                    "usb-status",
+                    serde_json::json!({
+                        "status": "connecting",
+                        "code": MessageCode::StatusResynchronizing,
+                        "detail": format!("Re-probe {} after {} corrupt frames", attempt, stream_ctx.stream_supervisor.threshold())
+                    }),
+                );
+            }
+            SupervisorAction::GiveUp => {
+                log::error!(
+                    "Frames still fail validation after {} re-probes, giving up until a valid frame arrives",
+                    crate::stream_supervisor::MAX_REPROBES
+                );
+            }
         }
-        buffer.timestamp = std::time::Instant::now();
-        buffer.width = width;
-        buffer.height = height;
     }
 
-    crate::emit_frame_ready(&stream_ctx.app_handle, width, height, is_jpeg);
+    /// Convert one assembled frame to RGB, record it and notify the frontend
+    ///
+    /// Frames that fail validation at the current level are dropped and
+    /// counted in `rejected_frames` of the stream stats. `notes` are what the
+    /// stream found out assembling the frame: a validation verdict is reused
+    /// while the frame is read with the format and resolution the stream
+    /// validated it with.
+    pub(crate) fn process(
+        &mut self,
+        stream_ctx: &StreamingContext,
+        frame_data: &[u8],
+        pixel_format: PixelFormat,
+        notes: &crate::frame_broadcast::FrameNotes,
+    ) {
+        use tauri::Emitter;
+
+        // A method 2 still replaces one video frame
+        if stream_ctx
+            .still_capture
+            .offer(frame_data, notes.still_image)
+        {
+            return;
+        }
+
+        self.frame_count += 1;
+        let frame_size = frame_data.len();
+
+        // Log detailed frame analysis for first few frames
+        if self.frame_count <= INITIAL_FRAMES_TO_LOG {
+            log_frame_analysis(
+                self.frame_count,
+                frame_data,
+                self.base_width,
+                self.base_height,
+            );
+        }
+
+        // Skip incomplete frames - must have at least minimum expected data
+        if frame_size < self.min_expected_size / 2 {
+            if self.frame_count <= 10 {
+                log::debug!(
+                    "Skipping incomplete frame: {} bytes (expected >= {} bytes)",
+                    frame_size,
+                    self.min_expected_size
+                );
+            }
+            return;
+        }
+
+        // Calculate frame dimensions using helper function
+        let dims = {
+            let display = lock_or_recover!(stream_ctx.display);
+            calculate_frame_dimensions(
+                frame_size,
+                self.base_width,
+                self.base_height,
+                pixel_format,
+                &display.settings,
+                &display.stride_index,
+            )
+        };
+
+        let FrameDimensions {
+            width,
+            height,
+            stride,
+            actual_width,
+            actual_stride,
+        } = dims;
+
+        // Log when we detect camera sending different resolution than descriptor
+        if actual_width != self.base_width && !self.resolution_logged {
+            self.resolution_logged = true;
+            log::warn!(
+                "Camera sending {}x{} (stride={}) but descriptor says {}x{}. Using actual dimensions.",
+                actual_width, height, actual_stride, self.base_width, self.base_height
+            );
+        }
+
+        // Log settings changes
+        let settings_hash =
+            ((width as u64) << 48) | ((height as u64) << 32) | ((stride as u64) << 16);
+        if self.last_settings_hash != settings_hash {
+            self.last_settings_hash = settings_hash;
+            log::info!("Display settings: {}x{} stride={}", width, height, stride);
+            let _ = stream_ctx.app_handle.emit(
+                "usb-status",
+                serde_json::json!({
+                    "status": "streaming",
+                    "code": MessageCode::StatusStreamingYuy2,
+                    "detail": format!("YUY2 {}x{} stride={} → RGB", width, height, stride)
+                }),
+            );
+        };
+
+        let validated_as_streamed = pixel_format == self.stream_format
+            && (width, height) == (self.base_width, self.base_height);
+        let validation = match notes.validation.as_deref() {
+            Some(validation) if validated_as_streamed => validation.clone(),
+            _ => crate::frame_validation::validate_frame(
+                frame_data,
+                width as usize,
+                height as usize,
+                pixel_format,
+                pixel_format.frame_size(width, height),
+                *lock_or_recover!(stream_ctx.validation_level),
+            ),
+        };
+
+        // Persist every stage of this frame if a trace is pending
+        let mut trace = stream_ctx
+            .frame_tracer
+            .begin_frame(&stream_ctx.capture_state);
+        if let Some(trace) = trace.as_mut() {
+            trace.assembled(frame_data, pixel_format, width, height, stride);
+            trace.validation(validation.clone());
+        }
+
+        self.supervise(stream_ctx, validation.valid);
+
+        // Don't publish frames that fail validation
+        if !validation.valid {
+            let reason = validation.failure_reason.as_deref().unwrap_or("unknown");
+            stream_ctx.stream_stats.record_rejected();
+            self.rejected_count += 1;
+            if self.rejected_count <= 10 || self.rejected_count % 100 == 0 {
+                log::warn!("Rejected frame (#{}): {}", self.rejected_count, reason);
+            }
+            if let Some(mut trace) = trace {
+                trace.fail(crate::frame_trace::TraceStage::Assembled, reason);
+                trace.finish();
+            }
+            return;
+        }
+
+        // Keep the assembled frame as-is in raw video recordings
+        stream_ctx.recording.record_raw_frame(
+            frame_data,
+            FrameLayout {
+                width,
+                height,
+                stride,
+                pixel_format,
+            },
+        );
+
+        // Convert frame to RGB (into the warm-up buffer for the first frame)
+        // and store in shared buffer
+        let buffer = std::mem::take(&mut *lock_or_recover!(stream_ctx.conversion_buffer));
+        match convert_to_rgb_into(frame_data, width, height, stride, pixel_format, buffer) {
+            Ok(rgb_data) => {
+                if let Some(mut trace) = trace {
+                    trace.rgb(&rgb_data, width, height);
+                    trace.finish();
+                }
+                store_frame_and_emit(
+                    stream_ctx,
+                    rgb_data,
+                    frame_data,
+                    width,
+                    height,
+                    false,
+                    &mut self.rgb_logged,
+                );
+
+                if self.frame_count % LOG_INTERVAL_FRAMES == 0 {
+                    log::info!(
+                        "Converted {} YUY2 frames to RGB ({}x{})",
+                        self.frame_count,
+                        width,
+                        height
+                    );
+                }
+            }
+            Err(e) => {
+                if let Some(mut trace) = trace {
+                    trace.fail(crate::frame_trace::TraceStage::Rgb, &e);
+                    trace.finish();
+                }
+                if self.frame_count <= INITIAL_FRAMES_TO_LOG_ERRORS {
+                    log::error!("YUY2 conversion error: {}", e);
+                }
+            }
+        }
+    }
 }
 
-/// Stream YUV 4:2:2 frames using isochronous transfers with RGB conversion
+/// Stream YUV 4:2:2 frames using isochronous or bulk transfers with RGB conversion
 /// Supports both YUYV and UYVY formats based on streaming config
 /// width/height: The negotiated resolution from UVC descriptors
+/// Bulk cameras with the `headerless` or `content-boundaries` quirk are handed
+/// off to `stream_frames_bulk_yuy2`, which assembles frames with a [`FrameAssembler`];
+/// isochronous ones with `content-boundaries` split frames by content in the stream
 /// Returns StreamResult to indicate if restart was requested
 #[cfg(target_os = "android")]
 fn stream_frames_yuy2(
@@ -1586,27 +2887,20 @@ fn stream_frames_yuy2(
     stream_ctx: &StreamingContext,
     descriptor_width: u32,
     descriptor_height: u32,
+    frame_interval: u32,
+    max_payload: u32,
 ) -> Result<StreamResult, LibusbError> {
     use std::time::Duration;
     use tauri::Emitter;
 
     // Get current pixel format to determine expected frame size
-    let pixel_format = {
+    let (pixel_format, quirks) = {
         let config = lock_or_recover!(stream_ctx.streaming_config);
-        config.pixel_format
+        (config.pixel_format, config.quirks)
     };
 
     // Calculate expected frame size based on format
-    // YUV422 (YUYV/UYVY): 2 bytes per pixel
-    // YUV420 (I420/NV12): 1.5 bytes per pixel
-    // RGB (RGB888/BGR888): 3 bytes per pixel
-    let bytes_per_pixel = match pixel_format {
-        PixelFormat::Yuyv | PixelFormat::Uyvy => 2.0,
-        PixelFormat::I420 | PixelFormat::Nv12 => 1.5,
-        PixelFormat::Rgb888 | PixelFormat::Bgr888 => 3.0,
-    };
-    let expected_frame_size =
-        ((descriptor_width * descriptor_height) as f64 * bytes_per_pixel) as usize;
+    let expected_frame_size = pixel_format.frame_size(descriptor_width, descriptor_height);
 
     log::info!(
         "Starting {} streaming with RGB conversion, descriptor resolution: {}x{}, expected frame size: {} bytes",
@@ -1623,33 +2917,51 @@ fn stream_frames_yuy2(
         Some(format!("{} Camera", pixel_format)),
     );
 
-    // For high-bandwidth isochronous endpoints, the effective packet size includes
-    // the transactions-per-microframe multiplier (e.g., 1024 x3 = 3072 bytes).
-    let effective_packet_size = ep_info.max_packet_size * ep_info.transactions_per_microframe;
-
-    // Create the isochronous stream with descriptor-based frame size
-    // SAFETY: ctx/dev pointers are valid libusb handles from LibusbContext/LibusbDeviceHandle.
-    let mut iso_stream = unsafe {
-        IsochronousStream::new(
-            usb_ctx.get_context_ptr(),
-            dev.get_handle_ptr(),
+    if ep_info.transfer_type == TransferType::Bulk
+        && (quirks.headerless_payloads || quirks.content_boundaries)
+    {
+        return stream_frames_bulk_yuy2(
+            dev,
             ep_info.address,
-            effective_packet_size,
-            expected_frame_size,
-            None, // No packet capture (can be enabled for E2E testing)
-            stream_ctx.validation_level,
-            descriptor_width as usize,
-            descriptor_height as usize,
-        )?
-    };
+            max_payload,
+            stream_ctx,
+            descriptor_width,
+            descriptor_height,
+        );
+    }
+
+    // Create the stream with descriptor-based frame size
+    let mut stream = open_video_stream(
+        usb_ctx,
+        dev,
+        ep_info,
+        stream_ctx,
+        expected_frame_size,
+        Arc::clone(&stream_ctx.validation_level),
+        pixel_format,
+        (descriptor_width, descriptor_height),
+        frame_interval,
+        max_payload,
+    )?;
+    if let VideoStream::Isochronous(iso) = &stream {
+        iso.set_content_boundaries(content_row_stride(quirks, pixel_format, descriptor_width));
+    }
 
-    let frame_receiver = iso_stream.take_frame_receiver().ok_or(LibusbError::Other)?;
-    iso_stream.start()?;
+    let mut frames = stream.subscribe();
+    warm_up_pipeline(
+        stream_ctx,
+        WarmupPlan::Uncompressed {
+            pixel_format,
+            width: descriptor_width,
+            height: descriptor_height,
+        },
+    );
+    stream.start()?;
 
     // Spawn event loop thread
     let event_loop_handle = spawn_libusb_event_loop(
         SendableContextPtr::new(usb_ctx.get_context_ptr()),
-        iso_stream.stop_flag.clone(),
+        Arc::clone(stream.stop_flag()),
         "yuy2-streaming",
         false,
     );
@@ -1659,27 +2971,12 @@ fn stream_frames_yuy2(
         "usb-status",
         serde_json::json!({
             "status": "streaming",
+            "code": MessageCode::StatusStreamingYuy2,
             "detail": "YUY2 format (converting to RGB)"
         }),
     );
 
-    let mut frame_count = 0u32;
-    // Session-scoped one-shot flags (reset each streaming session)
-    let mut rgb_logged = false;
-    let mut resolution_logged = false;
-    let mut last_settings_hash: u64 = 0;
-
-    // Use descriptor resolution - this is the authoritative source
-    let base_width = descriptor_width;
-    let base_height = descriptor_height;
-
-    // Calculate minimum acceptable frame size based on format
-    // YUV422: width*height*2, YUV420: width*height*1.5, RGB: width*height*3
-    let min_expected_size = match pixel_format {
-        PixelFormat::Yuyv | PixelFormat::Uyvy => (base_width * base_height * 2) as usize,
-        PixelFormat::I420 | PixelFormat::Nv12 => ((base_width * base_height * 3) / 2) as usize,
-        PixelFormat::Rgb888 | PixelFormat::Bgr888 => (base_width * base_height * 3) as usize,
-    };
+    let mut processor = YuvFrameProcessor::new(descriptor_width, descriptor_height, pixel_format);
 
     loop {
         // Check restart flag and read current pixel format in a single lock
@@ -1687,121 +2984,31 @@ fn stream_frames_yuy2(
             let config = lock_or_recover!(stream_ctx.streaming_config);
             if config.restart_requested {
                 log::info!("Restart requested, stopping YUY2 streaming");
-                iso_stream.stop();
+                stream.stop();
                 let _ = event_loop_handle.join();
                 return Ok(StreamResult::RestartRequested);
             }
             config.pixel_format
         };
 
-        match frame_receiver.recv_timeout(Duration::from_secs(FRAME_RECV_TIMEOUT_SECS)) {
-            Ok(frame_data) => {
-                frame_count += 1;
-                let frame_size = frame_data.len();
-
-                // Log detailed frame analysis for first few frames
-                if frame_count <= INITIAL_FRAMES_TO_LOG {
-                    log_frame_analysis(frame_count, &frame_data, base_width, base_height);
-                }
-
-                // Skip incomplete frames - must have at least minimum expected data
-                if frame_size < min_expected_size / 2 {
-                    if frame_count <= 10 {
-                        log::debug!(
-                            "Skipping incomplete frame: {} bytes (expected >= {} bytes)",
-                            frame_size,
-                            min_expected_size
-                        );
-                    }
-                    continue;
-                }
-
-                // Calculate frame dimensions using helper function
-                let dims = {
-                    let display = lock_or_recover!(stream_ctx.display);
-                    calculate_frame_dimensions(
-                        frame_size,
-                        base_width,
-                        base_height,
-                        &display.settings,
-                        &display.stride_index,
-                    )
-                };
-
-                let FrameDimensions {
-                    width,
-                    height,
-                    stride,
-                    actual_width,
-                    actual_stride,
-                } = dims;
-
-                // Log when we detect camera sending different resolution than descriptor
-                if actual_width != base_width && !resolution_logged {
-                    resolution_logged = true;
-                    log::warn!(
-                        "Camera sending {}x{} (stride={}) but descriptor says {}x{}. Using actual dimensions.",
-                        actual_width, height, actual_stride, base_width, base_height
-                    );
-                }
-
-                // Log settings changes
-                let settings_hash =
-                    ((width as u64) << 48) | ((height as u64) << 32) | ((stride as u64) << 16);
-                if last_settings_hash != settings_hash {
-                    last_settings_hash = settings_hash;
-                    log::info!("Display settings: {}x{} stride={}", width, height, stride);
-                    let _ = stream_ctx.app_handle.emit(
-                        "usb-status",
-                        serde_json::json!({
-                            "status": "streaming",
-                            "detail": format!("YUY2 {}x{} stride={} → RGB", width, height, stride)
-                        }),
-                    );
-                };
-
-                // Convert frame to RGB and store in shared buffer
-                match convert_frame_to_rgb(&frame_data, width, height, stride, pixel_format) {
-                    Ok(rgb_data) => {
-                        store_frame_and_emit(
-                            stream_ctx,
-                            rgb_data,
-                            &frame_data,
-                            width,
-                            height,
-                            false,
-                            &mut rgb_logged,
-                        );
-
-                        if frame_count % LOG_INTERVAL_FRAMES == 0 {
-                            log::info!(
-                                "Converted {} YUY2 frames to RGB ({}x{})",
-                                frame_count,
-                                width,
-                                height
-                            );
-                        }
-                    }
-                    Err(e) => {
-                        if frame_count <= INITIAL_FRAMES_TO_LOG_ERRORS {
-                            log::error!("YUY2 conversion error: {}", e);
-                        }
-                    }
-                }
+        match frames.recv_with_notes_timeout(Duration::from_secs(FRAME_RECV_TIMEOUT_SECS)) {
+            Ok(frame) => {
+                processor.process(stream_ctx, &frame.data, pixel_format, &frame.notes);
             }
             Err(std::sync::mpsc::RecvTimeoutError::Timeout) => {
                 log::warn!("No frames received in {} seconds", FRAME_RECV_TIMEOUT_SECS);
-                if iso_stream.is_stopped() {
+                stream_ctx.stream_health.record_stall();
+                if stream.is_stopped() {
                     break;
                 }
                 // If stream hasn't stopped but we're timing out, it might be the device
                 // Check again after a brief moment
                 std::thread::sleep(std::time::Duration::from_millis(SETTLE_MS));
-                if iso_stream.is_stopped() {
+                if stream.is_stopped() {
                     break;
                 }
                 // Set timeout as the stop reason if we keep timing out
-                iso_stream.set_stop_reason(crate::libusb_android::StopReason::Timeout);
+                stream.set_stop_reason(crate::libusb_android::StopReason::Timeout);
                 break;
             }
             Err(std::sync::mpsc::RecvTimeoutError::Disconnected) => {
@@ -1811,14 +3018,14 @@ fn stream_frames_yuy2(
         }
     }
 
-    iso_stream.stop();
+    stream.stop();
     let _ = event_loop_handle.join();
 
     // Determine the result based on why we stopped
-    let stop_reason = iso_stream.get_stop_reason();
+    let stop_reason = stream.get_stop_reason();
     log::info!(
         "YUY2 streaming ended after {} frames, stop reason: {:?}",
-        frame_count,
+        processor.frame_count,
         stop_reason
     );
 
@@ -1842,18 +3049,20 @@ fn start_uvc_streaming(
     frame_index: u8,
 ) -> Result<u8, LibusbError> {
     let params =
-        start_uvc_streaming_with_resolution(dev, endpoint_info, format_index, frame_index)?;
+        start_uvc_streaming_with_resolution(dev, endpoint_info, format_index, frame_index, None)?;
     Ok(params.endpoint)
 }
 
 /// Start UVC streaming and return full negotiated parameters including resolution.
 /// Looks up width/height from the UVC frame descriptors based on negotiated frame index.
+/// `frame_interval` is the selected interval (None = the frame's default).
 #[cfg(target_os = "android")]
 fn start_uvc_streaming_with_resolution(
     dev: &LibusbDeviceHandle,
     endpoint_info: Option<&EndpointInfo>,
     format_index: u8,
     frame_index: u8,
+    frame_interval: Option<u32>,
 ) -> Result<UvcNegotiatedParams, LibusbError> {
     log::info!(
         "Initiating UVC probe/commit sequence with format_index={}, frame_index={}",
@@ -1861,14 +3070,28 @@ fn start_uvc_streaming_with_resolution(
         frame_index
     );
 
-    // Get format descriptors first so we can look up resolution
-    let formats = dev.get_format_descriptors().unwrap_or_default();
+    // Get the format catalog first so we can look up resolution and frame rate
+    let catalog = dev.get_format_catalog().unwrap_or_default();
+
+    // Probe/commit must run on the zero-bandwidth alternate setting. This is a
+    // no-op on first start; on a restart it releases the previous stream's
+    // isochronous bandwidth before the new resolution is negotiated.
+    let interface = endpoint_info.map_or(i32::from(UVC_STREAMING_INTERFACE), |ep| {
+        i32::from(ep.interface_number)
+    });
+    if let Err(e) = dev.set_interface_alt_setting(interface, 0) {
+        log::debug!("Could not select alt setting 0 before probe: {}", e);
+    }
 
     // UVC probe control - request camera format
     let mut probe = UvcStreamControl::default();
     probe.bm_hint = 1; // dwFrameInterval field is valid
     probe.b_format_index = format_index; // Try specified format
     probe.b_frame_index = frame_index; // Selected resolution
+                                       // Request a frame interval the descriptor advertises (0 lets the camera choose)
+    probe.dw_frame_interval = catalog
+        .frame(format_index, frame_index)
+        .map_or(0, |frame| frame.interval_for(frame_interval));
 
     // Request type: Class request to interface, direction OUT then IN
     let request_type_out = uvc::USB_TYPE_CLASS | uvc::USB_RECIP_INTERFACE | uvc::USB_DIR_OUT;
@@ -1934,29 +3157,20 @@ fn start_uvc_streaming_with_resolution(
         frame_interval
     );
 
-    // Look up resolution from frame descriptors
-    let mut width: u16 = DEFAULT_WIDTH;
-    let mut height: u16 = DEFAULT_HEIGHT;
-    let mut found_descriptor = false;
-
-    // Find the format that matches the negotiated format_index
-    for format in &formats {
-        if format.format_index == neg_format_index {
-            // Find the frame that matches the negotiated frame_index
-            for frame in &format.frames {
-                if frame.frame_index == neg_frame_index {
-                    width = frame.width;
-                    height = frame.height;
-                    found_descriptor = true;
-                    log::info!(
-                        "Resolved negotiated resolution from descriptor: {}x{} (format={}, frame={})",
-                        width, height, neg_format_index, neg_frame_index
-                    );
-                    break;
-                }
-            }
-            break;
-        }
+    // Look up resolution from the catalog
+    let neg_format = catalog.format(neg_format_index);
+    let neg_frame = neg_format.and_then(|f| f.frame(neg_frame_index));
+    let (width, height) = neg_frame.map_or((DEFAULT_WIDTH, DEFAULT_HEIGHT), |frame| {
+        (frame.width, frame.height)
+    });
+    if neg_frame.is_some() {
+        log::info!(
+            "Resolved negotiated resolution from descriptor: {}x{} (format={}, frame={})",
+            width,
+            height,
+            neg_format_index,
+            neg_frame_index
+        );
     }
 
     // Log if probe's max_frame_size differs from descriptor
@@ -1964,19 +3178,24 @@ fn start_uvc_streaming_with_resolution(
     // Some cameras report incorrect max_frame_size in probe responses (e.g., 1843200 for 720p
     // when the camera only supports 640x480 per the descriptor). Using the wrong size causes
     // multiple frames to be concatenated, resulting in horizontal banding artifacts.
-    let descriptor_frame_size = (width as u32) * (height as u32) * 2; // YUY2 = 2 bytes per pixel
-    if max_frame_size != descriptor_frame_size {
-        log::warn!(
-            "Probe max_frame_size={} differs from descriptor {}x{} ({}). TRUSTING DESCRIPTOR.",
-            max_frame_size,
-            width,
-            height,
-            descriptor_frame_size
-        );
-        // Do NOT override width/height - the descriptor is authoritative
+    // Only uncompressed formats have a fixed frame size to compare against.
+    let descriptor_frame_size = neg_format
+        .zip(neg_frame)
+        .and_then(|(format, frame)| format.uncompressed_frame_size(frame));
+    if let Some(descriptor_frame_size) = descriptor_frame_size {
+        if max_frame_size != descriptor_frame_size {
+            log::warn!(
+                "Probe max_frame_size={} differs from descriptor {}x{} ({}). TRUSTING DESCRIPTOR.",
+                max_frame_size,
+                width,
+                height,
+                descriptor_frame_size
+            );
+            // Do NOT override width/height - the descriptor is authoritative
+        }
     }
 
-    if !found_descriptor {
+    if neg_frame.is_none() {
         log::warn!(
             "Could not find frame descriptor for format={} frame={}, using {}x{}",
             neg_format_index,
@@ -2024,42 +3243,363 @@ fn start_uvc_streaming_with_resolution(
         width,
         height,
         max_frame_size,
+        max_payload,
+        frame_interval,
     })
 }
 
-/// Stream frames from the camera using bulk transfers
-/// Note: Most endoscopes use isochronous transfers, this is a fallback
+/// USB I/O needed by the UVC bulk streaming path
+///
+/// Implemented by the libusb device handle and by the Android
+/// `UsbDeviceConnection` fallback, so both share negotiation and frame assembly.
+#[cfg(target_os = "android")]
+trait UsbTransport {
+    /// Perform a control transfer, returning the number of bytes transferred
+    fn control_transfer(
+        &self,
+        request_type: u8,
+        request: u8,
+        value: u16,
+        index: u16,
+        data: &mut [u8],
+        timeout_ms: u32,
+    ) -> Result<usize, LibusbError>;
+
+    /// Perform a bulk IN transfer, returning the number of bytes received
+    fn bulk_transfer(
+        &self,
+        endpoint: u8,
+        data: &mut [u8],
+        timeout_ms: u32,
+    ) -> Result<usize, LibusbError>;
+}
+
+#[cfg(target_os = "android")]
+impl UsbTransport for LibusbDeviceHandle {
+    fn control_transfer(
+        &self,
+        request_type: u8,
+        request: u8,
+        value: u16,
+        index: u16,
+        data: &mut [u8],
+        timeout_ms: u32,
+    ) -> Result<usize, LibusbError> {
+        LibusbDeviceHandle::control_transfer(
+            self,
+            request_type,
+            request,
+            value,
+            index,
+            data,
+            timeout_ms,
+        )
+    }
+
+    fn bulk_transfer(
+        &self,
+        endpoint: u8,
+        data: &mut [u8],
+        timeout_ms: u32,
+    ) -> Result<usize, LibusbError> {
+        LibusbDeviceHandle::bulk_transfer(self, endpoint, data, timeout_ms)
+    }
+}
+
+#[cfg(target_os = "android")]
+impl UsbTransport for JniUsbConnection {
+    fn control_transfer(
+        &self,
+        request_type: u8,
+        request: u8,
+        value: u16,
+        index: u16,
+        data: &mut [u8],
+        timeout_ms: u32,
+    ) -> Result<usize, LibusbError> {
+        JniUsbConnection::control_transfer(
+            self,
+            request_type,
+            request,
+            value,
+            index,
+            data,
+            timeout_ms,
+        )
+    }
+
+    fn bulk_transfer(
+        &self,
+        endpoint: u8,
+        data: &mut [u8],
+        timeout_ms: u32,
+    ) -> Result<usize, LibusbError> {
+        JniUsbConnection::bulk_transfer(self, endpoint, data, timeout_ms)
+    }
+}
+
+/// Stream via Android's `UsbDeviceConnection` API when libusb cannot wrap the fd
+///
+/// Some vendor ROMs reject `libusb_wrap_sys_device()`. The Java API only
+/// supports bulk transfers, so this works for bulk MJPEG cameras only. The
+/// format and frame are chosen from the device's raw descriptors like in the
+/// libusb path (see [`compat_stream_format`]).
+#[cfg(target_os = "android")]
+fn stream_via_usb_connection(
+    device: &OpenedDevice,
+    stream_ctx: &StreamingContext,
+) -> Result<StreamResult, LibusbError> {
+    log::warn!("libusb cannot wrap the USB fd on this device, falling back to UsbDeviceConnection");
+
+    let conn = JniUsbConnection::open(device)?;
+    let interface = u16::from(conn.interface_number());
+
+    let catalog = FormatCatalog::parse_raw(&conn.raw_descriptors()?, conn.interface_number());
+    let (format_index, frame_index, selected_interval) = {
+        let mut config = lock_or_recover!(stream_ctx.streaming_config);
+        config.format_catalog = catalog.clone();
+        let (format_index, frame_index) = compat_stream_format(
+            &catalog,
+            config.selected_format_index,
+            config.selected_frame_index,
+            config.preferred_resolution,
+            &config.format_preference,
+        );
+        (format_index, frame_index, config.selected_frame_interval)
+    };
+    log::info!(
+        "Compatibility mode: format {}, frame {}",
+        format_index,
+        frame_index
+    );
+
+    let mut probe = UvcStreamControl::default();
+    probe.bm_hint = 1; // dwFrameInterval field is valid
+    probe.b_format_index = format_index;
+    probe.b_frame_index = frame_index;
+    // A frame interval the descriptor advertises (0 lets the camera choose)
+    probe.dw_frame_interval = catalog
+        .frame(format_index, frame_index)
+        .map_or(0, |frame| frame.interval_for(selected_interval));
+    // SAFETY: UvcStreamControl is a #[repr(C, packed)] struct with no padding.
+    let probe_bytes: &mut [u8] = unsafe {
+        std::slice::from_raw_parts_mut(
+            &mut probe as *mut UvcStreamControl as *mut u8,
+            std::mem::size_of::<UvcStreamControl>(),
+        )
+    };
+
+    let request_type_out = uvc::USB_TYPE_CLASS | uvc::USB_RECIP_INTERFACE | uvc::USB_DIR_OUT;
+    let request_type_in = uvc::USB_TYPE_CLASS | uvc::USB_RECIP_INTERFACE | uvc::USB_DIR_IN;
+    let probe_control = uvc::UVC_VS_PROBE_CONTROL << 8;
+    let commit_control = uvc::UVC_VS_COMMIT_CONTROL << 8;
+
+    conn.control_transfer(
+        request_type_out,
+        uvc::UVC_SET_CUR,
+        probe_control,
+        interface,
+        probe_bytes,
+        CONTROL_TRANSFER_TIMEOUT_MS,
+    )?;
+    let mut response = [0u8; UVC_PROBE_RESPONSE_SIZE];
+    conn.control_transfer(
+        request_type_in,
+        uvc::UVC_GET_CUR,
+        probe_control,
+        interface,
+        &mut response,
+        CONTROL_TRANSFER_TIMEOUT_MS,
+    )?;
+    conn.control_transfer(
+        request_type_out,
+        uvc::UVC_SET_CUR,
+        commit_control,
+        interface,
+        &mut response,
+        CONTROL_TRANSFER_TIMEOUT_MS,
+    )?;
+    // SAFETY: response holds UVC_PROBE_RESPONSE_SIZE bytes, the size of UvcStreamControl.
+    let negotiated: UvcStreamControl =
+        unsafe { std::ptr::read_unaligned(response.as_ptr() as *const _) };
+    let max_payload = negotiated.dw_max_payload_transfer_size;
+    log::info!(
+        "UVC streaming committed via UsbDeviceConnection (max payload {} bytes)",
+        max_payload
+    );
+
+    // Record what was negotiated for get_stream_info and friends, as
+    // negotiate_stream does on the libusb path
+    let (neg_format_index, neg_frame_index) = (negotiated.b_format_index, negotiated.b_frame_index);
+    let (width, height) = catalog
+        .frame(neg_format_index, neg_frame_index)
+        .map_or((DEFAULT_WIDTH, DEFAULT_HEIGHT), |frame| {
+            (frame.width, frame.height)
+        });
+    record_active_stream(
+        stream_ctx,
+        crate::ActiveStream {
+            format_index: neg_format_index,
+            frame_index: neg_frame_index,
+            width,
+            height,
+            frame_interval: negotiated.dw_frame_interval,
+            max_frame_size: negotiated.dw_max_video_frame_size,
+            max_payload,
+        },
+    );
+
+    crate::emit_usb_event(
+        &stream_ctx.app_handle,
+        true,
+        Some("USB Camera (compatibility mode)".to_string()),
+    );
+
+    match stream_frames(&conn, conn.endpoint_address(), max_payload, stream_ctx)? {
+        FormatDetectionResult::MjpegFound => Ok(StreamResult::Normal),
+        FormatDetectionResult::RestartRequested => Ok(StreamResult::RestartRequested),
+        FormatDetectionResult::NotMjpeg => {
+            log::error!("Compatibility mode only supports MJPEG bulk cameras");
+            Err(LibusbError::NotSupported)
+        }
+    }
+}
+
+/// Format and frame to negotiate in compatibility mode, which streams MJPEG only
+///
+/// The selected format if it is MJPEG, else the MJPEG format ranked first by
+/// `format_preference`; format 1 if the descriptors list no MJPEG format.
+/// The frame is chosen as in the libusb path (see [`frame_for_format`]).
+#[cfg(target_os = "android")]
+fn compat_stream_format(
+    catalog: &FormatCatalog,
+    selected_format: Option<u8>,
+    selected_frame: Option<u8>,
+    preferred: Option<PreferredResolution>,
+    format_preference: &[String],
+) -> (u8, u8) {
+    let format_index = selected_format
+        .filter(|&index| {
+            catalog
+                .format(index)
+                .is_some_and(|f| f.kind == FormatKind::Mjpeg)
+        })
+        .or_else(|| {
+            catalog
+                .by_preference(format_preference)
+                .into_iter()
+                .find(|f| f.kind == FormatKind::Mjpeg)
+                .map(|f| f.index)
+        })
+        .unwrap_or(1);
+    let frame_index = frame_for_format(catalog, format_index, selected_frame, preferred);
+    (format_index, frame_index)
+}
+
+/// Start counting a new stream in `stream_stats` (`get_stream_stats`) and
+/// report the frames `assembler` completes and drops to it
+#[cfg(usb_streaming)]
+pub(crate) fn observe_assembly(stream_ctx: &StreamingContext, assembler: &mut FrameAssembler) {
+    stream_ctx.stream_stats.reset();
+    assembler
+        .set_observer(Some(Arc::clone(&stream_ctx.stream_stats)
+            as Arc<dyn crate::frame_assembler::AssemblyObserver>));
+}
+
+/// Delay before retrying a failed bulk transfer
+#[cfg(usb_streaming)]
+pub(crate) const BULK_RETRY_DELAY: std::time::Duration = std::time::Duration::from_millis(10);
+
+/// Whether a failed bulk transfer may succeed when retried
+#[cfg(target_os = "android")]
+fn is_retryable_bulk_error(err: LibusbError) -> bool {
+    matches!(
+        err,
+        LibusbError::Overflow | LibusbError::Pipe | LibusbError::Interrupted | LibusbError::IoError
+    )
+}
+
+/// Stream frames from the camera using synchronous bulk transfers
+///
+/// Used in compatibility mode, where Android's `UsbDeviceConnection` does the
+/// I/O; with libusb, bulk endpoints stream through [`BulkStream`].
+///
+/// `max_payload` is the negotiated `dwMaxPayloadTransferSize`, used to size the
+/// transfer buffer (see [`crate::bulk_transfer::BulkTransferConfig::transfer_size`]).
 #[cfg(target_os = "android")]
 fn stream_frames(
-    dev: &LibusbDeviceHandle,
+    dev: &impl UsbTransport,
     endpoint: u8,
-    app_handle: AppHandle,
-    shared_frame_buffer: Arc<Mutex<FrameBuffer>>,
+    max_payload: u32,
+    stream_ctx: &StreamingContext,
 ) -> Result<FormatDetectionResult, LibusbError> {
-    use std::time::Instant;
+    let config = lock_or_recover!(stream_ctx.streaming_config)
+        .bulk_transfer
+        .clone();
+    let transfer_size = config.transfer_size(max_payload);
 
     log::info!(
-        "Starting bulk frame streaming from endpoint 0x{:02x}",
-        endpoint
+        "Starting bulk frame streaming from endpoint 0x{:02x} ({} byte transfers, {} ms timeout)",
+        endpoint,
+        transfer_size,
+        config.timeout_ms
     );
 
-    // Buffer for receiving USB data
-    // USB packets are typically up to 512 bytes (full-speed) or 1024 bytes (high-speed)
-    // MJPEG frames can be several KB, so we need to accumulate packets
-    let mut packet_buffer = vec![0u8; BULK_TRANSFER_BUFFER_SIZE];
+    // One UVC payload per transfer; MJPEG frames span many payloads, so they
+    // are accumulated in a separate buffer
+    let mut packet_buffer = vec![0u8; transfer_size];
     let mut local_frame_buffer = Vec::with_capacity(FRAME_ACCUMULATION_CAPACITY);
+    // A payload of the frame being accumulated carried the still image bit
+    let mut frame_still = false;
+    // When the first payload of the frame being accumulated arrived
+    let mut frame_started = None;
+    stream_ctx.stream_stats.reset();
 
-    let timeout_ms = BULK_TRANSFER_TIMEOUT_MS;
     let mut frame_count = 0u32;
     let mut jpeg_frames = 0u32;
     let mut format_confirmed = false;
+    let mut timeouts = TimeoutStreak::new(config.stall_after_timeouts);
+    let mut failures = 0u32;
 
     loop {
+        if lock_or_recover!(stream_ctx.streaming_config).restart_requested {
+            log::info!("Restart requested, stopping bulk MJPEG streaming");
+            return Ok(FormatDetectionResult::RestartRequested);
+        }
+
         // Perform bulk transfer to read data
-        let transferred = match dev.bulk_transfer(endpoint, &mut packet_buffer, timeout_ms) {
+        let transferred = match dev.bulk_transfer(endpoint, &mut packet_buffer, config.timeout_ms) {
             Ok(n) => n,
             Err(LibusbError::Timeout) => {
                 log::trace!("Bulk transfer timeout");
+                if timeouts.timeout() != StreakChange::None {
+                    log::warn!(
+                        "No bulk data for {} consecutive transfers ({} ms each)",
+                        timeouts.count(),
+                        config.timeout_ms
+                    );
+                    stream_ctx.stream_health.record_stall();
+                    stream_ctx
+                        .stream_health
+                        .set_timeout_streak(timeouts.count());
+                    crate::emit_stream_health(&stream_ctx.app_handle);
+                } else if timeouts.is_stalled() {
+                    stream_ctx
+                        .stream_health
+                        .set_timeout_streak(timeouts.count());
+                }
+                continue;
+            }
+            Err(e) if is_retryable_bulk_error(e) && failures < config.max_retries => {
+                failures += 1;
+                log::warn!(
+                    "Bulk transfer error: {}, retrying ({}/{})",
+                    e,
+                    failures,
+                    config.max_retries
+                );
+                stream_ctx.stream_health.record_transfer_error();
+                std::thread::sleep(BULK_RETRY_DELAY);
                 continue;
             }
             Err(e) => {
@@ -2068,6 +3608,19 @@ fn stream_frames(
             }
         };
 
+        failures = 0;
+        if let StreakChange::Recovered(count) = timeouts.success() {
+            log::info!("Bulk data resumed after {} timeouts", count);
+            stream_ctx.stream_health.set_timeout_streak(0);
+            crate::emit_stream_health(&stream_ctx.app_handle);
+        }
+
+        if stream_ctx.capture_state.wants_packets() {
+            stream_ctx
+                .capture_state
+                .add_packet(&packet_buffer[..transferred], endpoint);
+        }
+
         // Skip empty or too-small packets (UVC headers are typically 12 bytes)
         if transferred <= UVC_HEADER_MIN_SIZE {
             continue;
@@ -2076,15 +3629,21 @@ fn stream_frames(
         let header_len = packet_buffer[0] as usize;
         let header_flags = packet_buffer[1];
         let end_of_frame = (header_flags & 0x02) != 0;
+        frame_still |= (header_flags & 0x20) != 0;
 
         // Append payload data (skip header)
         if header_len < transferred {
+            if local_frame_buffer.is_empty() {
+                frame_started = Some(std::time::Instant::now());
+            }
             local_frame_buffer.extend_from_slice(&packet_buffer[header_len..transferred]);
         }
 
         if !end_of_frame || local_frame_buffer.is_empty() {
             continue;
         }
+        let still_image = std::mem::take(&mut frame_still);
+        let assembly = frame_started.take().map(|started| started.elapsed());
 
         frame_count += 1;
 
@@ -2092,25 +3651,51 @@ fn stream_frames(
         let is_jpeg = local_frame_buffer.len() >= 2
             && local_frame_buffer[0] == 0xFF
             && local_frame_buffer[1] == 0xD8;
+        stream_ctx
+            .capture_state
+            .end_frame(!crate::frame_assembler::is_complete_jpeg(
+                &local_frame_buffer,
+            ));
+        if is_jpeg
+            && stream_ctx
+                .still_capture
+                .offer(&local_frame_buffer, still_image)
+        {
+            local_frame_buffer.clear();
+            continue;
+        }
 
         if is_jpeg {
             jpeg_frames += 1;
+            stream_ctx
+                .stream_stats
+                .record_frame(local_frame_buffer.len(), assembly);
             log::debug!(
                 "MJPEG frame {} received: {} bytes",
                 frame_count,
                 local_frame_buffer.len()
             );
 
-            // Store frame in shared buffer - swap to avoid clone inside lock
-            let frame_for_buffer = std::mem::take(&mut local_frame_buffer);
-            {
-                let mut buffer = lock_or_recover!(shared_frame_buffer);
-                buffer.frame = frame_for_buffer;
-                buffer.timestamp = Instant::now();
-            }
+            // Dimensions are unknown on the bulk path; the JPEG header has them
+            trace_jpeg_frame(stream_ctx, &local_frame_buffer, 0, 0);
+            stream_ctx
+                .recording
+                .record_frame(&local_frame_buffer, 0, 0, FrameFormat::Jpeg);
+            stream_ctx
+                .spooler
+                .offer(&local_frame_buffer, 0, 0, FrameFormat::Jpeg);
+
+            if publish_decoded_mjpeg(stream_ctx, &local_frame_buffer) {
+                local_frame_buffer.clear();
+            } else {
+                // Store frame in shared buffer - swap to avoid clone inside lock
+                let frame_for_buffer = std::mem::take(&mut local_frame_buffer);
+                let info = stream_ctx.frame_buffer.store(frame_for_buffer, 0, 0);
+                stream_ctx.stream_health.record_frame();
 
-            // Emit lightweight notification to trigger frontend fetch
-            let _ = app_handle.emit("frame-ready", ());
+                // Emit lightweight notification to trigger frontend fetch
+                crate::emit_frame_ready(&stream_ctx.app_handle, &info);
+            }
 
             if frame_count % LOG_INTERVAL_FRAMES == 0 {
                 log::info!("Received {} frames", frame_count);
@@ -2121,6 +3706,7 @@ fn stream_frames(
                 local_frame_buffer.len(),
                 &local_frame_buffer[..std::cmp::min(16, local_frame_buffer.len())]
             );
+            stream_ctx.stream_stats.record_dropped();
             local_frame_buffer.clear();
         }
 
@@ -2145,8 +3731,160 @@ fn stream_frames(
     }
 }
 
+/// Row stride to split frames by content with, for devices with the
+/// `content-boundaries` quirk (see [`crate::quirks`])
+#[cfg(target_os = "android")]
+fn content_row_stride(
+    quirks: crate::quirks::DeviceQuirks,
+    pixel_format: PixelFormat,
+    width: u32,
+) -> Option<usize> {
+    if !quirks.content_boundaries {
+        return None;
+    }
+    match pixel_format {
+        PixelFormat::Yuyv | PixelFormat::Uyvy => Some(width as usize * 2),
+        _ => {
+            log::warn!(
+                "Content frame boundaries need packed 4:2:2 frames, ignoring for {}",
+                pixel_format
+            );
+            None
+        }
+    }
+}
+
+/// Stream YUV frames using bulk transfers with RGB conversion
+///
+/// Payloads are assembled by size with a [`FrameAssembler`]. Cameras with the
+/// `headerless` quirk (see [`crate::quirks`]) send raw frame bytes without UVC
+/// payload headers, so every byte of every transfer is treated as pixel data;
+/// with `content-boundaries`, frames are split where the image content jumps.
+#[cfg(target_os = "android")]
+fn stream_frames_bulk_yuy2(
+    dev: &impl UsbTransport,
+    endpoint: u8,
+    max_payload: u32,
+    stream_ctx: &StreamingContext,
+    descriptor_width: u32,
+    descriptor_height: u32,
+) -> Result<StreamResult, LibusbError> {
+    use std::sync::atomic::Ordering;
+
+    let (config, quirks, pixel_format) = {
+        let streaming = lock_or_recover!(stream_ctx.streaming_config);
+        (
+            streaming.bulk_transfer.clone(),
+            streaming.quirks,
+            streaming.pixel_format,
+        )
+    };
+    let transfer_size = config.transfer_size(max_payload);
+
+    log::info!(
+        "Starting bulk {} streaming from endpoint 0x{:02x} ({} byte transfers, {} ms timeout, headerless: {})",
+        pixel_format,
+        endpoint,
+        transfer_size,
+        config.timeout_ms,
+        quirks.headerless_payloads
+    );
+
+    let mut assembler =
+        FrameAssembler::new(pixel_format.frame_size(descriptor_width, descriptor_height));
+    observe_assembly(stream_ctx, &mut assembler);
+    assembler.set_headerless(quirks.headerless_payloads);
+    assembler.set_content_boundaries(content_row_stride(quirks, pixel_format, descriptor_width));
+
+    let mut packet_buffer = vec![0u8; transfer_size];
+    let mut processor = YuvFrameProcessor::new(descriptor_width, descriptor_height, pixel_format);
+    let mut timeouts = TimeoutStreak::new(config.stall_after_timeouts);
+    let mut failures = 0u32;
+
+    warm_up_pipeline(
+        stream_ctx,
+        WarmupPlan::Uncompressed {
+            pixel_format,
+            width: descriptor_width,
+            height: descriptor_height,
+        },
+    );
+
+    loop {
+        if stream_ctx.stop_flag.load(Ordering::Relaxed) {
+            return Ok(StreamResult::Normal);
+        }
+        // Check restart flag and read current pixel format in a single lock
+        let pixel_format = {
+            let config = lock_or_recover!(stream_ctx.streaming_config);
+            if config.restart_requested {
+                log::info!("Restart requested, stopping bulk YUV streaming");
+                return Ok(StreamResult::RestartRequested);
+            }
+            config.pixel_format
+        };
+
+        let transferred = match dev.bulk_transfer(endpoint, &mut packet_buffer, config.timeout_ms) {
+            Ok(n) => n,
+            Err(LibusbError::Timeout) => {
+                log::trace!("Bulk transfer timeout");
+                if timeouts.timeout() != StreakChange::None {
+                    log::warn!(
+                        "No bulk data for {} consecutive transfers ({} ms each)",
+                        timeouts.count(),
+                        config.timeout_ms
+                    );
+                    stream_ctx.stream_health.record_stall();
+                    stream_ctx
+                        .stream_health
+                        .set_timeout_streak(timeouts.count());
+                    crate::emit_stream_health(&stream_ctx.app_handle);
+                } else if timeouts.is_stalled() {
+                    stream_ctx
+                        .stream_health
+                        .set_timeout_streak(timeouts.count());
+                }
+                continue;
+            }
+            Err(e) if is_retryable_bulk_error(e) && failures < config.max_retries => {
+                failures += 1;
+                log::warn!(
+                    "Bulk transfer error: {}, retrying ({}/{})",
+                    e,
+                    failures,
+                    config.max_retries
+                );
+                stream_ctx.stream_health.record_transfer_error();
+                std::thread::sleep(BULK_RETRY_DELAY);
+                continue;
+            }
+            Err(e) => {
+                log::error!("Bulk transfer error: {}", e);
+                return Err(e);
+            }
+        };
+
+        failures = 0;
+        if let StreakChange::Recovered(count) = timeouts.success() {
+            log::info!("Bulk data resumed after {} timeouts", count);
+            stream_ctx.stream_health.set_timeout_streak(0);
+            crate::emit_stream_health(&stream_ctx.app_handle);
+        }
+
+        let payload = &packet_buffer[..transferred];
+        if stream_ctx.capture_state.wants_packets() {
+            stream_ctx.capture_state.add_packet(payload, endpoint);
+        }
+
+        if let ProcessResult::Frame(frame_data) = assembler.process_packet(payload) {
+            let notes = processor.assembled(stream_ctx, &frame_data, assembler.last_frame_still());
+            processor.process(stream_ctx, &frame_data, pixel_format, &notes);
+        }
+    }
+}
+
 #[cfg(not(target_os = "android"))]
-fn run_camera_loop(_fd: i32, app_handle: AppHandle, frame_buffer: Arc<Mutex<FrameBuffer>>) {
+fn run_camera_loop(_fd: i32, app_handle: AppHandle, frame_buffer: Arc<FrameBuffer>) {
     if let Ok(replay_path) = std::env::var("CLEANSCOPE_REPLAY_PATH") {
         log::info!("Desktop replay mode: {}", replay_path);
         replay_frame_loop(app_handle, frame_buffer, &replay_path);
@@ -2163,14 +3901,9 @@ fn run_camera_loop(_fd: i32, app_handle: AppHandle, frame_buffer: Arc<Mutex<Fram
 /// through the frame assembler, updating the `FrameBuffer` and emitting events
 /// just like the Android USB path does.
 #[cfg(not(target_os = "android"))]
-fn replay_frame_loop(
-    app_handle: AppHandle,
-    frame_buffer: Arc<Mutex<FrameBuffer>>,
-    replay_path: &str,
-) {
+fn replay_frame_loop(app_handle: AppHandle, frame_buffer: Arc<FrameBuffer>, replay_path: &str) {
     use std::path::Path;
     use std::time::{Duration, Instant};
-    use tauri::Emitter;
 
     use crate::replay::{PacketReplay, ReplayConfig};
 
@@ -2207,6 +3940,11 @@ fn replay_frame_loop(
         }
     };
 
+    // Frame dimensions recorded with the capture (0 if unknown)
+    let (width, height) = replay
+        .metadata()
+        .map_or((0, 0), |meta| (meta.width, meta.height));
+
     // Get metadata for display info
     let info = if let Some(meta) = replay.metadata() {
         format!(
@@ -2253,14 +3991,10 @@ fn replay_frame_loop(
                 frame_count += 1;
 
                 // Store frame in shared buffer
-                {
-                    let mut buffer = lock_or_recover!(frame_buffer);
-                    buffer.frame = frame_data;
-                    buffer.timestamp = Instant::now();
-                }
+                let info = frame_buffer.store(frame_data, width, height);
 
                 // Emit notification to trigger frontend fetch
-                let _ = app_handle.emit("frame-ready", ());
+                crate::emit_frame_ready(&app_handle, &info);
 
                 if frame_count.is_multiple_of(30) {
                     let elapsed = start_time.elapsed().as_secs_f64();
@@ -2292,7 +4026,9 @@ fn replay_frame_loop(
 }
 
 /// JNI callback for USB device attached events
-/// This is called from Java when Android detects a USB device attachment
+/// This is called from Java when Android detects a USB device attachment.
+/// The device is reopened through `UsbManager` so permission handling stays in
+/// one place; `fd` is only logged (`MainActivity` passes -1).
 #[cfg(target_os = "android")]
 #[no_mangle]
 pub extern "system" fn Java_com_cleanscope_app_MainActivity_onUsbDeviceAttached(
@@ -2301,19 +4037,31 @@ pub extern "system" fn Java_com_cleanscope_app_MainActivity_onUsbDeviceAttached(
     fd: jint,
 ) {
     log::info!("USB Device Attached via JNI, fd: {}", fd);
-
-    // TODO: Notify the main app about the new device
-    // This would trigger the camera initialization
+    send_hotplug_event(HotplugEvent::Attached);
 }
 
 /// JNI callback for USB device detached events
+///
+/// `device_name` is the `UsbDevice.getDeviceName()` of the detached device
+/// (null if the intent carried none, which is treated as the camera).
 #[cfg(target_os = "android")]
 #[no_mangle]
 pub extern "system" fn Java_com_cleanscope_app_MainActivity_onUsbDeviceDetached(
-    _env: JNIEnv,
+    mut env: JNIEnv,
     _class: JClass,
+    device_name: JString,
 ) {
-    log::info!("USB Device Detached via JNI");
-
-    // TODO: Stop the camera stream and clean up resources
+    let device_name = if device_name.is_null() {
+        None
+    } else {
+        match env.get_string(&device_name) {
+            Ok(name) => Some(String::from(name)),
+            Err(e) => {
+                log::error!("Failed to read detached device name from JNI: {}", e);
+                None
+            }
+        }
+    };
+    log::info!("USB Device Detached via JNI: {:?}", device_name);
+    send_hotplug_event(HotplugEvent::Detached(device_name));
 }
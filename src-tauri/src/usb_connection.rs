@@ -0,0 +1,390 @@
+//! Streaming fallback through Android's `UsbDeviceConnection` API
+//!
+//! On some vendor ROMs `libusb_wrap_sys_device()` fails with
+//! `LIBUSB_ERROR_NOT_SUPPORTED`, so libusb cannot use the file descriptor from
+//! `UsbManager`. USB I/O is still possible through the Java API, which this
+//! module wraps via JNI.
+//!
+//! Android's Java API has no isochronous transfers, so only cameras with a bulk
+//! streaming endpoint can be used this way. Isochronous-only cameras still
+//! require libusb.
+//!
+//! The connection is the one the camera loop already opened (see
+//! [`OpenedDevice`]), so the device is never opened twice.
+
+use std::sync::Mutex;
+use std::time::Instant;
+
+use jni::objects::{GlobalRef, JByteArray, JObject, JValue};
+use jni::{JNIEnv, JavaVM};
+use ndk_context::android_context;
+
+use crate::jni_helpers::{self, JniError};
+use crate::libusb_android::LibusbError;
+use crate::usb::OpenedDevice;
+
+/// `UsbConstants.USB_CLASS_VIDEO`
+const USB_CLASS_VIDEO: i32 = 0x0E;
+
+/// UVC VideoStreaming interface subclass
+const UVC_SUBCLASS_STREAMING: i32 = 0x02;
+
+/// `UsbConstants.USB_ENDPOINT_XFER_ISOC`
+const USB_ENDPOINT_XFER_ISOC: i32 = 1;
+
+/// `UsbConstants.USB_ENDPOINT_XFER_BULK`
+const USB_ENDPOINT_XFER_BULK: i32 = 2;
+
+/// `UsbConstants.USB_DIR_IN`
+const USB_DIR_IN: i32 = 0x80;
+
+/// A `UsbDeviceConnection` with a claimed UVC bulk streaming interface
+pub struct JniUsbConnection {
+    /// Java VM used to attach the calling thread
+    vm: JavaVM,
+    /// `android.hardware.usb.UsbDeviceConnection`
+    connection: GlobalRef,
+    /// `android.hardware.usb.UsbInterface` of the streaming interface
+    interface: GlobalRef,
+    /// `android.hardware.usb.UsbEndpoint` of the bulk IN endpoint
+    endpoint: GlobalRef,
+    /// Streaming interface number
+    interface_number: u8,
+    /// Bulk IN endpoint address
+    endpoint_address: u8,
+    /// `byte[]` reused by every bulk transfer, with its length
+    transfer_buffer: Mutex<Option<(GlobalRef, usize)>>,
+}
+
+impl JniUsbConnection {
+    /// Claim the bulk streaming interface of an opened camera
+    ///
+    /// Returns `LibusbError::NotSupported` if the camera only has isochronous
+    /// streaming endpoints, and `LibusbError::Busy` if the interface cannot be
+    /// claimed. The connection stays owned by `device`, which must outlive
+    /// the returned value.
+    pub fn open(device: &OpenedDevice) -> Result<Self, LibusbError> {
+        let ctx = android_context();
+        // SAFETY: ctx.vm() returns a valid JNI JavaVM pointer from the Android runtime.
+        let vm = unsafe { JavaVM::from_raw(ctx.vm().cast()) }.map_err(|e| {
+            log::error!("Could not get Java VM: {}", e);
+            LibusbError::Other
+        })?;
+
+        let mut env = attach(&vm)?;
+
+        let (interface, endpoint) =
+            find_bulk_streaming_endpoint(&mut env, device.device().as_obj())?;
+        let connection = device.connection().as_obj();
+
+        let claimed = jni_helpers::call_bool(
+            &mut env,
+            connection,
+            "claimInterface",
+            "(Landroid/hardware/usb/UsbInterface;Z)Z",
+            &[JValue::Object(&interface), JValue::Bool(1)],
+        )?;
+        if !claimed {
+            log::error!("UsbDeviceConnection.claimInterface failed");
+            return Err(LibusbError::Busy);
+        }
+
+        // Select the alternate setting that carries the bulk endpoint
+        let selected = jni_helpers::call_bool(
+            &mut env,
+            connection,
+            "setInterface",
+            "(Landroid/hardware/usb/UsbInterface;)Z",
+            &[JValue::Object(&interface)],
+        )?;
+        if !selected {
+            log::warn!("UsbDeviceConnection.setInterface failed, continuing with current setting");
+        }
+
+        let interface_number = call_int(&mut env, &interface, "getId")? as u8;
+        let endpoint_address = call_int(&mut env, &endpoint, "getAddress")? as u8;
+
+        let global = |env: &mut JNIEnv, obj: &JObject| {
+            env.new_global_ref(obj)
+                .map_err(|e| jni_helpers::take_error(env, "NewGlobalRef", e))
+        };
+        let connection = device.connection().clone();
+        let interface = global(&mut env, &interface)?;
+        let endpoint = global(&mut env, &endpoint)?;
+
+        log::info!(
+            "Opened camera via UsbDeviceConnection: interface {}, bulk endpoint 0x{:02x}",
+            interface_number,
+            endpoint_address
+        );
+
+        Ok(Self {
+            vm,
+            connection,
+            interface,
+            endpoint,
+            interface_number,
+            endpoint_address,
+            transfer_buffer: Mutex::new(None),
+        })
+    }
+
+    /// All of the device's descriptors (`UsbDeviceConnection.getRawDescriptors`)
+    pub fn raw_descriptors(&self) -> Result<Vec<u8>, LibusbError> {
+        let mut env = attach(&self.vm)?;
+        let array = jni_helpers::call_non_null(
+            &mut env,
+            self.connection.as_obj(),
+            "getRawDescriptors",
+            "()[B",
+            &[],
+        )?;
+        let array = JByteArray::from(array);
+        let bytes = env
+            .convert_byte_array(&array)
+            .map_err(|e| jni_helpers::take_error(&mut env, "GetByteArrayRegion", e))?;
+        let _ = env.delete_local_ref(array);
+        Ok(bytes)
+    }
+
+    /// Streaming interface number
+    pub fn interface_number(&self) -> u8 {
+        self.interface_number
+    }
+
+    /// Bulk IN endpoint address
+    pub fn endpoint_address(&self) -> u8 {
+        self.endpoint_address
+    }
+
+    /// Perform a control transfer (`UsbDeviceConnection.controlTransfer`)
+    pub fn control_transfer(
+        &self,
+        request_type: u8,
+        request: u8,
+        value: u16,
+        index: u16,
+        data: &mut [u8],
+        timeout_ms: u32,
+    ) -> Result<usize, LibusbError> {
+        let mut env = attach(&self.vm)?;
+        let array = env
+            .new_byte_array(data.len() as i32)
+            .map_err(|e| jni_helpers::take_error(&mut env, "NewByteArray", e))?;
+
+        let is_in = request_type & 0x80 != 0;
+        if !is_in {
+            env.set_byte_array_region(&array, 0, as_jbytes(data))
+                .map_err(|e| jni_helpers::take_error(&mut env, "SetByteArrayRegion", e))?;
+        }
+
+        let ret = env
+            .call_method(
+                self.connection.as_obj(),
+                "controlTransfer",
+                "(IIII[BII)I",
+                &[
+                    JValue::Int(i32::from(request_type)),
+                    JValue::Int(i32::from(request)),
+                    JValue::Int(i32::from(value)),
+                    JValue::Int(i32::from(index)),
+                    JValue::Object(&array),
+                    JValue::Int(data.len() as i32),
+                    JValue::Int(timeout_ms as i32),
+                ],
+            )
+            .and_then(|v| v.i())
+            .map_err(|e| jni_helpers::take_error(&mut env, "controlTransfer", e))?;
+
+        if ret < 0 {
+            let _ = env.delete_local_ref(array);
+            return Err(LibusbError::Pipe);
+        }
+
+        let len = (ret as usize).min(data.len());
+        if is_in && len > 0 {
+            env.get_byte_array_region(&array, 0, as_jbytes_mut(&mut data[..len]))
+                .map_err(|e| jni_helpers::take_error(&mut env, "GetByteArrayRegion", e))?;
+        }
+        let _ = env.delete_local_ref(array);
+        Ok(len)
+    }
+
+    /// Perform a bulk IN transfer (`UsbDeviceConnection.bulkTransfer`)
+    ///
+    /// The Java API reports both timeouts and errors as -1. A failure that
+    /// returns well before the timeout is treated as an I/O error (e.g. the
+    /// camera was unplugged); otherwise it is reported as a timeout.
+    pub fn bulk_transfer(
+        &self,
+        endpoint: u8,
+        data: &mut [u8],
+        timeout_ms: u32,
+    ) -> Result<usize, LibusbError> {
+        if endpoint != self.endpoint_address {
+            return Err(LibusbError::InvalidParam);
+        }
+
+        let mut env = attach(&self.vm)?;
+        let buffer = self.transfer_array(&mut env, data.len())?;
+        let array = <&JByteArray>::from(buffer.as_obj());
+
+        let started = Instant::now();
+        let ret = env
+            .call_method(
+                self.connection.as_obj(),
+                "bulkTransfer",
+                "(Landroid/hardware/usb/UsbEndpoint;[BII)I",
+                &[
+                    JValue::Object(self.endpoint.as_obj()),
+                    JValue::Object(array),
+                    JValue::Int(data.len() as i32),
+                    JValue::Int(timeout_ms as i32),
+                ],
+            )
+            .and_then(|v| v.i())
+            .map_err(|e| jni_helpers::take_error(&mut env, "bulkTransfer", e))?;
+
+        if ret < 0 {
+            if started.elapsed().as_millis() < u128::from(timeout_ms / 2) {
+                return Err(LibusbError::IoError);
+            }
+            return Err(LibusbError::Timeout);
+        }
+
+        let len = (ret as usize).min(data.len());
+        if len > 0 {
+            env.get_byte_array_region(array, 0, as_jbytes_mut(&mut data[..len]))
+                .map_err(|e| jni_helpers::take_error(&mut env, "GetByteArrayRegion", e))?;
+        }
+        Ok(len)
+    }
+
+    /// The `byte[]` for bulk transfers, reallocated only if shorter than `len`
+    fn transfer_array(&self, env: &mut JNIEnv, len: usize) -> Result<GlobalRef, LibusbError> {
+        let mut buffer = crate::lock_or_recover(&self.transfer_buffer);
+        if let Some((array, capacity)) = buffer.as_ref() {
+            if *capacity >= len {
+                return Ok(array.clone());
+            }
+        }
+        let array = env
+            .new_byte_array(len as i32)
+            .map_err(|e| jni_helpers::take_error(env, "NewByteArray", e))?;
+        let global = env
+            .new_global_ref(&array)
+            .map_err(|e| jni_helpers::take_error(env, "NewGlobalRef", e))?;
+        let _ = env.delete_local_ref(array);
+        *buffer = Some((global.clone(), len));
+        Ok(global)
+    }
+}
+
+impl Drop for JniUsbConnection {
+    fn drop(&mut self) {
+        let Ok(mut env) = attach(&self.vm) else {
+            return;
+        };
+        let _ = env.call_method(
+            self.connection.as_obj(),
+            "releaseInterface",
+            "(Landroid/hardware/usb/UsbInterface;)Z",
+            &[JValue::Object(self.interface.as_obj())],
+        );
+        // The connection itself is closed by the `OpenedDevice`
+        if env.exception_check().unwrap_or(false) {
+            let _ = env.exception_clear();
+        }
+        log::info!("Released streaming interface {}", self.interface_number);
+    }
+}
+
+/// Find the bulk IN endpoint of the device's UVC streaming interface
+///
+/// Every alternate setting is a separate `UsbInterface` in the Java API, so the
+/// returned interface is the one to pass to `setInterface`.
+fn find_bulk_streaming_endpoint<'a>(
+    env: &mut JNIEnv<'a>,
+    device: &JObject,
+) -> Result<(JObject<'a>, JObject<'a>), LibusbError> {
+    let mut found_isochronous = false;
+
+    let interface_count = call_int(env, device, "getInterfaceCount")?;
+    for i in 0..interface_count {
+        let interface = jni_helpers::call_non_null(
+            env,
+            device,
+            "getInterface",
+            "(I)Landroid/hardware/usb/UsbInterface;",
+            &[JValue::Int(i)],
+        )?;
+
+        let is_streaming = call_int(env, &interface, "getInterfaceClass")? == USB_CLASS_VIDEO
+            && call_int(env, &interface, "getInterfaceSubclass")? == UVC_SUBCLASS_STREAMING;
+        if !is_streaming {
+            continue;
+        }
+
+        let endpoint_count = call_int(env, &interface, "getEndpointCount")?;
+        for j in 0..endpoint_count {
+            let endpoint = jni_helpers::call_non_null(
+                env,
+                &interface,
+                "getEndpoint",
+                "(I)Landroid/hardware/usb/UsbEndpoint;",
+                &[JValue::Int(j)],
+            )?;
+
+            let ep_type = call_int(env, &endpoint, "getType")?;
+            let direction = call_int(env, &endpoint, "getDirection")?;
+            if ep_type == USB_ENDPOINT_XFER_BULK && direction == USB_DIR_IN {
+                return Ok((interface, endpoint));
+            }
+            found_isochronous |= ep_type == USB_ENDPOINT_XFER_ISOC;
+        }
+    }
+
+    if found_isochronous {
+        log::error!(
+            "Camera streams over isochronous transfers only, which Android's USB API cannot do"
+        );
+    } else {
+        log::error!("No UVC bulk streaming endpoint found");
+    }
+    Err(LibusbError::NotSupported)
+}
+
+/// Attach the current thread to the JVM (no-op if already attached)
+fn attach(vm: &JavaVM) -> Result<JNIEnv<'_>, LibusbError> {
+    vm.attach_current_thread_permanently().map_err(|e| {
+        log::error!("Could not attach thread to JVM: {}", e);
+        LibusbError::Other
+    })
+}
+
+/// Call a no-argument Java method returning `int`
+fn call_int(env: &mut JNIEnv, obj: &JObject, name: &str) -> Result<i32, LibusbError> {
+    Ok(jni_helpers::call_int(env, obj, name, "()I", &[])?)
+}
+
+impl From<JniError> for LibusbError {
+    fn from(err: JniError) -> Self {
+        if err.is_permission_denied() {
+            LibusbError::Access
+        } else {
+            LibusbError::Other
+        }
+    }
+}
+
+/// View a byte slice as JNI `jbyte`s
+fn as_jbytes(data: &[u8]) -> &[i8] {
+    // SAFETY: u8 and i8 have identical size and alignment.
+    unsafe { std::slice::from_raw_parts(data.as_ptr().cast(), data.len()) }
+}
+
+/// View a mutable byte slice as JNI `jbyte`s
+fn as_jbytes_mut(data: &mut [u8]) -> &mut [i8] {
+    // SAFETY: u8 and i8 have identical size and alignment.
+    unsafe { std::slice::from_raw_parts_mut(data.as_mut_ptr().cast(), data.len()) }
+}
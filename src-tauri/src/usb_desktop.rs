@@ -0,0 +1,1365 @@
+//! Desktop USB camera backend for `CleanScope`
+//!
+//! Compiled in with the `desktop-usb` feature on Linux, macOS and Windows.
+//! Uses rusb to find a UVC camera (the one chosen with `select_device`, else
+//! the first one found), parses its streaming descriptors
+//! into a [`FormatCatalog`], negotiates a stream with probe/commit and feeds
+//! the payloads through the same [`FrameAssembler`] and frame processing as
+//! the Android backend.
+//!
+//! Bulk endpoints are read with rusb's synchronous API. rusb has no
+//! isochronous transfer API, so isochronous endpoints are streamed through
+//! the raw libusb API it re-exports ([`rusb::ffi`]): several transfers are
+//! kept in flight on the alternate setting that fits the negotiated payload
+//! size, and their events are handled on the camera thread.
+//!
+//! The streaming interface must be free to claim: on Linux the `uvcvideo`
+//! kernel driver is detached automatically (the device node must be writable,
+//! e.g. via a udev rule); on Windows the camera needs the `WinUSB` driver.
+
+use std::collections::VecDeque;
+use std::ops::ControlFlow;
+use std::ptr::NonNull;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use rusb::{
+    ffi, Device, DeviceHandle, Direction, GlobalContext, Recipient, RequestType, TransferType,
+    UsbContext,
+};
+
+use crate::capture::IsoPacketRecord;
+
+use crate::bulk_transfer::{StreakChange, TimeoutStreak};
+use crate::devices::{self, CameraDevice, DeviceError, DeviceRegistry};
+use crate::frame_assembler::{is_complete_jpeg, is_jpeg_data, FrameAssembler, ProcessResult};
+use crate::lifecycle::StopSignal;
+use crate::messages::MessageCode;
+use crate::still_capture::StillTransport;
+use crate::usb::{
+    frame_for_format, observe_assembly, record_active_stream, store_frame_and_emit, StreamResult,
+    StreamingContext, YuvFrameProcessor, BULK_RETRY_DELAY,
+};
+use crate::usb_permission::DeviceKey;
+use crate::uvc_controls::{self, ControlTransport, ControlUnits, UvcControlError};
+use crate::uvc_descriptors::{FormatCatalog, FormatKind};
+use crate::{DisconnectReason, PixelFormat};
+
+/// Lock a mutex with poison recovery (see [`crate::lock_or_recover`]).
+macro_rules! lock_or_recover {
+    ($mutex:expr) => {
+        crate::lock_or_recover(&$mutex)
+    };
+}
+
+/// USB interface class for video devices
+const USB_CLASS_VIDEO: u8 = 0x0E;
+/// Video interface subclass for the control interface
+const SUBCLASS_VIDEO_CONTROL: u8 = 0x01;
+/// Video interface subclass for the streaming interface
+const SUBCLASS_VIDEO_STREAMING: u8 = 0x02;
+
+/// UVC class-specific requests
+const UVC_SET_CUR: u8 = 0x01;
+const UVC_GET_CUR: u8 = 0x81;
+/// UVC streaming interface control selectors
+const VS_PROBE_CONTROL: u8 = 0x01;
+const VS_COMMIT_CONTROL: u8 = 0x02;
+
+/// Length of the UVC 1.0 probe/commit control
+const PROBE_CONTROL_LEN: usize = 26;
+
+/// Timeout for probe/commit control transfers
+const CONTROL_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// How often to look for a camera while none is connected
+const DEVICE_POLL_INTERVAL: Duration = Duration::from_secs(2);
+
+/// Delay before reconnecting after a streaming error
+const RECONNECT_DELAY: Duration = Duration::from_secs(1);
+
+/// Log frame count progress every N frames
+const LOG_INTERVAL_FRAMES: u32 = 30;
+
+/// Isochronous transfers kept in flight. Frames are processed on the thread
+/// that handles the transfer events, so more are queued than on Android.
+const ISO_TRANSFERS: usize = 8;
+/// Packets (service intervals) per isochronous transfer
+const ISO_PACKETS_PER_TRANSFER: usize = 32;
+/// How long one round of libusb event handling may block
+const ISO_EVENT_TIMEOUT: Duration = Duration::from_millis(100);
+/// Event handling rounds to wait for cancelled transfers before giving up
+const ISO_CANCEL_ROUNDS: u32 = 10;
+
+/// Descriptor type of the SuperSpeed endpoint companion descriptor
+const SS_ENDPOINT_COMPANION: u8 = 0x30;
+
+/// Parameters the camera accepted during probe/commit
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Negotiated {
+    format_index: u8,
+    frame_index: u8,
+    frame_interval: u32,
+    max_frame_size: u32,
+    max_payload: u32,
+}
+
+/// Alternate setting of the streaming interface with an isochronous endpoint
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct IsoAltSetting {
+    /// `bAlternateSetting`
+    setting: u8,
+    /// Isochronous IN endpoint address
+    endpoint: u8,
+    /// Bytes per service interval, i.e. per iso packet
+    packet_size: usize,
+}
+
+/// Where the camera streams video from
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum StreamEndpoint {
+    /// Bulk IN endpoint address, usable on alternate setting 0
+    Bulk(u8),
+    /// Isochronous alternate settings, by increasing bandwidth
+    Isochronous(Vec<IsoAltSetting>),
+}
+
+/// A UVC camera with its streaming interface claimed
+struct UvcCamera {
+    /// Shared with the camera controls while the camera is open
+    handle: Arc<DeviceHandle<GlobalContext>>,
+    /// Streaming interface number
+    interface: u8,
+    /// Video endpoint of the streaming interface
+    endpoint: StreamEndpoint,
+    /// Formats and resolutions from the streaming interface descriptors
+    catalog: FormatCatalog,
+    /// Camera terminal and processing unit of the control interface
+    control_units: ControlUnits,
+    /// Human-readable device description for status events
+    info: String,
+    /// Device name in the [`DeviceRegistry`]
+    id: String,
+}
+
+impl Drop for UvcCamera {
+    fn drop(&mut self) {
+        if let Err(e) = self.handle.release_interface(self.interface) {
+            log::debug!("Could not release interface {}: {}", self.interface, e);
+        }
+    }
+}
+
+/// Why a camera could not be used
+#[derive(Debug, thiserror::Error)]
+enum DesktopUsbError {
+    #[error("USB error: {0}")]
+    Usb(#[from] rusb::Error),
+    #[error("Camera rejected the stream parameters")]
+    Negotiation,
+}
+
+/// Find a camera, stream from it and reconnect until `stop` is signalled.
+///
+/// `stop` shares `ctx.stop_flag`; it also wakes the waits between attempts.
+pub fn run_camera_loop(ctx: StreamingContext, stop: &StopSignal) {
+    let mut waiting_logged = false;
+    let mut last_error = None;
+
+    while !ctx.stop_flag.load(Ordering::Relaxed) {
+        let camera = match open_camera(&ctx.devices) {
+            Ok(Some(camera)) => camera,
+            Ok(None) => {
+                if !waiting_logged {
+                    waiting_logged = true;
+                    log::info!("No UVC camera found, waiting for one to be plugged in");
+                }
+                stop.sleep(DEVICE_POLL_INTERVAL);
+                continue;
+            }
+            Err(e) => {
+                // Report each problem once instead of on every poll
+                let message = e.to_string();
+                if last_error.as_ref() != Some(&message) {
+                    log::error!("Could not open USB camera: {}", message);
+                    report_error(&ctx, &e);
+                    last_error = Some(message);
+                }
+                stop.sleep(DEVICE_POLL_INTERVAL);
+                continue;
+            }
+        };
+        waiting_logged = false;
+        last_error = None;
+
+        log::info!("Opened {}", camera.info);
+        crate::emit_usb_event(&ctx.app_handle, true, Some(camera.info.clone()));
+        lock_or_recover!(ctx.streaming_config).format_catalog = camera.catalog.clone();
+        let transport: Arc<dyn ControlTransport> = Arc::clone(&camera.handle);
+        let _controls = ctx
+            .camera_controls
+            .attach(transport, camera.control_units.clone());
+        let still_transport: Arc<dyn StillTransport> = Arc::clone(&camera.handle);
+        let _still = ctx.still_capture.attach(still_transport, camera.interface);
+
+        loop {
+            {
+                let mut config = lock_or_recover!(ctx.streaming_config);
+                config.restart_requested = false;
+                config.active_stream = None;
+            }
+
+            match stream_camera(&camera, &ctx) {
+                Ok(StreamResult::RestartRequested) => {
+                    if lock_or_recover!(ctx.devices).wants_switch() {
+                        log::info!("Switching to the selected camera");
+                        break;
+                    }
+                    log::info!("Restarting stream with new settings");
+                    continue;
+                }
+                Ok(_) => {
+                    crate::emit_usb_disconnect(&ctx.app_handle, DisconnectReason::Normal, None);
+                }
+                Err(DesktopUsbError::Usb(rusb::Error::NoDevice)) => {
+                    log::warn!("Camera unplugged");
+                    crate::emit_usb_disconnect(
+                        &ctx.app_handle,
+                        DisconnectReason::DeviceUnplugged,
+                        Some(camera.info.clone()),
+                    );
+                }
+                Err(e) => {
+                    log::error!("Streaming failed: {}", e);
+                    report_error(&ctx, &e);
+                    stop.sleep(RECONNECT_DELAY);
+                }
+            }
+            break;
+        }
+
+        lock_or_recover!(ctx.streaming_config).active_stream = None;
+        lock_or_recover!(ctx.devices).set_open(None);
+    }
+
+    log::info!("Stop flag set, exiting desktop camera loop");
+}
+
+/// Report an error to the frontend as a `usb-error` event
+fn report_error(ctx: &StreamingContext, error: &DesktopUsbError) {
+    let (code, error_type, recoverable) = match error {
+        DesktopUsbError::Usb(rusb::Error::NoDevice) => (
+            MessageCode::UsbDeviceUnplugged,
+            DisconnectReason::DeviceUnplugged,
+            true,
+        ),
+        DesktopUsbError::Usb(_) => (
+            MessageCode::UsbTransferError,
+            DisconnectReason::TransferError,
+            true,
+        ),
+        DesktopUsbError::Negotiation => (
+            MessageCode::UsbCameraError,
+            DisconnectReason::TransferError,
+            true,
+        ),
+    };
+    crate::emit_usb_error(
+        &ctx.app_handle,
+        crate::UsbError {
+            code,
+            error_type,
+            message: error.to_string(),
+            recoverable,
+        },
+    );
+}
+
+/// Open an attached UVC camera and claim its streaming interface
+///
+/// The camera selected in `registry` is tried first (and the selection
+/// dropped if it can't be opened), then the others in enumeration order. Cameras that can't be used are skipped, and the first
+/// such error is returned only if no usable camera is found.
+fn open_camera(registry: &Mutex<DeviceRegistry>) -> Result<Option<UvcCamera>, DesktopUsbError> {
+    let mut devices = Vec::new();
+    let mut candidates = Vec::new();
+    for device in GlobalContext::default().devices()?.iter() {
+        // Product names need each camera opened; list_cameras reads them
+        if let Some(camera) = camera_device(&device, false) {
+            devices.push(device);
+            candidates.push(camera);
+        }
+    }
+
+    // Try the camera chosen with select_device first
+    let mut selected = false;
+    {
+        let mut registry = lock_or_recover!(registry);
+        if let Some(index) = registry.pick(&candidates) {
+            devices[..=index].rotate_right(1);
+            selected = true;
+        }
+        registry.update(candidates);
+    }
+
+    let mut first_error = None;
+    for device in devices {
+        let opened = open_if_uvc(&device);
+        // Fall back to the others rather than retrying it on every restart
+        if std::mem::take(&mut selected) && !matches!(opened, Ok(Some(_))) {
+            log::warn!("Could not open the selected camera, dropping the selection");
+            lock_or_recover!(registry).clear_selection();
+        }
+        match opened {
+            Ok(Some(camera)) => {
+                lock_or_recover!(registry).set_open(Some(camera.id.clone()));
+                return Ok(Some(camera));
+            }
+            Ok(None) => {}
+            Err(e) => {
+                log::debug!(
+                    "Skipping camera on bus {} address {}: {}",
+                    device.bus_number(),
+                    device.address(),
+                    e
+                );
+                first_error.get_or_insert(e);
+            }
+        }
+    }
+    first_error.map_or(Ok(None), Err)
+}
+
+/// List the connected UVC cameras, with their product names
+///
+/// # Errors
+///
+/// Returns `DeviceError::Enumeration` if libusb can't list the devices.
+pub fn list_cameras() -> devices::Result<Vec<CameraDevice>> {
+    let list = GlobalContext::default()
+        .devices()
+        .map_err(|e| DeviceError::Enumeration(e.to_string()))?;
+    Ok(list
+        .iter()
+        .filter_map(|device| camera_device(&device, true))
+        .collect())
+}
+
+/// Registry entry of `device` if it has a video interface
+///
+/// The product name needs the device opened, so it is only read with `name`.
+fn camera_device(device: &Device<GlobalContext>, name: bool) -> Option<CameraDevice> {
+    let config = device.active_config_descriptor().ok()?;
+    let is_video = config.interfaces().any(|interface| {
+        interface
+            .descriptors()
+            .any(|setting| setting.class_code() == USB_CLASS_VIDEO)
+    });
+    if !is_video {
+        return None;
+    }
+    let descriptor = device.device_descriptor().ok()?;
+    let product = name
+        .then(|| device.open().ok())
+        .flatten()
+        .and_then(|handle| handle.read_product_string_ascii(&descriptor).ok());
+    Some(CameraDevice::new(
+        device_id(device),
+        DeviceKey {
+            vendor_id: descriptor.vendor_id(),
+            product_id: descriptor.product_id(),
+        },
+        product,
+    ))
+}
+
+/// Device name of `device`: `usb/<bus>/<address>`
+fn device_id(device: &Device<GlobalContext>) -> String {
+    format!("usb/{:03}/{:03}", device.bus_number(), device.address())
+}
+
+/// Claim `device`'s streaming interface if it is a UVC camera
+fn open_if_uvc(device: &Device<GlobalContext>) -> Result<Option<UvcCamera>, DesktopUsbError> {
+    let Ok(config) = device.active_config_descriptor() else {
+        return Ok(None);
+    };
+
+    let mut streaming = None;
+    let mut control_units = None;
+    let mut bulk_endpoint = None;
+    let mut iso_settings = Vec::new();
+    for interface in config.interfaces() {
+        for setting in interface.descriptors() {
+            if setting.class_code() != USB_CLASS_VIDEO {
+                continue;
+            }
+            if setting.sub_class_code() == SUBCLASS_VIDEO_CONTROL {
+                if control_units.is_none() {
+                    control_units = Some(ControlUnits::parse(
+                        setting.interface_number(),
+                        setting.extra(),
+                    ));
+                }
+                continue;
+            }
+            if setting.sub_class_code() != SUBCLASS_VIDEO_STREAMING {
+                continue;
+            }
+            // Class-specific format descriptors follow alternate setting 0
+            if streaming.is_none() && setting.setting_number() == 0 {
+                streaming = Some((setting.interface_number(), setting.extra().to_vec()));
+            }
+            for endpoint in setting.endpoint_descriptors() {
+                if endpoint.direction() != Direction::In {
+                    continue;
+                }
+                match endpoint.transfer_type() {
+                    TransferType::Bulk if bulk_endpoint.is_none() => {
+                        bulk_endpoint = Some(endpoint.address());
+                    }
+                    TransferType::Isochronous => iso_settings.push(IsoAltSetting {
+                        setting: setting.setting_number(),
+                        endpoint: endpoint.address(),
+                        packet_size: iso_packet_size(
+                            endpoint.max_packet_size(),
+                            endpoint.extra().unwrap_or_default(),
+                        ),
+                    }),
+                    _ => {}
+                }
+            }
+        }
+        if streaming.is_some() {
+            break;
+        }
+    }
+
+    let Some((interface, extra)) = streaming else {
+        return Ok(None);
+    };
+
+    let endpoint = match bulk_endpoint {
+        Some(address) => StreamEndpoint::Bulk(address),
+        None if !iso_settings.is_empty() => {
+            iso_settings.sort_by_key(|alt| alt.packet_size);
+            StreamEndpoint::Isochronous(iso_settings)
+        }
+        None => return Ok(None),
+    };
+
+    let descriptor = device.device_descriptor()?;
+    let id = format!(
+        "{:04x}:{:04x}",
+        descriptor.vendor_id(),
+        descriptor.product_id()
+    );
+
+    let handle = device.open()?;
+    let product = handle
+        .read_product_string_ascii(&descriptor)
+        .unwrap_or_else(|_| "USB Camera".to_string());
+    let info = format!("{} ({})", product, id);
+
+    // Only supported on Linux; elsewhere the interface must already be free
+    if let Err(e) = handle.set_auto_detach_kernel_driver(true) {
+        log::debug!("Kernel driver auto-detach unavailable: {}", e);
+    }
+    handle.claim_interface(interface)?;
+    if let Err(e) = handle.set_alternate_setting(interface, 0) {
+        log::debug!("Could not select alt setting 0: {}", e);
+    }
+
+    let catalog = FormatCatalog::parse(&extra);
+    match &endpoint {
+        StreamEndpoint::Bulk(address) => log::info!(
+            "UVC streaming interface {} (bulk endpoint 0x{:02x}, {} formats)",
+            interface,
+            address,
+            catalog.formats.len()
+        ),
+        StreamEndpoint::Isochronous(settings) => log::info!(
+            "UVC streaming interface {} ({} isochronous alt settings, {} formats)",
+            interface,
+            settings.len(),
+            catalog.formats.len()
+        ),
+    }
+
+    Ok(Some(UvcCamera {
+        handle: Arc::new(handle),
+        interface,
+        endpoint,
+        catalog,
+        control_units: control_units.unwrap_or_default(),
+        info,
+        id: device_id(device),
+    }))
+}
+
+/// Bytes an isochronous endpoint delivers per service interval
+///
+/// SuperSpeed endpoints give the total in the companion descriptor
+/// (`wBytesPerInterval`, in the endpoint's extra bytes); high-speed ones
+/// encode up to 3 transactions per microframe in bits 11-12 of
+/// `wMaxPacketSize`.
+fn iso_packet_size(max_packet_size: u16, extra: &[u8]) -> usize {
+    let mut offset = 0;
+    while offset + 2 <= extra.len() {
+        let len = usize::from(extra[offset]);
+        if len < 2 || offset + len > extra.len() {
+            break;
+        }
+        if extra[offset + 1] == SS_ENDPOINT_COMPANION && len >= 6 {
+            let bytes_per_interval = u16::from_le_bytes([extra[offset + 4], extra[offset + 5]]);
+            if bytes_per_interval > 0 {
+                return usize::from(bytes_per_interval);
+            }
+            let packets =
+                (usize::from(extra[offset + 2]) + 1) * (usize::from(extra[offset + 3] & 0x03) + 1);
+            return usize::from(max_packet_size & 0x07ff) * packets;
+        }
+        offset += len;
+    }
+    let transactions = usize::from((max_packet_size >> 11) & 0x03) + 1;
+    usize::from(max_packet_size & 0x07ff) * transactions
+}
+
+/// Alternate setting to stream `max_payload` bytes per interval from: the
+/// smallest one that fits, else the largest (`settings` is sorted by size)
+fn select_alt_setting(settings: &[IsoAltSetting], max_payload: u32) -> Option<IsoAltSetting> {
+    settings
+        .iter()
+        .find(|alt| alt.packet_size as u64 >= u64::from(max_payload))
+        .or_else(|| settings.last())
+        .copied()
+}
+
+/// Format to stream: the selected one, else the one named first in
+/// `preference`, else the first MJPEG, else the first uncompressed
+fn select_format(
+    catalog: &FormatCatalog,
+    selected: Option<u8>,
+    preference: &[String],
+) -> Option<u8> {
+    selected
+        .filter(|&index| {
+            catalog
+                .format(index)
+                .is_some_and(|f| f.kind != FormatKind::FrameBased)
+        })
+        .or_else(|| catalog.preferred_format(preference).map(|f| f.index))
+        .or_else(|| {
+            catalog
+                .formats_of_kind(FormatKind::Mjpeg)
+                .next()
+                .map(|f| f.index)
+        })
+        .or_else(|| {
+            catalog
+                .formats_of_kind(FormatKind::Uncompressed)
+                .next()
+                .map(|f| f.index)
+        })
+}
+
+/// Build a UVC 1.0 probe/commit control requesting a format, frame and interval
+fn probe_control(
+    format_index: u8,
+    frame_index: u8,
+    frame_interval: u32,
+) -> [u8; PROBE_CONTROL_LEN] {
+    let mut control = [0u8; PROBE_CONTROL_LEN];
+    // bmHint: dwFrameInterval is valid
+    control[0..2].copy_from_slice(&1u16.to_le_bytes());
+    control[2] = format_index;
+    control[3] = frame_index;
+    control[4..8].copy_from_slice(&frame_interval.to_le_bytes());
+    control
+}
+
+/// Read the negotiated parameters from a probe control the camera returned
+fn parse_probe(control: &[u8]) -> Option<Negotiated> {
+    if control.len() < PROBE_CONTROL_LEN {
+        return None;
+    }
+    let u32_at = |offset: usize| {
+        u32::from_le_bytes([
+            control[offset],
+            control[offset + 1],
+            control[offset + 2],
+            control[offset + 3],
+        ])
+    };
+    Some(Negotiated {
+        format_index: control[2],
+        frame_index: control[3],
+        frame_interval: u32_at(4),
+        max_frame_size: u32_at(18),
+        max_payload: u32_at(22),
+    })
+}
+
+/// Run probe/commit for a format and frame
+fn negotiate(
+    camera: &UvcCamera,
+    format_index: u8,
+    frame_index: u8,
+    frame_interval: Option<u32>,
+) -> Result<Negotiated, DesktopUsbError> {
+    let request_out = rusb::request_type(Direction::Out, RequestType::Class, Recipient::Interface);
+    let request_in = rusb::request_type(Direction::In, RequestType::Class, Recipient::Interface);
+    let index = u16::from(camera.interface);
+
+    let interval = camera
+        .catalog
+        .frame(format_index, frame_index)
+        .map_or(0, |frame| frame.interval_for(frame_interval));
+    let probe = probe_control(format_index, frame_index, interval);
+
+    camera.handle.write_control(
+        request_out,
+        UVC_SET_CUR,
+        u16::from(VS_PROBE_CONTROL) << 8,
+        index,
+        &probe,
+        CONTROL_TIMEOUT,
+    )?;
+
+    let mut response = [0u8; PROBE_CONTROL_LEN];
+    let len = camera.handle.read_control(
+        request_in,
+        UVC_GET_CUR,
+        u16::from(VS_PROBE_CONTROL) << 8,
+        index,
+        &mut response,
+        CONTROL_TIMEOUT,
+    )?;
+    let negotiated = parse_probe(&response[..len]).ok_or(DesktopUsbError::Negotiation)?;
+
+    camera.handle.write_control(
+        request_out,
+        UVC_SET_CUR,
+        u16::from(VS_COMMIT_CONTROL) << 8,
+        index,
+        &response,
+        CONTROL_TIMEOUT,
+    )?;
+
+    log::info!(
+        "Negotiated: format={} frame={} max_frame_size={} max_payload={} frame_interval={}",
+        negotiated.format_index,
+        negotiated.frame_index,
+        negotiated.max_frame_size,
+        negotiated.max_payload,
+        negotiated.frame_interval
+    );
+    Ok(negotiated)
+}
+
+/// Negotiate the configured stream and read frames until stopped or restarted
+fn stream_camera(
+    camera: &UvcCamera,
+    stream_ctx: &StreamingContext,
+) -> Result<StreamResult, DesktopUsbError> {
+    use tauri::Emitter;
+
+    let (selected_format, selected_frame, preferred, format_preference, selected_interval) = {
+        let config = lock_or_recover!(stream_ctx.streaming_config);
+        (
+            config.selected_format_index,
+            config.selected_frame_index,
+            config.preferred_resolution,
+            config.format_preference.clone(),
+            config.selected_frame_interval,
+        )
+    };
+    let format_index =
+        select_format(&camera.catalog, selected_format, &format_preference).unwrap_or(1);
+    let frame_index = frame_for_format(&camera.catalog, format_index, selected_frame, preferred);
+    let negotiated = negotiate(camera, format_index, frame_index, selected_interval)?;
+
+    let format = camera.catalog.format(negotiated.format_index);
+    let is_mjpeg = format.is_none_or(|f| f.kind == FormatKind::Mjpeg);
+    let (width, height) = camera
+        .catalog
+        .frame(negotiated.format_index, negotiated.frame_index)
+        .map_or((0, 0), |frame| (frame.width, frame.height));
+
+    record_active_stream(
+        stream_ctx,
+        crate::ActiveStream {
+            format_index: negotiated.format_index,
+            frame_index: negotiated.frame_index,
+            width,
+            height,
+            frame_interval: negotiated.frame_interval,
+            max_frame_size: negotiated.max_frame_size,
+            max_payload: negotiated.max_payload,
+        },
+    );
+
+    let (code, detail) = if is_mjpeg {
+        (
+            MessageCode::StatusStreamingMjpeg,
+            format!("MJPEG {}x{}", width, height),
+        )
+    } else {
+        (
+            MessageCode::StatusStreamingYuy2,
+            format!(
+                "{} {}x{} → RGB",
+                format.map_or_else(|| "YUV".to_string(), |f| f.name()),
+                width,
+                height
+            ),
+        )
+    };
+    let _ = stream_ctx.app_handle.emit(
+        "usb-status",
+        serde_json::json!({ "status": "streaming", "code": code, "detail": detail }),
+    );
+
+    let pixel_format = lock_or_recover!(stream_ctx.streaming_config).pixel_format;
+    let mut sink = FrameSink::new(
+        stream_ctx,
+        is_mjpeg,
+        u32::from(width),
+        u32::from(height),
+        pixel_format,
+    );
+    match &camera.endpoint {
+        StreamEndpoint::Bulk(endpoint) => stream_bulk(camera, *endpoint, &negotiated, &mut sink),
+        StreamEndpoint::Isochronous(settings) => {
+            let alt = select_alt_setting(settings, negotiated.max_payload)
+                .ok_or(DesktopUsbError::Negotiation)?;
+            stream_isochronous(camera, alt, &mut sink)
+        }
+    }
+}
+
+/// Pixel format to convert with, or how the stream ended if it was stopped
+/// or a restart was requested
+fn stream_control(stream_ctx: &StreamingContext) -> ControlFlow<StreamResult, PixelFormat> {
+    if stream_ctx.stop_flag.load(Ordering::Relaxed) {
+        return ControlFlow::Break(StreamResult::Normal);
+    }
+    let config = lock_or_recover!(stream_ctx.streaming_config);
+    if config.restart_requested {
+        return ControlFlow::Break(StreamResult::RestartRequested);
+    }
+    ControlFlow::Continue(config.pixel_format)
+}
+
+/// Read bulk transfers until stopped or restarted
+fn stream_bulk(
+    camera: &UvcCamera,
+    endpoint: u8,
+    negotiated: &Negotiated,
+    sink: &mut FrameSink<'_>,
+) -> Result<StreamResult, DesktopUsbError> {
+    let stream_ctx = sink.stream_ctx;
+    let config = lock_or_recover!(stream_ctx.streaming_config)
+        .bulk_transfer
+        .clone();
+    let timeout = Duration::from_millis(u64::from(config.timeout_ms));
+    let mut buffer = vec![0u8; config.transfer_size(negotiated.max_payload)];
+    let mut timeouts = TimeoutStreak::new(config.stall_after_timeouts);
+    let mut failures = 0u32;
+
+    log::info!(
+        "Starting bulk streaming from endpoint 0x{:02x} ({} byte transfers)",
+        endpoint,
+        buffer.len()
+    );
+
+    loop {
+        let pixel_format = match stream_control(stream_ctx) {
+            ControlFlow::Continue(pixel_format) => pixel_format,
+            ControlFlow::Break(result) => return Ok(result),
+        };
+
+        let transferred = match camera.handle.read_bulk(endpoint, &mut buffer, timeout) {
+            Ok(n) => n,
+            Err(rusb::Error::Timeout) => {
+                if timeouts.timeout() != StreakChange::None {
+                    log::warn!(
+                        "No bulk data for {} consecutive transfers",
+                        timeouts.count()
+                    );
+                    stream_ctx.stream_health.record_stall();
+                    stream_ctx
+                        .stream_health
+                        .set_timeout_streak(timeouts.count());
+                    crate::emit_stream_health(&stream_ctx.app_handle);
+                }
+                continue;
+            }
+            Err(
+                e @ (rusb::Error::Overflow
+                | rusb::Error::Pipe
+                | rusb::Error::Interrupted
+                | rusb::Error::Io),
+            ) if failures < config.max_retries => {
+                failures += 1;
+                log::warn!(
+                    "Bulk transfer error: {}, retrying ({}/{})",
+                    e,
+                    failures,
+                    config.max_retries
+                );
+                stream_ctx.stream_health.record_transfer_error();
+                std::thread::sleep(BULK_RETRY_DELAY);
+                continue;
+            }
+            Err(e) => return Err(e.into()),
+        };
+
+        failures = 0;
+        if let StreakChange::Recovered(count) = timeouts.success() {
+            log::info!("Bulk data resumed after {} timeouts", count);
+            stream_ctx.stream_health.set_timeout_streak(0);
+            crate::emit_stream_health(&stream_ctx.app_handle);
+        }
+
+        let payload = &buffer[..transferred];
+        if stream_ctx.capture_state.wants_packets() {
+            stream_ctx.capture_state.add_packet(payload, endpoint);
+        }
+        sink.push(payload, pixel_format);
+    }
+}
+
+/// Stream from an isochronous alternate setting until stopped or restarted
+///
+/// The alternate setting reserves the bus bandwidth, so alternate setting 0
+/// is selected again when the stream ends.
+fn stream_isochronous(
+    camera: &UvcCamera,
+    alt: IsoAltSetting,
+    sink: &mut FrameSink<'_>,
+) -> Result<StreamResult, DesktopUsbError> {
+    camera
+        .handle
+        .set_alternate_setting(camera.interface, alt.setting)?;
+    log::info!(
+        "Starting isochronous streaming from endpoint 0x{:02x} (alt setting {}, {} byte packets)",
+        alt.endpoint,
+        alt.setting,
+        alt.packet_size
+    );
+
+    let result = run_isochronous(&camera.handle, alt, sink);
+
+    match camera.handle.set_alternate_setting(camera.interface, 0) {
+        Ok(()) | Err(rusb::Error::NoDevice) => {}
+        Err(e) => log::debug!("Could not select alt setting 0: {}", e),
+    }
+    result
+}
+
+/// Keep isochronous transfers in flight and feed their packets to `sink`
+fn run_isochronous(
+    handle: &DeviceHandle<GlobalContext>,
+    alt: IsoAltSetting,
+    sink: &mut FrameSink<'_>,
+) -> Result<StreamResult, DesktopUsbError> {
+    let stream_ctx = sink.stream_ctx;
+    let mut transfers = IsoTransfers::new(handle, alt.endpoint, alt.packet_size)?;
+    for index in 0..ISO_TRANSFERS {
+        transfers.submit(index)?;
+    }
+    let mut sequence = 0u64;
+
+    loop {
+        let pixel_format = match stream_control(stream_ctx) {
+            ControlFlow::Continue(pixel_format) => pixel_format,
+            ControlFlow::Break(result) => return Ok(result),
+        };
+
+        match handle.context().handle_events(Some(ISO_EVENT_TIMEOUT)) {
+            Ok(()) | Err(rusb::Error::Interrupted) => {}
+            Err(e) => return Err(e.into()),
+        }
+
+        while let Some(index) = transfers.next_completed() {
+            match transfers.status(index) {
+                ffi::constants::LIBUSB_TRANSFER_COMPLETED => {}
+                ffi::constants::LIBUSB_TRANSFER_NO_DEVICE => {
+                    return Err(rusb::Error::NoDevice.into());
+                }
+                status => {
+                    log::warn!("Isochronous transfer failed with status {}", status);
+                    stream_ctx.stream_health.record_transfer_error();
+                    transfers.submit(index)?;
+                    continue;
+                }
+            }
+
+            let mut errors = 0;
+            for (packet_index, packet) in transfers.packets(index).enumerate() {
+                let usable = packet.status == ffi::constants::LIBUSB_TRANSFER_COMPLETED
+                    && !packet.data.is_empty();
+                if stream_ctx.capture_state.wants_packets() {
+                    let record = IsoPacketRecord {
+                        transfer_sequence: sequence,
+                        packet_index: packet_index as u16,
+                        status: packet.status,
+                        length: packet.length,
+                        actual_length: packet.data.len() as u32,
+                        captured_index: None,
+                    };
+                    stream_ctx.capture_state.record_iso_packet(
+                        record,
+                        usable.then_some(packet.data),
+                        alt.endpoint,
+                    );
+                }
+                if usable {
+                    sink.push(packet.data, pixel_format);
+                } else if packet.status != ffi::constants::LIBUSB_TRANSFER_COMPLETED {
+                    errors += 1;
+                }
+            }
+            if errors > 0 {
+                stream_ctx.stream_stats.record_usb_errors(errors);
+            }
+            sequence += 1;
+            transfers.submit(index)?;
+        }
+    }
+}
+
+/// Assembles payloads into frames and hands them to the frame pipeline
+struct FrameSink<'a> {
+    stream_ctx: &'a StreamingContext,
+    assembler: FrameAssembler,
+    processor: YuvFrameProcessor,
+    is_mjpeg: bool,
+    width: u32,
+    height: u32,
+    frame_count: u32,
+    rgb_logged: bool,
+}
+
+impl<'a> FrameSink<'a> {
+    fn new(
+        stream_ctx: &'a StreamingContext,
+        is_mjpeg: bool,
+        width: u32,
+        height: u32,
+        pixel_format: PixelFormat,
+    ) -> Self {
+        let mut assembler = if is_mjpeg {
+            FrameAssembler::new_mjpeg()
+        } else {
+            FrameAssembler::new(pixel_format.frame_size(width, height))
+        };
+        observe_assembly(stream_ctx, &mut assembler);
+        Self {
+            stream_ctx,
+            assembler,
+            processor: YuvFrameProcessor::new(width, height, pixel_format),
+            is_mjpeg,
+            width,
+            height,
+            frame_count: 0,
+            rgb_logged: false,
+        }
+    }
+
+    /// Add one UVC payload (header and data), emitting the frame it completes
+    fn push(&mut self, payload: &[u8], pixel_format: PixelFormat) {
+        let ProcessResult::Frame(frame_data) = self.assembler.process_packet(payload) else {
+            return;
+        };
+        let still_image = self.assembler.last_frame_still();
+        if !self.is_mjpeg {
+            let notes = self
+                .processor
+                .assembled(self.stream_ctx, &frame_data, still_image);
+            self.processor
+                .process(self.stream_ctx, &frame_data, pixel_format, &notes);
+            return;
+        }
+        self.stream_ctx
+            .capture_state
+            .end_frame(!is_complete_jpeg(&frame_data));
+        if !is_jpeg_data(&frame_data) {
+            log::debug!("Dropping non-JPEG frame ({} bytes)", frame_data.len());
+            return;
+        }
+        if self
+            .stream_ctx
+            .still_capture
+            .offer(&frame_data, still_image)
+        {
+            return;
+        }
+
+        self.frame_count += 1;
+        store_frame_and_emit(
+            self.stream_ctx,
+            frame_data.clone(),
+            &frame_data,
+            self.width,
+            self.height,
+            true,
+            &mut self.rgb_logged,
+        );
+        if self.frame_count % LOG_INTERVAL_FRAMES == 0 {
+            log::info!("Received {} MJPEG frames", self.frame_count);
+        }
+    }
+}
+
+/// One isochronous packet of a completed transfer
+struct IsoPacket<'a> {
+    /// libusb transfer status of the packet (`LIBUSB_TRANSFER_*`)
+    status: i32,
+    /// Requested packet length in bytes
+    length: u32,
+    /// Bytes received
+    data: &'a [u8],
+}
+
+/// A libusb isochronous transfer and the buffer it reads into
+struct IsoTransfer {
+    raw: NonNull<ffi::libusb_transfer>,
+    buffer: Vec<u8>,
+    /// Set by [`iso_transfer_done`]; boxed so its address stays stable
+    done: Box<AtomicBool>,
+}
+
+/// Isochronous transfers on one endpoint, resubmitted as they complete
+///
+/// Completion callbacks run wherever libusb events are handled (including
+/// rusb's synchronous transfers on other threads), so they only flag the
+/// transfer as done; the packets are read on the streaming thread.
+struct IsoTransfers<'a> {
+    handle: &'a DeviceHandle<GlobalContext>,
+    transfers: Vec<IsoTransfer>,
+    packet_size: usize,
+    /// Submitted transfers in submission order, which is completion order
+    pending: VecDeque<usize>,
+}
+
+impl<'a> IsoTransfers<'a> {
+    /// Allocate [`ISO_TRANSFERS`] transfers of [`ISO_PACKETS_PER_TRANSFER`]
+    /// packets of `packet_size` bytes
+    fn new(
+        handle: &'a DeviceHandle<GlobalContext>,
+        endpoint: u8,
+        packet_size: usize,
+    ) -> Result<Self, rusb::Error> {
+        let mut transfers = Self {
+            handle,
+            transfers: Vec::with_capacity(ISO_TRANSFERS),
+            packet_size,
+            pending: VecDeque::with_capacity(ISO_TRANSFERS),
+        };
+        let length = i32::try_from(packet_size * ISO_PACKETS_PER_TRANSFER)
+            .map_err(|_| rusb::Error::InvalidParam)?;
+
+        for _ in 0..ISO_TRANSFERS {
+            // SAFETY: libusb_alloc_transfer has no preconditions
+            let raw = unsafe { ffi::libusb_alloc_transfer(ISO_PACKETS_PER_TRANSFER as i32) };
+            let raw = NonNull::new(raw).ok_or(rusb::Error::NoMem)?;
+            let mut transfer = IsoTransfer {
+                raw,
+                buffer: vec![0u8; packet_size * ISO_PACKETS_PER_TRANSFER],
+                done: Box::new(AtomicBool::new(false)),
+            };
+            // SAFETY: the transfer was just allocated with room for the iso
+            // packet descriptors; the buffer and flag live until it is freed
+            unsafe {
+                let xfr = transfer.raw.as_mut();
+                xfr.dev_handle = handle.as_raw();
+                xfr.endpoint = endpoint;
+                xfr.transfer_type = ffi::constants::LIBUSB_TRANSFER_TYPE_ISOCHRONOUS;
+                xfr.timeout = 0;
+                xfr.buffer = transfer.buffer.as_mut_ptr();
+                xfr.length = length;
+                xfr.num_iso_packets = ISO_PACKETS_PER_TRANSFER as i32;
+                xfr.callback = iso_transfer_done;
+                xfr.user_data = std::ptr::from_ref::<AtomicBool>(&*transfer.done)
+                    .cast_mut()
+                    .cast();
+            }
+            transfers.transfers.push(transfer);
+        }
+        Ok(transfers)
+    }
+
+    /// Submit the transfer at `index`
+    fn submit(&mut self, index: usize) -> Result<(), rusb::Error> {
+        let transfer = &self.transfers[index];
+        transfer.done.store(false, Ordering::Release);
+        // SAFETY: the transfer is filled in and not in flight
+        let ret = unsafe {
+            ffi::libusb_set_iso_packet_lengths(transfer.raw.as_ptr(), self.packet_size as u32);
+            ffi::libusb_submit_transfer(transfer.raw.as_ptr())
+        };
+        if ret < 0 {
+            return Err(usb_error(ret));
+        }
+        self.pending.push_back(index);
+        Ok(())
+    }
+
+    /// Index of the oldest submitted transfer if it has completed
+    fn next_completed(&mut self) -> Option<usize> {
+        let &index = self.pending.front()?;
+        if !self.transfers[index].done.load(Ordering::Acquire) {
+            return None;
+        }
+        self.pending.pop_front()
+    }
+
+    /// Status of a completed transfer (`LIBUSB_TRANSFER_*`)
+    fn status(&self, index: usize) -> i32 {
+        // SAFETY: the transfer has completed, so libusb no longer writes it
+        unsafe { self.transfers[index].raw.as_ref().status }
+    }
+
+    /// Packets of a completed transfer, in order
+    fn packets(&self, index: usize) -> impl Iterator<Item = IsoPacket<'_>> {
+        let transfer = &self.transfers[index];
+        (0..ISO_PACKETS_PER_TRANSFER).map(move |i| {
+            // SAFETY: the transfer has completed and was allocated with
+            // ISO_PACKETS_PER_TRANSFER packet descriptors
+            let descriptor = unsafe { &*transfer.raw.as_ref().iso_packet_desc.as_ptr().add(i) };
+            let offset = i * self.packet_size;
+            let len = (descriptor.actual_length as usize).min(self.packet_size);
+            IsoPacket {
+                status: descriptor.status,
+                length: descriptor.length,
+                data: &transfer.buffer[offset..offset + len],
+            }
+        })
+    }
+}
+
+impl Drop for IsoTransfers<'_> {
+    fn drop(&mut self) {
+        for &index in &self.pending {
+            // SAFETY: the transfer is allocated; cancelling one that already
+            // completed just returns LIBUSB_ERROR_NOT_FOUND
+            unsafe { ffi::libusb_cancel_transfer(self.transfers[index].raw.as_ptr()) };
+        }
+
+        // A transfer can only be freed once its cancellation has completed
+        let in_flight = |transfers: &Self| {
+            transfers
+                .pending
+                .iter()
+                .any(|&index| !transfers.transfers[index].done.load(Ordering::Acquire))
+        };
+        let mut rounds = 0;
+        while in_flight(self) && rounds < ISO_CANCEL_ROUNDS {
+            if let Err(e) = self.handle.context().handle_events(Some(ISO_EVENT_TIMEOUT)) {
+                log::debug!("Event handling while cancelling transfers failed: {}", e);
+            }
+            rounds += 1;
+        }
+
+        for (index, transfer) in self.transfers.drain(..).enumerate() {
+            if self.pending.contains(&index) && !transfer.done.load(Ordering::Acquire) {
+                // Still owned by libusb: leaking it is the only safe option
+                log::warn!(
+                    "Isochronous transfer {} was not cancelled, leaking it",
+                    index
+                );
+                std::mem::forget(transfer);
+                continue;
+            }
+            // SAFETY: the transfer is not in flight
+            unsafe { ffi::libusb_free_transfer(transfer.raw.as_ptr()) };
+        }
+    }
+}
+
+/// Completion callback of [`IsoTransfers`]: flags the transfer as done
+extern "system" fn iso_transfer_done(transfer: *mut ffi::libusb_transfer) {
+    // SAFETY: user_data points at the transfer's `done` flag, which outlives it
+    unsafe { (*(*transfer).user_data.cast::<AtomicBool>()).store(true, Ordering::Release) };
+}
+
+/// rusb error for a libusb error code
+fn usb_error(code: i32) -> rusb::Error {
+    match code {
+        ffi::constants::LIBUSB_ERROR_IO => rusb::Error::Io,
+        ffi::constants::LIBUSB_ERROR_INVALID_PARAM => rusb::Error::InvalidParam,
+        ffi::constants::LIBUSB_ERROR_ACCESS => rusb::Error::Access,
+        ffi::constants::LIBUSB_ERROR_NO_DEVICE => rusb::Error::NoDevice,
+        ffi::constants::LIBUSB_ERROR_NOT_FOUND => rusb::Error::NotFound,
+        ffi::constants::LIBUSB_ERROR_BUSY => rusb::Error::Busy,
+        ffi::constants::LIBUSB_ERROR_OVERFLOW => rusb::Error::Overflow,
+        ffi::constants::LIBUSB_ERROR_PIPE => rusb::Error::Pipe,
+        ffi::constants::LIBUSB_ERROR_NO_MEM => rusb::Error::NoMem,
+        ffi::constants::LIBUSB_ERROR_NOT_SUPPORTED => rusb::Error::NotSupported,
+        _ => rusb::Error::Other,
+    }
+}
+
+impl ControlTransport for DeviceHandle<GlobalContext> {
+    fn control_transfer(
+        &self,
+        request_type: u8,
+        request: u8,
+        value: u16,
+        index: u16,
+        data: &mut [u8],
+        timeout_ms: u32,
+    ) -> uvc_controls::Result<usize> {
+        let timeout = Duration::from_millis(u64::from(timeout_ms));
+        // Bit 7 of bmRequestType is the direction
+        let result = if request_type & 0x80 != 0 {
+            self.read_control(request_type, request, value, index, data, timeout)
+        } else {
+            self.write_control(request_type, request, value, index, data, timeout)
+        };
+        result.map_err(|e| UvcControlError::Transfer(e.to_string()))
+    }
+}
+
+impl StillTransport for DeviceHandle<GlobalContext> {
+    fn read_bulk(
+        &self,
+        endpoint: u8,
+        data: &mut [u8],
+        timeout_ms: u32,
+    ) -> uvc_controls::Result<usize> {
+        let timeout = Duration::from_millis(u64::from(timeout_ms));
+        DeviceHandle::read_bulk(self, endpoint, data, timeout)
+            .map_err(|e| UvcControlError::Transfer(e.to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::uvc_descriptors::{FormatDescriptor, FrameDescriptor, FrameIntervals};
+
+    fn format(index: u8, kind: FormatKind) -> FormatDescriptor {
+        FormatDescriptor {
+            index,
+            kind,
+            guid: None,
+            bits_per_pixel: Some(16),
+            default_frame_index: 1,
+            frames: vec![FrameDescriptor {
+                index: 1,
+                width: 640,
+                height: 480,
+                min_bit_rate: 0,
+                max_bit_rate: 0,
+                max_frame_size: None,
+                default_interval: 333_333,
+                intervals: FrameIntervals::Discrete(vec![333_333]),
+            }],
+            still_frame: None,
+        }
+    }
+
+    #[test]
+    fn test_probe_control_layout() {
+        let control = probe_control(2, 3, 333_333);
+        assert_eq!(control.len(), PROBE_CONTROL_LEN);
+        assert_eq!(&control[0..2], &[1, 0]);
+        assert_eq!(control[2], 2);
+        assert_eq!(control[3], 3);
+        assert_eq!(&control[4..8], &333_333u32.to_le_bytes());
+        assert!(control[8..].iter().all(|&b| b == 0));
+    }
+
+    #[test]
+    fn test_parse_probe_reads_negotiated_fields() {
+        let mut control = probe_control(1, 2, 666_666);
+        control[18..22].copy_from_slice(&614_400u32.to_le_bytes());
+        control[22..26].copy_from_slice(&16_384u32.to_le_bytes());
+
+        assert_eq!(
+            parse_probe(&control),
+            Some(Negotiated {
+                format_index: 1,
+                frame_index: 2,
+                frame_interval: 666_666,
+                max_frame_size: 614_400,
+                max_payload: 16_384,
+            })
+        );
+        assert_eq!(parse_probe(&control[..20]), None);
+    }
+
+    #[test]
+    fn test_select_format_prefers_mjpeg() {
+        let catalog = FormatCatalog {
+            formats: vec![
+                format(1, FormatKind::Uncompressed),
+                format(2, FormatKind::Mjpeg),
+            ],
+            ..Default::default()
+        };
+        assert_eq!(select_format(&catalog, None, &[]), Some(2));
+        assert_eq!(select_format(&catalog, Some(1), &[]), Some(1));
+        // Unknown selections fall back to auto-selection
+        assert_eq!(select_format(&catalog, Some(7), &[]), Some(2));
+    }
+
+    #[test]
+    fn test_select_format_follows_preference() {
+        let mut yuy2 = format(1, FormatKind::Uncompressed);
+        yuy2.guid = Some(crate::uvc_descriptors::fourcc_guid(*b"YUY2"));
+        let catalog = FormatCatalog {
+            formats: vec![yuy2, format(2, FormatKind::Mjpeg)],
+            ..Default::default()
+        };
+        let preference = ["NV12".to_string(), "YUY2".to_string(), "MJPEG".to_string()];
+        assert_eq!(select_format(&catalog, None, &preference), Some(1));
+        // An explicit selection still wins
+        assert_eq!(select_format(&catalog, Some(2), &preference), Some(2));
+    }
+
+    #[test]
+    fn test_select_format_skips_frame_based() {
+        let catalog = FormatCatalog {
+            formats: vec![
+                format(1, FormatKind::FrameBased),
+                format(2, FormatKind::Uncompressed),
+            ],
+            ..Default::default()
+        };
+        assert_eq!(select_format(&catalog, Some(1), &[]), Some(2));
+        assert_eq!(select_format(&FormatCatalog::default(), None, &[]), None);
+    }
+
+    #[test]
+    fn test_iso_packet_size() {
+        // Full speed: just the packet size
+        assert_eq!(iso_packet_size(1023, &[]), 1023);
+        // High-bandwidth high speed: 3 transactions of 1024 bytes
+        assert_eq!(iso_packet_size(0x1400, &[]), 3072);
+        // SuperSpeed: wBytesPerInterval from the companion descriptor
+        let companion = [6, SS_ENDPOINT_COMPANION, 15, 0, 0x00, 0x3c];
+        assert_eq!(iso_packet_size(1024, &companion), 15_360);
+        // ...or burst x mult x packet size when it is zero
+        let companion = [6, SS_ENDPOINT_COMPANION, 3, 1, 0, 0];
+        assert_eq!(iso_packet_size(1024, &companion), 8192);
+    }
+
+    #[test]
+    fn test_select_alt_setting_fits_the_payload() {
+        let alt = |setting, packet_size| IsoAltSetting {
+            setting,
+            endpoint: 0x81,
+            packet_size,
+        };
+        let settings = [alt(1, 128), alt(2, 1024), alt(3, 3072)];
+        assert_eq!(select_alt_setting(&settings, 1000), Some(alt(2, 1024)));
+        assert_eq!(select_alt_setting(&settings, 1024), Some(alt(2, 1024)));
+        // Payloads larger than any setting take the largest
+        assert_eq!(select_alt_setting(&settings, 4096), Some(alt(3, 3072)));
+        assert_eq!(select_alt_setting(&[], 1024), None);
+    }
+}
@@ -0,0 +1,196 @@
+//! Per-device USB permission tracking
+//!
+//! Android only remembers a USB permission grant across replugs when the
+//! device matches the app's `USB_DEVICE_ATTACHED` intent filter. For other
+//! devices `openDevice()` starts returning null after a replug. The cache here
+//! tracks what happened to each camera during this session so the USB handler
+//! can tell a revoked grant from a device that was never allowed, re-request
+//! permission automatically, and stop asking once the user has declined
+//! repeatedly.
+//!
+//! Devices are keyed by vendor and product ID. The device name
+//! (`/dev/bus/usb/001/004`) changes on every replug, and the serial number
+//! cannot be read before permission is granted.
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fmt;
+
+/// Permission requests per device that may go unanswered before giving up
+pub const MAX_PERMISSION_REQUESTS: u32 = 3;
+
+/// Stable identity of a USB device across replugs
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct DeviceKey {
+    /// USB vendor ID
+    pub vendor_id: u16,
+    /// USB product ID
+    pub product_id: u16,
+}
+
+impl fmt::Display for DeviceKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:04x}:{:04x}", self.vendor_id, self.product_id)
+    }
+}
+
+/// Last known permission state of a device
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PermissionState {
+    /// Device has not been opened yet
+    #[default]
+    Unknown,
+    /// Device was opened successfully
+    Granted,
+    /// User declined or dismissed the permission dialog
+    Denied,
+    /// Permission was granted earlier but has been lost (usually after a replug)
+    Revoked,
+}
+
+/// What the USB handler should do when a device cannot be opened
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PermissionAction {
+    /// Show the system permission dialog
+    Request,
+    /// The user declined too often; report the error instead of asking again
+    GiveUp,
+}
+
+/// Permission record for one device
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DevicePermission {
+    /// Device identity
+    pub device: DeviceKey,
+    /// Last known state
+    pub state: PermissionState,
+    /// Permission requests declined since the last grant
+    pub declined_requests: u32,
+}
+
+/// Permission state of every device seen during this session
+#[derive(Debug, Default)]
+pub struct PermissionCache {
+    /// Records by device
+    devices: HashMap<DeviceKey, DevicePermission>,
+}
+
+impl PermissionCache {
+    /// Create an empty cache
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Last known state of a device
+    pub fn state(&self, device: DeviceKey) -> PermissionState {
+        self.devices
+            .get(&device)
+            .map(|record| record.state)
+            .unwrap_or_default()
+    }
+
+    /// Record that the device was opened successfully
+    pub fn record_granted(&mut self, device: DeviceKey) {
+        let record = self.entry(device);
+        record.state = PermissionState::Granted;
+        record.declined_requests = 0;
+    }
+
+    /// Record that the device could not be opened for lack of permission
+    ///
+    /// A device that was granted before is marked as revoked. Returns whether
+    /// to ask the user again.
+    pub fn record_missing(&mut self, device: DeviceKey) -> PermissionAction {
+        let record = self.entry(device);
+        if record.state == PermissionState::Granted {
+            log::warn!("USB permission for {} was revoked", device);
+            record.state = PermissionState::Revoked;
+        }
+
+        if record.declined_requests >= MAX_PERMISSION_REQUESTS {
+            PermissionAction::GiveUp
+        } else {
+            PermissionAction::Request
+        }
+    }
+
+    /// Record that a permission request was declined or dismissed
+    pub fn record_denied(&mut self, device: DeviceKey) {
+        let record = self.entry(device);
+        record.state = PermissionState::Denied;
+        record.declined_requests += 1;
+    }
+
+    /// All device records, sorted by device
+    pub fn devices(&self) -> Vec<DevicePermission> {
+        let mut devices: Vec<_> = self.devices.values().cloned().collect();
+        devices.sort_by_key(|record| (record.device.vendor_id, record.device.product_id));
+        devices
+    }
+
+    fn entry(&mut self, device: DeviceKey) -> &mut DevicePermission {
+        self.devices
+            .entry(device)
+            .or_insert_with(|| DevicePermission {
+                device,
+                ..Default::default()
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CAMERA: DeviceKey = DeviceKey {
+        vendor_id: 0x1234,
+        product_id: 0x5678,
+    };
+
+    #[test]
+    fn test_unknown_device_requests_permission() {
+        let mut cache = PermissionCache::new();
+        assert_eq!(cache.state(CAMERA), PermissionState::Unknown);
+        assert_eq!(cache.record_missing(CAMERA), PermissionAction::Request);
+        assert_eq!(cache.state(CAMERA), PermissionState::Unknown);
+    }
+
+    #[test]
+    fn test_lost_grant_is_revoked_and_requested_again() {
+        let mut cache = PermissionCache::new();
+        cache.record_granted(CAMERA);
+        assert_eq!(cache.state(CAMERA), PermissionState::Granted);
+
+        assert_eq!(cache.record_missing(CAMERA), PermissionAction::Request);
+        assert_eq!(cache.state(CAMERA), PermissionState::Revoked);
+
+        cache.record_granted(CAMERA);
+        assert_eq!(cache.state(CAMERA), PermissionState::Granted);
+    }
+
+    #[test]
+    fn test_gives_up_after_repeated_denials() {
+        let mut cache = PermissionCache::new();
+        for _ in 0..MAX_PERMISSION_REQUESTS {
+            assert_eq!(cache.record_missing(CAMERA), PermissionAction::Request);
+            cache.record_denied(CAMERA);
+        }
+        assert_eq!(cache.record_missing(CAMERA), PermissionAction::GiveUp);
+        assert_eq!(cache.state(CAMERA), PermissionState::Denied);
+
+        // A grant (e.g. from the attach intent) resets the count
+        cache.record_granted(CAMERA);
+        assert_eq!(cache.record_missing(CAMERA), PermissionAction::Request);
+    }
+
+    #[test]
+    fn test_devices_serialize_for_frontend() {
+        let mut cache = PermissionCache::new();
+        cache.record_granted(CAMERA);
+        let json = serde_json::to_value(cache.devices()).unwrap();
+        assert_eq!(json[0]["state"], "granted");
+        assert_eq!(json[0]["device"]["vendor_id"], 0x1234);
+        assert_eq!(CAMERA.to_string(), "1234:5678");
+    }
+}
@@ -0,0 +1,1067 @@
+//! UVC camera controls (brightness, contrast, saturation, gamma, exposure)
+//!
+//! Image quality controls live on two units of the video control interface:
+//! the Processing Unit (brightness, contrast, saturation, sharpness, gamma)
+//! and the Camera Terminal (exposure). [`ControlUnits::parse`] finds their IDs
+//! and supported-control bitmaps in the interface's class-specific
+//! descriptors; [`CameraControls`] then issues `GET_MIN` / `GET_MAX` /
+//! `GET_RES` / `GET_DEF` / `GET_CUR` and `SET_CUR` requests.
+//!
+//! The streaming backend attaches a [`ControlTransport`] for the open device
+//! with [`CameraControls::attach`]; the returned guard detaches it when the
+//! device is closed, so commands never reach a stale handle. Requests are
+//! serialized by the controls' mutex and run alongside streaming.
+//!
+//! Many endoscopes drive their LED ring through a vendor Extension Unit
+//! (XU). XU controls have no standard meaning, so the LED control is named
+//! explicitly, either at startup or with `set_led_control`:
+//!
+//! ```text
+//! CLEANSCOPE_LED_CONTROL=4:2                                     # unit ID 4, selector 2
+//! CLEANSCOPE_LED_CONTROL=a1b2c3d4-e5f6-4789-8abc-def012345678:2  # unit GUID, selector 2
+//! ```
+//!
+//! `get_extension_units` lists a camera's XUs with their GUIDs and selectors.
+
+use std::str::FromStr;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::uvc_descriptors::CS_INTERFACE;
+
+/// Video control input terminal descriptor subtype
+pub const VC_INPUT_TERMINAL: u8 = 0x02;
+/// Video control processing unit descriptor subtype
+pub const VC_PROCESSING_UNIT: u8 = 0x05;
+/// Video control extension unit descriptor subtype
+pub const VC_EXTENSION_UNIT: u8 = 0x06;
+/// Input terminal type of a camera sensor (`ITT_CAMERA`)
+pub const ITT_CAMERA: u16 = 0x0201;
+
+/// UVC request codes
+pub const SET_CUR: u8 = 0x01;
+pub const GET_CUR: u8 = 0x81;
+pub const GET_MIN: u8 = 0x82;
+pub const GET_MAX: u8 = 0x83;
+pub const GET_RES: u8 = 0x84;
+pub const GET_LEN: u8 = 0x85;
+pub const GET_DEF: u8 = 0x87;
+
+/// Class request to an interface, host to device
+pub const REQUEST_TYPE_OUT: u8 = 0x21;
+/// Class request to an interface, device to host
+pub const REQUEST_TYPE_IN: u8 = 0xA1;
+
+/// Timeout for control requests
+pub const CONTROL_TIMEOUT_MS: u32 = 1000;
+
+/// Environment variable naming the extension unit control of the LED
+pub const LED_CONTROL_ENV: &str = "CLEANSCOPE_LED_CONTROL";
+
+/// Camera Terminal auto-exposure mode selector (`CT_AE_MODE_CONTROL`)
+const CT_AE_MODE_CONTROL: u8 = 0x02;
+/// `CT_AE_MODE_CONTROL` bit in the Camera Terminal's `bmControls`
+const CT_AE_MODE_BIT: u32 = 1;
+/// Manual exposure time and iris
+const AE_MODE_MANUAL: u8 = 0x01;
+
+/// Minimum length of a camera terminal descriptor (up to `bControlSize`)
+const CAMERA_TERMINAL_LEN: usize = 15;
+/// Minimum length of a processing unit descriptor (up to `bControlSize`)
+const PROCESSING_UNIT_LEN: usize = 8;
+/// Minimum length of an extension unit descriptor (up to `bNrInPins`)
+const EXTENSION_UNIT_LEN: usize = 22;
+
+/// Errors reading or changing camera controls
+#[derive(Debug, Error)]
+pub enum UvcControlError {
+    /// No device is attached
+    #[error("no camera is connected")]
+    NoCamera,
+    /// The control name is not known
+    #[error("unknown camera control '{0}'")]
+    UnknownControl(String),
+    /// The camera does not advertise the control
+    #[error("camera does not support {0}")]
+    Unsupported(&'static str),
+    /// The value is outside the camera's range
+    #[error("{control} value {value} is outside {min}..={max}")]
+    OutOfRange {
+        /// Control name
+        control: &'static str,
+        /// Requested value
+        value: i32,
+        /// Camera minimum
+        min: i32,
+        /// Camera maximum
+        max: i32,
+    },
+    /// The control request failed
+    #[error("control transfer failed: {0}")]
+    Transfer(String),
+    /// No extension unit control is named for the LED
+    #[error("no LED control is configured (set {LED_CONTROL_ENV} or call set_led_control)")]
+    LedNotConfigured,
+    /// An LED control name could not be parsed
+    #[error("invalid LED control '{0}', expected <unit id or GUID>:<selector>")]
+    InvalidLedControl(String),
+}
+
+/// Result type alias for camera control operations
+pub type Result<T> = std::result::Result<T, UvcControlError>;
+
+/// Unit a control belongs to
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UnitKind {
+    /// Camera Terminal (exposure, focus, ...)
+    CameraTerminal,
+    /// Processing Unit (brightness, contrast, ...)
+    ProcessingUnit,
+}
+
+/// Controls exposed to the frontend
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CameraControl {
+    /// `PU_BRIGHTNESS_CONTROL` (signed)
+    Brightness,
+    /// `PU_CONTRAST_CONTROL`
+    Contrast,
+    /// `PU_SATURATION_CONTROL`
+    Saturation,
+    /// `PU_SHARPNESS_CONTROL`
+    Sharpness,
+    /// `PU_GAMMA_CONTROL`
+    Gamma,
+    /// `CT_EXPOSURE_TIME_ABSOLUTE_CONTROL`, in 100 µs units
+    Exposure,
+}
+
+impl CameraControl {
+    /// Every control, in display order
+    pub const ALL: [CameraControl; 6] = [
+        CameraControl::Brightness,
+        CameraControl::Contrast,
+        CameraControl::Saturation,
+        CameraControl::Sharpness,
+        CameraControl::Gamma,
+        CameraControl::Exposure,
+    ];
+
+    /// Name used by the commands
+    #[must_use]
+    pub fn name(self) -> &'static str {
+        match self {
+            CameraControl::Brightness => "brightness",
+            CameraControl::Contrast => "contrast",
+            CameraControl::Saturation => "saturation",
+            CameraControl::Sharpness => "sharpness",
+            CameraControl::Gamma => "gamma",
+            CameraControl::Exposure => "exposure",
+        }
+    }
+
+    /// Unit the control is addressed to
+    #[must_use]
+    pub fn unit(self) -> UnitKind {
+        match self {
+            CameraControl::Exposure => UnitKind::CameraTerminal,
+            _ => UnitKind::ProcessingUnit,
+        }
+    }
+
+    /// Control selector (high byte of `wValue`)
+    #[must_use]
+    pub fn selector(self) -> u8 {
+        match self {
+            CameraControl::Brightness => 0x02,
+            CameraControl::Contrast => 0x03,
+            CameraControl::Saturation => 0x07,
+            CameraControl::Sharpness => 0x08,
+            CameraControl::Gamma => 0x09,
+            CameraControl::Exposure => 0x04,
+        }
+    }
+
+    /// Bit in the unit's `bmControls`
+    fn control_bit(self) -> u32 {
+        match self {
+            CameraControl::Brightness => 0,
+            CameraControl::Contrast => 1,
+            CameraControl::Saturation => 3,
+            CameraControl::Sharpness => 4,
+            CameraControl::Gamma => 5,
+            CameraControl::Exposure => 3,
+        }
+    }
+
+    /// Length of the control value in bytes
+    #[must_use]
+    pub fn size(self) -> usize {
+        match self {
+            CameraControl::Exposure => 4,
+            _ => 2,
+        }
+    }
+
+    fn is_signed(self) -> bool {
+        self == CameraControl::Brightness
+    }
+}
+
+impl FromStr for CameraControl {
+    type Err = UvcControlError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        CameraControl::ALL
+            .into_iter()
+            .find(|c| c.name().eq_ignore_ascii_case(s.trim()))
+            .ok_or_else(|| UvcControlError::UnknownControl(s.to_string()))
+    }
+}
+
+/// A unit and the controls it advertises
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Unit {
+    /// Unit or terminal ID (high byte of `wIndex`)
+    pub id: u8,
+    /// `bmControls` bitmap (first four bytes)
+    pub controls: u32,
+}
+
+/// Control units of a camera's video control interface
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ControlUnits {
+    /// Video control interface number (low byte of `wIndex`)
+    pub interface: u8,
+    /// Camera terminal, if the camera has one
+    pub camera_terminal: Option<Unit>,
+    /// First processing unit, if any
+    pub processing_unit: Option<Unit>,
+    /// Vendor extension units
+    pub extension_units: Vec<ExtensionUnit>,
+}
+
+/// A vendor extension unit, returned by `get_extension_units`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ExtensionUnit {
+    /// Unit ID (high byte of `wIndex`)
+    pub id: u8,
+    /// `guidExtensionCode`, identifying the vendor's control set
+    pub guid: String,
+    /// Control selectors the unit advertises
+    pub selectors: Vec<u8>,
+}
+
+impl ExtensionUnit {
+    /// Parse an extension unit descriptor, `None` if it's truncated
+    fn parse(descriptor: &[u8]) -> Option<Self> {
+        if descriptor.len() < EXTENSION_UNIT_LEN {
+            return None;
+        }
+        let pins = descriptor[21] as usize;
+        let size = *descriptor.get(22 + pins)? as usize;
+        let controls = descriptor.get(23 + pins..23 + pins + size)?;
+        let selectors = (0..size * 8)
+            .filter(|bit| controls[bit / 8] & (1 << (bit % 8)) != 0)
+            .map(|bit| bit as u8 + 1)
+            .collect();
+        Some(Self {
+            id: descriptor[3],
+            guid: format_guid(descriptor[4..20].try_into().ok()?),
+            selectors,
+        })
+    }
+}
+
+/// Format a descriptor GUID (first three fields little-endian) as text
+fn format_guid(bytes: [u8; 16]) -> String {
+    let hex = |range: &[u8]| {
+        range
+            .iter()
+            .map(|b| format!("{:02x}", b))
+            .collect::<String>()
+    };
+    format!(
+        "{:08x}-{:04x}-{:04x}-{}-{}",
+        u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]),
+        u16::from_le_bytes([bytes[4], bytes[5]]),
+        u16::from_le_bytes([bytes[6], bytes[7]]),
+        hex(&bytes[8..10]),
+        hex(&bytes[10..16])
+    )
+}
+
+/// Extension unit of an [`LedControl`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum UnitRef {
+    /// Unit ID, specific to one camera model
+    Id(u8),
+    /// Unit GUID in lowercase text form, shared by a vendor's cameras
+    Guid(String),
+}
+
+/// Extension unit control that sets the LED brightness
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LedControl {
+    /// Extension unit
+    pub unit: UnitRef,
+    /// Control selector
+    pub selector: u8,
+}
+
+impl LedControl {
+    /// Read the LED control from [`LED_CONTROL_ENV`], ignoring invalid values
+    pub fn from_env() -> Option<Self> {
+        let spec = std::env::var(LED_CONTROL_ENV).ok()?;
+        match spec.parse() {
+            Ok(control) => Some(control),
+            Err(e) => {
+                log::warn!("Ignoring {}: {}", LED_CONTROL_ENV, e);
+                None
+            }
+        }
+    }
+}
+
+impl FromStr for LedControl {
+    type Err = UvcControlError;
+
+    /// Parse `<unit id>:<selector>` or `<unit guid>:<selector>`
+    fn from_str(s: &str) -> Result<Self> {
+        let invalid = || UvcControlError::InvalidLedControl(s.to_string());
+        let (unit, selector) = s.trim().rsplit_once(':').ok_or_else(invalid)?;
+        let selector: u8 = selector.parse().map_err(|_| invalid())?;
+        let unit = match unit.parse() {
+            Ok(id) => UnitRef::Id(id),
+            Err(_) => {
+                let guid = unit
+                    .trim_matches(|c| c == '{' || c == '}')
+                    .to_ascii_lowercase();
+                let digits = guid.chars().filter(char::is_ascii_hexdigit).count();
+                if digits != 32 || guid.len() != 36 {
+                    return Err(invalid());
+                }
+                UnitRef::Guid(guid)
+            }
+        };
+        if selector == 0 {
+            return Err(invalid());
+        }
+        Ok(Self { unit, selector })
+    }
+}
+
+impl ControlUnits {
+    /// Parse the class-specific descriptors of a video control interface
+    ///
+    /// `extra` is the interface's extra descriptor bytes. Malformed
+    /// descriptors are skipped.
+    #[must_use]
+    pub fn parse(interface: u8, extra: &[u8]) -> Self {
+        let mut units = Self {
+            interface,
+            ..Self::default()
+        };
+        let mut offset = 0;
+        while offset + 3 <= extra.len() {
+            let length = extra[offset] as usize;
+            if length < 3 || offset + length > extra.len() {
+                break;
+            }
+            let descriptor = &extra[offset..offset + length];
+            offset += length;
+            if descriptor[1] != CS_INTERFACE {
+                continue;
+            }
+            match descriptor[2] {
+                VC_INPUT_TERMINAL if length >= CAMERA_TERMINAL_LEN => {
+                    let terminal_type = u16::from_le_bytes([descriptor[4], descriptor[5]]);
+                    if terminal_type == ITT_CAMERA && units.camera_terminal.is_none() {
+                        units.camera_terminal = Some(Unit {
+                            id: descriptor[3],
+                            controls: bitmap(descriptor, 14),
+                        });
+                    }
+                }
+                VC_PROCESSING_UNIT
+                    if length >= PROCESSING_UNIT_LEN && units.processing_unit.is_none() =>
+                {
+                    units.processing_unit = Some(Unit {
+                        id: descriptor[3],
+                        controls: bitmap(descriptor, 7),
+                    });
+                }
+                VC_EXTENSION_UNIT => units
+                    .extension_units
+                    .extend(ExtensionUnit::parse(descriptor)),
+                _ => {}
+            }
+        }
+        units
+    }
+
+    /// Unit of `control`, if the camera advertises it
+    #[must_use]
+    pub fn unit_for(&self, control: CameraControl) -> Option<Unit> {
+        let unit = match control.unit() {
+            UnitKind::CameraTerminal => self.camera_terminal,
+            UnitKind::ProcessingUnit => self.processing_unit,
+        }?;
+        (unit.controls & (1 << control.control_bit()) != 0).then_some(unit)
+    }
+
+    /// Extension unit `unit` refers to
+    #[must_use]
+    pub fn extension_unit(&self, unit: &UnitRef) -> Option<&ExtensionUnit> {
+        self.extension_units.iter().find(|xu| match unit {
+            UnitRef::Id(id) => xu.id == *id,
+            UnitRef::Guid(guid) => xu.guid == *guid,
+        })
+    }
+
+    /// Controls the camera advertises
+    #[must_use]
+    pub fn supported(&self) -> Vec<CameraControl> {
+        CameraControl::ALL
+            .into_iter()
+            .filter(|c| self.unit_for(*c).is_some())
+            .collect()
+    }
+}
+
+/// Read `bmControls` after the `bControlSize` byte at `size_offset`
+fn bitmap(descriptor: &[u8], size_offset: usize) -> u32 {
+    let size = descriptor[size_offset] as usize;
+    let bytes = descriptor
+        .get(size_offset + 1..)
+        .unwrap_or_default()
+        .iter()
+        .take(size.min(4));
+    bytes
+        .enumerate()
+        .fold(0, |bits, (i, &b)| bits | (u32::from(b) << (8 * i)))
+}
+
+/// Decode a little-endian control value of 1, 2 or 4 bytes
+#[must_use]
+pub fn decode_value(bytes: &[u8], signed: bool) -> i32 {
+    match (bytes.len(), signed) {
+        (1, true) => i32::from(bytes[0] as i8),
+        (1, false) => i32::from(bytes[0]),
+        (2, true) => i32::from(i16::from_le_bytes([bytes[0], bytes[1]])),
+        (2, false) => i32::from(u16::from_le_bytes([bytes[0], bytes[1]])),
+        (len, _) if len >= 4 => i32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]),
+        _ => 0,
+    }
+}
+
+/// Encode a control value as `size` little-endian bytes
+#[must_use]
+pub fn encode_value(value: i32, size: usize) -> Vec<u8> {
+    value.to_le_bytes()[..size.min(4)].to_vec()
+}
+
+/// Issues control requests to an open device
+///
+/// Implemented by the libusb handle on Android and the rusb handle on desktop.
+pub trait ControlTransport: Send + Sync {
+    /// Perform a control transfer, returning the number of bytes transferred
+    ///
+    /// # Errors
+    ///
+    /// Returns `UvcControlError::Transfer` if the request fails or stalls.
+    fn control_transfer(
+        &self,
+        request_type: u8,
+        request: u8,
+        value: u16,
+        index: u16,
+        data: &mut [u8],
+        timeout_ms: u32,
+    ) -> Result<usize>;
+}
+
+/// A control's range and value, returned by `get_camera_controls`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ControlInfo {
+    /// Control
+    pub control: CameraControl,
+    /// Minimum value
+    pub min: i32,
+    /// Maximum value
+    pub max: i32,
+    /// Step between values (`GET_RES`)
+    pub step: i32,
+    /// Factory default
+    pub default: i32,
+    /// Current value
+    pub current: i32,
+}
+
+struct AttachedCamera {
+    transport: Arc<dyn ControlTransport>,
+    units: ControlUnits,
+    generation: u64,
+}
+
+impl AttachedCamera {
+    fn request(&self, control: CameraControl, request: u8, data: &mut [u8]) -> Result<usize> {
+        let unit = self
+            .units
+            .unit_for(control)
+            .ok_or(UvcControlError::Unsupported(control.name()))?;
+        let request_type = if request == SET_CUR {
+            REQUEST_TYPE_OUT
+        } else {
+            REQUEST_TYPE_IN
+        };
+        self.transport.control_transfer(
+            request_type,
+            request,
+            u16::from(control.selector()) << 8,
+            (u16::from(unit.id) << 8) | u16::from(self.units.interface),
+            data,
+            CONTROL_TIMEOUT_MS,
+        )
+    }
+
+    fn read(&self, control: CameraControl, request: u8) -> Result<i32> {
+        let mut data = vec![0u8; control.size()];
+        let len = self.request(control, request, &mut data)?;
+        if len < data.len() {
+            return Err(UvcControlError::Transfer(format!(
+                "{} returned {} of {} bytes",
+                control.name(),
+                len,
+                data.len()
+            )));
+        }
+        Ok(decode_value(&data, control.is_signed()))
+    }
+
+    fn info(&self, control: CameraControl) -> Result<ControlInfo> {
+        Ok(ControlInfo {
+            control,
+            min: self.read(control, GET_MIN)?,
+            max: self.read(control, GET_MAX)?,
+            step: self.read(control, GET_RES)?,
+            default: self.read(control, GET_DEF)?,
+            current: self.read(control, GET_CUR)?,
+        })
+    }
+
+    /// Issue a request to an extension unit control
+    fn xu_request(&self, unit: u8, selector: u8, request: u8, data: &mut [u8]) -> Result<usize> {
+        let request_type = if request == SET_CUR {
+            REQUEST_TYPE_OUT
+        } else {
+            REQUEST_TYPE_IN
+        };
+        self.transport.control_transfer(
+            request_type,
+            request,
+            u16::from(selector) << 8,
+            (u16::from(unit) << 8) | u16::from(self.units.interface),
+            data,
+            CONTROL_TIMEOUT_MS,
+        )
+    }
+
+    /// Read an unsigned extension unit value of `len` bytes
+    fn xu_read(&self, unit: u8, selector: u8, request: u8, len: usize) -> Result<u32> {
+        let mut data = vec![0u8; len];
+        if self.xu_request(unit, selector, request, &mut data)? < len {
+            return Err(UvcControlError::Transfer(format!(
+                "extension unit {} selector {} returned a short reply",
+                unit, selector
+            )));
+        }
+        Ok(data
+            .iter()
+            .enumerate()
+            .fold(0, |value, (i, &b)| value | (u32::from(b) << (8 * i))))
+    }
+
+    /// Switch to manual exposure so `CT_EXPOSURE_TIME_ABSOLUTE` takes effect
+    fn set_manual_exposure(&self) {
+        let Some(terminal) = self.units.camera_terminal else {
+            return;
+        };
+        if terminal.controls & (1 << CT_AE_MODE_BIT) == 0 {
+            return;
+        }
+        let mut mode = [AE_MODE_MANUAL];
+        let result = self.transport.control_transfer(
+            REQUEST_TYPE_OUT,
+            SET_CUR,
+            u16::from(CT_AE_MODE_CONTROL) << 8,
+            (u16::from(terminal.id) << 8) | u16::from(self.units.interface),
+            &mut mode,
+            CONTROL_TIMEOUT_MS,
+        );
+        if let Err(e) = result {
+            log::debug!("Could not switch to manual exposure: {}", e);
+        }
+    }
+}
+
+/// Controls of the connected camera, shared between streaming and commands
+#[derive(Default)]
+pub struct CameraControls {
+    camera: Mutex<Option<AttachedCamera>>,
+    generation: AtomicU64,
+    led: Mutex<Option<LedControl>>,
+}
+
+/// Detaches the camera from [`CameraControls`] when dropped
+pub struct ControlsAttachment {
+    controls: Arc<CameraControls>,
+    generation: u64,
+}
+
+impl Drop for ControlsAttachment {
+    fn drop(&mut self) {
+        let mut camera = crate::lock_or_recover(&self.controls.camera);
+        if camera
+            .as_ref()
+            .is_some_and(|c| c.generation == self.generation)
+        {
+            *camera = None;
+        }
+    }
+}
+
+impl CameraControls {
+    /// Create controls with no camera attached
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Create controls with the LED control from [`LED_CONTROL_ENV`]
+    #[must_use]
+    pub fn from_env() -> Self {
+        let controls = Self::new();
+        controls.set_led_control(LedControl::from_env());
+        controls
+    }
+
+    /// Name the extension unit control that sets the LED brightness
+    pub fn set_led_control(&self, led: Option<LedControl>) {
+        if let Some(led) = &led {
+            log::info!("LED control: {:?} selector {}", led.unit, led.selector);
+        }
+        *crate::lock_or_recover(&self.led) = led;
+    }
+
+    /// Route control requests to an opened camera until the guard is dropped
+    ///
+    /// Drop the guard before closing the device.
+    #[must_use = "the camera is detached when the guard is dropped"]
+    pub fn attach(
+        self: &Arc<Self>,
+        transport: Arc<dyn ControlTransport>,
+        units: ControlUnits,
+    ) -> ControlsAttachment {
+        let generation = self.generation.fetch_add(1, Ordering::Relaxed) + 1;
+        log::info!(
+            "Camera controls: {:?}",
+            units
+                .supported()
+                .iter()
+                .map(|c| c.name())
+                .collect::<Vec<_>>()
+        );
+        *crate::lock_or_recover(&self.camera) = Some(AttachedCamera {
+            transport,
+            units,
+            generation,
+        });
+        ControlsAttachment {
+            controls: Arc::clone(self),
+            generation,
+        }
+    }
+
+    /// Ranges and values of every control the camera supports
+    ///
+    /// Controls the camera advertises but fails to report are left out.
+    ///
+    /// # Errors
+    ///
+    /// Returns `UvcControlError::NoCamera` if no camera is attached.
+    pub fn list(&self) -> Result<Vec<ControlInfo>> {
+        let camera = crate::lock_or_recover(&self.camera);
+        let camera = camera.as_ref().ok_or(UvcControlError::NoCamera)?;
+        Ok(camera
+            .units
+            .supported()
+            .into_iter()
+            .filter_map(|control| match camera.info(control) {
+                Ok(info) => Some(info),
+                Err(e) => {
+                    log::debug!("Skipping camera control {}: {}", control.name(), e);
+                    None
+                }
+            })
+            .collect())
+    }
+
+    /// Set a control, returning its updated state
+    ///
+    /// Setting the exposure switches the camera to manual exposure first.
+    ///
+    /// # Errors
+    ///
+    /// Returns `UvcControlError` if no camera is attached, the camera lacks
+    /// the control, `value` is out of range, or a request fails.
+    pub fn set(&self, control: CameraControl, value: i32) -> Result<ControlInfo> {
+        let camera = crate::lock_or_recover(&self.camera);
+        let camera = camera.as_ref().ok_or(UvcControlError::NoCamera)?;
+        let (min, max) = (
+            camera.read(control, GET_MIN)?,
+            camera.read(control, GET_MAX)?,
+        );
+        if !(min..=max).contains(&value) {
+            return Err(UvcControlError::OutOfRange {
+                control: control.name(),
+                value,
+                min,
+                max,
+            });
+        }
+        if control == CameraControl::Exposure {
+            camera.set_manual_exposure();
+        }
+        camera.request(control, SET_CUR, &mut encode_value(value, control.size()))?;
+        camera.info(control)
+    }
+
+    /// Extension units of the connected camera
+    ///
+    /// # Errors
+    ///
+    /// Returns `UvcControlError::NoCamera` if no camera is attached.
+    pub fn extension_units(&self) -> Result<Vec<ExtensionUnit>> {
+        let camera = crate::lock_or_recover(&self.camera);
+        let camera = camera.as_ref().ok_or(UvcControlError::NoCamera)?;
+        Ok(camera.units.extension_units.clone())
+    }
+
+    /// Set the LED brightness in percent, returning the raw value written
+    ///
+    /// The level is scaled to the control's `GET_MIN`..`GET_MAX` range, or
+    /// to the full range of its `GET_LEN` bytes if the camera doesn't report
+    /// one.
+    ///
+    /// # Errors
+    ///
+    /// Returns `UvcControlError` if no LED control is configured, no camera
+    /// is attached, the camera lacks the control, `level` is above 100, or a
+    /// request fails.
+    pub fn set_led_brightness(&self, level: u8) -> Result<u32> {
+        if level > 100 {
+            return Err(UvcControlError::OutOfRange {
+                control: "LED brightness",
+                value: i32::from(level),
+                min: 0,
+                max: 100,
+            });
+        }
+        let led = crate::lock_or_recover(&self.led)
+            .clone()
+            .ok_or(UvcControlError::LedNotConfigured)?;
+        let camera = crate::lock_or_recover(&self.camera);
+        let camera = camera.as_ref().ok_or(UvcControlError::NoCamera)?;
+        let unit = camera
+            .units
+            .extension_unit(&led.unit)
+            .filter(|xu| xu.selectors.contains(&led.selector))
+            .ok_or(UvcControlError::Unsupported("the configured LED control"))?
+            .id;
+
+        let len = camera.xu_read(unit, led.selector, GET_LEN, 2)? as usize;
+        if !(1..=4).contains(&len) {
+            return Err(UvcControlError::Transfer(format!(
+                "LED control is {} bytes, expected 1 to 4",
+                len
+            )));
+        }
+        let full_range = (0, u32::MAX >> (32 - 8 * len));
+        let (min, max) = match (
+            camera.xu_read(unit, led.selector, GET_MIN, len),
+            camera.xu_read(unit, led.selector, GET_MAX, len),
+        ) {
+            (Ok(min), Ok(max)) if max > min => (min, max),
+            _ => full_range,
+        };
+        let value = min + ((u64::from(max - min) * u64::from(level)) / 100) as u32;
+        let mut data = value.to_le_bytes()[..len].to_vec();
+        camera.xu_request(unit, led.selector, SET_CUR, &mut data)?;
+        log::debug!("LED brightness {}% (value {})", level, value);
+        Ok(value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    /// Video control descriptors: header, camera terminal (ID 1, AE mode and
+    /// exposure), processing unit (ID 2, brightness, contrast and gamma)
+    const VC_EXTRA: &[u8] = &[
+        0x0D, 0x24, 0x01, 0x00, 0x01, 0x33, 0x00, 0x00, 0x00, 0x00, 0x00, 0x01,
+        0x01, // header
+        0x12, 0x24, 0x02, 0x01, 0x01, 0x02, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x03,
+        0x0A, 0x00, 0x00, // camera terminal
+        0x0B, 0x24, 0x05, 0x02, 0x01, 0x00, 0x00, 0x02, 0x23, 0x00, 0x00, // processing unit
+    ];
+
+    /// Extension unit ID 4 with selectors 1 and 2
+    const XU_EXTRA: &[u8] = &[
+        0x1A, 0x24, 0x06, 0x04, // unit 4
+        0xD4, 0xC3, 0xB2, 0xA1, 0xF6, 0xE5, 0x89, 0x47, 0x8A, 0xBC, 0xDE, 0xF0, 0x12, 0x34, 0x56,
+        0x78, // guidExtensionCode
+        0x02, 0x01, 0x02, 0x01, 0x03,
+        0x00, // controls, pins, source, size, bmControls, string
+    ];
+
+    /// Answers GET requests from a table and records SET_CUR values
+    #[derive(Default)]
+    struct MockTransport {
+        values: Mutex<HashMap<(u8, u16, u16), Vec<u8>>>,
+    }
+
+    impl ControlTransport for MockTransport {
+        fn control_transfer(
+            &self,
+            _request_type: u8,
+            request: u8,
+            value: u16,
+            index: u16,
+            data: &mut [u8],
+            _timeout_ms: u32,
+        ) -> Result<usize> {
+            let mut values = self.values.lock().unwrap();
+            if request == SET_CUR {
+                values.insert((GET_CUR, value, index), data.to_vec());
+                return Ok(data.len());
+            }
+            let reply = values
+                .get(&(request, value, index))
+                .ok_or_else(|| UvcControlError::Transfer("stall".to_string()))?;
+            data.copy_from_slice(reply);
+            Ok(reply.len())
+        }
+    }
+
+    fn attached(transport: Arc<MockTransport>) -> (Arc<CameraControls>, ControlsAttachment) {
+        let controls = Arc::new(CameraControls::new());
+        let guard = controls.attach(transport, ControlUnits::parse(0, VC_EXTRA));
+        (controls, guard)
+    }
+
+    fn brightness_transport() -> Arc<MockTransport> {
+        let transport = Arc::new(MockTransport::default());
+        let key = |request| (request, 0x0200, 0x0200);
+        let mut values = transport.values.lock().unwrap();
+        values.insert(key(GET_MIN), encode_value(-64, 2));
+        values.insert(key(GET_MAX), encode_value(64, 2));
+        values.insert(key(GET_RES), encode_value(1, 2));
+        values.insert(key(GET_DEF), encode_value(0, 2));
+        values.insert(key(GET_CUR), encode_value(10, 2));
+        drop(values);
+        transport
+    }
+
+    #[test]
+    fn test_parse_control_units() {
+        let units = ControlUnits::parse(0, VC_EXTRA);
+        assert_eq!(
+            units.camera_terminal,
+            Some(Unit {
+                id: 1,
+                controls: 0x0A
+            })
+        );
+        assert_eq!(
+            units.processing_unit,
+            Some(Unit {
+                id: 2,
+                controls: 0x23
+            })
+        );
+        assert_eq!(
+            units.supported(),
+            [
+                CameraControl::Brightness,
+                CameraControl::Contrast,
+                CameraControl::Gamma,
+                CameraControl::Exposure
+            ]
+        );
+
+        // Truncated descriptors are ignored
+        assert_eq!(
+            ControlUnits::parse(0, &VC_EXTRA[..20]).camera_terminal,
+            None
+        );
+    }
+
+    #[test]
+    fn test_value_encoding() {
+        assert_eq!(decode_value(&encode_value(-64, 2), true), -64);
+        assert_eq!(decode_value(&encode_value(65535, 2), false), 65535);
+        assert_eq!(decode_value(&encode_value(5000, 4), false), 5000);
+        assert_eq!(decode_value(&[0xFF], true), -1);
+        assert_eq!(decode_value(&[], false), 0);
+    }
+
+    #[test]
+    fn test_control_names() {
+        assert_eq!(
+            "Brightness".parse::<CameraControl>().unwrap(),
+            CameraControl::Brightness
+        );
+        assert!(matches!(
+            "zoom".parse::<CameraControl>(),
+            Err(UvcControlError::UnknownControl(_))
+        ));
+    }
+
+    #[test]
+    fn test_list_and_set_controls() {
+        let (controls, _guard) = attached(brightness_transport());
+
+        // Only brightness answers; the other advertised controls are skipped
+        let list = controls.list().unwrap();
+        assert_eq!(
+            list,
+            [ControlInfo {
+                control: CameraControl::Brightness,
+                min: -64,
+                max: 64,
+                step: 1,
+                default: 0,
+                current: 10,
+            }]
+        );
+
+        let info = controls.set(CameraControl::Brightness, -20).unwrap();
+        assert_eq!(info.current, -20);
+        assert!(matches!(
+            controls.set(CameraControl::Brightness, 100),
+            Err(UvcControlError::OutOfRange { max: 64, .. })
+        ));
+        assert!(matches!(
+            controls.set(CameraControl::Saturation, 1),
+            Err(UvcControlError::Unsupported("saturation"))
+        ));
+    }
+
+    #[test]
+    fn test_dropping_the_guard_detaches_the_camera() {
+        let (controls, guard) = attached(brightness_transport());
+        drop(guard);
+        assert!(matches!(controls.list(), Err(UvcControlError::NoCamera)));
+
+        // A stale guard doesn't detach a newer camera
+        let stale = controls.attach(brightness_transport(), ControlUnits::parse(0, VC_EXTRA));
+        let _current = controls.attach(brightness_transport(), ControlUnits::parse(0, VC_EXTRA));
+        drop(stale);
+        assert!(controls.list().is_ok());
+    }
+
+    #[test]
+    fn test_parse_extension_units() {
+        let units = ControlUnits::parse(0, &[VC_EXTRA, XU_EXTRA].concat());
+        assert!(units.processing_unit.is_some());
+        assert_eq!(
+            units.extension_units,
+            [ExtensionUnit {
+                id: 4,
+                guid: "a1b2c3d4-e5f6-4789-8abc-def012345678".to_string(),
+                selectors: vec![1, 2],
+            }]
+        );
+
+        // Truncated extension units are skipped
+        let mut truncated = XU_EXTRA[..24].to_vec();
+        truncated[0] = 24;
+        assert!(ControlUnits::parse(0, &truncated)
+            .extension_units
+            .is_empty());
+    }
+
+    #[test]
+    fn test_led_control_names() {
+        assert_eq!(
+            "4:2".parse::<LedControl>().unwrap(),
+            LedControl {
+                unit: UnitRef::Id(4),
+                selector: 2
+            }
+        );
+        assert_eq!(
+            "{A1B2C3D4-E5F6-4789-8ABC-DEF012345678}:2"
+                .parse::<LedControl>()
+                .unwrap()
+                .unit,
+            UnitRef::Guid("a1b2c3d4-e5f6-4789-8abc-def012345678".to_string())
+        );
+        for invalid in ["4", "4:0", "x:2", "a1b2c3d4:2", "4:300"] {
+            assert!(invalid.parse::<LedControl>().is_err(), "{}", invalid);
+        }
+    }
+
+    #[test]
+    fn test_set_led_brightness() {
+        let transport = Arc::new(MockTransport::default());
+        transport
+            .values
+            .lock()
+            .unwrap()
+            .insert((GET_LEN, 0x0200, 0x0400), vec![1, 0]);
+        let controls = Arc::new(CameraControls::new());
+        let _guard = controls.attach(
+            Arc::clone(&transport) as Arc<dyn ControlTransport>,
+            ControlUnits::parse(0, &[VC_EXTRA, XU_EXTRA].concat()),
+        );
+        assert!(matches!(
+            controls.set_led_brightness(50),
+            Err(UvcControlError::LedNotConfigured)
+        ));
+
+        controls.set_led_control(Some(
+            "a1b2c3d4-e5f6-4789-8abc-def012345678:2".parse().unwrap(),
+        ));
+        // No GET_MIN/GET_MAX: scaled to the full byte
+        assert_eq!(controls.set_led_brightness(50).unwrap(), 127);
+        assert_eq!(
+            transport.values.lock().unwrap()[&(GET_CUR, 0x0200, 0x0400)],
+            [127]
+        );
+
+        {
+            let mut values = transport.values.lock().unwrap();
+            values.insert((GET_MIN, 0x0200, 0x0400), vec![0]);
+            values.insert((GET_MAX, 0x0200, 0x0400), vec![8]);
+        }
+        assert_eq!(controls.set_led_brightness(100).unwrap(), 8);
+        assert!(matches!(
+            controls.set_led_brightness(101),
+            Err(UvcControlError::OutOfRange { .. })
+        ));
+
+        controls.set_led_control(Some("4:3".parse().unwrap()));
+        assert!(matches!(
+            controls.set_led_brightness(10),
+            Err(UvcControlError::Unsupported(_))
+        ));
+    }
+}
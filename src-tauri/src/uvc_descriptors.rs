@@ -0,0 +1,911 @@
+//! UVC video streaming descriptor parsing
+//!
+//! Parses the class-specific descriptors of a video streaming interface
+//! (`VS_FORMAT_*` followed by their `VS_FRAME_*` descriptors) into a
+//! [`FormatCatalog`]: every format the camera advertises, with its resolutions
+//! and frame intervals. Resolution lists and probe/commit negotiation are
+//! driven by the catalog instead of assumed defaults (format 1, frame 1,
+//! 2 bytes per pixel).
+//!
+//! The parser only reads the raw descriptor bytes (libusb's interface `extra`
+//! field, or the streaming interface's part of Android's raw descriptors), so
+//! it works the same on every platform. Malformed or truncated
+//! descriptors are skipped rather than failing the whole catalog.
+//!
+//! The catalog also records how the camera takes still images (the input
+//! header's `bStillCaptureMethod` and each format's `VS_STILL_IMAGE_FRAME`
+//! sizes), which `still_capture` uses.
+
+use crate::format_registry::{self, FOURCC_GUID_SUFFIX};
+use crate::PixelFormat;
+use serde::{Deserialize, Serialize};
+
+pub use crate::format_registry::{fourcc_guid, BGR24_GUID, RGB24_GUID};
+
+/// Standard interface descriptor type
+pub const INTERFACE_DESCRIPTOR: u8 = 0x04;
+
+/// Class-specific interface descriptor type (`CS_INTERFACE`)
+pub const CS_INTERFACE: u8 = 0x24;
+
+/// Video streaming input header
+pub const VS_INPUT_HEADER: u8 = 0x01;
+/// Still image frame descriptor (still sizes of the preceding format)
+pub const VS_STILL_IMAGE_FRAME: u8 = 0x03;
+/// Uncompressed format descriptor
+pub const VS_FORMAT_UNCOMPRESSED: u8 = 0x04;
+/// Uncompressed frame descriptor
+pub const VS_FRAME_UNCOMPRESSED: u8 = 0x05;
+/// MJPEG format descriptor
+pub const VS_FORMAT_MJPEG: u8 = 0x06;
+/// MJPEG frame descriptor
+pub const VS_FRAME_MJPEG: u8 = 0x07;
+/// Frame-based format descriptor (H.264 and other codecs)
+pub const VS_FORMAT_FRAME_BASED: u8 = 0x10;
+/// Frame-based frame descriptor
+pub const VS_FRAME_FRAME_BASED: u8 = 0x11;
+
+/// Minimum length of an input header carrying `bStillCaptureMethod`
+const INPUT_HEADER_STILL_LEN: usize = 10;
+/// Minimum length of a still image frame descriptor (no sizes, no compression)
+const STILL_IMAGE_FRAME_LEN: usize = 6;
+/// Minimum length of an MJPEG format descriptor
+const MJPEG_FORMAT_LEN: usize = 11;
+/// Minimum length of an uncompressed format descriptor
+const UNCOMPRESSED_FORMAT_LEN: usize = 27;
+/// Minimum length of a frame-based format descriptor
+const FRAME_BASED_FORMAT_LEN: usize = 28;
+/// Length of a frame descriptor up to its first frame interval
+const FRAME_HEADER_LEN: usize = 26;
+
+/// Kind of format descriptor
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FormatKind {
+    /// `VS_FORMAT_MJPEG`
+    Mjpeg,
+    /// `VS_FORMAT_UNCOMPRESSED` (YUV or RGB, identified by GUID)
+    Uncompressed,
+    /// `VS_FORMAT_FRAME_BASED` (H.264 and other codecs, identified by GUID)
+    FrameBased,
+}
+
+/// Frame intervals a frame descriptor supports, in 100 ns units
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FrameIntervals {
+    /// A list of supported intervals (`bFrameIntervalType` > 0)
+    Discrete(Vec<u32>),
+    /// Any interval from `min` to `max` in steps of `step` (`bFrameIntervalType` = 0)
+    Continuous {
+        /// Shortest interval (highest frame rate)
+        min: u32,
+        /// Longest interval (lowest frame rate)
+        max: u32,
+        /// Granularity
+        step: u32,
+    },
+}
+
+impl FrameIntervals {
+    /// The supported interval closest to `target`
+    pub fn nearest(&self, target: u32) -> Option<u32> {
+        match self {
+            FrameIntervals::Discrete(intervals) => intervals
+                .iter()
+                .copied()
+                .min_by_key(|&interval| interval.abs_diff(target)),
+            FrameIntervals::Continuous { min, max, step } => {
+                let (min, max, step) = (
+                    u64::from(*min),
+                    u64::from(*max).max(u64::from(*min)),
+                    u64::from(*step),
+                );
+                let clamped = u64::from(target).clamp(min, max);
+                if step == 0 {
+                    return Some(clamped as u32);
+                }
+                // Round to the nearest step, staying within the range
+                let mut interval = min + (clamped - min + step / 2) / step * step;
+                if interval > max {
+                    interval -= step;
+                }
+                Some(interval as u32)
+            }
+        }
+    }
+
+    /// Shortest supported interval (highest frame rate)
+    pub fn shortest(&self) -> Option<u32> {
+        match self {
+            FrameIntervals::Discrete(intervals) => intervals.iter().copied().min(),
+            FrameIntervals::Continuous { min, .. } => Some(*min),
+        }
+    }
+
+    /// Intervals to offer for selection, shortest (fastest) first
+    ///
+    /// Discrete lists are returned as is. A continuous range is offered as its
+    /// ends plus the [`COMMON_FRAME_RATES`] it contains.
+    pub fn selectable(&self) -> Vec<u32> {
+        let mut intervals = match self {
+            FrameIntervals::Discrete(intervals) => intervals.clone(),
+            FrameIntervals::Continuous { min, max, .. } => {
+                let common = COMMON_FRAME_RATES
+                    .iter()
+                    .map(|&fps| fps_to_interval(fps))
+                    .filter(|interval| (*min..=*max).contains(interval));
+                [*min, *max]
+                    .into_iter()
+                    .chain(common)
+                    .filter_map(|interval| self.nearest(interval))
+                    .collect()
+            }
+        };
+        intervals.retain(|&interval| interval > 0);
+        intervals.sort_unstable();
+        intervals.dedup();
+        intervals
+    }
+}
+
+/// Frame rates offered for cameras with a continuous interval range
+pub const COMMON_FRAME_RATES: &[f32] = &[5.0, 10.0, 15.0, 20.0, 24.0, 25.0, 30.0, 50.0, 60.0];
+
+/// Frame rate of an interval in 100 ns units (0 for an unknown interval)
+pub fn interval_to_fps(interval: u32) -> f32 {
+    if interval == 0 {
+        return 0.0;
+    }
+    (10_000_000.0 / f64::from(interval)) as f32
+}
+
+/// Interval in 100 ns units of a frame rate
+pub fn fps_to_interval(fps: f32) -> u32 {
+    if !(fps.is_finite() && fps > 0.0) {
+        return 0;
+    }
+    (10_000_000.0 / f64::from(fps))
+        .round()
+        .min(f64::from(u32::MAX)) as u32
+}
+
+/// A `VS_FRAME_*` descriptor: one resolution of a format
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FrameDescriptor {
+    /// UVC frame index (1-based)
+    pub index: u8,
+    /// Frame width in pixels
+    pub width: u16,
+    /// Frame height in pixels
+    pub height: u16,
+    /// Lowest bit rate (bits per second)
+    pub min_bit_rate: u32,
+    /// Highest bit rate (bits per second)
+    pub max_bit_rate: u32,
+    /// Largest frame (`dwMaxVideoFrameBufferSize`; `None` for frame-based formats)
+    pub max_frame_size: Option<u32>,
+    /// Interval used when the host does not request one (100 ns units)
+    pub default_interval: u32,
+    /// Supported intervals
+    pub intervals: FrameIntervals,
+}
+
+impl FrameDescriptor {
+    /// Interval to request in the probe: the supported interval closest to the default
+    pub fn probe_interval(&self) -> u32 {
+        self.intervals
+            .nearest(self.default_interval)
+            .unwrap_or(self.default_interval)
+    }
+
+    /// Interval to request for a selected interval: the closest supported one,
+    /// or [`Self::probe_interval`] without a selection
+    pub fn interval_for(&self, selected: Option<u32>) -> u32 {
+        selected
+            .and_then(|interval| self.intervals.nearest(interval))
+            .unwrap_or_else(|| self.probe_interval())
+    }
+}
+
+/// A `VS_FORMAT_*` descriptor and the frames that follow it
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FormatDescriptor {
+    /// UVC format index (1-based)
+    pub index: u8,
+    /// Descriptor kind
+    pub kind: FormatKind,
+    /// Format GUID (uncompressed and frame-based formats)
+    pub guid: Option<[u8; 16]>,
+    /// Bits per pixel (uncompressed and frame-based formats)
+    pub bits_per_pixel: Option<u8>,
+    /// Frame used when the host does not select one
+    pub default_frame_index: u8,
+    /// Frames (resolutions), in descriptor order
+    pub frames: Vec<FrameDescriptor>,
+    /// Still image sizes, if the format has a `VS_STILL_IMAGE_FRAME` descriptor
+    pub still_frame: Option<StillImageFrame>,
+}
+
+/// A `VS_STILL_IMAGE_FRAME` descriptor: the still sizes of a format
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StillImageFrame {
+    /// Bulk endpoint stills are sent on (still capture method 3), 0 otherwise
+    pub endpoint: u8,
+    /// Still sizes (`wWidth`, `wHeight`), in descriptor order (1-based index)
+    pub sizes: Vec<(u16, u16)>,
+    /// Compression ratios of compressed stills, in descriptor order
+    pub compressions: Vec<u8>,
+}
+
+impl FormatDescriptor {
+    /// FourCC of the format GUID (e.g. `"YUY2"`), if it is a FourCC-based GUID
+    pub fn fourcc(&self) -> Option<String> {
+        let guid = self.guid?;
+        let fourcc = &guid[..4];
+        (guid[4..] == FOURCC_GUID_SUFFIX && fourcc.iter().all(|b| b.is_ascii_graphic()))
+            .then(|| String::from_utf8_lossy(fourcc).into_owned())
+    }
+
+    /// Human-readable format name (e.g. `"MJPEG"`, `"YUY2"`, `"RGB24"`)
+    pub fn name(&self) -> String {
+        match self.kind {
+            FormatKind::Mjpeg => "MJPEG".to_string(),
+            _ if self.guid == Some(RGB24_GUID) => "RGB24".to_string(),
+            _ if self.guid == Some(BGR24_GUID) => "BGR24".to_string(),
+            _ => self.fourcc().unwrap_or_else(|| "UNKNOWN".to_string()),
+        }
+    }
+
+    /// Pixel format registered for the format GUID, if the pipeline can
+    /// convert it
+    pub fn pixel_format(&self) -> Option<PixelFormat> {
+        if self.kind != FormatKind::Uncompressed {
+            return None;
+        }
+        format_registry::by_guid(&self.guid?).map(|entry| entry.format)
+    }
+
+    /// Whether the format carries RGB rather than YUV pixels
+    pub fn is_rgb(&self) -> bool {
+        matches!(self.guid, Some(RGB24_GUID) | Some(BGR24_GUID))
+    }
+
+    /// Frame with UVC frame index `index`
+    pub fn frame(&self, index: u8) -> Option<&FrameDescriptor> {
+        self.frames.iter().find(|f| f.index == index)
+    }
+
+    /// The frame the camera uses by default, or the first one
+    pub fn default_frame(&self) -> Option<&FrameDescriptor> {
+        self.frame(self.default_frame_index)
+            .or_else(|| self.frames.first())
+    }
+
+    /// Size of one uncompressed frame in bytes (`None` for compressed formats)
+    pub fn uncompressed_frame_size(&self, frame: &FrameDescriptor) -> Option<u32> {
+        if self.kind != FormatKind::Uncompressed {
+            return None;
+        }
+        let bits = u32::from(self.bits_per_pixel?);
+        Some(u32::from(frame.width) * u32::from(frame.height) * bits / 8)
+    }
+}
+
+/// Every format advertised by a video streaming interface
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FormatCatalog {
+    /// Formats, in descriptor order
+    pub formats: Vec<FormatDescriptor>,
+    /// `bStillCaptureMethod` of the input header (0 = no still capture)
+    pub still_capture_method: u8,
+}
+
+impl FormatCatalog {
+    /// Parse the class-specific descriptors of a video streaming interface
+    ///
+    /// Frame and still image frame descriptors are attached to the preceding
+    /// format descriptor (frames only if it has the matching kind); anything
+    /// else is ignored.
+    pub fn parse(extra: &[u8]) -> Self {
+        let mut formats: Vec<FormatDescriptor> = Vec::new();
+        let mut still_capture_method = 0;
+
+        for desc in Descriptors(extra) {
+            if desc.len() < 3 || desc[1] != CS_INTERFACE {
+                continue;
+            }
+            match desc[2] {
+                VS_INPUT_HEADER if desc.len() >= 4 => {
+                    log::info!("VS Input Header: {} format(s) available", desc[3]);
+                    if desc.len() >= INPUT_HEADER_STILL_LEN {
+                        still_capture_method = desc[9];
+                    }
+                }
+                VS_STILL_IMAGE_FRAME => {
+                    let Some(format) = formats.last_mut() else {
+                        log::debug!("Still image frame descriptor without a format, skipped");
+                        continue;
+                    };
+                    match parse_still_frame(desc) {
+                        Some(still) => format.still_frame = Some(still),
+                        None => log::debug!("Truncated still image frame descriptor, skipped"),
+                    }
+                }
+                VS_FORMAT_MJPEG if desc.len() >= MJPEG_FORMAT_LEN => {
+                    formats.push(FormatDescriptor {
+                        index: desc[3],
+                        kind: FormatKind::Mjpeg,
+                        guid: None,
+                        bits_per_pixel: None,
+                        default_frame_index: desc[6],
+                        frames: Vec::new(),
+                        still_frame: None,
+                    });
+                }
+                VS_FORMAT_UNCOMPRESSED if desc.len() >= UNCOMPRESSED_FORMAT_LEN => {
+                    formats.push(guid_format(desc, FormatKind::Uncompressed));
+                }
+                VS_FORMAT_FRAME_BASED if desc.len() >= FRAME_BASED_FORMAT_LEN => {
+                    formats.push(guid_format(desc, FormatKind::FrameBased));
+                }
+                subtype @ (VS_FRAME_UNCOMPRESSED | VS_FRAME_MJPEG | VS_FRAME_FRAME_BASED) => {
+                    let kind = match subtype {
+                        VS_FRAME_MJPEG => FormatKind::Mjpeg,
+                        VS_FRAME_UNCOMPRESSED => FormatKind::Uncompressed,
+                        _ => FormatKind::FrameBased,
+                    };
+                    let Some(format) = formats.last_mut().filter(|f| f.kind == kind) else {
+                        log::debug!("Frame descriptor without a matching format, skipped");
+                        continue;
+                    };
+                    match parse_frame(desc, kind) {
+                        Some(frame) => format.frames.push(frame),
+                        None => log::debug!("Truncated frame descriptor, skipped"),
+                    }
+                }
+                subtype => {
+                    log::debug!(
+                        "UVC VS descriptor subtype {:02x}, len={}",
+                        subtype,
+                        desc.len()
+                    );
+                }
+            }
+        }
+
+        let catalog = Self {
+            formats,
+            still_capture_method,
+        };
+        for format in &catalog.formats {
+            log::info!(
+                "Found {} format: index={}, frames={}, default frame={}",
+                format.name(),
+                format.index,
+                format.frames.len(),
+                format.default_frame_index
+            );
+            for frame in &format.frames {
+                log::info!(
+                    "  Frame {}: {}x{} default_interval={} intervals={:?}",
+                    frame.index,
+                    frame.width,
+                    frame.height,
+                    frame.default_interval,
+                    frame.intervals
+                );
+            }
+        }
+        catalog
+    }
+
+    /// Parse the formats of streaming interface `interface_number` from all of
+    /// a device's descriptors (Android's `UsbDeviceConnection.getRawDescriptors`)
+    ///
+    /// Only the descriptors following that interface's interface descriptors
+    /// are used, since the video control interface reuses the same subtypes.
+    pub fn parse_raw(raw: &[u8], interface_number: u8) -> Self {
+        let mut extra = Vec::new();
+        let mut in_interface = false;
+        for desc in Descriptors(raw) {
+            if desc[1] == INTERFACE_DESCRIPTOR && desc.len() >= 3 {
+                in_interface = desc[2] == interface_number;
+            } else if in_interface {
+                extra.extend_from_slice(desc);
+            }
+        }
+        Self::parse(&extra)
+    }
+
+    /// Whether no formats were found
+    pub fn is_empty(&self) -> bool {
+        self.formats.is_empty()
+    }
+
+    /// Format with UVC format index `index`
+    pub fn format(&self, index: u8) -> Option<&FormatDescriptor> {
+        self.formats.iter().find(|f| f.index == index)
+    }
+
+    /// Frame `frame_index` of format `format_index`
+    pub fn frame(&self, format_index: u8, frame_index: u8) -> Option<&FrameDescriptor> {
+        self.format(format_index)?.frame(frame_index)
+    }
+
+    /// Formats of the given kind, in descriptor order
+    pub fn formats_of_kind(&self, kind: FormatKind) -> impl Iterator<Item = &FormatDescriptor> {
+        self.formats.iter().filter(move |f| f.kind == kind)
+    }
+
+    /// Formats ordered by `preference`, a list of format names (see
+    /// [`FormatDescriptor::name`], case-insensitive)
+    ///
+    /// Formats named earlier come first; formats not named follow in
+    /// descriptor order.
+    pub fn by_preference(&self, preference: &[String]) -> Vec<&FormatDescriptor> {
+        let mut formats: Vec<_> = self.formats.iter().collect();
+        formats.sort_by_key(|f| preference_rank(f, preference).unwrap_or(preference.len()));
+        formats
+    }
+
+    /// Streamable format named earliest in `preference`, if the camera has any
+    ///
+    /// Frame-based formats (H.264 etc.) are skipped, since they can't be
+    /// decoded.
+    pub fn preferred_format(&self, preference: &[String]) -> Option<&FormatDescriptor> {
+        self.formats
+            .iter()
+            .filter(|f| f.kind != FormatKind::FrameBased)
+            .filter_map(|f| Some((preference_rank(f, preference)?, f)))
+            .min_by_key(|&(rank, _)| rank)
+            .map(|(_, f)| f)
+    }
+}
+
+/// Position of the format's name in `preference`
+fn preference_rank(format: &FormatDescriptor, preference: &[String]) -> Option<usize> {
+    let name = format.name();
+    preference
+        .iter()
+        .position(|p| p.trim().eq_ignore_ascii_case(&name))
+}
+
+/// Iterator over the length-prefixed descriptors in a byte buffer
+struct Descriptors<'a>(&'a [u8]);
+
+impl<'a> Iterator for Descriptors<'a> {
+    type Item = &'a [u8];
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let len = usize::from(*self.0.first()?);
+        if len < 2 || len > self.0.len() {
+            return None;
+        }
+        let (desc, rest) = self.0.split_at(len);
+        self.0 = rest;
+        Some(desc)
+    }
+}
+
+fn u16_at(desc: &[u8], offset: usize) -> u16 {
+    u16::from_le_bytes([desc[offset], desc[offset + 1]])
+}
+
+fn u32_at(desc: &[u8], offset: usize) -> u32 {
+    u32::from_le_bytes([
+        desc[offset],
+        desc[offset + 1],
+        desc[offset + 2],
+        desc[offset + 3],
+    ])
+}
+
+/// Parse an uncompressed or frame-based format descriptor (GUID at offset 5)
+fn guid_format(desc: &[u8], kind: FormatKind) -> FormatDescriptor {
+    let mut guid = [0u8; 16];
+    guid.copy_from_slice(&desc[5..21]);
+    FormatDescriptor {
+        index: desc[3],
+        kind,
+        guid: Some(guid),
+        bits_per_pixel: Some(desc[21]),
+        default_frame_index: desc[22],
+        frames: Vec::new(),
+        still_frame: None,
+    }
+}
+
+/// Parse a still image frame descriptor
+///
+/// `bNumImageSizePatterns` (`wWidth`, `wHeight`) pairs follow the endpoint
+/// address, then `bNumCompressionPattern` compression ratios.
+fn parse_still_frame(desc: &[u8]) -> Option<StillImageFrame> {
+    if desc.len() < STILL_IMAGE_FRAME_LEN {
+        return None;
+    }
+    let size_count = usize::from(desc[4]);
+    let compression_at = 5 + size_count * 4;
+    let compression_count = usize::from(*desc.get(compression_at)?);
+    let compressions = desc.get(compression_at + 1..compression_at + 1 + compression_count)?;
+    Some(StillImageFrame {
+        endpoint: desc[3],
+        sizes: (0..size_count)
+            .map(|i| (u16_at(desc, 5 + i * 4), u16_at(desc, 7 + i * 4)))
+            .collect(),
+        compressions: compressions.to_vec(),
+    })
+}
+
+/// Parse a frame descriptor
+///
+/// MJPEG and uncompressed frames carry `dwMaxVideoFrameBufferSize` at offset
+/// 17; frame-based frames have the default interval there and
+/// `dwBytesPerLine` after the interval type, so both end their fixed part at
+/// offset 26.
+fn parse_frame(desc: &[u8], kind: FormatKind) -> Option<FrameDescriptor> {
+    if desc.len() < FRAME_HEADER_LEN {
+        return None;
+    }
+    let (max_frame_size, default_interval, interval_type) = if kind == FormatKind::FrameBased {
+        (None, u32_at(desc, 17), desc[21])
+    } else {
+        (Some(u32_at(desc, 17)), u32_at(desc, 21), desc[25])
+    };
+
+    let intervals = if interval_type == 0 {
+        if desc.len() < FRAME_HEADER_LEN + 12 {
+            return None;
+        }
+        FrameIntervals::Continuous {
+            min: u32_at(desc, FRAME_HEADER_LEN),
+            max: u32_at(desc, FRAME_HEADER_LEN + 4),
+            step: u32_at(desc, FRAME_HEADER_LEN + 8),
+        }
+    } else {
+        let count = usize::from(interval_type);
+        if desc.len() < FRAME_HEADER_LEN + count * 4 {
+            return None;
+        }
+        FrameIntervals::Discrete(
+            (0..count)
+                .map(|i| u32_at(desc, FRAME_HEADER_LEN + i * 4))
+                .collect(),
+        )
+    };
+
+    Some(FrameDescriptor {
+        index: desc[3],
+        width: u16_at(desc, 5),
+        height: u16_at(desc, 7),
+        min_bit_rate: u32_at(desc, 9),
+        max_bit_rate: u32_at(desc, 13),
+        max_frame_size,
+        default_interval,
+        intervals,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 30 fps frame interval in 100 ns units
+    const FPS_30: u32 = 333_333;
+    /// 15 fps frame interval in 100 ns units
+    const FPS_15: u32 = 666_666;
+
+    fn mjpeg_format(index: u8, frames: u8, default_frame: u8) -> Vec<u8> {
+        vec![
+            11,
+            CS_INTERFACE,
+            VS_FORMAT_MJPEG,
+            index,
+            frames,
+            0,
+            default_frame,
+            0,
+            0,
+            0,
+            0,
+        ]
+    }
+
+    fn uncompressed_format(index: u8, frames: u8, fourcc: [u8; 4]) -> Vec<u8> {
+        let mut desc = vec![27, CS_INTERFACE, VS_FORMAT_UNCOMPRESSED, index, frames];
+        desc.extend_from_slice(&fourcc_guid(fourcc));
+        desc.extend_from_slice(&[16, 1, 0, 0, 0, 0]);
+        desc
+    }
+
+    fn frame(subtype: u8, index: u8, width: u16, height: u16, intervals: &[u32]) -> Vec<u8> {
+        let mut desc = vec![0, CS_INTERFACE, subtype, index, 0];
+        desc.extend_from_slice(&width.to_le_bytes());
+        desc.extend_from_slice(&height.to_le_bytes());
+        desc.extend_from_slice(&1_000_000u32.to_le_bytes());
+        desc.extend_from_slice(&2_000_000u32.to_le_bytes());
+        let size = u32::from(width) * u32::from(height) * 2;
+        desc.extend_from_slice(&size.to_le_bytes());
+        desc.extend_from_slice(&intervals[0].to_le_bytes());
+        desc.push(intervals.len() as u8);
+        for interval in intervals {
+            desc.extend_from_slice(&interval.to_le_bytes());
+        }
+        desc[0] = desc.len() as u8;
+        desc
+    }
+
+    fn sample_descriptors() -> Vec<u8> {
+        let mut extra = vec![
+            14,
+            CS_INTERFACE,
+            VS_INPUT_HEADER,
+            2,
+            0,
+            0,
+            0,
+            0,
+            0,
+            0,
+            0,
+            0,
+            0,
+            0,
+        ];
+        extra.extend(uncompressed_format(1, 2, *b"YUY2"));
+        extra.extend(frame(VS_FRAME_UNCOMPRESSED, 1, 640, 480, &[FPS_30, FPS_15]));
+        extra.extend(frame(VS_FRAME_UNCOMPRESSED, 2, 1280, 720, &[FPS_15]));
+        extra.extend(mjpeg_format(2, 1, 1));
+        extra.extend(frame(VS_FRAME_MJPEG, 1, 1920, 1080, &[FPS_30]));
+        // Color matching descriptor, ignored
+        extra.extend([6, CS_INTERFACE, 0x0D, 1, 1, 4]);
+        extra
+    }
+
+    #[test]
+    fn test_parse_catalog() {
+        let catalog = FormatCatalog::parse(&sample_descriptors());
+        assert_eq!(catalog.formats.len(), 2);
+
+        let yuy2 = catalog.format(1).unwrap();
+        assert_eq!(yuy2.kind, FormatKind::Uncompressed);
+        assert_eq!(yuy2.name(), "YUY2");
+        assert_eq!(yuy2.pixel_format(), Some(PixelFormat::Yuyv));
+        assert_eq!(yuy2.bits_per_pixel, Some(16));
+        assert_eq!(yuy2.frames.len(), 2);
+        let hd = yuy2.frame(2).unwrap();
+        assert_eq!((hd.width, hd.height), (1280, 720));
+        assert_eq!(hd.max_frame_size, Some(1280 * 720 * 2));
+        assert_eq!(hd.intervals, FrameIntervals::Discrete(vec![FPS_15]));
+        assert_eq!(yuy2.uncompressed_frame_size(hd), Some(1280 * 720 * 2));
+
+        let mjpeg = catalog.format(2).unwrap();
+        assert_eq!(mjpeg.name(), "MJPEG");
+        assert_eq!(mjpeg.pixel_format(), None);
+        assert_eq!(mjpeg.default_frame().unwrap().width, 1920);
+        assert_eq!(mjpeg.uncompressed_frame_size(&mjpeg.frames[0]), None);
+        assert_eq!(catalog.formats_of_kind(FormatKind::Mjpeg).count(), 1);
+    }
+
+    #[test]
+    fn test_parse_raw_uses_only_the_streaming_interface() {
+        let interface = |number: u8, alt: u8, subclass: u8| {
+            vec![
+                9,
+                INTERFACE_DESCRIPTOR,
+                number,
+                alt,
+                1,
+                0x0E,
+                subclass,
+                0,
+                0,
+            ]
+        };
+        // Device and configuration descriptors, truncated to their headers
+        let mut raw = vec![18, 0x01];
+        raw.extend([0; 16]);
+        raw.extend([9, 0x02, 0, 0, 2, 1, 0, 0x80, 250]);
+        // Video control interface: its processing unit has subtype 0x05 like
+        // VS_FRAME_UNCOMPRESSED, and an extension unit 0x06 like VS_FORMAT_MJPEG
+        raw.extend(interface(0, 0, 0x01));
+        raw.extend([13, CS_INTERFACE, 0x01, 0, 1, 0, 0, 0, 0, 0, 0, 1, 1]);
+        raw.extend([11, CS_INTERFACE, 0x05, 2, 1, 0, 0, 2, 0, 0, 0]);
+        raw.extend(mjpeg_format(9, 1, 1));
+        // Streaming interface: alternate setting 0 with the formats, then an
+        // alternate setting with an endpoint
+        raw.extend(interface(1, 0, 0x02));
+        raw.extend(sample_descriptors());
+        raw.extend(interface(1, 1, 0x02));
+        raw.extend([7, 0x05, 0x81, 0x05, 0x00, 0x0C, 1]);
+
+        let catalog = FormatCatalog::parse_raw(&raw, 1);
+        assert_eq!(catalog, FormatCatalog::parse(&sample_descriptors()));
+        assert!(FormatCatalog::parse_raw(&raw, 2).is_empty());
+    }
+
+    #[test]
+    fn test_format_preference() {
+        let catalog = FormatCatalog::parse(&sample_descriptors());
+        let names = |formats: Vec<&FormatDescriptor>| -> Vec<String> {
+            formats.iter().map(|f| f.name()).collect()
+        };
+        let preference =
+            |list: &[&str]| -> Vec<String> { list.iter().map(|n| n.to_string()).collect() };
+
+        // Without a preference, descriptor order (format 1 first)
+        assert_eq!(names(catalog.by_preference(&[])), ["YUY2", "MJPEG"]);
+        assert_eq!(catalog.preferred_format(&[]), None);
+
+        let mjpeg_first = preference(&["mjpeg", "YUY2", "NV12"]);
+        assert_eq!(
+            names(catalog.by_preference(&mjpeg_first)),
+            ["MJPEG", "YUY2"]
+        );
+        assert_eq!(catalog.preferred_format(&mjpeg_first).unwrap().index, 2);
+
+        // Names the camera doesn't offer are skipped
+        let nv12_first = preference(&["NV12", "YUY2"]);
+        assert_eq!(catalog.preferred_format(&nv12_first).unwrap().index, 1);
+        assert_eq!(names(catalog.by_preference(&nv12_first)), ["YUY2", "MJPEG"]);
+        assert_eq!(catalog.preferred_format(&preference(&["H264"])), None);
+    }
+
+    #[test]
+    fn test_continuous_intervals() {
+        let mut extra = mjpeg_format(1, 1, 1);
+        let mut desc = frame(VS_FRAME_MJPEG, 1, 640, 480, &[FPS_30]);
+        // Switch to a continuous range: min 30 fps, max 5 fps, 1 fps-ish steps
+        desc.truncate(FRAME_HEADER_LEN);
+        desc[25] = 0;
+        for value in [FPS_30, 2_000_000u32, 100_000] {
+            desc.extend_from_slice(&value.to_le_bytes());
+        }
+        desc[0] = desc.len() as u8;
+        extra.extend(desc);
+
+        let catalog = FormatCatalog::parse(&extra);
+        let frame = catalog.frame(1, 1).unwrap();
+        assert_eq!(
+            frame.intervals,
+            FrameIntervals::Continuous {
+                min: FPS_30,
+                max: 2_000_000,
+                step: 100_000
+            }
+        );
+        assert_eq!(frame.intervals.nearest(FPS_15), Some(633_333));
+        assert_eq!(frame.intervals.nearest(0), Some(FPS_30));
+        assert_eq!(frame.intervals.nearest(u32::MAX), Some(1_933_333));
+        assert_eq!(frame.intervals.shortest(), Some(FPS_30));
+        assert_eq!(
+            frame.intervals.selectable(),
+            [333_333, 433_333, 533_333, 633_333, 1_033_333, 1_933_333]
+        );
+    }
+
+    #[test]
+    fn test_probe_interval_prefers_supported() {
+        let frame = FrameDescriptor {
+            index: 1,
+            width: 640,
+            height: 480,
+            min_bit_rate: 0,
+            max_bit_rate: 0,
+            max_frame_size: None,
+            default_interval: 400_000,
+            intervals: FrameIntervals::Discrete(vec![FPS_30, FPS_15]),
+        };
+        assert_eq!(frame.probe_interval(), FPS_30);
+        assert_eq!(frame.interval_for(None), FPS_30);
+        assert_eq!(frame.interval_for(Some(fps_to_interval(14.0))), FPS_15);
+        assert_eq!(frame.intervals.selectable(), [FPS_30, FPS_15]);
+    }
+
+    #[test]
+    fn test_fps_interval_conversion() {
+        assert_eq!(fps_to_interval(30.0), FPS_30);
+        assert_eq!(fps_to_interval(0.0), 0);
+        assert!((interval_to_fps(FPS_15) - 15.0).abs() < 0.01);
+        assert_eq!(interval_to_fps(0), 0.0);
+    }
+
+    #[test]
+    fn test_rgb_guid_names() {
+        let mut extra = uncompressed_format(1, 0, *b"YUY2");
+        extra[5..21].copy_from_slice(&BGR24_GUID);
+        let catalog = FormatCatalog::parse(&extra);
+        let format = catalog.format(1).unwrap();
+        assert_eq!(format.name(), "BGR24");
+        assert!(format.is_rgb());
+        assert_eq!(format.pixel_format(), Some(PixelFormat::Bgr888));
+        assert_eq!(format.fourcc(), None);
+    }
+
+    #[test]
+    fn test_bayer_guid() {
+        let mut extra = uncompressed_format(1, 0, *b"RGGB");
+        extra[21] = 8; // bBitsPerPixel
+        let catalog = FormatCatalog::parse(&extra);
+        let format = catalog.format(1).unwrap();
+        assert_eq!(format.name(), "RGGB");
+        assert!(!format.is_rgb());
+        assert_eq!(format.pixel_format(), Some(PixelFormat::BayerRggb));
+        assert_eq!(format.bits_per_pixel, Some(8));
+    }
+
+    #[test]
+    fn test_still_image_frames() {
+        let mut extra = sample_descriptors();
+        // bStillCaptureMethod
+        extra[9] = 2;
+        // Still sizes of the MJPEG format: 2592x1944 and 1920x1080, one compression
+        let mut still = vec![0, CS_INTERFACE, VS_STILL_IMAGE_FRAME, 0, 2];
+        for (width, height) in [(2592u16, 1944u16), (1920, 1080)] {
+            still.extend_from_slice(&width.to_le_bytes());
+            still.extend_from_slice(&height.to_le_bytes());
+        }
+        still.extend([1, 5]);
+        still[0] = still.len() as u8;
+        extra.extend(still);
+
+        let catalog = FormatCatalog::parse(&extra);
+        assert_eq!(catalog.still_capture_method, 2);
+        assert_eq!(catalog.format(1).unwrap().still_frame, None);
+        let still = catalog.format(2).unwrap().still_frame.as_ref().unwrap();
+        assert_eq!(still.endpoint, 0);
+        assert_eq!(still.sizes, vec![(2592, 1944), (1920, 1080)]);
+        assert_eq!(still.compressions, vec![5]);
+
+        // Sizes running past the descriptor are not trusted
+        assert_eq!(
+            parse_still_frame(&[6, CS_INTERFACE, VS_STILL_IMAGE_FRAME, 0, 2, 0]),
+            None
+        );
+    }
+
+    #[test]
+    fn test_frame_based_format() {
+        let mut extra = vec![28, CS_INTERFACE, VS_FORMAT_FRAME_BASED, 1, 1];
+        extra.extend_from_slice(&fourcc_guid(*b"H264"));
+        extra.extend_from_slice(&[16, 1, 0, 0, 0, 0, 1]);
+        let mut desc = vec![0, CS_INTERFACE, VS_FRAME_FRAME_BASED, 1, 0];
+        desc.extend_from_slice(&1920u16.to_le_bytes());
+        desc.extend_from_slice(&1080u16.to_le_bytes());
+        desc.extend_from_slice(&[0; 8]); // bit rates
+        desc.extend_from_slice(&FPS_30.to_le_bytes());
+        desc.push(1);
+        desc.extend_from_slice(&0u32.to_le_bytes()); // dwBytesPerLine
+        desc.extend_from_slice(&FPS_30.to_le_bytes());
+        desc[0] = desc.len() as u8;
+        extra.extend(desc);
+
+        let catalog = FormatCatalog::parse(&extra);
+        let format = catalog.format(1).unwrap();
+        assert_eq!(format.name(), "H264");
+        assert_eq!(format.pixel_format(), None);
+        let frame = format.frame(1).unwrap();
+        assert_eq!(frame.max_frame_size, None);
+        assert_eq!(frame.default_interval, FPS_30);
+        assert_eq!(frame.intervals, FrameIntervals::Discrete(vec![FPS_30]));
+    }
+
+    #[test]
+    fn test_malformed_descriptors_are_skipped() {
+        let mut extra = mjpeg_format(1, 2, 1);
+        let mut truncated = frame(VS_FRAME_MJPEG, 1, 640, 480, &[FPS_30, FPS_15]);
+        truncated[25] = 5; // claims more intervals than it has
+        extra.extend(truncated);
+        // Uncompressed frame after an MJPEG format
+        extra.extend(frame(VS_FRAME_UNCOMPRESSED, 2, 320, 240, &[FPS_30]));
+        extra.extend(frame(VS_FRAME_MJPEG, 3, 800, 600, &[FPS_30]));
+        // Length running past the end stops parsing
+        extra.extend([40, CS_INTERFACE, VS_FORMAT_MJPEG]);
+
+        let catalog = FormatCatalog::parse(&extra);
+        let format = catalog.format(1).unwrap();
+        assert_eq!(format.frames.len(), 1);
+        assert_eq!(format.frames[0].index, 3);
+        assert!(FormatCatalog::parse(&[]).is_empty());
+    }
+}
@@ -0,0 +1,537 @@
+//! Incremental MJPEG AVI writer for video recordings
+//!
+//! Muxes frame-buffer frames into an AVI file that plays in any desktop
+//! player, so an inspection can be documented without further tooling. JPEG
+//! frames from MJPEG cameras are stored unchanged; RGB frames (YUY2 cameras)
+//! are encoded to JPEG first, which needs the `jpeg` feature.
+//!
+//! AVI streams have a constant frame rate, so frames are placed on a fixed
+//! time base (`fps`) by their timestamp relative to the first frame. Gaps are
+//! filled with empty chunks, which players show as a repeat of the previous
+//! frame, and frames arriving faster than the time base are dropped. Exact
+//! per-frame timestamps stay available in the recording's `index.json`.
+//!
+//! # Layout
+//!
+//! ```text
+//! RIFF <size> AVI
+//!   LIST hdrl
+//!     avih  main header (frame period, frame count, dimensions)
+//!     LIST strl
+//!       strh  video stream header (MJPG, scale 1 / rate fps, length)
+//!       strf  BITMAPINFOHEADER
+//!   LIST movi
+//!     00dc  JPEG frame (or empty: repeat previous frame)
+//!     ...
+//!   idx1  one entry per 00dc chunk
+//! ```
+//!
+//! Frames are written as they arrive; the headers are rewritten and the index
+//! appended when the file is finished.
+//!
+//! [`VideoWriter`] picks between this and the Matroska writer
+//! ([`crate::mkv_writer`]), which keeps exact timestamps and embeds chapters.
+
+use serde::{Deserialize, Serialize};
+use std::io::{Seek, SeekFrom, Write};
+use thiserror::Error;
+
+use crate::chapters::Chapter;
+use crate::image_encoder::{encode_jpeg, EncodeError, DEFAULT_JPEG_QUALITY};
+use crate::mkv_writer::MkvWriter;
+
+/// Default time base for recorded video (frames per second)
+pub const DEFAULT_VIDEO_FPS: u32 = 30;
+
+/// Size of everything before the first `movi` chunk
+const HEADER_LEN: usize = 224;
+
+/// `avih` flag: the file has an `idx1` index
+const AVIF_HASINDEX: u32 = 0x10;
+
+/// `idx1` flag: the chunk is a key frame (every JPEG frame is)
+const AVIIF_KEYFRAME: u32 = 0x10;
+
+/// Chunk id of a compressed video frame in stream 0
+const FRAME_CHUNK: &[u8; 4] = b"00dc";
+
+/// Errors that can occur while writing a video
+#[derive(Error, Debug)]
+pub enum VideoError {
+    /// The frame cannot be added to this video
+    #[error("Invalid frame: {0}")]
+    InvalidFrame(String),
+
+    /// The video exceeds what the container can hold
+    #[error("Video too large: {0}")]
+    TooLarge(String),
+
+    /// Converting the frame to JPEG failed
+    #[error("Encode error: {0}")]
+    Encode(#[from] EncodeError),
+
+    /// I/O error
+    #[error("I/O error: {0}")]
+    Io(#[from] std::io::Error),
+}
+
+/// Result type for video operations
+pub type Result<T> = std::result::Result<T, VideoError>;
+
+/// Container for recorded video
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum VideoContainer {
+    /// Motion JPEG in AVI
+    #[default]
+    Avi,
+    /// Motion JPEG in Matroska, with bookmarks as embedded chapters
+    Mkv,
+}
+
+impl VideoContainer {
+    /// File name of the video in the recording directory
+    pub fn file_name(self) -> &'static str {
+        match self {
+            VideoContainer::Avi => "video.avi",
+            VideoContainer::Mkv => "video.mkv",
+        }
+    }
+}
+
+/// Position of a `00dc` chunk for `idx1`
+struct IndexEntry {
+    /// Offset of the chunk header from the `movi` tag
+    offset: u32,
+    /// Payload size (0 for a repeated frame)
+    size: u32,
+}
+
+/// Writes an MJPEG AVI file frame by frame
+pub struct AviWriter<W: Write + Seek> {
+    /// Output file
+    out: W,
+    /// Time base in frames per second
+    fps: u32,
+    /// Stream position of the `RIFF` tag
+    start: u64,
+    /// Frame size, set by the first frame
+    size: Option<(u32, u32)>,
+    /// Timestamp of the first frame (microseconds)
+    first_timestamp_us: u64,
+    /// Bytes written after the `movi` tag
+    movi_len: u64,
+    /// One entry per frame slot, including repeated frames
+    index: Vec<IndexEntry>,
+    /// Largest frame written
+    max_frame_size: u32,
+    /// JPEG quality for RGB frames
+    quality: u8,
+}
+
+impl<W: Write + Seek> AviWriter<W> {
+    /// Writer with a time base of `fps` frames per second
+    ///
+    /// Nothing is written until the first frame is added.
+    pub fn new(out: W, fps: u32) -> Self {
+        Self {
+            out,
+            fps: fps.max(1),
+            start: 0,
+            size: None,
+            first_timestamp_us: 0,
+            movi_len: 0,
+            index: Vec::new(),
+            max_frame_size: 0,
+            quality: DEFAULT_JPEG_QUALITY,
+        }
+    }
+
+    /// JPEG quality (1-100) for the RGB frames added from now on
+    pub fn set_quality(&mut self, quality: u8) {
+        self.quality = quality.clamp(1, 100);
+    }
+
+    /// Frame slots written so far, including repeated frames
+    pub fn frame_count(&self) -> u32 {
+        self.index.len() as u32
+    }
+
+    /// Encode a frame-buffer frame (JPEG or RGB24) and add it
+    ///
+    /// RGB frames are encoded at the quality set with
+    /// [`AviWriter::set_quality`]; JPEG frames are added unchanged.
+    ///
+    /// Returns whether the frame was written (see [`AviWriter::add_frame`]).
+    ///
+    /// # Errors
+    ///
+    /// Returns the encoding error (`EncodeError::Unsupported` for RGB frames
+    /// without the `jpeg` feature), or any error from [`AviWriter::add_frame`].
+    pub fn push_frame(
+        &mut self,
+        frame: &[u8],
+        width: u32,
+        height: u32,
+        timestamp_us: u64,
+    ) -> Result<bool> {
+        let jpeg = encode_jpeg(frame, width, height, self.quality)?;
+        self.add_frame(&jpeg, width, height, timestamp_us)
+    }
+
+    /// Add a JPEG image captured at `timestamp_us`
+    ///
+    /// The first frame sets the video size; later frames must match it.
+    /// Returns `false` if the frame was dropped because its time slot is
+    /// already taken.
+    ///
+    /// # Errors
+    ///
+    /// Returns `VideoError::InvalidFrame` if the size differs from the first
+    /// frame, `VideoError::TooLarge` if the file would exceed 4 GiB, or
+    /// `VideoError::Io` if writing fails.
+    pub fn add_frame(
+        &mut self,
+        jpeg: &[u8],
+        width: u32,
+        height: u32,
+        timestamp_us: u64,
+    ) -> Result<bool> {
+        match self.size {
+            Some(size) if size != (width, height) => {
+                return Err(VideoError::InvalidFrame(format!(
+                    "{}x{} frame in a {}x{} video",
+                    width, height, size.0, size.1
+                )));
+            }
+            Some(_) => {}
+            None => {
+                self.start = self.out.stream_position()?;
+                self.out.write_all(&[0; HEADER_LEN])?;
+                self.size = Some((width, height));
+                self.first_timestamp_us = timestamp_us;
+            }
+        }
+
+        let elapsed_us = timestamp_us.saturating_sub(self.first_timestamp_us);
+        let slot = ((elapsed_us * u64::from(self.fps) + 500_000) / 1_000_000) as usize;
+        if slot < self.index.len() {
+            return Ok(false);
+        }
+
+        let size = u32::try_from(jpeg.len())
+            .map_err(|_| VideoError::TooLarge("frame exceeds 4 GiB".to_string()))?;
+        let repeats = slot - self.index.len();
+        let needed = (repeats as u64) * (8 + 16) + 8 + u64::from(size) + 1 + 16;
+        if HEADER_LEN as u64 + self.movi_len + (self.index.len() as u64) * 16 + needed
+            > u64::from(u32::MAX)
+        {
+            return Err(VideoError::TooLarge("video exceeds 4 GiB".to_string()));
+        }
+
+        for _ in 0..repeats {
+            self.write_chunk(&[])?;
+        }
+        self.write_chunk(jpeg)?;
+        self.max_frame_size = self.max_frame_size.max(size);
+        Ok(true)
+    }
+
+    /// Append the index, rewrite the headers and return the output
+    ///
+    /// # Errors
+    ///
+    /// Returns `VideoError::InvalidFrame` if no frames were added, or
+    /// `VideoError::Io` if writing fails.
+    pub fn finish(mut self) -> Result<W> {
+        let Some((width, height)) = self.size else {
+            return Err(VideoError::InvalidFrame("video has no frames".to_string()));
+        };
+
+        let mut idx1 = Vec::with_capacity(8 + self.index.len() * 16);
+        idx1.extend_from_slice(b"idx1");
+        idx1.extend_from_slice(&((self.index.len() * 16) as u32).to_le_bytes());
+        for entry in &self.index {
+            let flags = if entry.size > 0 { AVIIF_KEYFRAME } else { 0 };
+            idx1.extend_from_slice(FRAME_CHUNK);
+            idx1.extend_from_slice(&flags.to_le_bytes());
+            idx1.extend_from_slice(&entry.offset.to_le_bytes());
+            idx1.extend_from_slice(&entry.size.to_le_bytes());
+        }
+        self.out.write_all(&idx1)?;
+
+        let end = self.start + HEADER_LEN as u64 + self.movi_len + idx1.len() as u64;
+        let header = self.header(width, height, end - self.start);
+        self.out.seek(SeekFrom::Start(self.start))?;
+        self.out.write_all(&header)?;
+        self.out.seek(SeekFrom::Start(end))?;
+        self.out.flush()?;
+        Ok(self.out)
+    }
+
+    /// Write a `00dc` chunk, padded to an even size, and index it
+    fn write_chunk(&mut self, data: &[u8]) -> Result<()> {
+        // Offsets count from the `movi` tag, which is 4 bytes before `movi_len` starts
+        let offset = (4 + self.movi_len) as u32;
+        self.out.write_all(FRAME_CHUNK)?;
+        self.out.write_all(&(data.len() as u32).to_le_bytes())?;
+        self.out.write_all(data)?;
+        let mut len = 8 + data.len() as u64;
+        if data.len() % 2 == 1 {
+            self.out.write_all(&[0])?;
+            len += 1;
+        }
+        self.movi_len += len;
+        self.index.push(IndexEntry {
+            offset,
+            size: data.len() as u32,
+        });
+        Ok(())
+    }
+
+    /// Everything before the first `movi` chunk, for a file of `file_len` bytes
+    fn header(&self, width: u32, height: u32, file_len: u64) -> Vec<u8> {
+        let frames = self.frame_count();
+        let buffer_size = self.max_frame_size + 8;
+        let mut h = Vec::with_capacity(HEADER_LEN);
+
+        h.extend_from_slice(b"RIFF");
+        h.extend_from_slice(&((file_len - 8) as u32).to_le_bytes());
+        h.extend_from_slice(b"AVI ");
+
+        h.extend_from_slice(b"LIST");
+        h.extend_from_slice(&192u32.to_le_bytes());
+        h.extend_from_slice(b"hdrl");
+
+        h.extend_from_slice(b"avih");
+        h.extend_from_slice(&56u32.to_le_bytes());
+        for value in [
+            1_000_000 / self.fps, // Microseconds per frame
+            0,                    // Max bytes per second
+            0,                    // Padding granularity
+            AVIF_HASINDEX,
+            frames,
+            0, // Initial frames
+            1, // Streams
+            buffer_size,
+            width,
+            height,
+            0,
+            0,
+            0,
+            0,
+        ] {
+            h.extend_from_slice(&value.to_le_bytes());
+        }
+
+        h.extend_from_slice(b"LIST");
+        h.extend_from_slice(&116u32.to_le_bytes());
+        h.extend_from_slice(b"strl");
+
+        h.extend_from_slice(b"strh");
+        h.extend_from_slice(&56u32.to_le_bytes());
+        h.extend_from_slice(b"vids");
+        h.extend_from_slice(b"MJPG");
+        for value in [
+            0, // Flags
+            0, // Priority and language
+            0, // Initial frames
+            1, // Scale
+            self.fps,
+            0, // Start
+            frames,
+            buffer_size,
+            u32::MAX, // Quality (default)
+            0,        // Sample size (varies)
+        ] {
+            h.extend_from_slice(&value.to_le_bytes());
+        }
+        for value in [0, 0, width as u16, height as u16] {
+            h.extend_from_slice(&value.to_le_bytes());
+        }
+
+        h.extend_from_slice(b"strf");
+        h.extend_from_slice(&40u32.to_le_bytes());
+        h.extend_from_slice(&40u32.to_le_bytes());
+        h.extend_from_slice(&width.to_le_bytes());
+        h.extend_from_slice(&height.to_le_bytes());
+        h.extend_from_slice(&1u16.to_le_bytes()); // Planes
+        h.extend_from_slice(&24u16.to_le_bytes()); // Bits per pixel
+        h.extend_from_slice(b"MJPG");
+        h.extend_from_slice(&(width * height * 3).to_le_bytes());
+        h.extend_from_slice(&[0; 16]);
+
+        h.extend_from_slice(b"LIST");
+        h.extend_from_slice(&((4 + self.movi_len) as u32).to_le_bytes());
+        h.extend_from_slice(b"movi");
+
+        debug_assert_eq!(h.len(), HEADER_LEN);
+        h
+    }
+}
+
+/// Writer for the chosen [`VideoContainer`]
+pub enum VideoWriter<W: Write + Seek> {
+    /// AVI on a fixed time base
+    Avi(AviWriter<W>),
+    /// Matroska with per-frame timestamps and chapters
+    Mkv(MkvWriter<W>),
+}
+
+impl<W: Write + Seek> VideoWriter<W> {
+    /// Writer for `container`; `fps` is the AVI time base and unused for MKV
+    pub fn new(container: VideoContainer, out: W, fps: u32) -> Self {
+        match container {
+            VideoContainer::Avi => VideoWriter::Avi(AviWriter::new(out, fps)),
+            VideoContainer::Mkv => VideoWriter::Mkv(MkvWriter::new(out)),
+        }
+    }
+
+    /// JPEG quality (1-100) for the RGB frames added from now on
+    pub fn set_quality(&mut self, quality: u8) {
+        match self {
+            VideoWriter::Avi(writer) => writer.set_quality(quality),
+            VideoWriter::Mkv(writer) => writer.set_quality(quality),
+        }
+    }
+
+    /// Frames written so far
+    pub fn frame_count(&self) -> u32 {
+        match self {
+            VideoWriter::Avi(writer) => writer.frame_count(),
+            VideoWriter::Mkv(writer) => writer.frame_count(),
+        }
+    }
+
+    /// Encode a frame-buffer frame (JPEG or RGB24) and add it
+    ///
+    /// # Errors
+    ///
+    /// Returns the container writer's error.
+    pub fn push_frame(
+        &mut self,
+        frame: &[u8],
+        width: u32,
+        height: u32,
+        timestamp_us: u64,
+    ) -> Result<()> {
+        match self {
+            VideoWriter::Avi(writer) => writer
+                .push_frame(frame, width, height, timestamp_us)
+                .map(drop),
+            VideoWriter::Mkv(writer) => writer.push_frame(frame, width, height, timestamp_us),
+        }
+    }
+
+    /// Finish the file, embedding `chapters` if the container supports them
+    ///
+    /// AVI has no chapters, so they are only embedded in MKV files.
+    ///
+    /// # Errors
+    ///
+    /// Returns the container writer's error.
+    pub fn finish(self, chapters: &[Chapter]) -> Result<W> {
+        match self {
+            VideoWriter::Avi(writer) => writer.finish(),
+            VideoWriter::Mkv(writer) => writer.finish(chapters),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    const JPEG: &[u8] = &[0xFF, 0xD8, 0xAA, 0xFF, 0xD9];
+
+    fn u32_at(data: &[u8], pos: usize) -> u32 {
+        u32::from_le_bytes(data[pos..pos + 4].try_into().unwrap())
+    }
+
+    /// `(id, flags, offset, size)` for each `idx1` entry
+    fn index_entries(data: &[u8]) -> Vec<(String, u32, u32, u32)> {
+        let movi_len = u32_at(data, HEADER_LEN - 8) as usize;
+        let idx1 = HEADER_LEN - 4 + movi_len;
+        assert_eq!(&data[idx1..idx1 + 4], b"idx1");
+        let len = u32_at(data, idx1 + 4) as usize;
+        data[idx1 + 8..idx1 + 8 + len]
+            .chunks_exact(16)
+            .map(|e| {
+                (
+                    String::from_utf8_lossy(&e[0..4]).to_string(),
+                    u32_at(e, 4),
+                    u32_at(e, 8),
+                    u32_at(e, 12),
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_avi_layout() {
+        let mut writer = AviWriter::new(Cursor::new(Vec::new()), 10);
+        assert!(writer.add_frame(JPEG, 640, 480, 1_000_000).unwrap());
+        assert!(writer
+            .add_frame(&[0xFF, 0xD8, 0xFF, 0xD9], 640, 480, 1_100_000)
+            .unwrap());
+        let data = writer.finish().unwrap().into_inner();
+
+        assert_eq!(&data[0..4], b"RIFF");
+        assert_eq!(u32_at(&data, 4) as usize, data.len() - 8);
+        assert_eq!(&data[8..12], b"AVI ");
+        assert_eq!(&data[24..28], b"avih");
+        assert_eq!(u32_at(&data, 32), 100_000); // Microseconds per frame
+        assert_eq!(u32_at(&data, 48), 2); // Total frames
+        assert_eq!((u32_at(&data, 64), u32_at(&data, 68)), (640, 480));
+        assert_eq!(&data[108..112], b"vids");
+        assert_eq!(&data[112..116], b"MJPG");
+        assert_eq!(u32_at(&data, 132), 10); // Rate
+        assert_eq!(&data[HEADER_LEN - 4..HEADER_LEN], b"movi");
+
+        let entries = index_entries(&data);
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0], ("00dc".to_string(), AVIIF_KEYFRAME, 4, 5));
+        // 8 byte header + 5 bytes + 1 byte padding
+        assert_eq!(entries[1].2, 4 + 14);
+
+        let first = HEADER_LEN - 4 + entries[0].2 as usize;
+        assert_eq!(&data[first + 8..first + 13], JPEG);
+    }
+
+    #[test]
+    fn test_gaps_repeat_previous_frame() {
+        let mut writer = AviWriter::new(Cursor::new(Vec::new()), 10);
+        writer.add_frame(JPEG, 2, 2, 0).unwrap();
+        // 300 ms later: two empty slots, then the frame
+        writer.add_frame(JPEG, 2, 2, 300_000).unwrap();
+        // Same slot as the previous frame: dropped
+        assert!(!writer.add_frame(JPEG, 2, 2, 320_000).unwrap());
+        assert_eq!(writer.frame_count(), 4);
+        let data = writer.finish().unwrap().into_inner();
+
+        let sizes: Vec<_> = index_entries(&data).iter().map(|e| (e.1, e.3)).collect();
+        assert_eq!(
+            sizes,
+            [(AVIIF_KEYFRAME, 5), (0, 0), (0, 0), (AVIIF_KEYFRAME, 5)]
+        );
+    }
+
+    #[test]
+    fn test_rejects_invalid_frames() {
+        let mut writer = AviWriter::new(Cursor::new(Vec::new()), 30);
+        writer.add_frame(JPEG, 8, 8, 0).unwrap();
+        assert!(writer.add_frame(JPEG, 16, 8, 100_000).is_err());
+
+        let empty = AviWriter::new(Cursor::new(Vec::new()), 30);
+        assert!(empty.finish().is_err());
+    }
+
+    #[test]
+    fn test_jpeg_frames_are_not_reencoded() {
+        let mut writer = AviWriter::new(Cursor::new(Vec::new()), 30);
+        assert!(writer.push_frame(JPEG, 4, 4, 0).unwrap());
+        let data = writer.finish().unwrap().into_inner();
+        assert_eq!(&data[HEADER_LEN + 8..HEADER_LEN + 13], JPEG);
+    }
+}
@@ -0,0 +1,210 @@
+//! Frame pipeline warm-up before streaming starts
+//!
+//! The first frames after starting a stream used to stutter: the first
+//! conversion pays for frame-sized allocations the allocator has never
+//! served, one-time initialization inside the converters, and the first
+//! snapshot pays for encoder setup. Once probe/commit has negotiated the
+//! format, `warm_up` runs the pipeline once on a synthetic mid-gray frame of
+//! the negotiated size, so that cost is paid before the first real frame
+//! arrives instead of while it is displayed. The converted frame's buffer is
+//! kept (`StreamingContext::conversion_buffer`) and the first real frame is
+//! converted into it.
+
+use std::time::{Duration, Instant};
+
+use crate::image_encoder::{encoder_for, ImageFormat};
+use crate::yuv_conversion::convert_to_rgb_into;
+use crate::PixelFormat;
+
+/// Side of the square frame used to prime the snapshot encoder
+const ENCODER_PRIME_SIZE: u32 = 16;
+
+/// Mid-gray in every channel (Y = U = V = 128 is gray in YUV as well)
+const GRAY: u8 = 128;
+
+/// What the negotiated stream delivers
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum WarmupPlan {
+    /// Compressed frames, displayed without conversion
+    Mjpeg,
+    /// Uncompressed frames converted to RGB on the device
+    Uncompressed {
+        /// Negotiated pixel format
+        pixel_format: PixelFormat,
+        /// Negotiated width in pixels
+        width: u32,
+        /// Negotiated height in pixels
+        height: u32,
+    },
+}
+
+/// Time spent in each warm-up step
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct WarmupReport {
+    /// Synthetic frame conversion, if the stream needs conversion
+    pub conversion: Option<Duration>,
+    /// Snapshot encoder priming, if a snapshot format is configured and available
+    pub encoder: Option<Duration>,
+    /// Whole warm-up
+    pub total: Duration,
+}
+
+/// Run the frame pipeline once before the first real frame
+///
+/// `snapshot_format` is the configured snapshot format (`None` keeps the
+/// native format, which needs no encoder). The RGB frame of an uncompressed
+/// stream is left in `conversion_buffer` for the first real frame; MJPEG
+/// streams release it. Failures are logged and never stop the stream:
+/// warm-up only moves cost earlier.
+pub fn warm_up(
+    plan: WarmupPlan,
+    snapshot_format: Option<ImageFormat>,
+    conversion_buffer: &mut Vec<u8>,
+) -> WarmupReport {
+    let start = Instant::now();
+
+    let conversion = match plan {
+        WarmupPlan::Mjpeg => {
+            *conversion_buffer = Vec::new();
+            None
+        }
+        WarmupPlan::Uncompressed {
+            pixel_format,
+            width,
+            height,
+        } => warm_up_conversion(pixel_format, width, height, conversion_buffer),
+    };
+    let encoder = snapshot_format.and_then(warm_up_encoder);
+
+    let report = WarmupReport {
+        conversion,
+        encoder,
+        total: start.elapsed(),
+    };
+    log::info!(
+        "Pipeline warm-up took {:?} (conversion: {:?}, encoder: {:?})",
+        report.total,
+        report.conversion,
+        report.encoder
+    );
+    report
+}
+
+/// Convert one synthetic frame of the negotiated size into `buffer`
+fn warm_up_conversion(
+    pixel_format: PixelFormat,
+    width: u32,
+    height: u32,
+    buffer: &mut Vec<u8>,
+) -> Option<Duration> {
+    if width == 0 || height == 0 {
+        return None;
+    }
+    let start = Instant::now();
+    let frame = vec![GRAY; pixel_format.frame_size(width, height)];
+    let stride = match pixel_format {
+        PixelFormat::Yuyv | PixelFormat::Uyvy => width * 2,
+        _ => width,
+    };
+    match convert_to_rgb_into(
+        &frame,
+        width,
+        height,
+        stride,
+        pixel_format,
+        std::mem::take(buffer),
+    ) {
+        Ok(rgb) => {
+            *buffer = rgb;
+            Some(start.elapsed())
+        }
+        Err(e) => {
+            log::warn!("Warm-up conversion failed: {}", e);
+            None
+        }
+    }
+}
+
+/// Encode a small frame in the snapshot format
+fn warm_up_encoder(format: ImageFormat) -> Option<Duration> {
+    let encoder = match encoder_for(format) {
+        Ok(encoder) => encoder,
+        Err(e) => {
+            log::debug!("Skipping encoder warm-up: {}", e);
+            return None;
+        }
+    };
+    let start = Instant::now();
+    let rgb = vec![GRAY; (ENCODER_PRIME_SIZE * ENCODER_PRIME_SIZE * 3) as usize];
+    match encoder.encode(&rgb, ENCODER_PRIME_SIZE, ENCODER_PRIME_SIZE) {
+        Ok(_) => Some(start.elapsed()),
+        Err(e) => {
+            log::warn!("Warm-up encode failed: {}", e);
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_uncompressed_formats_are_converted() {
+        for pixel_format in crate::format_registry::FORMATS.iter().map(|e| e.format) {
+            let mut buffer = Vec::new();
+            let report = warm_up(
+                WarmupPlan::Uncompressed {
+                    pixel_format,
+                    width: 64,
+                    height: 48,
+                },
+                None,
+                &mut buffer,
+            );
+            assert!(
+                report.conversion.is_some(),
+                "{} not warmed up",
+                pixel_format
+            );
+            assert_eq!(buffer.len(), 64 * 48 * 3, "{}", pixel_format);
+            assert!(report.encoder.is_none());
+        }
+    }
+
+    #[test]
+    fn test_mjpeg_needs_no_conversion() {
+        let mut buffer = vec![0; 12];
+        let report = warm_up(WarmupPlan::Mjpeg, None, &mut buffer);
+        assert_eq!(report.conversion, None);
+        assert_eq!(report.encoder, None);
+        assert_eq!(buffer.capacity(), 0);
+    }
+
+    #[test]
+    fn test_zero_size_is_skipped() {
+        let report = warm_up(
+            WarmupPlan::Uncompressed {
+                pixel_format: PixelFormat::Yuyv,
+                width: 0,
+                height: 480,
+            },
+            None,
+            &mut Vec::new(),
+        );
+        assert_eq!(report.conversion, None);
+    }
+
+    #[test]
+    fn test_encoder_is_primed_only_when_available() {
+        for &format in ImageFormat::ALL {
+            let report = warm_up(WarmupPlan::Mjpeg, Some(format), &mut Vec::new());
+            assert_eq!(
+                report.encoder.is_some(),
+                format.is_available(),
+                "{}",
+                format
+            );
+        }
+    }
+}
@@ -0,0 +1,409 @@
+//! Incremental animated WebP writer
+//!
+//! Builds an animated WebP file one frame at a time from still WebP images
+//! (as produced by [`encode_frame`] with [`ImageFormat::WebP`]). Each frame's
+//! bitstream chunks are copied into an `ANMF` chunk as soon as the frame is
+//! added, so only the frame being encoded is held in memory; the RIFF size is
+//! patched in when the file is finished.
+//!
+//! # Layout
+//!
+//! ```text
+//! RIFF <size> WEBP
+//!   VP8X  animation flag, canvas size (taken from the first frame)
+//!   ANIM  background color, loop count
+//!   ANMF  offset, size, duration, flags, then the frame's ALPH/VP8/VP8L chunks
+//!   ANMF  ...
+//! ```
+
+use std::io::{Seek, SeekFrom, Write};
+
+use crate::image_encoder::{encode_frame, EncodeError, ImageFormat, Result};
+
+/// Largest frame duration an `ANMF` chunk can hold (24 bits)
+pub const MAX_FRAME_DURATION_MS: u32 = 0x00FF_FFFF;
+
+/// Largest canvas dimension (24 bits, stored minus one)
+const MAX_CANVAS_DIMENSION: u32 = 1 << 24;
+
+/// VP8X flag: file contains an animation
+const VP8X_ANIMATION: u8 = 0x02;
+
+/// ANMF flag: draw the frame over the canvas instead of alpha-blending it
+const ANMF_NO_BLEND: u8 = 0x02;
+
+/// Writes an animated WebP file frame by frame
+pub struct AnimatedWebPWriter<W: Write + Seek> {
+    /// Output file
+    out: W,
+    /// Canvas size, set by the first frame
+    canvas: Option<(u32, u32)>,
+    /// Stream position of the `RIFF` tag
+    start: u64,
+    /// Bytes written, including the `RIFF` header
+    written: u64,
+    /// Frames added so far
+    frames: u32,
+    /// Number of times to play the animation (0 = forever)
+    loop_count: u16,
+}
+
+impl<W: Write + Seek> AnimatedWebPWriter<W> {
+    /// Writer for an animation that loops forever
+    ///
+    /// Nothing is written until the first frame is added.
+    pub fn new(out: W) -> Self {
+        Self {
+            out,
+            canvas: None,
+            start: 0,
+            written: 0,
+            frames: 0,
+            loop_count: 0,
+        }
+    }
+
+    /// Play the animation `count` times instead of forever
+    pub fn with_loop_count(mut self, count: u16) -> Self {
+        self.loop_count = count;
+        self
+    }
+
+    /// Frames added so far
+    pub fn frame_count(&self) -> u32 {
+        self.frames
+    }
+
+    /// Encode a frame-buffer frame (JPEG or RGB24) and add it
+    ///
+    /// # Errors
+    ///
+    /// Returns the encoding error (`EncodeError::Unsupported` without the
+    /// `webp` feature), or any error from [`AnimatedWebPWriter::add_frame`].
+    pub fn push_frame(
+        &mut self,
+        frame: &[u8],
+        width: u32,
+        height: u32,
+        duration_ms: u32,
+    ) -> Result<()> {
+        let still = encode_frame(frame, width, height, ImageFormat::WebP)?;
+        self.add_frame(&still, duration_ms)
+    }
+
+    /// Add a still WebP image shown for `duration_ms`
+    ///
+    /// The first frame sets the canvas size; later frames must fit in it.
+    /// Durations above [`MAX_FRAME_DURATION_MS`] are clamped.
+    ///
+    /// # Errors
+    ///
+    /// Returns `EncodeError::Encode` if `still` is not a still WebP image or
+    /// does not fit the canvas, or `EncodeError::Io` if writing fails.
+    pub fn add_frame(&mut self, still: &[u8], duration_ms: u32) -> Result<()> {
+        let image = parse_still(still)?;
+        match self.canvas {
+            Some((canvas_w, canvas_h)) if image.width > canvas_w || image.height > canvas_h => {
+                return Err(invalid(format!(
+                    "{}x{} frame does not fit the {}x{} canvas",
+                    image.width, image.height, canvas_w, canvas_h
+                )));
+            }
+            Some(_) => {}
+            None => self.write_header(image.width, image.height)?,
+        }
+        let (width, height) = (image.width, image.height);
+
+        let mut header = Vec::with_capacity(16);
+        header.extend_from_slice(&u24(0)); // X offset / 2
+        header.extend_from_slice(&u24(0)); // Y offset / 2
+        header.extend_from_slice(&u24(width - 1));
+        header.extend_from_slice(&u24(height - 1));
+        header.extend_from_slice(&u24(duration_ms.min(MAX_FRAME_DURATION_MS)));
+        header.push(ANMF_NO_BLEND);
+
+        let payload_len = header.len() + image.chunks.len();
+        self.write_chunk_header(b"ANMF", payload_len)?;
+        self.write_bytes(&header)?;
+        self.write_bytes(image.chunks)?;
+        self.pad(payload_len)?;
+        self.frames += 1;
+        Ok(())
+    }
+
+    /// Patch the RIFF size and return the output
+    ///
+    /// # Errors
+    ///
+    /// Returns `EncodeError::Encode` if no frames were added, or
+    /// `EncodeError::Io` if writing fails.
+    pub fn finish(mut self) -> Result<W> {
+        if self.frames == 0 {
+            return Err(invalid("animation has no frames".to_string()));
+        }
+        let riff_size = u32::try_from(self.written - 8)
+            .map_err(|_| invalid("animation exceeds 4 GiB".to_string()))?;
+        self.out.seek(SeekFrom::Start(self.start + 4))?;
+        self.out.write_all(&riff_size.to_le_bytes())?;
+        self.out.seek(SeekFrom::Start(self.start + self.written))?;
+        self.out.flush()?;
+        Ok(self.out)
+    }
+
+    /// Write the RIFF header (with a placeholder size), VP8X and ANIM
+    fn write_header(&mut self, width: u32, height: u32) -> Result<()> {
+        if width > MAX_CANVAS_DIMENSION || height > MAX_CANVAS_DIMENSION {
+            return Err(invalid(format!(
+                "{}x{} exceeds the canvas limit",
+                width, height
+            )));
+        }
+        self.start = self.out.stream_position()?;
+        self.write_bytes(b"RIFF")?;
+        self.write_bytes(&[0; 4])?;
+        self.write_bytes(b"WEBP")?;
+
+        self.write_chunk_header(b"VP8X", 10)?;
+        self.write_bytes(&[VP8X_ANIMATION, 0, 0, 0])?;
+        self.write_bytes(&u24(width - 1))?;
+        self.write_bytes(&u24(height - 1))?;
+
+        self.write_chunk_header(b"ANIM", 6)?;
+        self.write_bytes(&[0; 4])?; // Background color (BGRA), unused without blending
+        self.write_bytes(&self.loop_count.to_le_bytes())?;
+
+        self.canvas = Some((width, height));
+        Ok(())
+    }
+
+    /// Write a chunk tag and payload size
+    fn write_chunk_header(&mut self, tag: &[u8; 4], len: usize) -> Result<()> {
+        let len = u32::try_from(len).map_err(|_| invalid("chunk exceeds 4 GiB".to_string()))?;
+        self.write_bytes(tag)?;
+        self.write_bytes(&len.to_le_bytes())
+    }
+
+    /// Pad a chunk payload of `len` bytes to an even size
+    fn pad(&mut self, len: usize) -> Result<()> {
+        if len % 2 == 1 {
+            self.write_bytes(&[0])?;
+        }
+        Ok(())
+    }
+
+    fn write_bytes(&mut self, bytes: &[u8]) -> Result<()> {
+        self.out.write_all(bytes)?;
+        self.written += bytes.len() as u64;
+        Ok(())
+    }
+}
+
+/// Image chunks and size of a still WebP file
+struct StillImage<'a> {
+    /// `ALPH`/`VP8 `/`VP8L` chunks, with headers and padding
+    chunks: &'a [u8],
+    width: u32,
+    height: u32,
+}
+
+/// Find the image chunks of a still WebP file
+fn parse_still(data: &[u8]) -> Result<StillImage<'_>> {
+    if data.len() < 12 || &data[0..4] != b"RIFF" || &data[8..12] != b"WEBP" {
+        return Err(invalid("not a WebP file".to_string()));
+    }
+
+    let mut canvas = None;
+    let mut alpha_start = None;
+    let mut pos = 12;
+    while pos + 8 <= data.len() {
+        let tag = &data[pos..pos + 4];
+        let len = u32::from_le_bytes([data[pos + 4], data[pos + 5], data[pos + 6], data[pos + 7]])
+            as usize;
+        let payload_end = pos + 8 + len;
+        if payload_end > data.len() {
+            return Err(invalid(format!(
+                "truncated {} chunk",
+                String::from_utf8_lossy(tag)
+            )));
+        }
+        let payload = &data[pos + 8..payload_end];
+        let end = (payload_end + len % 2).min(data.len());
+
+        match tag {
+            b"VP8X" if payload.len() >= 10 => {
+                canvas = Some((read_u24(&payload[4..7]) + 1, read_u24(&payload[7..10]) + 1));
+            }
+            b"ANIM" | b"ANMF" => {
+                return Err(invalid(
+                    "expected a still image, got an animation".to_string(),
+                ));
+            }
+            b"ALPH" => {
+                alpha_start.get_or_insert(pos);
+            }
+            b"VP8L" | b"VP8 " => {
+                let size = if tag == b"VP8L" {
+                    vp8l_dimensions(payload)
+                } else {
+                    vp8_dimensions(payload)
+                };
+                let (width, height) = size
+                    .or(canvas)
+                    .ok_or_else(|| invalid("missing image dimensions".to_string()))?;
+                return Ok(StillImage {
+                    chunks: &data[alpha_start.unwrap_or(pos)..end],
+                    width,
+                    height,
+                });
+            }
+            _ => {}
+        }
+        pos = end;
+    }
+    Err(invalid("no image data".to_string()))
+}
+
+/// Dimensions from a lossless `VP8L` bitstream header
+fn vp8l_dimensions(payload: &[u8]) -> Option<(u32, u32)> {
+    if payload.len() < 5 || payload[0] != 0x2F {
+        return None;
+    }
+    let bits = u32::from_le_bytes([payload[1], payload[2], payload[3], payload[4]]);
+    Some(((bits & 0x3FFF) + 1, ((bits >> 14) & 0x3FFF) + 1))
+}
+
+/// Dimensions from a lossy `VP8 ` key frame header
+fn vp8_dimensions(payload: &[u8]) -> Option<(u32, u32)> {
+    if payload.len() < 10 || payload[3..6] != [0x9D, 0x01, 0x2A] {
+        return None;
+    }
+    let width = u32::from(u16::from_le_bytes([payload[6], payload[7]]) & 0x3FFF);
+    let height = u32::from(u16::from_le_bytes([payload[8], payload[9]]) & 0x3FFF);
+    Some((width, height))
+}
+
+/// Little-endian 24-bit value
+fn u24(value: u32) -> [u8; 3] {
+    let [a, b, c, _] = value.to_le_bytes();
+    [a, b, c]
+}
+
+fn read_u24(bytes: &[u8]) -> u32 {
+    u32::from_le_bytes([bytes[0], bytes[1], bytes[2], 0])
+}
+
+fn invalid(message: String) -> EncodeError {
+    EncodeError::Encode {
+        format: ImageFormat::WebP,
+        message,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    /// Minimal still WebP with a `VP8L` header for a `width`x`height` image
+    fn still(width: u32, height: u32, body_len: usize) -> Vec<u8> {
+        let bits = (width - 1) | ((height - 1) << 14);
+        let mut payload = vec![0x2F];
+        payload.extend_from_slice(&bits.to_le_bytes());
+        payload.resize(5 + body_len, 0xAA);
+
+        let mut chunk = b"VP8L".to_vec();
+        chunk.extend_from_slice(&(payload.len() as u32).to_le_bytes());
+        chunk.extend_from_slice(&payload);
+        if payload.len() % 2 == 1 {
+            chunk.push(0);
+        }
+
+        let mut file = b"RIFF".to_vec();
+        file.extend_from_slice(&(4 + chunk.len() as u32).to_le_bytes());
+        file.extend_from_slice(b"WEBP");
+        file.extend_from_slice(&chunk);
+        file
+    }
+
+    /// Top-level chunks of a WebP file as (tag, payload)
+    fn chunks(data: &[u8]) -> Vec<(String, &[u8])> {
+        let mut out = Vec::new();
+        let mut pos = 12;
+        while pos + 8 <= data.len() {
+            let len = u32::from_le_bytes(data[pos + 4..pos + 8].try_into().unwrap()) as usize;
+            let tag = String::from_utf8_lossy(&data[pos..pos + 4]).to_string();
+            out.push((tag, &data[pos + 8..pos + 8 + len]));
+            pos += 8 + len + len % 2;
+        }
+        assert_eq!(pos, data.len());
+        out
+    }
+
+    #[test]
+    fn test_animation_layout() {
+        let mut writer = AnimatedWebPWriter::new(Cursor::new(Vec::new())).with_loop_count(3);
+        writer.add_frame(&still(640, 480, 10), 100).unwrap();
+        writer.add_frame(&still(640, 480, 7), 250).unwrap();
+        assert_eq!(writer.frame_count(), 2);
+        let data = writer.finish().unwrap().into_inner();
+
+        assert_eq!(&data[0..4], b"RIFF");
+        assert_eq!(&data[8..12], b"WEBP");
+        let riff_size = u32::from_le_bytes(data[4..8].try_into().unwrap()) as usize;
+        assert_eq!(riff_size, data.len() - 8);
+
+        let chunks = chunks(&data);
+        let tags: Vec<_> = chunks.iter().map(|(t, _)| t.as_str()).collect();
+        assert_eq!(tags, ["VP8X", "ANIM", "ANMF", "ANMF"]);
+
+        let vp8x = chunks[0].1;
+        assert_eq!(vp8x[0], VP8X_ANIMATION);
+        assert_eq!((read_u24(&vp8x[4..7]), read_u24(&vp8x[7..10])), (639, 479));
+        assert_eq!(u16::from_le_bytes([chunks[1].1[4], chunks[1].1[5]]), 3);
+
+        let durations: Vec<_> = chunks[2..]
+            .iter()
+            .map(|(_, p)| read_u24(&p[12..15]))
+            .collect();
+        assert_eq!(durations, [100, 250]);
+
+        // Each frame carries the still's VP8L chunk unchanged
+        let frame = still(640, 480, 7);
+        assert_eq!(&chunks[3].1[16..], &frame[12..]);
+    }
+
+    #[test]
+    fn test_writes_after_existing_data() {
+        let mut out = Cursor::new(b"prefix".to_vec());
+        out.seek(SeekFrom::End(0)).unwrap();
+        let mut writer = AnimatedWebPWriter::new(out);
+        writer.add_frame(&still(4, 4, 3), 40).unwrap();
+        let data = writer.finish().unwrap().into_inner();
+
+        let riff = &data[6..];
+        assert_eq!(&riff[0..4], b"RIFF");
+        let riff_size = u32::from_le_bytes(riff[4..8].try_into().unwrap()) as usize;
+        assert_eq!(riff_size, riff.len() - 8);
+    }
+
+    #[test]
+    fn test_rejects_invalid_frames() {
+        let mut writer = AnimatedWebPWriter::new(Cursor::new(Vec::new()));
+        assert!(writer.add_frame(b"\xFF\xD8\xFF\xD9", 100).is_err());
+        writer.add_frame(&still(8, 8, 2), 100).unwrap();
+        assert!(writer.add_frame(&still(16, 8, 2), 100).is_err());
+        assert_eq!(writer.frame_count(), 1);
+
+        let empty = AnimatedWebPWriter::new(Cursor::new(Vec::new()));
+        assert!(empty.finish().is_err());
+    }
+
+    #[test]
+    fn test_duration_is_clamped() {
+        let mut writer = AnimatedWebPWriter::new(Cursor::new(Vec::new()));
+        writer.add_frame(&still(2, 2, 1), u32::MAX).unwrap();
+        let data = writer.finish().unwrap().into_inner();
+        let anmf = chunks(&data)[2].1;
+        assert_eq!(read_u24(&anmf[12..15]), MAX_FRAME_DURATION_MS);
+    }
+}
@@ -6,14 +6,35 @@
 //! # Supported Formats
 //!
 //! - **YUV 4:2:2 Packed**: YUYV and UYVY byte orders
-//! - **YUV 4:2:0 Planar**: I420 (Y/U/V planes)
-//! - **YUV 4:2:0 Semi-Planar**: NV12 (Y plane + interleaved UV)
+//! - **YUV 4:2:0 Planar**: I420 (Y/U/V planes) and YV12 (Y/V/U planes)
+//! - **YUV 4:2:0 Semi-Planar**: NV12 (Y plane + interleaved UV) and NV21
+//!   (Y plane + interleaved VU)
 //! - **RGB Passthrough**: RGB888 and BGR888
+//! - **Bayer RAW**: 8-bit RGGB mosaics, demosaiced bilinearly or with
+//!   Malvar-He-Cutler ([`DemosaicMethod`])
+//!
+//! The YUV converters decode limited range BT.601, which is what SD webcams
+//! and endoscopes send; the `*_with_matrix` variants also decode BT.709
+//! ([`YuvMatrix`]).
+//!
+//! [`convert_to_rgb`] picks the converter from
+//! [`format_registry`](crate::format_registry), which maps each
+//! [`PixelFormat`] to one of the functions here.
 //!
 //! # Architecture
 //!
-//! On Android, this module uses `yuvutils_rs` for hardware-optimized conversions.
-//! On other platforms, pure Rust implementations are provided for testing.
+//! On Android, this module uses `yuvutils_rs` for SIMD-optimized conversions.
+//! On other platforms, the pure Rust converters in [`scalar`] are used unless
+//! the `simd-yuv` feature switches them to `yuvutils_rs` as well (fast enough
+//! for 1080p30 preview during replay). Unit tests always run against the
+//! scalar converters, which serve as the reference; with `simd-yuv` a test
+//! checks the SIMD path against them. Compare both with
+//! `cargo bench --bench yuv_conversion --features simd-yuv`.
+
+use std::cell::Cell;
+
+use crate::format_registry;
+use crate::PixelFormat;
 
 /// Error type for conversion failures
 #[derive(Debug, Clone)]
@@ -33,6 +54,30 @@ impl From<String> for ConversionError {
     }
 }
 
+thread_local! {
+    /// Buffer lent to the conversion running on this thread by
+    /// [`convert_to_rgb_into`]
+    static OUTPUT_BUFFER: Cell<Vec<u8>> = const { Cell::new(Vec::new()) };
+}
+
+/// Empty output buffer with room for `capacity` bytes
+///
+/// Takes the buffer lent by [`convert_to_rgb_into`], if there is one, so the
+/// converter writes into its allocation.
+fn output_buffer(capacity: usize) -> Vec<u8> {
+    let mut buffer = OUTPUT_BUFFER.with(Cell::take);
+    buffer.clear();
+    buffer.reserve(capacity);
+    buffer
+}
+
+/// [`output_buffer`] filled with `len` zeroes
+fn zeroed_output_buffer(len: usize) -> Vec<u8> {
+    let mut buffer = output_buffer(len);
+    buffer.resize(len, 0);
+    buffer
+}
+
 /// YUV 4:2:2 packed format variant
 #[derive(Debug, Clone, Copy, PartialEq, Default)]
 pub enum YuvPackedFormat {
@@ -44,6 +89,30 @@ pub enum YuvPackedFormat {
     Uyvy,
 }
 
+/// YUV to RGB color matrix (limited range)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum YuvMatrix {
+    /// ITU-R BT.601 (SD video)
+    #[default]
+    Bt601,
+    /// ITU-R BT.709 (HD video)
+    Bt709,
+}
+
+impl YuvMatrix {
+    /// Coefficients `[Cr→R, Cb→G, Cr→G, Cb→B]` scaled by 256, applied after
+    /// the luma is scaled by 298/256 (255/219)
+    #[cfg(not(target_os = "android"))]
+    const fn coefficients(self) -> [i32; 4] {
+        match self {
+            // R = 1.164 Y + 1.596 V, G = 1.164 Y - 0.392 U - 0.813 V, B = 1.164 Y + 2.017 U
+            YuvMatrix::Bt601 => [409, 100, 208, 516],
+            // R = 1.164 Y + 1.793 V, G = 1.164 Y - 0.213 U - 0.533 V, B = 1.164 Y + 2.112 U
+            YuvMatrix::Bt709 => [459, 55, 136, 541],
+        }
+    }
+}
+
 /// Calculate YUY2 stride from frame size when dimensions don't match exactly
 ///
 /// Some cameras add padding bytes to each row for alignment. This function
@@ -105,17 +174,27 @@ pub fn calculate_yuy2_stride(frame_size: usize, width: u32, height: u32) -> u32
 }
 
 // ============================================================================
-// Android implementation using yuvutils_rs (hardware-optimized)
+// SIMD implementation using yuvutils_rs (Android, or the simd-yuv feature)
 // ============================================================================
 
-#[cfg(target_os = "android")]
-mod android_impl {
+#[cfg(any(target_os = "android", feature = "simd-yuv"))]
+mod simd {
     use super::*;
+    use std::sync::atomic::{AtomicBool, Ordering};
     use yuvutils_rs::{
-        uyvy422_to_rgb, yuv420_to_rgb, yuv_nv12_to_rgb, yuyv422_to_rgb, YuvBiPlanarImage,
-        YuvConversionMode, YuvPackedImage, YuvPlanarImage, YuvRange, YuvStandardMatrix,
+        uyvy422_to_rgb, yuv420_to_rgb, yuv_nv12_to_rgb, yuv_nv21_to_rgb, yuyv422_to_rgb,
+        YuvBiPlanarImage, YuvConversionMode, YuvPackedImage, YuvPlanarImage, YuvRange,
+        YuvStandardMatrix,
     };
 
+    /// yuvutils-rs matrix of `matrix`
+    fn standard_matrix(matrix: YuvMatrix) -> YuvStandardMatrix {
+        match matrix {
+            YuvMatrix::Bt601 => YuvStandardMatrix::Bt601,
+            YuvMatrix::Bt709 => YuvStandardMatrix::Bt709,
+        }
+    }
+
     /// Convert YUV 4:2:2 packed frame to RGB with automatic stride detection
     ///
     /// This function handles cameras that use row padding for alignment.
@@ -138,6 +217,25 @@ mod android_impl {
         height: u32,
         stride_override: Option<u32>,
         format: YuvPackedFormat,
+    ) -> Result<Vec<u8>, ConversionError> {
+        convert_yuv422_to_rgb_with_matrix(
+            yuv_data,
+            width,
+            height,
+            stride_override,
+            format,
+            YuvMatrix::Bt601,
+        )
+    }
+
+    /// [`convert_yuv422_to_rgb`] with a color matrix
+    pub fn convert_yuv422_to_rgb_with_matrix(
+        yuv_data: &[u8],
+        width: u32,
+        height: u32,
+        stride_override: Option<u32>,
+        format: YuvPackedFormat,
+        matrix: YuvMatrix,
     ) -> Result<Vec<u8>, ConversionError> {
         let frame_size = yuv_data.len();
         let expected_stride = width * 2;
@@ -191,9 +289,9 @@ mod android_impl {
 
         // RGB output: 3 bytes per pixel
         let rgb_stride = width * 3;
-        let mut rgb_buffer = vec![0u8; (rgb_stride * height) as usize];
+        let mut rgb_buffer = zeroed_output_buffer((rgb_stride * height) as usize);
 
-        // Convert based on format - BT.601 for standard cameras, Limited range is common
+        // Convert based on format - Limited range is common
         match format {
             YuvPackedFormat::Yuyv => {
                 yuyv422_to_rgb(
@@ -201,7 +299,7 @@ mod android_impl {
                     &mut rgb_buffer,
                     rgb_stride,
                     YuvRange::Limited,
-                    YuvStandardMatrix::Bt601,
+                    standard_matrix(matrix),
                 )
                 .map_err(|e| ConversionError(format!("YUYV conversion error: {:?}", e)))?;
             }
@@ -211,7 +309,7 @@ mod android_impl {
                     &mut rgb_buffer,
                     rgb_stride,
                     YuvRange::Limited,
-                    YuvStandardMatrix::Bt601,
+                    standard_matrix(matrix),
                 )
                 .map_err(|e| ConversionError(format!("UYVY conversion error: {:?}", e)))?;
             }
@@ -238,6 +336,77 @@ mod android_impl {
         yuv_data: &[u8],
         width: u32,
         height: u32,
+    ) -> Result<Vec<u8>, ConversionError> {
+        convert_i420_to_rgb_with_matrix(yuv_data, width, height, YuvMatrix::Bt601)
+    }
+
+    /// [`convert_i420_to_rgb`] with a color matrix
+    pub fn convert_i420_to_rgb_with_matrix(
+        yuv_data: &[u8],
+        width: u32,
+        height: u32,
+        matrix: YuvMatrix,
+    ) -> Result<Vec<u8>, ConversionError> {
+        static I420_LOGGED: AtomicBool = AtomicBool::new(false);
+        convert_planar_420(
+            yuv_data,
+            width,
+            height,
+            matrix,
+            PixelFormat::I420,
+            &I420_LOGGED,
+        )
+    }
+
+    /// Convert YV12 (planar YUV420, V plane first) frame to RGB
+    ///
+    /// YV12 layout: Y plane (width*height), V plane (width/2 * height/2), U plane (width/2 * height/2)
+    /// Total size: width * height * 1.5 bytes
+    ///
+    /// # Arguments
+    ///
+    /// * `yuv_data` - Raw YV12 planar data
+    /// * `width` - Frame width in pixels
+    /// * `height` - Frame height in pixels
+    ///
+    /// # Returns
+    ///
+    /// RGB24 data (3 bytes per pixel, R-G-B order)
+    pub fn convert_yv12_to_rgb(
+        yuv_data: &[u8],
+        width: u32,
+        height: u32,
+    ) -> Result<Vec<u8>, ConversionError> {
+        convert_yv12_to_rgb_with_matrix(yuv_data, width, height, YuvMatrix::Bt601)
+    }
+
+    /// [`convert_yv12_to_rgb`] with a color matrix
+    pub fn convert_yv12_to_rgb_with_matrix(
+        yuv_data: &[u8],
+        width: u32,
+        height: u32,
+        matrix: YuvMatrix,
+    ) -> Result<Vec<u8>, ConversionError> {
+        static YV12_LOGGED: AtomicBool = AtomicBool::new(false);
+        convert_planar_420(
+            yuv_data,
+            width,
+            height,
+            matrix,
+            PixelFormat::Yv12,
+            &YV12_LOGGED,
+        )
+    }
+
+    /// Shared I420/YV12 conversion; `format` decides which chroma plane
+    /// comes first, `logged` is set after the first frame was logged
+    fn convert_planar_420(
+        yuv_data: &[u8],
+        width: u32,
+        height: u32,
+        matrix: YuvMatrix,
+        format: PixelFormat,
+        logged: &AtomicBool,
     ) -> Result<Vec<u8>, ConversionError> {
         let y_size = (width * height) as usize;
         let uv_size = y_size / 4; // Each U and V plane is 1/4 the size of Y
@@ -245,7 +414,8 @@ mod android_impl {
 
         if yuv_data.len() < expected_size {
             return Err(ConversionError(format!(
-                "I420 data too small: {} bytes, expected {} bytes for {}x{}",
+                "{} data too small: {} bytes, expected {} bytes for {}x{}",
+                format,
                 yuv_data.len(),
                 expected_size,
                 width,
@@ -253,10 +423,15 @@ mod android_impl {
             )));
         }
 
-        // Split into Y, U, V planes
+        // Split into Y and the two chroma planes, U first for I420, V first for YV12
         let y_plane = &yuv_data[0..y_size];
-        let u_plane = &yuv_data[y_size..y_size + uv_size];
-        let v_plane = &yuv_data[y_size + uv_size..y_size + uv_size * 2];
+        let first = &yuv_data[y_size..y_size + uv_size];
+        let second = &yuv_data[y_size + uv_size..y_size + uv_size * 2];
+        let (u_plane, v_plane) = if format == PixelFormat::Yv12 {
+            (second, first)
+        } else {
+            (first, second)
+        };
 
         let planar_image = YuvPlanarImage {
             y_plane,
@@ -271,23 +446,22 @@ mod android_impl {
 
         // RGB output: 3 bytes per pixel
         let rgb_stride = width * 3;
-        let mut rgb_buffer = vec![0u8; (rgb_stride * height) as usize];
+        let mut rgb_buffer = zeroed_output_buffer((rgb_stride * height) as usize);
 
         yuv420_to_rgb(
             &planar_image,
             &mut rgb_buffer,
             rgb_stride,
             YuvRange::Limited,
-            YuvStandardMatrix::Bt601,
+            standard_matrix(matrix),
         )
-        .map_err(|e| ConversionError(format!("I420 conversion error: {:?}", e)))?;
+        .map_err(|e| ConversionError(format!("{} conversion error: {:?}", format, e)))?;
 
         // Log first conversion
-        static I420_LOGGED: std::sync::atomic::AtomicBool =
-            std::sync::atomic::AtomicBool::new(false);
-        if !I420_LOGGED.swap(true, std::sync::atomic::Ordering::Relaxed) {
+        if !logged.swap(true, Ordering::Relaxed) {
             log::info!(
-                "I420 conversion: {}x{}, Y={}bytes, U={}bytes, V={}bytes -> RGB={}bytes",
+                "{} conversion: {}x{}, Y={}bytes, U={}bytes, V={}bytes -> RGB={}bytes",
+                format,
                 width,
                 height,
                 y_size,
@@ -318,6 +492,77 @@ mod android_impl {
         yuv_data: &[u8],
         width: u32,
         height: u32,
+    ) -> Result<Vec<u8>, ConversionError> {
+        convert_nv12_to_rgb_with_matrix(yuv_data, width, height, YuvMatrix::Bt601)
+    }
+
+    /// [`convert_nv12_to_rgb`] with a color matrix
+    pub fn convert_nv12_to_rgb_with_matrix(
+        yuv_data: &[u8],
+        width: u32,
+        height: u32,
+        matrix: YuvMatrix,
+    ) -> Result<Vec<u8>, ConversionError> {
+        static NV12_LOGGED: AtomicBool = AtomicBool::new(false);
+        convert_semi_planar_420(
+            yuv_data,
+            width,
+            height,
+            matrix,
+            PixelFormat::Nv12,
+            &NV12_LOGGED,
+        )
+    }
+
+    /// Convert NV21 (semi-planar YUV420, VU order) frame to RGB
+    ///
+    /// NV21 layout: Y plane (width*height), interleaved VU plane (width * height/2)
+    /// Total size: width * height * 1.5 bytes
+    ///
+    /// # Arguments
+    ///
+    /// * `yuv_data` - Raw NV21 semi-planar data
+    /// * `width` - Frame width in pixels
+    /// * `height` - Frame height in pixels
+    ///
+    /// # Returns
+    ///
+    /// RGB24 data (3 bytes per pixel, R-G-B order)
+    pub fn convert_nv21_to_rgb(
+        yuv_data: &[u8],
+        width: u32,
+        height: u32,
+    ) -> Result<Vec<u8>, ConversionError> {
+        convert_nv21_to_rgb_with_matrix(yuv_data, width, height, YuvMatrix::Bt601)
+    }
+
+    /// [`convert_nv21_to_rgb`] with a color matrix
+    pub fn convert_nv21_to_rgb_with_matrix(
+        yuv_data: &[u8],
+        width: u32,
+        height: u32,
+        matrix: YuvMatrix,
+    ) -> Result<Vec<u8>, ConversionError> {
+        static NV21_LOGGED: AtomicBool = AtomicBool::new(false);
+        convert_semi_planar_420(
+            yuv_data,
+            width,
+            height,
+            matrix,
+            PixelFormat::Nv21,
+            &NV21_LOGGED,
+        )
+    }
+
+    /// Shared NV12/NV21 conversion; `format` decides the chroma byte order,
+    /// `logged` is set after the first frame was logged
+    fn convert_semi_planar_420(
+        yuv_data: &[u8],
+        width: u32,
+        height: u32,
+        matrix: YuvMatrix,
+        format: PixelFormat,
+        logged: &AtomicBool,
     ) -> Result<Vec<u8>, ConversionError> {
         let y_size = (width * height) as usize;
         let uv_size = y_size / 2; // UV plane is half the size of Y (interleaved)
@@ -325,7 +570,8 @@ mod android_impl {
 
         if yuv_data.len() < expected_size {
             return Err(ConversionError(format!(
-                "NV12 data too small: {} bytes, expected {} bytes for {}x{}",
+                "{} data too small: {} bytes, expected {} bytes for {}x{}",
+                format,
                 yuv_data.len(),
                 expected_size,
                 width,
@@ -341,31 +587,35 @@ mod android_impl {
             y_plane,
             y_stride: width,
             uv_plane,
-            uv_stride: width, // UV stride is same as width for NV12
+            uv_stride: width, // UV stride is same as width for NV12/NV21
             width,
             height,
         };
 
         // RGB output: 3 bytes per pixel
         let rgb_stride = width * 3;
-        let mut rgb_buffer = vec![0u8; (rgb_stride * height) as usize];
+        let mut rgb_buffer = zeroed_output_buffer((rgb_stride * height) as usize);
 
-        yuv_nv12_to_rgb(
+        let convert = if format == PixelFormat::Nv21 {
+            yuv_nv21_to_rgb
+        } else {
+            yuv_nv12_to_rgb
+        };
+        convert(
             &bi_planar_image,
             &mut rgb_buffer,
             rgb_stride,
             YuvRange::Limited,
-            YuvStandardMatrix::Bt601,
+            standard_matrix(matrix),
             YuvConversionMode::Balanced,
         )
-        .map_err(|e| ConversionError(format!("NV12 conversion error: {:?}", e)))?;
+        .map_err(|e| ConversionError(format!("{} conversion error: {:?}", format, e)))?;
 
         // Log first conversion
-        static NV12_LOGGED: std::sync::atomic::AtomicBool =
-            std::sync::atomic::AtomicBool::new(false);
-        if !NV12_LOGGED.swap(true, std::sync::atomic::Ordering::Relaxed) {
+        if !logged.swap(true, Ordering::Relaxed) {
             log::info!(
-                "NV12 conversion: {}x{}, Y={}bytes, UV={}bytes -> RGB={}bytes",
+                "{} conversion: {}x{}, Y={}bytes, UV={}bytes -> RGB={}bytes",
+                format,
                 width,
                 height,
                 y_size,
@@ -379,11 +629,15 @@ mod android_impl {
 }
 
 // ============================================================================
-// Pure Rust implementation for desktop testing
+// Pure Rust implementation for desktop and testing
 // ============================================================================
 
+/// Scalar reference converters (desktop only)
+///
+/// Used on desktop unless the `simd-yuv` feature is enabled, and by the unit
+/// tests in every configuration.
 #[cfg(not(target_os = "android"))]
-mod desktop_impl {
+pub mod scalar {
     use super::*;
 
     /// Clamp a value to the 0-255 range
@@ -392,25 +646,23 @@ mod desktop_impl {
         val.clamp(0, 255) as u8
     }
 
-    /// Convert YUV to RGB using BT.601 limited range coefficients
+    /// Convert YUV to RGB using limited range coefficients
     ///
-    /// BT.601 limited range:
+    /// Limited range:
     /// - Y: 16-235 (scaled to 0-255)
     /// - U, V: 16-240, centered at 128
     #[inline]
-    fn yuv_to_rgb_bt601(y: u8, u: u8, v: u8) -> (u8, u8, u8) {
+    fn yuv_to_rgb(y: u8, u: u8, v: u8, coefficients: [i32; 4]) -> (u8, u8, u8) {
         // Expand limited range Y to full range
         let y = y as i32 - 16;
         let u = u as i32 - 128;
         let v = v as i32 - 128;
 
-        // BT.601 coefficients (scaled by 256 for integer math)
-        // R = 1.164 * Y + 1.596 * V
-        // G = 1.164 * Y - 0.392 * U - 0.813 * V
-        // B = 1.164 * Y + 2.017 * U
-        let r = (298 * y + 409 * v + 128) >> 8;
-        let g = (298 * y - 100 * u - 208 * v + 128) >> 8;
-        let b = (298 * y + 516 * u + 128) >> 8;
+        // Coefficients are scaled by 256 for integer math
+        let [r_v, g_u, g_v, b_u] = coefficients;
+        let r = (298 * y + r_v * v + 128) >> 8;
+        let g = (298 * y - g_u * u - g_v * v + 128) >> 8;
+        let b = (298 * y + b_u * u + 128) >> 8;
 
         (clamp_u8(r), clamp_u8(g), clamp_u8(b))
     }
@@ -426,6 +678,29 @@ mod desktop_impl {
         stride_override: Option<u32>,
         format: YuvPackedFormat,
     ) -> Result<Vec<u8>, ConversionError> {
+        convert_yuv422_to_rgb_with_matrix(
+            yuv_data,
+            width,
+            height,
+            stride_override,
+            format,
+            YuvMatrix::Bt601,
+        )
+    }
+
+    /// [`convert_yuv422_to_rgb`] with a color matrix
+    ///
+    /// # Errors
+    /// Returns `ConversionError` if the input data is too small for the specified dimensions.
+    pub fn convert_yuv422_to_rgb_with_matrix(
+        yuv_data: &[u8],
+        width: u32,
+        height: u32,
+        stride_override: Option<u32>,
+        format: YuvPackedFormat,
+        matrix: YuvMatrix,
+    ) -> Result<Vec<u8>, ConversionError> {
+        let coefficients = matrix.coefficients();
         let frame_size = yuv_data.len();
         let expected_stride = width * 2;
 
@@ -444,7 +719,7 @@ mod desktop_impl {
 
         // RGB output: 3 bytes per pixel
         let rgb_stride = (width * 3) as usize;
-        let mut rgb_buffer = vec![0u8; rgb_stride * height as usize];
+        let mut rgb_buffer = zeroed_output_buffer(rgb_stride * height as usize);
 
         for row in 0..height {
             let yuv_row_start = (row * actual_stride) as usize;
@@ -475,7 +750,7 @@ mod desktop_impl {
                 };
 
                 // Convert first pixel
-                let (r0, g0, b0) = yuv_to_rgb_bt601(y0, u, v);
+                let (r0, g0, b0) = yuv_to_rgb(y0, u, v, coefficients);
                 let rgb_offset = rgb_row_start + (col * 3) as usize;
                 rgb_buffer[rgb_offset] = r0;
                 rgb_buffer[rgb_offset + 1] = g0;
@@ -483,7 +758,7 @@ mod desktop_impl {
 
                 // Convert second pixel (if within bounds)
                 if col + 1 < width {
-                    let (r1, g1, b1) = yuv_to_rgb_bt601(y1, u, v);
+                    let (r1, g1, b1) = yuv_to_rgb(y1, u, v, coefficients);
                     rgb_buffer[rgb_offset + 3] = r1;
                     rgb_buffer[rgb_offset + 4] = g1;
                     rgb_buffer[rgb_offset + 5] = b1;
@@ -503,13 +778,65 @@ mod desktop_impl {
         width: u32,
         height: u32,
     ) -> Result<Vec<u8>, ConversionError> {
+        convert_i420_to_rgb_with_matrix(yuv_data, width, height, YuvMatrix::Bt601)
+    }
+
+    /// [`convert_i420_to_rgb`] with a color matrix
+    ///
+    /// # Errors
+    /// Returns `ConversionError` if the input data is too small for the specified dimensions.
+    pub fn convert_i420_to_rgb_with_matrix(
+        yuv_data: &[u8],
+        width: u32,
+        height: u32,
+        matrix: YuvMatrix,
+    ) -> Result<Vec<u8>, ConversionError> {
+        convert_planar_420(yuv_data, width, height, matrix, PixelFormat::I420)
+    }
+
+    /// Convert YV12 (planar YUV420, V plane first) frame to RGB
+    ///
+    /// # Errors
+    /// Returns `ConversionError` if the input data is too small for the specified dimensions.
+    pub fn convert_yv12_to_rgb(
+        yuv_data: &[u8],
+        width: u32,
+        height: u32,
+    ) -> Result<Vec<u8>, ConversionError> {
+        convert_yv12_to_rgb_with_matrix(yuv_data, width, height, YuvMatrix::Bt601)
+    }
+
+    /// [`convert_yv12_to_rgb`] with a color matrix
+    ///
+    /// # Errors
+    /// Returns `ConversionError` if the input data is too small for the specified dimensions.
+    pub fn convert_yv12_to_rgb_with_matrix(
+        yuv_data: &[u8],
+        width: u32,
+        height: u32,
+        matrix: YuvMatrix,
+    ) -> Result<Vec<u8>, ConversionError> {
+        convert_planar_420(yuv_data, width, height, matrix, PixelFormat::Yv12)
+    }
+
+    /// Shared I420/YV12 conversion; `format` decides which chroma plane
+    /// comes first
+    fn convert_planar_420(
+        yuv_data: &[u8],
+        width: u32,
+        height: u32,
+        matrix: YuvMatrix,
+        format: PixelFormat,
+    ) -> Result<Vec<u8>, ConversionError> {
+        let coefficients = matrix.coefficients();
         let y_size = (width * height) as usize;
         let uv_size = y_size / 4;
         let expected_size = y_size + uv_size * 2;
 
         if yuv_data.len() < expected_size {
             return Err(ConversionError(format!(
-                "I420 data too small: {} bytes, expected {} bytes for {}x{}",
+                "{} data too small: {} bytes, expected {} bytes for {}x{}",
+                format,
                 yuv_data.len(),
                 expected_size,
                 width,
@@ -518,11 +845,16 @@ mod desktop_impl {
         }
 
         let y_plane = &yuv_data[0..y_size];
-        let u_plane = &yuv_data[y_size..y_size + uv_size];
-        let v_plane = &yuv_data[y_size + uv_size..];
+        let first = &yuv_data[y_size..y_size + uv_size];
+        let second = &yuv_data[y_size + uv_size..];
+        let (u_plane, v_plane) = if format == PixelFormat::Yv12 {
+            (second, first)
+        } else {
+            (first, second)
+        };
 
         let rgb_stride = (width * 3) as usize;
-        let mut rgb_buffer = vec![0u8; rgb_stride * height as usize];
+        let mut rgb_buffer = zeroed_output_buffer(rgb_stride * height as usize);
 
         let uv_width = (width / 2) as usize;
 
@@ -538,7 +870,7 @@ mod desktop_impl {
                 let u = u_plane[uv_idx];
                 let v = v_plane[uv_idx];
 
-                let (r, g, b) = yuv_to_rgb_bt601(y, u, v);
+                let (r, g, b) = yuv_to_rgb(y, u, v, coefficients);
                 let rgb_offset = rgb_row_start + col * 3;
                 rgb_buffer[rgb_offset] = r;
                 rgb_buffer[rgb_offset + 1] = g;
@@ -558,13 +890,64 @@ mod desktop_impl {
         width: u32,
         height: u32,
     ) -> Result<Vec<u8>, ConversionError> {
+        convert_nv12_to_rgb_with_matrix(yuv_data, width, height, YuvMatrix::Bt601)
+    }
+
+    /// [`convert_nv12_to_rgb`] with a color matrix
+    ///
+    /// # Errors
+    /// Returns `ConversionError` if the input data is too small for the specified dimensions.
+    pub fn convert_nv12_to_rgb_with_matrix(
+        yuv_data: &[u8],
+        width: u32,
+        height: u32,
+        matrix: YuvMatrix,
+    ) -> Result<Vec<u8>, ConversionError> {
+        convert_semi_planar_420(yuv_data, width, height, matrix, PixelFormat::Nv12)
+    }
+
+    /// Convert NV21 (semi-planar YUV420, VU order) frame to RGB
+    ///
+    /// # Errors
+    /// Returns `ConversionError` if the input data is too small for the specified dimensions.
+    pub fn convert_nv21_to_rgb(
+        yuv_data: &[u8],
+        width: u32,
+        height: u32,
+    ) -> Result<Vec<u8>, ConversionError> {
+        convert_nv21_to_rgb_with_matrix(yuv_data, width, height, YuvMatrix::Bt601)
+    }
+
+    /// [`convert_nv21_to_rgb`] with a color matrix
+    ///
+    /// # Errors
+    /// Returns `ConversionError` if the input data is too small for the specified dimensions.
+    pub fn convert_nv21_to_rgb_with_matrix(
+        yuv_data: &[u8],
+        width: u32,
+        height: u32,
+        matrix: YuvMatrix,
+    ) -> Result<Vec<u8>, ConversionError> {
+        convert_semi_planar_420(yuv_data, width, height, matrix, PixelFormat::Nv21)
+    }
+
+    /// Shared NV12/NV21 conversion; `format` decides the chroma byte order
+    fn convert_semi_planar_420(
+        yuv_data: &[u8],
+        width: u32,
+        height: u32,
+        matrix: YuvMatrix,
+        format: PixelFormat,
+    ) -> Result<Vec<u8>, ConversionError> {
+        let coefficients = matrix.coefficients();
         let y_size = (width * height) as usize;
         let uv_size = y_size / 2;
         let expected_size = y_size + uv_size;
 
         if yuv_data.len() < expected_size {
             return Err(ConversionError(format!(
-                "NV12 data too small: {} bytes, expected {} bytes for {}x{}",
+                "{} data too small: {} bytes, expected {} bytes for {}x{}",
+                format,
                 yuv_data.len(),
                 expected_size,
                 width,
@@ -574,9 +957,15 @@ mod desktop_impl {
 
         let y_plane = &yuv_data[0..y_size];
         let uv_plane = &yuv_data[y_size..];
+        // Offsets of U and V within each interleaved chroma pair
+        let (u_offset, v_offset) = if format == PixelFormat::Nv21 {
+            (1, 0)
+        } else {
+            (0, 1)
+        };
 
         let rgb_stride = (width * 3) as usize;
-        let mut rgb_buffer = vec![0u8; rgb_stride * height as usize];
+        let mut rgb_buffer = zeroed_output_buffer(rgb_stride * height as usize);
 
         for row in 0..height as usize {
             let y_row_start = row * width as usize;
@@ -588,10 +977,10 @@ mod desktop_impl {
                 let y = y_plane[y_row_start + col];
                 let uv_col = (col / 2) * 2; // UV pairs are interleaved
                 let uv_idx = uv_row_start + uv_col;
-                let u = uv_plane[uv_idx];
-                let v = uv_plane[uv_idx + 1];
+                let u = uv_plane[uv_idx + u_offset];
+                let v = uv_plane[uv_idx + v_offset];
 
-                let (r, g, b) = yuv_to_rgb_bt601(y, u, v);
+                let (r, g, b) = yuv_to_rgb(y, u, v, coefficients);
                 let rgb_offset = rgb_row_start + col * 3;
                 rgb_buffer[rgb_offset] = r;
                 rgb_buffer[rgb_offset + 1] = g;
@@ -650,7 +1039,9 @@ pub fn pass_through_rgb888(
         );
     }
 
-    Ok(data[..expected].to_vec())
+    let mut rgb = output_buffer(expected);
+    rgb.extend_from_slice(&data[..expected]);
+    Ok(rgb)
 }
 
 /// Convert BGR888 to RGB888 by swapping R and B channels
@@ -697,7 +1088,7 @@ pub fn convert_bgr888_to_rgb(
     }
 
     // Swap B and R channels: BGR -> RGB
-    let mut rgb = Vec::with_capacity(expected);
+    let mut rgb = output_buffer(expected);
     for chunk in data[..expected].chunks_exact(3) {
         rgb.push(chunk[2]); // R (was at position 2 in BGR)
         rgb.push(chunk[1]); // G (stays in middle)
@@ -707,15 +1098,144 @@ pub fn convert_bgr888_to_rgb(
     Ok(rgb)
 }
 
+/// Interpolation used to fill in the two missing colors of Bayer pixels
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum DemosaicMethod {
+    /// Average of the nearest pixels of each color (3x3)
+    #[default]
+    Bilinear,
+    /// Bilinear corrected by the gradient of the pixel's own color (5x5,
+    /// Malvar-He-Cutler 2004); less color fringing at edges
+    Malvar,
+}
+
+/// Convert an 8-bit RGGB Bayer mosaic to RGB888
+///
+/// Even rows are R-G-R-G, odd rows G-B-G-B. Frames must have even, non-zero
+/// dimensions; pixels beyond the edges are taken from the nearest pixel of
+/// the same color.
+///
+/// # Errors
+/// Returns `ConversionError` if the dimensions are odd or zero, or the input
+/// data is too small for them.
+pub fn convert_bayer_rggb_to_rgb(
+    data: &[u8],
+    width: u32,
+    height: u32,
+    method: DemosaicMethod,
+) -> Result<Vec<u8>, ConversionError> {
+    if width == 0 || height == 0 || !width.is_multiple_of(2) || !height.is_multiple_of(2) {
+        return Err(ConversionError(format!(
+            "Bayer frames need even dimensions, got {}x{}",
+            width, height
+        )));
+    }
+    let (width, height) = (width as usize, height as usize);
+    let expected = width * height;
+    if data.len() < expected {
+        return Err(ConversionError(format!(
+            "Bayer data too small: {} bytes, expected {} for {}x{}",
+            data.len(),
+            expected,
+            width,
+            height
+        )));
+    }
+
+    let mut rgb = output_buffer(expected * 3);
+    for y in 0..height {
+        for x in 0..width {
+            // Sample at an offset, in the mosaic's own units
+            let at = |dx: isize, dy: isize| {
+                let sx = same_color_index(x as isize + dx, width);
+                let sy = same_color_index(y as isize + dy, height);
+                i32::from(data[sy * width + sx])
+            };
+            let pixel = match method {
+                DemosaicMethod::Bilinear => bilinear_pixel(&at, x % 2 == 0, y % 2 == 0),
+                DemosaicMethod::Malvar => malvar_pixel(&at, x % 2 == 0, y % 2 == 0),
+            };
+            rgb.extend(pixel.map(|c| c.clamp(0, 255) as u8));
+        }
+    }
+    Ok(rgb)
+}
+
+/// Index `i` kept inside `0..len` without changing its parity (and so its
+/// Bayer color); `len` is even
+fn same_color_index(i: isize, len: usize) -> usize {
+    if i < 0 {
+        i.rem_euclid(2) as usize
+    } else if i as usize >= len {
+        len - 2 + (i as usize - len) % 2
+    } else {
+        i as usize
+    }
+}
+
+/// RGB of one RGGB pixel from the average of its neighbours
+fn bilinear_pixel(at: &impl Fn(isize, isize) -> i32, even_x: bool, even_y: bool) -> [i32; 3] {
+    let center = at(0, 0);
+    let cross = (at(-1, 0) + at(1, 0) + at(0, -1) + at(0, 1) + 2) / 4;
+    let diagonal = (at(-1, -1) + at(1, -1) + at(-1, 1) + at(1, 1) + 2) / 4;
+    let row = (at(-1, 0) + at(1, 0) + 1) / 2;
+    let column = (at(0, -1) + at(0, 1) + 1) / 2;
+    match (even_x, even_y) {
+        (true, true) => [center, cross, diagonal],
+        (false, false) => [diagonal, cross, center],
+        // Green on a red row: red left and right, blue above and below
+        (false, true) => [row, center, column],
+        (true, false) => [column, center, row],
+    }
+}
+
+/// RGB of one RGGB pixel with Malvar-He-Cutler gradient-corrected kernels
+///
+/// Weights are doubled so the half-weight taps stay integers; each kernel
+/// sums to 16.
+fn malvar_pixel(at: &impl Fn(isize, isize) -> i32, even_x: bool, even_y: bool) -> [i32; 3] {
+    let center = at(0, 0);
+    let cross = at(-1, 0) + at(1, 0) + at(0, -1) + at(0, 1);
+    let cross2 = at(-2, 0) + at(2, 0) + at(0, -2) + at(0, 2);
+    let diagonal = at(-1, -1) + at(1, -1) + at(-1, 1) + at(1, 1);
+    let row = at(-1, 0) + at(1, 0);
+    let row2 = at(-2, 0) + at(2, 0);
+    let column = at(0, -1) + at(0, 1);
+    let column2 = at(0, -2) + at(0, 2);
+    let scale = |sum: i32| (sum + 8).div_euclid(16);
+
+    let green = || scale(8 * center + 4 * cross - 2 * cross2);
+    let opposite = || scale(12 * center + 4 * diagonal - 3 * cross2);
+    // Color of the horizontal neighbours of a green pixel, then the vertical ones
+    let along_row = || scale(10 * center + 8 * row - 2 * row2 - 2 * diagonal + column2);
+    let along_column = || scale(10 * center + 8 * column - 2 * column2 - 2 * diagonal + row2);
+    match (even_x, even_y) {
+        (true, true) => [center, green(), opposite()],
+        (false, false) => [opposite(), green(), center],
+        (false, true) => [along_row(), center, along_column()],
+        (true, false) => [along_column(), center, along_row()],
+    }
+}
+
 // ============================================================================
 // Re-export the platform-specific implementations
 // ============================================================================
 
-#[cfg(target_os = "android")]
-pub use android_impl::{convert_i420_to_rgb, convert_nv12_to_rgb, convert_yuv422_to_rgb};
-
-#[cfg(not(target_os = "android"))]
-pub use desktop_impl::{convert_i420_to_rgb, convert_nv12_to_rgb, convert_yuv422_to_rgb};
+#[cfg(any(target_os = "android", all(feature = "simd-yuv", not(test))))]
+pub use simd::{
+    convert_i420_to_rgb, convert_i420_to_rgb_with_matrix, convert_nv12_to_rgb,
+    convert_nv12_to_rgb_with_matrix, convert_nv21_to_rgb, convert_nv21_to_rgb_with_matrix,
+    convert_yuv422_to_rgb, convert_yuv422_to_rgb_with_matrix, convert_yv12_to_rgb,
+    convert_yv12_to_rgb_with_matrix,
+};
+
+#[cfg(not(any(target_os = "android", all(feature = "simd-yuv", not(test)))))]
+pub use scalar::{
+    convert_i420_to_rgb, convert_i420_to_rgb_with_matrix, convert_nv12_to_rgb,
+    convert_nv12_to_rgb_with_matrix, convert_nv21_to_rgb, convert_nv21_to_rgb_with_matrix,
+    convert_yuv422_to_rgb, convert_yuv422_to_rgb_with_matrix, convert_yv12_to_rgb,
+    convert_yv12_to_rgb_with_matrix,
+};
 
 /// Legacy wrapper for backward compatibility
 /// Defaults to YUYV format
@@ -737,6 +1257,47 @@ pub fn convert_yuy2_to_rgb(
     )
 }
 
+/// Convert a frame to RGB based on its pixel format
+///
+/// Dispatches to the converter registered for the format. `stride` is only
+/// used by the packed YUV 4:2:2 formats (YUYV/UYVY).
+///
+/// # Errors
+/// Returns `ConversionError` if the input data is too small for the specified dimensions.
+pub fn convert_to_rgb(
+    frame_data: &[u8],
+    width: u32,
+    height: u32,
+    stride: u32,
+    pixel_format: PixelFormat,
+) -> Result<Vec<u8>, ConversionError> {
+    (format_registry::entry(pixel_format).convert)(frame_data, width, height, stride)
+}
+
+/// [`convert_to_rgb`] into the allocation of `buffer`
+///
+/// The stream keeps the buffer its warm-up conversion produced (see
+/// `crate::warmup`) and converts its first frame into it, so that frame
+/// doesn't wait for a frame-sized allocation. `buffer` is reused when it has
+/// the capacity and grown otherwise.
+///
+/// # Errors
+/// Returns `ConversionError` if the input data is too small for the specified dimensions.
+pub fn convert_to_rgb_into(
+    frame_data: &[u8],
+    width: u32,
+    height: u32,
+    stride: u32,
+    pixel_format: PixelFormat,
+    buffer: Vec<u8>,
+) -> Result<Vec<u8>, ConversionError> {
+    OUTPUT_BUFFER.with(|lent| lent.set(buffer));
+    let result = convert_to_rgb(frame_data, width, height, stride, pixel_format);
+    // A converter that failed before taking the buffer leaves it lent
+    OUTPUT_BUFFER.with(Cell::take);
+    result
+}
+
 // ============================================================================
 // Unit Tests
 // ============================================================================
@@ -981,6 +1542,85 @@ mod tests {
         assert!(result.is_err(), "Should reject data that is too small");
     }
 
+    /// 4x2 frame with a distinct chroma value per 2x2 block, as
+    /// `(luma plane, U plane, V plane)`
+    fn chroma_test_planes() -> (Vec<u8>, Vec<u8>, Vec<u8>) {
+        let luma = vec![40, 80, 120, 160, 60, 100, 140, 180];
+        (luma, vec![90, 200], vec![170, 60])
+    }
+
+    #[test]
+    fn test_nv21_matches_nv12_with_swapped_chroma() {
+        let (luma, u, v) = chroma_test_planes();
+        let mut nv12 = luma.clone();
+        let mut nv21 = luma;
+        for (&u, &v) in u.iter().zip(&v) {
+            nv12.extend_from_slice(&[u, v]);
+            nv21.extend_from_slice(&[v, u]);
+        }
+
+        let rgb = convert_nv21_to_rgb(&nv21, 4, 2).unwrap();
+        assert_eq!(rgb, convert_nv12_to_rgb(&nv12, 4, 2).unwrap());
+        assert_eq!(
+            convert_nv21_to_rgb_with_matrix(&nv21, 4, 2, YuvMatrix::Bt709).unwrap(),
+            convert_nv12_to_rgb_with_matrix(&nv12, 4, 2, YuvMatrix::Bt709).unwrap()
+        );
+        // Reading the VU pairs in NV12 order gives other colors
+        assert_ne!(rgb, convert_nv12_to_rgb(&nv21, 4, 2).unwrap());
+    }
+
+    #[test]
+    fn test_yv12_matches_i420_with_swapped_planes() {
+        let (luma, u, v) = chroma_test_planes();
+        let i420 = [luma.as_slice(), &u, &v].concat();
+        let yv12 = [luma.as_slice(), &v, &u].concat();
+
+        let rgb = convert_yv12_to_rgb(&yv12, 4, 2).unwrap();
+        assert_eq!(rgb, convert_i420_to_rgb(&i420, 4, 2).unwrap());
+        assert_eq!(
+            convert_yv12_to_rgb_with_matrix(&yv12, 4, 2, YuvMatrix::Bt709).unwrap(),
+            convert_i420_to_rgb_with_matrix(&i420, 4, 2, YuvMatrix::Bt709).unwrap()
+        );
+        assert_ne!(rgb, convert_i420_to_rgb(&yv12, 4, 2).unwrap());
+    }
+
+    #[test]
+    fn test_nv21_and_yv12_reject_too_small_data() {
+        let yuv_data = vec![0u8; 100];
+        let err = convert_nv21_to_rgb(&yuv_data, 640, 480).unwrap_err();
+        assert!(err.0.starts_with("NV21 data too small"), "{}", err);
+        let err = convert_yv12_to_rgb(&yuv_data, 640, 480).unwrap_err();
+        assert!(err.0.starts_with("YV12 data too small"), "{}", err);
+    }
+
+    #[test]
+    fn test_convert_to_rgb_dispatches_nv21_and_yv12() {
+        let (luma, u, v) = chroma_test_planes();
+        let yv12 = [luma.as_slice(), &v, &u].concat();
+        assert_eq!(
+            convert_to_rgb(&yv12, 4, 2, 4, PixelFormat::Yv12).unwrap(),
+            convert_yv12_to_rgb(&yv12, 4, 2).unwrap()
+        );
+        assert_eq!(
+            convert_to_rgb(&yv12, 4, 2, 4, PixelFormat::Nv21).unwrap(),
+            convert_nv21_to_rgb(&yv12, 4, 2).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_convert_to_rgb_into_reuses_the_buffer() {
+        let yuyv = create_test_yuyv_frame(4, 2);
+        let expected = convert_to_rgb(&yuyv, 4, 2, 8, PixelFormat::Yuyv).unwrap();
+
+        let buffer = vec![7u8; 64];
+        let allocation = buffer.as_ptr();
+        let rgb = convert_to_rgb_into(&yuyv, 4, 2, 8, PixelFormat::Yuyv, buffer).unwrap();
+        assert_eq!(rgb, expected);
+        assert_eq!(rgb.as_ptr(), allocation);
+
+        assert!(convert_to_rgb_into(&[0; 4], 4, 2, 8, PixelFormat::Yuyv, rgb).is_err());
+    }
+
     #[test]
     fn test_rgb888_passthrough() {
         let width = 4u32;
@@ -1008,6 +1648,84 @@ mod tests {
         assert!(result.is_err());
     }
 
+    /// Mosaic an RGB image into RGGB
+    fn mosaic_rggb(
+        width: usize,
+        height: usize,
+        pixel: impl Fn(usize, usize) -> [u8; 3],
+    ) -> Vec<u8> {
+        let mut bayer = Vec::with_capacity(width * height);
+        for y in 0..height {
+            for x in 0..width {
+                let channel = match (x % 2, y % 2) {
+                    (0, 0) => 0,
+                    (1, 1) => 2,
+                    _ => 1,
+                };
+                bayer.push(pixel(x, y)[channel]);
+            }
+        }
+        bayer
+    }
+
+    #[test]
+    fn test_bayer_flat_color_is_restored() {
+        let bayer = mosaic_rggb(8, 6, |_, _| [200, 120, 40]);
+        for method in [DemosaicMethod::Bilinear, DemosaicMethod::Malvar] {
+            let rgb = convert_bayer_rggb_to_rgb(&bayer, 8, 6, method).unwrap();
+            assert_eq!(rgb.len(), 8 * 6 * 3);
+            for pixel in rgb.chunks_exact(3) {
+                assert_eq!(pixel, [200, 120, 40], "{:?}", method);
+            }
+        }
+    }
+
+    #[test]
+    fn test_bayer_gradient_is_restored_inside_the_border() {
+        // Each channel is a different linear ramp; both methods reproduce
+        // linear images exactly where the kernels fit inside the frame
+        let (width, height) = (12usize, 10usize);
+        let ramp = |x: usize, y: usize| {
+            let (x, y) = (x as u8, y as u8);
+            [10 + 4 * x + 2 * y, 100 + 3 * y, 200 - 5 * x - y]
+        };
+        let bayer = mosaic_rggb(width, height, ramp);
+        for method in [DemosaicMethod::Bilinear, DemosaicMethod::Malvar] {
+            let rgb =
+                convert_bayer_rggb_to_rgb(&bayer, width as u32, height as u32, method).unwrap();
+            for y in 2..height - 2 {
+                for x in 2..width - 2 {
+                    let i = (y * width + x) * 3;
+                    assert_eq!(rgb[i..i + 3], ramp(x, y), "{:?} at ({}, {})", method, x, y);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_malvar_sharpens_a_vertical_edge() {
+        // Dark left half, bright right half: bilinear blurs the missing
+        // colors across the edge, Malvar's gradient correction keeps it
+        // closer to the true value
+        let edge = |x: usize, _| if x < 4 { [20u8; 3] } else { [220u8; 3] };
+        let bayer = mosaic_rggb(8, 8, edge);
+        let bilinear = convert_bayer_rggb_to_rgb(&bayer, 8, 8, DemosaicMethod::Bilinear).unwrap();
+        let malvar = convert_bayer_rggb_to_rgb(&bayer, 8, 8, DemosaicMethod::Malvar).unwrap();
+        // Green at the red pixel (4, 4), just right of the edge
+        let i = (4 * 8 + 4) * 3 + 1;
+        assert!(malvar[i] > bilinear[i]);
+        assert!(220 - i32::from(malvar[i]) < 220 - i32::from(bilinear[i]));
+    }
+
+    #[test]
+    fn test_bayer_rejects_bad_sizes() {
+        let method = DemosaicMethod::Bilinear;
+        assert!(convert_bayer_rggb_to_rgb(&[0; 15], 5, 3, method).is_err());
+        assert!(convert_bayer_rggb_to_rgb(&[0; 4], 4, 2, method).is_err());
+        assert!(convert_bayer_rggb_to_rgb(&[], 0, 0, method).is_err());
+        assert!(convert_bayer_rggb_to_rgb(&[0; 4], 2, 2, method).is_ok());
+    }
+
     #[test]
     fn test_bgr888_to_rgb_swaps_channels() {
         let width = 2u32;
@@ -1157,4 +1875,85 @@ mod tests {
             );
         }
     }
+
+    /// The SIMD converters agree with the scalar reference within rounding
+    #[cfg(feature = "simd-yuv")]
+    #[test]
+    fn test_simd_matches_scalar() {
+        // yuvutils-rs uses different fixed-point precision and chroma rounding
+        const TOLERANCE: u8 = 4;
+        let (width, height) = (64u32, 16u32);
+        // Noisy luma, slowly varying chroma: chroma upsampling may differ
+        // (nearest vs. interpolated) without changing the result much
+        let luma = |x: u32, y: u32| ((x * 37 + y * 101) % 220 + 16) as u8;
+        let (cb, cr) = (
+            |x: u32, _y: u32| (64 + x) as u8,
+            |_x: u32, y: u32| (96 + 4 * y) as u8,
+        );
+        let assert_close = |simd: Vec<u8>, scalar: Vec<u8>, name: &str| {
+            assert_eq!(simd.len(), scalar.len(), "{} output size", name);
+            let worst = simd
+                .iter()
+                .zip(&scalar)
+                .map(|(a, b)| a.abs_diff(*b))
+                .max()
+                .unwrap_or(0);
+            assert!(worst <= TOLERANCE, "{} differs by up to {}", name, worst);
+        };
+
+        let mut yuyv = Vec::new();
+        let mut uyvy = Vec::new();
+        for y in 0..height {
+            for x in (0..width).step_by(2) {
+                let (y0, y1, u, v) = (luma(x, y), luma(x + 1, y), cb(x, y), cr(x, y));
+                yuyv.extend_from_slice(&[y0, u, y1, v]);
+                uyvy.extend_from_slice(&[u, y0, v, y1]);
+            }
+        }
+        for (packed, format) in [(yuyv, YuvPackedFormat::Yuyv), (uyvy, YuvPackedFormat::Uyvy)] {
+            assert_close(
+                simd::convert_yuv422_to_rgb(&packed, width, height, None, format).unwrap(),
+                scalar::convert_yuv422_to_rgb(&packed, width, height, None, format).unwrap(),
+                &format!("{:?}", format),
+            );
+        }
+
+        let luma_plane: Vec<u8> = (0..height)
+            .flat_map(|y| (0..width).map(move |x| luma(x, y)))
+            .collect();
+        let chroma_sites: Vec<(u32, u32)> = (0..height / 2)
+            .flat_map(|y| (0..width / 2).map(move |x| (x * 2, y * 2)))
+            .collect();
+        let mut i420 = luma_plane.clone();
+        i420.extend(chroma_sites.iter().map(|&(x, y)| cb(x, y)));
+        i420.extend(chroma_sites.iter().map(|&(x, y)| cr(x, y)));
+        let mut yv12 = luma_plane.clone();
+        yv12.extend(chroma_sites.iter().map(|&(x, y)| cr(x, y)));
+        yv12.extend(chroma_sites.iter().map(|&(x, y)| cb(x, y)));
+        let mut nv12 = luma_plane.clone();
+        nv12.extend(chroma_sites.iter().flat_map(|&(x, y)| [cb(x, y), cr(x, y)]));
+        let mut nv21 = luma_plane;
+        nv21.extend(chroma_sites.iter().flat_map(|&(x, y)| [cr(x, y), cb(x, y)]));
+
+        assert_close(
+            simd::convert_i420_to_rgb(&i420, width, height).unwrap(),
+            scalar::convert_i420_to_rgb(&i420, width, height).unwrap(),
+            "I420",
+        );
+        assert_close(
+            simd::convert_nv12_to_rgb(&nv12, width, height).unwrap(),
+            scalar::convert_nv12_to_rgb(&nv12, width, height).unwrap(),
+            "NV12",
+        );
+        assert_close(
+            simd::convert_yv12_to_rgb(&yv12, width, height).unwrap(),
+            scalar::convert_yv12_to_rgb(&yv12, width, height).unwrap(),
+            "YV12",
+        );
+        assert_close(
+            simd::convert_nv21_to_rgb(&nv21, width, height).unwrap(),
+            scalar::convert_nv21_to_rgb(&nv21, width, height).unwrap(),
+            "NV21",
+        );
+    }
 }
@@ -0,0 +1,252 @@
+//! Digital zoom and pan
+//!
+//! `set_zoom(factor, center_x, center_y)` crops every RGB frame to a region
+//! `1 / factor` of its size and scales the crop back up to the full frame
+//! size (bilinear), so small details can be inspected without moving the
+//! probe. The centre is given as a fraction of the frame (0.0-1.0, after
+//! rotation and flips) and is moved inwards as needed to keep the crop inside
+//! the frame.
+
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+/// Largest zoom factor
+pub const MAX_ZOOM: f32 = 8.0;
+
+/// Errors setting or applying a zoom
+#[derive(Debug, Error)]
+pub enum ZoomError {
+    /// Factor outside 1.0..=MAX_ZOOM
+    #[error("zoom factor must be between 1 and 8, got {0}")]
+    Factor(f32),
+    /// Centre outside the frame
+    #[error("zoom centre ({0}, {1}) must be within 0.0-1.0")]
+    Center(f32, f32),
+    /// Buffer is smaller than the dimensions need
+    #[error("{len} bytes is too small for a {width}x{height} RGB frame")]
+    FrameSize {
+        /// Buffer length
+        len: usize,
+        /// Frame width
+        width: u32,
+        /// Frame height
+        height: u32,
+    },
+}
+
+/// Result type alias for zoom operations
+pub type Result<T> = std::result::Result<T, ZoomError>;
+
+/// Zoom factor and centre applied to every frame
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Zoom {
+    /// Magnification (1.0: whole frame)
+    pub factor: f32,
+    /// Horizontal centre of the crop as a fraction of the frame width
+    pub center_x: f32,
+    /// Vertical centre of the crop as a fraction of the frame height
+    pub center_y: f32,
+}
+
+impl Default for Zoom {
+    fn default() -> Self {
+        Self {
+            factor: 1.0,
+            center_x: 0.5,
+            center_y: 0.5,
+        }
+    }
+}
+
+impl std::fmt::Display for Zoom {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{:.2}x around ({:.2}, {:.2})",
+            self.factor, self.center_x, self.center_y
+        )
+    }
+}
+
+/// Crop of a frame in source pixels
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CropRegion {
+    /// Left edge
+    pub x: f32,
+    /// Top edge
+    pub y: f32,
+    /// Width
+    pub width: f32,
+    /// Height
+    pub height: f32,
+}
+
+impl Zoom {
+    /// Validated zoom
+    ///
+    /// # Errors
+    ///
+    /// Returns `ZoomError::Factor` if `factor` is not within 1.0 to
+    /// [`MAX_ZOOM`], or `ZoomError::Center` if the centre is outside the frame.
+    pub fn new(factor: f32, center_x: f32, center_y: f32) -> Result<Self> {
+        if !(1.0..=MAX_ZOOM).contains(&factor) {
+            return Err(ZoomError::Factor(factor));
+        }
+        if !(0.0..=1.0).contains(&center_x) || !(0.0..=1.0).contains(&center_y) {
+            return Err(ZoomError::Center(center_x, center_y));
+        }
+        Ok(Self {
+            factor,
+            center_x,
+            center_y,
+        })
+    }
+
+    /// Whether frames pass through unchanged
+    #[must_use]
+    pub fn is_identity(&self) -> bool {
+        self.factor <= 1.0
+    }
+
+    /// Region of a `width` x `height` frame that is shown
+    #[must_use]
+    pub fn crop_region(&self, width: u32, height: u32) -> CropRegion {
+        let (w, h) = (width as f32, height as f32);
+        let factor = self.factor.clamp(1.0, MAX_ZOOM);
+        let (crop_w, crop_h) = (w / factor, h / factor);
+        CropRegion {
+            x: (self.center_x * w - crop_w / 2.0).clamp(0.0, w - crop_w),
+            y: (self.center_y * h - crop_h / 2.0).clamp(0.0, h - crop_h),
+            width: crop_w,
+            height: crop_h,
+        }
+    }
+
+    /// Crop an RGB24 frame and scale the crop back to `width` x `height`
+    ///
+    /// # Errors
+    ///
+    /// Returns `ZoomError::FrameSize` if `rgb` is smaller than
+    /// `width * height * 3`.
+    pub fn apply_rgb(&self, rgb: &[u8], width: u32, height: u32) -> Result<Vec<u8>> {
+        let (w, h) = (width as usize, height as usize);
+        if rgb.len() < w * h * 3 {
+            return Err(ZoomError::FrameSize {
+                len: rgb.len(),
+                width,
+                height,
+            });
+        }
+        let crop = self.crop_region(width, height);
+        let (scale_x, scale_y) = (crop.width / width as f32, crop.height / height as f32);
+        let max_x = w.saturating_sub(1) as f32;
+        let max_y = h.saturating_sub(1) as f32;
+
+        // Source column and weight of each output column
+        let columns: Vec<(usize, usize, f32)> = (0..w)
+            .map(|x| {
+                let sx = (crop.x + (x as f32 + 0.5) * scale_x - 0.5).clamp(0.0, max_x);
+                let x0 = sx as usize;
+                (x0, (x0 + 1).min(w - 1), sx - x0 as f32)
+            })
+            .collect();
+
+        let mut out = Vec::with_capacity(w * h * 3);
+        for y in 0..h {
+            let sy = (crop.y + (y as f32 + 0.5) * scale_y - 0.5).clamp(0.0, max_y);
+            let y0 = sy as usize;
+            let y1 = (y0 + 1).min(h - 1);
+            let fy = sy - y0 as f32;
+            let (row0, row1) = (&rgb[y0 * w * 3..], &rgb[y1 * w * 3..]);
+            for &(x0, x1, fx) in &columns {
+                for c in 0..3 {
+                    let top = lerp(row0[x0 * 3 + c], row0[x1 * 3 + c], fx);
+                    let bottom = lerp(row1[x0 * 3 + c], row1[x1 * 3 + c], fx);
+                    out.push((top + (bottom - top) * fy).round() as u8);
+                }
+            }
+        }
+        Ok(out)
+    }
+}
+
+fn lerp(a: u8, b: u8, t: f32) -> f32 {
+    f32::from(a) + (f32::from(b) - f32::from(a)) * t
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Frame whose red channel is the column and green channel the row
+    fn gradient(width: u32, height: u32) -> Vec<u8> {
+        (0..height)
+            .flat_map(|y| (0..width).flat_map(move |x| [x as u8, y as u8, 0]))
+            .collect()
+    }
+
+    #[test]
+    fn test_validation() {
+        assert!(Zoom::new(2.0, 0.5, 0.5).is_ok());
+        assert!(matches!(
+            Zoom::new(0.5, 0.5, 0.5),
+            Err(ZoomError::Factor(_))
+        ));
+        assert!(matches!(
+            Zoom::new(MAX_ZOOM + 1.0, 0.5, 0.5),
+            Err(ZoomError::Factor(_))
+        ));
+        assert!(matches!(
+            Zoom::new(2.0, 1.5, 0.5),
+            Err(ZoomError::Center(..))
+        ));
+        assert!(matches!(
+            Zoom::new(f32::NAN, 0.5, 0.5),
+            Err(ZoomError::Factor(_))
+        ));
+        assert!(Zoom::default().is_identity());
+    }
+
+    #[test]
+    fn test_crop_stays_inside_the_frame() {
+        let centred = Zoom::new(2.0, 0.5, 0.5).unwrap().crop_region(100, 50);
+        assert_eq!(
+            centred,
+            CropRegion {
+                x: 25.0,
+                y: 12.5,
+                width: 50.0,
+                height: 25.0
+            }
+        );
+
+        let corner = Zoom::new(4.0, 0.0, 1.0).unwrap().crop_region(100, 50);
+        assert_eq!((corner.x, corner.y), (0.0, 37.5));
+    }
+
+    #[test]
+    fn test_zoom_magnifies_the_crop() {
+        let rgb = gradient(16, 8);
+        let out = Zoom::new(2.0, 0.0, 0.0)
+            .unwrap()
+            .apply_rgb(&rgb, 16, 8)
+            .unwrap();
+        assert_eq!(out.len(), rgb.len());
+
+        // The top-left quarter (columns 0-7, rows 0-3) now fills the frame
+        let red_at = |x: usize, y: usize| out[(y * 16 + x) * 3];
+        let green_at = |x: usize, y: usize| out[(y * 16 + x) * 3 + 1];
+        assert_eq!(red_at(0, 0), 0);
+        assert_eq!(red_at(15, 0), 7);
+        assert_eq!(green_at(0, 7), 3);
+        assert_eq!(red_at(8, 0), 4);
+    }
+
+    #[test]
+    fn test_short_buffer_is_rejected() {
+        assert!(matches!(
+            Zoom::new(2.0, 0.5, 0.5).unwrap().apply_rgb(&[0; 10], 4, 4),
+            Err(ZoomError::FrameSize { len: 10, .. })
+        ));
+    }
+}
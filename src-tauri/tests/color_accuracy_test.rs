@@ -0,0 +1,208 @@
+//! Color accuracy of the YUV converters.
+//!
+//! Every 75% SMPTE color bar is encoded in each YUV layout with its BT.601 and
+//! BT.709 codes, converted back to RGB with the matching matrix and compared
+//! against the reference RGB with a per-channel tolerance:
+//!
+//! ```text
+//! SMPTE bars → YCbCr codes → YUYV / UYVY / I420 / YV12 / NV12 / NV21 → RGB → reference ± tolerance
+//! ```
+
+use clean_scope_lib::test_utils::{Rgb, Tolerance, SMPTE_BARS};
+use clean_scope_lib::yuv_conversion::{
+    convert_i420_to_rgb_with_matrix, convert_nv12_to_rgb_with_matrix,
+    convert_nv21_to_rgb_with_matrix, convert_yuv422_to_rgb_with_matrix,
+    convert_yv12_to_rgb_with_matrix, ConversionError, YuvMatrix, YuvPackedFormat,
+};
+
+/// Width of each bar; even so 4:2:0 and 4:2:2 chroma never straddles two bars
+const BAR_WIDTH: u32 = 2;
+/// Frame height; even for 4:2:0
+const HEIGHT: u32 = 2;
+
+/// Extra tolerance for the yuvutils-rs converters, which round differently
+/// from the scalar ones (see `test_simd_matches_scalar`)
+const SIMD_SLACK: u8 = if cfg!(feature = "simd-yuv") { 4 } else { 0 };
+
+/// YUV byte layouts under test
+#[derive(Debug, Clone, Copy)]
+enum Layout {
+    Yuyv,
+    Uyvy,
+    I420,
+    Yv12,
+    Nv12,
+    Nv21,
+}
+
+/// Per-channel tolerance against the reference RGB
+///
+/// The integer coefficients land within one code value of the exact matrices
+/// for every bar; green sums two chroma terms and gets one more.
+const TOLERANCE: Tolerance = Tolerance { r: 1, g: 2, b: 1 };
+
+/// Every layout with both matrices
+const CASES: [(Layout, YuvMatrix); 12] = [
+    (Layout::Yuyv, YuvMatrix::Bt601),
+    (Layout::Uyvy, YuvMatrix::Bt601),
+    (Layout::I420, YuvMatrix::Bt601),
+    (Layout::Yv12, YuvMatrix::Bt601),
+    (Layout::Nv12, YuvMatrix::Bt601),
+    (Layout::Nv21, YuvMatrix::Bt601),
+    (Layout::Yuyv, YuvMatrix::Bt709),
+    (Layout::Uyvy, YuvMatrix::Bt709),
+    (Layout::I420, YuvMatrix::Bt709),
+    (Layout::Yv12, YuvMatrix::Bt709),
+    (Layout::Nv12, YuvMatrix::Bt709),
+    (Layout::Nv21, YuvMatrix::Bt709),
+];
+
+/// Frame width holding every bar
+fn frame_width() -> u32 {
+    BAR_WIDTH * SMPTE_BARS.len() as u32
+}
+
+/// Encode the bars in `layout` with the codes of `matrix`
+fn encode_bars(layout: Layout, matrix: YuvMatrix) -> Vec<u8> {
+    let width = frame_width();
+    let columns = |x: u32| SMPTE_BARS[(x / BAR_WIDTH) as usize].ycbcr(matrix);
+    let mut frame = Vec::new();
+    match layout {
+        Layout::Yuyv | Layout::Uyvy => {
+            for _ in 0..HEIGHT {
+                for x in (0..width).step_by(2) {
+                    let (y, u, v) = columns(x);
+                    match layout {
+                        Layout::Yuyv => frame.extend([y, u, y, v]),
+                        _ => frame.extend([u, y, v, y]),
+                    }
+                }
+            }
+        }
+        Layout::I420 | Layout::Yv12 | Layout::Nv12 | Layout::Nv21 => {
+            for _ in 0..HEIGHT {
+                frame.extend((0..width).map(|x| columns(x).0));
+            }
+            let chroma: Vec<(u8, u8)> = (0..HEIGHT / 2)
+                .flat_map(|_| (0..width).step_by(2).map(|x| (columns(x).1, columns(x).2)))
+                .collect();
+            match layout {
+                Layout::I420 => {
+                    frame.extend(chroma.iter().map(|&(u, _)| u));
+                    frame.extend(chroma.iter().map(|&(_, v)| v));
+                }
+                Layout::Yv12 => {
+                    frame.extend(chroma.iter().map(|&(_, v)| v));
+                    frame.extend(chroma.iter().map(|&(u, _)| u));
+                }
+                Layout::Nv21 => frame.extend(chroma.iter().flat_map(|&(u, v)| [v, u])),
+                _ => frame.extend(chroma.iter().flat_map(|&(u, v)| [u, v])),
+            }
+        }
+    }
+    frame
+}
+
+/// Convert a frame in `layout` to RGB888 with `matrix`
+fn convert(layout: Layout, frame: &[u8], matrix: YuvMatrix) -> Result<Vec<u8>, ConversionError> {
+    let width = frame_width();
+    match layout {
+        Layout::Yuyv => convert_yuv422_to_rgb_with_matrix(
+            frame,
+            width,
+            HEIGHT,
+            None,
+            YuvPackedFormat::Yuyv,
+            matrix,
+        ),
+        Layout::Uyvy => convert_yuv422_to_rgb_with_matrix(
+            frame,
+            width,
+            HEIGHT,
+            None,
+            YuvPackedFormat::Uyvy,
+            matrix,
+        ),
+        Layout::I420 => convert_i420_to_rgb_with_matrix(frame, width, HEIGHT, matrix),
+        Layout::Yv12 => convert_yv12_to_rgb_with_matrix(frame, width, HEIGHT, matrix),
+        Layout::Nv12 => convert_nv12_to_rgb_with_matrix(frame, width, HEIGHT, matrix),
+        Layout::Nv21 => convert_nv21_to_rgb_with_matrix(frame, width, HEIGHT, matrix),
+    }
+}
+
+/// Every pixel that misses its bar's reference color, as failure messages
+fn color_errors(
+    layout: Layout,
+    encoded_with: YuvMatrix,
+    decoded_with: YuvMatrix,
+    tolerance: Tolerance,
+) -> Vec<String> {
+    let frame = encode_bars(layout, encoded_with);
+    let rgb = convert(layout, &frame, decoded_with)
+        .unwrap_or_else(|e| panic!("{:?} {:?}: {}", layout, decoded_with, e));
+    let width = frame_width();
+    assert_eq!(rgb.len(), (width * HEIGHT * 3) as usize);
+
+    let mut errors = Vec::new();
+    for (i, pixel) in rgb.chunks_exact(3).enumerate() {
+        let bar = &SMPTE_BARS[(i as u32 % width / BAR_WIDTH) as usize];
+        let actual = Rgb {
+            r: pixel[0],
+            g: pixel[1],
+            b: pixel[2],
+        };
+        if !tolerance.accepts(bar.rgb, actual) {
+            errors.push(format!(
+                "{:?} {:?} {} at pixel {}: expected {:?}, got {:?}",
+                layout, decoded_with, bar.name, i, bar.rgb, actual
+            ));
+        }
+    }
+    errors
+}
+
+#[test]
+fn test_smpte_bars_are_within_tolerance() {
+    let errors: Vec<String> = CASES
+        .iter()
+        .flat_map(|&(layout, matrix)| {
+            color_errors(layout, matrix, matrix, TOLERANCE.widen(SIMD_SLACK))
+        })
+        .collect();
+    assert!(errors.is_empty(), "{}", errors.join("\n"));
+}
+
+#[test]
+fn test_wrong_matrix_is_out_of_tolerance() {
+    // The tolerances must be tight enough to tell the matrices apart
+    for &(layout, matrix) in &CASES {
+        let other = match matrix {
+            YuvMatrix::Bt601 => YuvMatrix::Bt709,
+            YuvMatrix::Bt709 => YuvMatrix::Bt601,
+        };
+        let errors = color_errors(layout, other, matrix, TOLERANCE.widen(SIMD_SLACK));
+        assert!(
+            !errors.is_empty(),
+            "{:?} {:?} accepted {:?} codes",
+            layout,
+            matrix,
+            other
+        );
+    }
+}
+
+#[test]
+fn test_default_converters_decode_bt601() {
+    // The converters used by the live pipeline assume BT.601
+    let frame = encode_bars(Layout::Yuyv, YuvMatrix::Bt601);
+    let matrix_rgb = convert(Layout::Yuyv, &frame, YuvMatrix::Bt601).unwrap();
+    let default_rgb = clean_scope_lib::yuv_conversion::convert_yuv422_to_rgb(
+        &frame,
+        frame_width(),
+        HEIGHT,
+        None,
+        YuvPackedFormat::Yuyv,
+    )
+    .unwrap();
+    assert_eq!(matrix_rgb, default_rgb);
+}
@@ -10,9 +10,20 @@
 
 use clean_scope_lib::frame_assembler::{FrameAssembler, ProcessResult};
 use clean_scope_lib::frame_validation::{validate_yuy2_frame, ValidationLevel};
-use clean_scope_lib::test_utils::{PacketGenerator, Rgb};
+use clean_scope_lib::test_utils::{
+    assert_color_near, check_frame_counters, read_frame_counter_rgb, HeaderStyle, PacketGenerator,
+    PayloadDistribution, QuirkProfile, Rgb, SimulatedCamera, Tolerance,
+};
 use clean_scope_lib::yuv_conversion::{convert_yuv422_to_rgb, YuvPackedFormat};
 
+/// How far solid colors may drift through the generator's BT.601 encoding
+/// (truncated to 8 bits) and back; the SIMD converters round differently
+const PIPELINE_TOLERANCE: Tolerance = if cfg!(feature = "simd-yuv") {
+    Tolerance::uniform(6)
+} else {
+    Tolerance::uniform(2)
+};
+
 /// Helper to assemble frames from packets
 fn assemble_frame(packets: &[Vec<u8>], width: u32, height: u32) -> Option<Vec<u8>> {
     let mut assembler = FrameAssembler::new_yuy2(width, height);
@@ -95,30 +106,8 @@ fn test_complete_pipeline_yuy2_solid_red() {
         "RGB output should be width * height * 3 bytes"
     );
 
-    // Verify red color dominates
-    // Red in YUV (BT.601 limited range) should convert back to high R, low G, low B
-    let (r, g, b) = (rgb[0], rgb[1], rgb[2]);
-    assert!(
-        r > 150,
-        "Red channel should be high for red color, got R={}, G={}, B={}",
-        r,
-        g,
-        b
-    );
-    assert!(
-        g < 100,
-        "Green channel should be low for red color, got R={}, G={}, B={}",
-        r,
-        g,
-        b
-    );
-    assert!(
-        b < 100,
-        "Blue channel should be low for red color, got R={}, G={}, B={}",
-        r,
-        g,
-        b
-    );
+    // Red in YUV (BT.601 limited range) should convert back to red
+    assert_color_near(&rgb[..3], Rgb::RED, PIPELINE_TOLERANCE);
 }
 
 #[test]
@@ -165,28 +154,7 @@ fn test_complete_pipeline_yuy2_solid_green() {
         .expect("Conversion should succeed");
 
     // Verify green color
-    let (r, g, b) = (rgb[0], rgb[1], rgb[2]);
-    assert!(
-        g > 150,
-        "Green channel should be high for green color, got R={}, G={}, B={}",
-        r,
-        g,
-        b
-    );
-    assert!(
-        r < 100,
-        "Red channel should be low for green color, got R={}, G={}, B={}",
-        r,
-        g,
-        b
-    );
-    assert!(
-        b < 100,
-        "Blue channel should be low for green color, got R={}, G={}, B={}",
-        r,
-        g,
-        b
-    );
+    assert_color_near(&rgb[..3], Rgb::GREEN, PIPELINE_TOLERANCE);
 }
 
 #[test]
@@ -227,28 +195,7 @@ fn test_complete_pipeline_yuy2_solid_blue() {
     let rgb = convert_yuv422_to_rgb(yuy2_frame, width, height, None, YuvPackedFormat::Yuyv)
         .expect("Conversion should succeed");
 
-    let (r, g, b) = (rgb[0], rgb[1], rgb[2]);
-    assert!(
-        b > 150,
-        "Blue channel should be high for blue color, got R={}, G={}, B={}",
-        r,
-        g,
-        b
-    );
-    assert!(
-        r < 100,
-        "Red channel should be low for blue color, got R={}, G={}, B={}",
-        r,
-        g,
-        b
-    );
-    assert!(
-        g < 100,
-        "Green channel should be low for blue color, got R={}, G={}, B={}",
-        r,
-        g,
-        b
-    );
+    assert_color_near(&rgb[..3], Rgb::BLUE, PIPELINE_TOLERANCE);
 }
 
 #[test]
@@ -424,6 +371,136 @@ fn test_pipeline_multiple_frames_sequence() {
     }
 }
 
+/// Run sequence frames through assembly and conversion, reading each output counter
+fn sequence_counters(frames: &[&[Vec<u8>]], width: u32, height: u32) -> Vec<Option<u32>> {
+    let assembler = FrameAssembler::new_yuy2(width, height);
+    assembled_counters(assembler, frames, width, height)
+}
+
+/// Like `sequence_counters`, with a preconfigured assembler
+fn assembled_counters(
+    mut assembler: FrameAssembler,
+    frames: &[&[Vec<u8>]],
+    width: u32,
+    height: u32,
+) -> Vec<Option<u32>> {
+    let mut counters = Vec::new();
+    for packets in frames {
+        for packet in packets.iter() {
+            if let ProcessResult::Frame(frame) = assembler.process_packet(packet) {
+                // Frames cut short by lost packets fail conversion and count as unreadable
+                let rgb =
+                    convert_yuv422_to_rgb(&frame, width, height, None, YuvPackedFormat::Yuyv).ok();
+                counters.push(rgb.and_then(|rgb| read_frame_counter_rgb(&rgb, width)));
+            }
+        }
+    }
+    counters
+}
+
+#[test]
+fn test_pipeline_sequence_delivers_every_frame() {
+    let (width, height) = (96u32, 64u32);
+    let sequence = PacketGenerator::new(1024).sequence_with_size(width, height, 12, 17);
+    let frames: Vec<&[Vec<u8>]> = sequence.iter().map(|f| f.packets.as_slice()).collect();
+
+    let check = check_frame_counters(sequence_counters(&frames, width, height), 0..12);
+
+    // The first frame may be lost while the assembler syncs
+    assert!(
+        check.dropped.is_empty() || check.dropped == [0],
+        "{:?}",
+        check
+    );
+    assert!(check.duplicated.is_empty(), "{:?}", check);
+    assert!(check.reordered.is_empty(), "{:?}", check);
+    assert_eq!(check.unreadable, 0);
+}
+
+#[test]
+fn test_pipeline_sequence_reports_dropped_and_repeated_frames() {
+    let (width, height) = (96u32, 64u32);
+    let sequence = PacketGenerator::new(1024).sequence_with_size(width, height, 12, 23);
+    // Lose frame 5 in transit and deliver frame 8 twice
+    let mut frames: Vec<&[Vec<u8>]> = Vec::new();
+    for frame in &sequence {
+        match frame.index {
+            5 => {}
+            8 => frames.extend([frame.packets.as_slice(), frame.packets.as_slice()]),
+            _ => frames.push(frame.packets.as_slice()),
+        }
+    }
+
+    let check = check_frame_counters(sequence_counters(&frames, width, height), 1..12);
+
+    assert_eq!(check.dropped, vec![5], "{:?}", check);
+    assert_eq!(check.duplicated, vec![8], "{:?}", check);
+    assert!(check.reordered.is_empty(), "{:?}", check);
+}
+
+#[test]
+fn test_pipeline_sequence_with_variable_payload_sizes() {
+    let (width, height) = (96u32, 64u32);
+    for seed in 0..8 {
+        let mut gen = PacketGenerator::new(1024).with_payload_distribution(PayloadDistribution {
+            seed,
+            short_packet_rate: 0.5,
+            empty_packet_rate: 0.2,
+            header_only_rate: 0.2,
+            ..Default::default()
+        });
+        let sequence = gen.sequence_with_size(width, height, 12, seed);
+        let frames: Vec<&[Vec<u8>]> = sequence.iter().map(|f| f.packets.as_slice()).collect();
+
+        let check = check_frame_counters(sequence_counters(&frames, width, height), 0..12);
+
+        // The first frame may be lost while the assembler syncs
+        assert!(
+            check.dropped.is_empty() || check.dropped == [0],
+            "seed {}: {:?}",
+            seed,
+            check
+        );
+        assert!(check.duplicated.is_empty(), "seed {}: {:?}", seed, check);
+        assert_eq!(check.unreadable, 0, "seed {}: {:?}", seed, check);
+    }
+}
+
+#[test]
+fn test_pipeline_quirk_profiles() {
+    let (width, height) = (96u32, 64u32);
+    let sequence = PacketGenerator::default().sequence_with_size(width, height, 40, 5);
+
+    for profile in QuirkProfile::builtin() {
+        let name = profile.name.clone();
+        let expects_errors = profile.error_packet_rate > 0.0;
+        let mut assembler = FrameAssembler::new_yuy2(width, height);
+        assembler.set_headerless(profile.header_style == HeaderStyle::None);
+        let mut camera = SimulatedCamera::new(profile, 99);
+        let simulated: Vec<_> = sequence.iter().map(|f| camera.packetize(&f.data)).collect();
+        let frames: Vec<&[Vec<u8>]> = simulated.iter().map(|f| f.packets.as_slice()).collect();
+
+        let counters = assembled_counters(assembler, &frames, width, height);
+        let check = check_frame_counters(counters, 0..40);
+
+        // Frames with a packet flagged as bad lose data, and the first frame
+        // may be lost while the assembler syncs; every other frame must come through
+        let damaged: Vec<u32> = (0..40)
+            .filter(|&i| i == 0 || simulated[i as usize].error_packets > 0)
+            .collect();
+        assert_eq!(damaged.len() > 1, expects_errors, "{}", name);
+        assert!(
+            check.dropped.iter().all(|i| damaged.contains(i)),
+            "{}: {:?} (damaged {:?})",
+            name,
+            check,
+            damaged
+        );
+        assert!(check.duplicated.is_empty(), "{}: {:?}", name, check);
+        assert!(check.reordered.is_empty(), "{}: {:?}", name, check);
+    }
+}
+
 // ============================================================================
 // Validation Level Tests
 // ============================================================================
@@ -521,12 +598,8 @@ fn test_pipeline_yuyv_format() {
     let (r_yuyv, g_yuyv, b_yuyv) = (rgb_yuyv[0], rgb_yuyv[1], rgb_yuyv[2]);
     let (r_uyvy, g_uyvy, b_uyvy) = (rgb_uyvy[0], rgb_uyvy[1], rgb_uyvy[2]);
 
-    // YUYV should have high red (correct format)
-    assert!(
-        r_yuyv > 150,
-        "YUYV (correct) should have high red: R={}",
-        r_yuyv
-    );
+    // YUYV should produce red (correct format)
+    assert_color_near(&rgb_yuyv[..3], Rgb::RED, PIPELINE_TOLERANCE);
 
     // UYVY result should be different (wrong format swaps byte interpretation)
     assert!(
@@ -0,0 +1,65 @@
+//! Fuzz harness: feeds a packet stream from stdin through the pipeline
+//!
+//! Built for `wasm32-wasip1` so fuzz inputs can run sandboxed under wasmtime:
+//!
+//! ```bash
+//! cargo build --release --target wasm32-wasip1 --bin fuzz_packets
+//! wasmtime target/wasm32-wasip1/release/fuzz_packets.wasm < input.bin
+//! ```
+//!
+//! Input layout: one mode byte (bit 0: MJPEG, bit 1: headerless), then
+//! packets as `[u16 little-endian length][payload]` until end of input. Every
+//! assembled raw frame is validated and converted to RGB; the harness must
+//! never panic.
+
+use std::io::Read;
+
+use clean_scope_wasm::frame_assembler::{FrameAssembler, ProcessResult};
+use clean_scope_wasm::frame_validation::{validate_yuy2_frame, ValidationLevel};
+use clean_scope_wasm::yuv_conversion::convert_to_rgb;
+use clean_scope_wasm::PixelFormat;
+
+const WIDTH: u32 = 64;
+const HEIGHT: u32 = 48;
+
+fn main() {
+    let mut input = Vec::new();
+    if let Err(e) = std::io::stdin().read_to_end(&mut input) {
+        eprintln!("Failed to read input: {e}");
+        std::process::exit(1);
+    }
+    let Some((&mode, mut rest)) = input.split_first() else {
+        return;
+    };
+
+    let mjpeg = mode & 1 != 0;
+    let frame_size = PixelFormat::Yuyv.frame_size(WIDTH, HEIGHT);
+    let mut assembler = if mjpeg {
+        FrameAssembler::new_mjpeg()
+    } else {
+        FrameAssembler::new(frame_size)
+    };
+    assembler.set_headerless(mode & 2 != 0);
+
+    let mut frames = 0usize;
+    while rest.len() >= 2 {
+        let len = usize::from(u16::from_le_bytes([rest[0], rest[1]])).min(rest.len() - 2);
+        let (packet, tail) = rest[2..].split_at(len);
+        rest = tail;
+
+        if let ProcessResult::Frame(frame) = assembler.process_packet(packet) {
+            frames += 1;
+            if !mjpeg {
+                let _ = validate_yuy2_frame(
+                    &frame,
+                    WIDTH as usize,
+                    HEIGHT as usize,
+                    frame_size,
+                    ValidationLevel::Strict,
+                );
+                let _ = convert_to_rgb(&frame, WIDTH, HEIGHT, WIDTH * 2, PixelFormat::Yuyv);
+            }
+        }
+    }
+    println!("{frames} frames");
+}
@@ -0,0 +1,98 @@
+//! JavaScript API
+//!
+//! ```js
+//! import init, { Assembler, convertToRgb, validateYuy2 } from "./pkg/clean_scope_wasm.js";
+//!
+//! await init();
+//! const assembler = new Assembler(640 * 480 * 2, false);
+//! for (const packet of packets) {
+//!   const frame = assembler.push(packet);
+//!   if (frame) draw(convertToRgb(frame, 640, 480, 0, "YUYV"));
+//! }
+//! ```
+
+use wasm_bindgen::prelude::*;
+
+use crate::frame_assembler::{FrameAssembler, ProcessResult};
+use crate::frame_validation::{validate_yuy2_frame, ValidationLevel};
+use crate::yuv_conversion::convert_to_rgb;
+use crate::PixelFormat;
+
+/// Assembles frames from UVC payload packets
+#[wasm_bindgen]
+pub struct Assembler {
+    inner: FrameAssembler,
+}
+
+#[wasm_bindgen]
+impl Assembler {
+    /// Creates an assembler for frames of `expected_frame_size` bytes, or for
+    /// MJPEG (EOF-delimited) frames with `mjpeg`
+    #[wasm_bindgen(constructor)]
+    pub fn new(expected_frame_size: usize, mjpeg: bool) -> Assembler {
+        let inner = if mjpeg {
+            FrameAssembler::new_mjpeg()
+        } else {
+            FrameAssembler::new(expected_frame_size)
+        };
+        Assembler { inner }
+    }
+
+    /// Treats packets as raw payload without UVC headers
+    #[wasm_bindgen(js_name = setHeaderless)]
+    pub fn set_headerless(&mut self, enabled: bool) {
+        self.inner.set_headerless(enabled);
+    }
+
+    /// Feeds one packet; returns the completed frame, if any
+    pub fn push(&mut self, packet: &[u8]) -> Option<Vec<u8>> {
+        match self.inner.process_packet(packet) {
+            ProcessResult::Frame(frame) => Some(frame),
+            ProcessResult::Accumulating | ProcessResult::Skipped => None,
+        }
+    }
+
+    /// Drops any partial frame and waits for the next frame boundary
+    pub fn reset(&mut self) {
+        self.inner.reset();
+    }
+}
+
+/// Converts a raw frame to packed RGB888
+///
+/// `pixelFormat` is a format name such as `"YUYV"`, `"NV12"` or `"RGB24"`;
+/// `stride` is the row stride of packed 4:2:2 frames (0: `width * 2`).
+#[wasm_bindgen(js_name = convertToRgb)]
+pub fn convert_to_rgb_js(
+    data: &[u8],
+    width: u32,
+    height: u32,
+    stride: u32,
+    pixel_format: &str,
+) -> Result<Vec<u8>, JsError> {
+    let pixel_format: PixelFormat = pixel_format.parse().map_err(|e: String| JsError::new(&e))?;
+    let stride = if stride == 0 { width * 2 } else { stride };
+    convert_to_rgb(data, width, height, stride, pixel_format).map_err(|e| JsError::new(&e.0))
+}
+
+/// Checks a YUY2 frame for corruption
+///
+/// `level` is `"strict"`, `"moderate"`, `"minimal"` or `"off"`. Returns the
+/// `ValidationResult` as a plain object (snake_case fields).
+#[wasm_bindgen(js_name = validateYuy2)]
+pub fn validate_yuy2_js(
+    data: &[u8],
+    width: usize,
+    height: usize,
+    expected_size: usize,
+    level: &str,
+) -> Result<JsValue, JsError> {
+    let result = validate_yuy2_frame(
+        data,
+        width,
+        height,
+        expected_size,
+        ValidationLevel::from_env_str(level),
+    );
+    serde_wasm_bindgen::to_value(&result).map_err(|e| JsError::new(&e.to_string()))
+}
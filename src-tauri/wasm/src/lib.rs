@@ -0,0 +1,34 @@
+//! WebAssembly build of the `CleanScope` frame processing core
+//!
+//! Compiles the Tauri-free pipeline modules of `clean_scope_lib` (frame
+//! assembly, YUV conversion, frame validation) for `wasm32`. The modules are
+//! included from `../src` rather than copied, so the browser demo and the app
+//! run the same code.
+//!
+//! - `wasm32-unknown-unknown`: JavaScript bindings via `wasm-bindgen`, for
+//!   processing replayed captures client-side (`wasm-pack build --target web`)
+//! - `wasm32-wasip1`: plain library plus the `fuzz_packets` harness and the
+//!   module tests, runnable under wasmtime
+
+#[path = "../../src/frame_assembler.rs"]
+pub mod frame_assembler;
+#[path = "../../src/frame_boundary.rs"]
+pub mod frame_boundary;
+#[path = "../../src/format_registry.rs"]
+pub mod format_registry;
+#[path = "../../src/frame_validation.rs"]
+pub mod frame_validation;
+#[path = "../../src/pixel_format.rs"]
+pub mod pixel_format;
+#[path = "../../src/yuv_conversion.rs"]
+pub mod yuv_conversion;
+
+#[cfg(test)]
+#[path = "../../src/test_utils/mod.rs"]
+pub mod test_utils;
+
+#[cfg(all(target_arch = "wasm32", target_os = "unknown"))]
+mod bindings;
+
+pub use frame_validation::ValidationLevel;
+pub use pixel_format::PixelFormat;
@@ -5,16 +5,104 @@ export interface ResolutionInfo {
   available_count: number;
 }
 
+/** Frame rate of a resolution, returned by `get_framerates` and `set_framerate` */
+export interface FrameRate {
+  fps: number;
+  /** `dwFrameInterval` in 100 ns units */
+  frame_interval: number;
+  active: boolean;
+}
+
+/** Parameters the camera agreed to in probe/commit, returned by `get_stream_info` */
+export interface StreamInfo {
+  /** Format name, e.g. "MJPEG" or "YUY2" */
+  format: string;
+  format_index: number;
+  frame_index: number;
+  width: number;
+  height: number;
+  /** 0 if the camera chose no interval */
+  fps: number;
+  /** `dwFrameInterval` in 100 ns units */
+  frame_interval: number;
+  /** `dwMaxVideoFrameSize` in bytes */
+  max_frame_size: number;
+  /** `dwMaxPayloadTransferSize` in bytes */
+  max_payload: number;
+}
+
+/** Error returned by backend commands; `code` is stable and can be used for localization */
+export interface AppError {
+  code: string;
+  message: string;
+}
+
+/** Extract a human-readable message from a rejected `invoke` call */
+export function errorText(e: unknown): string {
+  if (typeof e === "object" && e !== null && "message" in e) {
+    return String((e as AppError).message);
+  }
+  return String(e);
+}
+
 export interface UsbError {
+  code: string;
   error_type: "normal" | "device_unplugged" | "transfer_error" | "timeout" | "unknown";
   message: string;
   recoverable: boolean;
 }
 
+/** Metadata sent with every `frame-ready` event; width/height are 0 when unknown */
+export interface FrameInfo {
+  sequence: number;
+  width: number;
+  height: number;
+  format: "jpeg" | "rgb";
+  size: number;
+}
+
+/** Frame delivery statistics sent with `usb-device-event` and the periodic `usb-health` event */
+export interface HealthStats {
+  last_frame_age_ms: number | null;
+  fps: number;
+  frames: number;
+  transfer_errors: number;
+  stalls: number;
+  reconnects: number;
+  timeout_streak: number;
+}
+
+export type ResourceLevel = "ok" | "low" | "critical";
+
+/** Free storage and memory, emitted as `resource-warning` when low or when a recording or capture was stopped */
+export interface ResourceWarning {
+  level: ResourceLevel;
+  free_storage_bytes: number | null;
+  available_memory_bytes: number | null;
+  storage: ResourceLevel;
+  memory: ResourceLevel;
+  stopped: ("recording" | "packet_capture")[];
+}
+
+/** Frame assembly statistics returned by `get_stream_stats` and emitted as `stream-stats` */
+export interface StreamStatsReport {
+  fps: number;
+  frames: number;
+  dropped_frames: number;
+  corrupt_frames: number;
+  /** Frames not displayed because they failed validation (`set_validation_level`) */
+  rejected_frames: number;
+  usb_errors: number;
+  frame_size: { min: number; max: number; mean: number; p50: number; p95: number } | null;
+  avg_assembly_latency_ms: number | null;
+  frame_channel: { capacity: number; high_water_mark: number; overflows: number };
+}
+
 export interface UsbStatusExtended {
   connected: boolean;
   info?: string;
   disconnect_reason?: "normal" | "device_unplugged" | "transfer_error" | "timeout" | "unknown";
+  health?: HealthStats;
 }
 
 export interface ReconnectStatus {
@@ -31,8 +119,608 @@ export interface CaptureResult {
   raw_size: number;
   header_hex: string;
   format_hint: string;
+  /** Format the processed frame was encoded to (null: saved as captured) */
+  image_format: ImageFormat | null;
+  width: number;
+  height: number;
+}
+
+/** Summary of a packet capture session (metadata.json) */
+export interface PacketCaptureMetadata {
+  vendor_id: number;
+  product_id: number;
+  format_type: string;
   width: number;
   height: number;
+  total_packets: number;
+  total_frames: number;
+  duration_ms: number;
+  total_bytes: number;
+  description: string;
+  record_transfers: boolean;
+  total_transfer_records: number;
+}
+
+/** Files written by `stop_packet_capture` */
+export interface PacketCaptureResult {
+  packets_path: string;
+  metadata_path: string;
+  transfers_path?: string;
+  metadata: PacketCaptureMetadata;
+}
+
+/** Returned by `export_capture_pcap` */
+export interface PcapExportResult {
+  path: string;
+  packets: number;
+}
+
+/** Returned by `get_capture_status` */
+export interface PacketCaptureStatus {
+  is_capturing: boolean;
+  packet_count: number;
+  duration_ms: number;
+  total_bytes: number;
+  /** Which frames' packets are kept (set with `set_capture_filter`) */
+  filter: "all" | "malformed_only";
+  /** Malformed frames committed under the `malformed_only` filter */
+  malformed_frames: number;
+}
+
+/** Options for `start_replay` (all optional) */
+export interface ReplayOptions {
+  /** Speed multiplier (default 1.0, 0 = as fast as possible) */
+  speed?: number;
+  loop_playback?: boolean;
+  force_mjpeg?: boolean;
+  expected_frame_size?: number;
+}
+
+/** Returned by `load_replay`, `start_replay`, `stop_replay` and `replay_status` */
+export interface ReplayStatus {
+  path: string | null;
+  running: boolean;
+  packet_count: number;
+  duration_ms: number;
+  frames_played: number;
+  metadata: PacketCaptureMetadata | null;
+}
+
+/** Geometry of an annotation, in frame pixels */
+export type AnnotationKind =
+  | { type: "box"; x: number; y: number; width: number; height: number }
+  | { type: "label"; x: number; y: number }
+  | {
+      type: "measurement";
+      x1: number;
+      y1: number;
+      x2: number;
+      y2: number;
+      value: number;
+      unit: string;
+    };
+
+/** Result reported by a plugin or detection model */
+export type Annotation = AnnotationKind & {
+  /** Plugin or model that produced it */
+  source: string;
+  label: string;
+  confidence: number;
+};
+
+/** `frame-annotations` event payload */
+export interface FrameAnnotations {
+  sequence: number;
+  annotations: Annotation[];
+}
+
+/** Set with `set_annotation_config` */
+export interface AnnotationConfig {
+  enabled: boolean;
+  min_confidence: number;
+  /** Draw active annotations into recorded RGB frames */
+  burn_into_recordings: boolean;
+  /** Frames an annotation stays active after the frame it describes */
+  hold_frames: number;
+}
+
+export type PluginCapability = "modify_frame" | "annotate";
+
+export interface PluginManifest {
+  name: string;
+  version: string;
+  api_version: number;
+  library: string;
+  capabilities: PluginCapability[];
+  description: string;
+}
+
+/** Returned by `list_plugins` and `reload_plugins` */
+export interface PluginInfo {
+  directory: string;
+  manifest: PluginManifest | null;
+  loaded: boolean;
+  error: string | null;
+}
+
+/** Settings for `start_inference` */
+export interface InferenceConfig {
+  /** ONNX model file inside the output directory */
+  model_path: string;
+  input_size?: number;
+  confidence_threshold?: number;
+  /** Class names by class index */
+  labels?: string[];
+}
+
+/** Returned by `start_inference`, `stop_inference` and `inference_status` */
+export interface InferenceStatus {
+  running: boolean;
+  model: string | null;
+  frames_processed: number;
+  frames_skipped: number;
+  detections: number;
+  last_error: string | null;
+  /** Whether this build can run models (`inference` feature) */
+  available: boolean;
+}
+
+/** Connected UVC camera, returned by `list_usb_devices` and `select_device` */
+export interface CameraDevice {
+  /** Device name, accepted by `select_device` */
+  id: string;
+  key: { vendor_id: number; product_id: number };
+  name: string | null;
+  /** Whether this is the camera being streamed from */
+  active: boolean;
+}
+
+/** Image control accepted by `set_camera_control` */
+export type CameraControl =
+  | "brightness"
+  | "contrast"
+  | "saturation"
+  | "sharpness"
+  | "gamma"
+  | "exposure";
+
+/** Returned by `get_camera_controls` and `set_camera_control` */
+export interface CameraControlInfo {
+  control: CameraControl;
+  min: number;
+  max: number;
+  step: number;
+  default: number;
+  current: number;
+}
+
+/** Exposure of the analysed frames */
+export type ExposureCondition = "normal" | "underexposed" | "overexposed";
+
+/** Luma histogram summary of one frame */
+export interface LumaStats {
+  /** Mean luma (0-255) */
+  mean: number;
+  dark_fraction: number;
+  bright_fraction: number;
+}
+
+/** Payload of the `encode-quality` event */
+export interface QualityChange {
+  /** JPEG quality before the change */
+  from: number;
+  /** JPEG quality from now on */
+  to: number;
+  /** Encode time of the frame that triggered the change (µs) */
+  encode_us: number;
+  /** Time between frames (µs) */
+  interval_us: number;
+}
+
+/** Payload of the `exposure-suggestion` event */
+export interface ExposureSuggestion {
+  condition: ExposureCondition;
+  action:
+    | "increase_exposure"
+    | "reduce_exposure"
+    | "increase_brightness"
+    | "reduce_brightness"
+    | "increase_led"
+    | "reduce_led";
+  stats: LumaStats;
+  /** Control change for exposure and brightness actions */
+  change: { control: CameraControl; from: number; to: number } | null;
+  /** Whether the change was made (auto-apply) */
+  applied: boolean;
+}
+
+/** Returned by `set_exposure_advisor` and `get_exposure_advisor_status` */
+export interface ExposureStatus {
+  settings: { enabled: boolean; auto_apply: boolean };
+  condition: ExposureCondition;
+  /** Consecutive analyses with that condition */
+  streak: number;
+  stats: LumaStats | null;
+  suggestions: number;
+  applied: number;
+  last_suggestion: ExposureSuggestion | null;
+}
+
+/** Vendor extension unit returned by `get_extension_units` */
+export interface ExtensionUnit {
+  id: number;
+  guid: string;
+  selectors: number[];
+}
+
+/** Printed calibration target passed to `calibrate_from_target` */
+export interface TargetSpec {
+  kind: "dots" | "checker";
+  /** Dot pitch or checker square size in millimetres */
+  spacing_mm: number;
+}
+
+/** Pixel to millimetre mapping returned by the calibration commands */
+export interface Calibration {
+  pixels_per_mm: number;
+  /** Radial distortion coefficient (0: none) */
+  k1: number;
+  width: number;
+  height: number;
+  /** Zoom the calibration was made at; measuring at another zoom fails */
+  zoom: Zoom;
+  source:
+    | { method: "manual"; length_mm: number }
+    | { method: "target"; target: TargetSpec; points: number; residual: number };
+}
+
+/** Unit calibrated measurements are reported in */
+export type LengthUnit = "mm" | "inch";
+
+/** A measured value with its standard uncertainty */
+export interface Quantity {
+  value: number;
+  uncertainty: number;
+  /** "mm", "in", "px" when uncalibrated, squared for areas, "deg" for angles */
+  unit: string;
+}
+
+/** A measurement recorded in the session manifest */
+export interface Measurement {
+  id: number;
+  kind: "line" | "angle" | "area";
+  /** Measured points in frame pixels */
+  points: [number, number][];
+  value: Quantity;
+  frame_sequence: number;
+  timestamp_ms: number;
+}
+
+/** Geometry of an annotation on a frozen frame, in frame pixels */
+export type FrozenShape =
+  | { type: "arrow"; x1: number; y1: number; x2: number; y2: number }
+  | { type: "circle"; x: number; y: number; radius: number }
+  | { type: "text"; x: number; y: number; text: string };
+
+/** An annotation drawn on a frozen frame */
+export type VectorAnnotation = FrozenShape & {
+  id: number;
+  /** RGB colour (omitted: renderer default) */
+  color?: [number, number, number];
+};
+
+/** A frozen frame and its annotations */
+export interface FrozenFrame {
+  sequence: number;
+  width: number;
+  height: number;
+  annotations: VectorAnnotation[];
+}
+
+/** Rotation and flips applied to frames (`set_rotation`, `set_flip`) */
+export interface FrameTransform {
+  /** Clockwise rotation in degrees */
+  rotation: 0 | 90 | 180 | 270;
+  flip_horizontal: boolean;
+  flip_vertical: boolean;
+}
+
+/** Digital zoom applied to frames (`set_zoom`, `get_zoom`) */
+export interface Zoom {
+  /** Magnification, 1 to 8 (1: whole frame) */
+  factor: number;
+  /** Crop centre as fractions (0-1) of the frame width and height */
+  center_x: number;
+  center_y: number;
+}
+
+/** What `undo` and `redo` would change (returned by `undo`, `redo`, `get_history`) */
+export interface HistoryStatus {
+  undo_count: number;
+  redo_count: number;
+  /** e.g. "annotation", "calibration", "rotation", "flip", "measurement unit", "zoom" */
+  undo_label: string | null;
+  redo_label: string | null;
+}
+
+/** Settings saved in a session checkpoint */
+export interface ResumeSettings {
+  pixel_format: string;
+  decode_mjpeg: boolean;
+  /** UVC format and frame index of the stream (null: none was running) */
+  format_index: number | null;
+  frame_index: number | null;
+  /** Frame interval in 100 ns units (null: camera default) */
+  frame_interval: number | null;
+  transform: FrameTransform;
+  zoom: Zoom;
+  measurement_unit: LengthUnit;
+}
+
+/** User preferences kept across restarts (`get_settings`, `update_settings`) */
+export interface Settings {
+  /** Resolution to negotiate (null: camera default) */
+  resolution: { width: number; height: number } | null;
+  /** e.g. "Uyvy" for cameras that report YUYV (null: YUYV) */
+  pixel_format: string | null;
+  /** Clockwise rotation in degrees */
+  rotation: 0 | 90 | 180 | 270;
+  /** Mirror left and right (after rotating) */
+  flip_horizontal: boolean;
+  /** Mirror top and bottom (after rotating) */
+  flip_vertical: boolean;
+  /** Applies from the next start (null: strict) */
+  validation_level: "Strict" | "Moderate" | "Minimal" | "Off" | null;
+  /** Absolute directory for snapshots, recordings and captures (null: app cache) */
+  capture_dir: string | null;
+  /** Format names to negotiate, most preferred first, e.g. ["MJPEG", "YUY2", "NV12"] (empty: MJPEG if available) */
+  format_preference: string[];
+}
+
+/** Session left behind by a crashed run (`get_resume_offer`) */
+export interface ResumeOffer {
+  /** Saved session manifest file (null: no bookmarks or measurements yet) */
+  session_file: string | null;
+  bookmarks: number;
+  measurements: number;
+  /** Interrupted recording directory, relative to the output directory */
+  recording_dir: string | null;
+  /** Camera that was streaming */
+  device: string | null;
+  settings: ResumeSettings;
+  /** Whether that camera is connected again */
+  device_connected: boolean;
+}
+
+/** Recording finished by `stop_recording` or recovered after a crash */
+export interface RecordingResult {
+  directory: string;
+  frames_path: string;
+  index_path: string;
+  frame_count: number;
+  duration_ms: number;
+  chapters_path: string | null;
+  raw_video_path: string | null;
+  video_path: string | null;
+  /** Why the video could not be finished (the frames and index are kept) */
+  video_error: string | null;
+  /** Frames the recorder fell too far behind to write */
+  skipped_frames: number;
+}
+
+/** Outcome of `resume_session` */
+export interface ResumedSession {
+  session: {
+    started_at_ms: number;
+    bookmarks: { id: number; frame_sequence: number; timestamp_ms: number; note?: string }[];
+    measurements: Measurement[];
+    snapshots: SessionSnapshot[];
+  };
+  recording: RecordingResult | null;
+  /** Why the interrupted recording could not be recovered */
+  recording_error: string | null;
+  /** Whether the saved format and resolution are being renegotiated */
+  stream_restored: boolean;
+}
+
+/** Still image format for saved frames */
+export type ImageFormat = "jpeg" | "png" | "webp" | "avif";
+
+/** Scheduled snapshot in the session manifest, also emitted as `auto-snapshot` */
+export interface SessionSnapshot {
+  /** e.g. "auto-0003" */
+  label: string;
+  series: string;
+  sequence: number;
+  frame_sequence: number;
+  timestamp_ms: number;
+  path: string;
+}
+
+/** Returned by `start_auto_snapshot`, `stop_auto_snapshot` and `get_auto_snapshot_status` */
+export interface AutoSnapshotStatus {
+  active: boolean;
+  settings: { interval_secs: number; label: string; format: ImageFormat | null } | null;
+  taken: number;
+  failed: number;
+  /** Time until the next snapshot (null when stopped) */
+  next_in_ms: number | null;
+  last_path: string | null;
+  last_error: string | null;
+}
+
+/** What a script does when a step fails after its retries */
+export type ScriptErrorPolicy = "abort" | "continue";
+
+/** Operation of an `execute_script` step */
+export type ScriptOperation =
+  | { op: "set_resolution"; width: number; height: number }
+  | { op: "set_framerate"; fps: number }
+  | { op: "set_control"; name: string; value: number }
+  | { op: "set_led"; level: number }
+  | { op: "wait"; ms: number }
+  | { op: "wait_for_frame"; timeout_ms: number }
+  | { op: "snapshot"; path?: string; format?: ImageFormat }
+  | { op: "record"; seconds: number; label?: string }
+  | { op: "bookmark"; note?: string };
+
+export type ScriptStep = ScriptOperation & {
+  retries?: number;
+  on_error?: ScriptErrorPolicy;
+};
+
+/** Script passed (as JSON) to `execute_script` */
+export interface Script {
+  name?: string;
+  on_error?: ScriptErrorPolicy;
+  steps: ScriptStep[];
+}
+
+/** Outcome of a script step, also emitted as `script-step` */
+export interface StepReport {
+  index: number;
+  op: ScriptOperation["op"];
+  ok: boolean;
+  attempts: number;
+  duration_ms: number;
+  /** Snapshot path, recording directory, bookmark id, ... */
+  output?: string;
+  error?: string;
+}
+
+/** Returned by `execute_script` */
+export interface ScriptReport {
+  name: string | null;
+  succeeded: number;
+  failed: number;
+  aborted: boolean;
+  cancelled: boolean;
+  duration_ms: number;
+  steps: StepReport[];
+}
+
+/** Single frame saved by `save_snapshot` (also the `snapshot-saved` event payload) */
+export interface Snapshot {
+  path: string;
+  /** Format the frame was encoded to (null: saved as captured) */
+  format: ImageFormat | null;
+  width: number;
+  height: number;
+  size: number;
+}
+
+/** Animated WebP clip exported from a recording */
+export interface ClipResult {
+  path: string;
+  frame_count: number;
+  duration_ms: number;
+  size: number;
+}
+
+/** Capture bundle written by `prepare_capture_submission`, to hand to the share sheet */
+export interface SubmissionResult {
+  path: string;
+  size: number;
+  packets: number;
+  files: string[];
+}
+
+/** A frame assembled by `replay_last` */
+export interface ReproFrame {
+  packet_index: number;
+  timestamp_us: number;
+  size: number;
+  is_jpeg: boolean;
+}
+
+/** Result of `replay_last`: the buffered packets re-run through a fresh assembler */
+export interface ReproReport {
+  packets: number;
+  bytes: number;
+  duration_ms: number;
+  skipped_packets: number;
+  frames: ReproFrame[];
+}
+
+/** One assembler/conversion configuration of the pipeline comparison */
+export interface PipelineVariant {
+  label: string;
+  mjpeg: boolean;
+  pixel_format: string;
+  width: number;
+  height: number;
+  stride: number | null;
+  headerless: boolean;
+}
+
+/** Changes applied to the live pipeline for `start_pipeline_comparison` */
+export interface PipelineOverrides {
+  label?: string;
+  pixel_format?: string;
+  width?: number;
+  height?: number;
+  stride?: number;
+  headerless?: boolean;
+}
+
+/** How one pair of frames compared */
+export interface FrameComparison {
+  sequence: number;
+  baseline_bytes: number;
+  experimental_bytes: number;
+  differing_bytes: number;
+  mean_abs_diff: number;
+  max_abs_diff: number;
+  diverged: boolean;
+  error?: string;
+}
+
+/** Progress of the dual-pipeline comparison */
+export interface ComparisonReport {
+  running: boolean;
+  baseline: PipelineVariant | null;
+  experimental: PipelineVariant | null;
+  stats: {
+    compared: number;
+    diverged: number;
+    baseline_only: number;
+    experimental_only: number;
+    dropped_packets: number;
+    worst_mean_abs_diff: number;
+  };
+  recent: FrameComparison[];
+}
+
+/** Stage of a frame trace artifact or failure */
+export type TraceStage = 'raw_payload' | 'assembled' | 'rgb' | 'encoded';
+
+/** Manifest of a frame written by trace_next_frame */
+export interface TraceManifest {
+  directory: string;
+  created_at_ms: number;
+  /** null for MJPEG frames */
+  pixel_format: string | null;
+  width: number;
+  height: number;
+  stride: number | null;
+  packet_sizes: number[];
+  validation: {
+    valid: boolean;
+    avg_row_diff: number | null;
+    actual_size: number;
+    expected_size: number;
+    size_ratio: number;
+    stride_aligned: boolean;
+    failure_reason: string | null;
+  } | null;
+  artifacts: { stage: TraceStage; file: string; bytes: number }[];
+  failures: { stage: TraceStage; message: string }[];
+}
+
+/** Snapshot format setting and the formats compiled into the binary */
+export interface SnapshotFormats {
+  current: ImageFormat | null;
+  available: ImageFormat[];
 }
 
 export interface BuildInfo {
@@ -41,9 +729,31 @@ export interface BuildInfo {
   build_time: string;
 }
 
+/** Network-capable features compiled into the binary (all false in `no-network` builds) */
+export interface BuildCapabilities {
+  no_network: boolean;
+  shell_open: boolean;
+  native_plugins: boolean;
+  /** Android debug builds request INTERNET to reach the dev server */
+  internet_permission: boolean;
+}
+
 export type ConnectionStatus = "disconnected" | "connecting" | "connected" | "reconnecting";
 
 export interface UsbStatusEvent {
   status: string;
+  code?: string;
   detail?: string;
 }
+
+export interface PreflightCheck {
+  id: string;
+  status: "ok" | "warning" | "failed" | "unknown";
+  detail: string;
+  guidance?: string;
+}
+
+export interface PreflightReport {
+  ready: boolean;
+  checks: PreflightCheck[];
+}